@@ -0,0 +1,95 @@
+//! File pickers for the guest's open and save dialogs: the desktop's own where
+//! there's a helper to show one, otherwise a prompt on the terminal.
+
+use std::io::Write;
+use std::process::Command;
+
+pub fn pick_file(dialog: &win32::FileDialog) -> Option<String> {
+    let command = if cfg!(target_os = "macos") {
+        Some(osascript(dialog))
+    } else if cfg!(all(unix, not(target_os = "macos"))) {
+        Some(zenity(dialog))
+    } else {
+        None
+    };
+    if let Some(mut command) = command {
+        match command.output() {
+            // Both helpers exit with failure when the user cancels.
+            Ok(output) if output.status.success() => {
+                let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                return if path.is_empty() { None } else { Some(path) };
+            }
+            Ok(_) => return None,
+            Err(err) => log::warn!("no file picker ({err}), asking on the terminal"),
+        }
+    }
+    prompt(dialog)
+}
+
+/// The path to start at, from the dialog's directory and file name.
+fn start_path(dialog: &win32::FileDialog) -> Option<String> {
+    match (&dialog.dir, &dialog.name) {
+        (Some(dir), Some(name)) => Some(format!("{}/{name}", dir.trim_end_matches('/'))),
+        (Some(dir), None) => Some(format!("{}/", dir.trim_end_matches('/'))),
+        (None, Some(name)) => Some(name.clone()),
+        (None, None) => None,
+    }
+}
+
+fn zenity(dialog: &win32::FileDialog) -> Command {
+    let mut cmd = Command::new("zenity");
+    cmd.arg("--file-selection");
+    cmd.arg(format!("--title={}", dialog.title));
+    if dialog.save {
+        cmd.args(["--save", "--confirm-overwrite"]);
+    }
+    if let Some(path) = start_path(dialog) {
+        cmd.arg(format!("--filename={path}"));
+    }
+    // zenity offers its filters in order, starting with the first.
+    let n = dialog.filters.len();
+    for i in 0..n {
+        let (desc, patterns) = &dialog.filters[(dialog.filter_index + i) % n];
+        cmd.arg(format!("--file-filter={desc} | {}", patterns.join(" ")));
+    }
+    cmd
+}
+
+fn osascript(dialog: &win32::FileDialog) -> Command {
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut script = if dialog.save {
+        format!("choose file name with prompt {}", quote(&dialog.title))
+    } else {
+        format!("choose file with prompt {}", quote(&dialog.title))
+    };
+    if let Some(dir) = &dialog.dir {
+        script += &format!(" default location POSIX file {}", quote(dir));
+    }
+    if let (true, Some(name)) = (dialog.save, &dialog.name) {
+        script += &format!(" default name {}", quote(name));
+    }
+    let mut cmd = Command::new("osascript");
+    cmd.args(["-e", &format!("POSIX path of ({script})")]);
+    cmd
+}
+
+fn prompt(dialog: &win32::FileDialog) -> Option<String> {
+    let mut stderr = std::io::stderr().lock();
+    writeln!(stderr, "{}", dialog.title).ok()?;
+    for (desc, patterns) in &dialog.filters {
+        writeln!(stderr, "  {desc}: {}", patterns.join(" ")).ok()?;
+    }
+    if let Some(path) = start_path(dialog) {
+        writeln!(stderr, "  (starting at {path})").ok()?;
+    }
+    write!(stderr, "file (empty to cancel): ").ok()?;
+    stderr.flush().ok()?;
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).ok()?;
+    let path = line.trim();
+    if path.is_empty() {
+        None
+    } else {
+        Some(path.to_string())
+    }
+}
@@ -1,27 +1,148 @@
-pub struct GUI {}
+//! A GUI without a display, for batch runs like compatibility testing: windows are
+//! invisible, surfaces keep their pixels in memory, nothing comes in as input, and
+//! time skips ahead over the guest's waits rather than sleeping through them.
+
+use std::{cell::RefCell, rc::Rc, time::Instant};
+
+/// What has been shown on the screen.
+#[derive(Default)]
+pub struct Screen {
+    /// Count of surfaces shown, as by ddraw presents or GDI painting.
+    pub frames: u32,
+    /// The surface last shown, as (width, height, RGBA pixels).
+    pub image: Option<(u32, u32, Vec<[u8; 4]>)>,
+}
+
+impl Screen {
+    /// Encode the image last shown as a PNG.
+    pub fn png(&self) -> anyhow::Result<Vec<u8>> {
+        let Some((width, height, pixels)) = &self.image else {
+            anyhow::bail!("nothing was shown");
+        };
+        let mut buf = Vec::new();
+        let mut encoder = png::Encoder::new(&mut buf, *width, *height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(pixels.as_flattened())?;
+        writer.finish()?;
+        Ok(buf)
+    }
+}
+
+pub struct GUI {
+    start: Instant,
+    /// Time skipped over waits, in ms.
+    skipped: u32,
+    screen: Rc<RefCell<Screen>>,
+}
 
 impl GUI {
-    pub fn new() -> anyhow::Result<Self> {
-        Ok(GUI {})
+    pub fn new(screen: Rc<RefCell<Screen>>) -> Self {
+        GUI {
+            start: Instant::now(),
+            skipped: 0,
+            screen,
+        }
+    }
+}
+
+impl crate::Gui for GUI {
+    fn time(&self) -> u32 {
+        self.start.elapsed().as_millis() as u32 + self.skipped
+    }
+
+    fn get_message(&mut self) -> Option<win32::Message> {
+        None
     }
 
-    pub fn time(&self) -> u32 {
-        0
+    fn block(&mut self, wait: Option<u32>) -> bool {
+        match wait {
+            Some(until) => {
+                self.skipped += until.saturating_sub(self.time());
+                true
+            }
+            None => {
+                // Waiting for input, which never comes.
+                log::warn!("headless: blocked waiting for input");
+                false
+            }
+        }
     }
 
-    pub fn get_message(&mut self) -> Option<win32::Message> {
-        unimplemented!();
+    fn create_window(&mut self, _hwnd: u32) -> Box<dyn win32::Window> {
+        Box::new(Window)
     }
 
-    pub fn block(&mut self, _wait: Option<u32>) -> bool {
-        unimplemented!();
+    fn create_surface(&mut self, opts: &win32::SurfaceOptions) -> Box<dyn win32::Surface> {
+        Box::new(Surface {
+            width: opts.width,
+            height: opts.height,
+            pixels: vec![[0, 0, 0, 0xff]; (opts.width * opts.height) as usize],
+            screen: self.screen.clone(),
+        })
+    }
+
+    fn create_audio(&mut self, _sample_rate: u32) -> Option<Box<dyn win32::Audio>> {
+        None
+    }
+
+    fn gamepad(&self, _id: u32) -> Option<win32::GamepadState> {
+        None
+    }
+
+    fn grab_mouse(&self, _grab: bool) {}
+}
+
+struct Window;
+
+impl win32::Window for Window {
+    fn set_title(&mut self, _title: &str) {}
+    fn set_icon(&mut self, _icon: &win32::Icon) {}
+    fn set_size(&mut self, _width: u32, _height: u32) {}
+    fn set_fullscreen(&mut self, _fullscreen: bool) {}
+}
+
+struct Surface {
+    width: u32,
+    height: u32,
+    pixels: Vec<[u8; 4]>,
+    screen: Rc<RefCell<Screen>>,
+}
+
+impl win32::Surface for Surface {
+    fn write_pixels(&mut self, pixels: &[[u8; 4]]) {
+        let len = std::cmp::min(pixels.len(), self.pixels.len());
+        self.pixels[..len].copy_from_slice(&pixels[..len]);
     }
 
-    pub fn create_window(&mut self, _hwnd: u32) -> Box<dyn win32::Window> {
-        unimplemented!();
+    fn show(&mut self) {
+        let mut screen = self.screen.borrow_mut();
+        screen.frames += 1;
+        screen.image = Some((self.width, self.height, self.pixels.clone()));
     }
 
-    pub fn create_surface(&mut self, _opts: &win32::SurfaceOptions) -> Box<dyn win32::Surface> {
-        unimplemented!();
+    fn bit_blt(
+        &mut self,
+        dx: u32,
+        dy: u32,
+        src: &dyn win32::Surface,
+        sx: u32,
+        sy: u32,
+        w: u32,
+        h: u32,
+    ) {
+        let src = unsafe { &*(src as *const dyn win32::Surface as *const Surface) };
+        let w = w
+            .min(src.width.saturating_sub(sx))
+            .min(self.width.saturating_sub(dx));
+        let h = h
+            .min(src.height.saturating_sub(sy))
+            .min(self.height.saturating_sub(dy));
+        for y in 0..h {
+            let s = ((sy + y) * src.width + sx) as usize;
+            let d = ((dy + y) * self.width + dx) as usize;
+            self.pixels[d..d + w as usize].copy_from_slice(&src.pixels[s..s + w as usize]);
+        }
     }
 }
@@ -1,7 +1,11 @@
 extern crate argh;
 extern crate win32;
+mod dialog;
 mod logging;
-use anyhow::anyhow;
+mod net;
+mod port;
+mod profile;
+use anyhow::{anyhow, bail};
 use std::{
     cell::RefCell,
     io::{Read, Seek, Write},
@@ -9,14 +13,9 @@ use std::{
     rc::Rc,
 };
 
+mod headless;
 #[cfg(feature = "sdl")]
 mod sdl;
-#[cfg(feature = "sdl")]
-use sdl::GUI;
-#[cfg(not(feature = "sdl"))]
-mod headless;
-#[cfg(not(feature = "sdl"))]
-use headless::GUI;
 
 #[cfg(feature = "x86-64")]
 mod resv32;
@@ -24,11 +23,89 @@ mod resv32;
 #[cfg(feature = "x86-emu")]
 static mut SNAPSHOT_REQUESTED: bool = false;
 
+/// Set once the machine is created, for the SIGINT/SIGTERM handler to request a stop.
+#[cfg(feature = "x86-emu")]
+static STOP_HANDLE: std::sync::OnceLock<win32::StopHandle> = std::sync::OnceLock::new();
+
+/// Write a snapshot, logging rather than failing if the machine can't be snapshotted.
+#[cfg(feature = "x86-emu")]
+fn write_snapshot(machine: &win32::Machine, path: &str) {
+    match machine.snapshot() {
+        Ok(buf) => {
+            std::fs::write(path, buf).unwrap();
+            log::info!("wrote snapshot to {path:?}");
+        }
+        Err(err) => log::error!("snapshot: {err}"),
+    }
+}
+
+/// Write a frame dump (see win32::framedump) into a directory.
+fn write_frame_dump(dump: &win32::framedump::FrameDump) -> std::io::Result<String> {
+    let dir = format!("framedump/{}", dump.frame);
+    std::fs::create_dir_all(&dir)?;
+    let mut calls = dump.calls.join("\n");
+    calls.push('\n');
+    std::fs::write(format!("{dir}/calls.txt"), calls)?;
+    let mut index = String::new();
+    for surf in &dump.surfaces {
+        let name = format!("{}_{:08x}", surf.kind, surf.addr);
+        index.push_str(&format!(
+            "{name} {}x{} attached={:x}",
+            surf.width, surf.height, surf.attached
+        ));
+        if let Some(pixels) = &surf.pixels {
+            // PPM, because it's trivial to write.
+            let mut buf = format!("P6\n{} {}\n255\n", surf.width, surf.height).into_bytes();
+            for &[r, g, b, _a] in pixels {
+                buf.extend_from_slice(&[r, g, b]);
+            }
+            std::fs::write(format!("{dir}/{name}.ppm"), buf)?;
+        } else {
+            index.push_str(" (host only)");
+        }
+        index.push('\n');
+    }
+    std::fs::write(format!("{dir}/surfaces.txt"), index)?;
+    Ok(dir)
+}
+
+/// Write a captured frame (see win32::capture) into a directory, indexed in frames.txt
+/// by frame number and guest time.
+fn write_capture(dir: &str, frame: &win32::capture::Frame) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let image = &frame.image;
+    let name = format!("{dir}/frame_{:06}", frame.frame);
+    let mut buf = format!("P6\n{} {}\n255\n", image.width, image.height).into_bytes();
+    for &[r, g, b, _a] in &image.pixels {
+        buf.extend_from_slice(&[r, g, b]);
+    }
+    std::fs::write(format!("{name}.ppm"), buf)?;
+    if let Some(indexed) = &image.indexed {
+        // Indices as a graymap, alongside the palette as raw RGB triples.
+        let mut buf = format!("P5\n{} {}\n255\n", image.width, image.height).into_bytes();
+        buf.extend_from_slice(&indexed.pixels);
+        std::fs::write(format!("{name}.pgm"), buf)?;
+        std::fs::write(format!("{name}.pal"), indexed.palette.concat())?;
+    }
+    let mut index = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(format!("{dir}/frames.txt"))?;
+    writeln!(
+        index,
+        "{} {} {}x{}",
+        frame.frame, frame.time, image.width, image.height
+    )
+}
+
 #[cfg(feature = "x86-emu")]
 fn dump_asm(machine: &win32::Machine, count: usize) {
     let instrs = win32::disassemble(machine.mem(), machine.emu.x86.cpu().regs.eip, count);
 
     for instr in instrs {
+        if let Some((name, 0)) = machine.symbols.lookup(instr.addr) {
+            println!("{name}:");
+        }
         print!("{:08x} {:10} ", instr.addr, instr.bytes);
         for part in &instr.code {
             print!("{}", part.text);
@@ -77,24 +154,56 @@ impl win32::File for File {
     }
 }
 
+/// The windowing and input side of the host: SDL, or headless for batch runs.
+trait Gui {
+    fn time(&self) -> u32;
+    fn get_message(&mut self) -> Option<win32::Message>;
+    fn block(&mut self, wait: Option<u32>) -> bool;
+    fn create_window(&mut self, hwnd: u32) -> Box<dyn win32::Window>;
+    fn create_surface(&mut self, opts: &win32::SurfaceOptions) -> Box<dyn win32::Surface>;
+    fn create_audio(&mut self, sample_rate: u32) -> Option<Box<dyn win32::Audio>>;
+    fn gamepad(&self, id: u32) -> Option<win32::GamepadState>;
+    fn grab_mouse(&self, grab: bool);
+}
+
 struct Env {
-    gui: Option<GUI>,
+    gui: Option<Box<dyn Gui>>,
     exit_code: Option<u32>,
+    /// Honor programs' requests to go fullscreen.
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    fullscreen: bool,
+    ports: Vec<port::PortConfig>,
+    /// Where the headless GUI shows things, if running headless.
+    screen: Option<Rc<RefCell<headless::Screen>>>,
+    /// The machine's, for the GUI's pause hotkeys.
+    pause: win32::PauseHandle,
 }
 
 impl Env {
-    pub fn new() -> Self {
+    pub fn new(fullscreen: bool, ports: Vec<port::PortConfig>, headless: bool) -> Self {
+        // Without SDL there's nothing but headless.
+        let headless = headless || cfg!(not(feature = "sdl"));
         Env {
             gui: None,
             exit_code: None,
+            fullscreen,
+            ports,
+            screen: headless.then(Default::default),
+            pause: Default::default(),
         }
     }
 
-    pub fn ensure_gui(&mut self) -> anyhow::Result<&mut GUI> {
+    pub fn ensure_gui(&mut self) -> anyhow::Result<&mut dyn Gui> {
         if self.gui.is_none() {
-            self.gui = Some(GUI::new()?);
+            self.gui = Some(match &self.screen {
+                Some(screen) => Box::new(headless::GUI::new(screen.clone())),
+                #[cfg(feature = "sdl")]
+                None => Box::new(sdl::GUI::new(self.fullscreen, self.pause.clone())?),
+                #[cfg(not(feature = "sdl"))]
+                None => unreachable!(),
+            });
         }
-        Ok(self.gui.as_mut().unwrap())
+        Ok(self.gui.as_deref_mut().unwrap())
     }
 }
 
@@ -124,14 +233,101 @@ impl win32::Host for EnvRef {
         gui.block(wait)
     }
 
+    fn progress(&self, _task: &str, _done: u32, _total: u32) {
+        // Nothing to show on the command line.
+    }
+
+    fn guest_message(&self, channel: &str, payload: &str) {
+        // One line per message on stderr, keeping the prefix so scripts can pick
+        // these out from the log.
+        eprintln!("{}{channel}:{payload}", win32::GUEST_CHANNEL_PREFIX);
+    }
+
     fn open(&self, path: &str) -> Box<dyn win32::File> {
         Box::new(File::open(Path::new(path)))
     }
 
+    fn exists(&self, path: &str) -> bool {
+        Path::new(path).is_file()
+    }
+
     fn write(&self, buf: &[u8]) -> usize {
         std::io::stdout().lock().write(buf).unwrap()
     }
 
+    fn open_port(&self, name: &str) -> Option<Box<dyn win32::Port>> {
+        port::open(&self.0.borrow().ports, name)
+    }
+
+    fn socket(&self, kind: win32::SocketKind) -> std::io::Result<Box<dyn win32::Socket>> {
+        Ok(net::socket(kind))
+    }
+
+    fn resolve(&self, name: &str) -> std::io::Result<Vec<std::net::Ipv4Addr>> {
+        net::resolve(name)
+    }
+
+    fn random(&self, buf: &mut [u8]) {
+        #[cfg(unix)]
+        if std::fs::File::open("/dev/urandom")
+            .and_then(|mut f| f.read_exact(buf))
+            .is_ok()
+        {
+            return;
+        }
+        // Otherwise, std's hash keys, which it seeds from the OS.
+        use std::hash::{BuildHasher, Hasher};
+        for chunk in buf.chunks_mut(8) {
+            let n = std::collections::hash_map::RandomState::new()
+                .build_hasher()
+                .finish();
+            chunk.copy_from_slice(&n.to_le_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn shell_open(&self, target: &str) -> bool {
+        let mut cmd = if cfg!(target_os = "macos") {
+            std::process::Command::new("open")
+        } else if cfg!(target_family = "windows") {
+            let mut cmd = std::process::Command::new("cmd");
+            cmd.args(["/C", "start", ""]);
+            cmd
+        } else {
+            std::process::Command::new("xdg-open")
+        };
+        match cmd.arg(target).spawn() {
+            Ok(_) => true,
+            Err(err) => {
+                log::error!("opening {target:?}: {err}");
+                false
+            }
+        }
+    }
+
+    fn launch(&self, exe: &str, cmdline: &str, dir: Option<&str>) -> bool {
+        // Another run of ourselves.  It reads the exe after any --chdir, so the
+        // path must not be relative.
+        let spawn = || -> std::io::Result<std::process::Child> {
+            let mut cmd = std::process::Command::new(std::env::current_exe()?);
+            cmd.arg(std::fs::canonicalize(exe)?).arg(cmdline);
+            if let Some(dir) = dir {
+                cmd.arg("--chdir").arg(dir);
+            }
+            cmd.spawn()
+        };
+        match spawn() {
+            Ok(_) => true,
+            Err(err) => {
+                log::error!("launching {exe:?}: {err}");
+                false
+            }
+        }
+    }
+
+    fn pick_file(&self, dialog: &win32::FileDialog) -> Option<String> {
+        dialog::pick_file(dialog)
+    }
+
     fn create_window(&mut self, hwnd: u32) -> Box<dyn win32::Window> {
         let mut env = self.0.borrow_mut();
         let gui = env.ensure_gui().unwrap();
@@ -143,6 +339,24 @@ impl win32::Host for EnvRef {
         let gui = env.ensure_gui().unwrap();
         gui.create_surface(opts)
     }
+
+    fn create_audio(&mut self, sample_rate: u32) -> Option<Box<dyn win32::Audio>> {
+        let mut env = self.0.borrow_mut();
+        let gui = env.ensure_gui().unwrap();
+        gui.create_audio(sample_rate)
+    }
+
+    fn gamepad(&self, id: u32) -> Option<win32::GamepadState> {
+        let env = self.0.borrow();
+        env.gui.as_ref()?.gamepad(id)
+    }
+
+    fn grab_mouse(&self, grab: bool) {
+        let env = self.0.borrow();
+        if let Some(gui) = env.gui.as_ref() {
+            gui.grab_mouse(grab);
+        }
+    }
 }
 
 #[derive(argh::FromArgs)]
@@ -152,10 +366,25 @@ struct Args {
     #[argh(option)]
     chdir: Option<String>,
 
-    /// winapi systems to trace; see trace.rs for docs
+    /// read game settings from this profile file; options given on the command line
+    /// take precedence
+    #[argh(option)]
+    profile: Option<String>,
+
+    /// write the game settings given (including any from --profile) to this profile
+    /// file, then exit without running
+    #[argh(option)]
+    export_profile: Option<String>,
+
+    /// winapi systems and functions to trace, like "ddraw/*,-kernel32/GetTickCount";
+    /// see trace.rs for docs
     #[argh(option)]
     win32_trace: Option<String>,
 
+    /// format of winapi traces: native, or relay to match Wine's WINEDEBUG=+relay
+    #[argh(option, default = "win32::trace::Format::Native")]
+    win32_trace_format: win32::trace::Format,
+
     /// log CPU state upon each new basic block
     #[argh(switch)]
     #[cfg(feature = "x86-emu")]
@@ -165,6 +394,12 @@ struct Args {
     #[argh(option)]
     trace_points: Option<String>,
 
+    /// log each instruction that accesses memory, given as hex "ADDR[+LEN][:r|w|rw]"
+    /// (default 4 bytes, writes), like "4a1f00+4:w"; repeatable
+    #[cfg(feature = "x86-emu")]
+    #[argh(option)]
+    watch: Vec<x86::watch::Watchpoint>,
+
     /// exe to run
     #[argh(positional)]
     exe: String,
@@ -177,6 +412,188 @@ struct Args {
     #[cfg(feature = "x86-emu")]
     #[argh(option)]
     snapshot: Option<String>,
+
+    /// UI language as a hex LANGID (e.g. 407 for German), for picking localized resources
+    #[argh(option)]
+    lang: Option<String>,
+
+    /// ANSI code page that text input is encoded in for non-Unicode windows: 1252 (default),
+    /// 65001 for UTF-8, or 932, 936, 949 or 950 for Japanese, Simplified Chinese, Korean
+    /// or Traditional Chinese
+    #[argh(option)]
+    code_page: Option<u32>,
+
+    /// address space for large address aware programs: "2gb", "3gb", or "4gb" (default)
+    #[argh(option)]
+    address_space: Option<win32::AddressSpace>,
+
+    /// when the window loses focus: "continue" (default) with audio muted, "pause", or a frame
+    /// rate to throttle to
+    #[argh(option)]
+    background: Option<win32::BackgroundPolicy>,
+
+    /// cap on the host CPU used, as a percentage of one core
+    #[argh(option)]
+    cpu_limit: Option<u32>,
+
+    /// processor reported by CPUID: pentium, pentium-mmx (default) or k6-2, optionally
+    /// followed by overrides like ",mhz=300,tsc=instructions,flags=accurate,features=fpu/tsc/cx8,vendor=..."
+    #[cfg(feature = "x86-emu")]
+    #[argh(option)]
+    cpu: Option<x86::CpuModel>,
+
+    /// cap emulated execution at roughly this many MHz, for programs that run too fast
+    #[cfg(all(feature = "x86-emu", feature = "sdl"))]
+    #[argh(option)]
+    speed_limit: Option<u32>,
+
+    /// video memory reported to DirectDraw, in megabytes (default 8)
+    #[argh(option)]
+    video_memory: Option<u32>,
+
+    /// graphics card reported to Direct3D: generic, voodoo3, tnt2 or geforce2, optionally
+    /// followed by overrides like ",texture=1024,zbuffer=16/24,tnl=0,name=...,vendor=0x10de"
+    #[argh(option)]
+    gpu: Option<win32::Gpu>,
+
+    /// directory of track02.wav, track03.wav etc. files to play as CD audio tracks
+    #[argh(option)]
+    cd_audio: Option<String>,
+
+    /// assignment of controller inputs to joystick axes and buttons, like the default
+    /// "x=lx,y=ly,z=rt,r=rx,u=ry,v=lt,buttons=a/b/x/y/lb/rb/back/start/ls/rs"
+    #[argh(option)]
+    joystick_map: Option<win32::JoystickMap>,
+
+    /// registry hive file, loaded at start if it exists and saved back at exit, so the
+    /// game's settings persist between runs
+    #[argh(option)]
+    registry: Option<String>,
+
+    /// user name reported to the program (default "User"), for games that record it in
+    /// their saves
+    #[argh(option)]
+    user_name: Option<String>,
+
+    /// computer name reported to the program (default "RETROWIN32")
+    #[argh(option)]
+    computer_name: Option<String>,
+
+    /// keep a special folder in a host directory, like "documents=saves"; folders are
+    /// desktop, documents and appdata; repeatable
+    #[argh(option)]
+    folder: Vec<win32::FolderMapping>,
+
+    /// connect a serial or parallel port, like "COM1=tcp:localhost:2323"; backends are
+    /// null, file:PATH, tcp:HOST:PORT, and pty (Unix); repeatable
+    #[argh(option)]
+    port: Vec<port::PortConfig>,
+
+    /// run statically linked CRT routines (memcpy etc.) on the host rather than as
+    /// emulated code, by patching the program's copies of them as they load
+    #[argh(switch)]
+    crt_fast_paths: bool,
+
+    /// resolve delay-loaded imports when first called, through the program's own
+    /// helper, rather than at load
+    #[argh(switch)]
+    lazy_delay_imports: bool,
+
+    /// symbol file for the exe, naming functions in traces and crash reports: a
+    /// linker .map, or .json mapping hex RVAs to names
+    #[argh(option)]
+    symbols: Option<String>,
+
+    /// record the run's inputs (time, messages, random numbers, etc.) to this path,
+    /// for replaying with --replay
+    #[argh(option)]
+    record: Option<String>,
+
+    /// replay the inputs recorded with --record at this path, repeating that run
+    #[argh(option)]
+    replay: Option<String>,
+
+    /// let the program go fullscreen when it asks to (ignored by default, for debugging ease)
+    #[argh(switch)]
+    fullscreen: bool,
+
+    /// run without a window, for batch runs: nothing is displayed, no input arrives and
+    /// waits are skipped; exits with the program's exit code, or 0 if stopped by
+    /// --frames or --until, or 1 if it crashed or waited for input
+    #[argh(switch)]
+    headless: bool,
+
+    /// stop once this many frames have been shown (needs --headless)
+    #[cfg(feature = "x86-emu")]
+    #[argh(option)]
+    frames: Option<u32>,
+
+    /// stop on reaching this address (hex) or calling this function, as "GetTickCount"
+    /// or "kernel32.dll!GetTickCount"
+    #[cfg(feature = "x86-emu")]
+    #[argh(option)]
+    until: Option<String>,
+
+    /// write the last frame shown to this path as a PNG at exit (needs --headless)
+    #[argh(option)]
+    screenshot: Option<String>,
+
+    /// count executed opcodes and write a report to this path at exit;
+    /// the format follows the extension: .csv, .json, or text otherwise
+    #[cfg(feature = "x86-emu")]
+    #[argh(option)]
+    op_stats: Option<String>,
+
+    /// compile hot code to native code
+    #[cfg(feature = "jit")]
+    #[argh(switch)]
+    jit: bool,
+
+    /// count calls to each Windows API function and the time spent in them, and
+    /// write them to this path at exit (or crash), listing unimplemented functions first
+    #[cfg(feature = "x86-emu")]
+    #[argh(option)]
+    api_stats: Option<String>,
+
+    /// run this Rhai script, whose hooks on API calls and guest addresses can work
+    /// around a program's problems; see win32/src/script.rs
+    #[cfg(feature = "script")]
+    #[argh(option)]
+    script: Option<String>,
+
+    /// sample where time goes, in guest code and shims, and write it to this path at
+    /// exit as collapsed stacks for flamegraph tools (inferno, speedscope)
+    #[cfg(feature = "x86-emu")]
+    #[argh(option)]
+    sample_profile: Option<String>,
+
+    /// record executed code and write it to this path at exit, in the drcov
+    /// format understood by lighthouse and similar IDA/Ghidra plugins
+    #[cfg(feature = "x86-emu")]
+    #[argh(option)]
+    coverage: Option<String>,
+
+    /// record every frame shown into this directory, as images listed with their
+    /// frame number and guest time in frames.txt
+    #[cfg(feature = "x86-emu")]
+    #[argh(option)]
+    capture: Option<String>,
+
+    /// with --capture, also record the palette indices and palette of 8-bit frames
+    #[cfg(feature = "x86-emu")]
+    #[argh(switch)]
+    capture_indexed: bool,
+
+    /// on SIGINT/SIGTERM, write a snapshot before exiting
+    #[cfg(feature = "x86-emu")]
+    #[argh(switch)]
+    snapshot_on_stop: bool,
+
+    /// path snapshots are written to, on SIGUSR1 or with --snapshot-on-stop;
+    /// defaults to "snapshot"
+    #[cfg(feature = "x86-emu")]
+    #[argh(option, default = "String::from(\"snapshot\")")]
+    snapshot_path: String,
 }
 
 /// Transfer control to the executable's entry point.
@@ -198,6 +615,55 @@ fn jump_to_entry_point(machine: &mut win32::Machine, entry_point: u32) {
     win32::shims::call_sync(pin);
 }
 
+#[cfg(feature = "x86-emu")]
+fn write_api_stats(path: &str) {
+    match std::fs::write(path, win32::api_stats::report()) {
+        Ok(()) => log::info!("wrote API stats to {path:?}"),
+        Err(err) => log::error!("writing API stats: {err}"),
+    }
+}
+
+/// Where --until stops.
+#[cfg(feature = "x86-emu")]
+enum Until {
+    Addr(u32),
+    Call(String),
+}
+
+#[cfg(feature = "x86-emu")]
+impl Until {
+    fn parse(arg: &str) -> Self {
+        match u32::from_str_radix(arg.trim_start_matches("0x"), 16) {
+            Ok(addr) => Until::Addr(addr),
+            Err(_) => Until::Call(arg.to_string()),
+        }
+    }
+
+    /// Whether the machine is about to run the address or function.
+    fn reached(&self, machine: &win32::Machine) -> bool {
+        let eip = machine.emu.x86.cpu().regs.eip;
+        match self {
+            Until::Addr(addr) => eip == *addr,
+            // Calls land on the shim's address, labelled with its name.
+            Until::Call(name) => machine.labels.get(&eip).is_some_and(|label| {
+                label == name || label.rsplit_once('!').is_some_and(|(_, func)| func == name)
+            }),
+        }
+    }
+}
+
+/// Run f on the machine, printing the guest's backtrace should the emulator panic.
+#[cfg(feature = "x86-emu")]
+fn with_backtrace<T>(machine: &mut win32::Machine, f: impl FnOnce(&mut win32::Machine) -> T) -> T {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(&mut *machine))) {
+        Ok(ret) => ret,
+        Err(panic) => {
+            log::error!("{}", win32::backtrace::current(machine));
+            std::panic::resume_unwind(panic)
+        }
+    }
+}
+
 #[cfg(any(feature = "x86-emu", feature = "x86-unicorn"))]
 fn print_trace(machine: &win32::Machine) {
     #[cfg(feature = "x86-emu")]
@@ -233,6 +699,42 @@ fn print_trace(machine: &win32::Machine) {
     };
 
     println!("@{eip:x}\n  eax:{eax:x} ebx:{ebx:x} ecx:{ecx:x} edx:{edx:x} esi:{esi:x} edi:{edi:x} esp:{esp:x} st_top:{st_top}");
+    // On a line of its own, as scripts like lldb-trace.py parse the "@" line.
+    if let Some(name) = machine.symbols.name(eip as u32) {
+        println!("  in {name}");
+    }
+}
+
+/// Parse the command line, filling in the settings from any --profile.
+/// Returns the arguments and the profile too, for --export-profile and the profile's
+/// embedded files.
+fn parse_args() -> anyhow::Result<(Args, Vec<String>, Option<profile::Profile>)> {
+    use argh::FromArgs;
+    let mut args: Vec<String> = std::env::args().collect();
+    let cmd = args.remove(0);
+    let parse = |args: &[String]| {
+        let strs: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+        match Args::from_args(&[&cmd], &strs) {
+            Ok(parsed) => parsed,
+            Err(exit) => match exit.status {
+                Ok(()) => {
+                    print!("{}", exit.output);
+                    std::process::exit(0);
+                }
+                Err(()) => {
+                    eprint!("{}", exit.output);
+                    std::process::exit(1);
+                }
+            },
+        }
+    };
+    let parsed = parse(&args);
+    let Some(path) = &parsed.profile else {
+        return Ok((parsed, args, None));
+    };
+    let profile = profile::Profile::load(path)?;
+    args.splice(0..0, profile.to_args(&args)?);
+    Ok((parse(&args), args, Some(profile)))
 }
 
 fn main() -> anyhow::Result<()> {
@@ -243,7 +745,14 @@ fn main() -> anyhow::Result<()> {
         crate::resv32::init_resv32();
     }
 
-    let args: Args = argh::from_env();
+    let (args, raw_args, loaded_profile) = parse_args()?;
+
+    if let Some(path) = &args.export_profile {
+        let mut profile = profile::Profile::from_args(&raw_args);
+        profile.embed_files(loaded_profile.as_ref())?;
+        std::fs::write(path, profile.to_string()).map_err(|err| anyhow!("{path}: {err}"))?;
+        return Ok(());
+    }
 
     if let Some(dir) = args.chdir {
         std::env::set_current_dir(dir).unwrap();
@@ -263,15 +772,135 @@ fn main() -> anyhow::Result<()> {
     }
 
     win32::trace::set_scheme(args.win32_trace.as_deref().unwrap_or("-"));
+    win32::trace::set_format(args.win32_trace_format);
+    #[cfg(feature = "x86-emu")]
+    if let Some(path) = args.api_stats.clone() {
+        win32::api_stats::enable();
+        // Unimplemented functions crash, and those are what the report is for.
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            write_api_stats(&path);
+            hook(info);
+        }));
+    }
     let cmdline = args.cmdline.as_ref().unwrap_or(&args.exe);
 
     let buf = std::fs::read(&args.exe).map_err(|err| anyhow!("{}: {}", args.exe, err))?;
-    let host = EnvRef(Rc::new(RefCell::new(Env::new())));
-    let mut machine = win32::Machine::new(Box::new(host.clone()), cmdline.clone());
+    let host = EnvRef(Rc::new(RefCell::new(Env::new(
+        args.fullscreen,
+        args.port.clone(),
+        args.headless,
+    ))));
+    let screen = host.0.borrow().screen.clone();
+    #[cfg(feature = "x86-emu")]
+    if args.frames.is_some() && screen.is_none() {
+        bail!("--frames needs --headless");
+    }
+    if args.screenshot.is_some() && screen.is_none() {
+        bail!("--screenshot needs --headless");
+    }
+    if args.record.is_some() && args.replay.is_some() {
+        bail!("--record and --replay can't be used together");
+    }
+    let mut guest_host: Box<dyn win32::Host> = Box::new(host.clone());
+    if let Some(path) = &args.record {
+        guest_host = Box::new(win32::replay::ReplayHost::record(guest_host, path)?);
+    } else if let Some(path) = &args.replay {
+        guest_host = Box::new(win32::replay::ReplayHost::replay(guest_host, path)?);
+    }
+    let mut machine = win32::Machine::new(guest_host, cmdline.clone());
+    machine.pause = host.0.borrow().pause.clone();
+    if let Some(lang) = &args.lang {
+        machine.state.kernel32.ui_language = u16::from_str_radix(lang.trim_start_matches("0x"), 16)
+            .map_err(|_| anyhow!("bad LANGID {lang:?}"))?;
+    }
+    if let Some(code_page) = args.code_page {
+        if !win32::is_supported_code_page(code_page) {
+            bail!("unsupported code page {code_page}");
+        }
+        machine.state.kernel32.code_page = code_page;
+    }
+    if let Some(space) = args.address_space {
+        machine.state.kernel32.address_space = space;
+    }
+    if let Some(policy) = args.background {
+        machine.background = policy;
+    }
+    if let Some(percent) = args.cpu_limit {
+        if percent == 0 {
+            return Err(anyhow!("bad cpu limit {percent}"));
+        }
+        machine.cpu_limit = Some(percent);
+    }
+    #[cfg(feature = "x86-emu")]
+    if let Some(cpu) = &args.cpu {
+        machine.emu.x86.set_model(cpu.clone());
+    }
+    #[cfg(all(feature = "x86-emu", feature = "sdl"))]
+    if let Some(mhz) = args.speed_limit {
+        if mhz == 0 {
+            return Err(anyhow!("bad speed limit {mhz}"));
+        }
+        machine.emu.x86.speed_limit = Some(x86::cycles::SpeedLimit::new(mhz));
+    }
+    if let Some(mb) = args.video_memory {
+        machine.state.ddraw.video_memory = mb << 20;
+    }
+    if let Some(gpu) = &args.gpu {
+        machine.state.ddraw.gpu = gpu.clone();
+    }
+    machine.state.winmm.cd_audio = args.cd_audio.clone();
+    if let Some(map) = &args.joystick_map {
+        machine.state.winmm.joystick_map = map.clone();
+    }
+    machine.state.kernel32.crt_fast_paths = args.crt_fast_paths;
+    machine.state.kernel32.eager_delay_imports = !args.lazy_delay_imports;
+    if let Some(name) = &args.user_name {
+        machine.state.advapi32.user_name = name.clone();
+    }
+    if let Some(name) = &args.computer_name {
+        machine.state.kernel32.computer_name = name.clone();
+    }
+    for mapping in &args.folder {
+        let guest_dir = mapping.folder.guest_path();
+        machine.state.kernel32.map_dir(guest_dir, &mapping.host_dir);
+    }
+    // Whether to seed the hive from the profile: there's no hive file to load.
+    let mut seed_registry = args.registry.is_none();
+    if let Some(path) = &args.registry {
+        match std::fs::read_to_string(path) {
+            Ok(json) => {
+                machine.state.advapi32.registry =
+                    win32::Registry::from_json(&json).map_err(|err| anyhow!("{path}: {err}"))?;
+            }
+            // Start a new hive, written out even if unchanged so it can be edited.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                machine.state.advapi32.registry.dirty = true;
+                seed_registry = true;
+            }
+            Err(err) => bail!("{path}: {err}"),
+        }
+    }
+    let registry_seed = loaded_profile
+        .as_ref()
+        .and_then(|profile| profile.file("registry"));
+    if let (true, Some(json)) = (seed_registry, registry_seed) {
+        let mut registry =
+            win32::Registry::from_json(json).map_err(|err| anyhow!("profile registry: {err}"))?;
+        // Written out to the --registry file, if any, as a new hive would be.
+        registry.dirty = args.registry.is_some();
+        machine.state.advapi32.registry = registry;
+    }
 
     let addrs = machine
         .load_exe(&buf, cmdline.clone(), false)
         .map_err(|err| anyhow!("loading {}: {}", args.exe, err))?;
+    if let Some(path) = &args.symbols {
+        let exe = &machine.state.kernel32.images[0];
+        let (base, size) = (exe.base, exe.size);
+        let count = machine.symbols.load(path, base, size)?;
+        log::info!("{path}: loaded {count} symbols");
+    }
 
     #[cfg(feature = "x86-64")]
     unsafe {
@@ -292,17 +921,80 @@ fn main() -> anyhow::Result<()> {
             if libc::signal(libc::SIGUSR1, sigusr1 as *const fn(usize) as usize) != 0 {
                 log::error!("failed to install signal handler for snapshot");
             }
+            unsafe extern "C" fn sigusr2(_sig: usize) {
+                win32::framedump::request();
+            }
+            if libc::signal(libc::SIGUSR2, sigusr2 as *const fn(usize) as usize) != 0 {
+                log::error!("failed to install signal handler for frame dump");
+            }
+            _ = STOP_HANDLE.set(machine.stop_handle());
+            unsafe extern "C" fn sigint(_sig: usize) {
+                if let Some(stop) = STOP_HANDLE.get() {
+                    stop.request_stop();
+                }
+            }
+            for sig in [libc::SIGINT, libc::SIGTERM] {
+                if libc::signal(sig, sigint as *const fn(usize) as usize) == libc::SIG_ERR {
+                    log::error!("failed to install signal handler for stop");
+                }
+            }
         }
 
         if let Some(snap) = args.snapshot {
-            let bytes = std::fs::read(snap).unwrap();
+            let bytes = std::fs::read(&snap).map_err(|err| anyhow!("{snap}: {err}"))?;
             machine.load_snapshot(&bytes);
         }
 
+        if args.op_stats.is_some() {
+            machine.emu.x86.opstats = Some(Default::default());
+        }
+        if args.coverage.is_some() {
+            machine.emu.x86.coverage = Some(Default::default());
+        }
+        for watch in args.watch {
+            machine.emu.x86.watchpoints.add(
+                watch,
+                Some(Box::new(|_cpu, hit| {
+                    let kind = if hit.write { "write" } else { "read" };
+                    log::info!(
+                        "watch {:x}: {kind} {:x}+{:x} at {:x}",
+                        hit.watch.addr,
+                        hit.addr,
+                        hit.len,
+                        hit.ip
+                    );
+                    false
+                })),
+            );
+        }
+        #[cfg(feature = "jit")]
+        if args.jit {
+            machine.emu.x86.jit = Some(Default::default());
+        }
+        #[cfg(feature = "script")]
+        if let Some(path) = &args.script {
+            win32::script::load(&mut machine, path)?;
+        } else if let Some(source) = loaded_profile
+            .as_ref()
+            .and_then(|profile| profile.file("script"))
+        {
+            win32::script::load_source(&mut machine, "profile script", source)?;
+        }
+        let until = args.until.as_deref().map(Until::parse);
+        if let Some(Until::Addr(addr)) = until {
+            machine
+                .emu
+                .x86
+                .add_breakpoint(machine.emu.memory.mem(), addr);
+        }
+        if args.capture.is_some() {
+            win32::capture::record(true, args.capture_indexed);
+        }
+
         let start = std::time::Instant::now();
         if args.trace_blocks {
             let mut seen_blocks = std::collections::HashSet::new();
-            while machine.run() {
+            while with_backtrace(&mut machine, |machine| machine.run()) {
                 let regs = &machine.emu.x86.cpu().regs;
                 if regs.eip & 0xFFFF_0000 == 0xF1A7_0000 {
                     continue;
@@ -321,8 +1013,8 @@ fn main() -> anyhow::Result<()> {
                     .add_breakpoint(machine.emu.memory.mem(), next_trace);
                 loop {
                     // Ignore errors here because we will hit breakpoints.
-                    machine.run();
-                    if machine.emu.x86.cpu().regs.eip == next_trace {
+                    with_backtrace(&mut machine, |machine| machine.run());
+                    if machine.stopped() || machine.emu.x86.cpu().regs.eip == next_trace {
                         break;
                     }
                 }
@@ -334,27 +1026,74 @@ fn main() -> anyhow::Result<()> {
                 print_trace(&machine);
             }
         } else {
-            while machine.run() {
+            let mut profiler = args
+                .sample_profile
+                .as_ref()
+                .map(|_| win32::profiler::Profiler::new(std::time::Duration::from_millis(1)));
+            while match &mut profiler {
+                Some(profiler) => with_backtrace(&mut machine, |machine| profiler.run(machine)),
+                None => with_backtrace(&mut machine, |machine| machine.run()),
+            } {
+                if let Some(until) = &until {
+                    if until.reached(&machine) {
+                        log::info!("reached {}", args.until.as_ref().unwrap());
+                        machine.request_stop();
+                    }
+                }
+                if let (Some(frames), Some(screen)) = (args.frames, &screen) {
+                    if screen.borrow().frames >= frames {
+                        log::info!("shown {frames} frames");
+                        machine.request_stop();
+                    }
+                }
                 unsafe {
                     if SNAPSHOT_REQUESTED {
-                        let buf = machine.snapshot();
-                        let path = "snapshot";
-                        std::fs::write(path, buf).unwrap();
-                        log::info!("wrote snapshot to {path:?}");
+                        write_snapshot(&machine, &args.snapshot_path);
                         SNAPSHOT_REQUESTED = false;
                     }
                 }
+                if let Some(dump) = win32::framedump::take() {
+                    match write_frame_dump(&dump) {
+                        Ok(dir) => log::info!("wrote frame dump to {dir:?}"),
+                        Err(err) => log::error!("writing frame dump: {err}"),
+                    }
+                }
+                if let Some(dir) = &args.capture {
+                    while let Some(frame) = win32::capture::take() {
+                        if let Err(err) = write_capture(dir, &frame) {
+                            log::error!("writing capture: {err}");
+                        }
+                    }
+                }
+            }
+            if let (Some(path), Some(profiler)) = (&args.sample_profile, &profiler) {
+                std::fs::write(path, profiler.collapsed(&machine))?;
+                log::info!("wrote profile to {path:?}");
             }
         }
 
-        match &machine.emu.x86.cpu().state {
-            x86::CPUState::Error(error) => {
-                log::error!("{:?}", error);
-                dump_asm(&machine, 5);
+        if machine.stopped() {
+            log::info!("stopped at {:x}", machine.emu.x86.cpu().regs.eip);
+            if args.headless {
+                print_trace(&machine);
+                println!("{}", win32::backtrace::current(&machine));
+            }
+            if args.snapshot_on_stop {
+                write_snapshot(&machine, &args.snapshot_path);
+            }
+        } else {
+            match &machine.emu.x86.cpu().state {
+                x86::CPUState::Error(error) => {
+                    log::error!("{error}");
+                    dump_asm(&machine, 5);
+                }
+                x86::CPUState::Exit(_) => {}
+                // The headless GUI gives up waiting for input, having logged it.
+                x86::CPUState::Blocked(_) => {}
+                x86::CPUState::Running => unreachable!(),
+                // Delivered to the program as soon as it's raised.
+                x86::CPUState::Exception(_) => unreachable!(),
             }
-            x86::CPUState::Exit(_) => {}
-            x86::CPUState::Blocked(_) => unreachable!(),
-            x86::CPUState::Running => unreachable!(),
         }
 
         let millis = start.elapsed().as_millis() as usize;
@@ -366,6 +1105,30 @@ fn main() -> anyhow::Result<()> {
                 (machine.emu.x86.instr_count / millis) / 1000
             );
             eprintln!("icache: {}", machine.emu.x86.icache.stats());
+            #[cfg(feature = "jit")]
+            if let Some(jit) = &machine.emu.x86.jit {
+                eprintln!("jit: {}", jit.stats());
+            }
+        }
+
+        if let (Some(path), Some(stats)) = (&args.op_stats, &machine.emu.x86.opstats) {
+            let format = match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+                Some(ext @ ("csv" | "json")) => ext.parse().unwrap(),
+                _ => x86::opstats::ReportFormat::Text,
+            };
+            std::fs::write(path, stats.report(format))?;
+            log::info!("wrote op stats to {path:?}");
+        }
+
+        if let Some(path) = &args.api_stats {
+            write_api_stats(path);
+        }
+
+        // Name the exe by its file, as it was loaded under its command line.
+        if let (Some(path), Some(drcov)) = (&args.coverage, machine.coverage_drcov(Some(&args.exe)))
+        {
+            std::fs::write(path, drcov)?;
+            log::info!("wrote coverage to {path:?}");
         }
     }
 
@@ -384,5 +1147,30 @@ fn main() -> anyhow::Result<()> {
         }
     }
 
+    let registry = &machine.state.advapi32.registry;
+    if let (Some(path), true) = (&args.registry, registry.dirty) {
+        std::fs::write(path, registry.to_json())?;
+        log::info!("wrote registry to {path:?}");
+    }
+
+    if let (Some(path), Some(screen)) = (&args.screenshot, &screen) {
+        match screen.borrow().png() {
+            Ok(png) => {
+                std::fs::write(path, png)?;
+                log::info!("wrote screenshot to {path:?}");
+            }
+            Err(err) => log::error!("screenshot: {err}"),
+        }
+    }
+
+    if args.headless {
+        let exit_code = host.0.borrow().exit_code;
+        std::process::exit(match exit_code {
+            Some(code) => code as i32,
+            None if machine.stopped() => 0,
+            None => 1,
+        });
+    }
+
     Ok(())
 }
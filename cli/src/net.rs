@@ -0,0 +1,296 @@
+//! Host sockets for the guest's Winsock, over std::net.
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{
+        Ipv4Addr, Shutdown, SocketAddr, SocketAddrV4, TcpListener, TcpStream, ToSocketAddrs,
+        UdpSocket,
+    },
+    sync::mpsc,
+};
+
+fn would_block() -> std::io::Error {
+    ErrorKind::WouldBlock.into()
+}
+
+fn v4(addr: SocketAddr) -> std::io::Result<SocketAddrV4> {
+    match addr {
+        SocketAddr::V4(addr) => Ok(addr),
+        SocketAddr::V6(_) => Err(ErrorKind::Unsupported.into()),
+    }
+}
+
+enum Tcp {
+    Idle,
+    /// std::net can only connect by waiting, so that happens on a thread.
+    Connecting(mpsc::Receiver<std::io::Result<TcpStream>>),
+    Connected(TcpStream),
+    /// A connection readable() accepted, kept for the next accept().
+    Listening(TcpListener, Option<(TcpStream, SocketAddr)>),
+}
+
+/// A TCP socket.  std::net binds as part of connecting or listening, so bind()
+/// only records the address, for listen() to use.
+struct TcpSocket {
+    addr: SocketAddrV4,
+    state: Tcp,
+}
+
+impl TcpSocket {
+    fn stream(&mut self) -> std::io::Result<&mut TcpStream> {
+        match &mut self.state {
+            Tcp::Connected(stream) => Ok(stream),
+            Tcp::Connecting(_) => Err(would_block()),
+            _ => Err(ErrorKind::NotConnected.into()),
+        }
+    }
+
+    /// Check on a connection in progress.
+    fn poll_connect(&mut self) -> std::io::Result<()> {
+        let Tcp::Connecting(rx) = &self.state else {
+            return Ok(());
+        };
+        match rx.try_recv() {
+            Err(mpsc::TryRecvError::Empty) => Err(would_block()),
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.state = Tcp::Idle;
+                Err(ErrorKind::ConnectionAborted.into())
+            }
+            Ok(Err(err)) => {
+                self.state = Tcp::Idle;
+                Err(err)
+            }
+            Ok(Ok(stream)) => {
+                stream.set_nonblocking(true)?;
+                self.state = Tcp::Connected(stream);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl win32::Socket for TcpSocket {
+    fn bind(&mut self, addr: SocketAddrV4) -> std::io::Result<()> {
+        self.addr = addr;
+        Ok(())
+    }
+
+    fn listen(&mut self) -> std::io::Result<()> {
+        if let Tcp::Idle = self.state {
+            let listener = TcpListener::bind(self.addr)?;
+            listener.set_nonblocking(true)?;
+            self.addr = v4(listener.local_addr()?)?;
+            self.state = Tcp::Listening(listener, None);
+        }
+        Ok(())
+    }
+
+    fn accept(&mut self) -> std::io::Result<(Box<dyn win32::Socket>, SocketAddrV4)> {
+        let Tcp::Listening(listener, pending) = &mut self.state else {
+            return Err(ErrorKind::InvalidInput.into());
+        };
+        let (stream, peer) = match pending.take() {
+            Some(accepted) => accepted,
+            None => listener.accept()?,
+        };
+        stream.set_nonblocking(true)?;
+        let socket = TcpSocket {
+            addr: v4(stream.local_addr()?)?,
+            state: Tcp::Connected(stream),
+        };
+        Ok((Box::new(socket), v4(peer)?))
+    }
+
+    fn connect(&mut self, addr: SocketAddrV4) -> std::io::Result<()> {
+        if let Tcp::Idle = self.state {
+            let (tx, rx) = mpsc::channel();
+            std::thread::spawn(move || {
+                _ = tx.send(TcpStream::connect(addr));
+            });
+            self.state = Tcp::Connecting(rx);
+        }
+        self.poll_connect()
+    }
+
+    fn send(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.stream()?.write(buf)
+    }
+
+    fn send_to(&mut self, buf: &[u8], _addr: SocketAddrV4) -> std::io::Result<usize> {
+        self.send(buf)
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.stream()?.read(buf)
+    }
+
+    fn recv_from(&mut self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddrV4)> {
+        let n = self.recv(buf)?;
+        Ok((n, self.peer_addr()?))
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddrV4> {
+        match &self.state {
+            Tcp::Connected(stream) => v4(stream.local_addr()?),
+            _ => Ok(self.addr),
+        }
+    }
+
+    fn peer_addr(&self) -> std::io::Result<SocketAddrV4> {
+        match &self.state {
+            Tcp::Connected(stream) => v4(stream.peer_addr()?),
+            _ => Err(ErrorKind::NotConnected.into()),
+        }
+    }
+
+    fn readable(&mut self) -> bool {
+        match &mut self.state {
+            // An error or the end of the stream counts, as recv() will report it.
+            Tcp::Connected(stream) => {
+                !matches!(stream.peek(&mut [0]), Err(err) if err.kind() == ErrorKind::WouldBlock)
+            }
+            Tcp::Listening(listener, pending) => {
+                if pending.is_none() {
+                    *pending = listener.accept().ok();
+                }
+                pending.is_some()
+            }
+            _ => false,
+        }
+    }
+
+    fn writable(&mut self) -> bool {
+        match self.poll_connect() {
+            Err(err) if err.kind() == ErrorKind::WouldBlock => false,
+            Err(_) => true,
+            Ok(()) => matches!(self.state, Tcp::Connected(_)),
+        }
+    }
+
+    fn shutdown(&mut self, how: Shutdown) -> std::io::Result<()> {
+        match &self.state {
+            Tcp::Connected(stream) => stream.shutdown(how),
+            _ => Err(ErrorKind::NotConnected.into()),
+        }
+    }
+}
+
+/// A UDP socket, bound to any port on first use if not bound explicitly.
+struct UdpSock {
+    socket: Option<UdpSocket>,
+    peer: Option<SocketAddrV4>,
+}
+
+impl UdpSock {
+    fn socket(&mut self) -> std::io::Result<&UdpSocket> {
+        if self.socket.is_none() {
+            self.bind_to(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))?;
+        }
+        Ok(self.socket.as_ref().unwrap())
+    }
+
+    fn bind_to(&mut self, addr: SocketAddrV4) -> std::io::Result<()> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        // Games broadcast to find each other on a LAN.
+        socket.set_broadcast(true)?;
+        self.socket = Some(socket);
+        Ok(())
+    }
+}
+
+impl win32::Socket for UdpSock {
+    fn bind(&mut self, addr: SocketAddrV4) -> std::io::Result<()> {
+        if self.socket.is_some() {
+            return Err(ErrorKind::InvalidInput.into());
+        }
+        self.bind_to(addr)
+    }
+
+    fn listen(&mut self) -> std::io::Result<()> {
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    fn accept(&mut self) -> std::io::Result<(Box<dyn win32::Socket>, SocketAddrV4)> {
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    fn connect(&mut self, addr: SocketAddrV4) -> std::io::Result<()> {
+        self.socket()?;
+        self.peer = Some(addr);
+        Ok(())
+    }
+
+    fn send(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let peer = self.peer.ok_or(ErrorKind::NotConnected)?;
+        self.send_to(buf, peer)
+    }
+
+    fn send_to(&mut self, buf: &[u8], addr: SocketAddrV4) -> std::io::Result<usize> {
+        self.socket()?.send_to(buf, addr)
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(self.recv_from(buf)?.0)
+    }
+
+    fn recv_from(&mut self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddrV4)> {
+        loop {
+            let (n, from) = self.socket()?.recv_from(buf)?;
+            let from = v4(from)?;
+            // A connected datagram socket only hears from its peer.
+            if self.peer.map_or(true, |peer| peer == from) {
+                return Ok((n, from));
+            }
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddrV4> {
+        match &self.socket {
+            Some(socket) => v4(socket.local_addr()?),
+            None => Ok(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)),
+        }
+    }
+
+    fn peer_addr(&self) -> std::io::Result<SocketAddrV4> {
+        self.peer.ok_or(ErrorKind::NotConnected.into())
+    }
+
+    fn readable(&mut self) -> bool {
+        let Some(socket) = &self.socket else {
+            return false;
+        };
+        !matches!(socket.peek_from(&mut [0]), Err(err) if err.kind() == ErrorKind::WouldBlock)
+    }
+
+    fn writable(&mut self) -> bool {
+        true
+    }
+
+    fn shutdown(&mut self, _how: Shutdown) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn socket(kind: win32::SocketKind) -> Box<dyn win32::Socket> {
+    match kind {
+        win32::SocketKind::Stream => Box::new(TcpSocket {
+            addr: SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
+            state: Tcp::Idle,
+        }),
+        win32::SocketKind::Datagram => Box::new(UdpSock {
+            socket: None,
+            peer: None,
+        }),
+    }
+}
+
+pub fn resolve(name: &str) -> std::io::Result<Vec<Ipv4Addr>> {
+    Ok((name, 0)
+        .to_socket_addrs()?
+        .filter_map(|addr| match addr {
+            SocketAddr::V4(addr) => Some(*addr.ip()),
+            SocketAddr::V6(_) => None,
+        })
+        .collect())
+}
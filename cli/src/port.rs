@@ -0,0 +1,142 @@
+//! Backends for the guest's serial and parallel ports, as given by --port.
+
+use std::io::{Read, Write};
+
+/// What a port is connected to.
+#[derive(Debug, Clone)]
+enum Backend {
+    /// Discards output and never receives anything.
+    Null,
+    /// Appends output to a file, for capturing what's sent to a printer.
+    File(String),
+    /// Connects to a TCP server, such as another retrowin32 via a bridge like socat.
+    Tcp(String),
+    /// A pseudo-terminal, whose name is logged, for attaching a terminal program.
+    #[cfg(unix)]
+    Pty,
+}
+
+/// A --port option: a port name and what to connect it to, like "COM1=tcp:localhost:2323".
+#[derive(Debug, Clone)]
+pub struct PortConfig {
+    name: String,
+    backend: Backend,
+}
+
+impl std::str::FromStr for PortConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, backend) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected PORT=BACKEND, got {s:?}"))?;
+        let backend = match backend.split_once(':') {
+            None if backend == "null" => Backend::Null,
+            #[cfg(unix)]
+            None if backend == "pty" => Backend::Pty,
+            Some(("file", path)) => Backend::File(path.to_string()),
+            Some(("tcp", addr)) => Backend::Tcp(addr.to_string()),
+            _ => return Err(format!("unknown port backend {backend:?}")),
+        };
+        Ok(PortConfig {
+            name: name.to_ascii_uppercase(),
+            backend,
+        })
+    }
+}
+
+struct Null;
+
+impl win32::Port for Null {
+    fn read(&mut self, _buf: &mut [u8]) -> usize {
+        0
+    }
+
+    fn write(&mut self, buf: &[u8]) -> usize {
+        buf.len()
+    }
+}
+
+/// A nonblocking stream, whether a socket or a pty.
+struct Stream<T: Read + Write>(T);
+
+impl<T: Read + Write> win32::Port for Stream<T> {
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        match self.0.read(buf) {
+            Ok(n) => n,
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => 0,
+            Err(err) => {
+                log::error!("port read: {err}");
+                0
+            }
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> usize {
+        match self.0.write_all(buf) {
+            Ok(()) => buf.len(),
+            Err(err) => {
+                log::error!("port write: {err}");
+                0
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn open_pty() -> std::io::Result<std::fs::File> {
+    use std::os::fd::FromRawFd;
+    unsafe {
+        let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY | libc::O_NONBLOCK);
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let file = std::fs::File::from_raw_fd(fd);
+        if libc::grantpt(fd) < 0 || libc::unlockpt(fd) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let name = libc::ptsname(fd);
+        if name.is_null() {
+            return Err(std::io::Error::last_os_error());
+        }
+        log::info!(
+            "pty at {}",
+            std::ffi::CStr::from_ptr(name).to_string_lossy()
+        );
+        Ok(file)
+    }
+}
+
+impl PortConfig {
+    fn open(&self) -> std::io::Result<Box<dyn win32::Port>> {
+        Ok(match &self.backend {
+            Backend::Null => Box::new(Null),
+            Backend::File(path) => Box::new(Stream(
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?,
+            )),
+            Backend::Tcp(addr) => {
+                let stream = std::net::TcpStream::connect(addr)?;
+                stream.set_nonblocking(true)?;
+                Box::new(Stream(stream))
+            }
+            #[cfg(unix)]
+            Backend::Pty => Box::new(Stream(open_pty()?)),
+        })
+    }
+}
+
+/// Open the named port per the configured backends, or None if it isn't configured
+/// or can't be opened.
+pub fn open(ports: &[PortConfig], name: &str) -> Option<Box<dyn win32::Port>> {
+    let config = ports.iter().find(|p| p.name == name)?;
+    match config.open() {
+        Ok(port) => Some(port),
+        Err(err) => {
+            log::error!("{name}: {err}");
+            None
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Per-game profiles: the settings a particular game needs, in a single file that
+//! can be shared and passed back with --profile.
+//!
+//! A profile is lines of `name = value`, named after the command line options
+//! listed in SETTINGS, with switches taking "true" or "false".  Lines starting with
+//! '#' are comments.  The first setting is the format version; profiles written by
+//! older versions are migrated as they're read, so renamed options keep working.
+//!
+//! The options in EMBEDDED name files, which an exported profile carries within it
+//! so it stands alone, as a block of lines:
+//!
+//!     registry <<END
+//!     ...the file's contents...
+//!     END
+//!
+//! An embedded registry seeds the hive when there's no --registry file yet, and an
+//! embedded script runs as if given with --script.
+
+use anyhow::{anyhow, bail};
+
+/// Current profile format version, bumped whenever RENAMES gains an entry.
+const VERSION: u32 = 2;
+
+/// Options that describe how to run a game, as opposed to how to debug it.
+/// The bool is whether the option is a switch.
+const SETTINGS: &[(&str, bool)] = &[
+    ("lang", false),
+    ("code-page", false),
+    ("address-space", false),
+    ("background", false),
+    ("cpu", false),
+    ("video-memory", false),
+    ("gpu", false),
+    ("cd-audio", false),
+    ("joystick-map", false),
+    ("port", false),
+    ("registry", false),
+    ("user-name", false),
+    ("computer-name", false),
+    ("folder", false),
+    ("script", false),
+    ("crt-fast-paths", true),
+    ("fullscreen", true),
+];
+
+/// Options naming a file whose contents an exported profile embeds.
+const EMBEDDED: &[&str] = &["registry", "script"];
+
+/// Options renamed over time: the version a rename happened in, the old and new
+/// names, and whether a switch's sense was inverted by the rename.
+const RENAMES: &[(u32, &str, &str, bool)] = &[(2, "no-crt-fast-paths", "crt-fast-paths", true)];
+
+pub struct Profile {
+    settings: Vec<(String, String)>,
+    /// Contents of files named by EMBEDDED options.
+    files: Vec<(String, String)>,
+}
+
+impl Profile {
+    pub fn parse(text: &str) -> anyhow::Result<Profile> {
+        let mut version = None;
+        let mut settings = Vec::new();
+        let mut files = Vec::new();
+        let mut lines = text.lines().enumerate();
+        while let Some((i, line)) = lines.next() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let embedded = line
+                .split_once("<<")
+                .filter(|(name, _)| !name.contains('='));
+            if let Some((name, end)) = embedded {
+                let (name, end) = (name.trim(), end.trim());
+                if version.is_none() {
+                    bail!("line {}: profile must start with its version", i + 1);
+                }
+                if !EMBEDDED.contains(&name) {
+                    bail!("line {}: {name:?} can't be embedded", i + 1);
+                }
+                let mut contents = String::new();
+                loop {
+                    let Some((_, line)) = lines.next() else {
+                        bail!("line {}: {name:?} missing its closing {end:?}", i + 1);
+                    };
+                    if line == end {
+                        break;
+                    }
+                    contents.push_str(line);
+                    contents.push('\n');
+                }
+                files.push((name.to_string(), contents));
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("line {}: expected name = value", i + 1))?;
+            let (name, value) = (name.trim(), value.trim());
+            if name == "version" {
+                version = Some(value.parse::<u32>()?);
+                continue;
+            }
+            let Some(version) = version else {
+                bail!("line {}: profile must start with its version", i + 1);
+            };
+            if version > VERSION {
+                bail!("profile version {version} is newer than this retrowin32 understands");
+            }
+            let (mut name, mut value) = (name, value);
+            for &(since, old, new, inverted) in RENAMES {
+                if since <= version || name != old {
+                    continue;
+                }
+                name = new;
+                if inverted {
+                    value = match value {
+                        "true" => "false",
+                        "false" => "true",
+                        value => value,
+                    };
+                }
+            }
+            if !SETTINGS.iter().any(|&(setting, _)| setting == name) {
+                bail!("line {}: unknown setting {name:?}", i + 1);
+            }
+            settings.push((name.to_string(), value.to_string()));
+        }
+        Ok(Profile { settings, files })
+    }
+
+    pub fn load(path: &str) -> anyhow::Result<Profile> {
+        let text = std::fs::read_to_string(path).map_err(|err| anyhow!("{path}: {err}"))?;
+        Profile::parse(&text).map_err(|err| anyhow!("{path}: {err}"))
+    }
+
+    /// Collect the settings given on a command line.
+    pub fn from_args(args: &[String]) -> Profile {
+        let mut settings = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                continue;
+            };
+            match SETTINGS.iter().find(|&&(setting, _)| setting == name) {
+                Some(&(_, true)) => settings.push((name.to_string(), "true".to_string())),
+                Some(&(_, false)) => {
+                    if let Some(value) = args.next() {
+                        settings.push((name.to_string(), value.clone()));
+                    }
+                }
+                None => {}
+            }
+        }
+        Profile {
+            settings,
+            files: Vec::new(),
+        }
+    }
+
+    /// Replace the settings naming EMBEDDED files with the files' contents, keeping
+    /// any contents embedded in `base` that aren't replaced.
+    pub fn embed_files(&mut self, base: Option<&Profile>) -> anyhow::Result<()> {
+        let mut files = Vec::new();
+        for (name, path) in &self.settings {
+            if !EMBEDDED.contains(&name.as_str()) {
+                continue;
+            }
+            match std::fs::read_to_string(path) {
+                Ok(contents) => files.push((name.clone(), contents)),
+                // A registry that hasn't been written yet has nothing to seed.
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound && name == "registry" => {}
+                Err(err) => bail!("{path}: {err}"),
+            }
+        }
+        self.settings
+            .retain(|(name, _)| !EMBEDDED.contains(&name.as_str()));
+        if let Some(base) = base {
+            for (name, contents) in &base.files {
+                if !files.iter().any(|(file, _)| file == name) {
+                    files.push((name.clone(), contents.clone()));
+                }
+            }
+        }
+        self.files = files;
+        Ok(())
+    }
+
+    /// The contents embedded for an EMBEDDED option.
+    pub fn file(&self, name: &str) -> Option<&str> {
+        self.files
+            .iter()
+            .find(|(file, _)| file == name)
+            .map(|(_, contents)| contents.as_str())
+    }
+
+    /// Command line arguments for the settings not already given in `args`.
+    pub fn to_args(&self, args: &[String]) -> anyhow::Result<Vec<String>> {
+        let mut out = Vec::new();
+        for (name, value) in &self.settings {
+            let flag = format!("--{name}");
+            if args.contains(&flag) {
+                continue;
+            }
+            let switch = SETTINGS
+                .iter()
+                .any(|&(setting, switch)| setting == name && switch);
+            if switch {
+                match value.as_str() {
+                    "true" => out.push(flag),
+                    "false" => {}
+                    _ => bail!("{name}: expected true or false, got {value:?}"),
+                }
+            } else {
+                out.push(flag);
+                out.push(value.clone());
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl std::fmt::Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "# retrowin32 profile")?;
+        writeln!(f, "version = {VERSION}")?;
+        for (name, value) in &self.settings {
+            writeln!(f, "{name} = {value}")?;
+        }
+        for (name, contents) in &self.files {
+            // A terminator that doesn't appear as a line of the contents.
+            let mut end = "END".to_string();
+            while contents.lines().any(|line| line == end) {
+                end.push('_');
+            }
+            writeln!(f, "{name} <<{end}")?;
+            for line in contents.lines() {
+                writeln!(f, "{line}")?;
+            }
+            writeln!(f, "{end}")?;
+        }
+        Ok(())
+    }
+}
@@ -12,9 +12,90 @@ fn map_button(b: sdl2::mouse::MouseButton) -> Option<win32::MouseButton> {
     })
 }
 
-fn message_from_event(hwnd: u32, event: sdl2::event::Event) -> Option<win32::Message> {
+/// Game controllers attached to the host.  SDL only reports input from controllers
+/// that are open, so each is opened as it arrives.
+struct Controllers {
+    subsystem: sdl2::GameControllerSubsystem,
+    open: Vec<sdl2::controller::GameController>,
+}
+
+impl Controllers {
+    fn add(&mut self, index: u32) -> Option<win32::Controller> {
+        let controller = match self.subsystem.open(index) {
+            Ok(c) => c,
+            Err(err) => {
+                log::warn!("opening controller {index}: {err}");
+                return None;
+            }
+        };
+        let info = win32::Controller {
+            id: controller.instance_id(),
+            name: controller.name(),
+            // SDL maps every controller onto the same standard layout.
+            axes: 6,
+            buttons: 15,
+        };
+        self.open.push(controller);
+        Some(info)
+    }
+
+    fn remove(&mut self, id: u32) {
+        self.open.retain(|c| c.instance_id() != id);
+    }
+
+    fn state(&self, id: u32) -> Option<win32::GamepadState> {
+        use sdl2::controller::{Axis, Button};
+        let controller = self.open.iter().find(|c| c.instance_id() == id)?;
+        let axes = [
+            Axis::LeftX,
+            Axis::LeftY,
+            Axis::RightX,
+            Axis::RightY,
+            Axis::TriggerLeft,
+            Axis::TriggerRight,
+        ]
+        .map(|axis| controller.axis(axis));
+        // Same order as win32::GamepadButton.
+        let buttons = [
+            Button::A,
+            Button::B,
+            Button::X,
+            Button::Y,
+            Button::Back,
+            Button::Guide,
+            Button::Start,
+            Button::LeftStick,
+            Button::RightStick,
+            Button::LeftShoulder,
+            Button::RightShoulder,
+            Button::DPadUp,
+            Button::DPadDown,
+            Button::DPadLeft,
+            Button::DPadRight,
+        ]
+        .iter()
+        .enumerate()
+        .filter(|&(_, &button)| controller.button(button))
+        .fold(0, |bits, (i, _)| bits | 1 << i);
+        Some(win32::GamepadState { axes, buttons })
+    }
+}
+
+fn message_from_event(
+    hwnd: u32,
+    controllers: &mut Controllers,
+    event: sdl2::event::Event,
+) -> Option<win32::Message> {
     let detail = match event {
         sdl2::event::Event::Quit { .. } => win32::MessageDetail::Quit,
+        sdl2::event::Event::Window {
+            win_event: sdl2::event::WindowEvent::FocusGained,
+            ..
+        } => win32::MessageDetail::Activate(true),
+        sdl2::event::Event::Window {
+            win_event: sdl2::event::WindowEvent::FocusLost,
+            ..
+        } => win32::MessageDetail::Activate(false),
         sdl2::event::Event::MouseButtonDown {
             mouse_btn, x, y, ..
         } => win32::MessageDetail::Mouse(win32::MouseMessage {
@@ -31,6 +112,40 @@ fn message_from_event(hwnd: u32, event: sdl2::event::Event) -> Option<win32::Mes
             x: x as u32,
             y: y as u32,
         }),
+        sdl2::event::Event::MouseMotion {
+            x, y, xrel, yrel, ..
+        } => win32::MessageDetail::MouseMove(win32::MouseMoveMessage {
+            x: x as u32,
+            y: y as u32,
+            dx: xrel,
+            dy: yrel,
+        }),
+        sdl2::event::Event::KeyDown {
+            scancode: Some(scancode),
+            repeat: false,
+            ..
+        } => win32::MessageDetail::Key(win32::KeyMessage {
+            down: true,
+            // SDL scancodes are USB HID usages.
+            usage: scancode as u32,
+        }),
+        sdl2::event::Event::KeyUp {
+            scancode: Some(scancode),
+            ..
+        } => win32::MessageDetail::Key(win32::KeyMessage {
+            down: false,
+            usage: scancode as u32,
+        }),
+        // Includes what an input method composes, once it's committed.
+        sdl2::event::Event::TextInput { text, .. } => win32::MessageDetail::Text(text),
+        sdl2::event::Event::TextEditing { text, .. } => win32::MessageDetail::Composition(text),
+        sdl2::event::Event::ControllerDeviceAdded { which, .. } => {
+            win32::MessageDetail::ControllerAdded(controllers.add(which)?)
+        }
+        sdl2::event::Event::ControllerDeviceRemoved { which, .. } => {
+            controllers.remove(which);
+            win32::MessageDetail::ControllerRemoved(which)
+        }
         _ => {
             // log::warn!("unhandled event: {:?}", event);
             return None;
@@ -39,13 +154,44 @@ fn message_from_event(hwnd: u32, event: sdl2::event::Event) -> Option<win32::Mes
     Some(win32::Message { hwnd, detail })
 }
 
+/// Frontend hotkeys, taken before the guest sees them: Pause pauses and resumes, and
+/// while paused, "." steps a frame.  Returns whether the event was one of them.
+fn hotkey(pause: &win32::PauseHandle, event: &sdl2::event::Event) -> bool {
+    use sdl2::{event::Event, keyboard::Scancode};
+    match event {
+        Event::KeyDown {
+            scancode: Some(Scancode::Pause),
+            repeat: false,
+            ..
+        } => {
+            let paused = !pause.is_paused();
+            pause.set_paused(paused);
+            log::info!("{}", if paused { "paused" } else { "resumed" });
+            true
+        }
+        Event::KeyUp {
+            scancode: Some(Scancode::Pause),
+            ..
+        } => true,
+        Event::KeyDown {
+            scancode: Some(Scancode::Period),
+            ..
+        } if pause.is_paused() => {
+            pause.step_frame();
+            true
+        }
+        _ => false,
+    }
+}
+
 fn message_from_events(
     hwnd: u32,
+    controllers: &mut Controllers,
     mut f: impl FnMut() -> Option<sdl2::event::Event>,
 ) -> Option<win32::Message> {
     loop {
         let event = f()?;
-        let msg = message_from_event(hwnd, event);
+        let msg = message_from_event(hwnd, controllers, event);
         if msg.is_some() {
             return msg;
         }
@@ -53,35 +199,49 @@ fn message_from_events(
 }
 
 pub struct GUI {
+    sdl: sdl2::Sdl,
     video: sdl2::VideoSubsystem,
     pump: sdl2::EventPump,
     timer: sdl2::TimerSubsystem,
+    controllers: Controllers,
     win: Option<WindowRef>,
     msg_queue: Option<win32::Message>,
+    allow_fullscreen: bool,
+    pause: win32::PauseHandle,
 }
 
 impl GUI {
-    pub fn new() -> anyhow::Result<Self> {
+    pub fn new(allow_fullscreen: bool, pause: win32::PauseHandle) -> anyhow::Result<Self> {
         assert!(sdl2::hint::set("SDL_NO_SIGNAL_HANDLERS", "1"));
         let sdl = sdl2::init().map_err(|err| anyhow::anyhow!(err))?;
         let video = sdl.video().map_err(|err| anyhow::anyhow!(err))?;
         let pump = sdl.event_pump().map_err(|err| anyhow::anyhow!(err))?;
         let timer = sdl.timer().map_err(|err| anyhow::anyhow!(err))?;
+        let controllers = Controllers {
+            subsystem: sdl.game_controller().map_err(|err| anyhow::anyhow!(err))?,
+            open: Vec::new(),
+        };
 
         Ok(GUI {
+            sdl,
             video,
             pump,
             timer,
+            controllers,
             win: None,
             msg_queue: None,
+            allow_fullscreen,
+            pause,
         })
     }
+}
 
-    pub fn time(&self) -> u32 {
+impl crate::Gui for GUI {
+    fn time(&self) -> u32 {
         self.timer.ticks()
     }
 
-    pub fn get_message(&mut self) -> Option<win32::Message> {
+    fn get_message(&mut self) -> Option<win32::Message> {
         if let Some(msg) = self.msg_queue.take() {
             return Some(msg);
         }
@@ -89,22 +249,43 @@ impl GUI {
             Some(w) => w.0.borrow().hwnd,
             None => 0,
         };
-        message_from_events(hwnd, || self.pump.poll_event())
+        message_from_events(hwnd, &mut self.controllers, || loop {
+            let event = self.pump.poll_event()?;
+            if !hotkey(&self.pause, &event) {
+                return Some(event);
+            }
+        })
     }
 
-    pub fn block(&mut self, wait: Option<u32>) -> bool {
+    fn block(&mut self, wait: Option<u32>) -> bool {
+        if self.msg_queue.is_some() {
+            // A message already arrived and is waiting to be picked up; just
+            // let the time pass rather than pulling in another.
+            if let Some(until) = wait {
+                self.timer.delay(until.saturating_sub(self.time()));
+            }
+            return true;
+        }
         let hwnd = match &self.win {
             Some(w) => w.0.borrow().hwnd,
             None => 0,
         };
         let msg = match wait {
-            Some(until) => message_from_events(hwnd, || {
-                let now = self.time();
+            Some(until) => message_from_events(hwnd, &mut self.controllers, || loop {
+                let now = self.timer.ticks();
                 let delta = until - now;
-                self.pump.wait_event_timeout(delta)
+                let event = self.pump.wait_event_timeout(delta)?;
+                if !hotkey(&self.pause, &event) {
+                    return Some(event);
+                }
             }),
             None => loop {
-                let msg = message_from_event(hwnd, self.pump.wait_event());
+                let event = self.pump.wait_event();
+                if hotkey(&self.pause, &event) {
+                    // Return to let the machine see it's paused or resumed.
+                    break None;
+                }
+                let msg = message_from_event(hwnd, &mut self.controllers, event);
                 if msg.is_some() {
                     break msg;
                 }
@@ -115,27 +296,78 @@ impl GUI {
         true
     }
 
-    pub fn create_window(&mut self, hwnd: u32) -> Box<dyn win32::Window> {
-        let win = Window::new(&self.video, hwnd);
+    fn create_window(&mut self, hwnd: u32) -> Box<dyn win32::Window> {
+        let mut win = Window::new(&self.video, hwnd);
+        win.allow_fullscreen = self.allow_fullscreen;
         let win_ref = WindowRef(Rc::new(RefCell::new(win)));
         self.win = Some(win_ref.clone());
         Box::new(win_ref)
     }
 
-    pub fn create_surface(&mut self, opts: &win32::SurfaceOptions) -> Box<dyn win32::Surface> {
+    fn create_surface(&mut self, opts: &win32::SurfaceOptions) -> Box<dyn win32::Surface> {
         Box::new(Texture::new(self.win.as_ref().unwrap(), opts))
     }
+
+    fn gamepad(&self, id: u32) -> Option<win32::GamepadState> {
+        self.controllers.state(id)
+    }
+
+    fn grab_mouse(&self, grab: bool) {
+        self.sdl.mouse().set_relative_mouse_mode(grab);
+    }
+
+    fn create_audio(&mut self, sample_rate: u32) -> Option<Box<dyn win32::Audio>> {
+        let open = || -> Result<Audio, String> {
+            let audio = self.sdl.audio()?;
+            let spec = sdl2::audio::AudioSpecDesired {
+                freq: Some(sample_rate as i32),
+                channels: Some(2),
+                samples: None,
+            };
+            let queue = audio.open_queue::<i16, _>(None, &spec)?;
+            queue.resume();
+            Ok(Audio(queue))
+        };
+        match open() {
+            Ok(audio) => Some(Box::new(audio)),
+            Err(err) => {
+                log::warn!("no audio output: {err}");
+                None
+            }
+        }
+    }
+}
+
+struct Audio(sdl2::audio::AudioQueue<i16>);
+
+impl win32::Audio for Audio {
+    fn write(&mut self, samples: &[i16]) {
+        if let Err(err) = self.0.queue_audio(samples) {
+            log::warn!("queue_audio: {err}");
+        }
+    }
+
+    fn queued(&self) -> u32 {
+        // size() is in bytes, of 2 channels of i16.
+        self.0.size() / 4
+    }
 }
 
 struct Window {
     hwnd: u32,
     canvas: sdl2::render::WindowCanvas,
+    /// Fullscreen gets in the way of debugging, so it's opt-in.
+    allow_fullscreen: bool,
 }
 impl Window {
     fn new(video: &sdl2::VideoSubsystem, hwnd: u32) -> Self {
         let win = video.window("retrowin32", 640, 480).build().unwrap();
         let canvas = win.into_canvas().build().unwrap();
-        Window { hwnd, canvas }
+        Window {
+            hwnd,
+            canvas,
+            allow_fullscreen: false,
+        }
     }
 }
 
@@ -151,23 +383,53 @@ impl win32::Window for WindowRef {
             .unwrap();
     }
 
+    fn set_icon(&mut self, icon: &win32::Icon) {
+        let mut pixels: Vec<u8> = icon.pixels.iter().flatten().copied().collect();
+        let surface = sdl2::surface::Surface::from_data(
+            &mut pixels,
+            icon.width,
+            icon.height,
+            icon.width * 4,
+            sdl2::pixels::PixelFormatEnum::ABGR8888,
+        )
+        .unwrap();
+        self.0.borrow_mut().canvas.window_mut().set_icon(surface);
+    }
+
     fn set_size(&mut self, width: u32, height: u32) {
-        self.0
-            .borrow_mut()
-            .canvas
-            .window_mut()
-            .set_size(width, height)
-            .unwrap();
+        let canvas = &mut self.0.borrow_mut().canvas;
+        if canvas.logical_size() != (0, 0) {
+            // Fullscreen: the window stays the size of the display.
+            canvas.set_logical_size(width, height).unwrap();
+        } else {
+            canvas.window_mut().set_size(width, height).unwrap();
+        }
     }
 
-    fn fullscreen(&mut self) {
-        log::info!("fullscreen request ignored for debugging ease");
-        // self.0
-        //     .borrow_mut()
-        //     .canvas
-        //     .window_mut()
-        //     .set_fullscreen(sdl2::video::FullscreenType::Desktop)
-        //     .unwrap();
+    fn set_fullscreen(&mut self, fullscreen: bool) {
+        let mut win = self.0.borrow_mut();
+        if !win.allow_fullscreen {
+            log::info!("fullscreen request ignored for debugging ease; see --fullscreen");
+            return;
+        }
+        let (width, height) = win.canvas.window().size();
+        let canvas = &mut win.canvas;
+        if fullscreen {
+            // Render at the window's size, scaled up by whole pixels to the display.
+            canvas.set_logical_size(width, height).unwrap();
+            canvas.set_integer_scale(true).unwrap();
+            canvas
+                .window_mut()
+                .set_fullscreen(sdl2::video::FullscreenType::Desktop)
+                .unwrap();
+        } else {
+            canvas
+                .window_mut()
+                .set_fullscreen(sdl2::video::FullscreenType::Off)
+                .unwrap();
+            canvas.set_integer_scale(false).unwrap();
+            canvas.set_logical_size(0, 0).unwrap();
+        }
     }
 }
 
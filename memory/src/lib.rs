@@ -1,5 +1,7 @@
 mod mem;
+mod pages;
 mod pod;
 
 pub use mem::{Extensions, Iterator, Mem};
+pub use pages::{Page, PageState, PageTable, PAGE_SIZE};
 pub use pod::Pod;
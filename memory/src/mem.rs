@@ -1,4 +1,4 @@
-use crate::Pod;
+use crate::{PageTable, Pod};
 use std::mem::size_of;
 
 pub trait Extensions<'m>: Sized {
@@ -82,6 +82,9 @@ impl<'m> Extensions<'m> for &'m [u8] {
 pub struct Mem<'m> {
     ptr: *mut u8,
     end: *mut u8,
+    /// Which pages are accessible, for memory that isn't all backed; null if it is.
+    /// Only set on the full guest memory, not on slices of it.
+    pages: *const PageTable,
     _marker: std::marker::PhantomData<&'m u8>,
 }
 
@@ -91,10 +94,47 @@ impl<'m> Mem<'m> {
         Mem {
             ptr: range.start as *mut u8,
             end: range.end as *mut u8,
+            pages: std::ptr::null(),
             _marker: std::marker::PhantomData::default(),
         }
     }
 
+    /// Like from_slice, for guest memory with uncommitted pages within it.
+    pub fn with_pages(s: &'m [u8], pages: &'m PageTable) -> Mem<'m> {
+        Mem {
+            pages,
+            ..Mem::from_slice(s)
+        }
+    }
+
+    /// Whether addr..addr+size is committed, as far as the page table knows;
+    /// bounds are checked separately.
+    #[inline]
+    pub fn is_committed(&self, addr: u32, size: u32) -> bool {
+        match unsafe { self.pages.as_ref() } {
+            Some(pages) => pages.is_committed(addr, size),
+            None => true,
+        }
+    }
+
+    /// Whether writes to addr..addr+size are allowed, where it's committed.
+    #[inline]
+    pub fn is_writable(&self, addr: u32, size: u32) -> bool {
+        match unsafe { self.pages.as_ref() } {
+            Some(pages) => pages.is_writable(addr, size),
+            None => true,
+        }
+    }
+
+    /// The length of accessible memory starting at addr, up to max.
+    pub fn accessible_len(&self, addr: u32, max: u32) -> u32 {
+        let max = max.min(self.len().saturating_sub(addr));
+        match unsafe { self.pages.as_ref() } {
+            Some(pages) => pages.committed_len(addr, max),
+            None => max,
+        }
+    }
+
     pub fn is_oob<T>(&self, addr: u32) -> bool {
         self.ptr as usize + addr as usize + size_of::<T>() > self.end as usize
     }
@@ -152,6 +192,7 @@ impl<'m> Mem<'m> {
             Mem {
                 ptr,
                 end,
+                pages: std::ptr::null(),
                 _marker: std::marker::PhantomData::default(),
             }
         }
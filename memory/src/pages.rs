@@ -0,0 +1,150 @@
+//! Per-page state of the 4gb guest address space: whether each page is free,
+//! reserved, or committed (backed by memory), and its protection.
+//!
+//! The table is sparse, allocated in chunks covering 4mb of address space as pages
+//! in them are first used, so tracking a mostly empty address space is cheap.
+//! Alongside it are bitmaps of committed and of read-only pages, which is what
+//! memory accesses are checked against.
+
+pub const PAGE_SIZE: u32 = 0x1000;
+
+/// Pages in the address space.  Computed in u64, as 4gb overflows a 32-bit usize.
+const PAGES: usize = ((u32::MAX as u64 + 1) / PAGE_SIZE as u64) as usize;
+const PAGES_PER_CHUNK: usize = 1024;
+const CHUNKS: usize = PAGES / PAGES_PER_CHUNK;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PageState {
+    #[default]
+    Free,
+    /// Address space set aside, but not accessible.
+    Reserved,
+    /// Backed by memory and accessible.
+    Committed,
+    /// Backed by memory, but faults when first accessed, for the owner to notice
+    /// the access and commit it; this is how stacks grow.
+    Guard,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Page {
+    pub state: PageState,
+    /// Protection, in whatever terms the owner of the table uses (for us, the
+    /// Windows PAGE_* flags); the table itself doesn't interpret it.
+    pub protect: u32,
+}
+
+pub struct PageTable {
+    chunks: Vec<Option<Box<[Page; PAGES_PER_CHUNK]>>>,
+    /// One bit per page, set when committed.
+    committed: Box<[u64]>,
+    /// One bit per page, set when writes to it should fault.
+    read_only: Box<[u64]>,
+}
+
+impl Default for PageTable {
+    fn default() -> Self {
+        PageTable {
+            chunks: (0..CHUNKS).map(|_| None).collect(),
+            committed: vec![0; PAGES / 64].into_boxed_slice(),
+            read_only: vec![0; PAGES / 64].into_boxed_slice(),
+        }
+    }
+}
+
+/// The pages overlapping addr..addr+size, as page numbers.
+fn page_range(addr: u32, size: u32) -> std::ops::Range<usize> {
+    let start = addr / PAGE_SIZE;
+    let end = (addr as u64 + size as u64).div_ceil(PAGE_SIZE as u64);
+    start as usize..end as usize
+}
+
+impl PageTable {
+    pub fn get(&self, addr: u32) -> Page {
+        let page = addr as usize / PAGE_SIZE as usize;
+        match &self.chunks[page / PAGES_PER_CHUNK] {
+            Some(chunk) => chunk[page % PAGES_PER_CHUNK],
+            None => Page::default(),
+        }
+    }
+
+    /// Set the state of all pages overlapping addr..addr+size.  They're left
+    /// writable; see set_read_only.
+    pub fn set(&mut self, addr: u32, size: u32, state: Page) {
+        for page in page_range(addr, size) {
+            let chunk = self.chunks[page / PAGES_PER_CHUNK]
+                .get_or_insert_with(|| Box::new([Page::default(); PAGES_PER_CHUNK]));
+            chunk[page % PAGES_PER_CHUNK] = state;
+            let bit = 1 << (page % 64);
+            if state.state == PageState::Committed {
+                self.committed[page / 64] |= bit;
+            } else {
+                self.committed[page / 64] &= !bit;
+            }
+            self.read_only[page / 64] &= !bit;
+        }
+    }
+
+    /// Make writes to the pages overlapping addr..addr+size fault.  Since the table
+    /// doesn't interpret protections, the owner calls this to say which are read-only.
+    pub fn set_read_only(&mut self, addr: u32, size: u32) {
+        for page in page_range(addr, size) {
+            self.read_only[page / 64] |= 1 << (page % 64);
+        }
+    }
+
+    /// Whether none of addr..addr+size is read-only.
+    #[inline]
+    pub fn is_writable(&self, addr: u32, size: u32) -> bool {
+        page_range(addr, size).all(|page| {
+            self.read_only
+                .get(page / 64)
+                .map_or(true, |bits| bits & (1 << (page % 64)) == 0)
+        })
+    }
+
+    /// Whether all of addr..addr+size is committed.
+    #[inline]
+    pub fn is_committed(&self, addr: u32, size: u32) -> bool {
+        page_range(addr, size).all(|page| {
+            self.committed
+                .get(page / 64)
+                .is_some_and(|bits| bits & (1 << (page % 64)) != 0)
+        })
+    }
+
+    /// The run of pages starting at addr's page that all have the same state,
+    /// as its start address, size in bytes, and state.
+    pub fn region(&self, addr: u32) -> (u32, u32, Page) {
+        let start = addr & !(PAGE_SIZE - 1);
+        let page = self.get(start);
+        let mut end = start as u64 + PAGE_SIZE as u64;
+        while end < 1 << 32 {
+            let chunk = end as usize / (PAGE_SIZE as usize * PAGES_PER_CHUNK);
+            if self.chunks[chunk].is_none() && page == Page::default() {
+                // Untouched chunks are all free; skip them whole.
+                end = (chunk as u64 + 1) * (PAGE_SIZE as u64 * PAGES_PER_CHUNK as u64);
+                continue;
+            }
+            if self.get(end as u32) != page {
+                break;
+            }
+            end += PAGE_SIZE as u64;
+        }
+        (
+            start,
+            (end - start as u64).min(u32::MAX as u64) as u32,
+            page,
+        )
+    }
+
+    /// The length of the committed run of memory starting at addr, up to max.
+    pub fn committed_len(&self, addr: u32, max: u32) -> u32 {
+        let mut end = addr as u64;
+        let limit = addr as u64 + max as u64;
+        while end < limit && self.is_committed(end as u32, 1) {
+            end = (end & !(PAGE_SIZE as u64 - 1)) + PAGE_SIZE as u64;
+        }
+        (end.min(limit) - addr as u64) as u32
+    }
+}
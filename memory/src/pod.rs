@@ -17,7 +17,6 @@ pub unsafe trait Pod: 'static + Sized {
 
 // See discussion of endianness in doc/design_notes.md.
 unsafe impl Pod for u8 {}
-unsafe impl Pod for [u8; 4] {} // pixels
 unsafe impl Pod for u16 {}
 unsafe impl Pod for i16 {}
 unsafe impl Pod for u32 {}
@@ -26,3 +25,4 @@ unsafe impl Pod for u64 {}
 unsafe impl Pod for i64 {}
 unsafe impl Pod for f32 {}
 unsafe impl Pod for f64 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {} // e.g. pixels, matrices
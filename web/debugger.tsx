@@ -10,7 +10,7 @@ import { RegistersComponent } from './registers';
 import { SnapshotsComponent } from './snapshots';
 import { Stack } from './stack';
 import { Tabs } from './tabs';
-import { EmulatorComponent, loadEmulator } from './web';
+import { EmulatorComponent, loadEmulator, LoadError } from './web';
 
 namespace Debugger {
   export interface Props {
@@ -24,6 +24,8 @@ namespace Debugger {
     /** When running, the setInterval id that is updating the UI. */
     running?: number;
     selectedTab: string;
+    /** Whether executed code is being recorded, for downloading as drcov coverage. */
+    coverage?: boolean;
   }
 }
 export class Debugger extends preact.Component<Debugger.Props, Debugger.State> implements EmulatorHost {
@@ -62,6 +64,11 @@ export class Debugger extends preact.Component<Debugger.Props, Debugger.State> i
   onStdOut(msg: string): void {
     this.print(msg);
   }
+  onProgress(task: string, done: number, total: number): void {
+    if (done === total) {
+      console.info(`${task}: done`);
+    }
+  }
 
   step() {
     try {
@@ -71,8 +78,14 @@ export class Debugger extends preact.Component<Debugger.Props, Debugger.State> i
     }
   }
 
-  start() {
+  /** Run, resuming a machine paused by frame stepping; or if stepFrame, run one frame. */
+  start(stepFrame = false) {
     if (this.state.running) return;
+    if (stepFrame) {
+      this.props.emulator.emu.step_frame();
+    } else {
+      this.props.emulator.emu.set_paused(false);
+    }
     this.setState({
       running: setInterval(() => {
         this.forceUpdate();
@@ -88,6 +101,17 @@ export class Debugger extends preact.Component<Debugger.Props, Debugger.State> i
     this.setState({ running: undefined });
   }
 
+  /** Run until the next frame is presented. */
+  stepFrame() {
+    if (this.state.running) {
+      // The run loop stops once the machine pauses, but the UI stays running.
+      this.props.emulator.emu.step_frame();
+      this.props.emulator.start();
+    } else {
+      this.start(true);
+    }
+  }
+
   runTo(addr: number) {
     this.props.emulator.addBreak({ addr, oneShot: true });
     this.start();
@@ -98,6 +122,30 @@ export class Debugger extends preact.Component<Debugger.Props, Debugger.State> i
     this.setState({ selectedTab: 'memory', memBase });
   };
 
+  /** Replace the win32 trace scheme (see win32/src/trace.rs), like "ddraw/*,-kernel32/GetTickCount". */
+  setTracing = (e: Event) => {
+    e.preventDefault();
+    const form = e.target as HTMLFormElement;
+    const scheme = (form.elements.namedItem('scheme') as HTMLInputElement).value;
+    this.props.emulator.emu.set_tracing_scheme(scheme);
+  };
+
+  setCoverage(on: boolean) {
+    this.props.emulator.emu.set_coverage(on);
+    this.setState({ coverage: on });
+  }
+
+  /** Download the coverage recorded so far, for loading into lighthouse and the like. */
+  saveCoverage() {
+    const drcov = this.props.emulator.emu.coverage_drcov();
+    if (!drcov) return;
+    const a = document.createElement('a');
+    a.href = URL.createObjectURL(new Blob([drcov]));
+    a.download = `${this.props.emulator.storageKey}.drcov`;
+    a.click();
+    URL.revokeObjectURL(a.href);
+  }
+
   render() {
     // Note: disassemble_json() may cause allocations, invalidating any existing .memory()!
     let instrs: Instruction[] = [];
@@ -140,9 +188,19 @@ export class Debugger extends preact.Component<Debugger.Props, Debugger.State> i
             step over
           </button>
           &nbsp;
+          <button
+            onClick={() => this.stepFrame()}
+          >
+            step frame
+          </button>
+          &nbsp;
           <div>
             {this.props.emulator.emu.instr_count} instrs executed | {Math.floor(this.props.emulator.instrPerMs)}/ms
           </div>
+          &nbsp;
+          <form onSubmit={this.setTracing}>
+            trace: <input name='scheme' defaultValue='*' />
+          </form>
         </section>
         <div style={{ display: 'flex', margin: '1ex' }}>
           {code}
@@ -216,6 +274,18 @@ export class Debugger extends preact.Component<Debugger.Props, Debugger.State> i
                   }}
                 />
               ),
+
+              coverage: (
+                <div>
+                  <button onClick={() => this.setCoverage(!this.state.coverage)}>
+                    {this.state.coverage ? 'stop recording' : 'record coverage'}
+                  </button>
+                  &nbsp;
+                  <button disabled={!this.state.coverage} onClick={() => this.saveCoverage()}>
+                    save drcov
+                  </button>
+                </div>
+              ),
             }}
             selected={this.state.selectedTab}
             switchTab={(selectedTab) => this.setState({ selectedTab })}
@@ -233,7 +303,13 @@ export class Debugger extends preact.Component<Debugger.Props, Debugger.State> i
 }
 
 export async function main() {
-  const emulator = await loadEmulator();
+  let emulator;
+  try {
+    emulator = await loadEmulator();
+  } catch (err) {
+    preact.render(<LoadError err={err} />, document.body);
+    return;
+  }
   emulator.emu.set_tracing_scheme('*');
   preact.render(<Debugger emulator={emulator} />, document.body);
 }
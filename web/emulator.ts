@@ -11,6 +11,8 @@ export interface EmulatorHost {
   showTab(name: string): void;
   onError(msg: string): void;
   onStdOut(stdout: string): void;
+  /** Progress through a long-running operation; finished when done == total. */
+  onProgress(task: string, done: number, total: number): void;
 }
 
 /** Wraps wasm.Emulator, able to run in a RAF loop. */
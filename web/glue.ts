@@ -9,7 +9,9 @@ export { SurfaceOptions };
 // Matches 'pub type JsWindow' in glue/host.rs.
 export interface JsWindow {
   title: string;
+  set_icon(width: number, height: number, pixels: Uint8Array): void;
   set_size(width: number, height: number): void;
+  set_fullscreen(fullscreen: boolean): void;
 }
 
 // Matches 'pub type JsFile' in glue/host.rs.
@@ -23,6 +25,15 @@ export interface JsLogger {
   log(level: number, msg: string): void;
 }
 
+// Matches 'pub type JsSocket' in glue/host.rs.
+export interface JsSocket {
+  readonly state: number;
+  readonly available: number;
+  read(buf: Uint8Array): number;
+  write(buf: Uint8Array): number;
+  close(): void;
+}
+
 // Matches 'pub type JsHost' in glue/host.rs.
 export interface JsHost {
   exit(code: number): void;
@@ -30,8 +41,18 @@ export interface JsHost {
   ensure_timer(when: number): void;
   get_event(): Event | undefined;
 
+  progress(task: string, done: number, total: number): void;
+  guest_message(channel: string, payload: string): void;
+
   open(path: string): JsFile;
+  exists(path: string): boolean;
   write(buf: Uint8Array): number;
 
   create_window(hwnd: number): JsWindow;
+
+  grab_mouse(grab: boolean): void;
+
+  connect(host: string, port: number): JsSocket | undefined;
+
+  pick_file(save: boolean, title: string, patterns: string): string | undefined;
 }
@@ -91,7 +91,11 @@ extern "C" {
     #[wasm_bindgen(method, setter)]
     fn set_title(this: &JsWindow, title: &str);
     #[wasm_bindgen(method)]
+    fn set_icon(this: &JsWindow, width: u32, height: u32, pixels: &[u8]);
+    #[wasm_bindgen(method)]
     fn set_size(this: &JsWindow, width: u32, height: u32);
+    #[wasm_bindgen(method)]
+    fn set_fullscreen(this: &JsWindow, fullscreen: bool);
 }
 
 impl win32::Window for JsWindow {
@@ -99,12 +103,17 @@ impl win32::Window for JsWindow {
         JsWindow::set_title(self, title);
     }
 
+    fn set_icon(&mut self, icon: &win32::Icon) {
+        let pixels: Vec<u8> = icon.pixels.iter().flatten().copied().collect();
+        JsWindow::set_icon(self, icon.width, icon.height, &pixels);
+    }
+
     fn set_size(&mut self, width: u32, height: u32) {
         JsWindow::set_size(self, width, height);
     }
 
-    fn fullscreen(&mut self) {
-        log::warn!("todo: fullscreen");
+    fn set_fullscreen(&mut self, fullscreen: bool) {
+        JsWindow::set_fullscreen(self, fullscreen);
     }
 }
 
@@ -149,6 +158,77 @@ fn map_mousevent(event: web_sys::MouseEvent) -> anyhow::Result<win32::MouseMessa
     })
 }
 
+/// Map a KeyboardEvent.code, which names the physical key, to its USB HID usage.
+fn key_usage(code: &str) -> Option<u32> {
+    if let Some(letter) = code.strip_prefix("Key") {
+        let &[c] = letter.as_bytes() else { return None };
+        return c.is_ascii_uppercase().then(|| 0x04 + (c - b'A') as u32);
+    }
+    if let Some(digit) = code.strip_prefix("Digit") {
+        let n = digit.parse::<u32>().ok().filter(|&n| n < 10)?;
+        // 1 through 9, then 0.
+        return Some(0x1E + (n + 9) % 10);
+    }
+    if let Some(digit) = code.strip_prefix("Numpad") {
+        if let Ok(n) = digit.parse::<u32>() {
+            return (n < 10).then(|| 0x59 + (n + 9) % 10);
+        }
+    }
+    if let Some(n) = code.strip_prefix('F').and_then(|n| n.parse::<u32>().ok()) {
+        return (1..=12).contains(&n).then(|| 0x3A + n - 1);
+    }
+    Some(match code {
+        "Enter" => 0x28,
+        "Escape" => 0x29,
+        "Backspace" => 0x2A,
+        "Tab" => 0x2B,
+        "Space" => 0x2C,
+        "Minus" => 0x2D,
+        "Equal" => 0x2E,
+        "BracketLeft" => 0x2F,
+        "BracketRight" => 0x30,
+        "Backslash" => 0x31,
+        "Semicolon" => 0x33,
+        "Quote" => 0x34,
+        "Backquote" => 0x35,
+        "Comma" => 0x36,
+        "Period" => 0x37,
+        "Slash" => 0x38,
+        "CapsLock" => 0x39,
+        "PrintScreen" => 0x46,
+        "ScrollLock" => 0x47,
+        "Pause" => 0x48,
+        "Insert" => 0x49,
+        "Home" => 0x4A,
+        "PageUp" => 0x4B,
+        "Delete" => 0x4C,
+        "End" => 0x4D,
+        "PageDown" => 0x4E,
+        "ArrowRight" => 0x4F,
+        "ArrowLeft" => 0x50,
+        "ArrowDown" => 0x51,
+        "ArrowUp" => 0x52,
+        "NumLock" => 0x53,
+        "NumpadDivide" => 0x54,
+        "NumpadMultiply" => 0x55,
+        "NumpadSubtract" => 0x56,
+        "NumpadAdd" => 0x57,
+        "NumpadEnter" => 0x58,
+        "NumpadDecimal" => 0x63,
+        "IntlBackslash" => 0x64,
+        "ContextMenu" => 0x65,
+        "ControlLeft" => 0xE0,
+        "ShiftLeft" => 0xE1,
+        "AltLeft" => 0xE2,
+        "MetaLeft" => 0xE3,
+        "ControlRight" => 0xE4,
+        "ShiftRight" => 0xE5,
+        "AltRight" => 0xE6,
+        "MetaRight" => 0xE7,
+        _ => return None,
+    })
+}
+
 fn message_from_event(event: web_sys::Event) -> anyhow::Result<win32::Message> {
     let hwnd = js_sys::Reflect::get(&event, &JsValue::from_str("hwnd"))
         .unwrap()
@@ -165,12 +245,221 @@ fn message_from_event(event: web_sys::Event) -> anyhow::Result<win32::Message> {
             event.down = false;
             win32::MessageDetail::Mouse(event)
         }
+        "mousemove" => {
+            let event = event.unchecked_into::<web_sys::MouseEvent>();
+            win32::MessageDetail::MouseMove(win32::MouseMoveMessage {
+                x: event.offset_x() as u32,
+                y: event.offset_y() as u32,
+                dx: event.movement_x(),
+                dy: event.movement_y(),
+            })
+        }
+        ty @ ("keydown" | "keyup") => {
+            let event = event.unchecked_into::<web_sys::KeyboardEvent>();
+            let code = event.code();
+            let Some(usage) = key_usage(&code) else {
+                bail!("unhandled key {code}");
+            };
+            win32::MessageDetail::Key(win32::KeyMessage {
+                down: ty == "keydown",
+                usage,
+            })
+        }
+        "keypress" => {
+            win32::MessageDetail::Text(event.unchecked_into::<web_sys::KeyboardEvent>().key())
+        }
+        "compositionupdate" => {
+            let event = event.unchecked_into::<web_sys::CompositionEvent>();
+            win32::MessageDetail::Composition(event.data().unwrap_or_default())
+        }
+        "compositionend" => {
+            let event = event.unchecked_into::<web_sys::CompositionEvent>();
+            win32::MessageDetail::Text(event.data().unwrap_or_default())
+        }
+        "focus" => win32::MessageDetail::Activate(true),
+        "blur" => win32::MessageDetail::Activate(false),
+        "gamepadconnected" => {
+            let gamepad = event
+                .unchecked_into::<web_sys::GamepadEvent>()
+                .gamepad()
+                .unwrap();
+            win32::MessageDetail::ControllerAdded(win32::Controller {
+                id: gamepad.index(),
+                name: gamepad.id(),
+                axes: 6,
+                buttons: 15,
+            })
+        }
+        "gamepaddisconnected" => {
+            let gamepad = event
+                .unchecked_into::<web_sys::GamepadEvent>()
+                .gamepad()
+                .unwrap();
+            win32::MessageDetail::ControllerRemoved(gamepad.index())
+        }
         ty => bail!("unhandled event type {ty}"),
     };
     log::info!("msg: {:?}", detail);
     Ok(win32::Message { hwnd, detail })
 }
 
+#[wasm_bindgen]
+extern "C" {
+    pub type JsSocket;
+    /// 0 while connecting, 1 when open, 2 once closed, 3 if it failed to connect.
+    #[wasm_bindgen(method, getter)]
+    fn state(this: &JsSocket) -> u32;
+    /// Bytes received and not yet read.
+    #[wasm_bindgen(method, getter)]
+    fn available(this: &JsSocket) -> u32;
+    #[wasm_bindgen(method)]
+    fn read(this: &JsSocket, buf: &mut [u8]) -> u32;
+    #[wasm_bindgen(method)]
+    fn write(this: &JsSocket, buf: &[u8]) -> u32;
+    #[wasm_bindgen(method)]
+    fn close(this: &JsSocket);
+}
+
+thread_local! {
+    /// Host names the guest resolved, which have no real address in the browser.
+    /// The guest gets made-up addresses in 198.18.0.0/15, which connect() maps
+    /// back to names for the proxy to look up.
+    static HOST_NAMES: std::cell::RefCell<Vec<String>> = Default::default();
+}
+
+const FAKE_ADDR_BASE: u32 = 0xC612_0001; // 198.18.0.1
+
+fn fake_addr(name: &str) -> std::net::Ipv4Addr {
+    HOST_NAMES.with_borrow_mut(|names| {
+        let index = match names.iter().position(|n| n == name) {
+            Some(index) => index,
+            None => {
+                names.push(name.to_string());
+                names.len() - 1
+            }
+        };
+        std::net::Ipv4Addr::from(FAKE_ADDR_BASE + index as u32)
+    })
+}
+
+fn addr_host(addr: &std::net::Ipv4Addr) -> String {
+    let index = u32::from(*addr).wrapping_sub(FAKE_ADDR_BASE) as usize;
+    HOST_NAMES
+        .with_borrow(|names| names.get(index).cloned())
+        .unwrap_or_else(|| addr.to_string())
+}
+
+/// A TCP socket, tunneled through a WebSocket proxy as browsers can't make raw
+/// connections.  There's no way to listen or to send datagrams.
+struct WebSocket {
+    host: JsHost,
+    socket: Option<JsSocket>,
+    peer: Option<std::net::SocketAddrV4>,
+}
+
+impl WebSocket {
+    fn socket(&self) -> std::io::Result<&JsSocket> {
+        let socket = self
+            .socket
+            .as_ref()
+            .ok_or(std::io::ErrorKind::NotConnected)?;
+        match socket.state() {
+            0 => Err(std::io::ErrorKind::WouldBlock.into()),
+            1 => Ok(socket),
+            3 => Err(std::io::ErrorKind::ConnectionRefused.into()),
+            _ => Ok(socket),
+        }
+    }
+}
+
+impl win32::Socket for WebSocket {
+    fn bind(&mut self, _addr: std::net::SocketAddrV4) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn listen(&mut self) -> std::io::Result<()> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    fn accept(&mut self) -> std::io::Result<(Box<dyn win32::Socket>, std::net::SocketAddrV4)> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    fn connect(&mut self, addr: std::net::SocketAddrV4) -> std::io::Result<()> {
+        if self.socket.is_none() {
+            let socket = JsHost::connect(&self.host, &addr_host(addr.ip()), addr.port())
+                .ok_or(std::io::ErrorKind::NotFound)?;
+            self.socket = Some(socket);
+            self.peer = Some(addr);
+        }
+        self.socket().map(|_| ())
+    }
+
+    fn send(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let socket = self.socket()?;
+        if socket.state() != 1 {
+            return Err(std::io::ErrorKind::ConnectionReset.into());
+        }
+        Ok(socket.write(buf) as usize)
+    }
+
+    fn send_to(&mut self, buf: &[u8], _addr: std::net::SocketAddrV4) -> std::io::Result<usize> {
+        self.send(buf)
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let socket = self.socket()?;
+        match socket.read(buf) {
+            0 if socket.state() == 1 => Err(std::io::ErrorKind::WouldBlock.into()),
+            n => Ok(n as usize),
+        }
+    }
+
+    fn recv_from(&mut self, buf: &mut [u8]) -> std::io::Result<(usize, std::net::SocketAddrV4)> {
+        let n = self.recv(buf)?;
+        Ok((n, self.peer_addr()?))
+    }
+
+    fn local_addr(&self) -> std::io::Result<std::net::SocketAddrV4> {
+        Ok(std::net::SocketAddrV4::new(
+            std::net::Ipv4Addr::UNSPECIFIED,
+            0,
+        ))
+    }
+
+    fn peer_addr(&self) -> std::io::Result<std::net::SocketAddrV4> {
+        self.peer.ok_or(std::io::ErrorKind::NotConnected.into())
+    }
+
+    fn readable(&mut self) -> bool {
+        // Closing counts, as recv() will report it.
+        self.socket
+            .as_ref()
+            .is_some_and(|socket| socket.state() > 1 || socket.available() > 0)
+    }
+
+    fn writable(&mut self) -> bool {
+        self.socket
+            .as_ref()
+            .is_some_and(|socket| socket.state() != 0)
+    }
+
+    fn shutdown(&mut self, how: std::net::Shutdown) -> std::io::Result<()> {
+        if how != std::net::Shutdown::Read {
+            self.socket()?.close();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for WebSocket {
+    fn drop(&mut self) {
+        if let Some(socket) = &self.socket {
+            socket.close();
+        }
+    }
+}
+
 #[wasm_bindgen]
 extern "C" {
     pub type JsHost;
@@ -187,9 +476,16 @@ extern "C" {
     #[wasm_bindgen(method)]
     fn get_event(this: &JsHost) -> web_sys::Event;
 
+    #[wasm_bindgen(method)]
+    fn progress(this: &JsHost, task: &str, done: u32, total: u32);
+    #[wasm_bindgen(method)]
+    fn guest_message(this: &JsHost, channel: &str, payload: &str);
+
     #[wasm_bindgen(method)]
     fn open(this: &JsHost, path: &str) -> JsFile;
     #[wasm_bindgen(method)]
+    fn exists(this: &JsHost, path: &str) -> bool;
+    #[wasm_bindgen(method)]
     fn write(this: &JsHost, buf: &[u8]) -> usize;
 
     #[wasm_bindgen(method)]
@@ -197,6 +493,17 @@ extern "C" {
 
     #[wasm_bindgen(method)]
     fn screen(this: &JsHost) -> web_sys::CanvasRenderingContext2d;
+
+    #[wasm_bindgen(method)]
+    fn grab_mouse(this: &JsHost, grab: bool);
+
+    /// Open a connection through the network proxy, or None if there's no proxy.
+    #[wasm_bindgen(method)]
+    fn connect(this: &JsHost, host: &str, port: u16) -> Option<JsSocket>;
+
+    /// Ask the user for a file, among those matching patterns (like "*.map *.lvl").
+    #[wasm_bindgen(method)]
+    fn pick_file(this: &JsHost, save: bool, title: &str, patterns: &str) -> Option<String>;
 }
 
 impl win32::Host for JsHost {
@@ -226,11 +533,23 @@ impl win32::Host for JsHost {
         false
     }
 
+    fn progress(&self, task: &str, done: u32, total: u32) {
+        JsHost::progress(self, task, done, total)
+    }
+
+    fn guest_message(&self, channel: &str, payload: &str) {
+        JsHost::guest_message(self, channel, payload)
+    }
+
     fn open(&self, path: &str) -> Box<dyn win32::File> {
         let file = JsHost::open(self, path);
         Box::new(file)
     }
 
+    fn exists(&self, path: &str) -> bool {
+        JsHost::exists(self, path)
+    }
+
     fn write(&self, buf: &[u8]) -> usize {
         JsHost::write(self, buf)
     }
@@ -243,4 +562,93 @@ impl win32::Host for JsHost {
     fn create_surface(&mut self, opts: &win32::SurfaceOptions) -> Box<dyn win32::Surface> {
         Box::new(WebSurface::new(opts, JsHost::screen(self)))
     }
+
+    fn create_audio(&mut self, _sample_rate: u32) -> Option<Box<dyn win32::Audio>> {
+        // TODO: WebAudio.
+        None
+    }
+
+    fn gamepad(&self, id: u32) -> Option<win32::GamepadState> {
+        let gamepads = web_sys::window()?.navigator().get_gamepads().ok()?;
+        let gamepad = gamepads.get(id).dyn_into::<web_sys::Gamepad>().ok()?;
+        if !gamepad.connected() {
+            return None;
+        }
+        let axes = gamepad.axes();
+        let axis = |i: u32| (axes.get(i).as_f64().unwrap_or(0.0) * 32767.0) as i16;
+        let buttons = gamepad.buttons();
+        let button = |i: u32| {
+            (i < buttons.length())
+                .then(|| buttons.get(i).unchecked_into::<web_sys::GamepadButton>())
+        };
+        let trigger = |i: u32| button(i).map_or(0, |b| (b.value() * 32767.0) as i16);
+        // Indices of win32::GamepadButton in the Gamepad API's standard mapping.
+        const BUTTONS: [u32; 15] = [0, 1, 2, 3, 8, 16, 9, 10, 11, 4, 5, 12, 13, 14, 15];
+        let buttons = BUTTONS
+            .iter()
+            .enumerate()
+            .filter(|&(_, &b)| button(b).is_some_and(|b| b.pressed()))
+            .fold(0, |bits, (i, _)| bits | 1 << i);
+        Some(win32::GamepadState {
+            axes: [axis(0), axis(1), axis(2), axis(3), trigger(6), trigger(7)],
+            buttons,
+        })
+    }
+
+    fn grab_mouse(&self, grab: bool) {
+        JsHost::grab_mouse(self, grab)
+    }
+
+    fn open_port(&self, _name: &str) -> Option<Box<dyn win32::Port>> {
+        None
+    }
+
+    fn socket(&self, kind: win32::SocketKind) -> std::io::Result<Box<dyn win32::Socket>> {
+        if kind != win32::SocketKind::Stream {
+            return Err(std::io::ErrorKind::Unsupported.into());
+        }
+        Ok(Box::new(WebSocket {
+            host: JsValue::clone(self).unchecked_into(),
+            socket: None,
+            peer: None,
+        }))
+    }
+
+    fn resolve(&self, name: &str) -> std::io::Result<Vec<std::net::Ipv4Addr>> {
+        Ok(vec![fake_addr(name)])
+    }
+
+    fn random(&self, buf: &mut [u8]) {
+        let crypto = web_sys::window().unwrap().crypto().unwrap();
+        // getRandomValues() fills at most 64k at a time.
+        for chunk in buf.chunks_mut(0x10000) {
+            crypto.get_random_values_with_u8_array(chunk).unwrap();
+        }
+    }
+
+    fn shell_open(&self, target: &str) -> bool {
+        // There are no host files to open, only web pages.
+        if !(target.starts_with("http://") || target.starts_with("https://")) {
+            return false;
+        }
+        let window = web_sys::window().unwrap();
+        matches!(
+            window.open_with_url_and_target(target, "_blank"),
+            Ok(Some(_))
+        )
+    }
+
+    fn launch(&self, exe: &str, _cmdline: &str, _dir: Option<&str>) -> bool {
+        // The page runs a single emulator, with no way to start another beside it.
+        log::warn!("launching {exe:?} unsupported");
+        false
+    }
+
+    fn pick_file(&self, dialog: &win32::FileDialog) -> Option<String> {
+        let patterns = match dialog.filters.get(dialog.filter_index) {
+            Some((_, patterns)) => patterns.join(" "),
+            None => "*.*".into(),
+        };
+        JsHost::pick_file(self, dialog.save, &dialog.title, &patterns)
+    }
 }
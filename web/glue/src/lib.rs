@@ -35,10 +35,25 @@ impl Emulator {
 
     #[wasm_bindgen]
     pub fn labels(&self) -> JsResult<String> {
-        let str = serde_json::to_string(&self.machine.labels)?;
+        let mut labels = self.machine.labels.clone();
+        for (addr, name) in self.machine.symbols.iter() {
+            labels.entry(addr).or_insert_with(|| name.to_string());
+        }
+        let str = serde_json::to_string(&labels)?;
         Ok(str)
     }
 
+    /// Load a symbol file (see win32::symbols) for the exe, returning the symbol count.
+    #[wasm_bindgen]
+    pub fn load_symbols(&mut self, name: &str, text: &str) -> JsResult<usize> {
+        let exe = &self.machine.state.kernel32.images[0];
+        let (base, size) = (exe.base, exe.size);
+        self.machine
+            .symbols
+            .load_text(name, text, base, size)
+            .map_err(err_from_anyhow)
+    }
+
     pub fn memory(&self) -> js_sys::DataView {
         let mem = js_sys::WebAssembly::Memory::from(wasm_bindgen::memory());
         let buf = js_sys::ArrayBuffer::from(mem.buffer());
@@ -93,9 +108,27 @@ impl Emulator {
             x86::CPUState::Blocked(_) => CPUState::Blocked,
             x86::CPUState::Error(msg) => return Err(JsError::new(msg)),
             x86::CPUState::Exit(_) => CPUState::Exit,
+            // Delivered to the program as soon as it's raised.
+            x86::CPUState::Exception(_) => unreachable!(),
         })
     }
 
+    /// Pause or resume the guest, as distinct from stopping the run loop: a paused
+    /// machine blocks, and a frame step pauses it once the next frame is presented.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.machine.pause.set_paused(paused);
+    }
+
+    /// Run until the next frame is presented, then pause.
+    pub fn step_frame(&mut self) {
+        self.machine.pause.step_frame();
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn paused(&self) -> bool {
+        self.machine.pause.is_paused()
+    }
+
     pub fn breakpoint_add(&mut self, addr: u32) {
         self.machine
             .emu
@@ -109,21 +142,65 @@ impl Emulator {
             .clear_breakpoint(self.machine.emu.memory.mem(), addr)
     }
 
+    /// Pause when memory is accessed; watch is parsed like "4a1f00+4:w".
+    pub fn watchpoint_add(&mut self, watch: &str) -> Result<(), JsError> {
+        let watch = watch.parse().map_err(|err: String| JsError::new(&err))?;
+        self.machine.emu.x86.watchpoints.add(watch, None);
+        Ok(())
+    }
+    pub fn watchpoint_clear(&mut self, addr: u32) {
+        self.machine.emu.x86.watchpoints.remove(addr);
+    }
+
+    /// Start or stop recording executed code; stopping discards what was recorded.
+    pub fn set_coverage(&mut self, on: bool) {
+        self.machine.emu.x86.coverage = on.then(Default::default);
+    }
+
+    /// Coverage recorded since set_coverage, in drcov format.
+    pub fn coverage_drcov(&self) -> Option<Box<[u8]>> {
+        self.machine.coverage_drcov(None).map(Into::into)
+    }
+
     pub fn mappings_json(&self) -> String {
         serde_json::to_string(&self.machine.state.kernel32.mappings.vec()).unwrap_throw()
     }
 
+    /// Titles and icons of the guest's windows.
+    pub fn windows_json(&self) -> String {
+        serde_json::to_string(&self.machine.windows()).unwrap_throw()
+    }
+
     pub fn poke(&mut self, addr: u32, value: u8) {
         *self.machine.mem().view_mut::<u8>(addr) = value;
     }
 
-    pub fn snapshot(&self) -> Box<[u8]> {
-        self.machine.snapshot()
+    pub fn snapshot(&self) -> JsResult<Box<[u8]>> {
+        self.machine.snapshot().map_err(err_from_anyhow)
     }
     pub fn load_snapshot(&mut self, bytes: &[u8]) {
         self.machine.load_snapshot(bytes)
     }
 
+    /// Set what to do while the page is in the background; see win32::BackgroundPolicy.
+    pub fn set_background_policy(&mut self, policy: &str) -> JsResult<()> {
+        self.machine.background = policy.parse().map_err(|err: String| JsError::new(&err))?;
+        Ok(())
+    }
+
+    /// Set the UI language LANGID, used to pick among localized resources.
+    pub fn set_ui_language(&mut self, lang: u16) {
+        self.machine.state.kernel32.ui_language = lang;
+    }
+
+    /// Set the address space for large address aware programs ("2gb", "3gb" or "4gb");
+    /// see win32::AddressSpace.  Takes effect at load_exe.
+    pub fn set_address_space(&mut self, space: &str) -> JsResult<()> {
+        self.machine.state.kernel32.address_space =
+            space.parse().map_err(|err: String| JsError::new(&err))?;
+        Ok(())
+    }
+
     pub fn set_tracing_scheme(&self, scheme: &str) {
         win32::trace::set_scheme(scheme);
     }
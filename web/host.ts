@@ -15,23 +15,78 @@ class Window implements glue.JsWindow {
       jsHost.enqueueEvent(ev);
       return false;
     };
-    this.canvas.onmousedown = stashEvent;
+    this.canvas.onmousedown = (ev) => {
+      // Pointer lock is only granted in response to a click, so a grab requested
+      // earlier takes effect on the first one.
+      if (jsHost.grabbed && document.pointerLockElement !== this.canvas) {
+        this.canvas.requestPointerLock();
+      }
+      return stashEvent(ev);
+    };
     this.canvas.onmouseup = stashEvent;
+    this.canvas.onmousemove = stashEvent;
+    // Windows reports auto-repeat differently, so drop the browser's.
+    window.addEventListener('keydown', (ev) => !ev.repeat && stashEvent(ev));
+    window.addEventListener('keyup', stashEvent);
+    // Text arrives as characters: typed ones from keypress, which only fires for
+    // keys that type a single character, and input method results on compositionend,
+    // with the text being composed before that on compositionupdate.
+    window.addEventListener('keypress', (ev) => ev.key.length === 1 && stashEvent(ev));
+    window.addEventListener('compositionupdate', stashEvent);
+    window.addEventListener('compositionend', stashEvent);
+    window.addEventListener('focus', stashEvent);
+    window.addEventListener('blur', stashEvent);
+    window.addEventListener('gamepadconnected', stashEvent);
+    window.addEventListener('gamepaddisconnected', stashEvent);
     this.canvas.oncontextmenu = (ev) => {
       return false;
     };
   }
 
-  title: string = '';
+  private _title: string = '';
+  /** Data URL of the window icon, if any. */
+  icon?: string;
   canvas: HTMLCanvasElement = document.createElement('canvas');
 
+  get title() {
+    return this._title;
+  }
+  set title(title: string) {
+    this._title = title;
+    document.title = title;
+    this.jsHost.emuHost.onWindowChanged();
+  }
+
+  set_icon(width: number, height: number, pixels: Uint8Array) {
+    const canvas = document.createElement('canvas');
+    canvas.width = width;
+    canvas.height = height;
+    const data = new ImageData(new Uint8ClampedArray(pixels), width, height);
+    canvas.getContext('2d')!.putImageData(data, 0, 0);
+    this.icon = canvas.toDataURL();
+
+    let link = document.querySelector<HTMLLinkElement>('link[rel=icon]');
+    if (!link) {
+      link = document.createElement('link');
+      link.rel = 'icon';
+      document.head.appendChild(link);
+    }
+    link.href = this.icon;
+    this.jsHost.emuHost.onWindowChanged();
+  }
+
+  private width = 0;
+  private height = 0;
+  private fullscreen = false;
+
   set_size(w: number, h: number) {
+    this.width = w;
+    this.height = h;
     // Note: the canvas must be sized to the size of physical pixels,
     // or else it will be scaled up and pixels will be blurry.
     this.canvas.width = w * window.devicePixelRatio;
     this.canvas.height = h * window.devicePixelRatio;
-    this.canvas.style.width = `${w}px`;
-    this.canvas.style.height = `${h}px`;
+    this.applyScale();
 
     // The context scale seems preserved across calls to getContext, but then also
     // lost when the canvas is resized.  Rather than relying on this, always reset
@@ -43,6 +98,31 @@ class Window implements glue.JsWindow {
 
     this.jsHost.emuHost.onWindowChanged();
   }
+
+  set_fullscreen(fullscreen: boolean) {
+    this.fullscreen = fullscreen;
+    if (fullscreen) {
+      // Fullscreen the whole page rather than the canvas, as the browser would
+      // otherwise stretch the canvas to fill the screen.
+      document.documentElement.requestFullscreen().catch((err) => {
+        // Browsers only allow this in response to a user gesture.
+        console.warn('fullscreen failed:', err);
+      });
+    } else if (document.fullscreenElement) {
+      document.exitFullscreen();
+    }
+    this.applyScale();
+  }
+
+  /** Size the canvas on the page, scaled up by an integer factor when fullscreen. */
+  private applyScale() {
+    const scale = this.fullscreen && this.width && this.height
+      ? Math.max(1, Math.floor(Math.min(screen.width / this.width, screen.height / this.height)))
+      : 1;
+    this.canvas.style.width = `${this.width * scale}px`;
+    this.canvas.style.height = `${this.height * scale}px`;
+    this.canvas.style.imageRendering = scale > 1 ? 'pixelated' : '';
+  }
 }
 
 class File implements glue.JsFile {
@@ -68,6 +148,60 @@ class File implements glue.JsFile {
   }
 }
 
+/**
+ * A TCP connection tunneled over a WebSocket to a websockify-style proxy, which
+ * forwards the WebSocket's binary messages to and from the real connection.
+ */
+class Socket implements glue.JsSocket {
+  /** 0 while connecting, 1 when open, 2 once closed, 3 if it failed to connect. */
+  state = 0;
+  private ws: WebSocket;
+  private received: Uint8Array[] = [];
+  available = 0;
+
+  constructor(url: string) {
+    this.ws = new WebSocket(url, ['binary']);
+    this.ws.binaryType = 'arraybuffer';
+    this.ws.onopen = () => {
+      this.state = 1;
+    };
+    this.ws.onmessage = (ev) => {
+      const data = new Uint8Array(ev.data as ArrayBuffer);
+      this.received.push(data);
+      this.available += data.length;
+    };
+    this.ws.onclose = () => {
+      this.state = this.state === 0 ? 3 : 2;
+    };
+  }
+
+  read(buf: Uint8Array): number {
+    let n = 0;
+    while (n < buf.length && this.received.length > 0) {
+      const data = this.received[0];
+      const len = Math.min(buf.length - n, data.length);
+      buf.set(data.subarray(0, len), n);
+      n += len;
+      if (len === data.length) {
+        this.received.shift();
+      } else {
+        this.received[0] = data.subarray(len);
+      }
+    }
+    this.available -= n;
+    return n;
+  }
+
+  write(buf: Uint8Array): number {
+    this.ws.send(buf.slice());
+    return buf.length;
+  }
+
+  close() {
+    this.ws.close();
+  }
+}
+
 /** A set of (pre)loaded files; a temporary hack until the emulator can load files itself. */
 export type FileSet = Map<string, Uint8Array>;
 
@@ -144,6 +278,16 @@ export abstract class JsHost implements glue.JsHost, glue.JsLogger {
     return this.events.shift();
   }
 
+  progress(task: string, done: number, total: number) {
+    this.emuHost.onProgress(task, done, total);
+  }
+
+  /** Messages from guest code over the debug channel, dispatched for page scripts to observe. */
+  guest_message(channel: string, payload: string) {
+    console.info(`guest ${channel}: ${payload}`);
+    window.dispatchEvent(new CustomEvent('retrowin32-guest', { detail: { channel, payload } }));
+  }
+
   open(path: string): glue.JsFile {
     // TODO: async file loading.
     let bytes = this.files.get(path);
@@ -154,6 +298,10 @@ export abstract class JsHost implements glue.JsHost, glue.JsLogger {
     return new File(path, bytes);
   }
 
+  exists(path: string): boolean {
+    return this.files.has(path);
+  }
+
   write(buf: Uint8Array): number {
     const text = this.decoder.decode(buf);
     this.emuHost.onStdOut(text);
@@ -174,4 +322,44 @@ export abstract class JsHost implements glue.JsHost, glue.JsLogger {
     // but how to plumb that info across JS boundary?
     return this.windows[this.windows.length - 1].canvas.getContext('2d')!;
   }
+
+  /** Whether the emulator wants the mouse locked to its window. */
+  grabbed = false;
+  grab_mouse(grab: boolean) {
+    this.grabbed = grab;
+    if (grab) {
+      this.windows[this.windows.length - 1]?.canvas.requestPointerLock();
+    } else if (document.pointerLockElement) {
+      document.exitPointerLock();
+    }
+  }
+
+  /**
+   * WebSocket proxy that guest network connections go through, from the 'netproxy'
+   * URL parameter, like 'wss://proxy.example'.  Connections are made to
+   * '<netproxy>/<host>:<port>'.
+   */
+  netProxy = new URLSearchParams(document.location.search).get('netproxy');
+  connect(host: string, port: number): glue.JsSocket | undefined {
+    if (!this.netProxy) {
+      console.warn(`no netproxy for connection to ${host}:${port}`);
+      return undefined;
+    }
+    return new Socket(`${this.netProxy}/${host}:${port}`);
+  }
+
+  /**
+   * The guest's open and save dialogs, as a prompt listing the loaded files that
+   * match the dialog's patterns.
+   */
+  pick_file(save: boolean, title: string, patterns: string): string | undefined {
+    const globs = patterns.split(' ').map((pattern) => {
+      const re = pattern.replace(/[.+^${}()|[\]\\]/g, '\\$&').replace(/\*/g, '.*').replace(/\?/g, '.');
+      return new RegExp(`^${re}$`, 'i');
+    });
+    const names = [...this.files.keys()].filter((name) => globs.some((glob) => glob.test(name)));
+    const listing = names.length > 0 ? `\n\n${names.join('\n')}` : '';
+    const name = window.prompt(`${title}${listing}`, save ? '' : names[0] ?? '');
+    return name || undefined;
+  }
 }
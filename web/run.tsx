@@ -1,10 +1,12 @@
 import * as preact from 'preact';
 import { Fragment, h } from 'preact';
 import { Emulator, EmulatorHost } from './emulator';
-import { EmulatorComponent, loadEmulator } from './web';
+import { EmulatorComponent, loadEmulator, LoadError } from './web';
 
 interface State {
   output?: string;
+  /** Description of an unfinished long-running operation. */
+  progress?: string;
 }
 
 class Runner extends preact.Component<{ emulator: Emulator }, State> implements EmulatorHost {
@@ -41,9 +43,15 @@ class Runner extends preact.Component<{ emulator: Emulator }, State> implements
     this.print(stdout);
   }
 
+  onProgress(task: string, done: number, total: number): void {
+    const progress = done < total ? `${task}: ${Math.floor((done / total) * 100)}%` : undefined;
+    this.setState({ progress });
+  }
+
   render() {
     return (
       <>
+        {this.state.progress ? <div class='progress'>{this.state.progress}</div> : null}
         {this.state.output ? <pre class='stdout'>{this.state.output}</pre> : null}
         <EmulatorComponent emulator={this.props.emulator} />
       </>
@@ -52,7 +60,13 @@ class Runner extends preact.Component<{ emulator: Emulator }, State> implements
 }
 
 export async function main() {
-  const emulator = await loadEmulator();
+  let emulator;
+  try {
+    emulator = await loadEmulator();
+  } catch (err) {
+    preact.render(<LoadError err={err} />, document.getElementById('main')!);
+    return;
+  }
   emulator.emu.set_tracing_scheme('-');
   preact.render(<Runner emulator={emulator} />, document.getElementById('main')!);
 }
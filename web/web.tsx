@@ -8,6 +8,7 @@ import { parseCSV } from './labels';
 namespace WindowComponent {
   export interface Props {
     title: string;
+    icon?: string;
     canvas: HTMLCanvasElement;
   }
   export interface State {
@@ -55,6 +56,7 @@ class WindowComponent extends preact.Component<WindowComponent.Props, WindowComp
     return (
       <div class='window' style={{ left: `${this.state.pos[0]}px`, top: `${this.state.pos[1]}px` }}>
         <div class='titlebar' onPointerDown={this.beginDrag} onPointerUp={this.endDrag} onPointerMove={this.onDrag}>
+          {this.props.icon && <img class='icon' src={this.props.icon} />}
           {this.props.title}
         </div>
         <div ref={this.ref} />
@@ -75,6 +77,7 @@ export class EmulatorComponent extends preact.Component<EmulatorComponent.Props>
         <WindowComponent
           key={window.hwnd}
           title={window.title}
+          icon={window.icon}
           canvas={window.canvas}
         />
       );
@@ -104,6 +107,12 @@ function parseURL(): URLParams | undefined {
   return params;
 }
 
+/** Shown instead of the emulator when the exe can't be loaded, like when it's a kind of executable we don't run. */
+export function LoadError(props: { err: unknown }) {
+  const msg = props.err instanceof Error ? props.err.message : String(props.err);
+  return <pre class='load-error'>error: {msg}</pre>;
+}
+
 export async function loadEmulator() {
   const params = parseURL();
   if (!params) {
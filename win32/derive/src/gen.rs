@@ -82,7 +82,7 @@ pub fn gather_shims<'a>(
 
 enum Argument {
     /// Value is amount of stack the argument uses in stdcall.
-    /// (All of them except the array+size type are 4 bytes.)
+    /// (All of them except the array+size type and f64 are 4 bytes.)
     Ordinary(u32),
     VarArgs,
 }
@@ -98,7 +98,7 @@ fn parse_argument_type(ty: &syn::Type) -> Argument {
     }
 
     let name = &ty.path.segments[0].ident;
-    if name == "ArrayWithSize" || name == "ArrayWithSizeMut" || name == "POINT" {
+    if name == "ArrayWithSize" || name == "ArrayWithSizeMut" || name == "POINT" || name == "f64" {
         Argument::Ordinary(8)
     } else if name == "VarArgs" {
         Argument::VarArgs
@@ -237,7 +237,7 @@ pub fn add_trace(func: &mut syn::ItemFn) {
         .collect::<Vec<_>>();
     let arg_count = args.len();
     let stmt: syn::Stmt = syn::parse_quote! {
-        if crate::trace::enabled(TRACE_CONTEXT) {
+        if crate::trace::enabled(TRACE_CONTEXT, #name) {
             let args: &[(&str, &dyn std::fmt::Debug); #arg_count] = &[#(#synargs),*];
             crate::trace::trace(TRACE_CONTEXT, std::file!(), std::line!(), #name, args);
         }
@@ -0,0 +1,176 @@
+//! Statistics of the guest's calls to the Windows API: how often each function was
+//! called and how long it took, and which functions the guest wanted that aren't
+//! implemented, so porting a new program starts from a list of what's missing.
+//!
+//! Functions are flagged as unimplemented when imported but missing from our DLLs,
+//! which crashes when they're called, and as crashed when their implementation
+//! panicked, as partial implementations do with todo!().
+//! Times are host time in the shim itself; for async shims that excludes the guest
+//! code they call back.
+
+use crate::shims::Shim;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static STATS: RefCell<Stats> = RefCell::new(Stats::default());
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Status {
+    /// Ordering matters: the report lists the worst first.
+    Crashed,
+    Missing,
+    Ok,
+}
+
+struct Entry {
+    name: String,
+    status: Status,
+    calls: u64,
+    time: Duration,
+}
+
+#[derive(Default)]
+struct Stats {
+    entries: Vec<Entry>,
+    /// Index into entries, by the shim's function or, for missing functions, by name.
+    /// Entries are named "dll:function", as missing functions already are.
+    by_func: HashMap<usize, usize>,
+    by_name: HashMap<String, usize>,
+    /// The entry of the call in progress, to be marked if it crashes.
+    current: Option<usize>,
+}
+
+impl Stats {
+    fn entry(&mut self, name: String, status: Status) -> usize {
+        self.entries.push(Entry {
+            name,
+            status,
+            calls: 0,
+            time: Duration::ZERO,
+        });
+        self.entries.len() - 1
+    }
+
+    fn missing(&mut self, name: &str) -> usize {
+        match self.by_name.get(name) {
+            Some(&index) => index,
+            None => {
+                let index = self.entry(name.to_string(), Status::Missing);
+                self.by_name.insert(name.to_string(), index);
+                index
+            }
+        }
+    }
+}
+
+/// Start collecting, which must precede loading the exe to note its missing imports.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Note an import that resolved to nothing.
+pub fn missing(name: &str) {
+    if !enabled() {
+        return;
+    }
+    STATS.with_borrow_mut(|stats| {
+        stats.missing(name);
+    });
+}
+
+/// Note a call to a missing function, which is about to crash.
+pub fn missing_call(name: &str) {
+    if !enabled() {
+        return;
+    }
+    STATS.with_borrow_mut(|stats| {
+        let index = stats.missing(name);
+        stats.entries[index].calls += 1;
+    });
+}
+
+/// A call in progress, as begun by begin().
+pub struct Call {
+    index: usize,
+    start: Instant,
+}
+
+/// Note the start of a call to a shim, to be ended with end().
+pub fn begin(shim: &Shim) -> Option<Call> {
+    if !enabled() {
+        return None;
+    }
+    let index = STATS.with_borrow_mut(|stats| {
+        let func = shim.func as usize;
+        let index = match stats.by_func.get(&func) {
+            Some(&index) => index,
+            None => {
+                let name = match shim.dll() {
+                    Some(dll) => format!("{dll}:{}", shim.name),
+                    None => shim.name.to_string(),
+                };
+                let index = stats.entry(name, Status::Ok);
+                stats.by_func.insert(func, index);
+                index
+            }
+        };
+        stats.entries[index].calls += 1;
+        stats.current = Some(index);
+        index
+    });
+    Some(Call {
+        index,
+        start: Instant::now(),
+    })
+}
+
+pub fn end(call: Option<Call>) {
+    let Some(call) = call else {
+        return;
+    };
+    let elapsed = call.start.elapsed();
+    STATS.with_borrow_mut(|stats| {
+        stats.entries[call.index].time += elapsed;
+        stats.current = None;
+    });
+}
+
+/// The report, as text.  Called from a panic hook, any call in progress is the one
+/// that crashed.
+pub fn report() -> String {
+    STATS.with_borrow_mut(|stats| {
+        if let Some(index) = stats.current.take() {
+            stats.entries[index].status = Status::Crashed;
+        }
+        let mut entries = stats.entries.iter().collect::<Vec<_>>();
+        entries.sort_by(|a, b| (&a.status, b.calls, &a.name).cmp(&(&b.status, a.calls, &b.name)));
+        let mut out = String::new();
+        out.push_str("    calls   time (ms)  function\n");
+        for entry in entries {
+            let status = match entry.status {
+                Status::Crashed => "  CRASHED",
+                Status::Missing => "  UNIMPLEMENTED",
+                Status::Ok => "",
+            };
+            out.push_str(&format!(
+                "{:9} {:11.3}  {}{status}\n",
+                entry.calls,
+                entry.time.as_secs_f64() * 1000.0,
+                entry.name
+            ));
+        }
+        out
+    })
+}
@@ -0,0 +1,81 @@
+//! Guest call stacks, found by walking the EBP frame chain, for crash reports and
+//! the profiler.  Code built without frame pointers hides its callers, and the walk
+//! stops at the first frame that doesn't look like one rather than guess further.
+
+use crate::Machine;
+use memory::{Extensions, Mem};
+
+/// Deepest stack walked, for the sake of corrupt or cyclic EBP chains.
+const MAX_DEPTH: usize = 64;
+
+/// The code addresses of a stack, innermost first: eip, then the return addresses
+/// of the frames above it.
+pub fn walk(mem: Mem, eip: u32, esp: u32, ebp: u32) -> Vec<u32> {
+    let mut stack = vec![eip];
+    if crate::shims_emu::is_shim_addr(eip) && mem.is_committed(esp, 4) {
+        // A shim, called but not yet returned; its caller is on top of the stack.
+        stack.push(mem.get_pod::<u32>(esp));
+    }
+    let mut ebp = ebp;
+    while stack.len() < MAX_DEPTH && ebp >= esp && ebp % 4 == 0 && mem.is_committed(ebp, 8) {
+        let ret = mem.get_pod::<u32>(ebp + 4);
+        if !crate::shims_emu::is_shim_addr(ret) && !mem.is_committed(ret, 1) {
+            break;
+        }
+        stack.push(ret);
+        let next = mem.get_pod::<u32>(ebp);
+        if next <= ebp {
+            break;
+        }
+        ebp = next;
+    }
+    stack
+}
+
+/// A code address as "module!symbol+0x12", or "module+0x1234" (an RVA) where no
+/// symbol is known, or the name of the shim it calls.
+pub fn describe(machine: &Machine, addr: u32) -> String {
+    if let Some(label) = machine.labels.get(&addr) {
+        if crate::shims_emu::is_shim_addr(addr) {
+            return label.clone();
+        }
+    }
+    let symbol = machine.symbols.name(addr);
+    let image = machine
+        .state
+        .kernel32
+        .images
+        .iter()
+        .find(|image| addr >= image.base && addr - image.base < image.size);
+    match (image, symbol) {
+        (Some(image), Some(symbol)) => format!("{}!{symbol}", file_name(&image.name)),
+        (Some(image), None) => format!("{}+{:#x}", file_name(&image.name), addr - image.base),
+        (None, Some(symbol)) => symbol,
+        (None, None) => "?".into(),
+    }
+}
+
+/// The last component of a path; the exe is loaded under its command line.
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// A backtrace for display, one frame per line.
+pub fn format(machine: &Machine, eip: u32, esp: u32, ebp: u32) -> String {
+    let mut out = String::from("backtrace:");
+    for addr in walk(machine.mem(), eip, esp, ebp) {
+        out.push_str(&format!("\n  {addr:08x} {}", describe(machine, addr)));
+    }
+    out
+}
+
+/// The backtrace of the current thread.
+pub fn current(machine: &Machine) -> String {
+    let regs = &machine.emu.x86.cpu().regs;
+    format(
+        machine,
+        regs.eip,
+        regs.get32(x86::Register::ESP),
+        regs.get32(x86::Register::EBP),
+    )
+}
@@ -0,0 +1,88 @@
+//! Capture of the frames shown on screen, as screenshots or continuous recordings.
+//! The host calls screenshot() or record() and collects frames via take().
+//!
+//! Frames are taken as presented: at the guest's resolution, after palette lookup
+//! and with any GDI drawing composited on top.  There is no gamma ramp support, so
+//! the palette is the last step before display.  Frames are stamped with the guest
+//! clock, so a recording keeps the game's own timing however fast we ran.
+//!
+//! In 8-bit modes the palette indices and palette can be kept alongside, for
+//! pixel-exact archival of games that animate by cycling their palette: each
+//! palette change presents a new frame with the same indices.
+
+use std::{collections::VecDeque, sync::Mutex};
+
+/// Raw contents of an 8-bit framebuffer.
+pub struct Indexed {
+    /// Palette indices, width * height of them.
+    pub pixels: Vec<u8>,
+    /// RGB palette entries the indices refer to.
+    pub palette: Vec<[u8; 3]>,
+}
+
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// Final RGBA pixels, as displayed.
+    pub pixels: Vec<[u8; 4]>,
+    /// Present if requested and the display is in an 8-bit mode.
+    pub indexed: Option<Indexed>,
+}
+
+pub struct Frame {
+    /// Index of the frame, counting presents since startup.
+    pub frame: u32,
+    /// Guest clock (as returned by GetTickCount) when the frame was presented.
+    pub time: u32,
+    pub image: Image,
+}
+
+struct State {
+    frame: u32,
+    screenshot: bool,
+    recording: bool,
+    indexed: bool,
+    frames: VecDeque<Frame>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    frame: 0,
+    screenshot: false,
+    recording: false,
+    indexed: false,
+    frames: VecDeque::new(),
+});
+
+/// Capture the next presented frame.
+pub fn screenshot() {
+    STATE.lock().unwrap().screenshot = true;
+}
+
+/// Start or stop capturing every presented frame.  If `indexed`, 8-bit frames
+/// also carry their palette indices and palette.
+pub fn record(recording: bool, indexed: bool) {
+    let mut state = STATE.lock().unwrap();
+    state.recording = recording;
+    state.indexed = indexed;
+}
+
+/// Retrieve the oldest captured frame, if any.
+pub fn take() -> Option<Frame> {
+    STATE.lock().unwrap().frames.pop_front()
+}
+
+/// Called each time a frame is presented.  `image` is only invoked when the frame is
+/// to be captured, and is passed whether to include indexed pixels.
+pub(crate) fn present(time: u32, image: impl FnOnce(bool) -> Option<Image>) {
+    let mut state = STATE.lock().unwrap();
+    let frame = state.frame;
+    state.frame += 1;
+    if !state.recording && !state.screenshot {
+        return;
+    }
+    let Some(image) = image(state.indexed) else {
+        return;
+    };
+    state.screenshot = false;
+    state.frames.push_back(Frame { frame, time, image });
+}
@@ -0,0 +1,111 @@
+//! Capture of a single frame of graphics calls, for debugging rendering bugs.
+//! The host calls request() to ask for the next frame; once the following
+//! frame is complete (as delimited by frames going to the screen, whether by a
+//! DirectDraw Flip or present, GDI painting, or a GL or Glide buffer swap), the
+//! recorded calls and the contents of all surfaces are available via take().
+//!
+//! Calls are recorded via the trace machinery, regardless of whether the
+//! corresponding --win32-trace rule is enabled.
+
+use crate::Machine;
+use std::{
+    cell::RefCell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// winapi TRACE_CONTEXT prefixes that are recorded while capturing.
+const CONTEXTS: &[&str] = &["ddraw", "gdi32", "user32/paint", "opengl32", "glide"];
+
+/// Snapshot of a surface's contents at the end of the frame.
+pub struct SurfaceDump {
+    /// What kind of surface this is: "ddraw", "window", "opengl32" or "glide".
+    pub kind: &'static str,
+    /// Identifies the surface among those of its kind: the x86 address of a
+    /// DirectDraw surface object, or the handle of a window or GL context.
+    pub addr: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Address of attached surface (e.g. back buffer), or 0.
+    pub attached: u32,
+    /// RGBA pixels, or None if the surface's contents only exist on the host side.
+    pub pixels: Option<Vec<[u8; 4]>>,
+}
+
+pub struct FrameDump {
+    /// Index of the captured frame, counting presented frames since startup.
+    pub frame: u32,
+    /// Traced calls, one per line, in the order they were made.
+    pub calls: Vec<String>,
+    pub surfaces: Vec<SurfaceDump>,
+}
+
+#[derive(Default)]
+struct State {
+    frame: u32,
+    capture: Option<Vec<String>>,
+    done: Option<FrameDump>,
+}
+
+/// Set by request(), which may be called from a signal handler, so kept apart from
+/// the rest of the state.
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::default();
+}
+
+/// Request a dump of the next complete frame.
+pub fn request() {
+    REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Retrieve a completed frame dump, if any.
+pub fn take() -> Option<FrameDump> {
+    STATE.with_borrow_mut(|state| state.done.take())
+}
+
+/// Whether calls in the given trace context should currently be recorded.
+#[inline(never)]
+pub fn capturing(context: &'static str) -> bool {
+    STATE.with_borrow(|state| state.capture.is_some())
+        && CONTEXTS.iter().any(|c| context.starts_with(c))
+}
+
+pub(crate) fn record(msg: &str) {
+    STATE.with_borrow_mut(|state| {
+        if let Some(calls) = &mut state.capture {
+            calls.push(msg.to_string());
+        }
+    });
+}
+
+/// Called at each frame boundary.  `surfaces` is only invoked when a frame is completed.
+fn frame_end(surfaces: impl FnOnce() -> Vec<SurfaceDump>) {
+    let completed = STATE.with_borrow_mut(|state| {
+        let completed = state.capture.take().map(|calls| (state.frame, calls));
+        state.frame += 1;
+        if REQUESTED.swap(false, Ordering::Relaxed) {
+            state.capture = Some(Vec::new());
+        }
+        completed
+    });
+    if let Some((frame, calls)) = completed {
+        log::info!("framedump: captured frame {frame}");
+        let surfaces = surfaces();
+        STATE.with_borrow_mut(|state| {
+            state.done = Some(FrameDump {
+                frame,
+                calls,
+                surfaces,
+            })
+        });
+    }
+}
+
+impl Machine {
+    /// Mark a frame boundary, for both frame stepping and frame dumps.
+    pub(crate) fn presented(&self) {
+        self.pause.presented();
+        frame_end(|| crate::winapi::dump_surfaces(self));
+    }
+}
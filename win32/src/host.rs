@@ -33,11 +33,41 @@ impl Default for SurfaceOptions {
     }
 }
 
+/// Window icon, as RGBA pixels.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Icon {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>,
+}
+
 /// Floating window.
 pub trait Window {
     fn set_title(&mut self, title: &str);
+    fn set_icon(&mut self, icon: &Icon);
     fn set_size(&mut self, width: u32, height: u32);
-    fn fullscreen(&mut self);
+    /// Take over the whole display, showing the window's contents scaled up by the
+    /// largest integer factor that fits, or return to a normal window.
+    fn set_fullscreen(&mut self, fullscreen: bool);
+}
+
+/// Snapshot of a guest window's state, as reported by Machine::windows().
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct WindowInfo {
+    pub hwnd: u32,
+    pub title: String,
+    pub icon: Option<Icon>,
+}
+
+/// Stream of audio output, as used by DirectSound.
+/// Samples are signed 16-bit stereo at the rate given to Host::create_audio, interleaved.
+pub trait Audio {
+    /// Queue samples to be played after those already queued.
+    fn write(&mut self, samples: &[i16]);
+
+    /// Number of frames (sample pairs) queued but not yet played.
+    /// This is the host audio clock: guest play positions are derived from it.
+    fn queued(&self) -> u32;
 }
 
 pub trait File {
@@ -47,14 +77,56 @@ pub trait File {
     fn read(&mut self, buf: &mut [u8], len: &mut u32) -> bool;
 }
 
-#[derive(Debug, Clone, Copy)]
+/// A serial or parallel port, as the guest opens with CreateFile("COM1") and the
+/// like, connected to whatever the host configured for it.
+pub trait Port {
+    /// Read bytes that have arrived, without waiting for more.
+    fn read(&mut self, buf: &mut [u8]) -> usize;
+    fn write(&mut self, buf: &[u8]) -> usize;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SocketKind {
+    /// TCP.
+    Stream,
+    /// UDP.
+    Datagram,
+}
+
+/// A host network socket, for Winsock.  Operations never wait: those that would
+/// fail with io::ErrorKind::WouldBlock, and the caller retries as it sees fit.
+pub trait Socket {
+    /// Set the local address.  For a stream socket this may only take effect on
+    /// listen().
+    fn bind(&mut self, addr: std::net::SocketAddrV4) -> std::io::Result<()>;
+    fn listen(&mut self) -> std::io::Result<()>;
+    fn accept(&mut self) -> std::io::Result<(Box<dyn Socket>, std::net::SocketAddrV4)>;
+    /// Start connecting; WouldBlock means the connection is in progress, and
+    /// writable() reports when it's done.  Datagram sockets just set their peer.
+    fn connect(&mut self, addr: std::net::SocketAddrV4) -> std::io::Result<()>;
+    fn send(&mut self, buf: &[u8]) -> std::io::Result<usize>;
+    fn send_to(&mut self, buf: &[u8], addr: std::net::SocketAddrV4) -> std::io::Result<usize>;
+    /// Receive into buf; Ok(0) is the end of a stream.
+    fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<usize>;
+    fn recv_from(&mut self, buf: &mut [u8]) -> std::io::Result<(usize, std::net::SocketAddrV4)>;
+    fn local_addr(&self) -> std::io::Result<std::net::SocketAddrV4>;
+    fn peer_addr(&self) -> std::io::Result<std::net::SocketAddrV4>;
+    /// Whether recv() or accept() would complete without blocking.
+    fn readable(&mut self) -> bool;
+    /// Whether send() would complete without blocking, which includes a connect()
+    /// having finished, successfully or not.
+    fn writable(&mut self) -> bool;
+    fn shutdown(&mut self, how: std::net::Shutdown) -> std::io::Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub enum MouseButton {
     Left,
     Middle,
     Right,
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct MouseMessage {
     pub down: bool,
     pub button: MouseButton,
@@ -62,18 +134,108 @@ pub struct MouseMessage {
     pub y: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct MouseMoveMessage {
+    /// Position in the window.
+    pub x: u32,
+    pub y: u32,
+    /// Relative motion, which continues past the window's edges while the mouse
+    /// is grabbed.
+    pub dx: i32,
+    pub dy: i32,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct KeyMessage {
+    pub down: bool,
+    /// The key's USB HID usage id (keyboard page), which both SDL scancodes and
+    /// the web's KeyboardEvent.code name.
+    pub usage: u32,
+}
+
+/// A game controller attached to the host.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Controller {
+    /// Host identifier for the controller, unique for as long as it is attached.
+    pub id: u32,
+    pub name: String,
+    pub axes: u32,
+    pub buttons: u32,
+}
+
+/// Current state of a game controller, in the standard layout SDL and the web
+/// Gamepad API share.
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GamepadState {
+    /// Left stick x and y, right stick x and y, from -32768 to 32767 with y positive
+    /// downwards; then left and right triggers, from 0 to 32767.
+    pub axes: [i16; 6],
+    /// Bit set per button down, in GamepadButton order.
+    pub buttons: u32,
+}
+
+/// Bits of GamepadState::buttons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    Back,
+    Guide,
+    Start,
+    LeftStick,
+    RightStick,
+    LeftShoulder,
+    RightShoulder,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub enum MessageDetail {
     Quit,
     Mouse(MouseMessage),
+    MouseMove(MouseMoveMessage),
+    /// A key was pressed or released; auto-repeat isn't reported.
+    Key(KeyMessage),
+    /// Text typed or committed by the host's input method, as characters rather than keys.
+    Text(String),
+    /// The text the host's input method is composing, not yet committed; empty if
+    /// the composition was canceled.  The committed result then arrives as Text.
+    Composition(String),
+    /// The host window gained (true) or lost (false) focus.
+    Activate(bool),
+    /// A game controller was plugged in; also sent for controllers present at startup.
+    ControllerAdded(Controller),
+    /// The controller with the given id was unplugged.
+    ControllerRemoved(u32),
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct Message {
     pub hwnd: u32,
     pub detail: MessageDetail,
 }
 
+/// A request for the user to pick a file, from GetOpenFileName/GetSaveFileName.
+#[derive(Debug)]
+pub struct FileDialog {
+    /// Whether the file is to be saved, so may not exist yet.
+    pub save: bool,
+    pub title: String,
+    /// Descriptions of kinds of file, with the patterns (like "*.map") matching them.
+    pub filters: Vec<(String, Vec<String>)>,
+    /// Index into filters of the one to start with.
+    pub filter_index: usize,
+    /// Host directory to start in, if any.
+    pub dir: Option<String>,
+    /// File name to start with, if any.
+    pub name: Option<String>,
+}
+
 pub trait Host {
     fn exit(&self, code: u32);
     fn time(&self) -> u32;
@@ -87,9 +249,56 @@ pub trait Host {
     /// unblock() when ready.
     fn block(&self, wait: Option<u32>) -> bool;
 
+    /// Report progress through a long-running operation, like loading a large
+    /// executable, so the host can show it rather than appear frozen.
+    /// `done` counts up to `total`, at which point the task is finished.
+    fn progress(&self, task: &str, done: u32, total: u32);
+
+    /// Deliver a message sent by guest code over the debug channel,
+    /// see kernel32::OutputDebugStringA.
+    fn guest_message(&self, channel: &str, payload: &str);
+
     fn open(&self, path: &str) -> Box<dyn File>;
+    /// Whether a file exists, as opening a missing one gives an empty file.
+    fn exists(&self, path: &str) -> bool;
     fn write(&self, buf: &[u8]) -> usize;
 
+    /// Open a port by name ("COM1", "LPT1", ...), or None if the host has nothing
+    /// connected to it, which the guest sees as the port not existing.
+    fn open_port(&self, name: &str) -> Option<Box<dyn Port>>;
+
+    /// Create a network socket, for Winsock.
+    fn socket(&self, kind: SocketKind) -> std::io::Result<Box<dyn Socket>>;
+    /// Look up the addresses of a host by name.
+    fn resolve(&self, name: &str) -> std::io::Result<Vec<std::net::Ipv4Addr>>;
+
+    /// Fill buf with random bytes, suitable for cryptography.
+    fn random(&self, buf: &mut [u8]);
+
+    /// Open a URL, or a host file by its path, in the host's default application,
+    /// for ShellExecute.  Returns whether it did.
+    fn shell_open(&self, target: &str) -> bool;
+
+    /// Run another program in a new instance of the emulator, for ShellExecute of
+    /// an exe: `exe` is its host path, `cmdline` its full command line, and `dir`
+    /// the host directory to run it in, if given.  Returns whether it started.
+    fn launch(&self, exe: &str, cmdline: &str, dir: Option<&str>) -> bool;
+
+    /// Ask the user to pick a file, returning its host path, or None if they cancel.
+    fn pick_file(&self, dialog: &FileDialog) -> Option<String>;
+
     fn create_window(&mut self, hwnd: u32) -> Box<dyn Window>;
     fn create_surface(&mut self, opts: &SurfaceOptions) -> Box<dyn Surface>;
+
+    /// Open an audio output stream at the given sample rate, or None if the host
+    /// can't play sound, in which case playback is timed against time() instead.
+    fn create_audio(&mut self, sample_rate: u32) -> Option<Box<dyn Audio>>;
+
+    /// Read the state of the game controller with the given id, as reported in
+    /// MessageDetail::ControllerAdded, or None if it's no longer attached.
+    fn gamepad(&self, id: u32) -> Option<GamepadState>;
+
+    /// Grab the mouse, hiding the cursor and keeping it in the window, so that it
+    /// only reports relative motion; or release it.  For DirectInput's exclusive mode.
+    fn grab_mouse(&self, grab: bool);
 }
@@ -1,10 +1,25 @@
+pub mod api_stats;
+#[cfg(feature = "x86-emu")]
+pub mod backtrace;
+pub mod capture;
+pub mod framedump;
 mod host;
 mod machine;
 pub mod pe;
+#[cfg(feature = "x86-emu")]
+pub mod profiler;
+pub mod replay;
+#[cfg(feature = "script")]
+pub mod script;
 mod segments;
 pub mod shims;
+#[cfg(feature = "x86-emu")]
+mod snapshot;
 pub mod str16;
+pub mod symbols;
 pub mod trace;
+#[cfg(feature = "x86-emu")]
+mod win16;
 mod winapi;
 
 #[cfg(feature = "x86-emu")]
@@ -25,6 +40,15 @@ mod machine_unicorn;
 mod shims_unicorn;
 
 pub use host::*;
-pub use machine::Machine;
+pub use machine::{
+    set_global_cpu_limit, BackgroundPolicy, Machine, MuteHandle, PauseHandle, StopHandle,
+};
+pub use winapi::advapi32::Registry;
+pub use winapi::ddraw::Gpu;
+pub use winapi::kernel32::{
+    is_supported_code_page, load_library_from_memory, AddressSpace, GUEST_CHANNEL_PREFIX,
+};
+pub use winapi::shell32::FolderMapping;
+pub use winapi::winmm::JoystickMap;
 #[cfg(feature = "x86-emu")]
 pub use x86::debug::disassemble;
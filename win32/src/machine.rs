@@ -1,5 +1,16 @@
-use crate::{host, shims::Shim, winapi};
-use std::collections::HashMap;
+use crate::{host, shims::Shim, symbols::Symbols, winapi};
+use memory::{Mem, PAGE_SIZE};
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+};
 
 #[cfg(feature = "x86-emu")]
 pub use crate::machine_emu::{Machine, MemImpl};
@@ -13,8 +24,219 @@ pub struct LoadedAddrs {
     pub stack_pointer: u32,
 }
 
+/// A CPU execution engine: the x86 interpreter (x86-emu), Unicorn (x86-unicorn),
+/// or running natively (x86-64).  Which one a Machine uses is picked by build feature,
+/// as they need different memory layouts and linking.  The winapi layer reaches the
+/// CPU only through this trait, so adding a backend doesn't touch it; capabilities a
+/// backend lacks have defaults that the callers fall back from.
 pub trait Emulator {
+    /// Make a shim callable from x86 code, returning the address to call.
     fn register(&mut self, shim: Result<&'static Shim, String>) -> u32;
+
+    /// Guest memory.
+    fn mem(&self) -> Mem;
+
+    /// Id of the running guest thread.
+    fn current_thread(&self) -> u32 {
+        1
+    }
+
+    /// Whether new_thread() is supported.  If not, threads are run synchronously.
+    fn supports_threads(&self) -> bool {
+        false
+    }
+
+    /// Create a guest thread that starts at eip with the given stack and TEB, with
+    /// args pushed as for a stdcall call.  Returns the thread id.
+    fn new_thread(
+        &mut self,
+        _stack_pointer: u32,
+        _teb: u32,
+        _eip: u32,
+        _args: &[u32],
+    ) -> Result<u32, Unsupported> {
+        Err(Unsupported("threads"))
+    }
+
+    /// Suspend the running guest thread until the given host time, or until the
+    /// host has an event if None.  Returns None if the backend can't suspend guest
+    /// code, in which case the caller must wait some other way.
+    fn block(&mut self, _wait: Option<u32>) -> Option<Pin<Box<dyn Future<Output = ()>>>> {
+        None
+    }
+
+    /// Stop running guest code, as for ExitProcess.
+    fn exit(&mut self, _code: u32) {}
+
+    /// Forget anything decoded from the code in the range, after memory there changed.
+    fn invalidate_code(&mut self, _addr: u32, _len: u32) {}
+
+    /// Push onto the x87 stack, where functions return floating point values.
+    fn fpu_push(&mut self, _val: f64) -> Result<(), Unsupported> {
+        Err(Unsupported("x87 access from shims"))
+    }
+
+    /// Pop the x87 stack, for functions taking their arguments there.
+    fn fpu_pop(&mut self) -> Result<f64, Unsupported> {
+        Err(Unsupported("x87 access from shims"))
+    }
+
+    /// Set the x87 control word, for the CRT's _controlfp.
+    fn fpu_set_control(&mut self, _control: u16) {}
+}
+
+/// An Emulator capability the backend lacks, for callers to fall back from.
+#[derive(Debug)]
+pub struct Unsupported(pub &'static str);
+
+impl std::fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} not supported by this CPU backend", self.0)
+    }
+}
+
+/// Shared flag for asking a running Machine to stop.
+/// Cloneable and Send, so it can be handed to another thread; setting it is a single
+/// atomic store, so it is also safe to use from a signal handler.
+#[derive(Clone, Default)]
+pub struct StopHandle(Arc<AtomicBool>);
+
+impl StopHandle {
+    pub fn request_stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Shared control for pausing a Machine, and for stepping it a frame at a time to
+/// look at rendering glitches frame by frame.  Cloneable like StopHandle, for
+/// frontends to act on from their own UI.
+#[derive(Clone, Default)]
+pub struct PauseHandle(Arc<PauseState>);
+
+#[derive(Default)]
+struct PauseState {
+    paused: AtomicBool,
+    /// Pause once the next frame is presented.
+    step_frame: AtomicBool,
+}
+
+impl PauseHandle {
+    pub fn set_paused(&self, paused: bool) {
+        self.0.step_frame.store(false, Ordering::Relaxed);
+        self.0.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.paused.load(Ordering::Relaxed)
+    }
+
+    /// Run until the next frame is presented, then pause.
+    pub fn step_frame(&self) {
+        self.0.step_frame.store(true, Ordering::Relaxed);
+        self.0.paused.store(false, Ordering::Relaxed);
+    }
+
+    /// Called as a frame goes to the screen: a Flip, a blit to a window, a buffer swap.
+    pub(crate) fn presented(&self) {
+        if self.0.step_frame.swap(false, Ordering::Relaxed) {
+            self.0.paused.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Shared with audio output, which plays silence while set; see
+/// BackgroundPolicy::Continue.  Unlike StopHandle, only the machine's own thread
+/// uses it.
+#[derive(Clone, Default)]
+pub struct MuteHandle(Rc<Cell<bool>>);
+
+impl MuteHandle {
+    pub fn set_muted(&self, muted: bool) {
+        self.0.set(muted);
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.0.get()
+    }
+}
+
+/// What to do with the guest while the host window doesn't have focus.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BackgroundPolicy {
+    /// Keep running at full speed, with audio muted.
+    #[default]
+    Continue,
+    /// Stop running guest code until focus returns.
+    Pause,
+    /// Wake up `fps` times per second, running a short slice of guest code each time.
+    Throttle { fps: u32 },
+}
+
+/// How long each wakeup under BackgroundPolicy::Throttle runs, in ms.
+const THROTTLE_SLICE_MS: u32 = 2;
+
+/// Length of the periods a CPU limit is budgeted over, in ms: about a frame, so
+/// that a limited game still gets to run every frame.
+const CPU_LIMIT_PERIOD_MS: u32 = 16;
+
+/// Process-wide CPU limit shared among all machines, in percent; 0 for none.
+static GLOBAL_CPU_LIMIT: AtomicU32 = AtomicU32::new(0);
+/// Count of live machines, which share GLOBAL_CPU_LIMIT; see Throttle.
+static MACHINES: AtomicU32 = AtomicU32::new(0);
+
+/// Cap the host CPU used by all machines in the process together, as a percentage
+/// of one core, for e.g. servers running many instances.  The limit is split
+/// evenly among the machines alive at any moment, and combines with each machine's
+/// own cpu_limit.
+pub fn set_global_cpu_limit(percent: Option<u32>) {
+    GLOBAL_CPU_LIMIT.store(percent.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Timing of the slices of BackgroundPolicy::Throttle and of CPU limits, in host time.
+/// Each machine owns one, which also counts it among the machines alive.
+pub struct Throttle {
+    slice_end: u32,
+    next_slice: u32,
+    limit_slice_end: u32,
+    limit_next_period: u32,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        MACHINES.fetch_add(1, Ordering::Relaxed);
+        Throttle {
+            slice_end: 0,
+            next_slice: 0,
+            limit_slice_end: 0,
+            limit_next_period: 0,
+        }
+    }
+}
+
+impl Drop for Throttle {
+    fn drop(&mut self) {
+        MACHINES.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl std::str::FromStr for BackgroundPolicy {
+    type Err = String;
+
+    /// Parses "continue", "pause", or a frame rate like "10".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "continue" => BackgroundPolicy::Continue,
+            "pause" => BackgroundPolicy::Pause,
+            fps => match fps.parse::<u32>() {
+                Ok(fps) if fps > 0 => BackgroundPolicy::Throttle { fps },
+                _ => return Err(format!("bad background policy {s:?}")),
+            },
+        })
+    }
 }
 
 /// Integrates the X86 CPU emulator with the Windows OS support.
@@ -23,4 +245,153 @@ pub struct MachineX<Emu> {
     pub host: Box<dyn host::Host>,
     pub state: winapi::State,
     pub labels: HashMap<u32, String>,
+    /// Names from user-supplied symbol files.
+    pub symbols: Symbols,
+    pub stop: StopHandle,
+    pub pause: PauseHandle,
+    pub mute: MuteHandle,
+    pub background: BackgroundPolicy,
+    /// Cap on the host CPU this machine uses, as a percentage of one core.
+    pub cpu_limit: Option<u32>,
+    pub throttle: Throttle,
+}
+
+impl<Emu: Emulator> MachineX<Emu> {
+    pub fn mem(&self) -> Mem {
+        self.emu.mem()
+    }
+}
+
+impl Machine {
+    /// Initialize a memory mapping for the stack and return the initial stack pointer.
+    /// Like Windows, the whole stack is only reserved, with its top page committed
+    /// and a guard page below that; touching the guard page commits it and moves
+    /// the guard down, until the stack runs out (see kernel32::exception).
+    pub fn create_stack(&mut self, desc: String, stack_size: u32) -> u32 {
+        // 0 means the default size; Windows also won't make stacks smaller than the
+        // 64kb allocation granularity.
+        let stack_size = match stack_size {
+            0 => 1 << 20,
+            size => size.max(0x1_0000),
+        };
+        let memory = &mut self.emu.memory;
+        let stack = self
+            .state
+            .kernel32
+            .mappings
+            .reserve(stack_size, desc, memory)
+            .unwrap_or_else(|| panic!("no room for {stack_size:x} byte stack"));
+        let top = stack.addr + stack.size;
+        memory.commit(top - PAGE_SIZE, PAGE_SIZE);
+        memory.guard(top - 2 * PAGE_SIZE, PAGE_SIZE);
+        top - 4
+    }
+}
+
+impl<Emu> MachineX<Emu> {
+    /// The guest's windows, for a host UI to label its own windows with.
+    pub fn windows(&self) -> Vec<host::WindowInfo> {
+        self.state.user32.window_infos()
+    }
+
+    /// Ask the CPU loop to return control to the embedder at the next safe point,
+    /// i.e. between blocks.  Machine::run() returns false once it has stopped.
+    pub fn request_stop(&self) {
+        self.stop.request_stop();
+    }
+
+    /// A handle for requesting a stop from elsewhere, e.g. another thread.
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+
+    /// A handle for pausing and frame stepping from elsewhere, e.g. a frontend's UI.
+    pub fn pause_handle(&self) -> PauseHandle {
+        self.pause.clone()
+    }
+
+    /// Hold the guest while paused, returning how long to block for like
+    /// background_wait().
+    pub(crate) fn pause_wait(&mut self) -> Option<Option<u32>> {
+        if !self.pause.is_paused() {
+            return None;
+        }
+        // As in background_wait(), take in messages so the host can block for more.
+        let now = self.host.time();
+        while let Some(msg) = self.host.get_message() {
+            winapi::user32::enqueue_host_message(&mut self.state, now, msg);
+        }
+        Some(None)
+    }
+
+    /// Whether the machine stopped due to request_stop(), as opposed to exiting or erroring.
+    pub fn stopped(&self) -> bool {
+        self.stop.is_requested()
+    }
+
+    /// Apply the background policy: if the guest shouldn't run right now, returns
+    /// how long to block for, in the form taken by Host::block().
+    pub(crate) fn background_wait(&mut self) -> Option<Option<u32>> {
+        self.mute.set_muted(
+            self.state.user32.background && self.background == BackgroundPolicy::Continue,
+        );
+        if !self.state.user32.background || self.background == BackgroundPolicy::Continue {
+            return None;
+        }
+        let fps = match self.background {
+            BackgroundPolicy::Throttle { fps } => fps,
+            _ => 0,
+        };
+        let now = self.host.time();
+        if fps > 0 && now < self.throttle.slice_end {
+            return None;
+        }
+
+        // The guest isn't reading messages while we hold it, so pull them in here
+        // to notice when focus returns.
+        while let Some(msg) = self.host.get_message() {
+            winapi::user32::enqueue_host_message(&mut self.state, now, msg);
+        }
+        if !self.state.user32.background {
+            return None;
+        }
+
+        if fps == 0 {
+            return Some(None);
+        }
+        if now >= self.throttle.next_slice {
+            self.throttle.slice_end = now + THROTTLE_SLICE_MS;
+            self.throttle.next_slice = now + 1000 / fps;
+            return None;
+        }
+        Some(Some(self.throttle.next_slice))
+    }
+
+    /// Apply CPU limits: guest code runs for a share of each CPU_LIMIT_PERIOD_MS
+    /// and is held for the rest of it.  Returns how long to block for, like
+    /// background_wait().
+    pub(crate) fn cpu_limit_wait(&mut self) -> Option<Option<u32>> {
+        let global = match GLOBAL_CPU_LIMIT.load(Ordering::Relaxed) {
+            0 => None,
+            percent => Some(percent / std::cmp::max(MACHINES.load(Ordering::Relaxed), 1)),
+        };
+        let percent = match (self.cpu_limit, global) {
+            (Some(a), Some(b)) => std::cmp::min(a, b),
+            (a, b) => a.or(b)?,
+        };
+        if percent >= 100 {
+            return None;
+        }
+        let now = self.host.time();
+        if now < self.throttle.limit_slice_end {
+            return None;
+        }
+        if now >= self.throttle.limit_next_period {
+            let slice = std::cmp::max(CPU_LIMIT_PERIOD_MS * percent / 100, 1);
+            self.throttle.limit_slice_end = now + slice;
+            self.throttle.limit_next_period = now + CPU_LIMIT_PERIOD_MS;
+            return None;
+        }
+        Some(Some(self.throttle.limit_next_period))
+    }
 }
@@ -1,36 +1,396 @@
 use crate::{
     host,
-    machine::{LoadedAddrs, MachineX},
+    machine::{LoadedAddrs, MachineX, Unsupported},
     pe,
     shims_emu::Shims,
-    winapi,
+    winapi::{self, kernel32::PAGE_READWRITE},
 };
-use memory::Mem;
-use std::collections::HashMap;
+use memory::{Mem, Page, PageState, PageTable, PAGE_SIZE};
+use std::{collections::HashMap, future::Future, pin::Pin};
 
-// This is really Box<u8>, just using Vec<u8> to use serde_bytes.
-#[derive(serde::Serialize, serde::Deserialize)]
-pub struct BoxMem(#[serde(with = "serde_bytes")] Vec<u8>);
+/// Guest memory for the interpreter, where guest addresses are offsets into one buffer.
+///
+/// Where the host has the address space for it (64-bit unix), we reserve the whole
+/// 4gb guest range up front and commit pages as the guest commits them; the OS backs
+/// pages lazily, so only memory the guest touches costs anything.  On wasm32 we grow
+/// linear memory by as much of the guest range as the engine allows, which engines
+/// likewise back lazily, and guest mappings must fit within it.  Elsewhere we allocate
+/// a fixed-size buffer.  Either way the buffer never moves once created, because shims
+/// hold references into it across calls.
+///
+/// Which pages the guest may access is tracked in a page table, which the CPU checks
+/// accesses against, so uncommitted pages fault even where the host has them backed.
+pub struct BoxMem {
+    ptr: *mut u8,
+    /// End of the highest committed page.
+    committed: u32,
+    /// Bytes the buffer may be committed up to.
+    reserved: u32,
+    backing: Backing,
+    /// Boxed so Mems pointing at it stay valid when the BoxMem moves.
+    pages: Box<PageTable>,
+}
+
+enum Backing {
+    Heap(#[allow(dead_code)] Vec<u8>),
+    #[cfg(all(unix, target_pointer_width = "64"))]
+    Mapped,
+    #[cfg(target_arch = "wasm32")]
+    Linear,
+}
+
+/// Linear memory can't be given back, so a dropped BoxMem's grown region is kept
+/// here, as (address, size), for the next one to reuse.
+#[cfg(target_arch = "wasm32")]
+static LINEAR_SPARE: std::sync::Mutex<Option<(usize, u32)>> = std::sync::Mutex::new(None);
+
+/// Size of the fixed buffer used where we can't reserve the full address space.
+const HEAP_SIZE: u32 = 256 << 20;
 
 impl BoxMem {
-    fn new(size: usize) -> Self {
-        let mut buf = Vec::with_capacity(size);
-        unsafe {
-            buf.set_len(size);
+    fn new() -> Self {
+        #[cfg(all(unix, target_pointer_width = "64"))]
+        let mut mem = Self::new_mapped().unwrap_or_else(|| Self::new_heap(HEAP_SIZE));
+        #[cfg(target_arch = "wasm32")]
+        let mut mem = Self::new_linear().unwrap_or_else(|| Self::new_heap(HEAP_SIZE));
+        #[cfg(not(any(all(unix, target_pointer_width = "64"), target_arch = "wasm32")))]
+        let mut mem = Self::new_heap(HEAP_SIZE);
+        // Faulting instructions complete against the null page (see x86_addr), so
+        // the host backs it even though it's never committed for the guest.
+        mem.host_protect(0, PAGE_SIZE, true);
+        mem.committed = PAGE_SIZE;
+        mem
+    }
+
+    fn new_heap(size: u32) -> Self {
+        let mut buf = vec![0; size as usize];
+        BoxMem {
+            ptr: buf.as_mut_ptr(),
+            committed: 0,
+            reserved: size,
+            backing: Backing::Heap(buf),
+            pages: Default::default(),
+        }
+    }
+
+    #[cfg(all(unix, target_pointer_width = "64"))]
+    fn new_mapped() -> Option<Self> {
+        // The top 64kb is never available to user code, which keeps sizes in a u32.
+        let reserved = 0xFFFF_0000u32;
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                reserved as usize,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            log::warn!("couldn't reserve guest address space, falling back to fixed memory");
+            return None;
+        }
+        Some(BoxMem {
+            ptr: ptr as *mut u8,
+            committed: 0,
+            reserved,
+            backing: Backing::Mapped,
+            pages: Default::default(),
+        })
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn new_linear() -> Option<Self> {
+        use core::arch::wasm32::{memory_grow, memory_size};
+        // Sizes here are in wasm pages of 64kb.
+        const WASM_PAGE: usize = 64 << 10;
+        const MAX_PAGES: usize = 0x1_0000;
+        // Leave room for the host's own heap, which grows linear memory after us.
+        const HOST_HEADROOM: usize = (512 << 20) / WASM_PAGE;
+
+        let (ptr, reserved) = match LINEAR_SPARE.lock().unwrap().take() {
+            Some(spare) => spare,
+            None => {
+                let available = MAX_PAGES.saturating_sub(memory_size(0) + HOST_HEADROOM);
+                // The top 64kb is never available to user code, as in new_mapped.
+                let mut pages = std::cmp::min(available, MAX_PAGES - 1);
+                // Engines cap linear memory below the wasm32 limit, at a size we can
+                // only find by asking; halve until it fits.
+                loop {
+                    if pages * WASM_PAGE < HEAP_SIZE as usize {
+                        log::warn!(
+                            "couldn't grow linear memory for the guest, falling back to fixed memory"
+                        );
+                        return None;
+                    }
+                    let prev = memory_grow(0, pages);
+                    if prev != usize::MAX {
+                        break (prev * WASM_PAGE, (pages * WASM_PAGE) as u32);
+                    }
+                    pages /= 2;
+                }
+            }
+        };
+        Some(BoxMem {
+            ptr: ptr as *mut u8,
+            committed: 0,
+            reserved,
+            backing: Backing::Linear,
+            pages: Default::default(),
+        })
+    }
+
+    /// Page-aligned bounds of addr..addr+size, if within what the host can back.
+    fn page_bounds(&self, addr: u32, size: u32) -> Option<(u32, u32)> {
+        let start = addr & !(PAGE_SIZE - 1);
+        let end = (addr as u64 + size as u64).next_multiple_of(PAGE_SIZE as u64);
+        if end > self.reserved as u64 {
+            return None;
+        }
+        Some((start, end as u32))
+    }
+
+    /// Set the host protection of a page-aligned range, where memory is mapped.
+    fn host_protect(&mut self, start: u32, end: u32, accessible: bool) -> bool {
+        match self.backing {
+            Backing::Heap(_) => true,
+            // Linear memory has no protection; the page table alone guards the guest.
+            #[cfg(target_arch = "wasm32")]
+            Backing::Linear => true,
+            #[cfg(all(unix, target_pointer_width = "64"))]
+            Backing::Mapped => unsafe {
+                let ptr = self.ptr.add(start as usize) as *mut _;
+                let len = (end - start) as usize;
+                let prot = if accessible {
+                    libc::PROT_READ | libc::PROT_WRITE
+                } else {
+                    libc::PROT_NONE
+                };
+                libc::mprotect(ptr, len, prot) == 0
+            },
+        }
+    }
+
+    /// Discard the contents of a page-aligned range; it reads as zeros afterwards.
+    fn host_discard(&mut self, start: u32, end: u32) {
+        match self.backing {
+            Backing::Heap(_) => {
+                self.mem().as_mut_slice_todo()[start as usize..end as usize].fill(0);
+            }
+            #[cfg(target_arch = "wasm32")]
+            Backing::Linear => unsafe {
+                std::ptr::write_bytes(self.ptr.add(start as usize), 0, (end - start) as usize);
+            },
+            #[cfg(all(unix, target_pointer_width = "64"))]
+            Backing::Mapped => unsafe {
+                let ptr = self.ptr.add(start as usize) as *mut _;
+                libc::madvise(ptr, (end - start) as usize, libc::MADV_DONTNEED);
+            },
+        }
+    }
+
+    /// Make guest memory at addr..addr+size accessible, read/write.  Already
+    /// committed pages are left as they are.  Returns false if the host can't back it.
+    pub fn commit(&mut self, addr: u32, size: u32) -> bool {
+        let Some((start, end)) = self.page_bounds(addr, size) else {
+            return false;
+        };
+        let mut page = start;
+        while page < end {
+            let (run, len, state) = self.pages.region(page);
+            let run_end = std::cmp::min(run as u64 + len as u64, end as u64) as u32;
+            if state.state != PageState::Committed {
+                let run_start = std::cmp::max(run, page);
+                if !self.host_protect(run_start, run_end, true) {
+                    return false;
+                }
+                self.pages.set(
+                    run_start,
+                    run_end - run_start,
+                    Page {
+                        state: PageState::Committed,
+                        protect: PAGE_READWRITE,
+                    },
+                );
+            }
+            page = run_end;
+        }
+        self.committed = std::cmp::max(self.committed, end);
+        true
+    }
+
+    /// Set aside addr..addr+size without making it accessible.
+    /// The host backs it all the same (costing nothing until touched), so the
+    /// emulator itself can write into reserved memory, like a stack it pushes
+    /// onto, and only the page table decides what the guest may access.
+    pub fn reserve(&mut self, addr: u32, size: u32) -> bool {
+        let Some((start, end)) = self.page_bounds(addr, size) else {
+            return false;
+        };
+        if !self.host_protect(start, end, true) {
+            return false;
+        }
+        self.pages.set(
+            start,
+            end - start,
+            Page {
+                state: PageState::Reserved,
+                protect: 0,
+            },
+        );
+        true
+    }
+
+    /// Make committed memory inaccessible again, leaving it reserved (or with
+    /// `release`, free), and discard its contents.
+    pub fn decommit(&mut self, addr: u32, size: u32, release: bool) {
+        let Some((start, end)) = self.page_bounds(addr, size) else {
+            return;
+        };
+        self.host_discard(start, end);
+        let state = if release {
+            self.host_protect(start, end, false);
+            PageState::Free
+        } else {
+            PageState::Reserved
+        };
+        self.pages
+            .set(start, end - start, Page { state, protect: 0 });
+    }
+
+    /// Change the protection of committed pages, returning the previous protection
+    /// of the first, or None if any of them aren't committed.
+    pub fn protect(&mut self, addr: u32, size: u32, protect: u32) -> Option<u32> {
+        if !self.pages.is_committed(addr, size) {
+            return None;
+        }
+        let old = self.pages.get(addr).protect;
+        self.pages.set(
+            addr,
+            size,
+            Page {
+                state: PageState::Committed,
+                protect,
+            },
+        );
+        if !winapi::kernel32::is_writable_protection(protect) {
+            self.pages.set_read_only(addr, size);
         }
-        Self(buf)
+        Some(old)
     }
 
-    // TODO: we can support growing under wasm by using a custom allocator that
-    // ensures this is the last thing in the heap.
-    // pub fn grow();
+    /// Make the pages at addr..addr+size guard pages, which fault on first access;
+    /// see PageState::Guard.
+    pub fn guard(&mut self, addr: u32, size: u32) {
+        self.pages.set(
+            addr,
+            size,
+            Page {
+                state: PageState::Guard,
+                protect: PAGE_READWRITE,
+            },
+        );
+    }
+
+    pub fn pages(&self) -> &PageTable {
+        &self.pages
+    }
 
     pub fn len(&self) -> u32 {
-        self.0.len() as u32
+        self.committed
     }
 
     pub fn mem(&self) -> Mem {
-        Mem::from_slice(&self.0)
+        let buf = unsafe { std::slice::from_raw_parts(self.ptr, self.committed as usize) };
+        Mem::with_pages(buf, &self.pages)
+    }
+}
+
+impl Drop for BoxMem {
+    fn drop(&mut self) {
+        match self.backing {
+            Backing::Heap(_) => {}
+            #[cfg(all(unix, target_pointer_width = "64"))]
+            Backing::Mapped => unsafe {
+                libc::munmap(self.ptr as *mut _, self.reserved as usize);
+            },
+            #[cfg(target_arch = "wasm32")]
+            Backing::Linear => unsafe {
+                // Hand the region on zeroed, as fresh linear memory would be.
+                std::ptr::write_bytes(self.ptr, 0, self.committed as usize);
+                *LINEAR_SPARE.lock().unwrap() = Some((self.ptr as usize, self.reserved));
+            },
+        }
+    }
+}
+
+/// A run of pages in a snapshot, with its contents if committed.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SnapshotRegion {
+    addr: u32,
+    size: u32,
+    committed: bool,
+    #[serde(default)]
+    guard: bool,
+    protect: u32,
+    data: serde_bytes::ByteBuf,
+}
+
+// Snapshots hold the reserved and committed regions, and the committed memory.
+impl serde::Serialize for BoxMem {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut regions = Vec::new();
+        let mut addr = 0u64;
+        while addr < self.committed as u64 {
+            let (start, size, page) = self.pages.region(addr as u32);
+            let size = std::cmp::min(size as u64, self.committed as u64 - start as u64) as u32;
+            let committed = page.state == PageState::Committed;
+            let guard = page.state == PageState::Guard;
+            if page.state != PageState::Free {
+                let data = match committed {
+                    true => self.mem().sub(start, size).as_slice_todo().to_vec(),
+                    false => Vec::new(),
+                };
+                regions.push(SnapshotRegion {
+                    addr: start,
+                    size,
+                    committed,
+                    guard,
+                    protect: page.protect,
+                    data: serde_bytes::ByteBuf::from(data),
+                });
+            }
+            addr = start as u64 + size as u64;
+        }
+        regions.serialize(serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for BoxMem {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let regions = Vec::<SnapshotRegion>::deserialize(deserializer)?;
+        let mut mem = BoxMem::new();
+        for region in regions {
+            if !region.committed {
+                mem.reserve(region.addr, region.size);
+                if region.guard {
+                    mem.guard(region.addr, region.size);
+                }
+                continue;
+            }
+            if !mem.commit(region.addr, region.size) {
+                return Err(serde::de::Error::custom(
+                    "snapshot memory too large for host",
+                ));
+            }
+            mem.protect(region.addr, region.size, region.protect);
+            mem.mem()
+                .sub(region.addr, region.size)
+                .as_mut_slice_todo()
+                .copy_from_slice(&region.data);
+        }
+        Ok(mem)
     }
 }
 
@@ -40,20 +400,93 @@ pub struct Emulator {
     pub memory: BoxMem,
     #[serde(skip)]
     pub shims: Shims,
+    /// Set when running a 16-bit program, which snapshots don't support.
+    #[serde(skip)]
+    pub win16: Option<crate::win16::State>,
 }
 
 impl crate::machine::Emulator for Emulator {
     fn register(&mut self, shim: Result<&'static crate::shims::Shim, String>) -> u32 {
         self.shims.add(shim)
     }
+
+    fn mem(&self) -> Mem {
+        self.memory.mem()
+    }
+
+    fn current_thread(&self) -> u32 {
+        self.x86.cur_cpu as u32
+    }
+
+    fn supports_threads(&self) -> bool {
+        true
+    }
+
+    fn new_thread(
+        &mut self,
+        stack_pointer: u32,
+        teb: u32,
+        eip: u32,
+        args: &[u32],
+    ) -> Result<u32, Unsupported> {
+        let mem = self.memory.mem();
+        let cpu = self.x86.new_cpu();
+        cpu.fpu.control = FPU_CONTROL_DEFAULT;
+        set_fs(cpu, teb);
+        cpu.regs.set32(x86::Register::ESP, stack_pointer);
+        cpu.regs.set32(x86::Register::EBP, stack_pointer);
+        for &arg in args.iter().rev() {
+            x86::ops::push_unchecked(cpu, mem, arg);
+        }
+        x86::ops::push_unchecked(cpu, mem, 0); // return address
+        cpu.regs.eip = eip;
+        Ok(self.x86.cpus.len() as u32 - 1)
+    }
+
+    fn block(&mut self, wait: Option<u32>) -> Option<Pin<Box<dyn Future<Output = ()>>>> {
+        Some(Box::pin(self.x86.cpu_mut().block(wait)))
+    }
+
+    fn exit(&mut self, code: u32) {
+        // TODO: this is unsatisfying.
+        // Maybe better is to generate a hlt instruction somewhere and jump to it?
+        self.x86.cpu_mut().state = x86::CPUState::Exit(code);
+    }
+
+    fn invalidate_code(&mut self, addr: u32, len: u32) {
+        self.x86.invalidate_code(addr, len);
+    }
+
+    fn fpu_push(&mut self, val: f64) -> Result<(), Unsupported> {
+        self.x86.cpu_mut().fpu.push_f64(val);
+        Ok(())
+    }
+
+    fn fpu_pop(&mut self) -> Result<f64, Unsupported> {
+        Ok(self.x86.cpu_mut().fpu.pop_f64())
+    }
+
+    fn fpu_set_control(&mut self, control: u16) {
+        self.x86.cpu_mut().fpu.control = control;
+    }
 }
 
+/// Point FS at a thread's TEB, with the selector Windows gives it.
+fn set_fs(cpu: &mut x86::CPU, teb: u32) {
+    cpu.regs.set16(x86::Register::FS, 0x3B);
+    cpu.regs.fs_addr = teb;
+}
+
+/// The x87 control word Windows starts threads with: all exceptions masked,
+/// rounding to nearest, and 53-bit precision rather than FINIT's 64.
+const FPU_CONTROL_DEFAULT: u16 = 0x027F;
+
 pub type MemImpl = BoxMem;
 pub type Machine = MachineX<Emulator>;
 
 impl MachineX<Emulator> {
     pub fn new(host: Box<dyn host::Host>, cmdline: String) -> Self {
-        let mut memory = BoxMem::new(256 << 20);
+        let mut memory = BoxMem::new();
         let kernel32 = winapi::kernel32::State::new(&mut memory, cmdline);
         let shims = Shims::default();
         let state = winapi::State::new(kernel32);
@@ -63,41 +496,40 @@ impl MachineX<Emulator> {
                 x86: x86::X86::new(),
                 memory,
                 shims,
+                win16: None,
             },
             host,
             state,
             labels: HashMap::new(),
+            symbols: Default::default(),
+            stop: Default::default(),
+            pause: Default::default(),
+            mute: Default::default(),
+            background: Default::default(),
+            cpu_limit: None,
+            throttle: Default::default(),
         }
     }
 
-    pub fn mem(&self) -> Mem {
-        self.emu.memory.mem()
-    }
-
-    /// Initialize a memory mapping for the stack and return the initial stack pointer.
-    pub fn create_stack(&mut self, desc: String, stack_size: u32) -> u32 {
-        let stack = self
-            .state
-            .kernel32
-            .mappings
-            .alloc(stack_size, desc, &mut self.emu.memory);
-        let stack_pointer = stack.addr + stack.size - 4;
-        stack_pointer
-    }
-
     pub fn load_exe(
         &mut self,
         buf: &[u8],
         cmdline: String,
         relocate: bool,
     ) -> anyhow::Result<LoadedAddrs> {
+        if pe::ne::ne_header_offset(buf).is_some() {
+            return crate::win16::load_exe(self, buf, &cmdline);
+        }
         let exe = pe::load_exe(self, buf, cmdline, relocate)?;
 
         let stack_pointer = self.create_stack("stack".into(), exe.stack_size);
+        let kernel32 = &self.state.kernel32;
+        kernel32.set_teb_stack(self.emu.memory.mem(), kernel32.teb, stack_pointer);
+        self.emu.x86.cpu_mut().fpu.control = FPU_CONTROL_DEFAULT;
+        set_fs(self.emu.x86.cpu_mut(), self.state.kernel32.teb);
         let regs = &mut self.emu.x86.cpu_mut().regs;
         regs.set32(x86::Register::ESP, stack_pointer);
         regs.set32(x86::Register::EBP, stack_pointer);
-        regs.fs_addr = self.state.kernel32.teb;
 
         // To make CPU traces match more closely, set up some registers to what their
         // initial values appear to be from looking in a debugger.
@@ -108,8 +540,8 @@ impl MachineX<Emulator> {
 
         let retrowin32_main = winapi::kernel32::get_kernel32_builtin(self, "retrowin32_main");
         let cpu = self.emu.x86.cpu_mut();
-        x86::ops::push(cpu, self.emu.memory.mem(), exe.entry_point);
-        x86::ops::push(cpu, self.emu.memory.mem(), 0); // return address
+        x86::ops::push_unchecked(cpu, self.emu.memory.mem(), exe.entry_point);
+        x86::ops::push_unchecked(cpu, self.emu.memory.mem(), 0); // return address
         cpu.regs.eip = retrowin32_main;
 
         Ok(LoadedAddrs {
@@ -119,7 +551,7 @@ impl MachineX<Emulator> {
     }
 
     pub fn single_step_next_block(&mut self) {
-        if !crate::shims_emu::is_eip_at_shim_call(self) {
+        if !crate::shims_emu::is_eip_at_shim_call(self) && !crate::win16::is_eip_at_thunk(self) {
             self.emu.x86.single_step_next_block(self.emu.memory.mem());
         }
     }
@@ -132,9 +564,27 @@ impl MachineX<Emulator> {
         }
     }
 
+    /// Run a block of code.  Returns false when the machine has exited, errored,
+    /// or been stopped via request_stop().
     pub fn run(&mut self) -> bool {
+        if self.stop.is_requested() {
+            return false;
+        }
         match self.emu.x86.schedule() {
-            x86::CPUState::Running => self.execute_block(),
+            x86::CPUState::Running => {
+                if let Some(wait) = self
+                    .pause_wait()
+                    .or_else(|| self.background_wait())
+                    .or_else(|| self.cpu_limit_wait())
+                    .or_else(|| self.speed_limit_wait())
+                {
+                    // Held back while paused, in the background, over the CPU limit, or
+                    // ahead of the speed limit; block as if waiting on a message.
+                    self.emu.x86.cpu_mut().state = x86::CPUState::Blocked(wait);
+                    return true;
+                }
+                self.execute_block()
+            }
             x86::CPUState::Blocked(wait) => {
                 let wait = *wait;
                 if self.host.block(wait) {
@@ -148,6 +598,13 @@ impl MachineX<Emulator> {
         true
     }
 
+    /// Apply the speed limit, if any, returning how long to block like background_wait().
+    fn speed_limit_wait(&mut self) -> Option<Option<u32>> {
+        let x86 = &mut self.emu.x86;
+        let limit = x86.speed_limit.as_mut()?;
+        limit.wait(x86.cycles, self.host.time()).map(Some)
+    }
+
     // Execute one basic block.  Returns false if we stopped early.
     fn execute_block(&mut self) {
         debug_assert!(self.emu.x86.cpu().state.is_running());
@@ -156,10 +613,47 @@ impl MachineX<Emulator> {
             // Treat any shim call as a single block and return here.
             return;
         }
-        self.emu.x86.execute_block(self.emu.memory.mem())
+        if crate::win16::is_eip_at_thunk(self) {
+            crate::win16::handle_thunk(self);
+            return;
+        }
+        let eip = self.emu.x86.cpu().regs.eip;
+        let host = &self.host;
+        self.emu
+            .x86
+            .execute_block(self.emu.memory.mem(), &|| host.time());
+        #[cfg(feature = "script")]
+        crate::script::rearm(self, eip);
+        match self.emu.x86.cpu().state {
+            x86::CPUState::Exception(exception) if self.emu.win16.is_some() => {
+                crate::win16::cpu_exception(self, exception);
+            }
+            x86::CPUState::Exception(exception) => {
+                winapi::kernel32::raise_cpu_exception(self, exception);
+            }
+            // Errors the emulator itself hit, rather than ones raised from async
+            // shims, which report their own context.
+            x86::CPUState::Error(_) if eip != x86::MAGIC_ADDR => {
+                let backtrace = crate::backtrace::current(self);
+                if let x86::CPUState::Error(msg) = &mut self.emu.x86.cpu_mut().state {
+                    msg.push('\n');
+                    msg.push_str(&backtrace);
+                }
+            }
+            #[cfg(feature = "script")]
+            x86::CPUState::Blocked(None) => {
+                crate::script::on_breakpoint(self);
+            }
+            _ => {}
+        }
     }
 
-    pub fn call_x86(&mut self, func: u32, args: Vec<u32>) -> impl std::future::Future {
+    /// Call an x86 function, resolving to its return value.
+    pub fn call_x86(
+        &mut self,
+        func: u32,
+        args: Vec<u32>,
+    ) -> impl std::future::Future<Output = u32> {
         self.emu
             .x86
             .cpu_mut()
@@ -178,11 +672,55 @@ impl MachineX<Emulator> {
     //     }
     // }
 
-    pub fn snapshot(&self) -> Box<[u8]> {
-        bincode::serialize(&self.emu).unwrap().into()
+    /// The coverage recorded so far, in drcov format (see x86::coverage), with the
+    /// loaded images as its modules.  exe_path, if given, names the exe's file in
+    /// place of the name it was loaded under.
+    pub fn coverage_drcov(&self, exe_path: Option<&str>) -> Option<Vec<u8>> {
+        let coverage = self.emu.x86.coverage.as_ref()?;
+        let modules = self
+            .state
+            .kernel32
+            .images
+            .iter()
+            .enumerate()
+            .map(|(i, image)| x86::coverage::Module {
+                path: match exe_path {
+                    Some(path) if i == 0 => path.to_string(),
+                    _ => image.name.clone(),
+                },
+                base: image.base,
+                size: image.size,
+            })
+            .collect::<Vec<_>>();
+        Some(coverage.drcov(&modules))
+    }
+
+    pub fn snapshot(&self) -> anyhow::Result<Box<[u8]>> {
+        let snapshot = crate::snapshot::Snapshot {
+            emu: &self.emu,
+            resources: crate::snapshot::save(self)?,
+            state: &self.state,
+        };
+        Ok(bincode::serialize(&snapshot)?.into())
     }
 
     pub fn load_snapshot(&mut self, bytes: &[u8]) {
-        self.emu = bincode::deserialize(bytes).unwrap();
+        type Snapshot = crate::snapshot::Snapshot<Emulator, winapi::State>;
+        match bincode::deserialize::<Snapshot>(bytes) {
+            Ok(snapshot) => {
+                let shims = std::mem::take(&mut self.emu.shims);
+                self.emu = snapshot.emu;
+                self.emu.shims = shims;
+                crate::snapshot::restore(self, snapshot.resources, snapshot.state);
+            }
+            // Snapshots from before the state was recorded, of which we can still
+            // take the memory and registers.
+            Err(err) => {
+                log::warn!("snapshot state unreadable ({err}), loading memory and registers");
+                let shims = std::mem::take(&mut self.emu.shims);
+                self.emu = bincode::deserialize(bytes).unwrap();
+                self.emu.shims = shims;
+            }
+        }
     }
 }
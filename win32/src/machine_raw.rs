@@ -1,6 +1,6 @@
 use crate::{
     host,
-    machine::{LoadedAddrs, MachineX},
+    machine::{LoadedAddrs, MachineX, Unsupported},
     pe,
     shims_raw::Shims,
     winapi,
@@ -19,6 +19,18 @@ impl RawMem {
     pub fn len(&self) -> u32 {
         0xFFFF_FFFF
     }
+    /// Native memory is the host's own address space, with nothing to set up.
+    pub fn commit(&mut self, _addr: u32, _size: u32) -> bool {
+        true
+    }
+    pub fn reserve(&mut self, _addr: u32, _size: u32) -> bool {
+        true
+    }
+    pub fn decommit(&mut self, _addr: u32, _size: u32, _release: bool) {}
+    pub fn guard(&mut self, _addr: u32, _size: u32) {}
+    pub fn protect(&mut self, _addr: u32, _size: u32, _protect: u32) -> Option<u32> {
+        Some(crate::winapi::kernel32::PAGE_READWRITE)
+    }
 }
 
 pub struct Emulator {
@@ -30,6 +42,31 @@ impl crate::machine::Emulator for Emulator {
     fn register(&mut self, shim: Result<&'static crate::shims::Shim, String>) -> u32 {
         self.shims.add(shim)
     }
+
+    fn mem(&self) -> Mem {
+        self.memory.mem()
+    }
+
+    // Shims run on the guest's own CPU, between its instructions, and Rust code
+    // doesn't use the x87, so the guest's x87 stack is the one right here.
+
+    #[cfg(target_arch = "x86_64")]
+    fn fpu_push(&mut self, val: f64) -> Result<(), Unsupported> {
+        unsafe { std::arch::asm!("fld qword ptr [{}]", in(reg) &val as *const f64) };
+        Ok(())
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn fpu_pop(&mut self) -> Result<f64, Unsupported> {
+        let mut val = 0f64;
+        unsafe { std::arch::asm!("fstp qword ptr [{}]", in(reg) &mut val as *mut f64) };
+        Ok(val)
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn fpu_set_control(&mut self, control: u16) {
+        unsafe { std::arch::asm!("fldcw word ptr [{}]", in(reg) &control as *const u16) };
+    }
 }
 
 pub type MemImpl = RawMem;
@@ -52,13 +89,16 @@ impl MachineX<Emulator> {
             host,
             state,
             labels: HashMap::new(),
+            symbols: Default::default(),
+            stop: Default::default(),
+            pause: Default::default(),
+            mute: Default::default(),
+            background: Default::default(),
+            cpu_limit: None,
+            throttle: Default::default(),
         }
     }
 
-    pub fn mem(&self) -> Mem {
-        self.emu.memory.mem()
-    }
-
     #[allow(non_snake_case)]
     pub fn load_exe(
         &mut self,
@@ -68,12 +108,7 @@ impl MachineX<Emulator> {
     ) -> anyhow::Result<LoadedAddrs> {
         let exe = pe::load_exe(self, buf, cmdline, relocate)?;
 
-        let stack = self.state.kernel32.mappings.alloc(
-            exe.stack_size,
-            "stack".into(),
-            &mut self.emu.memory,
-        );
-        let stack_pointer = stack.addr + stack.size - 4;
+        let stack_pointer = self.create_stack("stack".into(), exe.stack_size);
 
         Ok(LoadedAddrs {
             entry_point: exe.entry_point,
@@ -81,7 +116,11 @@ impl MachineX<Emulator> {
         })
     }
 
-    pub fn call_x86(&mut self, func: u32, args: Vec<u32>) -> impl std::future::Future {
+    pub fn call_x86(
+        &mut self,
+        func: u32,
+        args: Vec<u32>,
+    ) -> impl std::future::Future<Output = u32> {
         crate::shims_raw::call_x86(self, func, args)
     }
 }
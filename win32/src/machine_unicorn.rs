@@ -1,11 +1,11 @@
 use crate::{
     host,
-    machine::{LoadedAddrs, MachineX},
+    machine::{LoadedAddrs, MachineX, Unsupported},
     pe,
     shims_unicorn::Shims,
     winapi,
 };
-use memory::Mem;
+use memory::{Extensions, Mem};
 use std::collections::HashMap;
 
 pub struct MemImpl(Box<[u8]>);
@@ -20,6 +20,19 @@ impl MemImpl {
     pub fn len(&self) -> u32 {
         self.0.len() as u32
     }
+    /// Memory is allocated up front, as Unicorn maps it once at startup.
+    pub fn commit(&mut self, addr: u32, size: u32) -> bool {
+        addr as u64 + size as u64 <= self.len() as u64
+    }
+    pub fn reserve(&mut self, addr: u32, size: u32) -> bool {
+        self.commit(addr, size)
+    }
+    /// Page state isn't tracked, so memory stays accessible.
+    pub fn decommit(&mut self, _addr: u32, _size: u32, _release: bool) {}
+    pub fn guard(&mut self, _addr: u32, _size: u32) {}
+    pub fn protect(&mut self, _addr: u32, _size: u32, _protect: u32) -> Option<u32> {
+        Some(crate::winapi::kernel32::PAGE_READWRITE)
+    }
     pub fn mem(&self) -> Mem {
         Mem::from_slice(&self.0)
     }
@@ -33,12 +46,59 @@ pub struct Emulator {
     pub unicorn: unicorn_engine::Unicorn<'static, ()>,
     pub shims: Shims,
     pub memory: MemImpl,
+    /// Memory for running single x87 instructions on behalf of shims: 8 bytes of
+    /// operand followed by the instruction.
+    x87_scratch: u32,
+}
+
+impl Emulator {
+    /// Run one x87 instruction, given its opcode and ModRM byte, with its memory
+    /// operand at the start of x87_scratch.  Unicorn doesn't expose the x87 stack
+    /// as a stack, so this lets it do the pushing and popping.
+    fn run_x87(&mut self, opcode: u8, modrm: u8) {
+        let data = self.x87_scratch;
+        let code = data + 8;
+        let mem = self.memory.mem();
+        mem.put::<u8>(code, opcode);
+        mem.put::<u8>(code + 1, modrm);
+        mem.put::<u32>(code + 2, data);
+        let eip = self
+            .unicorn
+            .reg_read(unicorn_engine::RegisterX86::EIP)
+            .unwrap();
+        self.unicorn
+            .emu_start(code as u64, (code + 6) as u64, 0, 1)
+            .unwrap();
+        self.unicorn
+            .reg_write(unicorn_engine::RegisterX86::EIP, eip)
+            .unwrap();
+    }
 }
 
 impl crate::machine::Emulator for Emulator {
     fn register(&mut self, shim: Result<&'static crate::shims::Shim, String>) -> u32 {
         self.shims.add(shim)
     }
+
+    fn mem(&self) -> Mem {
+        self.memory.mem()
+    }
+
+    fn fpu_push(&mut self, val: f64) -> Result<(), Unsupported> {
+        self.memory.mem().put::<f64>(self.x87_scratch, val);
+        self.run_x87(0xDD, 0x05); // fld qword [data]
+        Ok(())
+    }
+
+    fn fpu_pop(&mut self) -> Result<f64, Unsupported> {
+        self.run_x87(0xDD, 0x1D); // fstp qword [data]
+        Ok(self.memory.mem().get_pod::<f64>(self.x87_scratch))
+    }
+
+    fn fpu_set_control(&mut self, control: u16) {
+        self.memory.mem().put::<u16>(self.x87_scratch, control);
+        self.run_x87(0xD9, 0x2D); // fldcw [data]
+    }
 }
 
 pub type Machine = MachineX<Emulator>;
@@ -71,6 +131,11 @@ impl MachineX<Emulator> {
             Shims::new(&mut unicorn, mapping.addr)
         };
 
+        let x87_scratch = kernel32
+            .mappings
+            .alloc(0x1000, "x87 scratch".into(), &mut memory)
+            .addr;
+
         let state = winapi::State::new(kernel32);
 
         Machine {
@@ -78,25 +143,24 @@ impl MachineX<Emulator> {
                 unicorn,
                 shims,
                 memory,
+                x87_scratch,
             },
             host,
             state,
             labels: HashMap::new(),
+            symbols: Default::default(),
+            stop: Default::default(),
+            pause: Default::default(),
+            mute: Default::default(),
+            background: Default::default(),
+            cpu_limit: None,
+            throttle: Default::default(),
         }
     }
 
-    pub fn mem(&self) -> Mem {
-        self.emu.memory.mem()
-    }
-
     /// Initialize a memory mapping for the stack and return the initial stack pointer.
     fn setup_stack(&mut self, stack_size: u32) -> u32 {
-        let stack =
-            self.state
-                .kernel32
-                .mappings
-                .alloc(stack_size, "stack".into(), &mut self.emu.memory);
-        let stack_pointer = stack.addr + stack.size - 4;
+        let stack_pointer = self.create_stack("stack".into(), stack_size);
 
         // TODO: put this init somewhere better.
         self.emu
@@ -200,7 +264,11 @@ impl MachineX<Emulator> {
         })
     }
 
-    pub fn call_x86(&mut self, func: u32, args: Vec<u32>) -> impl std::future::Future {
+    pub fn call_x86(
+        &mut self,
+        func: u32,
+        args: Vec<u32>,
+    ) -> impl std::future::Future<Output = u32> {
         crate::shims_unicorn::call_x86(self, func, args)
     }
 }
@@ -0,0 +1,76 @@
+#![allow(non_snake_case)]
+#![allow(non_camel_case_types)]
+
+use crate::winapi::types::{DWORD, WORD};
+use memory::Extensions;
+
+/// Debug Directory (section 6.1.1)
+#[derive(Clone, Debug)]
+#[repr(C)]
+pub struct IMAGE_DEBUG_DIRECTORY {
+    Characteristics: DWORD,
+    TimeDateStamp: DWORD,
+    MajorVersion: WORD,
+    MinorVersion: WORD,
+    Type: DWORD,
+    SizeOfData: DWORD,
+    AddressOfRawData: DWORD,
+    PointerToRawData: DWORD,
+}
+unsafe impl memory::Pod for IMAGE_DEBUG_DIRECTORY {}
+
+const IMAGE_DEBUG_TYPE_CODEVIEW: u32 = 2;
+
+/// The PDB an image was linked with, as named by its CodeView debug record.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PdbInfo {
+    /// Path of the PDB on the machine that built the image.
+    pub path: String,
+    /// The key a symbol server files the PDB under: the GUID (or for older
+    /// NB10 records, the timestamp) followed by the age, in hex.
+    pub signature: String,
+    pub age: u32,
+}
+
+fn parse_codeview(data: &[u8]) -> Option<PdbInfo> {
+    let (signature, age, path) = match data.get(..4)? {
+        b"RSDS" => {
+            let guid = data.get(4..20)?;
+            let (d1, d2, d3) = (
+                guid.get_pod::<u32>(0),
+                guid.get_pod::<u16>(4),
+                guid.get_pod::<u16>(6),
+            );
+            let mut sig = format!("{d1:08X}{d2:04X}{d3:04X}");
+            for b in &guid[8..] {
+                sig.push_str(&format!("{b:02X}"));
+            }
+            (sig, data.get(20..24)?.get_pod::<u32>(0), data.get(24..)?)
+        }
+        b"NB10" => {
+            // Followed by an offset, always 0, as the debug info is in a separate file.
+            let stamp = data.get(8..12)?.get_pod::<u32>(0);
+            let age = data.get(12..16)?.get_pod::<u32>(0);
+            (format!("{stamp:08X}"), age, data.get(16..)?)
+        }
+        _ => return None,
+    };
+    let path = &path[..path.iter().position(|&c| c == 0).unwrap_or(path.len())];
+    Some(PdbInfo {
+        path: String::from_utf8_lossy(path).into_owned(),
+        signature: format!("{signature}{age:X}"),
+        age,
+    })
+}
+
+/// Find the PDB info among the entries of the debug directory, whose data is
+/// read from the file, as it isn't always mapped into memory.
+pub fn read_pdb_info(dir: &[u8], buf: &[u8]) -> Option<PdbInfo> {
+    dir.into_iter_pod::<IMAGE_DEBUG_DIRECTORY>()
+        .filter(|entry| entry.Type == IMAGE_DEBUG_TYPE_CODEVIEW)
+        .find_map(|entry| {
+            let start = entry.PointerToRawData as usize;
+            let data = buf.get(start..start.checked_add(entry.SizeOfData as usize)?)?;
+            parse_codeview(data)
+        })
+}
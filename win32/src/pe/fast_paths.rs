@@ -0,0 +1,116 @@
+//! Host implementations of hot routines from statically linked C runtimes.
+//!
+//! Programs linked against the static MSVC CRT carry their own copies of memcpy and
+//! friends, which then run a byte or dword at a time on the emulated CPU and can
+//! dominate profiles.  As code is loaded we look for the well-known entry sequences
+//! of these routines and overwrite them with a jump to the builtin implementation of
+//! the same function, which then runs on the host.  The jump arrives with the
+//! stack laid out as for a call, so the shim returns straight to the caller.
+//!
+//! This rewrites the program's code, which self-checking programs may notice, so
+//! it's only done when asked for (kernel32 State::crt_fast_paths).
+
+use super::{File, ImageSectionFlags};
+use crate::{
+    machine::{Emulator, Machine},
+    winapi,
+};
+
+struct Signature {
+    /// Builtin DLL and function taking over the routine.
+    dll: &'static str,
+    name: &'static str,
+    /// Hex bytes at the start of the routine, with "??" matching any byte.
+    prologue: &'static str,
+}
+
+const SIGNATURES: [Signature; 3] = [
+    // intel/memcpy.asm, which is also memmove.
+    Signature {
+        dll: "vcruntime140.dll",
+        name: "memcpy",
+        prologue: "55 8B EC 57 56 8B 75 0C 8B 4D 10 8B 7D 08 8B C1 8B D1 03 C6 3B FE 76 08 3B F8",
+    },
+    // intel/memset.asm
+    Signature {
+        dll: "vcruntime140.dll",
+        name: "memset",
+        prologue: "8B 54 24 0C 8B 4C 24 04 85 D2 74 ?? 33 C0 8A 44 24 08",
+    },
+    // intel/strlen.asm
+    Signature {
+        dll: "ucrtbase.dll",
+        name: "strlen",
+        prologue: "8B 4C 24 04 F7 C1 03 00 00 00 74 ?? 8A 01",
+    },
+];
+
+fn parse_prologue(prologue: &str) -> Vec<Option<u8>> {
+    prologue
+        .split_ascii_whitespace()
+        .map(|b| match b {
+            "??" => None,
+            b => Some(u8::from_str_radix(b, 16).unwrap()),
+        })
+        .collect()
+}
+
+fn builtin_shim(dll: &str, name: &str) -> &'static crate::shims::Shim {
+    let dll = winapi::DLLS.iter().find(|d| d.file_name == dll).unwrap();
+    &dll.exports
+        .iter()
+        .find(|sym| sym.shim.name == name)
+        .unwrap()
+        .shim
+}
+
+/// Replace recognized CRT routines in the code sections of the image at base.
+pub fn patch(machine: &mut Machine, image_name: &str, base: u32, file: &File) {
+    let patterns: Vec<_> = SIGNATURES
+        .iter()
+        .map(|sig| parse_prologue(sig.prologue))
+        .collect();
+    let mut targets = [0u32; SIGNATURES.len()];
+
+    for sec in &file.sections {
+        let code = sec.characteristics().map_or(false, |flags| {
+            flags.intersects(ImageSectionFlags::CODE | ImageSectionFlags::MEM_EXECUTE)
+        });
+        if !code {
+            continue;
+        }
+        let addr = base + sec.VirtualAddress;
+        let bytes = machine.mem().sub(addr, sec.VirtualSize).as_slice_todo();
+        let mut found = Vec::new();
+        let mut ofs = 0;
+        while ofs < bytes.len() {
+            let matching = patterns.iter().position(|pattern| {
+                bytes[ofs..].len() >= pattern.len()
+                    && pattern
+                        .iter()
+                        .zip(&bytes[ofs..])
+                        .all(|(p, b)| p.map_or(true, |p| p == *b))
+            });
+            match matching {
+                Some(i) => {
+                    found.push((addr + ofs as u32, i));
+                    ofs += patterns[i].len();
+                }
+                None => ofs += 1,
+            }
+        }
+
+        for (func, i) in found {
+            let sig = &SIGNATURES[i];
+            if targets[i] == 0 {
+                targets[i] = machine.emu.register(Ok(builtin_shim(sig.dll, sig.name)));
+            }
+            let rel = targets[i].wrapping_sub(func + 5);
+            let jmp = machine.mem().sub(func, 5).as_mut_slice_todo();
+            jmp[0] = 0xE9; // jmp rel32
+            jmp[1..].copy_from_slice(&rel.to_le_bytes());
+            log::info!("{image_name}: using host {} for code at {func:x}", sig.name);
+            machine.labels.insert(func, format!("{} (host)", sig.name));
+        }
+    }
+}
@@ -29,6 +29,23 @@ pub struct IMAGE_FILE_HEADER {
 }
 unsafe impl memory::Pod for IMAGE_FILE_HEADER {}
 
+bitflags! {
+    pub struct ImageFileCharacteristics: u16 {
+        const RELOCS_STRIPPED = 0x0001;
+        const EXECUTABLE_IMAGE = 0x0002;
+        const LARGE_ADDRESS_AWARE = 0x0020;
+        const _32BIT_MACHINE = 0x0100;
+        const DEBUG_STRIPPED = 0x0200;
+        const DLL = 0x2000;
+    }
+}
+
+impl IMAGE_FILE_HEADER {
+    pub fn characteristics(&self) -> ImageFileCharacteristics {
+        ImageFileCharacteristics::from_bits_truncate(self.Characteristics)
+    }
+}
+
 bitflags! {
     pub struct DllCharacteristics: u16 {
         const HIGH_ENTROPY_VA = 0x0020;
@@ -82,7 +99,7 @@ pub struct IMAGE_OPTIONAL_HEADER32 {
 unsafe impl memory::Pod for IMAGE_OPTIONAL_HEADER32 {}
 
 #[repr(C)]
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct IMAGE_DATA_DIRECTORY {
     pub VirtualAddress: DWORD,
     pub Size: DWORD,
@@ -120,12 +137,35 @@ fn pe_header<'m>(r: &mut Reader<'m>) -> anyhow::Result<IMAGE_FILE_HEADER> {
     r.expect("PE\0\0")?;
 
     let header = r.read::<IMAGE_FILE_HEADER>();
-    if header.Machine != 0x14c {
-        bail!("bad machine {:?}", header.Machine);
+    match header.Machine {
+        0x14c => {}
+        0x8664 => bail!("64-bit (x64) executable; only 32-bit x86 programs are supported"),
+        0x200 => bail!("Itanium executable; only 32-bit x86 programs are supported"),
+        0x1c0 | 0x1c2 | 0x1c4 | 0xaa64 => {
+            bail!("ARM executable; only 32-bit x86 programs are supported")
+        }
+        machine => bail!("executable for machine type {machine:#x}, not x86"),
     }
     Ok(header)
 }
 
+/// The message printed by the DOS stub, if it's the usual one that prints a
+/// message and exits, like "This program cannot be run in DOS mode."
+fn dos_stub_message(buf: &[u8]) -> Option<String> {
+    // The code, following the header, is
+    //   push cs; pop ds; mov dx, msg; mov ah, 9; int 21h
+    // which prints the '$'-terminated string at msg.
+    let code_ofs = buf.get_pod::<WORD>(8) as usize * 16;
+    let code = buf.get(code_ofs..code_ofs + 9)?;
+    if code[..3] != [0x0e, 0x1f, 0xba] || code[5..] != [0xb4, 0x09, 0xcd, 0x21] {
+        return None;
+    }
+    let msg_ofs = code_ofs + u16::from_le_bytes([code[3], code[4]]) as usize;
+    let msg = buf.get(msg_ofs..)?;
+    let end = msg.iter().position(|&c| c == b'$')?;
+    Some(String::from_utf8_lossy(&msg[..end]).trim().to_string())
+}
+
 #[repr(C)]
 #[derive(Debug, Default, Clone)]
 pub struct IMAGE_SECTION_HEADER {
@@ -191,23 +231,60 @@ impl File {
 }
 
 pub fn parse(buf: &[u8]) -> anyhow::Result<File> {
+    if buf.len() < 0x40 || !buf.starts_with(b"MZ") {
+        bail!("not a Windows executable (no MZ header)");
+    }
     let mut r = Reader::new(buf);
 
     let pe_header_ofs = dos_header(&mut r).map_err(|err| anyhow!("reading DOS header: {}", err))?;
-    if pe_header_ofs as usize + std::mem::size_of::<IMAGE_FILE_HEADER>() >= buf.len() {
-        bail!("invalid PE offset in DOS header, might be a DOS executable?");
+    let signature = buf
+        .get(pe_header_ofs as usize..)
+        .and_then(|sig| sig.get(..2));
+    match signature {
+        Some(b"PE") => {}
+        Some(b"NE") => bail!("16-bit Windows (NE) executable"),
+        Some(b"LE" | b"LX") => bail!("LE/LX executable (a VxD, DOS extender or OS/2 program)"),
+        _ => match dos_stub_message(buf) {
+            Some(msg) => bail!("DOS executable, not Windows; it says {msg:?}"),
+            None => bail!("DOS executable, not Windows (no PE header)"),
+        },
+    }
+    let headers_size = std::mem::size_of::<IMAGE_FILE_HEADER>()
+        + std::mem::size_of::<IMAGE_OPTIONAL_HEADER32>()
+        + 4;
+    if pe_header_ofs as usize + headers_size > buf.len() {
+        bail!("damaged or truncated file: PE header runs past the end");
     }
     r.seek(pe_header_ofs)
         .map_err(|err| anyhow!("seeking PE header {pe_header_ofs:x}: {}", err))?;
 
     let header = pe_header(&mut r).map_err(|err| anyhow!("reading PE header: {}", err))?;
     let opt_header = r.read::<IMAGE_OPTIONAL_HEADER32>();
+    if opt_header.Magic == 0x20b {
+        bail!("64-bit (PE32+) executable; only 32-bit x86 programs are supported");
+    }
     let data_directory = r
         .read_n::<IMAGE_DATA_DIRECTORY>(opt_header.NumberOfRvaAndSizes)
-        .unwrap();
+        .map_err(|_| anyhow!("damaged or truncated file: data directory runs past the end"))?;
     let sections = r
         .read_n::<IMAGE_SECTION_HEADER>(header.NumberOfSections as u32)
-        .unwrap();
+        .map_err(|_| anyhow!("damaged or truncated file: section table runs past the end"))?;
+
+    for sec in sections.iter() {
+        // Only the sections the loader copies from the file; see load_section.
+        let flags = ImageSectionFlags::from_bits_truncate(sec.Characteristics);
+        if !flags.intersects(ImageSectionFlags::CODE | ImageSectionFlags::INITIALIZED_DATA) {
+            continue;
+        }
+        let end = sec.PointerToRawData as u64 + sec.SizeOfRawData as u64;
+        if sec.SizeOfRawData > 0 && end > buf.len() as u64 {
+            bail!(
+                "damaged or truncated file: section {:?} data ends at {end:#x}, past the end of the {:#x} byte file",
+                sec.name().unwrap_or("[invalid]"),
+                buf.len()
+            );
+        }
+    }
 
     Ok(File {
         header,
@@ -231,6 +308,20 @@ mod tests {
         assert!(parse(&buf).is_err()); // no crash
     }
 
+    #[test]
+    fn dos_stub() {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.write(b"MZ").unwrap();
+        buf.write(&[0; 6]).unwrap();
+        buf.write(&4u16.to_le_bytes()).unwrap(); // header paragraphs
+        buf.write(&[0; 0x36]).unwrap();
+        buf.write(b"\x0e\x1f\xba\x0e\x00\xb4\x09\xcd\x21\xb8\x01\x4c\xcd\x21")
+            .unwrap();
+        buf.write(b"Not for DOS.\r\n$").unwrap();
+        let err = parse(&buf).unwrap_err().to_string();
+        assert!(err.contains("\"Not for DOS.\""), "{err}");
+    }
+
     #[test]
     fn kkrunchy_header() {
         let mut header = IMAGE_SECTION_HEADER::default();
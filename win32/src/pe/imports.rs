@@ -44,6 +44,14 @@ impl IMAGE_IMPORT_DESCRIPTOR {
         // Meanwhile, win2k's msvcrt.dll has invalid FirstThunk (IAT) data...
         let addr = if self.OriginalFirstThunk != 0 {
             self.OriginalFirstThunk
+        } else if self.is_bound() {
+            // The IAT holds addresses bound against some other system's DLLs,
+            // and without an ILT the names they were bound from are lost.
+            log::warn!(
+                "{}: bound imports without an ILT can't be resolved",
+                self.image_name(image)
+            );
+            image.len() as u32
         } else {
             self.FirstThunk
         };
@@ -57,13 +65,98 @@ impl IMAGE_IMPORT_DESCRIPTOR {
     pub fn iat_offset(&self) -> u32 {
         self.FirstThunk
     }
+
+    /// Whether the IAT was prefilled by binding the image against particular
+    /// DLL versions, which we never trust since our DLLs are different.
+    pub fn is_bound(&self) -> bool {
+        self.TimeDateStamp != 0
+    }
 }
 
+/// Offset of TimeDateStamp within IMAGE_IMPORT_DESCRIPTOR, for marking an image's
+/// imports unbound once they're resolved.
+pub const IMPORT_DESCRIPTOR_TIMESTAMP: u32 = 4;
+
 pub fn read_imports<'m>(buf: &'m [u8]) -> impl Iterator<Item = IMAGE_IMPORT_DESCRIPTOR> + 'm {
     buf.into_iter_pod::<IMAGE_IMPORT_DESCRIPTOR>()
         .take_while(|desc| desc.Name != 0)
 }
 
+/// Delay-load Import Directory Table (section 5.8.1): imports resolved when first
+/// called, by a helper linked into the image, rather than at load.  Each IAT entry
+/// initially points at a thunk that calls the helper (__delayLoadHelper2), which
+/// loads the DLL, stores its HMODULE, and patches the IAT.
+#[derive(Clone, Debug, Default)]
+#[repr(C)]
+pub struct IMAGE_DELAYLOAD_DESCRIPTOR {
+    Attributes: DWORD,
+    DllNameRVA: DWORD,
+    ModuleHandleRVA: DWORD,
+    ImportAddressTableRVA: DWORD,
+    ImportNameTableRVA: DWORD,
+    BoundImportAddressTableRVA: DWORD,
+    UnloadInformationTableRVA: DWORD,
+    TimeDateStamp: DWORD,
+}
+unsafe impl memory::Pod for IMAGE_DELAYLOAD_DESCRIPTOR {}
+
+impl IMAGE_DELAYLOAD_DESCRIPTOR {
+    /// Convert one of the descriptor's addresses to an offset within the image.
+    /// Descriptors from before VC7 hold addresses rather than offsets (marked by the
+    /// lack of the dlattrRva attribute); those are relocated with the image, so
+    /// base is the image's actual base.
+    fn rva(&self, addr: u32, base: u32) -> u32 {
+        if self.Attributes & 1 != 0 {
+            addr
+        } else {
+            addr.wrapping_sub(base)
+        }
+    }
+
+    /// The DLL's name, or None if the descriptor doesn't point at one within the image.
+    pub fn image_name<'m>(&self, image: &'m [u8], base: u32) -> Option<&'m str> {
+        let name = image.get(self.rva(self.DllNameRVA, base) as usize..)?;
+        let nul = name.iter().position(|&c| c == 0)?;
+        std::str::from_utf8(&name[..nul]).ok()
+    }
+
+    /// The import name table, which is laid out like an ILT, or None if the descriptor
+    /// doesn't point at one within the image.
+    pub fn ilt<'m>(
+        &self,
+        image: &'m [u8],
+        base: u32,
+    ) -> Option<impl Iterator<Item = ILTEntry> + 'm> {
+        let va = self.Attributes & 1 == 0;
+        let table = image.get(self.rva(self.ImportNameTableRVA, base) as usize..)?;
+        Some(
+            table
+                .into_iter_pod::<ILTEntry>()
+                .take_while(|entry| entry.0 != 0)
+                .map(move |entry| match entry.0 & (1 << 31) {
+                    0 if va => ILTEntry(entry.0.wrapping_sub(base)),
+                    _ => entry,
+                }),
+        )
+    }
+
+    pub fn iat_offset(&self, base: u32) -> u32 {
+        self.rva(self.ImportAddressTableRVA, base)
+    }
+
+    /// Where the helper keeps the DLL's HMODULE, nonzero once it's loaded.
+    pub fn hmodule_offset(&self, base: u32) -> u32 {
+        self.rva(self.ModuleHandleRVA, base)
+    }
+}
+
+pub fn read_delay_imports<'m>(
+    buf: &'m [u8],
+) -> impl Iterator<Item = IMAGE_DELAYLOAD_DESCRIPTOR> + 'm {
+    buf.into_iter_pod::<IMAGE_DELAYLOAD_DESCRIPTOR>()
+        .take_while(|desc| desc.DllNameRVA != 0)
+}
+
 #[repr(transparent)]
 #[derive(Clone)]
 pub struct ILTEntry(u32);
@@ -5,7 +5,7 @@ use crate::{
     machine::{Emulator, Machine},
     pe, winapi,
 };
-use memory::Mem;
+use memory::{Extensions, Mem, PAGE_SIZE};
 use std::collections::HashMap;
 
 /// Create a memory mapping, optionally copying some data to it.
@@ -13,8 +13,7 @@ fn map_memory(machine: &mut Machine, mapping: winapi::kernel32::Mapping, buf: Op
     let winapi::kernel32::Mapping { addr, size, .. } =
         *machine.state.kernel32.mappings.add(mapping);
 
-    let memory_end = addr + size;
-    if memory_end > machine.emu.memory.len() {
+    if !machine.emu.memory.commit(addr, size) {
         panic!("not enough memory reserved");
     }
 
@@ -25,24 +24,40 @@ fn map_memory(machine: &mut Machine, mapping: winapi::kernel32::Mapping, buf: Op
             .as_mut_slice_todo()
             .copy_from_slice(buf);
     }
+    // The range may have held other code before, like a since-freed DLL.
+    machine.emu.invalidate_code(addr, size);
 }
 
-/// Copy the file header itself into memory, choosing a base address.
+/// Copy the file header itself into memory, choosing a base address: the image's
+/// preferred base if it's free (and relocation isn't forced), or otherwise any
+/// free space, which then requires relocating the image.
 fn load_image(
     machine: &mut Machine,
     name: &str,
     file: &pe::File,
     buf: &[u8],
     relocate: bool,
-) -> u32 {
-    let addr = if relocate {
-        machine
-            .state
-            .kernel32
-            .mappings
-            .find_space(file.opt_header.SizeOfImage)
+) -> anyhow::Result<u32> {
+    let preferred = file.opt_header.ImageBase;
+    let size = file.opt_header.SizeOfImage;
+    let mappings = &machine.state.kernel32.mappings;
+    let addr = if !relocate && mappings.is_free(preferred, size) {
+        preferred
     } else {
-        file.opt_header.ImageBase
+        let relocatable = file
+            .get_data_directory(pe::IMAGE_DIRECTORY_ENTRY::BASERELOC)
+            .is_some();
+        if !relocate && !relocatable {
+            anyhow::bail!("preferred base {preferred:x} is in use, and no relocations");
+        }
+        let addr = mappings.find_space(size);
+        if addr as u64 + size as u64 > mappings.limit as u64 {
+            anyhow::bail!("no room for {size:x} byte image");
+        }
+        if !relocate {
+            log::info!("{name}: preferred base {preferred:x} is in use, relocating to {addr:x}");
+        }
+        addr
     };
 
     let first_page_size = std::cmp::min(buf.len(), 0x1000);
@@ -57,7 +72,22 @@ fn load_image(
         Some(&buf[..first_page_size]),
     );
 
-    addr
+    Ok(addr)
+}
+
+/// The page protection a section's characteristics ask for.
+fn section_protection(flags: pe::ImageSectionFlags) -> u32 {
+    use pe::ImageSectionFlags as F;
+    use winapi::kernel32::{
+        PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE, PAGE_READONLY, PAGE_READWRITE,
+    };
+    let execute = flags.intersects(F::MEM_EXECUTE | F::CODE);
+    match (flags.contains(F::MEM_WRITE), execute) {
+        (true, true) => PAGE_EXECUTE_READWRITE,
+        (true, false) => PAGE_READWRITE,
+        (false, true) => PAGE_EXECUTE_READ,
+        (false, false) => PAGE_READONLY,
+    }
 }
 
 /// Load a PE section into memory.
@@ -67,6 +97,7 @@ fn load_section(
     base: u32,
     buf: &[u8],
     sec: &IMAGE_SECTION_HEADER,
+    protect: bool,
 ) {
     let mut src = sec.PointerToRawData as usize;
     if src == 1 {
@@ -110,6 +141,47 @@ fn load_section(
             None
         },
     );
+    if protect {
+        machine
+            .emu
+            .memory
+            .protect(dst, sec.VirtualSize, section_protection(flags));
+    }
+}
+
+/// Resolve one DLL's imports, returning its HMODULE and the (IAT address, target)
+/// pairs to patch.
+fn resolve_imports(
+    machine: &mut Machine,
+    base: u32,
+    dll_name: &str,
+    ilt: impl Iterator<Item = pe::ILTEntry>,
+    iat_offset: u32,
+    image: &[u8],
+) -> (winapi::kernel32::HMODULE, Vec<(u32, u32)>) {
+    let mut patches = Vec::new();
+    let dll_name = dll_name.to_ascii_lowercase();
+    let hmodule = winapi::kernel32::LoadLibraryA(machine, Some(&dll_name));
+    // TODO: missing dll should not be an possibility here, we should error instead.
+    let mut dll = match hmodule.to_dll_index() {
+        Some(index) => Some(&mut machine.state.kernel32.dlls[index]),
+        None => None,
+    };
+    for (i, entry) in ilt.enumerate() {
+        let sym = entry.as_import_symbol(image);
+        let name = format!("{}!{}", dll_name, sym.to_string());
+        let iat_addr = base + iat_offset + (i as u32 * 4);
+        machine.labels.insert(iat_addr, format!("{}@IAT", name));
+
+        let resolved_addr = if let Some(dll) = dll.as_mut() {
+            dll.resolve(&sym, |shim| machine.emu.register(shim))
+        } else {
+            machine.emu.register(Err(format!("{name} not found")))
+        };
+        machine.labels.insert(resolved_addr, name);
+        patches.push((iat_addr, resolved_addr));
+    }
+    (hmodule, patches)
 }
 
 fn patch_iat(machine: &mut Machine, base: u32, imports_data: &IMAGE_DATA_DIRECTORY) {
@@ -123,27 +195,66 @@ fn patch_iat(machine: &mut Machine, base: u32, imports_data: &IMAGE_DATA_DIRECTO
         None => return,
         Some(s) => s,
     };
-    for dll_imports in pe::read_imports(section) {
-        let dll_name = dll_imports.image_name(image).to_ascii_lowercase();
-        let hmodule = winapi::kernel32::LoadLibraryA(machine, Some(&dll_name));
-        // TODO: missing dll should not be an possibility here, we should error instead.
-        let mut dll = match hmodule.to_dll_index() {
-            Some(index) => Some(&mut machine.state.kernel32.dlls[index]),
-            None => None,
+    for (i, dll_imports) in pe::read_imports(section).enumerate() {
+        let dll_name = dll_imports.image_name(image);
+        let ilt = dll_imports.ilt(image);
+        let (_, dll_patches) = resolve_imports(
+            machine,
+            base,
+            dll_name,
+            ilt,
+            dll_imports.iat_offset(),
+            image,
+        );
+        patches.extend(dll_patches);
+        if dll_imports.is_bound() {
+            // Mark the imports as resolved at load rather than bound, as the Windows
+            // loader does when a binding is stale, for code that inspects them.
+            let desc = base
+                + imports_data.VirtualAddress
+                + (i * std::mem::size_of::<pe::IMAGE_IMPORT_DESCRIPTOR>()) as u32;
+            patches.push((desc + pe::IMPORT_DESCRIPTOR_TIMESTAMP, 0));
+        }
+    }
+
+    for (addr, target) in patches {
+        machine.mem().put::<u32>(addr, target);
+    }
+}
+
+/// Resolve delay-loaded imports at load, as if they were ordinary imports: fill
+/// in the IAT and the HMODULE the helper checks, so the image's delay-load helper
+/// never runs.  This avoids depending on the helper's own LoadLibrary and
+/// GetProcAddress calls, and on the thunks it patches.
+fn patch_delay_iat(machine: &mut Machine, base: u32, delay_data: &IMAGE_DATA_DIRECTORY) {
+    let mut patches = Vec::new();
+
+    let image: Mem = unsafe { std::mem::transmute(machine.mem().slice(base..)) };
+    let image = image.as_slice_todo();
+    let section = match delay_data.as_slice(image) {
+        None => return,
+        Some(s) => s,
+    };
+    for desc in pe::read_delay_imports(section) {
+        // A damaged descriptor is skipped, leaving its imports unresolved, rather
+        // than failing the whole load.
+        let in_image = |ofs: u32| (ofs as usize) < image.len();
+        let (dll_name, ilt) = match (desc.image_name(image, base), desc.ilt(image, base)) {
+            (Some(name), Some(ilt))
+                if in_image(desc.iat_offset(base)) && in_image(desc.hmodule_offset(base)) =>
+            {
+                (name, ilt)
+            }
+            _ => {
+                log::warn!("skipping delay-load descriptor pointing outside the image: {desc:x?}");
+                continue;
+            }
         };
-        for (i, entry) in dll_imports.ilt(image).enumerate() {
-            let sym = entry.as_import_symbol(image);
-            let name = format!("{}!{}", dll_name, sym.to_string());
-            let iat_addr = base + dll_imports.iat_offset() + (i as u32 * 4);
-            machine.labels.insert(iat_addr, format!("{}@IAT", name));
-
-            let resolved_addr = if let Some(dll) = dll.as_mut() {
-                dll.resolve(&sym, |shim| machine.emu.register(shim))
-            } else {
-                machine.emu.register(Err(format!("{name} not found")))
-            };
-            machine.labels.insert(resolved_addr, name);
-            patches.push((iat_addr, resolved_addr));
+        let (hmodule, dll_patches) =
+            resolve_imports(machine, base, dll_name, ilt, desc.iat_offset(base), image);
+        patches.extend(dll_patches);
+        if !hmodule.is_null() {
+            patches.push((base + desc.hmodule_offset(base), hmodule.to_raw()));
         }
     }
 
@@ -152,6 +263,16 @@ fn patch_iat(machine: &mut Machine, base: u32, imports_data: &IMAGE_DATA_DIRECTO
     }
 }
 
+/// Where a PE image was placed in memory.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LoadedImage {
+    pub name: String,
+    pub base: u32,
+    pub size: u32,
+    /// The PDB named by the image's debug directory, if any.
+    pub pdb: Option<pe::PdbInfo>,
+}
+
 fn load_pe(
     machine: &mut Machine,
     name: &str,
@@ -159,17 +280,51 @@ fn load_pe(
     file: &pe::File,
     relocate: bool,
 ) -> anyhow::Result<u32> {
-    let base = load_image(machine, name, file, buf, relocate);
-
-    for sec in file.sections.iter() {
-        load_section(machine, name, base, buf, sec);
+    if file
+        .get_data_directory(pe::IMAGE_DIRECTORY_ENTRY::COM_DESCRIPTOR)
+        .is_some()
+    {
+        anyhow::bail!(".NET assembly; it needs the .NET runtime, which isn't supported");
+    }
+    let base = load_image(machine, name, file, buf, relocate)?;
+    let task = format!("loading {name}");
+
+    let count = file.sections.len() as u32;
+    machine.host.progress(&task, 0, count);
+    // Sections aligned to less than a page share pages, so Windows maps such
+    // images all read/write; so do we.
+    let protect = file.opt_header.SectionAlignment >= PAGE_SIZE;
+    for (i, sec) in file.sections.iter().enumerate() {
+        load_section(machine, name, base, buf, sec, protect);
+        machine.host.progress(&task, i as u32 + 1, count);
     }
 
-    if relocate {
+    let pdb = file
+        .get_data_directory(pe::IMAGE_DIRECTORY_ENTRY::DEBUG)
+        .and_then(|dir| dir.as_slice(machine.mem().slice(base..).as_slice_todo()))
+        .and_then(|dir| pe::read_pdb_info(dir, buf));
+    if let Some(pdb) = &pdb {
+        log::info!("{name}: built with {} ({})", pdb.path, pdb.signature);
+    }
+    machine.state.kernel32.images.push(LoadedImage {
+        name: name.into(),
+        base,
+        size: file.opt_header.SizeOfImage,
+        pdb,
+    });
+
+    if base != file.opt_header.ImageBase {
         if let Some(relocs) = file.get_data_directory(pe::IMAGE_DIRECTORY_ENTRY::BASERELOC) {
             let image = machine.mem().slice(base..);
             if let Some(sec) = relocs.as_slice(image.as_slice_todo()) {
-                apply_relocs(image, file.opt_header.ImageBase, base, sec);
+                let task = format!("relocating {name}");
+                apply_relocs(
+                    image,
+                    file.opt_header.ImageBase,
+                    base,
+                    sec,
+                    |done, total| machine.host.progress(&task, done, total),
+                );
             }
         }
     }
@@ -177,6 +332,15 @@ fn load_pe(
     if let Some(imports) = file.get_data_directory(pe::IMAGE_DIRECTORY_ENTRY::IMPORT) {
         patch_iat(machine, base, imports);
     }
+    if machine.state.kernel32.eager_delay_imports {
+        if let Some(imports) = file.get_data_directory(pe::IMAGE_DIRECTORY_ENTRY::DELAY_IMPORT) {
+            patch_delay_iat(machine, base, imports);
+        }
+    }
+
+    if machine.state.kernel32.crt_fast_paths {
+        super::fast_paths::patch(machine, name, base, file);
+    }
 
     Ok(base)
 }
@@ -192,8 +356,18 @@ pub fn load_exe(
     cmdline: String,
     relocate: bool,
 ) -> anyhow::Result<EXEFields> {
+    if pe::ne::ne_header_offset(buf).is_some() {
+        return Err(pe::ne::unsupported(buf));
+    }
     let file = pe::parse(buf)?;
 
+    let large_address_aware = file
+        .header
+        .characteristics()
+        .contains(pe::ImageFileCharacteristics::LARGE_ADDRESS_AWARE);
+    let kernel32 = &mut machine.state.kernel32;
+    kernel32.mappings.limit = kernel32.address_space.user_limit(large_address_aware);
+
     let base = load_pe(machine, &cmdline, buf, &file, relocate)?;
     machine.state.kernel32.image_base = base;
 
@@ -204,6 +378,10 @@ pub fn load_exe(
         machine.state.kernel32.resources = res_data.clone();
     }
 
+    if let Some(dir) = file.get_data_directory(pe::IMAGE_DIRECTORY_ENTRY::TLS) {
+        load_tls(machine, base + dir.VirtualAddress);
+    }
+
     let entry_point = base + file.opt_header.AddressOfEntryPoint;
 
     let addrs = EXEFields {
@@ -213,7 +391,38 @@ pub fn load_exe(
     Ok(addrs)
 }
 
-#[derive(Debug)]
+#[repr(C)]
+#[derive(Clone, Debug)]
+struct IMAGE_TLS_DIRECTORY32 {
+    StartAddressOfRawData: u32,
+    EndAddressOfRawData: u32,
+    AddressOfIndex: u32,
+    AddressOfCallBacks: u32,
+    SizeOfZeroFill: u32,
+    Characteristics: u32,
+}
+unsafe impl memory::Pod for IMAGE_TLS_DIRECTORY32 {}
+
+/// Set up the exe's static TLS, as the template for each thread's copy, and give
+/// the main thread its copy.  Its addresses are already relocated.
+fn load_tls(machine: &mut Machine, addr: u32) {
+    let dir = machine.mem().get_pod::<IMAGE_TLS_DIRECTORY32>(addr);
+    if dir.AddressOfCallBacks != 0 && machine.mem().get_pod::<u32>(dir.AddressOfCallBacks) != 0 {
+        log::warn!("TODO: TLS callbacks not called");
+    }
+    // The exe's TLS is always at index 0.
+    machine.mem().put::<u32>(dir.AddressOfIndex, 0);
+    let kernel32 = &mut machine.state.kernel32;
+    kernel32.tls = winapi::kernel32::TlsTemplate {
+        data: dir.StartAddressOfRawData,
+        data_len: dir.EndAddressOfRawData - dir.StartAddressOfRawData,
+        zero_fill: dir.SizeOfZeroFill,
+    };
+    let teb = kernel32.teb;
+    kernel32.alloc_tls(&mut machine.emu.memory, teb);
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct DLL {
     /// Function name => resolved address.
     pub names: HashMap<String, u32>,
@@ -229,7 +438,7 @@ pub struct DLL {
 pub fn load_dll(machine: &mut Machine, name: &str, buf: &[u8]) -> anyhow::Result<DLL> {
     let file = pe::parse(&buf)?;
 
-    let base = load_pe(machine, name, buf, &file, true)?;
+    let base = load_pe(machine, name, buf, &file, false)?;
     let image = machine.mem().slice(base..).as_slice_todo();
 
     let entry_point = base + file.opt_header.AddressOfEntryPoint;
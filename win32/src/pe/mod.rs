@@ -1,11 +1,15 @@
+mod debug;
 mod exports;
+mod fast_paths;
 mod file;
 mod imports;
 mod loader;
+pub mod ne;
 mod reader;
 mod relocations;
 mod resources;
 
+pub use debug::*;
 pub use exports::*;
 pub use file::*;
 pub use imports::*;
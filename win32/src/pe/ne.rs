@@ -0,0 +1,474 @@
+#![allow(non_snake_case)]
+#![allow(non_camel_case_types)]
+
+//! The NE ("new executable") format of 16-bit Windows programs, which preceded PE.
+//! Only the x86 emulator can run these (see crate::win16); the other backends only
+//! recognize them, to explain why they can't load them.
+
+use super::reader::Reader;
+use crate::winapi::types::{DWORD, WORD};
+use anyhow::{anyhow, bail};
+use memory::Extensions;
+
+// https://wiki.osdev.org/NE
+// http://bytepointer.com/resources/win16_ne_exe_format_win3.0.htm
+
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct IMAGE_OS2_HEADER {
+    pub ne_magic: WORD,
+    pub ne_ver: u8,
+    pub ne_rev: u8,
+    pub ne_enttab: WORD,
+    pub ne_cbenttab: WORD,
+    pub ne_crc: DWORD,
+    pub ne_flags: WORD,
+    pub ne_autodata: WORD,
+    pub ne_heap: WORD,
+    pub ne_stack: WORD,
+    /// Entry point, as segment number:offset.
+    pub ne_csip: DWORD,
+    pub ne_sssp: DWORD,
+    pub ne_cseg: WORD,
+    pub ne_cmod: WORD,
+    pub ne_cbnrestab: WORD,
+    /// Table offsets here are relative to the start of this header.
+    pub ne_segtab: WORD,
+    pub ne_rsrctab: WORD,
+    pub ne_restab: WORD,
+    pub ne_modtab: WORD,
+    pub ne_imptab: WORD,
+    /// Relative to the start of the file.
+    pub ne_nrestab: DWORD,
+    pub ne_cmovent: WORD,
+    /// Segment data offsets are in units of 1 << ne_align.
+    pub ne_align: WORD,
+    pub ne_cres: WORD,
+    pub ne_exetyp: u8,
+    pub ne_flagsothers: u8,
+    pub ne_pretthunks: WORD,
+    pub ne_psegrefbytes: WORD,
+    pub ne_swaparea: WORD,
+    /// Minimum Windows version, as minor, major bytes.
+    pub ne_expver: WORD,
+}
+unsafe impl memory::Pod for IMAGE_OS2_HEADER {}
+
+const NE_EXETYP_WINDOWS: u8 = 2;
+const NE_FLAGS_DLL: u16 = 0x8000;
+
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct NE_SEGMENT {
+    /// In units of the alignment, or 0 if the segment has no data in the file.
+    pub offset: WORD,
+    /// Bytes in the file, with 0 meaning 64k.
+    pub length: WORD,
+    pub flags: WORD,
+    /// Bytes to allocate, with 0 meaning 64k.
+    pub min_alloc: WORD,
+}
+unsafe impl memory::Pod for NE_SEGMENT {}
+
+impl NE_SEGMENT {
+    pub fn is_data(&self) -> bool {
+        self.flags & 1 != 0
+    }
+
+    /// Whether relocation records follow the segment's data.
+    pub fn has_relocs(&self) -> bool {
+        self.flags & 0x100 != 0
+    }
+
+    /// Bytes to allocate for the segment.
+    pub fn alloc_size(&self) -> u32 {
+        match self.min_alloc {
+            0 => 0x1_0000,
+            n => n as u32,
+        }
+    }
+}
+
+/// What a relocation patches, from the low bits of its address type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocKind {
+    /// The low byte of the offset.
+    LoByte,
+    /// A selector.
+    Segment,
+    /// A far pointer, as offset then selector.
+    FarAddr,
+    /// An offset.
+    Offset,
+}
+
+/// What a relocation points at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelocTarget {
+    /// An address in this module, as (segment number, offset).
+    Internal {
+        segment: u8,
+        offset: u16,
+    },
+    /// An import by ordinal, with the module numbered from 1 in File::imports.
+    ImportOrdinal {
+        module: u16,
+        ordinal: u16,
+    },
+    ImportName {
+        module: u16,
+        name: String,
+    },
+    /// A fixup of floating point instructions for the FPU emulator, which we
+    /// don't need as the x86 emulator has an FPU.
+    OsFixup,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reloc {
+    pub kind: RelocKind,
+    /// Additive relocations add to the value already there; others replace it,
+    /// and that value is instead the offset of the next place to patch, in a chain
+    /// ending with 0xFFFF.
+    pub additive: bool,
+    /// Offset in the segment of the (first) place to patch.
+    pub offset: u16,
+    pub target: RelocTarget,
+}
+
+pub struct File {
+    pub header: IMAGE_OS2_HEADER,
+    /// The module name, from the first entry of the resident names table.
+    pub name: String,
+    pub segments: Box<[NE_SEGMENT]>,
+    /// Names of the modules (DLLs) imported from, like "KERNEL" and "USER".
+    pub imports: Vec<String>,
+    /// Relocations to apply to each segment.
+    pub relocs: Vec<Vec<Reloc>>,
+}
+
+impl File {
+    pub fn is_windows(&self) -> bool {
+        self.header.ne_exetyp == NE_EXETYP_WINDOWS
+    }
+
+    pub fn is_dll(&self) -> bool {
+        self.header.ne_flags & NE_FLAGS_DLL != 0
+    }
+
+    /// Entry point as (segment number, offset); segment numbers count from 1.
+    pub fn entry_point(&self) -> (u16, u16) {
+        (
+            (self.header.ne_csip >> 16) as u16,
+            self.header.ne_csip as u16,
+        )
+    }
+
+    /// Where a segment's bytes are in the file; they may be fewer than it allocates.
+    fn segment_range(&self, index: usize) -> std::ops::Range<usize> {
+        let seg = &self.segments[index];
+        if seg.offset == 0 {
+            return 0..0;
+        }
+        let start = (seg.offset as usize) << self.header.ne_align;
+        let len = match seg.length {
+            0 => 0x1_0000,
+            n => n as usize,
+        };
+        start..start + len
+    }
+
+    pub fn segment_data<'a>(&self, buf: &'a [u8], index: usize) -> anyhow::Result<&'a [u8]> {
+        buf.get(self.segment_range(index))
+            .ok_or_else(|| anyhow!("EOF in segment {}", index + 1))
+    }
+
+    /// Minimum Windows version, as (major, minor).
+    pub fn windows_version(&self) -> (u8, u8) {
+        (
+            (self.header.ne_expver >> 8) as u8,
+            self.header.ne_expver as u8,
+        )
+    }
+}
+
+/// Read a length-prefixed string, as used throughout the name tables.
+fn pascal_str(buf: &[u8], ofs: usize) -> anyhow::Result<String> {
+    let len = *buf.get(ofs).ok_or_else(|| anyhow!("EOF"))? as usize;
+    let str = buf
+        .get(ofs + 1..ofs + 1 + len)
+        .ok_or_else(|| anyhow!("EOF"))?;
+    Ok(String::from_utf8_lossy(str).into_owned())
+}
+
+/// Parse the entry table, giving each ordinal's (segment number, offset), indexed by
+/// ordinal less one.  Unused ordinals have segment 0.
+fn parse_entries(table: &[u8]) -> anyhow::Result<Vec<(u8, u16)>> {
+    let mut entries = Vec::new();
+    let mut ofs = 0;
+    // Some linkers leave off the terminating zero count.
+    while let Some(&[count, indicator]) = table.get(ofs..ofs + 2) {
+        ofs += 2;
+        if count == 0 {
+            break;
+        }
+        let entry_len = match indicator {
+            0 => {
+                entries.resize(entries.len() + count as usize, (0, 0));
+                continue;
+            }
+            // Movable: flags, int 3fh, segment, offset.
+            0xFF => 6,
+            // Fixed, in the indicated segment: flags, offset.
+            _ => 3,
+        };
+        for _ in 0..count {
+            let entry = table
+                .get(ofs..ofs + entry_len)
+                .ok_or_else(|| anyhow!("EOF"))?;
+            entries.push(match indicator {
+                0xFF => (entry[3], entry.get_pod::<u16>(4)),
+                seg => (seg, entry.get_pod::<u16>(1)),
+            });
+            ofs += entry_len;
+        }
+    }
+    Ok(entries)
+}
+
+/// Parse the relocation records following a segment's data.
+fn parse_relocs(
+    buf: &[u8],
+    ofs: usize,
+    entries: &[(u8, u16)],
+    imported_name: impl Fn(u16) -> anyhow::Result<String>,
+) -> anyhow::Result<Vec<Reloc>> {
+    let count = buf.get(ofs..ofs + 2).ok_or_else(|| anyhow!("EOF"))?;
+    let count = count.get_pod::<u16>(0) as usize;
+    let records = buf
+        .get(ofs + 2..ofs + 2 + count * 8)
+        .ok_or_else(|| anyhow!("EOF"))?;
+    records
+        .chunks_exact(8)
+        .map(|rec| {
+            let kind = match rec[0] & 0xF {
+                0 => RelocKind::LoByte,
+                2 => RelocKind::Segment,
+                3 => RelocKind::FarAddr,
+                5 => RelocKind::Offset,
+                kind => bail!("unhandled relocation address type {kind}"),
+            };
+            let (word1, word2) = (rec.get_pod::<u16>(4), rec.get_pod::<u16>(6));
+            let target = match rec[1] & 3 {
+                0 => match rec[4] {
+                    // A movable segment, referred to by its entry ordinal.
+                    0xFF => {
+                        let &(segment, offset) = entries
+                            .get(word2 as usize - 1)
+                            .ok_or_else(|| anyhow!("bad entry ordinal {word2}"))?;
+                        RelocTarget::Internal { segment, offset }
+                    }
+                    segment => RelocTarget::Internal {
+                        segment,
+                        offset: word2,
+                    },
+                },
+                1 => RelocTarget::ImportOrdinal {
+                    module: word1,
+                    ordinal: word2,
+                },
+                2 => RelocTarget::ImportName {
+                    module: word1,
+                    name: imported_name(word2)?,
+                },
+                _ => RelocTarget::OsFixup,
+            };
+            Ok(Reloc {
+                kind,
+                additive: rec[1] & 4 != 0,
+                offset: rec.get_pod::<u16>(2),
+                target,
+            })
+        })
+        .collect()
+}
+
+/// Offset of the NE header, if buf is an NE executable.
+pub fn ne_header_offset(buf: &[u8]) -> Option<u32> {
+    if buf.len() < 0x40 || &buf[..2] != b"MZ" {
+        return None;
+    }
+    let ofs = buf.get_pod::<DWORD>(0x3c);
+    match buf.get(ofs as usize..ofs as usize + 2)? {
+        b"NE" => Some(ofs),
+        _ => None,
+    }
+}
+
+/// Describe an NE executable, for the error when refusing to load it.
+pub fn unsupported(buf: &[u8]) -> anyhow::Error {
+    let ne = match parse(buf) {
+        Ok(ne) => ne,
+        Err(err) => return err.context("16-bit (NE) executable"),
+    };
+    let (major, minor) = ne.windows_version();
+    log::info!(
+        "{}: NE {}, Windows {major}.{minor}, {} segments, entry {:x?}, imports {}",
+        ne.name,
+        if ne.is_dll() { "dll" } else { "exe" },
+        ne.segments.len(),
+        ne.entry_point(),
+        ne.imports.join(", ")
+    );
+    if !ne.is_windows() {
+        return anyhow!("16-bit OS/2 executable, not Windows");
+    }
+    anyhow!(
+        "{}: 16-bit Windows (NE) executables need the x86-emu backend",
+        ne.name
+    )
+}
+
+pub fn parse(buf: &[u8]) -> anyhow::Result<File> {
+    let Some(ne_ofs) = ne_header_offset(buf) else {
+        bail!("not an NE executable");
+    };
+    let mut r = Reader::new(buf);
+    r.seek(ne_ofs)?;
+    if buf.len() < ne_ofs as usize + std::mem::size_of::<IMAGE_OS2_HEADER>() {
+        bail!("EOF in NE header");
+    }
+    let header = r.read::<IMAGE_OS2_HEADER>();
+
+    r.seek(ne_ofs + header.ne_segtab as u32)?;
+    let segments = r
+        .read_n::<NE_SEGMENT>(header.ne_cseg as u32)
+        .map_err(|err| anyhow!("reading segment table: {err}"))?;
+
+    let name = pascal_str(buf, (ne_ofs + header.ne_restab as u32) as usize)
+        .map_err(|err| anyhow!("reading module name: {err}"))?;
+
+    r.seek(ne_ofs + header.ne_modtab as u32)?;
+    let imports = r
+        .read_n::<WORD>(header.ne_cmod as u32)
+        .map_err(|err| anyhow!("reading module table: {err}"))?
+        .iter()
+        .map(|&ofs| {
+            pascal_str(
+                buf,
+                (ne_ofs + header.ne_imptab as u32 + ofs as u32) as usize,
+            )
+        })
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map_err(|err| anyhow!("reading imported names: {err}"))?;
+
+    let enttab = (ne_ofs + header.ne_enttab as u32) as usize;
+    let entries = buf
+        .get(enttab..enttab + header.ne_cbenttab as usize)
+        .ok_or_else(|| anyhow!("EOF"))
+        .and_then(parse_entries)
+        .map_err(|err| anyhow!("reading entry table: {err}"))?;
+
+    let mut file = File {
+        header,
+        name,
+        segments,
+        imports,
+        relocs: Vec::new(),
+    };
+    let imported_name = |ofs: u16| {
+        pascal_str(
+            buf,
+            (ne_ofs + file.header.ne_imptab as u32 + ofs as u32) as usize,
+        )
+    };
+    let relocs = (0..file.segments.len())
+        .map(|i| {
+            if !file.segments[i].has_relocs() {
+                return Ok(Vec::new());
+            }
+            parse_relocs(buf, file.segment_range(i).end, &entries, imported_name)
+                .map_err(|err| anyhow!("reading segment {} relocations: {err}", i + 1))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    file.relocs = relocs;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An NE with a code segment that calls KERNEL.91 and an entry point in a
+    /// movable segment, with the tables at the offsets from its header.
+    fn build() -> Vec<u8> {
+        let mut buf = vec![0u8; 0x140];
+        buf[..2].copy_from_slice(b"MZ");
+        buf[0x3c] = 0x40;
+        let ne = |buf: &mut Vec<u8>, ofs: usize, bytes: &[u8]| {
+            buf[0x40 + ofs..][..bytes.len()].copy_from_slice(bytes)
+        };
+        let words = |words: &[u16]| {
+            words
+                .iter()
+                .flat_map(|w| w.to_le_bytes())
+                .collect::<Vec<_>>()
+        };
+        ne(&mut buf, 0, b"NE");
+        ne(&mut buf, 0x04, &words(&[0x60, 8])); // entry table
+        ne(&mut buf, 0x1C, &words(&[2, 1])); // segments, modules
+        ne(&mut buf, 0x22, &words(&[0x40, 0x48, 0x48, 0x50, 0x52])); // table offsets
+        ne(&mut buf, 0x32, &words(&[4])); // alignment
+        ne(&mut buf, 0x36, &[NE_EXETYP_WINDOWS]);
+        // Segment 1, code at 0x100 with relocations; segment 2, empty data.
+        ne(&mut buf, 0x40, &words(&[0x10, 6, 0x100, 6, 0, 0, 1, 0x20]));
+        ne(&mut buf, 0x48, b"\x04TEST");
+        ne(&mut buf, 0x50, &words(&[1]));
+        ne(&mut buf, 0x52, b"\0\x06KERNEL");
+        // One movable entry, segment 2 offset 0x10.
+        ne(&mut buf, 0x60, &[1, 0xFF, 1, 0xCD, 0x3F, 2, 0x10, 0]);
+        // call far ptr, then the relocations.
+        buf[0x100..0x106].copy_from_slice(&[0x9A, 0xFF, 0xFF, 0, 0, 0xCB]);
+        buf[0x106..0x118].copy_from_slice(&words(&[2, 0x0103, 1, 1, 91, 0x0405, 0, 0xFF, 1]));
+        buf
+    }
+
+    #[test]
+    fn parse_relocs() {
+        let buf = build();
+        let ne = parse(&buf).unwrap();
+        assert_eq!(ne.name, "TEST");
+        assert_eq!(ne.imports, ["KERNEL"]);
+        assert_eq!(ne.segment_data(&buf, 0).unwrap()[0], 0x9A);
+        assert_eq!(ne.segments[1].alloc_size(), 0x20);
+        let relocs = &ne.relocs[0];
+        assert_eq!(relocs.len(), 2);
+        assert_eq!(relocs[0].kind, RelocKind::FarAddr);
+        assert!(!relocs[0].additive);
+        assert_eq!(relocs[0].offset, 1);
+        assert_eq!(
+            relocs[0].target,
+            RelocTarget::ImportOrdinal {
+                module: 1,
+                ordinal: 91
+            }
+        );
+        assert_eq!(relocs[1].kind, RelocKind::Offset);
+        assert!(relocs[1].additive);
+        assert_eq!(
+            relocs[1].target,
+            RelocTarget::Internal {
+                segment: 2,
+                offset: 0x10
+            }
+        );
+    }
+
+    #[test]
+    fn truncated_relocs() {
+        let mut buf = build();
+        buf.truncate(0x10A);
+        let err = parse(&buf).err().unwrap().to_string();
+        assert!(err.contains("segment 1 relocations"), "{err}");
+    }
+}
@@ -11,7 +11,15 @@ struct IMAGE_BASE_RELOCATION {
 }
 unsafe impl memory::Pod for IMAGE_BASE_RELOCATION {}
 
-pub fn apply_relocs(image: Mem, prev_base: u32, base: u32, mut relocs: &[u8]) {
+/// Calls progress with the count of bytes of relocations processed so far.
+pub fn apply_relocs(
+    image: Mem,
+    prev_base: u32,
+    base: u32,
+    mut relocs: &[u8],
+    mut progress: impl FnMut(u32, u32),
+) {
+    let total = relocs.len() as u32;
     // monolife.exe has no IMAGE_DIRECTORY_ENTRY::BASERELOC, but does
     // have a .reloc section that is invalid (?).
     // Note: IMAGE_SECTION_HEADER itself also has some relocation-related fields
@@ -37,5 +45,6 @@ pub fn apply_relocs(image: Mem, prev_base: u32, base: u32, mut relocs: &[u8]) {
             }
         }
         relocs = &relocs[reloc.SizeOfBlock as usize..];
+        progress(total - relocs.len() as u32, total);
     }
 }
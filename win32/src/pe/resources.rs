@@ -51,7 +51,10 @@ unsafe impl memory::Pod for IMAGE_RESOURCE_DIRECTORY_ENTRY {}
 #[repr(u32)]
 pub enum RT {
     BITMAP = 2,
+    ICON = 3,
     STRING = 6,
+    GROUP_ICON = 14,
+    VERSION = 16,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -99,12 +102,33 @@ struct IMAGE_RESOURCE_DATA_ENTRY {
 }
 unsafe impl memory::Pod for IMAGE_RESOURCE_DATA_ENTRY {}
 
-/// Look up a resource by its type/id values.
+/// Pick the entry of a resource's language directory that best matches `lang`,
+/// following the FindResource fallback order: the exact language, then the same
+/// primary language, then language-neutral, then US English, then whatever is first.
+fn pick_language(
+    section: &[u8],
+    entries: impl Iterator<Item = IMAGE_RESOURCE_DIRECTORY_ENTRY>,
+    lang: u16,
+) -> Option<IMAGE_RESOURCE_DIRECTORY_ENTRY> {
+    let primary = |id: u32| id & 0x3FF;
+    let rank = |entry: &IMAGE_RESOURCE_DIRECTORY_ENTRY| match entry.name(section) {
+        ResourceName::Id(id) if id == lang as u32 => 0,
+        ResourceName::Id(id) if primary(id) == primary(lang as u32) => 1,
+        ResourceName::Id(id) if primary(id) == 0 => 2,
+        ResourceName::Id(0x409) => 3,
+        _ => 4,
+    };
+    // min_by_key returns the first of equally-ranked entries.
+    entries.min_by_key(rank)
+}
+
+/// Look up a resource by its type/id values, preferring the given LANGID.
 /// Returns a the range within the image of the data.
 pub fn find_resource(
     section: &[u8],
     query_type: ResourceName,
     query_id: ResourceName,
+    lang: u16,
 ) -> Option<Range<u32>> {
     // Resources are structured as generic nested directories, but in practice there
     // are always exactly three levels with known semantics.
@@ -117,16 +141,13 @@ pub fn find_resource(
     };
 
     let eid = dir.find(|entry| entry.name(section) == query_id)?;
-    let mut dir = match eid.value(section) {
+    let dir = match eid.value(section) {
         ResourceValue::Dir(dir) => IMAGE_RESOURCE_DIRECTORY::entries(dir),
         _ => todo!(),
     };
 
-    let first = dir.next()?;
-    if dir.next().is_some() {
-        log::warn!("multiple res entries, picking first");
-    }
-    let data = match first.value(section) {
+    let entry = pick_language(section, dir, lang)?;
+    let data = match entry.value(section) {
         ResourceValue::Data(data) => data,
         _ => todo!(),
     };
@@ -0,0 +1,142 @@
+//! A sampling profiler for guest code, to see where a slow program spends its time:
+//! in emulated code, in particular shims (like presentation through ddraw), or
+//! waiting on the host.
+//!
+//! Run the machine through Profiler::run instead of Machine::run.  The host time
+//! each step takes is charged to the guest stack the step started in, as one
+//! sample per interval of time; stacks are EIP plus the callers found by walking
+//! the EBP chain, so frames of code built without frame pointers go missing.
+//! Results are written as collapsed stacks ("outer;inner;leaf count" lines), as
+//! inferno-flamegraph and speedscope read.  See also backtrace.rs.
+//!
+//! Frames are named by the symbols loaded with --symbols where possible, so
+//! without them each distinct address is its own frame, as "image+rva".
+
+use crate::Machine;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Frame {
+    Code(u32),
+    /// The machine waiting for the host, as on GetMessage or Sleep.
+    Blocked,
+    /// Running async shims, like those calling back into guest code.
+    Async,
+}
+
+/// Registers at the start of a step, to find its stack from should it be sampled.
+struct Regs {
+    eip: u32,
+    esp: u32,
+    ebp: u32,
+    blocked: bool,
+}
+
+pub struct Profiler {
+    interval: Duration,
+    /// Time run since the last sample.
+    pending: Duration,
+    /// Sample counts by stack, innermost frame first.
+    stacks: HashMap<Vec<Frame>, u64>,
+}
+
+impl Profiler {
+    pub fn new(interval: Duration) -> Self {
+        Profiler {
+            interval,
+            pending: Duration::ZERO,
+            stacks: HashMap::new(),
+        }
+    }
+
+    /// Like Machine::run, timing the step.
+    pub fn run(&mut self, machine: &mut Machine) -> bool {
+        let cpu = machine.emu.x86.cpu();
+        let regs = Regs {
+            eip: cpu.regs.eip,
+            esp: cpu.regs.get32(x86::Register::ESP),
+            ebp: cpu.regs.get32(x86::Register::EBP),
+            blocked: matches!(cpu.state, x86::CPUState::Blocked(_)),
+        };
+        let start = Instant::now();
+        let running = machine.run();
+        self.pending += start.elapsed();
+        if self.pending >= self.interval {
+            let samples = (self.pending.as_nanos() / self.interval.as_nanos()) as u64;
+            self.pending -= self.interval * samples as u32;
+            // The walk reads memory as the step left it, which still holds the frames
+            // above the step's own, as a single block or shim call can't unwind them.
+            let stack = walk(machine, &regs);
+            *self.stacks.entry(stack).or_default() += samples;
+        }
+        running
+    }
+
+    /// Render the samples as collapsed stacks, outermost frame first.
+    pub fn collapsed(&self, machine: &Machine) -> String {
+        let mut names = HashMap::new();
+        let mut lines = self
+            .stacks
+            .iter()
+            .map(|(stack, count)| {
+                let frames = stack
+                    .iter()
+                    .rev()
+                    .map(|&frame| {
+                        names
+                            .entry(frame)
+                            .or_insert_with(|| frame_name(machine, frame))
+                            .clone()
+                    })
+                    .collect::<Vec<_>>();
+                format!("{} {count}", frames.join(";"))
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        let mut out = lines.join("\n");
+        out.push('\n');
+        out
+    }
+}
+
+fn walk(machine: &Machine, regs: &Regs) -> Vec<Frame> {
+    let mut stack = Vec::new();
+    if regs.blocked {
+        stack.push(Frame::Blocked);
+    }
+    if regs.eip == x86::MAGIC_ADDR {
+        stack.push(Frame::Async);
+        return stack;
+    }
+    let frames = crate::backtrace::walk(machine.mem(), regs.eip, regs.esp, regs.ebp);
+    stack.extend(frames.into_iter().map(Frame::Code));
+    stack
+}
+
+fn frame_name(machine: &Machine, frame: Frame) -> String {
+    let addr = match frame {
+        Frame::Blocked => return "[blocked]".into(),
+        Frame::Async => return "[async]".into(),
+        Frame::Code(addr) => addr,
+    };
+    let name = if let Some((name, _)) = machine.symbols.lookup(addr) {
+        name.to_string()
+    } else if let Some(label) = machine.labels.get(&addr) {
+        label.clone()
+    } else if let Some(image) = machine
+        .state
+        .kernel32
+        .images
+        .iter()
+        .find(|image| addr >= image.base && addr - image.base < image.size)
+    {
+        format!("{}+{:#x}", image.name, addr - image.base)
+    } else {
+        format!("{addr:#x}")
+    };
+    // Semicolons separate frames in the output.
+    name.replace(';', ":")
+}
@@ -0,0 +1,295 @@
+//! Recording a run's inputs from the host, and replaying them for an identical run,
+//! for reproducing bugs and for regression tests.
+//!
+//! Emulation is deterministic except for what the host provides: the clock, input
+//! messages and game controller state, random bytes, the audio device's progress,
+//! whether and how long waits block, and files the user picks.  Recording wraps the
+//! real host, logging each of these as the guest gets them; replaying wraps it too,
+//! handing back the logged values instead, so the guest sees the same inputs at the
+//! same points and so does the same things.  Replays don't wait in real time.
+//!
+//! The log is a file of JSON lines, one per input.  A replay that runs past the end
+//! of its log, or finds a different kind of input logged than the one asked for
+//! (as when replaying against a different exe or settings), warns and carries on
+//! with live input.
+
+use crate::host::{self, Host};
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    io::{BufRead, Write},
+    rc::Rc,
+};
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+enum Event {
+    Time(u32),
+    Message(Option<host::Message>),
+    Block(bool),
+    Random(Vec<u8>),
+    Gamepad(Option<host::GamepadState>),
+    PickFile(Option<String>),
+    /// Whether create_audio returned a stream.
+    Audio(bool),
+    AudioQueued(u32),
+}
+
+enum Tape {
+    Record(RefCell<std::io::BufWriter<std::fs::File>>),
+    Replay {
+        events: RefCell<VecDeque<Event>>,
+        /// Count of events replayed, for reporting divergence.
+        count: Cell<usize>,
+        /// Set once the replay ends or diverges, after which input is live.
+        live: Cell<bool>,
+    },
+}
+
+impl Tape {
+    fn record(&self, event: &Event) {
+        let Tape::Record(out) = self else {
+            unreachable!()
+        };
+        let mut out = out.borrow_mut();
+        serde_json::to_writer(&mut *out, event).unwrap();
+        out.write_all(b"\n").unwrap();
+    }
+
+    fn next(&self) -> Option<Event> {
+        let Tape::Replay {
+            events,
+            count,
+            live,
+        } = self
+        else {
+            unreachable!()
+        };
+        if live.get() {
+            return None;
+        }
+        count.set(count.get() + 1);
+        events.borrow_mut().pop_front()
+    }
+
+    /// Note the replay didn't have the input wanted, and switch to live input.
+    fn diverge(&self, wanted: &str, got: Option<Event>) {
+        let Tape::Replay { count, live, .. } = self else {
+            unreachable!()
+        };
+        if live.replace(true) {
+            return;
+        }
+        match got {
+            None => log::warn!("replay: ended after {} inputs, continuing live", count.get() - 1),
+            Some(got) => log::warn!(
+                "replay: diverged at input {}, wanted {wanted} but recorded {got:?}; continuing live",
+                count.get()
+            ),
+        }
+    }
+
+    fn flush(&self) {
+        if let Tape::Record(out) = self {
+            out.borrow_mut().flush().unwrap();
+        }
+    }
+}
+
+/// Get an input, as Event::$variant, from $live when recording (logging it) or
+/// from the log when replaying.
+macro_rules! taped {
+    ($tape:expr, $variant:ident, $live:expr) => {{
+        let tape: &Tape = $tape;
+        match tape {
+            Tape::Record(_) => {
+                let event = Event::$variant($live);
+                tape.record(&event);
+                let Event::$variant(value) = event else {
+                    unreachable!()
+                };
+                value
+            }
+            Tape::Replay { .. } => match tape.next() {
+                Some(Event::$variant(value)) => value,
+                got => {
+                    tape.diverge(stringify!($variant), got);
+                    $live
+                }
+            },
+        }
+    }};
+}
+
+/// A Host that records or replays the inputs of the host it wraps.
+pub struct ReplayHost {
+    inner: Box<dyn Host>,
+    tape: Rc<Tape>,
+}
+
+impl ReplayHost {
+    /// Record the inputs from inner to the file at path.
+    pub fn record(inner: Box<dyn Host>, path: &str) -> anyhow::Result<Self> {
+        let file = std::fs::File::create(path).map_err(|err| anyhow::anyhow!("{path}: {err}"))?;
+        Ok(ReplayHost {
+            inner,
+            tape: Rc::new(Tape::Record(RefCell::new(std::io::BufWriter::new(file)))),
+        })
+    }
+
+    /// Replay the inputs recorded in the file at path, in place of those from inner.
+    pub fn replay(inner: Box<dyn Host>, path: &str) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path).map_err(|err| anyhow::anyhow!("{path}: {err}"))?;
+        let mut events = VecDeque::new();
+        for (i, line) in std::io::BufReader::new(file).lines().enumerate() {
+            let event = serde_json::from_str(&line?)
+                .map_err(|err| anyhow::anyhow!("{path}:{}: {err}", i + 1))?;
+            events.push_back(event);
+        }
+        Ok(ReplayHost {
+            inner,
+            tape: Rc::new(Tape::Replay {
+                events: RefCell::new(events),
+                count: Cell::new(0),
+                live: Cell::new(false),
+            }),
+        })
+    }
+}
+
+impl Host for ReplayHost {
+    fn exit(&self, code: u32) {
+        self.tape.flush();
+        self.inner.exit(code)
+    }
+
+    fn time(&self) -> u32 {
+        taped!(&self.tape, Time, self.inner.time())
+    }
+
+    fn get_message(&self) -> Option<host::Message> {
+        taped!(&self.tape, Message, self.inner.get_message())
+    }
+
+    fn block(&self, wait: Option<u32>) -> bool {
+        taped!(&self.tape, Block, self.inner.block(wait))
+    }
+
+    fn progress(&self, task: &str, done: u32, total: u32) {
+        self.inner.progress(task, done, total)
+    }
+
+    fn guest_message(&self, channel: &str, payload: &str) {
+        self.inner.guest_message(channel, payload)
+    }
+
+    fn open(&self, path: &str) -> Box<dyn host::File> {
+        self.inner.open(path)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.inner.exists(path)
+    }
+
+    fn write(&self, buf: &[u8]) -> usize {
+        self.inner.write(buf)
+    }
+
+    fn open_port(&self, name: &str) -> Option<Box<dyn host::Port>> {
+        self.inner.open_port(name)
+    }
+
+    fn socket(&self, kind: host::SocketKind) -> std::io::Result<Box<dyn host::Socket>> {
+        self.inner.socket(kind)
+    }
+
+    fn resolve(&self, name: &str) -> std::io::Result<Vec<std::net::Ipv4Addr>> {
+        self.inner.resolve(name)
+    }
+
+    fn random(&self, buf: &mut [u8]) {
+        let bytes = taped!(&self.tape, Random, {
+            self.inner.random(buf);
+            buf.to_vec()
+        });
+        if bytes.len() == buf.len() {
+            buf.copy_from_slice(&bytes);
+        } else {
+            self.tape.diverge("Random", Some(Event::Random(bytes)));
+            self.inner.random(buf);
+        }
+    }
+
+    fn shell_open(&self, target: &str) -> bool {
+        self.inner.shell_open(target)
+    }
+
+    fn launch(&self, exe: &str, cmdline: &str, dir: Option<&str>) -> bool {
+        self.inner.launch(exe, cmdline, dir)
+    }
+
+    fn pick_file(&self, dialog: &host::FileDialog) -> Option<String> {
+        taped!(&self.tape, PickFile, self.inner.pick_file(dialog))
+    }
+
+    fn create_window(&mut self, hwnd: u32) -> Box<dyn host::Window> {
+        self.inner.create_window(hwnd)
+    }
+
+    fn create_surface(&mut self, opts: &host::SurfaceOptions) -> Box<dyn host::Surface> {
+        self.inner.create_surface(opts)
+    }
+
+    fn create_audio(&mut self, sample_rate: u32) -> Option<Box<dyn host::Audio>> {
+        let mut inner = None;
+        let created = taped!(&self.tape, Audio, {
+            inner = self.inner.create_audio(sample_rate);
+            inner.is_some()
+        });
+        if !created {
+            return None;
+        }
+        // Replaying, the host may not have audio though the recording did.
+        let inner = inner
+            .or_else(|| self.inner.create_audio(sample_rate))
+            .unwrap_or_else(|| Box::new(NoAudio));
+        Some(Box::new(TapedAudio {
+            inner,
+            tape: self.tape.clone(),
+        }))
+    }
+
+    fn gamepad(&self, id: u32) -> Option<host::GamepadState> {
+        taped!(&self.tape, Gamepad, self.inner.gamepad(id))
+    }
+
+    fn grab_mouse(&self, grab: bool) {
+        self.inner.grab_mouse(grab)
+    }
+}
+
+/// Audio whose clock, queued(), is recorded or replayed.
+struct TapedAudio {
+    inner: Box<dyn host::Audio>,
+    tape: Rc<Tape>,
+}
+
+impl host::Audio for TapedAudio {
+    fn write(&mut self, samples: &[i16]) {
+        self.inner.write(samples)
+    }
+
+    fn queued(&self) -> u32 {
+        taped!(&self.tape, AudioQueued, self.inner.queued())
+    }
+}
+
+/// Stands in for audio recorded but missing on replay; only its clock matters.
+struct NoAudio;
+
+impl host::Audio for NoAudio {
+    fn write(&mut self, _samples: &[i16]) {}
+
+    fn queued(&self) -> u32 {
+        0
+    }
+}
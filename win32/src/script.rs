@@ -0,0 +1,435 @@
+//! Scripted hooks, for prototyping workarounds for a program without rebuilding:
+//! a Rhai script given with --script can intercept calls to the Windows API and the
+//! execution of guest addresses.
+//!
+//! The script runs once after the exe is loaded, registering its hooks:
+//!
+//!   // Every drive is a CD drive.
+//!   on_call("GetDriveTypeA", |args| 5);
+//!   // Log a function's results, leaving them alone.
+//!   on_return("kernel32.dll:CreateFileA", |args, ret| {
+//!       print(`CreateFileA(${read_str(args[0])}) => ${ret}`);
+//!       ret
+//!   });
+//!   // Make the program's CD check, a function at 0x401234, succeed.
+//!   on_exec(0x401234, || ret(1));
+//!   // Find what keeps zeroing a variable.
+//!   on_watch("4a1f00+4:w", |hit| if read32(0x4a1f00) == 0 { print(`zeroed at ${hit.ip}`) });
+//!
+//! API hooks match by function name, or by "dll:name" to pick one DLL's.  on_call
+//! hooks get the arguments, as an array of the stack words the function pops, and
+//! return () to let the call go ahead or a value to return in its place.  on_return
+//! hooks get the arguments and the result, and return the result to use; they don't
+//! fire for async functions (those calling back into guest code).
+//!
+//! on_exec hooks run before the instruction at their address, which is patched with
+//! an int3 like a debugger breakpoint.
+//!
+//! on_watch hooks run after an instruction accesses the memory they watch, given as
+//! for --watch ("ADDR[+LEN][:r|w|rw]", see x86::watch).  They get the access, as a
+//! map with the accessing instruction's address (ip), the memory accessed (addr and
+//! len) and whether it was a write (write).
+//!
+//! Hooks, and the script's top level, can use:
+//!   reg(name), set_reg(name, value)    registers, like "eax" or "eip"
+//!   read32(addr), write32(addr, value) memory
+//!   read_str(addr)                     a NUL-terminated string
+//!   ret(value), ret(value, pop)        return from the function just entered,
+//!                                      popping pop bytes of (stdcall) arguments
+//! Errors in hooks are logged and otherwise ignored.
+
+use crate::{shims::Shim, Machine};
+use memory::{Extensions, Mem};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FnPtr, Map, AST, INT};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+
+type Result<T> = std::result::Result<T, Box<EvalAltResult>>;
+
+struct ApiHook {
+    /// The DLL the hook is limited to, if any.
+    dll: Option<String>,
+    func: FnPtr,
+}
+
+#[derive(Default)]
+struct Hooks {
+    /// By function name.
+    calls: HashMap<String, ApiHook>,
+    returns: HashMap<String, ApiHook>,
+    execs: HashMap<u32, FnPtr>,
+    watches: Vec<(x86::watch::Watchpoint, FnPtr)>,
+}
+
+struct Script {
+    engine: Engine,
+    ast: AST,
+    hooks: Rc<RefCell<Hooks>>,
+    /// An on_exec address whose breakpoint is lifted to run the instruction under it.
+    rearm: Option<u32>,
+    /// on_watch hits that paused the CPU, whose hooks are yet to run.
+    watch_hits: Rc<RefCell<Vec<(FnPtr, x86::watch::Hit)>>>,
+}
+
+thread_local! {
+    static SCRIPT: RefCell<Option<Script>> = const { RefCell::new(None) };
+    /// The machine, while the script runs.
+    static MACHINE: Cell<*mut Machine> = const { Cell::new(std::ptr::null_mut()) };
+}
+
+fn with_machine<T>(f: impl FnOnce(&mut Machine) -> T) -> Result<T> {
+    let machine = MACHINE.get();
+    if machine.is_null() {
+        return Err("no machine".into());
+    }
+    Ok(f(unsafe { &mut *machine }))
+}
+
+/// Run f with the script's functions acting on machine.
+fn enter<T>(machine: &mut Machine, f: impl FnOnce() -> T) -> T {
+    let prev = MACHINE.replace(machine);
+    let ret = f();
+    MACHINE.set(prev);
+    ret
+}
+
+fn register(name: &str) -> Result<x86::Register> {
+    use x86::Register::*;
+    Ok(match name.to_ascii_lowercase().as_str() {
+        "eax" => EAX,
+        "ebx" => EBX,
+        "ecx" => ECX,
+        "edx" => EDX,
+        "esi" => ESI,
+        "edi" => EDI,
+        "esp" => ESP,
+        "ebp" => EBP,
+        _ => return Err(format!("unknown register {name:?}").into()),
+    })
+}
+
+fn get_reg(machine: &Machine, name: &str) -> Result<u32> {
+    let regs = &machine.emu.x86.cpu().regs;
+    if name.eq_ignore_ascii_case("eip") {
+        return Ok(regs.eip);
+    }
+    Ok(regs.get32(register(name)?))
+}
+
+fn set_reg(machine: &mut Machine, name: &str, value: u32) -> Result<()> {
+    let regs = &mut machine.emu.x86.cpu_mut().regs;
+    if name.eq_ignore_ascii_case("eip") {
+        regs.eip = value;
+    } else {
+        regs.set32(register(name)?, value);
+    }
+    Ok(())
+}
+
+fn check(mem: Mem, addr: u32, len: u32) -> Result<()> {
+    if !mem.is_committed(addr, len) {
+        return Err(format!("{addr:#x} isn't mapped").into());
+    }
+    Ok(())
+}
+
+fn read32(addr: INT) -> Result<INT> {
+    with_machine(|machine| {
+        let mem = machine.mem();
+        check(mem, addr as u32, 4)?;
+        Ok(mem.get_pod::<u32>(addr as u32) as INT)
+    })?
+}
+
+fn write32(addr: INT, value: INT) -> Result<()> {
+    with_machine(|machine| {
+        let addr = addr as u32;
+        check(machine.mem(), addr, 4)?;
+        machine.mem().put::<u32>(addr, value as u32);
+        machine.emu.x86.invalidate_code(addr, 4);
+        Ok(())
+    })?
+}
+
+fn read_str(addr: INT) -> Result<String> {
+    with_machine(|machine| {
+        let mem = machine.mem();
+        check(mem, addr as u32, 1)?;
+        Ok(String::from_utf8_lossy(mem.slicez(addr as u32)).into_owned())
+    })?
+}
+
+/// Return from a function on its first instruction, as its ret would.
+fn ret(value: INT, pop: INT) -> Result<()> {
+    with_machine(|machine| {
+        let esp = get_reg(machine, "esp")?;
+        check(machine.mem(), esp, 4)?;
+        let addr = machine.mem().get_pod::<u32>(esp);
+        set_reg(machine, "eip", addr)?;
+        set_reg(machine, "esp", esp + 4 + pop as u32)?;
+        set_reg(machine, "eax", value as u32)
+    })?
+}
+
+/// Parse an API hook's "dll:name" or "name".
+fn api_hook(name: &str, func: FnPtr) -> (String, ApiHook) {
+    let (dll, name) = match name.split_once(':') {
+        Some((dll, name)) => (Some(dll.to_ascii_lowercase()), name),
+        None => (None, name),
+    };
+    (name.to_string(), ApiHook { dll, func })
+}
+
+fn engine(hooks: &Rc<RefCell<Hooks>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.on_print(|text| log::info!("script: {text}"));
+    engine.on_debug(|text, _, pos| log::info!("script {pos}: {text}"));
+
+    let h = hooks.clone();
+    engine.register_fn("on_call", move |name: &str, func: FnPtr| {
+        let (name, hook) = api_hook(name, func);
+        h.borrow_mut().calls.insert(name, hook);
+    });
+    let h = hooks.clone();
+    engine.register_fn("on_return", move |name: &str, func: FnPtr| {
+        let (name, hook) = api_hook(name, func);
+        h.borrow_mut().returns.insert(name, hook);
+    });
+    let h = hooks.clone();
+    engine.register_fn("on_exec", move |addr: INT, func: FnPtr| {
+        h.borrow_mut().execs.insert(addr as u32, func);
+    });
+    let h = hooks.clone();
+    engine.register_fn("on_watch", move |spec: &str, func: FnPtr| -> Result<()> {
+        let watch = spec.parse::<x86::watch::Watchpoint>()?;
+        h.borrow_mut().watches.push((watch, func));
+        Ok(())
+    });
+
+    engine.register_fn("reg", |name: &str| -> Result<INT> {
+        with_machine(|machine| get_reg(machine, name).map(|v| v as INT))?
+    });
+    engine.register_fn("set_reg", |name: &str, value: INT| -> Result<()> {
+        with_machine(|machine| set_reg(machine, name, value as u32))?
+    });
+    engine.register_fn("read32", read32);
+    engine.register_fn("write32", write32);
+    engine.register_fn("read_str", read_str);
+    engine.register_fn("ret", |value: INT| ret(value, 0));
+    engine.register_fn("ret", ret);
+    engine
+}
+
+/// Load the script at path, running it to register its hooks.
+pub fn load(machine: &mut Machine, path: &str) -> anyhow::Result<()> {
+    let source = std::fs::read_to_string(path).map_err(|err| anyhow::anyhow!("{path}: {err}"))?;
+    load_source(machine, path, &source)
+}
+
+/// Load a script from its source, with `path` naming it in messages.
+pub fn load_source(machine: &mut Machine, path: &str, source: &str) -> anyhow::Result<()> {
+    let hooks = Rc::new(RefCell::new(Hooks::default()));
+    let engine = engine(&hooks);
+    let ast = engine
+        .compile(source)
+        .map_err(|err| anyhow::anyhow!("{path}: {err}"))?;
+    enter(machine, || engine.run_ast(&ast)).map_err(|err| anyhow::anyhow!("{path}: {err}"))?;
+
+    let mem = machine.emu.memory.mem();
+    for &addr in hooks.borrow().execs.keys() {
+        if !mem.is_committed(addr, 1) {
+            anyhow::bail!("{path}: on_exec address {addr:#x} isn't mapped");
+        }
+        machine.emu.x86.add_breakpoint(mem, addr);
+    }
+    // Hits pause the CPU, so the hooks can run with the machine in on_breakpoint.
+    let watch_hits = Rc::new(RefCell::new(Vec::new()));
+    for (watch, func) in hooks.borrow().watches.iter() {
+        let (hits, func) = (watch_hits.clone(), func.clone());
+        machine.emu.x86.watchpoints.add(
+            *watch,
+            Some(Box::new(move |_cpu, hit| {
+                hits.borrow_mut().push((func.clone(), *hit));
+                true
+            })),
+        );
+    }
+    {
+        let hooks = hooks.borrow();
+        log::info!(
+            "{path}: hooked {} calls, {} returns, {} addresses, {} watches",
+            hooks.calls.len(),
+            hooks.returns.len(),
+            hooks.execs.len(),
+            hooks.watches.len()
+        );
+    }
+    SCRIPT.set(Some(Script {
+        engine,
+        ast,
+        hooks,
+        rearm: None,
+        watch_hits,
+    }));
+    Ok(())
+}
+
+/// Call a hook, logging any error.
+fn call(machine: &mut Machine, what: &str, func: &FnPtr, args: impl rhai::FuncArgs) -> Dynamic {
+    let result = enter(machine, || {
+        SCRIPT.with_borrow(|script| {
+            let script = script.as_ref().unwrap();
+            func.call::<Dynamic>(&script.engine, &script.ast, args)
+        })
+    });
+    result.unwrap_or_else(|err| {
+        log::error!("script: {what}: {err}");
+        Dynamic::UNIT
+    })
+}
+
+fn as_u32(what: &str, value: Dynamic) -> Option<u32> {
+    if let Ok(value) = value.as_int() {
+        Some(value as u32)
+    } else if let Ok(value) = value.as_bool() {
+        Some(value as u32)
+    } else {
+        if !value.is_unit() {
+            log::error!(
+                "script: {what}: expected a number, got {}",
+                value.type_name()
+            );
+        }
+        None
+    }
+}
+
+/// The on_call and on_return hooks of a shim.
+pub struct ApiHooks {
+    name: &'static str,
+    args: u32,
+    call: Option<FnPtr>,
+    ret: Option<FnPtr>,
+}
+
+/// Find the hooks for a call to shim, if a script hooks it.
+pub fn api_hooks(shim: &Shim) -> Option<ApiHooks> {
+    SCRIPT.with_borrow(|script| {
+        let hooks = script.as_ref()?.hooks.borrow();
+        let find = |map: &HashMap<String, ApiHook>| {
+            let hook = map.get(shim.name)?;
+            if let Some(dll) = &hook.dll {
+                if shim.dll() != Some(dll.as_str()) {
+                    return None;
+                }
+            }
+            Some(hook.func.clone())
+        };
+        let call = find(&hooks.calls);
+        let ret = find(&hooks.returns);
+        if call.is_none() && ret.is_none() {
+            return None;
+        }
+        Some(ApiHooks {
+            name: shim.name,
+            args: shim.stack_consumed / 4,
+            call,
+            ret,
+        })
+    })
+}
+
+impl ApiHooks {
+    fn args(&self, machine: &Machine, esp: u32) -> Array {
+        machine
+            .mem()
+            .iter_pod::<u32>(esp + 4, self.args)
+            .map(|arg| Dynamic::from_int(arg as INT))
+            .collect()
+    }
+
+    /// Run the on_call hook, returning the value to return in place of the call.
+    pub fn call(&self, machine: &mut Machine, esp: u32) -> Option<u32> {
+        let func = self.call.as_ref()?;
+        let args = self.args(machine, esp);
+        let ret = call(machine, self.name, func, (args,));
+        as_u32(self.name, ret)
+    }
+
+    /// Run the on_return hook, returning the result to use.
+    pub fn ret(&self, machine: &mut Machine, esp: u32, ret: u32) -> u32 {
+        let Some(func) = &self.ret else {
+            return ret;
+        };
+        let args = self.args(machine, esp);
+        let value = call(machine, self.name, func, (args, ret as INT));
+        as_u32(self.name, value).unwrap_or(ret)
+    }
+}
+
+/// Handle a stop at a breakpoint, running the on_exec hook if it's one of ours, or
+/// at a watchpoint, running the on_watch hooks it hit.
+/// Returns false if it's neither, as for the debugger's breakpoints.
+pub fn on_breakpoint(machine: &mut Machine) -> bool {
+    let hits = SCRIPT.with_borrow(|script| match script {
+        Some(script) => std::mem::take(&mut *script.watch_hits.borrow_mut()),
+        None => Vec::new(),
+    });
+    if !hits.is_empty() {
+        // The access has completed, so carry on from the next instruction.
+        machine.emu.x86.cpu_mut().state = x86::CPUState::Running;
+        for (func, hit) in hits {
+            let mut map = Map::new();
+            map.insert("ip".into(), Dynamic::from_int(hit.ip as INT));
+            map.insert("addr".into(), Dynamic::from_int(hit.addr as INT));
+            map.insert("len".into(), Dynamic::from_int(hit.len as INT));
+            map.insert("write".into(), Dynamic::from_bool(hit.write));
+            call(
+                machine,
+                &format!("watch {:#x}", hit.watch.addr),
+                &func,
+                (map,),
+            );
+        }
+        return true;
+    }
+
+    let addr = machine.emu.x86.cpu().regs.eip;
+    let Some(func) = SCRIPT.with_borrow(|script| {
+        let hooks = script.as_ref()?.hooks.borrow();
+        hooks.execs.get(&addr).cloned()
+    }) else {
+        return false;
+    };
+    machine.emu.x86.cpu_mut().state = x86::CPUState::Running;
+    let mem = machine.emu.memory.mem();
+    machine.emu.x86.clear_breakpoint(mem, addr);
+    call(machine, &format!("{addr:#x}"), &func, ());
+    let mem = machine.emu.memory.mem();
+    if machine.emu.x86.cpu().regs.eip == addr {
+        // Run the instruction under the breakpoint before patching it back in.
+        machine.emu.x86.single_step_next_block(mem);
+        SCRIPT.with_borrow_mut(|script| script.as_mut().unwrap().rearm = Some(addr));
+    } else {
+        machine.emu.x86.add_breakpoint(mem, addr);
+    }
+    true
+}
+
+/// Patch back an on_exec breakpoint, after running a block starting at eip.
+pub fn rearm(machine: &mut Machine, eip: u32) {
+    let rearm = SCRIPT.with_borrow_mut(|script| {
+        let script = script.as_mut()?;
+        if script.rearm != Some(eip) {
+            return None;
+        }
+        script.rearm.take()
+    });
+    if let Some(addr) = rearm {
+        let mem = machine.emu.memory.mem();
+        machine.emu.x86.add_breakpoint(mem, addr);
+    }
+}
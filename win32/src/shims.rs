@@ -26,15 +26,31 @@ pub struct Shim {
     pub is_async: bool,
 }
 
-pub struct UnimplFuture {}
+impl Shim {
+    /// The file name of the builtin DLL exporting this shim, if any.  Shims are
+    /// copied around, so they're matched by function.
+    pub fn dll(&self) -> Option<&'static str> {
+        crate::winapi::DLLS
+            .iter()
+            .find(|dll| {
+                dll.exports
+                    .iter()
+                    .any(|sym| sym.shim.func as usize == self.func as usize)
+            })
+            .map(|dll| dll.file_name)
+    }
+}
+
+/// The result of a call into x86 code that ran synchronously: its return value, from EAX.
+pub struct UnimplFuture(pub u32);
 impl std::future::Future for UnimplFuture {
-    type Output = ();
+    type Output = u32;
 
     fn poll(
         self: std::pin::Pin<&mut Self>,
         _cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Self::Output> {
-        std::task::Poll::Ready(())
+        std::task::Poll::Ready(self.0)
     }
 }
 
@@ -55,6 +55,9 @@ pub struct Shims {
 impl Shims {
     /// Returns the (fake) address of the registered function.
     pub fn add(&mut self, shim: Result<&'static Shim, String>) -> u32 {
+        if let Err(name) = &shim {
+            crate::api_stats::missing(name);
+        }
         let id = SHIM_BASE | self.shims.len() as u32;
         self.shims.push(shim);
         id
@@ -68,17 +71,78 @@ impl Shims {
             None => panic!("unknown import reference at {:x}", addr),
         }
     }
+
+    /// Record the shims by name, for snapshots, as the guest holds their addresses.
+    pub fn save(&self) -> Vec<ShimRecord> {
+        self.shims
+            .iter()
+            .map(|shim| match shim {
+                Ok(shim) => ShimRecord::Found {
+                    dll: shim.dll().map(str::to_string),
+                    name: shim.name.to_string(),
+                },
+                Err(name) => ShimRecord::Missing(name.clone()),
+            })
+            .collect()
+    }
+
+    /// Replace the shims with those recorded in a snapshot, under the same addresses.
+    /// DLL exports are found by name; others, like COM methods, can only be matched
+    /// against the shim this session registered at the same address, and are
+    /// reported as missing when called if that doesn't match.
+    pub fn restore(&mut self, records: Vec<ShimRecord>) {
+        let live = std::mem::take(&mut self.shims);
+        self.shims = records
+            .into_iter()
+            .enumerate()
+            .map(|(i, record)| match record {
+                ShimRecord::Missing(name) => Err(name),
+                ShimRecord::Found { dll, name } => {
+                    if let Some(Ok(shim)) = live.get(i) {
+                        if shim.name == name {
+                            return Ok(*shim);
+                        }
+                    }
+                    dll.as_deref()
+                        .and_then(|dll| find_export(dll, &name))
+                        .ok_or_else(|| format!("{name} (not restored from snapshot)"))
+                }
+            })
+            .collect();
+    }
+}
+
+/// A shim as recorded in a snapshot.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub enum ShimRecord {
+    Found { dll: Option<String>, name: String },
+    Missing(String),
+}
+
+fn find_export(dll: &str, name: &str) -> Option<&'static Shim> {
+    let dll = crate::winapi::DLLS.iter().find(|d| d.file_name == dll)?;
+    dll.exports
+        .iter()
+        .find(|sym| sym.shim.name == name)
+        .map(|sym| &sym.shim)
+}
+
+pub fn is_shim_addr(addr: u32) -> bool {
+    addr & 0xFFFF_0000 == SHIM_BASE
 }
 
 pub fn is_eip_at_shim_call(machine: &mut Machine) -> bool {
-    machine.emu.x86.cpu().regs.eip & 0xFFFF_0000 == SHIM_BASE
+    is_shim_addr(machine.emu.x86.cpu().regs.eip)
 }
 
 pub fn handle_shim_call(machine: &mut Machine) {
     let regs = &mut machine.emu.x86.cpu_mut().regs;
     let shim = match machine.emu.shims.get(regs.eip) {
         Ok(shim) => shim,
-        Err(name) => unimplemented!("{}", name),
+        Err(name) => {
+            crate::api_stats::missing_call(name);
+            unimplemented!("{}", name)
+        }
     };
     let crate::shims::Shim {
         func,
@@ -87,7 +151,34 @@ pub fn handle_shim_call(machine: &mut Machine) {
         ..
     } = *shim;
     let esp = regs.get32(x86::Register::ESP);
-    let ret = unsafe { func(machine, esp) };
+    let thread = machine.emu.x86.cur_cpu as u32;
+    let relay = crate::trace::relay_call(thread, shim, machine.emu.memory.mem(), esp);
+    #[cfg(feature = "script")]
+    let hooks = crate::script::api_hooks(shim);
+    let call = crate::api_stats::begin(shim);
+    #[cfg(feature = "script")]
+    let replaced = hooks.as_ref().and_then(|hooks| hooks.call(machine, esp));
+    #[cfg(not(feature = "script"))]
+    let replaced = None;
+    let ret = match replaced {
+        Some(ret) => ret,
+        None => unsafe { func(machine, esp) },
+    };
+    // A call the script replaced returns like any synchronous one.
+    let is_async = is_async && replaced.is_none();
+    #[cfg(feature = "script")]
+    let ret = match &hooks {
+        Some(hooks) if !is_async => hooks.ret(machine, esp, ret),
+        _ => ret,
+    };
+    crate::api_stats::end(call);
+    if let Some(relay) = relay {
+        // ExitProcess and the like don't return.
+        let exited = matches!(machine.emu.x86.cpu().state, x86::CPUState::Exit(_));
+        if !is_async && !exited {
+            relay.log_return(ret);
+        }
+    }
     if !is_async {
         let regs = &mut machine.emu.x86.cpu_mut().regs;
         regs.eip = machine
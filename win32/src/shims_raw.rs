@@ -213,6 +213,7 @@ pub fn call_x86(machine: &mut Machine, func: u32, args: Vec<u32>) -> UnimplFutur
         }
         STACK32 = esp;
 
+        let ret: u32;
         std::arch::asm!(
             // We need to back up all non-scratch registers (rbx/rbp),
             // because even callee-saved registers will only be saved as 32-bit,
@@ -239,7 +240,7 @@ pub fn call_x86(machine: &mut Machine, func: u32, args: Vec<u32>) -> UnimplFutur
 
             // We try to clear all registers so that traces line up across invocations,
             // so mark each one as clobbered and use them above explicitly.
-            inout("eax") func => _,  // passed to tramp32
+            inout("eax") func => ret,  // passed to tramp32, and the result
             inout("ecx") return_addr as u32 => _,
             // ebx is preserved/restored
             inout("edx") 0 => _,
@@ -252,7 +253,7 @@ pub fn call_x86(machine: &mut Machine, func: u32, args: Vec<u32>) -> UnimplFutur
             stack32 = sym STACK32,
         );
 
-        UnimplFuture {}
+        UnimplFuture(ret)
     }
 
     #[cfg(not(target_arch = "x86_64"))] // just to keep editor from getting confused
@@ -127,7 +127,12 @@ pub fn call_x86(machine: &mut Machine, func: u32, args: Vec<u32>) -> UnimplFutur
 
     unicorn_loop(machine, func, ret_addr);
 
-    UnimplFuture {}
+    let eax = machine
+        .emu
+        .unicorn
+        .reg_read(unicorn_engine::RegisterX86::EAX)
+        .unwrap() as u32;
+    UnimplFuture(eax)
 }
 
 /// Run emulation via machine.emu starting from eip=begin until eip==until is hit.
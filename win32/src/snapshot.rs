@@ -0,0 +1,79 @@
+//! Snapshots of a running machine.  Besides emulator memory and registers, a
+//! snapshot records the winapi state of every DLL and the host-side resources the
+//! guest holds handles to, so that loading one reconnects them rather than leaving
+//! the guest with dangling handles.
+//!
+//! Snapshots are only meaningful for the exe they were taken of, which must still
+//! be loaded first, to set up the session the snapshot then replaces.  What
+//! describes the host rather than the guest, like attached controllers, input
+//! state, and configuration such as the reported GPU, stays from that session.
+//!
+//! At load, every resource of the current session is closed and replaced by the
+//! recorded ones, under their recorded handles:
+//! - files, including those behind C runtime streams, are reopened by path at
+//!   their recorded position, unless the file has changed size, in which case the
+//!   handle stays closed;
+//! - waveOut devices reopen on a fresh host stream at their recorded position,
+//!   reporting any queued headers done so the guest refills them;
+//! - MCI devices reload their media and resume playing from their recorded
+//!   position, and PlaySound resumes its sound where it was;
+//! - multimedia timers resume with the time that was left until they fired;
+//! - AVI files and DirectShow movies are read again from their paths, their
+//!   decoders catching up to where they were; handles to files that are gone stay
+//!   invalid;
+//! - sockets are reopened on the addresses they had, connecting again to their
+//!   peer; see ws2_32 for what that means for the connection;
+//! - windows and DirectDraw, OpenGL and Glide surfaces are recreated on the host
+//!   showing what they last held, and DirectSound reopens its output stream;
+//! - the guest's addresses for builtin functions are mapped back to them by name.
+
+use crate::{
+    shims_emu::ShimRecord,
+    winapi::{self, avifil32, kernel32, msvcrt, quartz, winmm, ws2_32},
+    Machine,
+};
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Snapshot<Emu, State> {
+    pub emu: Emu,
+    pub resources: Resources,
+    pub state: State,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Resources {
+    files: Vec<kernel32::FileRecord>,
+    streams: Vec<(u32, msvcrt::StreamRecord)>,
+    winmm: winmm::Snapshot,
+    avifil32: avifil32::Snapshot,
+    graphs: Vec<quartz::GraphRecord>,
+    sockets: Vec<ws2_32::SocketRecord>,
+    shims: Vec<ShimRecord>,
+}
+
+pub fn save(machine: &Machine) -> anyhow::Result<Resources> {
+    if machine.emu.win16.is_some() {
+        anyhow::bail!("snapshots of 16-bit programs aren't supported");
+    }
+    Ok(Resources {
+        files: kernel32::save_files(machine),
+        streams: msvcrt::save_streams(machine),
+        winmm: winmm::save(machine),
+        avifil32: avifil32::save(machine),
+        graphs: quartz::save(machine),
+        sockets: ws2_32::save(machine),
+        shims: machine.emu.shims.save(),
+    })
+}
+
+pub fn restore(machine: &mut Machine, resources: Resources, state: winapi::State) {
+    machine.state.restore(state);
+    machine.emu.shims.restore(resources.shims);
+    kernel32::restore_files(machine, resources.files);
+    msvcrt::restore_streams(machine, resources.streams);
+    winmm::restore(machine, resources.winmm);
+    avifil32::restore(machine, resources.avifil32);
+    quartz::restore(machine, resources.graphs);
+    ws2_32::restore(machine, resources.sockets);
+    winapi::restore_host_objects(machine);
+}
@@ -0,0 +1,151 @@
+//! Function names for guest addresses, from symbol files supplied by the user, so
+//! traces, the debugger and crash reports can name code rather than show bare
+//! addresses.
+//!
+//! Two formats are understood, both giving addresses relative to an image's base
+//! so they apply wherever the image is loaded:
+//! - a linker .map file: MSVC's "Publics by Value" table, with any other lines
+//!   of the form "RVA name" (RVA in hex) also accepted, as from a hand-written list;
+//! - JSON, an object mapping hex RVAs to names, like {"1000": "WinMain"}.
+
+use anyhow::{anyhow, bail};
+use std::collections::BTreeMap;
+
+#[derive(Default)]
+pub struct Symbols {
+    names: BTreeMap<u32, String>,
+    /// Address ranges of the images symbols were loaded for; an address outside
+    /// of these doesn't belong to the nearest preceding symbol.
+    ranges: Vec<std::ops::Range<u32>>,
+}
+
+impl Symbols {
+    pub fn insert(&mut self, addr: u32, name: String) {
+        self.names.insert(addr, name);
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.names.iter().map(|(&addr, name)| (addr, name.as_str()))
+    }
+
+    /// The symbol an address falls within, and the address's offset from it.
+    pub fn lookup(&self, addr: u32) -> Option<(&str, u32)> {
+        let (&start, name) = self.names.range(..=addr).next_back()?;
+        if start != addr
+            && !self
+                .ranges
+                .iter()
+                .any(|r| r.contains(&start) && r.contains(&addr))
+        {
+            return None;
+        }
+        Some((name, addr - start))
+    }
+
+    /// Like lookup, as "name+0x12", or just "name" at the symbol itself.
+    pub fn name(&self, addr: u32) -> Option<String> {
+        let (name, ofs) = self.lookup(addr)?;
+        Some(match ofs {
+            0 => name.to_string(),
+            _ => format!("{name}+{ofs:#x}"),
+        })
+    }
+
+    /// An address for display, with its symbol if known, like "0x401012 (WinMain+0x12)".
+    pub fn describe(&self, addr: u32) -> String {
+        match self.name(addr) {
+            Some(name) => format!("{addr:#x} ({name})"),
+            None => format!("{addr:#x}"),
+        }
+    }
+
+    /// Load the symbol file at path for the image loaded at base, returning how many
+    /// symbols were read.
+    pub fn load(&mut self, path: &str, base: u32, size: u32) -> anyhow::Result<usize> {
+        let text = std::fs::read_to_string(path).map_err(|err| anyhow!("{path}: {err}"))?;
+        self.load_text(path, &text, base, size)
+    }
+
+    /// Like load, given the file's contents; the format is chosen by the extension
+    /// of the file name.
+    pub fn load_text(
+        &mut self,
+        name: &str,
+        text: &str,
+        base: u32,
+        size: u32,
+    ) -> anyhow::Result<usize> {
+        let syms = if name.ends_with(".json") {
+            parse_json(text)
+        } else {
+            parse_map(text)
+        }
+        .map_err(|err| anyhow!("{name}: {err}"))?;
+        let count = syms.len();
+        for (rva, sym) in syms {
+            self.insert(base.wrapping_add(rva), sym);
+        }
+        self.ranges.push(base..base.saturating_add(size));
+        Ok(count)
+    }
+}
+
+fn parse_hex(s: &str) -> Option<u32> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    u32::from_str_radix(s, 16).ok()
+}
+
+fn parse_json(text: &str) -> anyhow::Result<Vec<(u32, String)>> {
+    let map: BTreeMap<String, String> = serde_json::from_str(text)?;
+    map.into_iter()
+        .map(|(rva, name)| match parse_hex(&rva) {
+            Some(rva) => Ok((rva, name)),
+            None => bail!("bad RVA {rva:?}"),
+        })
+        .collect()
+}
+
+fn parse_map(text: &str) -> anyhow::Result<Vec<(u32, String)>> {
+    // MSVC maps list symbols as
+    //    0001:00000010       _WinMain@16                00401010 f   main.obj
+    // where the third column is the address given the preferred base.
+    let mut preferred = None;
+    let mut syms = Vec::new();
+    for line in text.lines() {
+        if let Some(addr) = line.trim().strip_prefix("Preferred load address is ") {
+            preferred = parse_hex(addr.trim());
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [seg, name, va, ..] if seg.contains(':') => {
+                let (Some(preferred), Some(va)) = (preferred, parse_hex(va)) else {
+                    continue;
+                };
+                // Absolute symbols, like ___safe_se_handler_count, aren't addresses.
+                if va < preferred {
+                    continue;
+                }
+                syms.push((va - preferred, name.to_string()));
+            }
+            [rva, name] => {
+                if let Some(rva) = parse_hex(rva) {
+                    syms.push((rva, name.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+    if syms.is_empty() {
+        bail!("no symbols found");
+    }
+    Ok(syms)
+}
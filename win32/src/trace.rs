@@ -1,13 +1,54 @@
 //! A system for enabling tracing of different subsystems of winapi.
 //! Each winapi file has a magic TRACE_CONTEXT constant string like
-//! "kernel32/file".  The user can specify tracing based on prefix
-//! matching, and a "-" suppresses, so e.g.
-//!   --win32-trace=kernel32/,-kernel32/file
-//! Pass '*' to enable all.
+//! "kernel32/file", and each function is also known by its DLL and name,
+//! like "kernel32/GetTickCount".  The user can specify tracing based on prefix
+//! matching of either, where '*' matches anything, and a "-" suppresses, so e.g.
+//!   --win32-trace=ddraw/*,kernel32/file,-kernel32/GetTickCount
+//! The last matching pattern wins.  Pass '*' to enable all.
+//!
+//! The scheme can be replaced while running, as the web debugger does.
+//!
+//! In the relay format (see Format::Relay), calls are instead logged as they
+//! cross the shim boundary, and patterns match only the "dll/Function" names.
 
+use memory::Extensions;
 use std::cell::UnsafeCell;
 use std::collections::HashMap;
 use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// How traced calls are logged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Log messages naming the arguments, decoded by type.
+    Native,
+    /// Like Wine's WINEDEBUG=+relay, so traces can be diffed against Wine's: lines
+    /// on stderr (from the x86 emulator's shim calls) with arguments as raw stack
+    /// words, and a line for each return:
+    ///   0000:Call KERNEL32.GetTickCount() ret=00401234
+    ///   0000:Ret  KERNEL32.GetTickCount() retval=0001e240 ret=00401234
+    /// Functions taking a variable number of arguments show none, and async ones
+    /// (those calling back into x86 code) have no Ret line.
+    Relay,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "native" => Format::Native,
+            "relay" => Format::Relay,
+            _ => return Err(format!("bad trace format {s:?}, expected native or relay")),
+        })
+    }
+}
+
+static RELAY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_format(format: Format) {
+    RELAY.store(format == Format::Relay, Ordering::Relaxed);
+}
 
 #[derive(Debug)]
 struct Rule {
@@ -17,7 +58,24 @@ struct Rule {
 
 struct State {
     rules: Vec<Rule>,
-    enabled: HashMap<*const u8, bool>,
+    /// Cached results, by context and function name.
+    enabled: HashMap<(*const u8, *const u8), bool>,
+}
+
+/// Whether name starts with pattern, where '*' in the pattern matches any run of
+/// characters.
+fn matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = name.strip_prefix(parts.next().unwrap()) else {
+        return false;
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
 }
 
 impl State {
@@ -27,9 +85,6 @@ impl State {
             if part.len() == 0 {
                 continue;
             }
-            if part == "*" {
-                part = ""
-            }
             let enabled = if part.starts_with('-') {
                 part = &part[1..];
                 false
@@ -47,22 +102,25 @@ impl State {
         }
     }
 
-    fn lookup(&mut self, context: &'static str) -> bool {
+    fn lookup(&mut self, context: &'static str, func: &'static str) -> bool {
         // Confusing: for a static 'foo', foo.as_ptr() has different values
         // when referenced from different mods (e.g. from ddraw's various mods),
         // but only in Debug builds.
         // This code still works in any case.
-        if let Some(&enabled) = self.enabled.get(&context.as_ptr()) {
+        let key = (context.as_ptr(), func.as_ptr());
+        if let Some(&enabled) = self.enabled.get(&key) {
             return enabled;
         }
+        let dll = context.split('/').next().unwrap();
+        let name = format!("{dll}/{func}");
         let mut enabled = false;
         for rule in &self.rules {
-            if context.starts_with(&rule.key) {
+            if matches(&rule.key, context) || matches(&rule.key, &name) {
                 enabled = rule.enabled;
                 // Don't break, so last match wins.
             }
         }
-        self.enabled.insert(context.as_ptr(), enabled);
+        self.enabled.insert(key, enabled);
         return enabled;
     }
 }
@@ -74,21 +132,26 @@ pub fn set_scheme(scheme: &str) {
 }
 
 #[inline(never)]
-pub fn enabled(context: &'static str) -> bool {
+pub fn enabled(context: &'static str, func: &'static str) -> bool {
+    crate::framedump::capturing(context) || logged(context, func)
+}
+
+/// Whether the user's trace scheme enables logging for the given function.
+fn logged(context: &'static str, func: &'static str) -> bool {
     unsafe {
         match STATE.get_mut() {
             None => return false,
-            Some(state) => state.lookup(context),
+            Some(state) => state.lookup(context, func),
         }
     }
 }
 
 #[inline(never)]
 pub fn trace(
-    context: &str,
+    context: &'static str,
     file: &'static str,
     line: u32,
-    func: &str,
+    func: &'static str,
     args: &[(&str, &dyn std::fmt::Debug)],
 ) {
     let mut msg = format!("{}/{}(", context, func);
@@ -99,6 +162,12 @@ pub fn trace(
         write!(&mut msg, "{}:{:x?}", arg.0, arg.1).unwrap();
     }
     msg.push_str(")");
+    if crate::framedump::capturing(context) {
+        crate::framedump::record(&msg);
+    }
+    if RELAY.load(Ordering::Relaxed) || !logged(context, func) {
+        return;
+    }
     log::log_record(&log::Record {
         level: log::Level::Info,
         file,
@@ -106,3 +175,62 @@ pub fn trace(
         args: format_args!("{}", msg),
     });
 }
+
+/// The builtin DLL exporting a shim, as Wine's relay trace names it ("KERNEL32"),
+/// along with its file name sans extension, for matching against the scheme.
+fn relay_dll(shim: &crate::shims::Shim) -> Option<(String, &'static str)> {
+    let file_name = shim.dll()?;
+    let name = file_name.strip_suffix(".dll").unwrap_or(file_name);
+    Some((name.to_ascii_uppercase(), name))
+}
+
+/// A call logged by relay_call, for logging its return.
+pub struct RelayCall {
+    thread: u32,
+    dll: String,
+    name: &'static str,
+    ret: u32,
+}
+
+/// If relay tracing a shim, log its call, given the stack pointer at entry.
+pub fn relay_call(
+    thread: u32,
+    shim: &crate::shims::Shim,
+    mem: memory::Mem,
+    esp: u32,
+) -> Option<RelayCall> {
+    if !RELAY.load(Ordering::Relaxed) {
+        return None;
+    }
+    let (dll, context) = relay_dll(shim)?;
+    if !logged(context, shim.name) {
+        return None;
+    }
+    let ret = mem.get_pod::<u32>(esp);
+    let args = (0..shim.stack_consumed / 4)
+        .map(|i| format!("{:08x}", mem.get_pod::<u32>(esp + 4 + i * 4)))
+        .collect::<Vec<_>>()
+        .join(",");
+    eprintln!(
+        "{thread:04x}:Call {dll}.{}({args}) ret={ret:08x}",
+        shim.name
+    );
+    Some(RelayCall {
+        thread,
+        dll,
+        name: shim.name,
+        ret,
+    })
+}
+
+impl RelayCall {
+    pub fn log_return(&self, retval: u32) {
+        let RelayCall {
+            thread,
+            dll,
+            name,
+            ret,
+        } = self;
+        eprintln!("{thread:04x}:Ret  {dll}.{name}() retval={retval:08x} ret={ret:08x}");
+    }
+}
@@ -0,0 +1,199 @@
+#![allow(non_snake_case)]
+
+//! KERNEL, and the DOS calls Win16 programs make through it.
+
+use super::{Args16, Export};
+use crate::{
+    machine::Machine,
+    winapi::kernel32::{self, GMEM},
+};
+use memory::Extensions;
+use x86::Register;
+
+pub const EXPORTS: &[Export] = &[
+    Export::func(1, "FATALEXIT", 2, FatalExit),
+    Export::func(3, "GETVERSION", 0, GetVersion),
+    Export::func(15, "GLOBALALLOC", 6, GlobalAlloc),
+    Export::func(17, "GLOBALFREE", 2, GlobalFree),
+    Export::func(18, "GLOBALLOCK", 2, GlobalLock),
+    Export::func(19, "GLOBALUNLOCK", 2, GlobalUnlock),
+    Export::func(23, "LOCKSEGMENT", 2, LockSegment),
+    Export::func(24, "UNLOCKSEGMENT", 2, UnlockSegment),
+    Export::func(30, "WAITEVENT", 2, WaitEvent),
+    Export::func(91, "INITTASK", 0, InitTask),
+    Export::func(102, "DOS3CALL", 0, Dos3Call),
+    Export::value(113, "__AHSHIFT", 3),
+    Export::value(114, "__AHINCR", 8),
+    Export::func(115, "OUTPUTDEBUGSTRING", 4, OutputDebugString),
+    Export::func(132, "GETWINFLAGS", 0, GetWinFlags),
+    Export::func(137, "FATALAPPEXIT", 6, FatalAppExit),
+    Export::value(178, "__WINFLAGS", WINFLAGS),
+];
+
+/// WF_PMODE | WF_CPU386 | WF_ENHANCED | WF_80x87: enhanced mode on a 386 with an FPU.
+const WINFLAGS: u16 = 0x0425;
+
+fn FatalExit(machine: &mut Machine, args: &mut Args16) -> u32 {
+    let code = args.word(machine);
+    machine
+        .emu
+        .x86
+        .cpu_mut()
+        .err(format!("win16: FatalExit({code:#x})"));
+    0
+}
+
+fn FatalAppExit(machine: &mut Machine, args: &mut Args16) -> u32 {
+    let _action = args.word(machine);
+    let msg = args.str(machine).unwrap_or_default();
+    machine
+        .emu
+        .x86
+        .cpu_mut()
+        .err(format!("win16: FatalAppExit({msg:?})"));
+    0
+}
+
+/// Windows 3.10 in AX, on DOS 5.0 in DX.
+fn GetVersion(_machine: &mut Machine, _args: &mut Args16) -> u32 {
+    0x0500_0A03
+}
+
+fn GetWinFlags(_machine: &mut Machine, _args: &mut Args16) -> u32 {
+    WINFLAGS as u32
+}
+
+/// Global memory is blocks of the process heap, each with a selector as its handle.
+/// Blocks don't move, so locking does nothing.
+fn GlobalAlloc(machine: &mut Machine, args: &mut Args16) -> u32 {
+    let flags = args.word(machine);
+    let size = args.dword(machine);
+    if size == 0 || size > 0x1_0000 {
+        // Larger blocks need consecutive selectors, tiled __AHINCR apart.
+        log::warn!("win16: GlobalAlloc({size:#x}) unsupported");
+        return 0;
+    }
+    let flags = GMEM::from_bits_truncate(flags as u32) & GMEM::ZEROINIT;
+    let addr = kernel32::GlobalAlloc(machine, flags, size);
+    let mem = machine.emu.memory.mem();
+    let state = machine.emu.win16.as_mut().unwrap();
+    state.alloc_selector(mem, addr, size, false) as u32
+}
+
+fn GlobalFree(machine: &mut Machine, args: &mut Args16) -> u32 {
+    let handle = args.word(machine);
+    let mem = machine.emu.memory.mem();
+    let state = machine.emu.win16.as_mut().unwrap();
+    let addr = state.linear(mem, handle, 0);
+    state.free_selector(mem, handle);
+    kernel32::GlobalFree(machine, addr);
+    0
+}
+
+/// A far pointer to the start of the block.
+fn GlobalLock(machine: &mut Machine, args: &mut Args16) -> u32 {
+    (args.word(machine) as u32) << 16
+}
+
+fn GlobalUnlock(machine: &mut Machine, args: &mut Args16) -> u32 {
+    let _handle = args.word(machine);
+    0
+}
+
+/// Segments don't move either.  -1 means the data segment.
+fn LockSegment(machine: &mut Machine, args: &mut Args16) -> u32 {
+    match args.word(machine) {
+        0xFFFF => machine.emu.x86.cpu().regs.get16(Register::DS) as u32,
+        segment => segment as u32,
+    }
+}
+
+fn UnlockSegment(machine: &mut Machine, args: &mut Args16) -> u32 {
+    let _segment = args.word(machine);
+    0
+}
+
+/// Programs wait for the event that starts their task, which it already is.
+fn WaitEvent(machine: &mut Machine, args: &mut Args16) -> u32 {
+    let _task = args.word(machine);
+    0
+}
+
+/// Called first by the startup code, taking and returning values in registers.
+/// Also records the stack's bounds in the data segment's header.
+fn InitTask(machine: &mut Machine, _args: &mut Args16) -> u32 {
+    let state = machine.emu.win16.as_ref().unwrap();
+    let (psp, stack_bottom, stack_size) = (state.psp, state.stack_bottom, state.stack_size);
+    let cpu = machine.emu.x86.cpu_mut();
+    let mem = machine.emu.memory.mem();
+    let ds = cpu.regs.seg_base(Register::DS);
+    mem.put::<u16>(ds + 0x0A, stack_bottom.saturating_sub(stack_size));
+    mem.put::<u16>(ds + 0x0C, stack_bottom);
+    mem.put::<u16>(ds + 0x0E, stack_bottom);
+
+    x86::ops::load_segment(cpu, mem, Register::ES, psp);
+    let hinstance = cpu.regs.get16(Register::DS);
+    cpu.regs.set16(Register::BX, 0x81); // command tail, in the PSP
+    cpu.regs.set16(Register::CX, stack_size);
+    cpu.regs.set16(Register::SI, 0); // hPrevInstance
+    cpu.regs.set16(Register::DI, hinstance);
+    const SW_SHOWNORMAL: u32 = 1;
+    (SW_SHOWNORMAL << 16) | 1
+}
+
+fn OutputDebugString(machine: &mut Machine, args: &mut Args16) -> u32 {
+    let msg = args.str(machine);
+    kernel32::OutputDebugStringA(machine, msg.as_deref());
+    0
+}
+
+/// Same as int 21h, taking and returning values in registers.
+fn Dos3Call(machine: &mut Machine, _args: &mut Args16) -> u32 {
+    dos_call(machine);
+    let regs = &machine.emu.x86.cpu().regs;
+    ((regs.get16(Register::DX) as u32) << 16) | regs.get16(Register::AX) as u32
+}
+
+/// Handle the int 21h DOS calls Win16 programs still make, mostly those of C
+/// runtime startup and exit.  The function is in AH; carry clear means success.
+pub fn dos_call(machine: &mut Machine) {
+    let cpu = machine.emu.x86.cpu_mut();
+    let mem = machine.emu.memory.mem();
+    let ds = cpu.regs.seg_base(Register::DS);
+    let dx = cpu.regs.get16(Register::DX) as u32;
+    cpu.flags.remove(x86::Flags::CF);
+    match cpu.regs.get8(Register::AH) {
+        // Write the character in DL.
+        0x02 => {
+            machine.host.write(&[dx as u8]);
+        }
+        // Write the '$'-terminated string at DS:DX.
+        0x09 => {
+            let str = mem.slice(ds + dx..);
+            let len = str.as_slice_todo().iter().position(|&c| c == b'$').unwrap();
+            machine.host.write(str.sub32(0, len as u32));
+        }
+        // Set an interrupt vector, like that of divide errors, which we don't deliver.
+        0x25 => {}
+        // DOS version 5.0.
+        0x30 => cpu.regs.set16(Register::AX, 0x0005),
+        // Get an interrupt vector, in ES:BX.
+        0x35 => {
+            x86::ops::load_segment(cpu, mem, Register::ES, 0);
+            cpu.regs.set16(Register::BX, 0);
+        }
+        // Write CX bytes at DS:DX to the file handle in BX, of which we have
+        // stdout and stderr.
+        0x40 if matches!(cpu.regs.get16(Register::BX), 1 | 2) => {
+            let len = cpu.regs.get16(Register::CX);
+            cpu.regs.set16(Register::AX, len);
+            machine.host.write(mem.sub32(ds + dx, len as u32));
+        }
+        // Exit with the code in AL.
+        0x4C => {
+            let code = cpu.regs.get8(Register::AL);
+            kernel32::ExitProcess(machine, code as u32);
+        }
+        ah => cpu.err(format!("win16: unhandled int 21h function {ah:#x}")),
+    }
+}
@@ -0,0 +1,186 @@
+//! Loading an NE executable: its segments, their relocations, and the registers
+//! Windows starts a task with.
+
+use super::{State, LDT_SIZE};
+use crate::{
+    machine::{LoadedAddrs, Machine},
+    pe::ne::{self, Reloc, RelocKind, RelocTarget},
+};
+use anyhow::{anyhow, bail};
+use memory::{Extensions, Mem};
+use x86::Register;
+
+/// Patch the places a relocation applies to in the segment at base with value,
+/// a selector:offset.
+fn apply_reloc(mem: Mem, base: u32, reloc: &Reloc, value: u32) -> anyhow::Result<()> {
+    let (selector, offset) = ((value >> 16) as u16, value as u16);
+    let mut ofs = reloc.offset;
+    // A 64k segment has room for this many distinct places, so a longer chain loops.
+    for _ in 0..0x8000 {
+        let addr = base + ofs as u32;
+        let old = mem.get_pod::<u16>(addr);
+        let add = if reloc.additive { old } else { 0 };
+        match reloc.kind {
+            RelocKind::LoByte => {
+                let add = if reloc.additive { old as u8 } else { 0 };
+                mem.put::<u8>(addr, add.wrapping_add(offset as u8));
+            }
+            RelocKind::Segment => mem.put::<u16>(addr, selector),
+            RelocKind::FarAddr => {
+                mem.put::<u16>(addr, add.wrapping_add(offset));
+                mem.put::<u16>(addr + 2, selector);
+            }
+            RelocKind::Offset => mem.put::<u16>(addr, add.wrapping_add(offset)),
+        }
+        // Non-additive relocations chain through the places they patch.
+        if reloc.additive || reloc.kind == RelocKind::LoByte || old == 0xFFFF {
+            return Ok(());
+        }
+        ofs = old;
+    }
+    bail!("relocation chain at {:#x} loops", reloc.offset)
+}
+
+/// The arguments in a command line, after the program name.
+fn command_tail(cmdline: &str) -> &str {
+    let rest = match cmdline.strip_prefix('"') {
+        Some(rest) => rest.split_once('"').map_or("", |(_, rest)| rest),
+        None => cmdline.split_once(' ').map_or("", |(_, rest)| rest),
+    };
+    rest.trim_start()
+}
+
+/// Allocate the program segment prefix, the DOS process header through which
+/// programs find their command line.
+fn create_psp(machine: &mut Machine, state: &mut State, cmdline: &str) -> u16 {
+    let addr = machine
+        .state
+        .kernel32
+        .mappings
+        .alloc(0x100, "PSP".into(), &mut machine.emu.memory)
+        .addr;
+    let mem = machine.emu.memory.mem();
+    mem.put::<u16>(addr, 0x20CD); // int 20h, the old way to exit
+    let tail = command_tail(cmdline).as_bytes();
+    let tail = &tail[..tail.len().min(126)];
+    mem.put::<u8>(addr + 0x80, tail.len() as u8);
+    mem.sub(addr + 0x81, tail.len() as u32)
+        .as_mut_slice_todo()
+        .copy_from_slice(tail);
+    mem.put::<u8>(addr + 0x81 + tail.len() as u32, b'\r');
+    state.alloc_selector(mem, addr, 0x100, false)
+}
+
+pub fn load_exe(machine: &mut Machine, buf: &[u8], cmdline: &str) -> anyhow::Result<LoadedAddrs> {
+    let file = ne::parse(buf)?;
+    if !file.is_windows() {
+        bail!("16-bit OS/2 executable, not Windows");
+    }
+    if file.is_dll() {
+        bail!("{}: can't run a DLL", file.name);
+    }
+    let header = &file.header;
+    let mut state = State::new(machine);
+
+    // Each segment as (selector, address, size).
+    let mut segments = Vec::new();
+    for (i, seg) in file.segments.iter().enumerate() {
+        let data = file.segment_data(buf, i)?;
+        let mut size = std::cmp::max(seg.alloc_size(), data.len() as u32);
+        if i + 1 == header.ne_autodata as usize {
+            // The data segment also holds the local heap and the stack.
+            size += header.ne_heap as u32 + header.ne_stack as u32;
+            size = size.min(0x1_0000);
+        }
+        let addr = machine
+            .state
+            .kernel32
+            .mappings
+            .alloc(
+                size,
+                format!("{} segment {}", file.name, i + 1),
+                &mut machine.emu.memory,
+            )
+            .addr;
+        let mem = machine.emu.memory.mem();
+        mem.sub(addr, data.len() as u32)
+            .as_mut_slice_todo()
+            .copy_from_slice(data);
+        let selector = state.alloc_selector(mem, addr, size, !seg.is_data());
+        segments.push((selector, addr, size));
+    }
+    let segment = |number: u16| {
+        number
+            .checked_sub(1)
+            .and_then(|i| segments.get(i as usize))
+            .copied()
+            .ok_or_else(|| anyhow!("bad segment number {number}"))
+    };
+
+    for (i, relocs) in file.relocs.iter().enumerate() {
+        let base = segments[i].1;
+        for reloc in relocs {
+            let module = |module: u16| {
+                file.imports
+                    .get((module as usize).wrapping_sub(1))
+                    .ok_or_else(|| anyhow!("bad module number {module}"))
+            };
+            let value = match &reloc.target {
+                &RelocTarget::Internal {
+                    segment: seg,
+                    offset,
+                } => ((segment(seg as u16)?.0 as u32) << 16) | offset as u32,
+                &RelocTarget::ImportOrdinal { module: m, ordinal } => {
+                    state.import(module(m)?, Some(ordinal), "")
+                }
+                RelocTarget::ImportName { module: m, name } => {
+                    state.import(module(*m)?, None, name)
+                }
+                RelocTarget::OsFixup => continue,
+            };
+            apply_reloc(machine.emu.memory.mem(), base, reloc, value)?;
+        }
+    }
+
+    let psp = create_psp(machine, &mut state, cmdline);
+    let (ds, _, ds_size) = segment(header.ne_autodata)?;
+    let (ss, ss_addr, ss_size) = match header.ne_sssp >> 16 {
+        0 => segment(header.ne_autodata)?,
+        n => segment(n as u16)?,
+    };
+    let sp = match header.ne_sssp as u16 {
+        0 => (ss_size & !1).min(0xFFFE) as u16,
+        sp => sp,
+    };
+    let (cs_number, ip) = file.entry_point();
+    let (cs, cs_addr, _) = segment(cs_number)?;
+    state.psp = psp;
+    state.stack_bottom = sp;
+    state.stack_size = header.ne_stack;
+    let ldt = state.ldt;
+    machine.emu.win16 = Some(state);
+
+    let cpu = machine.emu.x86.cpu_mut();
+    let mem = machine.emu.memory.mem();
+    cpu.ldt = (ldt, LDT_SIZE - 1);
+    x86::ops::load_segment(cpu, mem, Register::DS, ds);
+    x86::ops::load_segment(cpu, mem, Register::ES, psp);
+    x86::ops::load_segment(cpu, mem, Register::SS, ss);
+    x86::ops::far_jmp(cpu, mem, cs, ip);
+    cpu.regs.set32(Register::ESP, sp as u32);
+    cpu.regs.set32(Register::EBP, 0);
+    cpu.regs.set16(Register::BX, header.ne_stack);
+    cpu.regs.set16(Register::CX, header.ne_heap);
+    cpu.regs.set16(Register::SI, 0);
+    cpu.regs.set16(Register::DI, ds); // hInstance
+    log::info!(
+        "{}: NE, {} segments, data {ds:x} ({ds_size:#x} bytes)",
+        file.name,
+        segments.len()
+    );
+
+    Ok(LoadedAddrs {
+        entry_point: cs_addr + ip as u32,
+        stack_pointer: ss_addr + sp as u32,
+    })
+}
@@ -0,0 +1,293 @@
+//! Running 16-bit Windows (NE) programs, which only the x86 emulator can do.
+//!
+//! Each of the program's segments gets a descriptor in an LDT, which the emulator
+//! resolves selectors through (see x86::ops::load_segment).  Imported functions are
+//! thunks: offsets in a code segment of their own, recognized before executing like
+//! shims are, whose handlers take their Pascal-convention arguments off the 16-bit
+//! stack and mostly forward to the 32-bit APIs.
+
+mod kernel;
+mod loader;
+mod user;
+
+use crate::{machine::Machine, segments::SegmentDescriptor};
+use memory::{Extensions, Mem};
+
+pub use loader::load_exe;
+
+/// Bytes of LDT, enough for all 8192 descriptors.
+const LDT_SIZE: u32 = 0x1_0000;
+
+/// Bytes of the thunk segment, one per thunk.
+const THUNKS_SIZE: u32 = 0x1000;
+
+/// Pull the arguments of a Win16 API call off the stack.  They're pushed first to
+/// last, so read from the highest address down.
+pub struct Args16 {
+    ldt: u32,
+    addr: u32,
+}
+
+impl Args16 {
+    pub fn word(&mut self, machine: &Machine) -> u16 {
+        self.addr -= 2;
+        machine.emu.memory.mem().get_pod::<u16>(self.addr)
+    }
+
+    pub fn dword(&mut self, machine: &Machine) -> u32 {
+        self.addr -= 4;
+        machine.emu.memory.mem().get_pod::<u32>(self.addr)
+    }
+
+    /// A far pointer, as its linear address, or 0 for a null pointer.
+    pub fn ptr(&mut self, machine: &Machine) -> u32 {
+        let ptr = self.dword(machine);
+        linear(
+            machine.emu.memory.mem(),
+            self.ldt,
+            (ptr >> 16) as u16,
+            ptr as u16,
+        )
+    }
+
+    /// A far pointer to a nul-terminated string.
+    pub fn str(&mut self, machine: &Machine) -> Option<String> {
+        match self.ptr(machine) {
+            0 => None,
+            addr => {
+                let str = machine.emu.memory.mem().slicez(addr);
+                Some(String::from_utf8_lossy(str).into_owned())
+            }
+        }
+    }
+}
+
+/// The linear address of selector:offset, or 0 for the null selector.
+fn linear(mem: Mem, ldt: u32, selector: u16, offset: u16) -> u32 {
+    if selector & !3 == 0 {
+        return 0;
+    }
+    let desc = SegmentDescriptor::decode(mem.get_pod::<u64>(ldt + (selector & !7) as u32));
+    desc.base + offset as u32
+}
+
+/// The implementation of a Win16 API function.
+type Func = fn(&mut Machine, &mut Args16) -> u32;
+
+/// A function or value a Win16 module exports.
+pub struct Export {
+    pub ordinal: u16,
+    pub name: &'static str,
+    pub kind: ExportKind,
+}
+
+impl Export {
+    const fn func(ordinal: u16, name: &'static str, arg_bytes: u16, func: Func) -> Self {
+        Export {
+            ordinal,
+            name,
+            kind: ExportKind::Func { arg_bytes, func },
+        }
+    }
+
+    const fn value(ordinal: u16, name: &'static str, value: u16) -> Self {
+        Export {
+            ordinal,
+            name,
+            kind: ExportKind::Const(value),
+        }
+    }
+}
+
+pub enum ExportKind {
+    /// A function, which pops arg_bytes of arguments and returns DX:AX.
+    Func { arg_bytes: u16, func: Func },
+    /// A value that relocations patch in directly, like __AHINCR.
+    Const(u16),
+}
+
+/// The exports of the Win16 modules we implement.
+fn module_exports(module: &str) -> &'static [Export] {
+    match module {
+        "KERNEL" => kernel::EXPORTS,
+        "USER" => user::EXPORTS,
+        _ => &[],
+    }
+}
+
+/// An imported function, for which calls go to a thunk.
+struct Thunk {
+    /// MODULE.name or MODULE.ordinal, for logging.
+    name: String,
+    /// None if we don't implement it, which is an error once called.
+    func: Option<(u16, Func)>,
+}
+
+/// The LDT and thunks of a running 16-bit program.
+pub struct State {
+    /// Address of the LDT.
+    ldt: u32,
+    /// Number of descriptors allocated; descriptor 0 stays unused, as selector 0
+    /// is the null selector.
+    descriptors: u16,
+    thunk_selector: u16,
+    thunk_base: u32,
+    thunks: Vec<Thunk>,
+    /// The task's PSP selector, and its stack's initial SP and size, for InitTask.
+    psp: u16,
+    stack_bottom: u16,
+    stack_size: u16,
+}
+
+impl State {
+    fn new(machine: &mut Machine) -> Self {
+        let mappings = &mut machine.state.kernel32.mappings;
+        let ldt = mappings
+            .alloc(LDT_SIZE, "win16 LDT".into(), &mut machine.emu.memory)
+            .addr;
+        let thunk_base = mappings
+            .alloc(THUNKS_SIZE, "win16 thunks".into(), &mut machine.emu.memory)
+            .addr;
+        let mut state = State {
+            ldt,
+            descriptors: 1,
+            thunk_selector: 0,
+            thunk_base,
+            thunks: Vec::new(),
+            psp: 0,
+            stack_bottom: 0,
+            stack_size: 0,
+        };
+        state.thunk_selector =
+            state.alloc_selector(machine.emu.memory.mem(), thunk_base, THUNKS_SIZE, true);
+        state
+    }
+
+    /// Add an LDT descriptor for a segment of size bytes at base, returning its
+    /// selector, which is for the LDT at privilege level 3.
+    fn alloc_selector(&mut self, mem: Mem, base: u32, size: u32, code: bool) -> u16 {
+        let index = self.descriptors;
+        self.descriptors += 1;
+        let desc = SegmentDescriptor {
+            base,
+            limit: size - 1,
+            present: true,
+            dpl: 3,
+            system: true,
+            type_: if code { 0b1010 } else { 0b0010 },
+            ..Default::default()
+        };
+        mem.put::<u64>(self.ldt + index as u32 * 8, desc.encode());
+        (index << 3) | 7
+    }
+
+    /// Mark the descriptor of a selector not present, so loading it faults.
+    fn free_selector(&mut self, mem: Mem, selector: u16) {
+        mem.put::<u64>(self.ldt + (selector & !7) as u32, 0);
+    }
+
+    fn linear(&self, mem: Mem, selector: u16, offset: u16) -> u32 {
+        linear(mem, self.ldt, selector, offset)
+    }
+
+    /// Resolve an import to the value relocations patch in: a far pointer to a
+    /// thunk for a function, as selector:offset, or the value of a constant.
+    fn import(&mut self, module: &str, ordinal: Option<u16>, name: &str) -> u32 {
+        let export = module_exports(module)
+            .iter()
+            .find(|e| Some(e.ordinal) == ordinal || e.name == name);
+        let func = match export.map(|e| &e.kind) {
+            Some(&ExportKind::Const(value)) => return value as u32,
+            Some(&ExportKind::Func { arg_bytes, func }) => Some((arg_bytes, func)),
+            None => None,
+        };
+        let name = match export {
+            Some(e) => format!("{module}.{}", e.name),
+            None if name.is_empty() => format!("{module}.{}", ordinal.unwrap()),
+            None => format!("{module}.{name}"),
+        };
+        let offset = match self.thunks.iter().position(|t| t.name == name) {
+            Some(i) => i,
+            None => {
+                self.thunks.push(Thunk { name, func });
+                self.thunks.len() - 1
+            }
+        };
+        assert!((offset as u32) < THUNKS_SIZE, "too many win16 imports");
+        ((self.thunk_selector as u32) << 16) | offset as u32
+    }
+}
+
+pub fn is_eip_at_thunk(machine: &Machine) -> bool {
+    let Some(state) = &machine.emu.win16 else {
+        return false;
+    };
+    let eip = machine.emu.x86.cpu().regs.eip;
+    eip.wrapping_sub(state.thunk_base) < state.thunks.len() as u32
+}
+
+/// Run the function a far call to a thunk called, then return to the caller,
+/// popping its arguments as Pascal-convention functions do.
+pub fn handle_thunk(machine: &mut Machine) {
+    let state = machine.emu.win16.as_ref().unwrap();
+    let cpu = machine.emu.x86.cpu_mut();
+    let thunk = &state.thunks[(cpu.regs.eip - state.thunk_base) as usize];
+    let Some((arg_bytes, func)) = thunk.func else {
+        crate::api_stats::missing_call(&thunk.name);
+        cpu.err(format!("win16: unimplemented {}", thunk.name));
+        return;
+    };
+
+    let mem = machine.emu.memory.mem();
+    let sp = x86::ops::stack_addr(cpu);
+    let (ret_ip, ret_cs) = (mem.get_pod::<u16>(sp), mem.get_pod::<u16>(sp + 2));
+    let mut args = Args16 {
+        ldt: state.ldt,
+        addr: sp + 4 + arg_bytes as u32,
+    };
+    let ret = func(machine, &mut args);
+
+    let cpu = machine.emu.x86.cpu_mut();
+    if !cpu.state.is_running() {
+        return;
+    }
+    x86::ops::release_stack(cpu, 4 + arg_bytes as u32);
+    cpu.regs.set16(x86::Register::AX, ret as u16);
+    cpu.regs.set16(x86::Register::DX, (ret >> 16) as u16);
+    x86::ops::far_jmp(cpu, machine.emu.memory.mem(), ret_cs, ret_ip);
+}
+
+/// Handle an exception 16-bit code raised.  Interrupts are DOS calls and the
+/// hooks of the FPU emulator; anything else is an error, as we don't deliver
+/// exceptions to 16-bit programs.
+pub fn cpu_exception(machine: &mut Machine, exception: x86::Exception) {
+    let cpu = machine.emu.x86.cpu_mut();
+    cpu.state = x86::CPUState::Running;
+    match exception {
+        x86::Exception::Interrupt(0x21) => kernel::dos_call(machine),
+        x86::Exception::Interrupt(n @ 0x34..=0x3D) => fpu_fixup(machine, n),
+        _ => cpu.err(format!("win16: {exception:x?} at {:x}", cpu.regs.eip)),
+    }
+}
+
+/// Compilers for Win16 emit floating point instructions as interrupts 34h-3Dh,
+/// which Windows patches into the real instructions when there's an FPU.  Do the
+/// same, then go back to run the instruction.
+fn fpu_fixup(machine: &mut Machine, n: u8) {
+    let cpu = machine.emu.x86.cpu_mut();
+    let addr = cpu.regs.eip - 2;
+    let patch: [u8; 2] = match n {
+        // fwait
+        0x3D => [0x9B, 0x90],
+        // The escape opcodes d8-df, with the ModR/M byte following.
+        n if n <= 0x3B => [0x9B, 0xD8 + (n - 0x34)],
+        // Segment override forms and the like, which we haven't seen.
+        _ => {
+            cpu.err(format!("win16: unhandled FPU emulator int {n:x}h"));
+            return;
+        }
+    };
+    cpu.regs.eip = addr;
+    machine.emu.memory.mem().put::<[u8; 2]>(addr, patch);
+    machine.emu.x86.invalidate_code(addr, 2);
+}
@@ -0,0 +1,45 @@
+#![allow(non_snake_case)]
+
+//! USER.
+
+use super::{Args16, Export};
+use crate::{
+    machine::Machine,
+    winapi::{types::HWND, user32},
+};
+
+pub const EXPORTS: &[Export] = &[
+    Export::func(1, "MESSAGEBOX", 12, MessageBox),
+    Export::func(5, "INITAPP", 2, InitApp),
+    Export::func(13, "GETTICKCOUNT", 0, GetTickCount),
+    Export::func(104, "MESSAGEBEEP", 2, MessageBeep),
+];
+
+fn MessageBox(machine: &mut Machine, args: &mut Args16) -> u32 {
+    let _hwnd = args.word(machine);
+    let text = args.str(machine);
+    let caption = args.str(machine);
+    let type_ = args.word(machine);
+    user32::MessageBoxA(
+        machine,
+        HWND::null(),
+        text.as_deref(),
+        caption.as_deref(),
+        type_ as u32,
+    )
+}
+
+/// Sets up the task's message queue, which we have one of already.
+fn InitApp(machine: &mut Machine, args: &mut Args16) -> u32 {
+    let _hinstance = args.word(machine);
+    1
+}
+
+fn GetTickCount(machine: &mut Machine, _args: &mut Args16) -> u32 {
+    machine.host.time()
+}
+
+fn MessageBeep(machine: &mut Machine, args: &mut Args16) -> u32 {
+    let _type = args.word(machine);
+    0
+}
@@ -0,0 +1,235 @@
+//! CryptoAPI, as far as random numbers and MD5/SHA-1 hashes.
+
+use super::digest::Digest;
+use crate::{
+    machine::Machine,
+    winapi::{handle::HANDLE, kernel32::SetLastError, types::Str16},
+};
+
+const TRACE_CONTEXT: &'static str = "advapi32/crypt";
+
+/*
+## CryptoAPI
+
+There's one provider, which every CryptAcquireContext gets whatever it asks for;
+it has no keys or containers, only random numbers, drawn from the host, and
+hashes.
+*/
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct HCRYPTPROVT;
+pub type HCRYPTPROV = HANDLE<HCRYPTPROVT>;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct HCRYPTHASHT;
+pub type HCRYPTHASH = HANDLE<HCRYPTHASHT>;
+
+const ERROR_INVALID_HANDLE: u32 = 6;
+const ERROR_INVALID_PARAMETER: u32 = 87;
+const ERROR_MORE_DATA: u32 = 234;
+const NTE_BAD_ALGID: u32 = 0x80090008;
+const NTE_BAD_HASH_STATE: u32 = 0x8009000C;
+const NTE_BAD_TYPE: u32 = 0x8009000A;
+
+const CALG_MD5: u32 = 0x8003;
+const CALG_SHA1: u32 = 0x8004;
+
+const HP_ALGID: u32 = 1;
+const HP_HASHVAL: u32 = 2;
+const HP_HASHSIZE: u32 = 4;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Hash {
+    algid: u32,
+    /// The digest, until the hash value is read, which finishes it.
+    digest: Option<Digest>,
+    value: Vec<u8>,
+}
+
+impl Hash {
+    fn value(&mut self) -> &[u8] {
+        if let Some(digest) = self.digest.take() {
+            self.value = digest.finish();
+        }
+        &self.value
+    }
+}
+
+/// Fail with an error code for GetLastError.
+fn fail(machine: &mut Machine, err: u32) -> bool {
+    SetLastError(machine, err);
+    false
+}
+
+fn acquire_context(machine: &mut Machine, phProv: Option<&mut HCRYPTPROV>) -> bool {
+    let Some(out) = phProv else {
+        return fail(machine, ERROR_INVALID_PARAMETER);
+    };
+    *out = machine.state.advapi32.providers.add(());
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn CryptAcquireContextA(
+    machine: &mut Machine,
+    phProv: Option<&mut HCRYPTPROV>,
+    szContainer: Option<&str>,
+    szProvider: Option<&str>,
+    dwProvType: u32,
+    dwFlags: u32,
+) -> bool {
+    acquire_context(machine, phProv)
+}
+
+#[win32_derive::dllexport]
+pub fn CryptAcquireContextW(
+    machine: &mut Machine,
+    phProv: Option<&mut HCRYPTPROV>,
+    szContainer: Option<&Str16>,
+    szProvider: Option<&Str16>,
+    dwProvType: u32,
+    dwFlags: u32,
+) -> bool {
+    acquire_context(machine, phProv)
+}
+
+#[win32_derive::dllexport]
+pub fn CryptReleaseContext(machine: &mut Machine, hProv: HCRYPTPROV, dwFlags: u32) -> bool {
+    match machine.state.advapi32.providers.remove(hProv) {
+        Some(()) => true,
+        None => fail(machine, ERROR_INVALID_HANDLE),
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn CryptGenRandom(machine: &mut Machine, hProv: HCRYPTPROV, dwLen: u32, pbBuffer: u32) -> bool {
+    if machine.state.advapi32.providers.get(hProv).is_none() {
+        return fail(machine, ERROR_INVALID_HANDLE);
+    }
+    SystemFunction036(machine, pbBuffer, dwLen)
+}
+
+/// RtlGenRandom, which CRTs use for rand_s without going through a provider.
+#[win32_derive::dllexport]
+pub fn SystemFunction036(
+    machine: &mut Machine,
+    RandomBuffer: u32,
+    RandomBufferLength: u32,
+) -> bool {
+    let buf = machine
+        .emu
+        .memory
+        .mem()
+        .sub(RandomBuffer, RandomBufferLength)
+        .as_mut_slice_todo();
+    machine.host.random(buf);
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn CryptCreateHash(
+    machine: &mut Machine,
+    hProv: HCRYPTPROV,
+    Algid: u32,
+    hKey: u32,
+    dwFlags: u32,
+    phHash: Option<&mut HCRYPTHASH>,
+) -> bool {
+    if machine.state.advapi32.providers.get(hProv).is_none() {
+        return fail(machine, ERROR_INVALID_HANDLE);
+    }
+    let digest = match Algid {
+        CALG_MD5 => Digest::md5(),
+        CALG_SHA1 => Digest::sha1(),
+        _ => {
+            log::warn!("CryptCreateHash: unsupported algorithm {Algid:x}");
+            return fail(machine, NTE_BAD_ALGID);
+        }
+    };
+    let Some(out) = phHash else {
+        return fail(machine, ERROR_INVALID_PARAMETER);
+    };
+    *out = machine.state.advapi32.hashes.add(Hash {
+        algid: Algid,
+        digest: Some(digest),
+        value: Vec::new(),
+    });
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn CryptHashData(
+    machine: &mut Machine,
+    hHash: HCRYPTHASH,
+    pbData: u32,
+    dwDataLen: u32,
+    dwFlags: u32,
+) -> bool {
+    let mem = machine.emu.memory.mem();
+    let Some(hash) = machine.state.advapi32.hashes.get_mut(hHash) else {
+        return fail(machine, ERROR_INVALID_HANDLE);
+    };
+    // Once its value is read, a hash takes no more data.
+    let Some(digest) = &mut hash.digest else {
+        return fail(machine, NTE_BAD_HASH_STATE);
+    };
+    digest.update(mem.sub(pbData, dwDataLen).as_slice_todo());
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn CryptGetHashParam(
+    machine: &mut Machine,
+    hHash: HCRYPTHASH,
+    dwParam: u32,
+    pbData: u32,
+    pdwDataLen: Option<&mut u32>,
+    dwFlags: u32,
+) -> bool {
+    let Some(hash) = machine.state.advapi32.hashes.get_mut(hHash) else {
+        return fail(machine, ERROR_INVALID_HANDLE);
+    };
+    let data = match dwParam {
+        HP_ALGID => hash.algid.to_le_bytes().to_vec(),
+        HP_HASHSIZE => {
+            let size = match hash.algid {
+                CALG_SHA1 => 20u32,
+                _ => 16,
+            };
+            size.to_le_bytes().to_vec()
+        }
+        HP_HASHVAL => {
+            let size = hash.digest.as_ref().map_or(hash.value.len(), Digest::size);
+            // Only reading the value out finishes the hash, not asking its size.
+            match pdwDataLen.as_deref() {
+                Some(&len) if pbData != 0 && len as usize >= size => hash.value().to_vec(),
+                _ => vec![0; size],
+            }
+        }
+        _ => return fail(machine, NTE_BAD_TYPE),
+    };
+    let Some(len) = pdwDataLen else {
+        return fail(machine, ERROR_INVALID_PARAMETER);
+    };
+    let size = std::mem::replace(len, data.len() as u32);
+    if pbData == 0 {
+        return true;
+    }
+    if (size as usize) < data.len() {
+        return fail(machine, ERROR_MORE_DATA);
+    }
+    machine
+        .mem()
+        .sub(pbData, data.len() as u32)
+        .as_mut_slice_todo()
+        .copy_from_slice(&data);
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn CryptDestroyHash(machine: &mut Machine, hHash: HCRYPTHASH) -> bool {
+    match machine.state.advapi32.hashes.remove(hHash) {
+        Some(_) => true,
+        None => fail(machine, ERROR_INVALID_HANDLE),
+    }
+}
@@ -0,0 +1,140 @@
+//! MD5 and SHA-1, for CryptoAPI's hashes.
+
+/// The state of an MD5 or SHA-1 computation; the two share their padding and
+/// block structure, differing in the compression function and byte order.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Digest {
+    sha1: bool,
+    state: [u32; 5],
+    block: Vec<u8>,
+    len: u64,
+}
+
+const MD5_SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+
+impl Digest {
+    pub fn md5() -> Self {
+        Digest {
+            sha1: false,
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0],
+            block: Vec::with_capacity(64),
+            len: 0,
+        }
+    }
+
+    pub fn sha1() -> Self {
+        Digest {
+            sha1: true,
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0],
+            block: Vec::with_capacity(64),
+            len: 0,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        if self.sha1 {
+            20
+        } else {
+            16
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+        while !data.is_empty() {
+            let n = std::cmp::min(64 - self.block.len(), data.len());
+            self.block.extend_from_slice(&data[..n]);
+            data = &data[n..];
+            if self.block.len() == 64 {
+                let block: [u8; 64] = self.block[..].try_into().unwrap();
+                self.compress(&block);
+                self.block.clear();
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let bits = self.len.wrapping_mul(8);
+        let mut pad = vec![0x80u8];
+        pad.resize(1 + (119 - self.len as usize % 64) % 64, 0);
+        pad.extend(if self.sha1 {
+            bits.to_be_bytes()
+        } else {
+            bits.to_le_bytes()
+        });
+        self.update(&pad);
+        let words = &self.state[..self.size() / 4];
+        if self.sha1 {
+            words.iter().flat_map(|w| w.to_be_bytes()).collect()
+        } else {
+            words.iter().flat_map(|w| w.to_le_bytes()).collect()
+        }
+    }
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        if self.sha1 {
+            self.compress_sha1(block)
+        } else {
+            self.compress_md5(block)
+        }
+    }
+
+    fn compress_md5(&mut self, block: &[u8; 64]) {
+        let m: Vec<u32> = block
+            .chunks_exact(4)
+            .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+            .collect();
+        let [mut a, mut b, mut c, mut d, _] = self.state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let k = ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32;
+            let s = MD5_SHIFTS[i / 16 * 4 + i % 4];
+            let f = f.wrapping_add(a).wrapping_add(k).wrapping_add(m[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(s));
+        }
+        for (s, v) in self.state.iter_mut().zip([a, b, c, d]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    fn compress_sha1(&mut self, block: &[u8; 64]) {
+        let mut w = [0u32; 80];
+        for (i, c) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes(c.try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = self.state;
+        for (i, &w) in w.iter().enumerate() {
+            let (f, k) = match i / 20 {
+                0 => ((b & c) | (!b & d), 0x5a827999),
+                1 => (b ^ c ^ d, 0x6ed9eba1),
+                2 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(w);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (s, v) in self.state.iter_mut().zip([a, b, c, d, e]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+#![allow(non_snake_case)]
+
+mod crypt;
+mod digest;
+mod registry;
+mod security;
+
+pub use crypt::*;
+pub use registry::*;
+pub use security::*;
+
+use crate::winapi::handle::Handles;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct State {
+    pub registry: Registry,
+    /// The logged-in user's name, as GetUserName reports it.
+    pub user_name: String,
+    /// Open registry keys, by the path of their names.
+    keys: Handles<HKEY, Vec<String>>,
+    providers: Handles<HCRYPTPROV, ()>,
+    hashes: Handles<HCRYPTHASH, Hash>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            registry: Default::default(),
+            user_name: "User".into(),
+            keys: Default::default(),
+            providers: Default::default(),
+            hashes: Default::default(),
+        }
+    }
+}
@@ -0,0 +1,801 @@
+//! The registry, as a tree in memory that the host can load and save as JSON.
+
+use crate::{
+    machine::Machine,
+    winapi::{handle::HANDLE, kernel32::encode_ansi, types::Str16},
+};
+use std::collections::BTreeMap;
+
+const TRACE_CONTEXT: &'static str = "advapi32/registry";
+
+/*
+## Registry
+
+Keys live in a tree of `Key`s under the root keys, whose names are case
+insensitive but keep the case they were created with.  The host may load the
+tree from a hive file and save it back when the program exits, so a game's
+settings and install state persist between runs; see `Registry::from_json`.
+Without a hive, the registry starts out with a few keys games commonly check for
+(the Windows and DirectX versions, install directories) and is forgotten on exit.
+
+Values are stored as text where they are text, so hive files can be edited by
+hand; strings that the guest wrote in an encoding we can't read are kept as their
+raw bytes.
+*/
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct HKEYT;
+pub type HKEY = HANDLE<HKEYT>;
+
+const ERROR_FILE_NOT_FOUND: u32 = 2;
+const ERROR_INVALID_HANDLE: u32 = 6;
+const ERROR_INVALID_PARAMETER: u32 = 87;
+const ERROR_MORE_DATA: u32 = 234;
+const ERROR_NO_MORE_ITEMS: u32 = 259;
+
+const REG_SZ: u32 = 1;
+const REG_EXPAND_SZ: u32 = 2;
+const REG_BINARY: u32 = 3;
+const REG_DWORD: u32 = 4;
+const REG_MULTI_SZ: u32 = 7;
+
+const REG_CREATED_NEW_KEY: u32 = 1;
+const REG_OPENED_EXISTING_KEY: u32 = 2;
+
+/// The root keys, by predefined HKEY.  HKEY_CLASSES_ROOT is a view of a subkey
+/// of HKEY_LOCAL_MACHINE.
+fn root_path(hKey: HKEY) -> Option<Vec<String>> {
+    let path: &[&str] = match hKey.to_raw() {
+        0x8000_0000 => &["HKEY_LOCAL_MACHINE", "Software", "Classes"],
+        0x8000_0001 => &["HKEY_CURRENT_USER"],
+        0x8000_0002 => &["HKEY_LOCAL_MACHINE"],
+        0x8000_0003 => &["HKEY_USERS"],
+        0x8000_0005 => &["HKEY_CURRENT_CONFIG"],
+        _ => return None,
+    };
+    Some(path.iter().map(|name| name.to_string()).collect())
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Value {
+    /// REG_SZ.
+    String(String),
+    /// REG_EXPAND_SZ.
+    ExpandString(String),
+    /// REG_DWORD.
+    Dword(u32),
+    /// REG_MULTI_SZ.
+    MultiString(Vec<String>),
+    /// REG_BINARY.
+    Binary(Vec<u8>),
+    /// Any other type, or a string whose bytes we couldn't decode.
+    Raw { kind: u32, data: Vec<u8> },
+}
+
+/// Decode a string value, up to its terminating nul if any.
+fn decode_str(data: &[u8], wide: bool) -> Option<String> {
+    if wide {
+        let units = data
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|&c| c != 0)
+            .collect::<Vec<_>>();
+        String::from_utf16(&units).ok()
+    } else {
+        let len = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+        std::str::from_utf8(&data[..len]).ok().map(str::to_string)
+    }
+}
+
+fn encode_str(out: &mut Vec<u8>, str: &str, wide: bool, code_page: u32) {
+    if wide {
+        for c in str.encode_utf16().chain([0]) {
+            out.extend_from_slice(&c.to_le_bytes());
+        }
+    } else {
+        for c in str.chars() {
+            out.extend(encode_ansi(code_page, c));
+        }
+        out.push(0);
+    }
+}
+
+impl Value {
+    /// Interpret value data as RegSetValueEx received it, from the A or W API.
+    pub fn from_bytes(kind: u32, data: &[u8], wide: bool) -> Value {
+        let value = match kind {
+            REG_SZ => decode_str(data, wide).map(Value::String),
+            REG_EXPAND_SZ => decode_str(data, wide).map(Value::ExpandString),
+            REG_DWORD if data.len() == 4 => {
+                Some(Value::Dword(u32::from_le_bytes(data.try_into().unwrap())))
+            }
+            REG_MULTI_SZ => {
+                let step = if wide { 2 } else { 1 };
+                let mut strings = Vec::new();
+                let mut rest = data;
+                // Strings run until an empty one, or the end of the data.
+                while let Some(s) = decode_str(rest, wide) {
+                    if s.is_empty() {
+                        break;
+                    }
+                    let len = if wide {
+                        s.encode_utf16().count()
+                    } else {
+                        s.len()
+                    };
+                    rest = &rest[std::cmp::min(rest.len(), (len + 1) * step)..];
+                    strings.push(s);
+                }
+                Some(Value::MultiString(strings))
+            }
+            REG_BINARY => Some(Value::Binary(data.to_vec())),
+            _ => None,
+        };
+        value.unwrap_or_else(|| Value::Raw {
+            kind,
+            data: data.to_vec(),
+        })
+    }
+
+    /// The value's type and data, as RegQueryValueEx returns it from the A or W API.
+    pub fn to_bytes(&self, wide: bool, code_page: u32) -> (u32, Vec<u8>) {
+        let mut out = Vec::new();
+        let kind = match self {
+            Value::String(s) => {
+                encode_str(&mut out, s, wide, code_page);
+                REG_SZ
+            }
+            Value::ExpandString(s) => {
+                encode_str(&mut out, s, wide, code_page);
+                REG_EXPAND_SZ
+            }
+            Value::Dword(n) => {
+                out.extend_from_slice(&n.to_le_bytes());
+                REG_DWORD
+            }
+            Value::MultiString(strings) => {
+                for s in strings {
+                    encode_str(&mut out, s, wide, code_page);
+                }
+                encode_str(&mut out, "", wide, code_page);
+                REG_MULTI_SZ
+            }
+            Value::Binary(data) => {
+                out.extend_from_slice(data);
+                REG_BINARY
+            }
+            Value::Raw { kind, data } => {
+                match *kind {
+                    // Undecodable ANSI strings widen byte for byte.
+                    REG_SZ | REG_EXPAND_SZ | REG_MULTI_SZ if wide => {
+                        for &b in data {
+                            out.extend_from_slice(&(b as u16).to_le_bytes());
+                        }
+                    }
+                    _ => out.extend_from_slice(data),
+                }
+                *kind
+            }
+        };
+        (kind, out)
+    }
+}
+
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Key {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, Key>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub values: BTreeMap<String, Value>,
+}
+
+/// Find an entry by case-insensitive name.
+fn find<'a, V>(map: &'a BTreeMap<String, V>, name: &str) -> Option<(&'a String, &'a V)> {
+    map.iter().find(|(k, _)| k.eq_ignore_ascii_case(name))
+}
+
+fn find_name<V>(map: &BTreeMap<String, V>, name: &str) -> Option<String> {
+    find(map, name).map(|(k, _)| k.clone())
+}
+
+impl Key {
+    fn subkey(&self, name: &str) -> Option<&Key> {
+        find(&self.keys, name).map(|(_, key)| key)
+    }
+
+    fn subkey_mut(&mut self, name: &str) -> Option<&mut Key> {
+        let name = find_name(&self.keys, name)?;
+        self.keys.get_mut(&name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&Value> {
+        find(&self.values, name).map(|(_, value)| value)
+    }
+
+    pub fn set_value(&mut self, name: &str, value: Value) {
+        let name = find_name(&self.values, name).unwrap_or_else(|| name.to_string());
+        self.values.insert(name, value);
+    }
+
+    fn remove_value(&mut self, name: &str) -> Option<Value> {
+        let name = find_name(&self.values, name)?;
+        self.values.remove(&name)
+    }
+}
+
+/// Split a subkey path like "Software\Company\Game" into its names.
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('\\').filter(|name| !name.is_empty())
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Registry {
+    #[serde(with = "tree_as_json")]
+    root: Key,
+    /// Whether the tree changed since it was loaded, so the host knows to save it.
+    pub dirty: bool,
+}
+
+/// Snapshots record the tree as the JSON of a hive, as Key's serde attributes only
+/// suit self-describing formats.
+mod tree_as_json {
+    use super::Key;
+
+    pub fn serialize<S: serde::Serializer>(root: &Key, serializer: S) -> Result<S::Ok, S::Error> {
+        let json = serde_json::to_string(root).map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&json)
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Key, D::Error> {
+        let json = <String as serde::Deserialize>::deserialize(deserializer)?;
+        serde_json::from_str(&json).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Registry {
+            root: Key::default(),
+            dirty: false,
+        };
+        registry.seed();
+        registry.dirty = false;
+        registry
+    }
+}
+
+impl Registry {
+    /// Load a hive as written by to_json().  Keys games expect but the hive lacks
+    /// are added.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let root = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        let mut registry = Registry { root, dirty: false };
+        registry.seed();
+        Ok(registry)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.root).unwrap()
+    }
+
+    /// Fill in keys for the system we pretend to be, matching GetVersion, where
+    /// they're missing.
+    fn seed(&mut self) {
+        let strings: &[(&str, &str, &str)] = &[
+            (
+                r"HKEY_LOCAL_MACHINE\Software\Microsoft\Windows\CurrentVersion",
+                "Version",
+                "Windows 95",
+            ),
+            (
+                r"HKEY_LOCAL_MACHINE\Software\Microsoft\Windows\CurrentVersion",
+                "VersionNumber",
+                "4.00.950",
+            ),
+            (
+                r"HKEY_LOCAL_MACHINE\Software\Microsoft\Windows\CurrentVersion",
+                "ProgramFilesDir",
+                r"C:\Program Files",
+            ),
+            (
+                r"HKEY_LOCAL_MACHINE\Software\Microsoft\Windows\CurrentVersion",
+                "CommonFilesDir",
+                r"C:\Program Files\Common Files",
+            ),
+            (
+                r"HKEY_LOCAL_MACHINE\Software\Microsoft\Windows\CurrentVersion",
+                "SystemRoot",
+                r"C:\Windows",
+            ),
+            (
+                r"HKEY_LOCAL_MACHINE\Software\Microsoft\DirectX",
+                "Version",
+                "4.07.00.0700",
+            ),
+            (r"HKEY_CURRENT_USER\Software", "", ""),
+            (r"HKEY_LOCAL_MACHINE\Software\Classes", "", ""),
+            (r"HKEY_USERS\.Default", "", ""),
+            (r"HKEY_CURRENT_CONFIG", "", ""),
+        ];
+        for &(path, name, value) in strings {
+            let key = self.create(&[], path).0;
+            if !name.is_empty() && key.value(name).is_none() {
+                key.set_value(name, Value::String(value.to_string()));
+            }
+        }
+        let key = self
+            .create(&[], r"HKEY_LOCAL_MACHINE\Software\Microsoft\DirectX")
+            .0;
+        if key.value("InstalledVersion").is_none() {
+            key.set_value(
+                "InstalledVersion",
+                Value::Binary(vec![0, 0, 0, 7, 0, 0, 0, 0]),
+            );
+        }
+    }
+
+    pub fn get(&self, path: &[String]) -> Option<&Key> {
+        path.iter()
+            .try_fold(&self.root, |key, name| key.subkey(name))
+    }
+
+    pub fn get_mut(&mut self, path: &[String]) -> Option<&mut Key> {
+        path.iter()
+            .try_fold(&mut self.root, |key, name| key.subkey_mut(name))
+    }
+
+    /// Resolve a subkey path relative to a key to the names of the keys along it,
+    /// as they are cased in the tree, or None if any is missing.
+    pub fn lookup(&self, base: &[String], subkey: &str) -> Option<Vec<String>> {
+        let mut path = base.to_vec();
+        let mut key = self.get(base)?;
+        for name in split_path(subkey) {
+            let (name, subkey) = find(&key.keys, name)?;
+            path.push(name.clone());
+            key = subkey;
+        }
+        Some(path)
+    }
+
+    /// Open a subkey, creating it and any keys along the way.  Returns the key,
+    /// its path and whether it was created.
+    pub fn create(&mut self, base: &[String], subkey: &str) -> (&mut Key, Vec<String>, bool) {
+        let mut path = base.to_vec();
+        let mut created = false;
+        for name in split_path(subkey) {
+            let key = self.get_mut(&path).unwrap();
+            let name = match find_name(&key.keys, name) {
+                Some(name) => name,
+                None => {
+                    key.keys.insert(name.to_string(), Key::default());
+                    created = true;
+                    name.to_string()
+                }
+            };
+            path.push(name);
+        }
+        self.dirty |= created;
+        (self.get_mut(&path).unwrap(), path, created)
+    }
+}
+
+/// The path of an open or predefined key.
+fn key_path(machine: &Machine, hKey: HKEY) -> Option<Vec<String>> {
+    root_path(hKey).or_else(|| machine.state.advapi32.keys.get(hKey).cloned())
+}
+
+fn open_key(machine: &mut Machine, hKey: HKEY, subkey: &str, phkResult: Option<&mut HKEY>) -> u32 {
+    let Some(base) = key_path(machine, hKey) else {
+        return ERROR_INVALID_HANDLE;
+    };
+    let Some(path) = machine.state.advapi32.registry.lookup(&base, subkey) else {
+        return ERROR_FILE_NOT_FOUND;
+    };
+    let Some(out) = phkResult else {
+        return ERROR_INVALID_PARAMETER;
+    };
+    *out = machine.state.advapi32.keys.add(path);
+    0
+}
+
+fn create_key(
+    machine: &mut Machine,
+    hKey: HKEY,
+    subkey: &str,
+    phkResult: Option<&mut HKEY>,
+    lpdwDisposition: Option<&mut u32>,
+) -> u32 {
+    let Some(base) = key_path(machine, hKey) else {
+        return ERROR_INVALID_HANDLE;
+    };
+    let Some(out) = phkResult else {
+        return ERROR_INVALID_PARAMETER;
+    };
+    let (_, path, created) = machine.state.advapi32.registry.create(&base, subkey);
+    *out = machine.state.advapi32.keys.add(path);
+    if let Some(disposition) = lpdwDisposition {
+        *disposition = if created {
+            REG_CREATED_NEW_KEY
+        } else {
+            REG_OPENED_EXISTING_KEY
+        };
+    }
+    0
+}
+
+/// Copy value data out to lpData, with the size in lpcbData, as RegQueryValueEx
+/// and RegEnumValue do: a null lpData only asks for the size.
+fn write_data(machine: &mut Machine, data: &[u8], lpData: u32, lpcbData: Option<&mut u32>) -> u32 {
+    let Some(cbData) = lpcbData else {
+        return if lpData == 0 {
+            0
+        } else {
+            ERROR_INVALID_PARAMETER
+        };
+    };
+    let size = *cbData;
+    *cbData = data.len() as u32;
+    if lpData == 0 {
+        return 0;
+    }
+    if (size as usize) < data.len() {
+        return ERROR_MORE_DATA;
+    }
+    machine
+        .mem()
+        .sub(lpData, data.len() as u32)
+        .as_mut_slice_todo()
+        .copy_from_slice(data);
+    0
+}
+
+fn query_value(
+    machine: &mut Machine,
+    hKey: HKEY,
+    name: &str,
+    wide: bool,
+    lpType: Option<&mut u32>,
+    lpData: u32,
+    lpcbData: Option<&mut u32>,
+) -> u32 {
+    let Some(path) = key_path(machine, hKey) else {
+        return ERROR_INVALID_HANDLE;
+    };
+    let Some(value) = machine
+        .state
+        .advapi32
+        .registry
+        .get(&path)
+        .and_then(|key| key.value(name))
+    else {
+        return ERROR_FILE_NOT_FOUND;
+    };
+    let (kind, data) = value.to_bytes(wide, machine.state.kernel32.code_page);
+    if let Some(t) = lpType {
+        *t = kind;
+    }
+    write_data(machine, &data, lpData, lpcbData)
+}
+
+fn set_value(
+    machine: &mut Machine,
+    hKey: HKEY,
+    name: &str,
+    wide: bool,
+    kind: u32,
+    lpData: u32,
+    cbData: u32,
+) -> u32 {
+    let Some(path) = key_path(machine, hKey) else {
+        return ERROR_INVALID_HANDLE;
+    };
+    let data = machine.mem().sub(lpData, cbData).as_slice_todo().to_vec();
+    let registry = &mut machine.state.advapi32.registry;
+    let Some(key) = registry.get_mut(&path) else {
+        // The key was deleted while open.
+        return ERROR_FILE_NOT_FOUND;
+    };
+    key.set_value(name, Value::from_bytes(kind, &data, wide));
+    registry.dirty = true;
+    0
+}
+
+/// Write a name out to a buffer of lpcchName characters, as the RegEnum functions
+/// do, updating lpcchName to its length.
+fn write_name(machine: &mut Machine, name: &str, lpName: u32, lpcchName: Option<&mut u32>) -> u32 {
+    let Some(cchName) = lpcchName else {
+        return ERROR_INVALID_PARAMETER;
+    };
+    let mut buf = Vec::new();
+    encode_str(&mut buf, name, false, machine.state.kernel32.code_page);
+    if *cchName < buf.len() as u32 || lpName == 0 {
+        return ERROR_MORE_DATA;
+    }
+    machine
+        .mem()
+        .sub(lpName, buf.len() as u32)
+        .as_mut_slice_todo()
+        .copy_from_slice(&buf);
+    *cchName = buf.len() as u32 - 1;
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn RegOpenKeyExA(
+    machine: &mut Machine,
+    hKey: HKEY,
+    lpSubKey: Option<&str>,
+    ulOptions: u32,
+    samDesired: u32,
+    phkResult: Option<&mut HKEY>,
+) -> u32 {
+    open_key(machine, hKey, lpSubKey.unwrap_or(""), phkResult)
+}
+
+#[win32_derive::dllexport]
+pub fn RegOpenKeyA(
+    machine: &mut Machine,
+    hKey: HKEY,
+    lpSubKey: Option<&str>,
+    phkResult: Option<&mut HKEY>,
+) -> u32 {
+    open_key(machine, hKey, lpSubKey.unwrap_or(""), phkResult)
+}
+
+#[win32_derive::dllexport]
+pub fn RegCreateKeyExA(
+    machine: &mut Machine,
+    hKey: HKEY,
+    lpSubKey: Option<&str>,
+    Reserved: u32,
+    lpClass: Option<&str>,
+    dwOptions: u32,
+    samDesired: u32,
+    lpSecurityAttributes: u32,
+    phkResult: Option<&mut HKEY>,
+    lpdwDisposition: Option<&mut u32>,
+) -> u32 {
+    create_key(
+        machine,
+        hKey,
+        lpSubKey.unwrap_or(""),
+        phkResult,
+        lpdwDisposition,
+    )
+}
+
+#[win32_derive::dllexport]
+pub fn RegCreateKeyA(
+    machine: &mut Machine,
+    hKey: HKEY,
+    lpSubKey: Option<&str>,
+    phkResult: Option<&mut HKEY>,
+) -> u32 {
+    create_key(machine, hKey, lpSubKey.unwrap_or(""), phkResult, None)
+}
+
+#[win32_derive::dllexport]
+pub fn RegCreateKeyExW(
+    machine: &mut Machine,
+    hKey: HKEY,
+    lpSubKey: Option<&Str16>,
+    Reserved: u32,
+    lpClass: Option<&Str16>,
+    dwOptions: u32,
+    samDesired: u32,
+    lpSecurityAttributes: u32,
+    phkResult: Option<&mut HKEY>,
+    lpdwDisposition: Option<&mut u32>,
+) -> u32 {
+    let subkey = lpSubKey.map_or(String::new(), |s| String::from_utf16_lossy(s.buf()));
+    create_key(machine, hKey, &subkey, phkResult, lpdwDisposition)
+}
+
+#[win32_derive::dllexport]
+pub fn RegCloseKey(machine: &mut Machine, hKey: HKEY) -> u32 {
+    if root_path(hKey).is_some() {
+        return 0;
+    }
+    match machine.state.advapi32.keys.remove(hKey) {
+        Some(_) => 0,
+        None => ERROR_INVALID_HANDLE,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn RegFlushKey(machine: &mut Machine, hKey: HKEY) -> u32 {
+    // The host saves the registry when the program exits.
+    match key_path(machine, hKey) {
+        Some(_) => 0,
+        None => ERROR_INVALID_HANDLE,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn RegQueryValueExA(
+    machine: &mut Machine,
+    hKey: HKEY,
+    lpValueName: Option<&str>,
+    lpReserved: u32,
+    lpType: Option<&mut u32>,
+    lpData: u32,
+    lpcbData: Option<&mut u32>,
+) -> u32 {
+    let name = lpValueName.unwrap_or("");
+    query_value(machine, hKey, name, false, lpType, lpData, lpcbData)
+}
+
+#[win32_derive::dllexport]
+pub fn RegQueryValueExW(
+    machine: &mut Machine,
+    hKey: HKEY,
+    lpValueName: Option<&Str16>,
+    lpReserved: u32,
+    lpType: Option<&mut u32>,
+    lpData: u32,
+    lpcbData: Option<&mut u32>,
+) -> u32 {
+    let name = lpValueName.map_or(String::new(), |s| String::from_utf16_lossy(s.buf()));
+    query_value(machine, hKey, &name, true, lpType, lpData, lpcbData)
+}
+
+#[win32_derive::dllexport]
+pub fn RegSetValueExA(
+    machine: &mut Machine,
+    hKey: HKEY,
+    lpValueName: Option<&str>,
+    Reserved: u32,
+    dwType: u32,
+    lpData: u32,
+    cbData: u32,
+) -> u32 {
+    let name = lpValueName.unwrap_or("");
+    set_value(machine, hKey, name, false, dwType, lpData, cbData)
+}
+
+#[win32_derive::dllexport]
+pub fn RegSetValueExW(
+    machine: &mut Machine,
+    hKey: HKEY,
+    lpValueName: Option<&Str16>,
+    lpReserved: u32,
+    lpType: u32,
+    lpData: u32,
+    cbData: u32,
+) -> u32 {
+    let name = lpValueName.map_or(String::new(), |s| String::from_utf16_lossy(s.buf()));
+    set_value(machine, hKey, &name, true, lpType, lpData, cbData)
+}
+
+#[win32_derive::dllexport]
+pub fn RegDeleteValueA(machine: &mut Machine, hKey: HKEY, lpValueName: Option<&str>) -> u32 {
+    let Some(path) = key_path(machine, hKey) else {
+        return ERROR_INVALID_HANDLE;
+    };
+    let registry = &mut machine.state.advapi32.registry;
+    let removed = registry
+        .get_mut(&path)
+        .and_then(|key| key.remove_value(lpValueName.unwrap_or("")));
+    match removed {
+        Some(_) => {
+            registry.dirty = true;
+            0
+        }
+        None => ERROR_FILE_NOT_FOUND,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn RegDeleteKeyA(machine: &mut Machine, hKey: HKEY, lpSubKey: Option<&str>) -> u32 {
+    let Some(base) = key_path(machine, hKey) else {
+        return ERROR_INVALID_HANDLE;
+    };
+    let registry = &mut machine.state.advapi32.registry;
+    let Some(mut path) = registry.lookup(&base, lpSubKey.unwrap_or("")) else {
+        return ERROR_FILE_NOT_FOUND;
+    };
+    // Windows 95 deletes subkeys along with the key; NT refuses.  We're 95.
+    if path.len() <= base.len() {
+        return ERROR_INVALID_PARAMETER;
+    }
+    let name = path.pop().unwrap();
+    registry.get_mut(&path).unwrap().keys.remove(&name);
+    registry.dirty = true;
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn RegEnumKeyExA(
+    machine: &mut Machine,
+    hKey: HKEY,
+    dwIndex: u32,
+    lpName: u32,
+    lpcchName: Option<&mut u32>,
+    lpReserved: u32,
+    lpClass: u32,
+    lpcchClass: Option<&mut u32>,
+    lpftLastWriteTime: u32,
+) -> u32 {
+    let Some(path) = key_path(machine, hKey) else {
+        return ERROR_INVALID_HANDLE;
+    };
+    let Some(name) = machine
+        .state
+        .advapi32
+        .registry
+        .get(&path)
+        .and_then(|key| key.keys.keys().nth(dwIndex as usize))
+        .cloned()
+    else {
+        return ERROR_NO_MORE_ITEMS;
+    };
+    if let Some(cchClass) = lpcchClass {
+        if lpClass != 0 && *cchClass > 0 {
+            machine.mem().put::<u8>(lpClass, 0);
+        }
+        *cchClass = 0;
+    }
+    if lpftLastWriteTime != 0 {
+        machine.mem().put::<u64>(lpftLastWriteTime, 0);
+    }
+    write_name(machine, &name, lpName, lpcchName)
+}
+
+#[win32_derive::dllexport]
+pub fn RegEnumKeyA(
+    machine: &mut Machine,
+    hKey: HKEY,
+    dwIndex: u32,
+    lpName: u32,
+    cchName: u32,
+) -> u32 {
+    let mut cch = cchName;
+    RegEnumKeyExA(
+        machine,
+        hKey,
+        dwIndex,
+        lpName,
+        Some(&mut cch),
+        0,
+        0,
+        None,
+        0,
+    )
+}
+
+#[win32_derive::dllexport]
+pub fn RegEnumValueA(
+    machine: &mut Machine,
+    hKey: HKEY,
+    dwIndex: u32,
+    lpValueName: u32,
+    lpcchValueName: Option<&mut u32>,
+    lpReserved: u32,
+    lpType: Option<&mut u32>,
+    lpData: u32,
+    lpcbData: Option<&mut u32>,
+) -> u32 {
+    let Some(path) = key_path(machine, hKey) else {
+        return ERROR_INVALID_HANDLE;
+    };
+    let code_page = machine.state.kernel32.code_page;
+    let Some((name, (kind, data))) = machine
+        .state
+        .advapi32
+        .registry
+        .get(&path)
+        .and_then(|key| key.values.iter().nth(dwIndex as usize))
+        .map(|(name, value)| (name.clone(), value.to_bytes(false, code_page)))
+    else {
+        return ERROR_NO_MORE_ITEMS;
+    };
+    let err = write_name(machine, &name, lpValueName, lpcchValueName);
+    if err != 0 {
+        return err;
+    }
+    if let Some(t) = lpType {
+        *t = kind;
+    }
+    write_data(machine, &data, lpData, lpcbData)
+}
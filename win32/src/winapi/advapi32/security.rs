@@ -0,0 +1,404 @@
+//! The user account and the security calls games make about it.
+
+use crate::{
+    machine::Machine,
+    winapi::{handle::HANDLE, kernel32::SetLastError},
+};
+use memory::Extensions;
+
+const TRACE_CONTEXT: &'static str = "advapi32/security";
+
+/*
+## Security
+
+There's one user, named as the host configures (see State::user_name), who is
+an administrator on the machine named by kernel32's computer_name.  Games mostly
+only ask for the name, to put in save files or show, or check for administrator
+rights, which we grant.
+*/
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct HTOKENT;
+pub type HTOKEN = HANDLE<HTOKENT>;
+
+/// The one access token, of the process, which all OpenProcessTokens open.
+const PROCESS_TOKEN: HTOKEN = HTOKEN::from_raw(0x70C3_0001);
+
+const ERROR_INVALID_HANDLE: u32 = 6;
+const ERROR_INVALID_PARAMETER: u32 = 87;
+const ERROR_INSUFFICIENT_BUFFER: u32 = 122;
+const ERROR_NO_TOKEN: u32 = 1008;
+const ERROR_NONE_MAPPED: u32 = 1332;
+
+const SID_TYPE_USER: u32 = 1;
+const SID_TYPE_ALIAS: u32 = 4;
+
+/// A SID as bytes: revision 1, the count of subauthorities, the big-endian
+/// identifier authority, then the subauthorities.
+fn sid(authority: u8, subs: &[u32]) -> Vec<u8> {
+    let mut sid = vec![1, subs.len() as u8, 0, 0, 0, 0, 0, authority];
+    for sub in subs {
+        sid.extend_from_slice(&sub.to_le_bytes());
+    }
+    sid
+}
+
+/// The user: S-1-5-21-...-1000, an ordinary machine-local account.
+fn user_sid() -> Vec<u8> {
+    sid(5, &[21, 0x3BDA_2F1A, 0x4628_0D73, 0x28A6_4C92, 1000])
+}
+
+/// BUILTIN\Administrators, S-1-5-32-544.
+fn administrators_sid() -> Vec<u8> {
+    sid(5, &[32, 544])
+}
+
+/// BUILTIN\Users, S-1-5-32-545.
+fn users_sid() -> Vec<u8> {
+    sid(5, &[32, 545])
+}
+
+/// Read the SID at addr, if it looks like one.
+fn read_sid(machine: &Machine, addr: u32) -> Option<Vec<u8>> {
+    if addr == 0 {
+        return None;
+    }
+    let mem = machine.mem();
+    let count = mem.get_pod::<u8>(addr + 1);
+    if mem.get_pod::<u8>(addr) != 1 || count > 15 {
+        return None;
+    }
+    Some(mem.sub(addr, 8 + count as u32 * 4).as_slice_todo().to_vec())
+}
+
+/// Copy a string out to a buffer of *size characters, as GetUserName etc. do:
+/// returning whether it fit, and setting *size to the length written, or needed.
+fn write_str(
+    machine: &mut Machine,
+    str: &str,
+    buf: u32,
+    size: &mut u32,
+    wide: bool,
+    with_nul: bool,
+) -> bool {
+    let needed = str.len() as u32 + 1;
+    if *size < needed || buf == 0 {
+        *size = needed;
+        return false;
+    }
+    let mem = machine.mem();
+    if wide {
+        for (i, c) in str.encode_utf16().chain([0]).enumerate() {
+            mem.put::<u16>(buf + i as u32 * 2, c);
+        }
+    } else {
+        let out = mem.sub(buf, needed).as_mut_slice_todo();
+        out[..str.len()].copy_from_slice(str.as_bytes());
+        out[str.len()] = 0;
+    }
+    *size = if with_nul { needed } else { needed - 1 };
+    true
+}
+
+fn get_user_name(
+    machine: &mut Machine,
+    lpBuffer: u32,
+    pcbBuffer: Option<&mut u32>,
+    wide: bool,
+) -> bool {
+    let Some(size) = pcbBuffer else {
+        SetLastError(machine, ERROR_INVALID_PARAMETER);
+        return false;
+    };
+    let name = machine.state.advapi32.user_name.clone();
+    // Unlike most, the size returned counts the nul.
+    if !write_str(machine, &name, lpBuffer, size, wide, true) {
+        SetLastError(machine, ERROR_INSUFFICIENT_BUFFER);
+        return false;
+    }
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn GetUserNameA(machine: &mut Machine, lpBuffer: u32, pcbBuffer: Option<&mut u32>) -> bool {
+    get_user_name(machine, lpBuffer, pcbBuffer, false)
+}
+
+#[win32_derive::dllexport]
+pub fn GetUserNameW(machine: &mut Machine, lpBuffer: u32, pcbBuffer: Option<&mut u32>) -> bool {
+    get_user_name(machine, lpBuffer, pcbBuffer, true)
+}
+
+#[win32_derive::dllexport]
+pub fn OpenProcessToken(
+    machine: &mut Machine,
+    ProcessHandle: u32,
+    DesiredAccess: u32,
+    TokenHandle: Option<&mut HTOKEN>,
+) -> bool {
+    let Some(out) = TokenHandle else {
+        SetLastError(machine, ERROR_INVALID_PARAMETER);
+        return false;
+    };
+    *out = PROCESS_TOKEN;
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn OpenThreadToken(
+    machine: &mut Machine,
+    ThreadHandle: u32,
+    DesiredAccess: u32,
+    OpenAsSelf: bool,
+    TokenHandle: Option<&mut HTOKEN>,
+) -> bool {
+    // Threads don't impersonate anyone, so callers fall back to the process token.
+    SetLastError(machine, ERROR_NO_TOKEN);
+    false
+}
+
+#[win32_derive::dllexport]
+pub fn GetTokenInformation(
+    machine: &mut Machine,
+    TokenHandle: HTOKEN,
+    TokenInformationClass: u32,
+    TokenInformation: u32,
+    TokenInformationLength: u32,
+    ReturnLength: Option<&mut u32>,
+) -> bool {
+    const TOKEN_USER: u32 = 1;
+    const TOKEN_ELEVATION_TYPE: u32 = 18;
+    const TOKEN_ELEVATION: u32 = 20;
+    const TOKEN_ELEVATION_TYPE_DEFAULT: u32 = 1;
+
+    if TokenHandle != PROCESS_TOKEN {
+        SetLastError(machine, ERROR_INVALID_HANDLE);
+        return false;
+    }
+    let mut data = Vec::new();
+    match TokenInformationClass {
+        TOKEN_USER => {
+            // A TOKEN_USER struct: a SID_AND_ATTRIBUTES pointing at the SID, which follows.
+            data.extend_from_slice(&(TokenInformation + 8).to_le_bytes());
+            data.extend_from_slice(&0u32.to_le_bytes());
+            data.extend(user_sid());
+        }
+        TOKEN_ELEVATION_TYPE => data.extend_from_slice(&TOKEN_ELEVATION_TYPE_DEFAULT.to_le_bytes()),
+        TOKEN_ELEVATION => data.extend_from_slice(&1u32.to_le_bytes()),
+        _ => {
+            log::warn!("GetTokenInformation({TokenInformationClass}) unimplemented");
+            SetLastError(machine, ERROR_INVALID_PARAMETER);
+            return false;
+        }
+    }
+    if let Some(len) = ReturnLength {
+        *len = data.len() as u32;
+    }
+    if TokenInformationLength < data.len() as u32 || TokenInformation == 0 {
+        SetLastError(machine, ERROR_INSUFFICIENT_BUFFER);
+        return false;
+    }
+    machine
+        .mem()
+        .sub(TokenInformation, data.len() as u32)
+        .as_mut_slice_todo()
+        .copy_from_slice(&data);
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn LookupAccountNameA(
+    machine: &mut Machine,
+    lpSystemName: Option<&str>,
+    lpAccountName: Option<&str>,
+    Sid: u32,
+    cbSid: Option<&mut u32>,
+    ReferencedDomainName: u32,
+    cchReferencedDomainName: Option<&mut u32>,
+    peUse: Option<&mut u32>,
+) -> bool {
+    let computer = machine.state.kernel32.computer_name.clone();
+    let user = &machine.state.advapi32.user_name;
+    // The name may be qualified with its domain, as "COMPUTER\user".
+    let name = lpAccountName.unwrap_or("");
+    let name = match name.split_once('\\') {
+        Some((domain, name)) if domain.eq_ignore_ascii_case(&computer) => name,
+        Some(_) => "",
+        None => name,
+    };
+    if !name.eq_ignore_ascii_case(user) {
+        SetLastError(machine, ERROR_NONE_MAPPED);
+        return false;
+    }
+    let (Some(cbSid), Some(cchDomain)) = (cbSid, cchReferencedDomainName) else {
+        SetLastError(machine, ERROR_INVALID_PARAMETER);
+        return false;
+    };
+    let sid = user_sid();
+    let sid_fits = *cbSid >= sid.len() as u32 && Sid != 0;
+    let domain_fits = write_str(
+        machine,
+        &computer,
+        ReferencedDomainName,
+        cchDomain,
+        false,
+        false,
+    );
+    if !sid_fits || !domain_fits {
+        *cbSid = sid.len() as u32;
+        SetLastError(machine, ERROR_INSUFFICIENT_BUFFER);
+        return false;
+    }
+    machine
+        .mem()
+        .sub(Sid, sid.len() as u32)
+        .as_mut_slice_todo()
+        .copy_from_slice(&sid);
+    *cbSid = sid.len() as u32;
+    if let Some(use_) = peUse {
+        *use_ = SID_TYPE_USER;
+    }
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn LookupAccountSidA(
+    machine: &mut Machine,
+    lpSystemName: Option<&str>,
+    Sid: u32,
+    Name: u32,
+    cchName: Option<&mut u32>,
+    ReferencedDomainName: u32,
+    cchReferencedDomainName: Option<&mut u32>,
+    peUse: Option<&mut u32>,
+) -> bool {
+    let sid = read_sid(machine, Sid);
+    let (name, domain, kind) = if sid == Some(user_sid()) {
+        (
+            machine.state.advapi32.user_name.clone(),
+            machine.state.kernel32.computer_name.clone(),
+            SID_TYPE_USER,
+        )
+    } else if sid == Some(administrators_sid()) {
+        ("Administrators".into(), "BUILTIN".into(), SID_TYPE_ALIAS)
+    } else if sid == Some(users_sid()) {
+        ("Users".into(), "BUILTIN".into(), SID_TYPE_ALIAS)
+    } else {
+        SetLastError(machine, ERROR_NONE_MAPPED);
+        return false;
+    };
+    let (Some(cchName), Some(cchDomain)) = (cchName, cchReferencedDomainName) else {
+        SetLastError(machine, ERROR_INVALID_PARAMETER);
+        return false;
+    };
+    let name_fits = write_str(machine, &name, Name, cchName, false, false);
+    let domain_fits = write_str(
+        machine,
+        &domain,
+        ReferencedDomainName,
+        cchDomain,
+        false,
+        false,
+    );
+    if !name_fits || !domain_fits {
+        SetLastError(machine, ERROR_INSUFFICIENT_BUFFER);
+        return false;
+    }
+    if let Some(use_) = peUse {
+        *use_ = kind;
+    }
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn AllocateAndInitializeSid(
+    machine: &mut Machine,
+    pIdentifierAuthority: Option<&[u8; 6]>,
+    nSubAuthorityCount: u32,
+    nSubAuthority0: u32,
+    nSubAuthority1: u32,
+    nSubAuthority2: u32,
+    nSubAuthority3: u32,
+    nSubAuthority4: u32,
+    nSubAuthority5: u32,
+    nSubAuthority6: u32,
+    nSubAuthority7: u32,
+    pSid: Option<&mut u32>,
+) -> bool {
+    let (Some(authority), Some(out), 1..=8) = (pIdentifierAuthority, pSid, nSubAuthorityCount)
+    else {
+        SetLastError(machine, ERROR_INVALID_PARAMETER);
+        return false;
+    };
+    let subs = [
+        nSubAuthority0,
+        nSubAuthority1,
+        nSubAuthority2,
+        nSubAuthority3,
+        nSubAuthority4,
+        nSubAuthority5,
+        nSubAuthority6,
+        nSubAuthority7,
+    ];
+    let mut sid = vec![1, nSubAuthorityCount as u8];
+    sid.extend_from_slice(authority);
+    for sub in &subs[..nSubAuthorityCount as usize] {
+        sid.extend_from_slice(&sub.to_le_bytes());
+    }
+    let heap = machine
+        .state
+        .kernel32
+        .get_process_heap(&mut machine.emu.memory);
+    let addr = heap.alloc(machine.emu.memory.mem(), sid.len() as u32);
+    machine
+        .mem()
+        .sub(addr, sid.len() as u32)
+        .as_mut_slice_todo()
+        .copy_from_slice(&sid);
+    *out = addr;
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn FreeSid(machine: &mut Machine, pSid: u32) -> u32 {
+    let heap = machine
+        .state
+        .kernel32
+        .get_process_heap(&mut machine.emu.memory);
+    heap.free(machine.emu.memory.mem(), pSid);
+    0 // null
+}
+
+#[win32_derive::dllexport]
+pub fn IsValidSid(machine: &mut Machine, pSid: u32) -> bool {
+    read_sid(machine, pSid).is_some()
+}
+
+#[win32_derive::dllexport]
+pub fn GetLengthSid(machine: &mut Machine, pSid: u32) -> u32 {
+    read_sid(machine, pSid).map_or(0, |sid| sid.len() as u32)
+}
+
+#[win32_derive::dllexport]
+pub fn EqualSid(machine: &mut Machine, pSid1: u32, pSid2: u32) -> bool {
+    let sid = read_sid(machine, pSid1);
+    sid.is_some() && sid == read_sid(machine, pSid2)
+}
+
+#[win32_derive::dllexport]
+pub fn CheckTokenMembership(
+    machine: &mut Machine,
+    TokenHandle: HTOKEN,
+    SidToCheck: u32,
+    IsMember: Option<&mut u32>,
+) -> bool {
+    let Some(is_member) = IsMember else {
+        SetLastError(machine, ERROR_INVALID_PARAMETER);
+        return false;
+    };
+    let sid = read_sid(machine, SidToCheck);
+    *is_member = [user_sid(), administrators_sid(), users_sid()]
+        .iter()
+        .any(|s| Some(s) == sid.as_ref()) as u32;
+    true
+}
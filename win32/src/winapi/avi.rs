@@ -0,0 +1,598 @@
+//! AVI files: the RIFF layout and decoding of video frames, as used by both
+//! quartz and avifil32.
+//! This module does not become its own DLL.
+
+use super::bitmap::BI;
+
+/// The fields of an AVI stream header ('strh' chunk).
+#[derive(Debug, Clone, Default)]
+pub struct StreamHeader {
+    /// Stream type, e.g. b"vids" or b"auds".
+    pub kind: [u8; 4],
+    pub handler: [u8; 4],
+    pub flags: u32,
+    pub priority: u16,
+    pub language: u16,
+    pub initial_frames: u32,
+    pub scale: u32,
+    pub rate: u32,
+    pub start: u32,
+    pub length: u32,
+    pub suggested_buffer_size: u32,
+    pub quality: u32,
+    pub sample_size: u32,
+    /// left, top, right, bottom.
+    pub frame: [i16; 4],
+}
+
+pub struct Stream {
+    pub header: StreamHeader,
+    /// The raw format ('strf' chunk): a BITMAPINFO for video, WAVEFORMATEX for audio.
+    pub format: Vec<u8>,
+    /// The stream's data chunks, in file order.
+    pub chunks: Vec<Vec<u8>>,
+}
+
+impl Stream {
+    pub fn is_video(&self) -> bool {
+        &self.header.kind == b"vids"
+    }
+}
+
+pub struct Avi {
+    /// Fields of the main header ('avih' chunk).
+    pub usec_per_frame: u32,
+    pub max_bytes_per_sec: u32,
+    pub flags: u32,
+    pub total_frames: u32,
+    pub suggested_buffer_size: u32,
+    pub width: u32,
+    pub height: u32,
+    pub streams: Vec<Stream>,
+}
+
+fn u16_at(b: &[u8], ofs: usize) -> u16 {
+    u16::from_le_bytes([b[ofs], b[ofs + 1]])
+}
+
+fn u32_at(b: &[u8], ofs: usize) -> u32 {
+    u32::from_le_bytes(b[ofs..ofs + 4].try_into().unwrap())
+}
+
+/// Iterate the chunks of a RIFF list body as (id, body) pairs.
+fn chunks(buf: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut ofs = 0;
+    std::iter::from_fn(move || {
+        if ofs + 8 > buf.len() {
+            return None;
+        }
+        let id = &buf[ofs..ofs + 4];
+        let len = u32_at(buf, ofs + 4) as usize;
+        let body = &buf[ofs + 8..std::cmp::min(ofs + 8 + len, buf.len())];
+        // Chunks are padded to even sizes.
+        ofs += 8 + len + (len & 1);
+        Some((id, body))
+    })
+}
+
+impl Avi {
+    pub fn parse(buf: &[u8]) -> Option<Avi> {
+        if buf.len() < 12 || &buf[0..4] != b"RIFF" || &buf[8..12] != b"AVI " {
+            return None;
+        }
+        let mut avi = Avi {
+            usec_per_frame: 0,
+            max_bytes_per_sec: 0,
+            flags: 0,
+            total_frames: 0,
+            suggested_buffer_size: 0,
+            width: 0,
+            height: 0,
+            streams: Vec::new(),
+        };
+        // Files over 1GB continue in further 'AVIX' RIFF chunks, which hold more movi data.
+        for (id, body) in chunks(buf) {
+            if id != b"RIFF" || body.len() < 4 {
+                continue;
+            }
+            for (id, body) in chunks(&body[4..]) {
+                if id != b"LIST" || body.len() < 4 {
+                    continue;
+                }
+                match &body[0..4] {
+                    b"hdrl" => avi.parse_hdrl(&body[4..]),
+                    b"movi" => avi.parse_movi(&body[4..]),
+                    _ => {}
+                }
+            }
+        }
+        if avi.streams.is_empty() {
+            return None;
+        }
+        Some(avi)
+    }
+
+    fn parse_hdrl(&mut self, buf: &[u8]) {
+        for (id, body) in chunks(buf) {
+            match id {
+                b"avih" if body.len() >= 40 => {
+                    self.usec_per_frame = u32_at(body, 0);
+                    self.max_bytes_per_sec = u32_at(body, 4);
+                    self.flags = u32_at(body, 12);
+                    self.total_frames = u32_at(body, 16);
+                    self.suggested_buffer_size = u32_at(body, 28);
+                    self.width = u32_at(body, 32);
+                    self.height = u32_at(body, 36);
+                }
+                b"LIST" if body.len() >= 4 && &body[0..4] == b"strl" => {
+                    let mut header = None;
+                    let mut format = Vec::new();
+                    for (id, body) in chunks(&body[4..]) {
+                        match id {
+                            b"strh" if body.len() >= 48 => {
+                                header = Some(StreamHeader {
+                                    kind: body[0..4].try_into().unwrap(),
+                                    handler: body[4..8].try_into().unwrap(),
+                                    flags: u32_at(body, 8),
+                                    priority: u16_at(body, 12),
+                                    language: u16_at(body, 14),
+                                    initial_frames: u32_at(body, 16),
+                                    scale: u32_at(body, 20),
+                                    rate: u32_at(body, 24),
+                                    start: u32_at(body, 28),
+                                    length: u32_at(body, 32),
+                                    suggested_buffer_size: u32_at(body, 36),
+                                    quality: u32_at(body, 40),
+                                    sample_size: u32_at(body, 44),
+                                    frame: if body.len() >= 56 {
+                                        [0, 2, 4, 6].map(|i| u16_at(body, 48 + i) as i16)
+                                    } else {
+                                        [0; 4]
+                                    },
+                                });
+                            }
+                            b"strf" => format = body.to_vec(),
+                            _ => {}
+                        }
+                    }
+                    // Streams are numbered by their order, so keep a placeholder for a bad one.
+                    self.streams.push(Stream {
+                        header: header.unwrap_or_default(),
+                        format,
+                        chunks: Vec::new(),
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn parse_movi(&mut self, buf: &[u8]) {
+        for (id, body) in chunks(buf) {
+            if id == b"LIST" {
+                // 'rec ' lists group the chunks of interleaved streams.
+                if body.len() >= 4 {
+                    self.parse_movi(&body[4..]);
+                }
+                continue;
+            }
+            // Data chunk ids are the stream number in two decimal digits and a type,
+            // like '00dc'; palette changes ('xxpc') aren't supported.
+            let Ok(num) = std::str::from_utf8(&id[0..2])
+                .unwrap_or("")
+                .parse::<usize>()
+            else {
+                continue;
+            };
+            if &id[2..4] == b"pc" {
+                continue;
+            }
+            if let Some(stream) = self.streams.get_mut(num) {
+                stream.chunks.push(body.to_vec());
+            }
+        }
+    }
+
+    /// The first video stream.
+    pub fn video(&self) -> Option<&Stream> {
+        self.streams.iter().find(|s| s.is_video())
+    }
+
+    /// Duration of a frame of a video stream, in microseconds.
+    pub fn frame_usec(&self, stream: &Stream) -> u32 {
+        let header = &stream.header;
+        if header.rate != 0 && header.scale != 0 {
+            (header.scale as u64 * 1_000_000 / header.rate as u64) as u32
+        } else if self.usec_per_frame != 0 {
+            self.usec_per_frame
+        } else {
+            // A guess at the most common rate.
+            66_666
+        }
+    }
+}
+
+/// The Cinepak codec's FOURCC, as found in biCompression.
+const BI_CVID: u32 = u32::from_le_bytes(*b"cvid");
+
+enum Codec {
+    Rgb,
+    Rle8,
+    /// Codebooks of each strip, kept from frame to frame.
+    Cinepak(Vec<Codebooks>),
+}
+
+/// Decoder of the frames of a video stream into RGBA pixels, for the codecs we
+/// know: uncompressed DIBs, RLE8 and Cinepak.
+pub struct VideoDecoder {
+    pub width: u32,
+    pub height: u32,
+    bit_count: u16,
+    codec: Codec,
+    top_down: bool,
+    /// Colors for 8bpp, already as RGBA.
+    palette: Vec<[u8; 4]>,
+    /// The current frame, top row first.  RLE frames only update parts of it.
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl VideoDecoder {
+    /// Make a decoder for a BITMAPINFO, or None for codecs we can't decode.
+    pub fn new(format: &[u8]) -> Option<VideoDecoder> {
+        if format.len() < 40 {
+            return None;
+        }
+        let header_size = u32_at(format, 0) as usize;
+        let width = u32_at(format, 4);
+        let height = u32_at(format, 8) as i32;
+        let bit_count = u16_at(format, 14);
+        let compression = u32_at(format, 16);
+        let codec = match BI::try_from(compression) {
+            Ok(BI::RGB) if matches!(bit_count, 8 | 16 | 24 | 32) => Codec::Rgb,
+            Ok(BI::RLE8) if bit_count == 8 => Codec::Rle8,
+            _ if compression == BI_CVID && bit_count >= 24 => Codec::Cinepak(Vec::new()),
+            _ => {
+                let fourcc = &format[16..20];
+                log::warn!(
+                    "unsupported video format {:?} {bit_count}bpp",
+                    String::from_utf8_lossy(fourcc)
+                );
+                return None;
+            }
+        };
+        let palette = if bit_count == 8 {
+            let count = match u32_at(format, 32) {
+                0 => 256,
+                n => n.min(256) as usize,
+            };
+            let table = format.get(header_size..).unwrap_or(&[]);
+            let mut palette = table
+                .chunks_exact(4)
+                .take(count)
+                .map(|c| [c[2], c[1], c[0], 255])
+                .collect::<Vec<_>>();
+            palette.resize(256, [0, 0, 0, 255]);
+            palette
+        } else {
+            Vec::new()
+        };
+        let abs_height = height.unsigned_abs();
+        Some(VideoDecoder {
+            width,
+            height: abs_height,
+            bit_count,
+            codec,
+            top_down: height < 0,
+            palette,
+            pixels: vec![[0, 0, 0, 255]; width as usize * abs_height as usize],
+        })
+    }
+
+    /// Index into pixels of a row counted from the bottom, as DIBs store them.
+    fn row_start(&self, row: usize) -> usize {
+        let y = if self.top_down {
+            row
+        } else {
+            self.height as usize - 1 - row
+        };
+        y * self.width as usize
+    }
+
+    /// Whether each frame is complete in itself, so frames can be decoded out of order.
+    pub fn is_intra_only(&self) -> bool {
+        matches!(self.codec, Codec::Rgb)
+    }
+
+    /// Forget the frames decoded so far, to start again from the first.
+    pub fn reset(&mut self) {
+        self.pixels.fill([0, 0, 0, 255]);
+        if let Codec::Cinepak(strips) = &mut self.codec {
+            strips.clear();
+        }
+    }
+
+    /// Decode a frame's data over the current frame.
+    pub fn decode(&mut self, data: &[u8]) {
+        // An empty chunk is a dropped frame, which repeats the last one.
+        if data.is_empty() {
+            return;
+        }
+        match &mut self.codec {
+            Codec::Rgb => self.decode_rgb(data),
+            Codec::Rle8 => self.decode_rle8(data),
+            Codec::Cinepak(strips) => {
+                cinepak_frame(strips, &mut self.pixels, self.width, self.height, data)
+            }
+        }
+    }
+
+    fn decode_rgb(&mut self, data: &[u8]) {
+        let width = self.width as usize;
+        let stride = ((width * self.bit_count as usize + 31) & !31) >> 3;
+        for row in 0..self.height as usize {
+            let Some(src) = data.get(row * stride..(row + 1) * stride) else {
+                break;
+            };
+            let start = self.row_start(row);
+            let dst = &mut self.pixels[start..start + width];
+            match self.bit_count {
+                8 => {
+                    for (d, &p) in dst.iter_mut().zip(src) {
+                        *d = self.palette[p as usize];
+                    }
+                }
+                16 => {
+                    // RGB555.
+                    for (d, p) in dst.iter_mut().zip(src.chunks_exact(2)) {
+                        let p = u16::from_le_bytes([p[0], p[1]]);
+                        let c = |shift: u16| (((p >> shift) & 0x1f) as u32 * 255 / 31) as u8;
+                        *d = [c(10), c(5), c(0), 255];
+                    }
+                }
+                24 => {
+                    for (d, p) in dst.iter_mut().zip(src.chunks_exact(3)) {
+                        *d = [p[2], p[1], p[0], 255];
+                    }
+                }
+                32 => {
+                    for (d, p) in dst.iter_mut().zip(src.chunks_exact(4)) {
+                        *d = [p[2], p[1], p[0], 255];
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    fn decode_rle8(&mut self, data: &[u8]) {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let (mut x, mut row) = (0usize, 0usize);
+        let mut i = data.iter().copied();
+        let put = |x: usize, row: usize, p: u8, pixels: &mut Vec<[u8; 4]>| {
+            if x < width && row < height {
+                let y = if self.top_down { row } else { height - 1 - row };
+                pixels[y * width + x] = self.palette[p as usize];
+            }
+        };
+        while let (Some(count), Some(val)) = (i.next(), i.next()) {
+            if count > 0 {
+                for _ in 0..count {
+                    put(x, row, val, &mut self.pixels);
+                    x += 1;
+                }
+                continue;
+            }
+            match val {
+                0 => {
+                    x = 0;
+                    row += 1;
+                }
+                1 => break,
+                2 => {
+                    let (Some(dx), Some(dy)) = (i.next(), i.next()) else {
+                        break;
+                    };
+                    x += dx as usize;
+                    row += dy as usize;
+                }
+                n => {
+                    for _ in 0..n {
+                        let Some(p) = i.next() else {
+                            return;
+                        };
+                        put(x, row, p, &mut self.pixels);
+                        x += 1;
+                    }
+                    // Literal runs are padded to even lengths.
+                    if n & 1 != 0 {
+                        i.next();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/*
+## Cinepak
+
+A frame is a run of horizontal strips, each made of 4x4 blocks.  A block is drawn
+either from one "V1" codebook entry scaled up 2x, or from four "V4" entries, one
+per 2x2 quarter; an entry is four colors, given as four luma samples and shared
+chroma.  Each strip has its own pair of codebooks, which chunks in the frame
+replace wholly or in part, and which otherwise carry over from the last frame or,
+if the frame's flags say so, from the strip above.
+*/
+
+/// A Cinepak codebook entry: the colors of a 2x2 block, in reading order.
+type Entry = [[u8; 4]; 4];
+
+#[derive(Clone)]
+struct Codebooks {
+    v1: [Entry; 256],
+    v4: [Entry; 256],
+}
+
+impl Default for Codebooks {
+    fn default() -> Self {
+        Codebooks {
+            v1: [[[0, 0, 0, 255]; 4]; 256],
+            v4: [[[0, 0, 0, 255]; 4]; 256],
+        }
+    }
+}
+
+fn u16_be(b: &[u8]) -> u16 {
+    u16::from_be_bytes([b[0], b[1]])
+}
+
+fn u24_be(b: &[u8]) -> usize {
+    (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize
+}
+
+/// Bits of the big-endian flag words interleaved with Cinepak data, high bit first.
+struct Flags {
+    word: u32,
+    mask: u32,
+}
+
+impl Flags {
+    fn next(&mut self, data: &mut &[u8]) -> Option<bool> {
+        self.mask >>= 1;
+        if self.mask == 0 {
+            self.word = u32::from_be_bytes(data.get(..4)?.try_into().unwrap());
+            *data = &data[4..];
+            self.mask = 0x8000_0000;
+        }
+        Some(self.word & self.mask != 0)
+    }
+}
+
+fn cinepak_frame(
+    strips: &mut Vec<Codebooks>,
+    pixels: &mut [[u8; 4]],
+    width: u32,
+    height: u32,
+    data: &[u8],
+) {
+    if data.len() < 10 {
+        return;
+    }
+    let flags = data[0];
+    let count = u16_be(&data[8..]) as usize;
+    let mut data = &data[10..];
+    let mut y0 = 0;
+    for i in 0..count {
+        if data.len() < 12 {
+            break;
+        }
+        let size = u24_be(&data[1..]).clamp(12, data.len());
+        let (mut top, left, mut bottom, right) = (
+            u16_be(&data[4..]) as u32,
+            u16_be(&data[6..]) as u32,
+            u16_be(&data[8..]) as u32,
+            u16_be(&data[10..]) as u32,
+        );
+        // A zero top means the strip's position is relative to the one above.
+        if top == 0 {
+            top = y0;
+            bottom += y0;
+        }
+        if strips.len() <= i {
+            strips.push(Codebooks::default());
+        }
+        if i > 0 && flags & 0x01 == 0 {
+            strips[i] = strips[i - 1].clone();
+        }
+        let books = &mut strips[i];
+        let mut chunks = &data[12..size];
+        while chunks.len() >= 4 {
+            let id = chunks[0];
+            let len = u24_be(&chunks[1..]).clamp(4, chunks.len());
+            let body = &chunks[4..len];
+            match id {
+                0x20 | 0x21 | 0x24 | 0x25 => cinepak_codebook(&mut books.v4, id, body),
+                0x22 | 0x23 | 0x26 | 0x27 => cinepak_codebook(&mut books.v1, id, body),
+                0x30 | 0x31 | 0x32 => {
+                    let rect = (left, top, right.min(width), bottom.min(height));
+                    cinepak_vectors(books, pixels, width, rect, id, body);
+                }
+                _ => log::warn!("cinepak: unknown chunk {id:#x}"),
+            }
+            chunks = &chunks[len..];
+        }
+        y0 = bottom;
+        data = &data[size..];
+    }
+}
+
+/// Update codebook entries: all of them, or with id bit 0 set, those flagged.
+/// With id bit 2 set, entries are luma only.
+fn cinepak_codebook(book: &mut [Entry; 256], id: u8, mut data: &[u8]) {
+    let len = if id & 0x04 != 0 { 4 } else { 6 };
+    let mut flags = Flags { word: 0, mask: 0 };
+    for entry in book.iter_mut() {
+        if id & 0x01 != 0 {
+            match flags.next(&mut data) {
+                Some(true) => {}
+                Some(false) => continue,
+                None => break,
+            }
+        }
+        if data.len() < len {
+            break;
+        }
+        let (u, v) = if len == 6 {
+            (data[4] as i8 as i32, data[5] as i8 as i32)
+        } else {
+            (0, 0)
+        };
+        for (color, &y) in entry.iter_mut().zip(&data[..4]) {
+            let y = y as i32;
+            let c = |val: i32| val.clamp(0, 255) as u8;
+            *color = [c(y + 2 * v), c(y - u / 2 - v), c(y + 2 * u), 255];
+        }
+        data = &data[len..];
+    }
+}
+
+/// Draw a strip's blocks.  With id bit 0 set, only flagged blocks are drawn; with
+/// bit 1 set, every block is V1, else flags choose between V1 and V4.
+fn cinepak_vectors(
+    books: &Codebooks,
+    pixels: &mut [[u8; 4]],
+    width: u32,
+    (left, top, right, bottom): (u32, u32, u32, u32),
+    id: u8,
+    mut data: &[u8],
+) -> Option<()> {
+    let mut flags = Flags { word: 0, mask: 0 };
+    for y in (top..bottom).step_by(4) {
+        for x in (left..right).step_by(4) {
+            if id & 0x01 != 0 && !flags.next(&mut data)? {
+                continue;
+            }
+            let v1 = id & 0x02 != 0 || !flags.next(&mut data)?;
+            let quarters: [Entry; 4] = if v1 {
+                let entry = books.v1[*data.first()? as usize];
+                data = &data[1..];
+                entry.map(|color| [color; 4])
+            } else {
+                let indices = data.get(..4)?;
+                let quarters = [0, 1, 2, 3].map(|i| books.v4[indices[i] as usize]);
+                data = &data[4..];
+                quarters
+            };
+            for (q, quarter) in quarters.iter().enumerate() {
+                for (k, &color) in quarter.iter().enumerate() {
+                    let px = x + (q as u32 & 1) * 2 + (k as u32 & 1);
+                    let py = y + (q as u32 >> 1) * 2 + (k as u32 >> 1);
+                    if px < right && py < bottom {
+                        pixels[(py * width + px) as usize] = color;
+                    }
+                }
+            }
+        }
+    }
+    Some(())
+}
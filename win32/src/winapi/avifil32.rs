@@ -0,0 +1,942 @@
+//! AVIFile: reading AVI files a stream at a time, as games do for their animations.
+
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+
+use super::avi::{Avi, VideoDecoder};
+use super::bitmap::{encode_dib_row, BITMAPINFOHEADER};
+use super::com;
+use super::heap::Heap;
+use super::kernel32;
+use super::types::DWORD;
+use crate::{machine::Emulator, machine::Machine, winapi::vtable};
+use memory::{Extensions, Pod};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const TRACE_CONTEXT: &'static str = "avifil32";
+
+/*
+## Streams and frames
+
+Files are read and parsed whole when opened, and their streams share the parsed
+file, so a stream outlives the release of its file as it does on Windows.
+
+PAVIFILE and PAVISTREAM are COM objects whose methods lead to the same code as the
+API functions.  A PGETFRAME holds a decoder for the stream (see avi::VideoDecoder
+for the codecs it knows) and a DIB in guest memory that each AVIStreamGetFrame
+decodes into; it is only a handle, as IGetFrame's methods go unused in practice.
+
+Files can't be opened for writing, as the host only offers reading.
+
+Snapshots record the paths of open files, which are read and parsed again on
+load.  A decoder starts over, catching up from the first frame as needed.
+*/
+
+const fn make_avierror(code: u32) -> u32 {
+    (1 << 31) | (0x4 << 16) | (0x4000 + code)
+}
+
+const AVIERR_OK: u32 = 0;
+const AVIERR_BADFORMAT: u32 = make_avierror(102);
+const AVIERR_BADPARAM: u32 = make_avierror(106);
+const AVIERR_BADSIZE: u32 = make_avierror(107);
+const AVIERR_BADHANDLE: u32 = make_avierror(108);
+const AVIERR_FILEOPEN: u32 = make_avierror(111);
+const AVIERR_READONLY: u32 = make_avierror(114);
+const AVIERR_NODATA: u32 = make_avierror(115);
+const AVIERR_BUFFERTOOSMALL: u32 = make_avierror(116);
+
+const OF_WRITE: u32 = 0x0001;
+const OF_READWRITE: u32 = 0x0002;
+const OF_CREATE: u32 = 0x1000;
+
+const AVIFILECAPS_CANREAD: u32 = 0x0001;
+
+/// lpbiWanted asking for whatever suits the display.
+const AVIGETFRAMEF_BESTDISPLAYFMT: u32 = 1;
+/// AVIStreamRead lSamples asking for as many samples as fit the buffer.
+const AVISTREAMREAD_CONVENIENT: i32 = -1;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct AVIFILEINFOA {
+    pub dwMaxBytesPerSec: DWORD,
+    pub dwFlags: DWORD,
+    pub dwCaps: DWORD,
+    pub dwStreams: DWORD,
+    pub dwSuggestedBufferSize: DWORD,
+    pub dwWidth: DWORD,
+    pub dwHeight: DWORD,
+    pub dwScale: DWORD,
+    pub dwRate: DWORD,
+    pub dwLength: DWORD,
+    pub dwEditCount: DWORD,
+    pub szFileType: [u8; 64],
+}
+unsafe impl Pod for AVIFILEINFOA {}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct AVISTREAMINFOA {
+    pub fccType: DWORD,
+    pub fccHandler: DWORD,
+    pub dwFlags: DWORD,
+    pub dwCaps: DWORD,
+    pub wPriority: u16,
+    pub wLanguage: u16,
+    pub dwScale: DWORD,
+    pub dwRate: DWORD,
+    pub dwStart: DWORD,
+    pub dwLength: DWORD,
+    pub dwInitialFrames: DWORD,
+    pub dwSuggestedBufferSize: DWORD,
+    pub dwQuality: DWORD,
+    pub dwSampleSize: DWORD,
+    pub rcFrame: [i32; 4],
+    pub dwEditCount: DWORD,
+    pub dwFormatChangeCount: DWORD,
+    pub szName: [u8; 64],
+}
+unsafe impl Pod for AVISTREAMINFOA {}
+
+/// A parsed file and the path it was read from.
+struct File {
+    path: String,
+    avi: Avi,
+}
+
+/// A stream: its file and its index among the file's streams.
+struct Stream {
+    file: Rc<File>,
+    index: usize,
+}
+
+impl Stream {
+    fn get(&self) -> &super::avi::Stream {
+        &self.file.avi.streams[self.index]
+    }
+
+    /// Length in samples: frames for video, else sample_size units of the data.
+    fn length(&self) -> u32 {
+        let stream = self.get();
+        match stream.header.length {
+            0 if stream.header.sample_size == 0 => stream.chunks.len() as u32,
+            0 => {
+                let bytes = stream.chunks.iter().map(|c| c.len()).sum::<usize>();
+                (bytes / stream.header.sample_size as usize) as u32
+            }
+            len => len,
+        }
+    }
+}
+
+struct GetFrame {
+    stream: Stream,
+    decoder: VideoDecoder,
+    /// Count of frames decoded so far, for codecs that decode each over the last.
+    decoded: usize,
+    bit_count: u16,
+    /// BGRx color table, for 8bpp output.
+    palette: Vec<[u8; 4]>,
+    /// The packed DIB frames are decoded into: header, color table, pixels.
+    dib: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct State {
+    heap: Heap,
+    vtable_IAVIFile: u32,
+    vtable_IAVIStream: u32,
+    /// References to files and streams.
+    refs: com::RefCounts,
+    /// Open files and their decoders, which snapshots record separately; see save.
+    #[serde(skip)]
+    files: HashMap<u32, Rc<File>>,
+    #[serde(skip)]
+    streams: HashMap<u32, Stream>,
+    #[serde(skip)]
+    getframes: HashMap<u32, GetFrame>,
+}
+
+impl State {
+    pub fn new_init(machine: &mut Machine) -> Self {
+        let mut avifil32 = State::default();
+        avifil32.heap = machine.state.kernel32.new_private_heap(
+            &mut machine.emu.memory,
+            16 << 20,
+            "avifil32.dll heap".into(),
+        );
+        avifil32.vtable_IAVIFile = IAVIFile::vtable(&mut avifil32, machine);
+        avifil32.vtable_IAVIStream = IAVIStream::vtable(&mut avifil32, machine);
+        avifil32
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            heap: Heap::default(),
+            vtable_IAVIFile: 0,
+            vtable_IAVIStream: 0,
+            refs: com::RefCounts::default(),
+            files: HashMap::new(),
+            streams: HashMap::new(),
+            getframes: HashMap::new(),
+        }
+    }
+}
+
+/// A GetFrame as recorded in a snapshot; its DIB is in guest memory.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GetFrameRecord {
+    handle: u32,
+    stream: (String, usize),
+    bit_count: u16,
+    palette: Vec<[u8; 4]>,
+    dib: u32,
+}
+
+/// Open files, streams and GetFrames as recorded in a snapshot, by path.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Snapshot {
+    files: Vec<(u32, String)>,
+    streams: Vec<(u32, (String, usize))>,
+    getframes: Vec<GetFrameRecord>,
+}
+
+pub fn save(machine: &Machine) -> Snapshot {
+    let avifil32 = &machine.state.avifil32;
+    let stream = |s: &Stream| (s.file.path.clone(), s.index);
+    Snapshot {
+        files: (avifil32.files.iter())
+            .map(|(&handle, file)| (handle, file.path.clone()))
+            .collect(),
+        streams: (avifil32.streams.iter())
+            .map(|(&handle, s)| (handle, stream(s)))
+            .collect(),
+        getframes: (avifil32.getframes.iter())
+            .map(|(&handle, gf)| GetFrameRecord {
+                handle,
+                stream: stream(&gf.stream),
+                bit_count: gf.bit_count,
+                palette: gf.palette.clone(),
+                dib: gf.dib,
+            })
+            .collect(),
+    }
+}
+
+/// Reopen the files recorded in a snapshot.  Handles to files that are gone or
+/// no longer parse stay invalid, and calls on them fail with AVIERR_BADHANDLE.
+pub fn restore(machine: &mut Machine, snapshot: Snapshot) {
+    let mut opened: HashMap<String, Option<Rc<File>>> = HashMap::new();
+    let mut open = |machine: &Machine, path: &str| {
+        opened
+            .entry(path.to_string())
+            .or_insert_with(|| {
+                let avi = Avi::parse(&kernel32::read_file(machine, path));
+                if avi.is_none() {
+                    log::warn!("avifil32: can't reopen {path:?}");
+                }
+                avi.map(|avi| {
+                    Rc::new(File {
+                        path: path.to_string(),
+                        avi,
+                    })
+                })
+            })
+            .clone()
+    };
+
+    let mut files = HashMap::new();
+    for (handle, path) in snapshot.files {
+        if let Some(file) = open(machine, &path) {
+            files.insert(handle, file);
+        }
+    }
+    // The file may have changed so that the stream is no longer in it.
+    let mut stream = |machine: &Machine, (path, index): (String, usize)| {
+        let file = open(machine, &path)?;
+        (index < file.avi.streams.len()).then_some(Stream { file, index })
+    };
+    let mut streams = HashMap::new();
+    for (handle, record) in snapshot.streams {
+        if let Some(stream) = stream(machine, record) {
+            streams.insert(handle, stream);
+        }
+    }
+    let mut getframes = HashMap::new();
+    for record in snapshot.getframes {
+        let Some(stream) = stream(machine, record.stream) else {
+            continue;
+        };
+        let Some(decoder) = VideoDecoder::new(&stream.get().format) else {
+            continue;
+        };
+        getframes.insert(
+            record.handle,
+            GetFrame {
+                stream,
+                decoder,
+                decoded: 0,
+                bit_count: record.bit_count,
+                palette: record.palette,
+                dib: record.dib,
+            },
+        );
+    }
+    let avifil32 = &mut machine.state.avifil32;
+    avifil32.files = files;
+    avifil32.streams = streams;
+    avifil32.getframes = getframes;
+}
+
+/// Allocate an object holding just a vtable pointer; 0 for a plain handle.
+fn new_object(machine: &mut Machine, vtable: impl Fn(&State) -> u32) -> u32 {
+    if machine.state.avifil32.heap.addr == 0 {
+        machine.state.avifil32 = State::new_init(machine);
+    }
+    let avifil32 = &mut machine.state.avifil32;
+    let mem = machine.emu.memory.mem();
+    let addr = avifil32.heap.alloc(mem, 4);
+    mem.put::<u32>(addr, vtable(avifil32));
+    addr
+}
+
+/// Copy a string into a fixed-size, nul-terminated buffer.
+fn copy_name(dst: &mut [u8; 64], name: &str) {
+    dst.fill(0);
+    let len = std::cmp::min(name.len(), dst.len() - 1);
+    dst[..len].copy_from_slice(&name.as_bytes()[..len]);
+}
+
+#[win32_derive::dllexport]
+pub fn AVIFileInit(_machine: &mut Machine) -> u32 {
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn AVIFileExit(_machine: &mut Machine) -> u32 {
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn AVIFileOpenA(
+    machine: &mut Machine,
+    ppfile: Option<&mut u32>,
+    szFile: Option<&str>,
+    uMode: u32,
+    lpHandler: u32,
+) -> u32 {
+    let (Some(ppfile), Some(path)) = (ppfile, szFile) else {
+        return AVIERR_BADPARAM;
+    };
+    *ppfile = 0;
+    if uMode & (OF_WRITE | OF_READWRITE | OF_CREATE) != 0 {
+        log::warn!("AVIFileOpenA({path:?}, {uMode:#x}): files are read only");
+        return AVIERR_READONLY;
+    }
+    let buf = kernel32::read_file(machine, path);
+    if buf.is_empty() {
+        return AVIERR_FILEOPEN;
+    }
+    let Some(avi) = Avi::parse(&buf) else {
+        log::warn!("AVIFileOpenA({path:?}): not an AVI file");
+        return AVIERR_BADFORMAT;
+    };
+    let file = new_object(machine, |s| s.vtable_IAVIFile);
+    let path = path.to_string();
+    machine
+        .state
+        .avifil32
+        .files
+        .insert(file, Rc::new(File { path, avi }));
+    *ppfile = file;
+    AVIERR_OK
+}
+
+#[win32_derive::dllexport]
+pub fn AVIFileAddRef(machine: &mut Machine, pfile: u32) -> u32 {
+    IAVIFile::AddRef(machine, pfile)
+}
+
+#[win32_derive::dllexport]
+pub fn AVIFileRelease(machine: &mut Machine, pfile: u32) -> u32 {
+    IAVIFile::Release(machine, pfile)
+}
+
+#[win32_derive::dllexport]
+pub fn AVIFileInfoA(
+    machine: &mut Machine,
+    pfile: u32,
+    pfi: Option<&mut AVIFILEINFOA>,
+    lSize: u32,
+) -> u32 {
+    let Some(file) = machine.state.avifil32.files.get(&pfile) else {
+        return AVIERR_BADHANDLE;
+    };
+    let avi = &file.avi;
+    let Some(info) = pfi else {
+        return AVIERR_BADPARAM;
+    };
+    if (lSize as usize) < std::mem::size_of::<AVIFILEINFOA>() {
+        return AVIERR_BADSIZE;
+    }
+    *info = AVIFILEINFOA {
+        dwMaxBytesPerSec: avi.max_bytes_per_sec,
+        dwFlags: avi.flags,
+        dwCaps: AVIFILECAPS_CANREAD,
+        dwStreams: avi.streams.len() as u32,
+        dwSuggestedBufferSize: avi.suggested_buffer_size,
+        dwWidth: avi.width,
+        dwHeight: avi.height,
+        dwScale: avi.usec_per_frame,
+        dwRate: 1_000_000,
+        dwLength: avi.total_frames,
+        dwEditCount: 0,
+        szFileType: [0; 64],
+    };
+    copy_name(&mut info.szFileType, "AVI file");
+    AVIERR_OK
+}
+
+#[win32_derive::dllexport]
+pub fn AVIFileGetStream(
+    machine: &mut Machine,
+    pfile: u32,
+    ppavi: Option<&mut u32>,
+    fccType: u32,
+    lParam: u32,
+) -> u32 {
+    let Some(ppavi) = ppavi else {
+        return AVIERR_BADPARAM;
+    };
+    *ppavi = 0;
+    let Some(file) = machine.state.avifil32.files.get(&pfile) else {
+        return AVIERR_BADHANDLE;
+    };
+    // lParam counts streams of the given type, or of any type if fccType is 0.
+    let Some(index) = file
+        .avi
+        .streams
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| fccType == 0 || u32::from_le_bytes(s.header.kind) == fccType)
+        .map(|(i, _)| i)
+        .nth(lParam as usize)
+    else {
+        return AVIERR_NODATA;
+    };
+    let file = file.clone();
+    let stream = new_object(machine, |s| s.vtable_IAVIStream);
+    machine
+        .state
+        .avifil32
+        .streams
+        .insert(stream, Stream { file, index });
+    *ppavi = stream;
+    AVIERR_OK
+}
+
+#[win32_derive::dllexport]
+pub fn AVIStreamOpenFromFileA(
+    machine: &mut Machine,
+    ppavi: Option<&mut u32>,
+    szFile: Option<&str>,
+    fccType: u32,
+    lParam: u32,
+    mode: u32,
+    pclsidHandler: u32,
+) -> u32 {
+    let Some(ppavi) = ppavi else {
+        return AVIERR_BADPARAM;
+    };
+    let mut file = 0;
+    let err = AVIFileOpenA(machine, Some(&mut file), szFile, mode, pclsidHandler);
+    if err != AVIERR_OK {
+        return err;
+    }
+    let err = AVIFileGetStream(machine, file, Some(ppavi), fccType, lParam);
+    AVIFileRelease(machine, file);
+    err
+}
+
+#[win32_derive::dllexport]
+pub fn AVIStreamAddRef(machine: &mut Machine, pavi: u32) -> u32 {
+    IAVIStream::AddRef(machine, pavi)
+}
+
+#[win32_derive::dllexport]
+pub fn AVIStreamRelease(machine: &mut Machine, pavi: u32) -> u32 {
+    IAVIStream::Release(machine, pavi)
+}
+
+#[win32_derive::dllexport]
+pub fn AVIStreamInfoA(
+    machine: &mut Machine,
+    pavi: u32,
+    psi: Option<&mut AVISTREAMINFOA>,
+    lSize: u32,
+) -> u32 {
+    let Some(stream) = machine.state.avifil32.streams.get(&pavi) else {
+        return AVIERR_BADHANDLE;
+    };
+    let Some(info) = psi else {
+        return AVIERR_BADPARAM;
+    };
+    if (lSize as usize) < std::mem::size_of::<AVISTREAMINFOA>() {
+        return AVIERR_BADSIZE;
+    }
+    let header = &stream.get().header;
+    *info = AVISTREAMINFOA {
+        fccType: u32::from_le_bytes(header.kind),
+        fccHandler: u32::from_le_bytes(header.handler),
+        dwFlags: header.flags,
+        dwCaps: AVIFILECAPS_CANREAD,
+        wPriority: header.priority,
+        wLanguage: header.language,
+        dwScale: header.scale,
+        dwRate: header.rate,
+        dwStart: header.start,
+        dwLength: stream.length(),
+        dwInitialFrames: header.initial_frames,
+        dwSuggestedBufferSize: header.suggested_buffer_size,
+        dwQuality: header.quality,
+        dwSampleSize: header.sample_size,
+        rcFrame: header.frame.map(|v| v as i32),
+        dwEditCount: 0,
+        dwFormatChangeCount: 0,
+        szName: [0; 64],
+    };
+    AVIERR_OK
+}
+
+#[win32_derive::dllexport]
+pub fn AVIStreamStart(machine: &mut Machine, pavi: u32) -> i32 {
+    match machine.state.avifil32.streams.get(&pavi) {
+        Some(stream) => stream.get().header.start as i32,
+        None => -1,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn AVIStreamLength(machine: &mut Machine, pavi: u32) -> i32 {
+    match machine.state.avifil32.streams.get(&pavi) {
+        Some(stream) => stream.length() as i32,
+        None => -1,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn AVIStreamSampleToTime(machine: &mut Machine, pavi: u32, lSample: i32) -> i32 {
+    let Some(stream) = machine.state.avifil32.streams.get(&pavi) else {
+        return -1;
+    };
+    let header = &stream.get().header;
+    if header.rate == 0 {
+        return -1;
+    }
+    (lSample as i64 * header.scale as i64 * 1000 / header.rate as i64) as i32
+}
+
+#[win32_derive::dllexport]
+pub fn AVIStreamTimeToSample(machine: &mut Machine, pavi: u32, lTime: i32) -> i32 {
+    let Some(stream) = machine.state.avifil32.streams.get(&pavi) else {
+        return -1;
+    };
+    let header = &stream.get().header;
+    if header.scale == 0 {
+        return -1;
+    }
+    (lTime as i64 * header.rate as i64 / (header.scale as i64 * 1000)) as i32
+}
+
+#[win32_derive::dllexport]
+pub fn AVIStreamReadFormat(
+    machine: &mut Machine,
+    pavi: u32,
+    lPos: i32,
+    lpFormat: u32,
+    lpcbFormat: Option<&mut i32>,
+) -> u32 {
+    let Some(stream) = machine.state.avifil32.streams.get(&pavi) else {
+        return AVIERR_BADHANDLE;
+    };
+    let Some(cbFormat) = lpcbFormat else {
+        return AVIERR_BADPARAM;
+    };
+    let format = &stream.get().format;
+    if lpFormat != 0 {
+        let len = std::cmp::min(format.len(), std::cmp::max(*cbFormat, 0) as usize);
+        machine
+            .emu
+            .memory
+            .mem()
+            .sub(lpFormat, len as u32)
+            .as_mut_slice_todo()
+            .copy_from_slice(&format[..len]);
+    }
+    *cbFormat = format.len() as i32;
+    AVIERR_OK
+}
+
+/// Read `len` bytes at `ofs` of the concatenation of a stream's chunks.
+fn read_bytes(chunks: &[Vec<u8>], mut ofs: usize, mut len: usize, out: &mut [u8]) {
+    let mut pos = 0;
+    for chunk in chunks {
+        if len == 0 {
+            break;
+        }
+        if ofs >= chunk.len() {
+            ofs -= chunk.len();
+            continue;
+        }
+        let n = std::cmp::min(chunk.len() - ofs, len);
+        out[pos..pos + n].copy_from_slice(&chunk[ofs..ofs + n]);
+        pos += n;
+        len -= n;
+        ofs = 0;
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn AVIStreamRead(
+    machine: &mut Machine,
+    pavi: u32,
+    lStart: i32,
+    lSamples: i32,
+    lpBuffer: u32,
+    cbBuffer: u32,
+    plBytes: Option<&mut i32>,
+    plSamples: Option<&mut i32>,
+) -> u32 {
+    let Some(stream) = machine.state.avifil32.streams.get(&pavi) else {
+        return AVIERR_BADHANDLE;
+    };
+    let s = stream.get();
+    let start = lStart - s.header.start as i32;
+    if start < 0 || start as u32 >= stream.length() {
+        return AVIERR_BADPARAM;
+    }
+    let start = start as usize;
+    let mem = machine.emu.memory.mem();
+    let (bytes, samples) = if s.header.sample_size == 0 {
+        // Each chunk is one sample of its own size, as with video frames.
+        let chunk = &s.chunks[start];
+        if lpBuffer != 0 {
+            if (cbBuffer as usize) < chunk.len() {
+                if let Some(plBytes) = plBytes {
+                    *plBytes = chunk.len() as i32;
+                }
+                return AVIERR_BUFFERTOOSMALL;
+            }
+            mem.sub(lpBuffer, chunk.len() as u32)
+                .as_mut_slice_todo()
+                .copy_from_slice(chunk);
+        }
+        (chunk.len(), 1)
+    } else {
+        let size = s.header.sample_size as usize;
+        let available = stream.length() as usize - start;
+        let mut count = match lSamples {
+            AVISTREAMREAD_CONVENIENT => available,
+            n => std::cmp::min(std::cmp::max(n, 0) as usize, available),
+        };
+        if lpBuffer != 0 {
+            if lSamples == AVISTREAMREAD_CONVENIENT {
+                count = std::cmp::min(count, cbBuffer as usize / size);
+            }
+            if count == 0 || (cbBuffer as usize) < count * size {
+                if let Some(plBytes) = plBytes {
+                    *plBytes = (count * size) as i32;
+                }
+                return AVIERR_BUFFERTOOSMALL;
+            }
+            let out = mem.sub(lpBuffer, (count * size) as u32).as_mut_slice_todo();
+            read_bytes(&s.chunks, start * size, count * size, out);
+        }
+        (count * size, count)
+    };
+    if let Some(plBytes) = plBytes {
+        *plBytes = bytes as i32;
+    }
+    if let Some(plSamples) = plSamples {
+        *plSamples = samples as i32;
+    }
+    AVIERR_OK
+}
+
+#[win32_derive::dllexport]
+pub fn AVIStreamGetFrameOpen(machine: &mut Machine, pavi: u32, lpbiWanted: u32) -> u32 {
+    let Some(stream) = machine.state.avifil32.streams.get(&pavi) else {
+        return 0;
+    };
+    let stream = Stream {
+        file: stream.file.clone(),
+        index: stream.index,
+    };
+    let format = &stream.get().format;
+    if !stream.get().is_video() {
+        return 0;
+    }
+    let Some(decoder) = VideoDecoder::new(format) else {
+        return 0;
+    };
+
+    // Without a wanted format, frames come as 24bpp, which suits any display.
+    let mem = machine.emu.memory.mem();
+    let (bit_count, wanted_palette) = match lpbiWanted {
+        0 | AVIGETFRAMEF_BESTDISPLAYFMT => (24, None),
+        addr => {
+            let wanted = mem.view::<BITMAPINFOHEADER>(addr);
+            if wanted.biCompression != 0 || !matches!(wanted.biBitCount, 0 | 8 | 16 | 24 | 32) {
+                log::warn!("AVIStreamGetFrameOpen: unsupported format {wanted:?}");
+                return 0;
+            }
+            let palette = if wanted.biBitCount == 8 && wanted.biClrUsed != 0 {
+                let addr = addr + wanted.biSize;
+                Some(
+                    mem.iter_pod::<[u8; 4]>(addr, wanted.biClrUsed)
+                        .collect::<Vec<_>>(),
+                )
+            } else {
+                None
+            };
+            match wanted.biBitCount {
+                0 => (24, None),
+                n => (n, palette),
+            }
+        }
+    };
+    let palette = if bit_count == 8 {
+        // Lacking a color table of its own, 8bpp output uses the stream's.
+        let palette = wanted_palette.unwrap_or_else(|| {
+            let header_size = u32::from_le_bytes(format[0..4].try_into().unwrap()) as usize;
+            format
+                .get(header_size..)
+                .unwrap_or(&[])
+                .chunks_exact(4)
+                .take(256)
+                .map(|c| c.try_into().unwrap())
+                .collect()
+        });
+        if palette.is_empty() {
+            log::warn!("AVIStreamGetFrameOpen: 8bpp output needs a color table");
+            return 0;
+        }
+        palette
+    } else {
+        Vec::new()
+    };
+
+    let header = BITMAPINFOHEADER {
+        biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+        biWidth: decoder.width,
+        biHeight: decoder.height,
+        biPlanes: 1,
+        biBitCount: bit_count,
+        biCompression: 0,
+        biSizeImage: 0,
+        biXPelsPerMeter: 0,
+        biYPelsPerMeter: 0,
+        biClrUsed: palette.len() as u32,
+        biClrImportant: 0,
+    };
+    let size_image = header.stride() * decoder.height;
+    let palette_len = palette.len() as u32 * 4;
+    let dib_len = header.biSize + palette_len + size_image;
+
+    let pg = new_object(machine, |_| 0);
+    let avifil32 = &mut machine.state.avifil32;
+    let mem = machine.emu.memory.mem();
+    let dib = avifil32.heap.alloc(mem, dib_len);
+    *mem.view_mut::<BITMAPINFOHEADER>(dib) = BITMAPINFOHEADER {
+        biSizeImage: size_image,
+        ..header
+    };
+    for (i, &color) in palette.iter().enumerate() {
+        mem.put::<[u8; 4]>(dib + header.biSize + i as u32 * 4, color);
+    }
+    avifil32.getframes.insert(
+        pg,
+        GetFrame {
+            stream,
+            decoder,
+            decoded: 0,
+            bit_count,
+            palette,
+            dib,
+        },
+    );
+    pg
+}
+
+#[win32_derive::dllexport]
+pub fn AVIStreamGetFrame(machine: &mut Machine, pg: u32, lPos: i32) -> u32 {
+    let Some(gf) = machine.state.avifil32.getframes.get_mut(&pg) else {
+        return 0;
+    };
+    let stream = gf.stream.get();
+    let pos = lPos - stream.header.start as i32;
+    if pos < 0 || pos as usize >= stream.chunks.len() {
+        return 0;
+    }
+    let pos = pos as usize;
+
+    if gf.decoder.is_intra_only() {
+        gf.decoder.decode(&stream.chunks[pos]);
+    } else {
+        // Frames build on the ones before, so going back means starting over.
+        if pos + 1 < gf.decoded {
+            gf.decoder.reset();
+            gf.decoded = 0;
+        }
+        while gf.decoded <= pos {
+            gf.decoder.decode(&stream.chunks[gf.decoded]);
+            gf.decoded += 1;
+        }
+    }
+
+    let mem = machine.emu.memory.mem();
+    let header = mem.view::<BITMAPINFOHEADER>(gf.dib);
+    let stride = header.stride();
+    let bits = gf.dib + header.biSize + gf.palette.len() as u32 * 4;
+    let width = gf.decoder.width as usize;
+    let height = gf.decoder.height;
+    for (y, src) in gf.decoder.pixels.chunks_exact(width).enumerate() {
+        // The DIB is bottom-up.
+        let row = height - 1 - y as u32;
+        let dst = mem.sub(bits + row * stride, stride).as_mut_slice_todo();
+        encode_dib_row(src, gf.bit_count, &gf.palette, dst);
+    }
+    gf.dib
+}
+
+#[win32_derive::dllexport]
+pub fn AVIStreamGetFrameClose(machine: &mut Machine, pg: u32) -> u32 {
+    let avifil32 = &mut machine.state.avifil32;
+    let Some(gf) = avifil32.getframes.remove(&pg) else {
+        return AVIERR_BADHANDLE;
+    };
+    let mem = machine.emu.memory.mem();
+    avifil32.heap.free(mem, gf.dib);
+    avifil32.heap.free(mem, pg);
+    AVIERR_OK
+}
+
+#[win32_derive::shims_from_x86]
+mod IAVIFile {
+    use super::*;
+
+    #[win32_derive::dllexport]
+    pub fn AddRef(machine: &mut Machine, this: u32) -> u32 {
+        machine.state.avifil32.refs.add_ref(this)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Release(machine: &mut Machine, this: u32) -> u32 {
+        let avifil32 = &mut machine.state.avifil32;
+        if avifil32.files.get(&this).is_none() {
+            return 0;
+        }
+        let refs = avifil32.refs.release(this);
+        if refs == 0 {
+            avifil32.files.remove(&this);
+            avifil32.heap.free(machine.emu.memory.mem(), this);
+        }
+        refs
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetStream(
+        machine: &mut Machine,
+        this: u32,
+        ppStream: Option<&mut u32>,
+        fccType: u32,
+        lParam: u32,
+    ) -> u32 {
+        AVIFileGetStream(machine, this, ppStream, fccType, lParam)
+    }
+
+    vtable![IAVIFile shims
+        QueryInterface todo,
+        AddRef ok,
+        Release ok,
+        Info todo,
+        GetStream ok,
+        CreateStream todo,
+        WriteData todo,
+        ReadData todo,
+        EndRecord todo,
+        DeleteStream todo,
+    ];
+}
+
+#[win32_derive::shims_from_x86]
+mod IAVIStream {
+    use super::*;
+
+    #[win32_derive::dllexport]
+    pub fn AddRef(machine: &mut Machine, this: u32) -> u32 {
+        machine.state.avifil32.refs.add_ref(this)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Release(machine: &mut Machine, this: u32) -> u32 {
+        let avifil32 = &mut machine.state.avifil32;
+        if avifil32.streams.get(&this).is_none() {
+            return 0;
+        }
+        let refs = avifil32.refs.release(this);
+        if refs == 0 {
+            avifil32.streams.remove(&this);
+            avifil32.heap.free(machine.emu.memory.mem(), this);
+        }
+        refs
+    }
+
+    #[win32_derive::dllexport]
+    pub fn ReadFormat(
+        machine: &mut Machine,
+        this: u32,
+        lPos: i32,
+        lpFormat: u32,
+        lpcbFormat: Option<&mut i32>,
+    ) -> u32 {
+        AVIStreamReadFormat(machine, this, lPos, lpFormat, lpcbFormat)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Read(
+        machine: &mut Machine,
+        this: u32,
+        lStart: i32,
+        lSamples: i32,
+        lpBuffer: u32,
+        cbBuffer: u32,
+        plBytes: Option<&mut i32>,
+        plSamples: Option<&mut i32>,
+    ) -> u32 {
+        AVIStreamRead(
+            machine, this, lStart, lSamples, lpBuffer, cbBuffer, plBytes, plSamples,
+        )
+    }
+
+    vtable![IAVIStream shims
+        QueryInterface todo,
+        AddRef ok,
+        Release ok,
+        Create todo,
+        Info todo,
+        FindSample todo,
+        ReadFormat ok,
+        SetFormat todo,
+        Read ok,
+        Write todo,
+        Delete todo,
+        ReadData todo,
+        WriteData todo,
+        SetInfo todo,
+    ];
+}
@@ -49,6 +49,11 @@ impl BITMAPINFOHEADER {
     pub fn compression(&self) -> Result<BI, u32> {
         BI::try_from(self.biCompression)
     }
+    /// Count of bytes of color table or masks between the header and the pixel data.
+    /// The header must be followed by those bytes in memory.
+    pub fn extra_len(&self) -> usize {
+        DIBFormat::parse(self).extra_len
+    }
 }
 
 pub trait Bitmap {
@@ -56,6 +61,7 @@ pub trait Bitmap {
     fn height(&self) -> u32;
 }
 
+#[derive(serde::Serialize, serde::Deserialize)]
 pub enum PixelData<T> {
     Owned(Box<[T]>),
     Ptr(u32, u32),
@@ -77,14 +83,23 @@ impl<T> PixelData<T> {
         }
     }
 
-    pub fn as_slice_mut(&mut self) -> &mut [T] {
+    pub fn as_slice_mut<'a>(&'a mut self, mem: Mem<'a>) -> &'a mut [T] {
         match self {
             PixelData::Owned(b) => &mut *b,
-            _ => unimplemented!(),
+            &mut PixelData::Ptr(addr, len) => {
+                let bytes = mem.sub(addr, len).as_mut_slice_todo();
+                unsafe {
+                    std::slice::from_raw_parts_mut(
+                        bytes.as_mut_ptr() as *mut _,
+                        bytes.len() / std::mem::size_of::<T>(),
+                    )
+                }
+            }
         }
     }
 }
 
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct BitmapRGBA32 {
     pub width: u32,
     pub height: u32,
@@ -104,33 +119,7 @@ impl BitmapRGBA32 {
             panic!("bad bitmap header");
         }
 
-        let palette = match header.compression().unwrap() {
-            BI::RGB => {
-                let palette_len = match header.biBitCount {
-                    8 => 256,
-                    4 => 16,
-                    _ => unimplemented!(),
-                };
-                if header.biClrUsed != 0 && header.biClrUsed != palette_len {
-                    todo!();
-                }
-                unsafe {
-                    let ptr = (header as *const _ as *const u8).add(header_size) as *const _;
-                    std::slice::from_raw_parts(ptr as *const [u8; 4], palette_len as usize)
-                }
-            }
-            BI::RLE8 => todo!(),
-            BI::RLE4 => todo!(),
-            BI::BITFIELDS => todo!(),
-            BI::JPEG => todo!(),
-            BI::PNG => todo!(),
-        };
-
-        fn get_pixel(palette: &[[u8; 4]], val: u8) -> [u8; 4] {
-            // BMP palette is BGRx
-            let [b, g, r, _] = palette[val as usize];
-            [r, g, b, 255]
-        }
+        let format = DIBFormat::parse(header);
 
         let width = header.width() as usize;
         // Bitmap row stride is padded out to 4 bytes per row.
@@ -139,7 +128,7 @@ impl BitmapRGBA32 {
         let (src, height) = match pixels {
             Some(p) => p,
             None => unsafe {
-                let ptr = (palette as *const _ as *const u8).add(palette.len() * 4);
+                let ptr = (header as *const _ as *const u8).add(header_size + format.extra_len);
                 let height = header.height() as usize;
                 let len = stride * height;
                 (std::slice::from_raw_parts(ptr, len), height)
@@ -154,24 +143,7 @@ impl BitmapRGBA32 {
                 height - y - 1
             };
             let row = &src[y_src * stride..][..stride];
-            match header.biBitCount {
-                8 => {
-                    for &p in &row[..width] {
-                        dst.push(get_pixel(palette, p));
-                    }
-                }
-                4 => {
-                    for i in 0..width {
-                        let p = row[i / 2];
-                        if i % 2 == 0 {
-                            dst.push(get_pixel(palette, p >> 4));
-                        } else {
-                            dst.push(get_pixel(palette, p & 0xF));
-                        }
-                    }
-                }
-                _ => unimplemented!(),
-            }
+            format.decode_row(row, width, &mut dst);
         }
 
         BitmapRGBA32 {
@@ -182,6 +154,230 @@ impl BitmapRGBA32 {
     }
 }
 
+/// Channel bit masks, as found in BI_BITFIELDS DIBs.
+#[derive(Debug, Clone, Copy)]
+struct Masks {
+    r: u32,
+    g: u32,
+    b: u32,
+}
+
+impl Masks {
+    const RGB555: Masks = Masks {
+        r: 0x7c00,
+        g: 0x03e0,
+        b: 0x001f,
+    };
+    const RGB888: Masks = Masks {
+        r: 0xff_0000,
+        g: 0x00_ff00,
+        b: 0x00_00ff,
+    };
+
+    fn channel(val: u32, mask: u32) -> u8 {
+        if mask == 0 {
+            return 0;
+        }
+        let shift = mask.trailing_zeros();
+        let max = mask >> shift;
+        (((val & mask) >> shift) * 255 / max) as u8
+    }
+
+    fn decode(&self, val: u32) -> [u8; 4] {
+        [
+            Self::channel(val, self.r),
+            Self::channel(val, self.g),
+            Self::channel(val, self.b),
+            255,
+        ]
+    }
+}
+
+/// The pixel format of a DIB, as described by its header and the color table or
+/// masks that follow it.
+struct DIBFormat<'a> {
+    bit_count: u16,
+    /// BGRx color table, for 8bpp and below.
+    palette: &'a [[u8; 4]],
+    /// Channel masks, for 16 and 32bpp.
+    masks: Masks,
+    /// Count of bytes between the header and the pixel data.
+    extra_len: usize,
+}
+
+impl<'a> DIBFormat<'a> {
+    fn parse(header: &'a BITMAPINFOHEADER) -> Self {
+        let extra = unsafe {
+            (header as *const _ as *const u8).add(std::mem::size_of::<BITMAPINFOHEADER>())
+        };
+        let bit_count = header.biBitCount;
+        let mut masks = match bit_count {
+            16 => Masks::RGB555,
+            _ => Masks::RGB888,
+        };
+        let mut palette: &[[u8; 4]] = &[];
+        let extra_len;
+        match header.compression().unwrap() {
+            BI::RGB => {
+                // The color table is required for 8bpp and below, and optional otherwise.
+                let palette_len = match bit_count {
+                    1 | 4 | 8 if header.biClrUsed == 0 => 1 << bit_count,
+                    1 | 4 | 8 | 16 | 24 | 32 => header.biClrUsed,
+                    _ => unimplemented!("{bit_count}bpp"),
+                };
+                extra_len = palette_len as usize * 4;
+                if bit_count <= 8 {
+                    palette = unsafe {
+                        std::slice::from_raw_parts(extra as *const [u8; 4], palette_len as usize)
+                    };
+                }
+            }
+            BI::BITFIELDS => {
+                let raw = unsafe { std::slice::from_raw_parts(extra as *const [u8; 4], 3) };
+                masks = Masks {
+                    r: u32::from_le_bytes(raw[0]),
+                    g: u32::from_le_bytes(raw[1]),
+                    b: u32::from_le_bytes(raw[2]),
+                };
+                extra_len = 3 * 4;
+            }
+            BI::RLE8 => todo!(),
+            BI::RLE4 => todo!(),
+            BI::JPEG => todo!(),
+            BI::PNG => todo!(),
+        }
+        DIBFormat {
+            bit_count,
+            palette,
+            masks,
+            extra_len,
+        }
+    }
+
+    fn get_pixel(&self, val: u8) -> [u8; 4] {
+        // BMP palette is BGRx
+        let [b, g, r, _] = self.palette[val as usize];
+        [r, g, b, 255]
+    }
+
+    fn decode_row(&self, row: &[u8], width: usize, dst: &mut Vec<[u8; 4]>) {
+        match self.bit_count {
+            1 => {
+                for i in 0..width {
+                    let p = (row[i / 8] >> (7 - (i % 8))) & 1;
+                    dst.push(self.get_pixel(p));
+                }
+            }
+            4 => {
+                for i in 0..width {
+                    let p = row[i / 2];
+                    if i % 2 == 0 {
+                        dst.push(self.get_pixel(p >> 4));
+                    } else {
+                        dst.push(self.get_pixel(p & 0xF));
+                    }
+                }
+            }
+            8 => {
+                for &p in &row[..width] {
+                    dst.push(self.get_pixel(p));
+                }
+            }
+            16 => {
+                for p in row[..width * 2].chunks_exact(2) {
+                    dst.push(self.masks.decode(u16::from_le_bytes([p[0], p[1]]) as u32));
+                }
+            }
+            24 => {
+                for p in row[..width * 3].chunks_exact(3) {
+                    let [b, g, r] = [p[0], p[1], p[2]];
+                    dst.push([r, g, b, 255]);
+                }
+            }
+            32 => {
+                for p in row[..width * 4].chunks_exact(4) {
+                    dst.push(
+                        self.masks
+                            .decode(u32::from_le_bytes([p[0], p[1], p[2], p[3]])),
+                    );
+                }
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+/// Encode one row of RGBA pixels into DIB format at `bit_count` bits per pixel,
+/// without compression.  For 8bpp and below, `palette` is the BGRx color table to
+/// index into.
+pub fn encode_dib_row(pixels: &[[u8; 4]], bit_count: u16, palette: &[[u8; 4]], row: &mut [u8]) {
+    match bit_count {
+        1 | 4 => {
+            // Pixels pack from the high bits of each byte down.
+            let per_byte = 8 / bit_count as usize;
+            for (byte, chunk) in row.iter_mut().zip(pixels.chunks(per_byte)) {
+                *byte = 0;
+                for (i, &[r, g, b, _]) in chunk.iter().enumerate() {
+                    let index = nearest_color(palette, [b, g, r, 0]);
+                    *byte |= index << (8 - bit_count as usize * (i + 1));
+                }
+            }
+        }
+        8 => {
+            for (&[r, g, b, _], out) in pixels.iter().zip(row.iter_mut()) {
+                *out = nearest_color(palette, [b, g, r, 0]);
+            }
+        }
+        16 => {
+            for (&[r, g, b, _], out) in pixels.iter().zip(row.chunks_exact_mut(2)) {
+                let p = ((r as u16 >> 3) << 10) | ((g as u16 >> 3) << 5) | (b as u16 >> 3);
+                out.copy_from_slice(&p.to_le_bytes());
+            }
+        }
+        24 => {
+            for (&[r, g, b, _], out) in pixels.iter().zip(row.chunks_exact_mut(3)) {
+                out.copy_from_slice(&[b, g, r]);
+            }
+        }
+        32 => {
+            for (&[r, g, b, _], out) in pixels.iter().zip(row.chunks_exact_mut(4)) {
+                out.copy_from_slice(&[b, g, r, 0]);
+            }
+        }
+        _ => unimplemented!("encode {bit_count}bpp"),
+    }
+}
+
+/// Build a BGRx color table of up to `max` entries for the given pixels.
+/// Exact if the pixels use at most that many colors.
+pub fn build_palette(pixels: &[[u8; 4]], max: usize) -> Vec<[u8; 4]> {
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+    for &[r, g, b, _] in pixels {
+        let entry = [b, g, r, 0];
+        if palette.len() == max {
+            break;
+        }
+        if !palette.contains(&entry) {
+            palette.push(entry);
+        }
+    }
+    palette
+}
+
+fn nearest_color(palette: &[[u8; 4]], color: [u8; 4]) -> u8 {
+    let dist = |p: &[u8; 4]| -> u32 {
+        (0..3)
+            .map(|i| (p[i] as i32 - color[i] as i32).pow(2) as u32)
+            .sum()
+    };
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, p)| dist(p))
+        .map(|(i, _)| i as u8)
+        .unwrap_or(0)
+}
+
 impl std::fmt::Debug for BitmapRGBA32 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Bitmap")
@@ -202,6 +398,7 @@ impl Bitmap for BitmapRGBA32 {
     }
 }
 
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct BitmapMono {
     pub width: u32,
     pub height: u32,
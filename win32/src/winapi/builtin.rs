@@ -21,11 +21,276 @@ pub mod advapi32 {
         };
         use memory::Extensions;
         use winapi::advapi32::*;
+        pub unsafe fn AllocateAndInitializeSid(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pIdentifierAuthority = <Option<&[u8; 6]>>::from_stack(mem, esp + 4u32);
+            let nSubAuthorityCount = <u32>::from_stack(mem, esp + 8u32);
+            let nSubAuthority0 = <u32>::from_stack(mem, esp + 12u32);
+            let nSubAuthority1 = <u32>::from_stack(mem, esp + 16u32);
+            let nSubAuthority2 = <u32>::from_stack(mem, esp + 20u32);
+            let nSubAuthority3 = <u32>::from_stack(mem, esp + 24u32);
+            let nSubAuthority4 = <u32>::from_stack(mem, esp + 28u32);
+            let nSubAuthority5 = <u32>::from_stack(mem, esp + 32u32);
+            let nSubAuthority6 = <u32>::from_stack(mem, esp + 36u32);
+            let nSubAuthority7 = <u32>::from_stack(mem, esp + 40u32);
+            let pSid = <Option<&mut u32>>::from_stack(mem, esp + 44u32);
+            winapi::advapi32::AllocateAndInitializeSid(
+                machine,
+                pIdentifierAuthority,
+                nSubAuthorityCount,
+                nSubAuthority0,
+                nSubAuthority1,
+                nSubAuthority2,
+                nSubAuthority3,
+                nSubAuthority4,
+                nSubAuthority5,
+                nSubAuthority6,
+                nSubAuthority7,
+                pSid,
+            )
+            .to_raw()
+        }
+        pub unsafe fn CheckTokenMembership(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let TokenHandle = <HTOKEN>::from_stack(mem, esp + 4u32);
+            let SidToCheck = <u32>::from_stack(mem, esp + 8u32);
+            let IsMember = <Option<&mut u32>>::from_stack(mem, esp + 12u32);
+            winapi::advapi32::CheckTokenMembership(machine, TokenHandle, SidToCheck, IsMember)
+                .to_raw()
+        }
+        pub unsafe fn CryptAcquireContextA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let phProv = <Option<&mut HCRYPTPROV>>::from_stack(mem, esp + 4u32);
+            let szContainer = <Option<&str>>::from_stack(mem, esp + 8u32);
+            let szProvider = <Option<&str>>::from_stack(mem, esp + 12u32);
+            let dwProvType = <u32>::from_stack(mem, esp + 16u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 20u32);
+            winapi::advapi32::CryptAcquireContextA(
+                machine,
+                phProv,
+                szContainer,
+                szProvider,
+                dwProvType,
+                dwFlags,
+            )
+            .to_raw()
+        }
+        pub unsafe fn CryptAcquireContextW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let phProv = <Option<&mut HCRYPTPROV>>::from_stack(mem, esp + 4u32);
+            let szContainer = <Option<&Str16>>::from_stack(mem, esp + 8u32);
+            let szProvider = <Option<&Str16>>::from_stack(mem, esp + 12u32);
+            let dwProvType = <u32>::from_stack(mem, esp + 16u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 20u32);
+            winapi::advapi32::CryptAcquireContextW(
+                machine,
+                phProv,
+                szContainer,
+                szProvider,
+                dwProvType,
+                dwFlags,
+            )
+            .to_raw()
+        }
+        pub unsafe fn CryptCreateHash(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hProv = <HCRYPTPROV>::from_stack(mem, esp + 4u32);
+            let Algid = <u32>::from_stack(mem, esp + 8u32);
+            let hKey = <u32>::from_stack(mem, esp + 12u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 16u32);
+            let phHash = <Option<&mut HCRYPTHASH>>::from_stack(mem, esp + 20u32);
+            winapi::advapi32::CryptCreateHash(machine, hProv, Algid, hKey, dwFlags, phHash).to_raw()
+        }
+        pub unsafe fn CryptDestroyHash(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hHash = <HCRYPTHASH>::from_stack(mem, esp + 4u32);
+            winapi::advapi32::CryptDestroyHash(machine, hHash).to_raw()
+        }
+        pub unsafe fn CryptGenRandom(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hProv = <HCRYPTPROV>::from_stack(mem, esp + 4u32);
+            let dwLen = <u32>::from_stack(mem, esp + 8u32);
+            let pbBuffer = <u32>::from_stack(mem, esp + 12u32);
+            winapi::advapi32::CryptGenRandom(machine, hProv, dwLen, pbBuffer).to_raw()
+        }
+        pub unsafe fn CryptGetHashParam(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hHash = <HCRYPTHASH>::from_stack(mem, esp + 4u32);
+            let dwParam = <u32>::from_stack(mem, esp + 8u32);
+            let pbData = <u32>::from_stack(mem, esp + 12u32);
+            let pdwDataLen = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 20u32);
+            winapi::advapi32::CryptGetHashParam(
+                machine, hHash, dwParam, pbData, pdwDataLen, dwFlags,
+            )
+            .to_raw()
+        }
+        pub unsafe fn CryptHashData(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hHash = <HCRYPTHASH>::from_stack(mem, esp + 4u32);
+            let pbData = <u32>::from_stack(mem, esp + 8u32);
+            let dwDataLen = <u32>::from_stack(mem, esp + 12u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 16u32);
+            winapi::advapi32::CryptHashData(machine, hHash, pbData, dwDataLen, dwFlags).to_raw()
+        }
+        pub unsafe fn CryptReleaseContext(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hProv = <HCRYPTPROV>::from_stack(mem, esp + 4u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 8u32);
+            winapi::advapi32::CryptReleaseContext(machine, hProv, dwFlags).to_raw()
+        }
+        pub unsafe fn EqualSid(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pSid1 = <u32>::from_stack(mem, esp + 4u32);
+            let pSid2 = <u32>::from_stack(mem, esp + 8u32);
+            winapi::advapi32::EqualSid(machine, pSid1, pSid2).to_raw()
+        }
+        pub unsafe fn FreeSid(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pSid = <u32>::from_stack(mem, esp + 4u32);
+            winapi::advapi32::FreeSid(machine, pSid).to_raw()
+        }
+        pub unsafe fn GetLengthSid(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pSid = <u32>::from_stack(mem, esp + 4u32);
+            winapi::advapi32::GetLengthSid(machine, pSid).to_raw()
+        }
+        pub unsafe fn GetTokenInformation(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let TokenHandle = <HTOKEN>::from_stack(mem, esp + 4u32);
+            let TokenInformationClass = <u32>::from_stack(mem, esp + 8u32);
+            let TokenInformation = <u32>::from_stack(mem, esp + 12u32);
+            let TokenInformationLength = <u32>::from_stack(mem, esp + 16u32);
+            let ReturnLength = <Option<&mut u32>>::from_stack(mem, esp + 20u32);
+            winapi::advapi32::GetTokenInformation(
+                machine,
+                TokenHandle,
+                TokenInformationClass,
+                TokenInformation,
+                TokenInformationLength,
+                ReturnLength,
+            )
+            .to_raw()
+        }
+        pub unsafe fn GetUserNameA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpBuffer = <u32>::from_stack(mem, esp + 4u32);
+            let pcbBuffer = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
+            winapi::advapi32::GetUserNameA(machine, lpBuffer, pcbBuffer).to_raw()
+        }
+        pub unsafe fn GetUserNameW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpBuffer = <u32>::from_stack(mem, esp + 4u32);
+            let pcbBuffer = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
+            winapi::advapi32::GetUserNameW(machine, lpBuffer, pcbBuffer).to_raw()
+        }
+        pub unsafe fn IsValidSid(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pSid = <u32>::from_stack(mem, esp + 4u32);
+            winapi::advapi32::IsValidSid(machine, pSid).to_raw()
+        }
+        pub unsafe fn LookupAccountNameA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpSystemName = <Option<&str>>::from_stack(mem, esp + 4u32);
+            let lpAccountName = <Option<&str>>::from_stack(mem, esp + 8u32);
+            let Sid = <u32>::from_stack(mem, esp + 12u32);
+            let cbSid = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            let ReferencedDomainName = <u32>::from_stack(mem, esp + 20u32);
+            let cchReferencedDomainName = <Option<&mut u32>>::from_stack(mem, esp + 24u32);
+            let peUse = <Option<&mut u32>>::from_stack(mem, esp + 28u32);
+            winapi::advapi32::LookupAccountNameA(
+                machine,
+                lpSystemName,
+                lpAccountName,
+                Sid,
+                cbSid,
+                ReferencedDomainName,
+                cchReferencedDomainName,
+                peUse,
+            )
+            .to_raw()
+        }
+        pub unsafe fn LookupAccountSidA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpSystemName = <Option<&str>>::from_stack(mem, esp + 4u32);
+            let Sid = <u32>::from_stack(mem, esp + 8u32);
+            let Name = <u32>::from_stack(mem, esp + 12u32);
+            let cchName = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            let ReferencedDomainName = <u32>::from_stack(mem, esp + 20u32);
+            let cchReferencedDomainName = <Option<&mut u32>>::from_stack(mem, esp + 24u32);
+            let peUse = <Option<&mut u32>>::from_stack(mem, esp + 28u32);
+            winapi::advapi32::LookupAccountSidA(
+                machine,
+                lpSystemName,
+                Sid,
+                Name,
+                cchName,
+                ReferencedDomainName,
+                cchReferencedDomainName,
+                peUse,
+            )
+            .to_raw()
+        }
+        pub unsafe fn OpenProcessToken(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let ProcessHandle = <u32>::from_stack(mem, esp + 4u32);
+            let DesiredAccess = <u32>::from_stack(mem, esp + 8u32);
+            let TokenHandle = <Option<&mut HTOKEN>>::from_stack(mem, esp + 12u32);
+            winapi::advapi32::OpenProcessToken(machine, ProcessHandle, DesiredAccess, TokenHandle)
+                .to_raw()
+        }
+        pub unsafe fn OpenThreadToken(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let ThreadHandle = <u32>::from_stack(mem, esp + 4u32);
+            let DesiredAccess = <u32>::from_stack(mem, esp + 8u32);
+            let OpenAsSelf = <bool>::from_stack(mem, esp + 12u32);
+            let TokenHandle = <Option<&mut HTOKEN>>::from_stack(mem, esp + 16u32);
+            winapi::advapi32::OpenThreadToken(
+                machine,
+                ThreadHandle,
+                DesiredAccess,
+                OpenAsSelf,
+                TokenHandle,
+            )
+            .to_raw()
+        }
         pub unsafe fn RegCloseKey(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hKey = <HKEY>::from_stack(mem, esp + 4u32);
             winapi::advapi32::RegCloseKey(machine, hKey).to_raw()
         }
+        pub unsafe fn RegCreateKeyA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hKey = <HKEY>::from_stack(mem, esp + 4u32);
+            let lpSubKey = <Option<&str>>::from_stack(mem, esp + 8u32);
+            let phkResult = <Option<&mut HKEY>>::from_stack(mem, esp + 12u32);
+            winapi::advapi32::RegCreateKeyA(machine, hKey, lpSubKey, phkResult).to_raw()
+        }
+        pub unsafe fn RegCreateKeyExA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hKey = <HKEY>::from_stack(mem, esp + 4u32);
+            let lpSubKey = <Option<&str>>::from_stack(mem, esp + 8u32);
+            let Reserved = <u32>::from_stack(mem, esp + 12u32);
+            let lpClass = <Option<&str>>::from_stack(mem, esp + 16u32);
+            let dwOptions = <u32>::from_stack(mem, esp + 20u32);
+            let samDesired = <u32>::from_stack(mem, esp + 24u32);
+            let lpSecurityAttributes = <u32>::from_stack(mem, esp + 28u32);
+            let phkResult = <Option<&mut HKEY>>::from_stack(mem, esp + 32u32);
+            let lpdwDisposition = <Option<&mut u32>>::from_stack(mem, esp + 36u32);
+            winapi::advapi32::RegCreateKeyExA(
+                machine,
+                hKey,
+                lpSubKey,
+                Reserved,
+                lpClass,
+                dwOptions,
+                samDesired,
+                lpSecurityAttributes,
+                phkResult,
+                lpdwDisposition,
+            )
+            .to_raw()
+        }
         pub unsafe fn RegCreateKeyExW(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hKey = <HKEY>::from_stack(mem, esp + 4u32);
@@ -35,7 +300,7 @@ pub mod advapi32 {
             let dwOptions = <u32>::from_stack(mem, esp + 20u32);
             let samDesired = <u32>::from_stack(mem, esp + 24u32);
             let lpSecurityAttributes = <u32>::from_stack(mem, esp + 28u32);
-            let phkResult = <Option<&mut u32>>::from_stack(mem, esp + 32u32);
+            let phkResult = <Option<&mut HKEY>>::from_stack(mem, esp + 32u32);
             let lpdwDisposition = <Option<&mut u32>>::from_stack(mem, esp + 36u32);
             winapi::advapi32::RegCreateKeyExW(
                 machine,
@@ -51,6 +316,115 @@ pub mod advapi32 {
             )
             .to_raw()
         }
+        pub unsafe fn RegDeleteKeyA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hKey = <HKEY>::from_stack(mem, esp + 4u32);
+            let lpSubKey = <Option<&str>>::from_stack(mem, esp + 8u32);
+            winapi::advapi32::RegDeleteKeyA(machine, hKey, lpSubKey).to_raw()
+        }
+        pub unsafe fn RegDeleteValueA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hKey = <HKEY>::from_stack(mem, esp + 4u32);
+            let lpValueName = <Option<&str>>::from_stack(mem, esp + 8u32);
+            winapi::advapi32::RegDeleteValueA(machine, hKey, lpValueName).to_raw()
+        }
+        pub unsafe fn RegEnumKeyA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hKey = <HKEY>::from_stack(mem, esp + 4u32);
+            let dwIndex = <u32>::from_stack(mem, esp + 8u32);
+            let lpName = <u32>::from_stack(mem, esp + 12u32);
+            let cchName = <u32>::from_stack(mem, esp + 16u32);
+            winapi::advapi32::RegEnumKeyA(machine, hKey, dwIndex, lpName, cchName).to_raw()
+        }
+        pub unsafe fn RegEnumKeyExA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hKey = <HKEY>::from_stack(mem, esp + 4u32);
+            let dwIndex = <u32>::from_stack(mem, esp + 8u32);
+            let lpName = <u32>::from_stack(mem, esp + 12u32);
+            let lpcchName = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            let lpReserved = <u32>::from_stack(mem, esp + 20u32);
+            let lpClass = <u32>::from_stack(mem, esp + 24u32);
+            let lpcchClass = <Option<&mut u32>>::from_stack(mem, esp + 28u32);
+            let lpftLastWriteTime = <u32>::from_stack(mem, esp + 32u32);
+            winapi::advapi32::RegEnumKeyExA(
+                machine,
+                hKey,
+                dwIndex,
+                lpName,
+                lpcchName,
+                lpReserved,
+                lpClass,
+                lpcchClass,
+                lpftLastWriteTime,
+            )
+            .to_raw()
+        }
+        pub unsafe fn RegEnumValueA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hKey = <HKEY>::from_stack(mem, esp + 4u32);
+            let dwIndex = <u32>::from_stack(mem, esp + 8u32);
+            let lpValueName = <u32>::from_stack(mem, esp + 12u32);
+            let lpcchValueName = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            let lpReserved = <u32>::from_stack(mem, esp + 20u32);
+            let lpType = <Option<&mut u32>>::from_stack(mem, esp + 24u32);
+            let lpData = <u32>::from_stack(mem, esp + 28u32);
+            let lpcbData = <Option<&mut u32>>::from_stack(mem, esp + 32u32);
+            winapi::advapi32::RegEnumValueA(
+                machine,
+                hKey,
+                dwIndex,
+                lpValueName,
+                lpcchValueName,
+                lpReserved,
+                lpType,
+                lpData,
+                lpcbData,
+            )
+            .to_raw()
+        }
+        pub unsafe fn RegFlushKey(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hKey = <HKEY>::from_stack(mem, esp + 4u32);
+            winapi::advapi32::RegFlushKey(machine, hKey).to_raw()
+        }
+        pub unsafe fn RegOpenKeyA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hKey = <HKEY>::from_stack(mem, esp + 4u32);
+            let lpSubKey = <Option<&str>>::from_stack(mem, esp + 8u32);
+            let phkResult = <Option<&mut HKEY>>::from_stack(mem, esp + 12u32);
+            winapi::advapi32::RegOpenKeyA(machine, hKey, lpSubKey, phkResult).to_raw()
+        }
+        pub unsafe fn RegOpenKeyExA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hKey = <HKEY>::from_stack(mem, esp + 4u32);
+            let lpSubKey = <Option<&str>>::from_stack(mem, esp + 8u32);
+            let ulOptions = <u32>::from_stack(mem, esp + 12u32);
+            let samDesired = <u32>::from_stack(mem, esp + 16u32);
+            let phkResult = <Option<&mut HKEY>>::from_stack(mem, esp + 20u32);
+            winapi::advapi32::RegOpenKeyExA(
+                machine, hKey, lpSubKey, ulOptions, samDesired, phkResult,
+            )
+            .to_raw()
+        }
+        pub unsafe fn RegQueryValueExA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hKey = <HKEY>::from_stack(mem, esp + 4u32);
+            let lpValueName = <Option<&str>>::from_stack(mem, esp + 8u32);
+            let lpReserved = <u32>::from_stack(mem, esp + 12u32);
+            let lpType = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            let lpData = <u32>::from_stack(mem, esp + 20u32);
+            let lpcbData = <Option<&mut u32>>::from_stack(mem, esp + 24u32);
+            winapi::advapi32::RegQueryValueExA(
+                machine,
+                hKey,
+                lpValueName,
+                lpReserved,
+                lpType,
+                lpData,
+                lpcbData,
+            )
+            .to_raw()
+        }
         pub unsafe fn RegQueryValueExW(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hKey = <HKEY>::from_stack(mem, esp + 4u32);
@@ -70,6 +444,25 @@ pub mod advapi32 {
             )
             .to_raw()
         }
+        pub unsafe fn RegSetValueExA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hKey = <HKEY>::from_stack(mem, esp + 4u32);
+            let lpValueName = <Option<&str>>::from_stack(mem, esp + 8u32);
+            let Reserved = <u32>::from_stack(mem, esp + 12u32);
+            let dwType = <u32>::from_stack(mem, esp + 16u32);
+            let lpData = <u32>::from_stack(mem, esp + 20u32);
+            let cbData = <u32>::from_stack(mem, esp + 24u32);
+            winapi::advapi32::RegSetValueExA(
+                machine,
+                hKey,
+                lpValueName,
+                Reserved,
+                dwType,
+                lpData,
+                cbData,
+            )
+            .to_raw()
+        }
         pub unsafe fn RegSetValueExW(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hKey = <HKEY>::from_stack(mem, esp + 4u32);
@@ -89,59 +482,405 @@ pub mod advapi32 {
             )
             .to_raw()
         }
+        pub unsafe fn SystemFunction036(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let RandomBuffer = <u32>::from_stack(mem, esp + 4u32);
+            let RandomBufferLength = <u32>::from_stack(mem, esp + 8u32);
+            winapi::advapi32::SystemFunction036(machine, RandomBuffer, RandomBufferLength).to_raw()
+        }
     }
     mod shims {
         use super::impls;
         use crate::shims::Shim;
+        pub const AllocateAndInitializeSid: Shim = Shim {
+            name: "AllocateAndInitializeSid",
+            func: impls::AllocateAndInitializeSid,
+            stack_consumed: 44u32,
+            is_async: false,
+        };
+        pub const CheckTokenMembership: Shim = Shim {
+            name: "CheckTokenMembership",
+            func: impls::CheckTokenMembership,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const CryptAcquireContextA: Shim = Shim {
+            name: "CryptAcquireContextA",
+            func: impls::CryptAcquireContextA,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const CryptAcquireContextW: Shim = Shim {
+            name: "CryptAcquireContextW",
+            func: impls::CryptAcquireContextW,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const CryptCreateHash: Shim = Shim {
+            name: "CryptCreateHash",
+            func: impls::CryptCreateHash,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const CryptDestroyHash: Shim = Shim {
+            name: "CryptDestroyHash",
+            func: impls::CryptDestroyHash,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const CryptGenRandom: Shim = Shim {
+            name: "CryptGenRandom",
+            func: impls::CryptGenRandom,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const CryptGetHashParam: Shim = Shim {
+            name: "CryptGetHashParam",
+            func: impls::CryptGetHashParam,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const CryptHashData: Shim = Shim {
+            name: "CryptHashData",
+            func: impls::CryptHashData,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const CryptReleaseContext: Shim = Shim {
+            name: "CryptReleaseContext",
+            func: impls::CryptReleaseContext,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const EqualSid: Shim = Shim {
+            name: "EqualSid",
+            func: impls::EqualSid,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const FreeSid: Shim = Shim {
+            name: "FreeSid",
+            func: impls::FreeSid,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetLengthSid: Shim = Shim {
+            name: "GetLengthSid",
+            func: impls::GetLengthSid,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetTokenInformation: Shim = Shim {
+            name: "GetTokenInformation",
+            func: impls::GetTokenInformation,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const GetUserNameA: Shim = Shim {
+            name: "GetUserNameA",
+            func: impls::GetUserNameA,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const GetUserNameW: Shim = Shim {
+            name: "GetUserNameW",
+            func: impls::GetUserNameW,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const IsValidSid: Shim = Shim {
+            name: "IsValidSid",
+            func: impls::IsValidSid,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const LookupAccountNameA: Shim = Shim {
+            name: "LookupAccountNameA",
+            func: impls::LookupAccountNameA,
+            stack_consumed: 28u32,
+            is_async: false,
+        };
+        pub const LookupAccountSidA: Shim = Shim {
+            name: "LookupAccountSidA",
+            func: impls::LookupAccountSidA,
+            stack_consumed: 28u32,
+            is_async: false,
+        };
+        pub const OpenProcessToken: Shim = Shim {
+            name: "OpenProcessToken",
+            func: impls::OpenProcessToken,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const OpenThreadToken: Shim = Shim {
+            name: "OpenThreadToken",
+            func: impls::OpenThreadToken,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
         pub const RegCloseKey: Shim = Shim {
             name: "RegCloseKey",
             func: impls::RegCloseKey,
             stack_consumed: 4u32,
             is_async: false,
         };
+        pub const RegCreateKeyA: Shim = Shim {
+            name: "RegCreateKeyA",
+            func: impls::RegCreateKeyA,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const RegCreateKeyExA: Shim = Shim {
+            name: "RegCreateKeyExA",
+            func: impls::RegCreateKeyExA,
+            stack_consumed: 36u32,
+            is_async: false,
+        };
         pub const RegCreateKeyExW: Shim = Shim {
             name: "RegCreateKeyExW",
             func: impls::RegCreateKeyExW,
             stack_consumed: 36u32,
             is_async: false,
         };
-        pub const RegQueryValueExW: Shim = Shim {
-            name: "RegQueryValueExW",
-            func: impls::RegQueryValueExW,
-            stack_consumed: 24u32,
+        pub const RegDeleteKeyA: Shim = Shim {
+            name: "RegDeleteKeyA",
+            func: impls::RegDeleteKeyA,
+            stack_consumed: 8u32,
             is_async: false,
         };
-        pub const RegSetValueExW: Shim = Shim {
-            name: "RegSetValueExW",
-            func: impls::RegSetValueExW,
-            stack_consumed: 24u32,
+        pub const RegDeleteValueA: Shim = Shim {
+            name: "RegDeleteValueA",
+            func: impls::RegDeleteValueA,
+            stack_consumed: 8u32,
             is_async: false,
         };
-    }
-    const EXPORTS: [Symbol; 4usize] = [
-        Symbol {
-            ordinal: None,
-            shim: shims::RegCloseKey,
-        },
-        Symbol {
-            ordinal: None,
-            shim: shims::RegCreateKeyExW,
-        },
+        pub const RegEnumKeyA: Shim = Shim {
+            name: "RegEnumKeyA",
+            func: impls::RegEnumKeyA,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const RegEnumKeyExA: Shim = Shim {
+            name: "RegEnumKeyExA",
+            func: impls::RegEnumKeyExA,
+            stack_consumed: 32u32,
+            is_async: false,
+        };
+        pub const RegEnumValueA: Shim = Shim {
+            name: "RegEnumValueA",
+            func: impls::RegEnumValueA,
+            stack_consumed: 32u32,
+            is_async: false,
+        };
+        pub const RegFlushKey: Shim = Shim {
+            name: "RegFlushKey",
+            func: impls::RegFlushKey,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const RegOpenKeyA: Shim = Shim {
+            name: "RegOpenKeyA",
+            func: impls::RegOpenKeyA,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const RegOpenKeyExA: Shim = Shim {
+            name: "RegOpenKeyExA",
+            func: impls::RegOpenKeyExA,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const RegQueryValueExA: Shim = Shim {
+            name: "RegQueryValueExA",
+            func: impls::RegQueryValueExA,
+            stack_consumed: 24u32,
+            is_async: false,
+        };
+        pub const RegQueryValueExW: Shim = Shim {
+            name: "RegQueryValueExW",
+            func: impls::RegQueryValueExW,
+            stack_consumed: 24u32,
+            is_async: false,
+        };
+        pub const RegSetValueExA: Shim = Shim {
+            name: "RegSetValueExA",
+            func: impls::RegSetValueExA,
+            stack_consumed: 24u32,
+            is_async: false,
+        };
+        pub const RegSetValueExW: Shim = Shim {
+            name: "RegSetValueExW",
+            func: impls::RegSetValueExW,
+            stack_consumed: 24u32,
+            is_async: false,
+        };
+        pub const SystemFunction036: Shim = Shim {
+            name: "SystemFunction036",
+            func: impls::SystemFunction036,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 38usize] = [
+        Symbol {
+            ordinal: None,
+            shim: shims::AllocateAndInitializeSid,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::CheckTokenMembership,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::CryptAcquireContextA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::CryptAcquireContextW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::CryptCreateHash,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::CryptDestroyHash,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::CryptGenRandom,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::CryptGetHashParam,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::CryptHashData,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::CryptReleaseContext,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::EqualSid,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::FreeSid,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetLengthSid,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetTokenInformation,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetUserNameA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetUserNameW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::IsValidSid,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LookupAccountNameA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LookupAccountSidA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::OpenProcessToken,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::OpenThreadToken,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegCloseKey,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegCreateKeyA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegCreateKeyExA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegCreateKeyExW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegDeleteKeyA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegDeleteValueA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegEnumKeyA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegEnumKeyExA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegEnumValueA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegFlushKey,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegOpenKeyA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegOpenKeyExA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegQueryValueExA,
+        },
         Symbol {
             ordinal: None,
             shim: shims::RegQueryValueExW,
         },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegSetValueExA,
+        },
         Symbol {
             ordinal: None,
             shim: shims::RegSetValueExW,
         },
+        Symbol {
+            ordinal: None,
+            shim: shims::SystemFunction036,
+        },
     ];
     pub const DLL: BuiltinDLL = BuiltinDLL {
         file_name: "advapi32.dll",
         exports: &EXPORTS,
     };
 }
-pub mod bass {
+pub mod avifil32 {
     use super::*;
     mod impls {
         use crate::{
@@ -149,101 +888,359 @@ pub mod bass {
             winapi::{self, stack_args::*, types::*},
         };
         use memory::Extensions;
-        use winapi::bass::*;
-        pub unsafe fn BASS_ChannelGetPosition(machine: &mut Machine, esp: u32) -> u32 {
+        use winapi::avifil32::*;
+        pub unsafe fn AVIFileAddRef(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let arg1 = <u32>::from_stack(mem, esp + 4u32);
-            winapi::bass::BASS_ChannelGetPosition(machine, arg1).to_raw()
+            let pfile = <u32>::from_stack(mem, esp + 4u32);
+            winapi::avifil32::AVIFileAddRef(machine, pfile).to_raw()
         }
-        pub unsafe fn BASS_Init(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn AVIFileExit(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let arg1 = <u32>::from_stack(mem, esp + 4u32);
-            let arg2 = <u32>::from_stack(mem, esp + 8u32);
-            let arg3 = <u32>::from_stack(mem, esp + 12u32);
-            let arg4 = <u32>::from_stack(mem, esp + 16u32);
-            winapi::bass::BASS_Init(machine, arg1, arg2, arg3, arg4).to_raw()
+            winapi::avifil32::AVIFileExit(machine).to_raw()
         }
-        pub unsafe fn BASS_MusicLoad(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn AVIFileGetStream(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let arg1 = <u32>::from_stack(mem, esp + 4u32);
-            let arg2 = <u32>::from_stack(mem, esp + 8u32);
-            let arg3 = <u32>::from_stack(mem, esp + 12u32);
-            let arg4 = <u32>::from_stack(mem, esp + 16u32);
-            let arg5 = <u32>::from_stack(mem, esp + 20u32);
-            winapi::bass::BASS_MusicLoad(machine, arg1, arg2, arg3, arg4, arg5).to_raw()
+            let pfile = <u32>::from_stack(mem, esp + 4u32);
+            let ppavi = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
+            let fccType = <u32>::from_stack(mem, esp + 12u32);
+            let lParam = <u32>::from_stack(mem, esp + 16u32);
+            winapi::avifil32::AVIFileGetStream(machine, pfile, ppavi, fccType, lParam).to_raw()
         }
-        pub unsafe fn BASS_MusicPlay(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn AVIFileInfoA(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let arg1 = <u32>::from_stack(mem, esp + 4u32);
-            winapi::bass::BASS_MusicPlay(machine, arg1).to_raw()
+            let pfile = <u32>::from_stack(mem, esp + 4u32);
+            let pfi = <Option<&mut AVIFILEINFOA>>::from_stack(mem, esp + 8u32);
+            let lSize = <u32>::from_stack(mem, esp + 12u32);
+            winapi::avifil32::AVIFileInfoA(machine, pfile, pfi, lSize).to_raw()
         }
-        pub unsafe fn BASS_Start(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn AVIFileInit(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::bass::BASS_Start(machine).to_raw()
+            winapi::avifil32::AVIFileInit(machine).to_raw()
+        }
+        pub unsafe fn AVIFileOpenA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let ppfile = <Option<&mut u32>>::from_stack(mem, esp + 4u32);
+            let szFile = <Option<&str>>::from_stack(mem, esp + 8u32);
+            let uMode = <u32>::from_stack(mem, esp + 12u32);
+            let lpHandler = <u32>::from_stack(mem, esp + 16u32);
+            winapi::avifil32::AVIFileOpenA(machine, ppfile, szFile, uMode, lpHandler).to_raw()
+        }
+        pub unsafe fn AVIFileRelease(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pfile = <u32>::from_stack(mem, esp + 4u32);
+            winapi::avifil32::AVIFileRelease(machine, pfile).to_raw()
+        }
+        pub unsafe fn AVIStreamAddRef(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pavi = <u32>::from_stack(mem, esp + 4u32);
+            winapi::avifil32::AVIStreamAddRef(machine, pavi).to_raw()
+        }
+        pub unsafe fn AVIStreamGetFrame(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pg = <u32>::from_stack(mem, esp + 4u32);
+            let lPos = <i32>::from_stack(mem, esp + 8u32);
+            winapi::avifil32::AVIStreamGetFrame(machine, pg, lPos).to_raw()
+        }
+        pub unsafe fn AVIStreamGetFrameClose(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pg = <u32>::from_stack(mem, esp + 4u32);
+            winapi::avifil32::AVIStreamGetFrameClose(machine, pg).to_raw()
+        }
+        pub unsafe fn AVIStreamGetFrameOpen(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pavi = <u32>::from_stack(mem, esp + 4u32);
+            let lpbiWanted = <u32>::from_stack(mem, esp + 8u32);
+            winapi::avifil32::AVIStreamGetFrameOpen(machine, pavi, lpbiWanted).to_raw()
+        }
+        pub unsafe fn AVIStreamInfoA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pavi = <u32>::from_stack(mem, esp + 4u32);
+            let psi = <Option<&mut AVISTREAMINFOA>>::from_stack(mem, esp + 8u32);
+            let lSize = <u32>::from_stack(mem, esp + 12u32);
+            winapi::avifil32::AVIStreamInfoA(machine, pavi, psi, lSize).to_raw()
+        }
+        pub unsafe fn AVIStreamLength(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pavi = <u32>::from_stack(mem, esp + 4u32);
+            winapi::avifil32::AVIStreamLength(machine, pavi).to_raw()
+        }
+        pub unsafe fn AVIStreamOpenFromFileA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let ppavi = <Option<&mut u32>>::from_stack(mem, esp + 4u32);
+            let szFile = <Option<&str>>::from_stack(mem, esp + 8u32);
+            let fccType = <u32>::from_stack(mem, esp + 12u32);
+            let lParam = <u32>::from_stack(mem, esp + 16u32);
+            let mode = <u32>::from_stack(mem, esp + 20u32);
+            let pclsidHandler = <u32>::from_stack(mem, esp + 24u32);
+            winapi::avifil32::AVIStreamOpenFromFileA(
+                machine,
+                ppavi,
+                szFile,
+                fccType,
+                lParam,
+                mode,
+                pclsidHandler,
+            )
+            .to_raw()
+        }
+        pub unsafe fn AVIStreamRead(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pavi = <u32>::from_stack(mem, esp + 4u32);
+            let lStart = <i32>::from_stack(mem, esp + 8u32);
+            let lSamples = <i32>::from_stack(mem, esp + 12u32);
+            let lpBuffer = <u32>::from_stack(mem, esp + 16u32);
+            let cbBuffer = <u32>::from_stack(mem, esp + 20u32);
+            let plBytes = <Option<&mut i32>>::from_stack(mem, esp + 24u32);
+            let plSamples = <Option<&mut i32>>::from_stack(mem, esp + 28u32);
+            winapi::avifil32::AVIStreamRead(
+                machine, pavi, lStart, lSamples, lpBuffer, cbBuffer, plBytes, plSamples,
+            )
+            .to_raw()
+        }
+        pub unsafe fn AVIStreamReadFormat(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pavi = <u32>::from_stack(mem, esp + 4u32);
+            let lPos = <i32>::from_stack(mem, esp + 8u32);
+            let lpFormat = <u32>::from_stack(mem, esp + 12u32);
+            let lpcbFormat = <Option<&mut i32>>::from_stack(mem, esp + 16u32);
+            winapi::avifil32::AVIStreamReadFormat(machine, pavi, lPos, lpFormat, lpcbFormat)
+                .to_raw()
+        }
+        pub unsafe fn AVIStreamRelease(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pavi = <u32>::from_stack(mem, esp + 4u32);
+            winapi::avifil32::AVIStreamRelease(machine, pavi).to_raw()
+        }
+        pub unsafe fn AVIStreamSampleToTime(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pavi = <u32>::from_stack(mem, esp + 4u32);
+            let lSample = <i32>::from_stack(mem, esp + 8u32);
+            winapi::avifil32::AVIStreamSampleToTime(machine, pavi, lSample).to_raw()
+        }
+        pub unsafe fn AVIStreamStart(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pavi = <u32>::from_stack(mem, esp + 4u32);
+            winapi::avifil32::AVIStreamStart(machine, pavi).to_raw()
+        }
+        pub unsafe fn AVIStreamTimeToSample(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pavi = <u32>::from_stack(mem, esp + 4u32);
+            let lTime = <i32>::from_stack(mem, esp + 8u32);
+            winapi::avifil32::AVIStreamTimeToSample(machine, pavi, lTime).to_raw()
         }
     }
     mod shims {
         use super::impls;
         use crate::shims::Shim;
-        pub const BASS_ChannelGetPosition: Shim = Shim {
-            name: "BASS_ChannelGetPosition",
-            func: impls::BASS_ChannelGetPosition,
+        pub const AVIFileAddRef: Shim = Shim {
+            name: "AVIFileAddRef",
+            func: impls::AVIFileAddRef,
             stack_consumed: 4u32,
             is_async: false,
         };
-        pub const BASS_Init: Shim = Shim {
-            name: "BASS_Init",
-            func: impls::BASS_Init,
+        pub const AVIFileExit: Shim = Shim {
+            name: "AVIFileExit",
+            func: impls::AVIFileExit,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const AVIFileGetStream: Shim = Shim {
+            name: "AVIFileGetStream",
+            func: impls::AVIFileGetStream,
             stack_consumed: 16u32,
             is_async: false,
         };
-        pub const BASS_MusicLoad: Shim = Shim {
-            name: "BASS_MusicLoad",
-            func: impls::BASS_MusicLoad,
-            stack_consumed: 20u32,
+        pub const AVIFileInfoA: Shim = Shim {
+            name: "AVIFileInfoA",
+            func: impls::AVIFileInfoA,
+            stack_consumed: 12u32,
             is_async: false,
         };
-        pub const BASS_MusicPlay: Shim = Shim {
-            name: "BASS_MusicPlay",
-            func: impls::BASS_MusicPlay,
+        pub const AVIFileInit: Shim = Shim {
+            name: "AVIFileInit",
+            func: impls::AVIFileInit,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const AVIFileOpenA: Shim = Shim {
+            name: "AVIFileOpenA",
+            func: impls::AVIFileOpenA,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const AVIFileRelease: Shim = Shim {
+            name: "AVIFileRelease",
+            func: impls::AVIFileRelease,
             stack_consumed: 4u32,
             is_async: false,
         };
-        pub const BASS_Start: Shim = Shim {
-            name: "BASS_Start",
-            func: impls::BASS_Start,
-            stack_consumed: 0u32,
+        pub const AVIStreamAddRef: Shim = Shim {
+            name: "AVIStreamAddRef",
+            func: impls::AVIStreamAddRef,
+            stack_consumed: 4u32,
             is_async: false,
         };
-    }
-    const EXPORTS: [Symbol; 5usize] = [
-        Symbol {
-            ordinal: None,
-            shim: shims::BASS_ChannelGetPosition,
-        },
-        Symbol {
-            ordinal: None,
-            shim: shims::BASS_Init,
-        },
-        Symbol {
-            ordinal: None,
-            shim: shims::BASS_MusicLoad,
-        },
-        Symbol {
-            ordinal: None,
-            shim: shims::BASS_MusicPlay,
-        },
-        Symbol {
-            ordinal: None,
-            shim: shims::BASS_Start,
+        pub const AVIStreamGetFrame: Shim = Shim {
+            name: "AVIStreamGetFrame",
+            func: impls::AVIStreamGetFrame,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const AVIStreamGetFrameClose: Shim = Shim {
+            name: "AVIStreamGetFrameClose",
+            func: impls::AVIStreamGetFrameClose,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const AVIStreamGetFrameOpen: Shim = Shim {
+            name: "AVIStreamGetFrameOpen",
+            func: impls::AVIStreamGetFrameOpen,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const AVIStreamInfoA: Shim = Shim {
+            name: "AVIStreamInfoA",
+            func: impls::AVIStreamInfoA,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const AVIStreamLength: Shim = Shim {
+            name: "AVIStreamLength",
+            func: impls::AVIStreamLength,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const AVIStreamOpenFromFileA: Shim = Shim {
+            name: "AVIStreamOpenFromFileA",
+            func: impls::AVIStreamOpenFromFileA,
+            stack_consumed: 24u32,
+            is_async: false,
+        };
+        pub const AVIStreamRead: Shim = Shim {
+            name: "AVIStreamRead",
+            func: impls::AVIStreamRead,
+            stack_consumed: 28u32,
+            is_async: false,
+        };
+        pub const AVIStreamReadFormat: Shim = Shim {
+            name: "AVIStreamReadFormat",
+            func: impls::AVIStreamReadFormat,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const AVIStreamRelease: Shim = Shim {
+            name: "AVIStreamRelease",
+            func: impls::AVIStreamRelease,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const AVIStreamSampleToTime: Shim = Shim {
+            name: "AVIStreamSampleToTime",
+            func: impls::AVIStreamSampleToTime,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const AVIStreamStart: Shim = Shim {
+            name: "AVIStreamStart",
+            func: impls::AVIStreamStart,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const AVIStreamTimeToSample: Shim = Shim {
+            name: "AVIStreamTimeToSample",
+            func: impls::AVIStreamTimeToSample,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 20usize] = [
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIFileAddRef,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIFileExit,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIFileGetStream,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIFileInfoA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIFileInit,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIFileOpenA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIFileRelease,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIStreamAddRef,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIStreamGetFrame,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIStreamGetFrameClose,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIStreamGetFrameOpen,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIStreamInfoA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIStreamLength,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIStreamOpenFromFileA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIStreamRead,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIStreamReadFormat,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIStreamRelease,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIStreamSampleToTime,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIStreamStart,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AVIStreamTimeToSample,
         },
     ];
     pub const DLL: BuiltinDLL = BuiltinDLL {
-        file_name: "bass.dll",
+        file_name: "avifil32.dll",
         exports: &EXPORTS,
     };
 }
-pub mod ddraw {
+pub mod bass {
     use super::*;
     mod impls {
         use crate::{
@@ -251,55 +1248,101 @@ pub mod ddraw {
             winapi::{self, stack_args::*, types::*},
         };
         use memory::Extensions;
-        use winapi::ddraw::*;
-        pub unsafe fn DirectDrawCreate(machine: &mut Machine, esp: u32) -> u32 {
+        use winapi::bass::*;
+        pub unsafe fn BASS_ChannelGetPosition(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpGuid = <u32>::from_stack(mem, esp + 4u32);
-            let lplpDD = <u32>::from_stack(mem, esp + 8u32);
-            let pUnkOuter = <u32>::from_stack(mem, esp + 12u32);
-            winapi::ddraw::DirectDrawCreate(machine, lpGuid, lplpDD, pUnkOuter).to_raw()
+            let arg1 = <u32>::from_stack(mem, esp + 4u32);
+            winapi::bass::BASS_ChannelGetPosition(machine, arg1).to_raw()
         }
-        pub unsafe fn DirectDrawCreateEx(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn BASS_Init(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpGuid = <u32>::from_stack(mem, esp + 4u32);
-            let lplpDD = <u32>::from_stack(mem, esp + 8u32);
-            let iid = <u32>::from_stack(mem, esp + 12u32);
-            let pUnkOuter = <u32>::from_stack(mem, esp + 16u32);
-            winapi::ddraw::DirectDrawCreateEx(machine, lpGuid, lplpDD, iid, pUnkOuter).to_raw()
+            let arg1 = <u32>::from_stack(mem, esp + 4u32);
+            let arg2 = <u32>::from_stack(mem, esp + 8u32);
+            let arg3 = <u32>::from_stack(mem, esp + 12u32);
+            let arg4 = <u32>::from_stack(mem, esp + 16u32);
+            winapi::bass::BASS_Init(machine, arg1, arg2, arg3, arg4).to_raw()
+        }
+        pub unsafe fn BASS_MusicLoad(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let arg1 = <u32>::from_stack(mem, esp + 4u32);
+            let arg2 = <u32>::from_stack(mem, esp + 8u32);
+            let arg3 = <u32>::from_stack(mem, esp + 12u32);
+            let arg4 = <u32>::from_stack(mem, esp + 16u32);
+            let arg5 = <u32>::from_stack(mem, esp + 20u32);
+            winapi::bass::BASS_MusicLoad(machine, arg1, arg2, arg3, arg4, arg5).to_raw()
+        }
+        pub unsafe fn BASS_MusicPlay(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let arg1 = <u32>::from_stack(mem, esp + 4u32);
+            winapi::bass::BASS_MusicPlay(machine, arg1).to_raw()
+        }
+        pub unsafe fn BASS_Start(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::bass::BASS_Start(machine).to_raw()
         }
     }
     mod shims {
         use super::impls;
         use crate::shims::Shim;
-        pub const DirectDrawCreate: Shim = Shim {
-            name: "DirectDrawCreate",
-            func: impls::DirectDrawCreate,
-            stack_consumed: 12u32,
+        pub const BASS_ChannelGetPosition: Shim = Shim {
+            name: "BASS_ChannelGetPosition",
+            func: impls::BASS_ChannelGetPosition,
+            stack_consumed: 4u32,
             is_async: false,
         };
-        pub const DirectDrawCreateEx: Shim = Shim {
-            name: "DirectDrawCreateEx",
-            func: impls::DirectDrawCreateEx,
+        pub const BASS_Init: Shim = Shim {
+            name: "BASS_Init",
+            func: impls::BASS_Init,
             stack_consumed: 16u32,
             is_async: false,
         };
+        pub const BASS_MusicLoad: Shim = Shim {
+            name: "BASS_MusicLoad",
+            func: impls::BASS_MusicLoad,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const BASS_MusicPlay: Shim = Shim {
+            name: "BASS_MusicPlay",
+            func: impls::BASS_MusicPlay,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const BASS_Start: Shim = Shim {
+            name: "BASS_Start",
+            func: impls::BASS_Start,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
     }
-    const EXPORTS: [Symbol; 2usize] = [
+    const EXPORTS: [Symbol; 5usize] = [
         Symbol {
             ordinal: None,
-            shim: shims::DirectDrawCreate,
+            shim: shims::BASS_ChannelGetPosition,
         },
         Symbol {
             ordinal: None,
-            shim: shims::DirectDrawCreateEx,
+            shim: shims::BASS_Init,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::BASS_MusicLoad,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::BASS_MusicPlay,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::BASS_Start,
         },
     ];
     pub const DLL: BuiltinDLL = BuiltinDLL {
-        file_name: "ddraw.dll",
+        file_name: "bass.dll",
         exports: &EXPORTS,
     };
 }
-pub mod dsound {
+pub mod comctl32 {
     use super::*;
     mod impls {
         use crate::{
@@ -307,53 +1350,91 @@ pub mod dsound {
             winapi::{self, stack_args::*, types::*},
         };
         use memory::Extensions;
-        use winapi::dsound::*;
-        pub unsafe fn DirectSoundCreate(machine: &mut Machine, esp: u32) -> u32 {
+        use winapi::comctl32::*;
+        pub unsafe fn CreateStatusWindowA(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpGuid = <u32>::from_stack(mem, esp + 4u32);
-            let ppDS = <u32>::from_stack(mem, esp + 8u32);
-            let pUnkOuter = <u32>::from_stack(mem, esp + 12u32);
-            winapi::dsound::DirectSoundCreate(machine, lpGuid, ppDS, pUnkOuter).to_raw()
+            let style = <u32>::from_stack(mem, esp + 4u32);
+            let lpszText = <Option<&str>>::from_stack(mem, esp + 8u32);
+            let hwndParent = <HWND>::from_stack(mem, esp + 12u32);
+            let wID = <u32>::from_stack(mem, esp + 16u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::comctl32::CreateStatusWindowA(
+                        machine, style, lpszText, hwndParent, wID,
+                    )
+                    .await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 16u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::comctl32::CreateStatusWindowA(
+                    machine, style, lpszText, hwndParent, wID
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
         }
-        pub unsafe fn DirectSoundEnumerateA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn InitCommonControls(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpDSEnumCallback = <u32>::from_stack(mem, esp + 4u32);
-            let lpContext = <u32>::from_stack(mem, esp + 8u32);
-            winapi::dsound::DirectSoundEnumerateA(machine, lpDSEnumCallback, lpContext).to_raw()
+            winapi::comctl32::InitCommonControls(machine).to_raw()
+        }
+        pub unsafe fn InitCommonControlsEx(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let picce = <Option<&INITCOMMONCONTROLSEX>>::from_stack(mem, esp + 4u32);
+            winapi::comctl32::InitCommonControlsEx(machine, picce).to_raw()
         }
     }
     mod shims {
         use super::impls;
         use crate::shims::Shim;
-        pub const DirectSoundCreate: Shim = Shim {
-            name: "DirectSoundCreate",
-            func: impls::DirectSoundCreate,
-            stack_consumed: 12u32,
+        pub const CreateStatusWindowA: Shim = Shim {
+            name: "CreateStatusWindowA",
+            func: impls::CreateStatusWindowA,
+            stack_consumed: 16u32,
+            is_async: true,
+        };
+        pub const InitCommonControls: Shim = Shim {
+            name: "InitCommonControls",
+            func: impls::InitCommonControls,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const DirectSoundEnumerateA: Shim = Shim {
-            name: "DirectSoundEnumerateA",
-            func: impls::DirectSoundEnumerateA,
-            stack_consumed: 8u32,
+        pub const InitCommonControlsEx: Shim = Shim {
+            name: "InitCommonControlsEx",
+            func: impls::InitCommonControlsEx,
+            stack_consumed: 4u32,
             is_async: false,
         };
     }
-    const EXPORTS: [Symbol; 2usize] = [
+    const EXPORTS: [Symbol; 3usize] = [
         Symbol {
-            ordinal: Some(1usize),
-            shim: shims::DirectSoundCreate,
+            ordinal: None,
+            shim: shims::CreateStatusWindowA,
         },
         Symbol {
-            ordinal: Some(2usize),
-            shim: shims::DirectSoundEnumerateA,
+            ordinal: Some(17usize),
+            shim: shims::InitCommonControls,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::InitCommonControlsEx,
         },
     ];
     pub const DLL: BuiltinDLL = BuiltinDLL {
-        file_name: "dsound.dll",
+        file_name: "comctl32.dll",
         exports: &EXPORTS,
     };
 }
-pub mod gdi32 {
+pub mod comdlg32 {
     use super::*;
     mod impls {
         use crate::{
@@ -361,13 +1442,308 @@ pub mod gdi32 {
             winapi::{self, stack_args::*, types::*},
         };
         use memory::Extensions;
-        use winapi::gdi32::*;
-        pub unsafe fn BitBlt(machine: &mut Machine, esp: u32) -> u32 {
+        use winapi::comdlg32::*;
+        pub unsafe fn CommDlgExtendedError(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hdc = <HDC>::from_stack(mem, esp + 4u32);
-            let x = <u32>::from_stack(mem, esp + 8u32);
-            let y = <u32>::from_stack(mem, esp + 12u32);
-            let cx = <u32>::from_stack(mem, esp + 16u32);
+            winapi::comdlg32::CommDlgExtendedError(machine).to_raw()
+        }
+        pub unsafe fn GetOpenFileNameA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpofn = <Option<&mut OPENFILENAMEA>>::from_stack(mem, esp + 4u32);
+            winapi::comdlg32::GetOpenFileNameA(machine, lpofn).to_raw()
+        }
+        pub unsafe fn GetSaveFileNameA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpofn = <Option<&mut OPENFILENAMEA>>::from_stack(mem, esp + 4u32);
+            winapi::comdlg32::GetSaveFileNameA(machine, lpofn).to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const CommDlgExtendedError: Shim = Shim {
+            name: "CommDlgExtendedError",
+            func: impls::CommDlgExtendedError,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetOpenFileNameA: Shim = Shim {
+            name: "GetOpenFileNameA",
+            func: impls::GetOpenFileNameA,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetSaveFileNameA: Shim = Shim {
+            name: "GetSaveFileNameA",
+            func: impls::GetSaveFileNameA,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 3usize] = [
+        Symbol {
+            ordinal: None,
+            shim: shims::CommDlgExtendedError,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetOpenFileNameA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetSaveFileNameA,
+        },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "comdlg32.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod ddraw {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::ddraw::*;
+        pub unsafe fn DirectDrawCreate(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpGuid = <u32>::from_stack(mem, esp + 4u32);
+            let lplpDD = <u32>::from_stack(mem, esp + 8u32);
+            let pUnkOuter = <u32>::from_stack(mem, esp + 12u32);
+            winapi::ddraw::DirectDrawCreate(machine, lpGuid, lplpDD, pUnkOuter).to_raw()
+        }
+        pub unsafe fn DirectDrawCreateClipper(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let dwFlags = <u32>::from_stack(mem, esp + 4u32);
+            let lplpDDClipper = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
+            let pUnkOuter = <u32>::from_stack(mem, esp + 12u32);
+            winapi::ddraw::DirectDrawCreateClipper(machine, dwFlags, lplpDDClipper, pUnkOuter)
+                .to_raw()
+        }
+        pub unsafe fn DirectDrawCreateEx(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpGuid = <u32>::from_stack(mem, esp + 4u32);
+            let lplpDD = <u32>::from_stack(mem, esp + 8u32);
+            let iid = <u32>::from_stack(mem, esp + 12u32);
+            let pUnkOuter = <u32>::from_stack(mem, esp + 16u32);
+            winapi::ddraw::DirectDrawCreateEx(machine, lpGuid, lplpDD, iid, pUnkOuter).to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const DirectDrawCreate: Shim = Shim {
+            name: "DirectDrawCreate",
+            func: impls::DirectDrawCreate,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const DirectDrawCreateClipper: Shim = Shim {
+            name: "DirectDrawCreateClipper",
+            func: impls::DirectDrawCreateClipper,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const DirectDrawCreateEx: Shim = Shim {
+            name: "DirectDrawCreateEx",
+            func: impls::DirectDrawCreateEx,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 3usize] = [
+        Symbol {
+            ordinal: None,
+            shim: shims::DirectDrawCreate,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::DirectDrawCreateClipper,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::DirectDrawCreateEx,
+        },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "ddraw.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod dinput {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::dinput::*;
+        pub unsafe fn DirectInputCreateA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hinst = <u32>::from_stack(mem, esp + 4u32);
+            let dwVersion = <u32>::from_stack(mem, esp + 8u32);
+            let ppDI = <Option<&mut u32>>::from_stack(mem, esp + 12u32);
+            let punkOuter = <u32>::from_stack(mem, esp + 16u32);
+            winapi::dinput::DirectInputCreateA(machine, hinst, dwVersion, ppDI, punkOuter).to_raw()
+        }
+        pub unsafe fn DirectInputCreateEx(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hinst = <u32>::from_stack(mem, esp + 4u32);
+            let dwVersion = <u32>::from_stack(mem, esp + 8u32);
+            let riidltf = <u32>::from_stack(mem, esp + 12u32);
+            let ppvOut = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            let punkOuter = <u32>::from_stack(mem, esp + 20u32);
+            winapi::dinput::DirectInputCreateEx(
+                machine, hinst, dwVersion, riidltf, ppvOut, punkOuter,
+            )
+            .to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const DirectInputCreateA: Shim = Shim {
+            name: "DirectInputCreateA",
+            func: impls::DirectInputCreateA,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const DirectInputCreateEx: Shim = Shim {
+            name: "DirectInputCreateEx",
+            func: impls::DirectInputCreateEx,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 2usize] = [
+        Symbol {
+            ordinal: None,
+            shim: shims::DirectInputCreateA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::DirectInputCreateEx,
+        },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "dinput.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod dinput8 {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::dinput8::*;
+        pub unsafe fn DirectInput8Create(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hinst = <u32>::from_stack(mem, esp + 4u32);
+            let dwVersion = <u32>::from_stack(mem, esp + 8u32);
+            let riidltf = <u32>::from_stack(mem, esp + 12u32);
+            let ppvOut = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            let punkOuter = <u32>::from_stack(mem, esp + 20u32);
+            winapi::dinput8::DirectInput8Create(
+                machine, hinst, dwVersion, riidltf, ppvOut, punkOuter,
+            )
+            .to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const DirectInput8Create: Shim = Shim {
+            name: "DirectInput8Create",
+            func: impls::DirectInput8Create,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 1usize] = [Symbol {
+        ordinal: None,
+        shim: shims::DirectInput8Create,
+    }];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "dinput8.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod dsound {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::dsound::*;
+        pub unsafe fn DirectSoundCreate(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpGuid = <u32>::from_stack(mem, esp + 4u32);
+            let ppDS = <u32>::from_stack(mem, esp + 8u32);
+            let pUnkOuter = <u32>::from_stack(mem, esp + 12u32);
+            winapi::dsound::DirectSoundCreate(machine, lpGuid, ppDS, pUnkOuter).to_raw()
+        }
+        pub unsafe fn DirectSoundEnumerateA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpDSEnumCallback = <u32>::from_stack(mem, esp + 4u32);
+            let lpContext = <u32>::from_stack(mem, esp + 8u32);
+            winapi::dsound::DirectSoundEnumerateA(machine, lpDSEnumCallback, lpContext).to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const DirectSoundCreate: Shim = Shim {
+            name: "DirectSoundCreate",
+            func: impls::DirectSoundCreate,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const DirectSoundEnumerateA: Shim = Shim {
+            name: "DirectSoundEnumerateA",
+            func: impls::DirectSoundEnumerateA,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 2usize] = [
+        Symbol {
+            ordinal: Some(1usize),
+            shim: shims::DirectSoundCreate,
+        },
+        Symbol {
+            ordinal: Some(2usize),
+            shim: shims::DirectSoundEnumerateA,
+        },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "dsound.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod gdi32 {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::gdi32::*;
+        pub unsafe fn BitBlt(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let x = <u32>::from_stack(mem, esp + 8u32);
+            let y = <u32>::from_stack(mem, esp + 12u32);
+            let cx = <u32>::from_stack(mem, esp + 16u32);
             let cy = <u32>::from_stack(mem, esp + 20u32);
             let hdcSrc = <HDC>::from_stack(mem, esp + 24u32);
             let x1 = <u32>::from_stack(mem, esp + 28u32);
@@ -375,6 +1751,12 @@ pub mod gdi32 {
             let rop = <u32>::from_stack(mem, esp + 36u32);
             winapi::gdi32::BitBlt(machine, hdc, x, y, cx, cy, hdcSrc, x1, y1, rop).to_raw()
         }
+        pub unsafe fn ChoosePixelFormat(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let ppfd = <Option<&PIXELFORMATDESCRIPTOR>>::from_stack(mem, esp + 8u32);
+            winapi::gdi32::ChoosePixelFormat(machine, hdc, ppfd).to_raw()
+        }
         pub unsafe fn CreateBitmap(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let nWidth = <u32>::from_stack(mem, esp + 4u32);
@@ -452,49 +1834,134 @@ pub mod gdi32 {
         }
         pub unsafe fn DeleteDC(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hdc = <u32>::from_stack(mem, esp + 4u32);
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
             winapi::gdi32::DeleteDC(machine, hdc).to_raw()
         }
+        pub unsafe fn DeleteEnhMetaFile(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hmf = <HENHMETAFILE>::from_stack(mem, esp + 4u32);
+            winapi::gdi32::DeleteEnhMetaFile(machine, hmf).to_raw()
+        }
+        pub unsafe fn DeleteMetaFile(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hmf = <HMETAFILE>::from_stack(mem, esp + 4u32);
+            winapi::gdi32::DeleteMetaFile(machine, hmf).to_raw()
+        }
         pub unsafe fn DeleteObject(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let handle = <HGDIOBJ>::from_stack(mem, esp + 4u32);
             winapi::gdi32::DeleteObject(machine, handle).to_raw()
         }
-        pub unsafe fn GetDeviceCaps(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn DescribePixelFormat(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hdc = <HDC>::from_stack(mem, esp + 4u32);
-            let index = <Result<GetDeviceCapsArg, u32>>::from_stack(mem, esp + 8u32);
-            winapi::gdi32::GetDeviceCaps(machine, hdc, index).to_raw()
+            let iPixelFormat = <i32>::from_stack(mem, esp + 8u32);
+            let nBytes = <u32>::from_stack(mem, esp + 12u32);
+            let ppfd = <Option<&mut PIXELFORMATDESCRIPTOR>>::from_stack(mem, esp + 16u32);
+            winapi::gdi32::DescribePixelFormat(machine, hdc, iPixelFormat, nBytes, ppfd).to_raw()
         }
-        pub unsafe fn GetLayout(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn Ellipse(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hdc = <HDC>::from_stack(mem, esp + 4u32);
-            winapi::gdi32::GetLayout(machine, hdc).to_raw()
+            let left = <i32>::from_stack(mem, esp + 8u32);
+            let top = <i32>::from_stack(mem, esp + 12u32);
+            let right = <i32>::from_stack(mem, esp + 16u32);
+            let bottom = <i32>::from_stack(mem, esp + 20u32);
+            winapi::gdi32::Ellipse(machine, hdc, left, top, right, bottom).to_raw()
         }
-        pub unsafe fn GetObjectA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn ExtTextOutA(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let handle = <HGDIOBJ>::from_stack(mem, esp + 4u32);
-            let bytes = <u32>::from_stack(mem, esp + 8u32);
-            let out = <u32>::from_stack(mem, esp + 12u32);
-            winapi::gdi32::GetObjectA(machine, handle, bytes, out).to_raw()
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let x = <i32>::from_stack(mem, esp + 8u32);
+            let y = <i32>::from_stack(mem, esp + 12u32);
+            let options = <u32>::from_stack(mem, esp + 16u32);
+            let lprect = <Option<&RECT>>::from_stack(mem, esp + 20u32);
+            let lpString = <ArrayWithSize<u8>>::from_stack(mem, esp + 24u32);
+            let lpDx = <u32>::from_stack(mem, esp + 32u32);
+            winapi::gdi32::ExtTextOutA(machine, hdc, x, y, options, lprect, lpString, lpDx).to_raw()
         }
-        pub unsafe fn GetPixel(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn GetBkColor(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hdc = <HDC>::from_stack(mem, esp + 4u32);
-            let x = <u32>::from_stack(mem, esp + 8u32);
-            let y = <u32>::from_stack(mem, esp + 12u32);
-            winapi::gdi32::GetPixel(machine, hdc, x, y).to_raw()
+            winapi::gdi32::GetBkColor(machine, hdc).to_raw()
         }
-        pub unsafe fn GetStockObject(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn GetBkMode(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let i = <Result<GetStockObjectArg, u32>>::from_stack(mem, esp + 4u32);
-            winapi::gdi32::GetStockObject(machine, i).to_raw()
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            winapi::gdi32::GetBkMode(machine, hdc).to_raw()
         }
-        pub unsafe fn GetTextExtentPoint32A(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn GetDIBits(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hdc = <HDC>::from_stack(mem, esp + 4u32);
-            let lpString = <Option<&str>>::from_stack(mem, esp + 8u32);
-            let c = <i32>::from_stack(mem, esp + 12u32);
+            let hbm = <HGDIOBJ>::from_stack(mem, esp + 8u32);
+            let start = <u32>::from_stack(mem, esp + 12u32);
+            let cLines = <u32>::from_stack(mem, esp + 16u32);
+            let lpvBits = <u32>::from_stack(mem, esp + 20u32);
+            let lpbmi = <u32>::from_stack(mem, esp + 24u32);
+            let usage = <u32>::from_stack(mem, esp + 28u32);
+            winapi::gdi32::GetDIBits(machine, hdc, hbm, start, cLines, lpvBits, lpbmi, usage)
+                .to_raw()
+        }
+        pub unsafe fn GetDeviceCaps(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let index = <Result<GetDeviceCapsArg, u32>>::from_stack(mem, esp + 8u32);
+            winapi::gdi32::GetDeviceCaps(machine, hdc, index).to_raw()
+        }
+        pub unsafe fn GetEnhMetaFileA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpName = <Option<&str>>::from_stack(mem, esp + 4u32);
+            winapi::gdi32::GetEnhMetaFileA(machine, lpName).to_raw()
+        }
+        pub unsafe fn GetLayout(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            winapi::gdi32::GetLayout(machine, hdc).to_raw()
+        }
+        pub unsafe fn GetMapMode(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            winapi::gdi32::GetMapMode(machine, hdc).to_raw()
+        }
+        pub unsafe fn GetMetaFileA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpName = <Option<&str>>::from_stack(mem, esp + 4u32);
+            winapi::gdi32::GetMetaFileA(machine, lpName).to_raw()
+        }
+        pub unsafe fn GetObjectA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let handle = <HGDIOBJ>::from_stack(mem, esp + 4u32);
+            let bytes = <u32>::from_stack(mem, esp + 8u32);
+            let out = <u32>::from_stack(mem, esp + 12u32);
+            winapi::gdi32::GetObjectA(machine, handle, bytes, out).to_raw()
+        }
+        pub unsafe fn GetPixel(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let x = <u32>::from_stack(mem, esp + 8u32);
+            let y = <u32>::from_stack(mem, esp + 12u32);
+            winapi::gdi32::GetPixel(machine, hdc, x, y).to_raw()
+        }
+        pub unsafe fn GetPixelFormat(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            winapi::gdi32::GetPixelFormat(machine, hdc).to_raw()
+        }
+        pub unsafe fn GetStockObject(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let i = <Result<GetStockObjectArg, u32>>::from_stack(mem, esp + 4u32);
+            winapi::gdi32::GetStockObject(machine, i).to_raw()
+        }
+        pub unsafe fn GetTextColor(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            winapi::gdi32::GetTextColor(machine, hdc).to_raw()
+        }
+        pub unsafe fn GetTextExtentPoint32A(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let lpString = <Option<&str>>::from_stack(mem, esp + 8u32);
+            let c = <i32>::from_stack(mem, esp + 12u32);
             let psizl = <Option<&mut SIZE>>::from_stack(mem, esp + 16u32);
             winapi::gdi32::GetTextExtentPoint32A(machine, hdc, lpString, c, psizl).to_raw()
         }
@@ -519,6 +1986,51 @@ pub mod gdi32 {
             let lppt = <Option<&mut POINT>>::from_stack(mem, esp + 16u32);
             winapi::gdi32::MoveToEx(machine, hdc, x, y, lppt).to_raw()
         }
+        pub unsafe fn PlayEnhMetaFile(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let hmf = <HENHMETAFILE>::from_stack(mem, esp + 8u32);
+            let lprect = <Option<&RECT>>::from_stack(mem, esp + 12u32);
+            winapi::gdi32::PlayEnhMetaFile(machine, hdc, hmf, lprect).to_raw()
+        }
+        pub unsafe fn PlayMetaFile(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let hmf = <HMETAFILE>::from_stack(mem, esp + 8u32);
+            winapi::gdi32::PlayMetaFile(machine, hdc, hmf).to_raw()
+        }
+        pub unsafe fn Polygon(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let apt = <ArrayWithSize<POINT>>::from_stack(mem, esp + 8u32);
+            winapi::gdi32::Polygon(machine, hdc, apt).to_raw()
+        }
+        pub unsafe fn Polyline(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let apt = <ArrayWithSize<POINT>>::from_stack(mem, esp + 8u32);
+            winapi::gdi32::Polyline(machine, hdc, apt).to_raw()
+        }
+        pub unsafe fn Rectangle(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let left = <i32>::from_stack(mem, esp + 8u32);
+            let top = <i32>::from_stack(mem, esp + 12u32);
+            let right = <i32>::from_stack(mem, esp + 16u32);
+            let bottom = <i32>::from_stack(mem, esp + 20u32);
+            winapi::gdi32::Rectangle(machine, hdc, left, top, right, bottom).to_raw()
+        }
+        pub unsafe fn RestoreDC(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let nSavedDC = <i32>::from_stack(mem, esp + 8u32);
+            winapi::gdi32::RestoreDC(machine, hdc, nSavedDC).to_raw()
+        }
+        pub unsafe fn SaveDC(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            winapi::gdi32::SaveDC(machine, hdc).to_raw()
+        }
         pub unsafe fn SelectObject(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hdc = <HDC>::from_stack(mem, esp + 4u32);
@@ -534,9 +2046,29 @@ pub mod gdi32 {
         pub unsafe fn SetBkMode(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hdc = <HDC>::from_stack(mem, esp + 4u32);
-            let mode = <i32>::from_stack(mem, esp + 8u32);
+            let mode = <Result<BkMode, u32>>::from_stack(mem, esp + 8u32);
             winapi::gdi32::SetBkMode(machine, hdc, mode).to_raw()
         }
+        pub unsafe fn SetBrushOrgEx(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let x = <i32>::from_stack(mem, esp + 8u32);
+            let y = <i32>::from_stack(mem, esp + 12u32);
+            let lppt = <Option<&mut POINT>>::from_stack(mem, esp + 16u32);
+            winapi::gdi32::SetBrushOrgEx(machine, hdc, x, y, lppt).to_raw()
+        }
+        pub unsafe fn SetDIBits(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let hbm = <HGDIOBJ>::from_stack(mem, esp + 8u32);
+            let start = <u32>::from_stack(mem, esp + 12u32);
+            let cLines = <u32>::from_stack(mem, esp + 16u32);
+            let lpvBits = <u32>::from_stack(mem, esp + 20u32);
+            let lpbmi = <Option<&BITMAPINFOHEADER>>::from_stack(mem, esp + 24u32);
+            let usage = <u32>::from_stack(mem, esp + 28u32);
+            winapi::gdi32::SetDIBits(machine, hdc, hbm, start, cLines, lpvBits, lpbmi, usage)
+                .to_raw()
+        }
         pub unsafe fn SetDIBitsToDevice(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hdc = <HDC>::from_stack(mem, esp + 4u32);
@@ -557,6 +2089,12 @@ pub mod gdi32 {
             )
             .to_raw()
         }
+        pub unsafe fn SetMapMode(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let iMode = <Result<MM, u32>>::from_stack(mem, esp + 8u32);
+            winapi::gdi32::SetMapMode(machine, hdc, iMode).to_raw()
+        }
         pub unsafe fn SetPixel(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hdc = <HDC>::from_stack(mem, esp + 4u32);
@@ -565,6 +2103,13 @@ pub mod gdi32 {
             let color = <u32>::from_stack(mem, esp + 16u32);
             winapi::gdi32::SetPixel(machine, hdc, x, y, color).to_raw()
         }
+        pub unsafe fn SetPixelFormat(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let format = <i32>::from_stack(mem, esp + 8u32);
+            let ppfd = <Option<&PIXELFORMATDESCRIPTOR>>::from_stack(mem, esp + 12u32);
+            winapi::gdi32::SetPixelFormat(machine, hdc, format, ppfd).to_raw()
+        }
         pub unsafe fn SetROP2(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hdc = <HDC>::from_stack(mem, esp + 4u32);
@@ -577,6 +2122,38 @@ pub mod gdi32 {
             let color = <u32>::from_stack(mem, esp + 8u32);
             winapi::gdi32::SetTextColor(machine, hdc, color).to_raw()
         }
+        pub unsafe fn SetViewportExtEx(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let x = <i32>::from_stack(mem, esp + 8u32);
+            let y = <i32>::from_stack(mem, esp + 12u32);
+            let lpsz = <Option<&mut SIZE>>::from_stack(mem, esp + 16u32);
+            winapi::gdi32::SetViewportExtEx(machine, hdc, x, y, lpsz).to_raw()
+        }
+        pub unsafe fn SetViewportOrgEx(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let x = <i32>::from_stack(mem, esp + 8u32);
+            let y = <i32>::from_stack(mem, esp + 12u32);
+            let lppt = <Option<&mut POINT>>::from_stack(mem, esp + 16u32);
+            winapi::gdi32::SetViewportOrgEx(machine, hdc, x, y, lppt).to_raw()
+        }
+        pub unsafe fn SetWindowExtEx(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let x = <i32>::from_stack(mem, esp + 8u32);
+            let y = <i32>::from_stack(mem, esp + 12u32);
+            let lpsz = <Option<&mut SIZE>>::from_stack(mem, esp + 16u32);
+            winapi::gdi32::SetWindowExtEx(machine, hdc, x, y, lpsz).to_raw()
+        }
+        pub unsafe fn SetWindowOrgEx(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let x = <i32>::from_stack(mem, esp + 8u32);
+            let y = <i32>::from_stack(mem, esp + 12u32);
+            let lppt = <Option<&mut POINT>>::from_stack(mem, esp + 16u32);
+            winapi::gdi32::SetWindowOrgEx(machine, hdc, x, y, lppt).to_raw()
+        }
         pub unsafe fn StretchBlt(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hdcDest = <HDC>::from_stack(mem, esp + 4u32);
@@ -616,6 +2193,11 @@ pub mod gdi32 {
             )
             .to_raw()
         }
+        pub unsafe fn SwapBuffers(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            winapi::gdi32::SwapBuffers(machine, hdc).to_raw()
+        }
         pub unsafe fn TextOutA(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hdc = <HDC>::from_stack(mem, esp + 4u32);
@@ -634,6 +2216,12 @@ pub mod gdi32 {
             stack_consumed: 36u32,
             is_async: false,
         };
+        pub const ChoosePixelFormat: Shim = Shim {
+            name: "ChoosePixelFormat",
+            func: impls::ChoosePixelFormat,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
         pub const CreateBitmap: Shim = Shim {
             name: "CreateBitmap",
             func: impls::CreateBitmap,
@@ -676,24 +2264,90 @@ pub mod gdi32 {
             stack_consumed: 4u32,
             is_async: false,
         };
+        pub const DeleteEnhMetaFile: Shim = Shim {
+            name: "DeleteEnhMetaFile",
+            func: impls::DeleteEnhMetaFile,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const DeleteMetaFile: Shim = Shim {
+            name: "DeleteMetaFile",
+            func: impls::DeleteMetaFile,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
         pub const DeleteObject: Shim = Shim {
             name: "DeleteObject",
             func: impls::DeleteObject,
             stack_consumed: 4u32,
             is_async: false,
         };
+        pub const DescribePixelFormat: Shim = Shim {
+            name: "DescribePixelFormat",
+            func: impls::DescribePixelFormat,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const Ellipse: Shim = Shim {
+            name: "Ellipse",
+            func: impls::Ellipse,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const ExtTextOutA: Shim = Shim {
+            name: "ExtTextOutA",
+            func: impls::ExtTextOutA,
+            stack_consumed: 32u32,
+            is_async: false,
+        };
+        pub const GetBkColor: Shim = Shim {
+            name: "GetBkColor",
+            func: impls::GetBkColor,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetBkMode: Shim = Shim {
+            name: "GetBkMode",
+            func: impls::GetBkMode,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetDIBits: Shim = Shim {
+            name: "GetDIBits",
+            func: impls::GetDIBits,
+            stack_consumed: 28u32,
+            is_async: false,
+        };
         pub const GetDeviceCaps: Shim = Shim {
             name: "GetDeviceCaps",
             func: impls::GetDeviceCaps,
             stack_consumed: 8u32,
             is_async: false,
         };
+        pub const GetEnhMetaFileA: Shim = Shim {
+            name: "GetEnhMetaFileA",
+            func: impls::GetEnhMetaFileA,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
         pub const GetLayout: Shim = Shim {
             name: "GetLayout",
             func: impls::GetLayout,
             stack_consumed: 4u32,
             is_async: false,
         };
+        pub const GetMapMode: Shim = Shim {
+            name: "GetMapMode",
+            func: impls::GetMapMode,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetMetaFileA: Shim = Shim {
+            name: "GetMetaFileA",
+            func: impls::GetMetaFileA,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
         pub const GetObjectA: Shim = Shim {
             name: "GetObjectA",
             func: impls::GetObjectA,
@@ -706,12 +2360,24 @@ pub mod gdi32 {
             stack_consumed: 12u32,
             is_async: false,
         };
+        pub const GetPixelFormat: Shim = Shim {
+            name: "GetPixelFormat",
+            func: impls::GetPixelFormat,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
         pub const GetStockObject: Shim = Shim {
             name: "GetStockObject",
             func: impls::GetStockObject,
             stack_consumed: 4u32,
             is_async: false,
         };
+        pub const GetTextColor: Shim = Shim {
+            name: "GetTextColor",
+            func: impls::GetTextColor,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
         pub const GetTextExtentPoint32A: Shim = Shim {
             name: "GetTextExtentPoint32A",
             func: impls::GetTextExtentPoint32A,
@@ -736,6 +2402,48 @@ pub mod gdi32 {
             stack_consumed: 16u32,
             is_async: false,
         };
+        pub const PlayEnhMetaFile: Shim = Shim {
+            name: "PlayEnhMetaFile",
+            func: impls::PlayEnhMetaFile,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const PlayMetaFile: Shim = Shim {
+            name: "PlayMetaFile",
+            func: impls::PlayMetaFile,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const Polygon: Shim = Shim {
+            name: "Polygon",
+            func: impls::Polygon,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const Polyline: Shim = Shim {
+            name: "Polyline",
+            func: impls::Polyline,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const Rectangle: Shim = Shim {
+            name: "Rectangle",
+            func: impls::Rectangle,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const RestoreDC: Shim = Shim {
+            name: "RestoreDC",
+            func: impls::RestoreDC,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const SaveDC: Shim = Shim {
+            name: "SaveDC",
+            func: impls::SaveDC,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
         pub const SelectObject: Shim = Shim {
             name: "SelectObject",
             func: impls::SelectObject,
@@ -754,18 +2462,42 @@ pub mod gdi32 {
             stack_consumed: 8u32,
             is_async: false,
         };
+        pub const SetBrushOrgEx: Shim = Shim {
+            name: "SetBrushOrgEx",
+            func: impls::SetBrushOrgEx,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const SetDIBits: Shim = Shim {
+            name: "SetDIBits",
+            func: impls::SetDIBits,
+            stack_consumed: 28u32,
+            is_async: false,
+        };
         pub const SetDIBitsToDevice: Shim = Shim {
             name: "SetDIBitsToDevice",
             func: impls::SetDIBitsToDevice,
             stack_consumed: 48u32,
             is_async: false,
         };
+        pub const SetMapMode: Shim = Shim {
+            name: "SetMapMode",
+            func: impls::SetMapMode,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
         pub const SetPixel: Shim = Shim {
             name: "SetPixel",
             func: impls::SetPixel,
             stack_consumed: 16u32,
             is_async: false,
         };
+        pub const SetPixelFormat: Shim = Shim {
+            name: "SetPixelFormat",
+            func: impls::SetPixelFormat,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
         pub const SetROP2: Shim = Shim {
             name: "SetROP2",
             func: impls::SetROP2,
@@ -778,6 +2510,30 @@ pub mod gdi32 {
             stack_consumed: 8u32,
             is_async: false,
         };
+        pub const SetViewportExtEx: Shim = Shim {
+            name: "SetViewportExtEx",
+            func: impls::SetViewportExtEx,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const SetViewportOrgEx: Shim = Shim {
+            name: "SetViewportOrgEx",
+            func: impls::SetViewportOrgEx,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const SetWindowExtEx: Shim = Shim {
+            name: "SetWindowExtEx",
+            func: impls::SetWindowExtEx,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const SetWindowOrgEx: Shim = Shim {
+            name: "SetWindowOrgEx",
+            func: impls::SetWindowOrgEx,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
         pub const StretchBlt: Shim = Shim {
             name: "StretchBlt",
             func: impls::StretchBlt,
@@ -790,6 +2546,12 @@ pub mod gdi32 {
             stack_consumed: 52u32,
             is_async: false,
         };
+        pub const SwapBuffers: Shim = Shim {
+            name: "SwapBuffers",
+            func: impls::SwapBuffers,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
         pub const TextOutA: Shim = Shim {
             name: "TextOutA",
             func: impls::TextOutA,
@@ -797,11 +2559,15 @@ pub mod gdi32 {
             is_async: false,
         };
     }
-    const EXPORTS: [Symbol; 28usize] = [
+    const EXPORTS: [Symbol; 58usize] = [
         Symbol {
             ordinal: None,
             shim: shims::BitBlt,
         },
+        Symbol {
+            ordinal: None,
+            shim: shims::ChoosePixelFormat,
+        },
         Symbol {
             ordinal: None,
             shim: shims::CreateBitmap,
@@ -830,18 +2596,62 @@ pub mod gdi32 {
             ordinal: None,
             shim: shims::DeleteDC,
         },
+        Symbol {
+            ordinal: None,
+            shim: shims::DeleteEnhMetaFile,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::DeleteMetaFile,
+        },
         Symbol {
             ordinal: None,
             shim: shims::DeleteObject,
         },
+        Symbol {
+            ordinal: None,
+            shim: shims::DescribePixelFormat,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::Ellipse,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ExtTextOutA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetBkColor,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetBkMode,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetDIBits,
+        },
         Symbol {
             ordinal: None,
             shim: shims::GetDeviceCaps,
         },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetEnhMetaFileA,
+        },
         Symbol {
             ordinal: None,
             shim: shims::GetLayout,
         },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetMapMode,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetMetaFileA,
+        },
         Symbol {
             ordinal: None,
             shim: shims::GetObjectA,
@@ -850,10 +2660,18 @@ pub mod gdi32 {
             ordinal: None,
             shim: shims::GetPixel,
         },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetPixelFormat,
+        },
         Symbol {
             ordinal: None,
             shim: shims::GetStockObject,
         },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetTextColor,
+        },
         Symbol {
             ordinal: None,
             shim: shims::GetTextExtentPoint32A,
@@ -872,747 +2690,5091 @@ pub mod gdi32 {
         },
         Symbol {
             ordinal: None,
-            shim: shims::SelectObject,
+            shim: shims::PlayEnhMetaFile,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetBkColor,
+            shim: shims::PlayMetaFile,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetBkMode,
+            shim: shims::Polygon,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetDIBitsToDevice,
+            shim: shims::Polyline,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetPixel,
+            shim: shims::Rectangle,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetROP2,
+            shim: shims::RestoreDC,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetTextColor,
+            shim: shims::SaveDC,
         },
         Symbol {
             ordinal: None,
-            shim: shims::StretchBlt,
+            shim: shims::SelectObject,
         },
         Symbol {
             ordinal: None,
-            shim: shims::StretchDIBits,
+            shim: shims::SetBkColor,
         },
         Symbol {
             ordinal: None,
-            shim: shims::TextOutA,
+            shim: shims::SetBkMode,
         },
-    ];
-    pub const DLL: BuiltinDLL = BuiltinDLL {
-        file_name: "gdi32.dll",
-        exports: &EXPORTS,
-    };
-}
-pub mod kernel32 {
-    use super::*;
-    mod impls {
-        use crate::{
+        Symbol {
+            ordinal: None,
+            shim: shims::SetBrushOrgEx,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetDIBits,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetDIBitsToDevice,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetMapMode,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetPixel,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetPixelFormat,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetROP2,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetTextColor,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetViewportExtEx,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetViewportOrgEx,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetWindowExtEx,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetWindowOrgEx,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::StretchBlt,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::StretchDIBits,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SwapBuffers,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::TextOutA,
+        },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "gdi32.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod glide2x {
+    use super::*;
+    mod impls {
+        use crate::{
             machine::Machine,
             winapi::{self, stack_args::*, types::*},
         };
         use memory::Extensions;
-        use winapi::kernel32::*;
-        pub unsafe fn AcquireSRWLockExclusive(machine: &mut Machine, esp: u32) -> u32 {
+        use winapi::glide2x::*;
+        pub unsafe fn grAlphaCombine(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let function = <u32>::from_stack(mem, esp + 4u32);
+            let factor = <u32>::from_stack(mem, esp + 8u32);
+            let local = <u32>::from_stack(mem, esp + 12u32);
+            let other = <u32>::from_stack(mem, esp + 16u32);
+            let invert = <bool>::from_stack(mem, esp + 20u32);
+            winapi::glide2x::grAlphaCombine(machine, function, factor, local, other, invert)
+                .to_raw()
+        }
+        pub unsafe fn grBufferClear(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let SRWLock = <Option<&mut SRWLOCK>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::AcquireSRWLockExclusive(machine, SRWLock).to_raw()
+            let color = <u32>::from_stack(mem, esp + 4u32);
+            let alpha = <u32>::from_stack(mem, esp + 8u32);
+            let depth = <u32>::from_stack(mem, esp + 12u32);
+            winapi::glide2x::grBufferClear(machine, color, alpha, depth).to_raw()
         }
-        pub unsafe fn AcquireSRWLockShared(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grBufferNumPending(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let SRWLock = <Option<&mut SRWLOCK>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::AcquireSRWLockShared(machine, SRWLock).to_raw()
+            winapi::glide2x::grBufferNumPending(machine).to_raw()
         }
-        pub unsafe fn AddVectoredExceptionHandler(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grBufferSwap(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let first = <u32>::from_stack(mem, esp + 4u32);
-            let handler = <u32>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::AddVectoredExceptionHandler(machine, first, handler).to_raw()
+            let swap_interval = <i32>::from_stack(mem, esp + 4u32);
+            winapi::glide2x::grBufferSwap(machine, swap_interval).to_raw()
         }
-        pub unsafe fn CloseHandle(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grClipWindow(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hObject = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::CloseHandle(machine, hObject).to_raw()
+            let minx = <u32>::from_stack(mem, esp + 4u32);
+            let miny = <u32>::from_stack(mem, esp + 8u32);
+            let maxx = <u32>::from_stack(mem, esp + 12u32);
+            let maxy = <u32>::from_stack(mem, esp + 16u32);
+            winapi::glide2x::grClipWindow(machine, minx, miny, maxx, maxy).to_raw()
         }
-        pub unsafe fn CreateEventA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grColorCombine(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpEventAttributes = <u32>::from_stack(mem, esp + 4u32);
-            let bManualReset = <bool>::from_stack(mem, esp + 8u32);
-            let bInitialState = <bool>::from_stack(mem, esp + 12u32);
-            let lpName = <Option<&str>>::from_stack(mem, esp + 16u32);
-            winapi::kernel32::CreateEventA(
-                machine,
-                lpEventAttributes,
-                bManualReset,
-                bInitialState,
-                lpName,
-            )
-            .to_raw()
+            let function = <u32>::from_stack(mem, esp + 4u32);
+            let factor = <u32>::from_stack(mem, esp + 8u32);
+            let local = <u32>::from_stack(mem, esp + 12u32);
+            let other = <u32>::from_stack(mem, esp + 16u32);
+            let invert = <bool>::from_stack(mem, esp + 20u32);
+            winapi::glide2x::grColorCombine(machine, function, factor, local, other, invert)
+                .to_raw()
         }
-        pub unsafe fn CreateFileA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grConstantColorValue(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpFileName = <Option<&str>>::from_stack(mem, esp + 4u32);
-            let dwDesiredAccess = <u32>::from_stack(mem, esp + 8u32);
-            let dwShareMode = <u32>::from_stack(mem, esp + 12u32);
-            let lpSecurityAttributes = <u32>::from_stack(mem, esp + 16u32);
-            let dwCreationDisposition =
-                <Result<CreationDisposition, u32>>::from_stack(mem, esp + 20u32);
-            let dwFlagsAndAttributes = <Result<FileAttribute, u32>>::from_stack(mem, esp + 24u32);
-            let hTemplateFile = <HFILE>::from_stack(mem, esp + 28u32);
-            winapi::kernel32::CreateFileA(
-                machine,
-                lpFileName,
-                dwDesiredAccess,
-                dwShareMode,
-                lpSecurityAttributes,
-                dwCreationDisposition,
-                dwFlagsAndAttributes,
-                hTemplateFile,
-            )
-            .to_raw()
+            let value = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide2x::grConstantColorValue(machine, value).to_raw()
         }
-        pub unsafe fn CreateFileW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grCullMode(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpFileName = <Option<&Str16>>::from_stack(mem, esp + 4u32);
-            let dwDesiredAccess = <u32>::from_stack(mem, esp + 8u32);
-            let dwShareMode = <u32>::from_stack(mem, esp + 12u32);
-            let lpSecurityAttributes = <u32>::from_stack(mem, esp + 16u32);
-            let dwCreationDisposition =
-                <Result<CreationDisposition, u32>>::from_stack(mem, esp + 20u32);
-            let dwFlagsAndAttributes = <Result<FileAttribute, u32>>::from_stack(mem, esp + 24u32);
-            let hTemplateFile = <HFILE>::from_stack(mem, esp + 28u32);
-            winapi::kernel32::CreateFileW(
-                machine,
-                lpFileName,
-                dwDesiredAccess,
-                dwShareMode,
-                lpSecurityAttributes,
-                dwCreationDisposition,
-                dwFlagsAndAttributes,
-                hTemplateFile,
-            )
-            .to_raw()
+            let mode = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide2x::grCullMode(machine, mode).to_raw()
         }
-        pub unsafe fn CreateThread(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grDepthBufferFunction(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpThreadAttributes = <u32>::from_stack(mem, esp + 4u32);
-            let dwStackSize = <u32>::from_stack(mem, esp + 8u32);
-            let lpStartAddress = <u32>::from_stack(mem, esp + 12u32);
-            let lpParameter = <u32>::from_stack(mem, esp + 16u32);
-            let dwCreationFlags = <u32>::from_stack(mem, esp + 20u32);
-            let lpThreadId = <u32>::from_stack(mem, esp + 24u32);
-            #[cfg(feature = "x86-emu")]
-            {
-                let m: *mut Machine = machine;
-                let result = async move {
-                    use memory::Extensions;
-                    let machine = unsafe { &mut *m };
-                    let result = winapi::kernel32::CreateThread(
-                        machine,
-                        lpThreadAttributes,
-                        dwStackSize,
-                        lpStartAddress,
-                        lpParameter,
-                        dwCreationFlags,
-                        lpThreadId,
-                    )
-                    .await;
-                    let regs = &mut machine.emu.x86.cpu_mut().regs;
-                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
-                    *regs.get32_mut(x86::Register::ESP) += 24u32 + 4;
-                    regs.set32(x86::Register::EAX, result.to_raw());
-                };
-                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
-                0
-            }
-            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
-            {
-                let pin = std::pin::pin!(winapi::kernel32::CreateThread(
-                    machine,
-                    lpThreadAttributes,
-                    dwStackSize,
-                    lpStartAddress,
-                    lpParameter,
-                    dwCreationFlags,
-                    lpThreadId
-                ));
-                crate::shims::call_sync(pin).to_raw()
-            }
+            let function = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide2x::grDepthBufferFunction(machine, function).to_raw()
         }
-        pub unsafe fn DeleteCriticalSection(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grDepthBufferMode(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpCriticalSection = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::DeleteCriticalSection(machine, lpCriticalSection).to_raw()
+            let mode = <Result<GrDepthBufferMode, u32>>::from_stack(mem, esp + 4u32);
+            winapi::glide2x::grDepthBufferMode(machine, mode).to_raw()
         }
-        pub unsafe fn EnterCriticalSection(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grDepthMask(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpCriticalSection = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::EnterCriticalSection(machine, lpCriticalSection).to_raw()
+            let enable = <bool>::from_stack(mem, esp + 4u32);
+            winapi::glide2x::grDepthMask(machine, enable).to_raw()
         }
-        pub unsafe fn ExitProcess(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grDrawTriangle(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let uExitCode = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::ExitProcess(machine, uExitCode).to_raw()
+            let a = <Option<&GrVertex>>::from_stack(mem, esp + 4u32);
+            let b = <Option<&GrVertex>>::from_stack(mem, esp + 8u32);
+            let c = <Option<&GrVertex>>::from_stack(mem, esp + 12u32);
+            winapi::glide2x::grDrawTriangle(machine, a, b, c).to_raw()
         }
-        pub unsafe fn FindResourceA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grGlideGetVersion(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hModule = <u32>::from_stack(mem, esp + 4u32);
-            let lpName = <ResourceKey<&str>>::from_stack(mem, esp + 8u32);
-            let lpType = <ResourceKey<&str>>::from_stack(mem, esp + 12u32);
-            winapi::kernel32::FindResourceA(machine, hModule, lpName, lpType).to_raw()
+            let version = <Option<&mut [u8; 80]>>::from_stack(mem, esp + 4u32);
+            winapi::glide2x::grGlideGetVersion(machine, version).to_raw()
         }
-        pub unsafe fn FindResourceW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grGlideInit(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hModule = <u32>::from_stack(mem, esp + 4u32);
-            let lpName = <ResourceKey<&Str16>>::from_stack(mem, esp + 8u32);
-            let lpType = <ResourceKey<&Str16>>::from_stack(mem, esp + 12u32);
-            winapi::kernel32::FindResourceW(machine, hModule, lpName, lpType).to_raw()
+            winapi::glide2x::grGlideInit(machine).to_raw()
         }
-        pub unsafe fn FormatMessageW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grGlideShutdown(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let dwFlags = <Result<FormatMessageFlags, u32>>::from_stack(mem, esp + 4u32);
-            let lpSource = <u32>::from_stack(mem, esp + 8u32);
-            let dwMessageId = <u32>::from_stack(mem, esp + 12u32);
-            let dwLanguageId = <u32>::from_stack(mem, esp + 16u32);
-            let lpBuffer = <u32>::from_stack(mem, esp + 20u32);
-            let nSize = <u32>::from_stack(mem, esp + 24u32);
-            let args = <u32>::from_stack(mem, esp + 28u32);
-            winapi::kernel32::FormatMessageW(
-                machine,
-                dwFlags,
-                lpSource,
-                dwMessageId,
-                dwLanguageId,
-                lpBuffer,
-                nSize,
-                args,
-            )
-            .to_raw()
+            winapi::glide2x::grGlideShutdown(machine).to_raw()
         }
-        pub unsafe fn FreeEnvironmentStringsA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grSstQueryBoards(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let _penv = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::FreeEnvironmentStringsA(machine, _penv).to_raw()
+            let hwConfig = <Option<&mut GrHwConfiguration>>::from_stack(mem, esp + 4u32);
+            winapi::glide2x::grSstQueryBoards(machine, hwConfig).to_raw()
         }
-        pub unsafe fn FreeLibrary(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grSstQueryHardware(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hLibModule = <HMODULE>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::FreeLibrary(machine, hLibModule).to_raw()
+            let hwConfig = <Option<&mut GrHwConfiguration>>::from_stack(mem, esp + 4u32);
+            winapi::glide2x::grSstQueryHardware(machine, hwConfig).to_raw()
         }
-        pub unsafe fn GetACP(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grSstSelect(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::GetACP(machine).to_raw()
+            let which_sst = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide2x::grSstSelect(machine, which_sst).to_raw()
         }
-        pub unsafe fn GetCPInfo(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grSstWinClose(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let _CodePage = <u32>::from_stack(mem, esp + 4u32);
-            let _lpCPInfo = <u32>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::GetCPInfo(machine, _CodePage, _lpCPInfo).to_raw()
+            winapi::glide2x::grSstWinClose(machine).to_raw()
         }
-        pub unsafe fn GetCommandLineA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grSstWinOpen(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::GetCommandLineA(machine).to_raw()
+            let hWnd = <u32>::from_stack(mem, esp + 4u32);
+            let res = <Result<GrScreenResolution, u32>>::from_stack(mem, esp + 8u32);
+            let refresh = <u32>::from_stack(mem, esp + 12u32);
+            let cFormat = <Result<GrColorFormat, u32>>::from_stack(mem, esp + 16u32);
+            let org_loc = <Result<GrOriginLocation, u32>>::from_stack(mem, esp + 20u32);
+            let num_buffers = <i32>::from_stack(mem, esp + 24u32);
+            let num_aux_buffers = <i32>::from_stack(mem, esp + 28u32);
+            winapi::glide2x::grSstWinOpen(
+                machine,
+                hWnd,
+                res,
+                refresh,
+                cFormat,
+                org_loc,
+                num_buffers,
+                num_aux_buffers,
+            )
+            .to_raw()
         }
-        pub unsafe fn GetCommandLineW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grTexClampMode(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::GetCommandLineW(machine).to_raw()
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            let s_clampmode = <u32>::from_stack(mem, esp + 8u32);
+            let t_clampmode = <u32>::from_stack(mem, esp + 12u32);
+            winapi::glide2x::grTexClampMode(machine, tmu, s_clampmode, t_clampmode).to_raw()
         }
-        pub unsafe fn GetConsoleMode(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grTexCombine(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hConsoleHandle = <HFILE>::from_stack(mem, esp + 4u32);
-            let lpMode = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::GetConsoleMode(machine, hConsoleHandle, lpMode).to_raw()
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            let rgb_function = <u32>::from_stack(mem, esp + 8u32);
+            let rgb_factor = <u32>::from_stack(mem, esp + 12u32);
+            let alpha_function = <u32>::from_stack(mem, esp + 16u32);
+            let alpha_factor = <u32>::from_stack(mem, esp + 20u32);
+            let rgb_invert = <bool>::from_stack(mem, esp + 24u32);
+            let alpha_invert = <bool>::from_stack(mem, esp + 28u32);
+            winapi::glide2x::grTexCombine(
+                machine,
+                tmu,
+                rgb_function,
+                rgb_factor,
+                alpha_function,
+                alpha_factor,
+                rgb_invert,
+                alpha_invert,
+            )
+            .to_raw()
         }
-        pub unsafe fn GetCurrentProcessId(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grTexDownloadMipMap(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            let startAddress = <u32>::from_stack(mem, esp + 8u32);
+            let evenOdd = <u32>::from_stack(mem, esp + 12u32);
+            let info = <Option<&GrTexInfo>>::from_stack(mem, esp + 16u32);
+            winapi::glide2x::grTexDownloadMipMap(machine, tmu, startAddress, evenOdd, info).to_raw()
+        }
+        pub unsafe fn grTexDownloadMipMapLevel(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            let startAddress = <u32>::from_stack(mem, esp + 8u32);
+            let thisLod = <u32>::from_stack(mem, esp + 12u32);
+            let largeLod = <u32>::from_stack(mem, esp + 16u32);
+            let aspectRatio = <u32>::from_stack(mem, esp + 20u32);
+            let format = <u32>::from_stack(mem, esp + 24u32);
+            let evenOdd = <u32>::from_stack(mem, esp + 28u32);
+            let data = <u32>::from_stack(mem, esp + 32u32);
+            winapi::glide2x::grTexDownloadMipMapLevel(
+                machine,
+                tmu,
+                startAddress,
+                thisLod,
+                largeLod,
+                aspectRatio,
+                format,
+                evenOdd,
+                data,
+            )
+            .to_raw()
+        }
+        pub unsafe fn grTexDownloadTable(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::GetCurrentProcessId(machine).to_raw()
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            let type_ = <u32>::from_stack(mem, esp + 8u32);
+            let data = <u32>::from_stack(mem, esp + 12u32);
+            winapi::glide2x::grTexDownloadTable(machine, tmu, type_, data).to_raw()
         }
-        pub unsafe fn GetCurrentThread(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grTexFilterMode(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::GetCurrentThread(machine).to_raw()
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            let minfilter_mode = <u32>::from_stack(mem, esp + 8u32);
+            let magfilter_mode = <u32>::from_stack(mem, esp + 12u32);
+            winapi::glide2x::grTexFilterMode(machine, tmu, minfilter_mode, magfilter_mode).to_raw()
         }
-        pub unsafe fn GetCurrentThreadId(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grTexMaxAddress(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::GetCurrentThreadId(machine).to_raw()
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide2x::grTexMaxAddress(machine, tmu).to_raw()
         }
-        pub unsafe fn GetEnvironmentStrings(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grTexMinAddress(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::GetEnvironmentStrings(machine).to_raw()
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide2x::grTexMinAddress(machine, tmu).to_raw()
         }
-        pub unsafe fn GetEnvironmentStringsW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grTexSource(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::GetEnvironmentStringsW(machine).to_raw()
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            let startAddress = <u32>::from_stack(mem, esp + 8u32);
+            let evenOdd = <u32>::from_stack(mem, esp + 12u32);
+            let info = <Option<&GrTexInfo>>::from_stack(mem, esp + 16u32);
+            winapi::glide2x::grTexSource(machine, tmu, startAddress, evenOdd, info).to_raw()
         }
-        pub unsafe fn GetEnvironmentVariableA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn grTexTextureMemRequired(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let name = <Option<&str>>::from_stack(mem, esp + 4u32);
+            let evenOdd = <u32>::from_stack(mem, esp + 4u32);
+            let info = <Option<&GrTexInfo>>::from_stack(mem, esp + 8u32);
+            winapi::glide2x::grTexTextureMemRequired(machine, evenOdd, info).to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const grAlphaCombine: Shim = Shim {
+            name: "grAlphaCombine",
+            func: impls::grAlphaCombine,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const grBufferClear: Shim = Shim {
+            name: "grBufferClear",
+            func: impls::grBufferClear,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const grBufferNumPending: Shim = Shim {
+            name: "grBufferNumPending",
+            func: impls::grBufferNumPending,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const grBufferSwap: Shim = Shim {
+            name: "grBufferSwap",
+            func: impls::grBufferSwap,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grClipWindow: Shim = Shim {
+            name: "grClipWindow",
+            func: impls::grClipWindow,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const grColorCombine: Shim = Shim {
+            name: "grColorCombine",
+            func: impls::grColorCombine,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const grConstantColorValue: Shim = Shim {
+            name: "grConstantColorValue",
+            func: impls::grConstantColorValue,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grCullMode: Shim = Shim {
+            name: "grCullMode",
+            func: impls::grCullMode,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grDepthBufferFunction: Shim = Shim {
+            name: "grDepthBufferFunction",
+            func: impls::grDepthBufferFunction,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grDepthBufferMode: Shim = Shim {
+            name: "grDepthBufferMode",
+            func: impls::grDepthBufferMode,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grDepthMask: Shim = Shim {
+            name: "grDepthMask",
+            func: impls::grDepthMask,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grDrawTriangle: Shim = Shim {
+            name: "grDrawTriangle",
+            func: impls::grDrawTriangle,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const grGlideGetVersion: Shim = Shim {
+            name: "grGlideGetVersion",
+            func: impls::grGlideGetVersion,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grGlideInit: Shim = Shim {
+            name: "grGlideInit",
+            func: impls::grGlideInit,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const grGlideShutdown: Shim = Shim {
+            name: "grGlideShutdown",
+            func: impls::grGlideShutdown,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const grSstQueryBoards: Shim = Shim {
+            name: "grSstQueryBoards",
+            func: impls::grSstQueryBoards,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grSstQueryHardware: Shim = Shim {
+            name: "grSstQueryHardware",
+            func: impls::grSstQueryHardware,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grSstSelect: Shim = Shim {
+            name: "grSstSelect",
+            func: impls::grSstSelect,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grSstWinClose: Shim = Shim {
+            name: "grSstWinClose",
+            func: impls::grSstWinClose,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const grSstWinOpen: Shim = Shim {
+            name: "grSstWinOpen",
+            func: impls::grSstWinOpen,
+            stack_consumed: 28u32,
+            is_async: false,
+        };
+        pub const grTexClampMode: Shim = Shim {
+            name: "grTexClampMode",
+            func: impls::grTexClampMode,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const grTexCombine: Shim = Shim {
+            name: "grTexCombine",
+            func: impls::grTexCombine,
+            stack_consumed: 28u32,
+            is_async: false,
+        };
+        pub const grTexDownloadMipMap: Shim = Shim {
+            name: "grTexDownloadMipMap",
+            func: impls::grTexDownloadMipMap,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const grTexDownloadMipMapLevel: Shim = Shim {
+            name: "grTexDownloadMipMapLevel",
+            func: impls::grTexDownloadMipMapLevel,
+            stack_consumed: 32u32,
+            is_async: false,
+        };
+        pub const grTexDownloadTable: Shim = Shim {
+            name: "grTexDownloadTable",
+            func: impls::grTexDownloadTable,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const grTexFilterMode: Shim = Shim {
+            name: "grTexFilterMode",
+            func: impls::grTexFilterMode,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const grTexMaxAddress: Shim = Shim {
+            name: "grTexMaxAddress",
+            func: impls::grTexMaxAddress,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grTexMinAddress: Shim = Shim {
+            name: "grTexMinAddress",
+            func: impls::grTexMinAddress,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grTexSource: Shim = Shim {
+            name: "grTexSource",
+            func: impls::grTexSource,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const grTexTextureMemRequired: Shim = Shim {
+            name: "grTexTextureMemRequired",
+            func: impls::grTexTextureMemRequired,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 30usize] = [
+        Symbol {
+            ordinal: None,
+            shim: shims::grAlphaCombine,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grBufferClear,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grBufferNumPending,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grBufferSwap,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grClipWindow,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grColorCombine,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grConstantColorValue,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grCullMode,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grDepthBufferFunction,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grDepthBufferMode,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grDepthMask,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grDrawTriangle,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grGlideGetVersion,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grGlideInit,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grGlideShutdown,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grSstQueryBoards,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grSstQueryHardware,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grSstSelect,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grSstWinClose,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grSstWinOpen,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexClampMode,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexCombine,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexDownloadMipMap,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexDownloadMipMapLevel,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexDownloadTable,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexFilterMode,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexMaxAddress,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexMinAddress,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexSource,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexTextureMemRequired,
+        },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "glide2x.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod glide3x {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::glide3x::*;
+        pub unsafe fn grAlphaCombine(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let function = <u32>::from_stack(mem, esp + 4u32);
+            let factor = <u32>::from_stack(mem, esp + 8u32);
+            let local = <u32>::from_stack(mem, esp + 12u32);
+            let other = <u32>::from_stack(mem, esp + 16u32);
+            let invert = <bool>::from_stack(mem, esp + 20u32);
+            winapi::glide3x::grAlphaCombine(machine, function, factor, local, other, invert)
+                .to_raw()
+        }
+        pub unsafe fn grBufferClear(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let color = <u32>::from_stack(mem, esp + 4u32);
+            let alpha = <u32>::from_stack(mem, esp + 8u32);
+            let depth = <u32>::from_stack(mem, esp + 12u32);
+            winapi::glide3x::grBufferClear(machine, color, alpha, depth).to_raw()
+        }
+        pub unsafe fn grBufferSwap(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let swap_interval = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide3x::grBufferSwap(machine, swap_interval).to_raw()
+        }
+        pub unsafe fn grClipWindow(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let minx = <u32>::from_stack(mem, esp + 4u32);
+            let miny = <u32>::from_stack(mem, esp + 8u32);
+            let maxx = <u32>::from_stack(mem, esp + 12u32);
+            let maxy = <u32>::from_stack(mem, esp + 16u32);
+            winapi::glide3x::grClipWindow(machine, minx, miny, maxx, maxy).to_raw()
+        }
+        pub unsafe fn grColorCombine(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let function = <u32>::from_stack(mem, esp + 4u32);
+            let factor = <u32>::from_stack(mem, esp + 8u32);
+            let local = <u32>::from_stack(mem, esp + 12u32);
+            let other = <u32>::from_stack(mem, esp + 16u32);
+            let invert = <bool>::from_stack(mem, esp + 20u32);
+            winapi::glide3x::grColorCombine(machine, function, factor, local, other, invert)
+                .to_raw()
+        }
+        pub unsafe fn grConstantColorValue(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let value = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide3x::grConstantColorValue(machine, value).to_raw()
+        }
+        pub unsafe fn grCullMode(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let mode = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide3x::grCullMode(machine, mode).to_raw()
+        }
+        pub unsafe fn grDepthBufferFunction(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let function = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide3x::grDepthBufferFunction(machine, function).to_raw()
+        }
+        pub unsafe fn grDepthBufferMode(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let mode = <Result<GrDepthBufferMode, u32>>::from_stack(mem, esp + 4u32);
+            winapi::glide3x::grDepthBufferMode(machine, mode).to_raw()
+        }
+        pub unsafe fn grDepthMask(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let mask = <bool>::from_stack(mem, esp + 4u32);
+            winapi::glide3x::grDepthMask(machine, mask).to_raw()
+        }
+        pub unsafe fn grDrawTriangle(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let a = <u32>::from_stack(mem, esp + 4u32);
+            let b = <u32>::from_stack(mem, esp + 8u32);
+            let c = <u32>::from_stack(mem, esp + 12u32);
+            winapi::glide3x::grDrawTriangle(machine, a, b, c).to_raw()
+        }
+        pub unsafe fn grDrawVertexArray(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let mode = <Result<GrDrawMode, u32>>::from_stack(mem, esp + 4u32);
+            let Count = <u32>::from_stack(mem, esp + 8u32);
+            let pointers = <u32>::from_stack(mem, esp + 12u32);
+            winapi::glide3x::grDrawVertexArray(machine, mode, Count, pointers).to_raw()
+        }
+        pub unsafe fn grDrawVertexArrayContiguous(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let mode = <Result<GrDrawMode, u32>>::from_stack(mem, esp + 4u32);
+            let Count = <u32>::from_stack(mem, esp + 8u32);
+            let pointers = <u32>::from_stack(mem, esp + 12u32);
+            let stride = <u32>::from_stack(mem, esp + 16u32);
+            winapi::glide3x::grDrawVertexArrayContiguous(machine, mode, Count, pointers, stride)
+                .to_raw()
+        }
+        pub unsafe fn grGet(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pname = <Result<GrGetName, u32>>::from_stack(mem, esp + 4u32);
+            let plength = <u32>::from_stack(mem, esp + 8u32);
+            let params = <u32>::from_stack(mem, esp + 12u32);
+            winapi::glide3x::grGet(machine, pname, plength, params).to_raw()
+        }
+        pub unsafe fn grGetString(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pname = <Result<GrGetStringName, u32>>::from_stack(mem, esp + 4u32);
+            winapi::glide3x::grGetString(machine, pname).to_raw()
+        }
+        pub unsafe fn grGlideInit(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::glide3x::grGlideInit(machine).to_raw()
+        }
+        pub unsafe fn grGlideShutdown(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::glide3x::grGlideShutdown(machine).to_raw()
+        }
+        pub unsafe fn grSelectContext(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let context = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide3x::grSelectContext(machine, context).to_raw()
+        }
+        pub unsafe fn grSstSelect(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let which_sst = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide3x::grSstSelect(machine, which_sst).to_raw()
+        }
+        pub unsafe fn grSstWinClose(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let context = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide3x::grSstWinClose(machine, context).to_raw()
+        }
+        pub unsafe fn grSstWinOpen(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hWnd = <u32>::from_stack(mem, esp + 4u32);
+            let res = <Result<GrScreenResolution, u32>>::from_stack(mem, esp + 8u32);
+            let refresh = <u32>::from_stack(mem, esp + 12u32);
+            let cFormat = <Result<GrColorFormat, u32>>::from_stack(mem, esp + 16u32);
+            let org_loc = <Result<GrOriginLocation, u32>>::from_stack(mem, esp + 20u32);
+            let nColBuffers = <i32>::from_stack(mem, esp + 24u32);
+            let nAuxBuffers = <i32>::from_stack(mem, esp + 28u32);
+            winapi::glide3x::grSstWinOpen(
+                machine,
+                hWnd,
+                res,
+                refresh,
+                cFormat,
+                org_loc,
+                nColBuffers,
+                nAuxBuffers,
+            )
+            .to_raw()
+        }
+        pub unsafe fn grTexClampMode(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            let s_clampmode = <u32>::from_stack(mem, esp + 8u32);
+            let t_clampmode = <u32>::from_stack(mem, esp + 12u32);
+            winapi::glide3x::grTexClampMode(machine, tmu, s_clampmode, t_clampmode).to_raw()
+        }
+        pub unsafe fn grTexCombine(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            let rgb_function = <u32>::from_stack(mem, esp + 8u32);
+            let rgb_factor = <u32>::from_stack(mem, esp + 12u32);
+            let alpha_function = <u32>::from_stack(mem, esp + 16u32);
+            let alpha_factor = <u32>::from_stack(mem, esp + 20u32);
+            let rgb_invert = <bool>::from_stack(mem, esp + 24u32);
+            let alpha_invert = <bool>::from_stack(mem, esp + 28u32);
+            winapi::glide3x::grTexCombine(
+                machine,
+                tmu,
+                rgb_function,
+                rgb_factor,
+                alpha_function,
+                alpha_factor,
+                rgb_invert,
+                alpha_invert,
+            )
+            .to_raw()
+        }
+        pub unsafe fn grTexDownloadMipMap(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            let startAddress = <u32>::from_stack(mem, esp + 8u32);
+            let evenOdd = <u32>::from_stack(mem, esp + 12u32);
+            let info = <Option<&GrTexInfo>>::from_stack(mem, esp + 16u32);
+            winapi::glide3x::grTexDownloadMipMap(machine, tmu, startAddress, evenOdd, info).to_raw()
+        }
+        pub unsafe fn grTexDownloadMipMapLevel(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            let startAddress = <u32>::from_stack(mem, esp + 8u32);
+            let thisLod = <i32>::from_stack(mem, esp + 12u32);
+            let largeLod = <i32>::from_stack(mem, esp + 16u32);
+            let aspectRatio = <i32>::from_stack(mem, esp + 20u32);
+            let format = <u32>::from_stack(mem, esp + 24u32);
+            let evenOdd = <u32>::from_stack(mem, esp + 28u32);
+            let data = <u32>::from_stack(mem, esp + 32u32);
+            winapi::glide3x::grTexDownloadMipMapLevel(
+                machine,
+                tmu,
+                startAddress,
+                thisLod,
+                largeLod,
+                aspectRatio,
+                format,
+                evenOdd,
+                data,
+            )
+            .to_raw()
+        }
+        pub unsafe fn grTexDownloadTable(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let type_ = <u32>::from_stack(mem, esp + 4u32);
+            let data = <u32>::from_stack(mem, esp + 8u32);
+            winapi::glide3x::grTexDownloadTable(machine, type_, data).to_raw()
+        }
+        pub unsafe fn grTexFilterMode(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            let minfilter_mode = <u32>::from_stack(mem, esp + 8u32);
+            let magfilter_mode = <u32>::from_stack(mem, esp + 12u32);
+            winapi::glide3x::grTexFilterMode(machine, tmu, minfilter_mode, magfilter_mode).to_raw()
+        }
+        pub unsafe fn grTexMaxAddress(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide3x::grTexMaxAddress(machine, tmu).to_raw()
+        }
+        pub unsafe fn grTexMinAddress(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            winapi::glide3x::grTexMinAddress(machine, tmu).to_raw()
+        }
+        pub unsafe fn grTexSource(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let tmu = <u32>::from_stack(mem, esp + 4u32);
+            let startAddress = <u32>::from_stack(mem, esp + 8u32);
+            let evenOdd = <u32>::from_stack(mem, esp + 12u32);
+            let info = <Option<&GrTexInfo>>::from_stack(mem, esp + 16u32);
+            winapi::glide3x::grTexSource(machine, tmu, startAddress, evenOdd, info).to_raw()
+        }
+        pub unsafe fn grTexTextureMemRequired(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let evenOdd = <u32>::from_stack(mem, esp + 4u32);
+            let info = <Option<&GrTexInfo>>::from_stack(mem, esp + 8u32);
+            winapi::glide3x::grTexTextureMemRequired(machine, evenOdd, info).to_raw()
+        }
+        pub unsafe fn grVertexLayout(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let param = <u32>::from_stack(mem, esp + 4u32);
+            let offset = <i32>::from_stack(mem, esp + 8u32);
+            let mode = <u32>::from_stack(mem, esp + 12u32);
+            winapi::glide3x::grVertexLayout(machine, param, offset, mode).to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const grAlphaCombine: Shim = Shim {
+            name: "grAlphaCombine",
+            func: impls::grAlphaCombine,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const grBufferClear: Shim = Shim {
+            name: "grBufferClear",
+            func: impls::grBufferClear,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const grBufferSwap: Shim = Shim {
+            name: "grBufferSwap",
+            func: impls::grBufferSwap,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grClipWindow: Shim = Shim {
+            name: "grClipWindow",
+            func: impls::grClipWindow,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const grColorCombine: Shim = Shim {
+            name: "grColorCombine",
+            func: impls::grColorCombine,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const grConstantColorValue: Shim = Shim {
+            name: "grConstantColorValue",
+            func: impls::grConstantColorValue,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grCullMode: Shim = Shim {
+            name: "grCullMode",
+            func: impls::grCullMode,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grDepthBufferFunction: Shim = Shim {
+            name: "grDepthBufferFunction",
+            func: impls::grDepthBufferFunction,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grDepthBufferMode: Shim = Shim {
+            name: "grDepthBufferMode",
+            func: impls::grDepthBufferMode,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grDepthMask: Shim = Shim {
+            name: "grDepthMask",
+            func: impls::grDepthMask,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grDrawTriangle: Shim = Shim {
+            name: "grDrawTriangle",
+            func: impls::grDrawTriangle,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const grDrawVertexArray: Shim = Shim {
+            name: "grDrawVertexArray",
+            func: impls::grDrawVertexArray,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const grDrawVertexArrayContiguous: Shim = Shim {
+            name: "grDrawVertexArrayContiguous",
+            func: impls::grDrawVertexArrayContiguous,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const grGet: Shim = Shim {
+            name: "grGet",
+            func: impls::grGet,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const grGetString: Shim = Shim {
+            name: "grGetString",
+            func: impls::grGetString,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grGlideInit: Shim = Shim {
+            name: "grGlideInit",
+            func: impls::grGlideInit,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const grGlideShutdown: Shim = Shim {
+            name: "grGlideShutdown",
+            func: impls::grGlideShutdown,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const grSelectContext: Shim = Shim {
+            name: "grSelectContext",
+            func: impls::grSelectContext,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grSstSelect: Shim = Shim {
+            name: "grSstSelect",
+            func: impls::grSstSelect,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grSstWinClose: Shim = Shim {
+            name: "grSstWinClose",
+            func: impls::grSstWinClose,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grSstWinOpen: Shim = Shim {
+            name: "grSstWinOpen",
+            func: impls::grSstWinOpen,
+            stack_consumed: 28u32,
+            is_async: false,
+        };
+        pub const grTexClampMode: Shim = Shim {
+            name: "grTexClampMode",
+            func: impls::grTexClampMode,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const grTexCombine: Shim = Shim {
+            name: "grTexCombine",
+            func: impls::grTexCombine,
+            stack_consumed: 28u32,
+            is_async: false,
+        };
+        pub const grTexDownloadMipMap: Shim = Shim {
+            name: "grTexDownloadMipMap",
+            func: impls::grTexDownloadMipMap,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const grTexDownloadMipMapLevel: Shim = Shim {
+            name: "grTexDownloadMipMapLevel",
+            func: impls::grTexDownloadMipMapLevel,
+            stack_consumed: 32u32,
+            is_async: false,
+        };
+        pub const grTexDownloadTable: Shim = Shim {
+            name: "grTexDownloadTable",
+            func: impls::grTexDownloadTable,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const grTexFilterMode: Shim = Shim {
+            name: "grTexFilterMode",
+            func: impls::grTexFilterMode,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const grTexMaxAddress: Shim = Shim {
+            name: "grTexMaxAddress",
+            func: impls::grTexMaxAddress,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grTexMinAddress: Shim = Shim {
+            name: "grTexMinAddress",
+            func: impls::grTexMinAddress,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const grTexSource: Shim = Shim {
+            name: "grTexSource",
+            func: impls::grTexSource,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const grTexTextureMemRequired: Shim = Shim {
+            name: "grTexTextureMemRequired",
+            func: impls::grTexTextureMemRequired,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const grVertexLayout: Shim = Shim {
+            name: "grVertexLayout",
+            func: impls::grVertexLayout,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 32usize] = [
+        Symbol {
+            ordinal: None,
+            shim: shims::grAlphaCombine,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grBufferClear,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grBufferSwap,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grClipWindow,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grColorCombine,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grConstantColorValue,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grCullMode,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grDepthBufferFunction,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grDepthBufferMode,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grDepthMask,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grDrawTriangle,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grDrawVertexArray,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grDrawVertexArrayContiguous,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grGet,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grGetString,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grGlideInit,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grGlideShutdown,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grSelectContext,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grSstSelect,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grSstWinClose,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grSstWinOpen,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexClampMode,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexCombine,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexDownloadMipMap,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexDownloadMipMapLevel,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexDownloadTable,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexFilterMode,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexMaxAddress,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexMinAddress,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexSource,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grTexTextureMemRequired,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::grVertexLayout,
+        },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "glide3x.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod imm32 {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::imm32::*;
+        pub unsafe fn ImmAssociateContext(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hWnd = <HWND>::from_stack(mem, esp + 4u32);
+            let hIMC = <HIMC>::from_stack(mem, esp + 8u32);
+            winapi::imm32::ImmAssociateContext(machine, hWnd, hIMC).to_raw()
+        }
+        pub unsafe fn ImmAssociateContextEx(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hWnd = <HWND>::from_stack(mem, esp + 4u32);
+            let hIMC = <HIMC>::from_stack(mem, esp + 8u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 12u32);
+            winapi::imm32::ImmAssociateContextEx(machine, hWnd, hIMC, dwFlags).to_raw()
+        }
+        pub unsafe fn ImmCreateContext(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::imm32::ImmCreateContext(machine).to_raw()
+        }
+        pub unsafe fn ImmDestroyContext(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hIMC = <HIMC>::from_stack(mem, esp + 4u32);
+            winapi::imm32::ImmDestroyContext(machine, hIMC).to_raw()
+        }
+        pub unsafe fn ImmDisableIME(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let idThread = <u32>::from_stack(mem, esp + 4u32);
+            winapi::imm32::ImmDisableIME(machine, idThread).to_raw()
+        }
+        pub unsafe fn ImmGetCandidateListA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hIMC = <HIMC>::from_stack(mem, esp + 4u32);
+            let deIndex = <u32>::from_stack(mem, esp + 8u32);
+            let lpCandList = <u32>::from_stack(mem, esp + 12u32);
+            let dwBufLen = <u32>::from_stack(mem, esp + 16u32);
+            winapi::imm32::ImmGetCandidateListA(machine, hIMC, deIndex, lpCandList, dwBufLen)
+                .to_raw()
+        }
+        pub unsafe fn ImmGetCompositionStringA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hIMC = <HIMC>::from_stack(mem, esp + 4u32);
+            let dwIndex = <u32>::from_stack(mem, esp + 8u32);
+            let lpBuf = <u32>::from_stack(mem, esp + 12u32);
+            let dwBufLen = <u32>::from_stack(mem, esp + 16u32);
+            winapi::imm32::ImmGetCompositionStringA(machine, hIMC, dwIndex, lpBuf, dwBufLen)
+                .to_raw()
+        }
+        pub unsafe fn ImmGetCompositionStringW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hIMC = <HIMC>::from_stack(mem, esp + 4u32);
+            let dwIndex = <u32>::from_stack(mem, esp + 8u32);
+            let lpBuf = <u32>::from_stack(mem, esp + 12u32);
+            let dwBufLen = <u32>::from_stack(mem, esp + 16u32);
+            winapi::imm32::ImmGetCompositionStringW(machine, hIMC, dwIndex, lpBuf, dwBufLen)
+                .to_raw()
+        }
+        pub unsafe fn ImmGetContext(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hWnd = <HWND>::from_stack(mem, esp + 4u32);
+            winapi::imm32::ImmGetContext(machine, hWnd).to_raw()
+        }
+        pub unsafe fn ImmGetConversionStatus(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hIMC = <HIMC>::from_stack(mem, esp + 4u32);
+            let lpfdwConversion = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
+            let lpfdwSentence = <Option<&mut u32>>::from_stack(mem, esp + 12u32);
+            winapi::imm32::ImmGetConversionStatus(machine, hIMC, lpfdwConversion, lpfdwSentence)
+                .to_raw()
+        }
+        pub unsafe fn ImmGetDefaultIMEWnd(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hWnd = <HWND>::from_stack(mem, esp + 4u32);
+            winapi::imm32::ImmGetDefaultIMEWnd(machine, hWnd).to_raw()
+        }
+        pub unsafe fn ImmGetOpenStatus(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hIMC = <HIMC>::from_stack(mem, esp + 4u32);
+            winapi::imm32::ImmGetOpenStatus(machine, hIMC).to_raw()
+        }
+        pub unsafe fn ImmIsIME(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hKL = <u32>::from_stack(mem, esp + 4u32);
+            winapi::imm32::ImmIsIME(machine, hKL).to_raw()
+        }
+        pub unsafe fn ImmNotifyIME(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hIMC = <HIMC>::from_stack(mem, esp + 4u32);
+            let dwAction = <u32>::from_stack(mem, esp + 8u32);
+            let dwIndex = <u32>::from_stack(mem, esp + 12u32);
+            let dwValue = <u32>::from_stack(mem, esp + 16u32);
+            winapi::imm32::ImmNotifyIME(machine, hIMC, dwAction, dwIndex, dwValue).to_raw()
+        }
+        pub unsafe fn ImmReleaseContext(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hWnd = <HWND>::from_stack(mem, esp + 4u32);
+            let hIMC = <HIMC>::from_stack(mem, esp + 8u32);
+            winapi::imm32::ImmReleaseContext(machine, hWnd, hIMC).to_raw()
+        }
+        pub unsafe fn ImmSetCandidateWindow(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hIMC = <HIMC>::from_stack(mem, esp + 4u32);
+            let lpCandidate = <u32>::from_stack(mem, esp + 8u32);
+            winapi::imm32::ImmSetCandidateWindow(machine, hIMC, lpCandidate).to_raw()
+        }
+        pub unsafe fn ImmSetCompositionFontA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hIMC = <HIMC>::from_stack(mem, esp + 4u32);
+            let lplf = <u32>::from_stack(mem, esp + 8u32);
+            winapi::imm32::ImmSetCompositionFontA(machine, hIMC, lplf).to_raw()
+        }
+        pub unsafe fn ImmSetCompositionWindow(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hIMC = <HIMC>::from_stack(mem, esp + 4u32);
+            let lpCompForm = <u32>::from_stack(mem, esp + 8u32);
+            winapi::imm32::ImmSetCompositionWindow(machine, hIMC, lpCompForm).to_raw()
+        }
+        pub unsafe fn ImmSetConversionStatus(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hIMC = <HIMC>::from_stack(mem, esp + 4u32);
+            let fdwConversion = <u32>::from_stack(mem, esp + 8u32);
+            let fdwSentence = <u32>::from_stack(mem, esp + 12u32);
+            winapi::imm32::ImmSetConversionStatus(machine, hIMC, fdwConversion, fdwSentence)
+                .to_raw()
+        }
+        pub unsafe fn ImmSetOpenStatus(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hIMC = <HIMC>::from_stack(mem, esp + 4u32);
+            let fOpen = <bool>::from_stack(mem, esp + 8u32);
+            winapi::imm32::ImmSetOpenStatus(machine, hIMC, fOpen).to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const ImmAssociateContext: Shim = Shim {
+            name: "ImmAssociateContext",
+            func: impls::ImmAssociateContext,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const ImmAssociateContextEx: Shim = Shim {
+            name: "ImmAssociateContextEx",
+            func: impls::ImmAssociateContextEx,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const ImmCreateContext: Shim = Shim {
+            name: "ImmCreateContext",
+            func: impls::ImmCreateContext,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const ImmDestroyContext: Shim = Shim {
+            name: "ImmDestroyContext",
+            func: impls::ImmDestroyContext,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const ImmDisableIME: Shim = Shim {
+            name: "ImmDisableIME",
+            func: impls::ImmDisableIME,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const ImmGetCandidateListA: Shim = Shim {
+            name: "ImmGetCandidateListA",
+            func: impls::ImmGetCandidateListA,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const ImmGetCompositionStringA: Shim = Shim {
+            name: "ImmGetCompositionStringA",
+            func: impls::ImmGetCompositionStringA,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const ImmGetCompositionStringW: Shim = Shim {
+            name: "ImmGetCompositionStringW",
+            func: impls::ImmGetCompositionStringW,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const ImmGetContext: Shim = Shim {
+            name: "ImmGetContext",
+            func: impls::ImmGetContext,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const ImmGetConversionStatus: Shim = Shim {
+            name: "ImmGetConversionStatus",
+            func: impls::ImmGetConversionStatus,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const ImmGetDefaultIMEWnd: Shim = Shim {
+            name: "ImmGetDefaultIMEWnd",
+            func: impls::ImmGetDefaultIMEWnd,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const ImmGetOpenStatus: Shim = Shim {
+            name: "ImmGetOpenStatus",
+            func: impls::ImmGetOpenStatus,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const ImmIsIME: Shim = Shim {
+            name: "ImmIsIME",
+            func: impls::ImmIsIME,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const ImmNotifyIME: Shim = Shim {
+            name: "ImmNotifyIME",
+            func: impls::ImmNotifyIME,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const ImmReleaseContext: Shim = Shim {
+            name: "ImmReleaseContext",
+            func: impls::ImmReleaseContext,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const ImmSetCandidateWindow: Shim = Shim {
+            name: "ImmSetCandidateWindow",
+            func: impls::ImmSetCandidateWindow,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const ImmSetCompositionFontA: Shim = Shim {
+            name: "ImmSetCompositionFontA",
+            func: impls::ImmSetCompositionFontA,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const ImmSetCompositionWindow: Shim = Shim {
+            name: "ImmSetCompositionWindow",
+            func: impls::ImmSetCompositionWindow,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const ImmSetConversionStatus: Shim = Shim {
+            name: "ImmSetConversionStatus",
+            func: impls::ImmSetConversionStatus,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const ImmSetOpenStatus: Shim = Shim {
+            name: "ImmSetOpenStatus",
+            func: impls::ImmSetOpenStatus,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 20usize] = [
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmAssociateContext,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmAssociateContextEx,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmCreateContext,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmDestroyContext,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmDisableIME,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmGetCandidateListA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmGetCompositionStringA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmGetCompositionStringW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmGetContext,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmGetConversionStatus,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmGetDefaultIMEWnd,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmGetOpenStatus,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmIsIME,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmNotifyIME,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmReleaseContext,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmSetCandidateWindow,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmSetCompositionFontA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmSetCompositionWindow,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmSetConversionStatus,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ImmSetOpenStatus,
+        },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "imm32.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod kernel32 {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::kernel32::*;
+        pub unsafe fn AcquireSRWLockExclusive(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let SRWLock = <Option<&mut SRWLOCK>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::AcquireSRWLockExclusive(machine, SRWLock).to_raw()
+        }
+        pub unsafe fn AcquireSRWLockShared(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let SRWLock = <Option<&mut SRWLOCK>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::AcquireSRWLockShared(machine, SRWLock).to_raw()
+        }
+        pub unsafe fn AddVectoredExceptionHandler(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let first = <u32>::from_stack(mem, esp + 4u32);
+            let handler = <u32>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::AddVectoredExceptionHandler(machine, first, handler).to_raw()
+        }
+        pub unsafe fn ClearCommError(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
+            let lpErrors = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
+            let lpStat = <Option<&mut COMSTAT>>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::ClearCommError(machine, hFile, lpErrors, lpStat).to_raw()
+        }
+        pub unsafe fn CloseHandle(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hObject = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::CloseHandle(machine, hObject).to_raw()
+        }
+        pub unsafe fn CreateEventA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpEventAttributes = <u32>::from_stack(mem, esp + 4u32);
+            let bManualReset = <bool>::from_stack(mem, esp + 8u32);
+            let bInitialState = <bool>::from_stack(mem, esp + 12u32);
+            let lpName = <Option<&str>>::from_stack(mem, esp + 16u32);
+            winapi::kernel32::CreateEventA(
+                machine,
+                lpEventAttributes,
+                bManualReset,
+                bInitialState,
+                lpName,
+            )
+            .to_raw()
+        }
+        pub unsafe fn CreateFileA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpFileName = <Option<&str>>::from_stack(mem, esp + 4u32);
+            let dwDesiredAccess = <u32>::from_stack(mem, esp + 8u32);
+            let dwShareMode = <u32>::from_stack(mem, esp + 12u32);
+            let lpSecurityAttributes = <u32>::from_stack(mem, esp + 16u32);
+            let dwCreationDisposition =
+                <Result<CreationDisposition, u32>>::from_stack(mem, esp + 20u32);
+            let dwFlagsAndAttributes = <Result<FileAttribute, u32>>::from_stack(mem, esp + 24u32);
+            let hTemplateFile = <HFILE>::from_stack(mem, esp + 28u32);
+            winapi::kernel32::CreateFileA(
+                machine,
+                lpFileName,
+                dwDesiredAccess,
+                dwShareMode,
+                lpSecurityAttributes,
+                dwCreationDisposition,
+                dwFlagsAndAttributes,
+                hTemplateFile,
+            )
+            .to_raw()
+        }
+        pub unsafe fn CreateFileW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpFileName = <Option<&Str16>>::from_stack(mem, esp + 4u32);
+            let dwDesiredAccess = <u32>::from_stack(mem, esp + 8u32);
+            let dwShareMode = <u32>::from_stack(mem, esp + 12u32);
+            let lpSecurityAttributes = <u32>::from_stack(mem, esp + 16u32);
+            let dwCreationDisposition =
+                <Result<CreationDisposition, u32>>::from_stack(mem, esp + 20u32);
+            let dwFlagsAndAttributes = <Result<FileAttribute, u32>>::from_stack(mem, esp + 24u32);
+            let hTemplateFile = <HFILE>::from_stack(mem, esp + 28u32);
+            winapi::kernel32::CreateFileW(
+                machine,
+                lpFileName,
+                dwDesiredAccess,
+                dwShareMode,
+                lpSecurityAttributes,
+                dwCreationDisposition,
+                dwFlagsAndAttributes,
+                hTemplateFile,
+            )
+            .to_raw()
+        }
+        pub unsafe fn CreateThread(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpThreadAttributes = <u32>::from_stack(mem, esp + 4u32);
+            let dwStackSize = <u32>::from_stack(mem, esp + 8u32);
+            let lpStartAddress = <u32>::from_stack(mem, esp + 12u32);
+            let lpParameter = <u32>::from_stack(mem, esp + 16u32);
+            let dwCreationFlags = <u32>::from_stack(mem, esp + 20u32);
+            let lpThreadId = <u32>::from_stack(mem, esp + 24u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::kernel32::CreateThread(
+                        machine,
+                        lpThreadAttributes,
+                        dwStackSize,
+                        lpStartAddress,
+                        lpParameter,
+                        dwCreationFlags,
+                        lpThreadId,
+                    )
+                    .await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 24u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::kernel32::CreateThread(
+                    machine,
+                    lpThreadAttributes,
+                    dwStackSize,
+                    lpStartAddress,
+                    lpParameter,
+                    dwCreationFlags,
+                    lpThreadId
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn DeleteCriticalSection(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpCriticalSection = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::DeleteCriticalSection(machine, lpCriticalSection).to_raw()
+        }
+        pub unsafe fn EnterCriticalSection(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpCriticalSection = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::EnterCriticalSection(machine, lpCriticalSection).to_raw()
+        }
+        pub unsafe fn EscapeCommFunction(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
+            let dwFunc = <u32>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::EscapeCommFunction(machine, hFile, dwFunc).to_raw()
+        }
+        pub unsafe fn ExitProcess(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let uExitCode = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::ExitProcess(machine, uExitCode).to_raw()
+        }
+        pub unsafe fn FindResourceA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hModule = <u32>::from_stack(mem, esp + 4u32);
+            let lpName = <ResourceKey<&str>>::from_stack(mem, esp + 8u32);
+            let lpType = <ResourceKey<&str>>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::FindResourceA(machine, hModule, lpName, lpType).to_raw()
+        }
+        pub unsafe fn FindResourceExA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hModule = <u32>::from_stack(mem, esp + 4u32);
+            let lpType = <ResourceKey<&str>>::from_stack(mem, esp + 8u32);
+            let lpName = <ResourceKey<&str>>::from_stack(mem, esp + 12u32);
+            let wLanguage = <u32>::from_stack(mem, esp + 16u32);
+            winapi::kernel32::FindResourceExA(machine, hModule, lpType, lpName, wLanguage).to_raw()
+        }
+        pub unsafe fn FindResourceExW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hModule = <u32>::from_stack(mem, esp + 4u32);
+            let lpType = <ResourceKey<&Str16>>::from_stack(mem, esp + 8u32);
+            let lpName = <ResourceKey<&Str16>>::from_stack(mem, esp + 12u32);
+            let wLanguage = <u32>::from_stack(mem, esp + 16u32);
+            winapi::kernel32::FindResourceExW(machine, hModule, lpType, lpName, wLanguage).to_raw()
+        }
+        pub unsafe fn FindResourceW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hModule = <u32>::from_stack(mem, esp + 4u32);
+            let lpName = <ResourceKey<&Str16>>::from_stack(mem, esp + 8u32);
+            let lpType = <ResourceKey<&Str16>>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::FindResourceW(machine, hModule, lpName, lpType).to_raw()
+        }
+        pub unsafe fn FlushInstructionCache(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hProcess = <HANDLE<()>>::from_stack(mem, esp + 4u32);
+            let lpBaseAddress = <u32>::from_stack(mem, esp + 8u32);
+            let dwSize = <u32>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::FlushInstructionCache(machine, hProcess, lpBaseAddress, dwSize)
+                .to_raw()
+        }
+        pub unsafe fn FormatMessageW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let dwFlags = <Result<FormatMessageFlags, u32>>::from_stack(mem, esp + 4u32);
+            let lpSource = <u32>::from_stack(mem, esp + 8u32);
+            let dwMessageId = <u32>::from_stack(mem, esp + 12u32);
+            let dwLanguageId = <u32>::from_stack(mem, esp + 16u32);
+            let lpBuffer = <u32>::from_stack(mem, esp + 20u32);
+            let nSize = <u32>::from_stack(mem, esp + 24u32);
+            let args = <u32>::from_stack(mem, esp + 28u32);
+            winapi::kernel32::FormatMessageW(
+                machine,
+                dwFlags,
+                lpSource,
+                dwMessageId,
+                dwLanguageId,
+                lpBuffer,
+                nSize,
+                args,
+            )
+            .to_raw()
+        }
+        pub unsafe fn FreeEnvironmentStringsA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let _penv = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::FreeEnvironmentStringsA(machine, _penv).to_raw()
+        }
+        pub unsafe fn FreeLibrary(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hLibModule = <HMODULE>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::FreeLibrary(machine, hLibModule).to_raw()
+        }
+        pub unsafe fn GetACP(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetACP(machine).to_raw()
+        }
+        pub unsafe fn GetCPInfo(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let CodePage = <u32>::from_stack(mem, esp + 4u32);
+            let lpCPInfo = <u32>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::GetCPInfo(machine, CodePage, lpCPInfo).to_raw()
+        }
+        pub unsafe fn GetCommModemStatus(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
+            let lpModemStat = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::GetCommModemStatus(machine, hFile, lpModemStat).to_raw()
+        }
+        pub unsafe fn GetCommState(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
+            let lpDCB = <Option<&mut DCB>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::GetCommState(machine, hFile, lpDCB).to_raw()
+        }
+        pub unsafe fn GetCommTimeouts(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
+            let lpCommTimeouts = <Option<&mut COMMTIMEOUTS>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::GetCommTimeouts(machine, hFile, lpCommTimeouts).to_raw()
+        }
+        pub unsafe fn GetCommandLineA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetCommandLineA(machine).to_raw()
+        }
+        pub unsafe fn GetCommandLineW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetCommandLineW(machine).to_raw()
+        }
+        pub unsafe fn GetComputerNameA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpBuffer = <u32>::from_stack(mem, esp + 4u32);
+            let nSize = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::GetComputerNameA(machine, lpBuffer, nSize).to_raw()
+        }
+        pub unsafe fn GetComputerNameW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpBuffer = <u32>::from_stack(mem, esp + 4u32);
+            let nSize = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::GetComputerNameW(machine, lpBuffer, nSize).to_raw()
+        }
+        pub unsafe fn GetConsoleMode(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hConsoleHandle = <HFILE>::from_stack(mem, esp + 4u32);
+            let lpMode = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::GetConsoleMode(machine, hConsoleHandle, lpMode).to_raw()
+        }
+        pub unsafe fn GetCurrentProcessId(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetCurrentProcessId(machine).to_raw()
+        }
+        pub unsafe fn GetCurrentThread(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetCurrentThread(machine).to_raw()
+        }
+        pub unsafe fn GetCurrentThreadId(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetCurrentThreadId(machine).to_raw()
+        }
+        pub unsafe fn GetEnvironmentStrings(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetEnvironmentStrings(machine).to_raw()
+        }
+        pub unsafe fn GetEnvironmentStringsW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetEnvironmentStringsW(machine).to_raw()
+        }
+        pub unsafe fn GetEnvironmentVariableA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let name = <Option<&str>>::from_stack(mem, esp + 4u32);
             let buf = <ArrayWithSize<u8>>::from_stack(mem, esp + 8u32);
             winapi::kernel32::GetEnvironmentVariableA(machine, name, buf).to_raw()
         }
-        pub unsafe fn GetEnvironmentVariableW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn GetEnvironmentVariableW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let name = <Option<&Str16>>::from_stack(mem, esp + 4u32);
+            let buf = <ArrayWithSize<u16>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::GetEnvironmentVariableW(machine, name, buf).to_raw()
+        }
+        pub unsafe fn GetFileInformationByHandle(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
+            let lpFileInformation =
+                <Option<&mut BY_HANDLE_FILE_INFORMATION>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::GetFileInformationByHandle(machine, hFile, lpFileInformation).to_raw()
+        }
+        pub unsafe fn GetFileType(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::GetFileType(machine, hFile).to_raw()
+        }
+        pub unsafe fn GetFullPathNameW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpFileName = <Option<&Str16>>::from_stack(mem, esp + 4u32);
+            let nBufferLength = <u32>::from_stack(mem, esp + 8u32);
+            let lpBuffer = <u32>::from_stack(mem, esp + 12u32);
+            let lpFilePart = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            winapi::kernel32::GetFullPathNameW(
+                machine,
+                lpFileName,
+                nBufferLength,
+                lpBuffer,
+                lpFilePart,
+            )
+            .to_raw()
+        }
+        pub unsafe fn GetLastError(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetLastError(machine).to_raw()
+        }
+        pub unsafe fn GetModuleFileNameA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hModule = <HMODULE>::from_stack(mem, esp + 4u32);
+            let filename = <ArrayWithSizeMut<u8>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::GetModuleFileNameA(machine, hModule, filename).to_raw()
+        }
+        pub unsafe fn GetModuleFileNameW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hModule = <HMODULE>::from_stack(mem, esp + 4u32);
+            let _lpFilename = <u32>::from_stack(mem, esp + 8u32);
+            let _nSize = <u32>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::GetModuleFileNameW(machine, hModule, _lpFilename, _nSize).to_raw()
+        }
+        pub unsafe fn GetModuleHandleA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpModuleName = <Option<&str>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::GetModuleHandleA(machine, lpModuleName).to_raw()
+        }
+        pub unsafe fn GetModuleHandleExW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let dwFlags = <u32>::from_stack(mem, esp + 4u32);
+            let lpModuleName = <Option<&Str16>>::from_stack(mem, esp + 8u32);
+            let hModule = <Option<&mut HMODULE>>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::GetModuleHandleExW(machine, dwFlags, lpModuleName, hModule).to_raw()
+        }
+        pub unsafe fn GetModuleHandleW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpModuleName = <Option<&Str16>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::GetModuleHandleW(machine, lpModuleName).to_raw()
+        }
+        pub unsafe fn GetPrivateProfileIntW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpAppName = <Option<&Str16>>::from_stack(mem, esp + 4u32);
+            let lpKeyName = <Option<&Str16>>::from_stack(mem, esp + 8u32);
+            let nDefault = <u32>::from_stack(mem, esp + 12u32);
+            let lpFileName = <Option<&Str16>>::from_stack(mem, esp + 16u32);
+            winapi::kernel32::GetPrivateProfileIntW(
+                machine, lpAppName, lpKeyName, nDefault, lpFileName,
+            )
+            .to_raw()
+        }
+        pub unsafe fn GetPrivateProfileStringW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpAppName = <Option<&Str16>>::from_stack(mem, esp + 4u32);
+            let lpKeyName = <Option<&Str16>>::from_stack(mem, esp + 8u32);
+            let lpDefault = <Option<&Str16>>::from_stack(mem, esp + 12u32);
+            let lpReturnedString = <ArrayWithSizeMut<u16>>::from_stack(mem, esp + 16u32);
+            let lpFileName = <Option<&Str16>>::from_stack(mem, esp + 24u32);
+            winapi::kernel32::GetPrivateProfileStringW(
+                machine,
+                lpAppName,
+                lpKeyName,
+                lpDefault,
+                lpReturnedString,
+                lpFileName,
+            )
+            .to_raw()
+        }
+        pub unsafe fn GetProcAddress(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hModule = <HMODULE>::from_stack(mem, esp + 4u32);
+            let lpProcName = <GetProcAddressArg>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::GetProcAddress(machine, hModule, lpProcName).to_raw()
+        }
+        pub unsafe fn GetProcessHeap(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetProcessHeap(machine).to_raw()
+        }
+        pub unsafe fn GetStartupInfoA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpStartupInfo = <Option<&mut STARTUPINFOA>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::GetStartupInfoA(machine, lpStartupInfo).to_raw()
+        }
+        pub unsafe fn GetStartupInfoW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpStartupInfo = <Option<&mut STARTUPINFOA>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::GetStartupInfoW(machine, lpStartupInfo).to_raw()
+        }
+        pub unsafe fn GetStdHandle(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let nStdHandle = <Result<STD, u32>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::GetStdHandle(machine, nStdHandle).to_raw()
+        }
+        pub unsafe fn GetSystemDefaultLangID(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetSystemDefaultLangID(machine).to_raw()
+        }
+        pub unsafe fn GetSystemDefaultUILanguage(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetSystemDefaultUILanguage(machine).to_raw()
+        }
+        pub unsafe fn GetSystemInfo(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpSystemInfo = <Option<&mut SYSTEM_INFO>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::GetSystemInfo(machine, lpSystemInfo).to_raw()
+        }
+        pub unsafe fn GetSystemTimeAsFileTime(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let _time = <Option<&mut FILETIME>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::GetSystemTimeAsFileTime(machine, _time).to_raw()
+        }
+        pub unsafe fn GetThreadContext(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hThread = <HTHREAD>::from_stack(mem, esp + 4u32);
+            let lpContext = <Option<&mut CONTEXT>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::GetThreadContext(machine, hThread, lpContext).to_raw()
+        }
+        pub unsafe fn GetTickCount(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetTickCount(machine).to_raw()
+        }
+        pub unsafe fn GetUserDefaultLangID(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetUserDefaultLangID(machine).to_raw()
+        }
+        pub unsafe fn GetUserDefaultUILanguage(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetUserDefaultUILanguage(machine).to_raw()
+        }
+        pub unsafe fn GetVersion(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::GetVersion(machine).to_raw()
+        }
+        pub unsafe fn GetVersionExA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpVersionInformation = <Option<&mut OSVERSIONINFO>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::GetVersionExA(machine, lpVersionInformation).to_raw()
+        }
+        pub unsafe fn GlobalAlloc(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let uFlags = <GMEM>::from_stack(mem, esp + 4u32);
+            let dwBytes = <u32>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::GlobalAlloc(machine, uFlags, dwBytes).to_raw()
+        }
+        pub unsafe fn GlobalFree(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hMem = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::GlobalFree(machine, hMem).to_raw()
+        }
+        pub unsafe fn HeapAlloc(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hHeap = <u32>::from_stack(mem, esp + 4u32);
+            let dwFlags = <Result<HeapAllocFlags, u32>>::from_stack(mem, esp + 8u32);
+            let dwBytes = <u32>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::HeapAlloc(machine, hHeap, dwFlags, dwBytes).to_raw()
+        }
+        pub unsafe fn HeapCreate(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let flOptions = <Result<HeapCreateFlags, u32>>::from_stack(mem, esp + 4u32);
+            let dwInitialSize = <u32>::from_stack(mem, esp + 8u32);
+            let dwMaximumSize = <u32>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::HeapCreate(machine, flOptions, dwInitialSize, dwMaximumSize).to_raw()
+        }
+        pub unsafe fn HeapDestroy(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hHeap = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::HeapDestroy(machine, hHeap).to_raw()
+        }
+        pub unsafe fn HeapFree(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hHeap = <u32>::from_stack(mem, esp + 4u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 8u32);
+            let lpMem = <u32>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::HeapFree(machine, hHeap, dwFlags, lpMem).to_raw()
+        }
+        pub unsafe fn HeapReAlloc(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hHeap = <u32>::from_stack(mem, esp + 4u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 8u32);
+            let lpMem = <u32>::from_stack(mem, esp + 12u32);
+            let dwBytes = <u32>::from_stack(mem, esp + 16u32);
+            winapi::kernel32::HeapReAlloc(machine, hHeap, dwFlags, lpMem, dwBytes).to_raw()
+        }
+        pub unsafe fn HeapSetInformation(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let HeapHandle = <u32>::from_stack(mem, esp + 4u32);
+            let HeapInformationClass = <u32>::from_stack(mem, esp + 8u32);
+            let HeapInformation = <u32>::from_stack(mem, esp + 12u32);
+            let HeapInformationLength = <u32>::from_stack(mem, esp + 16u32);
+            winapi::kernel32::HeapSetInformation(
+                machine,
+                HeapHandle,
+                HeapInformationClass,
+                HeapInformation,
+                HeapInformationLength,
+            )
+            .to_raw()
+        }
+        pub unsafe fn HeapSize(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hHeap = <u32>::from_stack(mem, esp + 4u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 8u32);
+            let lpMem = <u32>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::HeapSize(machine, hHeap, dwFlags, lpMem).to_raw()
+        }
+        pub unsafe fn InitOnceBeginInitialize(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpInitOnce = <Option<&mut INIT_ONCE>>::from_stack(mem, esp + 4u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 8u32);
+            let fPending = <Option<&mut u32>>::from_stack(mem, esp + 12u32);
+            let lpContext = <u32>::from_stack(mem, esp + 16u32);
+            winapi::kernel32::InitOnceBeginInitialize(
+                machine, lpInitOnce, dwFlags, fPending, lpContext,
+            )
+            .to_raw()
+        }
+        pub unsafe fn InitOnceComplete(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpInitOnce = <Option<&mut INIT_ONCE>>::from_stack(mem, esp + 4u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 8u32);
+            let lpContext = <u32>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::InitOnceComplete(machine, lpInitOnce, dwFlags, lpContext).to_raw()
+        }
+        pub unsafe fn InitializeCriticalSection(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpCriticalSection = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::InitializeCriticalSection(machine, lpCriticalSection).to_raw()
+        }
+        pub unsafe fn InitializeCriticalSectionAndSpinCount(
+            machine: &mut Machine,
+            esp: u32,
+        ) -> u32 {
+            let mem = machine.mem().detach();
+            let lpCriticalSection = <u32>::from_stack(mem, esp + 4u32);
+            let dwSpinCount = <u32>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::InitializeCriticalSectionAndSpinCount(
+                machine,
+                lpCriticalSection,
+                dwSpinCount,
+            )
+            .to_raw()
+        }
+        pub unsafe fn InitializeCriticalSectionEx(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpCriticalSection = <u32>::from_stack(mem, esp + 4u32);
+            let dwSpinCount = <u32>::from_stack(mem, esp + 8u32);
+            let flags = <u32>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::InitializeCriticalSectionEx(
+                machine,
+                lpCriticalSection,
+                dwSpinCount,
+                flags,
+            )
+            .to_raw()
+        }
+        pub unsafe fn InitializeSListHead(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let ListHead = <Option<&mut SLIST_HEADER>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::InitializeSListHead(machine, ListHead).to_raw()
+        }
+        pub unsafe fn InterlockedIncrement(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let addend = <Option<&mut u32>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::InterlockedIncrement(machine, addend).to_raw()
+        }
+        pub unsafe fn IsBadReadPtr(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lp = <u32>::from_stack(mem, esp + 4u32);
+            let ucb = <u32>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::IsBadReadPtr(machine, lp, ucb).to_raw()
+        }
+        pub unsafe fn IsBadWritePtr(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lp = <u32>::from_stack(mem, esp + 4u32);
+            let ucb = <u32>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::IsBadWritePtr(machine, lp, ucb).to_raw()
+        }
+        pub unsafe fn IsDBCSLeadByte(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let TestChar = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::IsDBCSLeadByte(machine, TestChar).to_raw()
+        }
+        pub unsafe fn IsDebuggerPresent(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::IsDebuggerPresent(machine).to_raw()
+        }
+        pub unsafe fn IsProcessorFeaturePresent(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let feature = <Result<ProcessorFeature, u32>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::IsProcessorFeaturePresent(machine, feature).to_raw()
+        }
+        pub unsafe fn IsValidCodePage(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let CodePage = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::IsValidCodePage(machine, CodePage).to_raw()
+        }
+        pub unsafe fn LeaveCriticalSection(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpCriticalSection = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::LeaveCriticalSection(machine, lpCriticalSection).to_raw()
+        }
+        pub unsafe fn LoadLibraryA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let filename = <Option<&str>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::LoadLibraryA(machine, filename).to_raw()
+        }
+        pub unsafe fn LoadLibraryExW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpLibFileName = <Option<&Str16>>::from_stack(mem, esp + 4u32);
+            let hFile = <HFILE>::from_stack(mem, esp + 8u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::LoadLibraryExW(machine, lpLibFileName, hFile, dwFlags).to_raw()
+        }
+        pub unsafe fn LoadResource(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hModule = <u32>::from_stack(mem, esp + 4u32);
+            let hResInfo = <u32>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::LoadResource(machine, hModule, hResInfo).to_raw()
+        }
+        pub unsafe fn LocalAlloc(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let uFlags = <GMEM>::from_stack(mem, esp + 4u32);
+            let dwBytes = <u32>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::LocalAlloc(machine, uFlags, dwBytes).to_raw()
+        }
+        pub unsafe fn LockResource(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hResData = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::LockResource(machine, hResData).to_raw()
+        }
+        pub unsafe fn MultiByteToWideChar(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let CodePage = <Result<CP, u32>>::from_stack(mem, esp + 4u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 8u32);
+            let lpMultiByteStr = <u32>::from_stack(mem, esp + 12u32);
+            let cbMultiByte = <i32>::from_stack(mem, esp + 16u32);
+            let lpWideCharStr = <ArrayWithSizeMut<u16>>::from_stack(mem, esp + 20u32);
+            winapi::kernel32::MultiByteToWideChar(
+                machine,
+                CodePage,
+                dwFlags,
+                lpMultiByteStr,
+                cbMultiByte,
+                lpWideCharStr,
+            )
+            .to_raw()
+        }
+        pub unsafe fn NtCurrentTeb(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::NtCurrentTeb(machine).to_raw()
+        }
+        pub unsafe fn OutputDebugStringA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let msg = <Option<&str>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::OutputDebugStringA(machine, msg).to_raw()
+        }
+        pub unsafe fn OutputDebugStringW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let msg = <Option<&Str16>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::OutputDebugStringW(machine, msg).to_raw()
+        }
+        pub unsafe fn PurgeComm(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::PurgeComm(machine, hFile, dwFlags).to_raw()
+        }
+        pub unsafe fn QueryPerformanceCounter(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpPerformanceCount = <Option<&mut LARGE_INTEGER>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::QueryPerformanceCounter(machine, lpPerformanceCount).to_raw()
+        }
+        pub unsafe fn QueryPerformanceFrequency(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpFrequency = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::QueryPerformanceFrequency(machine, lpFrequency).to_raw()
+        }
+        pub unsafe fn RaiseException(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let dwExceptionCode = <u32>::from_stack(mem, esp + 4u32);
+            let dwExceptionFlags = <u32>::from_stack(mem, esp + 8u32);
+            let nNumberOfArguments = <u32>::from_stack(mem, esp + 12u32);
+            let lpArguments = <u32>::from_stack(mem, esp + 16u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::kernel32::RaiseException(
+                        machine,
+                        dwExceptionCode,
+                        dwExceptionFlags,
+                        nNumberOfArguments,
+                        lpArguments,
+                    )
+                    .await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 16u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::kernel32::RaiseException(
+                    machine,
+                    dwExceptionCode,
+                    dwExceptionFlags,
+                    nNumberOfArguments,
+                    lpArguments
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn ReadFile(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
+            let lpBuffer = <ArrayWithSizeMut<u8>>::from_stack(mem, esp + 8u32);
+            let lpNumberOfBytesRead = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            let lpOverlapped = <u32>::from_stack(mem, esp + 20u32);
+            winapi::kernel32::ReadFile(machine, hFile, lpBuffer, lpNumberOfBytesRead, lpOverlapped)
+                .to_raw()
+        }
+        pub unsafe fn ReleaseSRWLockExclusive(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let SRWLock = <Option<&mut SRWLOCK>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::ReleaseSRWLockExclusive(machine, SRWLock).to_raw()
+        }
+        pub unsafe fn ReleaseSRWLockShared(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let SRWLock = <Option<&mut SRWLOCK>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::ReleaseSRWLockShared(machine, SRWLock).to_raw()
+        }
+        pub unsafe fn ResetEvent(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hEvent = <HEVENT>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::ResetEvent(machine, hEvent).to_raw()
+        }
+        pub unsafe fn RtlUnwind(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let TargetFrame = <u32>::from_stack(mem, esp + 4u32);
+            let _TargetIp = <u32>::from_stack(mem, esp + 8u32);
+            let ExceptionRecord = <u32>::from_stack(mem, esp + 12u32);
+            let ReturnValue = <u32>::from_stack(mem, esp + 16u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::kernel32::RtlUnwind(
+                        machine,
+                        TargetFrame,
+                        _TargetIp,
+                        ExceptionRecord,
+                        ReturnValue,
+                    )
+                    .await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 16u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::kernel32::RtlUnwind(
+                    machine,
+                    TargetFrame,
+                    _TargetIp,
+                    ExceptionRecord,
+                    ReturnValue
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn SetCommState(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
+            let lpDCB = <Option<&DCB>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::SetCommState(machine, hFile, lpDCB).to_raw()
+        }
+        pub unsafe fn SetCommTimeouts(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
+            let lpCommTimeouts = <Option<&COMMTIMEOUTS>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::SetCommTimeouts(machine, hFile, lpCommTimeouts).to_raw()
+        }
+        pub unsafe fn SetEvent(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hEvent = <HEVENT>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::SetEvent(machine, hEvent).to_raw()
+        }
+        pub unsafe fn SetFilePointer(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
+            let lDistanceToMove = <u32>::from_stack(mem, esp + 8u32);
+            let lpDistanceToMoveHigh = <Option<&mut u32>>::from_stack(mem, esp + 12u32);
+            let dwMoveMethod = <u32>::from_stack(mem, esp + 16u32);
+            winapi::kernel32::SetFilePointer(
+                machine,
+                hFile,
+                lDistanceToMove,
+                lpDistanceToMoveHigh,
+                dwMoveMethod,
+            )
+            .to_raw()
+        }
+        pub unsafe fn SetHandleCount(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let uNumber = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::SetHandleCount(machine, uNumber).to_raw()
+        }
+        pub unsafe fn SetLastError(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let dwErrCode = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::SetLastError(machine, dwErrCode).to_raw()
+        }
+        pub unsafe fn SetPriorityClass(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hProcess = <HANDLE<()>>::from_stack(mem, esp + 4u32);
+            let dwPriorityClass = <u32>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::SetPriorityClass(machine, hProcess, dwPriorityClass).to_raw()
+        }
+        pub unsafe fn SetThreadContext(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hThread = <HTHREAD>::from_stack(mem, esp + 4u32);
+            let lpContext = <Option<&CONTEXT>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::SetThreadContext(machine, hThread, lpContext).to_raw()
+        }
+        pub unsafe fn SetThreadDescription(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hThread = <HTHREAD>::from_stack(mem, esp + 4u32);
+            let lpThreadDescription = <Option<&Str16>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::SetThreadDescription(machine, hThread, lpThreadDescription).to_raw()
+        }
+        pub unsafe fn SetThreadPriority(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hThread = <HTHREAD>::from_stack(mem, esp + 4u32);
+            let nPriority = <u32>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::SetThreadPriority(machine, hThread, nPriority).to_raw()
+        }
+        pub unsafe fn SetThreadStackGuarantee(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let StackSizeInBytes = <Option<&mut u32>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::SetThreadStackGuarantee(machine, StackSizeInBytes).to_raw()
+        }
+        pub unsafe fn SetUnhandledExceptionFilter(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpTopLevelExceptionFilter = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::SetUnhandledExceptionFilter(machine, lpTopLevelExceptionFilter)
+                .to_raw()
+        }
+        pub unsafe fn SetupComm(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
+            let dwInQueue = <u32>::from_stack(mem, esp + 8u32);
+            let dwOutQueue = <u32>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::SetupComm(machine, hFile, dwInQueue, dwOutQueue).to_raw()
+        }
+        pub unsafe fn Sleep(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let dwMilliseconds = <u32>::from_stack(mem, esp + 4u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::kernel32::Sleep(machine, dwMilliseconds).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 4u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::kernel32::Sleep(machine, dwMilliseconds));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn TlsAlloc(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::kernel32::TlsAlloc(machine).to_raw()
+        }
+        pub unsafe fn TlsFree(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let dwTlsIndex = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::TlsFree(machine, dwTlsIndex).to_raw()
+        }
+        pub unsafe fn TlsGetValue(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let dwTlsIndex = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::TlsGetValue(machine, dwTlsIndex).to_raw()
+        }
+        pub unsafe fn TlsSetValue(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let dwTlsIndex = <u32>::from_stack(mem, esp + 4u32);
+            let lpTlsValue = <u32>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::TlsSetValue(machine, dwTlsIndex, lpTlsValue).to_raw()
+        }
+        pub unsafe fn TryAcquireSRWLockExclusive(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let SRWLock = <Option<&mut SRWLOCK>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::TryAcquireSRWLockExclusive(machine, SRWLock).to_raw()
+        }
+        pub unsafe fn UnhandledExceptionFilter(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let _exceptionInfo = <u32>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::UnhandledExceptionFilter(machine, _exceptionInfo).to_raw()
+        }
+        pub unsafe fn VirtualAlloc(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpAddress = <u32>::from_stack(mem, esp + 4u32);
+            let dwSize = <u32>::from_stack(mem, esp + 8u32);
+            let flAllocationType = <u32>::from_stack(mem, esp + 12u32);
+            let flProtec = <u32>::from_stack(mem, esp + 16u32);
+            winapi::kernel32::VirtualAlloc(machine, lpAddress, dwSize, flAllocationType, flProtec)
+                .to_raw()
+        }
+        pub unsafe fn VirtualFree(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpAddress = <u32>::from_stack(mem, esp + 4u32);
+            let dwSize = <u32>::from_stack(mem, esp + 8u32);
+            let dwFreeType = <u32>::from_stack(mem, esp + 12u32);
+            winapi::kernel32::VirtualFree(machine, lpAddress, dwSize, dwFreeType).to_raw()
+        }
+        pub unsafe fn VirtualProtect(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpAddress = <u32>::from_stack(mem, esp + 4u32);
+            let dwSize = <u32>::from_stack(mem, esp + 8u32);
+            let flNewProtect = <u32>::from_stack(mem, esp + 12u32);
+            let lpflOldProtect = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            winapi::kernel32::VirtualProtect(
+                machine,
+                lpAddress,
+                dwSize,
+                flNewProtect,
+                lpflOldProtect,
+            )
+            .to_raw()
+        }
+        pub unsafe fn WaitForMultipleObjects(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let nCount = <u32>::from_stack(mem, esp + 4u32);
+            let lpHandles = <u32>::from_stack(mem, esp + 8u32);
+            let bWaitAll = <bool>::from_stack(mem, esp + 12u32);
+            let dwMilliseconds = <u32>::from_stack(mem, esp + 16u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::kernel32::WaitForMultipleObjects(
+                        machine,
+                        nCount,
+                        lpHandles,
+                        bWaitAll,
+                        dwMilliseconds,
+                    )
+                    .await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 16u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::kernel32::WaitForMultipleObjects(
+                    machine,
+                    nCount,
+                    lpHandles,
+                    bWaitAll,
+                    dwMilliseconds
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn WaitForSingleObject(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hHandle = <HEVENT>::from_stack(mem, esp + 4u32);
+            let dwMilliseconds = <u32>::from_stack(mem, esp + 8u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result =
+                        winapi::kernel32::WaitForSingleObject(machine, hHandle, dwMilliseconds)
+                            .await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 8u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::kernel32::WaitForSingleObject(
+                    machine,
+                    hHandle,
+                    dwMilliseconds
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn WriteConsoleA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hConsoleOutput = <HANDLE<()>>::from_stack(mem, esp + 4u32);
+            let lpBuffer = <ArrayWithSize<u8>>::from_stack(mem, esp + 8u32);
+            let lpNumberOfCharsWritten = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            let lpReserved = <u32>::from_stack(mem, esp + 20u32);
+            winapi::kernel32::WriteConsoleA(
+                machine,
+                hConsoleOutput,
+                lpBuffer,
+                lpNumberOfCharsWritten,
+                lpReserved,
+            )
+            .to_raw()
+        }
+        pub unsafe fn WriteConsoleW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hConsoleOutput = <HFILE>::from_stack(mem, esp + 4u32);
+            let lpBuffer = <ArrayWithSize<u16>>::from_stack(mem, esp + 8u32);
+            let lpNumberOfCharsWritten = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            let _lpReserved = <u32>::from_stack(mem, esp + 20u32);
+            winapi::kernel32::WriteConsoleW(
+                machine,
+                hConsoleOutput,
+                lpBuffer,
+                lpNumberOfCharsWritten,
+                _lpReserved,
+            )
+            .to_raw()
+        }
+        pub unsafe fn WriteFile(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
+            let lpBuffer = <ArrayWithSize<u8>>::from_stack(mem, esp + 8u32);
+            let lpNumberOfBytesWritten = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            let lpOverlapped = <u32>::from_stack(mem, esp + 20u32);
+            winapi::kernel32::WriteFile(
+                machine,
+                hFile,
+                lpBuffer,
+                lpNumberOfBytesWritten,
+                lpOverlapped,
+            )
+            .to_raw()
+        }
+        pub unsafe fn lstrcmpiA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpString1 = <Option<&str>>::from_stack(mem, esp + 4u32);
+            let lpString2 = <Option<&str>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::lstrcmpiA(machine, lpString1, lpString2).to_raw()
+        }
+        pub unsafe fn lstrcpyA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpString1 = <u32>::from_stack(mem, esp + 4u32);
+            let lpString2 = <Option<&str>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::lstrcpyA(machine, lpString1, lpString2).to_raw()
+        }
+        pub unsafe fn lstrcpyW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpString1 = <u32>::from_stack(mem, esp + 4u32);
+            let lpString2 = <Option<&Str16>>::from_stack(mem, esp + 8u32);
+            winapi::kernel32::lstrcpyW(machine, lpString1, lpString2).to_raw()
+        }
+        pub unsafe fn lstrlenA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpString = <Option<&str>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::lstrlenA(machine, lpString).to_raw()
+        }
+        pub unsafe fn lstrlenW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpString = <Option<&Str16>>::from_stack(mem, esp + 4u32);
+            winapi::kernel32::lstrlenW(machine, lpString).to_raw()
+        }
+        pub unsafe fn retrowin32_main(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let entry_point = <u32>::from_stack(mem, esp + 4u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::kernel32::retrowin32_main(machine, entry_point).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 4u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::kernel32::retrowin32_main(machine, entry_point));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn retrowin32_thread_main(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let entry_point = <u32>::from_stack(mem, esp + 4u32);
+            let param = <u32>::from_stack(mem, esp + 8u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result =
+                        winapi::kernel32::retrowin32_thread_main(machine, entry_point, param).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 8u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::kernel32::retrowin32_thread_main(
+                    machine,
+                    entry_point,
+                    param
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const AcquireSRWLockExclusive: Shim = Shim {
+            name: "AcquireSRWLockExclusive",
+            func: impls::AcquireSRWLockExclusive,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const AcquireSRWLockShared: Shim = Shim {
+            name: "AcquireSRWLockShared",
+            func: impls::AcquireSRWLockShared,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const AddVectoredExceptionHandler: Shim = Shim {
+            name: "AddVectoredExceptionHandler",
+            func: impls::AddVectoredExceptionHandler,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const ClearCommError: Shim = Shim {
+            name: "ClearCommError",
+            func: impls::ClearCommError,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const CloseHandle: Shim = Shim {
+            name: "CloseHandle",
+            func: impls::CloseHandle,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const CreateEventA: Shim = Shim {
+            name: "CreateEventA",
+            func: impls::CreateEventA,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const CreateFileA: Shim = Shim {
+            name: "CreateFileA",
+            func: impls::CreateFileA,
+            stack_consumed: 28u32,
+            is_async: false,
+        };
+        pub const CreateFileW: Shim = Shim {
+            name: "CreateFileW",
+            func: impls::CreateFileW,
+            stack_consumed: 28u32,
+            is_async: false,
+        };
+        pub const CreateThread: Shim = Shim {
+            name: "CreateThread",
+            func: impls::CreateThread,
+            stack_consumed: 24u32,
+            is_async: true,
+        };
+        pub const DeleteCriticalSection: Shim = Shim {
+            name: "DeleteCriticalSection",
+            func: impls::DeleteCriticalSection,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const EnterCriticalSection: Shim = Shim {
+            name: "EnterCriticalSection",
+            func: impls::EnterCriticalSection,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const EscapeCommFunction: Shim = Shim {
+            name: "EscapeCommFunction",
+            func: impls::EscapeCommFunction,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const ExitProcess: Shim = Shim {
+            name: "ExitProcess",
+            func: impls::ExitProcess,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const FindResourceA: Shim = Shim {
+            name: "FindResourceA",
+            func: impls::FindResourceA,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const FindResourceExA: Shim = Shim {
+            name: "FindResourceExA",
+            func: impls::FindResourceExA,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const FindResourceExW: Shim = Shim {
+            name: "FindResourceExW",
+            func: impls::FindResourceExW,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const FindResourceW: Shim = Shim {
+            name: "FindResourceW",
+            func: impls::FindResourceW,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const FlushInstructionCache: Shim = Shim {
+            name: "FlushInstructionCache",
+            func: impls::FlushInstructionCache,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const FormatMessageW: Shim = Shim {
+            name: "FormatMessageW",
+            func: impls::FormatMessageW,
+            stack_consumed: 28u32,
+            is_async: false,
+        };
+        pub const FreeEnvironmentStringsA: Shim = Shim {
+            name: "FreeEnvironmentStringsA",
+            func: impls::FreeEnvironmentStringsA,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const FreeLibrary: Shim = Shim {
+            name: "FreeLibrary",
+            func: impls::FreeLibrary,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetACP: Shim = Shim {
+            name: "GetACP",
+            func: impls::GetACP,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetCPInfo: Shim = Shim {
+            name: "GetCPInfo",
+            func: impls::GetCPInfo,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const GetCommModemStatus: Shim = Shim {
+            name: "GetCommModemStatus",
+            func: impls::GetCommModemStatus,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const GetCommState: Shim = Shim {
+            name: "GetCommState",
+            func: impls::GetCommState,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const GetCommTimeouts: Shim = Shim {
+            name: "GetCommTimeouts",
+            func: impls::GetCommTimeouts,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const GetCommandLineA: Shim = Shim {
+            name: "GetCommandLineA",
+            func: impls::GetCommandLineA,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetCommandLineW: Shim = Shim {
+            name: "GetCommandLineW",
+            func: impls::GetCommandLineW,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetComputerNameA: Shim = Shim {
+            name: "GetComputerNameA",
+            func: impls::GetComputerNameA,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const GetComputerNameW: Shim = Shim {
+            name: "GetComputerNameW",
+            func: impls::GetComputerNameW,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const GetConsoleMode: Shim = Shim {
+            name: "GetConsoleMode",
+            func: impls::GetConsoleMode,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const GetCurrentProcessId: Shim = Shim {
+            name: "GetCurrentProcessId",
+            func: impls::GetCurrentProcessId,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetCurrentThread: Shim = Shim {
+            name: "GetCurrentThread",
+            func: impls::GetCurrentThread,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetCurrentThreadId: Shim = Shim {
+            name: "GetCurrentThreadId",
+            func: impls::GetCurrentThreadId,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetEnvironmentStrings: Shim = Shim {
+            name: "GetEnvironmentStrings",
+            func: impls::GetEnvironmentStrings,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetEnvironmentStringsW: Shim = Shim {
+            name: "GetEnvironmentStringsW",
+            func: impls::GetEnvironmentStringsW,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetEnvironmentVariableA: Shim = Shim {
+            name: "GetEnvironmentVariableA",
+            func: impls::GetEnvironmentVariableA,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const GetEnvironmentVariableW: Shim = Shim {
+            name: "GetEnvironmentVariableW",
+            func: impls::GetEnvironmentVariableW,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const GetFileInformationByHandle: Shim = Shim {
+            name: "GetFileInformationByHandle",
+            func: impls::GetFileInformationByHandle,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const GetFileType: Shim = Shim {
+            name: "GetFileType",
+            func: impls::GetFileType,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetFullPathNameW: Shim = Shim {
+            name: "GetFullPathNameW",
+            func: impls::GetFullPathNameW,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const GetLastError: Shim = Shim {
+            name: "GetLastError",
+            func: impls::GetLastError,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetModuleFileNameA: Shim = Shim {
+            name: "GetModuleFileNameA",
+            func: impls::GetModuleFileNameA,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const GetModuleFileNameW: Shim = Shim {
+            name: "GetModuleFileNameW",
+            func: impls::GetModuleFileNameW,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const GetModuleHandleA: Shim = Shim {
+            name: "GetModuleHandleA",
+            func: impls::GetModuleHandleA,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetModuleHandleExW: Shim = Shim {
+            name: "GetModuleHandleExW",
+            func: impls::GetModuleHandleExW,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const GetModuleHandleW: Shim = Shim {
+            name: "GetModuleHandleW",
+            func: impls::GetModuleHandleW,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetPrivateProfileIntW: Shim = Shim {
+            name: "GetPrivateProfileIntW",
+            func: impls::GetPrivateProfileIntW,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const GetPrivateProfileStringW: Shim = Shim {
+            name: "GetPrivateProfileStringW",
+            func: impls::GetPrivateProfileStringW,
+            stack_consumed: 24u32,
+            is_async: false,
+        };
+        pub const GetProcAddress: Shim = Shim {
+            name: "GetProcAddress",
+            func: impls::GetProcAddress,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const GetProcessHeap: Shim = Shim {
+            name: "GetProcessHeap",
+            func: impls::GetProcessHeap,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetStartupInfoA: Shim = Shim {
+            name: "GetStartupInfoA",
+            func: impls::GetStartupInfoA,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetStartupInfoW: Shim = Shim {
+            name: "GetStartupInfoW",
+            func: impls::GetStartupInfoW,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetStdHandle: Shim = Shim {
+            name: "GetStdHandle",
+            func: impls::GetStdHandle,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetSystemDefaultLangID: Shim = Shim {
+            name: "GetSystemDefaultLangID",
+            func: impls::GetSystemDefaultLangID,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetSystemDefaultUILanguage: Shim = Shim {
+            name: "GetSystemDefaultUILanguage",
+            func: impls::GetSystemDefaultUILanguage,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetSystemInfo: Shim = Shim {
+            name: "GetSystemInfo",
+            func: impls::GetSystemInfo,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetSystemTimeAsFileTime: Shim = Shim {
+            name: "GetSystemTimeAsFileTime",
+            func: impls::GetSystemTimeAsFileTime,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GetThreadContext: Shim = Shim {
+            name: "GetThreadContext",
+            func: impls::GetThreadContext,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const GetTickCount: Shim = Shim {
+            name: "GetTickCount",
+            func: impls::GetTickCount,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetUserDefaultLangID: Shim = Shim {
+            name: "GetUserDefaultLangID",
+            func: impls::GetUserDefaultLangID,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetUserDefaultUILanguage: Shim = Shim {
+            name: "GetUserDefaultUILanguage",
+            func: impls::GetUserDefaultUILanguage,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetVersion: Shim = Shim {
+            name: "GetVersion",
+            func: impls::GetVersion,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const GetVersionExA: Shim = Shim {
+            name: "GetVersionExA",
+            func: impls::GetVersionExA,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const GlobalAlloc: Shim = Shim {
+            name: "GlobalAlloc",
+            func: impls::GlobalAlloc,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const GlobalFree: Shim = Shim {
+            name: "GlobalFree",
+            func: impls::GlobalFree,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const HeapAlloc: Shim = Shim {
+            name: "HeapAlloc",
+            func: impls::HeapAlloc,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const HeapCreate: Shim = Shim {
+            name: "HeapCreate",
+            func: impls::HeapCreate,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const HeapDestroy: Shim = Shim {
+            name: "HeapDestroy",
+            func: impls::HeapDestroy,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const HeapFree: Shim = Shim {
+            name: "HeapFree",
+            func: impls::HeapFree,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const HeapReAlloc: Shim = Shim {
+            name: "HeapReAlloc",
+            func: impls::HeapReAlloc,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const HeapSetInformation: Shim = Shim {
+            name: "HeapSetInformation",
+            func: impls::HeapSetInformation,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const HeapSize: Shim = Shim {
+            name: "HeapSize",
+            func: impls::HeapSize,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const InitOnceBeginInitialize: Shim = Shim {
+            name: "InitOnceBeginInitialize",
+            func: impls::InitOnceBeginInitialize,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const InitOnceComplete: Shim = Shim {
+            name: "InitOnceComplete",
+            func: impls::InitOnceComplete,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const InitializeCriticalSection: Shim = Shim {
+            name: "InitializeCriticalSection",
+            func: impls::InitializeCriticalSection,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const InitializeCriticalSectionAndSpinCount: Shim = Shim {
+            name: "InitializeCriticalSectionAndSpinCount",
+            func: impls::InitializeCriticalSectionAndSpinCount,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const InitializeCriticalSectionEx: Shim = Shim {
+            name: "InitializeCriticalSectionEx",
+            func: impls::InitializeCriticalSectionEx,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const InitializeSListHead: Shim = Shim {
+            name: "InitializeSListHead",
+            func: impls::InitializeSListHead,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const InterlockedIncrement: Shim = Shim {
+            name: "InterlockedIncrement",
+            func: impls::InterlockedIncrement,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const IsBadReadPtr: Shim = Shim {
+            name: "IsBadReadPtr",
+            func: impls::IsBadReadPtr,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const IsBadWritePtr: Shim = Shim {
+            name: "IsBadWritePtr",
+            func: impls::IsBadWritePtr,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const IsDBCSLeadByte: Shim = Shim {
+            name: "IsDBCSLeadByte",
+            func: impls::IsDBCSLeadByte,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const IsDebuggerPresent: Shim = Shim {
+            name: "IsDebuggerPresent",
+            func: impls::IsDebuggerPresent,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const IsProcessorFeaturePresent: Shim = Shim {
+            name: "IsProcessorFeaturePresent",
+            func: impls::IsProcessorFeaturePresent,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const IsValidCodePage: Shim = Shim {
+            name: "IsValidCodePage",
+            func: impls::IsValidCodePage,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const LeaveCriticalSection: Shim = Shim {
+            name: "LeaveCriticalSection",
+            func: impls::LeaveCriticalSection,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const LoadLibraryA: Shim = Shim {
+            name: "LoadLibraryA",
+            func: impls::LoadLibraryA,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const LoadLibraryExW: Shim = Shim {
+            name: "LoadLibraryExW",
+            func: impls::LoadLibraryExW,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const LoadResource: Shim = Shim {
+            name: "LoadResource",
+            func: impls::LoadResource,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const LocalAlloc: Shim = Shim {
+            name: "LocalAlloc",
+            func: impls::LocalAlloc,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const LockResource: Shim = Shim {
+            name: "LockResource",
+            func: impls::LockResource,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const MultiByteToWideChar: Shim = Shim {
+            name: "MultiByteToWideChar",
+            func: impls::MultiByteToWideChar,
+            stack_consumed: 24u32,
+            is_async: false,
+        };
+        pub const NtCurrentTeb: Shim = Shim {
+            name: "NtCurrentTeb",
+            func: impls::NtCurrentTeb,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const OutputDebugStringA: Shim = Shim {
+            name: "OutputDebugStringA",
+            func: impls::OutputDebugStringA,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const OutputDebugStringW: Shim = Shim {
+            name: "OutputDebugStringW",
+            func: impls::OutputDebugStringW,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const PurgeComm: Shim = Shim {
+            name: "PurgeComm",
+            func: impls::PurgeComm,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const QueryPerformanceCounter: Shim = Shim {
+            name: "QueryPerformanceCounter",
+            func: impls::QueryPerformanceCounter,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const QueryPerformanceFrequency: Shim = Shim {
+            name: "QueryPerformanceFrequency",
+            func: impls::QueryPerformanceFrequency,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const RaiseException: Shim = Shim {
+            name: "RaiseException",
+            func: impls::RaiseException,
+            stack_consumed: 16u32,
+            is_async: true,
+        };
+        pub const ReadFile: Shim = Shim {
+            name: "ReadFile",
+            func: impls::ReadFile,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const ReleaseSRWLockExclusive: Shim = Shim {
+            name: "ReleaseSRWLockExclusive",
+            func: impls::ReleaseSRWLockExclusive,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const ReleaseSRWLockShared: Shim = Shim {
+            name: "ReleaseSRWLockShared",
+            func: impls::ReleaseSRWLockShared,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const ResetEvent: Shim = Shim {
+            name: "ResetEvent",
+            func: impls::ResetEvent,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const RtlUnwind: Shim = Shim {
+            name: "RtlUnwind",
+            func: impls::RtlUnwind,
+            stack_consumed: 16u32,
+            is_async: true,
+        };
+        pub const SetCommState: Shim = Shim {
+            name: "SetCommState",
+            func: impls::SetCommState,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const SetCommTimeouts: Shim = Shim {
+            name: "SetCommTimeouts",
+            func: impls::SetCommTimeouts,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const SetEvent: Shim = Shim {
+            name: "SetEvent",
+            func: impls::SetEvent,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const SetFilePointer: Shim = Shim {
+            name: "SetFilePointer",
+            func: impls::SetFilePointer,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const SetHandleCount: Shim = Shim {
+            name: "SetHandleCount",
+            func: impls::SetHandleCount,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const SetLastError: Shim = Shim {
+            name: "SetLastError",
+            func: impls::SetLastError,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const SetPriorityClass: Shim = Shim {
+            name: "SetPriorityClass",
+            func: impls::SetPriorityClass,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const SetThreadContext: Shim = Shim {
+            name: "SetThreadContext",
+            func: impls::SetThreadContext,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const SetThreadDescription: Shim = Shim {
+            name: "SetThreadDescription",
+            func: impls::SetThreadDescription,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const SetThreadPriority: Shim = Shim {
+            name: "SetThreadPriority",
+            func: impls::SetThreadPriority,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const SetThreadStackGuarantee: Shim = Shim {
+            name: "SetThreadStackGuarantee",
+            func: impls::SetThreadStackGuarantee,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const SetUnhandledExceptionFilter: Shim = Shim {
+            name: "SetUnhandledExceptionFilter",
+            func: impls::SetUnhandledExceptionFilter,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const SetupComm: Shim = Shim {
+            name: "SetupComm",
+            func: impls::SetupComm,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const Sleep: Shim = Shim {
+            name: "Sleep",
+            func: impls::Sleep,
+            stack_consumed: 4u32,
+            is_async: true,
+        };
+        pub const TlsAlloc: Shim = Shim {
+            name: "TlsAlloc",
+            func: impls::TlsAlloc,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const TlsFree: Shim = Shim {
+            name: "TlsFree",
+            func: impls::TlsFree,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const TlsGetValue: Shim = Shim {
+            name: "TlsGetValue",
+            func: impls::TlsGetValue,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const TlsSetValue: Shim = Shim {
+            name: "TlsSetValue",
+            func: impls::TlsSetValue,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const TryAcquireSRWLockExclusive: Shim = Shim {
+            name: "TryAcquireSRWLockExclusive",
+            func: impls::TryAcquireSRWLockExclusive,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const UnhandledExceptionFilter: Shim = Shim {
+            name: "UnhandledExceptionFilter",
+            func: impls::UnhandledExceptionFilter,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const VirtualAlloc: Shim = Shim {
+            name: "VirtualAlloc",
+            func: impls::VirtualAlloc,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const VirtualFree: Shim = Shim {
+            name: "VirtualFree",
+            func: impls::VirtualFree,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const VirtualProtect: Shim = Shim {
+            name: "VirtualProtect",
+            func: impls::VirtualProtect,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const WaitForMultipleObjects: Shim = Shim {
+            name: "WaitForMultipleObjects",
+            func: impls::WaitForMultipleObjects,
+            stack_consumed: 16u32,
+            is_async: true,
+        };
+        pub const WaitForSingleObject: Shim = Shim {
+            name: "WaitForSingleObject",
+            func: impls::WaitForSingleObject,
+            stack_consumed: 8u32,
+            is_async: true,
+        };
+        pub const WriteConsoleA: Shim = Shim {
+            name: "WriteConsoleA",
+            func: impls::WriteConsoleA,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const WriteConsoleW: Shim = Shim {
+            name: "WriteConsoleW",
+            func: impls::WriteConsoleW,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const WriteFile: Shim = Shim {
+            name: "WriteFile",
+            func: impls::WriteFile,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const lstrcmpiA: Shim = Shim {
+            name: "lstrcmpiA",
+            func: impls::lstrcmpiA,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const lstrcpyA: Shim = Shim {
+            name: "lstrcpyA",
+            func: impls::lstrcpyA,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const lstrcpyW: Shim = Shim {
+            name: "lstrcpyW",
+            func: impls::lstrcpyW,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const lstrlenA: Shim = Shim {
+            name: "lstrlenA",
+            func: impls::lstrlenA,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const lstrlenW: Shim = Shim {
+            name: "lstrlenW",
+            func: impls::lstrlenW,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const retrowin32_main: Shim = Shim {
+            name: "retrowin32_main",
+            func: impls::retrowin32_main,
+            stack_consumed: 4u32,
+            is_async: true,
+        };
+        pub const retrowin32_thread_main: Shim = Shim {
+            name: "retrowin32_thread_main",
+            func: impls::retrowin32_thread_main,
+            stack_consumed: 8u32,
+            is_async: true,
+        };
+    }
+    const EXPORTS: [Symbol; 140usize] = [
+        Symbol {
+            ordinal: None,
+            shim: shims::AcquireSRWLockExclusive,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AcquireSRWLockShared,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::AddVectoredExceptionHandler,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ClearCommError,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::CloseHandle,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::CreateEventA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::CreateFileA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::CreateFileW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::CreateThread,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::DeleteCriticalSection,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::EnterCriticalSection,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::EscapeCommFunction,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ExitProcess,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::FindResourceA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::FindResourceExA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::FindResourceExW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::FindResourceW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::FlushInstructionCache,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::FormatMessageW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::FreeEnvironmentStringsA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::FreeLibrary,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetACP,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetCPInfo,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetCommModemStatus,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetCommState,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetCommTimeouts,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetCommandLineA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetCommandLineW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetComputerNameA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetComputerNameW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetConsoleMode,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetCurrentProcessId,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetCurrentThread,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetCurrentThreadId,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetEnvironmentStrings,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetEnvironmentStringsW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetEnvironmentVariableA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetEnvironmentVariableW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetFileInformationByHandle,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetFileType,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetFullPathNameW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetLastError,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetModuleFileNameA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetModuleFileNameW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetModuleHandleA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetModuleHandleExW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetModuleHandleW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetPrivateProfileIntW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetPrivateProfileStringW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetProcAddress,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetProcessHeap,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetStartupInfoA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetStartupInfoW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetStdHandle,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetSystemDefaultLangID,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetSystemDefaultUILanguage,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetSystemInfo,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetSystemTimeAsFileTime,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetThreadContext,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetTickCount,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetUserDefaultLangID,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetUserDefaultUILanguage,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetVersion,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GetVersionExA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GlobalAlloc,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::GlobalFree,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::HeapAlloc,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::HeapCreate,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::HeapDestroy,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::HeapFree,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::HeapReAlloc,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::HeapSetInformation,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::HeapSize,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::InitOnceBeginInitialize,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::InitOnceComplete,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::InitializeCriticalSection,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::InitializeCriticalSectionAndSpinCount,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::InitializeCriticalSectionEx,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::InitializeSListHead,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::InterlockedIncrement,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::IsBadReadPtr,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::IsBadWritePtr,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::IsDBCSLeadByte,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::IsDebuggerPresent,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::IsProcessorFeaturePresent,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::IsValidCodePage,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LeaveCriticalSection,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LoadLibraryA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LoadLibraryExW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LoadResource,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LocalAlloc,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LockResource,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::MultiByteToWideChar,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::NtCurrentTeb,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::OutputDebugStringA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::OutputDebugStringW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::PurgeComm,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::QueryPerformanceCounter,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::QueryPerformanceFrequency,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RaiseException,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ReadFile,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ReleaseSRWLockExclusive,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ReleaseSRWLockShared,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ResetEvent,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RtlUnwind,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetCommState,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetCommTimeouts,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetEvent,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetFilePointer,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetHandleCount,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetLastError,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetPriorityClass,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetThreadContext,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetThreadDescription,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetThreadPriority,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetThreadStackGuarantee,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetUnhandledExceptionFilter,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetupComm,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::Sleep,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::TlsAlloc,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::TlsFree,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::TlsGetValue,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::TlsSetValue,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::TryAcquireSRWLockExclusive,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::UnhandledExceptionFilter,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::VirtualAlloc,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::VirtualFree,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::VirtualProtect,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::WaitForMultipleObjects,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::WaitForSingleObject,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::WriteConsoleA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::WriteConsoleW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::WriteFile,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::lstrcmpiA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::lstrcpyA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::lstrcpyW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::lstrlenA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::lstrlenW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::retrowin32_main,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::retrowin32_thread_main,
+        },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "kernel32.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod lz32 {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::lz32::*;
+        pub unsafe fn GetExpandedNameA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpszSource = <Option<&str>>::from_stack(mem, esp + 4u32);
+            let lpszBuffer = <u32>::from_stack(mem, esp + 8u32);
+            winapi::lz32::GetExpandedNameA(machine, lpszSource, lpszBuffer).to_raw()
+        }
+        pub unsafe fn LZClose(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <i32>::from_stack(mem, esp + 4u32);
+            winapi::lz32::LZClose(machine, hFile).to_raw()
+        }
+        pub unsafe fn LZCopy(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hfSource = <i32>::from_stack(mem, esp + 4u32);
+            let hfDest = <i32>::from_stack(mem, esp + 8u32);
+            winapi::lz32::LZCopy(machine, hfSource, hfDest).to_raw()
+        }
+        pub unsafe fn LZInit(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hfSource = <HFILE>::from_stack(mem, esp + 4u32);
+            winapi::lz32::LZInit(machine, hfSource).to_raw()
+        }
+        pub unsafe fn LZOpenFileA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpFileName = <Option<&str>>::from_stack(mem, esp + 4u32);
+            let lpReOpenBuf = <Option<&mut OFSTRUCT>>::from_stack(mem, esp + 8u32);
+            let wStyle = <u32>::from_stack(mem, esp + 12u32);
+            winapi::lz32::LZOpenFileA(machine, lpFileName, lpReOpenBuf, wStyle).to_raw()
+        }
+        pub unsafe fn LZRead(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <i32>::from_stack(mem, esp + 4u32);
+            let lpBuffer = <u32>::from_stack(mem, esp + 8u32);
+            let cbRead = <i32>::from_stack(mem, esp + 12u32);
+            winapi::lz32::LZRead(machine, hFile, lpBuffer, cbRead).to_raw()
+        }
+        pub unsafe fn LZSeek(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hFile = <i32>::from_stack(mem, esp + 4u32);
+            let lOffset = <i32>::from_stack(mem, esp + 8u32);
+            let iOrigin = <i32>::from_stack(mem, esp + 12u32);
+            winapi::lz32::LZSeek(machine, hFile, lOffset, iOrigin).to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const GetExpandedNameA: Shim = Shim {
+            name: "GetExpandedNameA",
+            func: impls::GetExpandedNameA,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const LZClose: Shim = Shim {
+            name: "LZClose",
+            func: impls::LZClose,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const LZCopy: Shim = Shim {
+            name: "LZCopy",
+            func: impls::LZCopy,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const LZInit: Shim = Shim {
+            name: "LZInit",
+            func: impls::LZInit,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const LZOpenFileA: Shim = Shim {
+            name: "LZOpenFileA",
+            func: impls::LZOpenFileA,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const LZRead: Shim = Shim {
+            name: "LZRead",
+            func: impls::LZRead,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const LZSeek: Shim = Shim {
+            name: "LZSeek",
+            func: impls::LZSeek,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 7usize] = [
+        Symbol {
+            ordinal: None,
+            shim: shims::GetExpandedNameA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LZClose,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LZCopy,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LZInit,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LZOpenFileA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LZRead,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LZSeek,
+        },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "lz32.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod msvcrt {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::msvcrt::*;
+        pub unsafe fn _CIacos(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::msvcrt::_CIacos(machine).to_raw()
+        }
+        pub unsafe fn _CIasin(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::msvcrt::_CIasin(machine).to_raw()
+        }
+        pub unsafe fn _CIatan(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::msvcrt::_CIatan(machine).to_raw()
+        }
+        pub unsafe fn _CIatan2(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::msvcrt::_CIatan2(machine).to_raw()
+        }
+        pub unsafe fn _CIcos(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let name = <Option<&Str16>>::from_stack(mem, esp + 4u32);
-            let buf = <ArrayWithSize<u16>>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::GetEnvironmentVariableW(machine, name, buf).to_raw()
+            winapi::msvcrt::_CIcos(machine).to_raw()
         }
-        pub unsafe fn GetFileInformationByHandle(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn _CIexp(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
-            let lpFileInformation =
-                <Option<&mut BY_HANDLE_FILE_INFORMATION>>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::GetFileInformationByHandle(machine, hFile, lpFileInformation).to_raw()
+            winapi::msvcrt::_CIexp(machine).to_raw()
         }
-        pub unsafe fn GetFileType(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn _CIfmod(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::GetFileType(machine, hFile).to_raw()
+            winapi::msvcrt::_CIfmod(machine).to_raw()
         }
-        pub unsafe fn GetFullPathNameW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn _CIlog(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpFileName = <Option<&Str16>>::from_stack(mem, esp + 4u32);
-            let nBufferLength = <u32>::from_stack(mem, esp + 8u32);
-            let lpBuffer = <u32>::from_stack(mem, esp + 12u32);
-            let lpFilePart = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
-            winapi::kernel32::GetFullPathNameW(
-                machine,
-                lpFileName,
-                nBufferLength,
-                lpBuffer,
-                lpFilePart,
-            )
-            .to_raw()
+            winapi::msvcrt::_CIlog(machine).to_raw()
         }
-        pub unsafe fn GetLastError(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn _CIlog10(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::GetLastError(machine).to_raw()
+            winapi::msvcrt::_CIlog10(machine).to_raw()
         }
-        pub unsafe fn GetModuleFileNameA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn _CIpow(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hModule = <HMODULE>::from_stack(mem, esp + 4u32);
-            let filename = <ArrayWithSizeMut<u8>>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::GetModuleFileNameA(machine, hModule, filename).to_raw()
+            winapi::msvcrt::_CIpow(machine).to_raw()
         }
-        pub unsafe fn GetModuleFileNameW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn _CIsin(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hModule = <HMODULE>::from_stack(mem, esp + 4u32);
-            let _lpFilename = <u32>::from_stack(mem, esp + 8u32);
-            let _nSize = <u32>::from_stack(mem, esp + 12u32);
-            winapi::kernel32::GetModuleFileNameW(machine, hModule, _lpFilename, _nSize).to_raw()
+            winapi::msvcrt::_CIsin(machine).to_raw()
         }
-        pub unsafe fn GetModuleHandleA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn _CIsqrt(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpModuleName = <Option<&str>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::GetModuleHandleA(machine, lpModuleName).to_raw()
+            winapi::msvcrt::_CIsqrt(machine).to_raw()
         }
-        pub unsafe fn GetModuleHandleExW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn _CItan(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let dwFlags = <u32>::from_stack(mem, esp + 4u32);
-            let lpModuleName = <Option<&Str16>>::from_stack(mem, esp + 8u32);
-            let hModule = <Option<&mut HMODULE>>::from_stack(mem, esp + 12u32);
-            winapi::kernel32::GetModuleHandleExW(machine, dwFlags, lpModuleName, hModule).to_raw()
+            winapi::msvcrt::_CItan(machine).to_raw()
         }
-        pub unsafe fn GetModuleHandleW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn _XcptFilter(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpModuleName = <Option<&Str16>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::GetModuleHandleW(machine, lpModuleName).to_raw()
+            let xcptnum = <u32>::from_stack(mem, esp + 4u32);
+            let pxcptinfoptrs = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::_XcptFilter(machine, xcptnum, pxcptinfoptrs).to_raw()
         }
-        pub unsafe fn GetPrivateProfileIntW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn __getmainargs(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpAppName = <Option<&Str16>>::from_stack(mem, esp + 4u32);
-            let lpKeyName = <Option<&Str16>>::from_stack(mem, esp + 8u32);
-            let nDefault = <u32>::from_stack(mem, esp + 12u32);
-            let lpFileName = <Option<&Str16>>::from_stack(mem, esp + 16u32);
-            winapi::kernel32::GetPrivateProfileIntW(
-                machine, lpAppName, lpKeyName, nDefault, lpFileName,
-            )
-            .to_raw()
+            let argc = <u32>::from_stack(mem, esp + 4u32);
+            let argv = <u32>::from_stack(mem, esp + 8u32);
+            let env = <u32>::from_stack(mem, esp + 12u32);
+            let doWildCard = <u32>::from_stack(mem, esp + 16u32);
+            let startInfo = <u32>::from_stack(mem, esp + 20u32);
+            winapi::msvcrt::__getmainargs(machine, argc, argv, env, doWildCard, startInfo).to_raw()
         }
-        pub unsafe fn GetPrivateProfileStringW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn __iob_func(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpAppName = <Option<&Str16>>::from_stack(mem, esp + 4u32);
-            let lpKeyName = <Option<&Str16>>::from_stack(mem, esp + 8u32);
-            let lpDefault = <Option<&Str16>>::from_stack(mem, esp + 12u32);
-            let lpReturnedString = <ArrayWithSizeMut<u16>>::from_stack(mem, esp + 16u32);
-            let lpFileName = <Option<&Str16>>::from_stack(mem, esp + 24u32);
-            winapi::kernel32::GetPrivateProfileStringW(
-                machine,
-                lpAppName,
-                lpKeyName,
-                lpDefault,
-                lpReturnedString,
-                lpFileName,
-            )
-            .to_raw()
+            winapi::msvcrt::__iob_func(machine).to_raw()
         }
-        pub unsafe fn GetProcAddress(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn __p___argc(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hModule = <HMODULE>::from_stack(mem, esp + 4u32);
-            let lpProcName = <GetProcAddressArg>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::GetProcAddress(machine, hModule, lpProcName).to_raw()
+            winapi::msvcrt::__p___argc(machine).to_raw()
         }
-        pub unsafe fn GetProcessHeap(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn __p___argv(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::GetProcessHeap(machine).to_raw()
+            winapi::msvcrt::__p___argv(machine).to_raw()
         }
-        pub unsafe fn GetStartupInfoA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn __p__acmdln(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpStartupInfo = <Option<&mut STARTUPINFOA>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::GetStartupInfoA(machine, lpStartupInfo).to_raw()
+            winapi::msvcrt::__p__acmdln(machine).to_raw()
         }
-        pub unsafe fn GetStartupInfoW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn __p__commode(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpStartupInfo = <Option<&mut STARTUPINFOA>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::GetStartupInfoW(machine, lpStartupInfo).to_raw()
+            winapi::msvcrt::__p__commode(machine).to_raw()
         }
-        pub unsafe fn GetStdHandle(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn __p__environ(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let nStdHandle = <Result<STD, u32>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::GetStdHandle(machine, nStdHandle).to_raw()
+            winapi::msvcrt::__p__environ(machine).to_raw()
         }
-        pub unsafe fn GetSystemTimeAsFileTime(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn __p__fmode(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let _time = <Option<&mut FILETIME>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::GetSystemTimeAsFileTime(machine, _time).to_raw()
+            winapi::msvcrt::__p__fmode(machine).to_raw()
         }
-        pub unsafe fn GetTickCount(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn __set_app_type(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::GetTickCount(machine).to_raw()
+            let at = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::__set_app_type(machine, at).to_raw()
+        }
+        pub unsafe fn __setusermatherr(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pf = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::__setusermatherr(machine, pf).to_raw()
+        }
+        pub unsafe fn _amsg_exit(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let rterrnum = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::_amsg_exit(machine, rterrnum).to_raw()
+        }
+        pub unsafe fn _cexit(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::msvcrt::_cexit(machine).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 0u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::msvcrt::_cexit(machine,));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn _control87(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let new = <u32>::from_stack(mem, esp + 4u32);
+            let mask = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::_control87(machine, new, mask).to_raw()
+        }
+        pub unsafe fn _controlfp(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let new = <u32>::from_stack(mem, esp + 4u32);
+            let mask = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::_controlfp(machine, new, mask).to_raw()
+        }
+        pub unsafe fn _errno(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::msvcrt::_errno(machine).to_raw()
+        }
+        pub unsafe fn _exit(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let status = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::_exit(machine, status).to_raw()
+        }
+        pub unsafe fn _finite(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::_finite(machine, x).to_raw()
+        }
+        pub unsafe fn _ftol(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::msvcrt::_ftol(machine).to_raw()
+        }
+        pub unsafe fn _initterm(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let start = <u32>::from_stack(mem, esp + 4u32);
+            let end = <u32>::from_stack(mem, esp + 8u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::msvcrt::_initterm(machine, start, end).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 0u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::msvcrt::_initterm(machine, start, end));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn _initterm_e(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let start = <u32>::from_stack(mem, esp + 4u32);
+            let end = <u32>::from_stack(mem, esp + 8u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::msvcrt::_initterm_e(machine, start, end).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 0u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::msvcrt::_initterm_e(machine, start, end));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn _isnan(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::_isnan(machine, x).to_raw()
+        }
+        pub unsafe fn _itoa(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let value = <i32>::from_stack(mem, esp + 4u32);
+            let buffer = <u32>::from_stack(mem, esp + 8u32);
+            let radix = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::_itoa(machine, value, buffer, radix).to_raw()
+        }
+        pub unsafe fn _ltoa(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let value = <i32>::from_stack(mem, esp + 4u32);
+            let buffer = <u32>::from_stack(mem, esp + 8u32);
+            let radix = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::_ltoa(machine, value, buffer, radix).to_raw()
+        }
+        pub unsafe fn _msize(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let ptr = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::_msize(machine, ptr).to_raw()
+        }
+        pub unsafe fn _onexit(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let func = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::_onexit(machine, func).to_raw()
+        }
+        pub unsafe fn _snprintf(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let buffer = <u32>::from_stack(mem, esp + 4u32);
+            let count = <u32>::from_stack(mem, esp + 8u32);
+            let format = <u32>::from_stack(mem, esp + 12u32);
+            let args = <VarArgs>::from_stack(mem, esp + 16u32);
+            winapi::msvcrt::_snprintf(machine, buffer, count, format, args).to_raw()
+        }
+        pub unsafe fn _strcmpi(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let string1 = <u32>::from_stack(mem, esp + 4u32);
+            let string2 = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::_strcmpi(machine, string1, string2).to_raw()
+        }
+        pub unsafe fn _strdup(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let strSource = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::_strdup(machine, strSource).to_raw()
+        }
+        pub unsafe fn _stricmp(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let string1 = <u32>::from_stack(mem, esp + 4u32);
+            let string2 = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::_stricmp(machine, string1, string2).to_raw()
+        }
+        pub unsafe fn _strlwr(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let str = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::_strlwr(machine, str).to_raw()
+        }
+        pub unsafe fn _strnicmp(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let string1 = <u32>::from_stack(mem, esp + 4u32);
+            let string2 = <u32>::from_stack(mem, esp + 8u32);
+            let count = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::_strnicmp(machine, string1, string2, count).to_raw()
+        }
+        pub unsafe fn _strupr(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let str = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::_strupr(machine, str).to_raw()
+        }
+        pub unsafe fn _vsnprintf(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let buffer = <u32>::from_stack(mem, esp + 4u32);
+            let count = <u32>::from_stack(mem, esp + 8u32);
+            let format = <u32>::from_stack(mem, esp + 12u32);
+            let argptr = <u32>::from_stack(mem, esp + 16u32);
+            winapi::msvcrt::_vsnprintf(machine, buffer, count, format, argptr).to_raw()
+        }
+        pub unsafe fn abort(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::msvcrt::abort(machine).to_raw()
+        }
+        pub unsafe fn abs(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let n = <i32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::abs(machine, n).to_raw()
+        }
+        pub unsafe fn acos(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::acos(machine, x).to_raw()
+        }
+        pub unsafe fn asin(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::asin(machine, x).to_raw()
+        }
+        pub unsafe fn atan(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::atan(machine, x).to_raw()
+        }
+        pub unsafe fn atan2(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let y = <f64>::from_stack(mem, esp + 4u32);
+            let x = <f64>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::atan2(machine, y, x).to_raw()
+        }
+        pub unsafe fn atexit(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let func = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::atexit(machine, func).to_raw()
+        }
+        pub unsafe fn atof(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let str = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::atof(machine, str).to_raw()
+        }
+        pub unsafe fn atoi(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let str = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::atoi(machine, str).to_raw()
+        }
+        pub unsafe fn atol(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let str = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::atol(machine, str).to_raw()
+        }
+        pub unsafe fn bsearch(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let key = <u32>::from_stack(mem, esp + 4u32);
+            let base = <u32>::from_stack(mem, esp + 8u32);
+            let num = <u32>::from_stack(mem, esp + 12u32);
+            let width = <u32>::from_stack(mem, esp + 16u32);
+            let compare = <u32>::from_stack(mem, esp + 20u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result =
+                        winapi::msvcrt::bsearch(machine, key, base, num, width, compare).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 0u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::msvcrt::bsearch(
+                    machine, key, base, num, width, compare
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn calloc(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let count = <u32>::from_stack(mem, esp + 4u32);
+            let size = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::calloc(machine, count, size).to_raw()
+        }
+        pub unsafe fn ceil(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::ceil(machine, x).to_raw()
+        }
+        pub unsafe fn clearerr(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let stream = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::clearerr(machine, stream).to_raw()
+        }
+        pub unsafe fn cos(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::cos(machine, x).to_raw()
+        }
+        pub unsafe fn exit(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let status = <u32>::from_stack(mem, esp + 4u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::msvcrt::exit(machine, status).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 0u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::msvcrt::exit(machine, status));
+                crate::shims::call_sync(pin).to_raw()
+            }
         }
-        pub unsafe fn GetVersion(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn exp(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::GetVersion(machine).to_raw()
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::exp(machine, x).to_raw()
         }
-        pub unsafe fn GetVersionExA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn fabs(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpVersionInformation = <Option<&mut OSVERSIONINFO>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::GetVersionExA(machine, lpVersionInformation).to_raw()
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::fabs(machine, x).to_raw()
         }
-        pub unsafe fn GlobalAlloc(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn fclose(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let uFlags = <GMEM>::from_stack(mem, esp + 4u32);
-            let dwBytes = <u32>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::GlobalAlloc(machine, uFlags, dwBytes).to_raw()
+            let stream = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::fclose(machine, stream).to_raw()
         }
-        pub unsafe fn GlobalFree(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn feof(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hMem = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::GlobalFree(machine, hMem).to_raw()
+            let stream = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::feof(machine, stream).to_raw()
         }
-        pub unsafe fn HeapAlloc(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn ferror(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hHeap = <u32>::from_stack(mem, esp + 4u32);
-            let dwFlags = <Result<HeapAllocFlags, u32>>::from_stack(mem, esp + 8u32);
-            let dwBytes = <u32>::from_stack(mem, esp + 12u32);
-            winapi::kernel32::HeapAlloc(machine, hHeap, dwFlags, dwBytes).to_raw()
+            let stream = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::ferror(machine, stream).to_raw()
         }
-        pub unsafe fn HeapCreate(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn fflush(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let flOptions = <Result<HeapCreateFlags, u32>>::from_stack(mem, esp + 4u32);
-            let dwInitialSize = <u32>::from_stack(mem, esp + 8u32);
-            let dwMaximumSize = <u32>::from_stack(mem, esp + 12u32);
-            winapi::kernel32::HeapCreate(machine, flOptions, dwInitialSize, dwMaximumSize).to_raw()
+            let stream = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::fflush(machine, stream).to_raw()
         }
-        pub unsafe fn HeapDestroy(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn fgetc(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hHeap = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::HeapDestroy(machine, hHeap).to_raw()
+            let stream = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::fgetc(machine, stream).to_raw()
         }
-        pub unsafe fn HeapFree(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn fgets(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hHeap = <u32>::from_stack(mem, esp + 4u32);
-            let dwFlags = <u32>::from_stack(mem, esp + 8u32);
-            let lpMem = <u32>::from_stack(mem, esp + 12u32);
-            winapi::kernel32::HeapFree(machine, hHeap, dwFlags, lpMem).to_raw()
+            let str = <u32>::from_stack(mem, esp + 4u32);
+            let n = <i32>::from_stack(mem, esp + 8u32);
+            let stream = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::fgets(machine, str, n, stream).to_raw()
         }
-        pub unsafe fn HeapReAlloc(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn floor(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hHeap = <u32>::from_stack(mem, esp + 4u32);
-            let dwFlags = <u32>::from_stack(mem, esp + 8u32);
-            let lpMem = <u32>::from_stack(mem, esp + 12u32);
-            let dwBytes = <u32>::from_stack(mem, esp + 16u32);
-            winapi::kernel32::HeapReAlloc(machine, hHeap, dwFlags, lpMem, dwBytes).to_raw()
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::floor(machine, x).to_raw()
         }
-        pub unsafe fn HeapSetInformation(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn fmod(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let HeapHandle = <u32>::from_stack(mem, esp + 4u32);
-            let HeapInformationClass = <u32>::from_stack(mem, esp + 8u32);
-            let HeapInformation = <u32>::from_stack(mem, esp + 12u32);
-            let HeapInformationLength = <u32>::from_stack(mem, esp + 16u32);
-            winapi::kernel32::HeapSetInformation(
-                machine,
-                HeapHandle,
-                HeapInformationClass,
-                HeapInformation,
-                HeapInformationLength,
-            )
-            .to_raw()
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            let y = <f64>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::fmod(machine, x, y).to_raw()
         }
-        pub unsafe fn HeapSize(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn fopen(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hHeap = <u32>::from_stack(mem, esp + 4u32);
-            let dwFlags = <u32>::from_stack(mem, esp + 8u32);
-            let lpMem = <u32>::from_stack(mem, esp + 12u32);
-            winapi::kernel32::HeapSize(machine, hHeap, dwFlags, lpMem).to_raw()
+            let filename = <Option<&str>>::from_stack(mem, esp + 4u32);
+            let mode = <Option<&str>>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::fopen(machine, filename, mode).to_raw()
         }
-        pub unsafe fn InitOnceBeginInitialize(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn fprintf(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpInitOnce = <Option<&mut INIT_ONCE>>::from_stack(mem, esp + 4u32);
-            let dwFlags = <u32>::from_stack(mem, esp + 8u32);
-            let fPending = <Option<&mut u32>>::from_stack(mem, esp + 12u32);
-            let lpContext = <u32>::from_stack(mem, esp + 16u32);
-            winapi::kernel32::InitOnceBeginInitialize(
-                machine, lpInitOnce, dwFlags, fPending, lpContext,
-            )
-            .to_raw()
+            let stream = <u32>::from_stack(mem, esp + 4u32);
+            let format = <u32>::from_stack(mem, esp + 8u32);
+            let args = <VarArgs>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::fprintf(machine, stream, format, args).to_raw()
         }
-        pub unsafe fn InitOnceComplete(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn fputc(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpInitOnce = <Option<&mut INIT_ONCE>>::from_stack(mem, esp + 4u32);
-            let dwFlags = <u32>::from_stack(mem, esp + 8u32);
-            let lpContext = <u32>::from_stack(mem, esp + 12u32);
-            winapi::kernel32::InitOnceComplete(machine, lpInitOnce, dwFlags, lpContext).to_raw()
+            let c = <i32>::from_stack(mem, esp + 4u32);
+            let stream = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::fputc(machine, c, stream).to_raw()
         }
-        pub unsafe fn InitializeCriticalSection(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn fputs(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpCriticalSection = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::InitializeCriticalSection(machine, lpCriticalSection).to_raw()
+            let str = <u32>::from_stack(mem, esp + 4u32);
+            let stream = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::fputs(machine, str, stream).to_raw()
         }
-        pub unsafe fn InitializeCriticalSectionAndSpinCount(
-            machine: &mut Machine,
-            esp: u32,
-        ) -> u32 {
+        pub unsafe fn fread(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpCriticalSection = <u32>::from_stack(mem, esp + 4u32);
-            let dwSpinCount = <u32>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::InitializeCriticalSectionAndSpinCount(
-                machine,
-                lpCriticalSection,
-                dwSpinCount,
-            )
-            .to_raw()
+            let buffer = <u32>::from_stack(mem, esp + 4u32);
+            let size = <u32>::from_stack(mem, esp + 8u32);
+            let count = <u32>::from_stack(mem, esp + 12u32);
+            let stream = <u32>::from_stack(mem, esp + 16u32);
+            winapi::msvcrt::fread(machine, buffer, size, count, stream).to_raw()
         }
-        pub unsafe fn InitializeCriticalSectionEx(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn free(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpCriticalSection = <u32>::from_stack(mem, esp + 4u32);
-            let dwSpinCount = <u32>::from_stack(mem, esp + 8u32);
-            let flags = <u32>::from_stack(mem, esp + 12u32);
-            winapi::kernel32::InitializeCriticalSectionEx(
-                machine,
-                lpCriticalSection,
-                dwSpinCount,
-                flags,
-            )
-            .to_raw()
+            let ptr = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::free(machine, ptr).to_raw()
         }
-        pub unsafe fn InitializeSListHead(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn fseek(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let ListHead = <Option<&mut SLIST_HEADER>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::InitializeSListHead(machine, ListHead).to_raw()
+            let stream = <u32>::from_stack(mem, esp + 4u32);
+            let offset = <i32>::from_stack(mem, esp + 8u32);
+            let origin = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::fseek(machine, stream, offset, origin).to_raw()
         }
-        pub unsafe fn InterlockedIncrement(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn ftell(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let addend = <Option<&mut u32>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::InterlockedIncrement(machine, addend).to_raw()
+            let stream = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::ftell(machine, stream).to_raw()
         }
-        pub unsafe fn IsBadReadPtr(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn fwrite(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lp = <u32>::from_stack(mem, esp + 4u32);
-            let ucb = <u32>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::IsBadReadPtr(machine, lp, ucb).to_raw()
+            let buffer = <u32>::from_stack(mem, esp + 4u32);
+            let size = <u32>::from_stack(mem, esp + 8u32);
+            let count = <u32>::from_stack(mem, esp + 12u32);
+            let stream = <u32>::from_stack(mem, esp + 16u32);
+            winapi::msvcrt::fwrite(machine, buffer, size, count, stream).to_raw()
         }
-        pub unsafe fn IsBadWritePtr(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn getc(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lp = <u32>::from_stack(mem, esp + 4u32);
-            let ucb = <u32>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::IsBadWritePtr(machine, lp, ucb).to_raw()
+            let stream = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::getc(machine, stream).to_raw()
         }
-        pub unsafe fn IsDebuggerPresent(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn getchar(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::IsDebuggerPresent(machine).to_raw()
+            winapi::msvcrt::getchar(machine).to_raw()
         }
-        pub unsafe fn IsProcessorFeaturePresent(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn isalnum(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let feature = <Result<ProcessorFeature, u32>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::IsProcessorFeaturePresent(machine, feature).to_raw()
+            let c = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::isalnum(machine, c).to_raw()
         }
-        pub unsafe fn IsValidCodePage(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn isalpha(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let CodePage = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::IsValidCodePage(machine, CodePage).to_raw()
+            let c = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::isalpha(machine, c).to_raw()
         }
-        pub unsafe fn LeaveCriticalSection(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn isdigit(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpCriticalSection = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::LeaveCriticalSection(machine, lpCriticalSection).to_raw()
+            let c = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::isdigit(machine, c).to_raw()
         }
-        pub unsafe fn LoadLibraryA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn islower(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let filename = <Option<&str>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::LoadLibraryA(machine, filename).to_raw()
+            let c = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::islower(machine, c).to_raw()
         }
-        pub unsafe fn LoadLibraryExW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn isprint(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpLibFileName = <Option<&Str16>>::from_stack(mem, esp + 4u32);
-            let hFile = <HFILE>::from_stack(mem, esp + 8u32);
-            let dwFlags = <u32>::from_stack(mem, esp + 12u32);
-            winapi::kernel32::LoadLibraryExW(machine, lpLibFileName, hFile, dwFlags).to_raw()
+            let c = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::isprint(machine, c).to_raw()
         }
-        pub unsafe fn LoadResource(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn ispunct(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hModule = <u32>::from_stack(mem, esp + 4u32);
-            let hResInfo = <u32>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::LoadResource(machine, hModule, hResInfo).to_raw()
+            let c = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::ispunct(machine, c).to_raw()
         }
-        pub unsafe fn LocalAlloc(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn isspace(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let uFlags = <GMEM>::from_stack(mem, esp + 4u32);
-            let dwBytes = <u32>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::LocalAlloc(machine, uFlags, dwBytes).to_raw()
+            let c = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::isspace(machine, c).to_raw()
         }
-        pub unsafe fn LockResource(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn isupper(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hResData = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::LockResource(machine, hResData).to_raw()
+            let c = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::isupper(machine, c).to_raw()
         }
-        pub unsafe fn MultiByteToWideChar(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn isxdigit(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let CodePage = <Result<CP, u32>>::from_stack(mem, esp + 4u32);
-            let dwFlags = <u32>::from_stack(mem, esp + 8u32);
-            let lpMultiByteStr = <u32>::from_stack(mem, esp + 12u32);
-            let cbMultiByte = <i32>::from_stack(mem, esp + 16u32);
-            let lpWideCharStr = <ArrayWithSizeMut<u16>>::from_stack(mem, esp + 20u32);
-            winapi::kernel32::MultiByteToWideChar(
-                machine,
-                CodePage,
-                dwFlags,
-                lpMultiByteStr,
-                cbMultiByte,
-                lpWideCharStr,
-            )
-            .to_raw()
+            let c = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::isxdigit(machine, c).to_raw()
         }
-        pub unsafe fn NtCurrentTeb(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn labs(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::NtCurrentTeb(machine).to_raw()
+            let n = <i32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::labs(machine, n).to_raw()
         }
-        pub unsafe fn OutputDebugStringA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn ldexp(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let msg = <Option<&str>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::OutputDebugStringA(machine, msg).to_raw()
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            let exp = <i32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::ldexp(machine, x, exp).to_raw()
         }
-        pub unsafe fn QueryPerformanceCounter(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn log(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpPerformanceCount = <Option<&mut LARGE_INTEGER>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::QueryPerformanceCounter(machine, lpPerformanceCount).to_raw()
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::log(machine, x).to_raw()
         }
-        pub unsafe fn QueryPerformanceFrequency(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn log10(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpFrequency = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::QueryPerformanceFrequency(machine, lpFrequency).to_raw()
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::log10(machine, x).to_raw()
         }
-        pub unsafe fn ReadFile(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn malloc(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
-            let lpBuffer = <ArrayWithSizeMut<u8>>::from_stack(mem, esp + 8u32);
-            let lpNumberOfBytesRead = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
-            let lpOverlapped = <u32>::from_stack(mem, esp + 20u32);
-            winapi::kernel32::ReadFile(machine, hFile, lpBuffer, lpNumberOfBytesRead, lpOverlapped)
-                .to_raw()
+            let size = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::malloc(machine, size).to_raw()
         }
-        pub unsafe fn ReleaseSRWLockExclusive(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn memchr(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let SRWLock = <Option<&mut SRWLOCK>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::ReleaseSRWLockExclusive(machine, SRWLock).to_raw()
+            let buf = <u32>::from_stack(mem, esp + 4u32);
+            let c = <u32>::from_stack(mem, esp + 8u32);
+            let count = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::memchr(machine, buf, c, count).to_raw()
         }
-        pub unsafe fn ReleaseSRWLockShared(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn memcmp(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let SRWLock = <Option<&mut SRWLOCK>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::ReleaseSRWLockShared(machine, SRWLock).to_raw()
+            let buf1 = <u32>::from_stack(mem, esp + 4u32);
+            let buf2 = <u32>::from_stack(mem, esp + 8u32);
+            let count = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::memcmp(machine, buf1, buf2, count).to_raw()
         }
-        pub unsafe fn SetEvent(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn memcpy(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hEvent = <HANDLE<()>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::SetEvent(machine, hEvent).to_raw()
+            let dest = <u32>::from_stack(mem, esp + 4u32);
+            let src = <u32>::from_stack(mem, esp + 8u32);
+            let count = <u32>::from_stack(mem, esp + 12u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::msvcrt::memcpy(machine, dest, src, count).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 0u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::msvcrt::memcpy(machine, dest, src, count));
+                crate::shims::call_sync(pin).to_raw()
+            }
         }
-        pub unsafe fn SetFilePointer(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn memmove(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
-            let lDistanceToMove = <u32>::from_stack(mem, esp + 8u32);
-            let lpDistanceToMoveHigh = <Option<&mut u32>>::from_stack(mem, esp + 12u32);
-            let dwMoveMethod = <u32>::from_stack(mem, esp + 16u32);
-            winapi::kernel32::SetFilePointer(
-                machine,
-                hFile,
-                lDistanceToMove,
-                lpDistanceToMoveHigh,
-                dwMoveMethod,
-            )
-            .to_raw()
+            let dest = <u32>::from_stack(mem, esp + 4u32);
+            let src = <u32>::from_stack(mem, esp + 8u32);
+            let count = <u32>::from_stack(mem, esp + 12u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::msvcrt::memmove(machine, dest, src, count).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 0u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::msvcrt::memmove(machine, dest, src, count));
+                crate::shims::call_sync(pin).to_raw()
+            }
         }
-        pub unsafe fn SetHandleCount(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn memset(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let uNumber = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::SetHandleCount(machine, uNumber).to_raw()
+            let dest = <u32>::from_stack(mem, esp + 4u32);
+            let c = <u32>::from_stack(mem, esp + 8u32);
+            let count = <u32>::from_stack(mem, esp + 12u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::msvcrt::memset(machine, dest, c, count).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 0u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::msvcrt::memset(machine, dest, c, count));
+                crate::shims::call_sync(pin).to_raw()
+            }
         }
-        pub unsafe fn SetLastError(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn modf(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let dwErrCode = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::SetLastError(machine, dwErrCode).to_raw()
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            let intptr = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::modf(machine, x, intptr).to_raw()
         }
-        pub unsafe fn SetPriorityClass(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn pow(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hProcess = <HANDLE<()>>::from_stack(mem, esp + 4u32);
-            let dwPriorityClass = <u32>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::SetPriorityClass(machine, hProcess, dwPriorityClass).to_raw()
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            let y = <f64>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::pow(machine, x, y).to_raw()
         }
-        pub unsafe fn SetThreadDescription(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn printf(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hThread = <HTHREAD>::from_stack(mem, esp + 4u32);
-            let lpThreadDescription = <Option<&Str16>>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::SetThreadDescription(machine, hThread, lpThreadDescription).to_raw()
+            let format = <u32>::from_stack(mem, esp + 4u32);
+            let args = <VarArgs>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::printf(machine, format, args).to_raw()
         }
-        pub unsafe fn SetThreadPriority(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn putc(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hThread = <HTHREAD>::from_stack(mem, esp + 4u32);
-            let nPriority = <u32>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::SetThreadPriority(machine, hThread, nPriority).to_raw()
+            let c = <i32>::from_stack(mem, esp + 4u32);
+            let stream = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::putc(machine, c, stream).to_raw()
         }
-        pub unsafe fn SetThreadStackGuarantee(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn putchar(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let StackSizeInBytes = <Option<&mut u32>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::SetThreadStackGuarantee(machine, StackSizeInBytes).to_raw()
+            let c = <i32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::putchar(machine, c).to_raw()
         }
-        pub unsafe fn SetUnhandledExceptionFilter(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn puts(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let _lpTopLevelExceptionFilter = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::SetUnhandledExceptionFilter(machine, _lpTopLevelExceptionFilter)
-                .to_raw()
+            let str = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::puts(machine, str).to_raw()
         }
-        pub unsafe fn Sleep(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn qsort(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let dwMilliseconds = <u32>::from_stack(mem, esp + 4u32);
+            let base = <u32>::from_stack(mem, esp + 4u32);
+            let num = <u32>::from_stack(mem, esp + 8u32);
+            let width = <u32>::from_stack(mem, esp + 12u32);
+            let compare = <u32>::from_stack(mem, esp + 16u32);
             #[cfg(feature = "x86-emu")]
             {
                 let m: *mut Machine = machine;
                 let result = async move {
                     use memory::Extensions;
                     let machine = unsafe { &mut *m };
-                    let result = winapi::kernel32::Sleep(machine, dwMilliseconds).await;
+                    let result = winapi::msvcrt::qsort(machine, base, num, width, compare).await;
                     let regs = &mut machine.emu.x86.cpu_mut().regs;
                     regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
-                    *regs.get32_mut(x86::Register::ESP) += 4u32 + 4;
+                    *regs.get32_mut(x86::Register::ESP) += 0u32 + 4;
                     regs.set32(x86::Register::EAX, result.to_raw());
                 };
                 machine.emu.x86.cpu_mut().call_async(Box::pin(result));
@@ -1620,1347 +7782,2886 @@ pub mod kernel32 {
             }
             #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
             {
-                let pin = std::pin::pin!(winapi::kernel32::Sleep(machine, dwMilliseconds));
+                let pin = std::pin::pin!(winapi::msvcrt::qsort(machine, base, num, width, compare));
                 crate::shims::call_sync(pin).to_raw()
             }
         }
-        pub unsafe fn TlsAlloc(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn rand(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::kernel32::TlsAlloc(machine).to_raw()
+            winapi::msvcrt::rand(machine).to_raw()
         }
-        pub unsafe fn TlsFree(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn realloc(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let dwTlsIndex = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::TlsFree(machine, dwTlsIndex).to_raw()
+            let ptr = <u32>::from_stack(mem, esp + 4u32);
+            let size = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::realloc(machine, ptr, size).to_raw()
         }
-        pub unsafe fn TlsGetValue(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn rewind(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let dwTlsIndex = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::TlsGetValue(machine, dwTlsIndex).to_raw()
+            let stream = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::rewind(machine, stream).to_raw()
         }
-        pub unsafe fn TlsSetValue(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn sin(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let dwTlsIndex = <u32>::from_stack(mem, esp + 4u32);
-            let lpTlsValue = <u32>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::TlsSetValue(machine, dwTlsIndex, lpTlsValue).to_raw()
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::sin(machine, x).to_raw()
         }
-        pub unsafe fn TryAcquireSRWLockExclusive(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn sprintf(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let SRWLock = <Option<&mut SRWLOCK>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::TryAcquireSRWLockExclusive(machine, SRWLock).to_raw()
+            let buffer = <u32>::from_stack(mem, esp + 4u32);
+            let format = <u32>::from_stack(mem, esp + 8u32);
+            let args = <VarArgs>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::sprintf(machine, buffer, format, args).to_raw()
         }
-        pub unsafe fn UnhandledExceptionFilter(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn sqrt(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let _exceptionInfo = <u32>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::UnhandledExceptionFilter(machine, _exceptionInfo).to_raw()
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::sqrt(machine, x).to_raw()
         }
-        pub unsafe fn VirtualAlloc(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn srand(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpAddress = <u32>::from_stack(mem, esp + 4u32);
-            let dwSize = <u32>::from_stack(mem, esp + 8u32);
-            let flAllocationType = <u32>::from_stack(mem, esp + 12u32);
-            let flProtec = <u32>::from_stack(mem, esp + 16u32);
-            winapi::kernel32::VirtualAlloc(machine, lpAddress, dwSize, flAllocationType, flProtec)
-                .to_raw()
+            let seed = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::srand(machine, seed).to_raw()
         }
-        pub unsafe fn VirtualFree(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn strcat(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpAddress = <u32>::from_stack(mem, esp + 4u32);
-            let dwSize = <u32>::from_stack(mem, esp + 8u32);
-            let dwFreeType = <u32>::from_stack(mem, esp + 12u32);
-            winapi::kernel32::VirtualFree(machine, lpAddress, dwSize, dwFreeType).to_raw()
+            let dest = <u32>::from_stack(mem, esp + 4u32);
+            let src = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::strcat(machine, dest, src).to_raw()
         }
-        pub unsafe fn VirtualProtect(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn strchr(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpAddress = <u32>::from_stack(mem, esp + 4u32);
-            let dwSize = <u32>::from_stack(mem, esp + 8u32);
-            let flNewProtect = <u32>::from_stack(mem, esp + 12u32);
-            let lpflOldProtect = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
-            winapi::kernel32::VirtualProtect(
-                machine,
-                lpAddress,
-                dwSize,
-                flNewProtect,
-                lpflOldProtect,
-            )
-            .to_raw()
+            let str = <u32>::from_stack(mem, esp + 4u32);
+            let c = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::strchr(machine, str, c).to_raw()
         }
-        pub unsafe fn WaitForSingleObject(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn strcmp(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hHandle = <HANDLE<()>>::from_stack(mem, esp + 4u32);
-            let dwMilliseconds = <u32>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::WaitForSingleObject(machine, hHandle, dwMilliseconds).to_raw()
+            let string1 = <u32>::from_stack(mem, esp + 4u32);
+            let string2 = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::strcmp(machine, string1, string2).to_raw()
         }
-        pub unsafe fn WriteConsoleA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn strcpy(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hConsoleOutput = <HANDLE<()>>::from_stack(mem, esp + 4u32);
-            let lpBuffer = <ArrayWithSize<u8>>::from_stack(mem, esp + 8u32);
-            let lpNumberOfCharsWritten = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
-            let lpReserved = <u32>::from_stack(mem, esp + 20u32);
-            winapi::kernel32::WriteConsoleA(
-                machine,
-                hConsoleOutput,
-                lpBuffer,
-                lpNumberOfCharsWritten,
-                lpReserved,
-            )
-            .to_raw()
+            let dest = <u32>::from_stack(mem, esp + 4u32);
+            let src = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::strcpy(machine, dest, src).to_raw()
         }
-        pub unsafe fn WriteConsoleW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn strlen(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hConsoleOutput = <HFILE>::from_stack(mem, esp + 4u32);
-            let lpBuffer = <ArrayWithSize<u16>>::from_stack(mem, esp + 8u32);
-            let lpNumberOfCharsWritten = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
-            let _lpReserved = <u32>::from_stack(mem, esp + 20u32);
-            winapi::kernel32::WriteConsoleW(
-                machine,
-                hConsoleOutput,
-                lpBuffer,
-                lpNumberOfCharsWritten,
-                _lpReserved,
-            )
-            .to_raw()
+            let str = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::strlen(machine, str).to_raw()
         }
-        pub unsafe fn WriteFile(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn strncat(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hFile = <HFILE>::from_stack(mem, esp + 4u32);
-            let lpBuffer = <ArrayWithSize<u8>>::from_stack(mem, esp + 8u32);
-            let lpNumberOfBytesWritten = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
-            let lpOverlapped = <u32>::from_stack(mem, esp + 20u32);
-            winapi::kernel32::WriteFile(
-                machine,
-                hFile,
-                lpBuffer,
-                lpNumberOfBytesWritten,
-                lpOverlapped,
-            )
-            .to_raw()
+            let dest = <u32>::from_stack(mem, esp + 4u32);
+            let src = <u32>::from_stack(mem, esp + 8u32);
+            let count = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::strncat(machine, dest, src, count).to_raw()
         }
-        pub unsafe fn lstrcmpiA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn strncmp(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpString1 = <Option<&str>>::from_stack(mem, esp + 4u32);
-            let lpString2 = <Option<&str>>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::lstrcmpiA(machine, lpString1, lpString2).to_raw()
+            let string1 = <u32>::from_stack(mem, esp + 4u32);
+            let string2 = <u32>::from_stack(mem, esp + 8u32);
+            let count = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::strncmp(machine, string1, string2, count).to_raw()
         }
-        pub unsafe fn lstrcpyA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn strncpy(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpString1 = <u32>::from_stack(mem, esp + 4u32);
-            let lpString2 = <Option<&str>>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::lstrcpyA(machine, lpString1, lpString2).to_raw()
+            let dest = <u32>::from_stack(mem, esp + 4u32);
+            let src = <u32>::from_stack(mem, esp + 8u32);
+            let count = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::strncpy(machine, dest, src, count).to_raw()
         }
-        pub unsafe fn lstrcpyW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn strrchr(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpString1 = <u32>::from_stack(mem, esp + 4u32);
-            let lpString2 = <Option<&Str16>>::from_stack(mem, esp + 8u32);
-            winapi::kernel32::lstrcpyW(machine, lpString1, lpString2).to_raw()
+            let str = <u32>::from_stack(mem, esp + 4u32);
+            let c = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::strrchr(machine, str, c).to_raw()
         }
-        pub unsafe fn lstrlenA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn strstr(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpString = <Option<&str>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::lstrlenA(machine, lpString).to_raw()
+            let str = <u32>::from_stack(mem, esp + 4u32);
+            let strSearch = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::strstr(machine, str, strSearch).to_raw()
         }
-        pub unsafe fn lstrlenW(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn strtod(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let lpString = <Option<&Str16>>::from_stack(mem, esp + 4u32);
-            winapi::kernel32::lstrlenW(machine, lpString).to_raw()
+            let nptr = <u32>::from_stack(mem, esp + 4u32);
+            let endptr = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::strtod(machine, nptr, endptr).to_raw()
         }
-        pub unsafe fn retrowin32_main(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn strtol(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let entry_point = <u32>::from_stack(mem, esp + 4u32);
-            #[cfg(feature = "x86-emu")]
-            {
-                let m: *mut Machine = machine;
-                let result = async move {
-                    use memory::Extensions;
-                    let machine = unsafe { &mut *m };
-                    let result = winapi::kernel32::retrowin32_main(machine, entry_point).await;
-                    let regs = &mut machine.emu.x86.cpu_mut().regs;
-                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
-                    *regs.get32_mut(x86::Register::ESP) += 4u32 + 4;
-                    regs.set32(x86::Register::EAX, result.to_raw());
-                };
-                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
-                0
-            }
-            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
-            {
-                let pin = std::pin::pin!(winapi::kernel32::retrowin32_main(machine, entry_point));
-                crate::shims::call_sync(pin).to_raw()
-            }
+            let nptr = <u32>::from_stack(mem, esp + 4u32);
+            let endptr = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
+            let base = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::strtol(machine, nptr, endptr, base).to_raw()
         }
-        pub unsafe fn retrowin32_thread_main(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn strtoul(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let entry_point = <u32>::from_stack(mem, esp + 4u32);
-            let param = <u32>::from_stack(mem, esp + 8u32);
-            #[cfg(feature = "x86-emu")]
-            {
-                let m: *mut Machine = machine;
-                let result = async move {
-                    use memory::Extensions;
-                    let machine = unsafe { &mut *m };
-                    let result =
-                        winapi::kernel32::retrowin32_thread_main(machine, entry_point, param).await;
-                    let regs = &mut machine.emu.x86.cpu_mut().regs;
-                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
-                    *regs.get32_mut(x86::Register::ESP) += 8u32 + 4;
-                    regs.set32(x86::Register::EAX, result.to_raw());
-                };
-                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
-                0
-            }
-            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
-            {
-                let pin = std::pin::pin!(winapi::kernel32::retrowin32_thread_main(
-                    machine,
-                    entry_point,
-                    param
-                ));
-                crate::shims::call_sync(pin).to_raw()
-            }
+            let nptr = <u32>::from_stack(mem, esp + 4u32);
+            let endptr = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
+            let base = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::strtoul(machine, nptr, endptr, base).to_raw()
+        }
+        pub unsafe fn tan(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <f64>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::tan(machine, x).to_raw()
+        }
+        pub unsafe fn tolower(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let c = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::tolower(machine, c).to_raw()
+        }
+        pub unsafe fn toupper(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let c = <u32>::from_stack(mem, esp + 4u32);
+            winapi::msvcrt::toupper(machine, c).to_raw()
+        }
+        pub unsafe fn ungetc(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let c = <i32>::from_stack(mem, esp + 4u32);
+            let stream = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::ungetc(machine, c, stream).to_raw()
+        }
+        pub unsafe fn vfprintf(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let stream = <u32>::from_stack(mem, esp + 4u32);
+            let format = <u32>::from_stack(mem, esp + 8u32);
+            let argptr = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::vfprintf(machine, stream, format, argptr).to_raw()
+        }
+        pub unsafe fn vprintf(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let format = <u32>::from_stack(mem, esp + 4u32);
+            let argptr = <u32>::from_stack(mem, esp + 8u32);
+            winapi::msvcrt::vprintf(machine, format, argptr).to_raw()
+        }
+        pub unsafe fn vsprintf(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let buffer = <u32>::from_stack(mem, esp + 4u32);
+            let format = <u32>::from_stack(mem, esp + 8u32);
+            let argptr = <u32>::from_stack(mem, esp + 12u32);
+            winapi::msvcrt::vsprintf(machine, buffer, format, argptr).to_raw()
         }
     }
     mod shims {
         use super::impls;
         use crate::shims::Shim;
-        pub const AcquireSRWLockExclusive: Shim = Shim {
-            name: "AcquireSRWLockExclusive",
-            func: impls::AcquireSRWLockExclusive,
-            stack_consumed: 4u32,
+        pub const _CIacos: Shim = Shim {
+            name: "_CIacos",
+            func: impls::_CIacos,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const _CIasin: Shim = Shim {
+            name: "_CIasin",
+            func: impls::_CIasin,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const _CIatan: Shim = Shim {
+            name: "_CIatan",
+            func: impls::_CIatan,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const _CIatan2: Shim = Shim {
+            name: "_CIatan2",
+            func: impls::_CIatan2,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const _CIcos: Shim = Shim {
+            name: "_CIcos",
+            func: impls::_CIcos,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const _CIexp: Shim = Shim {
+            name: "_CIexp",
+            func: impls::_CIexp,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const _CIfmod: Shim = Shim {
+            name: "_CIfmod",
+            func: impls::_CIfmod,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const _CIlog: Shim = Shim {
+            name: "_CIlog",
+            func: impls::_CIlog,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const _CIlog10: Shim = Shim {
+            name: "_CIlog10",
+            func: impls::_CIlog10,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const _CIpow: Shim = Shim {
+            name: "_CIpow",
+            func: impls::_CIpow,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const _CIsin: Shim = Shim {
+            name: "_CIsin",
+            func: impls::_CIsin,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const _CIsqrt: Shim = Shim {
+            name: "_CIsqrt",
+            func: impls::_CIsqrt,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const _CItan: Shim = Shim {
+            name: "_CItan",
+            func: impls::_CItan,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const _XcptFilter: Shim = Shim {
+            name: "_XcptFilter",
+            func: impls::_XcptFilter,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const __getmainargs: Shim = Shim {
+            name: "__getmainargs",
+            func: impls::__getmainargs,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const AcquireSRWLockShared: Shim = Shim {
-            name: "AcquireSRWLockShared",
-            func: impls::AcquireSRWLockShared,
-            stack_consumed: 4u32,
+        pub const __iob_func: Shim = Shim {
+            name: "__iob_func",
+            func: impls::__iob_func,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const AddVectoredExceptionHandler: Shim = Shim {
-            name: "AddVectoredExceptionHandler",
-            func: impls::AddVectoredExceptionHandler,
-            stack_consumed: 8u32,
+        pub const __p___argc: Shim = Shim {
+            name: "__p___argc",
+            func: impls::__p___argc,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const CloseHandle: Shim = Shim {
-            name: "CloseHandle",
-            func: impls::CloseHandle,
-            stack_consumed: 4u32,
+        pub const __p___argv: Shim = Shim {
+            name: "__p___argv",
+            func: impls::__p___argv,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const CreateEventA: Shim = Shim {
-            name: "CreateEventA",
-            func: impls::CreateEventA,
-            stack_consumed: 16u32,
+        pub const __p__acmdln: Shim = Shim {
+            name: "__p__acmdln",
+            func: impls::__p__acmdln,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const CreateFileA: Shim = Shim {
-            name: "CreateFileA",
-            func: impls::CreateFileA,
-            stack_consumed: 28u32,
+        pub const __p__commode: Shim = Shim {
+            name: "__p__commode",
+            func: impls::__p__commode,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const CreateFileW: Shim = Shim {
-            name: "CreateFileW",
-            func: impls::CreateFileW,
-            stack_consumed: 28u32,
+        pub const __p__environ: Shim = Shim {
+            name: "__p__environ",
+            func: impls::__p__environ,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const CreateThread: Shim = Shim {
-            name: "CreateThread",
-            func: impls::CreateThread,
-            stack_consumed: 24u32,
-            is_async: true,
+        pub const __p__fmode: Shim = Shim {
+            name: "__p__fmode",
+            func: impls::__p__fmode,
+            stack_consumed: 0u32,
+            is_async: false,
         };
-        pub const DeleteCriticalSection: Shim = Shim {
-            name: "DeleteCriticalSection",
-            func: impls::DeleteCriticalSection,
-            stack_consumed: 4u32,
+        pub const __set_app_type: Shim = Shim {
+            name: "__set_app_type",
+            func: impls::__set_app_type,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const EnterCriticalSection: Shim = Shim {
-            name: "EnterCriticalSection",
-            func: impls::EnterCriticalSection,
-            stack_consumed: 4u32,
+        pub const __setusermatherr: Shim = Shim {
+            name: "__setusermatherr",
+            func: impls::__setusermatherr,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const ExitProcess: Shim = Shim {
-            name: "ExitProcess",
-            func: impls::ExitProcess,
-            stack_consumed: 4u32,
+        pub const _amsg_exit: Shim = Shim {
+            name: "_amsg_exit",
+            func: impls::_amsg_exit,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const FindResourceA: Shim = Shim {
-            name: "FindResourceA",
-            func: impls::FindResourceA,
-            stack_consumed: 12u32,
+        pub const _cexit: Shim = Shim {
+            name: "_cexit",
+            func: impls::_cexit,
+            stack_consumed: 0u32,
+            is_async: true,
+        };
+        pub const _control87: Shim = Shim {
+            name: "_control87",
+            func: impls::_control87,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const FindResourceW: Shim = Shim {
-            name: "FindResourceW",
-            func: impls::FindResourceW,
-            stack_consumed: 12u32,
+        pub const _controlfp: Shim = Shim {
+            name: "_controlfp",
+            func: impls::_controlfp,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const FormatMessageW: Shim = Shim {
-            name: "FormatMessageW",
-            func: impls::FormatMessageW,
-            stack_consumed: 28u32,
+        pub const _errno: Shim = Shim {
+            name: "_errno",
+            func: impls::_errno,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const FreeEnvironmentStringsA: Shim = Shim {
-            name: "FreeEnvironmentStringsA",
-            func: impls::FreeEnvironmentStringsA,
-            stack_consumed: 4u32,
+        pub const _exit: Shim = Shim {
+            name: "_exit",
+            func: impls::_exit,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const FreeLibrary: Shim = Shim {
-            name: "FreeLibrary",
-            func: impls::FreeLibrary,
-            stack_consumed: 4u32,
+        pub const _finite: Shim = Shim {
+            name: "_finite",
+            func: impls::_finite,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetACP: Shim = Shim {
-            name: "GetACP",
-            func: impls::GetACP,
+        pub const _ftol: Shim = Shim {
+            name: "_ftol",
+            func: impls::_ftol,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetCPInfo: Shim = Shim {
-            name: "GetCPInfo",
-            func: impls::GetCPInfo,
-            stack_consumed: 8u32,
+        pub const _initterm: Shim = Shim {
+            name: "_initterm",
+            func: impls::_initterm,
+            stack_consumed: 0u32,
+            is_async: true,
+        };
+        pub const _initterm_e: Shim = Shim {
+            name: "_initterm_e",
+            func: impls::_initterm_e,
+            stack_consumed: 0u32,
+            is_async: true,
+        };
+        pub const _isnan: Shim = Shim {
+            name: "_isnan",
+            func: impls::_isnan,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetCommandLineA: Shim = Shim {
-            name: "GetCommandLineA",
-            func: impls::GetCommandLineA,
+        pub const _itoa: Shim = Shim {
+            name: "_itoa",
+            func: impls::_itoa,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetCommandLineW: Shim = Shim {
-            name: "GetCommandLineW",
-            func: impls::GetCommandLineW,
+        pub const _ltoa: Shim = Shim {
+            name: "_ltoa",
+            func: impls::_ltoa,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetConsoleMode: Shim = Shim {
-            name: "GetConsoleMode",
-            func: impls::GetConsoleMode,
-            stack_consumed: 8u32,
+        pub const _msize: Shim = Shim {
+            name: "_msize",
+            func: impls::_msize,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetCurrentProcessId: Shim = Shim {
-            name: "GetCurrentProcessId",
-            func: impls::GetCurrentProcessId,
+        pub const _onexit: Shim = Shim {
+            name: "_onexit",
+            func: impls::_onexit,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetCurrentThread: Shim = Shim {
-            name: "GetCurrentThread",
-            func: impls::GetCurrentThread,
+        pub const _snprintf: Shim = Shim {
+            name: "_snprintf",
+            func: impls::_snprintf,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetCurrentThreadId: Shim = Shim {
-            name: "GetCurrentThreadId",
-            func: impls::GetCurrentThreadId,
+        pub const _strcmpi: Shim = Shim {
+            name: "_strcmpi",
+            func: impls::_strcmpi,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetEnvironmentStrings: Shim = Shim {
-            name: "GetEnvironmentStrings",
-            func: impls::GetEnvironmentStrings,
+        pub const _strdup: Shim = Shim {
+            name: "_strdup",
+            func: impls::_strdup,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetEnvironmentStringsW: Shim = Shim {
-            name: "GetEnvironmentStringsW",
-            func: impls::GetEnvironmentStringsW,
+        pub const _stricmp: Shim = Shim {
+            name: "_stricmp",
+            func: impls::_stricmp,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetEnvironmentVariableA: Shim = Shim {
-            name: "GetEnvironmentVariableA",
-            func: impls::GetEnvironmentVariableA,
-            stack_consumed: 12u32,
+        pub const _strlwr: Shim = Shim {
+            name: "_strlwr",
+            func: impls::_strlwr,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetEnvironmentVariableW: Shim = Shim {
-            name: "GetEnvironmentVariableW",
-            func: impls::GetEnvironmentVariableW,
-            stack_consumed: 12u32,
+        pub const _strnicmp: Shim = Shim {
+            name: "_strnicmp",
+            func: impls::_strnicmp,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetFileInformationByHandle: Shim = Shim {
-            name: "GetFileInformationByHandle",
-            func: impls::GetFileInformationByHandle,
-            stack_consumed: 8u32,
+        pub const _strupr: Shim = Shim {
+            name: "_strupr",
+            func: impls::_strupr,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetFileType: Shim = Shim {
-            name: "GetFileType",
-            func: impls::GetFileType,
-            stack_consumed: 4u32,
+        pub const _vsnprintf: Shim = Shim {
+            name: "_vsnprintf",
+            func: impls::_vsnprintf,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetFullPathNameW: Shim = Shim {
-            name: "GetFullPathNameW",
-            func: impls::GetFullPathNameW,
-            stack_consumed: 16u32,
+        pub const abort: Shim = Shim {
+            name: "abort",
+            func: impls::abort,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetLastError: Shim = Shim {
-            name: "GetLastError",
-            func: impls::GetLastError,
+        pub const abs: Shim = Shim {
+            name: "abs",
+            func: impls::abs,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetModuleFileNameA: Shim = Shim {
-            name: "GetModuleFileNameA",
-            func: impls::GetModuleFileNameA,
-            stack_consumed: 12u32,
+        pub const acos: Shim = Shim {
+            name: "acos",
+            func: impls::acos,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetModuleFileNameW: Shim = Shim {
-            name: "GetModuleFileNameW",
-            func: impls::GetModuleFileNameW,
-            stack_consumed: 12u32,
+        pub const asin: Shim = Shim {
+            name: "asin",
+            func: impls::asin,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetModuleHandleA: Shim = Shim {
-            name: "GetModuleHandleA",
-            func: impls::GetModuleHandleA,
-            stack_consumed: 4u32,
+        pub const atan: Shim = Shim {
+            name: "atan",
+            func: impls::atan,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetModuleHandleExW: Shim = Shim {
-            name: "GetModuleHandleExW",
-            func: impls::GetModuleHandleExW,
-            stack_consumed: 12u32,
+        pub const atan2: Shim = Shim {
+            name: "atan2",
+            func: impls::atan2,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetModuleHandleW: Shim = Shim {
-            name: "GetModuleHandleW",
-            func: impls::GetModuleHandleW,
-            stack_consumed: 4u32,
+        pub const atexit: Shim = Shim {
+            name: "atexit",
+            func: impls::atexit,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetPrivateProfileIntW: Shim = Shim {
-            name: "GetPrivateProfileIntW",
-            func: impls::GetPrivateProfileIntW,
-            stack_consumed: 16u32,
+        pub const atof: Shim = Shim {
+            name: "atof",
+            func: impls::atof,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetPrivateProfileStringW: Shim = Shim {
-            name: "GetPrivateProfileStringW",
-            func: impls::GetPrivateProfileStringW,
-            stack_consumed: 24u32,
+        pub const atoi: Shim = Shim {
+            name: "atoi",
+            func: impls::atoi,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetProcAddress: Shim = Shim {
-            name: "GetProcAddress",
-            func: impls::GetProcAddress,
-            stack_consumed: 8u32,
+        pub const atol: Shim = Shim {
+            name: "atol",
+            func: impls::atol,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetProcessHeap: Shim = Shim {
-            name: "GetProcessHeap",
-            func: impls::GetProcessHeap,
+        pub const bsearch: Shim = Shim {
+            name: "bsearch",
+            func: impls::bsearch,
+            stack_consumed: 0u32,
+            is_async: true,
+        };
+        pub const calloc: Shim = Shim {
+            name: "calloc",
+            func: impls::calloc,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetStartupInfoA: Shim = Shim {
-            name: "GetStartupInfoA",
-            func: impls::GetStartupInfoA,
-            stack_consumed: 4u32,
+        pub const ceil: Shim = Shim {
+            name: "ceil",
+            func: impls::ceil,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetStartupInfoW: Shim = Shim {
-            name: "GetStartupInfoW",
-            func: impls::GetStartupInfoW,
-            stack_consumed: 4u32,
+        pub const clearerr: Shim = Shim {
+            name: "clearerr",
+            func: impls::clearerr,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetStdHandle: Shim = Shim {
-            name: "GetStdHandle",
-            func: impls::GetStdHandle,
-            stack_consumed: 4u32,
+        pub const cos: Shim = Shim {
+            name: "cos",
+            func: impls::cos,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetSystemTimeAsFileTime: Shim = Shim {
-            name: "GetSystemTimeAsFileTime",
-            func: impls::GetSystemTimeAsFileTime,
-            stack_consumed: 4u32,
+        pub const exit: Shim = Shim {
+            name: "exit",
+            func: impls::exit,
+            stack_consumed: 0u32,
+            is_async: true,
+        };
+        pub const exp: Shim = Shim {
+            name: "exp",
+            func: impls::exp,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetTickCount: Shim = Shim {
-            name: "GetTickCount",
-            func: impls::GetTickCount,
+        pub const fabs: Shim = Shim {
+            name: "fabs",
+            func: impls::fabs,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetVersion: Shim = Shim {
-            name: "GetVersion",
-            func: impls::GetVersion,
+        pub const fclose: Shim = Shim {
+            name: "fclose",
+            func: impls::fclose,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GetVersionExA: Shim = Shim {
-            name: "GetVersionExA",
-            func: impls::GetVersionExA,
-            stack_consumed: 4u32,
+        pub const feof: Shim = Shim {
+            name: "feof",
+            func: impls::feof,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GlobalAlloc: Shim = Shim {
-            name: "GlobalAlloc",
-            func: impls::GlobalAlloc,
-            stack_consumed: 8u32,
+        pub const ferror: Shim = Shim {
+            name: "ferror",
+            func: impls::ferror,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const GlobalFree: Shim = Shim {
-            name: "GlobalFree",
-            func: impls::GlobalFree,
-            stack_consumed: 4u32,
+        pub const fflush: Shim = Shim {
+            name: "fflush",
+            func: impls::fflush,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const fgetc: Shim = Shim {
+            name: "fgetc",
+            func: impls::fgetc,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const HeapAlloc: Shim = Shim {
-            name: "HeapAlloc",
-            func: impls::HeapAlloc,
-            stack_consumed: 12u32,
+        pub const fgets: Shim = Shim {
+            name: "fgets",
+            func: impls::fgets,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const HeapCreate: Shim = Shim {
-            name: "HeapCreate",
-            func: impls::HeapCreate,
-            stack_consumed: 12u32,
+        pub const floor: Shim = Shim {
+            name: "floor",
+            func: impls::floor,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const HeapDestroy: Shim = Shim {
-            name: "HeapDestroy",
-            func: impls::HeapDestroy,
-            stack_consumed: 4u32,
+        pub const fmod: Shim = Shim {
+            name: "fmod",
+            func: impls::fmod,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const HeapFree: Shim = Shim {
-            name: "HeapFree",
-            func: impls::HeapFree,
-            stack_consumed: 12u32,
+        pub const fopen: Shim = Shim {
+            name: "fopen",
+            func: impls::fopen,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const HeapReAlloc: Shim = Shim {
-            name: "HeapReAlloc",
-            func: impls::HeapReAlloc,
-            stack_consumed: 16u32,
+        pub const fprintf: Shim = Shim {
+            name: "fprintf",
+            func: impls::fprintf,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const HeapSetInformation: Shim = Shim {
-            name: "HeapSetInformation",
-            func: impls::HeapSetInformation,
-            stack_consumed: 16u32,
+        pub const fputc: Shim = Shim {
+            name: "fputc",
+            func: impls::fputc,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const HeapSize: Shim = Shim {
-            name: "HeapSize",
-            func: impls::HeapSize,
-            stack_consumed: 12u32,
+        pub const fputs: Shim = Shim {
+            name: "fputs",
+            func: impls::fputs,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const InitOnceBeginInitialize: Shim = Shim {
-            name: "InitOnceBeginInitialize",
-            func: impls::InitOnceBeginInitialize,
-            stack_consumed: 16u32,
+        pub const fread: Shim = Shim {
+            name: "fread",
+            func: impls::fread,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const InitOnceComplete: Shim = Shim {
-            name: "InitOnceComplete",
-            func: impls::InitOnceComplete,
-            stack_consumed: 12u32,
+        pub const free: Shim = Shim {
+            name: "free",
+            func: impls::free,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const InitializeCriticalSection: Shim = Shim {
-            name: "InitializeCriticalSection",
-            func: impls::InitializeCriticalSection,
-            stack_consumed: 4u32,
+        pub const fseek: Shim = Shim {
+            name: "fseek",
+            func: impls::fseek,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const InitializeCriticalSectionAndSpinCount: Shim = Shim {
-            name: "InitializeCriticalSectionAndSpinCount",
-            func: impls::InitializeCriticalSectionAndSpinCount,
-            stack_consumed: 8u32,
+        pub const ftell: Shim = Shim {
+            name: "ftell",
+            func: impls::ftell,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const InitializeCriticalSectionEx: Shim = Shim {
-            name: "InitializeCriticalSectionEx",
-            func: impls::InitializeCriticalSectionEx,
-            stack_consumed: 12u32,
+        pub const fwrite: Shim = Shim {
+            name: "fwrite",
+            func: impls::fwrite,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const InitializeSListHead: Shim = Shim {
-            name: "InitializeSListHead",
-            func: impls::InitializeSListHead,
-            stack_consumed: 4u32,
+        pub const getc: Shim = Shim {
+            name: "getc",
+            func: impls::getc,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const InterlockedIncrement: Shim = Shim {
-            name: "InterlockedIncrement",
-            func: impls::InterlockedIncrement,
-            stack_consumed: 4u32,
+        pub const getchar: Shim = Shim {
+            name: "getchar",
+            func: impls::getchar,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const IsBadReadPtr: Shim = Shim {
-            name: "IsBadReadPtr",
-            func: impls::IsBadReadPtr,
-            stack_consumed: 8u32,
+        pub const isalnum: Shim = Shim {
+            name: "isalnum",
+            func: impls::isalnum,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const IsBadWritePtr: Shim = Shim {
-            name: "IsBadWritePtr",
-            func: impls::IsBadWritePtr,
-            stack_consumed: 8u32,
+        pub const isalpha: Shim = Shim {
+            name: "isalpha",
+            func: impls::isalpha,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const IsDebuggerPresent: Shim = Shim {
-            name: "IsDebuggerPresent",
-            func: impls::IsDebuggerPresent,
+        pub const isdigit: Shim = Shim {
+            name: "isdigit",
+            func: impls::isdigit,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const IsProcessorFeaturePresent: Shim = Shim {
-            name: "IsProcessorFeaturePresent",
-            func: impls::IsProcessorFeaturePresent,
-            stack_consumed: 4u32,
+        pub const islower: Shim = Shim {
+            name: "islower",
+            func: impls::islower,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const IsValidCodePage: Shim = Shim {
-            name: "IsValidCodePage",
-            func: impls::IsValidCodePage,
-            stack_consumed: 4u32,
+        pub const isprint: Shim = Shim {
+            name: "isprint",
+            func: impls::isprint,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const LeaveCriticalSection: Shim = Shim {
-            name: "LeaveCriticalSection",
-            func: impls::LeaveCriticalSection,
-            stack_consumed: 4u32,
+        pub const ispunct: Shim = Shim {
+            name: "ispunct",
+            func: impls::ispunct,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const LoadLibraryA: Shim = Shim {
-            name: "LoadLibraryA",
-            func: impls::LoadLibraryA,
-            stack_consumed: 4u32,
+        pub const isspace: Shim = Shim {
+            name: "isspace",
+            func: impls::isspace,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const LoadLibraryExW: Shim = Shim {
-            name: "LoadLibraryExW",
-            func: impls::LoadLibraryExW,
-            stack_consumed: 12u32,
+        pub const isupper: Shim = Shim {
+            name: "isupper",
+            func: impls::isupper,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const LoadResource: Shim = Shim {
-            name: "LoadResource",
-            func: impls::LoadResource,
-            stack_consumed: 8u32,
+        pub const isxdigit: Shim = Shim {
+            name: "isxdigit",
+            func: impls::isxdigit,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const LocalAlloc: Shim = Shim {
-            name: "LocalAlloc",
-            func: impls::LocalAlloc,
-            stack_consumed: 8u32,
+        pub const labs: Shim = Shim {
+            name: "labs",
+            func: impls::labs,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const LockResource: Shim = Shim {
-            name: "LockResource",
-            func: impls::LockResource,
-            stack_consumed: 4u32,
+        pub const ldexp: Shim = Shim {
+            name: "ldexp",
+            func: impls::ldexp,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const MultiByteToWideChar: Shim = Shim {
-            name: "MultiByteToWideChar",
-            func: impls::MultiByteToWideChar,
-            stack_consumed: 24u32,
+        pub const log: Shim = Shim {
+            name: "log",
+            func: impls::log,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const NtCurrentTeb: Shim = Shim {
-            name: "NtCurrentTeb",
-            func: impls::NtCurrentTeb,
+        pub const log10: Shim = Shim {
+            name: "log10",
+            func: impls::log10,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const OutputDebugStringA: Shim = Shim {
-            name: "OutputDebugStringA",
-            func: impls::OutputDebugStringA,
-            stack_consumed: 4u32,
+        pub const malloc: Shim = Shim {
+            name: "malloc",
+            func: impls::malloc,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const QueryPerformanceCounter: Shim = Shim {
-            name: "QueryPerformanceCounter",
-            func: impls::QueryPerformanceCounter,
-            stack_consumed: 4u32,
+        pub const memchr: Shim = Shim {
+            name: "memchr",
+            func: impls::memchr,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const QueryPerformanceFrequency: Shim = Shim {
-            name: "QueryPerformanceFrequency",
-            func: impls::QueryPerformanceFrequency,
-            stack_consumed: 4u32,
+        pub const memcmp: Shim = Shim {
+            name: "memcmp",
+            func: impls::memcmp,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const ReadFile: Shim = Shim {
-            name: "ReadFile",
-            func: impls::ReadFile,
-            stack_consumed: 20u32,
+        pub const memcpy: Shim = Shim {
+            name: "memcpy",
+            func: impls::memcpy,
+            stack_consumed: 0u32,
+            is_async: true,
+        };
+        pub const memmove: Shim = Shim {
+            name: "memmove",
+            func: impls::memmove,
+            stack_consumed: 0u32,
+            is_async: true,
+        };
+        pub const memset: Shim = Shim {
+            name: "memset",
+            func: impls::memset,
+            stack_consumed: 0u32,
+            is_async: true,
+        };
+        pub const modf: Shim = Shim {
+            name: "modf",
+            func: impls::modf,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const ReleaseSRWLockExclusive: Shim = Shim {
-            name: "ReleaseSRWLockExclusive",
-            func: impls::ReleaseSRWLockExclusive,
-            stack_consumed: 4u32,
+        pub const pow: Shim = Shim {
+            name: "pow",
+            func: impls::pow,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const ReleaseSRWLockShared: Shim = Shim {
-            name: "ReleaseSRWLockShared",
-            func: impls::ReleaseSRWLockShared,
-            stack_consumed: 4u32,
+        pub const printf: Shim = Shim {
+            name: "printf",
+            func: impls::printf,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const SetEvent: Shim = Shim {
-            name: "SetEvent",
-            func: impls::SetEvent,
-            stack_consumed: 4u32,
+        pub const putc: Shim = Shim {
+            name: "putc",
+            func: impls::putc,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const SetFilePointer: Shim = Shim {
-            name: "SetFilePointer",
-            func: impls::SetFilePointer,
-            stack_consumed: 16u32,
+        pub const putchar: Shim = Shim {
+            name: "putchar",
+            func: impls::putchar,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const SetHandleCount: Shim = Shim {
-            name: "SetHandleCount",
-            func: impls::SetHandleCount,
-            stack_consumed: 4u32,
+        pub const puts: Shim = Shim {
+            name: "puts",
+            func: impls::puts,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const SetLastError: Shim = Shim {
-            name: "SetLastError",
-            func: impls::SetLastError,
-            stack_consumed: 4u32,
+        pub const qsort: Shim = Shim {
+            name: "qsort",
+            func: impls::qsort,
+            stack_consumed: 0u32,
+            is_async: true,
+        };
+        pub const rand: Shim = Shim {
+            name: "rand",
+            func: impls::rand,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const SetPriorityClass: Shim = Shim {
-            name: "SetPriorityClass",
-            func: impls::SetPriorityClass,
-            stack_consumed: 8u32,
+        pub const realloc: Shim = Shim {
+            name: "realloc",
+            func: impls::realloc,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const SetThreadDescription: Shim = Shim {
-            name: "SetThreadDescription",
-            func: impls::SetThreadDescription,
-            stack_consumed: 8u32,
+        pub const rewind: Shim = Shim {
+            name: "rewind",
+            func: impls::rewind,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const SetThreadPriority: Shim = Shim {
-            name: "SetThreadPriority",
-            func: impls::SetThreadPriority,
-            stack_consumed: 8u32,
+        pub const sin: Shim = Shim {
+            name: "sin",
+            func: impls::sin,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const SetThreadStackGuarantee: Shim = Shim {
-            name: "SetThreadStackGuarantee",
-            func: impls::SetThreadStackGuarantee,
-            stack_consumed: 4u32,
+        pub const sprintf: Shim = Shim {
+            name: "sprintf",
+            func: impls::sprintf,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const SetUnhandledExceptionFilter: Shim = Shim {
-            name: "SetUnhandledExceptionFilter",
-            func: impls::SetUnhandledExceptionFilter,
-            stack_consumed: 4u32,
+        pub const sqrt: Shim = Shim {
+            name: "sqrt",
+            func: impls::sqrt,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const Sleep: Shim = Shim {
-            name: "Sleep",
-            func: impls::Sleep,
-            stack_consumed: 4u32,
-            is_async: true,
+        pub const srand: Shim = Shim {
+            name: "srand",
+            func: impls::srand,
+            stack_consumed: 0u32,
+            is_async: false,
         };
-        pub const TlsAlloc: Shim = Shim {
-            name: "TlsAlloc",
-            func: impls::TlsAlloc,
+        pub const strcat: Shim = Shim {
+            name: "strcat",
+            func: impls::strcat,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const TlsFree: Shim = Shim {
-            name: "TlsFree",
-            func: impls::TlsFree,
-            stack_consumed: 4u32,
+        pub const strchr: Shim = Shim {
+            name: "strchr",
+            func: impls::strchr,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const TlsGetValue: Shim = Shim {
-            name: "TlsGetValue",
-            func: impls::TlsGetValue,
-            stack_consumed: 4u32,
+        pub const strcmp: Shim = Shim {
+            name: "strcmp",
+            func: impls::strcmp,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const TlsSetValue: Shim = Shim {
-            name: "TlsSetValue",
-            func: impls::TlsSetValue,
-            stack_consumed: 8u32,
+        pub const strcpy: Shim = Shim {
+            name: "strcpy",
+            func: impls::strcpy,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const TryAcquireSRWLockExclusive: Shim = Shim {
-            name: "TryAcquireSRWLockExclusive",
-            func: impls::TryAcquireSRWLockExclusive,
-            stack_consumed: 4u32,
+        pub const strlen: Shim = Shim {
+            name: "strlen",
+            func: impls::strlen,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const UnhandledExceptionFilter: Shim = Shim {
-            name: "UnhandledExceptionFilter",
-            func: impls::UnhandledExceptionFilter,
-            stack_consumed: 4u32,
+        pub const strncat: Shim = Shim {
+            name: "strncat",
+            func: impls::strncat,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const VirtualAlloc: Shim = Shim {
-            name: "VirtualAlloc",
-            func: impls::VirtualAlloc,
-            stack_consumed: 16u32,
+        pub const strncmp: Shim = Shim {
+            name: "strncmp",
+            func: impls::strncmp,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const VirtualFree: Shim = Shim {
-            name: "VirtualFree",
-            func: impls::VirtualFree,
-            stack_consumed: 12u32,
+        pub const strncpy: Shim = Shim {
+            name: "strncpy",
+            func: impls::strncpy,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const VirtualProtect: Shim = Shim {
-            name: "VirtualProtect",
-            func: impls::VirtualProtect,
-            stack_consumed: 16u32,
+        pub const strrchr: Shim = Shim {
+            name: "strrchr",
+            func: impls::strrchr,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const WaitForSingleObject: Shim = Shim {
-            name: "WaitForSingleObject",
-            func: impls::WaitForSingleObject,
-            stack_consumed: 8u32,
+        pub const strstr: Shim = Shim {
+            name: "strstr",
+            func: impls::strstr,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const WriteConsoleA: Shim = Shim {
-            name: "WriteConsoleA",
-            func: impls::WriteConsoleA,
-            stack_consumed: 20u32,
+        pub const strtod: Shim = Shim {
+            name: "strtod",
+            func: impls::strtod,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const WriteConsoleW: Shim = Shim {
-            name: "WriteConsoleW",
-            func: impls::WriteConsoleW,
-            stack_consumed: 20u32,
+        pub const strtol: Shim = Shim {
+            name: "strtol",
+            func: impls::strtol,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const WriteFile: Shim = Shim {
-            name: "WriteFile",
-            func: impls::WriteFile,
-            stack_consumed: 20u32,
+        pub const strtoul: Shim = Shim {
+            name: "strtoul",
+            func: impls::strtoul,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const lstrcmpiA: Shim = Shim {
-            name: "lstrcmpiA",
-            func: impls::lstrcmpiA,
-            stack_consumed: 8u32,
+        pub const tan: Shim = Shim {
+            name: "tan",
+            func: impls::tan,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const lstrcpyA: Shim = Shim {
-            name: "lstrcpyA",
-            func: impls::lstrcpyA,
-            stack_consumed: 8u32,
+        pub const tolower: Shim = Shim {
+            name: "tolower",
+            func: impls::tolower,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const lstrcpyW: Shim = Shim {
-            name: "lstrcpyW",
-            func: impls::lstrcpyW,
-            stack_consumed: 8u32,
+        pub const toupper: Shim = Shim {
+            name: "toupper",
+            func: impls::toupper,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const lstrlenA: Shim = Shim {
-            name: "lstrlenA",
-            func: impls::lstrlenA,
-            stack_consumed: 4u32,
+        pub const ungetc: Shim = Shim {
+            name: "ungetc",
+            func: impls::ungetc,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const lstrlenW: Shim = Shim {
-            name: "lstrlenW",
-            func: impls::lstrlenW,
-            stack_consumed: 4u32,
+        pub const vfprintf: Shim = Shim {
+            name: "vfprintf",
+            func: impls::vfprintf,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const retrowin32_main: Shim = Shim {
-            name: "retrowin32_main",
-            func: impls::retrowin32_main,
-            stack_consumed: 4u32,
-            is_async: true,
+        pub const vprintf: Shim = Shim {
+            name: "vprintf",
+            func: impls::vprintf,
+            stack_consumed: 0u32,
+            is_async: false,
         };
-        pub const retrowin32_thread_main: Shim = Shim {
-            name: "retrowin32_thread_main",
-            func: impls::retrowin32_thread_main,
-            stack_consumed: 8u32,
-            is_async: true,
+        pub const vsprintf: Shim = Shim {
+            name: "vsprintf",
+            func: impls::vsprintf,
+            stack_consumed: 0u32,
+            is_async: false,
         };
     }
-    const EXPORTS: [Symbol; 113usize] = [
+    const EXPORTS: [Symbol; 137usize] = [
+        Symbol {
+            ordinal: None,
+            shim: shims::_CIacos,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_CIasin,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_CIatan,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_CIatan2,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_CIcos,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_CIexp,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_CIfmod,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_CIlog,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_CIlog10,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_CIpow,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_CIsin,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_CIsqrt,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_CItan,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_XcptFilter,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::__getmainargs,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::__iob_func,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::__p___argc,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::__p___argv,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::__p__acmdln,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::__p__commode,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::__p__environ,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::__p__fmode,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::__set_app_type,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::__setusermatherr,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_amsg_exit,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_cexit,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_control87,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_controlfp,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_errno,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_exit,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_finite,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_ftol,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_initterm,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_initterm_e,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_isnan,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_itoa,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_ltoa,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_msize,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_onexit,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_snprintf,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_strcmpi,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_strdup,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_stricmp,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_strlwr,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_strnicmp,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_strupr,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::_vsnprintf,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::abort,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::abs,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::acos,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::asin,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::atan,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::atan2,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::atexit,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::atof,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::atoi,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::atol,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::bsearch,
+        },
         Symbol {
             ordinal: None,
-            shim: shims::AcquireSRWLockExclusive,
+            shim: shims::calloc,
         },
         Symbol {
             ordinal: None,
-            shim: shims::AcquireSRWLockShared,
+            shim: shims::ceil,
         },
         Symbol {
             ordinal: None,
-            shim: shims::AddVectoredExceptionHandler,
+            shim: shims::clearerr,
         },
         Symbol {
             ordinal: None,
-            shim: shims::CloseHandle,
+            shim: shims::cos,
         },
         Symbol {
             ordinal: None,
-            shim: shims::CreateEventA,
+            shim: shims::exit,
         },
         Symbol {
             ordinal: None,
-            shim: shims::CreateFileA,
+            shim: shims::exp,
         },
         Symbol {
             ordinal: None,
-            shim: shims::CreateFileW,
+            shim: shims::fabs,
         },
         Symbol {
             ordinal: None,
-            shim: shims::CreateThread,
+            shim: shims::fclose,
         },
         Symbol {
             ordinal: None,
-            shim: shims::DeleteCriticalSection,
+            shim: shims::feof,
         },
         Symbol {
             ordinal: None,
-            shim: shims::EnterCriticalSection,
+            shim: shims::ferror,
         },
         Symbol {
             ordinal: None,
-            shim: shims::ExitProcess,
+            shim: shims::fflush,
         },
         Symbol {
             ordinal: None,
-            shim: shims::FindResourceA,
+            shim: shims::fgetc,
         },
         Symbol {
             ordinal: None,
-            shim: shims::FindResourceW,
+            shim: shims::fgets,
         },
         Symbol {
             ordinal: None,
-            shim: shims::FormatMessageW,
+            shim: shims::floor,
         },
         Symbol {
             ordinal: None,
-            shim: shims::FreeEnvironmentStringsA,
+            shim: shims::fmod,
         },
         Symbol {
             ordinal: None,
-            shim: shims::FreeLibrary,
+            shim: shims::fopen,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetACP,
+            shim: shims::fprintf,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetCPInfo,
+            shim: shims::fputc,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetCommandLineA,
+            shim: shims::fputs,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetCommandLineW,
+            shim: shims::fread,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetConsoleMode,
+            shim: shims::free,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetCurrentProcessId,
+            shim: shims::fseek,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetCurrentThread,
+            shim: shims::ftell,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetCurrentThreadId,
+            shim: shims::fwrite,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetEnvironmentStrings,
+            shim: shims::getc,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetEnvironmentStringsW,
+            shim: shims::getchar,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetEnvironmentVariableA,
+            shim: shims::isalnum,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetEnvironmentVariableW,
+            shim: shims::isalpha,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetFileInformationByHandle,
+            shim: shims::isdigit,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetFileType,
+            shim: shims::islower,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetFullPathNameW,
+            shim: shims::isprint,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetLastError,
+            shim: shims::ispunct,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetModuleFileNameA,
+            shim: shims::isspace,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetModuleFileNameW,
+            shim: shims::isupper,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetModuleHandleA,
+            shim: shims::isxdigit,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetModuleHandleExW,
+            shim: shims::labs,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetModuleHandleW,
+            shim: shims::ldexp,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetPrivateProfileIntW,
+            shim: shims::log,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetPrivateProfileStringW,
+            shim: shims::log10,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetProcAddress,
+            shim: shims::malloc,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetProcessHeap,
+            shim: shims::memchr,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetStartupInfoA,
+            shim: shims::memcmp,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::memcpy,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::memmove,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::memset,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::modf,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::pow,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::printf,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::putc,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::putchar,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::puts,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::qsort,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::rand,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::realloc,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::rewind,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::sin,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::sprintf,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::sqrt,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::srand,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::strcat,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::strchr,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::strcmp,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::strcpy,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::strlen,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::strncat,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::strncmp,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::strncpy,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::strrchr,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::strstr,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::strtod,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::strtol,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::strtoul,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::tan,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::tolower,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetStartupInfoW,
+            shim: shims::toupper,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ungetc,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::vfprintf,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::vprintf,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::vsprintf,
+        },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "msvcrt.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod ntdll {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::ntdll::*;
+        pub unsafe fn NtReadFile(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let FileHandle = <HFILE>::from_stack(mem, esp + 4u32);
+            let Event = <u32>::from_stack(mem, esp + 8u32);
+            let ApcRoutine = <u32>::from_stack(mem, esp + 12u32);
+            let ApcContext = <u32>::from_stack(mem, esp + 16u32);
+            let IoStatusBlock = <Option<&mut IO_STATUS_BLOCK>>::from_stack(mem, esp + 20u32);
+            let Buffer = <ArrayWithSizeMut<u8>>::from_stack(mem, esp + 24u32);
+            let ByteOffset = <Option<&mut u64>>::from_stack(mem, esp + 32u32);
+            let Key = <u32>::from_stack(mem, esp + 36u32);
+            winapi::ntdll::NtReadFile(
+                machine,
+                FileHandle,
+                Event,
+                ApcRoutine,
+                ApcContext,
+                IoStatusBlock,
+                Buffer,
+                ByteOffset,
+                Key,
+            )
+            .to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const NtReadFile: Shim = Shim {
+            name: "NtReadFile",
+            func: impls::NtReadFile,
+            stack_consumed: 36u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 1usize] = [Symbol {
+        ordinal: None,
+        shim: shims::NtReadFile,
+    }];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "ntdll.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod ole32 {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::ole32::*;
+        pub unsafe fn CoCreateInstance(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let rclsid = <u32>::from_stack(mem, esp + 4u32);
+            let pUnkOuter = <u32>::from_stack(mem, esp + 8u32);
+            let dwClsContext = <u32>::from_stack(mem, esp + 12u32);
+            let riid = <u32>::from_stack(mem, esp + 16u32);
+            let ppv = <u32>::from_stack(mem, esp + 20u32);
+            winapi::ole32::CoCreateInstance(machine, rclsid, pUnkOuter, dwClsContext, riid, ppv)
+                .to_raw()
+        }
+        pub unsafe fn CoInitialize(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pvReserved = <u32>::from_stack(mem, esp + 4u32);
+            winapi::ole32::CoInitialize(machine, pvReserved).to_raw()
+        }
+        pub unsafe fn CoInitializeEx(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pvReserved = <u32>::from_stack(mem, esp + 4u32);
+            let dwCoInit = <u32>::from_stack(mem, esp + 8u32);
+            winapi::ole32::CoInitializeEx(machine, pvReserved, dwCoInit).to_raw()
+        }
+        pub unsafe fn CoTaskMemAlloc(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let cb = <u32>::from_stack(mem, esp + 4u32);
+            winapi::ole32::CoTaskMemAlloc(machine, cb).to_raw()
+        }
+        pub unsafe fn CoTaskMemFree(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pv = <u32>::from_stack(mem, esp + 4u32);
+            winapi::ole32::CoTaskMemFree(machine, pv).to_raw()
+        }
+        pub unsafe fn CoTaskMemRealloc(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pv = <u32>::from_stack(mem, esp + 4u32);
+            let cb = <u32>::from_stack(mem, esp + 8u32);
+            winapi::ole32::CoTaskMemRealloc(machine, pv, cb).to_raw()
+        }
+        pub unsafe fn CoUninitialize(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::ole32::CoUninitialize(machine).to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const CoCreateInstance: Shim = Shim {
+            name: "CoCreateInstance",
+            func: impls::CoCreateInstance,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const CoInitialize: Shim = Shim {
+            name: "CoInitialize",
+            func: impls::CoInitialize,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const CoInitializeEx: Shim = Shim {
+            name: "CoInitializeEx",
+            func: impls::CoInitializeEx,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const CoTaskMemAlloc: Shim = Shim {
+            name: "CoTaskMemAlloc",
+            func: impls::CoTaskMemAlloc,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const CoTaskMemFree: Shim = Shim {
+            name: "CoTaskMemFree",
+            func: impls::CoTaskMemFree,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const CoTaskMemRealloc: Shim = Shim {
+            name: "CoTaskMemRealloc",
+            func: impls::CoTaskMemRealloc,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const CoUninitialize: Shim = Shim {
+            name: "CoUninitialize",
+            func: impls::CoUninitialize,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 7usize] = [
+        Symbol {
+            ordinal: None,
+            shim: shims::CoCreateInstance,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetStdHandle,
+            shim: shims::CoInitialize,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetSystemTimeAsFileTime,
+            shim: shims::CoInitializeEx,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetTickCount,
+            shim: shims::CoTaskMemAlloc,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetVersion,
+            shim: shims::CoTaskMemFree,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GetVersionExA,
+            shim: shims::CoTaskMemRealloc,
         },
         Symbol {
             ordinal: None,
-            shim: shims::GlobalAlloc,
+            shim: shims::CoUninitialize,
         },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "ole32.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod oleaut32 {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::oleaut32::*;
+        pub unsafe fn SysAllocString(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let psz = <Option<&Str16>>::from_stack(mem, esp + 4u32);
+            winapi::oleaut32::SysAllocString(machine, psz).to_raw()
+        }
+        pub unsafe fn SysAllocStringByteLen(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let psz = <u32>::from_stack(mem, esp + 4u32);
+            let len = <u32>::from_stack(mem, esp + 8u32);
+            winapi::oleaut32::SysAllocStringByteLen(machine, psz, len).to_raw()
+        }
+        pub unsafe fn SysAllocStringLen(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let strIn = <u32>::from_stack(mem, esp + 4u32);
+            let ui = <u32>::from_stack(mem, esp + 8u32);
+            winapi::oleaut32::SysAllocStringLen(machine, strIn, ui).to_raw()
+        }
+        pub unsafe fn SysFreeString(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let bstrString = <u32>::from_stack(mem, esp + 4u32);
+            winapi::oleaut32::SysFreeString(machine, bstrString).to_raw()
+        }
+        pub unsafe fn SysReAllocString(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pbstr = <Option<&mut u32>>::from_stack(mem, esp + 4u32);
+            let psz = <Option<&Str16>>::from_stack(mem, esp + 8u32);
+            winapi::oleaut32::SysReAllocString(machine, pbstr, psz).to_raw()
+        }
+        pub unsafe fn SysStringByteLen(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let bstr = <u32>::from_stack(mem, esp + 4u32);
+            winapi::oleaut32::SysStringByteLen(machine, bstr).to_raw()
+        }
+        pub unsafe fn SysStringLen(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pbstr = <u32>::from_stack(mem, esp + 4u32);
+            winapi::oleaut32::SysStringLen(machine, pbstr).to_raw()
+        }
+        pub unsafe fn VariantChangeType(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pvargDest = <u32>::from_stack(mem, esp + 4u32);
+            let pvarSrc = <u32>::from_stack(mem, esp + 8u32);
+            let wFlags = <u32>::from_stack(mem, esp + 12u32);
+            let vt = <u32>::from_stack(mem, esp + 16u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::oleaut32::VariantChangeType(
+                        machine, pvargDest, pvarSrc, wFlags, vt,
+                    )
+                    .await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 16u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::oleaut32::VariantChangeType(
+                    machine, pvargDest, pvarSrc, wFlags, vt
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn VariantClear(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pvarg = <u32>::from_stack(mem, esp + 4u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::oleaut32::VariantClear(machine, pvarg).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 4u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::oleaut32::VariantClear(machine, pvarg));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn VariantCopy(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pvargDest = <u32>::from_stack(mem, esp + 4u32);
+            let pvargSrc = <u32>::from_stack(mem, esp + 8u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::oleaut32::VariantCopy(machine, pvargDest, pvargSrc).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 8u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin =
+                    std::pin::pin!(winapi::oleaut32::VariantCopy(machine, pvargDest, pvargSrc));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn VariantInit(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pvarg = <Option<&mut VARIANT>>::from_stack(mem, esp + 4u32);
+            winapi::oleaut32::VariantInit(machine, pvarg).to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const SysAllocString: Shim = Shim {
+            name: "SysAllocString",
+            func: impls::SysAllocString,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const SysAllocStringByteLen: Shim = Shim {
+            name: "SysAllocStringByteLen",
+            func: impls::SysAllocStringByteLen,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const SysAllocStringLen: Shim = Shim {
+            name: "SysAllocStringLen",
+            func: impls::SysAllocStringLen,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const SysFreeString: Shim = Shim {
+            name: "SysFreeString",
+            func: impls::SysFreeString,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const SysReAllocString: Shim = Shim {
+            name: "SysReAllocString",
+            func: impls::SysReAllocString,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const SysStringByteLen: Shim = Shim {
+            name: "SysStringByteLen",
+            func: impls::SysStringByteLen,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const SysStringLen: Shim = Shim {
+            name: "SysStringLen",
+            func: impls::SysStringLen,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const VariantChangeType: Shim = Shim {
+            name: "VariantChangeType",
+            func: impls::VariantChangeType,
+            stack_consumed: 16u32,
+            is_async: true,
+        };
+        pub const VariantClear: Shim = Shim {
+            name: "VariantClear",
+            func: impls::VariantClear,
+            stack_consumed: 4u32,
+            is_async: true,
+        };
+        pub const VariantCopy: Shim = Shim {
+            name: "VariantCopy",
+            func: impls::VariantCopy,
+            stack_consumed: 8u32,
+            is_async: true,
+        };
+        pub const VariantInit: Shim = Shim {
+            name: "VariantInit",
+            func: impls::VariantInit,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 11usize] = [
         Symbol {
-            ordinal: None,
-            shim: shims::GlobalFree,
+            ordinal: Some(2usize),
+            shim: shims::SysAllocString,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::HeapAlloc,
+            ordinal: Some(150usize),
+            shim: shims::SysAllocStringByteLen,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::HeapCreate,
+            ordinal: Some(4usize),
+            shim: shims::SysAllocStringLen,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::HeapDestroy,
+            ordinal: Some(6usize),
+            shim: shims::SysFreeString,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::HeapFree,
+            ordinal: Some(3usize),
+            shim: shims::SysReAllocString,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::HeapReAlloc,
+            ordinal: Some(149usize),
+            shim: shims::SysStringByteLen,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::HeapSetInformation,
+            ordinal: Some(7usize),
+            shim: shims::SysStringLen,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::HeapSize,
+            ordinal: Some(12usize),
+            shim: shims::VariantChangeType,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::InitOnceBeginInitialize,
+            ordinal: Some(9usize),
+            shim: shims::VariantClear,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::InitOnceComplete,
+            ordinal: Some(10usize),
+            shim: shims::VariantCopy,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::InitializeCriticalSection,
+            ordinal: Some(8usize),
+            shim: shims::VariantInit,
         },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "oleaut32.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod opengl32 {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::opengl32::*;
+        pub unsafe fn glBegin(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let mode = <u32>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::glBegin(machine, mode).to_raw()
+        }
+        pub unsafe fn glBindTexture(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let target = <u32>::from_stack(mem, esp + 4u32);
+            let texture = <u32>::from_stack(mem, esp + 8u32);
+            winapi::opengl32::glBindTexture(machine, target, texture).to_raw()
+        }
+        pub unsafe fn glClear(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let mask = <u32>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::glClear(machine, mask).to_raw()
+        }
+        pub unsafe fn glClearColor(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let red = <f32>::from_stack(mem, esp + 4u32);
+            let green = <f32>::from_stack(mem, esp + 8u32);
+            let blue = <f32>::from_stack(mem, esp + 12u32);
+            let alpha = <f32>::from_stack(mem, esp + 16u32);
+            winapi::opengl32::glClearColor(machine, red, green, blue, alpha).to_raw()
+        }
+        pub unsafe fn glClearDepth(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let depth = <f64>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::glClearDepth(machine, depth).to_raw()
+        }
+        pub unsafe fn glColor3f(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let red = <f32>::from_stack(mem, esp + 4u32);
+            let green = <f32>::from_stack(mem, esp + 8u32);
+            let blue = <f32>::from_stack(mem, esp + 12u32);
+            winapi::opengl32::glColor3f(machine, red, green, blue).to_raw()
+        }
+        pub unsafe fn glColor3ub(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let red = <u32>::from_stack(mem, esp + 4u32);
+            let green = <u32>::from_stack(mem, esp + 8u32);
+            let blue = <u32>::from_stack(mem, esp + 12u32);
+            winapi::opengl32::glColor3ub(machine, red, green, blue).to_raw()
+        }
+        pub unsafe fn glColor4f(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let red = <f32>::from_stack(mem, esp + 4u32);
+            let green = <f32>::from_stack(mem, esp + 8u32);
+            let blue = <f32>::from_stack(mem, esp + 12u32);
+            let alpha = <f32>::from_stack(mem, esp + 16u32);
+            winapi::opengl32::glColor4f(machine, red, green, blue, alpha).to_raw()
+        }
+        pub unsafe fn glColor4ub(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let red = <u32>::from_stack(mem, esp + 4u32);
+            let green = <u32>::from_stack(mem, esp + 8u32);
+            let blue = <u32>::from_stack(mem, esp + 12u32);
+            let alpha = <u32>::from_stack(mem, esp + 16u32);
+            winapi::opengl32::glColor4ub(machine, red, green, blue, alpha).to_raw()
+        }
+        pub unsafe fn glDeleteTextures(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let n = <i32>::from_stack(mem, esp + 4u32);
+            let textures = <u32>::from_stack(mem, esp + 8u32);
+            winapi::opengl32::glDeleteTextures(machine, n, textures).to_raw()
+        }
+        pub unsafe fn glDepthFunc(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let func = <u32>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::glDepthFunc(machine, func).to_raw()
+        }
+        pub unsafe fn glDisable(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let cap = <u32>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::glDisable(machine, cap).to_raw()
+        }
+        pub unsafe fn glEnable(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let cap = <u32>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::glEnable(machine, cap).to_raw()
+        }
+        pub unsafe fn glEnd(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::opengl32::glEnd(machine).to_raw()
+        }
+        pub unsafe fn glFinish(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::opengl32::glFinish(machine).to_raw()
+        }
+        pub unsafe fn glFlush(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::opengl32::glFlush(machine).to_raw()
+        }
+        pub unsafe fn glFrustum(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let left = <f64>::from_stack(mem, esp + 4u32);
+            let right = <f64>::from_stack(mem, esp + 12u32);
+            let bottom = <f64>::from_stack(mem, esp + 20u32);
+            let top = <f64>::from_stack(mem, esp + 28u32);
+            let zNear = <f64>::from_stack(mem, esp + 36u32);
+            let zFar = <f64>::from_stack(mem, esp + 44u32);
+            winapi::opengl32::glFrustum(machine, left, right, bottom, top, zNear, zFar).to_raw()
+        }
+        pub unsafe fn glGenTextures(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let n = <i32>::from_stack(mem, esp + 4u32);
+            let textures = <u32>::from_stack(mem, esp + 8u32);
+            winapi::opengl32::glGenTextures(machine, n, textures).to_raw()
+        }
+        pub unsafe fn glGetError(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::opengl32::glGetError(machine).to_raw()
+        }
+        pub unsafe fn glGetString(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let name = <Result<GetStringName, u32>>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::glGetString(machine, name).to_raw()
+        }
+        pub unsafe fn glHint(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let target = <u32>::from_stack(mem, esp + 4u32);
+            let mode = <u32>::from_stack(mem, esp + 8u32);
+            winapi::opengl32::glHint(machine, target, mode).to_raw()
+        }
+        pub unsafe fn glLoadIdentity(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::opengl32::glLoadIdentity(machine).to_raw()
+        }
+        pub unsafe fn glLoadMatrixf(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let m = <Option<&[f32; 16]>>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::glLoadMatrixf(machine, m).to_raw()
+        }
+        pub unsafe fn glMatrixMode(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let mode = <u32>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::glMatrixMode(machine, mode).to_raw()
+        }
+        pub unsafe fn glMultMatrixf(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let m = <Option<&[f32; 16]>>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::glMultMatrixf(machine, m).to_raw()
+        }
+        pub unsafe fn glOrtho(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let left = <f64>::from_stack(mem, esp + 4u32);
+            let right = <f64>::from_stack(mem, esp + 12u32);
+            let bottom = <f64>::from_stack(mem, esp + 20u32);
+            let top = <f64>::from_stack(mem, esp + 28u32);
+            let zNear = <f64>::from_stack(mem, esp + 36u32);
+            let zFar = <f64>::from_stack(mem, esp + 44u32);
+            winapi::opengl32::glOrtho(machine, left, right, bottom, top, zNear, zFar).to_raw()
+        }
+        pub unsafe fn glPixelStorei(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pname = <u32>::from_stack(mem, esp + 4u32);
+            let param = <i32>::from_stack(mem, esp + 8u32);
+            winapi::opengl32::glPixelStorei(machine, pname, param).to_raw()
+        }
+        pub unsafe fn glPopMatrix(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::opengl32::glPopMatrix(machine).to_raw()
+        }
+        pub unsafe fn glPushMatrix(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::opengl32::glPushMatrix(machine).to_raw()
+        }
+        pub unsafe fn glRotatef(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let angle = <f32>::from_stack(mem, esp + 4u32);
+            let x = <f32>::from_stack(mem, esp + 8u32);
+            let y = <f32>::from_stack(mem, esp + 12u32);
+            let z = <f32>::from_stack(mem, esp + 16u32);
+            winapi::opengl32::glRotatef(machine, angle, x, y, z).to_raw()
+        }
+        pub unsafe fn glScalef(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <f32>::from_stack(mem, esp + 4u32);
+            let y = <f32>::from_stack(mem, esp + 8u32);
+            let z = <f32>::from_stack(mem, esp + 12u32);
+            winapi::opengl32::glScalef(machine, x, y, z).to_raw()
+        }
+        pub unsafe fn glShadeModel(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let mode = <u32>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::glShadeModel(machine, mode).to_raw()
+        }
+        pub unsafe fn glTexCoord2f(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let s = <f32>::from_stack(mem, esp + 4u32);
+            let t = <f32>::from_stack(mem, esp + 8u32);
+            winapi::opengl32::glTexCoord2f(machine, s, t).to_raw()
+        }
+        pub unsafe fn glTexCoord2fv(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let v = <Option<&[f32; 2]>>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::glTexCoord2fv(machine, v).to_raw()
+        }
+        pub unsafe fn glTexEnvf(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let target = <u32>::from_stack(mem, esp + 4u32);
+            let pname = <u32>::from_stack(mem, esp + 8u32);
+            let param = <f32>::from_stack(mem, esp + 12u32);
+            winapi::opengl32::glTexEnvf(machine, target, pname, param).to_raw()
+        }
+        pub unsafe fn glTexEnvi(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let target = <u32>::from_stack(mem, esp + 4u32);
+            let pname = <u32>::from_stack(mem, esp + 8u32);
+            let param = <i32>::from_stack(mem, esp + 12u32);
+            winapi::opengl32::glTexEnvi(machine, target, pname, param).to_raw()
+        }
+        pub unsafe fn glTexImage2D(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let target = <u32>::from_stack(mem, esp + 4u32);
+            let level = <i32>::from_stack(mem, esp + 8u32);
+            let internalformat = <i32>::from_stack(mem, esp + 12u32);
+            let width = <i32>::from_stack(mem, esp + 16u32);
+            let height = <i32>::from_stack(mem, esp + 20u32);
+            let border = <i32>::from_stack(mem, esp + 24u32);
+            let format = <u32>::from_stack(mem, esp + 28u32);
+            let type_ = <u32>::from_stack(mem, esp + 32u32);
+            let pixels = <u32>::from_stack(mem, esp + 36u32);
+            winapi::opengl32::glTexImage2D(
+                machine,
+                target,
+                level,
+                internalformat,
+                width,
+                height,
+                border,
+                format,
+                type_,
+                pixels,
+            )
+            .to_raw()
+        }
+        pub unsafe fn glTexParameterf(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let target = <u32>::from_stack(mem, esp + 4u32);
+            let pname = <u32>::from_stack(mem, esp + 8u32);
+            let param = <f32>::from_stack(mem, esp + 12u32);
+            winapi::opengl32::glTexParameterf(machine, target, pname, param).to_raw()
+        }
+        pub unsafe fn glTexParameteri(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let target = <u32>::from_stack(mem, esp + 4u32);
+            let pname = <u32>::from_stack(mem, esp + 8u32);
+            let param = <i32>::from_stack(mem, esp + 12u32);
+            winapi::opengl32::glTexParameteri(machine, target, pname, param).to_raw()
+        }
+        pub unsafe fn glTexSubImage2D(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let target = <u32>::from_stack(mem, esp + 4u32);
+            let level = <i32>::from_stack(mem, esp + 8u32);
+            let xoffset = <i32>::from_stack(mem, esp + 12u32);
+            let yoffset = <i32>::from_stack(mem, esp + 16u32);
+            let width = <i32>::from_stack(mem, esp + 20u32);
+            let height = <i32>::from_stack(mem, esp + 24u32);
+            let format = <u32>::from_stack(mem, esp + 28u32);
+            let type_ = <u32>::from_stack(mem, esp + 32u32);
+            let pixels = <u32>::from_stack(mem, esp + 36u32);
+            winapi::opengl32::glTexSubImage2D(
+                machine, target, level, xoffset, yoffset, width, height, format, type_, pixels,
+            )
+            .to_raw()
+        }
+        pub unsafe fn glTranslatef(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <f32>::from_stack(mem, esp + 4u32);
+            let y = <f32>::from_stack(mem, esp + 8u32);
+            let z = <f32>::from_stack(mem, esp + 12u32);
+            winapi::opengl32::glTranslatef(machine, x, y, z).to_raw()
+        }
+        pub unsafe fn glVertex2f(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <f32>::from_stack(mem, esp + 4u32);
+            let y = <f32>::from_stack(mem, esp + 8u32);
+            winapi::opengl32::glVertex2f(machine, x, y).to_raw()
+        }
+        pub unsafe fn glVertex2i(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <i32>::from_stack(mem, esp + 4u32);
+            let y = <i32>::from_stack(mem, esp + 8u32);
+            winapi::opengl32::glVertex2i(machine, x, y).to_raw()
+        }
+        pub unsafe fn glVertex3f(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <f32>::from_stack(mem, esp + 4u32);
+            let y = <f32>::from_stack(mem, esp + 8u32);
+            let z = <f32>::from_stack(mem, esp + 12u32);
+            winapi::opengl32::glVertex3f(machine, x, y, z).to_raw()
+        }
+        pub unsafe fn glVertex3fv(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let v = <Option<&[f32; 3]>>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::glVertex3fv(machine, v).to_raw()
+        }
+        pub unsafe fn glVertex4f(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <f32>::from_stack(mem, esp + 4u32);
+            let y = <f32>::from_stack(mem, esp + 8u32);
+            let z = <f32>::from_stack(mem, esp + 12u32);
+            let w = <f32>::from_stack(mem, esp + 16u32);
+            winapi::opengl32::glVertex4f(machine, x, y, z, w).to_raw()
+        }
+        pub unsafe fn glViewport(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let x = <i32>::from_stack(mem, esp + 4u32);
+            let y = <i32>::from_stack(mem, esp + 8u32);
+            let width = <i32>::from_stack(mem, esp + 12u32);
+            let height = <i32>::from_stack(mem, esp + 16u32);
+            winapi::opengl32::glViewport(machine, x, y, width, height).to_raw()
+        }
+        pub unsafe fn wglCreateContext(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::wglCreateContext(machine, hdc).to_raw()
+        }
+        pub unsafe fn wglDeleteContext(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hglrc = <HGLRC>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::wglDeleteContext(machine, hglrc).to_raw()
+        }
+        pub unsafe fn wglGetCurrentContext(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::opengl32::wglGetCurrentContext(machine).to_raw()
+        }
+        pub unsafe fn wglGetCurrentDC(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::opengl32::wglGetCurrentDC(machine).to_raw()
+        }
+        pub unsafe fn wglGetProcAddress(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpszProc = <Option<&str>>::from_stack(mem, esp + 4u32);
+            winapi::opengl32::wglGetProcAddress(machine, lpszProc).to_raw()
+        }
+        pub unsafe fn wglMakeCurrent(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hdc = <HDC>::from_stack(mem, esp + 4u32);
+            let hglrc = <HGLRC>::from_stack(mem, esp + 8u32);
+            winapi::opengl32::wglMakeCurrent(machine, hdc, hglrc).to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const glBegin: Shim = Shim {
+            name: "glBegin",
+            func: impls::glBegin,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const glBindTexture: Shim = Shim {
+            name: "glBindTexture",
+            func: impls::glBindTexture,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const glClear: Shim = Shim {
+            name: "glClear",
+            func: impls::glClear,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const glClearColor: Shim = Shim {
+            name: "glClearColor",
+            func: impls::glClearColor,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const glClearDepth: Shim = Shim {
+            name: "glClearDepth",
+            func: impls::glClearDepth,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const glColor3f: Shim = Shim {
+            name: "glColor3f",
+            func: impls::glColor3f,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const glColor3ub: Shim = Shim {
+            name: "glColor3ub",
+            func: impls::glColor3ub,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const glColor4f: Shim = Shim {
+            name: "glColor4f",
+            func: impls::glColor4f,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const glColor4ub: Shim = Shim {
+            name: "glColor4ub",
+            func: impls::glColor4ub,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const glDeleteTextures: Shim = Shim {
+            name: "glDeleteTextures",
+            func: impls::glDeleteTextures,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const glDepthFunc: Shim = Shim {
+            name: "glDepthFunc",
+            func: impls::glDepthFunc,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const glDisable: Shim = Shim {
+            name: "glDisable",
+            func: impls::glDisable,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const glEnable: Shim = Shim {
+            name: "glEnable",
+            func: impls::glEnable,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const glEnd: Shim = Shim {
+            name: "glEnd",
+            func: impls::glEnd,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const glFinish: Shim = Shim {
+            name: "glFinish",
+            func: impls::glFinish,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const glFlush: Shim = Shim {
+            name: "glFlush",
+            func: impls::glFlush,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const glFrustum: Shim = Shim {
+            name: "glFrustum",
+            func: impls::glFrustum,
+            stack_consumed: 48u32,
+            is_async: false,
+        };
+        pub const glGenTextures: Shim = Shim {
+            name: "glGenTextures",
+            func: impls::glGenTextures,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const glGetError: Shim = Shim {
+            name: "glGetError",
+            func: impls::glGetError,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const glGetString: Shim = Shim {
+            name: "glGetString",
+            func: impls::glGetString,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const glHint: Shim = Shim {
+            name: "glHint",
+            func: impls::glHint,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const glLoadIdentity: Shim = Shim {
+            name: "glLoadIdentity",
+            func: impls::glLoadIdentity,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const glLoadMatrixf: Shim = Shim {
+            name: "glLoadMatrixf",
+            func: impls::glLoadMatrixf,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const glMatrixMode: Shim = Shim {
+            name: "glMatrixMode",
+            func: impls::glMatrixMode,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const glMultMatrixf: Shim = Shim {
+            name: "glMultMatrixf",
+            func: impls::glMultMatrixf,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const glOrtho: Shim = Shim {
+            name: "glOrtho",
+            func: impls::glOrtho,
+            stack_consumed: 48u32,
+            is_async: false,
+        };
+        pub const glPixelStorei: Shim = Shim {
+            name: "glPixelStorei",
+            func: impls::glPixelStorei,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const glPopMatrix: Shim = Shim {
+            name: "glPopMatrix",
+            func: impls::glPopMatrix,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const glPushMatrix: Shim = Shim {
+            name: "glPushMatrix",
+            func: impls::glPushMatrix,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const glRotatef: Shim = Shim {
+            name: "glRotatef",
+            func: impls::glRotatef,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const glScalef: Shim = Shim {
+            name: "glScalef",
+            func: impls::glScalef,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const glShadeModel: Shim = Shim {
+            name: "glShadeModel",
+            func: impls::glShadeModel,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const glTexCoord2f: Shim = Shim {
+            name: "glTexCoord2f",
+            func: impls::glTexCoord2f,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const glTexCoord2fv: Shim = Shim {
+            name: "glTexCoord2fv",
+            func: impls::glTexCoord2fv,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const glTexEnvf: Shim = Shim {
+            name: "glTexEnvf",
+            func: impls::glTexEnvf,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const glTexEnvi: Shim = Shim {
+            name: "glTexEnvi",
+            func: impls::glTexEnvi,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const glTexImage2D: Shim = Shim {
+            name: "glTexImage2D",
+            func: impls::glTexImage2D,
+            stack_consumed: 36u32,
+            is_async: false,
+        };
+        pub const glTexParameterf: Shim = Shim {
+            name: "glTexParameterf",
+            func: impls::glTexParameterf,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const glTexParameteri: Shim = Shim {
+            name: "glTexParameteri",
+            func: impls::glTexParameteri,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const glTexSubImage2D: Shim = Shim {
+            name: "glTexSubImage2D",
+            func: impls::glTexSubImage2D,
+            stack_consumed: 36u32,
+            is_async: false,
+        };
+        pub const glTranslatef: Shim = Shim {
+            name: "glTranslatef",
+            func: impls::glTranslatef,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const glVertex2f: Shim = Shim {
+            name: "glVertex2f",
+            func: impls::glVertex2f,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const glVertex2i: Shim = Shim {
+            name: "glVertex2i",
+            func: impls::glVertex2i,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const glVertex3f: Shim = Shim {
+            name: "glVertex3f",
+            func: impls::glVertex3f,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const glVertex3fv: Shim = Shim {
+            name: "glVertex3fv",
+            func: impls::glVertex3fv,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const glVertex4f: Shim = Shim {
+            name: "glVertex4f",
+            func: impls::glVertex4f,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const glViewport: Shim = Shim {
+            name: "glViewport",
+            func: impls::glViewport,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const wglCreateContext: Shim = Shim {
+            name: "wglCreateContext",
+            func: impls::wglCreateContext,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const wglDeleteContext: Shim = Shim {
+            name: "wglDeleteContext",
+            func: impls::wglDeleteContext,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const wglGetCurrentContext: Shim = Shim {
+            name: "wglGetCurrentContext",
+            func: impls::wglGetCurrentContext,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const wglGetCurrentDC: Shim = Shim {
+            name: "wglGetCurrentDC",
+            func: impls::wglGetCurrentDC,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const wglGetProcAddress: Shim = Shim {
+            name: "wglGetProcAddress",
+            func: impls::wglGetProcAddress,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const wglMakeCurrent: Shim = Shim {
+            name: "wglMakeCurrent",
+            func: impls::wglMakeCurrent,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 53usize] = [
         Symbol {
             ordinal: None,
-            shim: shims::InitializeCriticalSectionAndSpinCount,
+            shim: shims::glBegin,
         },
         Symbol {
             ordinal: None,
-            shim: shims::InitializeCriticalSectionEx,
+            shim: shims::glBindTexture,
         },
         Symbol {
             ordinal: None,
-            shim: shims::InitializeSListHead,
+            shim: shims::glClear,
         },
         Symbol {
             ordinal: None,
-            shim: shims::InterlockedIncrement,
+            shim: shims::glClearColor,
         },
         Symbol {
             ordinal: None,
-            shim: shims::IsBadReadPtr,
+            shim: shims::glClearDepth,
         },
         Symbol {
             ordinal: None,
-            shim: shims::IsBadWritePtr,
+            shim: shims::glColor3f,
         },
         Symbol {
             ordinal: None,
-            shim: shims::IsDebuggerPresent,
+            shim: shims::glColor3ub,
         },
         Symbol {
             ordinal: None,
-            shim: shims::IsProcessorFeaturePresent,
+            shim: shims::glColor4f,
         },
         Symbol {
             ordinal: None,
-            shim: shims::IsValidCodePage,
+            shim: shims::glColor4ub,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LeaveCriticalSection,
+            shim: shims::glDeleteTextures,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LoadLibraryA,
+            shim: shims::glDepthFunc,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LoadLibraryExW,
+            shim: shims::glDisable,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LoadResource,
+            shim: shims::glEnable,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LocalAlloc,
+            shim: shims::glEnd,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LockResource,
+            shim: shims::glFinish,
         },
         Symbol {
             ordinal: None,
-            shim: shims::MultiByteToWideChar,
+            shim: shims::glFlush,
         },
         Symbol {
             ordinal: None,
-            shim: shims::NtCurrentTeb,
+            shim: shims::glFrustum,
         },
         Symbol {
             ordinal: None,
-            shim: shims::OutputDebugStringA,
+            shim: shims::glGenTextures,
         },
         Symbol {
             ordinal: None,
-            shim: shims::QueryPerformanceCounter,
+            shim: shims::glGetError,
         },
         Symbol {
             ordinal: None,
-            shim: shims::QueryPerformanceFrequency,
+            shim: shims::glGetString,
         },
         Symbol {
             ordinal: None,
-            shim: shims::ReadFile,
+            shim: shims::glHint,
         },
         Symbol {
             ordinal: None,
-            shim: shims::ReleaseSRWLockExclusive,
+            shim: shims::glLoadIdentity,
         },
         Symbol {
             ordinal: None,
-            shim: shims::ReleaseSRWLockShared,
+            shim: shims::glLoadMatrixf,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetEvent,
+            shim: shims::glMatrixMode,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetFilePointer,
+            shim: shims::glMultMatrixf,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetHandleCount,
+            shim: shims::glOrtho,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetLastError,
+            shim: shims::glPixelStorei,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetPriorityClass,
+            shim: shims::glPopMatrix,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetThreadDescription,
+            shim: shims::glPushMatrix,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetThreadPriority,
+            shim: shims::glRotatef,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetThreadStackGuarantee,
+            shim: shims::glScalef,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetUnhandledExceptionFilter,
+            shim: shims::glShadeModel,
         },
         Symbol {
             ordinal: None,
-            shim: shims::Sleep,
+            shim: shims::glTexCoord2f,
         },
         Symbol {
             ordinal: None,
-            shim: shims::TlsAlloc,
+            shim: shims::glTexCoord2fv,
         },
         Symbol {
             ordinal: None,
-            shim: shims::TlsFree,
+            shim: shims::glTexEnvf,
         },
         Symbol {
             ordinal: None,
-            shim: shims::TlsGetValue,
+            shim: shims::glTexEnvi,
         },
         Symbol {
             ordinal: None,
-            shim: shims::TlsSetValue,
+            shim: shims::glTexImage2D,
         },
         Symbol {
             ordinal: None,
-            shim: shims::TryAcquireSRWLockExclusive,
+            shim: shims::glTexParameterf,
         },
         Symbol {
             ordinal: None,
-            shim: shims::UnhandledExceptionFilter,
+            shim: shims::glTexParameteri,
         },
         Symbol {
             ordinal: None,
-            shim: shims::VirtualAlloc,
+            shim: shims::glTexSubImage2D,
         },
         Symbol {
             ordinal: None,
-            shim: shims::VirtualFree,
+            shim: shims::glTranslatef,
         },
         Symbol {
             ordinal: None,
-            shim: shims::VirtualProtect,
+            shim: shims::glVertex2f,
         },
         Symbol {
             ordinal: None,
-            shim: shims::WaitForSingleObject,
+            shim: shims::glVertex2i,
         },
         Symbol {
             ordinal: None,
-            shim: shims::WriteConsoleA,
+            shim: shims::glVertex3f,
         },
         Symbol {
             ordinal: None,
-            shim: shims::WriteConsoleW,
+            shim: shims::glVertex3fv,
         },
         Symbol {
             ordinal: None,
-            shim: shims::WriteFile,
+            shim: shims::glVertex4f,
         },
         Symbol {
             ordinal: None,
-            shim: shims::lstrcmpiA,
+            shim: shims::glViewport,
         },
         Symbol {
             ordinal: None,
-            shim: shims::lstrcpyA,
+            shim: shims::wglCreateContext,
         },
         Symbol {
             ordinal: None,
-            shim: shims::lstrcpyW,
+            shim: shims::wglDeleteContext,
         },
         Symbol {
             ordinal: None,
-            shim: shims::lstrlenA,
+            shim: shims::wglGetCurrentContext,
         },
         Symbol {
             ordinal: None,
-            shim: shims::lstrlenW,
+            shim: shims::wglGetCurrentDC,
         },
         Symbol {
             ordinal: None,
-            shim: shims::retrowin32_main,
+            shim: shims::wglGetProcAddress,
         },
         Symbol {
             ordinal: None,
-            shim: shims::retrowin32_thread_main,
+            shim: shims::wglMakeCurrent,
         },
     ];
     pub const DLL: BuiltinDLL = BuiltinDLL {
-        file_name: "kernel32.dll",
+        file_name: "opengl32.dll",
         exports: &EXPORTS,
     };
 }
-pub mod ntdll {
+pub mod quartz {
     use super::*;
     mod impls {
         use crate::{
@@ -2968,87 +10669,31 @@ pub mod ntdll {
             winapi::{self, stack_args::*, types::*},
         };
         use memory::Extensions;
-        use winapi::ntdll::*;
-        pub unsafe fn NtReadFile(machine: &mut Machine, esp: u32) -> u32 {
+        use winapi::quartz::*;
+        pub unsafe fn AMGetErrorTextA(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let FileHandle = <HFILE>::from_stack(mem, esp + 4u32);
-            let Event = <u32>::from_stack(mem, esp + 8u32);
-            let ApcRoutine = <u32>::from_stack(mem, esp + 12u32);
-            let ApcContext = <u32>::from_stack(mem, esp + 16u32);
-            let IoStatusBlock = <Option<&mut IO_STATUS_BLOCK>>::from_stack(mem, esp + 20u32);
-            let Buffer = <ArrayWithSizeMut<u8>>::from_stack(mem, esp + 24u32);
-            let ByteOffset = <Option<&mut u64>>::from_stack(mem, esp + 32u32);
-            let Key = <u32>::from_stack(mem, esp + 36u32);
-            winapi::ntdll::NtReadFile(
-                machine,
-                FileHandle,
-                Event,
-                ApcRoutine,
-                ApcContext,
-                IoStatusBlock,
-                Buffer,
-                ByteOffset,
-                Key,
-            )
-            .to_raw()
+            let hr = <u32>::from_stack(mem, esp + 4u32);
+            let pbuffer = <u32>::from_stack(mem, esp + 8u32);
+            let MaxLen = <u32>::from_stack(mem, esp + 12u32);
+            winapi::quartz::AMGetErrorTextA(machine, hr, pbuffer, MaxLen).to_raw()
         }
     }
     mod shims {
         use super::impls;
         use crate::shims::Shim;
-        pub const NtReadFile: Shim = Shim {
-            name: "NtReadFile",
-            func: impls::NtReadFile,
-            stack_consumed: 36u32,
+        pub const AMGetErrorTextA: Shim = Shim {
+            name: "AMGetErrorTextA",
+            func: impls::AMGetErrorTextA,
+            stack_consumed: 12u32,
             is_async: false,
         };
     }
     const EXPORTS: [Symbol; 1usize] = [Symbol {
         ordinal: None,
-        shim: shims::NtReadFile,
+        shim: shims::AMGetErrorTextA,
     }];
     pub const DLL: BuiltinDLL = BuiltinDLL {
-        file_name: "ntdll.dll",
-        exports: &EXPORTS,
-    };
-}
-pub mod ole32 {
-    use super::*;
-    mod impls {
-        use crate::{
-            machine::Machine,
-            winapi::{self, stack_args::*, types::*},
-        };
-        use memory::Extensions;
-        use winapi::ole32::*;
-    }
-    mod shims {
-        use super::impls;
-        use crate::shims::Shim;
-    }
-    const EXPORTS: [Symbol; 0usize] = [];
-    pub const DLL: BuiltinDLL = BuiltinDLL {
-        file_name: "ole32.dll",
-        exports: &EXPORTS,
-    };
-}
-pub mod oleaut32 {
-    use super::*;
-    mod impls {
-        use crate::{
-            machine::Machine,
-            winapi::{self, stack_args::*, types::*},
-        };
-        use memory::Extensions;
-        use winapi::oleaut32::*;
-    }
-    mod shims {
-        use super::impls;
-        use crate::shims::Shim;
-    }
-    const EXPORTS: [Symbol; 0usize] = [];
-    pub const DLL: BuiltinDLL = BuiltinDLL {
-        file_name: "oleaut32.dll",
+        file_name: "quartz.dll",
         exports: &EXPORTS,
     };
 }
@@ -3110,6 +10755,95 @@ pub mod retrowin32_test {
         exports: &EXPORTS,
     };
 }
+pub mod shell32 {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::shell32::*;
+        pub unsafe fn SHGetFolderPathA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hwnd = <u32>::from_stack(mem, esp + 4u32);
+            let csidl = <u32>::from_stack(mem, esp + 8u32);
+            let hToken = <u32>::from_stack(mem, esp + 12u32);
+            let dwFlags = <u32>::from_stack(mem, esp + 16u32);
+            let pszPath = <u32>::from_stack(mem, esp + 20u32);
+            winapi::shell32::SHGetFolderPathA(machine, hwnd, csidl, hToken, dwFlags, pszPath)
+                .to_raw()
+        }
+        pub unsafe fn SHGetSpecialFolderPathA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hwnd = <u32>::from_stack(mem, esp + 4u32);
+            let pszPath = <u32>::from_stack(mem, esp + 8u32);
+            let csidl = <u32>::from_stack(mem, esp + 12u32);
+            let fCreate = <bool>::from_stack(mem, esp + 16u32);
+            winapi::shell32::SHGetSpecialFolderPathA(machine, hwnd, pszPath, csidl, fCreate)
+                .to_raw()
+        }
+        pub unsafe fn ShellExecuteA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hwnd = <u32>::from_stack(mem, esp + 4u32);
+            let lpOperation = <Option<&str>>::from_stack(mem, esp + 8u32);
+            let lpFile = <Option<&str>>::from_stack(mem, esp + 12u32);
+            let lpParameters = <Option<&str>>::from_stack(mem, esp + 16u32);
+            let lpDirectory = <Option<&str>>::from_stack(mem, esp + 20u32);
+            let nShowCmd = <u32>::from_stack(mem, esp + 24u32);
+            winapi::shell32::ShellExecuteA(
+                machine,
+                hwnd,
+                lpOperation,
+                lpFile,
+                lpParameters,
+                lpDirectory,
+                nShowCmd,
+            )
+            .to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const SHGetFolderPathA: Shim = Shim {
+            name: "SHGetFolderPathA",
+            func: impls::SHGetFolderPathA,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const SHGetSpecialFolderPathA: Shim = Shim {
+            name: "SHGetSpecialFolderPathA",
+            func: impls::SHGetSpecialFolderPathA,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const ShellExecuteA: Shim = Shim {
+            name: "ShellExecuteA",
+            func: impls::ShellExecuteA,
+            stack_consumed: 24u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 3usize] = [
+        Symbol {
+            ordinal: None,
+            shim: shims::SHGetFolderPathA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SHGetSpecialFolderPathA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ShellExecuteA,
+        },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "shell32.dll",
+        exports: &EXPORTS,
+    };
+}
 pub mod ucrtbase {
     use super::*;
     mod impls {
@@ -3148,6 +10882,30 @@ pub mod ucrtbase {
             let status = <u32>::from_stack(mem, esp + 4u32);
             winapi::ucrtbase::exit(machine, status).to_raw()
         }
+        pub unsafe fn strlen(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let str = <u32>::from_stack(mem, esp + 4u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::ucrtbase::strlen(machine, str).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 0u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::ucrtbase::strlen(machine, str));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
     }
     mod shims {
         use super::impls;
@@ -3188,8 +10946,14 @@ pub mod ucrtbase {
             stack_consumed: 4u32,
             is_async: false,
         };
+        pub const strlen: Shim = Shim {
+            name: "strlen",
+            func: impls::strlen,
+            stack_consumed: 0u32,
+            is_async: true,
+        };
     }
-    const EXPORTS: [Symbol; 6usize] = [
+    const EXPORTS: [Symbol; 7usize] = [
         Symbol {
             ordinal: None,
             shim: shims::__p___argc,
@@ -3214,6 +10978,10 @@ pub mod ucrtbase {
             ordinal: None,
             shim: shims::exit,
         },
+        Symbol {
+            ordinal: None,
+            shim: shims::strlen,
+        },
     ];
     pub const DLL: BuiltinDLL = BuiltinDLL {
         file_name: "ucrtbase.dll",
@@ -3247,14 +11015,78 @@ pub mod vcruntime140 {
             let dst = <u32>::from_stack(mem, esp + 4u32);
             let src = <u32>::from_stack(mem, esp + 8u32);
             let len = <u32>::from_stack(mem, esp + 12u32);
-            winapi::vcruntime140::memcpy(machine, dst, src, len).to_raw()
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::vcruntime140::memcpy(machine, dst, src, len).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 0u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::vcruntime140::memcpy(machine, dst, src, len));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn memmove(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let dst = <u32>::from_stack(mem, esp + 4u32);
+            let src = <u32>::from_stack(mem, esp + 8u32);
+            let len = <u32>::from_stack(mem, esp + 12u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::vcruntime140::memmove(machine, dst, src, len).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 0u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::vcruntime140::memmove(machine, dst, src, len));
+                crate::shims::call_sync(pin).to_raw()
+            }
         }
         pub unsafe fn memset(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let dst = <u32>::from_stack(mem, esp + 4u32);
             let val = <u32>::from_stack(mem, esp + 8u32);
             let len = <u32>::from_stack(mem, esp + 12u32);
-            winapi::vcruntime140::memset(machine, dst, val, len).to_raw()
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::vcruntime140::memset(machine, dst, val, len).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 0u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::vcruntime140::memset(machine, dst, val, len));
+                crate::shims::call_sync(pin).to_raw()
+            }
         }
     }
     mod shims {
@@ -3272,39 +11104,123 @@ pub mod vcruntime140 {
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const memcpy: Shim = Shim {
-            name: "memcpy",
-            func: impls::memcpy,
-            stack_consumed: 0u32,
+        pub const memcpy: Shim = Shim {
+            name: "memcpy",
+            func: impls::memcpy,
+            stack_consumed: 0u32,
+            is_async: true,
+        };
+        pub const memmove: Shim = Shim {
+            name: "memmove",
+            func: impls::memmove,
+            stack_consumed: 0u32,
+            is_async: true,
+        };
+        pub const memset: Shim = Shim {
+            name: "memset",
+            func: impls::memset,
+            stack_consumed: 0u32,
+            is_async: true,
+        };
+    }
+    const EXPORTS: [Symbol; 5usize] = [
+        Symbol {
+            ordinal: None,
+            shim: shims::_CxxThrowException,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::memcmp,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::memcpy,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::memmove,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::memset,
+        },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "vcruntime140.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod version {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::version::*;
+        pub unsafe fn GetFileVersionInfoA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lptstrFilename = <Option<&str>>::from_stack(mem, esp + 4u32);
+            let dwHandle = <u32>::from_stack(mem, esp + 8u32);
+            let dwLen = <u32>::from_stack(mem, esp + 12u32);
+            let lpData = <u32>::from_stack(mem, esp + 16u32);
+            winapi::version::GetFileVersionInfoA(machine, lptstrFilename, dwHandle, dwLen, lpData)
+                .to_raw()
+        }
+        pub unsafe fn GetFileVersionInfoSizeA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lptstrFilename = <Option<&str>>::from_stack(mem, esp + 4u32);
+            let lpdwHandle = <Option<&mut u32>>::from_stack(mem, esp + 8u32);
+            winapi::version::GetFileVersionInfoSizeA(machine, lptstrFilename, lpdwHandle).to_raw()
+        }
+        pub unsafe fn VerQueryValueA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pBlock = <u32>::from_stack(mem, esp + 4u32);
+            let lpSubBlock = <Option<&str>>::from_stack(mem, esp + 8u32);
+            let lplpBuffer = <Option<&mut u32>>::from_stack(mem, esp + 12u32);
+            let puLen = <Option<&mut u32>>::from_stack(mem, esp + 16u32);
+            winapi::version::VerQueryValueA(machine, pBlock, lpSubBlock, lplpBuffer, puLen).to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const GetFileVersionInfoA: Shim = Shim {
+            name: "GetFileVersionInfoA",
+            func: impls::GetFileVersionInfoA,
+            stack_consumed: 16u32,
+            is_async: false,
+        };
+        pub const GetFileVersionInfoSizeA: Shim = Shim {
+            name: "GetFileVersionInfoSizeA",
+            func: impls::GetFileVersionInfoSizeA,
+            stack_consumed: 8u32,
             is_async: false,
         };
-        pub const memset: Shim = Shim {
-            name: "memset",
-            func: impls::memset,
-            stack_consumed: 0u32,
+        pub const VerQueryValueA: Shim = Shim {
+            name: "VerQueryValueA",
+            func: impls::VerQueryValueA,
+            stack_consumed: 16u32,
             is_async: false,
         };
     }
-    const EXPORTS: [Symbol; 4usize] = [
-        Symbol {
-            ordinal: None,
-            shim: shims::_CxxThrowException,
-        },
+    const EXPORTS: [Symbol; 3usize] = [
         Symbol {
             ordinal: None,
-            shim: shims::memcmp,
+            shim: shims::GetFileVersionInfoA,
         },
         Symbol {
             ordinal: None,
-            shim: shims::memcpy,
+            shim: shims::GetFileVersionInfoSizeA,
         },
         Symbol {
             ordinal: None,
-            shim: shims::memset,
+            shim: shims::VerQueryValueA,
         },
     ];
     pub const DLL: BuiltinDLL = BuiltinDLL {
-        file_name: "vcruntime140.dll",
+        file_name: "version.dll",
         exports: &EXPORTS,
     };
 }
@@ -3790,13 +11706,13 @@ pub mod user32 {
         pub unsafe fn LoadIconA(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hInstance = <u32>::from_stack(mem, esp + 4u32);
-            let lpIconName = <u32>::from_stack(mem, esp + 8u32);
+            let lpIconName = <ResourceKey<&str>>::from_stack(mem, esp + 8u32);
             winapi::user32::LoadIconA(machine, hInstance, lpIconName).to_raw()
         }
         pub unsafe fn LoadIconW(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hInstance = <u32>::from_stack(mem, esp + 4u32);
-            let lpIconName = <u32>::from_stack(mem, esp + 8u32);
+            let lpIconName = <ResourceKey<&Str16>>::from_stack(mem, esp + 8u32);
             winapi::user32::LoadIconW(machine, hInstance, lpIconName).to_raw()
         }
         pub unsafe fn LoadImageA(machine: &mut Machine, esp: u32) -> u32 {
@@ -3806,7 +11722,7 @@ pub mod user32 {
             let typ = <u32>::from_stack(mem, esp + 12u32);
             let cx = <u32>::from_stack(mem, esp + 16u32);
             let cy = <u32>::from_stack(mem, esp + 20u32);
-            let fuLoad = <u32>::from_stack(mem, esp + 24u32);
+            let fuLoad = <Result<LR, u32>>::from_stack(mem, esp + 24u32);
             winapi::user32::LoadImageA(machine, hInstance, name, typ, cx, cy, fuLoad).to_raw()
         }
         pub unsafe fn LoadMenuW(machine: &mut Machine, esp: u32) -> u32 {
@@ -3871,15 +11787,41 @@ pub mod user32 {
             let wMsgFilterMin = <u32>::from_stack(mem, esp + 12u32);
             let wMsgFilterMax = <u32>::from_stack(mem, esp + 16u32);
             let wRemoveMsg = <Result<RemoveMsg, u32>>::from_stack(mem, esp + 20u32);
-            winapi::user32::PeekMessageA(
-                machine,
-                lpMsg,
-                hWnd,
-                wMsgFilterMin,
-                wMsgFilterMax,
-                wRemoveMsg,
-            )
-            .to_raw()
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::user32::PeekMessageA(
+                        machine,
+                        lpMsg,
+                        hWnd,
+                        wMsgFilterMin,
+                        wMsgFilterMax,
+                        wRemoveMsg,
+                    )
+                    .await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 20u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::user32::PeekMessageA(
+                    machine,
+                    lpMsg,
+                    hWnd,
+                    wMsgFilterMin,
+                    wMsgFilterMax,
+                    wRemoveMsg
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
         }
         pub unsafe fn PeekMessageW(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
@@ -3888,15 +11830,41 @@ pub mod user32 {
             let wMsgFilterMin = <u32>::from_stack(mem, esp + 12u32);
             let wMsgFilterMax = <u32>::from_stack(mem, esp + 16u32);
             let wRemoveMsg = <Result<RemoveMsg, u32>>::from_stack(mem, esp + 20u32);
-            winapi::user32::PeekMessageW(
-                machine,
-                lpMsg,
-                hWnd,
-                wMsgFilterMin,
-                wMsgFilterMax,
-                wRemoveMsg,
-            )
-            .to_raw()
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::user32::PeekMessageW(
+                        machine,
+                        lpMsg,
+                        hWnd,
+                        wMsgFilterMin,
+                        wMsgFilterMax,
+                        wRemoveMsg,
+                    )
+                    .await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 20u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::user32::PeekMessageW(
+                    machine,
+                    lpMsg,
+                    hWnd,
+                    wMsgFilterMin,
+                    wMsgFilterMax,
+                    wRemoveMsg
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
         }
         pub unsafe fn PostQuitMessage(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
@@ -3934,6 +11902,66 @@ pub mod user32 {
             let hdc = <HDC>::from_stack(mem, esp + 8u32);
             winapi::user32::ReleaseDC(machine, hwnd, hdc).to_raw()
         }
+        pub unsafe fn SendMessageA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hWnd = <HWND>::from_stack(mem, esp + 4u32);
+            let Msg = <u32>::from_stack(mem, esp + 8u32);
+            let wParam = <u32>::from_stack(mem, esp + 12u32);
+            let lParam = <u32>::from_stack(mem, esp + 16u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result =
+                        winapi::user32::SendMessageA(machine, hWnd, Msg, wParam, lParam).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 16u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::user32::SendMessageA(
+                    machine, hWnd, Msg, wParam, lParam
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn SendMessageW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hWnd = <HWND>::from_stack(mem, esp + 4u32);
+            let Msg = <u32>::from_stack(mem, esp + 8u32);
+            let wParam = <u32>::from_stack(mem, esp + 12u32);
+            let lParam = <u32>::from_stack(mem, esp + 16u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result =
+                        winapi::user32::SendMessageW(machine, hWnd, Msg, wParam, lParam).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 16u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::user32::SendMessageW(
+                    machine, hWnd, Msg, wParam, lParam
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
         pub unsafe fn SetCapture(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let hwnd = <HWND>::from_stack(mem, esp + 4u32);
@@ -3995,6 +12023,12 @@ pub mod user32 {
             let lpString = <Option<&str>>::from_stack(mem, esp + 8u32);
             winapi::user32::SetWindowTextA(machine, hWnd, lpString).to_raw()
         }
+        pub unsafe fn SetWindowTextW(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hWnd = <HWND>::from_stack(mem, esp + 4u32);
+            let lpString = <Option<&Str16>>::from_stack(mem, esp + 8u32);
+            winapi::user32::SetWindowTextW(machine, hWnd, lpString).to_raw()
+        }
         pub unsafe fn ShowCursor(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
             let bShow = <bool>::from_stack(mem, esp + 4u32);
@@ -4368,13 +12402,13 @@ pub mod user32 {
             name: "PeekMessageA",
             func: impls::PeekMessageA,
             stack_consumed: 20u32,
-            is_async: false,
+            is_async: true,
         };
         pub const PeekMessageW: Shim = Shim {
             name: "PeekMessageW",
             func: impls::PeekMessageW,
             stack_consumed: 20u32,
-            is_async: false,
+            is_async: true,
         };
         pub const PostQuitMessage: Shim = Shim {
             name: "PostQuitMessage",
@@ -4418,6 +12452,18 @@ pub mod user32 {
             stack_consumed: 8u32,
             is_async: false,
         };
+        pub const SendMessageA: Shim = Shim {
+            name: "SendMessageA",
+            func: impls::SendMessageA,
+            stack_consumed: 16u32,
+            is_async: true,
+        };
+        pub const SendMessageW: Shim = Shim {
+            name: "SendMessageW",
+            func: impls::SendMessageW,
+            stack_consumed: 16u32,
+            is_async: true,
+        };
         pub const SetCapture: Shim = Shim {
             name: "SetCapture",
             func: impls::SetCapture,
@@ -4472,6 +12518,12 @@ pub mod user32 {
             stack_consumed: 8u32,
             is_async: false,
         };
+        pub const SetWindowTextW: Shim = Shim {
+            name: "SetWindowTextW",
+            func: impls::SetWindowTextW,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
         pub const ShowCursor: Shim = Shim {
             name: "ShowCursor",
             func: impls::ShowCursor,
@@ -4521,7 +12573,7 @@ pub mod user32 {
             is_async: false,
         };
     }
-    const EXPORTS: [Symbol; 73usize] = [
+    const EXPORTS: [Symbol; 76usize] = [
         Symbol {
             ordinal: None,
             shim: shims::AdjustWindowRect,
@@ -4648,179 +12700,699 @@ pub mod user32 {
         },
         Symbol {
             ordinal: None,
-            shim: shims::InvalidateRect,
+            shim: shims::InvalidateRect,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::InvalidateRgn,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LoadAcceleratorsW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LoadBitmapA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LoadCursorA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LoadCursorW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LoadIconA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LoadIconW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LoadImageA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LoadMenuW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LoadStringA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::LoadStringW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::MapWindowPoints,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::MessageBoxA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::MessageBoxW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::MoveWindow,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::PeekMessageA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::PeekMessageW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::PostQuitMessage,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::PtInRect,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegisterClassA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegisterClassExA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::RegisterClassW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ReleaseCapture,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::ReleaseDC,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SendMessageA,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SendMessageW,
+        },
+        Symbol {
+            ordinal: None,
+            shim: shims::SetCapture,
         },
         Symbol {
             ordinal: None,
-            shim: shims::InvalidateRgn,
+            shim: shims::SetCursor,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LoadAcceleratorsW,
+            shim: shims::SetFocus,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LoadBitmapA,
+            shim: shims::SetForegroundWindow,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LoadCursorA,
+            shim: shims::SetMenu,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LoadCursorW,
+            shim: shims::SetRect,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LoadIconA,
+            shim: shims::SetTimer,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LoadIconW,
+            shim: shims::SetWindowPos,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LoadImageA,
+            shim: shims::SetWindowTextA,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LoadMenuW,
+            shim: shims::SetWindowTextW,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LoadStringA,
+            shim: shims::ShowCursor,
         },
         Symbol {
             ordinal: None,
-            shim: shims::LoadStringW,
+            shim: shims::ShowWindow,
         },
         Symbol {
             ordinal: None,
-            shim: shims::MapWindowPoints,
+            shim: shims::TranslateAcceleratorW,
         },
         Symbol {
             ordinal: None,
-            shim: shims::MessageBoxA,
+            shim: shims::TranslateMessage,
         },
         Symbol {
             ordinal: None,
-            shim: shims::MessageBoxW,
+            shim: shims::UpdateWindow,
         },
         Symbol {
             ordinal: None,
-            shim: shims::MoveWindow,
+            shim: shims::ValidateRect,
         },
         Symbol {
             ordinal: None,
-            shim: shims::PeekMessageA,
+            shim: shims::WaitMessage,
         },
         Symbol {
             ordinal: None,
-            shim: shims::PeekMessageW,
+            shim: shims::wsprintfA,
         },
+    ];
+    pub const DLL: BuiltinDLL = BuiltinDLL {
+        file_name: "user32.dll",
+        exports: &EXPORTS,
+    };
+}
+pub mod winmm {
+    use super::*;
+    mod impls {
+        use crate::{
+            machine::Machine,
+            winapi::{self, stack_args::*, types::*},
+        };
+        use memory::Extensions;
+        use winapi::winmm::*;
+        pub unsafe fn PlaySoundA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let pszSound = <u32>::from_stack(mem, esp + 4u32);
+            let hmod = <u32>::from_stack(mem, esp + 8u32);
+            let fdwSound = <Result<SND, u32>>::from_stack(mem, esp + 12u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::winmm::PlaySoundA(machine, pszSound, hmod, fdwSound).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 12u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin =
+                    std::pin::pin!(winapi::winmm::PlaySoundA(machine, pszSound, hmod, fdwSound));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn joyGetDevCapsA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let uJoyID = <u32>::from_stack(mem, esp + 4u32);
+            let pjc = <Option<&mut JOYCAPSA>>::from_stack(mem, esp + 8u32);
+            let cbjc = <u32>::from_stack(mem, esp + 12u32);
+            winapi::winmm::joyGetDevCapsA(machine, uJoyID, pjc, cbjc).to_raw()
+        }
+        pub unsafe fn joyGetNumDevs(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::winmm::joyGetNumDevs(machine).to_raw()
+        }
+        pub unsafe fn joyGetPos(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let uJoyID = <u32>::from_stack(mem, esp + 4u32);
+            let pji = <Option<&mut JOYINFO>>::from_stack(mem, esp + 8u32);
+            winapi::winmm::joyGetPos(machine, uJoyID, pji).to_raw()
+        }
+        pub unsafe fn joyGetPosEx(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let uJoyID = <u32>::from_stack(mem, esp + 4u32);
+            let pji = <Option<&mut JOYINFOEX>>::from_stack(mem, esp + 8u32);
+            winapi::winmm::joyGetPosEx(machine, uJoyID, pji).to_raw()
+        }
+        pub unsafe fn mciGetErrorStringA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let mcierr = <u32>::from_stack(mem, esp + 4u32);
+            let pszText = <ArrayWithSizeMut<u8>>::from_stack(mem, esp + 8u32);
+            winapi::winmm::mciGetErrorStringA(machine, mcierr, pszText).to_raw()
+        }
+        pub unsafe fn mciSendCommandA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let IDDevice = <MCIDEVICEID>::from_stack(mem, esp + 4u32);
+            let uMsg = <u32>::from_stack(mem, esp + 8u32);
+            let fdwCommand = <u32>::from_stack(mem, esp + 12u32);
+            let dwParam = <u32>::from_stack(mem, esp + 16u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::winmm::mciSendCommandA(
+                        machine, IDDevice, uMsg, fdwCommand, dwParam,
+                    )
+                    .await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 16u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::winmm::mciSendCommandA(
+                    machine, IDDevice, uMsg, fdwCommand, dwParam
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn mciSendStringA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpstrCommand = <Option<&str>>::from_stack(mem, esp + 4u32);
+            let lpstrReturnString = <ArrayWithSizeMut<'_, u8>>::from_stack(mem, esp + 8u32);
+            let hwndCallback = <HWND>::from_stack(mem, esp + 16u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::winmm::mciSendStringA(
+                        machine,
+                        lpstrCommand,
+                        lpstrReturnString,
+                        hwndCallback,
+                    )
+                    .await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 16u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::winmm::mciSendStringA(
+                    machine,
+                    lpstrCommand,
+                    lpstrReturnString,
+                    hwndCallback
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn sndPlaySoundA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let lpszSound = <u32>::from_stack(mem, esp + 4u32);
+            let fuSound = <Result<SND, u32>>::from_stack(mem, esp + 8u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::winmm::sndPlaySoundA(machine, lpszSound, fuSound).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 8u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::winmm::sndPlaySoundA(machine, lpszSound, fuSound));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn timeBeginPeriod(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let uPeriod = <u32>::from_stack(mem, esp + 4u32);
+            winapi::winmm::timeBeginPeriod(machine, uPeriod).to_raw()
+        }
+        pub unsafe fn timeEndPeriod(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let uPeriod = <u32>::from_stack(mem, esp + 4u32);
+            winapi::winmm::timeEndPeriod(machine, uPeriod).to_raw()
+        }
+        pub unsafe fn timeGetDevCaps(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let ptc = <Option<&mut TIMECAPS>>::from_stack(mem, esp + 4u32);
+            let cbtc = <u32>::from_stack(mem, esp + 8u32);
+            winapi::winmm::timeGetDevCaps(machine, ptc, cbtc).to_raw()
+        }
+        pub unsafe fn timeGetTime(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::winmm::timeGetTime(machine).to_raw()
+        }
+        pub unsafe fn timeKillEvent(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let uTimerID = <u32>::from_stack(mem, esp + 4u32);
+            winapi::winmm::timeKillEvent(machine, uTimerID).to_raw()
+        }
+        pub unsafe fn timeSetEvent(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let uDelay = <u32>::from_stack(mem, esp + 4u32);
+            let uResolution = <u32>::from_stack(mem, esp + 8u32);
+            let lpTimeProc = <u32>::from_stack(mem, esp + 12u32);
+            let dwUser = <u32>::from_stack(mem, esp + 16u32);
+            let fuEvent = <u32>::from_stack(mem, esp + 20u32);
+            winapi::winmm::timeSetEvent(machine, uDelay, uResolution, lpTimeProc, dwUser, fuEvent)
+                .to_raw()
+        }
+        pub unsafe fn waveOutClose(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hwo = <HWAVEOUT>::from_stack(mem, esp + 4u32);
+            winapi::winmm::waveOutClose(machine, hwo).to_raw()
+        }
+        pub unsafe fn waveOutGetDevCapsA(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let uDeviceID = <u32>::from_stack(mem, esp + 4u32);
+            let pwoc = <Option<&mut WAVEOUTCAPS>>::from_stack(mem, esp + 8u32);
+            let cbwoc = <u32>::from_stack(mem, esp + 12u32);
+            winapi::winmm::waveOutGetDevCapsA(machine, uDeviceID, pwoc, cbwoc).to_raw()
+        }
+        pub unsafe fn waveOutGetNumDevs(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            winapi::winmm::waveOutGetNumDevs(machine).to_raw()
+        }
+        pub unsafe fn waveOutGetPosition(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hwo = <HWAVEOUT>::from_stack(mem, esp + 4u32);
+            let pmmt = <Option<&mut MMTIME>>::from_stack(mem, esp + 8u32);
+            let cbmmt = <u32>::from_stack(mem, esp + 12u32);
+            winapi::winmm::waveOutGetPosition(machine, hwo, pmmt, cbmmt).to_raw()
+        }
+        pub unsafe fn waveOutOpen(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let phwo = <Option<&mut HWAVEOUT>>::from_stack(mem, esp + 4u32);
+            let uDeviceID = <u32>::from_stack(mem, esp + 8u32);
+            let pwfx = <Option<&WAVEFORMATEX>>::from_stack(mem, esp + 12u32);
+            let dwCallback = <u32>::from_stack(mem, esp + 16u32);
+            let dwInstance = <u32>::from_stack(mem, esp + 20u32);
+            let fdwOpen = <Result<WaveOutOpenFlags, u32>>::from_stack(mem, esp + 24u32);
+            winapi::winmm::waveOutOpen(
+                machine, phwo, uDeviceID, pwfx, dwCallback, dwInstance, fdwOpen,
+            )
+            .to_raw()
+        }
+        pub unsafe fn waveOutPrepareHeader(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hwo = <HWAVEOUT>::from_stack(mem, esp + 4u32);
+            let pwh = <Option<&mut WAVEHDR>>::from_stack(mem, esp + 8u32);
+            let cbwh = <u32>::from_stack(mem, esp + 12u32);
+            winapi::winmm::waveOutPrepareHeader(machine, hwo, pwh, cbwh).to_raw()
+        }
+        pub unsafe fn waveOutReset(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hwo = <HWAVEOUT>::from_stack(mem, esp + 4u32);
+            winapi::winmm::waveOutReset(machine, hwo).to_raw()
+        }
+        pub unsafe fn waveOutUnprepareHeader(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hwo = <HWAVEOUT>::from_stack(mem, esp + 4u32);
+            let pwh = <Option<&mut WAVEHDR>>::from_stack(mem, esp + 8u32);
+            let cbwh = <u32>::from_stack(mem, esp + 12u32);
+            winapi::winmm::waveOutUnprepareHeader(machine, hwo, pwh, cbwh).to_raw()
+        }
+        pub unsafe fn waveOutWrite(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hwo = <HWAVEOUT>::from_stack(mem, esp + 4u32);
+            let pwh = <u32>::from_stack(mem, esp + 8u32);
+            let cbwh = <u32>::from_stack(mem, esp + 12u32);
+            winapi::winmm::waveOutWrite(machine, hwo, pwh, cbwh).to_raw()
+        }
+    }
+    mod shims {
+        use super::impls;
+        use crate::shims::Shim;
+        pub const PlaySoundA: Shim = Shim {
+            name: "PlaySoundA",
+            func: impls::PlaySoundA,
+            stack_consumed: 12u32,
+            is_async: true,
+        };
+        pub const joyGetDevCapsA: Shim = Shim {
+            name: "joyGetDevCapsA",
+            func: impls::joyGetDevCapsA,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const joyGetNumDevs: Shim = Shim {
+            name: "joyGetNumDevs",
+            func: impls::joyGetNumDevs,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const joyGetPos: Shim = Shim {
+            name: "joyGetPos",
+            func: impls::joyGetPos,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const joyGetPosEx: Shim = Shim {
+            name: "joyGetPosEx",
+            func: impls::joyGetPosEx,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const mciGetErrorStringA: Shim = Shim {
+            name: "mciGetErrorStringA",
+            func: impls::mciGetErrorStringA,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const mciSendCommandA: Shim = Shim {
+            name: "mciSendCommandA",
+            func: impls::mciSendCommandA,
+            stack_consumed: 16u32,
+            is_async: true,
+        };
+        pub const mciSendStringA: Shim = Shim {
+            name: "mciSendStringA",
+            func: impls::mciSendStringA,
+            stack_consumed: 16u32,
+            is_async: true,
+        };
+        pub const sndPlaySoundA: Shim = Shim {
+            name: "sndPlaySoundA",
+            func: impls::sndPlaySoundA,
+            stack_consumed: 8u32,
+            is_async: true,
+        };
+        pub const timeBeginPeriod: Shim = Shim {
+            name: "timeBeginPeriod",
+            func: impls::timeBeginPeriod,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const timeEndPeriod: Shim = Shim {
+            name: "timeEndPeriod",
+            func: impls::timeEndPeriod,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const timeGetDevCaps: Shim = Shim {
+            name: "timeGetDevCaps",
+            func: impls::timeGetDevCaps,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const timeGetTime: Shim = Shim {
+            name: "timeGetTime",
+            func: impls::timeGetTime,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const timeKillEvent: Shim = Shim {
+            name: "timeKillEvent",
+            func: impls::timeKillEvent,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const timeSetEvent: Shim = Shim {
+            name: "timeSetEvent",
+            func: impls::timeSetEvent,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const waveOutClose: Shim = Shim {
+            name: "waveOutClose",
+            func: impls::waveOutClose,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const waveOutGetDevCapsA: Shim = Shim {
+            name: "waveOutGetDevCapsA",
+            func: impls::waveOutGetDevCapsA,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const waveOutGetNumDevs: Shim = Shim {
+            name: "waveOutGetNumDevs",
+            func: impls::waveOutGetNumDevs,
+            stack_consumed: 0u32,
+            is_async: false,
+        };
+        pub const waveOutGetPosition: Shim = Shim {
+            name: "waveOutGetPosition",
+            func: impls::waveOutGetPosition,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const waveOutOpen: Shim = Shim {
+            name: "waveOutOpen",
+            func: impls::waveOutOpen,
+            stack_consumed: 24u32,
+            is_async: false,
+        };
+        pub const waveOutPrepareHeader: Shim = Shim {
+            name: "waveOutPrepareHeader",
+            func: impls::waveOutPrepareHeader,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const waveOutReset: Shim = Shim {
+            name: "waveOutReset",
+            func: impls::waveOutReset,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const waveOutUnprepareHeader: Shim = Shim {
+            name: "waveOutUnprepareHeader",
+            func: impls::waveOutUnprepareHeader,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+        pub const waveOutWrite: Shim = Shim {
+            name: "waveOutWrite",
+            func: impls::waveOutWrite,
+            stack_consumed: 12u32,
+            is_async: false,
+        };
+    }
+    const EXPORTS: [Symbol; 24usize] = [
         Symbol {
             ordinal: None,
-            shim: shims::PostQuitMessage,
+            shim: shims::PlaySoundA,
         },
         Symbol {
             ordinal: None,
-            shim: shims::PtInRect,
+            shim: shims::joyGetDevCapsA,
         },
         Symbol {
             ordinal: None,
-            shim: shims::RegisterClassA,
+            shim: shims::joyGetNumDevs,
         },
         Symbol {
             ordinal: None,
-            shim: shims::RegisterClassExA,
+            shim: shims::joyGetPos,
         },
         Symbol {
             ordinal: None,
-            shim: shims::RegisterClassW,
+            shim: shims::joyGetPosEx,
         },
         Symbol {
             ordinal: None,
-            shim: shims::ReleaseCapture,
+            shim: shims::mciGetErrorStringA,
         },
         Symbol {
             ordinal: None,
-            shim: shims::ReleaseDC,
+            shim: shims::mciSendCommandA,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetCapture,
+            shim: shims::mciSendStringA,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetCursor,
+            shim: shims::sndPlaySoundA,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetFocus,
+            shim: shims::timeBeginPeriod,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetForegroundWindow,
+            shim: shims::timeEndPeriod,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetMenu,
+            shim: shims::timeGetDevCaps,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetRect,
+            shim: shims::timeGetTime,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetTimer,
+            shim: shims::timeKillEvent,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetWindowPos,
+            shim: shims::timeSetEvent,
         },
         Symbol {
             ordinal: None,
-            shim: shims::SetWindowTextA,
+            shim: shims::waveOutClose,
         },
         Symbol {
             ordinal: None,
-            shim: shims::ShowCursor,
+            shim: shims::waveOutGetDevCapsA,
         },
         Symbol {
             ordinal: None,
-            shim: shims::ShowWindow,
+            shim: shims::waveOutGetNumDevs,
         },
         Symbol {
             ordinal: None,
-            shim: shims::TranslateAcceleratorW,
+            shim: shims::waveOutGetPosition,
         },
         Symbol {
             ordinal: None,
-            shim: shims::TranslateMessage,
+            shim: shims::waveOutOpen,
         },
         Symbol {
             ordinal: None,
-            shim: shims::UpdateWindow,
+            shim: shims::waveOutPrepareHeader,
         },
         Symbol {
             ordinal: None,
-            shim: shims::ValidateRect,
+            shim: shims::waveOutReset,
         },
         Symbol {
             ordinal: None,
-            shim: shims::WaitMessage,
+            shim: shims::waveOutUnprepareHeader,
         },
         Symbol {
             ordinal: None,
-            shim: shims::wsprintfA,
+            shim: shims::waveOutWrite,
         },
     ];
     pub const DLL: BuiltinDLL = BuiltinDLL {
-        file_name: "user32.dll",
+        file_name: "winmm.dll",
         exports: &EXPORTS,
     };
 }
-pub mod winmm {
+pub mod ws2_32 {
     use super::*;
     mod impls {
         use crate::{
@@ -4828,200 +13400,673 @@ pub mod winmm {
             winapi::{self, stack_args::*, types::*},
         };
         use memory::Extensions;
-        use winapi::winmm::*;
-        pub unsafe fn timeBeginPeriod(machine: &mut Machine, esp: u32) -> u32 {
+        use winapi::ws2_32::*;
+        pub unsafe fn WSAAsyncSelect(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let uPeriod = <u32>::from_stack(mem, esp + 4u32);
-            winapi::winmm::timeBeginPeriod(machine, uPeriod).to_raw()
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let hWnd = <HWND>::from_stack(mem, esp + 8u32);
+            let wMsg = <u32>::from_stack(mem, esp + 12u32);
+            let lEvent = <u32>::from_stack(mem, esp + 16u32);
+            winapi::ws2_32::WSAAsyncSelect(machine, s, hWnd, wMsg, lEvent).to_raw()
         }
-        pub unsafe fn timeGetTime(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn WSACleanup(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::winmm::timeGetTime(machine).to_raw()
+            winapi::ws2_32::WSACleanup(machine).to_raw()
         }
-        pub unsafe fn timeSetEvent(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn WSAGetLastError(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let uDelay = <u32>::from_stack(mem, esp + 4u32);
-            let uResolution = <u32>::from_stack(mem, esp + 8u32);
-            let lpTimeProc = <u32>::from_stack(mem, esp + 12u32);
-            let dwUser = <u32>::from_stack(mem, esp + 16u32);
-            let fuEvent = <u32>::from_stack(mem, esp + 20u32);
-            winapi::winmm::timeSetEvent(machine, uDelay, uResolution, lpTimeProc, dwUser, fuEvent)
-                .to_raw()
+            winapi::ws2_32::WSAGetLastError(machine).to_raw()
         }
-        pub unsafe fn waveOutClose(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn WSASetLastError(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hwo = <HWAVEOUT>::from_stack(mem, esp + 4u32);
-            winapi::winmm::waveOutClose(machine, hwo).to_raw()
+            let iError = <u32>::from_stack(mem, esp + 4u32);
+            winapi::ws2_32::WSASetLastError(machine, iError).to_raw()
         }
-        pub unsafe fn waveOutGetDevCapsA(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn WSAStartup(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let uDeviceID = <u32>::from_stack(mem, esp + 4u32);
-            let pwoc = <Option<&mut WAVEOUTCAPS>>::from_stack(mem, esp + 8u32);
-            let cbwoc = <u32>::from_stack(mem, esp + 12u32);
-            winapi::winmm::waveOutGetDevCapsA(machine, uDeviceID, pwoc, cbwoc).to_raw()
+            let wVersionRequested = <u32>::from_stack(mem, esp + 4u32);
+            let lpWSAData = <Option<&mut WSADATA>>::from_stack(mem, esp + 8u32);
+            winapi::ws2_32::WSAStartup(machine, wVersionRequested, lpWSAData).to_raw()
+        }
+        pub unsafe fn __WSAFDIsSet(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let set = <u32>::from_stack(mem, esp + 8u32);
+            winapi::ws2_32::__WSAFDIsSet(machine, s, set).to_raw()
+        }
+        pub unsafe fn accept(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let addr = <u32>::from_stack(mem, esp + 8u32);
+            let addrlen = <u32>::from_stack(mem, esp + 12u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::ws2_32::accept(machine, s, addr, addrlen).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 12u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::ws2_32::accept(machine, s, addr, addrlen));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn bind(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let name = <u32>::from_stack(mem, esp + 8u32);
+            let namelen = <i32>::from_stack(mem, esp + 12u32);
+            winapi::ws2_32::bind(machine, s, name, namelen).to_raw()
+        }
+        pub unsafe fn closesocket(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            winapi::ws2_32::closesocket(machine, s).to_raw()
+        }
+        pub unsafe fn connect(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let name = <u32>::from_stack(mem, esp + 8u32);
+            let namelen = <i32>::from_stack(mem, esp + 12u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::ws2_32::connect(machine, s, name, namelen).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 12u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::ws2_32::connect(machine, s, name, namelen));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn gethostbyname(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let name = <Option<&str>>::from_stack(mem, esp + 4u32);
+            winapi::ws2_32::gethostbyname(machine, name).to_raw()
+        }
+        pub unsafe fn gethostname(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let name = <u32>::from_stack(mem, esp + 4u32);
+            let namelen = <i32>::from_stack(mem, esp + 8u32);
+            winapi::ws2_32::gethostname(machine, name, namelen).to_raw()
+        }
+        pub unsafe fn getpeername(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let name = <u32>::from_stack(mem, esp + 8u32);
+            let namelen = <Option<&mut i32>>::from_stack(mem, esp + 12u32);
+            winapi::ws2_32::getpeername(machine, s, name, namelen).to_raw()
+        }
+        pub unsafe fn getsockname(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let name = <u32>::from_stack(mem, esp + 8u32);
+            let namelen = <Option<&mut i32>>::from_stack(mem, esp + 12u32);
+            winapi::ws2_32::getsockname(machine, s, name, namelen).to_raw()
+        }
+        pub unsafe fn getsockopt(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let level = <u32>::from_stack(mem, esp + 8u32);
+            let optname = <u32>::from_stack(mem, esp + 12u32);
+            let optval = <u32>::from_stack(mem, esp + 16u32);
+            let optlen = <Option<&mut i32>>::from_stack(mem, esp + 20u32);
+            winapi::ws2_32::getsockopt(machine, s, level, optname, optval, optlen).to_raw()
+        }
+        pub unsafe fn htonl(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hostlong = <u32>::from_stack(mem, esp + 4u32);
+            winapi::ws2_32::htonl(machine, hostlong).to_raw()
+        }
+        pub unsafe fn htons(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let hostshort = <u32>::from_stack(mem, esp + 4u32);
+            winapi::ws2_32::htons(machine, hostshort).to_raw()
+        }
+        pub unsafe fn inet_addr(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let cp = <Option<&str>>::from_stack(mem, esp + 4u32);
+            winapi::ws2_32::inet_addr(machine, cp).to_raw()
+        }
+        pub unsafe fn inet_ntoa(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let in_ = <u32>::from_stack(mem, esp + 4u32);
+            winapi::ws2_32::inet_ntoa(machine, in_).to_raw()
+        }
+        pub unsafe fn ioctlsocket(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let cmd = <u32>::from_stack(mem, esp + 8u32);
+            let argp = <Option<&mut u32>>::from_stack(mem, esp + 12u32);
+            winapi::ws2_32::ioctlsocket(machine, s, cmd, argp).to_raw()
+        }
+        pub unsafe fn listen(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let backlog = <i32>::from_stack(mem, esp + 8u32);
+            winapi::ws2_32::listen(machine, s, backlog).to_raw()
+        }
+        pub unsafe fn ntohl(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let netlong = <u32>::from_stack(mem, esp + 4u32);
+            winapi::ws2_32::ntohl(machine, netlong).to_raw()
+        }
+        pub unsafe fn ntohs(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let netshort = <u32>::from_stack(mem, esp + 4u32);
+            winapi::ws2_32::ntohs(machine, netshort).to_raw()
+        }
+        pub unsafe fn recv(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let buf = <u32>::from_stack(mem, esp + 8u32);
+            let len = <i32>::from_stack(mem, esp + 12u32);
+            let flags = <u32>::from_stack(mem, esp + 16u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::ws2_32::recv(machine, s, buf, len, flags).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 16u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::ws2_32::recv(machine, s, buf, len, flags));
+                crate::shims::call_sync(pin).to_raw()
+            }
+        }
+        pub unsafe fn recvfrom(machine: &mut Machine, esp: u32) -> u32 {
+            let mem = machine.mem().detach();
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let buf = <u32>::from_stack(mem, esp + 8u32);
+            let len = <i32>::from_stack(mem, esp + 12u32);
+            let flags = <u32>::from_stack(mem, esp + 16u32);
+            let from = <u32>::from_stack(mem, esp + 20u32);
+            let fromlen = <u32>::from_stack(mem, esp + 24u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result =
+                        winapi::ws2_32::recvfrom(machine, s, buf, len, flags, from, fromlen).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 24u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::ws2_32::recvfrom(
+                    machine, s, buf, len, flags, from, fromlen
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
         }
-        pub unsafe fn waveOutGetNumDevs(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn select(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            winapi::winmm::waveOutGetNumDevs(machine).to_raw()
+            let nfds = <i32>::from_stack(mem, esp + 4u32);
+            let readfds = <u32>::from_stack(mem, esp + 8u32);
+            let writefds = <u32>::from_stack(mem, esp + 12u32);
+            let exceptfds = <u32>::from_stack(mem, esp + 16u32);
+            let timeout = <u32>::from_stack(mem, esp + 20u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::ws2_32::select(
+                        machine, nfds, readfds, writefds, exceptfds, timeout,
+                    )
+                    .await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 20u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::ws2_32::select(
+                    machine, nfds, readfds, writefds, exceptfds, timeout
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
         }
-        pub unsafe fn waveOutGetPosition(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn send(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hwo = <HWAVEOUT>::from_stack(mem, esp + 4u32);
-            let pmmt = <Option<&mut MMTIME>>::from_stack(mem, esp + 8u32);
-            let cbmmt = <u32>::from_stack(mem, esp + 12u32);
-            winapi::winmm::waveOutGetPosition(machine, hwo, pmmt, cbmmt).to_raw()
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let buf = <u32>::from_stack(mem, esp + 8u32);
+            let len = <i32>::from_stack(mem, esp + 12u32);
+            let flags = <u32>::from_stack(mem, esp + 16u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result = winapi::ws2_32::send(machine, s, buf, len, flags).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 16u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::ws2_32::send(machine, s, buf, len, flags));
+                crate::shims::call_sync(pin).to_raw()
+            }
         }
-        pub unsafe fn waveOutOpen(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn sendto(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let phwo = <Option<&mut HWAVEOUT>>::from_stack(mem, esp + 4u32);
-            let uDeviceID = <u32>::from_stack(mem, esp + 8u32);
-            let pwfx = <Option<&WAVEFORMATEX>>::from_stack(mem, esp + 12u32);
-            let dwCallback = <u32>::from_stack(mem, esp + 16u32);
-            let dwInstance = <u32>::from_stack(mem, esp + 20u32);
-            let fdwOpen = <Result<WaveOutOpenFlags, u32>>::from_stack(mem, esp + 24u32);
-            winapi::winmm::waveOutOpen(
-                machine, phwo, uDeviceID, pwfx, dwCallback, dwInstance, fdwOpen,
-            )
-            .to_raw()
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let buf = <u32>::from_stack(mem, esp + 8u32);
+            let len = <i32>::from_stack(mem, esp + 12u32);
+            let flags = <u32>::from_stack(mem, esp + 16u32);
+            let to = <u32>::from_stack(mem, esp + 20u32);
+            let tolen = <i32>::from_stack(mem, esp + 24u32);
+            #[cfg(feature = "x86-emu")]
+            {
+                let m: *mut Machine = machine;
+                let result = async move {
+                    use memory::Extensions;
+                    let machine = unsafe { &mut *m };
+                    let result =
+                        winapi::ws2_32::sendto(machine, s, buf, len, flags, to, tolen).await;
+                    let regs = &mut machine.emu.x86.cpu_mut().regs;
+                    regs.eip = machine.emu.memory.mem().get_pod::<u32>(esp);
+                    *regs.get32_mut(x86::Register::ESP) += 24u32 + 4;
+                    regs.set32(x86::Register::EAX, result.to_raw());
+                };
+                machine.emu.x86.cpu_mut().call_async(Box::pin(result));
+                0
+            }
+            #[cfg(any(feature = "x86-64", feature = "x86-unicorn"))]
+            {
+                let pin = std::pin::pin!(winapi::ws2_32::sendto(
+                    machine, s, buf, len, flags, to, tolen
+                ));
+                crate::shims::call_sync(pin).to_raw()
+            }
         }
-        pub unsafe fn waveOutPrepareHeader(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn setsockopt(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hwo = <HWAVEOUT>::from_stack(mem, esp + 4u32);
-            let pwh = <Option<&WAVEHDR>>::from_stack(mem, esp + 8u32);
-            let cbwh = <u32>::from_stack(mem, esp + 12u32);
-            winapi::winmm::waveOutPrepareHeader(machine, hwo, pwh, cbwh).to_raw()
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let level = <u32>::from_stack(mem, esp + 8u32);
+            let optname = <u32>::from_stack(mem, esp + 12u32);
+            let optval = <u32>::from_stack(mem, esp + 16u32);
+            let optlen = <i32>::from_stack(mem, esp + 20u32);
+            winapi::ws2_32::setsockopt(machine, s, level, optname, optval, optlen).to_raw()
         }
-        pub unsafe fn waveOutReset(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn shutdown(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hwo = <HWAVEOUT>::from_stack(mem, esp + 4u32);
-            winapi::winmm::waveOutReset(machine, hwo).to_raw()
+            let s = <SOCKET>::from_stack(mem, esp + 4u32);
+            let how = <u32>::from_stack(mem, esp + 8u32);
+            winapi::ws2_32::shutdown(machine, s, how).to_raw()
         }
-        pub unsafe fn waveOutWrite(machine: &mut Machine, esp: u32) -> u32 {
+        pub unsafe fn socket(machine: &mut Machine, esp: u32) -> u32 {
             let mem = machine.mem().detach();
-            let hwo = <HWAVEOUT>::from_stack(mem, esp + 4u32);
-            let pwh = <Option<&WAVEHDR>>::from_stack(mem, esp + 8u32);
-            let cbwh = <u32>::from_stack(mem, esp + 12u32);
-            winapi::winmm::waveOutWrite(machine, hwo, pwh, cbwh).to_raw()
+            let af = <u32>::from_stack(mem, esp + 4u32);
+            let type_ = <u32>::from_stack(mem, esp + 8u32);
+            let protocol = <u32>::from_stack(mem, esp + 12u32);
+            winapi::ws2_32::socket(machine, af, type_, protocol).to_raw()
         }
     }
     mod shims {
         use super::impls;
         use crate::shims::Shim;
-        pub const timeBeginPeriod: Shim = Shim {
-            name: "timeBeginPeriod",
-            func: impls::timeBeginPeriod,
-            stack_consumed: 4u32,
+        pub const WSAAsyncSelect: Shim = Shim {
+            name: "WSAAsyncSelect",
+            func: impls::WSAAsyncSelect,
+            stack_consumed: 16u32,
             is_async: false,
         };
-        pub const timeGetTime: Shim = Shim {
-            name: "timeGetTime",
-            func: impls::timeGetTime,
+        pub const WSACleanup: Shim = Shim {
+            name: "WSACleanup",
+            func: impls::WSACleanup,
             stack_consumed: 0u32,
             is_async: false,
         };
-        pub const timeSetEvent: Shim = Shim {
-            name: "timeSetEvent",
-            func: impls::timeSetEvent,
-            stack_consumed: 20u32,
+        pub const WSAGetLastError: Shim = Shim {
+            name: "WSAGetLastError",
+            func: impls::WSAGetLastError,
+            stack_consumed: 0u32,
             is_async: false,
         };
-        pub const waveOutClose: Shim = Shim {
-            name: "waveOutClose",
-            func: impls::waveOutClose,
+        pub const WSASetLastError: Shim = Shim {
+            name: "WSASetLastError",
+            func: impls::WSASetLastError,
             stack_consumed: 4u32,
             is_async: false,
         };
-        pub const waveOutGetDevCapsA: Shim = Shim {
-            name: "waveOutGetDevCapsA",
-            func: impls::waveOutGetDevCapsA,
+        pub const WSAStartup: Shim = Shim {
+            name: "WSAStartup",
+            func: impls::WSAStartup,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const __WSAFDIsSet: Shim = Shim {
+            name: "__WSAFDIsSet",
+            func: impls::__WSAFDIsSet,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const accept: Shim = Shim {
+            name: "accept",
+            func: impls::accept,
+            stack_consumed: 12u32,
+            is_async: true,
+        };
+        pub const bind: Shim = Shim {
+            name: "bind",
+            func: impls::bind,
             stack_consumed: 12u32,
             is_async: false,
         };
-        pub const waveOutGetNumDevs: Shim = Shim {
-            name: "waveOutGetNumDevs",
-            func: impls::waveOutGetNumDevs,
-            stack_consumed: 0u32,
+        pub const closesocket: Shim = Shim {
+            name: "closesocket",
+            func: impls::closesocket,
+            stack_consumed: 4u32,
             is_async: false,
         };
-        pub const waveOutGetPosition: Shim = Shim {
-            name: "waveOutGetPosition",
-            func: impls::waveOutGetPosition,
+        pub const connect: Shim = Shim {
+            name: "connect",
+            func: impls::connect,
+            stack_consumed: 12u32,
+            is_async: true,
+        };
+        pub const gethostbyname: Shim = Shim {
+            name: "gethostbyname",
+            func: impls::gethostbyname,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const gethostname: Shim = Shim {
+            name: "gethostname",
+            func: impls::gethostname,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const getpeername: Shim = Shim {
+            name: "getpeername",
+            func: impls::getpeername,
             stack_consumed: 12u32,
             is_async: false,
         };
-        pub const waveOutOpen: Shim = Shim {
-            name: "waveOutOpen",
-            func: impls::waveOutOpen,
-            stack_consumed: 24u32,
+        pub const getsockname: Shim = Shim {
+            name: "getsockname",
+            func: impls::getsockname,
+            stack_consumed: 12u32,
             is_async: false,
         };
-        pub const waveOutPrepareHeader: Shim = Shim {
-            name: "waveOutPrepareHeader",
-            func: impls::waveOutPrepareHeader,
+        pub const getsockopt: Shim = Shim {
+            name: "getsockopt",
+            func: impls::getsockopt,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const htonl: Shim = Shim {
+            name: "htonl",
+            func: impls::htonl,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const htons: Shim = Shim {
+            name: "htons",
+            func: impls::htons,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const inet_addr: Shim = Shim {
+            name: "inet_addr",
+            func: impls::inet_addr,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const inet_ntoa: Shim = Shim {
+            name: "inet_ntoa",
+            func: impls::inet_ntoa,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const ioctlsocket: Shim = Shim {
+            name: "ioctlsocket",
+            func: impls::ioctlsocket,
             stack_consumed: 12u32,
             is_async: false,
         };
-        pub const waveOutReset: Shim = Shim {
-            name: "waveOutReset",
-            func: impls::waveOutReset,
+        pub const listen: Shim = Shim {
+            name: "listen",
+            func: impls::listen,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const ntohl: Shim = Shim {
+            name: "ntohl",
+            func: impls::ntohl,
             stack_consumed: 4u32,
             is_async: false,
         };
-        pub const waveOutWrite: Shim = Shim {
-            name: "waveOutWrite",
-            func: impls::waveOutWrite,
+        pub const ntohs: Shim = Shim {
+            name: "ntohs",
+            func: impls::ntohs,
+            stack_consumed: 4u32,
+            is_async: false,
+        };
+        pub const recv: Shim = Shim {
+            name: "recv",
+            func: impls::recv,
+            stack_consumed: 16u32,
+            is_async: true,
+        };
+        pub const recvfrom: Shim = Shim {
+            name: "recvfrom",
+            func: impls::recvfrom,
+            stack_consumed: 24u32,
+            is_async: true,
+        };
+        pub const select: Shim = Shim {
+            name: "select",
+            func: impls::select,
+            stack_consumed: 20u32,
+            is_async: true,
+        };
+        pub const send: Shim = Shim {
+            name: "send",
+            func: impls::send,
+            stack_consumed: 16u32,
+            is_async: true,
+        };
+        pub const sendto: Shim = Shim {
+            name: "sendto",
+            func: impls::sendto,
+            stack_consumed: 24u32,
+            is_async: true,
+        };
+        pub const setsockopt: Shim = Shim {
+            name: "setsockopt",
+            func: impls::setsockopt,
+            stack_consumed: 20u32,
+            is_async: false,
+        };
+        pub const shutdown: Shim = Shim {
+            name: "shutdown",
+            func: impls::shutdown,
+            stack_consumed: 8u32,
+            is_async: false,
+        };
+        pub const socket: Shim = Shim {
+            name: "socket",
+            func: impls::socket,
             stack_consumed: 12u32,
             is_async: false,
         };
     }
-    const EXPORTS: [Symbol; 11usize] = [
+    const EXPORTS: [Symbol; 31usize] = [
         Symbol {
-            ordinal: None,
-            shim: shims::timeBeginPeriod,
+            ordinal: Some(101usize),
+            shim: shims::WSAAsyncSelect,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::timeGetTime,
+            ordinal: Some(116usize),
+            shim: shims::WSACleanup,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::timeSetEvent,
+            ordinal: Some(111usize),
+            shim: shims::WSAGetLastError,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::waveOutClose,
+            ordinal: Some(112usize),
+            shim: shims::WSASetLastError,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::waveOutGetDevCapsA,
+            ordinal: Some(115usize),
+            shim: shims::WSAStartup,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::waveOutGetNumDevs,
+            ordinal: Some(151usize),
+            shim: shims::__WSAFDIsSet,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::waveOutGetPosition,
+            ordinal: Some(1usize),
+            shim: shims::accept,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::waveOutOpen,
+            ordinal: Some(2usize),
+            shim: shims::bind,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::waveOutPrepareHeader,
+            ordinal: Some(3usize),
+            shim: shims::closesocket,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::waveOutReset,
+            ordinal: Some(4usize),
+            shim: shims::connect,
         },
         Symbol {
-            ordinal: None,
-            shim: shims::waveOutWrite,
+            ordinal: Some(52usize),
+            shim: shims::gethostbyname,
+        },
+        Symbol {
+            ordinal: Some(57usize),
+            shim: shims::gethostname,
+        },
+        Symbol {
+            ordinal: Some(5usize),
+            shim: shims::getpeername,
+        },
+        Symbol {
+            ordinal: Some(6usize),
+            shim: shims::getsockname,
+        },
+        Symbol {
+            ordinal: Some(7usize),
+            shim: shims::getsockopt,
+        },
+        Symbol {
+            ordinal: Some(8usize),
+            shim: shims::htonl,
+        },
+        Symbol {
+            ordinal: Some(9usize),
+            shim: shims::htons,
+        },
+        Symbol {
+            ordinal: Some(10usize),
+            shim: shims::inet_addr,
+        },
+        Symbol {
+            ordinal: Some(11usize),
+            shim: shims::inet_ntoa,
+        },
+        Symbol {
+            ordinal: Some(12usize),
+            shim: shims::ioctlsocket,
+        },
+        Symbol {
+            ordinal: Some(13usize),
+            shim: shims::listen,
+        },
+        Symbol {
+            ordinal: Some(14usize),
+            shim: shims::ntohl,
+        },
+        Symbol {
+            ordinal: Some(15usize),
+            shim: shims::ntohs,
+        },
+        Symbol {
+            ordinal: Some(16usize),
+            shim: shims::recv,
+        },
+        Symbol {
+            ordinal: Some(17usize),
+            shim: shims::recvfrom,
+        },
+        Symbol {
+            ordinal: Some(18usize),
+            shim: shims::select,
+        },
+        Symbol {
+            ordinal: Some(19usize),
+            shim: shims::send,
+        },
+        Symbol {
+            ordinal: Some(20usize),
+            shim: shims::sendto,
+        },
+        Symbol {
+            ordinal: Some(21usize),
+            shim: shims::setsockopt,
+        },
+        Symbol {
+            ordinal: Some(22usize),
+            shim: shims::shutdown,
+        },
+        Symbol {
+            ordinal: Some(23usize),
+            shim: shims::socket,
         },
     ];
     pub const DLL: BuiltinDLL = BuiltinDLL {
-        file_name: "winmm.dll",
+        file_name: "ws2_32.dll",
         exports: &EXPORTS,
     };
 }
@@ -0,0 +1,51 @@
+//! COM plumbing shared by the interfaces the builtin DLLs implement.
+
+#![allow(non_upper_case_globals)]
+
+use crate::machine::Machine;
+use std::collections::HashMap;
+
+pub const S_OK: u32 = 0;
+pub const S_FALSE: u32 = 1;
+pub const E_NOINTERFACE: u32 = 0x8000_4002;
+pub const E_POINTER: u32 = 0x8000_4003;
+pub const CLASS_E_NOAGGREGATION: u32 = 0x8004_0110;
+pub const REGDB_E_CLASSNOTREG: u32 = 0x8004_0154;
+
+pub type GUID = [u8; 16];
+
+pub const IID_IUnknown: GUID = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
+];
+
+/// Read the GUID a REFIID/REFCLSID points at.
+pub fn read_guid(machine: &Machine, addr: u32) -> GUID {
+    machine
+        .mem()
+        .sub(addr, 16)
+        .as_slice_todo()
+        .try_into()
+        .unwrap()
+}
+
+/// Reference counts of COM objects, by object address.  Objects start with the one
+/// reference they were created with, so need no entry until AddRef'd.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct RefCounts(HashMap<u32, u32>);
+
+impl RefCounts {
+    pub fn add_ref(&mut self, this: u32) -> u32 {
+        let refs = self.0.entry(this).or_insert(1);
+        *refs += 1;
+        *refs
+    }
+
+    /// Drop a reference, returning the count left; at 0 the caller frees the object.
+    pub fn release(&mut self, this: u32) -> u32 {
+        let refs = self.0.remove(&this).unwrap_or(1) - 1;
+        if refs > 0 {
+            self.0.insert(this, refs);
+        }
+        refs
+    }
+}
@@ -0,0 +1,215 @@
+//! Edit controls: a single line of text typed by the user.
+//!
+//! Though user32 rather than comctl32 provides these on Windows, they draw the
+//! same way as the common controls.  Text is kept as bytes in the ANSI code page,
+//! as an ANSI window receives it: a WM_CHAR per single-byte character and a
+//! WM_IME_CHAR per double-byte one (see user32::text_messages).
+
+use super::*;
+use crate::winapi::kernel32;
+
+pub const CLASS: &str = "Edit";
+
+const WM_GETTEXT: u32 = 0x000D;
+const WM_GETTEXTLENGTH: u32 = 0x000E;
+const WM_CHAR: u32 = WM::CHAR as u32;
+const WM_IME_CHAR: u32 = WM::IME_CHAR as u32;
+
+const EM_GETSEL: u32 = 0x00B0;
+const EM_SETSEL: u32 = 0x00B1;
+const EM_LIMITTEXT: u32 = 0x00C5;
+
+const EN_CHANGE: u32 = 0x0300;
+
+const ES_PASSWORD: u32 = 0x0020;
+
+/// The limit on text length before EM_LIMITTEXT sets one.
+const DEFAULT_LIMIT: usize = 30000;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Edit {
+    text: Vec<u8>,
+    limit: usize,
+}
+
+impl Edit {
+    /// Append typed bytes, unless that would go past the limit.
+    fn push(&mut self, bytes: &[u8]) -> bool {
+        if self.text.len() + bytes.len() > self.limit {
+            return false;
+        }
+        self.text.extend_from_slice(bytes);
+        true
+    }
+
+    /// Remove the last character, both bytes of it if double-byte.
+    fn pop(&mut self, code_page: u32) -> bool {
+        let mut last = None;
+        let mut i = 0;
+        while i < self.text.len() {
+            last = Some(i);
+            i += if kernel32::is_dbcs_lead_byte(code_page, self.text[i]) {
+                2
+            } else {
+                1
+            };
+        }
+        match last {
+            Some(last) => {
+                self.text.truncate(last);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn draw(&self, code_page: u32, width: i32, height: i32, style: u32, focused: bool) -> Drawing {
+        let mut d = Drawing::default();
+        let bounds = rect(0, 0, width, height);
+        d.fill(bounds, WINDOW);
+        d.edge(bounds, false);
+        // The built-in font has only ASCII glyphs.
+        let text: String = if style & ES_PASSWORD != 0 {
+            "*".repeat(kernel32::decode_ansi(code_page, &self.text).chars().count())
+        } else {
+            kernel32::decode_ansi(code_page, &self.text)
+                .chars()
+                .map(|c| if c.is_ascii() { c } else { '?' })
+                .collect()
+        };
+        let inner = rect(4, 2, width - 2, height - 2);
+        d.text(inner, &text, DARK);
+        if focused {
+            let x = inner.left + text_width(&text);
+            let top = inner.top + (inner.bottom - inner.top - CHAR_HEIGHT) / 2;
+            d.fill(rect(x, top, x + 1, top + CHAR_HEIGHT), DARK);
+        }
+        d
+    }
+}
+
+fn redraw(machine: &mut Machine, hwnd: HWND) {
+    let window = machine.state.user32.windows.get(hwnd).unwrap();
+    let (width, height, style) = (
+        window.width as i32,
+        window.height as i32,
+        window.class_style,
+    );
+    let focused = machine.state.user32.focus == hwnd;
+    let code_page = machine.state.kernel32.code_page;
+    let drawing =
+        machine.state.comctl32.edits[&hwnd].draw(code_page, width, height, style, focused);
+    paint(machine, hwnd, drawing);
+}
+
+/// Tell the parent the text changed, and show the change.
+fn changed(machine: &mut Machine, hwnd: HWND) {
+    redraw(machine, hwnd);
+    let (parent, id) = parent_of(machine, hwnd);
+    let wParam = (EN_CHANGE << 16) | (id & 0xFFFF);
+    user32::post_message(machine, parent, WM_COMMAND, wParam, hwnd.to_raw());
+}
+
+pub fn wndproc(machine: &mut Machine, hwnd: HWND, msg: u32, wParam: u32, lParam: u32) -> u32 {
+    match msg {
+        WM_CREATE => {
+            let title = machine
+                .state
+                .user32
+                .windows
+                .get(hwnd)
+                .unwrap()
+                .title
+                .clone();
+            let edit = Edit {
+                text: title.into_bytes(),
+                limit: DEFAULT_LIMIT,
+            };
+            machine.state.comctl32.edits.insert(hwnd, edit);
+            0
+        }
+        WM_PAINT => {
+            redraw(machine, hwnd);
+            0
+        }
+        WM_LBUTTONDOWN => {
+            user32::SetFocus(machine, hwnd);
+            redraw(machine, hwnd);
+            0
+        }
+        WM_SETTEXT => {
+            let text = match lParam {
+                0 => Vec::new(),
+                addr => machine.mem().slicez(addr).to_vec(),
+            };
+            machine.state.comctl32.edits.get_mut(&hwnd).unwrap().text = text;
+            changed(machine, hwnd);
+            true as u32
+        }
+        WM_GETTEXT => {
+            let text = &machine.state.comctl32.edits[&hwnd].text;
+            if lParam == 0 || wParam == 0 {
+                return 0;
+            }
+            let n = text.len().min(wParam as usize - 1);
+            let out = machine.mem().sub(lParam, n as u32 + 1).as_mut_slice_todo();
+            out[..n].copy_from_slice(&text[..n]);
+            out[n] = 0;
+            n as u32
+        }
+        WM_GETTEXTLENGTH => machine.state.comctl32.edits[&hwnd].text.len() as u32,
+        WM_CHAR => {
+            let code_page = machine.state.kernel32.code_page;
+            let edit = machine.state.comctl32.edits.get_mut(&hwnd).unwrap();
+            let modified = match wParam as u8 {
+                0x08 => edit.pop(code_page),
+                // Control characters, including Enter, are for multiline edits.
+                0x00..=0x1F => false,
+                byte => edit.push(&[byte]),
+            };
+            if modified {
+                changed(machine, hwnd);
+            }
+            0
+        }
+        WM_IME_CHAR => {
+            let bytes: Vec<u8> = (wParam as u16)
+                .to_be_bytes()
+                .into_iter()
+                .skip_while(|&b| b == 0)
+                .collect();
+            if machine
+                .state
+                .comctl32
+                .edits
+                .get_mut(&hwnd)
+                .unwrap()
+                .push(&bytes)
+            {
+                changed(machine, hwnd);
+            }
+            0
+        }
+        EM_GETSEL => {
+            // The caret is always at the end, with nothing selected.
+            let len = machine.state.comctl32.edits[&hwnd].text.len() as u32;
+            let mem = machine.mem();
+            for addr in [wParam, lParam] {
+                if addr != 0 {
+                    mem.put::<u32>(addr, len);
+                }
+            }
+            (len << 16) | len
+        }
+        EM_SETSEL => 0,
+        EM_LIMITTEXT => {
+            let limit = match wParam {
+                0 => 0x7FFF_FFFE,
+                limit => limit as usize,
+            };
+            machine.state.comctl32.edits.get_mut(&hwnd).unwrap().limit = limit;
+            0
+        }
+        _ => def_window_proc(machine, hwnd, msg, wParam, lParam),
+    }
+}
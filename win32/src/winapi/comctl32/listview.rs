@@ -0,0 +1,518 @@
+//! List views, in report mode only: rows of items under a header of columns.
+
+use super::*;
+
+pub const CLASS: &str = "SysListView32";
+
+const LVM_GETITEMCOUNT: u32 = 0x1004;
+const LVM_GETITEMA: u32 = 0x1005;
+const LVM_SETITEMA: u32 = 0x1006;
+const LVM_INSERTITEMA: u32 = 0x1007;
+const LVM_DELETEITEM: u32 = 0x1008;
+const LVM_DELETEALLITEMS: u32 = 0x1009;
+const LVM_GETNEXTITEM: u32 = 0x100C;
+const LVM_ENSUREVISIBLE: u32 = 0x1013;
+const LVM_INSERTCOLUMNA: u32 = 0x101B;
+const LVM_DELETECOLUMN: u32 = 0x101C;
+const LVM_GETCOLUMNWIDTH: u32 = 0x101D;
+const LVM_SETCOLUMNWIDTH: u32 = 0x101E;
+const LVM_SETITEMSTATE: u32 = 0x102B;
+const LVM_GETITEMSTATE: u32 = 0x102C;
+const LVM_GETITEMTEXTA: u32 = 0x102D;
+const LVM_SETITEMTEXTA: u32 = 0x102E;
+const LVM_GETSELECTEDCOUNT: u32 = 0x1032;
+const LVM_SETEXTENDEDLISTVIEWSTYLE: u32 = 0x1036;
+const LVM_GETEXTENDEDLISTVIEWSTYLE: u32 = 0x1037;
+
+// LVITEM mask bits.
+const LVIF_TEXT: u32 = 0x0001;
+const LVIF_PARAM: u32 = 0x0004;
+const LVIF_STATE: u32 = 0x0008;
+
+// Item states.
+const LVIS_FOCUSED: u32 = 0x0001;
+const LVIS_SELECTED: u32 = 0x0002;
+
+// LVM_GETNEXTITEM flags.
+const LVNI_FOCUSED: u32 = 0x0001;
+const LVNI_SELECTED: u32 = 0x0002;
+
+const LVS_SINGLESEL: u32 = 0x0004;
+const LVS_NOCOLUMNHEADER: u32 = 0x4000;
+
+// Notification codes.
+const NM_CLICK: i32 = -2;
+const LVN_ITEMCHANGED: i32 = -101;
+
+const HEADER_HEIGHT: i32 = 18;
+const ROW_HEIGHT: i32 = 16;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct LVCOLUMNA {
+    mask: u32,
+    fmt: i32,
+    cx: i32,
+    pszText: u32,
+    cchTextMax: i32,
+    iSubItem: i32,
+}
+unsafe impl memory::Pod for LVCOLUMNA {}
+
+const LVCF_WIDTH: u32 = 0x0002;
+const LVCF_TEXT: u32 = 0x0004;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct LVITEMA {
+    mask: u32,
+    iItem: i32,
+    iSubItem: i32,
+    state: u32,
+    stateMask: u32,
+    pszText: u32,
+    cchTextMax: i32,
+    iImage: i32,
+    lParam: u32,
+}
+unsafe impl memory::Pod for LVITEMA {}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Column {
+    text: String,
+    width: i32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Item {
+    /// The item's text followed by its subitems', one per column.
+    texts: Vec<String>,
+    state: u32,
+    param: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ListView {
+    columns: Vec<Column>,
+    items: Vec<Item>,
+    extended_style: u32,
+    /// Index of the first row shown.
+    top: usize,
+}
+
+impl ListView {
+    fn header_height(&self, style: u32) -> i32 {
+        if style & LVS_NOCOLUMNHEADER != 0 {
+            0
+        } else {
+            HEADER_HEIGHT
+        }
+    }
+
+    fn draw(&self, width: i32, height: i32, style: u32) -> Drawing {
+        let mut d = Drawing::default();
+        d.fill(rect(0, 0, width, height), WINDOW);
+        let inner = rect(2, 2, width - 2, height - 2);
+        let header = self.header_height(style);
+        if header > 0 {
+            let mut left = inner.left;
+            for column in &self.columns {
+                let r = rect(left, inner.top, left + column.width, inner.top + header);
+                d.fill(r, FACE);
+                d.thin_edge(r, true);
+                d.text(cell(r), &column.text, DARK);
+                left += column.width;
+            }
+            if left < inner.right {
+                let r = rect(left, inner.top, inner.right, inner.top + header);
+                d.fill(r, FACE);
+                d.thin_edge(r, true);
+            }
+        }
+        let full_width: i32 = self.columns.iter().map(|c| c.width).sum();
+        let mut y = inner.top + header;
+        for item in &self.items[self.top.min(self.items.len())..] {
+            if y >= inner.bottom {
+                break;
+            }
+            let selected = item.state & LVIS_SELECTED != 0;
+            if selected {
+                // Full-row select or not, the first column gets the highlight.
+                let right = inner.left + self.columns.first().map_or(full_width, |c| c.width);
+                d.fill(rect(inner.left, y, right, y + ROW_HEIGHT), SELECTION);
+            }
+            let mut left = inner.left;
+            for (i, text) in item.texts.iter().enumerate() {
+                // Without columns, the item alone spans the control.
+                let width = self.columns.get(i).map_or(inner.right - left, |c| c.width);
+                let color = if selected && i == 0 { WINDOW } else { DARK };
+                let r = rect(left, y, (left + width).min(inner.right), y + ROW_HEIGHT);
+                d.text(cell(r), text, color);
+                left += width;
+                if i + 1 >= self.columns.len() {
+                    break;
+                }
+            }
+            y += ROW_HEIGHT;
+        }
+        d.edge(rect(0, 0, width, height), false);
+        d
+    }
+}
+
+/// The area of a header or item cell its text goes in.
+fn cell(r: RECT) -> RECT {
+    rect(r.left + 4, r.top, r.right - 2, r.bottom)
+}
+
+impl ListView {
+    /// The item at a point in the control, if any.
+    fn item_at(&self, y: i32, style: u32) -> Option<usize> {
+        let row = y - 2 - self.header_height(style);
+        if row < 0 {
+            return None;
+        }
+        let i = self.top + (row / ROW_HEIGHT) as usize;
+        if i < self.items.len() {
+            Some(i)
+        } else {
+            None
+        }
+    }
+
+    fn next_item(&self, start: i32, flags: u32) -> i32 {
+        let want = flags & (LVNI_FOCUSED | LVNI_SELECTED);
+        let state_wanted = (if want & LVNI_FOCUSED != 0 {
+            LVIS_FOCUSED
+        } else {
+            0
+        }) | (if want & LVNI_SELECTED != 0 {
+            LVIS_SELECTED
+        } else {
+            0
+        });
+        // Start after the item given, or at the first with -1.
+        let first = (start + 1).max(0) as usize;
+        match self.items[first.min(self.items.len())..]
+            .iter()
+            .position(|item| item.state & state_wanted == state_wanted)
+        {
+            Some(i) => (first + i) as i32,
+            None => -1,
+        }
+    }
+}
+
+fn style_of(machine: &Machine, hwnd: HWND) -> u32 {
+    machine.state.user32.windows.get(hwnd).unwrap().class_style
+}
+
+fn redraw(machine: &mut Machine, hwnd: HWND) {
+    let window = machine.state.user32.windows.get(hwnd).unwrap();
+    let (width, height) = (window.width as i32, window.height as i32);
+    let style = window.class_style;
+    let drawing = machine.state.comctl32.listviews[&hwnd].draw(width, height, style);
+    paint(machine, hwnd, drawing);
+}
+
+/// Apply LVITEM fields to an item, returning its previous state.
+fn set_item(machine: &Machine, item: &mut Item, lvitem: &LVITEMA) -> u32 {
+    let prev = item.state;
+    if lvitem.mask & LVIF_TEXT != 0 {
+        let sub = lvitem.iSubItem.max(0) as usize;
+        if item.texts.len() <= sub {
+            item.texts.resize(sub + 1, String::new());
+        }
+        item.texts[sub] = read_str(machine, lvitem.pszText);
+    }
+    if lvitem.mask & LVIF_STATE != 0 {
+        item.state = (item.state & !lvitem.stateMask) | (lvitem.state & lvitem.stateMask);
+    }
+    if lvitem.mask & LVIF_PARAM != 0 && lvitem.iSubItem == 0 {
+        item.param = lvitem.lParam;
+    }
+    prev
+}
+
+/// Tell the parent an item's state changed, with an NMLISTVIEW.
+fn notify_changed(machine: &mut Machine, hwnd: HWND, index: usize, old_state: u32) {
+    let item = &machine.state.comctl32.listviews[&hwnd].items[index];
+    if item.state == old_state {
+        return;
+    }
+    let data = [
+        index as u32, // iItem
+        0,            // iSubItem
+        item.state,   // uNewState
+        old_state,    // uOldState
+        LVIF_STATE,   // uChanged
+        0,            // ptAction.x
+        0,            // ptAction.y
+        item.param,   // lParam
+    ];
+    notify(machine, hwnd, LVN_ITEMCHANGED, &data);
+}
+
+/// Set the state bits in mask for one item, or all with index -1, notifying the parent.
+fn set_state(machine: &mut Machine, hwnd: HWND, index: i32, state: u32, mask: u32) -> bool {
+    let listview = machine.state.comctl32.listviews.get_mut(&hwnd).unwrap();
+    let indices = if index == -1 {
+        0..listview.items.len()
+    } else if (index as usize) < listview.items.len() {
+        index as usize..index as usize + 1
+    } else {
+        return false;
+    };
+    let mut changes = Vec::new();
+    for i in indices {
+        let item = &mut listview.items[i];
+        changes.push((i, item.state));
+        item.state = (item.state & !mask) | (state & mask);
+    }
+    for (i, old_state) in changes {
+        notify_changed(machine, hwnd, i, old_state);
+    }
+    true
+}
+
+pub fn wndproc(machine: &mut Machine, hwnd: HWND, msg: u32, wParam: u32, lParam: u32) -> u32 {
+    match msg {
+        WM_CREATE => {
+            let listview = ListView {
+                columns: Vec::new(),
+                items: Vec::new(),
+                extended_style: 0,
+                top: 0,
+            };
+            machine.state.comctl32.listviews.insert(hwnd, listview);
+            return 0;
+        }
+        WM_PAINT => {
+            redraw(machine, hwnd);
+            return 0;
+        }
+        WM_LBUTTONDOWN => {
+            let style = style_of(machine, hwnd);
+            let (_, y) = mouse_pos(lParam);
+            let clicked = machine.state.comctl32.listviews[&hwnd].item_at(y, style);
+            let ctrl = wParam & 0x0008 != 0; // MK_CONTROL
+            if !ctrl || style & LVS_SINGLESEL != 0 {
+                set_state(machine, hwnd, -1, 0, LVIS_SELECTED | LVIS_FOCUSED);
+            }
+            if let Some(i) = clicked {
+                let selected =
+                    machine.state.comctl32.listviews[&hwnd].items[i].state & LVIS_SELECTED != 0;
+                let state = if ctrl && selected {
+                    LVIS_FOCUSED
+                } else {
+                    LVIS_SELECTED | LVIS_FOCUSED
+                };
+                set_state(machine, hwnd, i as i32, state, LVIS_SELECTED | LVIS_FOCUSED);
+            }
+            redraw(machine, hwnd);
+            let data = [clicked.map_or(-1, |i| i as i32) as u32];
+            notify(machine, hwnd, NM_CLICK, &data);
+            return 0;
+        }
+        _ => {}
+    }
+
+    let ret = match msg {
+        LVM_INSERTCOLUMNA => {
+            let col = machine.mem().get_pod::<LVCOLUMNA>(lParam);
+            let column = Column {
+                text: if col.mask & LVCF_TEXT != 0 {
+                    read_str(machine, col.pszText)
+                } else {
+                    String::new()
+                },
+                width: if col.mask & LVCF_WIDTH != 0 {
+                    col.cx
+                } else {
+                    50
+                },
+            };
+            let listview = machine.state.comctl32.listviews.get_mut(&hwnd).unwrap();
+            let at = (wParam as usize).min(listview.columns.len());
+            listview.columns.insert(at, column);
+            at as u32
+        }
+        LVM_DELETECOLUMN => {
+            let listview = machine.state.comctl32.listviews.get_mut(&hwnd).unwrap();
+            let at = wParam as usize;
+            if at >= listview.columns.len() {
+                return false as u32;
+            }
+            listview.columns.remove(at);
+            for item in &mut listview.items {
+                if at < item.texts.len() {
+                    item.texts.remove(at);
+                }
+            }
+            true as u32
+        }
+        LVM_GETCOLUMNWIDTH => {
+            let listview = &machine.state.comctl32.listviews[&hwnd];
+            return listview.columns.get(wParam as usize).map_or(0, |c| c.width) as u32;
+        }
+        LVM_SETCOLUMNWIDTH => {
+            let listview = machine.state.comctl32.listviews.get_mut(&hwnd).unwrap();
+            let Some(column) = listview.columns.get_mut(wParam as usize) else {
+                return false as u32;
+            };
+            // Negative widths ask to fit the contents, which we can't measure.
+            column.width = match lParam as i16 {
+                -1 | -2 => 100,
+                width => width as i32,
+            };
+            true as u32
+        }
+        LVM_INSERTITEMA => {
+            let lvitem = machine.mem().get_pod::<LVITEMA>(lParam);
+            if lvitem.iSubItem != 0 {
+                return -1i32 as u32;
+            }
+            let mut item = Item {
+                texts: vec![String::new()],
+                state: 0,
+                param: 0,
+            };
+            set_item(machine, &mut item, &lvitem);
+            let listview = machine.state.comctl32.listviews.get_mut(&hwnd).unwrap();
+            let at = (lvitem.iItem.max(0) as usize).min(listview.items.len());
+            listview.items.insert(at, item);
+            at as u32
+        }
+        LVM_SETITEMA | LVM_SETITEMTEXTA => {
+            let mut lvitem = machine.mem().get_pod::<LVITEMA>(lParam);
+            if msg == LVM_SETITEMTEXTA {
+                lvitem.iItem = wParam as i32;
+                lvitem.mask = LVIF_TEXT;
+            }
+            let listview = machine.state.comctl32.listviews.get_mut(&hwnd).unwrap();
+            let Some(item) = listview.items.get_mut(lvitem.iItem as usize) else {
+                return false as u32;
+            };
+            // Borrow the item out while reading guest strings into it.
+            let mut item = std::mem::replace(
+                item,
+                Item {
+                    texts: Vec::new(),
+                    state: 0,
+                    param: 0,
+                },
+            );
+            let old_state = set_item(machine, &mut item, &lvitem);
+            let listview = machine.state.comctl32.listviews.get_mut(&hwnd).unwrap();
+            listview.items[lvitem.iItem as usize] = item;
+            notify_changed(machine, hwnd, lvitem.iItem as usize, old_state);
+            true as u32
+        }
+        LVM_GETITEMA | LVM_GETITEMTEXTA => {
+            let mut lvitem = machine.mem().get_pod::<LVITEMA>(lParam);
+            if msg == LVM_GETITEMTEXTA {
+                lvitem.iItem = wParam as i32;
+                lvitem.mask = LVIF_TEXT;
+            }
+            let listview = &machine.state.comctl32.listviews[&hwnd];
+            let Some(item) = listview.items.get(lvitem.iItem as usize) else {
+                return false as u32;
+            };
+            let text = item
+                .texts
+                .get(lvitem.iSubItem.max(0) as usize)
+                .cloned()
+                .unwrap_or_default();
+            if lvitem.mask & LVIF_STATE != 0 {
+                lvitem.state = item.state & lvitem.stateMask;
+            }
+            if lvitem.mask & LVIF_PARAM != 0 {
+                lvitem.lParam = item.param;
+            }
+            let mut len = 0;
+            if lvitem.mask & LVIF_TEXT != 0 {
+                len = write_str(
+                    machine,
+                    lvitem.pszText,
+                    lvitem.cchTextMax.max(0) as u32,
+                    &text,
+                );
+            }
+            machine.mem().put::<LVITEMA>(lParam, lvitem);
+            return if msg == LVM_GETITEMTEXTA {
+                len
+            } else {
+                true as u32
+            };
+        }
+        LVM_DELETEITEM => {
+            let listview = machine.state.comctl32.listviews.get_mut(&hwnd).unwrap();
+            if wParam as usize >= listview.items.len() {
+                return false as u32;
+            }
+            listview.items.remove(wParam as usize);
+            true as u32
+        }
+        LVM_DELETEALLITEMS => {
+            let listview = machine.state.comctl32.listviews.get_mut(&hwnd).unwrap();
+            listview.items.clear();
+            listview.top = 0;
+            true as u32
+        }
+        LVM_GETITEMCOUNT => return machine.state.comctl32.listviews[&hwnd].items.len() as u32,
+        LVM_GETNEXTITEM => {
+            let listview = &machine.state.comctl32.listviews[&hwnd];
+            return listview.next_item(wParam as i32, lParam) as u32;
+        }
+        LVM_SETITEMSTATE => {
+            let lvitem = machine.mem().get_pod::<LVITEMA>(lParam);
+            set_state(machine, hwnd, wParam as i32, lvitem.state, lvitem.stateMask) as u32
+        }
+        LVM_GETITEMSTATE => {
+            let listview = &machine.state.comctl32.listviews[&hwnd];
+            return listview
+                .items
+                .get(wParam as usize)
+                .map_or(0, |item| item.state & lParam);
+        }
+        LVM_GETSELECTEDCOUNT => {
+            let listview = &machine.state.comctl32.listviews[&hwnd];
+            return listview
+                .items
+                .iter()
+                .filter(|item| item.state & LVIS_SELECTED != 0)
+                .count() as u32;
+        }
+        LVM_SETEXTENDEDLISTVIEWSTYLE => {
+            // wParam masks the styles to change, with 0 meaning all.
+            let mask = if wParam == 0 { !0 } else { wParam };
+            let listview = machine.state.comctl32.listviews.get_mut(&hwnd).unwrap();
+            let prev = listview.extended_style;
+            listview.extended_style = (prev & !mask) | (lParam & mask);
+            prev
+        }
+        LVM_GETEXTENDEDLISTVIEWSTYLE => {
+            return machine.state.comctl32.listviews[&hwnd].extended_style;
+        }
+        LVM_ENSUREVISIBLE => {
+            let window = machine.state.user32.windows.get(hwnd).unwrap();
+            let style = window.class_style;
+            let height = window.height as i32;
+            let listview = machine.state.comctl32.listviews.get_mut(&hwnd).unwrap();
+            let index = wParam as usize;
+            if index >= listview.items.len() {
+                return false as u32;
+            }
+            let rows = ((height - 4 - listview.header_height(style)) / ROW_HEIGHT).max(1) as usize;
+            if index < listview.top {
+                listview.top = index;
+            } else if index >= listview.top + rows {
+                listview.top = index + 1 - rows;
+            }
+            true as u32
+        }
+        _ => return def_window_proc(machine, hwnd, msg, wParam, lParam),
+    };
+    redraw(machine, hwnd);
+    ret
+}
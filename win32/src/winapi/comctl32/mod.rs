@@ -0,0 +1,342 @@
+//! Common controls: status bars, toolbars, progress bars, trackbars, list views
+//! and tab controls, along with user32's edit controls, which draw the same way.
+
+#![allow(non_snake_case)]
+
+mod edit;
+mod listview;
+mod progress;
+mod status;
+mod tab;
+mod toolbar;
+mod trackbar;
+
+use super::{
+    gdi32::font::{glyph_pixels, CHAR_HEIGHT, CHAR_WIDTH},
+    types::{HWND, RECT},
+    user32::{self, UpdateRegion, WM},
+};
+use crate::machine::Machine;
+use memory::Extensions;
+use std::collections::HashMap;
+
+const TRACE_CONTEXT: &'static str = "comctl32";
+
+/*
+## Common controls
+
+Each control is a window class implemented here (see user32::BuiltinWndProc),
+registered by InitCommonControls.  Controls are children of the guest's windows
+and draw straight into their parent's pixels, as the classic flat-gray 3D look,
+whenever their state changes and again whenever the parent repaints under them.
+
+Mouse input within a control goes to the control (see user32::retarget_mouse),
+which reports clicks and drags to its parent with WM_COMMAND, WM_HSCROLL or
+WM_NOTIFY, posted rather than sent.
+
+Text is drawn in GDI's built-in font (see gdi32::font), as TextOutA draws it.
+*/
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct State {
+    status: HashMap<HWND, status::StatusBar>,
+    toolbars: HashMap<HWND, toolbar::Toolbar>,
+    progress: HashMap<HWND, progress::Progress>,
+    trackbars: HashMap<HWND, trackbar::Trackbar>,
+    listviews: HashMap<HWND, listview::ListView>,
+    tabs: HashMap<HWND, tab::Tab>,
+    edits: HashMap<HWND, edit::Edit>,
+    /// Guest memory for each control's WM_NOTIFY data.
+    notify_bufs: HashMap<HWND, u32>,
+}
+
+// Messages the controls handle beyond their own.
+const WM_CREATE: u32 = WM::CREATE as u32;
+const WM_PAINT: u32 = WM::PAINT as u32;
+const WM_SIZE: u32 = 0x0005;
+const WM_SETTEXT: u32 = WM::SETTEXT as u32;
+const WM_NOTIFY: u32 = 0x004E;
+const WM_COMMAND: u32 = 0x0111;
+const WM_HSCROLL: u32 = 0x0114;
+const WM_VSCROLL: u32 = 0x0115;
+const WM_MOUSEMOVE: u32 = WM::MOUSEMOVE as u32;
+const WM_LBUTTONDOWN: u32 = WM::LBUTTONDOWN as u32;
+const WM_LBUTTONUP: u32 = WM::LBUTTONUP as u32;
+
+/// Hands messages a control doesn't handle itself to DefWindowProc.
+fn def_window_proc(machine: &mut Machine, hwnd: HWND, msg: u32, wParam: u32, lParam: u32) -> u32 {
+    user32::DefWindowProcA(machine, hwnd, WM::try_from(msg), wParam, lParam)
+}
+
+/// The x and y packed into a mouse message's lParam.
+fn mouse_pos(lParam: u32) -> (i32, i32) {
+    (lParam as i16 as i32, (lParam >> 16) as i16 as i32)
+}
+
+/// Read a guest string, treating null as empty.
+fn read_str(machine: &Machine, addr: u32) -> String {
+    if addr == 0 {
+        return String::new();
+    }
+    String::from_utf8_lossy(machine.mem().slicez(addr)).into_owned()
+}
+
+/// Copy a string out to a guest buffer of len bytes, truncating to fit with its nul,
+/// and returning the length copied.
+fn write_str(machine: &Machine, addr: u32, len: u32, str: &str) -> u32 {
+    if addr == 0 || len == 0 {
+        return 0;
+    }
+    let n = std::cmp::min(str.len(), len as usize - 1);
+    let out = machine.mem().sub(addr, n as u32 + 1).as_mut_slice_todo();
+    out[..n].copy_from_slice(&str.as_bytes()[..n]);
+    out[n] = 0;
+    n as u32
+}
+
+/// A control window's parent and control ID.
+fn parent_of(machine: &Machine, hwnd: HWND) -> (HWND, u32) {
+    let window = machine.state.user32.windows.get(hwnd).unwrap();
+    (window.parent, window.id)
+}
+
+/// Post WM_NOTIFY to a control's parent, with an NMHDR followed by data.
+fn notify(machine: &mut Machine, hwnd: HWND, code: i32, data: &[u32]) {
+    let (parent, id) = parent_of(machine, hwnd);
+    let buf = match machine.state.comctl32.notify_bufs.get(&hwnd) {
+        Some(&buf) => buf,
+        None => {
+            // Big enough for the largest notification, NMLISTVIEW.
+            let heap = machine
+                .state
+                .kernel32
+                .get_process_heap(&mut machine.emu.memory);
+            let buf = heap.alloc(machine.emu.memory.mem(), 64);
+            machine.state.comctl32.notify_bufs.insert(hwnd, buf);
+            buf
+        }
+    };
+    let mem = machine.mem();
+    let words = [hwnd.to_raw(), id, code as u32]
+        .into_iter()
+        .chain(data.iter().copied());
+    for (i, word) in words.take(16).enumerate() {
+        mem.put::<u32>(buf + i as u32 * 4, word);
+    }
+    user32::post_message(machine, parent, WM_NOTIFY, id, buf);
+}
+
+// Colors of the classic 3D look.
+const FACE: [u8; 4] = [0xc0, 0xc0, 0xc0, 0xff];
+const SHADOW: [u8; 4] = [0x80, 0x80, 0x80, 0xff];
+const HILIGHT: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+const DARK: [u8; 4] = [0x00, 0x00, 0x00, 0xff];
+const WINDOW: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+const SELECTION: [u8; 4] = [0x00, 0x00, 0x80, 0xff];
+
+fn rect(left: i32, top: i32, right: i32, bottom: i32) -> RECT {
+    RECT {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+/// A control's appearance, as shapes in its client coordinates.
+#[derive(Default)]
+struct Drawing(Vec<(RECT, Shape)>);
+
+enum Shape {
+    Fill([u8; 4]),
+    /// A one-pixel frame, with the first color along the top and left.
+    Frame([u8; 4], [u8; 4]),
+    /// A line of text starting at the rect's left, centered vertically and
+    /// clipped to the rect.
+    Text(String, [u8; 4]),
+}
+
+impl Drawing {
+    fn fill(&mut self, r: RECT, color: [u8; 4]) {
+        self.0.push((r, Shape::Fill(color)));
+    }
+
+    fn frame(&mut self, r: RECT, top_left: [u8; 4], bottom_right: [u8; 4]) {
+        self.0.push((r, Shape::Frame(top_left, bottom_right)));
+    }
+
+    fn text(&mut self, r: RECT, text: &str, color: [u8; 4]) {
+        self.0.push((r, Shape::Text(text.to_string(), color)));
+    }
+
+    /// Text centered horizontally in a rect.
+    fn text_centered(&mut self, r: RECT, text: &str, color: [u8; 4]) {
+        let left = r.left + ((r.right - r.left) - text_width(text)).max(0) / 2;
+        self.text(rect(left, r.top, r.right, r.bottom), text, color);
+    }
+
+    /// A two-pixel 3D edge, standing out like a button or sunk in like a well.
+    fn edge(&mut self, r: RECT, raised: bool) {
+        let inner = rect(r.left + 1, r.top + 1, r.right - 1, r.bottom - 1);
+        if raised {
+            self.frame(r, HILIGHT, DARK);
+            self.frame(inner, FACE, SHADOW);
+        } else {
+            self.frame(r, SHADOW, HILIGHT);
+            self.frame(inner, DARK, FACE);
+        }
+    }
+
+    /// A one-pixel 3D border, for thinner elements like status bar panes.
+    fn thin_edge(&mut self, r: RECT, raised: bool) {
+        if raised {
+            self.frame(r, HILIGHT, SHADOW);
+        } else {
+            self.frame(r, SHADOW, HILIGHT);
+        }
+    }
+}
+
+/// The width of a line of text in the font controls draw with.
+fn text_width(text: &str) -> i32 {
+    text.len() as i32 * CHAR_WIDTH
+}
+
+/// Draw a control into its parent window's pixels.
+fn paint(machine: &mut Machine, hwnd: HWND, drawing: Drawing) {
+    let windows = &mut machine.state.user32.windows;
+    let window = windows.get_mut(hwnd).unwrap();
+    window.dirty = None;
+    let (parent, bounds) = (window.parent, window.rect_in_parent());
+    let Some(parent) = windows.get_mut(parent) else {
+        return;
+    };
+    let bitmap = parent.bitmap_mut(&mut *machine.host);
+    let (width, height) = (bitmap.width as i32, bitmap.height as i32);
+    let clip = rect(
+        bounds.left.max(0),
+        bounds.top.max(0),
+        bounds.right.min(width),
+        bounds.bottom.min(height),
+    );
+    if clip.left >= clip.right || clip.top >= clip.bottom {
+        return;
+    }
+    let pixels = bitmap.pixels.as_slice_mut(machine.emu.memory.mem());
+    let mut fill = |r: RECT, color: [u8; 4]| {
+        let left = (r.left + bounds.left).max(clip.left);
+        let top = (r.top + bounds.top).max(clip.top);
+        let right = (r.right + bounds.left).min(clip.right);
+        let bottom = (r.bottom + bounds.top).min(clip.bottom);
+        for y in top..bottom {
+            let row = (y * width) as usize;
+            for x in left..right {
+                pixels[row + x as usize] = color;
+            }
+        }
+    };
+    for (r, shape) in drawing.0 {
+        match shape {
+            Shape::Fill(color) => fill(r, color),
+            Shape::Frame(top_left, bottom_right) => {
+                fill(rect(r.left, r.top, r.right, r.top + 1), top_left);
+                fill(rect(r.left, r.top, r.left + 1, r.bottom), top_left);
+                fill(rect(r.left, r.bottom - 1, r.right, r.bottom), bottom_right);
+                fill(rect(r.right - 1, r.top, r.right, r.bottom), bottom_right);
+            }
+            Shape::Text(text, color) => {
+                let top = r.top + (r.bottom - r.top - CHAR_HEIGHT) / 2;
+                for (i, c) in text.bytes().enumerate() {
+                    let left = r.left + i as i32 * CHAR_WIDTH;
+                    if left >= r.right {
+                        break;
+                    }
+                    for (gx, gy) in glyph_pixels(c) {
+                        let (x, y) = (left + gx, top + gy);
+                        if x < r.right && y >= r.top && y < r.bottom {
+                            fill(rect(x, y, x + 1, y + 1), color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if parent.flush_pixels(machine.emu.memory.mem(), clip) {
+        machine.presented();
+    }
+}
+
+/// Mark a control for repainting, after a change made outside its own messages.
+fn invalidate(machine: &mut Machine, hwnd: HWND) {
+    if let Some(window) = machine.state.user32.windows.get_mut(hwnd) {
+        window.dirty = Some(UpdateRegion {
+            erase_background: false,
+        });
+    }
+}
+
+/// The size of a control's parent's client area.
+fn parent_size(machine: &Machine, hwnd: HWND) -> (u32, u32) {
+    let (parent, _) = parent_of(machine, hwnd);
+    match machine.state.user32.windows.get(parent) {
+        Some(parent) => (parent.width, parent.height),
+        None => (0, 0),
+    }
+}
+
+/// Move a control within its parent.
+fn place(machine: &mut Machine, hwnd: HWND, x: i32, y: i32, width: u32, height: u32) {
+    let window = machine.state.user32.windows.get_mut(hwnd).unwrap();
+    window.x = x;
+    window.y = y;
+    window.width = width;
+    window.height = height;
+}
+
+/// The window classes implemented here, with their window procedures.
+pub const CLASSES: [(&str, user32::BuiltinWndProc); 6] = [
+    (status::CLASS, status::wndproc),
+    (toolbar::CLASS, toolbar::wndproc),
+    (progress::CLASS, progress::wndproc),
+    (trackbar::CLASS, trackbar::wndproc),
+    (listview::CLASS, listview::wndproc),
+    (tab::CLASS, tab::wndproc),
+];
+
+/// The window procedure of a class user32 provides without registering, looked up
+/// by CreateWindowEx when the guest first creates one.
+pub fn system_class(name: &str) -> Option<(&'static str, user32::BuiltinWndProc)> {
+    if name.eq_ignore_ascii_case(edit::CLASS) {
+        return Some((edit::CLASS, edit::wndproc));
+    }
+    None
+}
+
+fn register_classes(machine: &mut Machine) {
+    for (name, wndproc) in CLASSES {
+        user32::register_builtin_class(machine, name, wndproc);
+    }
+}
+
+#[win32_derive::dllexport(17)]
+pub fn InitCommonControls(machine: &mut Machine) -> u32 {
+    register_classes(machine);
+    0
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct INITCOMMONCONTROLSEX {
+    dwSize: u32,
+    dwICC: u32,
+}
+unsafe impl memory::Pod for INITCOMMONCONTROLSEX {}
+
+#[win32_derive::dllexport]
+pub fn InitCommonControlsEx(machine: &mut Machine, picce: Option<&INITCOMMONCONTROLSEX>) -> bool {
+    // The classes asked for are a subset of ours, or ones we lack anyway.
+    register_classes(machine);
+    true
+}
+
+pub use status::CreateStatusWindowA;
@@ -0,0 +1,151 @@
+//! Progress bars: a well filling with blocks as a task proceeds.
+
+use super::*;
+
+pub const CLASS: &str = "msctls_progress32";
+
+const PBM_SETRANGE: u32 = 0x0401;
+const PBM_SETPOS: u32 = 0x0402;
+const PBM_DELTAPOS: u32 = 0x0403;
+const PBM_SETSTEP: u32 = 0x0404;
+const PBM_STEPIT: u32 = 0x0405;
+const PBM_SETRANGE32: u32 = 0x0406;
+const PBM_GETRANGE: u32 = 0x0407;
+const PBM_GETPOS: u32 = 0x0408;
+const PBM_SETBARCOLOR: u32 = 0x0409;
+const PBM_SETBKCOLOR: u32 = 0x2001; // CCM_SETBKCOLOR
+
+/// The PBRANGE struct PBM_GETRANGE fills.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PBRANGE {
+    iLow: i32,
+    iHigh: i32,
+}
+unsafe impl memory::Pod for PBRANGE {}
+
+/// Returned from color messages for the default color.
+const CLR_DEFAULT: u32 = 0xFF00_0000;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Progress {
+    low: i32,
+    high: i32,
+    pos: i32,
+    step: i32,
+    bar: Option<[u8; 4]>,
+    background: Option<[u8; 4]>,
+}
+
+impl Progress {
+    fn set_pos(&mut self, pos: i32) -> i32 {
+        std::mem::replace(&mut self.pos, pos.clamp(self.low, self.high.max(self.low)))
+    }
+
+    fn set_range(&mut self, low: i32, high: i32) -> u32 {
+        let prev = (self.low as u16 as u32) | (self.high as u16 as u32) << 16;
+        self.low = low;
+        self.high = high;
+        self.set_pos(self.pos);
+        prev
+    }
+
+    fn draw(&self, width: i32, height: i32) -> Drawing {
+        let mut d = Drawing::default();
+        d.fill(rect(0, 0, width, height), self.background.unwrap_or(FACE));
+        d.thin_edge(rect(0, 0, width, height), false);
+        // Blocks two-thirds as wide as the bar is high, spaced two pixels apart.
+        let inner = rect(2, 2, width - 2, height - 2);
+        let range = (self.high - self.low).max(1) as i64;
+        let filled =
+            ((self.pos - self.low) as i64 * (inner.right - inner.left) as i64 / range) as i32;
+        let block = ((height - 4) * 2 / 3).max(2);
+        let mut x = inner.left;
+        while x < inner.left + filled {
+            let right = (x + block).min(inner.right);
+            d.fill(
+                rect(x, inner.top, right, inner.bottom),
+                self.bar.unwrap_or(SELECTION),
+            );
+            x += block + 2;
+        }
+        d
+    }
+}
+
+fn redraw(machine: &mut Machine, hwnd: HWND) {
+    let window = machine.state.user32.windows.get(hwnd).unwrap();
+    let (width, height) = (window.width as i32, window.height as i32);
+    let drawing = machine.state.comctl32.progress[&hwnd].draw(width, height);
+    paint(machine, hwnd, drawing);
+}
+
+fn color(raw: u32) -> Option<[u8; 4]> {
+    if raw == CLR_DEFAULT {
+        return None;
+    }
+    Some([raw as u8, (raw >> 8) as u8, (raw >> 16) as u8, 0xff])
+}
+
+fn color_to_raw(color: Option<[u8; 4]>) -> u32 {
+    match color {
+        Some([r, g, b, _]) => r as u32 | (g as u32) << 8 | (b as u32) << 16,
+        None => CLR_DEFAULT,
+    }
+}
+
+pub fn wndproc(machine: &mut Machine, hwnd: HWND, msg: u32, wParam: u32, lParam: u32) -> u32 {
+    if msg == WM_CREATE {
+        let bar = Progress {
+            low: 0,
+            high: 100,
+            pos: 0,
+            step: 10,
+            bar: None,
+            background: None,
+        };
+        machine.state.comctl32.progress.insert(hwnd, bar);
+        return 0;
+    }
+    if msg == WM_PAINT {
+        redraw(machine, hwnd);
+        return 0;
+    }
+    let Some(bar) = machine.state.comctl32.progress.get_mut(&hwnd) else {
+        return def_window_proc(machine, hwnd, msg, wParam, lParam);
+    };
+    let ret = match msg {
+        PBM_SETRANGE => bar.set_range(lParam as u16 as i32, (lParam >> 16) as u16 as i32),
+        PBM_SETRANGE32 => bar.set_range(wParam as i32, lParam as i32),
+        PBM_GETRANGE => {
+            let (low, high) = (bar.low, bar.high);
+            if lParam != 0 {
+                let range = PBRANGE {
+                    iLow: low,
+                    iHigh: high,
+                };
+                machine.mem().put::<PBRANGE>(lParam, range);
+            }
+            // wParam asks for the low limit rather than the high.
+            let limit = if wParam != 0 { low } else { high };
+            return limit as u32;
+        }
+        PBM_SETPOS => bar.set_pos(wParam as i32) as u32,
+        PBM_DELTAPOS => bar.set_pos(bar.pos + wParam as i32) as u32,
+        PBM_GETPOS => return bar.pos as u32,
+        PBM_SETSTEP => return std::mem::replace(&mut bar.step, wParam as i32) as u32,
+        PBM_STEPIT => {
+            // Stepping past the end wraps around to the start.
+            let mut pos = bar.pos + bar.step;
+            if pos > bar.high {
+                pos = bar.low + (pos - bar.high);
+            }
+            bar.set_pos(pos) as u32
+        }
+        PBM_SETBARCOLOR => color_to_raw(std::mem::replace(&mut bar.bar, color(lParam))),
+        PBM_SETBKCOLOR => color_to_raw(std::mem::replace(&mut bar.background, color(lParam))),
+        _ => return def_window_proc(machine, hwnd, msg, wParam, lParam),
+    };
+    redraw(machine, hwnd);
+    ret
+}
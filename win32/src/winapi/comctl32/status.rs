@@ -0,0 +1,244 @@
+//! Status bars: a strip along the bottom of a window, divided into panes of text.
+
+use super::*;
+use crate::str16::String16;
+
+pub const CLASS: &str = "msctls_statusbar32";
+
+const HEIGHT: u32 = 20;
+
+// Common control styles, for placing status bars and toolbars.
+pub const CCS_TOP: u32 = 0x0001;
+pub const CCS_NORESIZE: u32 = 0x0004;
+pub const CCS_NOPARENTALIGN: u32 = 0x0008;
+
+const SB_SETTEXTA: u32 = 0x0401;
+const SB_GETTEXTA: u32 = 0x0402;
+const SB_GETTEXTLENGTHA: u32 = 0x0403;
+const SB_SETPARTS: u32 = 0x0404;
+const SB_GETPARTS: u32 = 0x0406;
+const SB_GETBORDERS: u32 = 0x0407;
+const SB_GETRECT: u32 = 0x040A;
+const SB_SIMPLE: u32 = 0x0409;
+const SB_ISSIMPLE: u32 = 0x040E;
+
+/// SB_SETTEXT's part index means the simple mode's single pane.
+const SB_SIMPLEID: u32 = 0xFF;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct StatusBar {
+    /// Right edge of each pane, with -1 extending to the bar's right edge.
+    parts: Vec<i32>,
+    texts: Vec<String>,
+    /// Showing one pane across the whole bar, as during menu browsing.
+    simple: bool,
+    simple_text: String,
+}
+
+/// Dock a control to its parent's top or bottom edge, per its CCS_ styles.
+pub(super) fn dock(machine: &mut Machine, hwnd: HWND, height: u32) {
+    let style = machine.state.user32.windows.get(hwnd).unwrap().class_style;
+    if style & (CCS_NORESIZE | CCS_NOPARENTALIGN) != 0 {
+        return;
+    }
+    let (width, parent_height) = parent_size(machine, hwnd);
+    let y = if style & CCS_TOP != 0 {
+        0
+    } else {
+        parent_height as i32 - height as i32
+    };
+    place(machine, hwnd, 0, y, width, height);
+}
+
+impl StatusBar {
+    fn pane_rects(&self, width: i32) -> Vec<RECT> {
+        if self.simple {
+            return vec![rect(2, 2, width - 2, HEIGHT as i32)];
+        }
+        let mut left = 2;
+        self.parts
+            .iter()
+            .map(|&right| {
+                let right = if right < 0 {
+                    width - 2
+                } else {
+                    right.min(width)
+                };
+                let r = rect(left, 2, right, HEIGHT as i32);
+                left = right + 2;
+                r
+            })
+            .collect()
+    }
+
+    fn draw(&self, width: i32) -> Drawing {
+        let mut d = Drawing::default();
+        d.fill(rect(0, 0, width, HEIGHT as i32), FACE);
+        let texts = match self.simple {
+            true => std::slice::from_ref(&self.simple_text),
+            false => &self.texts,
+        };
+        for (pane, text) in self.pane_rects(width).into_iter().zip(texts) {
+            d.thin_edge(pane, false);
+            let inner = rect(pane.left + 3, pane.top + 1, pane.right - 1, pane.bottom - 1);
+            d.text(inner, text, DARK);
+        }
+        d
+    }
+
+    fn text(&self, part: u32) -> Option<&String> {
+        if self.simple {
+            return Some(&self.simple_text);
+        }
+        self.texts.get(part as usize)
+    }
+}
+
+fn redraw(machine: &mut Machine, hwnd: HWND) {
+    let width = machine.state.user32.windows.get(hwnd).unwrap().width as i32;
+    let drawing = machine.state.comctl32.status[&hwnd].draw(width);
+    paint(machine, hwnd, drawing);
+}
+
+pub fn wndproc(machine: &mut Machine, hwnd: HWND, msg: u32, wParam: u32, lParam: u32) -> u32 {
+    match msg {
+        WM_CREATE => {
+            let title = machine
+                .state
+                .user32
+                .windows
+                .get(hwnd)
+                .unwrap()
+                .title
+                .clone();
+            let bar = StatusBar {
+                parts: vec![-1],
+                texts: vec![title],
+                simple: false,
+                simple_text: String::new(),
+            };
+            machine.state.comctl32.status.insert(hwnd, bar);
+            dock(machine, hwnd, HEIGHT);
+            0
+        }
+        WM_SIZE => {
+            dock(machine, hwnd, HEIGHT);
+            invalidate(machine, hwnd);
+            0
+        }
+        WM_PAINT => {
+            redraw(machine, hwnd);
+            0
+        }
+        WM_SETTEXT => {
+            let text = read_str(machine, lParam);
+            let bar = machine.state.comctl32.status.get_mut(&hwnd).unwrap();
+            bar.texts[0] = text;
+            redraw(machine, hwnd);
+            true as u32
+        }
+        SB_SETTEXTA => {
+            let part = wParam & 0xFF;
+            let text = read_str(machine, lParam);
+            let bar = machine.state.comctl32.status.get_mut(&hwnd).unwrap();
+            if part == SB_SIMPLEID {
+                bar.simple_text = text;
+            } else if let Some(t) = bar.texts.get_mut(part as usize) {
+                *t = text;
+            } else {
+                return false as u32;
+            }
+            redraw(machine, hwnd);
+            true as u32
+        }
+        SB_GETTEXTA | SB_GETTEXTLENGTHA => {
+            let bar = &machine.state.comctl32.status[&hwnd];
+            let Some(text) = bar.text(wParam).cloned() else {
+                return 0;
+            };
+            if msg == SB_GETTEXTA && lParam != 0 {
+                // The buffer is assumed big enough, as Windows does.
+                write_str(machine, lParam, text.len() as u32 + 1, &text);
+            }
+            text.len() as u32 // low word: length; high word: drawing flags
+        }
+        SB_SETPARTS => {
+            if wParam == 0 || wParam > 256 {
+                return false as u32;
+            }
+            let mem = machine.mem();
+            let parts: Vec<i32> = (0..wParam)
+                .map(|i| mem.get_pod::<i32>(lParam + i * 4))
+                .collect();
+            let bar = machine.state.comctl32.status.get_mut(&hwnd).unwrap();
+            bar.texts.resize(parts.len(), String::new());
+            bar.parts = parts;
+            redraw(machine, hwnd);
+            true as u32
+        }
+        SB_GETPARTS => {
+            let parts = machine.state.comctl32.status[&hwnd].parts.clone();
+            if lParam != 0 {
+                let mem = machine.mem();
+                for (i, &part) in parts.iter().take(wParam as usize).enumerate() {
+                    mem.put::<i32>(lParam + i as u32 * 4, part);
+                }
+            }
+            parts.len() as u32
+        }
+        SB_GETBORDERS => {
+            // Horizontal border, vertical border, and spacing between panes.
+            let mem = machine.mem();
+            for (i, border) in [0, 2, 2].into_iter().enumerate() {
+                mem.put::<i32>(lParam + i as u32 * 4, border);
+            }
+            true as u32
+        }
+        SB_GETRECT => {
+            let width = machine.state.user32.windows.get(hwnd).unwrap().width as i32;
+            let panes = machine.state.comctl32.status[&hwnd].pane_rects(width);
+            let Some(&pane) = panes.get(wParam as usize) else {
+                return false as u32;
+            };
+            machine.mem().put::<RECT>(lParam, pane);
+            true as u32
+        }
+        SB_SIMPLE => {
+            let bar = machine.state.comctl32.status.get_mut(&hwnd).unwrap();
+            bar.simple = wParam != 0;
+            redraw(machine, hwnd);
+            0
+        }
+        SB_ISSIMPLE => machine.state.comctl32.status[&hwnd].simple as u32,
+        _ => def_window_proc(machine, hwnd, msg, wParam, lParam),
+    }
+}
+
+#[win32_derive::dllexport]
+pub async fn CreateStatusWindowA(
+    machine: &mut Machine,
+    style: u32,
+    lpszText: Option<&str>,
+    hwndParent: HWND,
+    wID: u32,
+) -> HWND {
+    register_classes(machine);
+    let class = String16::from(CLASS);
+    let text = String16::from(lpszText.unwrap_or(""));
+    user32::CreateWindowExW(
+        machine,
+        Ok(user32::WindowStyleEx::empty()),
+        user32::CreateWindowClassName::Name(class.as_str16()),
+        Some(text.as_str16()),
+        Err(style),
+        0,
+        0,
+        0,
+        0,
+        hwndParent,
+        wID,
+        0,
+        0,
+    )
+    .await
+}
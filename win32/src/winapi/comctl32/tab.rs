@@ -0,0 +1,252 @@
+//! Tab controls: a row of tabs across the top of a page, one selected at a time.
+
+use super::*;
+
+pub const CLASS: &str = "SysTabControl32";
+
+const TCM_GETITEMCOUNT: u32 = 0x1304;
+const TCM_GETITEMA: u32 = 0x1305;
+const TCM_SETITEMA: u32 = 0x1306;
+const TCM_INSERTITEMA: u32 = 0x1307;
+const TCM_DELETEITEM: u32 = 0x1308;
+const TCM_DELETEALLITEMS: u32 = 0x1309;
+const TCM_GETITEMRECT: u32 = 0x130A;
+const TCM_GETCURSEL: u32 = 0x130B;
+const TCM_SETCURSEL: u32 = 0x130C;
+const TCM_ADJUSTRECT: u32 = 0x1328;
+
+// TCITEM mask bits.
+const TCIF_TEXT: u32 = 0x0001;
+const TCIF_PARAM: u32 = 0x0008;
+
+// Notification codes.
+const TCN_SELCHANGE: i32 = -551;
+const TCN_SELCHANGING: i32 = -552;
+
+/// Height of the strip of tabs.
+const STRIP_HEIGHT: i32 = 20;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct TCITEMA {
+    mask: u32,
+    dwState: u32,
+    dwStateMask: u32,
+    pszText: u32,
+    cchTextMax: i32,
+    iImage: i32,
+    lParam: u32,
+}
+unsafe impl memory::Pod for TCITEMA {}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct TabItem {
+    text: String,
+    param: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Tab {
+    items: Vec<TabItem>,
+    /// The selected tab, or -1 for none.
+    selected: i32,
+}
+
+impl Tab {
+    /// Each tab's rect, sized as if its text were drawn in the system font.
+    fn item_rects(&self) -> Vec<RECT> {
+        let mut left = 2;
+        self.items
+            .iter()
+            .map(|item| {
+                let width = text_width(&item.text) + 12;
+                let r = rect(left, 2, left + width, STRIP_HEIGHT);
+                left += width;
+                r
+            })
+            .collect()
+    }
+
+    fn draw(&self, width: i32, height: i32) -> Drawing {
+        let mut d = Drawing::default();
+        d.fill(rect(0, 0, width, height), FACE);
+        d.edge(rect(0, STRIP_HEIGHT, width, height), true);
+        for (i, mut r) in self.item_rects().into_iter().enumerate() {
+            // The selected tab stands taller and joins the page below.
+            if i as i32 == self.selected {
+                r = rect(r.left - 2, r.top - 2, r.right + 2, r.bottom + 1);
+            }
+            d.fill(r, FACE);
+            d.frame(r, HILIGHT, SHADOW);
+            if i as i32 == self.selected {
+                d.fill(rect(r.left + 1, r.bottom - 1, r.right - 1, r.bottom), FACE);
+            }
+            d.text_centered(r, &self.items[i].text, DARK);
+        }
+        d
+    }
+}
+
+fn redraw(machine: &mut Machine, hwnd: HWND) {
+    let window = machine.state.user32.windows.get(hwnd).unwrap();
+    let (width, height) = (window.width as i32, window.height as i32);
+    let drawing = machine.state.comctl32.tabs[&hwnd].draw(width, height);
+    paint(machine, hwnd, drawing);
+}
+
+fn set_item(machine: &Machine, item: &mut TabItem, tcitem: &TCITEMA) {
+    if tcitem.mask & TCIF_TEXT != 0 {
+        item.text = read_str(machine, tcitem.pszText);
+    }
+    if tcitem.mask & TCIF_PARAM != 0 {
+        item.param = tcitem.lParam;
+    }
+}
+
+pub fn wndproc(machine: &mut Machine, hwnd: HWND, msg: u32, wParam: u32, lParam: u32) -> u32 {
+    match msg {
+        WM_CREATE => {
+            let tab = Tab {
+                items: Vec::new(),
+                selected: -1,
+            };
+            machine.state.comctl32.tabs.insert(hwnd, tab);
+            return 0;
+        }
+        WM_PAINT => {
+            redraw(machine, hwnd);
+            return 0;
+        }
+        WM_LBUTTONDOWN => {
+            let (x, y) = mouse_pos(lParam);
+            let tab = &machine.state.comctl32.tabs[&hwnd];
+            let Some(i) = tab
+                .item_rects()
+                .iter()
+                .position(|r| x >= r.left && x < r.right && y >= r.top && y < r.bottom)
+            else {
+                return 0;
+            };
+            if i as i32 == tab.selected {
+                return 0;
+            }
+            // TODO: TCN_SELCHANGING's result can veto the change, but we only post it.
+            notify(machine, hwnd, TCN_SELCHANGING, &[]);
+            machine.state.comctl32.tabs.get_mut(&hwnd).unwrap().selected = i as i32;
+            redraw(machine, hwnd);
+            notify(machine, hwnd, TCN_SELCHANGE, &[]);
+            return 0;
+        }
+        _ => {}
+    }
+
+    let ret = match msg {
+        TCM_INSERTITEMA => {
+            let tcitem = machine.mem().get_pod::<TCITEMA>(lParam);
+            let mut item = TabItem {
+                text: String::new(),
+                param: 0,
+            };
+            set_item(machine, &mut item, &tcitem);
+            let tab = machine.state.comctl32.tabs.get_mut(&hwnd).unwrap();
+            let at = (wParam as usize).min(tab.items.len());
+            tab.items.insert(at, item);
+            if tab.selected == -1 {
+                tab.selected = 0;
+            } else if at as i32 <= tab.selected {
+                tab.selected += 1;
+            }
+            at as u32
+        }
+        TCM_SETITEMA => {
+            let tcitem = machine.mem().get_pod::<TCITEMA>(lParam);
+            let tab = machine.state.comctl32.tabs.get_mut(&hwnd).unwrap();
+            let Some(item) = tab.items.get_mut(wParam as usize) else {
+                return false as u32;
+            };
+            let mut item = std::mem::replace(
+                item,
+                TabItem {
+                    text: String::new(),
+                    param: 0,
+                },
+            );
+            set_item(machine, &mut item, &tcitem);
+            machine.state.comctl32.tabs.get_mut(&hwnd).unwrap().items[wParam as usize] = item;
+            true as u32
+        }
+        TCM_GETITEMA => {
+            let mut tcitem = machine.mem().get_pod::<TCITEMA>(lParam);
+            let tab = &machine.state.comctl32.tabs[&hwnd];
+            let Some(item) = tab.items.get(wParam as usize) else {
+                return false as u32;
+            };
+            let text = item.text.clone();
+            if tcitem.mask & TCIF_PARAM != 0 {
+                tcitem.lParam = item.param;
+            }
+            if tcitem.mask & TCIF_TEXT != 0 {
+                write_str(
+                    machine,
+                    tcitem.pszText,
+                    tcitem.cchTextMax.max(0) as u32,
+                    &text,
+                );
+            }
+            machine.mem().put::<TCITEMA>(lParam, tcitem);
+            return true as u32;
+        }
+        TCM_DELETEITEM => {
+            let tab = machine.state.comctl32.tabs.get_mut(&hwnd).unwrap();
+            let at = wParam as usize;
+            if at >= tab.items.len() {
+                return false as u32;
+            }
+            tab.items.remove(at);
+            if at as i32 == tab.selected {
+                tab.selected = -1;
+            } else if (at as i32) < tab.selected {
+                tab.selected -= 1;
+            }
+            true as u32
+        }
+        TCM_DELETEALLITEMS => {
+            let tab = machine.state.comctl32.tabs.get_mut(&hwnd).unwrap();
+            tab.items.clear();
+            tab.selected = -1;
+            true as u32
+        }
+        TCM_GETITEMCOUNT => return machine.state.comctl32.tabs[&hwnd].items.len() as u32,
+        TCM_GETITEMRECT => {
+            let rects = machine.state.comctl32.tabs[&hwnd].item_rects();
+            let Some(&r) = rects.get(wParam as usize) else {
+                return false as u32;
+            };
+            machine.mem().put::<RECT>(lParam, r);
+            return true as u32;
+        }
+        TCM_GETCURSEL => return machine.state.comctl32.tabs[&hwnd].selected as u32,
+        TCM_SETCURSEL => {
+            let tab = machine.state.comctl32.tabs.get_mut(&hwnd).unwrap();
+            if wParam as usize >= tab.items.len() {
+                return -1i32 as u32;
+            }
+            std::mem::replace(&mut tab.selected, wParam as i32) as u32
+        }
+        TCM_ADJUSTRECT => {
+            // Convert between the page's display area and the whole control's.
+            let mut r = machine.mem().get_pod::<RECT>(lParam);
+            let (top, edge) = (STRIP_HEIGHT + 2, 2);
+            if wParam != 0 {
+                r = rect(r.left - edge, r.top - top, r.right + edge, r.bottom + edge);
+            } else {
+                r = rect(r.left + edge, r.top + top, r.right - edge, r.bottom - edge);
+            }
+            machine.mem().put::<RECT>(lParam, r);
+            return 0;
+        }
+        _ => return def_window_proc(machine, hwnd, msg, wParam, lParam),
+    };
+    redraw(machine, hwnd);
+    ret
+}
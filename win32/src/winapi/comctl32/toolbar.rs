@@ -0,0 +1,345 @@
+//! Toolbars: a strip of push buttons along the top of a window.
+
+use super::*;
+use status::{dock, CCS_NORESIZE};
+
+pub const CLASS: &str = "ToolbarWindow32";
+
+const TB_ENABLEBUTTON: u32 = 0x0401;
+const TB_CHECKBUTTON: u32 = 0x0402;
+const TB_ISBUTTONENABLED: u32 = 0x0409;
+const TB_ISBUTTONCHECKED: u32 = 0x040A;
+const TB_ADDBITMAP: u32 = 0x0413;
+const TB_ADDBUTTONSA: u32 = 0x0414;
+const TB_INSERTBUTTONA: u32 = 0x0415;
+const TB_DELETEBUTTON: u32 = 0x0416;
+const TB_BUTTONCOUNT: u32 = 0x0418;
+const TB_ADDSTRINGA: u32 = 0x041C;
+const TB_BUTTONSTRUCTSIZE: u32 = 0x041E;
+const TB_SETBUTTONSIZE: u32 = 0x041F;
+const TB_AUTOSIZE: u32 = 0x0421;
+
+// fsState bits.
+const TBSTATE_CHECKED: u8 = 0x01;
+const TBSTATE_PRESSED: u8 = 0x02;
+const TBSTATE_ENABLED: u8 = 0x04;
+const TBSTATE_HIDDEN: u8 = 0x08;
+
+// fsStyle bits.
+const TBSTYLE_SEP: u8 = 0x01;
+const TBSTYLE_CHECK: u8 = 0x02;
+
+/// Width of a separator between buttons.
+const SEPARATOR_WIDTH: i32 = 8;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct TBBUTTON {
+    iBitmap: i32,
+    idCommand: u32,
+    fsState: u8,
+    fsStyle: u8,
+    bReserved: [u8; 2],
+    dwData: u32,
+    iString: u32,
+}
+unsafe impl memory::Pod for TBBUTTON {}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Button {
+    id: u32,
+    state: u8,
+    style: u8,
+    /// TBBUTTON's iString: an index into the toolbar's strings, unless it
+    /// pointed at the text itself, read into `text`.
+    string: u32,
+    text: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Toolbar {
+    buttons: Vec<Button>,
+    button_size: (i32, i32),
+    /// Count of bitmaps added, to number the next TB_ADDBITMAP's.
+    bitmaps: u32,
+    /// Strings added by TB_ADDSTRING, for buttons' labels.
+    strings: Vec<String>,
+    /// The button the mouse went down on, while it's held.
+    pressed: Option<usize>,
+}
+
+impl Toolbar {
+    fn height(&self) -> u32 {
+        self.button_size.1 as u32 + 6
+    }
+
+    /// Each button's rect, with hidden buttons taking up no space.
+    fn button_rects(&self) -> Vec<Option<RECT>> {
+        let (width, height) = self.button_size;
+        let mut left = 4;
+        self.buttons
+            .iter()
+            .map(|button| {
+                if button.state & TBSTATE_HIDDEN != 0 {
+                    return None;
+                }
+                let w = if button.style & TBSTYLE_SEP != 0 {
+                    SEPARATOR_WIDTH
+                } else {
+                    width
+                };
+                let r = rect(left, 3, left + w, 3 + height);
+                left += w;
+                Some(r)
+            })
+            .collect()
+    }
+
+    fn button_at(&self, x: i32, y: i32) -> Option<usize> {
+        self.button_rects().iter().position(|r| match r {
+            Some(r) => x >= r.left && x < r.right && y >= r.top && y < r.bottom,
+            None => false,
+        })
+    }
+
+    fn index_of(&self, id: u32) -> Option<usize> {
+        self.buttons
+            .iter()
+            .position(|b| b.id == id && b.style & TBSTYLE_SEP == 0)
+    }
+
+    fn label<'a>(&'a self, button: &'a Button) -> &'a str {
+        match &button.text {
+            Some(text) => text,
+            None => self
+                .strings
+                .get(button.string as usize)
+                .map_or("", |s| s.as_str()),
+        }
+    }
+
+    fn draw(&self, width: i32) -> Drawing {
+        let mut d = Drawing::default();
+        let height = self.height() as i32;
+        d.fill(rect(0, 0, width, height), FACE);
+        d.thin_edge(rect(0, 0, width, height), true);
+        for (button, r) in self.buttons.iter().zip(self.button_rects()) {
+            let Some(r) = r else { continue };
+            if button.style & TBSTYLE_SEP != 0 {
+                let x = (r.left + r.right) / 2 - 1;
+                d.thin_edge(rect(x, r.top, x + 2, r.bottom), false);
+                continue;
+            }
+            // TODO: the button's image; we have no image lists.
+            let down = button.state & (TBSTATE_PRESSED | TBSTATE_CHECKED) != 0;
+            d.edge(r, !down);
+            let enabled = button.state & TBSTATE_ENABLED != 0;
+            let label = self.label(button);
+            if !label.is_empty() {
+                let inner = rect(r.left + 2, r.top + 2, r.right - 2, r.bottom - 2);
+                d.text_centered(inner, label, if enabled { DARK } else { SHADOW });
+            } else if !enabled {
+                let inner = rect(r.left + 6, r.top + 6, r.right - 6, r.bottom - 6);
+                d.thin_edge(inner, false);
+            }
+        }
+        d
+    }
+}
+
+fn redraw(machine: &mut Machine, hwnd: HWND) {
+    let width = machine.state.user32.windows.get(hwnd).unwrap().width as i32;
+    let drawing = machine.state.comctl32.toolbars[&hwnd].draw(width);
+    paint(machine, hwnd, drawing);
+}
+
+fn autosize(machine: &mut Machine, hwnd: HWND) {
+    let height = machine.state.comctl32.toolbars[&hwnd].height();
+    dock(machine, hwnd, height);
+    invalidate(machine, hwnd);
+}
+
+/// Read count TBBUTTONs from guest memory.
+fn read_buttons(machine: &Machine, addr: u32, count: u32) -> Vec<Button> {
+    let size = std::mem::size_of::<TBBUTTON>() as u32;
+    (0..count)
+        .map(|i| {
+            let b = machine.mem().get_pod::<TBBUTTON>(addr + i * size);
+            // iString is either a string index or, above 0xFFFF, the string itself.
+            let text = match b.iString {
+                0..=0xFFFF => None,
+                addr => Some(read_str(machine, addr)),
+            };
+            Button {
+                id: b.idCommand,
+                state: b.fsState,
+                style: b.fsStyle,
+                string: b.iString,
+                text,
+            }
+        })
+        .collect()
+}
+
+fn set_state(machine: &mut Machine, hwnd: HWND, id: u32, bit: u8, on: bool) -> u32 {
+    let toolbar = machine.state.comctl32.toolbars.get_mut(&hwnd).unwrap();
+    let Some(i) = toolbar.index_of(id) else {
+        return false as u32;
+    };
+    let state = &mut toolbar.buttons[i].state;
+    if on {
+        *state |= bit;
+    } else {
+        *state &= !bit;
+    }
+    redraw(machine, hwnd);
+    true as u32
+}
+
+pub fn wndproc(machine: &mut Machine, hwnd: HWND, msg: u32, wParam: u32, lParam: u32) -> u32 {
+    match msg {
+        WM_CREATE => {
+            let toolbar = Toolbar {
+                buttons: Vec::new(),
+                button_size: (24, 22),
+                bitmaps: 0,
+                strings: Vec::new(),
+                pressed: None,
+            };
+            machine.state.comctl32.toolbars.insert(hwnd, toolbar);
+            // Toolbars sit along the top, unlike status bars.
+            let window = machine.state.user32.windows.get_mut(hwnd).unwrap();
+            window.class_style |= status::CCS_TOP;
+            autosize(machine, hwnd);
+            0
+        }
+        WM_SIZE | TB_AUTOSIZE => {
+            autosize(machine, hwnd);
+            0
+        }
+        WM_PAINT => {
+            redraw(machine, hwnd);
+            0
+        }
+        WM_LBUTTONDOWN => {
+            let (x, y) = mouse_pos(lParam);
+            let toolbar = machine.state.comctl32.toolbars.get_mut(&hwnd).unwrap();
+            let Some(i) = toolbar.button_at(x, y) else {
+                return 0;
+            };
+            let button = &mut toolbar.buttons[i];
+            if button.style & TBSTYLE_SEP != 0 || button.state & TBSTATE_ENABLED == 0 {
+                return 0;
+            }
+            button.state |= TBSTATE_PRESSED;
+            toolbar.pressed = Some(i);
+            machine.state.user32.capture = hwnd;
+            redraw(machine, hwnd);
+            0
+        }
+        WM_LBUTTONUP => {
+            let (x, y) = mouse_pos(lParam);
+            let toolbar = machine.state.comctl32.toolbars.get_mut(&hwnd).unwrap();
+            let Some(i) = toolbar.pressed.take() else {
+                return 0;
+            };
+            machine.state.user32.capture = HWND::null();
+            let button = &mut toolbar.buttons[i];
+            button.state &= !TBSTATE_PRESSED;
+            let id = button.id;
+            let clicked = toolbar.button_at(x, y) == Some(i);
+            if clicked && toolbar.buttons[i].style & TBSTYLE_CHECK != 0 {
+                toolbar.buttons[i].state ^= TBSTATE_CHECKED;
+            }
+            redraw(machine, hwnd);
+            if clicked {
+                let (parent, _) = parent_of(machine, hwnd);
+                user32::post_message(machine, parent, WM_COMMAND, id, hwnd.to_raw());
+            }
+            0
+        }
+        TB_BUTTONSTRUCTSIZE => {
+            if wParam != std::mem::size_of::<TBBUTTON>() as u32 {
+                log::warn!("TB_BUTTONSTRUCTSIZE: unexpected size {wParam}");
+            }
+            0
+        }
+        TB_ADDBUTTONSA | TB_INSERTBUTTONA => {
+            let count = if msg == TB_ADDBUTTONSA { wParam } else { 1 };
+            let buttons = read_buttons(machine, lParam, count);
+            let toolbar = machine.state.comctl32.toolbars.get_mut(&hwnd).unwrap();
+            let at = if msg == TB_ADDBUTTONSA {
+                toolbar.buttons.len()
+            } else {
+                (wParam as usize).min(toolbar.buttons.len())
+            };
+            toolbar.buttons.splice(at..at, buttons);
+            redraw(machine, hwnd);
+            true as u32
+        }
+        TB_DELETEBUTTON => {
+            let toolbar = machine.state.comctl32.toolbars.get_mut(&hwnd).unwrap();
+            if wParam as usize >= toolbar.buttons.len() {
+                return false as u32;
+            }
+            toolbar.buttons.remove(wParam as usize);
+            toolbar.pressed = None;
+            redraw(machine, hwnd);
+            true as u32
+        }
+        TB_BUTTONCOUNT => machine.state.comctl32.toolbars[&hwnd].buttons.len() as u32,
+        TB_ENABLEBUTTON => set_state(machine, hwnd, wParam, TBSTATE_ENABLED, lParam & 0xFFFF != 0),
+        TB_CHECKBUTTON => set_state(machine, hwnd, wParam, TBSTATE_CHECKED, lParam & 0xFFFF != 0),
+        TB_ISBUTTONENABLED | TB_ISBUTTONCHECKED => {
+            let toolbar = &machine.state.comctl32.toolbars[&hwnd];
+            let bit = if msg == TB_ISBUTTONENABLED {
+                TBSTATE_ENABLED
+            } else {
+                TBSTATE_CHECKED
+            };
+            match toolbar.index_of(wParam) {
+                Some(i) => (toolbar.buttons[i].state & bit != 0) as u32,
+                None => 0,
+            }
+        }
+        TB_SETBUTTONSIZE => {
+            let toolbar = machine.state.comctl32.toolbars.get_mut(&hwnd).unwrap();
+            toolbar.button_size = (lParam as u16 as i32, (lParam >> 16) as u16 as i32);
+            let style = machine.state.user32.windows.get(hwnd).unwrap().class_style;
+            if style & CCS_NORESIZE == 0 {
+                autosize(machine, hwnd);
+            }
+            true as u32
+        }
+        TB_ADDSTRINGA => {
+            if wParam != 0 {
+                log::warn!("TB_ADDSTRING: string resources unimplemented");
+                return -1i32 as u32;
+            }
+            // A list of nul-terminated strings, ending with an empty one.
+            let mut strings = Vec::new();
+            let mut addr = lParam;
+            loop {
+                let str = read_str(machine, addr);
+                if str.is_empty() {
+                    break;
+                }
+                addr += str.len() as u32 + 1;
+                strings.push(str);
+            }
+            let toolbar = machine.state.comctl32.toolbars.get_mut(&hwnd).unwrap();
+            let first = toolbar.strings.len() as u32;
+            toolbar.strings.extend(strings);
+            redraw(machine, hwnd);
+            first
+        }
+        TB_ADDBITMAP => {
+            // Images aren't drawn, but callers index the bitmaps they add.
+            let toolbar = machine.state.comctl32.toolbars.get_mut(&hwnd).unwrap();
+            let first = toolbar.bitmaps;
+            toolbar.bitmaps += wParam;
+            first
+        }
+        _ => def_window_proc(machine, hwnd, msg, wParam, lParam),
+    }
+}
@@ -0,0 +1,269 @@
+//! Trackbars: a slider dragged along a channel to pick a value in a range.
+
+use super::*;
+
+pub const CLASS: &str = "msctls_trackbar32";
+
+const TBM_GETPOS: u32 = 0x0400;
+const TBM_GETRANGEMIN: u32 = 0x0401;
+const TBM_GETRANGEMAX: u32 = 0x0402;
+const TBM_SETPOS: u32 = 0x0405;
+const TBM_SETRANGE: u32 = 0x0406;
+const TBM_SETRANGEMIN: u32 = 0x0407;
+const TBM_SETRANGEMAX: u32 = 0x0408;
+const TBM_SETTICFREQ: u32 = 0x0414;
+const TBM_SETPAGESIZE: u32 = 0x0415;
+const TBM_GETPAGESIZE: u32 = 0x0416;
+const TBM_SETLINESIZE: u32 = 0x0417;
+const TBM_GETLINESIZE: u32 = 0x0418;
+
+const TBS_VERT: u32 = 0x0002;
+
+// Scroll codes in the WM_HSCROLL/WM_VSCROLL sent to the parent.
+const TB_PAGEUP: u32 = 2;
+const TB_PAGEDOWN: u32 = 3;
+const TB_THUMBTRACK: u32 = 5;
+const TB_ENDTRACK: u32 = 8;
+
+/// The thumb's length along the channel and its breadth across it.
+const THUMB: (i32, i32) = (11, 20);
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Trackbar {
+    min: i32,
+    max: i32,
+    pos: i32,
+    line_size: i32,
+    page_size: i32,
+    tic_freq: i32,
+    vertical: bool,
+    dragging: bool,
+}
+
+impl Trackbar {
+    fn set_pos(&mut self, pos: i32) {
+        self.pos = pos.clamp(self.min, self.max.max(self.min));
+    }
+
+    /// The length and breadth of the control, whichever way it runs.
+    fn extent(&self, width: i32, height: i32) -> (i32, i32) {
+        if self.vertical {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Where along the channel the thumb's center goes for a position.
+    fn offset_of(&self, pos: i32, length: i32) -> i32 {
+        let travel = (length - 2 * 4 - THUMB.0).max(0);
+        let range = (self.max - self.min).max(1);
+        4 + THUMB.0 / 2 + ((pos - self.min) as i64 * travel as i64 / range as i64) as i32
+    }
+
+    /// The position nearest a point along the channel.
+    fn pos_at(&self, offset: i32, length: i32) -> i32 {
+        let travel = (length - 2 * 4 - THUMB.0).max(1);
+        let range = self.max - self.min;
+        let along = (offset - 4 - THUMB.0 / 2).clamp(0, travel);
+        self.min + ((along as i64 * range as i64 + travel as i64 / 2) / travel as i64) as i32
+    }
+
+    fn draw(&self, width: i32, height: i32) -> Drawing {
+        let (length, breadth) = self.extent(width, height);
+        // Shapes are laid out running horizontally, then flipped if vertical.
+        let mut shapes = Vec::new();
+        let channel_top = breadth / 2 - 2;
+        shapes.push((rect(4, channel_top, length - 4, channel_top + 4), false));
+        if self.tic_freq > 0 && self.max > self.min {
+            let mut tic = self.min;
+            while tic <= self.max {
+                let x = self.offset_of(tic, length);
+                shapes.push((rect(x, breadth - 4, x + 1, breadth - 1), true));
+                tic += self.tic_freq;
+            }
+        }
+        let center = self.offset_of(self.pos, length);
+        let thumb_top = (breadth - THUMB.1) / 2 - 2;
+        let thumb = rect(
+            center - THUMB.0 / 2,
+            thumb_top,
+            center - THUMB.0 / 2 + THUMB.0,
+            thumb_top + THUMB.1,
+        );
+
+        let flip = |r: RECT| {
+            if self.vertical {
+                rect(r.top, r.left, r.bottom, r.right)
+            } else {
+                r
+            }
+        };
+        let mut d = Drawing::default();
+        d.fill(rect(0, 0, width, height), FACE);
+        for (r, tic) in shapes {
+            if tic {
+                d.fill(flip(r), DARK);
+            } else {
+                d.thin_edge(flip(r), false);
+            }
+        }
+        d.fill(flip(thumb), FACE);
+        d.edge(flip(thumb), true);
+        d
+    }
+}
+
+fn size_of(machine: &Machine, hwnd: HWND) -> (i32, i32) {
+    let window = machine.state.user32.windows.get(hwnd).unwrap();
+    (window.width as i32, window.height as i32)
+}
+
+fn redraw(machine: &mut Machine, hwnd: HWND) {
+    let (width, height) = size_of(machine, hwnd);
+    let drawing = machine.state.comctl32.trackbars[&hwnd].draw(width, height);
+    paint(machine, hwnd, drawing);
+}
+
+/// Tell the parent about a change the user made, as a scroll bar would.
+fn scroll(machine: &mut Machine, hwnd: HWND, code: u32) {
+    let trackbar = &machine.state.comctl32.trackbars[&hwnd];
+    let msg = if trackbar.vertical {
+        WM_VSCROLL
+    } else {
+        WM_HSCROLL
+    };
+    let wParam = code | (trackbar.pos as u16 as u32) << 16;
+    let (parent, _) = parent_of(machine, hwnd);
+    user32::post_message(machine, parent, msg, wParam, hwnd.to_raw());
+}
+
+/// Move the thumb to the mouse, returning whether the position changed.
+fn track(machine: &mut Machine, hwnd: HWND, lParam: u32) -> bool {
+    let (width, height) = size_of(machine, hwnd);
+    let (x, y) = mouse_pos(lParam);
+    let trackbar = machine.state.comctl32.trackbars.get_mut(&hwnd).unwrap();
+    let (length, _) = trackbar.extent(width, height);
+    let offset = if trackbar.vertical { y } else { x };
+    let prev = trackbar.pos;
+    let pos = trackbar.pos_at(offset, length);
+    trackbar.set_pos(pos);
+    trackbar.pos != prev
+}
+
+pub fn wndproc(machine: &mut Machine, hwnd: HWND, msg: u32, wParam: u32, lParam: u32) -> u32 {
+    match msg {
+        WM_CREATE => {
+            let style = machine.state.user32.windows.get(hwnd).unwrap().class_style;
+            let trackbar = Trackbar {
+                min: 0,
+                max: 100,
+                pos: 0,
+                line_size: 1,
+                page_size: 20,
+                tic_freq: 1,
+                vertical: style & TBS_VERT != 0,
+                dragging: false,
+            };
+            machine.state.comctl32.trackbars.insert(hwnd, trackbar);
+            0
+        }
+        WM_PAINT => {
+            redraw(machine, hwnd);
+            0
+        }
+        WM_LBUTTONDOWN => {
+            let (width, height) = size_of(machine, hwnd);
+            let (x, y) = mouse_pos(lParam);
+            let trackbar = machine.state.comctl32.trackbars.get_mut(&hwnd).unwrap();
+            let (length, _) = trackbar.extent(width, height);
+            let (offset, center) = (
+                if trackbar.vertical { y } else { x },
+                trackbar.offset_of(trackbar.pos, length),
+            );
+            if (offset - center).abs() <= THUMB.0 / 2 {
+                // On the thumb: drag it.
+                trackbar.dragging = true;
+                machine.state.user32.capture = hwnd;
+                return 0;
+            }
+            // Elsewhere in the channel: page towards the click.
+            let (code, pos) = if offset < center {
+                (TB_PAGEUP, trackbar.pos - trackbar.page_size)
+            } else {
+                (TB_PAGEDOWN, trackbar.pos + trackbar.page_size)
+            };
+            trackbar.set_pos(pos);
+            redraw(machine, hwnd);
+            scroll(machine, hwnd, code);
+            scroll(machine, hwnd, TB_ENDTRACK);
+            0
+        }
+        WM_MOUSEMOVE => {
+            if machine.state.comctl32.trackbars[&hwnd].dragging && track(machine, hwnd, lParam) {
+                redraw(machine, hwnd);
+                scroll(machine, hwnd, TB_THUMBTRACK);
+            }
+            0
+        }
+        WM_LBUTTONUP => {
+            let trackbar = machine.state.comctl32.trackbars.get_mut(&hwnd).unwrap();
+            if !std::mem::replace(&mut trackbar.dragging, false) {
+                return 0;
+            }
+            machine.state.user32.capture = HWND::null();
+            if track(machine, hwnd, lParam) {
+                redraw(machine, hwnd);
+            }
+            scroll(machine, hwnd, TB_ENDTRACK);
+            0
+        }
+        _ => {
+            let Some(trackbar) = machine.state.comctl32.trackbars.get_mut(&hwnd) else {
+                return def_window_proc(machine, hwnd, msg, wParam, lParam);
+            };
+            let ret = match msg {
+                TBM_GETPOS => return trackbar.pos as u32,
+                TBM_GETRANGEMIN => return trackbar.min as u32,
+                TBM_GETRANGEMAX => return trackbar.max as u32,
+                TBM_GETPAGESIZE => return trackbar.page_size as u32,
+                TBM_GETLINESIZE => return trackbar.line_size as u32,
+                TBM_SETPOS => {
+                    trackbar.set_pos(lParam as i32);
+                    0
+                }
+                TBM_SETRANGE => {
+                    trackbar.min = lParam as u16 as i32;
+                    trackbar.max = (lParam >> 16) as u16 as i32;
+                    trackbar.set_pos(trackbar.pos);
+                    0
+                }
+                TBM_SETRANGEMIN | TBM_SETRANGEMAX => {
+                    if msg == TBM_SETRANGEMIN {
+                        trackbar.min = lParam as i32;
+                    } else {
+                        trackbar.max = lParam as i32;
+                    }
+                    trackbar.set_pos(trackbar.pos);
+                    0
+                }
+                TBM_SETTICFREQ => {
+                    trackbar.tic_freq = wParam as i32;
+                    0
+                }
+                TBM_SETPAGESIZE => {
+                    return std::mem::replace(&mut trackbar.page_size, lParam as i32) as u32
+                }
+                TBM_SETLINESIZE => {
+                    return std::mem::replace(&mut trackbar.line_size, lParam as i32) as u32
+                }
+                _ => return def_window_proc(machine, hwnd, msg, wParam, lParam),
+            };
+            // The set messages' wParam asks for a redraw.
+            if wParam != 0 || msg == TBM_SETTICFREQ {
+                redraw(machine, hwnd);
+            }
+            ret
+        }
+    }
+}
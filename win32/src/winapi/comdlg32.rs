@@ -0,0 +1,193 @@
+//! Common dialogs, as far as picking files to open and save.
+
+#![allow(non_snake_case)]
+
+use crate::{host::FileDialog, machine::Machine};
+use memory::Extensions;
+
+const TRACE_CONTEXT: &'static str = "comdlg32";
+
+/*
+## Common dialogs
+
+The open and save dialogs are the host's own file picker, given the guest's
+filters and starting directory; the file picked is handed back as a guest path,
+via kernel32's directory mappings, so the guest can open it like any other.
+*/
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct State {
+    /// The error from the last dialog, for CommDlgExtendedError.
+    error: u32,
+}
+
+#[repr(C, packed)]
+#[derive(Clone, Debug)]
+pub struct OPENFILENAMEA {
+    lStructSize: u32,
+    hwndOwner: u32,
+    hInstance: u32,
+    lpstrFilter: u32,
+    lpstrCustomFilter: u32,
+    nMaxCustFilter: u32,
+    nFilterIndex: u32,
+    lpstrFile: u32,
+    nMaxFile: u32,
+    lpstrFileTitle: u32,
+    nMaxFileTitle: u32,
+    lpstrInitialDir: u32,
+    lpstrTitle: u32,
+    Flags: u32,
+    nFileOffset: u16,
+    nFileExtension: u16,
+    lpstrDefExt: u32,
+    lCustData: u32,
+    lpfnHook: u32,
+    lpTemplateName: u32,
+    // Windows 2000 added more fields, which we leave alone.
+}
+unsafe impl memory::Pod for OPENFILENAMEA {}
+
+const OFN_ALLOWMULTISELECT: u32 = 0x0200;
+
+const CDERR_INITIALIZATION: u32 = 0x0002;
+const FNERR_BUFFERTOOSMALL: u32 = 0x3003;
+
+/// Read a guest string, if the pointer isn't null.
+fn read_str(machine: &Machine, addr: u32) -> Option<String> {
+    if addr == 0 {
+        return None;
+    }
+    let bytes = machine.mem().slicez(addr);
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Parse a filter string: pairs of a description and ';'-separated patterns,
+/// each nul-terminated, ending with an empty string.
+fn read_filters(machine: &Machine, mut addr: u32) -> Vec<(String, Vec<String>)> {
+    let mut filters = Vec::new();
+    if addr == 0 {
+        return filters;
+    }
+    let mem = machine.mem();
+    loop {
+        let desc = mem.slicez(addr);
+        if desc.is_empty() {
+            break;
+        }
+        addr += desc.len() as u32 + 1;
+        let patterns = mem.slicez(addr);
+        addr += patterns.len() as u32 + 1;
+        let patterns = String::from_utf8_lossy(patterns)
+            .split(';')
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect();
+        filters.push((String::from_utf8_lossy(desc).into_owned(), patterns));
+    }
+    filters
+}
+
+/// Copy a string out to a buffer of len bytes, if it fits with its nul.
+fn write_str(machine: &Machine, addr: u32, len: u32, str: &str) -> bool {
+    if str.len() as u32 >= len {
+        return false;
+    }
+    let out = machine
+        .mem()
+        .sub(addr, str.len() as u32 + 1)
+        .as_mut_slice_todo();
+    out[..str.len()].copy_from_slice(str.as_bytes());
+    out[str.len()] = 0;
+    true
+}
+
+fn get_file_name(machine: &mut Machine, lpofn: Option<&mut OPENFILENAMEA>, save: bool) -> bool {
+    let Some(ofn) = lpofn else {
+        machine.state.comdlg32.error = CDERR_INITIALIZATION;
+        return false;
+    };
+    machine.state.comdlg32.error = 0;
+    if ofn.lpstrFile == 0 {
+        machine.state.comdlg32.error = CDERR_INITIALIZATION;
+        return false;
+    }
+    let flags = ofn.Flags;
+    if flags & OFN_ALLOWMULTISELECT != 0 {
+        log::warn!("GetOpenFileName: multiple selection unsupported, picking one");
+    }
+
+    // The file buffer holds the name to start with, which may carry a directory.
+    let initial = read_str(machine, ofn.lpstrFile).filter(|s| !s.is_empty());
+    let (mut dir, name) = match initial.as_deref().map(|s| s.rsplit_once('\\')) {
+        Some(Some((dir, name))) => (Some(dir.to_string()), Some(name.to_string())),
+        Some(None) => (None, initial.clone()),
+        None => (None, None),
+    };
+    if let Some(initial_dir) = read_str(machine, ofn.lpstrInitialDir) {
+        dir = Some(initial_dir);
+    }
+    let dialog = FileDialog {
+        save,
+        title: read_str(machine, ofn.lpstrTitle)
+            .unwrap_or_else(|| if save { "Save As" } else { "Open" }.into()),
+        filters: read_filters(machine, ofn.lpstrFilter),
+        // nFilterIndex counts from 1, with 0 meaning the custom filter, which we lack.
+        filter_index: (ofn.nFilterIndex as usize).saturating_sub(1),
+        dir: dir.map(|dir| machine.state.kernel32.host_path(&dir)),
+        name,
+    };
+
+    let Some(picked) = machine.host.pick_file(&dialog) else {
+        return false; // cancelled, with no error
+    };
+    let mut path = machine.state.kernel32.guest_path(&picked);
+    let name_start = path.rfind(['\\', '/']).map_or(0, |i| i + 1);
+    let ext_start = path[name_start..].rfind('.').map(|i| name_start + i + 1);
+    let ext_start = match (ext_start, read_str(machine, ofn.lpstrDefExt)) {
+        (None, Some(ext)) if save && !ext.is_empty() => {
+            path.push('.');
+            let start = path.len();
+            path.push_str(&ext);
+            Some(start)
+        }
+        (ext_start, _) => ext_start,
+    };
+
+    if !write_str(machine, ofn.lpstrFile, ofn.nMaxFile, &path) {
+        // The first two bytes of the buffer give the size needed.
+        if ofn.nMaxFile >= 2 {
+            machine
+                .mem()
+                .put::<u16>(ofn.lpstrFile, path.len() as u16 + 1);
+        }
+        machine.state.comdlg32.error = FNERR_BUFFERTOOSMALL;
+        return false;
+    }
+    if ofn.lpstrFileTitle != 0 {
+        write_str(
+            machine,
+            ofn.lpstrFileTitle,
+            ofn.nMaxFileTitle,
+            &path[name_start..],
+        );
+    }
+    ofn.nFileOffset = name_start as u16;
+    ofn.nFileExtension = ext_start.unwrap_or(0) as u16;
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn GetOpenFileNameA(machine: &mut Machine, lpofn: Option<&mut OPENFILENAMEA>) -> bool {
+    get_file_name(machine, lpofn, false)
+}
+
+#[win32_derive::dllexport]
+pub fn GetSaveFileNameA(machine: &mut Machine, lpofn: Option<&mut OPENFILENAMEA>) -> bool {
+    get_file_name(machine, lpofn, true)
+}
+
+#[win32_derive::dllexport]
+pub fn CommDlgExtendedError(machine: &mut Machine) -> u32 {
+    machine.state.comdlg32.error
+}
@@ -0,0 +1,133 @@
+//! Blits between the x86-side pixel buffers of surfaces, as used by Blt and BltFast.
+//! Pixels are treated as raw values in the surface's format, which is what color keys
+//! and fill colors are expressed in.
+
+use super::types::DDCOLORKEY;
+use crate::winapi::types::RECT;
+use memory::Mem;
+
+/// Location and layout of a surface's pixel buffer.
+#[derive(Clone, Copy)]
+pub struct PixelBuf {
+    pub addr: u32,
+    pub pitch: u32,
+    pub bytes_per_pixel: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl PixelBuf {
+    fn bounds(&self) -> RECT {
+        RECT {
+            left: 0,
+            top: 0,
+            right: self.width as i32,
+            bottom: self.height as i32,
+        }
+    }
+}
+
+pub enum Source {
+    /// Fill with a pixel value.
+    Fill(u32),
+    /// Copy from a rect of a surface, stretching it to the destination rect.
+    Surface { buf: PixelBuf, rect: RECT },
+}
+
+fn read_pixel(bytes: &[u8]) -> u32 {
+    let mut value = [0u8; 4];
+    value[..bytes.len()].copy_from_slice(bytes);
+    u32::from_le_bytes(value)
+}
+
+fn write_pixel(bytes: &mut [u8], value: u32) {
+    let len = bytes.len();
+    bytes.copy_from_slice(&value.to_le_bytes()[..len]);
+}
+
+fn intersect(a: &RECT, b: &RECT) -> RECT {
+    RECT {
+        left: std::cmp::max(a.left, b.left),
+        top: std::cmp::max(a.top, b.top),
+        right: std::cmp::min(a.right, b.right),
+        bottom: std::cmp::min(a.bottom, b.bottom),
+    }
+}
+
+/// Blit into dst_rect of dst, limited to the clip rects (or the whole surface if none).
+/// Pixels equal to src_key in the source are skipped, and only pixels equal to dst_key
+/// in the destination are written.
+pub fn blit(
+    mem: Mem,
+    dst: &PixelBuf,
+    dst_rect: RECT,
+    clip: &[RECT],
+    src: &Source,
+    src_key: Option<DDCOLORKEY>,
+    dst_key: Option<DDCOLORKEY>,
+) {
+    let bpp = dst.bytes_per_pixel as usize;
+    let dst_pitch = dst.pitch as usize;
+    let (dst_w, dst_h) = (
+        dst_rect.right - dst_rect.left,
+        dst_rect.bottom - dst_rect.top,
+    );
+    if dst_w <= 0 || dst_h <= 0 {
+        return;
+    }
+
+    // Copy the source out first, as it may overlap the destination.
+    let src = match src {
+        &Source::Fill(color) => Err(color),
+        Source::Surface { buf, rect } => {
+            assert_eq!(buf.bytes_per_pixel, dst.bytes_per_pixel);
+            let rect = intersect(rect, &buf.bounds());
+            if rect.right <= rect.left || rect.bottom <= rect.top {
+                return;
+            }
+            let bytes = mem
+                .sub(buf.addr, buf.pitch * buf.height)
+                .as_slice_todo()
+                .to_vec();
+            Ok((bytes, buf.pitch as usize, rect))
+        }
+    };
+
+    let dst_bytes = mem
+        .sub(dst.addr, dst.pitch * dst.height)
+        .as_mut_slice_todo();
+    let whole = [dst.bounds()];
+    let clip = if clip.is_empty() { &whole[..] } else { clip };
+    for clip in clip {
+        let r = intersect(&intersect(clip, &dst_rect), &dst.bounds());
+        for y in r.top..r.bottom {
+            let row = &mut dst_bytes[y as usize * dst_pitch..][..dst_pitch];
+            for x in r.left..r.right {
+                let pixel = &mut row[x as usize * bpp..][..bpp];
+                if let Some(key) = dst_key {
+                    if !key.matches(read_pixel(pixel)) {
+                        continue;
+                    }
+                }
+                let value = match &src {
+                    &Err(color) => color,
+                    Ok((bytes, pitch, rect)) => {
+                        // Nearest-neighbor stretch from the source rect.
+                        let (src_w, src_h) = (rect.right - rect.left, rect.bottom - rect.top);
+                        let sx = rect.left + (x - dst_rect.left) * src_w / dst_w;
+                        let sy = rect.top + (y - dst_rect.top) * src_h / dst_h;
+                        let value =
+                            read_pixel(&bytes[sy as usize * pitch + sx as usize * bpp..][..bpp]);
+                        if let Some(key) = src_key {
+                            if key.matches(value) {
+                                continue;
+                            }
+                        }
+                        value
+                    }
+                };
+                write_pixel(pixel, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,1010 @@
+//! A minimal Direct3D7 device, reached via IDirectDraw7::QueryInterface.
+//! All device types are the same software device: it draws into the render target
+//! surface with raster.rs.  There is no lighting, and only the first texture stage
+//! is used, modulating the diffuse color.
+
+use super::{gpu::Gpu, types::*, State, D3DENUMRET_CANCEL, DDERR_INVALIDPARAMS, DD_OK};
+use crate::{
+    machine::Emulator,
+    winapi::{
+        ddraw,
+        raster::{DepthFunc, Framebuffer, Texture, Vertex},
+        types::*,
+        vtable,
+    },
+    Machine,
+};
+use memory::{Extensions, Mem};
+use std::collections::HashMap;
+
+const TRACE_CONTEXT: &'static str = "ddraw/d3d7";
+
+pub const IID_IDirect3D7: [u8; 16] = [
+    0x77, 0x9e, 0x04, 0xf5, 0x61, 0x48, 0xd2, 0x11, 0xa4, 0x07, 0x00, 0xa0, 0xc9, 0x06, 0x29, 0xa8,
+];
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct D3DMATRIX {
+    /// Row-major; vectors are rows, multiplied on the left.
+    pub m: [f32; 16],
+}
+unsafe impl memory::Pod for D3DMATRIX {}
+
+impl D3DMATRIX {
+    const IDENTITY: D3DMATRIX = D3DMATRIX {
+        m: [
+            1.0, 0.0, 0.0, 0.0, //
+            0.0, 1.0, 0.0, 0.0, //
+            0.0, 0.0, 1.0, 0.0, //
+            0.0, 0.0, 0.0, 1.0, //
+        ],
+    };
+
+    fn mul(&self, other: &D3DMATRIX) -> D3DMATRIX {
+        let mut m = [0f32; 16];
+        for row in 0..4 {
+            for col in 0..4 {
+                m[row * 4 + col] = (0..4)
+                    .map(|i| self.m[row * 4 + i] * other.m[i * 4 + col])
+                    .sum();
+            }
+        }
+        D3DMATRIX { m }
+    }
+
+    fn transform(&self, v: [f32; 4]) -> [f32; 4] {
+        let mut out = [0f32; 4];
+        for col in 0..4 {
+            out[col] = (0..4).map(|i| v[i] * self.m[i * 4 + col]).sum();
+        }
+        out
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct D3DVIEWPORT7 {
+    pub dwX: u32,
+    pub dwY: u32,
+    pub dwWidth: u32,
+    pub dwHeight: u32,
+    pub dvMinZ: f32,
+    pub dvMaxZ: f32,
+}
+unsafe impl memory::Pod for D3DVIEWPORT7 {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct D3DRECT {
+    pub x1: i32,
+    pub y1: i32,
+    pub x2: i32,
+    pub y2: i32,
+}
+unsafe impl memory::Pod for D3DRECT {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct D3DPRIMCAPS {
+    pub dwSize: DWORD,
+    pub dwMiscCaps: DWORD,
+    pub dwRasterCaps: DWORD,
+    pub dwZCmpCaps: DWORD,
+    pub dwSrcBlendCaps: DWORD,
+    pub dwDestBlendCaps: DWORD,
+    pub dwAlphaCmpCaps: DWORD,
+    pub dwShadeCaps: DWORD,
+    pub dwTextureCaps: DWORD,
+    pub dwTextureFilterCaps: DWORD,
+    pub dwTextureBlendCaps: DWORD,
+    pub dwTextureAddressCaps: DWORD,
+    pub dwStippleWidth: DWORD,
+    pub dwStippleHeight: DWORD,
+}
+unsafe impl memory::Pod for D3DPRIMCAPS {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct D3DDEVICEDESC7 {
+    pub dwDevCaps: DWORD,
+    pub dpcLineCaps: D3DPRIMCAPS,
+    pub dpcTriCaps: D3DPRIMCAPS,
+    pub dwDeviceRenderBitDepth: DWORD,
+    pub dwDeviceZBufferBitDepth: DWORD,
+    pub dwMinTextureWidth: DWORD,
+    pub dwMinTextureHeight: DWORD,
+    pub dwMaxTextureWidth: DWORD,
+    pub dwMaxTextureHeight: DWORD,
+    pub dwMaxTextureRepeat: DWORD,
+    pub dwMaxTextureAspectRatio: DWORD,
+    pub dwMaxAnisotropy: DWORD,
+    pub dvGuardBandLeft: f32,
+    pub dvGuardBandTop: f32,
+    pub dvGuardBandRight: f32,
+    pub dvGuardBandBottom: f32,
+    pub dvExtentsAdjust: f32,
+    pub dwStencilCaps: DWORD,
+    pub dwFVFCaps: DWORD,
+    pub dwTextureOpCaps: DWORD,
+    pub wMaxTextureBlendStages: u16,
+    pub wMaxSimultaneousTextures: u16,
+    pub dwMaxActiveLights: DWORD,
+    pub dvMaxVertexW: f32,
+    pub deviceGUID: [u8; 16],
+    pub wMaxUserClipPlanes: u16,
+    pub wMaxVertexBlendMatrices: u16,
+    pub dwVertexProcessingCaps: DWORD,
+    pub dwReserved1: DWORD,
+    pub dwReserved2: DWORD,
+    pub dwReserved3: DWORD,
+    pub dwReserved4: DWORD,
+}
+unsafe impl memory::Pod for D3DDEVICEDESC7 {}
+
+/*
+## Devices
+
+Direct3D7 enumerates a software device and one or two hardware ones, depending
+on whether the card does transform and lighting.  They are all the same device
+here, but report caps following the configured Gpu, so that programs find the
+hardware device they look for.
+*/
+
+pub const IID_IDirect3DRGBDevice: [u8; 16] = [
+    0x60, 0x5c, 0x66, 0xa4, 0x73, 0x26, 0xcf, 0x11, 0xa3, 0x1a, 0x00, 0xaa, 0x00, 0xb9, 0x33, 0x56,
+];
+pub const IID_IDirect3DHALDevice: [u8; 16] = [
+    0xe0, 0x3d, 0xe6, 0x84, 0xaa, 0x46, 0xcf, 0x11, 0x81, 0x6f, 0x00, 0x00, 0xc0, 0x20, 0x15, 0x6e,
+];
+pub const IID_IDirect3DTnLHalDevice: [u8; 16] = [
+    0x78, 0x9e, 0x04, 0xf5, 0x61, 0x48, 0xd2, 0x11, 0xa4, 0x07, 0x00, 0xa0, 0xc9, 0x06, 0x29, 0xa8,
+];
+
+struct DeviceInfo {
+    guid: [u8; 16],
+    description: &'static str,
+    name: &'static str,
+    hardware: bool,
+    tnl: bool,
+}
+
+const DEVICES: [DeviceInfo; 3] = [
+    DeviceInfo {
+        guid: IID_IDirect3DRGBDevice,
+        description: "Microsoft Direct3D RGB Software Emulation",
+        name: "RGB Emulation",
+        hardware: false,
+        tnl: false,
+    },
+    DeviceInfo {
+        guid: IID_IDirect3DHALDevice,
+        description: "Microsoft Direct3D Hardware acceleration through Direct3D HAL",
+        name: "Direct3D HAL",
+        hardware: true,
+        tnl: false,
+    },
+    DeviceInfo {
+        guid: IID_IDirect3DTnLHalDevice,
+        description:
+            "Microsoft Direct3D Hardware Transform and Lighting acceleration capable device",
+        name: "Direct3D T&L HAL",
+        hardware: true,
+        tnl: true,
+    },
+];
+
+/// The devices offered given the configured card.
+fn devices(gpu: &Gpu) -> impl Iterator<Item = &'static DeviceInfo> + '_ {
+    DEVICES.iter().filter(|d| !d.tnl || gpu.tnl)
+}
+
+/// DDBD_* flag for a bit depth.
+fn ddbd(bits: u32) -> u32 {
+    match bits {
+        8 => 0x800,
+        16 => 0x400,
+        24 => 0x200,
+        32 => 0x100,
+        _ => 0,
+    }
+}
+
+fn device_desc(gpu: &Gpu, device: &DeviceInfo) -> D3DDEVICEDESC7 {
+    let prim_caps = D3DPRIMCAPS {
+        dwSize: std::mem::size_of::<D3DPRIMCAPS>() as u32,
+        dwMiscCaps: 0x2 | 0x10 | 0x20 | 0x40, // MASKZ, CULLNONE, CULLCW, CULLCCW
+        dwRasterCaps: 0x10 | 0x20,            // ZTEST, SUBPIXEL
+        dwZCmpCaps: 0xFF,                     // all of D3DPCMPCAPS_*
+        dwSrcBlendCaps: 0x1FFF,
+        dwDestBlendCaps: 0x1FFF,
+        dwAlphaCmpCaps: 0xFF,
+        dwShadeCaps: 0x8 | 0x4000, // COLORGOURAUDRGB, ALPHAGOURAUDBLEND
+        dwTextureCaps: 0x1 | 0x2 | 0x4, // PERSPECTIVE, POW2, ALPHA
+        // NEAREST, LINEAR, and their MIN/MAG variants.
+        dwTextureFilterCaps: 0x1 | 0x2 | 0x100 | 0x200 | 0x0100_0000 | 0x0200_0000,
+        dwTextureBlendCaps: 0x8 | 0x1,   // MODULATE, DECAL
+        dwTextureAddressCaps: 0x1 | 0x4, // WRAP, CLAMP
+        dwStippleWidth: 0,
+        dwStippleHeight: 0,
+    };
+    let mut dev_caps = 0x1 | 0x10 | 0x40 | 0x100 | 0x400 | 0x2000; // FLOATTLVERTEX ... DRAWPRIMITIVES2
+    if device.hardware {
+        dev_caps |= 0x200 | 0x800 | 0x8000 | 0x80000; // TEXTUREVIDEOMEMORY, CANRENDERAFTERFLIP, DRAWPRIMITIVES2EX, HWRASTERIZATION
+    }
+    if device.tnl {
+        dev_caps |= 0x10000; // HWTRANSFORMANDLIGHT
+    }
+    D3DDEVICEDESC7 {
+        dwDevCaps: dev_caps,
+        dpcLineCaps: prim_caps,
+        dpcTriCaps: prim_caps,
+        dwDeviceRenderBitDepth: ddbd(16) | ddbd(32),
+        dwDeviceZBufferBitDepth: gpu.zbuffer_depths.iter().fold(0, |f, &d| f | ddbd(d)),
+        dwMinTextureWidth: 1,
+        dwMinTextureHeight: 1,
+        dwMaxTextureWidth: gpu.max_texture_size,
+        dwMaxTextureHeight: gpu.max_texture_size,
+        dwMaxTextureRepeat: gpu.max_texture_size,
+        dwMaxTextureAspectRatio: gpu.max_texture_size,
+        dwMaxAnisotropy: 1,
+        dvGuardBandLeft: 0.0,
+        dvGuardBandTop: 0.0,
+        dvGuardBandRight: 0.0,
+        dvGuardBandBottom: 0.0,
+        dvExtentsAdjust: 0.0,
+        dwStencilCaps: 0,
+        dwFVFCaps: 8,                           // texture coordinate sets
+        dwTextureOpCaps: 0x1 | 0x2 | 0x4 | 0x8, // DISABLE, SELECTARG1/2, MODULATE
+        wMaxTextureBlendStages: 1,
+        wMaxSimultaneousTextures: 1,
+        dwMaxActiveLights: if device.tnl { 8 } else { 0 },
+        dvMaxVertexW: 1e10,
+        deviceGUID: device.guid,
+        wMaxUserClipPlanes: 0,
+        wMaxVertexBlendMatrices: 0,
+        // TEXGEN, MATERIALSOURCE7, DIRECTIONALLIGHTS, POSITIONALLIGHTS, LOCALVIEWER
+        dwVertexProcessingCaps: if device.tnl { 0x3B } else { 0 },
+        dwReserved1: 0,
+        dwReserved2: 0,
+        dwReserved3: 0,
+        dwReserved4: 0,
+    }
+}
+
+/// Pixel format of a depth buffer with the given bit depth.
+fn zbuffer_format(bits: u32) -> DDPIXELFORMAT {
+    const DDPF_ZBUFFER: u32 = 0x400;
+    // 24-bit depth is stored in 32 bits.
+    let (depth, mask) = match bits {
+        16 => (16, 0xFFFF),
+        24 => (32, 0xFF_FFFF),
+        _ => (32, 0xFFFF_FFFF),
+    };
+    DDPIXELFORMAT {
+        dwSize: std::mem::size_of::<DDPIXELFORMAT>() as u32,
+        dwFlags: DDPF_ZBUFFER,
+        dwFourCC: 0,
+        dwRGBBitCount: depth, // dwZBufferBitDepth
+        dwRBitMask: 0,        // dwStencilBitDepth
+        dwGBitMask: mask,     // dwZBitMask
+        dwBBitMask: 0,        // dwStencilBitMask
+        dwRGBAlphaBitMask: 0,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, win32_derive::TryFromEnum)]
+pub enum D3DPRIMITIVETYPE {
+    POINTLIST = 1,
+    LINELIST = 2,
+    LINESTRIP = 3,
+    TRIANGLELIST = 4,
+    TRIANGLESTRIP = 5,
+    TRIANGLEFAN = 6,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, win32_derive::TryFromEnum)]
+pub enum D3DTRANSFORMSTATETYPE {
+    WORLD = 1,
+    VIEW = 2,
+    PROJECTION = 3,
+}
+
+// D3DRENDERSTATETYPE values we act on.
+const D3DRENDERSTATE_ZENABLE: u32 = 7;
+const D3DRENDERSTATE_ZWRITEENABLE: u32 = 14;
+const D3DRENDERSTATE_CULLMODE: u32 = 22;
+const D3DRENDERSTATE_ZFUNC: u32 = 23;
+
+// D3DCULL
+const D3DCULL_CW: u32 = 2;
+const D3DCULL_CCW: u32 = 3;
+
+// Clear flags.
+const D3DCLEAR_TARGET: u32 = 1;
+const D3DCLEAR_ZBUFFER: u32 = 2;
+
+/// The layout of a vertex, as described by D3DFVF flags.
+struct VertexFormat {
+    /// D3DFVF_XYZRHW: positions are already in screen space.
+    transformed: bool,
+    stride: u32,
+    /// Offsets of the diffuse color and the first texture coordinates, if present.
+    diffuse: Option<u32>,
+    tex: Option<u32>,
+}
+
+impl VertexFormat {
+    fn new(fvf: u32) -> Option<Self> {
+        // D3DFVF_POSITION_MASK: XYZ, XYZRHW, or XYZ with 1-5 blend weights.
+        let (transformed, mut stride) = match fvf & 0xE {
+            0x2 => (false, 12),
+            0x4 => (true, 16),
+            p @ (0x6 | 0x8 | 0xA | 0xC | 0xE) => (false, 12 + 4 * ((p - 0x6) / 2 + 1)),
+            _ => return None,
+        };
+        if fvf & 0x10 != 0 {
+            stride += 12; // D3DFVF_NORMAL
+        }
+        if fvf & 0x20 != 0 {
+            stride += 4; // D3DFVF_PSIZE
+        }
+        let mut diffuse = None;
+        if fvf & 0x40 != 0 {
+            diffuse = Some(stride);
+            stride += 4;
+        }
+        if fvf & 0x80 != 0 {
+            stride += 4; // D3DFVF_SPECULAR
+        }
+        let mut tex = None;
+        for i in 0..(fvf >> 8) & 0xF {
+            if i == 0 {
+                tex = Some(stride);
+            }
+            // D3DFVF_TEXCOORDSIZEn: 0 is the default of 2 floats.
+            stride += match (fvf >> (16 + i * 2)) & 3 {
+                0 => 8,
+                1 => 12,
+                2 => 16,
+                _ => 4,
+            };
+        }
+        Some(VertexFormat {
+            transformed,
+            stride,
+            diffuse,
+            tex,
+        })
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Device {
+    /// Which of DEVICES this was created as.
+    guid: [u8; 16],
+    /// The IDirectDrawSurface7 drawn into.
+    target: u32,
+    render_states: HashMap<u32, u32>,
+    /// The IDirectDrawSurface7 set as the first stage's texture, or 0.
+    texture: u32,
+    world: D3DMATRIX,
+    view: D3DMATRIX,
+    projection: D3DMATRIX,
+    viewport: D3DVIEWPORT7,
+    /// Drawing happens here.  The color buffer is loaded from the target by the first
+    /// drawing of a scene and written back at the end of it; the depth buffer persists.
+    fb: Framebuffer,
+    loaded: bool,
+    in_scene: bool,
+}
+
+impl Device {
+    fn render_state(&self, state: u32) -> u32 {
+        self.render_states.get(&state).copied().unwrap_or(0)
+    }
+
+    /// Apply render states to the framebuffer before drawing.
+    fn update_fb(&mut self) {
+        self.fb.depth_write = self.render_state(D3DRENDERSTATE_ZWRITEENABLE) != 0;
+        self.fb.depth_func = if self.render_state(D3DRENDERSTATE_ZENABLE) == 0 {
+            None
+        } else {
+            Some(match self.render_state(D3DRENDERSTATE_ZFUNC) {
+                1 => DepthFunc::Never,
+                2 => DepthFunc::Less,
+                3 => DepthFunc::Equal,
+                4 => DepthFunc::LessEqual,
+                5 => DepthFunc::Greater,
+                6 => DepthFunc::NotEqual,
+                7 => DepthFunc::GreaterEqual,
+                _ => DepthFunc::Always,
+            })
+        };
+    }
+
+    /// Read the vertex at addr and transform it into screen space.
+    /// mvp is the combined world, view and projection transform.
+    /// Returns None for vertices behind the camera.
+    fn vertex(
+        &self,
+        mem: Mem,
+        format: &VertexFormat,
+        mvp: &D3DMATRIX,
+        addr: u32,
+    ) -> Option<Vertex> {
+        let f = |ofs: u32| mem.get_pod::<f32>(addr + ofs);
+        let color = match format.diffuse {
+            Some(ofs) => {
+                let [b, g, r, a] = mem.get_pod::<u32>(addr + ofs).to_le_bytes();
+                [r, g, b, a].map(|c| c as f32 / 255.0)
+            }
+            None => [1.0; 4],
+        };
+        let uv = format.tex.map_or([0.0; 2], |ofs| [f(ofs), f(ofs + 4)]);
+        let (x, y, z) = if format.transformed {
+            (f(0), f(4), f(8))
+        } else {
+            let [x, y, z, w] = mvp.transform([f(0), f(4), f(8), 1.0]);
+            if w <= 0.0 {
+                return None;
+            }
+            let (x, y, z) = (x / w, y / w, z / w);
+            let vp = &self.viewport;
+            (
+                vp.dwX as f32 + (x + 1.0) / 2.0 * vp.dwWidth as f32,
+                vp.dwY as f32 + (1.0 - y) / 2.0 * vp.dwHeight as f32,
+                vp.dvMinZ + z * (vp.dvMaxZ - vp.dvMinZ),
+            )
+        };
+        Some(Vertex { x, y, z, color, uv })
+    }
+
+    /// Whether the cull mode hides a triangle with the given screen-space vertices.
+    fn culled(&self, v: [&Vertex; 3]) -> bool {
+        // Positive for clockwise on screen, as y points down.
+        let area = (v[1].x - v[0].x) * (v[2].y - v[0].y) - (v[1].y - v[0].y) * (v[2].x - v[0].x);
+        match self.render_state(D3DRENDERSTATE_CULLMODE) {
+            D3DCULL_CW => area > 0.0,
+            D3DCULL_CCW => area < 0.0,
+            _ => false,
+        }
+    }
+}
+
+/// Load the target's pixels into the device's framebuffer, if not already.
+fn begin_drawing(machine: &mut Machine, this: u32) {
+    let ddraw = &machine.state.ddraw;
+    let device = &ddraw.devices[&this];
+    if device.loaded {
+        return;
+    }
+    let pixels = ddraw.surfaces[&device.target].to_rgba(machine.emu.memory.mem(), ddraw);
+    let device = machine.state.ddraw.devices.get_mut(&this).unwrap();
+    match pixels {
+        Some(pixels) => device.fb.color = pixels,
+        None => device.fb.clear_color([0.0, 0.0, 0.0, 1.0]),
+    }
+    device.loaded = true;
+}
+
+/// Write the device's framebuffer back to its target, if it was loaded.
+fn end_drawing(machine: &mut Machine, this: u32) {
+    let device = machine.state.ddraw.devices.get_mut(&this).unwrap();
+    if !device.loaded {
+        return;
+    }
+    device.loaded = false;
+    let target = device.target;
+    let pixels = device.fb.color.clone();
+    ddraw::write_rgba(machine, target, &pixels);
+}
+
+/// Convert a texture surface to RGBA for sampling.
+fn load_texture(machine: &Machine, addr: u32) -> Option<Texture> {
+    let ddraw = &machine.state.ddraw;
+    let surf = ddraw.surfaces.get(&addr)?;
+    Some(Texture {
+        width: surf.width,
+        height: surf.height,
+        texels: surf.to_rgba(machine.emu.memory.mem(), ddraw)?,
+    })
+}
+
+/// Shared implementation of DrawPrimitive and DrawIndexedPrimitive: draw the
+/// primitives formed by the vertices at the given indices.
+fn draw(
+    machine: &mut Machine,
+    this: u32,
+    prim: Result<D3DPRIMITIVETYPE, u32>,
+    fvf: u32,
+    vertices: u32,
+    vertex_count: u32,
+    indices: Vec<usize>,
+) -> u32 {
+    let Some(format) = VertexFormat::new(fvf) else {
+        log::warn!("DrawPrimitive: unsupported vertex format {fvf:x}");
+        return DDERR_INVALIDPARAMS;
+    };
+    let n = indices.len();
+    let tris: Vec<[usize; 3]> = match prim {
+        Ok(D3DPRIMITIVETYPE::TRIANGLELIST) => {
+            (0..n / 3).map(|i| [3 * i, 3 * i + 1, 3 * i + 2]).collect()
+        }
+        Ok(D3DPRIMITIVETYPE::TRIANGLESTRIP) => (0..n.saturating_sub(2))
+            // Every other triangle is flipped to keep the winding consistent.
+            .map(|i| {
+                if i % 2 == 0 {
+                    [i, i + 1, i + 2]
+                } else {
+                    [i + 1, i, i + 2]
+                }
+            })
+            .collect(),
+        Ok(D3DPRIMITIVETYPE::TRIANGLEFAN) => {
+            (1..n.saturating_sub(1)).map(|i| [0, i, i + 1]).collect()
+        }
+        prim => {
+            log::warn!("DrawPrimitive: unimplemented primitive {prim:?}");
+            return DD_OK;
+        }
+    };
+
+    begin_drawing(machine, this);
+    let texture = match machine.state.ddraw.devices[&this].texture {
+        0 => None,
+        addr => load_texture(machine, addr),
+    };
+    let mem = machine.emu.memory.mem();
+    let device = machine.state.ddraw.devices.get_mut(&this).unwrap();
+    let mvp = device.world.mul(&device.view).mul(&device.projection);
+    let screen: Vec<Option<Vertex>> = (0..vertex_count)
+        .map(|i| device.vertex(mem, &format, &mvp, vertices + i * format.stride))
+        .collect();
+    device.update_fb();
+    for tri in tris {
+        let v = tri.map(|i| indices[i]);
+        let (Some(Some(a)), Some(Some(b)), Some(Some(c))) =
+            (screen.get(v[0]), screen.get(v[1]), screen.get(v[2]))
+        else {
+            continue;
+        };
+        if device.culled([a, b, c]) {
+            continue;
+        }
+        device.fb.triangle_textured([a, b, c], texture.as_ref());
+    }
+    if !device.in_scene {
+        end_drawing(machine, this);
+    }
+    DD_OK
+}
+
+#[win32_derive::shims_from_x86]
+pub(super) mod IDirect3D7 {
+    use super::*;
+
+    vtable![IDirect3D7 shims
+        QueryInterface todo,
+        AddRef todo,
+        Release ok,
+        EnumDevices ok,
+        CreateDevice ok,
+        CreateVertexBuffer todo,
+        EnumZBufferFormats ok,
+        EvictManagedTextures todo,
+    ];
+
+    #[win32_derive::dllexport]
+    fn Release(_machine: &mut Machine, this: u32) -> u32 {
+        log::warn!("{this:x}->Release()");
+        0 // TODO: return refcount?
+    }
+
+    #[win32_derive::dllexport]
+    async fn EnumDevices(
+        machine: &mut Machine,
+        this: u32,
+        lpEnumDevicesCallback: u32,
+        lpUserArg: u32,
+    ) -> u32 {
+        let descs = devices(&machine.state.ddraw.gpu)
+            .map(|d| (d, device_desc(&machine.state.ddraw.gpu, d)))
+            .collect::<Vec<_>>();
+        for (device, desc) in descs {
+            let mem = machine.emu.memory.mem();
+            let heap = &mut machine.state.ddraw.heap;
+            let description = heap.alloc(mem, device.description.len() as u32 + 1);
+            mem.sub(description, device.description.len() as u32 + 1)
+                .as_mut_slice_todo()
+                .copy_from_slice(format!("{}\0", device.description).as_bytes());
+            let name = heap.alloc(mem, device.name.len() as u32 + 1);
+            mem.sub(name, device.name.len() as u32 + 1)
+                .as_mut_slice_todo()
+                .copy_from_slice(format!("{}\0", device.name).as_bytes());
+            let desc_addr = heap.alloc(mem, std::mem::size_of::<D3DDEVICEDESC7>() as u32);
+            mem.put::<D3DDEVICEDESC7>(desc_addr, desc);
+
+            let ret = machine
+                .call_x86(
+                    lpEnumDevicesCallback,
+                    vec![description, name, desc_addr, lpUserArg],
+                )
+                .await;
+
+            let mem = machine.emu.memory.mem();
+            let heap = &mut machine.state.ddraw.heap;
+            for addr in [description, name, desc_addr] {
+                heap.free(mem, addr);
+            }
+            if ret == D3DENUMRET_CANCEL {
+                break;
+            }
+        }
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    async fn EnumZBufferFormats(
+        machine: &mut Machine,
+        this: u32,
+        riidDevice: u32,
+        lpEnumCallback: u32,
+        lpContext: u32,
+    ) -> u32 {
+        let depths = machine.state.ddraw.gpu.zbuffer_depths.clone();
+        for bits in depths {
+            let mem = machine.emu.memory.mem();
+            let addr = machine
+                .state
+                .ddraw
+                .heap
+                .alloc(mem, std::mem::size_of::<DDPIXELFORMAT>() as u32);
+            *mem.view_mut::<DDPIXELFORMAT>(addr) = zbuffer_format(bits);
+            let ret = machine
+                .call_x86(lpEnumCallback, vec![addr, lpContext])
+                .await;
+            machine
+                .state
+                .ddraw
+                .heap
+                .free(machine.emu.memory.mem(), addr);
+            if ret == D3DENUMRET_CANCEL {
+                break;
+            }
+        }
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    fn CreateDevice(
+        machine: &mut Machine,
+        this: u32,
+        rclsid: u32,
+        lpDDS: u32,
+        lplpD3DDevice: Option<&mut u32>,
+    ) -> u32 {
+        let guid: [u8; 16] = machine
+            .mem()
+            .sub(rclsid, 16)
+            .as_slice_todo()
+            .try_into()
+            .unwrap();
+        if !devices(&machine.state.ddraw.gpu).any(|d| d.guid == guid) {
+            log::warn!("CreateDevice: unknown device {guid:x?}");
+            return DDERR_INVALIDPARAMS;
+        }
+        let ddraw = &machine.state.ddraw;
+        let Some(surf) = ddraw.surfaces.get(&lpDDS) else {
+            return DDERR_INVALIDPARAMS;
+        };
+        let (width, height) = (surf.width, surf.height);
+        // Depth testing defaults to on if the target has a depth buffer attached.
+        let zbuffer = ddraw::find_attached(ddraw, lpDDS, DDSCAPS::ZBUFFER).is_some();
+        // The device holds a reference to its target until it's released.
+        ddraw::add_ref(machine, lpDDS);
+        let device = Device {
+            guid,
+            target: lpDDS,
+            render_states: HashMap::from([
+                (D3DRENDERSTATE_ZENABLE, zbuffer as u32),
+                (D3DRENDERSTATE_ZWRITEENABLE, 1),
+                (D3DRENDERSTATE_CULLMODE, D3DCULL_CCW),
+                (D3DRENDERSTATE_ZFUNC, 4), // D3DCMP_LESSEQUAL
+            ]),
+            texture: 0,
+            world: D3DMATRIX::IDENTITY,
+            view: D3DMATRIX::IDENTITY,
+            projection: D3DMATRIX::IDENTITY,
+            viewport: D3DVIEWPORT7 {
+                dwX: 0,
+                dwY: 0,
+                dwWidth: width,
+                dwHeight: height,
+                dvMinZ: 0.0,
+                dvMaxZ: 1.0,
+            },
+            fb: Framebuffer::new(width, height),
+            loaded: false,
+            in_scene: false,
+        };
+        let vtable = machine.state.ddraw.vtable_IDirect3DDevice7;
+        let addr = ddraw::new_object(machine, vtable);
+        machine.state.ddraw.devices.insert(addr, device);
+        *lplpD3DDevice.unwrap() = addr;
+        DD_OK
+    }
+}
+
+#[win32_derive::shims_from_x86]
+pub(super) mod IDirect3DDevice7 {
+    use super::*;
+
+    vtable![IDirect3DDevice7 shims
+        QueryInterface todo,
+        AddRef todo,
+        Release ok,
+        GetCaps ok,
+        EnumTextureFormats todo,
+        BeginScene ok,
+        EndScene ok,
+        GetDirect3D todo,
+        SetRenderTarget todo,
+        GetRenderTarget todo,
+        Clear ok,
+        SetTransform ok,
+        GetTransform todo,
+        SetViewport ok,
+        MultiplyTransform todo,
+        GetViewport ok,
+        SetMaterial todo,
+        GetMaterial todo,
+        SetLight todo,
+        GetLight todo,
+        SetRenderState ok,
+        GetRenderState ok,
+        BeginStateBlock todo,
+        EndStateBlock todo,
+        PreLoad todo,
+        DrawPrimitive ok,
+        DrawIndexedPrimitive ok,
+        SetClipStatus todo,
+        GetClipStatus todo,
+        DrawPrimitiveStrided todo,
+        DrawIndexedPrimitiveStrided todo,
+        DrawPrimitiveVB todo,
+        DrawIndexedPrimitiveVB todo,
+        ComputeSphereVisibility todo,
+        GetTexture todo,
+        SetTexture ok,
+        GetTextureStageState todo,
+        SetTextureStageState ok,
+        ValidateDevice todo,
+        ApplyStateBlock todo,
+        CaptureStateBlock todo,
+        DeleteStateBlock todo,
+        CreateStateBlock todo,
+        Load todo,
+        LightEnable todo,
+        GetLightEnable todo,
+        SetClipPlane todo,
+        GetClipPlane todo,
+        GetInfo todo,
+    ];
+
+    #[win32_derive::dllexport]
+    fn Release(machine: &mut Machine, this: u32) -> u32 {
+        end_drawing(machine, this);
+        if let Some(device) = machine.state.ddraw.devices.remove(&this) {
+            ddraw::release(machine, device.target);
+        }
+        0
+    }
+
+    #[win32_derive::dllexport]
+    fn GetCaps(machine: &mut Machine, this: u32, lpD3DDevDesc: Option<&mut D3DDEVICEDESC7>) -> u32 {
+        let ddraw = &machine.state.ddraw;
+        let (Some(device), Some(desc)) = (ddraw.devices.get(&this), lpD3DDevDesc) else {
+            return DDERR_INVALIDPARAMS;
+        };
+        let info = DEVICES.iter().find(|d| d.guid == device.guid).unwrap();
+        *desc = device_desc(&ddraw.gpu, info);
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    fn BeginScene(machine: &mut Machine, this: u32) -> u32 {
+        machine.state.ddraw.devices.get_mut(&this).unwrap().in_scene = true;
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    fn EndScene(machine: &mut Machine, this: u32) -> u32 {
+        machine.state.ddraw.devices.get_mut(&this).unwrap().in_scene = false;
+        end_drawing(machine, this);
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    fn Clear(
+        machine: &mut Machine,
+        this: u32,
+        dwCount: u32,
+        lpRects: u32,
+        dwFlags: u32,
+        dwColor: u32,
+        dvZ: f32,
+        dwStencil: u32,
+    ) -> u32 {
+        if dwFlags & D3DCLEAR_TARGET != 0 {
+            begin_drawing(machine, this);
+        }
+        let mem = machine.emu.memory.mem();
+        let device = machine.state.ddraw.devices.get_mut(&this).unwrap();
+        let rects: Vec<D3DRECT> = if dwCount == 0 {
+            // Without rects, clear the viewport.
+            let vp = &device.viewport;
+            vec![D3DRECT {
+                x1: vp.dwX as i32,
+                y1: vp.dwY as i32,
+                x2: (vp.dwX + vp.dwWidth) as i32,
+                y2: (vp.dwY + vp.dwHeight) as i32,
+            }]
+        } else {
+            mem.iter_pod::<D3DRECT>(lpRects, dwCount).collect()
+        };
+        let [b, g, r, a] = dwColor.to_le_bytes();
+        let fb = &mut device.fb;
+        for rect in rects {
+            let x1 = rect.x1.clamp(0, fb.width as i32) as u32;
+            let x2 = rect.x2.clamp(0, fb.width as i32) as u32;
+            let y1 = rect.y1.clamp(0, fb.height as i32) as u32;
+            let y2 = rect.y2.clamp(0, fb.height as i32) as u32;
+            if x1 >= x2 {
+                continue;
+            }
+            for y in y1..y2 {
+                let row = (y * fb.width) as usize;
+                let span = row + x1 as usize..row + x2 as usize;
+                if dwFlags & D3DCLEAR_TARGET != 0 {
+                    fb.color[span.clone()].fill([r, g, b, a]);
+                }
+                if dwFlags & D3DCLEAR_ZBUFFER != 0 {
+                    fb.depth[span].fill(dvZ);
+                }
+            }
+        }
+        if !device.in_scene {
+            end_drawing(machine, this);
+        }
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    fn SetTransform(
+        machine: &mut Machine,
+        this: u32,
+        dtstTransformStateType: Result<D3DTRANSFORMSTATETYPE, u32>,
+        lpD3DMatrix: Option<&D3DMATRIX>,
+    ) -> u32 {
+        let device = machine.state.ddraw.devices.get_mut(&this).unwrap();
+        let matrix = *lpD3DMatrix.unwrap();
+        match dtstTransformStateType {
+            Ok(D3DTRANSFORMSTATETYPE::WORLD) => device.world = matrix,
+            Ok(D3DTRANSFORMSTATETYPE::VIEW) => device.view = matrix,
+            Ok(D3DTRANSFORMSTATETYPE::PROJECTION) => device.projection = matrix,
+            Err(state) => log::warn!("SetTransform: unimplemented transform {state}"),
+        }
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    fn SetViewport(machine: &mut Machine, this: u32, lpViewport: Option<&D3DVIEWPORT7>) -> u32 {
+        let device = machine.state.ddraw.devices.get_mut(&this).unwrap();
+        device.viewport = *lpViewport.unwrap();
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    fn GetViewport(machine: &mut Machine, this: u32, lpViewport: Option<&mut D3DVIEWPORT7>) -> u32 {
+        *lpViewport.unwrap() = machine.state.ddraw.devices[&this].viewport;
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    fn SetRenderState(
+        machine: &mut Machine,
+        this: u32,
+        dwRenderStateType: u32,
+        dwRenderState: u32,
+    ) -> u32 {
+        let device = machine.state.ddraw.devices.get_mut(&this).unwrap();
+        device
+            .render_states
+            .insert(dwRenderStateType, dwRenderState);
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    fn GetRenderState(
+        machine: &mut Machine,
+        this: u32,
+        dwRenderStateType: u32,
+        lpdwRenderState: Option<&mut u32>,
+    ) -> u32 {
+        *lpdwRenderState.unwrap() =
+            machine.state.ddraw.devices[&this].render_state(dwRenderStateType);
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    fn DrawPrimitive(
+        machine: &mut Machine,
+        this: u32,
+        d3dptPrimitiveType: Result<D3DPRIMITIVETYPE, u32>,
+        dwVertexTypeDesc: u32,
+        lpvVertices: u32,
+        dwVertexCount: u32,
+        dwFlags: u32,
+    ) -> u32 {
+        let indices = (0..dwVertexCount as usize).collect();
+        draw(
+            machine,
+            this,
+            d3dptPrimitiveType,
+            dwVertexTypeDesc,
+            lpvVertices,
+            dwVertexCount,
+            indices,
+        )
+    }
+
+    #[win32_derive::dllexport]
+    fn DrawIndexedPrimitive(
+        machine: &mut Machine,
+        this: u32,
+        d3dptPrimitiveType: Result<D3DPRIMITIVETYPE, u32>,
+        dwVertexTypeDesc: u32,
+        lpvVertices: u32,
+        dwVertexCount: u32,
+        lpwIndices: u32,
+        dwIndexCount: u32,
+        dwFlags: u32,
+    ) -> u32 {
+        let indices = machine
+            .mem()
+            .iter_pod::<u16>(lpwIndices, dwIndexCount)
+            .map(|i| i as usize)
+            .collect();
+        draw(
+            machine,
+            this,
+            d3dptPrimitiveType,
+            dwVertexTypeDesc,
+            lpvVertices,
+            dwVertexCount,
+            indices,
+        )
+    }
+
+    #[win32_derive::dllexport]
+    fn SetTexture(machine: &mut Machine, this: u32, dwStage: u32, lpTexture: u32) -> u32 {
+        if dwStage != 0 {
+            log::warn!("SetTexture: ignoring stage {dwStage}");
+            return DD_OK;
+        }
+        machine.state.ddraw.devices.get_mut(&this).unwrap().texture = lpTexture;
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    fn SetTextureStageState(
+        _machine: &mut Machine,
+        this: u32,
+        dwStage: u32,
+        d3dTexStageStateType: u32,
+        dwState: u32,
+    ) -> u32 {
+        DD_OK // TODO: stage 0 always modulates the texture with the diffuse color.
+    }
+}
@@ -4,41 +4,48 @@
 use super::{
     ddraw7::{IDirectDraw7, IDirectDrawSurface7},
     types::*,
-    State, DD_OK,
+    DisplayMode, State, DDENUMRET_CANCEL, DDERR_NOTFOUND, DD_OK,
 };
 use crate::{
     machine::Emulator,
     winapi::{ddraw, types::*, vtable},
     Machine,
 };
-use memory::Pod;
 
 const TRACE_CONTEXT: &'static str = "ddraw/1";
 
+pub const IID_IDirectDraw: [u8; 16] = [
+    0x80, 0xdb, 0x14, 0x6c, 0x33, 0xa7, 0xce, 0x11, 0xa5, 0x21, 0x00, 0x20, 0xaf, 0x0b, 0xe5, 0x60,
+];
+
+pub const IID_IDirectDrawSurface: [u8; 16] = [
+    0x81, 0xdb, 0x14, 0x6c, 0x33, 0xa7, 0xce, 0x11, 0xa5, 0x21, 0x00, 0x20, 0xaf, 0x0b, 0xe5, 0x60,
+];
+
 #[win32_derive::shims_from_x86]
 pub(super) mod IDirectDraw {
     use super::*;
 
     vtable![IDirectDraw shims
-        QueryInterface todo,
-        AddRef todo,
-        Release ok,
+        QueryInterface (IDirectDraw7::shims::QueryInterface),
+        AddRef (IDirectDraw7::shims::AddRef),
+        Release (IDirectDraw7::shims::Release),
         Compact todo,
-        CreateClipper todo,
+        CreateClipper (IDirectDraw7::shims::CreateClipper),
         CreatePalette (IDirectDraw7::shims::CreatePalette),
         CreateSurface ok,
         DuplicateSurface todo,
         EnumDisplayModes ok,
         EnumSurfaces todo,
         FlipToGDISurface todo,
-        GetCaps todo,
-        GetDisplayMode todo,
+        GetCaps (IDirectDraw7::shims::GetCaps),
+        GetDisplayMode ok,
         GetFourCCCodes todo,
         GetGDISurface todo,
         GetMonitorFrequency todo,
         GetScanLine todo,
         GetVerticalBlankStatus todo,
-        Initialize todo,
+        Initialize (IDirectDraw7::shims::Initialize),
         RestoreDisplayMode (IDirectDraw7::shims::RestoreDisplayMode),
         SetCooperativeLevel (IDirectDraw7::shims::SetCooperativeLevel),
         SetDisplayMode ok,
@@ -46,33 +53,25 @@ pub(super) mod IDirectDraw {
     ];
 
     #[win32_derive::dllexport]
-    fn CreateSurface(
+    pub fn CreateSurface(
         machine: &mut Machine,
         this: u32,
         desc: Option<&DDSURFACEDESC>,
         lplpDDSurface: Option<&mut u32>,
         pUnkOuter: u32,
     ) -> u32 {
-        let surfaces = ddraw::Surface::create(machine, &DDSURFACEDESC2::from_desc(desc.unwrap()));
-        if surfaces.len() > 2 {
-            todo!()
+        let desc = DDSURFACEDESC2::from_desc(desc.unwrap());
+        match ddraw::create_surfaces(machine, &desc, IDirectDrawSurface::new) {
+            Ok(surface) => {
+                *lplpDDSurface.unwrap() = surface;
+                DD_OK
+            }
+            Err(err) => err,
         }
-
-        let mut prev = 0;
-        for mut surface in surfaces.into_iter().rev() {
-            let ptr = IDirectDrawSurface::new(machine);
-            surface.attached = prev;
-            machine.state.ddraw.surfaces.insert(ptr, surface);
-            prev = ptr;
-        }
-
-        *lplpDDSurface.unwrap() = prev;
-
-        DD_OK
     }
 
     #[win32_derive::dllexport]
-    async fn EnumDisplayModes(
+    pub async fn EnumDisplayModes(
         machine: &mut Machine,
         this: u32,
         dwFlags: u32,
@@ -89,27 +88,20 @@ pub(super) mod IDirectDraw {
             .ddraw
             .heap
             .alloc(mem, std::mem::size_of::<DDSURFACEDESC>() as u32);
-        let desc = mem.view_mut::<DDSURFACEDESC>(desc_addr);
-        desc.clear_struct();
-        // TODO: offer multiple display modes rather than hardcoding this one.
-        desc.dwSize = std::mem::size_of::<DDSURFACEDESC>() as u32;
-        desc.dwWidth = 320;
-        desc.dwHeight = 200;
-        desc.ddpfPixelFormat = DDPIXELFORMAT {
-            dwSize: std::mem::size_of::<DDPIXELFORMAT>() as u32,
-            dwFlags: 0,
-            dwFourCC: 0,
-            dwRGBBitCount: 8,
-            dwRBitMask: 0xFF000000,
-            dwGBitMask: 0x00FF0000,
-            dwBBitMask: 0x0000FF00,
-            dwRGBAlphaBitMask: 0x000000FF,
-        };
-
-        machine
-            .call_x86(lpEnumCallback, vec![desc_addr, lpContext])
-            .await;
-
+        for mode in DisplayMode::all() {
+            let mut desc2 = DDSURFACEDESC2::default();
+            mode.describe(&mut desc2);
+            let desc = machine.mem().view_mut::<DDSURFACEDESC>(desc_addr);
+            *desc = DDSURFACEDESC::from_desc2(&desc2);
+            desc.dwMipMapCount_dwZBufferBitDepth_dwRefreshRate =
+                desc2.dwMipMapCount_dwRefreshRate_dwSrcVBHandle;
+            let ret = machine
+                .call_x86(lpEnumCallback, vec![desc_addr, lpContext])
+                .await;
+            if ret == DDENUMRET_CANCEL {
+                break;
+            }
+        }
         machine
             .state
             .ddraw
@@ -120,13 +112,28 @@ pub(super) mod IDirectDraw {
     }
 
     #[win32_derive::dllexport]
-    fn Release(_machine: &mut Machine, this: u32) -> u32 {
-        log::warn!("{this:x}->Release()");
-        0 // TODO: return refcount?
+    pub fn GetDisplayMode(
+        machine: &mut Machine,
+        this: u32,
+        lpDDSurfaceDesc: Option<&mut DDSURFACEDESC>,
+    ) -> u32 {
+        let mut desc = DDSURFACEDESC2::default();
+        machine.state.ddraw.mode.describe(&mut desc);
+        let out = lpDDSurfaceDesc.unwrap();
+        *out = DDSURFACEDESC::from_desc2(&desc);
+        out.dwMipMapCount_dwZBufferBitDepth_dwRefreshRate =
+            desc.dwMipMapCount_dwRefreshRate_dwSrcVBHandle;
+        DD_OK
     }
 
     #[win32_derive::dllexport]
-    fn SetDisplayMode(machine: &mut Machine, this: u32, width: u32, height: u32, bpp: u32) -> u32 {
+    pub fn SetDisplayMode(
+        machine: &mut Machine,
+        this: u32,
+        width: u32,
+        height: u32,
+        bpp: u32,
+    ) -> u32 {
         IDirectDraw7::SetDisplayMode(machine, 0, width, height, bpp, 0, 0)
     }
 }
@@ -136,14 +143,14 @@ pub(super) mod IDirectDrawSurface {
     use super::*;
 
     vtable![IDirectDrawSurface shims
-        QueryInterface todo,
-        AddRef todo,
-        Release ok,
+        QueryInterface (IDirectDrawSurface7::shims::QueryInterface),
+        AddRef (IDirectDrawSurface7::shims::AddRef),
+        Release (IDirectDrawSurface7::shims::Release),
         AddAttachedSurface todo,
         AddOverlayDirtyRect todo,
         Blt (IDirectDrawSurface7::shims::Blt),
         BltBatch todo,
-        BltFast todo,
+        BltFast (IDirectDrawSurface7::shims::BltFast),
         DeleteAttachedSurface todo,
         EnumAttachedSurfaces todo,
         EnumOverlayZOrders todo,
@@ -152,7 +159,7 @@ pub(super) mod IDirectDrawSurface {
         GetBltStatus todo,
         GetCaps ok,
         GetClipper todo,
-        GetColorKey todo,
+        GetColorKey (IDirectDrawSurface7::shims::GetColorKey),
         GetDC (IDirectDrawSurface7::shims::GetDC),
         GetFlipStatus todo,
         GetOverlayPosition todo,
@@ -164,8 +171,8 @@ pub(super) mod IDirectDrawSurface {
         Lock ok,
         ReleaseDC (IDirectDrawSurface7::shims::ReleaseDC),
         Restore todo,
-        SetClipper todo,
-        SetColorKey todo,
+        SetClipper (IDirectDrawSurface7::shims::SetClipper),
+        SetColorKey (IDirectDrawSurface7::shims::SetColorKey),
         SetOverlayPosition todo,
         SetPalette (IDirectDrawSurface7::shims::SetPalette),
         Unlock ok,
@@ -183,30 +190,34 @@ pub(super) mod IDirectDrawSurface {
     }
 
     #[win32_derive::dllexport]
-    pub fn Release(_machine: &mut Machine, this: u32) -> u32 {
-        0
-    }
-
-    #[win32_derive::dllexport]
-    fn GetAttachedSurface(
+    pub fn GetAttachedSurface(
         machine: &mut Machine,
         this: u32,
         lpDDSCaps: Option<&DDSCAPS>,
         lpDirectDrawSurface: Option<&mut u32>,
     ) -> u32 {
-        // TODO: consider caps.
-        let surface = machine.state.ddraw.surfaces.get(&this).unwrap();
-        *lpDirectDrawSurface.unwrap() = surface.attached;
-        DD_OK
+        let caps = *lpDDSCaps.unwrap();
+        match ddraw::get_attached(machine, this, caps) {
+            Some(addr) => {
+                *lpDirectDrawSurface.unwrap() = addr;
+                DD_OK
+            }
+            None => DDERR_NOTFOUND,
+        }
     }
 
     #[win32_derive::dllexport]
-    fn GetCaps(_machine: &mut Machine, this: u32, lpDDSCAPS: Option<&mut DDSCAPS>) -> u32 {
+    pub fn GetCaps(machine: &mut Machine, this: u32, lpDDSCAPS: Option<&mut DDSCAPS>) -> u32 {
+        *lpDDSCAPS.unwrap() = machine.state.ddraw.surfaces[&this].caps;
         DD_OK
     }
 
     #[win32_derive::dllexport]
-    fn GetPixelFormat(_machine: &mut Machine, this: u32, fmt: Option<&mut DDPIXELFORMAT>) -> u32 {
+    pub fn GetPixelFormat(
+        _machine: &mut Machine,
+        this: u32,
+        fmt: Option<&mut DDPIXELFORMAT>,
+    ) -> u32 {
         let fmt = fmt.unwrap();
         *fmt = unsafe { std::mem::zeroed() };
         fmt.dwSize = std::mem::size_of::<DDPIXELFORMAT>() as u32;
@@ -214,7 +225,11 @@ pub(super) mod IDirectDrawSurface {
     }
 
     #[win32_derive::dllexport]
-    fn GetSurfaceDesc(machine: &mut Machine, this: u32, desc: Option<&mut DDSURFACEDESC>) -> u32 {
+    pub fn GetSurfaceDesc(
+        machine: &mut Machine,
+        this: u32,
+        desc: Option<&mut DDSURFACEDESC>,
+    ) -> u32 {
         let surface = machine.state.ddraw.surfaces.get(&this).unwrap();
         let desc = desc.unwrap();
         desc.dwWidth = surface.width;
@@ -225,7 +240,7 @@ pub(super) mod IDirectDrawSurface {
     }
 
     #[win32_derive::dllexport]
-    fn Lock(
+    pub fn Lock(
         machine: &mut Machine,
         this: u32,
         rect: Option<&RECT>,
@@ -250,7 +265,7 @@ pub(super) mod IDirectDrawSurface {
     }
 
     #[win32_derive::dllexport]
-    fn Unlock(machine: &mut Machine, this: u32, ptr: u32) -> u32 {
+    pub fn Unlock(machine: &mut Machine, this: u32, ptr: u32) -> u32 {
         IDirectDrawSurface7::Unlock(machine, this, None)
     }
 }
@@ -0,0 +1,155 @@
+//! Implementation of the DirectX 3 and 5 interfaces: IDirectDraw2, IDirectDrawSurface2
+//! and IDirectDrawSurface3.  These extend the DirectDraw1 interfaces with a few
+//! entries at the end, so they mostly share the DirectDraw1 shims.
+
+use super::{
+    ddraw1::{IDirectDraw, IDirectDrawSurface},
+    ddraw7::{IDirectDraw7, IDirectDrawSurface7},
+    State,
+};
+use crate::{
+    machine::Emulator,
+    winapi::{types::*, vtable},
+    Machine,
+};
+
+pub const IID_IDirectDraw2: [u8; 16] = [
+    0xe0, 0xf3, 0xa6, 0xb3, 0x43, 0x2b, 0xcf, 0x11, 0xa2, 0xde, 0x00, 0xaa, 0x00, 0xb9, 0x33, 0x56,
+];
+
+pub const IID_IDirectDrawSurface2: [u8; 16] = [
+    0x85, 0x58, 0x80, 0x57, 0xec, 0x6e, 0xcf, 0x11, 0x94, 0x41, 0xa8, 0x23, 0x03, 0xc1, 0x0e, 0x27,
+];
+
+pub const IID_IDirectDrawSurface3: [u8; 16] = [
+    0x00, 0x4e, 0x04, 0xda, 0xb2, 0x69, 0xd0, 0x11, 0xa1, 0xd5, 0x00, 0xaa, 0x00, 0xb8, 0xdf, 0xbb,
+];
+
+#[win32_derive::shims_from_x86]
+pub(super) mod IDirectDraw2 {
+    use super::*;
+
+    // CreateSurface takes a DDSURFACEDESC and returns an IDirectDrawSurface, as in
+    // DirectDraw1; callers QueryInterface it for the newer surface versions.
+    vtable![IDirectDraw2 shims
+        QueryInterface (IDirectDraw7::shims::QueryInterface),
+        AddRef (IDirectDraw7::shims::AddRef),
+        Release (IDirectDraw7::shims::Release),
+        Compact todo,
+        CreateClipper (IDirectDraw7::shims::CreateClipper),
+        CreatePalette (IDirectDraw7::shims::CreatePalette),
+        CreateSurface (IDirectDraw::shims::CreateSurface),
+        DuplicateSurface todo,
+        EnumDisplayModes (IDirectDraw::shims::EnumDisplayModes),
+        EnumSurfaces todo,
+        FlipToGDISurface todo,
+        GetCaps (IDirectDraw7::shims::GetCaps),
+        GetDisplayMode (IDirectDraw::shims::GetDisplayMode),
+        GetFourCCCodes todo,
+        GetGDISurface todo,
+        GetMonitorFrequency todo,
+        GetScanLine todo,
+        GetVerticalBlankStatus todo,
+        Initialize (IDirectDraw7::shims::Initialize),
+        RestoreDisplayMode (IDirectDraw7::shims::RestoreDisplayMode),
+        SetCooperativeLevel (IDirectDraw7::shims::SetCooperativeLevel),
+        SetDisplayMode (IDirectDraw7::shims::SetDisplayMode),
+        WaitForVerticalBlank (IDirectDraw7::shims::WaitForVerticalBlank),
+        GetAvailableVidMem (IDirectDraw7::shims::GetAvailableVidMem),
+    ];
+}
+
+#[win32_derive::shims_from_x86]
+pub(super) mod IDirectDrawSurface2 {
+    use super::*;
+
+    vtable![IDirectDrawSurface2 shims
+        QueryInterface (IDirectDrawSurface7::shims::QueryInterface),
+        AddRef (IDirectDrawSurface7::shims::AddRef),
+        Release (IDirectDrawSurface7::shims::Release),
+        AddAttachedSurface todo,
+        AddOverlayDirtyRect todo,
+        Blt (IDirectDrawSurface7::shims::Blt),
+        BltBatch todo,
+        BltFast (IDirectDrawSurface7::shims::BltFast),
+        DeleteAttachedSurface todo,
+        EnumAttachedSurfaces todo,
+        EnumOverlayZOrders todo,
+        Flip (IDirectDrawSurface7::shims::Flip),
+        GetAttachedSurface (IDirectDrawSurface::shims::GetAttachedSurface),
+        GetBltStatus todo,
+        GetCaps (IDirectDrawSurface::shims::GetCaps),
+        GetClipper todo,
+        GetColorKey (IDirectDrawSurface7::shims::GetColorKey),
+        GetDC (IDirectDrawSurface7::shims::GetDC),
+        GetFlipStatus todo,
+        GetOverlayPosition todo,
+        GetPalette todo,
+        GetPixelFormat (IDirectDrawSurface::shims::GetPixelFormat),
+        GetSurfaceDesc (IDirectDrawSurface::shims::GetSurfaceDesc),
+        Initialize todo,
+        IsLost todo,
+        Lock (IDirectDrawSurface::shims::Lock),
+        ReleaseDC (IDirectDrawSurface7::shims::ReleaseDC),
+        Restore (IDirectDrawSurface7::shims::Restore),
+        SetClipper (IDirectDrawSurface7::shims::SetClipper),
+        SetColorKey (IDirectDrawSurface7::shims::SetColorKey),
+        SetOverlayPosition todo,
+        SetPalette (IDirectDrawSurface7::shims::SetPalette),
+        Unlock (IDirectDrawSurface::shims::Unlock),
+        UpdateOverlay todo,
+        UpdateOverlayDisplay todo,
+        UpdateOverlayZOrder todo,
+        GetDDInterface todo,
+        PageLock (IDirectDrawSurface7::shims::PageLock),
+        PageUnlock (IDirectDrawSurface7::shims::PageUnlock),
+    ];
+}
+
+#[win32_derive::shims_from_x86]
+pub(super) mod IDirectDrawSurface3 {
+    use super::*;
+
+    vtable![IDirectDrawSurface3 shims
+        QueryInterface (IDirectDrawSurface7::shims::QueryInterface),
+        AddRef (IDirectDrawSurface7::shims::AddRef),
+        Release (IDirectDrawSurface7::shims::Release),
+        AddAttachedSurface todo,
+        AddOverlayDirtyRect todo,
+        Blt (IDirectDrawSurface7::shims::Blt),
+        BltBatch todo,
+        BltFast (IDirectDrawSurface7::shims::BltFast),
+        DeleteAttachedSurface todo,
+        EnumAttachedSurfaces todo,
+        EnumOverlayZOrders todo,
+        Flip (IDirectDrawSurface7::shims::Flip),
+        GetAttachedSurface (IDirectDrawSurface::shims::GetAttachedSurface),
+        GetBltStatus todo,
+        GetCaps (IDirectDrawSurface::shims::GetCaps),
+        GetClipper todo,
+        GetColorKey (IDirectDrawSurface7::shims::GetColorKey),
+        GetDC (IDirectDrawSurface7::shims::GetDC),
+        GetFlipStatus todo,
+        GetOverlayPosition todo,
+        GetPalette todo,
+        GetPixelFormat (IDirectDrawSurface::shims::GetPixelFormat),
+        GetSurfaceDesc (IDirectDrawSurface::shims::GetSurfaceDesc),
+        Initialize todo,
+        IsLost todo,
+        Lock (IDirectDrawSurface::shims::Lock),
+        ReleaseDC (IDirectDrawSurface7::shims::ReleaseDC),
+        Restore (IDirectDrawSurface7::shims::Restore),
+        SetClipper (IDirectDrawSurface7::shims::SetClipper),
+        SetColorKey (IDirectDrawSurface7::shims::SetColorKey),
+        SetOverlayPosition todo,
+        SetPalette (IDirectDrawSurface7::shims::SetPalette),
+        Unlock (IDirectDrawSurface::shims::Unlock),
+        UpdateOverlay todo,
+        UpdateOverlayDisplay todo,
+        UpdateOverlayZOrder todo,
+        GetDDInterface todo,
+        PageLock (IDirectDrawSurface7::shims::PageLock),
+        PageUnlock (IDirectDrawSurface7::shims::PageUnlock),
+        SetSurfaceDesc todo,
+    ];
+}
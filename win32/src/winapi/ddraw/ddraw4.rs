@@ -0,0 +1,131 @@
+//! Implementation of the DirectX 6 interfaces, IDirectDraw4 and IDirectDrawSurface4.
+//! These take the same DDSURFACEDESC2 structures as the DirectDraw7 interfaces and
+//! are a prefix of their vtables, so they mostly share the DirectDraw7 shims.
+
+use super::{ddraw7::IDirectDraw7, ddraw7::IDirectDrawSurface7, types::*, State, DD_OK};
+use crate::{
+    machine::Emulator,
+    winapi::{ddraw, types::*, vtable},
+    Machine,
+};
+
+const TRACE_CONTEXT: &'static str = "ddraw/4";
+
+pub const IID_IDirectDraw4: [u8; 16] = [
+    0x9a, 0x50, 0x59, 0x9c, 0xbd, 0x39, 0xd1, 0x11, 0x8c, 0x4a, 0x00, 0xc0, 0x4f, 0xd9, 0x30, 0xc5,
+];
+
+pub const IID_IDirectDrawSurface4: [u8; 16] = [
+    0x30, 0x86, 0x2b, 0x0b, 0x35, 0xad, 0xd0, 0x11, 0x8e, 0xa6, 0x00, 0x60, 0x97, 0x97, 0xea, 0x5b,
+];
+
+#[win32_derive::shims_from_x86]
+pub(super) mod IDirectDraw4 {
+    use super::*;
+
+    vtable![IDirectDraw4 shims
+        QueryInterface (IDirectDraw7::shims::QueryInterface),
+        AddRef (IDirectDraw7::shims::AddRef),
+        Release (IDirectDraw7::shims::Release),
+        Compact todo,
+        CreateClipper (IDirectDraw7::shims::CreateClipper),
+        CreatePalette (IDirectDraw7::shims::CreatePalette),
+        CreateSurface ok,
+        DuplicateSurface todo,
+        EnumDisplayModes (IDirectDraw7::shims::EnumDisplayModes),
+        EnumSurfaces todo,
+        FlipToGDISurface todo,
+        GetCaps (IDirectDraw7::shims::GetCaps),
+        GetDisplayMode (IDirectDraw7::shims::GetDisplayMode),
+        GetFourCCCodes todo,
+        GetGDISurface todo,
+        GetMonitorFrequency todo,
+        GetScanLine todo,
+        GetVerticalBlankStatus todo,
+        Initialize (IDirectDraw7::shims::Initialize),
+        RestoreDisplayMode (IDirectDraw7::shims::RestoreDisplayMode),
+        SetCooperativeLevel (IDirectDraw7::shims::SetCooperativeLevel),
+        SetDisplayMode (IDirectDraw7::shims::SetDisplayMode),
+        WaitForVerticalBlank (IDirectDraw7::shims::WaitForVerticalBlank),
+        GetAvailableVidMem (IDirectDraw7::shims::GetAvailableVidMem),
+        GetSurfaceFromDC todo,
+        RestoreAllSurfaces todo,
+        TestCooperativeLevel todo,
+        GetDeviceIdentifier todo,
+    ];
+
+    #[win32_derive::dllexport]
+    fn CreateSurface(
+        machine: &mut Machine,
+        this: u32,
+        desc: Option<&DDSURFACEDESC2>,
+        lpDirectDrawSurface4: Option<&mut u32>,
+        unused: u32,
+    ) -> u32 {
+        match ddraw::create_surfaces(machine, desc.unwrap(), IDirectDrawSurface4::new) {
+            Ok(surface) => {
+                *lpDirectDrawSurface4.unwrap() = surface;
+                DD_OK
+            }
+            Err(err) => err,
+        }
+    }
+}
+
+#[win32_derive::shims_from_x86]
+pub(super) mod IDirectDrawSurface4 {
+    use super::*;
+
+    vtable![IDirectDrawSurface4 shims
+        QueryInterface (IDirectDrawSurface7::shims::QueryInterface),
+        AddRef (IDirectDrawSurface7::shims::AddRef),
+        Release (IDirectDrawSurface7::shims::Release),
+        AddAttachedSurface todo,
+        AddOverlayDirtyRect todo,
+        Blt (IDirectDrawSurface7::shims::Blt),
+        BltBatch todo,
+        BltFast (IDirectDrawSurface7::shims::BltFast),
+        DeleteAttachedSurface todo,
+        EnumAttachedSurfaces todo,
+        EnumOverlayZOrders todo,
+        Flip (IDirectDrawSurface7::shims::Flip),
+        GetAttachedSurface (IDirectDrawSurface7::shims::GetAttachedSurface),
+        GetBltStatus todo,
+        GetCaps (IDirectDrawSurface7::shims::GetCaps),
+        GetClipper todo,
+        GetColorKey (IDirectDrawSurface7::shims::GetColorKey),
+        GetDC (IDirectDrawSurface7::shims::GetDC),
+        GetFlipStatus todo,
+        GetOverlayPosition todo,
+        GetPalette todo,
+        GetPixelFormat todo,
+        GetSurfaceDesc (IDirectDrawSurface7::shims::GetSurfaceDesc),
+        Initialize todo,
+        IsLost todo,
+        Lock (IDirectDrawSurface7::shims::Lock),
+        ReleaseDC (IDirectDrawSurface7::shims::ReleaseDC),
+        Restore (IDirectDrawSurface7::shims::Restore),
+        SetClipper (IDirectDrawSurface7::shims::SetClipper),
+        SetColorKey (IDirectDrawSurface7::shims::SetColorKey),
+        SetOverlayPosition todo,
+        SetPalette (IDirectDrawSurface7::shims::SetPalette),
+        Unlock (IDirectDrawSurface7::shims::Unlock),
+        UpdateOverlay todo,
+        UpdateOverlayDisplay todo,
+        UpdateOverlayZOrder todo,
+        GetDDInterface todo,
+        PageLock (IDirectDrawSurface7::shims::PageLock),
+        PageUnlock (IDirectDrawSurface7::shims::PageUnlock),
+        SetSurfaceDesc todo,
+        SetPrivateData todo,
+        GetPrivateData todo,
+        FreePrivateData todo,
+        GetUniquenessValue todo,
+        ChangeUniquenessValue todo,
+    ];
+
+    pub fn new(machine: &mut Machine) -> u32 {
+        let vtable = machine.state.ddraw.vtable_IDirectDrawSurface4;
+        ddraw::new_object(machine, vtable)
+    }
+}
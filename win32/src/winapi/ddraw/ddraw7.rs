@@ -1,13 +1,17 @@
 //! Implementation of DirectDraw7 interfaces.
 
-use super::{types::*, IDirectDrawPalette, State, DDERR_GENERIC, DD_OK};
+use super::{
+    blit, types::*, DisplayMode, IDirectDrawClipper, IDirectDrawPalette, State, DDENUMRET_CANCEL,
+    DDERR_GENERIC, DDERR_INVALIDCAPS, DDERR_INVALIDMODE, DDERR_INVALIDPARAMS, DDERR_NOCOLORKEY,
+    DDERR_NOTFOUND, DDERR_NOTLOCKED, DD_OK,
+};
 use crate::{
     machine::Emulator,
-    winapi::{ddraw, types::*, vtable},
+    winapi::{ddraw, gdi32::HDC, types::*, vtable},
     Machine,
 };
 use bitflags::bitflags;
-use memory::Pod;
+use memory::{Extensions, Pod};
 
 const TRACE_CONTEXT: &'static str = "ddraw/7";
 
@@ -15,47 +19,83 @@ pub const IID_IDirectDraw7: [u8; 16] = [
     0xc0, 0x5e, 0xe6, 0x15, 0x9c, 0x3b, 0xd2, 0x11, 0xb9, 0x2f, 0x00, 0x60, 0x97, 0x97, 0xea, 0x5b,
 ];
 
+pub const IID_IDirectDrawSurface7: [u8; 16] = [
+    0x80, 0x5a, 0x67, 0x06, 0x9b, 0x3b, 0xd2, 0x11, 0xb9, 0x2f, 0x00, 0x60, 0x97, 0x97, 0xea, 0x5b,
+];
+
 #[win32_derive::shims_from_x86]
 pub(super) mod IDirectDraw7 {
     use super::*;
 
     vtable![IDirectDraw7 shims
-        QueryInterface todo,
-        AddRef todo,
+        QueryInterface ok,
+        AddRef ok,
         Release ok,
         Compact todo,
-        CreateClipper todo,
+        CreateClipper ok,
         CreatePalette ok,
         CreateSurface ok,
         DuplicateSurface todo,
         EnumDisplayModes ok,
         EnumSurfaces todo,
         FlipToGDISurface todo,
-        GetCaps todo,
-        GetDisplayMode todo,
+        GetCaps ok,
+        GetDisplayMode ok,
         GetFourCCCodes todo,
         GetGDISurface todo,
         GetMonitorFrequency todo,
         GetScanLine todo,
         GetVerticalBlankStatus todo,
-        Initialize todo,
+        Initialize ok,
         RestoreDisplayMode ok,
         SetCooperativeLevel ok,
         SetDisplayMode ok,
         WaitForVerticalBlank ok,
-        GetAvailableVidMem todo,
+        GetAvailableVidMem ok,
         GetSurfaceFromDC todo,
         RestoreAllSurfaces todo,
         TestCooperativeLevel todo,
-        GetDeviceIdentifier todo,
+        GetDeviceIdentifier ok,
         StartModeTest todo,
         EvaluateMode todo,
     ];
 
     #[win32_derive::dllexport]
-    fn Release(_machine: &mut Machine, this: u32) -> u32 {
-        log::warn!("{this:x}->Release()");
-        0 // TODO: return refcount?
+    pub fn QueryInterface(machine: &mut Machine, this: u32, riid: u32, ppvObject: u32) -> u32 {
+        ddraw::query_interface(machine, this, riid, ppvObject)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn AddRef(machine: &mut Machine, this: u32) -> u32 {
+        machine.state.ddraw.refs.add_ref(this)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Release(machine: &mut Machine, this: u32) -> u32 {
+        let ddraw = &mut machine.state.ddraw;
+        let refs = ddraw.refs.release(this);
+        if refs == 0 {
+            ddraw.heap.free(machine.emu.memory.mem(), this);
+        }
+        refs
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Initialize(_machine: &mut Machine, this: u32, lpGUID: u32) -> u32 {
+        // Objects from CoCreateInstance are initialized here, but ours are ready already.
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn CreateClipper(
+        machine: &mut Machine,
+        this: u32,
+        flags: u32,
+        lplpClipper: Option<&mut u32>,
+        pUnkOuter: u32,
+    ) -> u32 {
+        *lplpClipper.unwrap() = IDirectDrawClipper::new(machine);
+        DD_OK
     }
 
     #[win32_derive::dllexport]
@@ -68,15 +108,27 @@ pub(super) mod IDirectDraw7 {
         unused: u32,
     ) -> u32 {
         let flags = flags.unwrap();
-        if !flags.contains(DDPCAPS::_8BIT) {
-            todo!();
+        let count = if flags.contains(DDPCAPS::_8BIT) {
+            256
+        } else if flags.contains(DDPCAPS::_4BIT) {
+            16
+        } else if flags.contains(DDPCAPS::_2BIT) {
+            4
+        } else if flags.contains(DDPCAPS::_1BIT) {
+            2
+        } else {
+            log::warn!("CreatePalette: unknown size in {flags:?}");
+            return DDERR_GENERIC;
+        };
+        if flags.contains(DDPCAPS::_8BITENTRIES) {
+            // TODO: entries are then one-byte indexes into the destination's palette.
+            todo!("DDPCAPS_8BITENTRIES");
         }
-        // TODO: if palette is DDPCAPS_8BITENTRIES then SetEntries needs change too.
 
         let palette = IDirectDrawPalette::new(machine);
         let entries = machine
             .mem()
-            .view_n::<PALETTEENTRY>(entries, 256)
+            .view_n::<PALETTEENTRY>(entries, count)
             .to_vec()
             .into_boxed_slice();
         machine.state.ddraw.palettes.insert(palette, entries);
@@ -85,33 +137,24 @@ pub(super) mod IDirectDraw7 {
     }
 
     #[win32_derive::dllexport]
-    fn CreateSurface(
+    pub fn CreateSurface(
         machine: &mut Machine,
         this: u32,
         desc: Option<&DDSURFACEDESC2>,
         lpDirectDrawSurface7: Option<&mut u32>,
         unused: u32,
     ) -> u32 {
-        let surfaces = ddraw::Surface::create(machine, desc.unwrap());
-        if surfaces.len() > 2 {
-            todo!()
-        }
-
-        let mut prev = 0;
-        for mut surface in surfaces.into_iter().rev() {
-            let ptr = IDirectDrawSurface7::new(machine);
-            surface.attached = prev;
-            machine.state.ddraw.surfaces.insert(ptr, surface);
-            prev = ptr;
+        match ddraw::create_surfaces(machine, desc.unwrap(), IDirectDrawSurface7::new) {
+            Ok(surface) => {
+                *lpDirectDrawSurface7.unwrap() = surface;
+                DD_OK
+            }
+            Err(err) => err,
         }
-
-        *lpDirectDrawSurface7.unwrap() = prev;
-
-        DD_OK
     }
 
     #[win32_derive::dllexport]
-    async fn EnumDisplayModes(
+    pub async fn EnumDisplayModes(
         machine: &mut Machine,
         this: u32,
         dwFlags: u32,
@@ -129,27 +172,15 @@ pub(super) mod IDirectDraw7 {
             .ddraw
             .heap
             .alloc(mem, std::mem::size_of::<DDSURFACEDESC2>() as u32);
-        let desc = mem.view_mut::<DDSURFACEDESC2>(desc_addr);
-        desc.clear_struct();
-        // TODO: offer multiple display modes rather than hardcoding this one.
-        desc.dwSize = std::mem::size_of::<DDSURFACEDESC2>() as u32;
-        desc.dwWidth = 320;
-        desc.dwHeight = 200;
-        desc.ddpfPixelFormat = DDPIXELFORMAT {
-            dwSize: std::mem::size_of::<DDPIXELFORMAT>() as u32,
-            dwFlags: 0,
-            dwFourCC: 0,
-            dwRGBBitCount: 8,
-            dwRBitMask: 0xFF000000,
-            dwGBitMask: 0x00FF0000,
-            dwBBitMask: 0x0000FF00,
-            dwRGBAlphaBitMask: 0x000000FF,
-        };
-
-        machine
-            .call_x86(lpEnumCallback, vec![desc_addr, lpContext])
-            .await;
-
+        for mode in DisplayMode::all() {
+            mode.describe(machine.mem().view_mut::<DDSURFACEDESC2>(desc_addr));
+            let ret = machine
+                .call_x86(lpEnumCallback, vec![desc_addr, lpContext])
+                .await;
+            if ret == DDENUMRET_CANCEL {
+                break;
+            }
+        }
         machine
             .state
             .ddraw
@@ -184,8 +215,95 @@ pub(super) mod IDirectDraw7 {
     }
 
     #[win32_derive::dllexport]
-    pub fn RestoreDisplayMode(_machine: &mut Machine, this: u32) -> u32 {
-        0
+    pub fn GetAvailableVidMem(
+        machine: &mut Machine,
+        this: u32,
+        lpDDSCaps: u32,
+        lpdwTotal: Option<&mut u32>,
+        lpdwFree: Option<&mut u32>,
+    ) -> u32 {
+        // All surfaces share a single pool of local video memory, and there is no AGP
+        // memory.  The caps are a DDSCAPS or DDSCAPS2, which both start with dwCaps.
+        let caps = match lpDDSCaps {
+            0 => DDSCAPS::empty(),
+            addr => DDSCAPS::from_bits_truncate(machine.mem().get_pod::<u32>(addr)),
+        };
+        if caps.contains(DDSCAPS::SYSTEMMEMORY) {
+            return DDERR_INVALIDCAPS;
+        }
+        let ddraw = &machine.state.ddraw;
+        let (total, free) = if caps.contains(DDSCAPS::NONLOCALVIDMEM) {
+            (0, 0)
+        } else {
+            (ddraw.video_memory, ddraw.video_memory_free())
+        };
+        if let Some(lpdwTotal) = lpdwTotal {
+            *lpdwTotal = total;
+        }
+        if let Some(lpdwFree) = lpdwFree {
+            *lpdwFree = free;
+        }
+        DD_OK
+    }
+
+    #[repr(C)]
+    #[derive(Debug)]
+    pub struct DDDEVICEIDENTIFIER2 {
+        pub szDriver: [u8; 512],
+        pub szDescription: [u8; 512],
+        pub liDriverVersion: u64,
+        pub dwVendorId: DWORD,
+        pub dwDeviceId: DWORD,
+        pub dwSubSysId: DWORD,
+        pub dwRevision: DWORD,
+        pub guidDeviceIdentifier: [u8; 16],
+        pub dwWHQLLevel: DWORD,
+    }
+    unsafe impl memory::Pod for DDDEVICEIDENTIFIER2 {}
+
+    #[win32_derive::dllexport]
+    pub fn GetDeviceIdentifier(
+        machine: &mut Machine,
+        this: u32,
+        lpdddi: Option<&mut DDDEVICEIDENTIFIER2>,
+        dwFlags: u32,
+    ) -> u32 {
+        let Some(id) = lpdddi else {
+            return DDERR_INVALIDPARAMS;
+        };
+        let gpu = &machine.state.ddraw.gpu;
+        id.clear_struct();
+        let driver = b"retrowin32.drv";
+        id.szDriver[..driver.len()].copy_from_slice(driver);
+        let name = &gpu.name.as_bytes()[..std::cmp::min(gpu.name.len(), 511)];
+        id.szDescription[..name.len()].copy_from_slice(name);
+        id.dwVendorId = gpu.vendor_id;
+        id.dwDeviceId = gpu.device_id;
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetCaps(machine: &mut Machine, this: u32, lpDDDriverCaps: u32, lpDDHELCaps: u32) -> u32 {
+        ddraw::write_caps(machine, lpDDDriverCaps);
+        ddraw::write_caps(machine, lpDDHELCaps);
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetDisplayMode(
+        machine: &mut Machine,
+        this: u32,
+        lpDDSurfaceDesc: Option<&mut DDSURFACEDESC2>,
+    ) -> u32 {
+        machine.state.ddraw.mode.describe(lpDDSurfaceDesc.unwrap());
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn RestoreDisplayMode(machine: &mut Machine, this: u32) -> u32 {
+        // The window keeps its size; when fullscreen, the host scales it to the display.
+        machine.state.ddraw.mode = DisplayMode::DESKTOP;
+        DD_OK
     }
 
     #[win32_derive::dllexport]
@@ -195,13 +313,18 @@ pub(super) mod IDirectDraw7 {
         hwnd: HWND,
         flags: Result<DDSCL, u32>,
     ) -> u32 {
-        // TODO: this triggers behaviors like fullscreen.
-        machine.state.ddraw.hwnd = hwnd;
         let flags = flags.unwrap();
-        if flags.contains(DDSCL::EXCLUSIVE) {
-            let window = machine.state.user32.windows.get_mut(hwnd).unwrap();
-            window.host.fullscreen();
+        let ddraw = &mut machine.state.ddraw;
+        let exclusive = flags.contains(DDSCL::EXCLUSIVE | DDSCL::FULLSCREEN);
+        if exclusive != ddraw.exclusive {
+            // Leaving exclusive mode affects the window that had it.
+            let hwnd = if exclusive { hwnd } else { ddraw.hwnd };
+            ddraw.exclusive = exclusive;
+            if let Some(window) = machine.state.user32.windows.get_mut(hwnd) {
+                window.host.set_fullscreen(exclusive);
+            }
         }
+        machine.state.ddraw.hwnd = hwnd;
         DD_OK
     }
 
@@ -215,6 +338,12 @@ pub(super) mod IDirectDraw7 {
         refresh: u32,
         flags: u32,
     ) -> u32 {
+        if !matches!(bpp, 8 | 16 | 24 | 32) || width == 0 || height == 0 {
+            log::warn!("SetDisplayMode({width}x{height}x{bpp}): unsupported mode");
+            return DDERR_INVALIDMODE;
+        }
+        // The window becomes the size of the mode; when fullscreen, the host scales
+        // that up to the real display.
         if let Some(wnd) = machine
             .state
             .user32
@@ -223,7 +352,11 @@ pub(super) mod IDirectDraw7 {
         {
             wnd.set_client_size(width, height);
         }
-        machine.state.ddraw.bytes_per_pixel = bpp / 8;
+        machine.state.ddraw.mode = DisplayMode {
+            width,
+            height,
+            bytes_per_pixel: bpp / 8,
+        };
         DD_OK
     }
 
@@ -246,8 +379,8 @@ pub(super) mod IDirectDrawSurface7 {
     use super::*;
 
     vtable![IDirectDrawSurface7 shims
-        QueryInterface todo,
-        AddRef todo,
+        QueryInterface ok,
+        AddRef ok,
         Release ok,
         AddAttachedSurface todo,
         AddOverlayDirtyRect todo,
@@ -262,7 +395,7 @@ pub(super) mod IDirectDrawSurface7 {
         GetBltStatus todo,
         GetCaps ok,
         GetClipper todo,
-        GetColorKey todo,
+        GetColorKey ok,
         GetDC ok,
         GetFlipStatus todo,
         GetOverlayPosition todo,
@@ -274,8 +407,8 @@ pub(super) mod IDirectDrawSurface7 {
         Lock ok,
         ReleaseDC ok,
         Restore ok,
-        SetClipper todo,
-        SetColorKey todo,
+        SetClipper ok,
+        SetColorKey ok,
         SetOverlayPosition todo,
         SetPalette ok,
         Unlock ok,
@@ -283,8 +416,8 @@ pub(super) mod IDirectDrawSurface7 {
         UpdateOverlayDisplay todo,
         UpdateOverlayZOrder todo,
         GetDDInterface todo,
-        PageLock todo,
-        PageUnlock todo,
+        PageLock ok,
+        PageUnlock ok,
         SetSurfaceDesc todo,
         SetPrivateData todo,
         GetPrivateData todo,
@@ -306,13 +439,85 @@ pub(super) mod IDirectDrawSurface7 {
     }
 
     #[win32_derive::dllexport]
-    fn Release(_machine: &mut Machine, this: u32) -> u32 {
-        log::warn!("{this:x}->Release()");
-        0 // TODO: return refcount?
+    pub fn QueryInterface(machine: &mut Machine, this: u32, riid: u32, ppvObject: u32) -> u32 {
+        ddraw::query_interface(machine, this, riid, ppvObject)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn AddRef(machine: &mut Machine, this: u32) -> u32 {
+        ddraw::add_ref(machine, this)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Release(machine: &mut Machine, this: u32) -> u32 {
+        ddraw::release(machine, this)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn PageLock(_machine: &mut Machine, this: u32, dwFlags: u32) -> u32 {
+        DD_OK // Surface memory is never paged out.
     }
 
     #[win32_derive::dllexport]
-    fn Blt(
+    pub fn PageUnlock(_machine: &mut Machine, this: u32, dwFlags: u32) -> u32 {
+        DD_OK
+    }
+
+    /// Shared implementation of Blt and BltFast.
+    fn blit(
+        machine: &mut Machine,
+        this: u32,
+        dst_rect: Option<RECT>,
+        src: Option<(u32, Option<RECT>)>,
+        fill: u32,
+        src_key: Option<DDCOLORKEY>,
+        dst_key: Option<DDCOLORKEY>,
+        clip: bool,
+    ) -> u32 {
+        let mem = machine.emu.memory.mem();
+        let ddraw = &mut machine.state.ddraw;
+        let src = match src {
+            Some((addr, rect)) => {
+                let Some(surf) = ddraw.surfaces.get_mut(&addr) else {
+                    return DDERR_GENERIC;
+                };
+                let buf = surf.pixel_buf(mem, &mut ddraw.heap);
+                let rect = rect.unwrap_or(RECT {
+                    left: 0,
+                    top: 0,
+                    right: surf.width as i32,
+                    bottom: surf.height as i32,
+                });
+                blit::Source::Surface { buf, rect }
+            }
+            None => blit::Source::Fill(fill),
+        };
+        let dst = ddraw.surfaces.get_mut(&this).unwrap();
+        let buf = dst.pixel_buf(mem, &mut ddraw.heap);
+        if let blit::Source::Surface { buf: src_buf, .. } = &src {
+            if src_buf.bytes_per_pixel != buf.bytes_per_pixel {
+                log::warn!("blit between surfaces of different formats");
+                return DDERR_GENERIC;
+            }
+        }
+        // Without a dest rect, the source is stretched over the whole surface.
+        let dst_rect = dst_rect.unwrap_or(RECT {
+            left: 0,
+            top: 0,
+            right: dst.width as i32,
+            bottom: dst.height as i32,
+        });
+        let clip_rects = match ddraw.clippers.get(&dst.clipper) {
+            Some(clipper) if clip => clipper.rects.as_slice(),
+            _ => &[],
+        };
+        blit::blit(mem, &buf, dst_rect, clip_rects, &src, src_key, dst_key);
+        ddraw::flush(machine, this);
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Blt(
         machine: &mut Machine,
         this: u32,
         lpDstRect: Option<&RECT>,
@@ -321,49 +526,108 @@ pub(super) mod IDirectDrawSurface7 {
         flags: Result<DDBLT, u32>,
         lpDDBLTFX: Option<&DDBLTFX>,
     ) -> u32 {
-        if lpDstRect.is_some() || lpSrcRect.is_some() {
-            todo!()
+        let flags = flags.unwrap_or_else(|flags| {
+            log::warn!("Blt: unknown flags {flags:x}");
+            DDBLT::from_bits_truncate(flags)
+        });
+        let unhandled = flags
+            - (DDBLT::COLORFILL
+                | DDBLT::KEYSRC
+                | DDBLT::KEYSRCOVERRIDE
+                | DDBLT::KEYDEST
+                | DDBLT::KEYDESTOVERRIDE
+                | DDBLT::WAIT
+                | DDBLT::DONOTWAIT
+                | DDBLT::ASYNC);
+        if !unhandled.is_empty() {
+            log::warn!("Blt: ignoring flags {unhandled:?}");
         }
-        let flags = flags.unwrap();
-        if flags.contains(DDBLT::COLORFILL) {
-            log::warn!("todo: DDBLT::COLORFILL");
-            return DD_OK;
-        }
-        log::warn!("Blt: ignoring behavioral flags");
-        BltFast(machine, this, 0, 0, lpSurf, None, 0);
-        DD_OK
+
+        let ddraw = &machine.state.ddraw;
+        let fx = lpDDBLTFX;
+        let src_key = if flags.contains(DDBLT::KEYSRCOVERRIDE) {
+            fx.map(|fx| fx.ddckSrcColorkey)
+        } else if flags.contains(DDBLT::KEYSRC) {
+            ddraw.surfaces.get(&lpSurf).and_then(|surf| surf.src_key)
+        } else {
+            None
+        };
+        let dst_key = if flags.contains(DDBLT::KEYDESTOVERRIDE) {
+            fx.map(|fx| fx.ddckDestColorkey)
+        } else if flags.contains(DDBLT::KEYDEST) {
+            ddraw.surfaces.get(&this).and_then(|surf| surf.dst_key)
+        } else {
+            None
+        };
+
+        let (src, fill) = if flags.contains(DDBLT::COLORFILL) {
+            (None, fx.unwrap().fill)
+        } else {
+            (Some((lpSurf, lpSrcRect.copied())), 0)
+        };
+        blit(
+            machine,
+            this,
+            lpDstRect.copied(),
+            src,
+            fill,
+            src_key,
+            dst_key,
+            true,
+        )
     }
 
     #[win32_derive::dllexport]
-    fn BltFast(
+    pub fn BltFast(
         machine: &mut Machine,
         this: u32,
         x: u32,
         y: u32,
         lpSurf: u32,
         lpRect: Option<&RECT>,
-        flags: u32,
+        flags: Result<DDBLTFAST, u32>,
     ) -> u32 {
-        if flags != 0 {
-            log::warn!("BltFast flags: {:x}", flags);
-        }
-        let (dst, src) = unsafe {
-            let dst = machine.state.ddraw.surfaces.get_mut(&this).unwrap() as *mut ddraw::Surface;
-            let src = machine.state.ddraw.surfaces.get(&lpSurf).unwrap() as *const ddraw::Surface;
-            assert_ne!(dst as *const ddraw::Surface, src);
-            (&mut *dst, &*src)
+        let flags = flags.unwrap_or_else(|flags| {
+            log::warn!("BltFast: unknown flags {flags:x}");
+            DDBLTFAST::from_bits_truncate(flags)
+        });
+        let ddraw = &machine.state.ddraw;
+        let Some(src) = ddraw.surfaces.get(&lpSurf) else {
+            return DDERR_GENERIC;
         };
-        if let Some(rect) = lpRect {
-            let sx = rect.left as u32;
-            let w = (rect.right - rect.left) as u32;
-            let sy = rect.top as u32;
-            let h = (rect.bottom - rect.top) as u32;
-            dst.host.bit_blt(x, y, src.host.as_ref(), sx, sy, w, h);
+        let src_rect = lpRect.copied().unwrap_or(RECT {
+            left: 0,
+            top: 0,
+            right: src.width as i32,
+            bottom: src.height as i32,
+        });
+        let dst_rect = RECT {
+            left: x as i32,
+            top: y as i32,
+            right: x as i32 + (src_rect.right - src_rect.left),
+            bottom: y as i32 + (src_rect.bottom - src_rect.top),
+        };
+        let src_key = if flags.contains(DDBLTFAST::SRCCOLORKEY) {
+            src.src_key
         } else {
-            dst.host
-                .bit_blt(x, y, src.host.as_ref(), 0, 0, src.width, src.height);
-        }
-        DD_OK
+            None
+        };
+        let dst_key = if flags.contains(DDBLTFAST::DESTCOLORKEY) {
+            ddraw.surfaces.get(&this).and_then(|surf| surf.dst_key)
+        } else {
+            None
+        };
+        // BltFast doesn't support clippers.
+        blit(
+            machine,
+            this,
+            Some(dst_rect),
+            Some((lpSurf, Some(src_rect))),
+            0,
+            src_key,
+            dst_key,
+            false,
+        )
     }
 
     bitflags! {
@@ -388,43 +652,124 @@ pub(super) mod IDirectDrawSurface7 {
     }
 
     #[win32_derive::dllexport]
-    fn Flip(machine: &mut Machine, this: u32, lpSurf: u32, flags: Result<DDFLIP, u32>) -> u32 {
-        let surface = machine.state.ddraw.surfaces.get(&this).unwrap();
-        let attached = surface.attached;
-        let back = machine.state.ddraw.surfaces.get_mut(&attached).unwrap();
-        back.host.show();
+    pub async fn Flip(
+        machine: &mut Machine,
+        this: u32,
+        lpSurf: u32,
+        flags: Result<DDFLIP, u32>,
+    ) -> u32 {
+        let flags = flags.unwrap_or_else(|flags| {
+            log::warn!("Flip: unknown flags {flags:x}");
+            DDFLIP::from_bits_truncate(flags)
+        });
+        if !flags.contains(DDFLIP::DDFLIP_NOVSYNC) {
+            // Pace to a 60Hz display, waiting out the requested number of refreshes.
+            let intervals = std::cmp::max((flags.bits() >> 24) & 0xF, 1);
+            let next = machine.state.ddraw.last_flip + intervals * 1000 / 60;
+            let now = machine.host.time();
+            if now < next {
+                if let Some(block) = machine.emu.block(Some(next)) {
+                    block.await;
+                }
+            }
+            machine.state.ddraw.last_flip = std::cmp::max(now, next);
+        }
+
+        ddraw::flip_chain(&mut machine.state.ddraw, this, lpSurf);
+        if machine.state.ddraw.surfaces[&this].bytes_per_pixel == 1 {
+            // Pick up any palette changes since the back buffer was last drawn.
+            ddraw::upload(machine, this);
+        }
+        ddraw::present(machine, this, true);
         DD_OK
     }
 
     #[win32_derive::dllexport]
-    fn GetAttachedSurface(
+    pub fn GetAttachedSurface(
         machine: &mut Machine,
         this: u32,
         lpDDSCaps2: Option<&DDSCAPS2>,
         lpDirectDrawSurface7: Option<&mut u32>,
     ) -> u32 {
-        // TODO: consider caps.
-        let surface = machine.state.ddraw.surfaces.get(&this).unwrap();
-        *lpDirectDrawSurface7.unwrap() = surface.attached;
+        let caps = lpDDSCaps2.unwrap().dwCaps;
+        match ddraw::get_attached(machine, this, caps) {
+            Some(addr) => {
+                *lpDirectDrawSurface7.unwrap() = addr;
+                DD_OK
+            }
+            None => DDERR_NOTFOUND,
+        }
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetCaps(machine: &mut Machine, this: u32, lpDDSCAPS2: Option<&mut DDSCAPS2>) -> u32 {
+        let caps = lpDDSCAPS2.unwrap();
+        caps.clear_struct();
+        caps.dwCaps = machine.state.ddraw.surfaces[&this].caps;
         DD_OK
     }
 
     #[win32_derive::dllexport]
-    fn GetCaps(_machine: &mut Machine, this: u32, lpDDSCAPS2: Option<&mut DDSCAPS2>) -> u32 {
+    pub fn GetColorKey(
+        machine: &mut Machine,
+        this: u32,
+        flags: Result<DDCKEY, u32>,
+        key: Option<&mut DDCOLORKEY>,
+    ) -> u32 {
+        let surf = machine.state.ddraw.surfaces.get(&this).unwrap();
+        let value = match flags {
+            Ok(DDCKEY::SRCBLT) => surf.src_key,
+            Ok(DDCKEY::DESTBLT) => surf.dst_key,
+            _ => {
+                log::warn!("GetColorKey({flags:?}): unsupported");
+                None
+            }
+        };
+        match value {
+            Some(value) => {
+                *key.unwrap() = value;
+                DD_OK
+            }
+            None => DDERR_NOCOLORKEY,
+        }
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetColorKey(
+        machine: &mut Machine,
+        this: u32,
+        flags: Result<DDCKEY, u32>,
+        key: Option<&DDCOLORKEY>,
+    ) -> u32 {
+        let surf = machine.state.ddraw.surfaces.get_mut(&this).unwrap();
+        let key = key.copied();
+        match flags.map(|flags| flags - DDCKEY::COLORSPACE) {
+            Ok(DDCKEY::SRCBLT) => surf.src_key = key,
+            Ok(DDCKEY::DESTBLT) => surf.dst_key = key,
+            _ => log::warn!("SetColorKey({flags:?}): unsupported"),
+        }
         DD_OK
     }
 
     #[win32_derive::dllexport]
-    fn GetDC(machine: &mut Machine, this: u32, lpHDC: u32) -> u32 {
-        let dc =
-            crate::winapi::gdi32::DC::new(crate::winapi::gdi32::DCTarget::DirectDrawSurface(this));
-        let handle = machine.state.gdi32.dcs.add(dc);
-        machine.mem().put::<u32>(lpHDC, handle.to_raw());
+    pub fn SetClipper(machine: &mut Machine, this: u32, clipper: u32) -> u32 {
+        machine.state.ddraw.surfaces.get_mut(&this).unwrap().clipper = clipper;
         DD_OK
     }
 
     #[win32_derive::dllexport]
-    fn GetSurfaceDesc(
+    pub fn GetDC(machine: &mut Machine, this: u32, lpHDC: u32) -> u32 {
+        match ddraw::get_dc(machine, this) {
+            Ok(hdc) => {
+                machine.mem().put::<u32>(lpHDC, hdc.to_raw());
+                DD_OK
+            }
+            Err(err) => err,
+        }
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetSurfaceDesc(
         machine: &mut Machine,
         this: u32,
         lpDesc: Option<&mut DDSURFACEDESC2>,
@@ -459,98 +804,70 @@ pub(super) mod IDirectDrawSurface7 {
         flags: Result<DDLOCK, u32>,
         unused: u32,
     ) -> u32 {
-        if rect.is_some() {
-            // TODO: once we implement this, we need corresponding logic in Unlock.
-            // Note also ddraw1's Unlock has a different type than 7.
-            todo!();
-        }
+        let flags = flags.unwrap_or_else(|flags| {
+            log::warn!("Lock: unknown flags {flags:x}");
+            DDLOCK::from_bits_truncate(flags)
+        });
+        // DDLOCK::WAIT needs no handling, as our surfaces are never busy with a blit.
         let desc = desc.unwrap();
-        let surf = machine.state.ddraw.surfaces.get_mut(&this).unwrap();
-        if surf.pixels == 0 {
-            surf.pixels = machine.state.ddraw.heap.alloc(
-                machine.emu.memory.mem(),
-                surf.width * surf.height * machine.state.ddraw.bytes_per_pixel,
-            );
+        let ddraw = &mut machine.state.ddraw;
+        let surf = ddraw.surfaces.get_mut(&this).unwrap();
+        if surf.lock.is_some() {
+            log::warn!("{this:x}->Lock(): surface already locked");
         }
+        let pixels = surf.pixels(machine.emu.memory.mem(), &mut ddraw.heap);
+        let pitch = surf.pitch();
+        let (x, y) = match rect {
+            Some(rect) => (rect.left as u32, rect.top as u32),
+            None => (0, 0),
+        };
+        surf.lock = Some(flags);
+
         // It seems callers (effect, monolife) don't provide flags for what they want,
         // and instead expect all fields to be included.
-        desc.lpSurface = surf.pixels;
-        desc.lPitch_dwLinearSize = surf.width * machine.state.ddraw.bytes_per_pixel;
+        desc.dwFlags =
+            DDSD::WIDTH | DDSD::HEIGHT | DDSD::PITCH | DDSD::LPSURFACE | DDSD::PIXELFORMAT;
+        desc.dwWidth = surf.width;
+        desc.dwHeight = surf.height;
+        desc.lpSurface = pixels + y * pitch + x * surf.bytes_per_pixel;
+        desc.lPitch_dwLinearSize = pitch;
+        desc.ddpfPixelFormat = DDPIXELFORMAT::for_bpp(surf.bytes_per_pixel);
         DD_OK
     }
 
     #[win32_derive::dllexport]
-    fn ReleaseDC(_machine: &mut Machine, _this: u32, _hDC: u32) -> u32 {
-        // leak
-        DD_OK
+    pub fn ReleaseDC(machine: &mut Machine, this: u32, hDC: HDC) -> u32 {
+        ddraw::release_dc(machine, this, hDC)
     }
 
     #[win32_derive::dllexport]
-    fn Restore(_machine: &mut Machine, _this: u32) -> u32 {
+    pub fn Restore(_machine: &mut Machine, _this: u32) -> u32 {
         DD_OK
     }
 
     #[win32_derive::dllexport]
-    fn SetPalette(machine: &mut Machine, this: u32, palette: u32) -> u32 {
-        machine.state.ddraw.surfaces.get_mut(&this).unwrap().palette = palette;
-        machine.state.ddraw.palette_hack = palette;
+    pub fn SetPalette(machine: &mut Machine, this: u32, palette: u32) -> u32 {
+        let ddraw = &mut machine.state.ddraw;
+        let surf = ddraw.surfaces.get_mut(&this).unwrap();
+        surf.palette = palette;
+        if surf.primary || surf.caps.contains(DDSCAPS::FLIP) || ddraw.display_palette == 0 {
+            ddraw.display_palette = palette;
+        }
         DD_OK
     }
 
     #[win32_derive::dllexport]
-    pub fn Unlock(machine: &mut Machine, this: u32, rect: Option<&mut RECT>) -> u32 {
+    pub fn Unlock(machine: &mut Machine, this: u32, rect: Option<&RECT>) -> u32 {
+        // rect is the rect passed to Lock; we always update the whole surface.
         let surf = machine.state.ddraw.surfaces.get_mut(&this).unwrap();
-        if let Some(rect) = rect {
-            // TODO: needs to match the rect passed in Lock.
-            rect.left = 0;
-            rect.top = 0;
-            rect.right = surf.width as i32;
-            rect.bottom = surf.height as i32;
-        }
-        assert!(surf.pixels != 0);
-        match machine.state.ddraw.bytes_per_pixel {
-            1 => {
-                let pixels = machine
-                    .emu
-                    .memory
-                    .mem()
-                    .view_n::<u8>(surf.pixels, surf.width * surf.height);
-                if let Some(palette) = machine
-                    .state
-                    .ddraw
-                    .palettes
-                    .get(&machine.state.ddraw.palette_hack)
-                {
-                    // XXX very inefficient
-                    let pixels32: Vec<_> = pixels
-                        .iter()
-                        .map(|&i| {
-                            let p = &palette[i as usize];
-                            [p.peRed, p.peGreen, p.peBlue, 255]
-                        })
-                        .collect();
-                    surf.host.write_pixels(&pixels32);
-                }
-            }
-            4 => {
-                let pixels = machine
-                    .emu
-                    .memory
-                    .mem()
-                    .view_n::<[u8; 4]>(surf.pixels, surf.width * surf.height);
-                // XXX setting alpha channel manually, very inefficient :(
-                let pixels32: Vec<_> = pixels.iter().map(|&[r, g, b, _a]| [r, g, b, 255]).collect();
-                surf.host.write_pixels(&pixels32);
-            }
-            bpp => todo!("Unlock for {bpp}bpp"),
-        }
-
-        // If surface is primary then updates should show immediately.
-        // XXX probably need something other than attached here
-        if surf.attached == 0 {
-            surf.host.show();
+        let Some(flags) = surf.lock.take() else {
+            return DDERR_NOTLOCKED;
+        };
+        if flags.contains(DDLOCK::READONLY) {
+            return DD_OK;
         }
 
+        ddraw::flush(machine, this);
         DD_OK
     }
 }
@@ -0,0 +1,107 @@
+//! The graphics card we claim to be.  Device-selection dialogs and engine startup
+//! checks look at what Direct3D enumerates, and some games refuse cards they don't
+//! recognize or that lack a depth format, so this is configurable per game.
+
+/// Description of the reported graphics card.
+#[derive(Debug, Clone)]
+pub struct Gpu {
+    /// Adapter name, as shown in device-selection dialogs.
+    pub name: String,
+    /// PCI vendor and device IDs, which some games match against known cards.
+    pub vendor_id: u32,
+    pub device_id: u32,
+    /// Largest texture width/height accepted.
+    pub max_texture_size: u32,
+    /// Depth buffer bit depths offered, in order of preference.
+    pub zbuffer_depths: Vec<u32>,
+    /// Whether to offer a hardware transform and lighting device.
+    pub tnl: bool,
+}
+
+impl Gpu {
+    pub fn preset(name: &str) -> Option<Gpu> {
+        Some(match name {
+            "generic" => Gpu {
+                name: "retrowin32 Direct3D".into(),
+                vendor_id: 0,
+                device_id: 0,
+                max_texture_size: 2048,
+                zbuffer_depths: vec![16, 24, 32],
+                tnl: true,
+            },
+            "voodoo3" => Gpu {
+                name: "3dfx Voodoo3".into(),
+                vendor_id: 0x121A,
+                device_id: 0x0005,
+                max_texture_size: 256,
+                zbuffer_depths: vec![16],
+                tnl: false,
+            },
+            "tnt2" => Gpu {
+                name: "NVIDIA RIVA TNT2".into(),
+                vendor_id: 0x10DE,
+                device_id: 0x0028,
+                max_texture_size: 2048,
+                zbuffer_depths: vec![16, 24],
+                tnl: false,
+            },
+            "geforce2" => Gpu {
+                name: "NVIDIA GeForce2 MX".into(),
+                vendor_id: 0x10DE,
+                device_id: 0x0110,
+                max_texture_size: 2048,
+                zbuffer_depths: vec![16, 24, 32],
+                tnl: true,
+            },
+            _ => return None,
+        })
+    }
+}
+
+impl Default for Gpu {
+    fn default() -> Self {
+        Gpu::preset("generic").unwrap()
+    }
+}
+
+impl std::str::FromStr for Gpu {
+    type Err = String;
+
+    /// Parses a preset name ("generic", "voodoo3", "tnt2", "geforce2"), optionally
+    /// followed by overrides, like "tnt2,texture=1024,zbuffer=16/32,tnl=1".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',');
+        let preset = parts.next().unwrap_or_default();
+        let mut gpu = Gpu::preset(preset).ok_or_else(|| format!("unknown gpu {preset:?}"))?;
+        let number = |v: &str| {
+            match v.strip_prefix("0x") {
+                Some(hex) => u32::from_str_radix(hex, 16),
+                None => v.parse::<u32>(),
+            }
+            .map_err(|_| format!("bad number {v:?}"))
+        };
+        for part in parts {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got {part:?}"))?;
+            match key {
+                "name" => gpu.name = value.into(),
+                "vendor" => gpu.vendor_id = number(value)?,
+                "device" => gpu.device_id = number(value)?,
+                "texture" => gpu.max_texture_size = number(value)?,
+                "zbuffer" => {
+                    gpu.zbuffer_depths = value
+                        .split('/')
+                        .map(|d| match d {
+                            "16" | "24" | "32" => Ok(d.parse().unwrap()),
+                            _ => Err(format!("bad zbuffer depth {d:?}")),
+                        })
+                        .collect::<Result<_, _>>()?
+                }
+                "tnl" => gpu.tnl = value != "0",
+                _ => return Err(format!("unknown gpu setting {key:?}")),
+            }
+        }
+        Ok(gpu)
+    }
+}
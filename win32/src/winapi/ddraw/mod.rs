@@ -1,26 +1,62 @@
 #![allow(non_snake_case)]
 #![allow(non_upper_case_globals)]
 
+mod blit;
+mod d3d7;
 mod ddraw1;
+mod ddraw2;
+mod ddraw4;
 mod ddraw7;
+mod gpu;
 mod types;
 
-use super::{heap::Heap, types::*};
+pub use gpu::Gpu;
+
+use super::{
+    bitmap::{BitmapRGBA32, PixelData},
+    com::{self, IID_IUnknown},
+    gdi32::{BitmapType, DCTarget, Object, DC, HDC, HGDIOBJ},
+    heap::Heap,
+    types::*,
+};
 use crate::{host, machine::Emulator, machine::Machine, winapi::vtable, SurfaceOptions};
+use memory::{Extensions, Pod};
 use std::collections::HashMap;
 use types::*;
 
 const TRACE_CONTEXT: &'static str = "ddraw";
 
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct Surface {
+    /// Recreated when a snapshot is restored; see restore.
+    #[serde(skip, default = "crate::winapi::detached_surface")]
     pub host: Box<dyn host::Surface>,
     pub width: u32,
     pub height: u32,
     pub palette: u32, // same as key in palettes
+    /// Bytes per pixel, fixed at creation from the display mode.
+    pub bytes_per_pixel: u32,
+    /// Whether this is the primary surface, i.e. what is shown on screen.
+    pub primary: bool,
+    /// Capabilities, including its role (FRONTBUFFER/BACKBUFFER) in a flip chain and
+    /// its placement in video or system memory.
+    pub caps: DDSCAPS,
     /// x86 address to pixel buffer, or 0 if unused.
     pixels: u32,
     /// Address of attached surface, e.g. back buffer.
     attached: u32,
+    /// Flags passed to Lock, while the surface is locked.
+    lock: Option<DDLOCK>,
+    /// Color keys set via SetColorKey or at creation.
+    src_key: Option<DDCOLORKEY>,
+    dst_key: Option<DDCOLORKEY>,
+    /// Attached IDirectDrawClipper, or 0.
+    clipper: u32,
+    /// DC and its bitmap handed out by GetDC, until ReleaseDC.
+    dc: Option<(HDC, HGDIOBJ)>,
+    /// COM reference count, shared by all interfaces to the surface.  Back buffers
+    /// start with one held by their front buffer, which frees them along with itself.
+    refs: u32,
 }
 
 impl Surface {
@@ -33,12 +69,55 @@ impl Surface {
             width: opts.width,
             height: opts.height,
             palette: 0,
+            bytes_per_pixel: machine.state.ddraw.mode.bytes_per_pixel,
+            primary: opts.primary,
+            caps: DDSCAPS::empty(),
             pixels: 0,
             attached: 0,
+            lock: None,
+            src_key: None,
+            dst_key: None,
+            clipper: 0,
+            dc: None,
+            refs: 1,
+        }
+    }
+
+    /// Bytes per row of the pixel buffer, padded to a multiple of 4 as on real hardware.
+    pub fn pitch(&self) -> u32 {
+        pitch(self.width, self.bytes_per_pixel)
+    }
+
+    /// Bytes of video memory the surface takes up, if it was placed there.
+    fn video_bytes(&self) -> u32 {
+        if self.caps.contains(DDSCAPS::VIDEOMEMORY) {
+            self.pitch() * self.height
+        } else {
+            0
         }
     }
 
-    pub fn create(machine: &mut Machine, desc: &DDSURFACEDESC2) -> Vec<Surface> {
+    /// Get the x86 address of the pixel buffer, allocating it on first use.
+    fn pixels(&mut self, mem: memory::Mem, heap: &mut Heap) -> u32 {
+        if self.pixels == 0 {
+            self.pixels = heap.alloc(mem, self.pitch() * self.height);
+        }
+        self.pixels
+    }
+
+    fn pixel_buf(&mut self, mem: memory::Mem, heap: &mut Heap) -> blit::PixelBuf {
+        blit::PixelBuf {
+            addr: self.pixels(mem, heap),
+            pitch: self.pitch(),
+            bytes_per_pixel: self.bytes_per_pixel,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Create the surfaces described by desc, placing them in video memory if they
+    /// fit, as described in "Video memory" below.
+    pub fn create(machine: &mut Machine, desc: &DDSURFACEDESC2) -> Result<Vec<Surface>, u32> {
         assert!(std::mem::size_of::<DDSURFACEDESC2>() == desc.dwSize as usize);
 
         let mut surfaces = Vec::new();
@@ -51,56 +130,840 @@ impl Surface {
             opts.height = desc.dwHeight;
         }
 
-        if let Some(caps) = desc.caps() {
-            if caps.dwCaps.contains(DDSCAPS::PRIMARYSURFACE) {
-                opts.primary = true;
-            }
+        let caps = desc.caps().map(|caps| caps.dwCaps).unwrap_or_default();
+        if caps.contains(DDSCAPS::PRIMARYSURFACE) {
+            opts.primary = true;
         }
 
         if opts.width == 0 || opts.height == 0 {
-            // Take width/height from window dimensions
-            if let Some(wnd) = machine.state.user32.windows.get(machine.state.ddraw.hwnd) {
+            // Take width/height from the display mode when we own the display,
+            // and otherwise from window dimensions.
+            if machine.state.ddraw.exclusive {
+                opts.width = machine.state.ddraw.mode.width;
+                opts.height = machine.state.ddraw.mode.height;
+            } else if let Some(wnd) = machine.state.user32.windows.get(machine.state.ddraw.hwnd) {
                 opts.width = wnd.width;
                 opts.height = wnd.height;
             }
         }
 
-        surfaces.push(Surface::new(machine, &opts));
+        if opts.primary {
+            if let Some(wnd) = machine
+                .state
+                .user32
+                .windows
+                .get_mut(machine.state.ddraw.hwnd)
+            {
+                wnd.ddraw = true;
+            }
+        }
+
+        let count = desc.back_buffer_count().unwrap_or(0);
+        let size =
+            pitch(opts.width, machine.state.ddraw.mode.bytes_per_pixel) * opts.height * (1 + count);
+        let placement = if caps.contains(DDSCAPS::SYSTEMMEMORY) {
+            DDSCAPS::SYSTEMMEMORY
+        } else if size <= machine.state.ddraw.video_memory_free() {
+            DDSCAPS::VIDEOMEMORY | DDSCAPS::LOCALVIDMEM
+        } else if caps.contains(DDSCAPS::VIDEOMEMORY) || opts.primary {
+            log::warn!("CreateSurface: out of video memory for {size} bytes");
+            return Err(DDERR_OUTOFVIDEOMEMORY);
+        } else {
+            DDSCAPS::SYSTEMMEMORY
+        };
+        let caps = caps & !(DDSCAPS::SYSTEMMEMORY | DDSCAPS::VIDEOMEMORY) | placement;
+
+        let mut surface = Surface::new(machine, &opts);
+        surface.caps = caps;
+        if desc.dwFlags.contains(DDSD::CKSRCBLT) {
+            surface.src_key = Some(desc.ddckCKSrcBlt);
+        }
+        if desc.dwFlags.contains(DDSD::CKDESTBLT) {
+            surface.dst_key = Some(desc.ddckCKDestBlt);
+        }
+        surfaces.push(surface);
 
         if let Some(count) = desc.back_buffer_count() {
+            // A flip chain: this surface is the front buffer, followed by the back buffers.
+            let chain_caps = caps & (DDSCAPS::FLIP | DDSCAPS::COMPLEX) | placement;
+            surfaces[0].caps |= DDSCAPS::FRONTBUFFER;
             opts.primary = false;
-            for _ in 0..count {
-                surfaces.push(Surface::new(machine, &opts));
+            for i in 0..count {
+                let mut surface = Surface::new(machine, &opts);
+                surface.caps = chain_caps;
+                if i == 0 {
+                    surface.caps |= DDSCAPS::BACKBUFFER;
+                }
+                surfaces.push(surface);
+            }
+        }
+
+        Ok(surfaces)
+    }
+
+    /// Convert the surface's x86-side pixel buffer to RGBA, if it has one.
+    fn to_rgba(&self, mem: memory::Mem, ddraw: &State) -> Option<Vec<[u8; 4]>> {
+        if self.pixels == 0 {
+            return None;
+        }
+        let pitch = self.pitch() as usize;
+        let row_len = (self.width * self.bytes_per_pixel) as usize;
+        let buf = mem.view_n::<u8>(self.pixels, pitch as u32 * self.height);
+        let rows = buf.chunks_exact(pitch).map(|row| &row[..row_len]);
+        let mut pixels = Vec::with_capacity((self.width * self.height) as usize);
+        match self.bytes_per_pixel {
+            1 => {
+                let palette = ddraw.palettes.get(&ddraw.display_palette)?;
+                for row in rows {
+                    pixels.extend(row.iter().map(|&i| match palette.get(i as usize) {
+                        Some(p) => [p.peRed, p.peGreen, p.peBlue, 255],
+                        None => [0, 0, 0, 255],
+                    }));
+                }
+            }
+            2 => {
+                // RGB565, matching DDPIXELFORMAT::for_bpp.
+                for row in rows {
+                    pixels.extend(row.chunks_exact(2).map(|p| {
+                        let p = u16::from_le_bytes([p[0], p[1]]);
+                        let r = (p >> 11) as u8 & 0x1F;
+                        let g = (p >> 5) as u8 & 0x3F;
+                        let b = p as u8 & 0x1F;
+                        [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2, 255]
+                    }));
+                }
             }
+            3 | 4 => {
+                // Little-endian 0x00RRGGBB, i.e. BGR(X) in memory.
+                let bpp = self.bytes_per_pixel as usize;
+                for row in rows {
+                    pixels.extend(row.chunks_exact(bpp).map(|p| [p[2], p[1], p[0], 255]));
+                }
+            }
+            bpp => todo!("pixels for {bpp}bpp"),
+        }
+        Some(pixels)
+    }
+}
+
+/// Bytes per row of a surface's pixel buffer, padded to a multiple of 4 as on real
+/// hardware.
+fn pitch(width: u32, bytes_per_pixel: u32) -> u32 {
+    (width * bytes_per_pixel + 3) & !3
+}
+
+/*
+
+## Video memory
+
+Surfaces all live in the same (x86-side) memory, but games make decisions
+based on how much video memory is reported, and some cope with running out of
+it by falling back to system memory.  So surfaces are accounted for as if on a
+card with State::video_memory of it: a surface goes into video memory if it
+fits, and otherwise into system memory, unless it asked for VIDEOMEMORY (or is
+the primary surface), in which case CreateSurface fails.  Releasing a surface's
+last reference returns its memory, so games that recreate their surfaces (e.g.
+on level changes) don't leak the pool away.  There's no AGP (nonlocal) memory.
+
+*/
+
+/// Copy a surface's x86-side pixels to its host surface.
+fn upload(machine: &mut Machine, addr: u32) {
+    let ddraw = &machine.state.ddraw;
+    let surf = ddraw.surfaces.get(&addr).unwrap();
+    let pixels = surf.to_rgba(machine.emu.memory.mem(), ddraw);
+    let surf = machine.state.ddraw.surfaces.get_mut(&addr).unwrap();
+    if let Some(pixels) = pixels {
+        surf.host.write_pixels(&pixels);
+    }
+}
+
+/// After a snapshot is restored, recreate the host surfaces with the x86-side pixels
+/// they held, and give the display back to a window that had it exclusively.
+/// The window itself was recreated by user32::restore.
+pub fn restore(machine: &mut Machine) {
+    let ddraw = &mut machine.state.ddraw;
+    if ddraw.exclusive {
+        if let Some(window) = machine.state.user32.windows.get_mut(ddraw.hwnd) {
+            window.host.set_fullscreen(true);
+        }
+    }
+    let addrs: Vec<u32> = ddraw.surfaces.iter().map(|(&addr, _)| addr).collect();
+    for addr in addrs {
+        let surf = &machine.state.ddraw.surfaces[&addr];
+        let host = machine.host.create_surface(&SurfaceOptions {
+            width: surf.width,
+            height: surf.height,
+            primary: surf.primary,
+        });
+        machine.state.ddraw.surfaces.get_mut(&addr).unwrap().host = host;
+        upload(machine, addr);
+        let surf = machine.state.ddraw.surfaces.get_mut(&addr).unwrap();
+        if surf.primary {
+            surf.host.show();
+        }
+    }
+}
+
+/// Copy a surface's x86-side pixels to its host surface, showing it if it's the primary.
+fn flush(machine: &mut Machine, addr: u32) {
+    upload(machine, addr);
+    if machine.state.ddraw.surfaces[&addr].primary {
+        present(machine, addr, false);
+    }
+}
+
+/// Replace a surface's contents with RGBA pixels, e.g. from GDI drawing, converting
+/// them to the surface's format.
+fn write_rgba(machine: &mut Machine, addr: u32, rgba: &[[u8; 4]]) {
+    let mem = machine.emu.memory.mem();
+    let ddraw = &mut machine.state.ddraw;
+    let surf = ddraw.surfaces.get_mut(&addr).unwrap();
+    let buf = surf.pixel_buf(mem, &mut ddraw.heap);
+    let palette = ddraw.palettes.get(&ddraw.display_palette);
+    let bpp = buf.bytes_per_pixel as usize;
+    let bytes = mem
+        .sub(buf.addr, buf.pitch * buf.height)
+        .as_mut_slice_todo();
+    for (row, src) in bytes
+        .chunks_exact_mut(buf.pitch as usize)
+        .zip(rgba.chunks_exact(buf.width as usize))
+    {
+        for (dst, &[r, g, b, _]) in row.chunks_exact_mut(bpp).zip(src) {
+            match bpp {
+                1 => {
+                    // Nearest palette entry.
+                    let Some(palette) = palette else { break };
+                    let dist = |p: &PALETTEENTRY| {
+                        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+                        d(p.peRed, r) + d(p.peGreen, g) + d(p.peBlue, b)
+                    };
+                    let (index, _) = palette
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, p)| dist(p))
+                        .unwrap();
+                    dst[0] = index as u8;
+                }
+                2 => {
+                    let p = (r as u16 >> 3) << 11 | (g as u16 >> 2) << 5 | (b as u16 >> 3);
+                    dst.copy_from_slice(&p.to_le_bytes());
+                }
+                _ => dst[..3].copy_from_slice(&[b, g, r]),
+            }
+        }
+    }
+    flush(machine, addr);
+}
+
+/// Create a GDI DC drawing onto a surface, for GetDC.
+/// Our GDI bitmaps are RGBA, so rather than aliasing the surface memory the DC's
+/// bitmap holds a converted copy of the surface, which release_dc writes back.
+pub fn get_dc(machine: &mut Machine, addr: u32) -> Result<HDC, u32> {
+    let addr = machine.state.ddraw.surfaces.resolve(addr);
+    let ddraw = &machine.state.ddraw;
+    let surf = &ddraw.surfaces[&addr];
+    if surf.dc.is_some() {
+        return Err(DDERR_DCALREADYCREATED);
+    }
+    let (width, height) = (surf.width, surf.height);
+    let pixels = surf
+        .to_rgba(machine.emu.memory.mem(), ddraw)
+        .unwrap_or_else(|| vec![[0, 0, 0, 255]; (width * height) as usize]);
+    let bitmap = machine
+        .state
+        .gdi32
+        .objects
+        .add(Object::Bitmap(BitmapType::RGBA32(BitmapRGBA32 {
+            width,
+            height,
+            pixels: PixelData::Owned(pixels.into_boxed_slice()),
+        })));
+    let hdc = machine
+        .state
+        .gdi32
+        .dcs
+        .add(DC::new(DCTarget::Memory(bitmap)));
+    machine.state.ddraw.surfaces.get_mut(&addr).unwrap().dc = Some((hdc, bitmap));
+    Ok(hdc)
+}
+
+/// Finish drawing via a DC from get_dc, copying the drawing into the surface.
+pub fn release_dc(machine: &mut Machine, addr: u32, hdc: HDC) -> u32 {
+    let addr = machine.state.ddraw.surfaces.resolve(addr);
+    let surf = machine.state.ddraw.surfaces.get_mut(&addr).unwrap();
+    let bitmap = match surf.dc {
+        Some((dc, bitmap)) if dc.to_raw() == hdc.to_raw() => bitmap,
+        _ => return DDERR_INVALIDPARAMS,
+    };
+    surf.dc = None;
+    let gdi32 = &mut machine.state.gdi32;
+    gdi32.dcs.remove(hdc);
+    let pixels = match gdi32.objects.remove(bitmap) {
+        Some(Object::Bitmap(BitmapType::RGBA32(bmp))) => {
+            bmp.pixels_slice(machine.emu.memory.mem()).to_vec()
         }
+        _ => unreachable!(),
+    };
+    write_rgba(machine, addr, &pixels);
+    DD_OK
+}
+
+/// Create the surfaces described by desc, linking any back buffers via `attached`.
+/// `new` allocates the x86-side COM object for each surface.
+/// Returns the address of the first (front) surface.
+fn create_surfaces(
+    machine: &mut Machine,
+    desc: &DDSURFACEDESC2,
+    new: fn(&mut Machine) -> u32,
+) -> Result<u32, u32> {
+    let surfaces = Surface::create(machine, desc)?;
+    let mut prev = 0;
+    for mut surface in surfaces.into_iter().rev() {
+        let ptr = new(machine);
+        surface.attached = prev;
+        machine.state.ddraw.surfaces.insert(ptr, surface);
+        prev = ptr;
+    }
+    Ok(prev)
+}
+
+/// Surfaces, by the address of their COM object.  A surface reached as another
+/// interface version through QueryInterface gets another object, an alias for the
+/// one it was created as.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct Surfaces {
+    surfaces: HashMap<u32, Surface>,
+    aliases: HashMap<u32, u32>,
+}
+
+impl Surfaces {
+    /// Map an alias to the object the surface was created as.
+    fn resolve(&self, addr: u32) -> u32 {
+        *self.aliases.get(&addr).unwrap_or(&addr)
+    }
+
+    pub fn get(&self, addr: &u32) -> Option<&Surface> {
+        self.surfaces.get(&self.resolve(*addr))
+    }
+
+    pub fn get_mut(&mut self, addr: &u32) -> Option<&mut Surface> {
+        let addr = self.resolve(*addr);
+        self.surfaces.get_mut(&addr)
+    }
 
-        surfaces
+    fn insert(&mut self, addr: u32, surface: Surface) {
+        self.surfaces.insert(addr, surface);
+    }
+
+    fn remove(&mut self, addr: &u32) -> Option<Surface> {
+        self.surfaces.remove(addr)
+    }
+
+    /// Iterate surfaces by the address they were created as.
+    pub fn iter(&self) -> impl Iterator<Item = (&u32, &Surface)> {
+        self.surfaces.iter()
+    }
+}
+
+impl std::ops::Index<&u32> for Surfaces {
+    type Output = Surface;
+
+    fn index(&self, addr: &u32) -> &Surface {
+        self.get(addr).unwrap()
+    }
+}
+
+/// Create a COM object with the given vtable.  Our objects carry no state of their
+/// own beyond that; it's keyed by their address.
+fn new_object(machine: &mut Machine, vtable: u32) -> u32 {
+    let addr = machine.state.ddraw.heap.alloc(machine.emu.memory.mem(), 4);
+    machine.mem().put::<u32>(addr, vtable);
+    addr
+}
+
+/// Get the surface `addr` as the interface with the given vtable, creating an alias
+/// object if it wasn't created as that interface.
+fn surface_interface(machine: &mut Machine, addr: u32, vtable: u32) -> u32 {
+    let addr = machine.state.ddraw.surfaces.resolve(addr);
+    let mem = machine.mem();
+    if mem.get_pod::<u32>(addr) == vtable {
+        return addr;
+    }
+    let ddraw = &machine.state.ddraw;
+    if let Some((&alias, _)) = ddraw
+        .surfaces
+        .aliases
+        .iter()
+        .find(|&(&alias, &target)| target == addr && mem.get_pod::<u32>(alias) == vtable)
+    {
+        return alias;
     }
+    let alias = new_object(machine, vtable);
+    machine.state.ddraw.surfaces.aliases.insert(alias, addr);
+    alias
 }
 
+/// Find the surface attached to `this` matching `caps`, as the same interface version
+/// as `this`.
+fn get_attached(machine: &mut Machine, this: u32, caps: DDSCAPS) -> Option<u32> {
+    let addr = find_attached(&machine.state.ddraw, this, caps)?;
+    add_ref(machine, addr);
+    let vtable = machine.mem().get_pod::<u32>(this);
+    Some(surface_interface(machine, addr, vtable))
+}
+
+/// AddRef for surfaces.
+fn add_ref(machine: &mut Machine, this: u32) -> u32 {
+    let Some(surf) = machine.state.ddraw.surfaces.get_mut(&this) else {
+        return 0;
+    };
+    surf.refs += 1;
+    surf.refs
+}
+
+/// Release for surfaces.  Dropping the last reference destroys the surface along
+/// with the back buffers created with it, freeing their pixels and video memory.
+fn release(machine: &mut Machine, this: u32) -> u32 {
+    let Some(surf) = machine.state.ddraw.surfaces.get_mut(&this) else {
+        log::warn!("{this:x}->Release(): unknown surface");
+        return 0;
+    };
+    surf.refs = surf.refs.saturating_sub(1);
+    if surf.refs > 0 {
+        return surf.refs;
+    }
+
+    let this = machine.state.ddraw.surfaces.resolve(this);
+    let mem = machine.emu.memory.mem();
+    let ddraw = &mut machine.state.ddraw;
+    let mut addr = this;
+    while addr != 0 {
+        let Some(surf) = ddraw.surfaces.remove(&addr) else {
+            break;
+        };
+        if surf.pixels != 0 {
+            ddraw.heap.free(mem, surf.pixels);
+        }
+        if let Some((hdc, bitmap)) = surf.dc {
+            machine.state.gdi32.dcs.remove(hdc);
+            machine.state.gdi32.objects.remove(bitmap);
+        }
+        let aliases: Vec<u32> = ddraw
+            .surfaces
+            .aliases
+            .iter()
+            .filter(|&(_, &target)| target == addr)
+            .map(|(&alias, _)| alias)
+            .collect();
+        for alias in aliases {
+            ddraw.surfaces.aliases.remove(&alias);
+            ddraw.heap.free(mem, alias);
+        }
+        ddraw.heap.free(mem, addr);
+        addr = surf.attached;
+    }
+    0
+}
+
+/// The vtable of the IDirectDraw version named by an IID.
+fn ddraw_vtable(ddraw: &State, iid: &[u8]) -> Option<u32> {
+    [
+        (ddraw1::IID_IDirectDraw, ddraw.vtable_IDirectDraw),
+        (ddraw2::IID_IDirectDraw2, ddraw.vtable_IDirectDraw2),
+        (ddraw4::IID_IDirectDraw4, ddraw.vtable_IDirectDraw4),
+        (ddraw7::IID_IDirectDraw7, ddraw.vtable_IDirectDraw7),
+    ]
+    .into_iter()
+    .find(|(id, _)| id == iid)
+    .map(|(_, vtable)| vtable)
+}
+
+/// The vtable of the IDirectDrawSurface version named by an IID.
+fn surface_vtable(ddraw: &State, iid: &[u8]) -> Option<u32> {
+    [
+        (
+            ddraw1::IID_IDirectDrawSurface,
+            ddraw.vtable_IDirectDrawSurface,
+        ),
+        (
+            ddraw2::IID_IDirectDrawSurface2,
+            ddraw.vtable_IDirectDrawSurface2,
+        ),
+        (
+            ddraw2::IID_IDirectDrawSurface3,
+            ddraw.vtable_IDirectDrawSurface3,
+        ),
+        (
+            ddraw4::IID_IDirectDrawSurface4,
+            ddraw.vtable_IDirectDrawSurface4,
+        ),
+        (
+            ddraw7::IID_IDirectDrawSurface7,
+            ddraw.vtable_IDirectDrawSurface7,
+        ),
+    ]
+    .into_iter()
+    .find(|(id, _)| id == iid)
+    .map(|(_, vtable)| vtable)
+}
+
+/// QueryInterface for DirectDraw objects and surfaces, which are each available as
+/// all versions of their interface.
+fn query_interface(machine: &mut Machine, this: u32, riid: u32, ppvObject: u32) -> u32 {
+    let mem = machine.mem();
+    let iid = mem.sub(riid, 16).as_slice_todo();
+    let ddraw = &machine.state.ddraw;
+    let is_surface = ddraw.surfaces.get(&this).is_some();
+    let vtable = if iid == IID_IUnknown {
+        Some(mem.get_pod::<u32>(this))
+    } else if !is_surface && iid == d3d7::IID_IDirect3D7 {
+        Some(ddraw.vtable_IDirect3D7)
+    } else if is_surface {
+        surface_vtable(ddraw, iid)
+    } else {
+        ddraw_vtable(ddraw, iid)
+    };
+    let Some(vtable) = vtable else {
+        log::warn!("QueryInterface({this:x}): unknown IID {iid:x?}");
+        mem.put::<u32>(ppvObject, 0);
+        return E_NOINTERFACE;
+    };
+    let obj = if is_surface {
+        add_ref(machine, this);
+        surface_interface(machine, this, vtable)
+    } else {
+        new_object(machine, vtable)
+    };
+    machine.mem().put::<u32>(ppvObject, obj);
+    DD_OK
+}
+
+/// Find the surface attached to `this` matching `caps`, searching down the flip chain.
+fn find_attached(ddraw: &State, this: u32, caps: DDSCAPS) -> Option<u32> {
+    let this = ddraw.surfaces.resolve(this);
+    let mut addr = ddraw.surfaces.get(&this)?.attached;
+    while addr != 0 && addr != this {
+        let surf = ddraw.surfaces.get(&addr)?;
+        if surf.caps.contains(caps) {
+            return Some(addr);
+        }
+        addr = surf.attached;
+    }
+    None
+}
+
+/// Flip a chain: each surface takes on the contents of the next one, the last one
+/// taking the front buffer's, as on hardware where only the buffer pointers move.
+/// If `target` is nonzero, flip to that surface directly rather than the next one.
+fn flip_chain(ddraw: &mut State, this: u32, target: u32) {
+    let this = ddraw.surfaces.resolve(this);
+    let mut chain = vec![this];
+    if target != 0 {
+        chain.push(ddraw.surfaces.resolve(target));
+    } else {
+        let mut addr = ddraw.surfaces[&this].attached;
+        while addr != 0 && addr != this {
+            chain.push(addr);
+            addr = ddraw.surfaces[&addr].attached;
+        }
+    }
+    let mut surfaces: Vec<Surface> = chain
+        .iter()
+        .map(|addr| ddraw.surfaces.remove(addr).unwrap())
+        .collect();
+    for i in 0..surfaces.len() - 1 {
+        let (a, b) = surfaces.split_at_mut(i + 1);
+        let (a, b) = (&mut a[i], &mut b[0]);
+        std::mem::swap(&mut a.host, &mut b.host);
+        std::mem::swap(&mut a.pixels, &mut b.pixels);
+    }
+    for (addr, surface) in chain.into_iter().zip(surfaces) {
+        ddraw.surfaces.insert(addr, surface);
+    }
+}
+
+/*
+
+## GDI interop
+
+Games commonly draw some things (e.g. menus) with GDI into the same window that
+DirectDraw presents into.  On Windows both write into the same framebuffer, but
+here the window's GDI pixels and the DirectDraw surfaces are separate host
+surfaces, so showing each of them as they are written flickers between the two.
+
+Instead, once a window has a DirectDraw primary surface, GDI drawing into it is
+not shown directly.  The window tracks the region GDI has drawn into, and that
+region is composited on top of the DirectDraw output whenever a surface is
+presented.  A Flip replaces the whole frame, so it also hands the region back
+to DirectDraw; GDI drawing that should stay visible must be redrawn each frame,
+as it would on real hardware.
+
+*/
+
+/// Show a surface, compositing any GDI drawing into the DirectDraw window on top.
+/// If `replaced` is true, the presented frame replaces everything GDI has drawn.
+fn present(machine: &mut Machine, surface: u32, replaced: bool) {
+    let now = machine.host.time();
+    crate::capture::present(now, |indexed| capture_image(machine, surface, indexed));
+    let surf = machine.state.ddraw.surfaces.get_mut(&surface).unwrap();
+    if let Some(window) = machine
+        .state
+        .user32
+        .windows
+        .get_mut(machine.state.ddraw.hwnd)
+    {
+        if let (Some(region), Some(pixels)) = (&window.gdi_region, &window.pixels) {
+            // Clip to both surfaces.
+            let right = std::cmp::min(region.right as u32, std::cmp::min(surf.width, window.width));
+            let bottom = std::cmp::min(
+                region.bottom as u32,
+                std::cmp::min(surf.height, window.height),
+            );
+            let (x, y) = (region.left as u32, region.top as u32);
+            if x < right && y < bottom {
+                surf.host
+                    .bit_blt(x, y, pixels.surface.as_ref(), x, y, right - x, bottom - y);
+            }
+        }
+        if replaced {
+            window.gdi_region = None;
+        }
+    }
+    surf.host.show();
+    machine.presented();
+}
+
+/// Render what presenting a surface shows, for capture: its pixels, with the GDI
+/// drawing that present composites on top.
+fn capture_image(machine: &Machine, surface: u32, indexed: bool) -> Option<crate::capture::Image> {
+    let mem = machine.emu.memory.mem();
+    let ddraw = &machine.state.ddraw;
+    let surf = ddraw.surfaces.get(&surface)?;
+    let mut pixels = surf.to_rgba(mem, ddraw)?;
+    if let Some(window) = machine.state.user32.windows.get(ddraw.hwnd) {
+        if let (Some(region), Some(gdi)) = (&window.gdi_region, &window.pixels) {
+            let src = gdi.bitmap.pixels_slice(mem);
+            let right = std::cmp::min(
+                region.right as u32,
+                std::cmp::min(surf.width, gdi.bitmap.width),
+            );
+            let bottom = std::cmp::min(
+                region.bottom as u32,
+                std::cmp::min(surf.height, gdi.bitmap.height),
+            );
+            for y in region.top as u32..bottom {
+                for x in region.left as u32..right {
+                    pixels[(y * surf.width + x) as usize] =
+                        src[(y * gdi.bitmap.width + x) as usize];
+                }
+            }
+        }
+    }
+    let indexed = match ddraw.palettes.get(&ddraw.display_palette) {
+        Some(palette) if indexed && surf.bytes_per_pixel == 1 => {
+            let buf = mem.view_n::<u8>(surf.pixels, surf.pitch() * surf.height);
+            Some(crate::capture::Indexed {
+                pixels: buf
+                    .chunks_exact(surf.pitch() as usize)
+                    .flat_map(|row| &row[..surf.width as usize])
+                    .copied()
+                    .collect(),
+                palette: palette
+                    .iter()
+                    .map(|p| [p.peRed, p.peGreen, p.peBlue])
+                    .collect(),
+            })
+        }
+        _ => None,
+    };
+    Some(crate::capture::Image {
+        width: surf.width,
+        height: surf.height,
+        pixels,
+        indexed,
+    })
+}
+
+/// Gather the state of all surfaces for a frame dump.
+pub fn dump_surfaces(machine: &Machine) -> Vec<crate::framedump::SurfaceDump> {
+    let ddraw = &machine.state.ddraw;
+    let mut surfaces: Vec<_> = ddraw
+        .surfaces
+        .iter()
+        .map(|(&addr, surf)| crate::framedump::SurfaceDump {
+            kind: "ddraw",
+            addr,
+            width: surf.width,
+            height: surf.height,
+            attached: surf.attached,
+            pixels: if surf.pixels == 0 {
+                // Never locked or drawn to, so still blank.
+                Some(vec![[0, 0, 0, 255]; (surf.width * surf.height) as usize])
+            } else {
+                surf.to_rgba(machine.emu.memory.mem(), ddraw)
+            },
+        })
+        .collect();
+    surfaces.sort_by_key(|s| s.addr);
+    surfaces
+}
+
+/// A display mode, as set by SetDisplayMode.
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub struct DisplayMode {
+    pub width: u32,
+    pub height: u32,
+    pub bytes_per_pixel: u32,
+}
+
+impl DisplayMode {
+    /// The mode reported before any SetDisplayMode, and restored by RestoreDisplayMode.
+    pub const DESKTOP: DisplayMode = DisplayMode {
+        width: 640,
+        height: 480,
+        bytes_per_pixel: 4,
+    };
+
+    /// The modes EnumDisplayModes offers, by depth and then size.
+    fn all() -> impl Iterator<Item = DisplayMode> {
+        const SIZES: [(u32, u32); 6] = [
+            (320, 200),
+            (320, 240),
+            (640, 400),
+            (640, 480),
+            (800, 600),
+            (1024, 768),
+        ];
+        [1, 2, 4].into_iter().flat_map(|bytes_per_pixel| {
+            SIZES.into_iter().map(move |(width, height)| DisplayMode {
+                width,
+                height,
+                bytes_per_pixel,
+            })
+        })
+    }
+
+    /// Describe the mode, as returned by GetDisplayMode.
+    fn describe(&self, desc: &mut DDSURFACEDESC2) {
+        desc.clear_struct();
+        desc.dwSize = std::mem::size_of::<DDSURFACEDESC2>() as u32;
+        desc.dwFlags =
+            DDSD::WIDTH | DDSD::HEIGHT | DDSD::PITCH | DDSD::PIXELFORMAT | DDSD::REFRESHRATE;
+        desc.dwWidth = self.width;
+        desc.dwHeight = self.height;
+        desc.lPitch_dwLinearSize = self.width * self.bytes_per_pixel;
+        desc.dwMipMapCount_dwRefreshRate_dwSrcVBHandle = 60;
+        desc.ddpfPixelFormat = DDPIXELFORMAT::for_bpp(self.bytes_per_pixel);
+    }
+
+    /// Hardware capabilities, as returned by GetCaps.  Palettes are only offered
+    /// in 8-bit modes.
+    fn caps(&self) -> DDCAPS {
+        let mut caps = DDCAPS::default();
+        caps.dwSize = std::mem::size_of::<DDCAPS>() as u32;
+        caps.dwCaps =
+            DDCAPS_BLT | DDCAPS_BLTSTRETCH | DDCAPS_BLTCOLORFILL | DDCAPS_COLORKEY | DDCAPS_CANCLIP;
+        caps.dwCKeyCaps = DDCKEYCAPS_SRCBLT | DDCKEYCAPS_DESTBLT;
+        if self.bytes_per_pixel == 1 {
+            caps.dwCaps |= DDCAPS_PALETTE;
+            caps.dwPalCaps = (DDPCAPS::_8BIT | DDPCAPS::PRIMARYSURFACE).bits();
+        }
+        caps.ddsCaps.dwCaps = DDSCAPS::BACKBUFFER
+            | DDSCAPS::COMPLEX
+            | DDSCAPS::FLIP
+            | DDSCAPS::FRONTBUFFER
+            | DDSCAPS::OFFSCREENPLAIN
+            | DDSCAPS::PALETTE
+            | DDSCAPS::PRIMARYSURFACE
+            | DDSCAPS::SYSTEMMEMORY
+            | DDSCAPS::VIDEOMEMORY;
+        caps.ddsOldCaps = caps.ddsCaps.dwCaps;
+        caps
+    }
+}
+
+/// Video memory we claim to have, unless configured otherwise.
+pub const VIDEO_MEMORY: u32 = 8 << 20;
+
+/// Write GetCaps output to a caller's DDCAPS, of whatever size it says it is.
+fn write_caps(machine: &mut Machine, addr: u32) {
+    if addr == 0 {
+        return;
+    }
+    let mem = machine.emu.memory.mem();
+    let size = std::cmp::min(
+        mem.get_pod::<u32>(addr),
+        std::mem::size_of::<DDCAPS>() as u32,
+    );
+    let ddraw = &machine.state.ddraw;
+    let mut caps = ddraw.mode.caps();
+    caps.dwVidMemTotal = ddraw.video_memory;
+    caps.dwVidMemFree = ddraw.video_memory_free();
+    let bytes =
+        unsafe { std::slice::from_raw_parts(&caps as *const DDCAPS as *const u8, size as usize) };
+    mem.sub(addr, size)
+        .as_mut_slice_todo()
+        .copy_from_slice(bytes);
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct State {
     heap: Heap,
     vtable_IDirectDraw: u32,
     vtable_IDirectDrawSurface: u32,
+    vtable_IDirectDraw2: u32,
+    vtable_IDirectDrawSurface2: u32,
+    vtable_IDirectDrawSurface3: u32,
+    vtable_IDirectDraw4: u32,
+    vtable_IDirectDrawSurface4: u32,
     vtable_IDirectDraw7: u32,
     vtable_IDirectDrawSurface7: u32,
     vtable_IDirectDrawPalette: u32,
+    vtable_IDirectDrawClipper: u32,
+    vtable_IDirect3D7: u32,
+    vtable_IDirect3DDevice7: u32,
+
+    /// References to IDirectDraw objects.
+    refs: com::RefCounts,
 
     // TODO: this is per-IDirectDraw state.
     hwnd: HWND,
-    pub surfaces: HashMap<u32, Surface>,
+    pub surfaces: Surfaces,
 
-    bytes_per_pixel: u32,
+    mode: DisplayMode,
+    /// Set by SetCooperativeLevel(DDSCL_EXCLUSIVE | DDSCL_FULLSCREEN): the window
+    /// takes over the host display.
+    exclusive: bool,
 
     palettes: HashMap<u32, Box<[PALETTEENTRY]>>,
-    /// XXX monolife attaches palette only to back surface, then flips; we need to rearrange
-    /// how surface flipping works for the palettes to work out, so this is hacked for now.
-    palette_hack: u32,
+    clippers: HashMap<u32, Clipper>,
+    /// Direct3D devices, by the address of their COM object.
+    devices: HashMap<u32, d3d7::Device>,
+    /// The palette used to show 8-bit surfaces.  Hardware has a single palette for the
+    /// display, set via the primary surface or its flip chain (monolife sets it only
+    /// on the back buffer).
+    display_palette: u32,
+
+    /// Host time of the last vsync-paced Flip.
+    last_flip: u32,
+
+    /// Size of the video memory surfaces are placed in, which can be configured
+    /// before DirectDraw is initialized.  Like gpu, this is host configuration and
+    /// not part of snapshots.
+    #[serde(skip)]
+    pub video_memory: u32,
+
+    /// The graphics card reported to programs, which can likewise be configured.
+    #[serde(skip)]
+    pub gpu: Gpu,
 }
 
 impl State {
     pub fn new_init(machine: &mut Machine) -> Self {
         let mut ddraw = State::default();
+        ddraw.video_memory = machine.state.ddraw.video_memory;
+        ddraw.gpu = machine.state.ddraw.gpu.clone();
         ddraw.heap = machine.state.kernel32.new_private_heap(
             &mut machine.emu.memory,
             4 << 20,
@@ -109,12 +972,26 @@ impl State {
 
         ddraw.vtable_IDirectDraw = ddraw1::IDirectDraw::vtable(&mut ddraw, machine);
         ddraw.vtable_IDirectDrawSurface = ddraw1::IDirectDrawSurface::vtable(&mut ddraw, machine);
+        ddraw.vtable_IDirectDraw2 = ddraw2::IDirectDraw2::vtable(&mut ddraw, machine);
+        ddraw.vtable_IDirectDrawSurface2 = ddraw2::IDirectDrawSurface2::vtable(&mut ddraw, machine);
+        ddraw.vtable_IDirectDrawSurface3 = ddraw2::IDirectDrawSurface3::vtable(&mut ddraw, machine);
+        ddraw.vtable_IDirectDraw4 = ddraw4::IDirectDraw4::vtable(&mut ddraw, machine);
+        ddraw.vtable_IDirectDrawSurface4 = ddraw4::IDirectDrawSurface4::vtable(&mut ddraw, machine);
         ddraw.vtable_IDirectDraw7 = ddraw7::IDirectDraw7::vtable(&mut ddraw, machine);
         ddraw.vtable_IDirectDrawSurface7 = ddraw7::IDirectDrawSurface7::vtable(&mut ddraw, machine);
         ddraw.vtable_IDirectDrawPalette = IDirectDrawPalette::vtable(&mut ddraw, machine);
+        ddraw.vtable_IDirectDrawClipper = IDirectDrawClipper::vtable(&mut ddraw, machine);
+        ddraw.vtable_IDirect3D7 = d3d7::IDirect3D7::vtable(&mut ddraw, machine);
+        ddraw.vtable_IDirect3DDevice7 = d3d7::IDirect3DDevice7::vtable(&mut ddraw, machine);
 
         ddraw
     }
+
+    /// Video memory not taken up by surfaces.
+    fn video_memory_free(&self) -> u32 {
+        let used: u32 = self.surfaces.iter().map(|(_, s)| s.video_bytes()).sum();
+        self.video_memory.saturating_sub(used)
+    }
 }
 
 impl Default for State {
@@ -123,21 +1000,49 @@ impl Default for State {
             heap: Heap::default(),
             vtable_IDirectDraw: 0,
             vtable_IDirectDrawSurface: 0,
+            vtable_IDirectDraw2: 0,
+            vtable_IDirectDrawSurface2: 0,
+            vtable_IDirectDrawSurface3: 0,
+            vtable_IDirectDraw4: 0,
+            vtable_IDirectDrawSurface4: 0,
             vtable_IDirectDraw7: 0,
             vtable_IDirectDrawSurface7: 0,
             vtable_IDirectDrawPalette: 0,
+            vtable_IDirectDrawClipper: 0,
+            vtable_IDirect3D7: 0,
+            vtable_IDirect3DDevice7: 0,
+            refs: com::RefCounts::default(),
             hwnd: HWND::null(),
-            surfaces: HashMap::new(),
-            bytes_per_pixel: 4,
+            surfaces: Surfaces::default(),
+            mode: DisplayMode::DESKTOP,
+            exclusive: false,
             palettes: HashMap::new(),
-            palette_hack: 0,
+            clippers: HashMap::new(),
+            devices: HashMap::new(),
+            display_palette: 0,
+            last_flip: 0,
+            video_memory: VIDEO_MEMORY,
+            gpu: Gpu::default(),
         }
     }
 }
 
 const DD_OK: u32 = 0;
+/// Returned by enumeration callbacks to end the enumeration, rather than the
+/// DDENUMRET_OK or D3DENUMRET_OK (both 1) that continue it.
+const DDENUMRET_CANCEL: u32 = 0;
+const D3DENUMRET_CANCEL: u32 = 0;
 // DD error codes are generated with this MAKE_HRESULT macro, maybe it doesn't matter too much.
+const DDERR_DCALREADYCREATED: u32 = 0x8876021C;
 const DDERR_GENERIC: u32 = 0x80004005;
+const E_NOINTERFACE: u32 = 0x80004002;
+const DDERR_INVALIDCAPS: u32 = 0x88760064;
+const DDERR_INVALIDMODE: u32 = 0x8876005A;
+const DDERR_INVALIDPARAMS: u32 = 0x80070057;
+const DDERR_NOCOLORKEY: u32 = 0x887600D7;
+const DDERR_NOTFOUND: u32 = 0x887600FF;
+const DDERR_NOTLOCKED: u32 = 0x88760248;
+const DDERR_OUTOFVIDEOMEMORY: u32 = 0x8876017C;
 
 #[win32_derive::shims_from_x86]
 mod IDirectDrawPalette {
@@ -148,7 +1053,7 @@ mod IDirectDrawPalette {
         AddRef todo,
         Release ok,
         GetCaps todo,
-        GetEntries todo,
+        GetEntries ok,
         Initialize todo,
         SetEntries ok,
     ];
@@ -167,6 +1072,26 @@ mod IDirectDrawPalette {
         0 // TODO: return refcount?
     }
 
+    #[win32_derive::dllexport]
+    fn GetEntries(
+        machine: &mut Machine,
+        this: u32,
+        unused: u32,
+        start: u32,
+        count: u32,
+        entries: u32,
+    ) -> u32 {
+        let palette = machine.state.ddraw.palettes.get(&this).unwrap();
+        let Some(src) = palette.get(start as usize..(start + count) as usize) else {
+            return DDERR_INVALIDPARAMS;
+        };
+        let mem = machine.emu.memory.mem();
+        for (i, entry) in src.iter().enumerate() {
+            mem.put::<PALETTEENTRY>(entries + (i as u32 * 4), *entry);
+        }
+        DD_OK
+    }
+
     #[win32_derive::dllexport]
     fn SetEntries(
         machine: &mut Machine,
@@ -183,11 +1108,115 @@ mod IDirectDrawPalette {
             .memory
             .mem()
             .view_n::<PALETTEENTRY>(entries, count);
-        palette[start as usize..][..count as usize].clone_from_slice(entries);
+        let Some(dst) = palette.get_mut(start as usize..(start + count) as usize) else {
+            return DDERR_INVALIDPARAMS;
+        };
+        dst.clone_from_slice(entries);
+
+        // Changing the display palette changes what's on screen, without a redraw.
+        // This is how palette animation (cycling colors) works.
+        if this == machine.state.ddraw.display_palette {
+            let primary = machine
+                .state
+                .ddraw
+                .surfaces
+                .iter()
+                .find(|(_, surf)| surf.primary)
+                .map(|(&addr, _)| addr);
+            if let Some(primary) = primary {
+                flush(machine, primary);
+            }
+        }
+        DD_OK
+    }
+}
+
+/// State behind an IDirectDrawClipper.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct Clipper {
+    hwnd: HWND,
+    /// Clip list from SetClipList, in surface coordinates.
+    rects: Vec<RECT>,
+}
+
+#[win32_derive::shims_from_x86]
+mod IDirectDrawClipper {
+    use super::*;
+    use memory::Extensions;
+
+    vtable![IDirectDrawClipper shims
+        QueryInterface todo,
+        AddRef todo,
+        Release ok,
+        GetClipList todo,
+        GetHWnd ok,
+        Initialize todo,
+        IsClipListChanged todo,
+        SetClipList ok,
+        SetHWnd ok,
+    ];
+
+    pub fn new(machine: &mut Machine) -> u32 {
+        let ddraw = &mut machine.state.ddraw;
+        let lpDirectDrawClipper = ddraw.heap.alloc(machine.emu.memory.mem(), 4);
+        let vtable = ddraw.vtable_IDirectDrawClipper;
+        ddraw
+            .clippers
+            .insert(lpDirectDrawClipper, Clipper::default());
+        machine.mem().put::<u32>(lpDirectDrawClipper, vtable);
+        lpDirectDrawClipper
+    }
+
+    #[win32_derive::dllexport]
+    fn Release(_machine: &mut Machine, this: u32) -> u32 {
+        log::warn!("{this:x}->Release()");
+        0 // TODO: return refcount?
+    }
+
+    #[win32_derive::dllexport]
+    fn GetHWnd(machine: &mut Machine, this: u32, lphWnd: Option<&mut HWND>) -> u32 {
+        *lphWnd.unwrap() = machine.state.ddraw.clippers[&this].hwnd;
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    fn SetClipList(machine: &mut Machine, this: u32, lpClipList: u32, flags: u32) -> u32 {
+        let mem = machine.emu.memory.mem();
+        let mut rects = Vec::new();
+        if lpClipList != 0 {
+            let header = mem.get_pod::<RGNDATAHEADER>(lpClipList);
+            let first = lpClipList + header.dwSize;
+            for i in 0..header.nCount {
+                rects.push(mem.get_pod::<RECT>(first + i * std::mem::size_of::<RECT>() as u32));
+            }
+        }
+        machine.state.ddraw.clippers.get_mut(&this).unwrap().rects = rects;
+        DD_OK
+    }
+
+    #[win32_derive::dllexport]
+    fn SetHWnd(machine: &mut Machine, this: u32, flags: u32, hWnd: HWND) -> u32 {
+        // Our primary surface is the size of the window's client area, so clipping to
+        // the window is the same as clipping to the surface.
+        machine.state.ddraw.clippers.get_mut(&this).unwrap().hwnd = hWnd;
         DD_OK
     }
 }
 
+#[win32_derive::dllexport]
+pub fn DirectDrawCreateClipper(
+    machine: &mut Machine,
+    dwFlags: u32,
+    lplpDDClipper: Option<&mut u32>,
+    pUnkOuter: u32,
+) -> u32 {
+    if machine.state.ddraw.heap.addr == 0 {
+        machine.state.ddraw = State::new_init(machine);
+    }
+    *lplpDDClipper.unwrap() = IDirectDrawClipper::new(machine);
+    DD_OK
+}
+
 #[win32_derive::dllexport]
 pub fn DirectDrawCreate(machine: &mut Machine, lpGuid: u32, lplpDD: u32, pUnkOuter: u32) -> u32 {
     DirectDrawCreateEx(machine, lpGuid, lplpDD, 0, pUnkOuter)
@@ -207,30 +1236,26 @@ pub fn DirectDrawCreateEx(
     if machine.state.ddraw.heap.addr == 0 {
         machine.state.ddraw = State::new_init(machine);
     }
-    let ddraw = &mut machine.state.ddraw;
+    let ddraw = &machine.state.ddraw;
 
-    if iid == 0 {
+    let vtable = if iid == 0 {
         // DirectDrawCreate
-        let lpDirectDraw = ddraw.heap.alloc(machine.emu.memory.mem(), 4);
-        let vtable = ddraw.vtable_IDirectDraw;
-        machine.mem().put::<u32>(lpDirectDraw, vtable);
-        machine.mem().put::<u32>(lplpDD, lpDirectDraw);
-        return DD_OK;
-    }
-
-    let iid_slice = machine.emu.memory.mem().sub(iid, 16).as_slice_todo();
-    if iid_slice == ddraw7::IID_IDirectDraw7 {
-        // Caller gives us:
-        //   pointer (lplpDD) that they want us to fill in to point to ->
-        //   [vtable, ...] (lpDirectDraw7), where vtable is pointer to ->
-        //   [fn1, fn2, ...] (vtable_IDirectDraw7)
-        let lpDirectDraw7 = ddraw.heap.alloc(machine.emu.memory.mem(), 4);
-        let vtable = ddraw.vtable_IDirectDraw7;
-        machine.mem().put::<u32>(lpDirectDraw7, vtable);
-        machine.mem().put::<u32>(lplpDD, lpDirectDraw7);
-        DD_OK
+        ddraw.vtable_IDirectDraw
     } else {
-        log::error!("DirectDrawCreateEx: unknown IID {iid_slice:x?}");
-        DDERR_GENERIC
-    }
+        let iid_slice = machine.emu.memory.mem().sub(iid, 16).as_slice_todo();
+        match ddraw_vtable(ddraw, iid_slice) {
+            Some(vtable) => vtable,
+            None => {
+                log::error!("DirectDrawCreateEx: unknown IID {iid_slice:x?}");
+                return DDERR_GENERIC;
+            }
+        }
+    };
+    // Caller gives us:
+    //   pointer (lplpDD) that they want us to fill in to point to ->
+    //   [vtable, ...] (lpDirectDraw), where vtable is pointer to ->
+    //   [fn1, fn2, ...] (e.g. vtable_IDirectDraw7)
+    let lpDirectDraw = new_object(machine, vtable);
+    machine.mem().put::<u32>(lplpDD, lpDirectDraw);
+    DD_OK
 }
@@ -1,6 +1,6 @@
 //! Types defined in the DirectDraw API.
 
-use crate::winapi::types::*;
+use crate::winapi::{serde_bitflags, types::*};
 use bitflags::bitflags;
 
 #[repr(C)]
@@ -45,6 +45,7 @@ bitflags! {
         const STANDARDVGAMODE = 0x40000000;
     }
 }
+serde_bitflags!(DDSCAPS);
 unsafe impl memory::Pod for DDSCAPS {}
 
 bitflags! {
@@ -112,6 +113,7 @@ bitflags! {
         const NODIRTYUPDATE = 0x00010000;
     }
 }
+serde_bitflags!(DDLOCK);
 impl TryFrom<u32> for DDLOCK {
     type Error = u32;
 
@@ -121,13 +123,67 @@ impl TryFrom<u32> for DDLOCK {
 }
 
 #[repr(C)]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub struct DDCOLORKEY {
-    dwColorSpaceLowValue: DWORD,
-    dwColorSpaceHighValue: DWORD,
+    pub dwColorSpaceLowValue: DWORD,
+    pub dwColorSpaceHighValue: DWORD,
 }
 unsafe impl memory::Pod for DDCOLORKEY {}
 
+impl DDCOLORKEY {
+    /// Whether a pixel value falls within the key's range.
+    /// Callers often leave the high value 0 to mean a single color.
+    pub fn matches(&self, value: u32) -> bool {
+        let high = std::cmp::max(self.dwColorSpaceLowValue, self.dwColorSpaceHighValue);
+        value >= self.dwColorSpaceLowValue && value <= high
+    }
+}
+
+bitflags! {
+    pub struct DDCKEY: u32 {
+        const COLORSPACE = 0x00000001;
+        const DESTBLT = 0x00000002;
+        const DESTOVERLAY = 0x00000004;
+        const SRCBLT = 0x00000008;
+        const SRCOVERLAY = 0x00000010;
+    }
+}
+impl TryFrom<u32> for DDCKEY {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        DDCKEY::from_bits(value).ok_or(value)
+    }
+}
+
+bitflags! {
+    pub struct DDBLTFAST: u32 {
+        const NOCOLORKEY = 0x00000000;
+        const SRCCOLORKEY = 0x00000001;
+        const DESTCOLORKEY = 0x00000002;
+        const WAIT = 0x00000010;
+        const DONOTWAIT = 0x00000020;
+    }
+}
+impl TryFrom<u32> for DDBLTFAST {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        DDBLTFAST::from_bits(value).ok_or(value)
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct RGNDATAHEADER {
+    pub dwSize: DWORD,
+    pub iType: DWORD,
+    pub nCount: DWORD,
+    pub nRgnSize: DWORD,
+    pub rcBound: RECT,
+}
+unsafe impl memory::Pod for RGNDATAHEADER {}
+
 #[repr(C)]
 pub struct DDSURFACEDESC {
     pub dwSize: DWORD,
@@ -236,7 +292,7 @@ impl DDSURFACEDESC {
             ddckCKDestBlt: Default::default(),
             ddckCKSrcOverlay: Default::default(),
             ddckCKSrcBlt: Default::default(),
-            ddpfPixelFormat: Default::default(),
+            ddpfPixelFormat: desc2.ddpfPixelFormat.clone(),
             ddsCaps: desc2.ddsCaps.dwCaps,
         }
     }
@@ -357,7 +413,7 @@ impl DDSURFACEDESC2 {
             ddckCKDestBlt: Default::default(),
             ddckCKSrcOverlay: Default::default(),
             ddckCKSrcBlt: Default::default(),
-            ddpfPixelFormat: Default::default(),
+            ddpfPixelFormat: desc.ddpfPixelFormat.clone(),
             ddsCaps: DDSCAPS2 {
                 dwCaps: desc.ddsCaps,
                 dwCaps2: Default::default(),
@@ -369,8 +425,82 @@ impl DDSURFACEDESC2 {
     }
 }
 
+/// DDCAPS as of DirectDraw 7.  Callers of older versions pass a smaller dwSize and
+/// only get the leading fields.
 #[repr(C)]
 #[derive(Debug, Default)]
+pub struct DDCAPS {
+    pub dwSize: DWORD,
+    pub dwCaps: DWORD,
+    pub dwCaps2: DWORD,
+    pub dwCKeyCaps: DWORD,
+    pub dwFXCaps: DWORD,
+    pub dwFXAlphaCaps: DWORD,
+    pub dwPalCaps: DWORD,
+    pub dwSVCaps: DWORD,
+    pub dwAlphaBltConstBitDepths: DWORD,
+    pub dwAlphaBltPixelBitDepths: DWORD,
+    pub dwAlphaBltSurfaceBitDepths: DWORD,
+    pub dwAlphaOverlayConstBitDepths: DWORD,
+    pub dwAlphaOverlayPixelBitDepths: DWORD,
+    pub dwAlphaOverlaySurfaceBitDepths: DWORD,
+    pub dwZBufferBitDepths: DWORD,
+    pub dwVidMemTotal: DWORD,
+    pub dwVidMemFree: DWORD,
+    pub dwMaxVisibleOverlays: DWORD,
+    pub dwCurrVisibleOverlays: DWORD,
+    pub dwNumFourCCCodes: DWORD,
+    pub dwAlignBoundarySrc: DWORD,
+    pub dwAlignSizeSrc: DWORD,
+    pub dwAlignBoundaryDest: DWORD,
+    pub dwAlignSizeDest: DWORD,
+    pub dwAlignStrideAlign: DWORD,
+    pub dwRops: [DWORD; 8],
+    pub ddsOldCaps: DDSCAPS,
+    pub dwMinOverlayStretch: DWORD,
+    pub dwMaxOverlayStretch: DWORD,
+    pub dwMinLiveVideoStretch: DWORD,
+    pub dwMaxLiveVideoStretch: DWORD,
+    pub dwMinHwCodecStretch: DWORD,
+    pub dwMaxHwCodecStretch: DWORD,
+    pub dwReserved1: DWORD,
+    pub dwReserved2: DWORD,
+    pub dwReserved3: DWORD,
+    pub dwSVBCaps: DWORD,
+    pub dwSVBCKeyCaps: DWORD,
+    pub dwSVBFXCaps: DWORD,
+    pub dwSVBRops: [DWORD; 8],
+    pub dwVSBCaps: DWORD,
+    pub dwVSBCKeyCaps: DWORD,
+    pub dwVSBFXCaps: DWORD,
+    pub dwVSBRops: [DWORD; 8],
+    pub dwSSBCaps: DWORD,
+    pub dwSSBCKeyCaps: DWORD,
+    pub dwSSBFXCaps: DWORD,
+    pub dwSSBRops: [DWORD; 8],
+    pub dwMaxVideoPorts: DWORD,
+    pub dwCurrVideoPorts: DWORD,
+    pub dwSVBCaps2: DWORD,
+    pub dwNLVBCaps: DWORD,
+    pub dwNLVBCaps2: DWORD,
+    pub dwNLVBCKeyCaps: DWORD,
+    pub dwNLVBFXCaps: DWORD,
+    pub dwNLVBRops: [DWORD; 8],
+    pub ddsCaps: DDSCAPS2,
+}
+unsafe impl memory::Pod for DDCAPS {}
+
+pub const DDCAPS_BLT: u32 = 0x00000040;
+pub const DDCAPS_BLTSTRETCH: u32 = 0x00000200;
+pub const DDCAPS_PALETTE: u32 = 0x00008000;
+pub const DDCAPS_COLORKEY: u32 = 0x00400000;
+pub const DDCAPS_BLTCOLORFILL: u32 = 0x04000000;
+pub const DDCAPS_CANCLIP: u32 = 0x20000000;
+pub const DDCKEYCAPS_DESTBLT: u32 = 0x00000002;
+pub const DDCKEYCAPS_SRCBLT: u32 = 0x00000200;
+
+#[repr(C)]
+#[derive(Debug, Default, Clone)]
 pub struct DDPIXELFORMAT {
     pub dwSize: DWORD,
     pub dwFlags: DWORD,
@@ -383,8 +513,32 @@ pub struct DDPIXELFORMAT {
 }
 unsafe impl memory::Pod for DDPIXELFORMAT {}
 
+pub const DDPF_PALETTEINDEXED8: u32 = 0x00000020;
+pub const DDPF_RGB: u32 = 0x00000040;
+
+impl DDPIXELFORMAT {
+    /// The pixel format we use for surfaces of a given depth.
+    pub fn for_bpp(bytes_per_pixel: u32) -> Self {
+        let (flags, [r, g, b]) = match bytes_per_pixel {
+            1 => (DDPF_RGB | DDPF_PALETTEINDEXED8, [0, 0, 0]),
+            2 => (DDPF_RGB, [0xF800, 0x07E0, 0x001F]),
+            _ => (DDPF_RGB, [0xFF_0000, 0x00_FF00, 0x00_00FF]),
+        };
+        DDPIXELFORMAT {
+            dwSize: std::mem::size_of::<DDPIXELFORMAT>() as u32,
+            dwFlags: flags,
+            dwFourCC: 0,
+            dwRGBBitCount: bytes_per_pixel * 8,
+            dwRBitMask: r,
+            dwGBitMask: g,
+            dwBBitMask: b,
+            dwRGBAlphaBitMask: 0,
+        }
+    }
+}
+
 #[repr(C)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
 pub struct PALETTEENTRY {
     pub peRed: u8,
     pub peGreen: u8,
@@ -0,0 +1,223 @@
+//! Joysticks, one per host game controller, read with c_dfDIJoystick or
+//! c_dfDIJoystick2: DIJOYSTATE(2).
+
+use super::{
+    Event, DIDATAFORMAT, DIDEVICEINSTANCEA, DIPH_BYID, DIPH_BYOFFSET, DIPH_DEVICE, DIPROPHEADER,
+};
+use crate::winapi::winmm::{JoystickMap, Position, AXIS_MAX, JOY_POVCENTERED};
+
+/// DIDEVTYPE_JOYSTICK or DI8DEVTYPE_GAMEPAD, with the gamepad or standard subtype,
+/// and DIDEVTYPE_HID.
+const DEVTYPE: u32 = 0x10504;
+const DEVTYPE8: u32 = 0x10215;
+
+/// GUID_Joystick, which instance GUIDs are made from by adding the joystick's index
+/// to the first byte.
+const GUID_Joystick: [u8; 16] = [
+    0x70, 0x2b, 0x1d, 0x6f, 0xa0, 0xd5, 0xcf, 0x11, 0xbf, 0xc7, 0x44, 0x45, 0x53, 0x54, 0x00, 0x00,
+];
+
+/// Object type GUIDs are A36D02xx-C9F3-11CF-BFC7-444553540000, with these low bytes.
+const GUID_XAxis: u8 = 0xE0;
+const GUID_YAxis: u8 = 0xE1;
+const GUID_ZAxis: u8 = 0xE2;
+const GUID_RzAxis: u8 = 0xE3;
+const GUID_RxAxis: u8 = 0xF4;
+const GUID_RyAxis: u8 = 0xF5;
+const GUID_Button: u8 = 0xF0;
+const GUID_POV: u8 = 0xF2;
+
+fn object_guid(low: u8) -> [u8; 16] {
+    [
+        low, 0x02, 0x6d, 0xa3, 0xf3, 0xc9, 0xcf, 0x11, 0xbf, 0xc7, 0x44, 0x45, 0x53, 0x54, 0x00,
+        0x00,
+    ]
+}
+
+const DIDFT_ABSAXIS: u32 = 0x2;
+const DIDFT_AXIS: u32 = 0x3;
+const DIDFT_PSHBUTTON: u32 = 0x4;
+const DIDFT_POV: u32 = 0x10;
+
+const DIDOI_ASPECTPOSITION: u32 = 0x100;
+
+/// Offsets of the fields of DIJOYSTATE: six axes, two sliders, four POVs, then a
+/// byte per button.
+const DIJOFS_POV0: u32 = 32;
+const DIJOFS_BUTTON0: u32 = 48;
+/// Size of DIJOYSTATE; DIJOYSTATE2 continues with velocities and forces we leave zero.
+const STATE_SIZE: usize = 80;
+
+/// The joystick's axes, lX, lY, lZ, lRx, lRy and lRz, carry the JoystickMap's X, Y,
+/// Z, R, U and V, which with the default map lays out a gamepad as DirectInput's
+/// own drivers do.
+const AXES: [(u8, &str); 6] = [
+    (GUID_XAxis, "X Axis"),
+    (GUID_YAxis, "Y Axis"),
+    (GUID_ZAxis, "Z Axis"),
+    (GUID_RxAxis, "X Rotation"),
+    (GUID_RyAxis, "Y Rotation"),
+    (GUID_RzAxis, "Z Rotation"),
+];
+
+pub fn guid(index: usize) -> [u8; 16] {
+    let mut guid = GUID_Joystick;
+    guid[0] += index as u8;
+    guid
+}
+
+pub fn instance(di8: bool, index: usize, name: &str) -> DIDEVICEINSTANCEA {
+    let dev_type = if di8 { DEVTYPE8 } else { DEVTYPE };
+    // HID usage 5 is a game pad.
+    DIDEVICEINSTANCEA::new(guid(index), dev_type, name, 5)
+}
+
+/// Whether the format is c_dfDIJoystick or c_dfDIJoystick2.
+pub fn supports_format(format: &DIDATAFORMAT) -> bool {
+    format.dwDataSize == 80 || format.dwDataSize == 272
+}
+
+/// The range and dead zone an axis reports through, as set by SetProperty.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct AxisRange {
+    pub min: i32,
+    pub max: i32,
+    /// Dead zone and saturation, from 0 to 10000 of the way from center to edge.
+    pub deadzone: u32,
+    pub saturation: u32,
+}
+
+impl Default for AxisRange {
+    fn default() -> Self {
+        AxisRange {
+            min: 0,
+            max: AXIS_MAX as i32,
+            deadzone: 0,
+            saturation: 10000,
+        }
+    }
+}
+
+impl AxisRange {
+    fn scale(&self, pos: u32) -> i32 {
+        let half = AXIS_MAX as f64 / 2.0;
+        let v = (pos as f64 - half) / half;
+        let (deadzone, saturation) = (self.deadzone as f64 / 1e4, self.saturation as f64 / 1e4);
+        let mag = if v.abs() <= deadzone || saturation <= deadzone {
+            0.0
+        } else {
+            ((v.abs() - deadzone) / (saturation - deadzone)).min(1.0)
+        };
+        let v = mag.copysign(v);
+        let (min, max) = (self.min as f64, self.max as f64);
+        (min + (v + 1.0) / 2.0 * (max - min)).round() as i32
+    }
+}
+
+/// A control on the joystick, as EnumObjects reports it.
+pub struct Object {
+    pub guid: [u8; 16],
+    pub ofs: u32,
+    /// DIDFT_ type and instance.
+    pub typ: u32,
+    pub flags: u32,
+    pub name: String,
+}
+
+/// The joystick's controls: the axes the map assigns, its buttons and a POV hat.
+pub fn objects(map: &JoystickMap) -> Vec<Object> {
+    let mut objects = Vec::new();
+    for (instance, slot) in axes(map).into_iter().enumerate() {
+        let (guid, name) = AXES[slot];
+        objects.push(Object {
+            guid: object_guid(guid),
+            ofs: slot as u32 * 4,
+            typ: (instance as u32) << 8 | DIDFT_ABSAXIS,
+            flags: DIDOI_ASPECTPOSITION,
+            name: name.to_string(),
+        });
+    }
+    for i in 0..map.buttons.len() as u32 {
+        objects.push(Object {
+            guid: object_guid(GUID_Button),
+            ofs: DIJOFS_BUTTON0 + i,
+            typ: i << 8 | DIDFT_PSHBUTTON,
+            flags: 0,
+            name: format!("Button {i}"),
+        });
+    }
+    objects.push(Object {
+        guid: object_guid(GUID_POV),
+        ofs: DIJOFS_POV0,
+        typ: DIDFT_POV,
+        flags: 0,
+        name: "Hat Switch".to_string(),
+    });
+    objects
+}
+
+/// The axis slots the map assigns, in order.
+pub fn axes(map: &JoystickMap) -> Vec<usize> {
+    (0..6).filter(|&i| map.axes[i].is_some()).collect()
+}
+
+/// The axis slots a property applies to, or None if it names no axis.
+pub fn property_axes(map: &JoystickMap, header: &DIPROPHEADER) -> Option<Vec<usize>> {
+    match header.dwHow {
+        DIPH_DEVICE => Some((0..6).collect()),
+        DIPH_BYOFFSET if header.dwObj < 24 && header.dwObj % 4 == 0 => {
+            Some(vec![header.dwObj as usize / 4])
+        }
+        DIPH_BYID if header.dwObj & DIDFT_AXIS != 0 => {
+            let instance = (header.dwObj >> 8 & 0xFFFF) as usize;
+            axes(map).get(instance).map(|&slot| vec![slot])
+        }
+        _ => None,
+    }
+}
+
+/// Fill in a DIJOYSTATE(2).
+pub fn state(pos: &Position, ranges: &[AxisRange; 6], data: &mut [u8]) {
+    data.fill(0);
+    for (i, range) in ranges.iter().enumerate() {
+        data[i * 4..][..4].copy_from_slice(&range.scale(pos.axes[i]).to_le_bytes());
+    }
+    let pov = match pos.pov {
+        JOY_POVCENTERED => !0u32,
+        pov => pov,
+    };
+    for i in 0..4 {
+        let ofs = DIJOFS_POV0 as usize + i * 4;
+        let pov = if i == 0 { pov } else { !0 };
+        data[ofs..][..4].copy_from_slice(&pov.to_le_bytes());
+    }
+    for (i, byte) in data[DIJOFS_BUTTON0 as usize..STATE_SIZE]
+        .iter_mut()
+        .enumerate()
+    {
+        *byte = if pos.buttons & (1 << i) != 0 { 0x80 } else { 0 };
+    }
+}
+
+/// Events for the controls that changed between two DIJOYSTATEs.
+pub fn events(old: &[u8], new: &[u8], time: u32, sequence: u32) -> Vec<Event> {
+    let event = |ofs: usize, data: u32| Event {
+        ofs: ofs as u32,
+        data,
+        time,
+        sequence,
+    };
+    let dword = |buf: &[u8], ofs: usize| u32::from_le_bytes(buf[ofs..][..4].try_into().unwrap());
+    let mut out = Vec::new();
+    for ofs in (0..DIJOFS_BUTTON0 as usize).step_by(4) {
+        if dword(old, ofs) != dword(new, ofs) {
+            out.push(event(ofs, dword(new, ofs)));
+        }
+    }
+    for ofs in DIJOFS_BUTTON0 as usize..STATE_SIZE {
+        if old[ofs] != new[ofs] {
+            out.push(event(ofs, new[ofs] as u32));
+        }
+    }
+    out
+}
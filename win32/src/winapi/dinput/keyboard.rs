@@ -0,0 +1,42 @@
+//! The system keyboard, read with c_dfDIKeyboard: one byte per DIK_ code.
+
+use super::{Event, GUID_SysKeyboard, DIDATAFORMAT, DIDEVICEINSTANCEA};
+use crate::winapi::user32::{Input, InputKind};
+
+/// DIDEVTYPE_KEYBOARD or DI8DEVTYPE_KEYBOARD, with the enhanced 101/102-key subtype.
+const DEVTYPE: u32 = 0x403;
+const DEVTYPE8: u32 = 0x413;
+
+pub fn instance(di8: bool) -> DIDEVICEINSTANCEA {
+    let dev_type = if di8 { DEVTYPE8 } else { DEVTYPE };
+    // HID usage 6 is a keyboard.
+    DIDEVICEINSTANCEA::new(GUID_SysKeyboard, dev_type, "Keyboard", 6)
+}
+
+/// Whether the format is c_dfDIKeyboard, the only one we support for keyboards.
+pub fn supports_format(format: &DIDATAFORMAT) -> bool {
+    format.dwDataSize == 256
+}
+
+pub fn state(input: &Input, data: &mut [u8]) {
+    for (byte, &down) in data.iter_mut().zip(input.keys.iter()) {
+        *byte = if down { 0x80 } else { 0 };
+    }
+}
+
+/// Key events after the given sequence number, along with whether none were lost.
+pub fn events(input: &Input, sequence: u32) -> (Vec<Event>, bool) {
+    let (events, complete) = input.events_since(sequence);
+    let events = events
+        .filter_map(|e| match e.kind {
+            InputKind::Key { dik, down } => Some(Event {
+                ofs: dik as u32,
+                data: if down { 0x80 } else { 0 },
+                time: e.time,
+                sequence: e.sequence,
+            }),
+            _ => None,
+        })
+        .collect();
+    (events, complete)
+}
@@ -0,0 +1,1080 @@
+//! DirectInput: keyboard, mouse and joystick state and buffered data read straight
+//! from the devices.
+
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+
+mod joystick;
+mod keyboard;
+mod mouse;
+
+use super::com::IID_IUnknown;
+use super::heap::Heap;
+use super::types::DWORD;
+use super::{user32, winmm};
+use crate::{machine::Emulator, machine::Machine, winapi::vtable};
+use memory::{Extensions, Pod};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+const TRACE_CONTEXT: &'static str = "dinput";
+
+/*
+## DirectInput
+
+Devices read the same host input that user32 turns into window messages (see
+user32::Input), pulling in waiting host messages themselves since games that
+use DirectInput often poll it without pumping their message queue.
+
+Buffered data is copied from user32's log of recent events into a device's own
+buffer whenever the device is read, so a device that isn't read for a long time
+may report DI_BUFFEROVERFLOW even with a large buffer.  Sequence numbers are
+shared across devices, as in DirectInput, so events from several devices can be
+merged in order.
+
+Joysticks are the host's game controllers, as mapped by the winmm JoystickMap, and
+have no events of their own: their buffered data comes from comparing successive
+states each time they're read or polled.
+
+An exclusive mouse grabs the host mouse while acquired, so that games steering
+with it get relative motion that doesn't stop at the window's edge.
+
+All versions of each interface share one vtable: later versions only add
+methods at the end, apart from IDirectInput8 which has its own.
+*/
+
+pub const DI_OK: u32 = 0;
+pub const DI_BUFFEROVERFLOW: u32 = 1;
+pub const DI_NOEFFECT: u32 = 1;
+pub const DIERR_INVALIDPARAM: u32 = 0x80070057;
+pub const DIERR_NOTACQUIRED: u32 = 0x8007000C;
+pub const DIERR_DEVICENOTREG: u32 = 0x80040154;
+pub const DIERR_NOTBUFFERED: u32 = 0x80040207;
+pub const DIERR_UNSUPPORTED: u32 = 0x80004001;
+pub const DIERR_INPUTLOST: u32 = 0x8007001E;
+pub const DIERR_OBJECTNOTFOUND: u32 = 0x80070002;
+const E_NOINTERFACE: u32 = 0x80004002;
+
+/// Returned by enumeration callbacks to end the enumeration; DIENUM_CONTINUE is 1.
+const DIENUM_STOP: u32 = 0;
+
+const IID_IDirectInputA: [u8; 16] = [
+    0x60, 0x13, 0x52, 0x89, 0x8a, 0xaa, 0xcf, 0x11, 0xbf, 0xc7, 0x44, 0x45, 0x53, 0x54, 0x00, 0x00,
+];
+const IID_IDirectInput2A: [u8; 16] = [
+    0x62, 0xe6, 0x44, 0x59, 0x2e, 0xc9, 0xcf, 0x11, 0xbf, 0xc7, 0x44, 0x45, 0x53, 0x54, 0x00, 0x00,
+];
+const IID_IDirectInput7A: [u8; 16] = [
+    0x84, 0xb6, 0x4c, 0x9a, 0x6d, 0x23, 0xd3, 0x11, 0x8e, 0x9d, 0x00, 0xc0, 0x4f, 0x68, 0x44, 0xae,
+];
+pub const IID_IDirectInput8A: [u8; 16] = [
+    0x30, 0x80, 0x79, 0xbf, 0x3a, 0x48, 0xa2, 0x4d, 0xaa, 0x99, 0x5d, 0x64, 0xed, 0x36, 0x97, 0x00,
+];
+const IID_IDirectInputDeviceA: [u8; 16] = [
+    0x80, 0xe6, 0x44, 0x59, 0x2e, 0xc9, 0xcf, 0x11, 0xbf, 0xc7, 0x44, 0x45, 0x53, 0x54, 0x00, 0x00,
+];
+const IID_IDirectInputDevice2A: [u8; 16] = [
+    0x82, 0xe6, 0x44, 0x59, 0x2e, 0xc9, 0xcf, 0x11, 0xbf, 0xc7, 0x44, 0x45, 0x53, 0x54, 0x00, 0x00,
+];
+const IID_IDirectInputDevice7A: [u8; 16] = [
+    0xbc, 0xc6, 0xd7, 0x57, 0x56, 0x23, 0xd3, 0x11, 0x8e, 0x9d, 0x00, 0xc0, 0x4f, 0x68, 0x44, 0xae,
+];
+const IID_IDirectInputDevice8A: [u8; 16] = [
+    0x80, 0x10, 0xd4, 0x54, 0x15, 0xdc, 0x33, 0x48, 0xa4, 0x1b, 0x74, 0x8f, 0x73, 0xa3, 0x81, 0x79,
+];
+const GUID_SysMouse: [u8; 16] = [
+    0x60, 0x2b, 0x1d, 0x6f, 0xa0, 0xd5, 0xcf, 0x11, 0xbf, 0xc7, 0x44, 0x45, 0x53, 0x54, 0x00, 0x00,
+];
+const GUID_SysKeyboard: [u8; 16] = [
+    0x61, 0x2b, 0x1d, 0x6f, 0xa0, 0xd5, 0xcf, 0x11, 0xbf, 0xc7, 0x44, 0x45, 0x53, 0x54, 0x00, 0x00,
+];
+
+/// DIPROP_BUFFERSIZE, which is MAKEDIPROP(1): a small integer in place of a GUID pointer.
+const DIPROP_BUFFERSIZE: u32 = 1;
+const DIPROP_AXISMODE: u32 = 2;
+const DIPROP_RANGE: u32 = 4;
+const DIPROP_DEADZONE: u32 = 5;
+const DIPROP_SATURATION: u32 = 6;
+
+/// How a DIPROPHEADER picks the object it applies to.
+const DIPH_DEVICE: u32 = 0;
+const DIPH_BYOFFSET: u32 = 1;
+const DIPH_BYID: u32 = 2;
+
+const DIPROPAXISMODE_ABS: u32 = 0;
+const DIPROPAXISMODE_REL: u32 = 1;
+
+const DISCL_EXCLUSIVE: u32 = 0x1;
+
+const DIGDD_PEEK: u32 = 0x1;
+
+/// EnumDevices filter values, which are the same for the DirectInput 8 classes
+/// and the older device types.
+const DEVCLASS_ALL: u32 = 0;
+const DEVCLASS_POINTER: u32 = 2;
+const DEVCLASS_KEYBOARD: u32 = 3;
+const DEVCLASS_GAMECTRL: u32 = 4;
+
+const DIDC_ATTACHED: u32 = 0x1;
+const DIDC_POLLEDDEVICE: u32 = 0x2;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct DIDATAFORMAT {
+    dwSize: DWORD,
+    dwObjSize: DWORD,
+    dwFlags: DWORD,
+    dwDataSize: DWORD,
+    dwNumObjs: DWORD,
+    rgodf: DWORD,
+}
+unsafe impl Pod for DIDATAFORMAT {}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct DIPROPHEADER {
+    dwSize: DWORD,
+    dwHeaderSize: DWORD,
+    dwObj: DWORD,
+    dwHow: DWORD,
+}
+unsafe impl Pod for DIPROPHEADER {}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct DIPROPDWORD {
+    diph: DIPROPHEADER,
+    dwData: DWORD,
+}
+unsafe impl Pod for DIPROPDWORD {}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct DIPROPRANGE {
+    diph: DIPROPHEADER,
+    lMin: i32,
+    lMax: i32,
+}
+unsafe impl Pod for DIPROPRANGE {}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct DIDEVICEOBJECTINSTANCEA {
+    dwSize: DWORD,
+    guidType: [u8; 16],
+    dwOfs: DWORD,
+    dwType: DWORD,
+    dwFlags: DWORD,
+    tszName: [u8; 260],
+    dwFFMaxForce: DWORD,
+    dwFFForceResolution: DWORD,
+    wCollectionNumber: u16,
+    wDesignatorIndex: u16,
+    wUsagePage: u16,
+    wUsage: u16,
+    dwDimension: DWORD,
+    wExponent: u16,
+    wReportId: u16,
+}
+unsafe impl Pod for DIDEVICEOBJECTINSTANCEA {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DIDEVICEINSTANCEA {
+    dwSize: DWORD,
+    guidInstance: [u8; 16],
+    guidProduct: [u8; 16],
+    dwDevType: DWORD,
+    tszInstanceName: [u8; 260],
+    tszProductName: [u8; 260],
+    guidFFDriver: [u8; 16],
+    wUsagePage: u16,
+    wUsage: u16,
+}
+unsafe impl Pod for DIDEVICEINSTANCEA {}
+
+impl DIDEVICEINSTANCEA {
+    fn new(guid: [u8; 16], dev_type: u32, name: &str, usage: u16) -> Self {
+        let mut tszName = [0u8; 260];
+        let len = std::cmp::min(name.len(), tszName.len() - 1);
+        tszName[..len].copy_from_slice(&name.as_bytes()[..len]);
+        DIDEVICEINSTANCEA {
+            dwSize: std::mem::size_of::<DIDEVICEINSTANCEA>() as u32,
+            guidInstance: guid,
+            guidProduct: guid,
+            dwDevType: dev_type,
+            tszInstanceName: tszName,
+            tszProductName: tszName,
+            guidFFDriver: [0; 16],
+            // HID generic desktop page.
+            wUsagePage: 1,
+            wUsage: usage,
+        }
+    }
+}
+
+/// The kinds of device we provide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+enum Kind {
+    Keyboard,
+    Mouse,
+    /// A joystick, by its index among the host's controllers, as winmm numbers them.
+    Joystick(usize),
+}
+
+impl Kind {
+    /// The devices attached: the keyboard, the mouse, and a joystick per controller.
+    fn all(user32: &user32::State) -> impl Iterator<Item = Kind> {
+        let joysticks = (0..user32.controllers.len()).map(Kind::Joystick);
+        [Kind::Keyboard, Kind::Mouse].into_iter().chain(joysticks)
+    }
+
+    fn guid(self) -> [u8; 16] {
+        match self {
+            Kind::Keyboard => GUID_SysKeyboard,
+            Kind::Mouse => GUID_SysMouse,
+            Kind::Joystick(index) => joystick::guid(index),
+        }
+    }
+
+    fn class(self) -> u32 {
+        match self {
+            Kind::Keyboard => DEVCLASS_KEYBOARD,
+            Kind::Mouse => DEVCLASS_POINTER,
+            Kind::Joystick(_) => DEVCLASS_GAMECTRL,
+        }
+    }
+
+    fn instance(self, di8: bool, user32: &user32::State) -> DIDEVICEINSTANCEA {
+        match self {
+            Kind::Keyboard => keyboard::instance(di8),
+            Kind::Mouse => mouse::instance(di8),
+            Kind::Joystick(index) => {
+                // The controller may have been unplugged since the device was created.
+                let name = user32
+                    .controllers
+                    .get(index)
+                    .map_or("Joystick", |c| &c.name);
+                joystick::instance(di8, index, name)
+            }
+        }
+    }
+}
+
+/// One record of buffered data, as written to a DIDEVICEOBJECTDATA.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+struct Event {
+    ofs: u32,
+    data: u32,
+    time: u32,
+    sequence: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Device {
+    kind: Kind,
+    /// Whether the device was created by IDirectInput8, which reports device
+    /// types differently.
+    di8: bool,
+    refs: u32,
+    acquired: bool,
+    /// Size of the data format's state, once SetDataFormat has been called.
+    data_size: Option<u32>,
+    /// Number of events the buffer holds; zero for unbuffered.
+    buffer_size: u32,
+    buffer: VecDeque<Event>,
+    /// Sequence number of the last event copied into the buffer.
+    sequence: u32,
+    /// Whether SetCooperativeLevel asked for exclusive access.
+    exclusive: bool,
+    /// Whether mouse axes report positions rather than motion.
+    absolute: bool,
+    /// Total mouse motion as of the last GetDeviceState, for relative axes.
+    motion: (i32, i32),
+    /// Ranges of the joystick axes, lX through lRz.
+    ranges: [joystick::AxisRange; 6],
+    /// The joystick's DIJOYSTATE as last read, to find what changed since.
+    joystate: Vec<u8>,
+}
+
+impl Device {
+    /// Copy new events into the buffer, dropping those that don't fit.
+    /// Returns true if any were dropped.
+    fn fill(&mut self, user32: &user32::State) -> bool {
+        let input = &user32.input;
+        let (events, complete) = match self.kind {
+            Kind::Keyboard => keyboard::events(input, self.sequence),
+            Kind::Mouse => mouse::events(input, self.sequence, !self.absolute),
+            // Joystick events are buffered as the joystick is polled.
+            Kind::Joystick(_) => (Vec::new(), true),
+        };
+        let overflow = self.push(events);
+        self.sequence = input.sequence();
+        overflow || !complete
+    }
+
+    /// Add events to the buffer, dropping those that don't fit.
+    /// Returns true if any were dropped.
+    fn push(&mut self, events: Vec<Event>) -> bool {
+        let mut overflow = false;
+        for event in events {
+            if self.buffer.len() < self.buffer_size as usize {
+                self.buffer.push_back(event);
+            } else {
+                overflow = true;
+            }
+        }
+        overflow
+    }
+
+    /// Whether acquiring the device grabs the host mouse.
+    fn grabs_mouse(&self) -> bool {
+        self.kind == Kind::Mouse && self.exclusive
+    }
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct State {
+    heap: Heap,
+    vtable_IDirectInput: u32,
+    vtable_IDirectInput8: u32,
+    vtable_IDirectInputDevice: u32,
+    /// Devices by the address of their IDirectInputDevice.
+    devices: HashMap<u32, Device>,
+    /// Buffered joystick events lost when a buffer filled, reported by the next
+    /// GetDeviceData, by device.
+    overflowed: HashSet<u32>,
+}
+
+impl State {
+    pub fn new_init(machine: &mut Machine) -> Self {
+        let mut dinput = State::default();
+        dinput.heap = machine.state.kernel32.new_private_heap(
+            &mut machine.emu.memory,
+            1 << 20,
+            "dinput.dll heap".into(),
+        );
+
+        dinput.vtable_IDirectInput = IDirectInput::vtable(&mut dinput, machine);
+        dinput.vtable_IDirectInput8 = IDirectInput8::vtable(&mut dinput, machine);
+        dinput.vtable_IDirectInputDevice = IDirectInputDevice::vtable(&mut dinput, machine);
+        dinput
+    }
+}
+
+/// After a snapshot is restored, catch devices up with the host's input, which
+/// snapshots don't record (see user32::State), so they report only what comes next.
+pub fn restore(machine: &mut Machine) {
+    let input = &machine.state.user32.input;
+    for device in machine.state.dinput.devices.values_mut() {
+        device.buffer.clear();
+        device.sequence = input.sequence();
+        device.motion = input.motion;
+        device.joystate.clear();
+    }
+}
+
+/// Create an IDirectInput object, or IDirectInput8 if `di8`, at ppv.
+pub fn create(machine: &mut Machine, di8: bool, ppv: Option<&mut u32>) -> u32 {
+    let Some(ppv) = ppv else {
+        return DIERR_INVALIDPARAM;
+    };
+    if machine.state.dinput.heap.addr == 0 {
+        machine.state.dinput = State::new_init(machine);
+    }
+    let dinput = &mut machine.state.dinput;
+    let lpDirectInput = dinput.heap.alloc(machine.emu.memory.mem(), 4);
+    let vtable = match di8 {
+        false => dinput.vtable_IDirectInput,
+        true => dinput.vtable_IDirectInput8,
+    };
+    machine.mem().put::<u32>(lpDirectInput, vtable);
+    *ppv = lpDirectInput;
+    DI_OK
+}
+
+fn create_device(
+    machine: &mut Machine,
+    this: u32,
+    rguid: u32,
+    lplpDevice: Option<&mut u32>,
+) -> u32 {
+    let Some(lplpDevice) = lplpDevice else {
+        return DIERR_INVALIDPARAM;
+    };
+    user32::read_host_messages(machine);
+    let guid = machine.mem().sub(rguid, 16).as_slice_todo();
+    let Some(kind) = Kind::all(&machine.state.user32).find(|kind| kind.guid() == guid) else {
+        log::warn!("CreateDevice: unknown device {guid:x?}");
+        return DIERR_DEVICENOTREG;
+    };
+    let di8 = machine.mem().get_pod::<u32>(this) == machine.state.dinput.vtable_IDirectInput8;
+    let dinput = &mut machine.state.dinput;
+    let lpDevice = dinput.heap.alloc(machine.emu.memory.mem(), 4);
+    let vtable = dinput.vtable_IDirectInputDevice;
+    machine.mem().put::<u32>(lpDevice, vtable);
+    machine.state.dinput.devices.insert(
+        lpDevice,
+        Device {
+            kind,
+            di8,
+            refs: 1,
+            acquired: false,
+            data_size: None,
+            buffer_size: 0,
+            buffer: VecDeque::new(),
+            sequence: 0,
+            exclusive: false,
+            absolute: false,
+            motion: (0, 0),
+            ranges: Default::default(),
+            joystate: Vec::new(),
+        },
+    );
+    *lplpDevice = lpDevice;
+    DI_OK
+}
+
+async fn enum_devices(
+    machine: &mut Machine,
+    di8: bool,
+    dwDevType: u32,
+    lpCallback: u32,
+    pvRef: u32,
+) -> u32 {
+    let filter = dwDevType & 0xFF;
+    user32::read_host_messages(machine);
+    let kinds = Kind::all(&machine.state.user32).collect::<Vec<_>>();
+    for kind in kinds {
+        let instance = kind.instance(di8, &machine.state.user32);
+        if filter != DEVCLASS_ALL && filter != kind.class() && filter != instance.dwDevType & 0xFF {
+            continue;
+        }
+        let mem = machine.emu.memory.mem();
+        let heap = &mut machine.state.dinput.heap;
+        let addr = heap.alloc(mem, std::mem::size_of::<DIDEVICEINSTANCEA>() as u32);
+        mem.put::<DIDEVICEINSTANCEA>(addr, instance);
+        let ret = machine.call_x86(lpCallback, vec![addr, pvRef]).await;
+        machine
+            .state
+            .dinput
+            .heap
+            .free(machine.emu.memory.mem(), addr);
+        if ret == DIENUM_STOP {
+            break;
+        }
+    }
+    DI_OK
+}
+
+/// Read a joystick device's current DIJOYSTATE, buffering events for what changed
+/// since the last read if it's acquired.  Returns None for other devices and for
+/// joysticks whose controller is unplugged.
+fn poll_joystick(machine: &mut Machine, this: u32) -> Option<Vec<u8>> {
+    let device = machine.state.dinput.devices.get(&this)?;
+    let Kind::Joystick(index) = device.kind else {
+        return None;
+    };
+    let pos = winmm::joystick_position(machine, index as u32)?;
+    let mut state = vec![0u8; 80];
+    joystick::state(&pos, &device.ranges, &mut state);
+
+    let now = machine.host.time();
+    let sequence = machine.state.user32.input.sequence();
+    let dinput = &mut machine.state.dinput;
+    let device = dinput.devices.get_mut(&this).unwrap();
+    if device.acquired && device.joystate.len() == state.len() {
+        let events = joystick::events(&device.joystate, &state, now, sequence);
+        if device.push(events) {
+            dinput.overflowed.insert(this);
+        }
+    }
+    device.joystate = state.clone();
+    Some(state)
+}
+
+fn query_interface(
+    machine: &mut Machine,
+    this: u32,
+    riid: u32,
+    ppvObject: u32,
+    iids: &[[u8; 16]],
+) -> u32 {
+    let iid = machine.mem().sub(riid, 16).as_slice_todo();
+    if iid != IID_IUnknown && !iids.iter().any(|known| known == iid) {
+        log::warn!("QueryInterface({this:x}): unknown IID {iid:x?}");
+        return E_NOINTERFACE;
+    }
+    if let Some(device) = machine.state.dinput.devices.get_mut(&this) {
+        device.refs += 1;
+    }
+    machine.mem().put::<u32>(ppvObject, this);
+    DI_OK
+}
+
+#[win32_derive::shims_from_x86]
+mod IDirectInput {
+    use super::*;
+
+    vtable![IDirectInput shims
+        QueryInterface ok,
+        AddRef ok,
+        Release ok,
+        CreateDevice ok,
+        EnumDevices ok,
+        GetDeviceStatus todo,
+        RunControlPanel todo,
+        Initialize ok,
+        FindDevice todo,
+        CreateDeviceEx todo,
+    ];
+
+    #[win32_derive::dllexport]
+    pub fn QueryInterface(machine: &mut Machine, this: u32, riid: u32, ppvObject: u32) -> u32 {
+        query_interface(
+            machine,
+            this,
+            riid,
+            ppvObject,
+            &[IID_IDirectInputA, IID_IDirectInput2A, IID_IDirectInput7A],
+        )
+    }
+
+    #[win32_derive::dllexport]
+    pub fn AddRef(_machine: &mut Machine, this: u32) -> u32 {
+        1
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Release(_machine: &mut Machine, this: u32) -> u32 {
+        0
+    }
+
+    #[win32_derive::dllexport]
+    pub fn CreateDevice(
+        machine: &mut Machine,
+        this: u32,
+        rguid: u32,
+        lplpDirectInputDevice: Option<&mut u32>,
+        pUnkOuter: u32,
+    ) -> u32 {
+        create_device(machine, this, rguid, lplpDirectInputDevice)
+    }
+
+    #[win32_derive::dllexport]
+    pub async fn EnumDevices(
+        machine: &mut Machine,
+        this: u32,
+        dwDevType: u32,
+        lpCallback: u32,
+        pvRef: u32,
+        dwFlags: u32,
+    ) -> u32 {
+        enum_devices(machine, false, dwDevType, lpCallback, pvRef).await
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Initialize(_machine: &mut Machine, this: u32, hinst: u32, dwVersion: u32) -> u32 {
+        DI_OK
+    }
+}
+
+#[win32_derive::shims_from_x86]
+mod IDirectInput8 {
+    use super::*;
+
+    vtable![IDirectInput8 shims
+        QueryInterface ok,
+        AddRef (IDirectInput::shims::AddRef),
+        Release (IDirectInput::shims::Release),
+        CreateDevice (IDirectInput::shims::CreateDevice),
+        EnumDevices ok,
+        GetDeviceStatus todo,
+        RunControlPanel todo,
+        Initialize (IDirectInput::shims::Initialize),
+        FindDevice todo,
+        EnumDevicesBySemantics todo,
+        ConfigureDevices todo,
+    ];
+
+    #[win32_derive::dllexport]
+    pub fn QueryInterface(machine: &mut Machine, this: u32, riid: u32, ppvObject: u32) -> u32 {
+        query_interface(machine, this, riid, ppvObject, &[IID_IDirectInput8A])
+    }
+
+    #[win32_derive::dllexport]
+    pub async fn EnumDevices(
+        machine: &mut Machine,
+        this: u32,
+        dwDevType: u32,
+        lpCallback: u32,
+        pvRef: u32,
+        dwFlags: u32,
+    ) -> u32 {
+        enum_devices(machine, true, dwDevType, lpCallback, pvRef).await
+    }
+}
+
+#[win32_derive::shims_from_x86]
+mod IDirectInputDevice {
+    use super::*;
+
+    vtable![IDirectInputDevice shims
+        QueryInterface ok,
+        AddRef ok,
+        Release ok,
+        GetCapabilities ok,
+        EnumObjects ok,
+        GetProperty ok,
+        SetProperty ok,
+        Acquire ok,
+        Unacquire ok,
+        GetDeviceState ok,
+        GetDeviceData ok,
+        SetDataFormat ok,
+        SetEventNotification todo,
+        SetCooperativeLevel ok,
+        GetObjectInfo todo,
+        GetDeviceInfo todo,
+        RunControlPanel todo,
+        Initialize todo,
+        // IDirectInputDevice2
+        CreateEffect todo,
+        EnumEffects todo,
+        GetEffectInfo todo,
+        GetForceFeedbackState todo,
+        SendForceFeedbackCommand todo,
+        EnumCreatedEffectObjects todo,
+        Escape todo,
+        Poll ok,
+        SendDeviceData todo,
+        // IDirectInputDevice7
+        EnumEffectsInFile todo,
+        WriteEffectToFile todo,
+        // IDirectInputDevice8
+        BuildActionMap todo,
+        SetActionMap todo,
+        GetImageInfo todo,
+    ];
+
+    #[win32_derive::dllexport]
+    pub fn QueryInterface(machine: &mut Machine, this: u32, riid: u32, ppvObject: u32) -> u32 {
+        query_interface(
+            machine,
+            this,
+            riid,
+            ppvObject,
+            &[
+                IID_IDirectInputDeviceA,
+                IID_IDirectInputDevice2A,
+                IID_IDirectInputDevice7A,
+                IID_IDirectInputDevice8A,
+            ],
+        )
+    }
+
+    #[win32_derive::dllexport]
+    pub fn AddRef(machine: &mut Machine, this: u32) -> u32 {
+        let Some(device) = machine.state.dinput.devices.get_mut(&this) else {
+            return 0;
+        };
+        device.refs += 1;
+        device.refs
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Release(machine: &mut Machine, this: u32) -> u32 {
+        let dinput = &mut machine.state.dinput;
+        let Some(device) = dinput.devices.get_mut(&this) else {
+            return 0;
+        };
+        device.refs -= 1;
+        let refs = device.refs;
+        if refs == 0 {
+            let device = dinput.devices.remove(&this).unwrap();
+            dinput.heap.free(machine.emu.memory.mem(), this);
+            if device.acquired && device.grabs_mouse() {
+                machine.host.grab_mouse(false);
+            }
+        }
+        refs
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetCapabilities(machine: &mut Machine, this: u32, lpDIDevCaps: u32) -> u32 {
+        let Some(device) = machine.state.dinput.devices.get(&this) else {
+            return DIERR_INVALIDPARAM;
+        };
+        let instance = device.kind.instance(device.di8, &machine.state.user32);
+        let map = &machine.state.winmm.joystick_map;
+        let (axes, buttons, povs) = match device.kind {
+            Kind::Keyboard => (0, 128, 0),
+            Kind::Mouse => (2, 3, 0),
+            Kind::Joystick(_) => (
+                joystick::axes(map).len() as u32,
+                map.buttons.len() as u32,
+                1,
+            ),
+        };
+        let mut flags = DIDC_ATTACHED;
+        if let Kind::Joystick(_) = device.kind {
+            flags |= DIDC_POLLEDDEVICE;
+        }
+        // DIDEVCAPS grew force feedback fields in DirectX 5, which we leave zero.
+        let mem = machine.emu.memory.mem();
+        let size = mem.get_pod::<u32>(lpDIDevCaps);
+        if size < 24 {
+            return DIERR_INVALIDPARAM;
+        }
+        mem.sub(lpDIDevCaps + 4, size - 4)
+            .as_mut_slice_todo()
+            .fill(0);
+        let fields = [flags, instance.dwDevType, axes, buttons, povs];
+        for (i, field) in fields.into_iter().enumerate() {
+            mem.put::<u32>(lpDIDevCaps + 4 + i as u32 * 4, field);
+        }
+        DI_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub async fn EnumObjects(
+        machine: &mut Machine,
+        this: u32,
+        lpCallback: u32,
+        pvRef: u32,
+        dwFlags: u32,
+    ) -> u32 {
+        let Some(device) = machine.state.dinput.devices.get(&this) else {
+            return DIERR_INVALIDPARAM;
+        };
+        let objects = match device.kind {
+            Kind::Joystick(_) => joystick::objects(&machine.state.winmm.joystick_map),
+            kind => {
+                log::warn!("EnumObjects: unimplemented for {kind:?}");
+                Vec::new()
+            }
+        };
+        for object in objects {
+            if dwFlags != 0 && object.typ & dwFlags & 0xFF == 0 {
+                continue;
+            }
+            let mem = machine.emu.memory.mem();
+            let size = std::mem::size_of::<DIDEVICEOBJECTINSTANCEA>() as u32;
+            let addr = machine.state.dinput.heap.alloc(mem, size);
+            let instance = mem.view_mut::<DIDEVICEOBJECTINSTANCEA>(addr);
+            instance.clear_struct();
+            instance.dwSize = size;
+            instance.guidType = object.guid;
+            instance.dwOfs = object.ofs;
+            instance.dwType = object.typ;
+            instance.dwFlags = object.flags;
+            instance.tszName[..object.name.len()].copy_from_slice(object.name.as_bytes());
+            let ret = machine.call_x86(lpCallback, vec![addr, pvRef]).await;
+            machine
+                .state
+                .dinput
+                .heap
+                .free(machine.emu.memory.mem(), addr);
+            if ret == DIENUM_STOP {
+                break;
+            }
+        }
+        DI_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetProperty(machine: &mut Machine, this: u32, rguidProp: u32, pdiph: u32) -> u32 {
+        let Some(device) = machine.state.dinput.devices.get(&this) else {
+            return DIERR_INVALIDPARAM;
+        };
+        match rguidProp {
+            DIPROP_BUFFERSIZE => {
+                let prop = machine.mem().view_mut::<DIPROPDWORD>(pdiph);
+                prop.dwData = device.buffer_size;
+                DI_OK
+            }
+            DIPROP_AXISMODE if device.kind == Kind::Mouse => {
+                let prop = machine.mem().view_mut::<DIPROPDWORD>(pdiph);
+                prop.dwData = match device.absolute {
+                    true => DIPROPAXISMODE_ABS,
+                    false => DIPROPAXISMODE_REL,
+                };
+                DI_OK
+            }
+            DIPROP_RANGE | DIPROP_DEADZONE | DIPROP_SATURATION
+                if matches!(device.kind, Kind::Joystick(_)) =>
+            {
+                let map = &machine.state.winmm.joystick_map;
+                let header = machine.mem().view::<DIPROPHEADER>(pdiph);
+                // The whole device only makes sense for setting.
+                let Some(&[axis]) = joystick::property_axes(map, header).as_deref() else {
+                    return DIERR_OBJECTNOTFOUND;
+                };
+                let range = &device.ranges[axis];
+                if rguidProp == DIPROP_RANGE {
+                    let prop = machine.mem().view_mut::<DIPROPRANGE>(pdiph);
+                    prop.lMin = range.min;
+                    prop.lMax = range.max;
+                } else {
+                    let prop = machine.mem().view_mut::<DIPROPDWORD>(pdiph);
+                    prop.dwData = match rguidProp {
+                        DIPROP_DEADZONE => range.deadzone,
+                        _ => range.saturation,
+                    };
+                }
+                DI_OK
+            }
+            _ => {
+                log::warn!("GetProperty({rguidProp:x}): unsupported");
+                DIERR_UNSUPPORTED
+            }
+        }
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetProperty(machine: &mut Machine, this: u32, rguidProp: u32, pdiph: u32) -> u32 {
+        let mem = machine.emu.memory.mem();
+        let Some(device) = machine.state.dinput.devices.get_mut(&this) else {
+            return DIERR_INVALIDPARAM;
+        };
+        match rguidProp {
+            DIPROP_BUFFERSIZE => {
+                let size = mem.view::<DIPROPDWORD>(pdiph).dwData;
+                device.buffer_size = size;
+                device.buffer.truncate(size as usize);
+                DI_OK
+            }
+            DIPROP_AXISMODE if device.kind == Kind::Mouse => {
+                let mode = mem.view::<DIPROPDWORD>(pdiph).dwData;
+                device.absolute = mode == DIPROPAXISMODE_ABS;
+                DI_OK
+            }
+            DIPROP_RANGE | DIPROP_DEADZONE | DIPROP_SATURATION
+                if matches!(device.kind, Kind::Joystick(_)) =>
+            {
+                let map = &machine.state.winmm.joystick_map;
+                let header = mem.view::<DIPROPHEADER>(pdiph);
+                let Some(axes) = joystick::property_axes(map, header) else {
+                    return DIERR_OBJECTNOTFOUND;
+                };
+                for axis in axes {
+                    let range = &mut device.ranges[axis];
+                    match rguidProp {
+                        DIPROP_RANGE => {
+                            let prop = mem.view::<DIPROPRANGE>(pdiph);
+                            range.min = prop.lMin;
+                            range.max = prop.lMax;
+                        }
+                        DIPROP_DEADZONE => range.deadzone = mem.view::<DIPROPDWORD>(pdiph).dwData,
+                        _ => range.saturation = mem.view::<DIPROPDWORD>(pdiph).dwData,
+                    }
+                }
+                DI_OK
+            }
+            _ => {
+                log::warn!("SetProperty({rguidProp:x}): unsupported");
+                DIERR_UNSUPPORTED
+            }
+        }
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Acquire(machine: &mut Machine, this: u32) -> u32 {
+        user32::read_host_messages(machine);
+        // Start buffered joystick data from the current state.
+        poll_joystick(machine, this);
+        let Some(device) = machine.state.dinput.devices.get_mut(&this) else {
+            return DIERR_INVALIDPARAM;
+        };
+        if device.data_size.is_none() {
+            return DIERR_INVALIDPARAM;
+        }
+        if device.acquired {
+            return DI_NOEFFECT;
+        }
+        // Buffered data only collects while the device is acquired.
+        let input = &machine.state.user32.input;
+        device.acquired = true;
+        device.buffer.clear();
+        machine.state.dinput.overflowed.remove(&this);
+        device.sequence = input.sequence();
+        device.motion = input.motion;
+        if device.grabs_mouse() {
+            machine.host.grab_mouse(true);
+        }
+        DI_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Unacquire(machine: &mut Machine, this: u32) -> u32 {
+        let Some(device) = machine.state.dinput.devices.get_mut(&this) else {
+            return DIERR_INVALIDPARAM;
+        };
+        if !device.acquired {
+            return DI_NOEFFECT;
+        }
+        device.acquired = false;
+        if device.grabs_mouse() {
+            machine.host.grab_mouse(false);
+        }
+        DI_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetDeviceState(machine: &mut Machine, this: u32, cbData: u32, lpvData: u32) -> u32 {
+        user32::read_host_messages(machine);
+        let joystate = poll_joystick(machine, this);
+        let mem = machine.emu.memory.mem();
+        let Some(device) = machine.state.dinput.devices.get_mut(&this) else {
+            return DIERR_INVALIDPARAM;
+        };
+        if !device.acquired {
+            return DIERR_NOTACQUIRED;
+        }
+        if device.data_size != Some(cbData) {
+            return DIERR_INVALIDPARAM;
+        }
+        let input = &machine.state.user32.input;
+        let data = mem.sub(lpvData, cbData).as_mut_slice_todo();
+        match device.kind {
+            Kind::Keyboard => keyboard::state(input, data),
+            Kind::Mouse => {
+                let motion = (!device.absolute).then_some(device.motion);
+                mouse::state(input, motion, data);
+                device.motion = input.motion;
+            }
+            Kind::Joystick(_) => {
+                let Some(joystate) = joystate else {
+                    return DIERR_INPUTLOST;
+                };
+                data.fill(0);
+                data[..joystate.len()].copy_from_slice(&joystate);
+            }
+        }
+        DI_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetDeviceData(
+        machine: &mut Machine,
+        this: u32,
+        cbObjectData: u32,
+        rgdod: u32,
+        pdwInOut: Option<&mut u32>,
+        dwFlags: u32,
+    ) -> u32 {
+        user32::read_host_messages(machine);
+        poll_joystick(machine, this);
+        let Some(pdwInOut) = pdwInOut else {
+            return DIERR_INVALIDPARAM;
+        };
+        let Some(device) = machine.state.dinput.devices.get_mut(&this) else {
+            return DIERR_INVALIDPARAM;
+        };
+        if !device.acquired {
+            return DIERR_NOTACQUIRED;
+        }
+        if device.buffer_size == 0 {
+            return DIERR_NOTBUFFERED;
+        }
+        // DIDEVICEOBJECTDATA grew an application data field in DirectInput 8.
+        if rgdod != 0 && cbObjectData < 16 {
+            return DIERR_INVALIDPARAM;
+        }
+        let overflow =
+            device.fill(&machine.state.user32) | machine.state.dinput.overflowed.remove(&this);
+
+        // A null rgdod with a count of INFINITE flushes the buffer.
+        let count = std::cmp::min(*pdwInOut as usize, device.buffer.len());
+        if rgdod != 0 {
+            let mem = machine.emu.memory.mem();
+            for (i, event) in device.buffer.iter().take(count).enumerate() {
+                let addr = rgdod + i as u32 * cbObjectData;
+                mem.sub(addr, cbObjectData).as_mut_slice_todo().fill(0);
+                mem.put::<u32>(addr, event.ofs);
+                mem.put::<u32>(addr + 4, event.data);
+                mem.put::<u32>(addr + 8, event.time);
+                mem.put::<u32>(addr + 12, event.sequence);
+            }
+        }
+        if dwFlags & DIGDD_PEEK == 0 {
+            device.buffer.drain(..count);
+        }
+        *pdwInOut = count as u32;
+        if overflow {
+            DI_BUFFEROVERFLOW
+        } else {
+            DI_OK
+        }
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetDataFormat(machine: &mut Machine, this: u32, lpdf: Option<&DIDATAFORMAT>) -> u32 {
+        let Some(lpdf) = lpdf else {
+            return DIERR_INVALIDPARAM;
+        };
+        let Some(device) = machine.state.dinput.devices.get_mut(&this) else {
+            return DIERR_INVALIDPARAM;
+        };
+        let supported = match device.kind {
+            Kind::Keyboard => keyboard::supports_format(lpdf),
+            Kind::Mouse => mouse::supports_format(lpdf),
+            Kind::Joystick(_) => joystick::supports_format(lpdf),
+        };
+        if !supported {
+            log::warn!("SetDataFormat: unsupported format {lpdf:x?}");
+            return DIERR_INVALIDPARAM;
+        }
+        device.data_size = Some(lpdf.dwDataSize);
+        DI_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetCooperativeLevel(machine: &mut Machine, this: u32, hwnd: u32, dwFlags: u32) -> u32 {
+        let Some(device) = machine.state.dinput.devices.get_mut(&this) else {
+            return DIERR_INVALIDPARAM;
+        };
+        device.exclusive = dwFlags & DISCL_EXCLUSIVE != 0;
+        DI_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Poll(machine: &mut Machine, this: u32) -> u32 {
+        let Some(device) = machine.state.dinput.devices.get(&this) else {
+            return DIERR_INVALIDPARAM;
+        };
+        if !device.acquired {
+            return DIERR_NOTACQUIRED;
+        }
+        match device.kind {
+            Kind::Joystick(_) => match poll_joystick(machine, this) {
+                Some(_) => DI_OK,
+                None => DIERR_INPUTLOST,
+            },
+            // The keyboard and mouse don't need polling; DirectInput reports that as no effect.
+            _ => DI_NOEFFECT,
+        }
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn DirectInputCreateA(
+    machine: &mut Machine,
+    hinst: u32,
+    dwVersion: u32,
+    ppDI: Option<&mut u32>,
+    punkOuter: u32,
+) -> u32 {
+    create(machine, false, ppDI)
+}
+
+#[win32_derive::dllexport]
+pub fn DirectInputCreateEx(
+    machine: &mut Machine,
+    hinst: u32,
+    dwVersion: u32,
+    riidltf: u32,
+    ppvOut: Option<&mut u32>,
+    punkOuter: u32,
+) -> u32 {
+    create(machine, false, ppvOut)
+}
@@ -0,0 +1,76 @@
+//! The system mouse, read with c_dfDIMouse or c_dfDIMouse2: DIMOUSESTATE(2).
+
+use super::{Event, GUID_SysMouse, DIDATAFORMAT, DIDEVICEINSTANCEA};
+use crate::winapi::user32::{Input, InputKind};
+
+/// DIDEVTYPE_MOUSE or DI8DEVTYPE_MOUSE, with the unknown subtype.
+const DEVTYPE: u32 = 0x102;
+const DEVTYPE8: u32 = 0x112;
+
+/// Offsets of the fields of DIMOUSESTATE: lX, lY, lZ, then a byte per button.
+const DIMOFS_X: u32 = 0;
+const DIMOFS_Y: u32 = 4;
+const DIMOFS_BUTTON0: u32 = 12;
+
+pub fn instance(di8: bool) -> DIDEVICEINSTANCEA {
+    let dev_type = if di8 { DEVTYPE8 } else { DEVTYPE };
+    // HID usage 2 is a mouse.
+    DIDEVICEINSTANCEA::new(GUID_SysMouse, dev_type, "Mouse", 2)
+}
+
+/// Whether the format is c_dfDIMouse (four buttons) or c_dfDIMouse2 (eight).
+pub fn supports_format(format: &DIDATAFORMAT) -> bool {
+    format.dwDataSize == 16 || format.dwDataSize == 20
+}
+
+/// Fill in a DIMOUSESTATE(2).  `motion` is the total motion as of the previous
+/// read, for relative axes, or None for absolute ones.
+pub fn state(input: &Input, motion: Option<(i32, i32)>, data: &mut [u8]) {
+    let (x, y) = match motion {
+        Some((x, y)) => (
+            input.motion.0.wrapping_sub(x),
+            input.motion.1.wrapping_sub(y),
+        ),
+        None => input.motion,
+    };
+    data.fill(0);
+    data[0..4].copy_from_slice(&x.to_le_bytes());
+    data[4..8].copy_from_slice(&y.to_le_bytes());
+    // TODO: lZ, once hosts report the wheel.
+    for (byte, &down) in data[12..].iter_mut().zip(input.buttons.iter()) {
+        *byte = if down { 0x80 } else { 0 };
+    }
+}
+
+/// Mouse events after the given sequence number, along with whether none were lost.
+/// Axes report motion if `relative`, and otherwise positions.
+pub fn events(input: &Input, sequence: u32, relative: bool) -> (Vec<Event>, bool) {
+    let (events, complete) = input.events_since(sequence);
+    let mut out = Vec::new();
+    for e in events {
+        let mut push = |ofs: u32, data: u32| {
+            out.push(Event {
+                ofs,
+                data,
+                time: e.time,
+                sequence: e.sequence,
+            })
+        };
+        match e.kind {
+            InputKind::Motion { dx, dy, x, y } => {
+                let (x, y) = if relative { (dx, dy) } else { (x, y) };
+                if dx != 0 {
+                    push(DIMOFS_X, x as u32);
+                }
+                if dy != 0 {
+                    push(DIMOFS_Y, y as u32);
+                }
+            }
+            InputKind::Button { button, down } => {
+                push(DIMOFS_BUTTON0 + button as u32, if down { 0x80 } else { 0 })
+            }
+            InputKind::Key { .. } => {}
+        }
+    }
+    (out, complete)
+}
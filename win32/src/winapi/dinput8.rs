@@ -0,0 +1,25 @@
+//! dinput8.dll, which only adds the DirectInput 8 entry point to dinput.dll.
+
+#![allow(non_snake_case)]
+
+use super::dinput::{self, IID_IDirectInput8A, DIERR_INVALIDPARAM};
+use crate::machine::Machine;
+
+const TRACE_CONTEXT: &'static str = "dinput8";
+
+#[win32_derive::dllexport]
+pub fn DirectInput8Create(
+    machine: &mut Machine,
+    hinst: u32,
+    dwVersion: u32,
+    riidltf: u32,
+    ppvOut: Option<&mut u32>,
+    punkOuter: u32,
+) -> u32 {
+    let iid = machine.mem().sub(riidltf, 16).as_slice_todo();
+    if iid != IID_IDirectInput8A {
+        log::warn!("DirectInput8Create: unsupported interface {iid:x?}");
+        return DIERR_INVALIDPARAM;
+    }
+    dinput::create(machine, true, ppvOut)
+}
@@ -1,21 +1,36 @@
 #![allow(non_snake_case)]
 #![allow(non_upper_case_globals)]
 
+use super::com::{self, IID_IUnknown};
 use super::heap::Heap;
+use super::kernel32::{self, HEVENT};
 use super::types::DWORD;
-use crate::{machine::Emulator, machine::Machine, winapi::vtable};
+use super::winmm::WAVEFORMATEX;
+use crate::{
+    host,
+    machine::Emulator,
+    machine::{Machine, MuteHandle},
+    winapi::{serde_bitflags, vtable},
+};
+use bitflags::bitflags;
+use memory::{Extensions, Mem};
+use std::collections::HashMap;
 
 const TRACE_CONTEXT: &'static str = "dsound";
 
 /// Set to true to make DirectSoundCreate report no sound device available.
-/// Doing this from the beginning would have been a better idea than trying to implement stubs here...
-const DISABLE: bool = true;
+const DISABLE: bool = false;
 
 pub const DS_OK: u32 = 0;
 #[allow(unused)]
 const E_FAIL: u32 = 0x80004005;
+const E_NOINTERFACE: u32 = 0x80004002;
 #[allow(unused)]
 pub const DSERR_GENERIC: u32 = E_FAIL;
+pub const DSERR_INVALIDPARAM: u32 = 0x80070057;
+pub const DSERR_CONTROLUNAVAIL: u32 = make_dhsresult(30);
+pub const DSERR_INVALIDCALL: u32 = make_dhsresult(50);
+pub const DSERR_BADFORMAT: u32 = make_dhsresult(100);
 #[allow(unused)]
 pub const DSERR_NODRIVER: u32 = make_dhsresult(120);
 
@@ -23,10 +38,432 @@ const fn make_dhsresult(code: u32) -> u32 {
     (1 << 31) | (0x878 << 16) | code
 }
 
+const IID_IDirectSoundBuffer: [u8; 16] = [
+    0x85, 0x04, 0x96, 0x27, 0x80, 0x4b, 0xcf, 0x11, 0xa5, 0x00, 0x00, 0x20, 0xaf, 0x0b, 0xe5, 0x60,
+];
+const IID_IDirectSoundNotify: [u8; 16] = [
+    0x83, 0x07, 0x21, 0xb0, 0xcd, 0x89, 0xd0, 0x11, 0xaf, 0x08, 0x00, 0xa0, 0xc9, 0x25, 0xcd, 0x16,
+];
+
+/*
+## Playback
+
+Buffers hold their samples in x86 memory, where the guest writes them between Lock and
+Unlock.  Playing buffers are mixed into a single stereo stream handed to the host, kept
+LATENCY_MS ahead of what the host has actually played.
+
+The play cursor is derived from how much of that stream the host has consumed, so it
+advances in real time against the host audio clock; the write cursor is where mixing
+has reached, as data before it is already committed.  Without host audio, consumption
+is timed against Host::time instead.
+
+Nothing here runs on its own: update() is called from DirectSound methods and, via
+winapi::poll_devices, whenever the guest waits or pumps messages.  That is also when
+position notifications are signaled.
+*/
+
+/// Sample rate of the stream handed to the host.
+const OUTPUT_RATE: u32 = 44100;
+/// How far ahead of the host's playback we mix.
+pub(super) const LATENCY_MS: u32 = 60;
+/// How often playback wants update() called while anything is playing.
+pub(super) const POLL_MS: u32 = 20;
+
+const DSBPLAY_LOOPING: u32 = 0x1;
+
+const DSBSTATUS_PLAYING: u32 = 0x1;
+const DSBSTATUS_LOOPING: u32 = 0x4;
+
+const DSBLOCK_FROMWRITECURSOR: u32 = 0x1;
+const DSBLOCK_ENTIREBUFFER: u32 = 0x2;
+
+/// dwOffset of a notification that fires when the buffer stops.
+const DSBPN_OFFSETSTOP: u32 = 0xFFFF_FFFF;
+
+/// Volume and pan are attenuations in hundredths of a decibel, so these range
+/// from full volume to silence.
+const DSBVOLUME_MIN: i32 = -10000;
+const DSBVOLUME_MAX: i32 = 0;
+const DSBPAN_LEFT: i32 = -10000;
+const DSBPAN_RIGHT: i32 = 10000;
+
+/// SetFrequency value meaning the rate of the buffer's format.
+const DSBFREQUENCY_ORIGINAL: u32 = 0;
+const DSBFREQUENCY_MIN: u32 = 100;
+const DSBFREQUENCY_MAX: u32 = 200000;
+
+/// Linear gain of an attenuation in hundredths of a decibel.
+fn gain(attenuation: i32) -> f32 {
+    if attenuation <= DSBVOLUME_MIN {
+        return 0.0;
+    }
+    10f32.powf(attenuation as f32 / 2000.0)
+}
+
+bitflags! {
+    pub struct DSBCAPS: u32 {
+        const PRIMARYBUFFER = 0x00000001;
+        const STATIC = 0x00000002;
+        const LOCHARDWARE = 0x00000004;
+        const LOCSOFTWARE = 0x00000008;
+        const CTRL3D = 0x00000010;
+        const CTRLFREQUENCY = 0x00000020;
+        const CTRLPAN = 0x00000040;
+        const CTRLVOLUME = 0x00000080;
+        const CTRLPOSITIONNOTIFY = 0x00000100;
+        const STICKYFOCUS = 0x00004000;
+        const GLOBALFOCUS = 0x00008000;
+        const GETCURRENTPOSITION2 = 0x00010000;
+        const MUTE3DATMAXDISTANCE = 0x00020000;
+        const LOCDEFER = 0x00040000;
+    }
+}
+serde_bitflags!(DSBCAPS);
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct DSBUFFERDESC {
+    pub dwSize: DWORD,
+    pub dwFlags: DWORD,
+    pub dwBufferBytes: DWORD,
+    pub dwReserved: DWORD,
+    pub lpwfxFormat: DWORD,
+}
+unsafe impl memory::Pod for DSBUFFERDESC {}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct DSBCAPS_ {
+    pub dwSize: DWORD,
+    pub dwFlags: DWORD,
+    pub dwBufferBytes: DWORD,
+    pub dwUnlockTransferRate: DWORD,
+    pub dwPlayCpuOverhead: DWORD,
+}
+unsafe impl memory::Pod for DSBCAPS_ {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct DSBPOSITIONNOTIFY {
+    pub dwOffset: DWORD,
+    pub hEventNotify: HEVENT,
+}
+unsafe impl memory::Pod for DSBPOSITIONNOTIFY {}
+
+/// PCM sample format of a buffer, or of a waveOut device.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub(super) struct Format {
+    channels: u16,
+    pub(super) rate: u32,
+    bits: u16,
+}
+
+impl Format {
+    pub(super) fn from_wave(fmt: &WAVEFORMATEX) -> Option<Self> {
+        const WAVE_FORMAT_PCM: u16 = 1;
+        if fmt.wFormatTag != WAVE_FORMAT_PCM
+            || !matches!(fmt.nChannels, 1 | 2)
+            || !matches!(fmt.wBitsPerSample, 8 | 16)
+            || fmt.nSamplesPerSec == 0
+        {
+            return None;
+        }
+        Some(Format {
+            channels: fmt.nChannels,
+            rate: fmt.nSamplesPerSec,
+            bits: fmt.wBitsPerSample,
+        })
+    }
+
+    pub(super) fn block_align(&self) -> u32 {
+        self.channels as u32 * self.bits as u32 / 8
+    }
+
+    fn silence(&self) -> u8 {
+        if self.bits == 8 {
+            0x80
+        } else {
+            0
+        }
+    }
+
+    /// Read one frame as a stereo pair of 16-bit range samples.
+    pub(super) fn sample(&self, data: &[u8], frame: u32) -> [i32; 2] {
+        let ofs = (frame * self.block_align()) as usize;
+        let get = |ch: usize| -> i32 {
+            if self.bits == 8 {
+                (data[ofs + ch] as i32 - 0x80) << 8
+            } else {
+                i16::from_le_bytes([data[ofs + ch * 2], data[ofs + ch * 2 + 1]]) as i32
+            }
+        };
+        if self.channels == 1 {
+            let s = get(0);
+            [s, s]
+        } else {
+            [get(0), get(1)]
+        }
+    }
+}
+
+/// DirectSound's default primary buffer format.
+const DEFAULT_FORMAT: Format = Format {
+    channels: 2,
+    rate: 22050,
+    bits: 8,
+};
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Buffer {
+    refs: u32,
+    flags: DSBCAPS,
+    format: Format,
+    /// Attenuation in hundredths of a dB, DSBVOLUME_MIN to DSBVOLUME_MAX.
+    volume: i32,
+    /// Relative attenuation of the channels, DSBPAN_LEFT to DSBPAN_RIGHT.
+    pan: i32,
+    /// Playback rate if changed by SetFrequency.
+    frequency: Option<u32>,
+    /// Address of the sample data, or 0 for the primary buffer, which has none.
+    addr: u32,
+    size: u32,
+    playing: bool,
+    looping: bool,
+    /// Frame at which the current run of playback started.
+    start: f64,
+    /// Output frames mixed from this buffer since start.
+    mixed: u64,
+    /// Output frames of those the host had played, as of the last update.
+    played: u64,
+    notify: Vec<DSBPOSITIONNOTIFY>,
+}
+
+impl Buffer {
+    fn new(flags: DSBCAPS, format: Format, addr: u32, size: u32) -> Self {
+        Buffer {
+            refs: 1,
+            flags,
+            format,
+            volume: DSBVOLUME_MAX,
+            pan: 0,
+            frequency: None,
+            addr,
+            size,
+            playing: false,
+            looping: false,
+            start: 0.0,
+            mixed: 0,
+            played: 0,
+            notify: Vec::new(),
+        }
+    }
+
+    fn frames(&self) -> u32 {
+        self.size / self.format.block_align()
+    }
+
+    fn rate(&self) -> u32 {
+        self.frequency.unwrap_or(self.format.rate)
+    }
+
+    /// Unwrapped position in frames after some number of output frames.
+    fn pos_at(&self, output_frames: u64) -> f64 {
+        self.start + output_frames as f64 * self.rate() as f64 / OUTPUT_RATE as f64
+    }
+
+    /// Change the playback rate, keeping positions already mixed where they are.
+    fn set_rate(&mut self, frequency: Option<u32>) {
+        let pos = self.pos_at(self.mixed);
+        self.frequency = frequency;
+        self.start = pos - (self.pos_at(self.mixed) - self.start);
+    }
+
+    /// Gains of the left and right channels.
+    fn gains(&self) -> [f32; 2] {
+        let volume = gain(self.volume);
+        // Panning attenuates the channel opposite the direction of the pan.
+        [
+            volume * gain(-std::cmp::max(self.pan, 0)),
+            volume * gain(std::cmp::min(self.pan, 0)),
+        ]
+    }
+
+    /// Byte offset of an unwrapped position.
+    fn offset(&self, pos: f64) -> u32 {
+        let frames = self.frames();
+        if frames == 0 {
+            return 0;
+        }
+        pos.rem_euclid(frames as f64) as u32 * self.format.block_align()
+    }
+
+    fn play_cursor(&self) -> u32 {
+        if self.playing {
+            self.offset(self.pos_at(self.played))
+        } else {
+            self.offset(self.start)
+        }
+    }
+
+    fn write_cursor(&self) -> u32 {
+        if self.playing {
+            self.offset(self.pos_at(self.mixed))
+        } else {
+            self.play_cursor()
+        }
+    }
+
+    /// Move the play cursor up to `played` output frames, collecting the events of
+    /// notification positions passed along the way.
+    fn advance(&mut self, played: u64, signal: &mut Vec<HEVENT>) {
+        let frames = self.frames() as f64;
+        let old = self.pos_at(self.played);
+        let mut new = self.pos_at(played);
+        self.played = played;
+        let ended = !self.looping && new >= frames;
+        if ended {
+            new = frames;
+        }
+        for n in &self.notify {
+            if n.dwOffset == DSBPN_OFFSETSTOP {
+                continue;
+            }
+            let at = (n.dwOffset / self.format.block_align()) as f64;
+            let passed = if self.looping {
+                // The first occurrence of `at` after `old`, in unwrapped terms.
+                let next = at + (((old - at) / frames).floor() + 1.0) * frames;
+                next <= new
+            } else {
+                old < at && at <= new
+            };
+            if passed {
+                signal.push(n.hEventNotify);
+            }
+        }
+        if ended {
+            self.stop(signal);
+            self.start = 0.0;
+        }
+    }
+
+    fn stop(&mut self, signal: &mut Vec<HEVENT>) {
+        if !self.playing {
+            return;
+        }
+        self.start = self.pos_at(self.played) % self.frames() as f64;
+        self.rewind(self.start);
+        self.playing = false;
+        signal.extend(
+            self.notify
+                .iter()
+                .filter(|n| n.dwOffset == DSBPN_OFFSETSTOP)
+                .map(|n| n.hEventNotify),
+        );
+    }
+
+    fn rewind(&mut self, start: f64) {
+        self.start = start;
+        self.mixed = 0;
+        self.played = 0;
+    }
+
+    /// Add this buffer's next output frames into acc, scaled by gains.
+    fn mix_into(&mut self, mem: Mem, acc: &mut [i32], gains: [f32; 2]) {
+        let frames = acc.len() as u64 / 2;
+        let len = self.frames() as f64;
+        let [gain_l, gain_r] = gains;
+        if gain_l == 0.0 && gain_r == 0.0 {
+            self.mixed += frames;
+            return;
+        }
+        let data = mem.sub(self.addr, self.size).as_slice_todo();
+        for (i, out) in acc.chunks_exact_mut(2).enumerate() {
+            let mut pos = self.pos_at(self.mixed + i as u64);
+            if self.looping {
+                pos = pos.rem_euclid(len);
+            } else if pos >= len {
+                break;
+            }
+            // Interpolate linearly between neighboring frames, which matters
+            // when resampling pitch-shifted sounds.
+            let frame = pos as u32;
+            let frac = (pos - frame as f64) as f32;
+            let next = if frame + 1 < self.frames() {
+                frame + 1
+            } else if self.looping {
+                0
+            } else {
+                frame
+            };
+            let [l0, r0] = self.format.sample(data, frame);
+            let [l1, r1] = self.format.sample(data, next);
+            let l = l0 as f32 + (l1 - l0) as f32 * frac;
+            let r = r0 as f32 + (r1 - r0) as f32 * frac;
+            out[0] += (l * gain_l) as i32;
+            out[1] += (r * gain_r) as i32;
+        }
+        self.mixed += frames;
+    }
+}
+
+/// A stream of stereo 16-bit output to the host, at a fixed rate.  Also used by
+/// winmm's waveOut.
+pub(super) struct Output {
+    stream: Option<Box<dyn host::Audio>>,
+    /// While muted, silence is written in place of samples, keeping time as usual.
+    mute: MuteHandle,
+    rate: u32,
+    /// Output frames written, when there's no host stream to ask.
+    written: u64,
+}
+
+impl Output {
+    pub(super) fn new(machine: &mut Machine, rate: u32) -> Self {
+        Output {
+            stream: machine.host.create_audio(rate),
+            mute: machine.mute.clone(),
+            rate,
+            written: 0,
+        }
+    }
+
+    fn elapsed(&self, now: u32) -> u64 {
+        now as u64 * self.rate as u64 / 1000
+    }
+
+    /// Output frames written but not yet played.
+    pub(super) fn queued(&self, now: u32) -> u32 {
+        match &self.stream {
+            Some(stream) => stream.queued(),
+            None => self.written.saturating_sub(self.elapsed(now)) as u32,
+        }
+    }
+
+    pub(super) fn write(&mut self, samples: &[i16], now: u32) {
+        match &mut self.stream {
+            Some(stream) if self.mute.is_muted() => stream.write(&vec![0; samples.len()]),
+            Some(stream) => stream.write(samples),
+            None => {
+                self.written =
+                    std::cmp::max(self.written, self.elapsed(now)) + samples.len() as u64 / 2
+            }
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct State {
     heap: Heap,
     vtable_IDirectSound: u32,
     vtable_IDirectSoundBuffer: u32,
+    vtable_IDirectSoundNotify: u32,
+    /// References to IDirectSound objects; buffers count their own.
+    refs: com::RefCounts,
+    /// Buffers by the address of their IDirectSoundBuffer.
+    buffers: HashMap<u32, Buffer>,
+    /// The host stream, which is reopened when a snapshot is restored; see restore.
+    #[serde(skip)]
+    output: Option<Output>,
 }
 
 impl State {
@@ -34,45 +471,162 @@ impl State {
         let mut dsound = State::default();
         dsound.heap = machine.state.kernel32.new_private_heap(
             &mut machine.emu.memory,
-            0x1000,
+            16 << 20,
             "dsound.dll heap".into(),
         );
 
         dsound.vtable_IDirectSound = IDirectSound::vtable(&mut dsound, machine);
         dsound.vtable_IDirectSoundBuffer = IDirectSoundBuffer::vtable(&mut dsound, machine);
+        dsound.vtable_IDirectSoundNotify = IDirectSoundNotify::vtable(&mut dsound, machine);
+        dsound.output = Some(Output::new(machine, OUTPUT_RATE));
         dsound
     }
 }
 
+/// After a snapshot is restored, reopen the host stream if DirectSound was in use.
+/// Playing buffers continue from where the host had played them; what was mixed
+/// ahead of that is mixed again into the new stream.
+pub fn restore(machine: &mut Machine) {
+    if machine.state.dsound.heap.addr == 0 {
+        return;
+    }
+    let output = Output::new(machine, OUTPUT_RATE);
+    let dsound = &mut machine.state.dsound;
+    dsound.output = Some(output);
+    for buf in dsound.buffers.values_mut().filter(|b| b.playing) {
+        buf.rewind(buf.pos_at(buf.played));
+    }
+}
+
 impl Default for State {
     fn default() -> Self {
         State {
             heap: Heap::default(),
             vtable_IDirectSound: 0,
             vtable_IDirectSoundBuffer: 0,
+            vtable_IDirectSoundNotify: 0,
+            refs: com::RefCounts::default(),
+            buffers: HashMap::new(),
+            output: None,
         }
     }
 }
 
+/// Advance playback: move play cursors along with the host, signal the
+/// notifications they pass, and mix more output.
+/// Returns the host time by which it should be called again, if anything is playing.
+pub fn update(machine: &mut Machine) -> Option<u32> {
+    let dsound = &mut machine.state.dsound;
+    if !dsound.buffers.values().any(|b| b.playing) {
+        return None;
+    }
+    let now = machine.host.time();
+    let output = dsound.output.as_mut()?;
+    let queued = output.queued(now) as u64;
+
+    let mut signal = Vec::new();
+    for buf in dsound.buffers.values_mut().filter(|b| b.playing) {
+        let played = buf.mixed - std::cmp::min(queued, buf.mixed);
+        buf.advance(played, &mut signal);
+    }
+
+    let target = (OUTPUT_RATE * LATENCY_MS / 1000) as u64;
+    if queued < target && dsound.buffers.values().any(|b| b.playing && b.addr != 0) {
+        let mut acc = vec![0i32; (target - queued) as usize * 2];
+        let mem = machine.emu.memory.mem();
+        // The primary buffer's volume and pan apply to the whole mix.
+        let master = match dsound.buffers.values().find(|b| b.addr == 0) {
+            Some(primary) => primary.gains(),
+            None => [1.0, 1.0],
+        };
+        for buf in dsound.buffers.values_mut() {
+            if buf.playing && buf.addr != 0 {
+                let [l, r] = buf.gains();
+                buf.mix_into(mem, &mut acc, [l * master[0], r * master[1]]);
+            }
+        }
+        let samples = acc
+            .into_iter()
+            .map(|s| s.clamp(i16::MIN as i32, i16::MAX as i32) as i16)
+            .collect::<Vec<_>>();
+        output.write(&samples, now);
+    }
+
+    signal_all(machine, signal);
+    Some(now + POLL_MS)
+}
+
+fn signal_all(machine: &mut Machine, events: Vec<HEVENT>) {
+    for event in events {
+        kernel32::set_event(machine, event);
+    }
+}
+
 #[win32_derive::shims_from_x86]
 mod IDirectSound {
     use super::*;
 
     #[win32_derive::dllexport]
-    pub fn Release(_machine: &mut Machine, this: u32) -> u32 {
-        0
+    pub fn AddRef(machine: &mut Machine, this: u32) -> u32 {
+        machine.state.dsound.refs.add_ref(this)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Release(machine: &mut Machine, this: u32) -> u32 {
+        let dsound = &mut machine.state.dsound;
+        let refs = dsound.refs.release(this);
+        if refs == 0 {
+            dsound.heap.free(machine.emu.memory.mem(), this);
+        }
+        refs
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Initialize(_machine: &mut Machine, this: u32, pcGuidDevice: u32) -> u32 {
+        // Objects from CoCreateInstance are initialized here, but ours are ready already.
+        DS_OK
     }
 
     #[win32_derive::dllexport]
     pub fn CreateSoundBuffer(
         machine: &mut Machine,
         this: u32,
-        lpcDSBufferDesc: u32,
-        lplpDirectSoundBuffer: u32,
+        lpcDSBufferDesc: Option<&DSBUFFERDESC>,
+        lplpDirectSoundBuffer: Option<&mut u32>,
         pUnkOuter: u32,
     ) -> u32 {
+        let (Some(desc), Some(lplpDirectSoundBuffer)) = (lpcDSBufferDesc, lplpDirectSoundBuffer)
+        else {
+            return DSERR_INVALIDPARAM;
+        };
+        let flags = DSBCAPS::from_bits_truncate(desc.dwFlags);
+        let buffer = if flags.contains(DSBCAPS::PRIMARYBUFFER) {
+            Buffer::new(flags, DEFAULT_FORMAT, 0, 0)
+        } else {
+            if desc.lpwfxFormat == 0 || !(4..0x1000_0000).contains(&desc.dwBufferBytes) {
+                return DSERR_INVALIDPARAM;
+            }
+            let wave = machine.mem().get_pod::<WAVEFORMATEX>(desc.lpwfxFormat);
+            let Some(format) = Format::from_wave(&wave) else {
+                log::warn!("CreateSoundBuffer: unsupported format {wave:?}");
+                return DSERR_BADFORMAT;
+            };
+            // Round down to whole frames.
+            let size = desc.dwBufferBytes / format.block_align() * format.block_align();
+            let dsound = &mut machine.state.dsound;
+            let addr = dsound.heap.alloc(machine.emu.memory.mem(), size);
+            machine
+                .emu
+                .memory
+                .mem()
+                .sub(addr, size)
+                .as_mut_slice_todo()
+                .fill(format.silence());
+            Buffer::new(flags, format, addr, size)
+        };
         let x86_buffer = IDirectSoundBuffer::new(machine);
-        machine.mem().put::<u32>(lplpDirectSoundBuffer, x86_buffer);
+        machine.state.dsound.buffers.insert(x86_buffer, buffer);
+        *lplpDirectSoundBuffer = x86_buffer;
         DS_OK
     }
 
@@ -83,7 +637,7 @@ mod IDirectSound {
 
     vtable![IDirectSound shims
         QueryInterface todo,
-        AddRef todo,
+        AddRef ok,
         Release ok,
         CreateSoundBuffer ok,
         GetCaps todo,
@@ -92,7 +646,7 @@ mod IDirectSound {
         Compact todo,
         GetSpeakerConfig todo,
         SetSpeakerConfig todo,
-        Initialize todo,
+        Initialize ok,
     ];
 }
 
@@ -109,63 +663,305 @@ mod IDirectSoundBuffer {
     }
 
     #[win32_derive::dllexport]
-    pub fn Release(_machine: &mut Machine, this: u32) -> u32 {
-        0
+    pub fn QueryInterface(machine: &mut Machine, this: u32, riid: u32, ppvObject: u32) -> u32 {
+        let iid = machine.mem().sub(riid, 16).as_slice_todo();
+        let obj = if iid == IID_IUnknown || iid == IID_IDirectSoundBuffer {
+            this
+        } else if iid == IID_IDirectSoundNotify {
+            IDirectSoundNotify::new(machine, this)
+        } else {
+            log::warn!("QueryInterface({this:x}): unknown IID {iid:x?}");
+            machine.mem().put::<u32>(ppvObject, 0);
+            return E_NOINTERFACE;
+        };
+        if let Some(buf) = machine.state.dsound.buffers.get_mut(&this) {
+            buf.refs += 1;
+        }
+        machine.mem().put::<u32>(ppvObject, obj);
+        DS_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn AddRef(machine: &mut Machine, this: u32) -> u32 {
+        let Some(buf) = machine.state.dsound.buffers.get_mut(&this) else {
+            return 0;
+        };
+        buf.refs += 1;
+        buf.refs
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Release(machine: &mut Machine, this: u32) -> u32 {
+        let dsound = &mut machine.state.dsound;
+        let Some(buf) = dsound.buffers.get_mut(&this) else {
+            return 0;
+        };
+        buf.refs -= 1;
+        let refs = buf.refs;
+        if refs == 0 {
+            let buf = dsound.buffers.remove(&this).unwrap();
+            let mem = machine.emu.memory.mem();
+            if buf.addr != 0 {
+                dsound.heap.free(mem, buf.addr);
+            }
+            dsound.heap.free(mem, this);
+        }
+        refs
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetCaps(machine: &mut Machine, this: u32, lpDSBufferCaps: Option<&mut DSBCAPS_>) -> u32 {
+        let (Some(buf), Some(caps)) = (machine.state.dsound.buffers.get(&this), lpDSBufferCaps)
+        else {
+            return DSERR_INVALIDPARAM;
+        };
+        caps.dwFlags = (buf.flags | DSBCAPS::LOCSOFTWARE).bits();
+        caps.dwBufferBytes = buf.size;
+        caps.dwUnlockTransferRate = 0;
+        caps.dwPlayCpuOverhead = 0;
+        DS_OK
     }
 
     #[win32_derive::dllexport]
     pub fn GetCurrentPosition(
-        _machine: &mut Machine,
+        machine: &mut Machine,
         this: u32,
         lpdwCurrentPlayCursor: Option<&mut u32>,
         lpdwCurrentWriteCursor: Option<&mut u32>,
     ) -> u32 {
-        match lpdwCurrentPlayCursor {
-            Some(play) => *play = 0,
-            None => {}
+        update(machine);
+        let Some(buf) = machine.state.dsound.buffers.get(&this) else {
+            return DSERR_INVALIDCALL;
+        };
+        if let Some(play) = lpdwCurrentPlayCursor {
+            *play = buf.play_cursor();
         }
-        match lpdwCurrentWriteCursor {
-            Some(write) => *write = 0,
-            None => {}
+        if let Some(write) = lpdwCurrentWriteCursor {
+            *write = buf.write_cursor();
         }
         DS_OK
     }
 
     #[win32_derive::dllexport]
-    pub fn GetStatus(_machine: &mut Machine, this: u32, lpdwStatus: Option<&mut u32>) -> u32 {
-        let status = lpdwStatus.unwrap();
+    pub fn GetStatus(machine: &mut Machine, this: u32, lpdwStatus: Option<&mut u32>) -> u32 {
+        update(machine);
+        let (Some(buf), Some(status)) = (machine.state.dsound.buffers.get(&this), lpdwStatus)
+        else {
+            return DSERR_INVALIDPARAM;
+        };
         *status = 0;
+        if buf.playing {
+            *status |= DSBSTATUS_PLAYING;
+            if buf.looping {
+                *status |= DSBSTATUS_LOOPING;
+            }
+        }
         DS_OK
     }
 
     #[win32_derive::dllexport]
     pub fn Lock(
-        _machine: &mut Machine,
+        machine: &mut Machine,
         this: u32,
         dwWriteCursor: u32,
         dwWriteBytes: u32,
-        lplpvAudioPtr1: u32,
-        lpdwAudioBytes1: u32,
-        lplpvAudioPtr2: u32,
-        lpdwAudioBytes2: u32,
+        lplpvAudioPtr1: Option<&mut u32>,
+        lpdwAudioBytes1: Option<&mut u32>,
+        lplpvAudioPtr2: Option<&mut u32>,
+        lpdwAudioBytes2: Option<&mut u32>,
         dwFlags: u32,
     ) -> u32 {
+        update(machine);
+        let Some(buf) = machine.state.dsound.buffers.get(&this) else {
+            return DSERR_INVALIDCALL;
+        };
+        if buf.addr == 0 {
+            // We don't expose the mix of the primary buffer.
+            return DSERR_INVALIDCALL;
+        }
+        let start = if dwFlags & DSBLOCK_FROMWRITECURSOR != 0 {
+            buf.write_cursor()
+        } else {
+            dwWriteCursor
+        };
+        let len = if dwFlags & DSBLOCK_ENTIREBUFFER != 0 {
+            buf.size
+        } else {
+            dwWriteBytes
+        };
+        if start >= buf.size || len > buf.size {
+            return DSERR_INVALIDPARAM;
+        }
+
+        // The locked region wraps around to the start of the buffer if needed.
+        let first = std::cmp::min(len, buf.size - start);
+        let (Some(ptr1), Some(bytes1)) = (lplpvAudioPtr1, lpdwAudioBytes1) else {
+            return DSERR_INVALIDPARAM;
+        };
+        *ptr1 = buf.addr + start;
+        *bytes1 = first;
+        if let (Some(ptr2), Some(bytes2)) = (lplpvAudioPtr2, lpdwAudioBytes2) {
+            *ptr2 = if len > first { buf.addr } else { 0 };
+            *bytes2 = len - first;
+        } else if len > first {
+            return DSERR_INVALIDPARAM;
+        }
         DS_OK
     }
 
     #[win32_derive::dllexport]
     pub fn Play(
-        _machine: &mut Machine,
+        machine: &mut Machine,
         this: u32,
         dwReserved1: u32,
         dwReserved2: u32,
         dwFlags: u32,
     ) -> u32 {
+        update(machine);
+        let Some(buf) = machine.state.dsound.buffers.get_mut(&this) else {
+            return DSERR_INVALIDCALL;
+        };
+        if buf.addr == 0 {
+            // The primary buffer plays whenever anything is mixed into it.
+            return DS_OK;
+        }
+        buf.looping = dwFlags & DSBPLAY_LOOPING != 0;
+        if !buf.playing {
+            buf.rewind(buf.start);
+            buf.playing = true;
+        }
+        update(machine);
+        DS_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetCurrentPosition(machine: &mut Machine, this: u32, dwNewPosition: u32) -> u32 {
+        update(machine);
+        let Some(buf) = machine.state.dsound.buffers.get_mut(&this) else {
+            return DSERR_INVALIDCALL;
+        };
+        if dwNewPosition >= buf.size {
+            return DSERR_INVALIDPARAM;
+        }
+        buf.rewind((dwNewPosition / buf.format.block_align()) as f64);
+        DS_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetFormat(machine: &mut Machine, this: u32, lpcfxFormat: Option<&WAVEFORMATEX>) -> u32 {
+        let Some(wave) = lpcfxFormat else {
+            return DSERR_INVALIDPARAM;
+        };
+        let Some(buf) = machine.state.dsound.buffers.get_mut(&this) else {
+            return DSERR_INVALIDCALL;
+        };
+        if buf.addr != 0 {
+            // Only the primary buffer's format can be changed.
+            return DSERR_INVALIDCALL;
+        }
+        match Format::from_wave(wave) {
+            Some(format) => buf.format = format,
+            None => log::warn!("SetFormat: unsupported format {wave:?}"),
+        }
+        DS_OK
+    }
+
+    /// Look up the buffer for a control method, which requires the buffer to have
+    /// been created with the corresponding DSBCAPS_CTRL* flag.
+    fn with_control(
+        machine: &mut Machine,
+        this: u32,
+        control: DSBCAPS,
+        f: impl FnOnce(&mut Buffer),
+    ) -> u32 {
+        let Some(buf) = machine.state.dsound.buffers.get_mut(&this) else {
+            return DSERR_INVALIDCALL;
+        };
+        if !buf.flags.contains(control) {
+            return DSERR_CONTROLUNAVAIL;
+        }
+        f(buf);
         DS_OK
     }
 
     #[win32_derive::dllexport]
-    pub fn SetFormat(_machine: &mut Machine, _this: u32, _lpcfxFormat: u32) -> u32 {
+    pub fn GetVolume(machine: &mut Machine, this: u32, plVolume: Option<&mut i32>) -> u32 {
+        let Some(volume) = plVolume else {
+            return DSERR_INVALIDPARAM;
+        };
+        with_control(machine, this, DSBCAPS::CTRLVOLUME, |buf| {
+            *volume = buf.volume
+        })
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetVolume(machine: &mut Machine, this: u32, lVolume: i32) -> u32 {
+        if !(DSBVOLUME_MIN..=DSBVOLUME_MAX).contains(&lVolume) {
+            return DSERR_INVALIDPARAM;
+        }
+        with_control(machine, this, DSBCAPS::CTRLVOLUME, |buf| {
+            buf.volume = lVolume
+        })
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetPan(machine: &mut Machine, this: u32, plPan: Option<&mut i32>) -> u32 {
+        let Some(pan) = plPan else {
+            return DSERR_INVALIDPARAM;
+        };
+        with_control(machine, this, DSBCAPS::CTRLPAN, |buf| *pan = buf.pan)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetPan(machine: &mut Machine, this: u32, lPan: i32) -> u32 {
+        if !(DSBPAN_LEFT..=DSBPAN_RIGHT).contains(&lPan) {
+            return DSERR_INVALIDPARAM;
+        }
+        with_control(machine, this, DSBCAPS::CTRLPAN, |buf| buf.pan = lPan)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetFrequency(machine: &mut Machine, this: u32, pdwFrequency: Option<&mut u32>) -> u32 {
+        let Some(frequency) = pdwFrequency else {
+            return DSERR_INVALIDPARAM;
+        };
+        with_control(machine, this, DSBCAPS::CTRLFREQUENCY, |buf| {
+            *frequency = buf.rate()
+        })
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetFrequency(machine: &mut Machine, this: u32, dwFrequency: u32) -> u32 {
+        let frequency = match dwFrequency {
+            DSBFREQUENCY_ORIGINAL => None,
+            DSBFREQUENCY_MIN..=DSBFREQUENCY_MAX => Some(dwFrequency),
+            _ => return DSERR_INVALIDPARAM,
+        };
+        update(machine);
+        if machine
+            .state
+            .dsound
+            .buffers
+            .get(&this)
+            .map_or(false, |buf| buf.addr == 0)
+        {
+            // The primary buffer's rate is set by its format.
+            return DSERR_CONTROLUNAVAIL;
+        }
+        with_control(machine, this, DSBCAPS::CTRLFREQUENCY, |buf| {
+            buf.set_rate(frequency)
+        })
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Stop(machine: &mut Machine, this: u32) -> u32 {
+        update(machine);
+        let Some(buf) = machine.state.dsound.buffers.get_mut(&this) else {
+            return DSERR_INVALIDCALL;
+        };
+        let mut signal = Vec::new();
+        buf.stop(&mut signal);
+        signal_all(machine, signal);
         DS_OK
     }
 
@@ -178,34 +974,94 @@ mod IDirectSoundBuffer {
         lpvAudioPtr2: u32,
         dwAudioBytes2: u32,
     ) -> u32 {
+        // The guest wrote directly into the buffer's memory.
         DS_OK
     }
 
-    vtable![IDirectSound shims
-        QueryInterface todo,
-        AddRef todo,
+    vtable![IDirectSoundBuffer shims
+        QueryInterface ok,
+        AddRef ok,
         Release ok,
-        GetCaps todo,
+        GetCaps ok,
         GetCurrentPosition ok,
         GetFormat todo,
-        GetVolume todo,
-        GetPan todo,
-        GetFrequency todo,
+        GetVolume ok,
+        GetPan ok,
+        GetFrequency ok,
         GetStatus ok,
         Initialize todo,
         Lock ok,
         Play ok,
-        SetCurrentPosition todo,
+        SetCurrentPosition ok,
         SetFormat ok,
-        SetVolume todo,
-        SetPan todo,
-        SetFrequency todo,
-        Stop todo,
+        SetVolume ok,
+        SetPan ok,
+        SetFrequency ok,
+        Stop ok,
         Unlock ok,
         Restore todo,
     ];
 }
 
+#[win32_derive::shims_from_x86]
+mod IDirectSoundNotify {
+    use super::*;
+
+    /// The object holds its vtable followed by the address of its buffer.
+    pub fn new(machine: &mut Machine, buffer: u32) -> u32 {
+        let dsound = &mut machine.state.dsound;
+        let lpDirectSoundNotify = dsound.heap.alloc(machine.emu.memory.mem(), 8);
+        let vtable = dsound.vtable_IDirectSoundNotify;
+        machine.mem().put::<u32>(lpDirectSoundNotify, vtable);
+        machine.mem().put::<u32>(lpDirectSoundNotify + 4, buffer);
+        lpDirectSoundNotify
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Release(machine: &mut Machine, this: u32) -> u32 {
+        let buffer = machine.mem().get_pod::<u32>(this + 4);
+        let dsound = &mut machine.state.dsound;
+        dsound.heap.free(machine.emu.memory.mem(), this);
+        IDirectSoundBuffer::Release(machine, buffer);
+        0
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetNotificationPositions(
+        machine: &mut Machine,
+        this: u32,
+        dwPositionNotifies: u32,
+        pcPositionNotifies: u32,
+    ) -> u32 {
+        let buffer = machine.mem().get_pod::<u32>(this + 4);
+        let notify = machine
+            .mem()
+            .iter_pod::<DSBPOSITIONNOTIFY>(pcPositionNotifies, dwPositionNotifies)
+            .collect::<Vec<_>>();
+        let Some(buf) = machine.state.dsound.buffers.get_mut(&buffer) else {
+            return DSERR_INVALIDCALL;
+        };
+        if buf.playing {
+            return DSERR_INVALIDCALL;
+        }
+        if notify
+            .iter()
+            .any(|n| n.dwOffset != DSBPN_OFFSETSTOP && n.dwOffset >= buf.size)
+        {
+            return DSERR_INVALIDPARAM;
+        }
+        buf.notify = notify;
+        DS_OK
+    }
+
+    vtable![IDirectSoundNotify shims
+        QueryInterface todo,
+        AddRef todo,
+        Release ok,
+        SetNotificationPositions ok,
+    ];
+}
+
 #[win32_derive::dllexport(1)]
 pub fn DirectSoundCreate(machine: &mut Machine, lpGuid: u32, ppDS: u32, pUnkOuter: u32) -> u32 {
     if DISABLE {
@@ -225,6 +1081,6 @@ pub fn DirectSoundCreate(machine: &mut Machine, lpGuid: u32, ppDS: u32, pUnkOute
 
 #[win32_derive::dllexport(2)]
 pub fn DirectSoundEnumerateA(_machine: &mut Machine, lpDSEnumCallback: u32, lpContext: u32) -> u32 {
-    // No sound devices => no calling the callback.
+    // TODO: report the one device; callers fall back to the default (null) device.
     DS_OK
 }
@@ -2,11 +2,14 @@ use super::{BitmapType, DCTarget, Object, BITMAPINFOHEADER, HDC, HGDIOBJ};
 use crate::{
     machine::Machine,
     winapi::{
-        bitmap::{BitmapMono, BitmapRGBA32, PixelData, BI},
+        bitmap::{build_palette, encode_dib_row, BitmapMono, BitmapRGBA32, PixelData, BI},
         kernel32,
+        types::RECT,
     },
 };
 
+use std::borrow::Cow;
+
 const TRACE_CONTEXT: &'static str = "gdi32/bitmap";
 
 #[allow(dead_code)]
@@ -50,6 +53,52 @@ fn bit_blt(
     }
 }
 
+/// Like bit_blt, but into a mono bitmap with the given row stride in bytes.  As with
+/// Windows' color to mono conversion, source pixels of the color bk become 1 bits
+/// and all others 0.
+fn bit_blt_mono(
+    dst: &mut [u8],
+    dx: usize,
+    dy: usize,
+    dstride: usize,
+    w: usize,
+    h: usize,
+    src: &[[u8; 4]],
+    sx: usize,
+    sy: usize,
+    sstride: usize,
+    bk: [u8; 4],
+) {
+    for row in 0..h {
+        let src_row = &src[(((sy + row) * sstride) + sx)..][..w];
+        let dst_row = &mut dst[(dy + row) * dstride..][..dstride];
+        for (col, p) in src_row.iter().enumerate() {
+            let x = dx + col;
+            let mask = 0x80 >> (x % 8);
+            if p[..3] == bk[..3] {
+                dst_row[x / 8] |= mask;
+            } else {
+                dst_row[x / 8] &= !mask;
+            }
+        }
+    }
+}
+
+/// Expand a mono bitmap to pixels, with 0 bits as fg and 1 bits as bg, as Windows
+/// does when copying mono to color.
+fn mono_to_rgba(bitmap: &BitmapMono, mem: memory::Mem, fg: [u8; 4], bg: [u8; 4]) -> Vec<[u8; 4]> {
+    let stride = BitmapMono::stride(bitmap.width) as usize;
+    let bits = bitmap.pixels.as_slice(mem);
+    let mut pixels = Vec::with_capacity((bitmap.width * bitmap.height) as usize);
+    for row in bits.chunks_exact(stride).take(bitmap.height as usize) {
+        for x in 0..bitmap.width as usize {
+            let set = row[x / 8] & (0x80 >> (x % 8)) != 0;
+            pixels.push(if set { bg } else { fg });
+        }
+    }
+    pixels
+}
+
 const SRCCOPY: u32 = 0xcc0020;
 const NOTSRCCOPY: u32 = 0x330008;
 
@@ -74,83 +123,139 @@ pub fn BitBlt(
     }
 
     let src_dc = machine.state.gdi32.dcs.get(hdcSrc).unwrap();
-    let src_bitmap = match src_dc.target {
-        DCTarget::Memory(bitmap) => {
-            let obj = machine.state.gdi32.objects.get(bitmap).unwrap();
-            match obj {
-                Object::Bitmap(BitmapType::RGBA32(bmp)) => bmp,
-                _ => unimplemented!("{:?}", obj),
+    let dst_dc = machine.state.gdi32.dcs.get(hdc).unwrap();
+
+    // Map the origins to device coordinates and clip at the top/left edges.
+    // TODO: scale the extents for the non-MM_TEXT mapping modes.
+    let (x, y) = dst_dc.to_device(x as i32, y as i32);
+    let (x1, y1) = src_dc.to_device(x1 as i32, y1 as i32);
+    let (dx, dy) = (0.max(-x).max(-x1), 0.max(-y).max(-y1));
+    if dx >= cx as i32 || dy >= cy as i32 {
+        return true;
+    }
+    let (x, y, x1, y1) = (
+        (x + dx) as u32,
+        (y + dy) as u32,
+        (x1 + dx) as u32,
+        (y1 + dy) as u32,
+    );
+    let (cx, cy) = (cx - dx as u32, cy - dy as u32);
+
+    let mem = machine.emu.memory.mem();
+    let (src, src_width, src_height) = match src_dc.target {
+        DCTarget::Memory(bitmap) => match machine.state.gdi32.objects.get(bitmap).unwrap() {
+            Object::Bitmap(BitmapType::RGBA32(bmp)) => {
+                (Cow::Borrowed(bmp.pixels_slice(mem)), bmp.width, bmp.height)
             }
-        }
+            Object::Bitmap(BitmapType::Mono(bmp)) => {
+                let (fg, bg) = (dst_dc.text_color.to_pixel(), dst_dc.bk_color.to_pixel());
+                (
+                    Cow::Owned(mono_to_rgba(bmp, mem, fg, bg)),
+                    bmp.width,
+                    bmp.height,
+                )
+            }
+            obj => unimplemented!("{:?}", obj),
+        },
         _ => todo!(),
     };
-    let src = src_bitmap.pixels_slice(machine.emu.memory.mem());
+    let src_bk = src_dc.bk_color.to_pixel();
+
+    // Clip to the source region.
+    if x1 >= src_width || y1 >= src_height {
+        return true;
+    }
+    let cx = std::cmp::min(cx, src_width - x1);
+    let cy = std::cmp::min(cy, src_height - y1);
 
-    let dst_dc = machine.state.gdi32.dcs.get(hdc).unwrap();
     match dst_dc.target {
         DCTarget::Memory(obj) => {
-            let _bitmap = match machine.state.gdi32.objects.get_mut(obj).unwrap() {
-                Object::Bitmap(bmp) => bmp,
-                _ => unimplemented!("{:?}", obj),
-            };
+            // Copy the source pixels out, because we can't borrow two bitmaps at once.
+            let src = src.into_owned();
+            match machine.state.gdi32.objects.get_mut(obj).unwrap() {
+                Object::Bitmap(BitmapType::RGBA32(dst)) => {
+                    // Clip to the destination region.
+                    if x >= dst.width || y >= dst.height {
+                        return true;
+                    }
+                    let cx = std::cmp::min(cx, dst.width - x);
+                    let cy = std::cmp::min(cy, dst.height - y);
+
+                    let dst_width = dst.width as usize;
+                    bit_blt(
+                        dst.pixels.as_slice_mut(mem),
+                        x as usize,
+                        y as usize,
+                        dst_width,
+                        cx as usize,
+                        cy as usize,
+                        &src,
+                        x1 as usize,
+                        y1 as usize,
+                        src_width as usize,
+                        false,
+                    );
+                }
+                Object::Bitmap(BitmapType::Mono(dst)) => {
+                    if x >= dst.width || y >= dst.height {
+                        return true;
+                    }
+                    let cx = std::cmp::min(cx, dst.width - x);
+                    let cy = std::cmp::min(cy, dst.height - y);
 
-            // TODO: we can't BltBlt here because of borrow checker -- can't have two
-            // bitmaps at the same time.  Need to revisit how object handles work.
-            log::warn!("TODO: BltBlt between bitmaps");
-            // assert_eq!(bitmap.format, PixelFormat::RGBA32);
-            // let dst = bitmap::bytes_as_rgba_mut(bitmap.pixels.as_slice_mut());
-            // bit_blt(
-            //     dst,
-            //     x as usize,
-            //     y as usize,
-            //     bitmap.width as usize,
-            //     cx as usize,
-            //     cy as usize,
-            //     src,
-            //     x1 as usize,
-            //     y1 as usize,
-            //     src_bitmap.width as usize,
-            // );
+                    let dst_stride = BitmapMono::stride(dst.width) as usize;
+                    bit_blt_mono(
+                        dst.pixels.as_slice_mut(mem),
+                        x as usize,
+                        y as usize,
+                        dst_stride,
+                        cx as usize,
+                        cy as usize,
+                        &src,
+                        x1 as usize,
+                        y1 as usize,
+                        src_width as usize,
+                        src_bk,
+                    );
+                }
+                obj => log::warn!("TODO: BitBlt into {:?}", obj),
+            }
         }
         DCTarget::Window(hwnd) => {
             let window = machine.state.user32.windows.get_mut(hwnd).unwrap();
             let dst = window.bitmap_mut(&mut *machine.host);
 
-            // Clip to src/dst regions.
-            if x >= dst.width
-                || x1 >= src_bitmap.width
-                || y >= dst.height
-                || y1 >= src_bitmap.height
-            {
+            // Clip to the destination region.
+            if x >= dst.width || y >= dst.height {
                 return true;
             }
-            let cx = std::cmp::min(cx, std::cmp::min(dst.width - x, src_bitmap.width - x1));
-            let cy = std::cmp::min(cy, std::cmp::min(dst.height - y, src_bitmap.height - y1));
+            let cx = std::cmp::min(cx, dst.width - x);
+            let cy = std::cmp::min(cy, dst.height - y);
 
+            let dst_width = dst.width as usize;
             bit_blt(
-                dst.pixels.as_slice_mut(),
+                dst.pixels.as_slice_mut(mem),
                 x as usize,
                 y as usize,
-                dst.width as usize,
+                dst_width,
                 cx as usize,
                 cy as usize,
-                src,
+                &src,
                 x1 as usize,
                 y1 as usize,
-                src_bitmap.width as usize,
+                src_width as usize,
                 true,
             );
 
-            window.flush_pixels(machine.emu.memory.mem());
-        }
-        DCTarget::DirectDrawSurface(ptr) => {
-            let surface = machine.state.ddraw.surfaces.get_mut(&ptr).unwrap();
-
-            assert!(x == 0 && y == 0 && x1 == 0 && y1 == 0);
-            assert!(cx == surface.width && cy == surface.height);
-            assert!(surface.width == src_bitmap.width && surface.height == src_bitmap.height);
-
-            surface.host.write_pixels(src);
+            let rect = RECT {
+                left: x as i32,
+                top: y as i32,
+                right: (x + cx) as i32,
+                bottom: (y + cy) as i32,
+            };
+            if window.flush_pixels(machine.emu.memory.mem(), rect) {
+                machine.presented();
+            }
         }
     }
     true
@@ -190,18 +295,7 @@ pub fn CreateBitmap(
 ) -> HGDIOBJ {
     assert_eq!(nPlanes, 1);
     let bitmap = match nBitCount {
-        1 => {
-            let stride = BitmapMono::stride(nWidth);
-            let len = (nHeight * stride) as usize;
-            let mut pixels = Vec::with_capacity(len);
-            pixels.resize(len, 0);
-            let bitmap = BitmapMono {
-                width: nWidth,
-                height: nHeight,
-                pixels: PixelData::Owned(pixels.into_boxed_slice()),
-            };
-            BitmapType::Mono(bitmap)
-        }
+        1 => BitmapType::Mono(new_mono_bitmap(nWidth, nHeight)),
         _ => unimplemented!(),
     };
     machine.state.gdi32.objects.add(Object::Bitmap(bitmap))
@@ -268,38 +362,57 @@ pub fn CreateDIBSection(
         .add(Object::Bitmap(BitmapType::RGBA32(bitmap)))
 }
 
+fn new_mono_bitmap(width: u32, height: u32) -> BitmapMono {
+    let stride = BitmapMono::stride(width);
+    let len = (height * stride) as usize;
+    let mut pixels = Vec::with_capacity(len);
+    pixels.resize(len, 0);
+    BitmapMono {
+        width,
+        height,
+        pixels: PixelData::Owned(pixels.into_boxed_slice()),
+    }
+}
+
 #[win32_derive::dllexport]
 pub fn CreateCompatibleBitmap(machine: &mut Machine, hdc: HDC, cx: u32, cy: u32) -> HGDIOBJ {
-    let dc = machine.state.gdi32.dcs.get(hdc).unwrap();
-    match dc.target {
-        DCTarget::Memory(hbitmap) => match machine.state.gdi32.objects.get_mut(hbitmap).unwrap() {
-            Object::Bitmap(BitmapType::RGBA32(_)) => {}
-            Object::Bitmap(_) => {
-                // Cryogoat does a series of:
-                //   let dc1 = GetDC(0); // desktop dc
-                //   let dc2 = CreateCompatibleDc(dc1); // memory dc
-                //   CreateCompatibleBitmap(dc2);
-                // The MSDN docs say this sequence should produce a 1bpp bitmap because
-                // the initial state of dc1 is monochrome, but I think cryogoat doesn't expect this (?)
-            }
-            _ => todo!(),
+    let gdi32 = &machine.state.gdi32;
+    let dc = match gdi32.dcs.get(hdc) {
+        None => return HGDIOBJ::null(),
+        Some(dc) => dc,
+    };
+    // The new bitmap matches the format of the bitmap selected into a memory DC,
+    // and the screen format otherwise.
+    let mono = match dc.target {
+        DCTarget::Memory(hbitmap) if hbitmap.to_raw() == gdi32.default_bitmap.to_raw() => {
+            // Cryogoat does a series of:
+            //   let dc1 = GetDC(0); // desktop dc
+            //   let dc2 = CreateCompatibleDc(dc1); // memory dc
+            //   CreateCompatibleBitmap(dc2);
+            // The MSDN docs say this sequence should produce a 1bpp bitmap because
+            // the initial state of dc1 is monochrome, but I think cryogoat doesn't expect this (?)
+            false
+        }
+        DCTarget::Memory(hbitmap) => match gdi32.objects.get(hbitmap).unwrap() {
+            Object::Bitmap(BitmapType::RGBA32(_)) => false,
+            Object::Bitmap(BitmapType::Mono(_)) => true,
+            _ => unreachable!(),
         },
-        DCTarget::Window(_) => {} // screen has known format
-        _ => todo!(),
+        DCTarget::Window(_) => false,
     };
 
-    let mut pixels = Vec::new();
-    pixels.resize((cx * cy) as usize, [0; 4]);
-    let bitmap = BitmapRGBA32 {
-        width: cx,
-        height: cy,
-        pixels: PixelData::Owned(pixels.into_boxed_slice()),
+    let bitmap = if mono {
+        BitmapType::Mono(new_mono_bitmap(cx, cy))
+    } else {
+        let mut pixels = Vec::new();
+        pixels.resize((cx * cy) as usize, [0; 4]);
+        BitmapType::RGBA32(BitmapRGBA32 {
+            width: cx,
+            height: cy,
+            pixels: PixelData::Owned(pixels.into_boxed_slice()),
+        })
     };
-    machine
-        .state
-        .gdi32
-        .objects
-        .add(Object::Bitmap(BitmapType::RGBA32(bitmap)))
+    machine.state.gdi32.objects.add(Object::Bitmap(bitmap))
 }
 
 #[win32_derive::dllexport]
@@ -337,9 +450,36 @@ pub fn SetDIBitsToDevice(
             cLines as usize,
         )),
     );
+    set_dibits_to_device(
+        machine,
+        hdc,
+        (xDest as i32, yDest as i32),
+        w,
+        h,
+        (xSrc, ySrc),
+        &src_bitmap,
+    );
+    cLines
+}
+
+/// Copy a w x h region of a parsed DIB to a DC, as shared by SetDIBitsToDevice
+/// and metafile playback.  The copy is clipped to the destination.
+pub fn set_dibits_to_device(
+    machine: &mut Machine,
+    hdc: HDC,
+    (xDest, yDest): (i32, i32),
+    w: u32,
+    h: u32,
+    (xSrc, ySrc): (u32, u32),
+    src_bitmap: &BitmapRGBA32,
+) {
     let src = src_bitmap.pixels_slice(machine.emu.memory.mem());
 
     let dc = machine.state.gdi32.dcs.get(hdc).unwrap();
+    let (xDest, yDest) = match dc.to_device(xDest, yDest) {
+        (x, y) if x >= 0 && y >= 0 => (x as u32, y as u32),
+        _ => todo!("SetDIBitsToDevice clipping"),
+    };
     let (dst, flush_alpha) = match dc.target {
         DCTarget::Memory(hbitmap) => match machine.state.gdi32.objects.get_mut(hbitmap).unwrap() {
             Object::Bitmap(BitmapType::RGBA32(b)) => (b, false),
@@ -349,14 +489,20 @@ pub fn SetDIBitsToDevice(
             let window = machine.state.user32.windows.get_mut(hwnd).unwrap();
             (window.bitmap_mut(&mut *machine.host), true)
         }
-        _ => todo!(),
     };
 
+    let dst_width = dst.width as usize;
+    let w = w
+        .min(dst.width.saturating_sub(xDest))
+        .min(src_bitmap.width.saturating_sub(xSrc));
+    let h = h
+        .min(dst.height.saturating_sub(yDest))
+        .min(src_bitmap.height.saturating_sub(ySrc));
     bit_blt(
-        &mut dst.pixels.as_slice_mut(),
+        dst.pixels.as_slice_mut(machine.emu.memory.mem()),
         xDest as usize,
         yDest as usize,
-        dst.width as usize,
+        dst_width,
         w as usize,
         h as usize,
         src,
@@ -369,12 +515,18 @@ pub fn SetDIBitsToDevice(
     match dc.target {
         DCTarget::Window(hwnd) => {
             let window = machine.state.user32.windows.get_mut(hwnd).unwrap();
-            window.flush_pixels(machine.emu.memory.mem());
+            let rect = RECT {
+                left: xDest as i32,
+                top: yDest as i32,
+                right: (xDest + w) as i32,
+                bottom: (yDest + h) as i32,
+            };
+            if window.flush_pixels(machine.emu.memory.mem(), rect) {
+                machine.presented();
+            }
         }
         _ => {}
     }
-
-    cLines
 }
 
 #[win32_derive::dllexport]
@@ -397,3 +549,147 @@ pub fn StretchDIBits(
     log::warn!("TODO: StretchDIBits");
     SrcHeight // success
 }
+
+/// Look up the RGBA32 bitmap backing a handle, as used by the DIB conversion functions.
+fn rgba32_bitmap(gdi32: &mut super::State, hbm: HGDIOBJ) -> Option<&mut BitmapRGBA32> {
+    match gdi32.objects.get_mut(hbm) {
+        Some(Object::Bitmap(BitmapType::RGBA32(bmp))) => Some(bmp),
+        Some(obj) => {
+            log::warn!("unimplemented: DIB conversion of {obj:?}");
+            None
+        }
+        None => None,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn GetDIBits(
+    machine: &mut Machine,
+    hdc: HDC,
+    hbm: HGDIOBJ,
+    start: u32,
+    cLines: u32,
+    lpvBits: u32,
+    lpbmi: u32,
+    usage: u32,
+) -> i32 {
+    if usage != DIB_RGB_COLORS {
+        todo!()
+    }
+    let mem = machine.emu.memory.mem();
+    let header = mem.view_mut::<BITMAPINFOHEADER>(lpbmi);
+    if header.biSize != std::mem::size_of::<BITMAPINFOHEADER>() as u32 {
+        todo!()
+    }
+    let bitmap = match rgba32_bitmap(&mut machine.state.gdi32, hbm) {
+        Some(bmp) => bmp,
+        None => return 0,
+    };
+
+    if lpvBits == 0 {
+        // Caller is querying the bitmap format.
+        if header.biBitCount == 0 {
+            header.biWidth = bitmap.width;
+            header.biHeight = bitmap.height;
+            header.biPlanes = 1;
+            header.biBitCount = 32;
+            header.biCompression = BI::RGB as u32;
+            header.biSizeImage = header.stride() * bitmap.height;
+        }
+        return 1;
+    }
+
+    if header.biWidth != bitmap.width {
+        todo!("GetDIBits with different width");
+    }
+    match header.compression().unwrap() {
+        BI::RGB => {}
+        c => todo!("GetDIBits to {c:?}"),
+    }
+
+    let pixels = bitmap.pixels_slice(mem);
+    let palette = if header.biBitCount <= 8 {
+        // The caller's BITMAPINFO has room for biClrUsed entries, or all the
+        // bit count allows if that's 0.
+        let len = match header.biClrUsed {
+            0 => 1 << header.biBitCount,
+            n => n.min(1 << header.biBitCount),
+        };
+        let palette = build_palette(pixels, len as usize);
+        let table = mem.sub(lpbmi + header.biSize, len * 4).as_mut_slice_todo();
+        table.fill(0);
+        for (i, entry) in palette.iter().enumerate() {
+            table[i * 4..][..4].copy_from_slice(entry);
+        }
+        palette
+    } else {
+        Vec::new()
+    };
+
+    // DIB scan lines count from the bottom unless the DIB is top-down.
+    let stride = header.stride();
+    let lines = std::cmp::min(cLines, bitmap.height.saturating_sub(start));
+    for line in 0..lines {
+        let scan = start + line;
+        let y = if header.is_top_down() {
+            scan
+        } else {
+            bitmap.height - scan - 1
+        };
+        let src = &pixels[(y * bitmap.width) as usize..][..bitmap.width as usize];
+        let row = mem.sub(lpvBits + line * stride, stride).as_mut_slice_todo();
+        encode_dib_row(src, header.biBitCount, &palette, row);
+    }
+    lines as i32
+}
+
+#[win32_derive::dllexport]
+pub fn SetDIBits(
+    machine: &mut Machine,
+    hdc: HDC,
+    hbm: HGDIOBJ,
+    start: u32,
+    cLines: u32,
+    lpvBits: u32,
+    lpbmi: Option<&BITMAPINFOHEADER>,
+    usage: u32,
+) -> i32 {
+    if usage != DIB_RGB_COLORS {
+        todo!()
+    }
+    let header = lpbmi.unwrap();
+    let src = BitmapRGBA32::parse(
+        header,
+        Some((
+            machine.mem().slice(lpvBits..).as_slice_todo(),
+            cLines as usize,
+        )),
+    );
+    let mem = machine.emu.memory.mem();
+    let src = src.pixels_slice(mem);
+    let bitmap = match rgba32_bitmap(&mut machine.state.gdi32, hbm) {
+        Some(bmp) => bmp,
+        None => return 0,
+    };
+    let (stride, height) = (bitmap.width as usize, bitmap.height);
+    let width = std::cmp::min(bitmap.width, header.width()) as usize;
+    let lines = std::cmp::min(cLines, height.saturating_sub(start));
+    // parse() yields lines top-down, so a bottom-up DIB's lines start at the
+    // bottom of the range.
+    let top = if header.is_top_down() {
+        start
+    } else {
+        height - start - lines
+    };
+    let dst = bitmap.pixels.as_slice_mut(mem);
+    for line in 0..lines as usize {
+        let src_line = if header.is_top_down() {
+            line
+        } else {
+            line + cLines as usize - lines as usize
+        };
+        let dst_row = &mut dst[(top as usize + line) * stride..][..width];
+        dst_row.copy_from_slice(&src[src_line * header.width() as usize..][..width]);
+    }
+    lines as i32
+}
@@ -1,10 +1,7 @@
-use super::{BitmapType, Object, HGDIOBJ, R2};
+use super::{COLORREF, HGDIOBJ, R2, SIZE};
 use crate::{
     machine::Machine,
-    winapi::{
-        bitmap::{BitmapMono, PixelData},
-        types::{HANDLE, HWND},
-    },
+    winapi::types::{HANDLE, HWND, POINT},
 };
 
 const TRACE_CONTEXT: &'static str = "gdi32/dc";
@@ -12,14 +9,50 @@ const TRACE_CONTEXT: &'static str = "gdi32/dc";
 pub type HDC = HANDLE<DC>;
 
 /// Target device for a DC.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub enum DCTarget {
     Memory(HGDIOBJ), // aka Bitmap
     Window(HWND),
-    DirectDrawSurface(u32),
 }
 
-#[derive(Debug)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    win32_derive::TryFromEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum BkMode {
+    TRANSPARENT = 1,
+    OPAQUE = 2,
+}
+
+/// Mapping modes, which determine how logical coordinates map to device pixels.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    win32_derive::TryFromEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum MM {
+    TEXT = 1,
+    LOMETRIC = 2,
+    HIMETRIC = 3,
+    LOENGLISH = 4,
+    HIENGLISH = 5,
+    TWIPS = 6,
+    ISOTROPIC = 7,
+    ANISOTROPIC = 8,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct DC {
     // TODO: it's unclear to me what the representation of a DC ought to be.
     // DirectDraw can also create a DC, and DirectDraw (as a DLL that came
@@ -36,6 +69,21 @@ pub struct DC {
     // per object type.
     pub brush: HGDIOBJ,
     pub pen: HGDIOBJ,
+
+    pub text_color: COLORREF,
+    pub bk_color: COLORREF,
+    pub bk_mode: BkMode,
+    pub brush_org: (i32, i32),
+
+    // Logical to device coordinate mapping; see to_device().
+    pub map_mode: MM,
+    pub window_org: (i32, i32),
+    pub window_ext: (i32, i32),
+    pub viewport_org: (i32, i32),
+    pub viewport_ext: (i32, i32),
+
+    /// Copies of this DC pushed by SaveDC, oldest first.
+    saved: Vec<DC>,
 }
 
 impl DC {
@@ -47,23 +95,91 @@ impl DC {
             y: 0,
             brush: Default::default(),
             pen: Default::default(),
+            text_color: COLORREF((0, 0, 0)),
+            bk_color: COLORREF((0xff, 0xff, 0xff)),
+            bk_mode: BkMode::OPAQUE,
+            brush_org: (0, 0),
+            map_mode: MM::TEXT,
+            window_org: (0, 0),
+            window_ext: (1, 1),
+            viewport_org: (0, 0),
+            viewport_ext: (1, 1),
+            saved: Vec::new(),
         }
     }
 
     pub fn new_memory(machine: &mut Machine) -> Self {
-        // MSDN says: "When a memory device context is created, it initially has a 1-by-1 monochrome bitmap selected into it."
-        // SkiFree depends on this!
-        let bitmap = BitmapMono {
-            width: 1,
-            height: 1,
-            pixels: PixelData::Ptr(0, 0),
+        Self::new(DCTarget::Memory(machine.state.gdi32.default_bitmap))
+    }
+
+    /// Whether the object is selected into this DC or any of its saved states.
+    pub fn selects(&self, obj: HGDIOBJ) -> bool {
+        let raw = obj.to_raw();
+        let bitmap = match self.target {
+            DCTarget::Memory(bitmap) => bitmap.to_raw() == raw,
+            _ => false,
         };
-        let hobj = machine
-            .state
-            .gdi32
-            .objects
-            .add(Object::Bitmap(BitmapType::Mono(bitmap)));
-        Self::new(DCTarget::Memory(hobj))
+        bitmap
+            || self.brush.to_raw() == raw
+            || self.pen.to_raw() == raw
+            || self.saved.iter().any(|dc| dc.selects(obj))
+    }
+
+    /// Map a point from logical to device coordinates.
+    pub fn to_device(&self, x: i32, y: i32) -> (i32, i32) {
+        let map = |l: i32, worg: i32, wext: i32, vorg: i32, vext: i32| {
+            ((l - worg) as i64 * vext as i64 / wext as i64) as i32 + vorg
+        };
+        (
+            map(
+                x,
+                self.window_org.0,
+                self.window_ext.0,
+                self.viewport_org.0,
+                self.viewport_ext.0,
+            ),
+            map(
+                y,
+                self.window_org.1,
+                self.window_ext.1,
+                self.viewport_org.1,
+                self.viewport_ext.1,
+            ),
+        )
+    }
+
+    fn set_map_mode(&mut self, mode: MM) {
+        // The fixed modes assume 96 pixels per inch, with y increasing upwards.
+        let fixed = |units_per_inch: i32| ((units_per_inch, units_per_inch), (96, -96));
+        let (window_ext, viewport_ext) = match mode {
+            MM::TEXT => ((1, 1), (1, 1)),
+            MM::LOMETRIC => fixed(254),
+            MM::HIMETRIC => fixed(2540),
+            MM::LOENGLISH => fixed(100),
+            MM::HIENGLISH => fixed(1000),
+            MM::TWIPS => fixed(1440),
+            // The isotropic/anisotropic modes keep the current extents.
+            MM::ISOTROPIC | MM::ANISOTROPIC => (self.window_ext, self.viewport_ext),
+        };
+        self.map_mode = mode;
+        self.window_ext = window_ext;
+        self.viewport_ext = viewport_ext;
+        self.fix_isotropic();
+    }
+
+    /// In MM_ISOTROPIC, shrink the viewport extent so both axes use the same scale.
+    fn fix_isotropic(&mut self) {
+        if self.map_mode != MM::ISOTROPIC {
+            return;
+        }
+        let (wx, wy) = (self.window_ext.0 as f64, self.window_ext.1 as f64);
+        let (vx, vy) = (self.viewport_ext.0 as f64, self.viewport_ext.1 as f64);
+        let (sx, sy) = ((vx / wx).abs(), (vy / wy).abs());
+        if sx < sy {
+            self.viewport_ext.1 = (wy.abs() * sx).round().copysign(vy) as i32;
+        } else if sy < sx {
+            self.viewport_ext.0 = (wx.abs() * sy).round().copysign(vx) as i32;
+        }
     }
 }
 
@@ -75,9 +191,189 @@ pub fn CreateCompatibleDC(machine: &mut Machine, hdc: HDC) -> HDC {
 }
 
 #[win32_derive::dllexport]
-pub fn DeleteDC(_machine: &mut Machine, hdc: u32) -> u32 {
-    log::warn!("todo: DeleteDC({hdc:x})");
-    0 // fail
+pub fn DeleteDC(machine: &mut Machine, hdc: HDC) -> bool {
+    match machine.state.gdi32.dcs.get(hdc) {
+        // Deleting a DC doesn't delete the objects selected into it; they become
+        // deletable via DeleteObject once no DC references them.
+        Some(DC {
+            target: DCTarget::Memory(_),
+            ..
+        }) => {
+            machine.state.gdi32.dcs.remove(hdc);
+            true
+        }
+        Some(dc) => {
+            log::warn!("DeleteDC({hdc:x}): not a memory DC: {:?}", dc.target);
+            false
+        }
+        None => false,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn SaveDC(machine: &mut Machine, hdc: HDC) -> i32 {
+    let dc = match machine.state.gdi32.dcs.get_mut(hdc) {
+        Some(dc) => dc,
+        None => return 0,
+    };
+    let saved = std::mem::take(&mut dc.saved);
+    let copy = dc.clone();
+    dc.saved = saved;
+    dc.saved.push(copy);
+    dc.saved.len() as i32
+}
+
+/// nSavedDC is either a level as returned by SaveDC, or negative to count back
+/// from the most recent save.
+#[win32_derive::dllexport]
+pub fn RestoreDC(machine: &mut Machine, hdc: HDC, nSavedDC: i32) -> bool {
+    let dc = match machine.state.gdi32.dcs.get_mut(hdc) {
+        Some(dc) => dc,
+        None => return false,
+    };
+    let depth = dc.saved.len() as i32;
+    let level = if nSavedDC < 0 {
+        depth + 1 + nSavedDC
+    } else {
+        nSavedDC
+    };
+    if level < 1 || level > depth {
+        return false;
+    }
+    dc.saved.truncate(level as usize);
+    let restored = dc.saved.pop().unwrap();
+    let saved = std::mem::take(&mut dc.saved);
+    *dc = restored;
+    dc.saved = saved;
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn SetMapMode(machine: &mut Machine, hdc: HDC, iMode: Result<MM, u32>) -> u32 {
+    let dc = match machine.state.gdi32.dcs.get_mut(hdc) {
+        Some(dc) => dc,
+        None => return 0,
+    };
+    let mode = match iMode {
+        Ok(mode) => mode,
+        Err(_) => return 0,
+    };
+    let prev = dc.map_mode;
+    dc.set_map_mode(mode);
+    prev as u32
+}
+
+#[win32_derive::dllexport]
+pub fn GetMapMode(machine: &mut Machine, hdc: HDC) -> u32 {
+    match machine.state.gdi32.dcs.get(hdc) {
+        Some(dc) => dc.map_mode as u32,
+        None => 0,
+    }
+}
+
+/// Shared implementation of the Set*OrgEx functions: replace one of the DC's
+/// coordinate pairs, returning the previous value via lppt.
+fn set_origin(
+    machine: &mut Machine,
+    hdc: HDC,
+    field: impl FnOnce(&mut DC) -> &mut (i32, i32),
+    (x, y): (i32, i32),
+    lppt: Option<&mut POINT>,
+) -> bool {
+    let dc = match machine.state.gdi32.dcs.get_mut(hdc) {
+        Some(dc) => dc,
+        None => return false,
+    };
+    let (px, py) = std::mem::replace(field(dc), (x, y));
+    if let Some(pt) = lppt {
+        *pt = POINT {
+            x: px as u32,
+            y: py as u32,
+        };
+    }
+    true
+}
+
+/// Shared implementation of the Set*ExtEx functions.  Extents can only be
+/// changed in the isotropic/anisotropic mapping modes; elsewhere this is a no-op.
+fn set_extent(
+    machine: &mut Machine,
+    hdc: HDC,
+    field: impl FnOnce(&mut DC) -> &mut (i32, i32),
+    (x, y): (i32, i32),
+    lpsz: Option<&mut SIZE>,
+) -> bool {
+    let dc = match machine.state.gdi32.dcs.get_mut(hdc) {
+        Some(dc) => dc,
+        None => return false,
+    };
+    if x == 0 || y == 0 {
+        return false;
+    }
+    if !matches!(dc.map_mode, MM::ISOTROPIC | MM::ANISOTROPIC) {
+        return true;
+    }
+    let (cx, cy) = std::mem::replace(field(dc), (x, y));
+    dc.fix_isotropic();
+    if let Some(size) = lpsz {
+        *size = SIZE { cx, cy };
+    }
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn SetWindowOrgEx(
+    machine: &mut Machine,
+    hdc: HDC,
+    x: i32,
+    y: i32,
+    lppt: Option<&mut POINT>,
+) -> bool {
+    set_origin(machine, hdc, |dc| &mut dc.window_org, (x, y), lppt)
+}
+
+#[win32_derive::dllexport]
+pub fn SetViewportOrgEx(
+    machine: &mut Machine,
+    hdc: HDC,
+    x: i32,
+    y: i32,
+    lppt: Option<&mut POINT>,
+) -> bool {
+    set_origin(machine, hdc, |dc| &mut dc.viewport_org, (x, y), lppt)
+}
+
+#[win32_derive::dllexport]
+pub fn SetWindowExtEx(
+    machine: &mut Machine,
+    hdc: HDC,
+    x: i32,
+    y: i32,
+    lpsz: Option<&mut SIZE>,
+) -> bool {
+    set_extent(machine, hdc, |dc| &mut dc.window_ext, (x, y), lpsz)
+}
+
+#[win32_derive::dllexport]
+pub fn SetViewportExtEx(
+    machine: &mut Machine,
+    hdc: HDC,
+    x: i32,
+    y: i32,
+    lpsz: Option<&mut SIZE>,
+) -> bool {
+    set_extent(machine, hdc, |dc| &mut dc.viewport_ext, (x, y), lpsz)
+}
+
+#[win32_derive::dllexport]
+pub fn SetBrushOrgEx(
+    machine: &mut Machine,
+    hdc: HDC,
+    x: i32,
+    y: i32,
+    lppt: Option<&mut POINT>,
+) -> bool {
+    set_origin(machine, hdc, |dc| &mut dc.brush_org, (x, y), lppt)
 }
 
 #[derive(Debug, win32_derive::TryFromEnum)]
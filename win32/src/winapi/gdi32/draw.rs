@@ -1,43 +1,73 @@
 //! Pens, brushes, color.
 
-use super::{DCTarget, Object, CLR_INVALID, HDC, HGDIOBJ};
+use super::{BitmapType, BkMode, DCTarget, Object, CLR_INVALID, DC, HDC, HGDIOBJ};
 use crate::{
     machine::Machine,
-    winapi::types::{POINT, RECT},
+    winapi::{
+        bitmap::BitmapMono,
+        stack_args::ArrayWithSize,
+        types::{POINT, RECT},
+    },
 };
 
 const TRACE_CONTEXT: &'static str = "gdi32/draw";
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub struct COLORREF(pub (u8, u8, u8));
 impl COLORREF {
     pub fn from_u32(raw: u32) -> Self {
         Self((raw as u8, (raw >> 8) as u8, (raw >> 16) as u8))
     }
+    pub fn to_u32(&self) -> u32 {
+        let (r, g, b) = self.0;
+        r as u32 | (g as u32) << 8 | (b as u32) << 16
+    }
     pub fn to_pixel(&self) -> [u8; 4] {
         let (r, g, b) = self.0;
         [r, g, b, 0xff]
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct Pen {
     pub color: COLORREF,
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct Brush {
     pub color: Option<COLORREF>,
 }
 
 #[win32_derive::dllexport]
-pub fn SetBkMode(_machine: &mut Machine, hdc: HDC, mode: i32) -> i32 {
-    0 // fail
+pub fn SetBkMode(machine: &mut Machine, hdc: HDC, mode: Result<BkMode, u32>) -> i32 {
+    match (machine.state.gdi32.dcs.get_mut(hdc), mode) {
+        (Some(dc), Ok(mode)) => std::mem::replace(&mut dc.bk_mode, mode) as i32,
+        _ => 0, // fail
+    }
 }
 
 #[win32_derive::dllexport]
-pub fn SetBkColor(_machine: &mut Machine, hdc: HDC, color: u32) -> u32 {
-    CLR_INVALID // fail
+pub fn GetBkMode(machine: &mut Machine, hdc: HDC) -> i32 {
+    match machine.state.gdi32.dcs.get(hdc) {
+        Some(dc) => dc.bk_mode as i32,
+        None => 0,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn SetBkColor(machine: &mut Machine, hdc: HDC, color: u32) -> u32 {
+    match machine.state.gdi32.dcs.get_mut(hdc) {
+        Some(dc) => std::mem::replace(&mut dc.bk_color, COLORREF::from_u32(color)).to_u32(),
+        None => CLR_INVALID, // fail
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn GetBkColor(machine: &mut Machine, hdc: HDC) -> u32 {
+    match machine.state.gdi32.dcs.get(hdc) {
+        Some(dc) => dc.bk_color.to_u32(),
+        None => CLR_INVALID,
+    }
 }
 
 #[derive(Debug, win32_derive::TryFromEnum)]
@@ -73,7 +103,205 @@ pub fn MoveToEx(machine: &mut Machine, hdc: HDC, x: u32, y: u32, lppt: Option<&m
     true
 }
 
-fn ascending(a: u32, b: u32) -> (u32, u32) {
+/// The pixels a DC draws into, as handed to the drawing functions by draw_to_dc.
+/// Coordinates are device coordinates, and drawing is clipped to the pixels.
+pub struct Canvas<'a> {
+    pixels: CanvasPixels<'a>,
+    width: i32,
+    height: i32,
+    /// Further limit on where drawing lands, as with ExtTextOut's ETO_CLIPPED.
+    pub clip: Option<RECT>,
+    /// Bounds of what was drawn, to push to the screen afterwards.
+    drawn: Option<RECT>,
+}
+
+enum CanvasPixels<'a> {
+    RGBA32(&'a mut [[u8; 4]]),
+    /// Rows of `stride` bytes, leftmost pixel in the high bit, set for white.
+    Mono(&'a mut [u8], usize),
+}
+
+impl<'a> Canvas<'a> {
+    fn new(pixels: CanvasPixels<'a>, width: u32, height: u32) -> Self {
+        Canvas {
+            pixels,
+            width: width as i32,
+            height: height as i32,
+            clip: None,
+            drawn: None,
+        }
+    }
+
+    /// Clip a rect, given as left, top, right, bottom with the latter exclusive,
+    /// to where drawing may land.
+    fn clipped(&self, (left, top, right, bottom): (i32, i32, i32, i32)) -> Option<RECT> {
+        let mut rect = RECT {
+            left: left.max(0),
+            top: top.max(0),
+            right: right.min(self.width),
+            bottom: bottom.min(self.height),
+        };
+        if let Some(clip) = &self.clip {
+            rect.left = rect.left.max(clip.left);
+            rect.top = rect.top.max(clip.top);
+            rect.right = rect.right.min(clip.right);
+            rect.bottom = rect.bottom.min(clip.bottom);
+        }
+        if rect.left >= rect.right || rect.top >= rect.bottom {
+            return None;
+        }
+        Some(rect)
+    }
+
+    fn mark_drawn(&mut self, rect: RECT) {
+        self.drawn = Some(match &self.drawn {
+            Some(drawn) => drawn.union(&rect),
+            None => rect,
+        });
+    }
+
+    /// Fill the rect from left, top up to but excluding right, bottom.
+    pub fn fill(&mut self, left: i32, top: i32, right: i32, bottom: i32, color: COLORREF) {
+        let Some(rect) = self.clipped((left, top, right, bottom)) else {
+            return;
+        };
+        let width = self.width as usize;
+        let (x0, x1) = (rect.left as usize, rect.right as usize);
+        for y in rect.top as usize..rect.bottom as usize {
+            match &mut self.pixels {
+                CanvasPixels::RGBA32(pixels) => {
+                    pixels[y * width + x0..y * width + x1].fill(color.to_pixel());
+                }
+                CanvasPixels::Mono(pixels, stride) => {
+                    let row = &mut pixels[y * *stride..][..*stride];
+                    for x in x0..x1 {
+                        let mask = 0x80 >> (x % 8);
+                        if color.is_light() {
+                            row[x / 8] |= mask;
+                        } else {
+                            row[x / 8] &= !mask;
+                        }
+                    }
+                }
+            }
+        }
+        self.mark_drawn(rect);
+    }
+
+    pub fn plot(&mut self, x: i32, y: i32, color: COLORREF) {
+        self.fill(x, y, x + 1, y + 1, color);
+    }
+
+    /// Draw a line including both endpoints.
+    pub fn line(&mut self, (x0, y0): (i32, i32), (x1, y1): (i32, i32), color: COLORREF) {
+        if x0 == x1 || y0 == y1 {
+            let (left, right) = ascending(x0, x1);
+            let (top, bottom) = ascending(y0, y1);
+            self.fill(left, top, right + 1, bottom + 1, color);
+            return;
+        }
+        // Bresenham, stepping along both axes.
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut x, mut y) = (x0, y0);
+        let mut err = dx + dy;
+        loop {
+            self.plot(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+}
+
+impl COLORREF {
+    /// Whether the color maps to white in a monochrome bitmap.
+    fn is_light(&self) -> bool {
+        let (r, g, b) = self.0;
+        r as u32 + g as u32 + b as u32 >= 3 * 0x80
+    }
+}
+
+/// Draw into the pixels of a DC's target, then push what was drawn to the
+/// screen if the DC is a window's.  Returns false if the DC has nothing to draw into.
+pub fn draw_to_dc(machine: &mut Machine, hdc: HDC, draw: impl FnOnce(&mut Canvas)) -> bool {
+    let Some(dc) = machine.state.gdi32.dcs.get(hdc) else {
+        return false;
+    };
+    let mem = machine.emu.memory.mem();
+    match dc.target {
+        DCTarget::Memory(hbitmap) => {
+            let mut canvas = match machine.state.gdi32.objects.get_mut(hbitmap) {
+                Some(Object::Bitmap(BitmapType::RGBA32(bitmap))) => Canvas::new(
+                    CanvasPixels::RGBA32(bitmap.pixels.as_slice_mut(mem)),
+                    bitmap.width,
+                    bitmap.height,
+                ),
+                Some(Object::Bitmap(BitmapType::Mono(bitmap))) => Canvas::new(
+                    CanvasPixels::Mono(
+                        bitmap.pixels.as_slice_mut(mem),
+                        BitmapMono::stride(bitmap.width) as usize,
+                    ),
+                    bitmap.width,
+                    bitmap.height,
+                ),
+                _ => return false,
+            };
+            draw(&mut canvas);
+        }
+        DCTarget::Window(hwnd) => {
+            let Some(window) = machine.state.user32.windows.get_mut(hwnd) else {
+                return false;
+            };
+            let bitmap = window.bitmap_mut(&mut *machine.host);
+            let mut canvas = Canvas::new(
+                CanvasPixels::RGBA32(bitmap.pixels.as_slice_mut(mem)),
+                bitmap.width,
+                bitmap.height,
+            );
+            draw(&mut canvas);
+            if let Some(rect) = canvas.drawn {
+                if window.flush_pixels(mem, rect) {
+                    machine.presented();
+                }
+            }
+        }
+    }
+    true
+}
+
+/// The colors a DC's pen and brush draw in, with None for a null brush.
+/// A DC without a pen or brush selected has Windows' defaults of a black pen
+/// and a white brush.
+fn pen_and_brush(machine: &Machine, dc: &DC) -> (COLORREF, Option<COLORREF>) {
+    let objects = &machine.state.gdi32.objects;
+    let pen = match objects.get(dc.pen) {
+        Some(Object::Pen(pen)) => pen.color,
+        _ => COLORREF((0, 0, 0)),
+    };
+    let brush = match objects.get(dc.brush) {
+        Some(Object::Brush(brush)) => brush.color,
+        _ => Some(COLORREF((0xff, 0xff, 0xff))),
+    };
+    match dc.r2 {
+        R2::COPYPEN => (pen, brush),
+        R2::WHITE => {
+            let white = COLORREF((0xff, 0xff, 0xff));
+            (white, brush.map(|_| white))
+        }
+    }
+}
+
+fn ascending(a: i32, b: i32) -> (i32, i32) {
     if a > b {
         (b, a)
     } else {
@@ -83,44 +311,179 @@ fn ascending(a: u32, b: u32) -> (u32, u32) {
 
 #[win32_derive::dllexport]
 pub fn LineTo(machine: &mut Machine, hdc: HDC, x: u32, y: u32) -> bool {
-    let dc = machine.state.gdi32.dcs.get_mut(hdc).unwrap();
-    let hwnd = match dc.target {
-        DCTarget::Memory(_) => todo!(),
-        DCTarget::Window(hwnd) => hwnd,
-        _ => todo!(),
+    let Some(dc) = machine.state.gdi32.dcs.get(hdc) else {
+        return false;
     };
-    let window = machine.state.user32.windows.get_mut(hwnd).unwrap();
-    let stride = window.width;
-    let pixels = window.bitmap_mut(&mut *machine.host).pixels.as_slice_mut();
-
-    let color = match dc.r2 {
-        R2::COPYPEN => match machine.state.gdi32.objects.get(dc.pen).unwrap() {
-            Object::Pen(pen) => pen.color.to_pixel(),
-            _ => todo!(),
-        },
-        R2::WHITE => COLORREF((0xff, 0xff, 0xff)).to_pixel(),
+    let from = dc.to_device(dc.x as i32, dc.y as i32);
+    let to = dc.to_device(x as i32, y as i32);
+    let (pen, _) = pen_and_brush(machine, dc);
+    draw_to_dc(machine, hdc, |canvas| canvas.line(from, to, pen));
+    let dc = machine.state.gdi32.dcs.get_mut(hdc).unwrap();
+    dc.x = x;
+    dc.y = y;
+    false // fail
+}
+
+#[win32_derive::dllexport]
+pub fn Rectangle(
+    machine: &mut Machine,
+    hdc: HDC,
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
+) -> bool {
+    let Some(dc) = machine.state.gdi32.dcs.get(hdc) else {
+        return false;
     };
+    let (pen, brush) = pen_and_brush(machine, dc);
+    let (left, top) = dc.to_device(left, top);
+    let (right, bottom) = dc.to_device(right, bottom);
+    let (left, right) = ascending(left, right);
+    let (top, bottom) = ascending(top, bottom);
+    // The rect excludes its right and bottom edges, and the pen draws just inside it.
+    draw_to_dc(machine, hdc, |canvas| {
+        if let Some(brush) = brush {
+            canvas.fill(left + 1, top + 1, right - 1, bottom - 1, brush);
+        }
+        canvas.fill(left, top, right, top + 1, pen);
+        canvas.fill(left, bottom - 1, right, bottom, pen);
+        canvas.fill(left, top, left + 1, bottom, pen);
+        canvas.fill(right - 1, top, right, bottom, pen);
+    })
+}
 
-    let (dstX, dstY) = (x, y);
-    if dstX == dc.x {
-        let (y0, y1) = ascending(dstY, dc.y);
-        for y in y0..=y1 {
-            pixels[((y * stride) + x) as usize] = color;
+#[win32_derive::dllexport]
+pub fn Ellipse(
+    machine: &mut Machine,
+    hdc: HDC,
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
+) -> bool {
+    let Some(dc) = machine.state.gdi32.dcs.get(hdc) else {
+        return false;
+    };
+    let (pen, brush) = pen_and_brush(machine, dc);
+    let (left, top) = dc.to_device(left, top);
+    let (right, bottom) = dc.to_device(right, bottom);
+    let (left, right) = ascending(left, right);
+    let (top, bottom) = ascending(top, bottom);
+    if right - left < 2 || bottom - top < 2 {
+        return true;
+    }
+    // The ellipse is inscribed in the pixels left..right, top..bottom.
+    let (cx, cy) = (
+        (left + right - 1) as f32 / 2.0,
+        (top + bottom - 1) as f32 / 2.0,
+    );
+    let (rx, ry) = (
+        (right - left - 1) as f32 / 2.0,
+        (bottom - top - 1) as f32 / 2.0,
+    );
+    // Half the ellipse's extent along one axis at an offset d along the other.
+    let half = |d: f32, r: f32, other_r: f32| r * (1.0 - (d / other_r).powi(2)).max(0.0).sqrt();
+    draw_to_dc(machine, hdc, |canvas| {
+        for y in top..bottom {
+            let w = half(y as f32 - cy, rx, ry);
+            let (x0, x1) = ((cx - w).round() as i32, (cx + w).round() as i32);
+            if let Some(brush) = brush {
+                canvas.fill(x0, y, x1 + 1, y + 1, brush);
+            }
+            canvas.plot(x0, y, pen);
+            canvas.plot(x1, y, pen);
         }
-        dc.y = dstY;
-    } else if dstY == dc.y {
-        let (x0, x1) = ascending(dstX, dc.x);
-        for x in x0..=x1 {
-            pixels[((y * stride) + x) as usize] = color;
+        // Outline by columns too, to close the gaps where the edge is flat.
+        for x in left..right {
+            let h = half(x as f32 - cx, ry, rx);
+            canvas.plot(x, (cy - h).round() as i32, pen);
+            canvas.plot(x, (cy + h).round() as i32, pen);
         }
-        dc.x = dstX;
-    } else {
-        todo!();
+    })
+}
+
+/// Draw lines through logical points, as Polyline and metafile playback do.
+pub fn polyline(machine: &mut Machine, hdc: HDC, points: &[(i32, i32)]) -> bool {
+    let Some(dc) = machine.state.gdi32.dcs.get(hdc) else {
+        return false;
+    };
+    let (pen, _) = pen_and_brush(machine, dc);
+    let points: Vec<_> = points.iter().map(|&(x, y)| dc.to_device(x, y)).collect();
+    draw_to_dc(machine, hdc, |canvas| {
+        for pair in points.windows(2) {
+            canvas.line(pair[0], pair[1], pen);
+        }
+    })
+}
+
+/// Draw a polygon through logical points, filled with the alternate (even-odd) rule,
+/// as Polygon and metafile playback do.
+pub fn polygon(machine: &mut Machine, hdc: HDC, points: &[(i32, i32)]) -> bool {
+    let Some(dc) = machine.state.gdi32.dcs.get(hdc) else {
+        return false;
+    };
+    let (pen, brush) = pen_and_brush(machine, dc);
+    let points: Vec<_> = points.iter().map(|&(x, y)| dc.to_device(x, y)).collect();
+    if points.len() < 2 {
+        return true;
     }
-    false // fail
+    let edges: Vec<_> = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(&a, &b)| (a, b))
+        .collect();
+    draw_to_dc(machine, hdc, |canvas| {
+        if let Some(brush) = brush {
+            let top = points.iter().map(|p| p.1).min().unwrap();
+            let bottom = points.iter().map(|p| p.1).max().unwrap();
+            let mut crossings = Vec::new();
+            for y in top..bottom {
+                // Sample each row at pixel centers.
+                let sy = y as f32 + 0.5;
+                crossings.clear();
+                for &((x0, y0), (x1, y1)) in &edges {
+                    let (y0, y1, x0, x1) = (y0 as f32, y1 as f32, x0 as f32, x1 as f32);
+                    if (y0 <= sy) != (y1 <= sy) {
+                        crossings.push(x0 + (sy - y0) / (y1 - y0) * (x1 - x0));
+                    }
+                }
+                crossings.sort_by(f32::total_cmp);
+                for span in crossings.chunks_exact(2) {
+                    let (x0, x1) = ((span[0] - 0.5).ceil(), (span[1] - 0.5).ceil());
+                    canvas.fill(x0 as i32, y, x1 as i32, y + 1, brush);
+                }
+            }
+        }
+        for &(a, b) in &edges {
+            canvas.line(a, b, pen);
+        }
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn Polyline(machine: &mut Machine, hdc: HDC, apt: ArrayWithSize<POINT>) -> bool {
+    let points: Vec<_> = apt
+        .unwrap()
+        .iter()
+        .map(|pt| (pt.x as i32, pt.y as i32))
+        .collect();
+    polyline(machine, hdc, &points)
 }
 
-#[derive(Debug, Default, win32_derive::TryFromEnum)]
+#[win32_derive::dllexport]
+pub fn Polygon(machine: &mut Machine, hdc: HDC, apt: ArrayWithSize<POINT>) -> bool {
+    let points: Vec<_> = apt
+        .unwrap()
+        .iter()
+        .map(|pt| (pt.x as i32, pt.y as i32))
+        .collect();
+    polygon(machine, hdc, &points)
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, win32_derive::TryFromEnum, serde::Serialize, serde::Deserialize,
+)]
 pub enum R2 {
     #[default]
     COPYPEN = 13,
@@ -133,29 +496,31 @@ pub fn SetROP2(machine: &mut Machine, hdc: HDC, rop2: Result<R2, u32>) -> u32 {
     std::mem::replace(&mut dc.r2, rop2.unwrap()) as u32
 }
 
-pub fn fill_rect(machine: &mut Machine, hdc: HDC, _rect: &RECT, color: COLORREF) {
-    let dc = machine.state.gdi32.dcs.get_mut(hdc).unwrap();
-    match dc.target {
-        DCTarget::Memory(_) => todo!(),
-        DCTarget::Window(hwnd) => {
-            let window = machine.state.user32.windows.get_mut(hwnd).unwrap();
-            // TODO: obey rect
-            window
-                .bitmap_mut(&mut *machine.host)
-                .pixels
-                .as_slice_mut()
-                .fill(color.to_pixel());
-            window.flush_pixels(machine.emu.memory.mem());
-        }
-        DCTarget::DirectDrawSurface(_) => todo!(),
-    }
+/// Fill a logical rect, as FillRect and window background erasing do.
+pub fn fill_rect(machine: &mut Machine, hdc: HDC, rect: &RECT, color: COLORREF) {
+    let Some(dc) = machine.state.gdi32.dcs.get(hdc) else {
+        return;
+    };
+    let (left, top) = dc.to_device(rect.left, rect.top);
+    let (right, bottom) = dc.to_device(rect.right, rect.bottom);
+    let (left, right) = ascending(left, right);
+    let (top, bottom) = ascending(top, bottom);
+    draw_to_dc(machine, hdc, |canvas| {
+        canvas.fill(left, top, right, bottom, color)
+    });
 }
 
 #[win32_derive::dllexport]
-pub fn SetPixel(_machine: &mut Machine, hdc: HDC, x: u32, y: u32, color: u32) -> u32 {
+pub fn SetPixel(machine: &mut Machine, hdc: HDC, x: u32, y: u32, color: u32) -> u32 {
     let color = COLORREF::from_u32(color);
-    // TODO: actually draw
-    u32::from_le_bytes(color.to_pixel())
+    let Some(dc) = machine.state.gdi32.dcs.get(hdc) else {
+        return CLR_INVALID;
+    };
+    let (x, y) = dc.to_device(x as i32, y as i32);
+    if !draw_to_dc(machine, hdc, |canvas| canvas.plot(x, y, color)) {
+        return CLR_INVALID;
+    }
+    color.to_u32()
 }
 
 #[win32_derive::dllexport]
@@ -0,0 +1,638 @@
+//! Metafiles: recorded sequences of GDI calls, played back onto a DC.
+//! We load both the 16-bit Windows metafile (WMF) and the enhanced (EMF) formats
+//! from files and replay them via the GDI functions in this module's siblings.
+//! Recording (CreateMetaFile etc.) is not implemented, and records without a GDI
+//! counterpart here (e.g. arcs, regions) are logged and skipped.
+
+use super::{
+    ext_text_out, polygon, polyline, set_dibits_to_device, BkMode, Brush, GetStockObjectArg,
+    Object, Pen, COLORREF, ETO_CLIPPED, ETO_OPAQUE, HDC, HGDIOBJ, MM, R2,
+};
+use crate::{
+    machine::Machine,
+    winapi::{bitmap::BitmapRGBA32, bitmap::BITMAPINFOHEADER, kernel32, types::*},
+};
+use memory::{Extensions, Mem};
+
+const TRACE_CONTEXT: &'static str = "gdi32/metafile";
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Metafile {
+    enhanced: bool,
+    data: Box<[u8]>,
+}
+
+pub type HMETAFILE = HANDLE<Metafile>;
+pub type HENHMETAFILE = HANDLE<Metafile>;
+
+/// Key found at the start of "placeable" WMF files, which prefix the metafile
+/// with a 22-byte header giving its bounds.
+const PLACEABLE_KEY: u32 = 0x9AC6_CDD7;
+/// dSignature of the EMF header record, " EMF".
+const ENHMETA_SIGNATURE: u32 = 0x464D_4520;
+
+const SRCCOPY: u32 = 0xcc0020;
+const BS_NULL: u32 = 1;
+
+#[win32_derive::dllexport]
+pub fn GetMetaFileA(machine: &mut Machine, lpName: Option<&str>) -> HMETAFILE {
+    let mut data = kernel32::read_file(machine, lpName.unwrap());
+    if data.len() >= 22 && Mem::from_slice(&data).get_pod::<u32>(0) == PLACEABLE_KEY {
+        data.drain(..22);
+    }
+    // METAHEADER: mtType is 1 (memory) or 2 (disk), mtHeaderSize is 9 words.
+    let mem = Mem::from_slice(&data);
+    if data.len() < 18 || !matches!(mem.get_pod::<u16>(0), 1 | 2) || mem.get_pod::<u16>(2) != 9 {
+        log::warn!("GetMetaFileA({lpName:?}): not a metafile");
+        return HMETAFILE::null();
+    }
+    machine.state.gdi32.metafiles.add(Metafile {
+        enhanced: false,
+        data: data.into_boxed_slice(),
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn GetEnhMetaFileA(machine: &mut Machine, lpName: Option<&str>) -> HENHMETAFILE {
+    let data = kernel32::read_file(machine, lpName.unwrap());
+    let mem = Mem::from_slice(&data);
+    if data.len() < 88 || mem.get_pod::<u32>(0) != 1 || mem.get_pod::<u32>(40) != ENHMETA_SIGNATURE
+    {
+        log::warn!("GetEnhMetaFileA({lpName:?}): not an enhanced metafile");
+        return HENHMETAFILE::null();
+    }
+    machine.state.gdi32.metafiles.add(Metafile {
+        enhanced: true,
+        data: data.into_boxed_slice(),
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn DeleteMetaFile(machine: &mut Machine, hmf: HMETAFILE) -> bool {
+    machine.state.gdi32.metafiles.remove(hmf).is_some()
+}
+
+#[win32_derive::dllexport]
+pub fn DeleteEnhMetaFile(machine: &mut Machine, hmf: HENHMETAFILE) -> bool {
+    machine.state.gdi32.metafiles.remove(hmf).is_some()
+}
+
+/// Look up a metafile's records, copied so playback can mutate the machine.
+fn metafile_data(machine: &Machine, hmf: HMETAFILE, enhanced: bool) -> Option<Box<[u8]>> {
+    match machine.state.gdi32.metafiles.get(hmf) {
+        Some(mf) if mf.enhanced == enhanced => Some(mf.data.clone()),
+        _ => None,
+    }
+}
+
+/// Table of objects created during playback, indexed as the records refer to them.
+/// Slots holding a null handle are objects we don't support creating.
+struct ObjectTable(Vec<Option<HGDIOBJ>>);
+
+impl ObjectTable {
+    /// WMF creation records take the lowest free slot.
+    fn add(&mut self, obj: HGDIOBJ) {
+        match self.0.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => *slot = Some(obj),
+            None => self.0.push(Some(obj)),
+        }
+    }
+
+    /// EMF creation records name their slot.
+    fn set(&mut self, index: u32, obj: HGDIOBJ) {
+        let index = index as usize;
+        if index >= self.0.len() {
+            self.0.resize(index + 1, None);
+        }
+        self.0[index] = Some(obj);
+    }
+
+    fn get(&self, index: u32) -> HGDIOBJ {
+        self.0
+            .get(index as usize)
+            .copied()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    fn delete(&mut self, machine: &mut Machine, index: u32) {
+        if let Some(Some(obj)) = self.0.get_mut(index as usize).map(Option::take) {
+            super::DeleteObject(machine, obj);
+        }
+    }
+
+    /// Playback deletes any objects the metafile didn't.
+    fn delete_all(mut self, machine: &mut Machine) {
+        for index in 0..self.0.len() {
+            self.delete(machine, index as u32);
+        }
+    }
+}
+
+fn create_pen(machine: &mut Machine, color: u32) -> HGDIOBJ {
+    // TODO: pen styles and widths.
+    machine.state.gdi32.objects.add(Object::Pen(Pen {
+        color: COLORREF::from_u32(color),
+    }))
+}
+
+fn create_brush(machine: &mut Machine, style: u32, color: u32) -> HGDIOBJ {
+    // TODO: hatched and pattern brushes.
+    machine.state.gdi32.objects.add(Object::Brush(Brush {
+        color: if style == BS_NULL {
+            None
+        } else {
+            Some(COLORREF::from_u32(color))
+        },
+    }))
+}
+
+/// Draw a DIB as found embedded in a record: a BITMAPINFO, and its bits,
+/// which if None immediately follow the BITMAPINFO.
+fn draw_dib(
+    machine: &mut Machine,
+    hdc: HDC,
+    dst: (i32, i32),
+    dst_size: (i32, i32),
+    src: (u32, u32),
+    src_size: (i32, i32),
+    rop: u32,
+    bmi: Mem,
+    bits: Option<Mem>,
+) {
+    if rop != SRCCOPY {
+        log::warn!("metafile DIB with rop {rop:x}, drawing as SRCCOPY");
+    }
+    if dst_size.0.abs() != src_size.0 || dst_size.1.abs() != src_size.1 {
+        log::warn!("TODO: metafile DIB stretching {src_size:?} => {dst_size:?}");
+        return;
+    }
+    // Records are only 2-byte aligned, so copy the DIB out before viewing its header.
+    let dib = bmi.as_slice_todo().to_vec();
+    let header = Mem::from_slice(&dib).view::<BITMAPINFOHEADER>(0);
+    let bits = match bits {
+        Some(bits) => bits.as_slice_todo(),
+        None => &dib[header.biSize as usize + header.extra_len()..],
+    };
+    let bitmap = BitmapRGBA32::parse(header, Some((bits, header.height() as usize)));
+    let (w, h) = (src_size.0 as u32, src_size.1 as u32);
+    set_dibits_to_device(machine, hdc, dst, w, h, src, &bitmap);
+}
+
+#[win32_derive::dllexport]
+pub fn PlayMetaFile(machine: &mut Machine, hdc: HDC, hmf: HMETAFILE) -> bool {
+    let data = match metafile_data(machine, hmf, false) {
+        Some(data) => data,
+        None => return false,
+    };
+    let mem = Mem::from_slice(&data);
+    let num_objects = mem.get_pod::<u16>(10);
+    let mut objects = ObjectTable(Vec::with_capacity(num_objects as usize));
+
+    let level = super::SaveDC(machine, hdc);
+    // Each record is a size in words, a function number, and 16-bit parameters.
+    let mut ofs = mem.get_pod::<u16>(2) as u32 * 2;
+    while ofs + 6 <= mem.len() {
+        let size = mem.get_pod::<u32>(ofs) * 2;
+        let function = mem.get_pod::<u16>(ofs + 4);
+        if function == 0 || size < 6 || ofs + size > mem.len() {
+            break; // META_EOF
+        }
+        let params = mem.sub(ofs + 6, size - 6);
+        play_wmf_record(machine, hdc, &mut objects, function, params);
+        ofs += size;
+    }
+    super::RestoreDC(machine, hdc, level);
+    objects.delete_all(machine);
+    true
+}
+
+fn play_wmf_record(
+    machine: &mut Machine,
+    hdc: HDC,
+    objects: &mut ObjectTable,
+    function: u16,
+    params: Mem,
+) {
+    // Parameters are stored in the reverse order of the corresponding function's arguments.
+    let p = |i: u32| params.get_pod::<u16>(i * 2) as i16 as i32;
+    let color = |i: u32| params.get_pod::<u32>(i * 2);
+    match function {
+        0x001E => {
+            super::SaveDC(machine, hdc);
+        }
+        0x0102 => {
+            super::SetBkMode(machine, hdc, BkMode::try_from(p(0) as u32));
+        }
+        0x0103 => {
+            super::SetMapMode(machine, hdc, MM::try_from(p(0) as u32));
+        }
+        0x0104 => match R2::try_from(p(0) as u32) {
+            Ok(rop2) => {
+                super::SetROP2(machine, hdc, Ok(rop2));
+            }
+            Err(rop2) => log::warn!("metafile: unimplemented ROP2 {rop2}"),
+        },
+        0x0127 => {
+            super::RestoreDC(machine, hdc, p(0));
+        }
+        0x012D => {
+            super::SelectObject(machine, hdc, objects.get(p(0) as u32));
+        }
+        0x01F0 => objects.delete(machine, p(0) as u32),
+        0x0201 => {
+            super::SetBkColor(machine, hdc, color(0));
+        }
+        0x0209 => {
+            super::SetTextColor(machine, hdc, color(0));
+        }
+        0x020B => {
+            super::SetWindowOrgEx(machine, hdc, p(1), p(0), None);
+        }
+        0x020C => {
+            super::SetWindowExtEx(machine, hdc, p(1), p(0), None);
+        }
+        0x020D => {
+            super::SetViewportOrgEx(machine, hdc, p(1), p(0), None);
+        }
+        0x020E => {
+            super::SetViewportExtEx(machine, hdc, p(1), p(0), None);
+        }
+        0x0213 => {
+            super::LineTo(machine, hdc, p(1) as u32, p(0) as u32);
+        }
+        0x0214 => {
+            super::MoveToEx(machine, hdc, p(1) as u32, p(0) as u32, None);
+        }
+        0x02FA => {
+            // META_CREATEPENINDIRECT: LOGPEN16 of style, width as a point, color.
+            let pen = create_pen(machine, color(3));
+            objects.add(pen);
+        }
+        0x02FC => {
+            // META_CREATEBRUSHINDIRECT: LOGBRUSH16 of style, color, hatch.
+            let brush = create_brush(machine, p(0) as u32, color(1));
+            objects.add(brush);
+        }
+        0x0324 | 0x0325 => {
+            // META_POLYGON and META_POLYLINE: a count, then that many x, y points.
+            let count = std::cmp::min(p(0) as u32, (params.len() / 2).saturating_sub(1) / 2);
+            let points: Vec<_> = (0..count).map(|i| (p(1 + i * 2), p(2 + i * 2))).collect();
+            if function == 0x0324 {
+                polygon(machine, hdc, &points);
+            } else {
+                polyline(machine, hdc, &points);
+            }
+        }
+        0x0418 => {
+            super::Ellipse(machine, hdc, p(3), p(2), p(1), p(0));
+        }
+        0x041B => {
+            super::Rectangle(machine, hdc, p(3), p(2), p(1), p(0));
+        }
+        0x041F => {
+            super::SetPixel(machine, hdc, p(3) as u32, p(2) as u32, color(0));
+        }
+        0x0521 => {
+            // META_TEXTOUT: string length, the string padded to a word, y, x.
+            let len = p(0) as u32;
+            let text = params.sub(2, len).as_slice_todo();
+            let at = 1 + len.div_ceil(2);
+            ext_text_out(machine, hdc, p(at + 1), p(at), 0, None, text, None);
+        }
+        0x0940 => {
+            // META_DIBBITBLT: rop, ySrc, xSrc, height, width, yDest, xDest, DIB.
+            let bmi = params.slice(16..);
+            let size = (p(5), p(4));
+            draw_dib(
+                machine,
+                hdc,
+                (p(7), p(6)),
+                size,
+                (p(3) as u32, p(2) as u32),
+                size,
+                color(0),
+                bmi,
+                None,
+            );
+        }
+        0x0A32 => {
+            // META_EXTTEXTOUT: y, x, string length, options, a rect if opaque or
+            // clipped, the string padded to a word, and optionally advances.
+            let (len, options) = (p(2) as u32, p(3) as u32 & 0xFFFF);
+            let (rect, text_at) = if options & (ETO_OPAQUE | ETO_CLIPPED) != 0 {
+                let rect = RECT {
+                    left: p(4),
+                    top: p(5),
+                    right: p(6),
+                    bottom: p(7),
+                };
+                (Some(rect), 8)
+            } else {
+                (None, 4)
+            };
+            let text = params.sub(text_at * 2, len).as_slice_todo();
+            let dx_at = text_at + len.div_ceil(2);
+            let dx = if params.len() >= (dx_at + len) * 2 {
+                Some((0..len).map(|i| p(dx_at + i)).collect::<Vec<_>>())
+            } else {
+                None
+            };
+            let (x, y) = (p(1), p(0));
+            ext_text_out(
+                machine,
+                hdc,
+                x,
+                y,
+                options,
+                rect.as_ref(),
+                text,
+                dx.as_deref(),
+            );
+        }
+        0x0B41 => {
+            // META_DIBSTRETCHBLT: rop, srcHeight, srcWidth, ySrc, xSrc,
+            // destHeight, destWidth, yDest, xDest, DIB.
+            let bmi = params.slice(20..);
+            draw_dib(
+                machine,
+                hdc,
+                (p(9), p(8)),
+                (p(7), p(6)),
+                (p(5) as u32, p(4) as u32),
+                (p(3), p(2)),
+                color(0),
+                bmi,
+                None,
+            );
+        }
+        0x0D33 => {
+            // META_SETDIBTODEV: colorUse, scanCount, startScan, yDib, xDib,
+            // height, width, yDest, xDest, DIB.
+            let bmi = params.slice(18..);
+            let size = (p(6), p(5));
+            draw_dib(
+                machine,
+                hdc,
+                (p(8), p(7)),
+                size,
+                (p(4) as u32, p(3) as u32),
+                size,
+                SRCCOPY,
+                bmi,
+                None,
+            );
+        }
+        0x0F43 => {
+            // META_STRETCHDIB: rop, colorUse, srcHeight, srcWidth, ySrc, xSrc,
+            // destHeight, destWidth, yDest, xDest, DIB.
+            let bmi = params.slice(22..);
+            draw_dib(
+                machine,
+                hdc,
+                (p(10), p(9)),
+                (p(8), p(7)),
+                (p(6) as u32, p(5) as u32),
+                (p(4), p(3)),
+                color(0),
+                bmi,
+                None,
+            );
+        }
+        // Other creation records still occupy a slot in the object table.
+        0x00F7 | 0x0142 | 0x01F9 | 0x02FB | 0x06FF => {
+            log::warn!("metafile: unimplemented object record {function:x}");
+            objects.add(HGDIOBJ::null());
+        }
+        _ => log::warn!("metafile: unimplemented record {function:x}"),
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn PlayEnhMetaFile(
+    machine: &mut Machine,
+    hdc: HDC,
+    hmf: HENHMETAFILE,
+    lprect: Option<&RECT>,
+) -> bool {
+    let data = match metafile_data(machine, hmf, true) {
+        Some(data) => data,
+        None => return false,
+    };
+    let mem = Mem::from_slice(&data);
+    let num_objects = mem.get_pod::<u16>(56);
+    // Slot 0 refers to the metafile itself.
+    let mut objects = ObjectTable(vec![None; num_objects.max(1) as usize]);
+    objects.0[0] = Some(HGDIOBJ::null());
+
+    let level = super::SaveDC(machine, hdc);
+    // Place the picture's bounds at the rect's origin.
+    // TODO: scale the picture frame to the rect, too.
+    if let (Some(rect), Some(dc)) = (lprect, machine.state.gdi32.dcs.get_mut(hdc)) {
+        let (bounds_left, bounds_top) = (mem.get_pod::<i32>(8), mem.get_pod::<i32>(12));
+        dc.viewport_org.0 += rect.left - bounds_left;
+        dc.viewport_org.1 += rect.top - bounds_top;
+    }
+    // Each record is a type and a size in bytes, followed by its parameters.
+    let mut ofs = 0;
+    while ofs + 8 <= mem.len() {
+        let typ = mem.get_pod::<u32>(ofs);
+        let size = mem.get_pod::<u32>(ofs + 4);
+        if typ == 14 || size < 8 || ofs + size > mem.len() {
+            break; // EMR_EOF
+        }
+        play_emf_record(machine, hdc, &mut objects, typ, mem.sub(ofs, size));
+        ofs += size;
+    }
+    super::RestoreDC(machine, hdc, level);
+    objects.0[0] = None;
+    objects.delete_all(machine);
+    true
+}
+
+fn play_emf_record(
+    machine: &mut Machine,
+    hdc: HDC,
+    objects: &mut ObjectTable,
+    typ: u32,
+    record: Mem,
+) {
+    // Parameters are 32-bit and follow the 8-byte record header.
+    let u = |i: u32| record.get_pod::<u32>(8 + i * 4);
+    let i = |i: u32| u(i) as i32;
+    match typ {
+        1 => {} // EMR_HEADER
+        3 | 4 => {
+            // EMR_POLYGON and EMR_POLYLINE: bounds, a count, then x, y points.
+            let count = std::cmp::min(u(4), (record.len() - 28) / 8);
+            let points: Vec<_> = (0..count).map(|n| (i(5 + n * 2), i(6 + n * 2))).collect();
+            if typ == 3 {
+                polygon(machine, hdc, &points);
+            } else {
+                polyline(machine, hdc, &points);
+            }
+        }
+        9 => {
+            super::SetWindowExtEx(machine, hdc, i(0), i(1), None);
+        }
+        10 => {
+            super::SetWindowOrgEx(machine, hdc, i(0), i(1), None);
+        }
+        11 => {
+            super::SetViewportExtEx(machine, hdc, i(0), i(1), None);
+        }
+        12 => {
+            super::SetViewportOrgEx(machine, hdc, i(0), i(1), None);
+        }
+        13 => {
+            super::SetBrushOrgEx(machine, hdc, i(0), i(1), None);
+        }
+        15 => {
+            super::SetPixel(machine, hdc, u(0), u(1), u(2));
+        }
+        17 => {
+            super::SetMapMode(machine, hdc, MM::try_from(u(0)));
+        }
+        18 => {
+            super::SetBkMode(machine, hdc, BkMode::try_from(u(0)));
+        }
+        20 => match R2::try_from(u(0)) {
+            Ok(rop2) => {
+                super::SetROP2(machine, hdc, Ok(rop2));
+            }
+            Err(rop2) => log::warn!("metafile: unimplemented ROP2 {rop2}"),
+        },
+        24 => {
+            super::SetTextColor(machine, hdc, u(0));
+        }
+        25 => {
+            super::SetBkColor(machine, hdc, u(0));
+        }
+        27 => {
+            super::MoveToEx(machine, hdc, u(0), u(1), None);
+        }
+        33 => {
+            super::SaveDC(machine, hdc);
+        }
+        34 => {
+            super::RestoreDC(machine, hdc, i(0));
+        }
+        37 => {
+            // High bit set means a stock object.
+            let index = u(0);
+            let obj = if index & 0x8000_0000 != 0 {
+                match GetStockObjectArg::try_from(index & !0x8000_0000) {
+                    Ok(arg) => super::GetStockObject(machine, Ok(arg)),
+                    Err(index) => {
+                        log::warn!("metafile: unimplemented stock object {index}");
+                        HGDIOBJ::null()
+                    }
+                }
+            } else {
+                objects.get(index)
+            };
+            super::SelectObject(machine, hdc, obj);
+        }
+        38 => {
+            // EMR_CREATEPEN: index, LOGPEN of style, width as a point, color.
+            let pen = create_pen(machine, u(4));
+            objects.set(u(0), pen);
+        }
+        39 => {
+            // EMR_CREATEBRUSHINDIRECT: index, LOGBRUSH of style, color, hatch.
+            let brush = create_brush(machine, u(1), u(2));
+            objects.set(u(0), brush);
+        }
+        40 => objects.delete(machine, u(0)),
+        42 => {
+            super::Ellipse(machine, hdc, i(0), i(1), i(2), i(3));
+        }
+        43 => {
+            super::Rectangle(machine, hdc, i(0), i(1), i(2), i(3));
+        }
+        54 => {
+            super::LineTo(machine, hdc, u(0), u(1));
+        }
+        80 | 81 => {
+            // EMR_SETDIBITSTODEVICE and EMR_STRETCHDIBITS share a layout up to
+            // the usage field: bounds, dest, src, src size, BITMAPINFO and bits offsets.
+            let (off_bmi, off_bits, cb_bits) = (u(10), u(12), u(13));
+            let src_size = (i(8), i(9));
+            let (dst_size, rop) = if typ == 81 {
+                ((i(16), i(17)), u(15))
+            } else {
+                (src_size, SRCCOPY)
+            };
+            draw_dib(
+                machine,
+                hdc,
+                (i(4), i(5)),
+                dst_size,
+                (u(6), u(7)),
+                src_size,
+                rop,
+                record.slice(off_bmi..),
+                Some(record.sub(off_bits, cb_bits)),
+            );
+        }
+        83 | 84 => {
+            // EMR_EXTTEXTOUTA and EMR_EXTTEXTOUTW: bounds, graphics mode, scales,
+            // then an EMRTEXT of reference point, length, string offset, options,
+            // rect, and advances offset.  Offsets are from the record start.
+            let (len, off_string, options, off_dx) = (u(9), u(10), u(11), u(16));
+            let rect = RECT {
+                left: i(12),
+                top: i(13),
+                right: i(14),
+                bottom: i(15),
+            };
+            let text: Vec<u8> = if typ == 83 {
+                record.sub(off_string, len).as_slice_todo().to_vec()
+            } else {
+                // We only draw single-byte characters.
+                (0..len)
+                    .map(|n| match record.get_pod::<u16>(off_string + n * 2) {
+                        c @ 0..=0xFF => c as u8,
+                        _ => b'?',
+                    })
+                    .collect()
+            };
+            let dx = match off_dx {
+                0 => None,
+                ofs => Some(
+                    (0..len)
+                        .map(|n| record.get_pod::<i32>(ofs + n * 4))
+                        .collect::<Vec<_>>(),
+                ),
+            };
+            ext_text_out(
+                machine,
+                hdc,
+                i(7),
+                i(8),
+                options,
+                Some(&rect),
+                &text,
+                dx.as_deref(),
+            );
+        }
+        86 | 87 => {
+            // EMR_POLYGON16 and EMR_POLYLINE16: bounds, a count, then 16-bit points.
+            let count = std::cmp::min(u(4), (record.len() - 28) / 4);
+            let points: Vec<_> = (0..count)
+                .map(|n| {
+                    let ofs = 28 + n * 4;
+                    let x = record.get_pod::<u16>(ofs) as i16 as i32;
+                    let y = record.get_pod::<u16>(ofs + 2) as i16 as i32;
+                    (x, y)
+                })
+                .collect();
+            if typ == 86 {
+                polygon(machine, hdc, &points);
+            } else {
+                polyline(machine, hdc, &points);
+            }
+        }
+        _ => log::warn!("metafile: unimplemented record {typ}"),
+    }
+}
@@ -4,13 +4,18 @@
 mod bitmap;
 mod dc;
 mod draw;
+pub mod font;
+mod metafile;
 mod object;
+mod pixel_format;
 mod state;
 mod text;
 pub use bitmap::*;
 pub use dc::*;
 pub use draw::*;
+pub use metafile::*;
 pub use object::*;
+pub use pixel_format::*;
 pub use state::*;
 pub use text::*;
 
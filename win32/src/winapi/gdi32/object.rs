@@ -9,7 +9,7 @@ use crate::{
 
 const TRACE_CONTEXT: &'static str = "gdi32/object";
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub enum BitmapType {
     RGBA32(BitmapRGBA32),
     Mono(BitmapMono),
@@ -25,7 +25,7 @@ impl BitmapType {
 }
 
 /// GDI Object, as identified by HANDLEs.
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub enum Object {
     Brush(Brush),
     Bitmap(BitmapType),
@@ -61,6 +61,12 @@ pub fn GetStockObject(machine: &mut Machine, i: Result<GetStockObjectArg, u32>)
         GetStockObjectArg::LTGRAY_BRUSH => machine.state.gdi32.objects.add(Object::Brush(Brush {
             color: Some(COLORREF((0xc0, 0xc0, 0xc0))),
         })),
+        GetStockObjectArg::GRAY_BRUSH => machine.state.gdi32.objects.add(Object::Brush(Brush {
+            color: Some(COLORREF((0x80, 0x80, 0x80))),
+        })),
+        GetStockObjectArg::DKGRAY_BRUSH => machine.state.gdi32.objects.add(Object::Brush(Brush {
+            color: Some(COLORREF((0x40, 0x40, 0x40))),
+        })),
         GetStockObjectArg::BLACK_BRUSH => machine.state.gdi32.objects.add(Object::Brush(Brush {
             color: Some(COLORREF((0x00, 0x00, 0x00))),
         })),
@@ -73,7 +79,6 @@ pub fn GetStockObject(machine: &mut Machine, i: Result<GetStockObjectArg, u32>)
             log::error!("returning null stock object");
             HGDIOBJ::null()
         }
-        _ => todo!(),
     }
 }
 
@@ -95,7 +100,6 @@ pub fn SelectObject(machine: &mut Machine, hdc: HDC, hGdiObj: HGDIOBJ) -> HGDIOB
                 prev
             }
             DCTarget::Window(_) => todo!(),
-            DCTarget::DirectDrawSurface(_) => todo!(),
         },
         Object::Brush(_) => std::mem::replace(&mut dc.brush, hGdiObj),
         Object::Pen(_) => std::mem::replace(&mut dc.pen, hGdiObj),
@@ -131,7 +135,16 @@ pub fn GetObjectA(machine: &mut Machine, handle: HGDIOBJ, bytes: u32, out: u32)
 }
 
 #[win32_derive::dllexport]
-pub fn DeleteObject(_machine: &mut Machine, handle: HGDIOBJ) -> bool {
-    // TODO: leak
-    true
+pub fn DeleteObject(machine: &mut Machine, handle: HGDIOBJ) -> bool {
+    let gdi32 = &mut machine.state.gdi32;
+    if handle.to_raw() == gdi32.default_bitmap.to_raw() {
+        return true; // stock object, deleting is a no-op
+    }
+    if gdi32.is_selected(handle) {
+        // MSDN: "If the specified handle is not valid or is currently selected into a DC,
+        // the return value is zero."
+        return false;
+    }
+    // TODO: pixel memory of DIB sections is leaked.
+    gdi32.objects.remove(handle).is_some()
 }
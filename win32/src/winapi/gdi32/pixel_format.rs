@@ -0,0 +1,113 @@
+//! Pixel formats, as used to set up OpenGL on a window DC.
+//! We offer exactly one format, matching what opengl32 renders.
+
+use super::HDC;
+use crate::{machine::Machine, winapi::opengl32};
+use memory::Pod;
+
+const TRACE_CONTEXT: &'static str = "gdi32/pixel_format";
+
+pub const PFD_DOUBLEBUFFER: u32 = 0x1;
+pub const PFD_DRAW_TO_WINDOW: u32 = 0x4;
+pub const PFD_SUPPORT_OPENGL: u32 = 0x20;
+
+pub const PFD_TYPE_RGBA: u8 = 0;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct PIXELFORMATDESCRIPTOR {
+    pub nSize: u16,
+    pub nVersion: u16,
+    pub dwFlags: u32,
+    pub iPixelType: u8,
+    pub cColorBits: u8,
+    pub cRedBits: u8,
+    pub cRedShift: u8,
+    pub cGreenBits: u8,
+    pub cGreenShift: u8,
+    pub cBlueBits: u8,
+    pub cBlueShift: u8,
+    pub cAlphaBits: u8,
+    pub cAlphaShift: u8,
+    pub cAccumBits: u8,
+    pub cAccumRedBits: u8,
+    pub cAccumGreenBits: u8,
+    pub cAccumBlueBits: u8,
+    pub cAccumAlphaBits: u8,
+    pub cDepthBits: u8,
+    pub cStencilBits: u8,
+    pub cAuxBuffers: u8,
+    pub iLayerType: u8,
+    pub bReserved: u8,
+    pub dwLayerMask: u32,
+    pub dwVisibleMask: u32,
+    pub dwDamageMask: u32,
+}
+unsafe impl memory::Pod for PIXELFORMATDESCRIPTOR {}
+
+/// Index of the single pixel format we support.
+const PIXEL_FORMAT: i32 = 1;
+
+#[win32_derive::dllexport]
+pub fn ChoosePixelFormat(
+    _machine: &mut Machine,
+    hdc: HDC,
+    ppfd: Option<&PIXELFORMATDESCRIPTOR>,
+) -> i32 {
+    PIXEL_FORMAT
+}
+
+#[win32_derive::dllexport]
+pub fn SetPixelFormat(
+    _machine: &mut Machine,
+    hdc: HDC,
+    format: i32,
+    ppfd: Option<&PIXELFORMATDESCRIPTOR>,
+) -> bool {
+    format == PIXEL_FORMAT
+}
+
+#[win32_derive::dllexport]
+pub fn GetPixelFormat(_machine: &mut Machine, hdc: HDC) -> i32 {
+    PIXEL_FORMAT
+}
+
+/// Returns the number of available formats, filling in ppfd if provided.
+#[win32_derive::dllexport]
+pub fn DescribePixelFormat(
+    _machine: &mut Machine,
+    hdc: HDC,
+    iPixelFormat: i32,
+    nBytes: u32,
+    ppfd: Option<&mut PIXELFORMATDESCRIPTOR>,
+) -> i32 {
+    if let Some(pfd) = ppfd {
+        if iPixelFormat != PIXEL_FORMAT
+            || (nBytes as usize) < std::mem::size_of::<PIXELFORMATDESCRIPTOR>()
+        {
+            return 0;
+        }
+        pfd.clear_struct();
+        pfd.nSize = std::mem::size_of::<PIXELFORMATDESCRIPTOR>() as u16;
+        pfd.nVersion = 1;
+        pfd.dwFlags = PFD_DRAW_TO_WINDOW | PFD_SUPPORT_OPENGL | PFD_DOUBLEBUFFER;
+        pfd.iPixelType = PFD_TYPE_RGBA;
+        // Matches the byte order of raster::Framebuffer pixels.
+        pfd.cColorBits = 32;
+        pfd.cRedBits = 8;
+        pfd.cRedShift = 0;
+        pfd.cGreenBits = 8;
+        pfd.cGreenShift = 8;
+        pfd.cBlueBits = 8;
+        pfd.cBlueShift = 16;
+        pfd.cAlphaBits = 8;
+        pfd.cAlphaShift = 24;
+        pfd.cDepthBits = 24;
+    }
+    PIXEL_FORMAT
+}
+
+#[win32_derive::dllexport]
+pub fn SwapBuffers(machine: &mut Machine, hdc: HDC) -> bool {
+    opengl32::swap_buffers(machine, hdc)
+}
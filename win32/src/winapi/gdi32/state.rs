@@ -1,20 +1,45 @@
-use super::{DCTarget, Object, DC, HDC, HGDIOBJ};
-use crate::winapi::{handle::Handles, types::HWND};
+use super::{BitmapType, DCTarget, Metafile, Object, DC, HDC, HGDIOBJ, HMETAFILE};
+use crate::winapi::{
+    bitmap::{BitmapMono, PixelData},
+    handle::Handles,
+    types::HWND,
+};
 
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct State {
     pub dcs: Handles<HDC, DC>,
     pub screen_dc: HDC,
     pub objects: Handles<HGDIOBJ, Object>,
+    /// The 1x1 monochrome bitmap initially selected into memory DCs.
+    pub default_bitmap: HGDIOBJ,
+    pub metafiles: Handles<HMETAFILE, Metafile>,
+}
+
+impl State {
+    /// Whether the object is selected into any DC.
+    pub fn is_selected(&self, obj: HGDIOBJ) -> bool {
+        self.dcs.iter().any(|dc| dc.selects(obj))
+    }
 }
 
 impl Default for State {
     fn default() -> Self {
         let mut dcs: Handles<HDC, DC> = Default::default();
         let screen_dc = dcs.add(DC::new(DCTarget::Window(HWND::null())));
+        let mut objects = Handles::new(HGDIOBJ::lowest_value());
+        // MSDN says: "When a memory device context is created, it initially has a 1-by-1 monochrome bitmap selected into it."
+        // SkiFree depends on this!
+        let default_bitmap = objects.add(Object::Bitmap(BitmapType::Mono(BitmapMono {
+            width: 1,
+            height: 1,
+            pixels: PixelData::Ptr(0, 0),
+        })));
         State {
             dcs,
             screen_dc,
-            objects: Handles::new(HGDIOBJ::lowest_value()),
+            objects,
+            default_bitmap,
+            metafiles: Default::default(),
         }
     }
 }
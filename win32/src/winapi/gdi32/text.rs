@@ -1,6 +1,13 @@
-use super::{CLR_INVALID, HDC};
+use super::{
+    draw_to_dc,
+    font::{glyph_pixels, CHAR_HEIGHT, CHAR_WIDTH},
+    BkMode, CLR_INVALID, COLORREF, HDC,
+};
 use crate::{
-    winapi::{stack_args::ArrayWithSize, types::HANDLE},
+    winapi::{
+        stack_args::ArrayWithSize,
+        types::{HANDLE, RECT},
+    },
     Machine,
 };
 use memory::Pod;
@@ -32,20 +39,135 @@ pub fn CreateFontA(
 }
 
 #[win32_derive::dllexport]
-pub fn SetTextColor(_machine: &mut Machine, hdc: HDC, color: u32) -> u32 {
-    CLR_INVALID // fail
+pub fn SetTextColor(machine: &mut Machine, hdc: HDC, color: u32) -> u32 {
+    match machine.state.gdi32.dcs.get_mut(hdc) {
+        Some(dc) => std::mem::replace(&mut dc.text_color, COLORREF::from_u32(color)).to_u32(),
+        None => CLR_INVALID, // fail
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn GetTextColor(machine: &mut Machine, hdc: HDC) -> u32 {
+    match machine.state.gdi32.dcs.get(hdc) {
+        Some(dc) => dc.text_color.to_u32(),
+        None => CLR_INVALID,
+    }
 }
 
 #[win32_derive::dllexport]
 pub fn TextOutA(
-    _machine: &mut Machine,
+    machine: &mut Machine,
     hdc: HDC,
     x: u32,
     y: u32,
     lpString: ArrayWithSize<u8>,
 ) -> bool {
-    let _text = std::str::from_utf8(lpString.unwrap()).unwrap();
-    true
+    ext_text_out(
+        machine,
+        hdc,
+        x as i32,
+        y as i32,
+        0,
+        None,
+        lpString.unwrap(),
+        None,
+    )
+}
+
+pub const ETO_OPAQUE: u32 = 2;
+pub const ETO_CLIPPED: u32 = 4;
+
+#[win32_derive::dllexport]
+pub fn ExtTextOutA(
+    machine: &mut Machine,
+    hdc: HDC,
+    x: i32,
+    y: i32,
+    options: u32,
+    lprect: Option<&RECT>,
+    lpString: ArrayWithSize<u8>,
+    lpDx: u32,
+) -> bool {
+    let text = lpString.unwrap_or_default();
+    let dx = match lpDx {
+        0 => None,
+        addr => Some(
+            machine
+                .mem()
+                .view_n::<i32>(addr, text.len() as u32)
+                .to_vec(),
+        ),
+    };
+    let rect = lprect.copied();
+    ext_text_out(
+        machine,
+        hdc,
+        x,
+        y,
+        options,
+        rect.as_ref(),
+        text,
+        dx.as_deref(),
+    )
+}
+
+/// Draw a string of single-byte characters in the built-in font, with its top left
+/// at a logical point, as TextOut, ExtTextOut and metafile playback do.  `dx` gives
+/// the distance from each character to the next, in place of the font's advance.
+pub fn ext_text_out(
+    machine: &mut Machine,
+    hdc: HDC,
+    x: i32,
+    y: i32,
+    options: u32,
+    rect: Option<&RECT>,
+    text: &[u8],
+    dx: Option<&[i32]>,
+) -> bool {
+    let Some(dc) = machine.state.gdi32.dcs.get(hdc) else {
+        return false;
+    };
+    let device_rect = rect.map(|rect| {
+        let (left, top) = dc.to_device(rect.left, rect.top);
+        let (right, bottom) = dc.to_device(rect.right, rect.bottom);
+        RECT {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    });
+    // Character cells' left edges, in logical x.
+    let mut lefts = Vec::with_capacity(text.len() + 1);
+    let mut left = x;
+    for i in 0..text.len() {
+        lefts.push(left);
+        left += dx.and_then(|dx| dx.get(i).copied()).unwrap_or(CHAR_WIDTH);
+    }
+    lefts.push(left);
+    let y0 = dc.to_device(x, y).1;
+    let cells: Vec<_> = lefts.iter().map(|&l| dc.to_device(l, y).0).collect();
+    let (text_color, bk_color) = (dc.text_color, dc.bk_color);
+    let opaque = dc.bk_mode == BkMode::OPAQUE;
+    draw_to_dc(machine, hdc, |canvas| {
+        if let Some(rect) = &device_rect {
+            if options & ETO_OPAQUE != 0 {
+                canvas.fill(rect.left, rect.top, rect.right, rect.bottom, bk_color);
+            }
+            if options & ETO_CLIPPED != 0 {
+                canvas.clip = Some(*rect);
+            }
+        }
+        if opaque {
+            let (x0, x1) = (cells[0], cells[text.len()]);
+            canvas.fill(x0, y0, x1, y0 + CHAR_HEIGHT, bk_color);
+        }
+        for (&c, &left) in text.iter().zip(&cells) {
+            for (gx, gy) in glyph_pixels(c) {
+                canvas.plot(left + gx, y0 + gy, text_color);
+            }
+        }
+    })
 }
 
 #[repr(C)]
@@ -79,8 +201,10 @@ pub fn GetTextMetricsA(_machine: &mut Machine, hdc: HDC, lptm: Option<&mut TEXTM
     let tm = lptm.unwrap();
     tm.clear_struct();
 
-    // SkiFree only cares about the height, just make something up for now.
-    tm.tmHeight = 12;
+    // The built-in font is all we draw text with.
+    tm.tmHeight = CHAR_HEIGHT as u32;
+    tm.tmAveCharWidth = CHAR_WIDTH as u32;
+    tm.tmMaxCharWidth = CHAR_WIDTH as u32;
     true
 }
 
@@ -101,8 +225,8 @@ pub fn GetTextExtentPoint32A(
     psizl: Option<&mut SIZE>,
 ) -> bool {
     *psizl.unwrap() = SIZE {
-        cx: lpString.unwrap().len() as i32 * 10,
-        cy: 12,
+        cx: lpString.unwrap().len() as i32 * CHAR_WIDTH,
+        cy: CHAR_HEIGHT,
     };
     true
 }
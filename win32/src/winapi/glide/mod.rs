@@ -0,0 +1,626 @@
+//! State and rendering shared by the 3dfx Glide DLLs, glide2x and glide3x,
+//! drawn with the software rasterizer in winapi::raster.
+//! This module does not become its own DLL.
+//!
+//! We emulate a single Voodoo board with a single TMU.  Textures are downloaded
+//! into the TMU's memory and sampled from its largest mipmap level, without
+//! filtering.  Color combining distinguishes only a few of the hardware's modes:
+//! a local (iterated or constant) color, or some other color, which may be the
+//! texture, scaled by a factor.
+
+#![allow(non_camel_case_types)]
+
+use super::{
+    heap::Heap,
+    raster::{DepthFunc, Framebuffer, Texture, Vertex},
+};
+use crate::{host, machine::Machine, SurfaceOptions};
+use memory::{Extensions, Mem};
+
+const GR_COMBINE_FUNCTION_ZERO: u32 = 0;
+const GR_COMBINE_FUNCTION_LOCAL: u32 = 1;
+const GR_COMBINE_FUNCTION_LOCAL_ALPHA: u32 = 2;
+const GR_COMBINE_FACTOR_ZERO: u32 = 0;
+const GR_COMBINE_FACTOR_LOCAL: u32 = 1;
+const GR_COMBINE_LOCAL_ITERATED: u32 = 0;
+const GR_COMBINE_LOCAL_CONSTANT: u32 = 1;
+const GR_COMBINE_OTHER_ITERATED: u32 = 0;
+const GR_COMBINE_OTHER_TEXTURE: u32 = 1;
+const GR_COMBINE_OTHER_CONSTANT: u32 = 2;
+
+pub const GR_TEXTABLE_PALETTE: u32 = 2;
+
+/// Texture memory we claim to have, per TMU.
+pub const TEXTURE_MEMORY: u32 = 4 << 20;
+/// Frame buffer memory we claim to have.
+pub const FRAME_BUFFER_MEMORY: u32 = 4 << 20;
+
+#[derive(Debug, win32_derive::TryFromEnum)]
+pub enum GrScreenResolution {
+    GR_RESOLUTION_320x200 = 0x0,
+    GR_RESOLUTION_320x240 = 0x1,
+    GR_RESOLUTION_400x256 = 0x2,
+    GR_RESOLUTION_512x384 = 0x3,
+    GR_RESOLUTION_640x200 = 0x4,
+    GR_RESOLUTION_640x350 = 0x5,
+    GR_RESOLUTION_640x400 = 0x6,
+    GR_RESOLUTION_640x480 = 0x7,
+    GR_RESOLUTION_800x600 = 0x8,
+    GR_RESOLUTION_960x720 = 0x9,
+    GR_RESOLUTION_856x480 = 0xa,
+    GR_RESOLUTION_512x256 = 0xb,
+    GR_RESOLUTION_1024x768 = 0xc,
+    GR_RESOLUTION_1280x1024 = 0xd,
+    GR_RESOLUTION_1600x1200 = 0xe,
+    GR_RESOLUTION_400x300 = 0xf,
+}
+
+impl GrScreenResolution {
+    fn size(&self) -> (u32, u32) {
+        use GrScreenResolution::*;
+        match self {
+            GR_RESOLUTION_320x200 => (320, 200),
+            GR_RESOLUTION_320x240 => (320, 240),
+            GR_RESOLUTION_400x256 => (400, 256),
+            GR_RESOLUTION_512x384 => (512, 384),
+            GR_RESOLUTION_640x200 => (640, 200),
+            GR_RESOLUTION_640x350 => (640, 350),
+            GR_RESOLUTION_640x400 => (640, 400),
+            GR_RESOLUTION_640x480 => (640, 480),
+            GR_RESOLUTION_800x600 => (800, 600),
+            GR_RESOLUTION_960x720 => (960, 720),
+            GR_RESOLUTION_856x480 => (856, 480),
+            GR_RESOLUTION_512x256 => (512, 256),
+            GR_RESOLUTION_1024x768 => (1024, 768),
+            GR_RESOLUTION_1280x1024 => (1280, 1024),
+            GR_RESOLUTION_1600x1200 => (1600, 1200),
+            GR_RESOLUTION_400x300 => (400, 300),
+        }
+    }
+}
+
+/// Byte order of GrColor_t values.
+#[derive(Debug, Clone, Copy, win32_derive::TryFromEnum, serde::Serialize, serde::Deserialize)]
+pub enum GrColorFormat {
+    ARGB = 0,
+    ABGR = 1,
+    RGBA = 2,
+    BGRA = 3,
+}
+
+#[derive(Debug, win32_derive::TryFromEnum)]
+pub enum GrOriginLocation {
+    UPPER_LEFT = 0,
+    LOWER_LEFT = 1,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    win32_derive::TryFromEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum GrDepthBufferMode {
+    DISABLE = 0,
+    ZBUFFER = 1,
+    WBUFFER = 2,
+    ZBUFFER_COMPARE_TO_BIAS = 3,
+    WBUFFER_COMPARE_TO_BIAS = 4,
+}
+
+/// A vertex as passed by the application, in screen space, independent of Glide version.
+pub struct ScreenVertex {
+    pub x: f32,
+    pub y: f32,
+    /// 65535/z, for z-buffering.
+    pub ooz: f32,
+    /// 1/w, for w-buffering.
+    pub oow: f32,
+    /// RGBA, each in [0, 255].
+    pub color: [f32; 4],
+    /// Texture coordinates, with 256 spanning the texture's longer side.
+    pub st: [f32; 2],
+}
+
+/// The largest mipmap level of a texture, independent of Glide version.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct TexInfo {
+    pub width: u32,
+    pub height: u32,
+    /// GrTextureFormat_t.
+    pub format: u32,
+}
+
+impl TexInfo {
+    /// Byte offset of a mipmap level, counting down from the largest as 0,
+    /// in memory holding the levels largest first.
+    pub fn level_offset(&self, level: u32) -> u32 {
+        // Formats below GR_TEXFMT_16BIT (8) are one byte per texel.
+        let bpp = if self.format < 8 { 1 } else { 2 };
+        (0..level)
+            .map(|i| (self.width >> i).max(1) * (self.height >> i).max(1) * bpp)
+            .sum()
+    }
+
+    /// Decode the level at the start of `data` to RGBA, or None for the YIQ formats,
+    /// which need NCC tables we don't implement.
+    fn decode(&self, data: &[u8], palette: &[[u8; 4]]) -> Option<Texture> {
+        if matches!(self.format, 1 | 6 | 7 | 9) || self.format > 14 {
+            return None;
+        }
+        let count = (self.width * self.height) as usize;
+        let texels = if self.format < 8 {
+            data[..count]
+                .iter()
+                .map(|&v| self.texel(v as u16, palette))
+                .collect()
+        } else {
+            data[..count * 2]
+                .chunks_exact(2)
+                .map(|v| self.texel(u16::from_le_bytes([v[0], v[1]]), palette))
+                .collect()
+        };
+        Some(Texture {
+            width: self.width,
+            height: self.height,
+            texels,
+        })
+    }
+
+    fn texel(&self, v: u16, palette: &[[u8; 4]]) -> [u8; 4] {
+        // Scale an n-bit channel to 8 bits.
+        let x = |v: u16, bits: u32| (v as u32 * 255 / ((1 << bits) - 1)) as u8;
+        let lookup = |i: u16| palette.get(i as usize).copied().unwrap_or([0, 0, 0, 255]);
+        match self.format {
+            // GR_TEXFMT_RGB_332
+            0 => [x(v >> 5 & 7, 3), x(v >> 2 & 7, 3), x(v & 3, 2), 255],
+            // GR_TEXFMT_ALPHA_8
+            2 => [255, 255, 255, v as u8],
+            // GR_TEXFMT_INTENSITY_8
+            3 => [v as u8, v as u8, v as u8, 255],
+            // GR_TEXFMT_ALPHA_INTENSITY_44
+            4 => {
+                let i = x(v & 0xF, 4);
+                [i, i, i, x(v >> 4, 4)]
+            }
+            // GR_TEXFMT_P_8
+            5 => lookup(v),
+            // GR_TEXFMT_ARGB_8332
+            8 => [
+                x(v >> 5 & 7, 3),
+                x(v >> 2 & 7, 3),
+                x(v & 3, 2),
+                (v >> 8) as u8,
+            ],
+            // GR_TEXFMT_RGB_565
+            10 => [x(v >> 11, 5), x(v >> 5 & 0x3F, 6), x(v & 0x1F, 5), 255],
+            // GR_TEXFMT_ARGB_1555
+            11 => [
+                x(v >> 10 & 0x1F, 5),
+                x(v >> 5 & 0x1F, 5),
+                x(v & 0x1F, 5),
+                x(v >> 15, 1),
+            ],
+            // GR_TEXFMT_ARGB_4444
+            12 => [
+                x(v >> 8 & 0xF, 4),
+                x(v >> 4 & 0xF, 4),
+                x(v & 0xF, 4),
+                x(v >> 12, 4),
+            ],
+            // GR_TEXFMT_ALPHA_INTENSITY_88
+            13 => [v as u8, v as u8, v as u8, (v >> 8) as u8],
+            // GR_TEXFMT_AP_88
+            14 => {
+                let [r, g, b, _] = lookup(v & 0xFF);
+                [r, g, b, (v >> 8) as u8]
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct State {
+    heap: Heap,
+    /// Addresses of strings returned by grGetString, allocated on first use.
+    strings: [u32; 5],
+
+    /// Created by grSstWinOpen.
+    fb: Option<Framebuffer>,
+    /// The host surface showing fb, which is recreated when a snapshot is restored.
+    #[serde(skip)]
+    surface: Option<Box<dyn host::Surface>>,
+    color_format: GrColorFormat,
+    origin_lower_left: bool,
+
+    depth_mode: GrDepthBufferMode,
+    depth_func: DepthFunc,
+    /// Color for GR_COMBINE_LOCAL_CONSTANT.
+    constant_color: [f32; 4],
+    /// grColorCombine's function, factor, local and other.
+    color_combine: [u32; 4],
+    /// Whether grTexCombine has the TMU put out its texture, rather than zero.
+    tex_output: bool,
+
+    /// The TMU's texture memory, allocated by grGlideInit.
+    texture_memory: Vec<u8>,
+    /// The texture selected by grTexSource, by its address in texture memory.
+    tex_source: Option<(u32, TexInfo)>,
+    /// GR_TEXTABLE_PALETTE, as RGBA.
+    palette: Vec<[u8; 4]>,
+    /// tex_source decoded, cleared whenever what it was decoded from changes.
+    #[serde(skip)]
+    texture: Option<Texture>,
+
+    /// Glide 3 only.
+    pub vertex_layout: super::glide3x::VertexLayout,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            heap: Heap::default(),
+            strings: [0; 5],
+            fb: None,
+            surface: None,
+            color_format: GrColorFormat::ARGB,
+            origin_lower_left: false,
+            depth_mode: GrDepthBufferMode::DISABLE,
+            depth_func: DepthFunc::Less,
+            constant_color: [1.0; 4],
+            color_combine: [
+                GR_COMBINE_FUNCTION_LOCAL,
+                GR_COMBINE_FACTOR_ZERO,
+                GR_COMBINE_LOCAL_ITERATED,
+                GR_COMBINE_OTHER_ITERATED,
+            ],
+            tex_output: true,
+            texture_memory: Vec::new(),
+            tex_source: None,
+            palette: Vec::new(),
+            texture: None,
+            vertex_layout: Default::default(),
+        }
+    }
+}
+
+impl State {
+    fn new_init(machine: &mut Machine) -> Self {
+        State {
+            heap: machine.state.kernel32.new_private_heap(
+                &mut machine.emu.memory,
+                0x1000,
+                "glide.dll heap".into(),
+            ),
+            texture_memory: vec![0; TEXTURE_MEMORY as usize],
+            ..State::default()
+        }
+    }
+
+    /// Convert a GrColor_t to RGBA, each in [0, 1].
+    fn unpack_color(&self, color: u32) -> [f32; 4] {
+        let [b0, b1, b2, b3] = color.to_be_bytes();
+        let [r, g, b, a] = match self.color_format {
+            GrColorFormat::ARGB => [b1, b2, b3, b0],
+            GrColorFormat::ABGR => [b3, b2, b1, b0],
+            GrColorFormat::RGBA => [b0, b1, b2, b3],
+            GrColorFormat::BGRA => [b2, b1, b0, b3],
+        };
+        [r, g, b, a].map(|c| c as f32 / 255.0)
+    }
+
+    pub fn color_combine(&mut self, function: u32, factor: u32, local: u32, other: u32) {
+        self.color_combine = [function, factor, local, other];
+    }
+
+    pub fn constant_color_value(&mut self, color: u32) {
+        self.constant_color = self.unpack_color(color);
+    }
+
+    /// The color a vertex draws with, per grColorCombine, given its iterated color,
+    /// and whether that's then modulated by the texture.
+    fn combine(&self, iterated: [f32; 4]) -> ([f32; 4], bool) {
+        let [function, factor, local, other] = self.color_combine;
+        let local = match local {
+            GR_COMBINE_LOCAL_CONSTANT => self.constant_color,
+            _ => iterated,
+        };
+        match function {
+            GR_COMBINE_FUNCTION_ZERO => ([0.0, 0.0, 0.0, 1.0], false),
+            GR_COMBINE_FUNCTION_LOCAL | GR_COMBINE_FUNCTION_LOCAL_ALPHA => (local, false),
+            // The other functions scale the other color by the factor; we ignore
+            // anything they then add to or subtract from it.
+            _ => {
+                let factor = match factor {
+                    GR_COMBINE_FACTOR_ZERO => [0.0, 0.0, 0.0, 1.0],
+                    GR_COMBINE_FACTOR_LOCAL => local,
+                    _ => [1.0; 4],
+                };
+                let scale = |other: [f32; 4]| [0, 1, 2, 3].map(|i| factor[i] * other[i]);
+                match other {
+                    GR_COMBINE_OTHER_TEXTURE if self.tex_output => (factor, true),
+                    GR_COMBINE_OTHER_TEXTURE => ([0.0, 0.0, 0.0, 1.0], false),
+                    GR_COMBINE_OTHER_CONSTANT => (scale(self.constant_color), false),
+                    _ => (scale(iterated), false),
+                }
+            }
+        }
+    }
+
+    pub fn tex_combine(&mut self, tmu: u32, rgb_function: u32) {
+        if tmu != 0 {
+            return;
+        }
+        // Only the TMU's own texture (LOCAL) or nothing; there are no further TMUs
+        // for the other functions to combine it with.
+        self.tex_output = rgb_function != GR_COMBINE_FUNCTION_ZERO;
+    }
+
+    pub fn tex_source(&mut self, tmu: u32, start: u32, info: TexInfo) {
+        if tmu != 0 {
+            log::warn!("grTexSource: no TMU {tmu}");
+            return;
+        }
+        self.tex_source = Some((start, info));
+        self.texture = None;
+    }
+
+    /// Copy texture data from guest memory into texture memory at `start`.
+    pub fn tex_download(&mut self, mem: Mem, tmu: u32, start: u32, data: u32, len: u32) {
+        if tmu != 0 {
+            log::warn!("grTexDownloadMipMap: no TMU {tmu}");
+            return;
+        }
+        let Some(dst) = self
+            .texture_memory
+            .get_mut(start as usize..(start + len) as usize)
+        else {
+            log::warn!("grTexDownloadMipMap: {len:x} bytes at {start:x} overflow texture memory");
+            return;
+        };
+        dst.copy_from_slice(mem.sub(data, len).as_slice_todo());
+        self.texture = None;
+    }
+
+    /// Set the palette for GR_TEXFMT_P_8 textures from 256 0x00RRGGBB entries.
+    pub fn tex_download_palette(&mut self, mem: Mem, data: u32) {
+        self.palette = (0..256)
+            .map(|i| {
+                let [b, g, r, _] = mem.get_pod::<u32>(data + i * 4).to_le_bytes();
+                [r, g, b, 255]
+            })
+            .collect();
+        self.texture = None;
+    }
+
+    /// Decode the source texture, if it needs it.
+    fn update_texture(&mut self) {
+        let Some((start, info)) = self.tex_source else {
+            return;
+        };
+        if self.texture.is_some() {
+            return;
+        }
+        let end = start as usize + info.level_offset(1) as usize;
+        let Some(data) = self.texture_memory.get(start as usize..end) else {
+            log::warn!("glide: texture at {start:x} overflows texture memory");
+            return;
+        };
+        self.texture = info.decode(data, &self.palette);
+        if self.texture.is_none() {
+            log::warn!("glide: unimplemented texture format {}", info.format);
+        }
+    }
+
+    fn update_depth(&mut self) {
+        if let Some(fb) = &mut self.fb {
+            fb.depth_func = match self.depth_mode {
+                GrDepthBufferMode::DISABLE => None,
+                _ => Some(self.depth_func),
+            };
+        }
+    }
+
+    pub fn depth_buffer_mode(&mut self, mode: Result<GrDepthBufferMode, u32>) {
+        self.depth_mode = match mode {
+            Ok(mode) => mode,
+            Err(mode) => {
+                log::warn!("grDepthBufferMode({mode:x}): unknown mode");
+                GrDepthBufferMode::DISABLE
+            }
+        };
+        self.update_depth();
+    }
+
+    pub fn depth_buffer_function(&mut self, function: u32) {
+        // GR_CMP_* values match the order of the GL comparisons.
+        self.depth_func = match function {
+            0 => DepthFunc::Never,
+            1 => DepthFunc::Less,
+            2 => DepthFunc::Equal,
+            3 => DepthFunc::LessEqual,
+            4 => DepthFunc::Greater,
+            5 => DepthFunc::NotEqual,
+            6 => DepthFunc::GreaterEqual,
+            7 => DepthFunc::Always,
+            _ => {
+                log::warn!("grDepthBufferFunction({function:x}): unknown function");
+                return;
+            }
+        };
+        self.update_depth();
+    }
+
+    pub fn depth_mask(&mut self, enable: bool) {
+        if let Some(fb) = &mut self.fb {
+            fb.depth_write = enable;
+        }
+    }
+
+    /// Map a Glide depth value to a rasterizer depth, per the depth buffer mode.
+    fn depth(&self, v: &ScreenVertex) -> f32 {
+        match self.depth_mode {
+            GrDepthBufferMode::WBUFFER | GrDepthBufferMode::WBUFFER_COMPARE_TO_BIAS => 1.0 / v.oow,
+            _ => v.ooz / 65535.0,
+        }
+    }
+
+    pub fn buffer_clear(&mut self, color: u32, alpha: u8, depth: u16) {
+        let mut rgba = self.unpack_color(color);
+        rgba[3] = alpha as f32 / 255.0;
+        let depth = match self.depth_mode {
+            // The hardware stores w as a 16-bit float; 0xFFFF is the usual "far" value.
+            GrDepthBufferMode::WBUFFER | GrDepthBufferMode::WBUFFER_COMPARE_TO_BIAS => {
+                if depth == 0xFFFF {
+                    f32::MAX
+                } else {
+                    depth as f32
+                }
+            }
+            _ => depth as f32 / 65535.0,
+        };
+        if let Some(fb) = &mut self.fb {
+            fb.clear_color(rgba);
+            fb.clear_depth(depth);
+        }
+    }
+
+    pub fn buffer_swap(&mut self) {
+        if let (Some(fb), Some(surface)) = (&self.fb, &mut self.surface) {
+            let pixels: Vec<_> = fb
+                .color
+                .iter()
+                .map(|&[r, g, b, _]| [r, g, b, 255])
+                .collect();
+            surface.write_pixels(&pixels);
+            surface.show();
+        }
+    }
+
+    pub fn draw_triangle(&mut self, v: [&ScreenVertex; 3]) {
+        let fb_height = match &self.fb {
+            Some(fb) => fb.height as f32,
+            None => return,
+        };
+        let (_, textured) = self.combine([1.0; 4]);
+        if textured {
+            self.update_texture();
+        }
+        // Texture coordinates span 256 along the longer side of the texture.
+        let st_range = match self.tex_source {
+            Some((_, info)) => {
+                let long = info.width.max(info.height) as f32;
+                [info.width as f32, info.height as f32].map(|size| size * 256.0 / long)
+            }
+            None => [256.0; 2],
+        };
+        let [a, b, c] = v.map(|v| Vertex {
+            x: v.x,
+            y: if self.origin_lower_left {
+                fb_height - v.y
+            } else {
+                v.y
+            },
+            z: self.depth(v),
+            color: self.combine(v.color.map(|c| c / 255.0)).0,
+            uv: [v.st[0] / st_range[0], v.st[1] / st_range[1]],
+        });
+        let texture = if textured {
+            self.texture.as_ref()
+        } else {
+            None
+        };
+        self.fb
+            .as_mut()
+            .unwrap()
+            .triangle_textured([&a, &b, &c], texture);
+    }
+
+    /// Allocate a NUL-terminated string in the Glide heap, once per index.
+    pub fn string(&mut self, mem: memory::Mem, index: usize, value: &str) -> u32 {
+        if self.strings[index] == 0 {
+            let len = value.len() as u32 + 1;
+            let addr = self.heap.alloc(mem, len);
+            let buf = mem.sub(addr, len).as_mut_slice_todo();
+            buf[..value.len()].copy_from_slice(value.as_bytes());
+            buf[value.len()] = 0;
+            self.strings[index] = addr;
+        }
+        self.strings[index]
+    }
+}
+
+pub fn glide_init(machine: &mut Machine) {
+    if machine.state.glide.heap.addr == 0 {
+        machine.state.glide = State::new_init(machine);
+    }
+}
+
+pub fn sst_win_open(
+    machine: &mut Machine,
+    res: Result<GrScreenResolution, u32>,
+    cformat: Result<GrColorFormat, u32>,
+    origin: Result<GrOriginLocation, u32>,
+) -> bool {
+    let (width, height) = match res {
+        Ok(res) => res.size(),
+        Err(res) => {
+            log::warn!("grSstWinOpen: unknown resolution {res:x}");
+            return false;
+        }
+    };
+    let surface = machine.host.create_surface(&SurfaceOptions {
+        width,
+        height,
+        primary: true,
+    });
+    let glide = &mut machine.state.glide;
+    glide.color_format = cformat.unwrap_or(GrColorFormat::ARGB);
+    glide.origin_lower_left = matches!(origin, Ok(GrOriginLocation::LOWER_LEFT));
+    glide.fb = Some(Framebuffer::new(width, height));
+    glide.surface = Some(surface);
+    glide.update_depth();
+    true
+}
+
+/// After a snapshot is restored, recreate the host surface of an open framebuffer
+/// and show what it last held.
+pub fn restore(machine: &mut Machine) {
+    let Some(fb) = &machine.state.glide.fb else {
+        return;
+    };
+    let surface = machine.host.create_surface(&SurfaceOptions {
+        width: fb.width,
+        height: fb.height,
+        primary: true,
+    });
+    let glide = &mut machine.state.glide;
+    glide.surface = Some(surface);
+    glide.buffer_swap();
+}
+
+pub fn sst_win_close(machine: &mut Machine) {
+    let glide = &mut machine.state.glide;
+    glide.fb = None;
+    glide.surface = None;
+}
+
+/// Gather the framebuffer, if open, for a frame dump.
+pub fn dump_framebuffer(machine: &Machine) -> Vec<crate::framedump::SurfaceDump> {
+    let Some(fb) = &machine.state.glide.fb else {
+        return Vec::new();
+    };
+    vec![crate::framedump::SurfaceDump {
+        kind: "glide",
+        addr: 0,
+        width: fb.width,
+        height: fb.height,
+        attached: 0,
+        pixels: Some(fb.color.clone()),
+    }]
+}
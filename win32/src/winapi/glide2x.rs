@@ -0,0 +1,412 @@
+//! 3dfx Glide 2.x.  See glide/mod.rs for the shared implementation.
+//!
+//! Missing: grSstScreenWidth/grSstScreenHeight, which return floats in st(0).
+
+#![allow(non_snake_case)]
+
+use super::glide::{self, ScreenVertex, TexInfo, FRAME_BUFFER_MEMORY, TEXTURE_MEMORY};
+pub use super::glide::{GrColorFormat, GrDepthBufferMode, GrOriginLocation, GrScreenResolution};
+use crate::machine::Machine;
+use memory::Pod;
+
+const TRACE_CONTEXT: &'static str = "glide2x";
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GrTmuVertex {
+    pub sow: f32,
+    pub tow: f32,
+    pub oow: f32,
+}
+unsafe impl memory::Pod for GrTmuVertex {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GrVertex {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub ooz: f32,
+    pub a: f32,
+    pub oow: f32,
+    pub tmuvtx: [GrTmuVertex; 3],
+}
+unsafe impl memory::Pod for GrVertex {}
+
+impl GrVertex {
+    fn to_screen(&self) -> ScreenVertex {
+        ScreenVertex {
+            x: self.x,
+            y: self.y,
+            ooz: self.ooz,
+            oow: self.oow,
+            color: [self.r, self.g, self.b, self.a],
+            st: match self.tmuvtx[0].oow {
+                0.0 => [0.0; 2],
+                oow => [self.tmuvtx[0].sow / oow, self.tmuvtx[0].tow / oow],
+            },
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct GrTMUConfig {
+    pub tmuRev: u32,
+    pub tmuRam: u32,
+}
+unsafe impl memory::Pod for GrTMUConfig {}
+
+/// GrSstConfig_t, with the board union as the Voodoo variant.
+#[repr(C)]
+#[derive(Debug)]
+pub struct GrSstConfig {
+    pub type_: u32,
+    pub fbRam: u32,
+    pub fbiRev: u32,
+    pub nTexelfx: u32,
+    pub sliDetect: u32,
+    pub tmuConfig: [GrTMUConfig; 3],
+}
+unsafe impl memory::Pod for GrSstConfig {}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct GrHwConfiguration {
+    pub num_sst: u32,
+    pub SSTs: [GrSstConfig; 4],
+}
+unsafe impl memory::Pod for GrHwConfiguration {}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct GrTexInfo {
+    pub smallLod: u32,
+    pub largeLod: u32,
+    pub aspectRatio: u32,
+    pub format: u32,
+    pub data: u32,
+}
+unsafe impl memory::Pod for GrTexInfo {}
+
+impl GrTexInfo {
+    /// Bytes needed for the mipmap levels from largeLod down to smallLod.
+    fn mem_required(&self) -> u32 {
+        // GR_LOD_256 = 0 .. GR_LOD_1 = 8; GR_ASPECT_8x1 = 0 .. GR_ASPECT_1x8 = 6.
+        // Formats below GR_TEXFMT_16BIT (8) are one byte per texel.
+        let bpp = if self.format < 8 { 1 } else { 2 };
+        let shift = (self.aspectRatio as i32 - 3).unsigned_abs();
+        let size: u32 = (self.largeLod..=self.smallLod.min(8))
+            .map(|lod| {
+                let long = 256 >> lod;
+                let short = (long >> shift).max(1);
+                long * short * bpp
+            })
+            .sum();
+        (size + 7) & !7
+    }
+
+    fn tex_info(&self) -> TexInfo {
+        lod_info(self.largeLod, self.aspectRatio, self.format)
+    }
+}
+
+/// The size of a mipmap level, given its GrLOD_t and GrAspectRatio_t.
+fn lod_info(lod: u32, aspect: u32, format: u32) -> TexInfo {
+    let long = 256 >> lod.min(8);
+    let (width, height) = if aspect < 3 {
+        (long, long >> (3 - aspect))
+    } else {
+        (long >> (aspect - 3).min(3), long)
+    };
+    TexInfo {
+        width: width.max(1),
+        height: height.max(1),
+        format,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn grGlideInit(machine: &mut Machine) -> u32 {
+    glide::glide_init(machine);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grGlideShutdown(machine: &mut Machine) -> u32 {
+    glide::sst_win_close(machine);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grGlideGetVersion(_machine: &mut Machine, version: Option<&mut [u8; 80]>) -> u32 {
+    let version = version.unwrap();
+    let str = b"Glide Version 2.43\0";
+    version[..str.len()].copy_from_slice(str);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grSstQueryBoards(_machine: &mut Machine, hwConfig: Option<&mut GrHwConfiguration>) -> bool {
+    let hwconfig = hwConfig.unwrap();
+    hwconfig.clear_struct();
+    hwconfig.num_sst = 1;
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn grSstQueryHardware(
+    _machine: &mut Machine,
+    hwConfig: Option<&mut GrHwConfiguration>,
+) -> bool {
+    let hwconfig = hwConfig.unwrap();
+    hwconfig.clear_struct();
+    hwconfig.num_sst = 1;
+    let sst = &mut hwconfig.SSTs[0];
+    sst.type_ = 0; // GR_SSTTYPE_VOODOO
+    sst.fbRam = FRAME_BUFFER_MEMORY >> 20;
+    sst.fbiRev = 2;
+    sst.nTexelfx = 1;
+    sst.tmuConfig[0] = GrTMUConfig {
+        tmuRev: 1,
+        tmuRam: TEXTURE_MEMORY >> 20,
+    };
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn grSstSelect(_machine: &mut Machine, which_sst: u32) -> u32 {
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grSstWinOpen(
+    machine: &mut Machine,
+    hWnd: u32,
+    res: Result<GrScreenResolution, u32>,
+    refresh: u32,
+    cFormat: Result<GrColorFormat, u32>,
+    org_loc: Result<GrOriginLocation, u32>,
+    num_buffers: i32,
+    num_aux_buffers: i32,
+) -> bool {
+    glide::sst_win_open(machine, res, cFormat, org_loc)
+}
+
+#[win32_derive::dllexport]
+pub fn grSstWinClose(machine: &mut Machine) -> u32 {
+    glide::sst_win_close(machine);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grBufferClear(machine: &mut Machine, color: u32, alpha: u32, depth: u32) -> u32 {
+    machine
+        .state
+        .glide
+        .buffer_clear(color, alpha as u8, depth as u16);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grBufferSwap(machine: &mut Machine, swap_interval: i32) -> u32 {
+    machine.state.glide.buffer_swap();
+    machine.presented();
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grBufferNumPending(_machine: &mut Machine) -> i32 {
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grDrawTriangle(
+    machine: &mut Machine,
+    a: Option<&GrVertex>,
+    b: Option<&GrVertex>,
+    c: Option<&GrVertex>,
+) -> u32 {
+    let [a, b, c] = [a, b, c].map(|v| v.unwrap().to_screen());
+    machine.state.glide.draw_triangle([&a, &b, &c]);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grColorCombine(
+    machine: &mut Machine,
+    function: u32,
+    factor: u32,
+    local: u32,
+    other: u32,
+    invert: bool,
+) -> u32 {
+    machine
+        .state
+        .glide
+        .color_combine(function, factor, local, other);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grAlphaCombine(
+    _machine: &mut Machine,
+    function: u32,
+    factor: u32,
+    local: u32,
+    other: u32,
+    invert: bool,
+) -> u32 {
+    0 // TODO
+}
+
+#[win32_derive::dllexport]
+pub fn grConstantColorValue(machine: &mut Machine, value: u32) -> u32 {
+    machine.state.glide.constant_color_value(value);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grDepthBufferMode(machine: &mut Machine, mode: Result<GrDepthBufferMode, u32>) -> u32 {
+    machine.state.glide.depth_buffer_mode(mode);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grDepthBufferFunction(machine: &mut Machine, function: u32) -> u32 {
+    machine.state.glide.depth_buffer_function(function);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grDepthMask(machine: &mut Machine, enable: bool) -> u32 {
+    machine.state.glide.depth_mask(enable);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grCullMode(_machine: &mut Machine, mode: u32) -> u32 {
+    0 // TODO: we draw both windings regardless.
+}
+
+#[win32_derive::dllexport]
+pub fn grClipWindow(_machine: &mut Machine, minx: u32, miny: u32, maxx: u32, maxy: u32) -> u32 {
+    0 // TODO
+}
+
+#[win32_derive::dllexport]
+pub fn grTexMinAddress(_machine: &mut Machine, tmu: u32) -> u32 {
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grTexMaxAddress(_machine: &mut Machine, tmu: u32) -> u32 {
+    TEXTURE_MEMORY
+}
+
+#[win32_derive::dllexport]
+pub fn grTexTextureMemRequired(
+    _machine: &mut Machine,
+    evenOdd: u32,
+    info: Option<&GrTexInfo>,
+) -> u32 {
+    info.unwrap().mem_required()
+}
+
+#[win32_derive::dllexport]
+pub fn grTexDownloadMipMap(
+    machine: &mut Machine,
+    tmu: u32,
+    startAddress: u32,
+    evenOdd: u32,
+    info: Option<&GrTexInfo>,
+) -> u32 {
+    let info = info.unwrap();
+    let mem = machine.emu.memory.mem();
+    machine
+        .state
+        .glide
+        .tex_download(mem, tmu, startAddress, info.data, info.mem_required());
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grTexDownloadMipMapLevel(
+    machine: &mut Machine,
+    tmu: u32,
+    startAddress: u32,
+    thisLod: u32,
+    largeLod: u32,
+    aspectRatio: u32,
+    format: u32,
+    evenOdd: u32,
+    data: u32,
+) -> u32 {
+    let large = lod_info(largeLod, aspectRatio, format);
+    let offset = large.level_offset(thisLod.saturating_sub(largeLod));
+    let this = lod_info(thisLod, aspectRatio, format);
+    let len = this.level_offset(1);
+    let mem = machine.emu.memory.mem();
+    machine
+        .state
+        .glide
+        .tex_download(mem, tmu, startAddress + offset, data, len);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grTexDownloadTable(machine: &mut Machine, tmu: u32, type_: u32, data: u32) -> u32 {
+    if type_ != glide::GR_TEXTABLE_PALETTE {
+        log::warn!("grTexDownloadTable({type_:x}): unimplemented");
+        return 0;
+    }
+    let mem = machine.emu.memory.mem();
+    machine.state.glide.tex_download_palette(mem, data);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grTexSource(
+    machine: &mut Machine,
+    tmu: u32,
+    startAddress: u32,
+    evenOdd: u32,
+    info: Option<&GrTexInfo>,
+) -> u32 {
+    let info = info.unwrap().tex_info();
+    machine.state.glide.tex_source(tmu, startAddress, info);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grTexCombine(
+    machine: &mut Machine,
+    tmu: u32,
+    rgb_function: u32,
+    rgb_factor: u32,
+    alpha_function: u32,
+    alpha_factor: u32,
+    rgb_invert: bool,
+    alpha_invert: bool,
+) -> u32 {
+    machine.state.glide.tex_combine(tmu, rgb_function);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grTexFilterMode(
+    _machine: &mut Machine,
+    tmu: u32,
+    minfilter_mode: u32,
+    magfilter_mode: u32,
+) -> u32 {
+    0 // TODO: we always point sample.
+}
+
+#[win32_derive::dllexport]
+pub fn grTexClampMode(_machine: &mut Machine, tmu: u32, s_clampmode: u32, t_clampmode: u32) -> u32 {
+    0 // TODO: we always wrap.
+}
@@ -0,0 +1,546 @@
+//! 3dfx Glide 3.x.  See glide/mod.rs for the shared implementation.
+//!
+//! The main difference from Glide 2 is that vertices are untyped memory,
+//! described by grVertexLayout.
+
+#![allow(non_snake_case)]
+#![allow(non_camel_case_types)]
+
+use super::glide::{self, ScreenVertex, TexInfo, FRAME_BUFFER_MEMORY, TEXTURE_MEMORY};
+pub use super::glide::{GrColorFormat, GrDepthBufferMode, GrOriginLocation, GrScreenResolution};
+use crate::machine::Machine;
+use memory::{Extensions, Mem};
+
+const TRACE_CONTEXT: &'static str = "glide3x";
+
+/// The single GrContext_t we hand out.
+const CONTEXT: u32 = 1;
+
+const GR_PARAM_XY: u32 = 0x01;
+const GR_PARAM_Z: u32 = 0x02;
+const GR_PARAM_W: u32 = 0x03;
+const GR_PARAM_Q: u32 = 0x04;
+const GR_PARAM_A: u32 = 0x10;
+const GR_PARAM_RGB: u32 = 0x20;
+const GR_PARAM_PARGB: u32 = 0x30;
+const GR_PARAM_ST0: u32 = 0x40;
+const GR_PARAM_Q0: u32 = 0x50;
+
+/// Byte offsets of vertex parameters, as configured by grVertexLayout.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct VertexLayout {
+    xy: Option<u32>,
+    z: Option<u32>,
+    w: Option<u32>,
+    q: Option<u32>,
+    a: Option<u32>,
+    rgb: Option<u32>,
+    pargb: Option<u32>,
+    st0: Option<u32>,
+    q0: Option<u32>,
+}
+
+impl VertexLayout {
+    fn read(&self, mem: Mem, addr: u32) -> ScreenVertex {
+        let f = |ofs: u32| mem.get_pod::<f32>(addr + ofs);
+        let (x, y) = match self.xy {
+            Some(ofs) => (f(ofs), f(ofs + 4)),
+            None => (0.0, 0.0),
+        };
+        let mut color = [255.0; 4];
+        if let Some(ofs) = self.pargb {
+            let [b, g, r, a] = mem.get_pod::<u32>(addr + ofs).to_le_bytes();
+            color = [r, g, b, a].map(|c| c as f32);
+        }
+        if let Some(ofs) = self.rgb {
+            color[..3].copy_from_slice(&[f(ofs), f(ofs + 4), f(ofs + 8)]);
+        }
+        if let Some(ofs) = self.a {
+            color[3] = f(ofs);
+        }
+        let oow = self.w.or(self.q).map_or(1.0, f);
+        // s/w and t/w, divided by TMU 0's own 1/w if it has one.
+        let st = match self.st0 {
+            Some(ofs) => match self.q0.map_or(oow, f) {
+                0.0 => [0.0; 2],
+                q => [f(ofs) / q, f(ofs + 4) / q],
+            },
+            None => [0.0; 2],
+        };
+        ScreenVertex {
+            x,
+            y,
+            ooz: self.z.map_or(0.0, f),
+            oow,
+            color,
+            st,
+        }
+    }
+}
+
+fn draw_triangle(machine: &mut Machine, addrs: [u32; 3]) {
+    let mem = machine.emu.memory.mem();
+    let glide = &mut machine.state.glide;
+    let [a, b, c] = addrs.map(|addr| glide.vertex_layout.read(mem, addr));
+    glide.draw_triangle([&a, &b, &c]);
+}
+
+#[derive(Debug, win32_derive::TryFromEnum)]
+pub enum GrDrawMode {
+    GR_POINTS = 0,
+    GR_LINE_STRIP = 1,
+    GR_LINES = 2,
+    GR_POLYGON = 3,
+    GR_TRIANGLE_STRIP = 4,
+    GR_TRIANGLE_FAN = 5,
+    GR_TRIANGLES = 6,
+    GR_TRIANGLE_STRIP_CONTINUE = 7,
+    GR_TRIANGLE_FAN_CONTINUE = 8,
+}
+
+/// Draw a primitive given the addresses of its vertices.
+fn draw_array(machine: &mut Machine, mode: Result<GrDrawMode, u32>, addrs: &[u32]) {
+    let mode = match mode {
+        Ok(mode) => mode,
+        Err(mode) => {
+            log::warn!("grDrawVertexArray: unknown mode {mode:x}");
+            return;
+        }
+    };
+    match mode {
+        GrDrawMode::GR_TRIANGLES => {
+            for tri in addrs.chunks_exact(3) {
+                draw_triangle(machine, [tri[0], tri[1], tri[2]]);
+            }
+        }
+        // TODO: the _CONTINUE modes should reuse vertices from the previous call.
+        GrDrawMode::GR_TRIANGLE_STRIP | GrDrawMode::GR_TRIANGLE_STRIP_CONTINUE => {
+            for tri in addrs.windows(3) {
+                draw_triangle(machine, [tri[0], tri[1], tri[2]]);
+            }
+        }
+        GrDrawMode::GR_TRIANGLE_FAN
+        | GrDrawMode::GR_TRIANGLE_FAN_CONTINUE
+        | GrDrawMode::GR_POLYGON => {
+            for i in 2..addrs.len() {
+                draw_triangle(machine, [addrs[0], addrs[i - 1], addrs[i]]);
+            }
+        }
+        _ => log::warn!("grDrawVertexArray({mode:?}): unimplemented"),
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct GrTexInfo {
+    pub smallLodLog2: i32,
+    pub largeLodLog2: i32,
+    pub aspectRatioLog2: i32,
+    pub format: u32,
+    pub data: u32,
+}
+unsafe impl memory::Pod for GrTexInfo {}
+
+impl GrTexInfo {
+    /// Bytes needed for the mipmap levels from largeLodLog2 down to smallLodLog2.
+    fn mem_required(&self) -> u32 {
+        // Formats below GR_TEXFMT_16BIT (8) are one byte per texel.
+        let bpp = if self.format < 8 { 1 } else { 2 };
+        let shift = self.aspectRatioLog2.unsigned_abs();
+        let size: u32 = (self.smallLodLog2.max(0)..=self.largeLodLog2.min(8))
+            .map(|lod| {
+                let long = 1u32 << lod;
+                let short = (long >> shift).max(1);
+                long * short * bpp
+            })
+            .sum();
+        (size + 7) & !7
+    }
+
+    fn tex_info(&self) -> TexInfo {
+        lod_info(self.largeLodLog2, self.aspectRatioLog2, self.format)
+    }
+}
+
+/// The size of a mipmap level, given its GrLOD_t and GrAspectRatio_t.
+fn lod_info(lod: i32, aspect: i32, format: u32) -> TexInfo {
+    let long = 1u32 << lod.clamp(0, 8);
+    let (width, height) = if aspect >= 0 {
+        (long, long >> aspect.min(3))
+    } else {
+        (long >> (-aspect).min(3), long)
+    };
+    TexInfo {
+        width: width.max(1),
+        height: height.max(1),
+        format,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn grGlideInit(machine: &mut Machine) -> u32 {
+    glide::glide_init(machine);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grGlideShutdown(machine: &mut Machine) -> u32 {
+    glide::sst_win_close(machine);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grSstSelect(_machine: &mut Machine, which_sst: u32) -> u32 {
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grSstWinOpen(
+    machine: &mut Machine,
+    hWnd: u32,
+    res: Result<GrScreenResolution, u32>,
+    refresh: u32,
+    cFormat: Result<GrColorFormat, u32>,
+    org_loc: Result<GrOriginLocation, u32>,
+    nColBuffers: i32,
+    nAuxBuffers: i32,
+) -> u32 {
+    if !glide::sst_win_open(machine, res, cFormat, org_loc) {
+        return 0;
+    }
+    CONTEXT
+}
+
+#[win32_derive::dllexport]
+pub fn grSstWinClose(machine: &mut Machine, context: u32) -> bool {
+    glide::sst_win_close(machine);
+    context == CONTEXT
+}
+
+#[win32_derive::dllexport]
+pub fn grSelectContext(_machine: &mut Machine, context: u32) -> bool {
+    context == CONTEXT
+}
+
+#[derive(Debug, win32_derive::TryFromEnum)]
+pub enum GrGetName {
+    GR_BITS_DEPTH = 0x01,
+    GR_BITS_RGBA = 0x02,
+    GR_MAX_TEXTURE_SIZE = 0x0a,
+    GR_MAX_TEXTURE_ASPECT_RATIO = 0x0b,
+    GR_MEMORY_FB = 0x0c,
+    GR_MEMORY_TMU = 0x0d,
+    GR_MEMORY_UMA = 0x0e,
+    GR_NUM_BOARDS = 0x0f,
+    GR_NUM_FB = 0x11,
+    GR_NUM_TMU = 0x13,
+    GR_PENDING_BUFFERSWAPS = 0x14,
+    GR_REVISION_FB = 0x15,
+    GR_REVISION_TMU = 0x16,
+}
+
+/// Returns the number of bytes written to params.
+#[win32_derive::dllexport]
+pub fn grGet(
+    machine: &mut Machine,
+    pname: Result<GrGetName, u32>,
+    plength: u32,
+    params: u32,
+) -> u32 {
+    let values: &[i32] = match pname {
+        Ok(GrGetName::GR_BITS_DEPTH) => &[16],
+        Ok(GrGetName::GR_BITS_RGBA) => &[8, 8, 8, 8],
+        Ok(GrGetName::GR_MAX_TEXTURE_SIZE) => &[256],
+        Ok(GrGetName::GR_MAX_TEXTURE_ASPECT_RATIO) => &[3],
+        Ok(GrGetName::GR_MEMORY_FB) => &[FRAME_BUFFER_MEMORY as i32],
+        Ok(GrGetName::GR_MEMORY_TMU) => &[TEXTURE_MEMORY as i32],
+        Ok(GrGetName::GR_MEMORY_UMA) => &[0],
+        Ok(GrGetName::GR_NUM_BOARDS | GrGetName::GR_NUM_FB | GrGetName::GR_NUM_TMU) => &[1],
+        Ok(GrGetName::GR_PENDING_BUFFERSWAPS) => &[0],
+        Ok(GrGetName::GR_REVISION_FB | GrGetName::GR_REVISION_TMU) => &[1],
+        Err(pname) => {
+            log::warn!("grGet({pname:x}): unimplemented");
+            return 0;
+        }
+    };
+    let len = values.len() as u32 * 4;
+    if params == 0 || plength < len {
+        return 0;
+    }
+    let mem = machine.emu.memory.mem();
+    for (i, &value) in values.iter().enumerate() {
+        mem.put::<i32>(params + i as u32 * 4, value);
+    }
+    len
+}
+
+#[derive(Debug, win32_derive::TryFromEnum)]
+pub enum GrGetStringName {
+    GR_EXTENSION = 0xa0,
+    GR_HARDWARE = 0xa1,
+    GR_RENDERER = 0xa2,
+    GR_VENDOR = 0xa3,
+    GR_VERSION = 0xa4,
+}
+
+#[win32_derive::dllexport]
+pub fn grGetString(machine: &mut Machine, pname: Result<GrGetStringName, u32>) -> u32 {
+    let index = match pname {
+        Ok(pname) => pname as usize - GrGetStringName::GR_EXTENSION as usize,
+        Err(_) => return 0,
+    };
+    let value = ["", "Voodoo Graphics", "Glide", "3Dfx Interactive", "3.0"][index];
+    let mem = machine.emu.memory.mem();
+    machine.state.glide.string(mem, index, value)
+}
+
+#[win32_derive::dllexport]
+pub fn grBufferClear(machine: &mut Machine, color: u32, alpha: u32, depth: u32) -> u32 {
+    machine
+        .state
+        .glide
+        .buffer_clear(color, alpha as u8, depth as u16);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grBufferSwap(machine: &mut Machine, swap_interval: u32) -> u32 {
+    machine.state.glide.buffer_swap();
+    machine.presented();
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grVertexLayout(machine: &mut Machine, param: u32, offset: i32, mode: u32) -> u32 {
+    let layout = &mut machine.state.glide.vertex_layout;
+    let field = match param {
+        GR_PARAM_XY => &mut layout.xy,
+        GR_PARAM_Z => &mut layout.z,
+        GR_PARAM_W => &mut layout.w,
+        GR_PARAM_Q => &mut layout.q,
+        GR_PARAM_A => &mut layout.a,
+        GR_PARAM_RGB => &mut layout.rgb,
+        GR_PARAM_PARGB => &mut layout.pargb,
+        GR_PARAM_ST0 => &mut layout.st0,
+        GR_PARAM_Q0 => &mut layout.q0,
+        _ => {
+            log::warn!("grVertexLayout({param:x}): unimplemented");
+            return 0;
+        }
+    };
+    // GR_PARAM_ENABLE = 1
+    *field = if mode == 1 { Some(offset as u32) } else { None };
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grDrawTriangle(machine: &mut Machine, a: u32, b: u32, c: u32) -> u32 {
+    draw_triangle(machine, [a, b, c]);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grDrawVertexArray(
+    machine: &mut Machine,
+    mode: Result<GrDrawMode, u32>,
+    Count: u32,
+    pointers: u32,
+) -> u32 {
+    let addrs = machine
+        .emu
+        .memory
+        .mem()
+        .view_n::<u32>(pointers, Count)
+        .to_vec();
+    draw_array(machine, mode, &addrs);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grDrawVertexArrayContiguous(
+    machine: &mut Machine,
+    mode: Result<GrDrawMode, u32>,
+    Count: u32,
+    pointers: u32,
+    stride: u32,
+) -> u32 {
+    let addrs: Vec<u32> = (0..Count).map(|i| pointers + i * stride).collect();
+    draw_array(machine, mode, &addrs);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grColorCombine(
+    machine: &mut Machine,
+    function: u32,
+    factor: u32,
+    local: u32,
+    other: u32,
+    invert: bool,
+) -> u32 {
+    machine
+        .state
+        .glide
+        .color_combine(function, factor, local, other);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grAlphaCombine(
+    _machine: &mut Machine,
+    function: u32,
+    factor: u32,
+    local: u32,
+    other: u32,
+    invert: bool,
+) -> u32 {
+    0 // TODO
+}
+
+#[win32_derive::dllexport]
+pub fn grConstantColorValue(machine: &mut Machine, value: u32) -> u32 {
+    machine.state.glide.constant_color_value(value);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grDepthBufferMode(machine: &mut Machine, mode: Result<GrDepthBufferMode, u32>) -> u32 {
+    machine.state.glide.depth_buffer_mode(mode);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grDepthBufferFunction(machine: &mut Machine, function: u32) -> u32 {
+    machine.state.glide.depth_buffer_function(function);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grDepthMask(machine: &mut Machine, mask: bool) -> u32 {
+    machine.state.glide.depth_mask(mask);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grCullMode(_machine: &mut Machine, mode: u32) -> u32 {
+    0 // TODO: we draw both windings regardless.
+}
+
+#[win32_derive::dllexport]
+pub fn grClipWindow(_machine: &mut Machine, minx: u32, miny: u32, maxx: u32, maxy: u32) -> u32 {
+    0 // TODO
+}
+
+#[win32_derive::dllexport]
+pub fn grTexMinAddress(_machine: &mut Machine, tmu: u32) -> u32 {
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grTexMaxAddress(_machine: &mut Machine, tmu: u32) -> u32 {
+    TEXTURE_MEMORY
+}
+
+#[win32_derive::dllexport]
+pub fn grTexTextureMemRequired(
+    _machine: &mut Machine,
+    evenOdd: u32,
+    info: Option<&GrTexInfo>,
+) -> u32 {
+    info.unwrap().mem_required()
+}
+
+#[win32_derive::dllexport]
+pub fn grTexDownloadMipMap(
+    machine: &mut Machine,
+    tmu: u32,
+    startAddress: u32,
+    evenOdd: u32,
+    info: Option<&GrTexInfo>,
+) -> u32 {
+    let info = info.unwrap();
+    let mem = machine.emu.memory.mem();
+    machine
+        .state
+        .glide
+        .tex_download(mem, tmu, startAddress, info.data, info.mem_required());
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grTexDownloadMipMapLevel(
+    machine: &mut Machine,
+    tmu: u32,
+    startAddress: u32,
+    thisLod: i32,
+    largeLod: i32,
+    aspectRatio: i32,
+    format: u32,
+    evenOdd: u32,
+    data: u32,
+) -> u32 {
+    let large = lod_info(largeLod, aspectRatio, format);
+    let offset = large.level_offset((largeLod - thisLod).max(0) as u32);
+    let this = lod_info(thisLod, aspectRatio, format);
+    let len = this.level_offset(1);
+    let mem = machine.emu.memory.mem();
+    machine
+        .state
+        .glide
+        .tex_download(mem, tmu, startAddress + offset, data, len);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grTexDownloadTable(machine: &mut Machine, type_: u32, data: u32) -> u32 {
+    if type_ != glide::GR_TEXTABLE_PALETTE {
+        log::warn!("grTexDownloadTable({type_:x}): unimplemented");
+        return 0;
+    }
+    let mem = machine.emu.memory.mem();
+    machine.state.glide.tex_download_palette(mem, data);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grTexSource(
+    machine: &mut Machine,
+    tmu: u32,
+    startAddress: u32,
+    evenOdd: u32,
+    info: Option<&GrTexInfo>,
+) -> u32 {
+    let info = info.unwrap().tex_info();
+    machine.state.glide.tex_source(tmu, startAddress, info);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grTexCombine(
+    machine: &mut Machine,
+    tmu: u32,
+    rgb_function: u32,
+    rgb_factor: u32,
+    alpha_function: u32,
+    alpha_factor: u32,
+    rgb_invert: bool,
+    alpha_invert: bool,
+) -> u32 {
+    machine.state.glide.tex_combine(tmu, rgb_function);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn grTexFilterMode(
+    _machine: &mut Machine,
+    tmu: u32,
+    minfilter_mode: u32,
+    magfilter_mode: u32,
+) -> u32 {
+    0 // TODO: we always point sample.
+}
+
+#[win32_derive::dllexport]
+pub fn grTexClampMode(_machine: &mut Machine, tmu: u32, s_clampmode: u32, t_clampmode: u32) -> u32 {
+    0 // TODO: we always wrap.
+}
@@ -37,6 +37,18 @@ impl<T> Default for HANDLE<T> {
 
 unsafe impl<T: 'static> memory::Pod for HANDLE<T> {}
 
+impl<T> serde::Serialize for HANDLE<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.raw.serialize(serializer)
+    }
+}
+
+impl<'de, T> serde::Deserialize<'de> for HANDLE<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::from_raw(u32::deserialize(deserializer)?))
+    }
+}
+
 impl<T> HANDLE<T> {
     pub const fn from_raw(raw: u32) -> Self {
         HANDLE {
@@ -121,6 +133,24 @@ impl<H: Handle, V> Default for Handles<H, V> {
     }
 }
 
+// Snapshots record the handles along with the next one to vend, so restored
+// handles stay valid and new ones don't collide with them.
+impl<H: Handle, V: serde::Serialize> serde::Serialize for Handles<H, V> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&self.map, self.next.to_raw()).serialize(serializer)
+    }
+}
+
+impl<'de, H: Handle, V: serde::Deserialize<'de>> serde::Deserialize<'de> for Handles<H, V> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (map, next) = <(HashMap<u32, V>, u32)>::deserialize(deserializer)?;
+        Ok(Handles {
+            map,
+            next: H::from_raw(next),
+        })
+    }
+}
+
 impl<H: Handle, V> Handles<H, V> {
     pub fn new(start: u32) -> Self {
         Handles {
@@ -135,7 +165,12 @@ impl<H: Handle, V> Handles<H, V> {
         handle
     }
 
+    /// Store a value under a given handle, such as one reserved earlier or one
+    /// restored from a snapshot; later handles are vended past it.
     pub fn set(&mut self, handle: H, t: V) {
+        if handle.to_raw() >= self.next.to_raw() {
+            self.next = H::from_raw(handle.to_raw() + 1);
+        }
         self.map.insert(handle.to_raw(), t);
     }
 
@@ -153,7 +188,23 @@ impl<H: Handle, V> Handles<H, V> {
         self.map.get_mut(&handle.to_raw())
     }
 
+    pub fn remove(&mut self, handle: H) -> Option<V> {
+        self.map.remove(&handle.to_raw())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &V> {
         self.map.values()
     }
+
+    pub fn entries(&self) -> impl Iterator<Item = (H, &V)> {
+        self.map.iter().map(|(&raw, v)| (H::from_raw(raw), v))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (H, &mut V)> {
+        self.map.iter_mut().map(|(&raw, v)| (H::from_raw(raw), v))
+    }
 }
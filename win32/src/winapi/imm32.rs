@@ -0,0 +1,347 @@
+//! Input method contexts, driven by the host's own input method.
+
+#![allow(non_snake_case)]
+
+use super::{handle::Handles, kernel32, types::*};
+use crate::machine::Machine;
+use std::collections::HashMap;
+
+const TRACE_CONTEXT: &'static str = "imm32";
+
+/*
+## Input method
+
+There's no input method of our own: the host's composes text, showing its own
+candidates, and reports the text in progress (host::MessageDetail::Composition)
+and the text committed (Text).  The user32 message queue turns these into
+WM_IME_STARTCOMPOSITION, WM_IME_COMPOSITION and WM_IME_ENDCOMPOSITION for the
+focused window's input context, whose strings the guest reads here with
+ImmGetCompositionString.  A window that doesn't handle WM_IME_COMPOSITION passes
+it to DefWindowProc, which posts the committed text as WM_IME_CHAR/WM_CHAR.
+
+Windows associated with no context, or all windows after ImmDisableIME, get the
+host's text as plain characters, as they would with no input method.
+*/
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct HIMCT;
+pub type HIMC = HANDLE<HIMCT>;
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct Context {
+    open: bool,
+    conversion: u32,
+    sentence: u32,
+    /// Whether the host is composing text in this context.
+    pub composing: bool,
+    /// The text being composed.
+    pub composition: String,
+    /// The text last committed, read once WM_IME_COMPOSITION announces it.
+    pub result: String,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct State {
+    contexts: Handles<HIMC, Context>,
+    /// The context windows have unless associated with another (or none).
+    default: HIMC,
+    associations: HashMap<HWND, HIMC>,
+    disabled: bool,
+}
+
+impl State {
+    fn default_context(&mut self) -> HIMC {
+        if self.default.is_null() {
+            self.default = self.contexts.add(Context {
+                open: true,
+                ..Default::default()
+            });
+        }
+        self.default
+    }
+
+    fn context_handle(&mut self, hwnd: HWND) -> HIMC {
+        if self.disabled {
+            return HIMC::null();
+        }
+        match self.associations.get(&hwnd) {
+            Some(&himc) => himc,
+            None => self.default_context(),
+        }
+    }
+
+    /// The input context of a window, if it has one.
+    pub fn context_mut(&mut self, hwnd: HWND) -> Option<&mut Context> {
+        let himc = self.context_handle(hwnd);
+        self.contexts.get_mut(himc)
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn ImmGetContext(machine: &mut Machine, hWnd: HWND) -> HIMC {
+    machine.state.imm32.context_handle(hWnd)
+}
+
+#[win32_derive::dllexport]
+pub fn ImmReleaseContext(_machine: &mut Machine, hWnd: HWND, hIMC: HIMC) -> bool {
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn ImmCreateContext(machine: &mut Machine) -> HIMC {
+    machine.state.imm32.contexts.add(Context::default())
+}
+
+#[win32_derive::dllexport]
+pub fn ImmDestroyContext(machine: &mut Machine, hIMC: HIMC) -> bool {
+    let imm32 = &mut machine.state.imm32;
+    if hIMC == imm32.default {
+        return false;
+    }
+    imm32.associations.retain(|_, &mut himc| himc != hIMC);
+    imm32.contexts.remove(hIMC).is_some()
+}
+
+#[win32_derive::dllexport]
+pub fn ImmAssociateContext(machine: &mut Machine, hWnd: HWND, hIMC: HIMC) -> HIMC {
+    let imm32 = &mut machine.state.imm32;
+    let prev = imm32.context_handle(hWnd);
+    imm32.associations.insert(hWnd, hIMC);
+    prev
+}
+
+const IACE_DEFAULT: u32 = 0x0010;
+
+#[win32_derive::dllexport]
+pub fn ImmAssociateContextEx(machine: &mut Machine, hWnd: HWND, hIMC: HIMC, dwFlags: u32) -> bool {
+    let imm32 = &mut machine.state.imm32;
+    if dwFlags & IACE_DEFAULT != 0 {
+        imm32.associations.remove(&hWnd);
+    } else {
+        // IACE_CHILDREN would also cover child windows, but guest windows with
+        // text input are top-level.
+        imm32.associations.insert(hWnd, hIMC);
+    }
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn ImmDisableIME(machine: &mut Machine, idThread: u32) -> bool {
+    machine.state.imm32.disabled = true;
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn ImmIsIME(_machine: &mut Machine, hKL: u32) -> bool {
+    // The host's input method stands in for any keyboard layout's.
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn ImmGetDefaultIMEWnd(_machine: &mut Machine, hWnd: HWND) -> HWND {
+    // The host draws the input method's UI; there's no window for it.
+    HWND::null()
+}
+
+#[win32_derive::dllexport]
+pub fn ImmGetOpenStatus(machine: &mut Machine, hIMC: HIMC) -> bool {
+    match machine.state.imm32.contexts.get(hIMC) {
+        Some(context) => context.open,
+        None => false,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn ImmSetOpenStatus(machine: &mut Machine, hIMC: HIMC, fOpen: bool) -> bool {
+    match machine.state.imm32.contexts.get_mut(hIMC) {
+        Some(context) => {
+            context.open = fOpen;
+            true
+        }
+        None => false,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn ImmGetConversionStatus(
+    machine: &mut Machine,
+    hIMC: HIMC,
+    lpfdwConversion: Option<&mut u32>,
+    lpfdwSentence: Option<&mut u32>,
+) -> bool {
+    let Some(context) = machine.state.imm32.contexts.get(hIMC) else {
+        return false;
+    };
+    if let Some(conversion) = lpfdwConversion {
+        *conversion = context.conversion;
+    }
+    if let Some(sentence) = lpfdwSentence {
+        *sentence = context.sentence;
+    }
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn ImmSetConversionStatus(
+    machine: &mut Machine,
+    hIMC: HIMC,
+    fdwConversion: u32,
+    fdwSentence: u32,
+) -> bool {
+    match machine.state.imm32.contexts.get_mut(hIMC) {
+        Some(context) => {
+            // Recorded for the guest to read back; the host's input method picks its own.
+            context.conversion = fdwConversion;
+            context.sentence = fdwSentence;
+            true
+        }
+        None => false,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn ImmSetCompositionWindow(machine: &mut Machine, hIMC: HIMC, lpCompForm: u32) -> bool {
+    // The host places its composition window itself.
+    machine.state.imm32.contexts.get(hIMC).is_some()
+}
+
+#[win32_derive::dllexport]
+pub fn ImmSetCandidateWindow(machine: &mut Machine, hIMC: HIMC, lpCandidate: u32) -> bool {
+    machine.state.imm32.contexts.get(hIMC).is_some()
+}
+
+#[win32_derive::dllexport]
+pub fn ImmSetCompositionFontA(machine: &mut Machine, hIMC: HIMC, lplf: u32) -> bool {
+    machine.state.imm32.contexts.get(hIMC).is_some()
+}
+
+#[win32_derive::dllexport]
+pub fn ImmGetCandidateListA(
+    _machine: &mut Machine,
+    hIMC: HIMC,
+    deIndex: u32,
+    lpCandList: u32,
+    dwBufLen: u32,
+) -> u32 {
+    // The host shows candidates without telling us of them.
+    0
+}
+
+const NI_COMPOSITIONSTR: u32 = 0x0015;
+const CPS_CANCEL: u32 = 0x0004;
+
+#[win32_derive::dllexport]
+pub fn ImmNotifyIME(
+    machine: &mut Machine,
+    hIMC: HIMC,
+    dwAction: u32,
+    dwIndex: u32,
+    dwValue: u32,
+) -> bool {
+    let Some(context) = machine.state.imm32.contexts.get_mut(hIMC) else {
+        return false;
+    };
+    match (dwAction, dwIndex) {
+        (NI_COMPOSITIONSTR, CPS_CANCEL) => {
+            // The host carries on composing; its next update starts a new composition.
+            context.composing = false;
+            context.composition.clear();
+        }
+        _ => log::warn!("ImmNotifyIME({dwAction:#x}, {dwIndex:#x}) ignored"),
+    }
+    true
+}
+
+const IMM_ERROR_NODATA: i32 = -1;
+
+pub const GCS_CURSORPOS: u32 = 0x0080;
+pub const GCS_COMPSTR: u32 = 0x0008;
+const GCS_COMPATTR: u32 = 0x0010;
+const GCS_COMPCLAUSE: u32 = 0x0020;
+const GCS_DELTASTART: u32 = 0x0100;
+pub const GCS_RESULTSTR: u32 = 0x0800;
+const GCS_RESULTCLAUSE: u32 = 0x1000;
+
+/// ATTR_INPUT: composition text not yet converted.
+const ATTR_INPUT: u8 = 0;
+
+/// ImmGetCompositionString for characters of the given width: their string, one
+/// attribute each, clause boundaries as offsets, or the cursor position.
+fn get_composition_string(
+    machine: &mut Machine,
+    hIMC: HIMC,
+    dwIndex: u32,
+    lpBuf: u32,
+    dwBufLen: u32,
+    encode: impl Fn(&str) -> Vec<u8>,
+    char_size: usize,
+) -> i32 {
+    let Some(context) = machine.state.imm32.contexts.get(hIMC) else {
+        return IMM_ERROR_NODATA;
+    };
+    let chars = |text: &str| (encode(text).len() / char_size) as u32;
+    let clause = |text: &str| {
+        [0, chars(text)]
+            .iter()
+            .flat_map(|o| o.to_le_bytes())
+            .collect()
+    };
+    let data: Vec<u8> = match dwIndex {
+        // Positions are returned rather than copied out.
+        GCS_CURSORPOS => return chars(&context.composition) as i32,
+        GCS_DELTASTART => return 0,
+        GCS_COMPSTR => encode(&context.composition),
+        GCS_COMPATTR => vec![ATTR_INPUT; chars(&context.composition) as usize],
+        GCS_COMPCLAUSE => clause(&context.composition),
+        GCS_RESULTSTR => encode(&context.result),
+        GCS_RESULTCLAUSE => clause(&context.result),
+        _ => {
+            log::warn!("ImmGetCompositionString({dwIndex:#x}): no data");
+            Vec::new()
+        }
+    };
+    // A null buffer asks for the size.
+    if lpBuf == 0 || dwBufLen == 0 {
+        return data.len() as i32;
+    }
+    let len = data.len().min(dwBufLen as usize);
+    machine
+        .mem()
+        .sub(lpBuf, len as u32)
+        .as_mut_slice_todo()
+        .copy_from_slice(&data[..len]);
+    len as i32
+}
+
+#[win32_derive::dllexport]
+pub fn ImmGetCompositionStringA(
+    machine: &mut Machine,
+    hIMC: HIMC,
+    dwIndex: u32,
+    lpBuf: u32,
+    dwBufLen: u32,
+) -> i32 {
+    let code_page = machine.state.kernel32.code_page;
+    let encode = |text: &str| {
+        text.chars()
+            .flat_map(|c| kernel32::encode_ansi(code_page, c))
+            .collect()
+    };
+    get_composition_string(machine, hIMC, dwIndex, lpBuf, dwBufLen, encode, 1)
+}
+
+#[win32_derive::dllexport]
+pub fn ImmGetCompositionStringW(
+    machine: &mut Machine,
+    hIMC: HIMC,
+    dwIndex: u32,
+    lpBuf: u32,
+    dwBufLen: u32,
+) -> i32 {
+    let encode = |text: &str| {
+        text.encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect()
+    };
+    get_composition_string(machine, hIMC, dwIndex, lpBuf, dwBufLen, encode, 2)
+}
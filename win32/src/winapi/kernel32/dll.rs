@@ -28,15 +28,41 @@ impl HMODULE {
     }
 }
 
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct DLL {
     pub name: String,
 
     pub dll: pe::DLL,
 
     /// If present, DLL is one defined in winapi/...
+    #[serde(with = "builtin_by_name")]
     pub builtin: Option<&'static BuiltinDLL>,
 }
 
+/// Snapshots refer to builtin DLLs by file name.
+mod builtin_by_name {
+    use super::*;
+
+    pub fn serialize<S: serde::Serializer>(
+        builtin: &Option<&'static BuiltinDLL>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&builtin.map(|dll| dll.file_name), serializer)
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<&'static BuiltinDLL>, D::Error> {
+        let Some(name) = <Option<String> as serde::Deserialize>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        match winapi::DLLS.iter().find(|dll| dll.file_name == name) {
+            Some(dll) => Ok(Some(dll)),
+            None => Err(serde::de::Error::custom(format!("unknown builtin {name}"))),
+        }
+    }
+}
+
 impl DLL {
     fn resolve_from_pe(&self, sym: &ImportSymbol) -> Option<u32> {
         match *sym {
@@ -53,10 +79,13 @@ impl DLL {
         let builtin = self.builtin?;
 
         let export = match *sym {
-            ImportSymbol::Name(name) => builtin
-                .exports
-                .iter()
-                .find(|&export| export.shim.name == name),
+            ImportSymbol::Name(name) => {
+                let name = undecorate(name);
+                builtin
+                    .exports
+                    .iter()
+                    .find(|&export| export.shim.name == name)
+            }
             ImportSymbol::Ordinal(ord) => builtin
                 .exports
                 .iter()
@@ -99,6 +128,15 @@ impl DLL {
     }
 }
 
+/// Strip stdcall name decoration ("_foo@12" => "foo"), which some DLLs
+/// (e.g. Glide) use in their export tables.
+fn undecorate(name: &str) -> &str {
+    match name.strip_prefix('_').and_then(|n| n.rsplit_once('@')) {
+        Some((base, n)) if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => base,
+        _ => name,
+    }
+}
+
 fn normalize_module_name(name: &str) -> String {
     let mut name = name.to_ascii_lowercase();
     if !name.ends_with(".dll") && !name.ends_with(".") {
@@ -185,6 +223,12 @@ pub fn GetModuleFileNameW(
 pub fn LoadLibraryA(machine: &mut Machine, filename: Option<&str>) -> HMODULE {
     let mut filename = normalize_module_name(filename.unwrap());
 
+    match winapi::apiset(&filename) {
+        Some(name) => filename = name.to_string(),
+        None if filename.starts_with("api-") => return HMODULE::null(),
+        None => {}
+    }
+
     // See if already loaded.
     if let Some(index) = machine
         .state
@@ -196,38 +240,40 @@ pub fn LoadLibraryA(machine: &mut Machine, filename: Option<&str>) -> HMODULE {
         return HMODULE::from_dll_index(index);
     }
 
-    if filename.starts_with("api-") {
-        match winapi::apiset(&filename) {
-            Some(name) => filename = name.to_string(),
-            None => return HMODULE::null(),
-        }
-    }
-
     // Check if builtin.
     if let Some(builtin) = winapi::DLLS.iter().find(|&dll| dll.file_name == filename) {
         return machine.state.kernel32.load_builtin_dll(builtin);
     }
 
-    let mut file = machine.host.open(&filename);
-    let mut contents = Vec::new();
-    let mut buf: [u8; 16 << 10] = [0; 16 << 10];
-    loop {
-        let mut len = 0u32;
-        assert!(file.read(&mut buf, &mut len));
-        if len == 0 {
-            break;
-        }
-        contents.extend_from_slice(&buf[..len as usize]);
-    }
-    // TODO: close file.
+    // Through the guest's view of the filesystem, which includes files the guest
+    // itself wrote, like a DLL unpacked to a temporary file.
+    let contents = super::read_file(machine, &filename);
     if contents.len() == 0 {
         // HACK: zero-length indicates not found.
         return HMODULE::null();
     }
+    load_dll_image(machine, filename, &contents)
+}
+
+/// Load a DLL from an image in guest memory rather than a file, as some protection
+/// wrappers do with a DLL they decrypt in place.  The image is in its file layout,
+/// as if read from a file; name is what the DLL is then known as to GetModuleHandle.
+pub fn load_library_from_memory(machine: &mut Machine, name: &str, addr: u32, len: u32) -> HMODULE {
+    let name = normalize_module_name(name);
+    let contents = machine.mem().sub32(addr, len).to_vec();
+    load_dll_image(machine, name, &contents)
+}
 
-    let dll = pe::load_dll(machine, &filename, &contents).unwrap();
+fn load_dll_image(machine: &mut Machine, name: String, contents: &[u8]) -> HMODULE {
+    let dll = match pe::load_dll(machine, &name, contents) {
+        Ok(dll) => dll,
+        Err(err) => {
+            log::error!("loading {name}: {err}");
+            return HMODULE::null();
+        }
+    };
     machine.state.kernel32.dlls.push(DLL {
-        name: filename,
+        name,
         dll,
         builtin: None,
     });
@@ -0,0 +1,638 @@
+//! Structured exception handling: faults and RaiseException are delivered to the
+//! handlers the program registered in its fs:[0] chain, as ntdll does.
+
+use super::{teb, teb_mut, _EXCEPTION_REGISTRATION_RECORD, HTHREAD, SEH_END_HANDLER};
+use crate::{machine::Emulator, winapi::types::*, Machine};
+use memory::{Extensions, PageState, Pod, PAGE_SIZE};
+
+const TRACE_CONTEXT: &'static str = "kernel32/exception";
+
+pub const EXCEPTION_ACCESS_VIOLATION: u32 = 0xC000_0005;
+pub const EXCEPTION_BREAKPOINT: u32 = 0x8000_0003;
+pub const EXCEPTION_SINGLE_STEP: u32 = 0x8000_0004;
+pub const EXCEPTION_ILLEGAL_INSTRUCTION: u32 = 0xC000_001D;
+pub const EXCEPTION_INT_DIVIDE_BY_ZERO: u32 = 0xC000_0094;
+pub const EXCEPTION_INT_OVERFLOW: u32 = 0xC000_0095;
+pub const EXCEPTION_ARRAY_BOUNDS_EXCEEDED: u32 = 0xC000_008C;
+pub const EXCEPTION_STACK_OVERFLOW: u32 = 0xC000_00FD;
+const STATUS_UNWIND: u32 = 0xC000_0027;
+
+/// ExceptionFlags bits.
+const EXCEPTION_UNWINDING: u32 = 0x2;
+const EXCEPTION_EXIT_UNWIND: u32 = 0x4;
+
+const EXCEPTION_MAXIMUM_PARAMETERS: usize = 15;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EXCEPTION_RECORD {
+    pub ExceptionCode: DWORD,
+    pub ExceptionFlags: DWORD,
+    pub ExceptionRecord: DWORD,
+    pub ExceptionAddress: DWORD,
+    pub NumberParameters: DWORD,
+    pub ExceptionInformation: [DWORD; EXCEPTION_MAXIMUM_PARAMETERS],
+}
+unsafe impl Pod for EXCEPTION_RECORD {}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct EXCEPTION_POINTERS {
+    pub ExceptionRecord: DWORD,
+    pub ContextRecord: DWORD,
+}
+unsafe impl Pod for EXCEPTION_POINTERS {}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct FLOATING_SAVE_AREA {
+    pub ControlWord: DWORD,
+    pub StatusWord: DWORD,
+    pub TagWord: DWORD,
+    pub ErrorOffset: DWORD,
+    pub ErrorSelector: DWORD,
+    pub DataOffset: DWORD,
+    pub DataSelector: DWORD,
+    /// ST(0) through ST(7), 10 bytes each.
+    pub RegisterArea: [u8; 80],
+    pub Cr0NpxState: DWORD,
+}
+unsafe impl Pod for FLOATING_SAVE_AREA {}
+
+/// ContextFlags bits.
+#[allow(non_upper_case_globals)]
+pub const CONTEXT_i386: u32 = 0x1_0000;
+pub const CONTEXT_CONTROL: u32 = CONTEXT_i386 | 0x1;
+pub const CONTEXT_INTEGER: u32 = CONTEXT_i386 | 0x2;
+pub const CONTEXT_SEGMENTS: u32 = CONTEXT_i386 | 0x4;
+pub const CONTEXT_FLOATING_POINT: u32 = CONTEXT_i386 | 0x8;
+pub const CONTEXT_DEBUG_REGISTERS: u32 = CONTEXT_i386 | 0x10;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct CONTEXT {
+    pub ContextFlags: DWORD,
+    pub Dr0: DWORD,
+    pub Dr1: DWORD,
+    pub Dr2: DWORD,
+    pub Dr3: DWORD,
+    pub Dr6: DWORD,
+    pub Dr7: DWORD,
+    pub FloatSave: FLOATING_SAVE_AREA,
+    pub SegGs: DWORD,
+    pub SegFs: DWORD,
+    pub SegEs: DWORD,
+    pub SegDs: DWORD,
+    pub Edi: DWORD,
+    pub Esi: DWORD,
+    pub Ebx: DWORD,
+    pub Edx: DWORD,
+    pub Ecx: DWORD,
+    pub Eax: DWORD,
+    pub Ebp: DWORD,
+    pub Eip: DWORD,
+    pub SegCs: DWORD,
+    pub EFlags: DWORD,
+    pub Esp: DWORD,
+    pub SegSs: DWORD,
+    pub ExtendedRegisters: [u8; 512],
+}
+unsafe impl Pod for CONTEXT {}
+
+/// The current CPU's registers as a CONTEXT.
+#[cfg(feature = "x86-emu")]
+pub fn capture_context(machine: &Machine) -> CONTEXT {
+    context_of(machine.emu.x86.cpu())
+}
+
+#[cfg(feature = "x86-emu")]
+fn context_of(cpu: &x86::CPU) -> CONTEXT {
+    use x86::Register::*;
+    let regs = &cpu.regs;
+    let mut context: CONTEXT = unsafe { std::mem::zeroed() };
+    context.ContextFlags = CONTEXT_CONTROL
+        | CONTEXT_INTEGER
+        | CONTEXT_SEGMENTS
+        | CONTEXT_FLOATING_POINT
+        | CONTEXT_DEBUG_REGISTERS;
+
+    context.Dr0 = cpu.dr[0];
+    context.Dr1 = cpu.dr[1];
+    context.Dr2 = cpu.dr[2];
+    context.Dr3 = cpu.dr[3];
+    context.Dr6 = cpu.dr[6];
+    context.Dr7 = cpu.dr[7];
+
+    let fpu = &cpu.fpu;
+    let float = &mut context.FloatSave;
+    float.ControlWord = fpu.control as u32;
+    float.StatusWord = fpu.status_word() as u32;
+    // Two tag bits per physical register; below the stack top they're empty.
+    float.TagWord = (0..fpu.st_top.min(8)).fold(0, |tags, i| tags | 0b11 << (i * 2));
+    for (i, st) in fpu.st[fpu.st_top.min(8)..].iter().enumerate() {
+        float.RegisterArea[i * 10..][..10].copy_from_slice(&st.to_bytes());
+    }
+
+    context.SegGs = regs.get16(GS) as u32;
+    context.SegFs = regs.get16(FS) as u32;
+    context.SegEs = regs.get16(ES) as u32;
+    context.SegDs = regs.get16(DS) as u32;
+    context.Edi = regs.get32(EDI);
+    context.Esi = regs.get32(ESI);
+    context.Ebx = regs.get32(EBX);
+    context.Edx = regs.get32(EDX);
+    context.Ecx = regs.get32(ECX);
+    context.Eax = regs.get32(EAX);
+    context.Ebp = regs.get32(EBP);
+    context.Eip = regs.eip;
+    context.SegCs = regs.get16(CS) as u32;
+    context.EFlags = cpu.flags.bits();
+    context.Esp = regs.get32(ESP);
+    context.SegSs = regs.get16(SS) as u32;
+    context
+}
+
+/// Load the current CPU's registers from a CONTEXT.
+#[cfg(feature = "x86-emu")]
+pub fn restore_context(machine: &mut Machine, context: &CONTEXT) {
+    set_context(machine.emu.x86.cpu_mut(), context)
+}
+
+/// Load a CPU's registers from a CONTEXT.  Segment registers are left alone,
+/// as FS must keep pointing at the thread's TEB.
+#[cfg(feature = "x86-emu")]
+fn set_context(cpu: &mut x86::CPU, context: &CONTEXT) {
+    use x86::Register::*;
+    if context.ContextFlags & CONTEXT_DEBUG_REGISTERS == CONTEXT_DEBUG_REGISTERS {
+        cpu.dr[0] = context.Dr0;
+        cpu.dr[1] = context.Dr1;
+        cpu.dr[2] = context.Dr2;
+        cpu.dr[3] = context.Dr3;
+        cpu.dr[6] = context.Dr6;
+        cpu.dr[7] = context.Dr7;
+    }
+    let regs = &mut cpu.regs;
+    regs.set32(EDI, context.Edi);
+    regs.set32(ESI, context.Esi);
+    regs.set32(EBX, context.Ebx);
+    regs.set32(EDX, context.Edx);
+    regs.set32(ECX, context.Ecx);
+    regs.set32(EAX, context.Eax);
+    regs.set32(EBP, context.Ebp);
+    regs.eip = context.Eip;
+    regs.set32(ESP, context.Esp);
+    cpu.flags = x86::Flags::from_bits_truncate(context.EFlags);
+    cpu.fpu.control = context.FloatSave.ControlWord as u16;
+}
+
+/// Deliver a fault the CPU raised to the program's exception handlers.
+#[cfg(feature = "x86-emu")]
+pub fn raise_cpu_exception(machine: &mut Machine, exception: x86::Exception) {
+    machine.emu.x86.cpu_mut().state = x86::CPUState::Running;
+    let mut overflow = false;
+    if let x86::Exception::AccessViolation { addr, .. } = exception {
+        match grow_stack(machine, addr) {
+            // Retry the faulting instruction.
+            Some(false) => return,
+            Some(true) => overflow = true,
+            None => {}
+        }
+    }
+    let cpu = machine.emu.x86.cpu();
+    let mut record = EXCEPTION_RECORD {
+        ExceptionAddress: cpu.regs.eip,
+        ..Default::default()
+    };
+    record.ExceptionCode = match exception {
+        x86::Exception::DivideByZero => EXCEPTION_INT_DIVIDE_BY_ZERO,
+        x86::Exception::DivideOverflow => EXCEPTION_INT_OVERFLOW,
+        x86::Exception::InvalidOpcode => EXCEPTION_ILLEGAL_INSTRUCTION,
+        x86::Exception::AccessViolation { addr, write } => {
+            record.NumberParameters = 2;
+            record.ExceptionInformation[0] = write as u32;
+            record.ExceptionInformation[1] = addr;
+            if overflow {
+                EXCEPTION_STACK_OVERFLOW
+            } else {
+                EXCEPTION_ACCESS_VIOLATION
+            }
+        }
+        x86::Exception::Breakpoint => EXCEPTION_BREAKPOINT,
+        x86::Exception::BoundRange => EXCEPTION_ARRAY_BOUNDS_EXCEEDED,
+        x86::Exception::SingleStep => EXCEPTION_SINGLE_STEP,
+        // Both are general protection faults in 32-bit code, which Windows reports
+        // as access violations at no particular address.
+        x86::Exception::GeneralProtection { .. } | x86::Exception::Interrupt(_) => {
+            record.NumberParameters = 2;
+            record.ExceptionInformation[0] = 0;
+            record.ExceptionInformation[1] = 0xFFFF_FFFF;
+            EXCEPTION_ACCESS_VIOLATION
+        }
+    };
+    log::warn!("{exception:x?} at {:x}", record.ExceptionAddress);
+    let context = capture_context(machine);
+
+    // Same trick as async shims: Machine outlives the future.
+    let m: *mut Machine = machine;
+    let dispatch = async move {
+        let machine = unsafe { &mut *m };
+        if let Some(context) = dispatch_exception(machine, record, context).await {
+            restore_context(machine, &context);
+        }
+    };
+    machine.emu.x86.cpu_mut().call_async(Box::pin(dispatch));
+}
+
+/// Grow the current thread's stack if a fault at addr was a touch of its guard page
+/// (see Machine::create_stack), by committing down to addr and moving the guard
+/// page below it.  Returns None if the fault wasn't that, or whether the stack
+/// has now run out, which the program is told about as a stack overflow.
+#[cfg(feature = "x86-emu")]
+fn grow_stack(machine: &mut Machine, addr: u32) -> Option<bool> {
+    let esp = machine.emu.x86.cpu().regs.get32(x86::Register::ESP);
+    let stack = machine.state.kernel32.mappings.find(esp)?;
+    let (stack_addr, stack_end) = (stack.addr, stack.addr + stack.size);
+    if addr < stack_addr || addr >= stack_end {
+        return None;
+    }
+    let memory = &mut machine.emu.memory;
+    let page = addr & !(PAGE_SIZE - 1);
+    let bottom = match memory.pages().get(page).state {
+        PageState::Guard => page,
+        // Our own pushes, like the frames of exception dispatch, skip the guard
+        // page check, so the live part of the stack may be reserved pages.
+        PageState::Reserved if addr >= esp => std::cmp::min(page, esp & !(PAGE_SIZE - 1)),
+        _ => return None,
+    };
+    memory.commit(bottom, stack_end - bottom);
+    // The bottom page of the stack is never committed.
+    let overflow = bottom <= stack_addr + PAGE_SIZE;
+    if !overflow {
+        memory.guard(bottom - PAGE_SIZE, PAGE_SIZE);
+    }
+    teb_mut(machine).Tib.StackLimit = bottom;
+    Some(overflow)
+}
+
+/// Read the registration record of a frame in the SEH chain, or None at its end
+/// (or if the program corrupted it).
+#[cfg(feature = "x86-emu")]
+fn seh_frame(machine: &Machine, frame: u32) -> Option<_EXCEPTION_REGISTRATION_RECORD> {
+    let mem = machine.mem();
+    if frame == 0xFFFF_FFFF || frame < 0x1000 || mem.is_oob::<_EXCEPTION_REGISTRATION_RECORD>(frame)
+    {
+        return None;
+    }
+    let record = mem.get_pod::<_EXCEPTION_REGISTRATION_RECORD>(frame);
+    if record.Handler == SEH_END_HANDLER {
+        return None;
+    }
+    Some(record)
+}
+
+/// Call a frame's handler, which is cdecl:
+///   EXCEPTION_DISPOSITION handler(EXCEPTION_RECORD*, frame, CONTEXT*, dispatcher context)
+#[cfg(feature = "x86-emu")]
+async fn call_handler(machine: &mut Machine, handler: u32, args: Vec<u32>) -> u32 {
+    let mem = machine.emu.memory.mem();
+    machine
+        .emu
+        .x86
+        .cpu_mut()
+        .call_x86_cdecl(mem, handler, args)
+        .await
+}
+
+/// Copy a value onto the x86 stack, returning its address.
+#[cfg(feature = "x86-emu")]
+fn push_struct<T: Pod + Copy>(machine: &mut Machine, value: T) -> u32 {
+    let esp = machine.emu.x86.cpu_mut().regs.get32_mut(x86::Register::ESP);
+    *esp = (*esp - std::mem::size_of::<T>() as u32) & !3;
+    let addr = *esp;
+    machine.mem().put::<T>(addr, value);
+    addr
+}
+
+/// Run the handlers in the current thread's SEH chain until one handles the exception.
+/// Returns the context to resume with, possibly modified by the handler, or None if
+/// nothing handled it and the program has been stopped.
+#[cfg(feature = "x86-emu")]
+pub async fn dispatch_exception(
+    machine: &mut Machine,
+    record: EXCEPTION_RECORD,
+    context: CONTEXT,
+) -> Option<CONTEXT> {
+    // Handlers run on the faulting thread's stack, below the fault.
+    let esp = machine.emu.x86.cpu().regs.get32(x86::Register::ESP);
+    let context_addr = push_struct(machine, context);
+    let record_addr = push_struct(machine, record);
+
+    let mut resume = None;
+    let mut frame = teb(machine).Tib.ExceptionList;
+    while let Some(registration) = seh_frame(machine, frame) {
+        let disposition = call_handler(
+            machine,
+            registration.Handler,
+            vec![record_addr, frame, context_addr, 0],
+        )
+        .await;
+        match disposition {
+            0 => {
+                // ExceptionContinueExecution
+                resume = Some(machine.mem().get_pod::<CONTEXT>(context_addr));
+                break;
+            }
+            1 => {} // ExceptionContinueSearch
+            _ => log::warn!(
+                "exception handler {:x} returned {disposition}",
+                registration.Handler
+            ),
+        }
+        frame = registration.Prev;
+    }
+
+    if resume.is_none() {
+        resume = unhandled_exception(machine, &record, record_addr, context_addr).await;
+    }
+    machine
+        .emu
+        .x86
+        .cpu_mut()
+        .regs
+        .set32(x86::Register::ESP, esp);
+    resume
+}
+
+/// No frame handled an exception: consult the SetUnhandledExceptionFilter filter,
+/// then give up on the program.
+#[cfg(feature = "x86-emu")]
+async fn unhandled_exception(
+    machine: &mut Machine,
+    record: &EXCEPTION_RECORD,
+    record_addr: u32,
+    context_addr: u32,
+) -> Option<CONTEXT> {
+    let filter = machine.state.kernel32.unhandled_exception_filter;
+    if filter != 0 {
+        let pointers = push_struct(
+            machine,
+            EXCEPTION_POINTERS {
+                ExceptionRecord: record_addr,
+                ContextRecord: context_addr,
+            },
+        );
+        let mem = machine.emu.memory.mem();
+        let ret = machine
+            .emu
+            .x86
+            .cpu_mut()
+            .call_x86(mem, filter, vec![pointers])
+            .await;
+        match ret as i32 {
+            -1 => {
+                // EXCEPTION_CONTINUE_EXECUTION
+                return Some(machine.mem().get_pod::<CONTEXT>(context_addr));
+            }
+            1 => {
+                // EXCEPTION_EXECUTE_HANDLER: the process ends quietly.
+                machine.host.exit(record.ExceptionCode);
+                machine.emu.exit(record.ExceptionCode);
+                return None;
+            }
+            _ => {}
+        }
+    }
+    let context = machine.mem().get_pod::<CONTEXT>(context_addr);
+    let msg = format!(
+        "unhandled exception {:#x} at {}\n{}",
+        record.ExceptionCode,
+        machine.symbols.describe(record.ExceptionAddress),
+        crate::backtrace::format(machine, context.Eip, context.Esp, context.Ebp)
+    );
+    let cpu = machine.emu.x86.cpu_mut();
+    cpu.regs.eip = record.ExceptionAddress;
+    cpu.err(msg);
+    None
+}
+
+/// Copy the parts of a CONTEXT selected by `flags` from `src` to `dst`.
+#[cfg(feature = "x86-emu")]
+fn copy_context(dst: &mut CONTEXT, src: &CONTEXT, flags: u32) {
+    let has = |part: u32| flags & part == part;
+    if has(CONTEXT_CONTROL) {
+        dst.Ebp = src.Ebp;
+        dst.Eip = src.Eip;
+        dst.SegCs = src.SegCs;
+        dst.EFlags = src.EFlags;
+        dst.Esp = src.Esp;
+        dst.SegSs = src.SegSs;
+    }
+    if has(CONTEXT_INTEGER) {
+        dst.Edi = src.Edi;
+        dst.Esi = src.Esi;
+        dst.Ebx = src.Ebx;
+        dst.Edx = src.Edx;
+        dst.Ecx = src.Ecx;
+        dst.Eax = src.Eax;
+    }
+    if has(CONTEXT_SEGMENTS) {
+        dst.SegGs = src.SegGs;
+        dst.SegFs = src.SegFs;
+        dst.SegEs = src.SegEs;
+        dst.SegDs = src.SegDs;
+    }
+    if has(CONTEXT_FLOATING_POINT) {
+        dst.FloatSave = src.FloatSave;
+    }
+    if has(CONTEXT_DEBUG_REGISTERS) {
+        dst.Dr0 = src.Dr0;
+        dst.Dr1 = src.Dr1;
+        dst.Dr2 = src.Dr2;
+        dst.Dr3 = src.Dr3;
+        dst.Dr6 = src.Dr6;
+        dst.Dr7 = src.Dr7;
+    }
+}
+
+/// The CPU of a thread handle, where GetCurrentThread's pseudo-handle means the caller.
+#[cfg(feature = "x86-emu")]
+fn thread_cpu(machine: &mut Machine, hThread: HTHREAD) -> Option<&mut x86::CPU> {
+    let x86 = &mut machine.emu.x86;
+    let index = match hThread.to_raw() {
+        0xFFFF_FFFE => x86.cur_cpu,
+        id => id as usize,
+    };
+    x86.cpus.get_mut(index).map(|cpu| &mut **cpu)
+}
+
+#[win32_derive::dllexport]
+pub fn GetThreadContext(
+    machine: &mut Machine,
+    hThread: HTHREAD,
+    lpContext: Option<&mut CONTEXT>,
+) -> bool {
+    #[cfg(feature = "x86-emu")]
+    {
+        let (Some(context), Some(cpu)) = (lpContext, thread_cpu(machine, hThread)) else {
+            return false;
+        };
+        let flags = context.ContextFlags;
+        copy_context(context, &context_of(cpu), flags);
+        return true;
+    }
+    #[cfg(not(feature = "x86-emu"))]
+    todo!("GetThreadContext({hThread:?})");
+}
+
+#[win32_derive::dllexport]
+pub fn SetThreadContext(
+    machine: &mut Machine,
+    hThread: HTHREAD,
+    lpContext: Option<&CONTEXT>,
+) -> bool {
+    #[cfg(feature = "x86-emu")]
+    {
+        let (Some(context), Some(cpu)) = (lpContext, thread_cpu(machine, hThread)) else {
+            return false;
+        };
+        let mut new = context_of(cpu);
+        copy_context(&mut new, context, context.ContextFlags);
+        set_context(cpu, &new);
+        return true;
+    }
+    #[cfg(not(feature = "x86-emu"))]
+    todo!("SetThreadContext({hThread:?})");
+}
+
+#[win32_derive::dllexport]
+pub fn SetUnhandledExceptionFilter(machine: &mut Machine, lpTopLevelExceptionFilter: u32) -> u32 {
+    std::mem::replace(
+        &mut machine.state.kernel32.unhandled_exception_filter,
+        lpTopLevelExceptionFilter,
+    )
+}
+
+#[win32_derive::dllexport]
+pub async fn RaiseException(
+    machine: &mut Machine,
+    dwExceptionCode: u32,
+    dwExceptionFlags: u32,
+    nNumberOfArguments: u32,
+    lpArguments: u32,
+) -> u32 {
+    #[cfg(feature = "x86-emu")]
+    {
+        // The exception appears to come from the caller, as if RaiseException returned.
+        let mut context = capture_context(machine);
+        context.Eip = machine.mem().get_pod::<u32>(context.Esp);
+        context.Esp += 4 + 16;
+
+        let count = nNumberOfArguments.min(EXCEPTION_MAXIMUM_PARAMETERS as u32);
+        let mut record = EXCEPTION_RECORD {
+            ExceptionCode: dwExceptionCode,
+            ExceptionFlags: dwExceptionFlags,
+            ExceptionAddress: context.Eip,
+            NumberParameters: count,
+            ..Default::default()
+        };
+        if lpArguments != 0 {
+            let args = machine.mem().view_n::<u32>(lpArguments, count);
+            record.ExceptionInformation[..count as usize].copy_from_slice(args);
+        }
+        // TODO: a handler continuing execution can't change the registers we return with.
+        dispatch_exception(machine, record, context).await;
+    }
+    #[cfg(not(feature = "x86-emu"))]
+    todo!(
+        "RaiseException({dwExceptionCode:x}, {dwExceptionFlags:x}, {nNumberOfArguments}, {lpArguments:x})"
+    );
+    #[allow(unreachable_code)]
+    0 // unused
+}
+
+/// Raise an access violation from a shim standing in for guest code, like the CRT
+/// routines of pe/fast_paths.rs, whose faults programs may expect to catch.  The
+/// fault appears to come from the (cdecl) shim's caller.
+pub async fn raise_access_violation(machine: &mut Machine, addr: u32, write: bool) {
+    #[cfg(feature = "x86-emu")]
+    {
+        let mut context = capture_context(machine);
+        context.Eip = machine.mem().get_pod::<u32>(context.Esp);
+        context.Esp += 4;
+
+        let mut record = EXCEPTION_RECORD {
+            ExceptionCode: EXCEPTION_ACCESS_VIOLATION,
+            ExceptionAddress: context.Eip,
+            NumberParameters: 2,
+            ..Default::default()
+        };
+        record.ExceptionInformation[0] = write as u32;
+        record.ExceptionInformation[1] = addr;
+        log::warn!("access violation at {addr:x} called from {:x}", context.Eip);
+        // TODO: as with RaiseException, a handler continuing execution can't
+        // change the registers we return with.
+        dispatch_exception(machine, record, context).await;
+    }
+    #[cfg(not(feature = "x86-emu"))]
+    log::error!("access violation at {addr:x} (write: {write}) from a shim");
+}
+
+/// Called by a handler that is about to take over an exception, like an __except
+/// block, to run the handlers of the frames it is jumping past.
+#[win32_derive::dllexport]
+pub async fn RtlUnwind(
+    machine: &mut Machine,
+    TargetFrame: u32,
+    _TargetIp: u32,
+    ExceptionRecord: u32,
+    ReturnValue: u32,
+) -> u32 {
+    #[cfg(feature = "x86-emu")]
+    {
+        let context = capture_context(machine);
+        let mut record = if ExceptionRecord != 0 {
+            machine.mem().get_pod::<EXCEPTION_RECORD>(ExceptionRecord)
+        } else {
+            EXCEPTION_RECORD {
+                ExceptionCode: STATUS_UNWIND,
+                ExceptionAddress: machine.mem().get_pod::<u32>(context.Esp),
+                ..Default::default()
+            }
+        };
+        record.ExceptionFlags |= EXCEPTION_UNWINDING;
+        if TargetFrame == 0 {
+            record.ExceptionFlags |= EXCEPTION_EXIT_UNWIND;
+        }
+
+        let esp = context.Esp;
+        let context_addr = push_struct(machine, context);
+        let record_addr = push_struct(machine, record);
+        let mut frame = teb(machine).Tib.ExceptionList;
+        while frame != TargetFrame {
+            let Some(registration) = seh_frame(machine, frame) else {
+                if TargetFrame != 0 {
+                    log::warn!("RtlUnwind: target frame {TargetFrame:x} not in SEH chain");
+                }
+                break;
+            };
+            call_handler(
+                machine,
+                registration.Handler,
+                vec![record_addr, frame, context_addr, 0],
+            )
+            .await;
+            frame = registration.Prev;
+            teb_mut(machine).Tib.ExceptionList = frame;
+        }
+        machine
+            .emu
+            .x86
+            .cpu_mut()
+            .regs
+            .set32(x86::Register::ESP, esp);
+    }
+    #[cfg(not(feature = "x86-emu"))]
+    todo!("RtlUnwind({TargetFrame:x}, {ExceptionRecord:x})");
+    // The caller continues at TargetIp, which x86 compilers make the return address.
+    ReturnValue
+}
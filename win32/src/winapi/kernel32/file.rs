@@ -7,9 +7,256 @@ use crate::{
 };
 use bitflags::bitflags;
 use memory::Pod;
+use std::collections::HashMap;
 
 const TRACE_CONTEXT: &'static str = "kernel32/file";
 
+/// Read a whole file, for APIs that load files by name.
+/// Missing files read as empty.
+pub fn read_file(machine: &Machine, path: &str) -> Vec<u8> {
+    if let Some(data) = machine.state.kernel32.written_files.get(&written_key(path)) {
+        return data.clone();
+    }
+    let mut file = machine.host.open(&machine.state.kernel32.host_path(path));
+    let mut buf = vec![0u8; file.info() as usize];
+    let mut ofs = 0;
+    while ofs < buf.len() {
+        let mut len = 0;
+        if !file.read(&mut buf[ofs..], &mut len) || len == 0 {
+            break;
+        }
+        ofs += len as usize;
+    }
+    buf.truncate(ofs);
+    buf
+}
+
+/// Whether a file exists, either written by the guest or on the host.
+pub fn file_exists(machine: &Machine, path: &str) -> bool {
+    machine
+        .state
+        .kernel32
+        .written_files
+        .contains_key(&written_key(path))
+        || machine.host.exists(&machine.state.kernel32.host_path(path))
+}
+
+/// The contents of a file opened for writing with File::create.
+pub fn written_data<'a>(machine: &'a mut Machine, path: &str) -> &'a mut Vec<u8> {
+    machine
+        .state
+        .kernel32
+        .written_files
+        .get_mut(&written_key(path))
+        .unwrap()
+}
+
+/// The key for a path in State::written_files.
+fn written_key(path: &str) -> String {
+    path.to_ascii_lowercase()
+}
+
+/// A file the guest wrote, read back from a copy of its State::written_files
+/// entry that writes through the file keep up to date.
+struct WrittenFile {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl crate::host::File for WrittenFile {
+    fn info(&self) -> u32 {
+        self.data.len() as u32
+    }
+
+    fn seek(&mut self, ofs: u32) -> bool {
+        if ofs as usize > self.data.len() {
+            return false;
+        }
+        self.pos = ofs as usize;
+        true
+    }
+
+    fn read(&mut self, buf: &mut [u8], len: &mut u32) -> bool {
+        let n = buf.len().min(self.data.len() - self.pos);
+        buf[..n].copy_from_slice(&self.data[self.pos..][..n]);
+        self.pos += n;
+        *len = n as u32;
+        true
+    }
+}
+
+/// A file opened by the guest, tracking what's needed to reopen it after loading
+/// a snapshot.
+pub struct File {
+    pub path: String,
+    /// Current read position.
+    pub pos: u32,
+    /// Whether opened for writing, with writes going to State::written_files.
+    pub write: bool,
+    source: Source,
+}
+
+enum Source {
+    Host(Box<dyn crate::host::File>),
+    Written(WrittenFile),
+}
+
+impl File {
+    pub fn open(machine: &Machine, path: &str) -> Self {
+        let source = match machine.state.kernel32.written_files.get(&written_key(path)) {
+            Some(data) => Source::Written(WrittenFile {
+                data: data.clone(),
+                pos: 0,
+            }),
+            None => Source::Host(machine.host.open(&machine.state.kernel32.host_path(path))),
+        };
+        File {
+            path: path.to_string(),
+            pos: 0,
+            write: false,
+            source,
+        }
+    }
+
+    /// Open a file for writing, creating it in State::written_files; any host file
+    /// of the same name is copied in first unless truncating.
+    pub fn create(machine: &mut Machine, path: &str, truncate: bool) -> Self {
+        let key = written_key(path);
+        if !machine.state.kernel32.written_files.contains_key(&key) {
+            let data = if truncate {
+                Vec::new()
+            } else {
+                read_file(machine, path)
+            };
+            machine
+                .state
+                .kernel32
+                .written_files
+                .insert(key.clone(), data);
+        }
+        let data = machine.state.kernel32.written_files.get_mut(&key).unwrap();
+        if truncate {
+            data.clear();
+        }
+        let mut file = File::open(machine, path);
+        file.write = true;
+        file
+    }
+
+    fn host(&mut self) -> &mut dyn crate::host::File {
+        match &mut self.source {
+            Source::Host(file) => file.as_mut(),
+            Source::Written(file) => file,
+        }
+    }
+
+    pub fn info(&self) -> u32 {
+        match &self.source {
+            Source::Host(file) => file.info(),
+            Source::Written(file) => crate::host::File::info(file),
+        }
+    }
+
+    pub fn seek(&mut self, ofs: u32) -> bool {
+        // Writers may seek past the end, extending the file on the next write.
+        if !self.host().seek(ofs) && !self.write {
+            return false;
+        }
+        self.pos = ofs;
+        true
+    }
+
+    pub fn read(&mut self, buf: &mut [u8], len: &mut u32) -> bool {
+        if !self.host().read(buf, len) {
+            return false;
+        }
+        self.pos += *len;
+        true
+    }
+
+    /// Write at the current position, to the file's entry in written_files (the
+    /// State field) and to the copy reads come from, returning false unless the
+    /// file was opened for writing.
+    pub fn write(&mut self, written_files: &mut HashMap<String, Vec<u8>>, buf: &[u8]) -> bool {
+        let Source::Written(copy) = &mut self.source else {
+            return false;
+        };
+        if !self.write {
+            return false;
+        }
+        let pos = self.pos as usize;
+        let data = written_files.get_mut(&written_key(&self.path)).unwrap();
+        for data in [data, &mut copy.data] {
+            if data.len() < pos + buf.len() {
+                data.resize(pos + buf.len(), 0);
+            }
+            data[pos..][..buf.len()].copy_from_slice(buf);
+        }
+        self.pos += buf.len() as u32;
+        copy.pos = self.pos as usize;
+        true
+    }
+}
+
+/// An open file as recorded in a snapshot.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct FileRecord {
+    handle: u32,
+    path: String,
+    pos: u32,
+    /// Size when recorded, to notice the file having changed underneath us.
+    size: u32,
+    write: bool,
+}
+
+impl FileRecord {
+    pub fn new(handle: u32, file: &File) -> Self {
+        FileRecord {
+            handle,
+            path: file.path.clone(),
+            pos: file.pos,
+            size: file.info(),
+            write: file.write,
+        }
+    }
+
+    /// Reopen the file at its recorded position, unless it no longer matches what
+    /// was recorded.
+    pub fn reopen(&self, machine: &Machine) -> Option<File> {
+        let mut file = File::open(machine, &self.path);
+        if file.info() != self.size || !file.seek(self.pos) {
+            log::warn!("snapshot: {:?} changed, leaving it closed", self.path);
+            return None;
+        }
+        file.write = self.write;
+        Some(file)
+    }
+}
+
+/// Record the guest's open files, for snapshots.
+pub fn save_files(machine: &Machine) -> Vec<FileRecord> {
+    machine
+        .state
+        .kernel32
+        .files
+        .iter()
+        .map(|(hfile, file)| FileRecord::new(hfile.to_raw(), file))
+        .collect()
+}
+
+/// Replace the open files with those recorded in a snapshot, reopened at their
+/// recorded positions.  A file that no longer matches what was recorded is left
+/// closed, so the guest sees an invalid handle rather than different contents.
+pub fn restore_files(machine: &mut Machine, records: Vec<FileRecord>) {
+    machine.state.kernel32.files.clear();
+    for record in records {
+        if let Some(file) = record.reopen(machine) {
+            let hfile = HFILE::from_raw(record.handle);
+            machine.state.kernel32.files.insert(hfile, file);
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum STD {
     INPUT_HANDLE = -10,
@@ -46,7 +293,11 @@ pub fn GetStdHandle(_machine: &mut Machine, nStdHandle: Result<STD, u32>) -> HFI
 
 #[derive(Debug, win32_derive::TryFromEnum)]
 pub enum CreationDisposition {
+    CREATE_NEW = 1,
+    CREATE_ALWAYS = 2,
     OPEN_EXISTING = 3,
+    OPEN_ALWAYS = 4,
+    TRUNCATE_EXISTING = 5,
 }
 
 bitflags! {
@@ -63,6 +314,7 @@ impl TryFrom<u32> for FileAttribute {
 }
 
 const GENERIC_READ: u32 = 0x8000_0000;
+const GENERIC_WRITE: u32 = 0x4000_0000;
 
 #[win32_derive::dllexport]
 pub fn CreateFileA(
@@ -76,10 +328,13 @@ pub fn CreateFileA(
     hTemplateFile: HFILE,
 ) -> HFILE {
     let file_name = lpFileName.unwrap();
-    if dwDesiredAccess != GENERIC_READ {
+    if let Some(name) = super::port_name(file_name) {
+        return super::open_port(machine, name);
+    }
+    if dwDesiredAccess & !(GENERIC_READ | GENERIC_WRITE) != 0 {
         unimplemented!("CreateFile access {:x}", dwDesiredAccess);
     }
-    let _dwCreationDisposition = dwCreationDisposition.unwrap();
+    let disposition = dwCreationDisposition.unwrap();
 
     let attr = dwFlagsAndAttributes.unwrap();
     if attr - FileAttribute::NORMAL != FileAttribute::empty() {
@@ -90,8 +345,21 @@ pub fn CreateFileA(
         unimplemented!("hTemplateFile {hTemplateFile:?}");
     }
 
-    let file = machine.host.open(file_name);
-    let hfile = HFILE::from_raw(0xF11E_0001);
+    let file = if dwDesiredAccess & GENERIC_WRITE != 0 {
+        let truncate = matches!(
+            disposition,
+            CreationDisposition::CREATE_NEW
+                | CreationDisposition::CREATE_ALWAYS
+                | CreationDisposition::TRUNCATE_EXISTING
+        );
+        File::create(machine, file_name, truncate)
+    } else {
+        File::open(machine, file_name)
+    };
+    let mut hfile = HFILE::from_raw(0xF11E_0001);
+    while machine.state.kernel32.files.contains_key(&hfile) {
+        hfile = HFILE::from_raw(hfile.to_raw() + 1);
+    }
     machine.state.kernel32.files.insert(hfile, file);
     hfile
 }
@@ -130,7 +398,9 @@ pub fn GetFileType(machine: &mut Machine, hFile: HFILE) -> u32 {
         STDIN_HFILE | STDOUT_HFILE | STDERR_HFILE => return FILE_TYPE_CHAR,
         _ => {}
     }
-    if machine.state.kernel32.files.get(&hFile).is_some() {
+    if machine.state.kernel32.files.get(&hFile).is_some()
+        || machine.state.kernel32.ports.get(hFile).is_some()
+    {
         return FILE_TYPE_CHAR;
     }
 
@@ -213,6 +483,10 @@ pub fn ReadFile(
     lpNumberOfBytesRead: Option<&mut u32>,
     lpOverlapped: u32,
 ) -> bool {
+    if let Some(port) = machine.state.kernel32.ports.get_mut(hFile) {
+        *lpNumberOfBytesRead.unwrap() = port.read(lpBuffer.unwrap());
+        return true;
+    }
     let file = machine.state.kernel32.files.get_mut(&hFile).unwrap();
     // TODO: SetLastError
     file.read(lpBuffer.unwrap(), lpNumberOfBytesRead.unwrap())
@@ -226,10 +500,20 @@ pub fn WriteFile(
     lpNumberOfBytesWritten: Option<&mut u32>,
     lpOverlapped: u32,
 ) -> bool {
-    assert!(hFile == STDOUT_HFILE || hFile == STDERR_HFILE);
     assert!(lpOverlapped == 0);
-
-    let n = machine.host.write(lpBuffer.unwrap());
+    let n = if let Some(port) = machine.state.kernel32.ports.get_mut(hFile) {
+        port.write(lpBuffer.unwrap()) as usize
+    } else if let Some(file) = machine.state.kernel32.files.get_mut(&hFile) {
+        let buf = lpBuffer.unwrap();
+        if !file.write(&mut machine.state.kernel32.written_files, buf) {
+            // TODO: SetLastError(ERROR_ACCESS_DENIED)
+            return false;
+        }
+        buf.len()
+    } else {
+        assert!(hFile == STDOUT_HFILE || hFile == STDERR_HFILE);
+        machine.host.write(lpBuffer.unwrap())
+    };
 
     // The docs say this parameter may not be null, but a test program with the param as null
     // runs fine on real Windows...
@@ -1,14 +1,16 @@
 //! Process initialization and startup.
 
-use super::{ExitProcess, Mappings, DLL, HMODULE, STDERR_HFILE, STDOUT_HFILE};
+use super::{
+    AddressSpace, Event, ExitProcess, Mappings, DLL, HEVENT, HMODULE, STDERR_HFILE, STDOUT_HFILE,
+};
 use crate::{
-    machine::MemImpl,
+    machine::{Emulator, MemImpl},
     pe,
     segments::SegmentDescriptor,
-    winapi::{self, alloc::Arena, builtin::BuiltinDLL, heap::Heap, types::*},
+    winapi::{self, alloc::Arena, builtin::BuiltinDLL, handle::Handles, heap::Heap, types::*},
     Machine,
 };
-use ::memory::Mem;
+use ::memory::{Mem, PAGE_SIZE};
 use std::collections::HashMap;
 
 const TRACE_CONTEXT: &'static str = "kernel32/init";
@@ -103,15 +105,20 @@ struct RTL_USER_PROCESS_PARAMETERS {
 unsafe impl ::memory::Pod for RTL_USER_PROCESS_PARAMETERS {}
 
 #[repr(C)]
-struct _EXCEPTION_REGISTRATION_RECORD {
-    Prev: DWORD,
-    Handler: DWORD,
+#[derive(Clone, Copy)]
+pub struct _EXCEPTION_REGISTRATION_RECORD {
+    pub Prev: DWORD,
+    pub Handler: DWORD,
 }
+
+/// The handler of the record that ends every thread's SEH chain, which isn't called;
+/// exceptions reaching it are unhandled.
+pub const SEH_END_HANDLER: u32 = 0xFF5E_5EFF; // Hopefully easier to spot.
 unsafe impl ::memory::Pod for _EXCEPTION_REGISTRATION_RECORD {}
 
-/// Set up TEB, PEB, and other process info.
-/// The FS register points at the TEB (thread info), which points at the PEB (process info).
-fn init_teb(cmdline: &CommandLine, arena: &mut Arena, mem: Mem) -> u32 {
+/// Set up the PEB and other process info.
+/// The FS register points at a thread's TEB (thread info), which points at the PEB (process info).
+fn init_peb(cmdline: &CommandLine, arena: &mut Arena, mem: Mem) -> u32 {
     // RTL_USER_PROCESS_PARAMETERS
     let params_addr = arena.alloc(
         std::cmp::max(
@@ -136,24 +143,22 @@ fn init_teb(cmdline: &CommandLine, arena: &mut Arena, mem: Mem) -> u32 {
     peb.ProcessHeap = 0; // TODO: we use state.process_heap instead
     peb.TlsCount = 0;
 
-    // SEH chain
-    let seh_addr = arena.alloc(
-        std::mem::size_of::<_EXCEPTION_REGISTRATION_RECORD>() as u32,
-        4,
-    );
-    let seh = mem.view_mut::<_EXCEPTION_REGISTRATION_RECORD>(seh_addr);
-    seh.Prev = 0xFFFF_FFFF;
-    seh.Handler = 0xFF5E_5EFF; // Hopefully easier to spot.
+    peb_addr
+}
 
-    // TEB
-    let teb_addr = arena.alloc(std::cmp::max(std::mem::size_of::<TEB>() as u32, 0x100), 4);
-    let teb = mem.view_mut::<TEB>(teb_addr);
-    teb.Tib.ExceptionList = seh_addr;
-    teb.Tib._Self = teb_addr; // Confusing: it points to itself.
-    teb.Peb = peb_addr;
+/// The exe's static TLS (__declspec(thread) variables), from its TLS directory:
+/// initial data each thread's copy starts from, followed by zeroes.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct TlsTemplate {
+    pub data: u32,
+    pub data_len: u32,
+    pub zero_fill: u32,
+}
 
-    teb_addr
-    // log::info!("params {params_addr:x} peb {peb_addr:x} teb {teb_addr:x}");
+impl TlsTemplate {
+    fn len(&self) -> u32 {
+        self.data_len + self.zero_fill
+    }
 }
 
 /// Result of setting up the GDT, with initial values for all the relevant segment registers.
@@ -166,27 +171,42 @@ pub struct GDTEntries {
     pub ss: u16,
 }
 
+/// MAKELANGID(LANG_ENGLISH, SUBLANG_ENGLISH_US).
+pub const LANG_EN_US: u16 = 0x0409;
+
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct State {
     /// Memory for kernel32 data structures.
     arena: Arena,
     /// Address image was loaded at.
     pub image_base: u32,
-    /// Address of TEB (what FS register-relative addresses refer to).
+    /// Address of the main thread's TEB (what FS register-relative addresses refer to).
     pub teb: u32,
+    /// TEBs of other threads, by thread id.
+    tebs: HashMap<u32, u32>,
+    peb: u32,
+    pub tls: TlsTemplate,
     pub mappings: Mappings,
     /// Heaps created by HeapAlloc().
     heaps: HashMap<u32, Heap>,
     pub process_heap: u32,
 
-    #[serde(skip)] // TODO
     pub dlls: Vec<DLL>,
 
-    #[serde(skip)] // TODO
+    /// PE images loaded so far, starting with the exe.
+    pub images: Vec<pe::LoadedImage>,
+
     pub resources: pe::IMAGE_DATA_DIRECTORY,
 
-    #[serde(skip)] // TODO
-    pub files: HashMap<HFILE, Box<dyn crate::host::File>>,
+    /// Open files, which snapshots record separately; see save_files.
+    #[serde(skip)]
+    pub files: HashMap<HFILE, super::File>,
+
+    /// Open serial and parallel ports, which snapshots leave closed.
+    #[serde(skip)]
+    pub ports: Handles<HFILE, super::Port>,
+
+    pub events: Handles<HEVENT, Event>,
 
     #[serde(skip)]
     #[cfg(feature = "x86-64")]
@@ -195,6 +215,38 @@ pub struct State {
     pub(super) env: u32,
 
     cmdline: CommandLine,
+
+    /// LANGID of the user interface language, used to pick among localized resources.
+    pub ui_language: u16,
+
+    /// The ANSI code page, as GetACP reports it; one of those encode_ansi supports.
+    pub code_page: u32,
+
+    /// Address space given to large address aware programs, applied at exe load.
+    pub address_space: AddressSpace,
+
+    /// Whether to replace recognized statically linked CRT routines with host
+    /// implementations as code is loaded; see pe/fast_paths.rs.  Off by default,
+    /// as it rewrites the program's code.
+    pub crt_fast_paths: bool,
+
+    /// Whether to resolve delay-loaded imports at load, like ordinary imports, rather
+    /// than leaving them to the image's delay-load helper on first call.
+    pub eager_delay_imports: bool,
+
+    /// The machine's name, as GetComputerName reports it.
+    pub computer_name: String,
+
+    /// Guest directories redirected to host directories, like the shell's special
+    /// folders, as (guest path, host path).
+    dir_mappings: Vec<(String, String)>,
+
+    /// Files the guest has written, by lowercased guest path.  They're kept only in
+    /// memory, where they shadow any host file of the same name.
+    pub written_files: HashMap<String, Vec<u8>>,
+
+    /// The filter SetUnhandledExceptionFilter installed, or 0.
+    pub unhandled_exception_filter: u32,
 }
 
 impl State {
@@ -212,7 +264,8 @@ impl State {
 
         let cmdline = CommandLine::new(cmdline, &mut arena, mem.mem());
 
-        let teb = init_teb(&cmdline, &mut arena, mem.mem());
+        let peb = init_peb(&cmdline, &mut arena, mem.mem());
+        let teb = alloc_teb(&mut mappings, mem, peb, "main thread TEB".into());
 
         #[cfg(feature = "x86-64")]
         let ldt = {
@@ -235,16 +288,31 @@ impl State {
             arena,
             image_base: 0,
             teb,
+            tebs: HashMap::new(),
+            peb,
+            tls: TlsTemplate::default(),
             process_heap: 0,
             mappings,
             heaps: HashMap::new(),
             dlls: Vec::new(),
+            images: Vec::new(),
             files: HashMap::new(),
+            ports: super::new_ports(),
+            events: super::new_events(),
             env: env_addr,
             cmdline,
             #[cfg(feature = "x86-64")]
             ldt,
             resources: Default::default(),
+            ui_language: LANG_EN_US,
+            code_page: 1252,
+            address_space: AddressSpace::default(),
+            crt_fast_paths: false,
+            eager_delay_imports: true,
+            computer_name: "RETROWIN32".into(),
+            dir_mappings: Vec::new(),
+            written_files: HashMap::new(),
+            unhandled_exception_filter: 0,
         };
         // Always load kernel32, because we pull retrowin32_main from it.
         let kernel32_dll = winapi::DLLS
@@ -255,6 +323,57 @@ impl State {
         state
     }
 
+    /// Create the TEB for a new thread, whose stack contains stack_pointer.
+    pub fn new_teb(&mut self, mem: &mut MemImpl, stack_pointer: u32, desc: String) -> u32 {
+        let teb = alloc_teb(&mut self.mappings, mem, self.peb, desc);
+        self.set_teb_stack(mem.mem(), teb, stack_pointer);
+        self.alloc_tls(mem, teb);
+        teb
+    }
+
+    /// Record a thread's id in its TEB, and the TEB as the one FS points at for it.
+    pub fn add_thread(&mut self, mem: Mem, teb: u32, thread_id: u32) {
+        mem.view_mut::<TEB>(teb).ClientId_UniqueThread = thread_id;
+        self.tebs.insert(thread_id, teb);
+    }
+
+    /// Fill in the stack bounds of a TEB from the mapping holding stack_pointer.
+    pub fn set_teb_stack(&self, mem: Mem, teb: u32, stack_pointer: u32) {
+        let Some(stack) = self
+            .mappings
+            .vec()
+            .iter()
+            .find(|m| m.addr <= stack_pointer && stack_pointer < m.addr + m.size)
+        else {
+            return;
+        };
+        // The limit is the bottom of the committed part, which grows downwards.
+        let mut limit = stack_pointer & !(PAGE_SIZE - 1);
+        while limit > stack.addr && mem.is_committed(limit - PAGE_SIZE, 1) {
+            limit -= PAGE_SIZE;
+        }
+        let tib = &mut mem.view_mut::<TEB>(teb).Tib;
+        tib.StackBase = stack.addr + stack.size;
+        tib.StackLimit = limit;
+    }
+
+    /// Give a TEB its own copy of the exe's static TLS, if it has any, which code
+    /// finds through ThreadLocalStoragePointer at fs:[2Ch].
+    pub fn alloc_tls(&mut self, mem: &mut MemImpl, teb: u32) {
+        if self.tls.len() == 0 {
+            return;
+        }
+        // A slot array, with only the exe's index 0, followed by the exe's block.
+        let mapping = self.mappings.alloc(4 + self.tls.len(), "TLS".into(), mem);
+        let (array, block) = (mapping.addr, mapping.addr + 4);
+        let mem = mem.mem();
+        mem.put::<u32>(array, block);
+        let data = self.tls.data as usize;
+        mem.as_mut_slice_todo()
+            .copy_within(data..data + self.tls.data_len as usize, block as usize);
+        mem.view_mut::<TEB>(teb).ThreadLocalStoragePointer = array;
+    }
+
     pub fn new_private_heap(&mut self, mem: &mut MemImpl, size: usize, desc: String) -> Heap {
         let mapping = self.mappings.alloc(size as u32, desc, mem);
         Heap::new(mapping.addr, mapping.size)
@@ -280,6 +399,42 @@ impl State {
         self.get_heap(self.process_heap).unwrap()
     }
 
+    /// Redirect files under the guest directory guest_dir to host_dir.
+    pub fn map_dir(&mut self, guest_dir: &str, host_dir: &str) {
+        let guest_dir = guest_dir.trim_end_matches('\\').to_string();
+        self.dir_mappings
+            .retain(|(dir, _)| !dir.eq_ignore_ascii_case(&guest_dir));
+        self.dir_mappings.push((guest_dir, host_dir.to_string()));
+    }
+
+    /// The host path for a guest path, which is the path itself unless it's under
+    /// a mapped directory.
+    pub fn host_path(&self, path: &str) -> String {
+        for (dir, host_dir) in &self.dir_mappings {
+            let Some(prefix) = path.get(..dir.len()) else {
+                continue;
+            };
+            let rest = &path[dir.len()..];
+            if prefix.eq_ignore_ascii_case(dir) && (rest.is_empty() || rest.starts_with('\\')) {
+                return format!("{host_dir}{}", rest.replace('\\', "/"));
+            }
+        }
+        path.to_string()
+    }
+
+    /// The guest path for a host path, the reverse of host_path.
+    pub fn guest_path(&self, path: &str) -> String {
+        for (dir, host_dir) in &self.dir_mappings {
+            let Some(rest) = path.strip_prefix(host_dir.trim_end_matches('/')) else {
+                continue;
+            };
+            if rest.is_empty() || rest.starts_with('/') {
+                return format!("{dir}{}", rest.replace('/', "\\"));
+            }
+        }
+        path.to_string()
+    }
+
     pub fn create_gdt(&mut self, mem: Mem) -> GDTEntries {
         const COUNT: usize = 5;
         let addr = self.arena.alloc(COUNT as u32 * 8, 8);
@@ -373,11 +528,43 @@ impl State {
     }
 }
 
+/// Allocate a thread's TEB, in its own page as on Windows, followed by the
+/// exception registration record that ends its SEH chain.
+fn alloc_teb(mappings: &mut Mappings, mem: &mut MemImpl, peb: u32, desc: String) -> u32 {
+    let teb_size = std::mem::size_of::<TEB>() as u32;
+    let seh_size = std::mem::size_of::<_EXCEPTION_REGISTRATION_RECORD>() as u32;
+    let teb_addr = mappings.alloc(teb_size + seh_size, desc, mem).addr;
+    let mem = mem.mem();
+
+    let seh_addr = teb_addr + teb_size;
+    let seh = mem.view_mut::<_EXCEPTION_REGISTRATION_RECORD>(seh_addr);
+    seh.Prev = 0xFFFF_FFFF;
+    seh.Handler = SEH_END_HANDLER;
+
+    let teb = mem.view_mut::<TEB>(teb_addr);
+    teb.Tib.ExceptionList = seh_addr;
+    teb.Tib._Self = teb_addr; // Confusing: it points to itself.
+    teb.ClientId_UniqueProcess = 1; // matches GetCurrentProcessId
+    teb.Peb = peb;
+    teb_addr
+}
+
+/// The address of the running thread's TEB.
+pub fn current_teb(machine: &Machine) -> u32 {
+    let kernel32 = &machine.state.kernel32;
+    let thread_id = machine.emu.current_thread();
+    kernel32
+        .tebs
+        .get(&thread_id)
+        .copied()
+        .unwrap_or(kernel32.teb)
+}
+
 pub fn teb(machine: &Machine) -> &TEB {
-    machine.mem().view::<TEB>(machine.state.kernel32.teb)
+    machine.mem().view::<TEB>(current_teb(machine))
 }
 pub fn teb_mut(machine: &mut Machine) -> &mut TEB {
-    machine.mem().view_mut::<TEB>(machine.state.kernel32.teb)
+    machine.mem().view_mut::<TEB>(current_teb(machine))
 }
 pub fn peb_mut(machine: &mut Machine) -> &mut PEB {
     let peb_addr = teb(machine).Peb;
@@ -407,9 +594,9 @@ unsafe impl ::memory::Pod for PEB {}
 
 #[repr(C)]
 pub struct NT_TIB {
-    ExceptionList: DWORD,
+    pub ExceptionList: DWORD,
     StackBase: DWORD,
-    StackLimit: DWORD,
+    pub StackLimit: DWORD,
     SubSystemTib: DWORD,
     FiberData: DWORD,
     ArbitraryUserPointer: DWORD,
@@ -435,8 +622,8 @@ pub struct TEB {
     pub WOW32Reserved: DWORD,
     pub CurrentLocale: DWORD,
     // TODO: ... there are many more fields here
-
-    // This is at the wrong offset, but it shouldn't matter.
+    _unused: [DWORD; (0xE10 - 0xC8) / 4],
+    /* 0xE10 */
     pub TlsSlots: [DWORD; 64],
 }
 unsafe impl ::memory::Pod for TEB {}
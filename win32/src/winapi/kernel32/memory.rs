@@ -1,7 +1,7 @@
 use crate::{
-    machine::{Machine, MemImpl},
+    machine::{Emulator, Machine, MemImpl},
     pe::ImageSectionFlags,
-    winapi::stack_args,
+    winapi::{stack_args, types::*},
 };
 use bitflags::bitflags;
 use memory::Mem;
@@ -9,6 +9,29 @@ use std::cmp::max;
 
 const TRACE_CONTEXT: &'static str = "kernel32/memory";
 
+/// Page protections, as tracked in the guest page table; PAGE_READWRITE is the
+/// usual one.
+pub const PAGE_READONLY: u32 = 0x02;
+pub const PAGE_READWRITE: u32 = 0x04;
+const PAGE_WRITECOPY: u32 = 0x08;
+pub const PAGE_EXECUTE_READ: u32 = 0x20;
+pub const PAGE_EXECUTE_READWRITE: u32 = 0x40;
+const PAGE_EXECUTE_WRITECOPY: u32 = 0x80;
+
+/// Whether a page protection allows writes.  Only writes are enforced: reads of
+/// committed memory always succeed, and like Win9x (and pre-NX hardware) any
+/// readable memory can be executed.
+pub fn is_writable_protection(protect: u32) -> bool {
+    protect & (PAGE_READWRITE | PAGE_WRITECOPY | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY)
+        != 0
+}
+
+/// VirtualAlloc/VirtualFree operations.
+const MEM_COMMIT: u32 = 0x1000;
+const MEM_RESERVE: u32 = 0x2000;
+const MEM_DECOMMIT: u32 = 0x4000;
+const MEM_RELEASE: u32 = 0x8000;
+
 pub fn round_up_to_page_granularity(size: u32) -> u32 {
     size + (0x1000 - 1) & !(0x1000 - 1)
 }
@@ -22,46 +45,93 @@ pub struct Mapping {
     pub flags: ImageSectionFlags,
 }
 
+/// How much address space programs marked IMAGE_FILE_LARGE_ADDRESS_AWARE get.
+/// Programs without the flag always get 2GB, as on Windows; some break when given
+/// pointers with the high bit set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum AddressSpace {
+    /// What a 32-bit Windows gives everything.
+    Gb2,
+    /// 32-bit Windows booted with /3GB.
+    Gb3,
+    /// 64-bit Windows.
+    #[default]
+    Gb4,
+}
+
+impl std::str::FromStr for AddressSpace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "2gb" => AddressSpace::Gb2,
+            "3gb" => AddressSpace::Gb3,
+            "4gb" => AddressSpace::Gb4,
+            _ => return Err(format!("bad address space {s:?}, want 2gb/3gb/4gb")),
+        })
+    }
+}
+
+impl AddressSpace {
+    /// End of the user address space for a program.  The top 64kb below each
+    /// boundary is reserved, as on Windows.
+    pub fn user_limit(self, large_address_aware: bool) -> u32 {
+        match self {
+            _ if !large_address_aware => 0x7FFF_0000,
+            AddressSpace::Gb2 => 0x7FFF_0000,
+            AddressSpace::Gb3 => 0xBFFF_0000,
+            AddressSpace::Gb4 => 0xFFFF_0000,
+        }
+    }
+}
+
 /// The set of Mappings managed by the kernel.
 /// These get visualized in the debugger when you hover a pointer.
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
-pub struct Mappings(Vec<Mapping>);
+pub struct Mappings {
+    mappings: Vec<Mapping>,
+    /// End of the user address space; dynamic allocations stay below it.
+    pub limit: u32,
+}
 impl Mappings {
     pub fn new() -> Self {
-        Mappings(vec![Mapping {
-            addr: 0,
-            size: 0x1000,
-            desc: "avoid null pointers".into(),
-            flags: ImageSectionFlags::empty(),
-        }])
+        Mappings {
+            mappings: vec![Mapping {
+                addr: 0,
+                size: 0x1000,
+                desc: "avoid null pointers".into(),
+                flags: ImageSectionFlags::empty(),
+            }],
+            limit: AddressSpace::Gb2.user_limit(false),
+        }
     }
 
     pub fn add(&mut self, mut mapping: Mapping) -> &Mapping {
         mapping.size = round_up_to_page_granularity(mapping.size);
         let pos = self
-            .0
+            .mappings
             .iter()
             .position(|m| m.addr > mapping.addr)
-            .unwrap_or(self.0.len());
+            .unwrap_or(self.mappings.len());
         if pos > 0 {
-            let prev = &mut self.0[pos - 1];
+            let prev = &mut self.mappings[pos - 1];
             if prev.addr + prev.size > mapping.addr {
                 panic!("mapping conflict loading {mapping:x?} conflicts with {prev:x?}",);
             }
         }
-        if pos < self.0.len() {
-            let next = &self.0[pos];
+        if pos < self.mappings.len() {
+            let next = &self.mappings[pos];
             assert!(mapping.addr + mapping.size <= next.addr);
         }
-        self.0.insert(pos, mapping);
-        &self.0[pos]
+        self.mappings.insert(pos, mapping);
+        &self.mappings[pos]
     }
 
     /// Find an address where we can create a new mapping of given size.
     pub fn find_space(&self, size: u32) -> u32 {
         let size = round_up_to_page_granularity(size);
         let mut prev_end = 0;
-        for mapping in &self.0 {
+        for mapping in &self.mappings {
             let space = mapping.addr - prev_end;
             if space > size {
                 break;
@@ -77,7 +147,10 @@ impl Mappings {
             panic!("new mapping {:?} too large: {size:x} bytes", desc);
         }
         let addr = self.find_space(size);
-        if addr + size > mem.len() {
+        if addr as u64 + size as u64 > self.limit as u64 {
+            panic!("new mapping {:?} out of address space", desc);
+        }
+        if !mem.commit(addr, size) {
             panic!(
                 "not enough memory reserved, need at least {}mb",
                 (addr + size) >> 20
@@ -91,27 +164,66 @@ impl Mappings {
         })
     }
 
+    /// Like alloc, but only reserving the address space, which any amount of
+    /// can be done cheaply.  Returns None if there's no room.
+    pub fn reserve(&mut self, size: u32, desc: String, mem: &mut MemImpl) -> Option<&Mapping> {
+        let size = round_up_to_page_granularity(size);
+        let addr = self.find_space(size);
+        if addr as u64 + size as u64 > self.limit as u64 || !mem.reserve(addr, size) {
+            return None;
+        }
+        Some(self.add(Mapping {
+            addr,
+            size,
+            desc,
+            flags: ImageSectionFlags::empty(),
+        }))
+    }
+
+    /// The mapping containing addr, if any.
+    pub fn find(&self, addr: u32) -> Option<&Mapping> {
+        self.mappings
+            .iter()
+            .find(|m| m.addr <= addr && (addr as u64) < m.addr as u64 + m.size as u64)
+    }
+
+    /// Whether addr..addr+size overlaps no mapping.
+    pub fn is_free(&self, addr: u32, size: u32) -> bool {
+        let end = addr as u64 + size as u64;
+        end <= self.limit as u64
+            && !self
+                .mappings
+                .iter()
+                .any(|m| (m.addr as u64) < end && addr < m.addr + m.size)
+    }
+
+    /// Remove the mapping starting at addr, returning it.
+    pub fn remove(&mut self, addr: u32) -> Option<Mapping> {
+        let pos = self.mappings.iter().position(|m| m.addr == addr)?;
+        Some(self.mappings.remove(pos))
+    }
+
     pub fn vec(&self) -> &Vec<Mapping> {
-        &self.0
+        &self.mappings
     }
 
     pub fn grow(&mut self, addr: u32, min_growth: u32) -> u32 {
-        let pos = self.0.iter().position(|m| m.addr == addr).unwrap();
-        let mapping = &self.0[pos];
+        let pos = self.mappings.iter().position(|m| m.addr == addr).unwrap();
+        let mapping = &self.mappings[pos];
         let mut new_size = mapping.size;
         while new_size - mapping.size < min_growth {
             new_size *= 2;
         }
 
         // Check if we run into a mapping after this one.
-        if pos + 1 < self.0.len() {
-            let next = &self.0[pos + 1];
+        if pos + 1 < self.mappings.len() {
+            let next = &self.mappings[pos + 1];
             if mapping.addr + new_size > next.addr {
                 panic!("cannot grow {:?}", mapping);
             }
         }
 
-        let mapping = &mut self.0[pos];
+        let mapping = &mut self.mappings[pos];
         let growth = new_size - mapping.size;
         mapping.size = new_size;
         log::info!(
@@ -125,7 +237,7 @@ impl Mappings {
     }
 
     pub fn dump_memory(&self, mem: Mem) {
-        for map in &self.0 {
+        for map in &self.mappings {
             println!("{map:x?}");
             for addr in (map.addr..map.addr + map.size).step_by(16) {
                 println!("{addr:x} {:x?}", mem.slice(addr..addr + 16).as_slice_todo());
@@ -298,41 +410,113 @@ pub fn VirtualAlloc(
     flAllocationType: u32,
     flProtec: u32,
 ) -> u32 {
-    if lpAddress != 0 {
-        // Changing flags on an existing address, hopefully.
-        match machine
-            .state
-            .kernel32
-            .mappings
-            .vec()
-            .iter()
-            .find(|&mapping| mapping.addr == lpAddress)
-        {
-            None => {
-                log::error!("failing VirtualAlloc({lpAddress:x}, ...) refers to unknown mapping");
-                return 0;
-            }
-            Some(_) => {
-                // adjusting flags on existing mapping, ignore.
-                return lpAddress;
-            }
+    let mappings = &mut machine.state.kernel32.mappings;
+    let memory = &mut machine.emu.memory;
+    let commit = flAllocationType & MEM_COMMIT != 0;
+    let addr = if lpAddress == 0 {
+        // Reservations can be huge and cost nothing until committed, so commit
+        // them page by page rather than all at once.
+        let Some(mapping) = mappings.reserve(dwSize, "VirtualAlloc".into(), memory) else {
+            log::error!("failing VirtualAlloc({dwSize:x}): out of address space");
+            return 0;
+        };
+        mapping.addr
+    } else if mappings.find(lpAddress).is_some() {
+        // Committing (or just changing flags on) part of an existing reservation.
+        lpAddress & !(0x1000 - 1)
+    } else if flAllocationType & MEM_RESERVE != 0 && mappings.is_free(lpAddress, dwSize) {
+        // Reserving at a chosen address, which is rounded to allocation granularity.
+        let addr = lpAddress & !(0x1_0000 - 1);
+        let size = round_up_to_page_granularity(lpAddress + dwSize - addr);
+        if !memory.reserve(addr, size) {
+            return 0;
+        }
+        mappings.add(Mapping {
+            addr,
+            size,
+            desc: "VirtualAlloc".into(),
+            flags: ImageSectionFlags::empty(),
+        });
+        addr
+    } else {
+        log::error!("failing VirtualAlloc({lpAddress:x}, ...) refers to unknown mapping");
+        return 0;
+    };
+    if commit {
+        let start = match lpAddress {
+            0 => addr,
+            _ => lpAddress & !(0x1000 - 1),
+        };
+        let size = round_up_to_page_granularity(lpAddress.max(start) + dwSize - start);
+        if !memory.commit(start, size) {
+            log::error!("failing VirtualAlloc({lpAddress:x}, {dwSize:x}): can't commit");
+            return 0;
+        }
+        if flProtec != PAGE_READWRITE {
+            memory.protect(start, size, flProtec);
         }
     }
-    // TODO round dwSize to page boundary
-
-    let mapping = machine.state.kernel32.mappings.alloc(
-        dwSize,
-        "VirtualAlloc".into(),
-        &mut machine.emu.memory,
-    );
-    mapping.addr
+    addr
 }
 
 #[win32_derive::dllexport]
-pub fn VirtualFree(_machine: &mut Machine, lpAddress: u32, dwSize: u32, dwFreeType: u32) -> u32 {
+pub fn VirtualFree(machine: &mut Machine, lpAddress: u32, dwSize: u32, dwFreeType: u32) -> u32 {
+    let memory = &mut machine.emu.memory;
+    if dwFreeType & MEM_RELEASE != 0 {
+        // Releasing frees the whole reservation, which must be named by its start.
+        let Some(mapping) = machine.state.kernel32.mappings.remove(lpAddress) else {
+            return 0; // failure
+        };
+        memory.decommit(mapping.addr, mapping.size, true);
+    } else if dwFreeType & MEM_DECOMMIT != 0 {
+        let Some(mapping) = machine.state.kernel32.mappings.find(lpAddress) else {
+            return 0; // failure
+        };
+        let size = match dwSize {
+            0 => mapping.addr + mapping.size - lpAddress,
+            size => size,
+        };
+        memory.decommit(lpAddress, size, false);
+    }
     1 // success
 }
 
+#[repr(C)]
+#[derive(Debug)]
+pub struct SYSTEM_INFO {
+    wProcessorArchitecture: WORD,
+    wReserved: WORD,
+    dwPageSize: DWORD,
+    lpMinimumApplicationAddress: DWORD,
+    lpMaximumApplicationAddress: DWORD,
+    dwActiveProcessorMask: DWORD,
+    dwNumberOfProcessors: DWORD,
+    dwProcessorType: DWORD,
+    dwAllocationGranularity: DWORD,
+    wProcessorLevel: WORD,
+    wProcessorRevision: WORD,
+}
+unsafe impl memory::Pod for SYSTEM_INFO {}
+
+#[win32_derive::dllexport]
+pub fn GetSystemInfo(machine: &mut Machine, lpSystemInfo: Option<&mut SYSTEM_INFO>) -> u32 {
+    let info = lpSystemInfo.unwrap();
+    *info = SYSTEM_INFO {
+        wProcessorArchitecture: 0, // PROCESSOR_ARCHITECTURE_INTEL
+        wReserved: 0,
+        dwPageSize: 0x1000,
+        lpMinimumApplicationAddress: 0x1_0000,
+        lpMaximumApplicationAddress: machine.state.kernel32.mappings.limit - 1,
+        dwActiveProcessorMask: 1,
+        dwNumberOfProcessors: 1,
+        dwProcessorType: 586, // PROCESSOR_INTEL_PENTIUM
+        dwAllocationGranularity: 0x1_0000,
+        wProcessorLevel: 5,
+        wProcessorRevision: 0,
+    };
+    0
+}
+
 #[win32_derive::dllexport]
 pub fn IsBadReadPtr(_machine: &mut Machine, lp: u32, ucb: u32) -> bool {
     false // all pointers are valid
@@ -390,12 +574,42 @@ pub fn LocalAlloc(machine: &mut Machine, uFlags: GMEM, dwBytes: u32) -> u32 {
 
 #[win32_derive::dllexport]
 pub fn VirtualProtect(
-    _machine: &mut Machine,
+    machine: &mut Machine,
     lpAddress: u32,
     dwSize: u32,
     flNewProtect: u32,
     lpflOldProtect: Option<&mut u32>,
 ) -> bool {
+    let Some(old) = machine
+        .emu
+        .memory
+        .protect(lpAddress, dwSize.max(1), flNewProtect)
+    else {
+        return false;
+    };
+    // Packers unprotect a section, unpack code into it, and protect it again before
+    // jumping in; anything decoded from it before is stale.
+    machine.emu.invalidate_code(lpAddress, dwSize.max(1));
+    if let Some(lpflOldProtect) = lpflOldProtect {
+        *lpflOldProtect = old;
+    }
+    true // success
+}
+
+#[win32_derive::dllexport]
+pub fn FlushInstructionCache(
+    machine: &mut Machine,
+    hProcess: HANDLE<()>,
+    lpBaseAddress: u32,
+    dwSize: u32,
+) -> bool {
+    // Programs that generate code call this after writing it.
+    if lpBaseAddress == 0 {
+        // Flush everything.
+        machine.emu.invalidate_code(0, u32::MAX);
+    } else {
+        machine.emu.invalidate_code(lpBaseAddress, dwSize);
+    }
     true // success
 }
 
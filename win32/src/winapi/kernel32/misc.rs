@@ -1,7 +1,8 @@
 //! kernel32 API without a better home.
 
-use super::{teb_mut, WriteFile, FILETIME};
+use super::{current_teb, teb_mut, WriteFile, FILETIME, HEVENT};
 use crate::{
+    machine::Emulator,
     winapi::{
         stack_args::{ArrayWithSize, ArrayWithSizeMut},
         types::*,
@@ -28,28 +29,67 @@ pub fn GetLastError(_machine: &mut Machine) -> u32 {
 #[win32_derive::dllexport]
 pub fn ExitProcess(machine: &mut Machine, uExitCode: u32) -> u32 {
     machine.host.exit(uExitCode);
-    // TODO: this is unsatisfying.
-    // Maybe better is to generate a hlt instruction somewhere and jump to it?
-    #[cfg(feature = "x86-emu")]
-    {
-        machine.emu.x86.cpu_mut().state = x86::CPUState::Exit(uExitCode);
-    }
+    machine.emu.exit(uExitCode);
     0
 }
 
 #[win32_derive::dllexport]
-pub fn GetACP(_machine: &mut Machine) -> u32 {
-    1252 // windows-1252
+pub fn GetACP(machine: &mut Machine) -> u32 {
+    machine.state.kernel32.code_page
+}
+
+#[win32_derive::dllexport]
+pub fn GetUserDefaultUILanguage(machine: &mut Machine) -> u32 {
+    machine.state.kernel32.ui_language as u32
+}
+
+#[win32_derive::dllexport]
+pub fn GetSystemDefaultUILanguage(machine: &mut Machine) -> u32 {
+    machine.state.kernel32.ui_language as u32
+}
+
+#[win32_derive::dllexport]
+pub fn GetUserDefaultLangID(machine: &mut Machine) -> u32 {
+    machine.state.kernel32.ui_language as u32
 }
 
 #[win32_derive::dllexport]
-pub fn IsValidCodePage(_machine: &mut Machine, CodePage: u32) -> bool {
-    CodePage == 1252
+pub fn GetSystemDefaultLangID(machine: &mut Machine) -> u32 {
+    machine.state.kernel32.ui_language as u32
 }
 
 #[win32_derive::dllexport]
-pub fn GetCPInfo(_machine: &mut Machine, _CodePage: u32, _lpCPInfo: u32) -> u32 {
-    0 // fail
+pub fn IsValidCodePage(machine: &mut Machine, CodePage: u32) -> bool {
+    CodePage == 1252 || CodePage == machine.state.kernel32.code_page
+}
+
+#[win32_derive::dllexport]
+pub fn GetCPInfo(machine: &mut Machine, CodePage: u32, lpCPInfo: u32) -> u32 {
+    let code_page = match CodePage {
+        0 => machine.state.kernel32.code_page, // CP_ACP
+        cp => cp,
+    };
+    if !is_supported_code_page(code_page) || lpCPInfo == 0 {
+        return 0; // fail
+    }
+    // CPINFO: MaxCharSize, DefaultChar[2], then LeadByte[12] as pairs of
+    // inclusive ranges, ending with a pair of zeros.
+    let lead_ranges: &[u8] = match code_page {
+        932 => &[0x81, 0x9F, 0xE0, 0xFC],
+        936 | 949 | 950 => &[0x81, 0xFE],
+        _ => &[],
+    };
+    let info = machine.mem().sub(lpCPInfo, 18).as_mut_slice_todo();
+    info.fill(0);
+    let max_char_size: u32 = match code_page {
+        65001 => 4,
+        _ if lead_ranges.is_empty() => 1,
+        _ => 2,
+    };
+    info[..4].copy_from_slice(&max_char_size.to_le_bytes());
+    info[4] = b'?';
+    info[6..6 + lead_ranges.len()].copy_from_slice(lead_ranges);
+    1 // success
 }
 
 #[win32_derive::dllexport]
@@ -86,6 +126,50 @@ pub fn GetEnvironmentVariableW(
     false
 }
 
+fn get_computer_name(
+    machine: &mut Machine,
+    lpBuffer: u32,
+    nSize: Option<&mut u32>,
+    wide: bool,
+) -> bool {
+    const ERROR_INVALID_PARAMETER: u32 = 87;
+    const ERROR_BUFFER_OVERFLOW: u32 = 111;
+    let Some(size) = nSize else {
+        SetLastError(machine, ERROR_INVALID_PARAMETER);
+        return false;
+    };
+    let name = machine.state.kernel32.computer_name.clone();
+    let needed = name.len() as u32 + 1;
+    if *size < needed || lpBuffer == 0 {
+        *size = needed;
+        SetLastError(machine, ERROR_BUFFER_OVERFLOW);
+        return false;
+    }
+    let mem = machine.mem();
+    if wide {
+        for (i, c) in name.encode_utf16().chain([0]).enumerate() {
+            mem.put::<u16>(lpBuffer + i as u32 * 2, c);
+        }
+    } else {
+        let out = mem.sub(lpBuffer, needed).as_mut_slice_todo();
+        out[..name.len()].copy_from_slice(name.as_bytes());
+        out[name.len()] = 0;
+    }
+    // On success the size excludes the nul.
+    *size = needed - 1;
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn GetComputerNameA(machine: &mut Machine, lpBuffer: u32, nSize: Option<&mut u32>) -> bool {
+    get_computer_name(machine, lpBuffer, nSize, false)
+}
+
+#[win32_derive::dllexport]
+pub fn GetComputerNameW(machine: &mut Machine, lpBuffer: u32, nSize: Option<&mut u32>) -> bool {
+    get_computer_name(machine, lpBuffer, nSize, true)
+}
+
 #[derive(Debug, win32_derive::TryFromEnum)]
 pub enum ProcessorFeature {
     FLOATING_POINT_PRECISION_ERRATA = 0,
@@ -233,9 +317,30 @@ pub fn SetHandleCount(_machine: &mut Machine, uNumber: u32) -> u32 {
     uNumber
 }
 
+/// Prefix marking an OutputDebugString as a message for the host rather than a log.
+/// Patched guest code can send "retrowin32:channel:payload" to pass data out to host
+/// scripts; the payload format is up to the channel.  See doc/design_notes.md.
+pub const GUEST_CHANNEL_PREFIX: &str = "retrowin32:";
+
+fn output_debug_string(machine: &mut Machine, msg: &str) {
+    if let Some(msg) = msg.strip_prefix(GUEST_CHANNEL_PREFIX) {
+        let (channel, payload) = msg.split_once(':').unwrap_or((msg, ""));
+        machine.host.guest_message(channel, payload);
+        return;
+    }
+    log::warn!("OutputDebugString: {:?}", msg);
+}
+
+#[win32_derive::dllexport]
+pub fn OutputDebugStringA(machine: &mut Machine, msg: Option<&str>) -> u32 {
+    output_debug_string(machine, msg.unwrap_or_default());
+    0
+}
+
 #[win32_derive::dllexport]
-pub fn OutputDebugStringA(_machine: &mut Machine, msg: Option<&str>) -> u32 {
-    log::warn!("OutputDebugStringA: {:?}", msg);
+pub fn OutputDebugStringW(machine: &mut Machine, msg: Option<&Str16>) -> u32 {
+    let msg = msg.map(|m| m.to_string()).unwrap_or_default();
+    output_debug_string(machine, &msg);
     0
 }
 
@@ -255,11 +360,6 @@ pub fn WriteConsoleA(
     true // success
 }
 
-#[win32_derive::dllexport]
-pub fn SetUnhandledExceptionFilter(_machine: &mut Machine, _lpTopLevelExceptionFilter: u32) -> u32 {
-    0 // No current handler.
-}
-
 #[win32_derive::dllexport]
 pub fn UnhandledExceptionFilter(_machine: &mut Machine, _exceptionInfo: u32) -> u32 {
     // "The process is being debugged, so the exception should be passed (as second chance) to the application's debugger."
@@ -268,7 +368,7 @@ pub fn UnhandledExceptionFilter(_machine: &mut Machine, _exceptionInfo: u32) ->
 
 #[win32_derive::dllexport]
 pub fn NtCurrentTeb(machine: &mut Machine) -> u32 {
-    machine.state.kernel32.teb
+    current_teb(machine)
 }
 
 // TODO: this has a bunch of synchronization magic that I haven't implemented,
@@ -292,10 +392,94 @@ pub fn InitializeSListHead(_machine: &mut Machine, ListHead: Option<&mut SLIST_H
 pub enum CP {
     /// The system default Windows ANSI code page.
     ACP = 0,
+    SHIFT_JIS = 932,
+    GBK = 936,
+    KOREAN = 949,
+    BIG5 = 950,
     WINDOWS_1252 = 1252,
     UTF8 = 65001,
 }
 
+/// The characters 0x80 through 0x9F of windows-1252, which differ from Latin-1.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+/// The double-byte (DBCS) code pages, used for Chinese, Japanese and Korean.
+/// encoding_rs's encoders for these produce the same bytes as Windows' code pages.
+fn dbcs_encoding(code_page: u32) -> Option<&'static encoding_rs::Encoding> {
+    Some(match code_page {
+        932 => encoding_rs::SHIFT_JIS,
+        936 => encoding_rs::GBK,
+        949 => encoding_rs::EUC_KR,
+        950 => encoding_rs::BIG5,
+        _ => return None,
+    })
+}
+
+/// Whether encode_ansi supports a code page.
+pub fn is_supported_code_page(code_page: u32) -> bool {
+    matches!(code_page, 1252 | 65001) || dbcs_encoding(code_page).is_some()
+}
+
+/// Whether a byte starts a two-byte character in a DBCS code page.
+pub fn is_dbcs_lead_byte(code_page: u32, byte: u8) -> bool {
+    match code_page {
+        932 => matches!(byte, 0x81..=0x9F | 0xE0..=0xFC),
+        936 | 949 | 950 => matches!(byte, 0x81..=0xFE),
+        _ => false,
+    }
+}
+
+/// Encode a character in an ANSI code page, giving '?' for those it lacks.
+pub fn encode_ansi(code_page: u32, c: char) -> Vec<u8> {
+    if let Some(encoding) = dbcs_encoding(code_page) {
+        let mut buf = [0; 4];
+        let (bytes, _, unmappable) = encoding.encode(c.encode_utf8(&mut buf));
+        return if unmappable {
+            vec![b'?']
+        } else {
+            bytes.into_owned()
+        };
+    }
+    match code_page {
+        65001 => c.to_string().into_bytes(),
+        _ => {
+            let byte = match c as u32 {
+                0..=0x7F | 0xA0..=0xFF => Some(c as u8),
+                _ => WINDOWS_1252_HIGH
+                    .iter()
+                    .position(|&high| high == c)
+                    .map(|i| 0x80 + i as u8),
+            };
+            vec![byte.unwrap_or(b'?')]
+        }
+    }
+}
+
+/// Decode text in an ANSI code page.
+pub fn decode_ansi(code_page: u32, bytes: &[u8]) -> String {
+    if let Some(encoding) = dbcs_encoding(code_page) {
+        return encoding.decode_without_bom_handling(bytes).0.into_owned();
+    }
+    match code_page {
+        65001 => String::from_utf8_lossy(bytes).into_owned(),
+        _ => bytes
+            .iter()
+            .map(|&b| match b {
+                0x80..=0x9F => WINDOWS_1252_HIGH[b as usize - 0x80],
+                _ => b as char,
+            })
+            .collect(),
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn IsDBCSLeadByte(machine: &mut Machine, TestChar: u32) -> bool {
+    is_dbcs_lead_byte(machine.state.kernel32.code_page, TestChar as u8)
+}
+
 #[win32_derive::dllexport]
 pub fn MultiByteToWideChar(
     machine: &mut Machine,
@@ -303,12 +487,13 @@ pub fn MultiByteToWideChar(
     dwFlags: u32,
     lpMultiByteStr: u32,
     cbMultiByte: i32,
-    mut lpWideCharStr: ArrayWithSizeMut<u16>,
+    lpWideCharStr: ArrayWithSizeMut<u16>,
 ) -> u32 {
-    match CodePage {
+    let code_page = match CodePage {
         Err(value) => unimplemented!("MultiByteToWideChar code page {value}"),
-        _ => {} // treat all others as ansi for now
-    }
+        Ok(CP::ACP) => machine.state.kernel32.code_page,
+        Ok(cp) => cp as u32,
+    };
     // TODO: dwFlags
 
     let input_len = match cbMultiByte {
@@ -316,26 +501,18 @@ pub fn MultiByteToWideChar(
         -1 => machine.mem().slicez(lpMultiByteStr).len() as u32 + 1, // include nul
         len => len as u32,
     };
+    let input = machine.mem().sub(lpMultiByteStr, input_len);
+    let output: Vec<u16> = decode_ansi(code_page, input.as_slice_todo())
+        .encode_utf16()
+        .collect();
 
     match lpWideCharStr {
-        Some(buf) if buf.len() == 0 => lpWideCharStr = None,
-        _ => (),
-    };
-
-    match lpWideCharStr {
-        None => input_len,
-        Some(buf) => {
-            let input = machine.mem().sub(lpMultiByteStr, input_len);
-            let mut len = 0;
-            for (&c_in, c_out) in std::iter::zip(input.as_slice_todo(), buf) {
-                if c_in > 0x7f {
-                    unimplemented!("unicode");
-                }
-                *c_out = c_in as u16;
-                len += 1;
-            }
-            len
+        Some(buf) if buf.len() > 0 => {
+            let len = output.len().min(buf.len());
+            buf[..len].copy_from_slice(&output[..len]);
+            len as u32
         }
+        _ => output.len() as u32,
     }
 }
 
@@ -375,16 +552,13 @@ pub fn SetPriorityClass(
 
 #[win32_derive::dllexport]
 pub async fn Sleep(machine: &mut Machine, dwMilliseconds: u32) -> u32 {
-    #[cfg(feature = "x86-emu")]
-    {
-        let until = machine.host.time() + dwMilliseconds;
-        machine.emu.x86.cpu_mut().block(Some(until)).await;
-    }
-
-    #[cfg(not(feature = "x86-emu"))]
-    {
-        log::warn!("TODO: sleep");
+    let until = machine.host.time() + dwMilliseconds;
+    match machine.emu.block(Some(until)) {
+        Some(block) => block.await,
+        None => log::warn!("TODO: sleep"),
     }
+    crate::winapi::poll_devices(machine);
+    crate::winapi::run_device_callbacks(machine).await;
     0
 }
 
@@ -452,7 +626,22 @@ pub fn FormatMessageW(
 }
 
 #[win32_derive::dllexport]
-pub fn CloseHandle(_machine: &mut Machine, hObject: u32) -> bool {
-    // TODO
+pub fn CloseHandle(machine: &mut Machine, hObject: u32) -> bool {
+    // TODO: other kinds of handles.
+    machine
+        .state
+        .kernel32
+        .events
+        .remove(HEVENT::from_raw(hObject));
+    machine
+        .state
+        .kernel32
+        .ports
+        .remove(HFILE::from_raw(hObject));
+    machine
+        .state
+        .kernel32
+        .files
+        .remove(&HFILE::from_raw(hObject));
     true
 }
@@ -2,23 +2,27 @@
 #![allow(non_camel_case_types)]
 
 mod dll;
+mod exception;
 mod file;
 mod ini;
 mod init;
 mod libc;
 mod memory;
 mod misc;
+mod port;
 mod resource;
 mod sync;
 mod thread;
 
 pub use self::memory::*;
 pub use dll::*;
+pub use exception::*;
 pub use file::*;
 pub use ini::*;
 pub use init::*;
 pub use libc::*;
 pub use misc::*;
+pub use port::*;
 pub use resource::*;
 pub use sync::*;
 pub use thread::*;
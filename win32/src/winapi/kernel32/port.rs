@@ -0,0 +1,263 @@
+//! Serial and parallel ports, opened by name with CreateFile.
+
+use super::SetLastError;
+use crate::{
+    machine::Machine,
+    winapi::{handle::Handles, types::HFILE},
+};
+use memory::Pod;
+use std::collections::VecDeque;
+
+const TRACE_CONTEXT: &'static str = "kernel32/port";
+
+/*
+## Ports
+
+CreateFile of "COM1".."COM9" or "LPT1".."LPT9" (optionally as "\\.\COM1" or
+"COM1:") opens whatever the host connected to that port, such as a file, a
+socket, or a pseudo-terminal; ports the host has nothing for don't exist, as on
+a machine without them.
+
+There is no line to speak of, so the DCB and timeouts are only stored for the
+guest to read back, and the modem status lines all read as on, which is what
+dongle checks and link cable games wait for.  Reads never wait: ReadFile returns
+what has arrived, as if ReadIntervalTimeout were MAXDWORD.
+*/
+
+const ERROR_FILE_NOT_FOUND: u32 = 2;
+
+/// The port a CreateFile name refers to, normalized to like "COM1".
+pub fn port_name(name: &str) -> Option<String> {
+    let name = name.strip_prefix(r"\\.\").unwrap_or(name);
+    let name = name.strip_suffix(':').unwrap_or(name).to_ascii_uppercase();
+    match name.as_bytes() {
+        [b'C', b'O', b'M', b'1'..=b'9'] | [b'L', b'P', b'T', b'1'..=b'9'] => Some(name),
+        _ => None,
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DCB {
+    pub DCBlength: u32,
+    pub BaudRate: u32,
+    /// fBinary, fParity etc. bitfields.
+    pub flags: u32,
+    pub wReserved: u16,
+    pub XonLim: u16,
+    pub XoffLim: u16,
+    pub ByteSize: u8,
+    pub Parity: u8,
+    pub StopBits: u8,
+    pub XonChar: u8,
+    pub XoffChar: u8,
+    pub ErrorChar: u8,
+    pub EofChar: u8,
+    pub EvtChar: u8,
+    pub wReserved1: u16,
+}
+unsafe impl memory::Pod for DCB {}
+
+impl Default for DCB {
+    /// 9600 baud, 8 bits, no parity, one stop bit.
+    fn default() -> Self {
+        DCB {
+            DCBlength: std::mem::size_of::<DCB>() as u32,
+            BaudRate: 9600,
+            flags: 1, // fBinary
+            wReserved: 0,
+            XonLim: 2048,
+            XoffLim: 512,
+            ByteSize: 8,
+            Parity: 0,
+            StopBits: 0,
+            XonChar: 0x11,
+            XoffChar: 0x13,
+            ErrorChar: 0,
+            EofChar: 0,
+            EvtChar: 0,
+            wReserved1: 0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct COMMTIMEOUTS {
+    pub ReadIntervalTimeout: u32,
+    pub ReadTotalTimeoutMultiplier: u32,
+    pub ReadTotalTimeoutConstant: u32,
+    pub WriteTotalTimeoutMultiplier: u32,
+    pub WriteTotalTimeoutConstant: u32,
+}
+unsafe impl memory::Pod for COMMTIMEOUTS {}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct COMSTAT {
+    /// fCtsHold etc. bitfields.
+    pub flags: u32,
+    pub cbInQue: u32,
+    pub cbOutQue: u32,
+}
+unsafe impl memory::Pod for COMSTAT {}
+
+pub struct Port {
+    pub name: String,
+    host: Box<dyn crate::host::Port>,
+    /// Bytes that arrived from the host but the guest hasn't read yet.
+    input: VecDeque<u8>,
+    dcb: DCB,
+    timeouts: COMMTIMEOUTS,
+}
+
+impl Port {
+    /// Pull in whatever the host has received.
+    fn poll(&mut self) {
+        let mut buf = [0u8; 256];
+        loop {
+            let n = self.host.read(&mut buf);
+            if n == 0 {
+                break;
+            }
+            self.input.extend(&buf[..n]);
+        }
+    }
+
+    pub fn read(&mut self, buf: &mut [u8]) -> u32 {
+        self.poll();
+        let n = std::cmp::min(buf.len(), self.input.len());
+        for (dst, src) in buf.iter_mut().zip(self.input.drain(..n)) {
+            *dst = src;
+        }
+        n as u32
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> u32 {
+        self.host.write(buf) as u32
+    }
+}
+
+pub fn new_ports() -> Handles<HFILE, Port> {
+    Handles::new(0xF11E_0200)
+}
+
+/// Open a port for CreateFile, given a name from port_name.
+pub fn open_port(machine: &mut Machine, name: String) -> HFILE {
+    let Some(host) = machine.host.open_port(&name) else {
+        log::warn!("{name}: no port connected");
+        SetLastError(machine, ERROR_FILE_NOT_FOUND);
+        return HFILE::invalid();
+    };
+    machine.state.kernel32.ports.add(Port {
+        name,
+        host,
+        input: VecDeque::new(),
+        dcb: DCB::default(),
+        timeouts: COMMTIMEOUTS::default(),
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn SetupComm(machine: &mut Machine, hFile: HFILE, dwInQueue: u32, dwOutQueue: u32) -> bool {
+    machine.state.kernel32.ports.get(hFile).is_some()
+}
+
+#[win32_derive::dllexport]
+pub fn GetCommState(machine: &mut Machine, hFile: HFILE, lpDCB: Option<&mut DCB>) -> bool {
+    let Some(port) = machine.state.kernel32.ports.get(hFile) else {
+        return false;
+    };
+    *lpDCB.unwrap() = port.dcb;
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn SetCommState(machine: &mut Machine, hFile: HFILE, lpDCB: Option<&DCB>) -> bool {
+    let Some(port) = machine.state.kernel32.ports.get_mut(hFile) else {
+        return false;
+    };
+    port.dcb = *lpDCB.unwrap();
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn GetCommTimeouts(
+    machine: &mut Machine,
+    hFile: HFILE,
+    lpCommTimeouts: Option<&mut COMMTIMEOUTS>,
+) -> bool {
+    let Some(port) = machine.state.kernel32.ports.get(hFile) else {
+        return false;
+    };
+    *lpCommTimeouts.unwrap() = port.timeouts;
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn SetCommTimeouts(
+    machine: &mut Machine,
+    hFile: HFILE,
+    lpCommTimeouts: Option<&COMMTIMEOUTS>,
+) -> bool {
+    let Some(port) = machine.state.kernel32.ports.get_mut(hFile) else {
+        return false;
+    };
+    port.timeouts = *lpCommTimeouts.unwrap();
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn PurgeComm(machine: &mut Machine, hFile: HFILE, dwFlags: u32) -> bool {
+    const PURGE_RXCLEAR: u32 = 0x8;
+    let Some(port) = machine.state.kernel32.ports.get_mut(hFile) else {
+        return false;
+    };
+    if dwFlags & PURGE_RXCLEAR != 0 {
+        port.poll();
+        port.input.clear();
+    }
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn ClearCommError(
+    machine: &mut Machine,
+    hFile: HFILE,
+    lpErrors: Option<&mut u32>,
+    lpStat: Option<&mut COMSTAT>,
+) -> bool {
+    let Some(port) = machine.state.kernel32.ports.get_mut(hFile) else {
+        return false;
+    };
+    if let Some(errors) = lpErrors {
+        *errors = 0;
+    }
+    if let Some(stat) = lpStat {
+        port.poll();
+        stat.clear_struct();
+        stat.cbInQue = port.input.len() as u32;
+    }
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn EscapeCommFunction(machine: &mut Machine, hFile: HFILE, dwFunc: u32) -> bool {
+    machine.state.kernel32.ports.get(hFile).is_some()
+}
+
+#[win32_derive::dllexport]
+pub fn GetCommModemStatus(
+    machine: &mut Machine,
+    hFile: HFILE,
+    lpModemStat: Option<&mut u32>,
+) -> bool {
+    const MS_CTS_ON: u32 = 0x10;
+    const MS_DSR_ON: u32 = 0x20;
+    const MS_RLSD_ON: u32 = 0x80;
+    if machine.state.kernel32.ports.get(hFile).is_none() {
+        return false;
+    }
+    *lpModemStat.unwrap() = MS_CTS_ON | MS_DSR_ON | MS_RLSD_ON;
+    true
+}
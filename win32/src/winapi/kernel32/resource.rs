@@ -69,15 +69,31 @@ where
     }
 }
 
+/// Find a resource in the UI language, or the closest available.
 pub fn find_resource<'a>(
     kernel32: &kernel32::State,
     mem: Mem<'a>,
     typ: ResourceKey<&Str16>,
     name: ResourceKey<&Str16>,
+) -> Option<Mem<'a>> {
+    find_resource_lang(kernel32, mem, typ, name, kernel32.ui_language)
+}
+
+pub fn find_resource_lang<'a>(
+    kernel32: &kernel32::State,
+    mem: Mem<'a>,
+    typ: ResourceKey<&Str16>,
+    name: ResourceKey<&Str16>,
+    lang: u16,
 ) -> Option<Mem<'a>> {
     let image = mem.slice(kernel32.image_base..);
     let section = kernel32.resources.as_slice(image.as_slice_todo())?;
-    Some(image.slice(pe::find_resource(section, typ.into_pe(), name.into_pe())?))
+    Some(image.slice(pe::find_resource(
+        section,
+        typ.into_pe(),
+        name.into_pe(),
+        lang,
+    )?))
 }
 
 #[win32_derive::dllexport]
@@ -105,6 +121,38 @@ pub fn FindResourceW(
     }
 }
 
+#[win32_derive::dllexport]
+pub fn FindResourceExA(
+    machine: &mut Machine,
+    hModule: u32,
+    lpType: ResourceKey<&str>,
+    lpName: ResourceKey<&str>,
+    wLanguage: u32,
+) -> u32 {
+    let name = lpName.to_string16();
+    let type_ = lpType.to_string16();
+    FindResourceExW(machine, hModule, type_.as_ref(), name.as_ref(), wLanguage)
+}
+
+#[win32_derive::dllexport]
+pub fn FindResourceExW(
+    machine: &mut Machine,
+    hModule: u32,
+    lpType: ResourceKey<&Str16>,
+    lpName: ResourceKey<&Str16>,
+    wLanguage: u32,
+) -> u32 {
+    // LANG_NEUTRAL means the thread's language.
+    let lang = match wLanguage {
+        0 => machine.state.kernel32.ui_language,
+        lang => lang as u16,
+    };
+    match find_resource_lang(&machine.state.kernel32, machine.mem(), lpType, lpName, lang) {
+        None => 0,
+        Some(mem) => mem.offset_from(machine.mem()),
+    }
+}
+
 #[win32_derive::dllexport]
 pub fn LoadResource(_machine: &mut Machine, hModule: u32, hResInfo: u32) -> u32 {
     hResInfo
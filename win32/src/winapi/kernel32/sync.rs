@@ -1,30 +1,171 @@
-//! Synchronization.  Currently all no-ops as we don't support threads.
+//! Synchronization.  We don't support threads, so the only way an event gets
+//! signaled while a wait is underway is by a device like DirectSound, which
+//! winapi::poll_devices advances while we wait.
 
-use crate::{winapi::types::HANDLE, Machine};
+use crate::{
+    machine::Emulator,
+    winapi::{self, handle::Handles, types::HANDLE},
+    Machine,
+};
+use memory::Extensions;
 
-const TRACE_CONTEXT: &'static str = "kernel32/misc";
+const TRACE_CONTEXT: &'static str = "kernel32/sync";
+
+pub struct HEVENTT;
+pub type HEVENT = HANDLE<HEVENTT>;
+
+pub const INFINITE: u32 = 0xFFFF_FFFF;
+pub const WAIT_OBJECT_0: u32 = 0;
+pub const WAIT_TIMEOUT: u32 = 0x102;
+pub const WAIT_FAILED: u32 = 0xFFFF_FFFF;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Event {
+    manual_reset: bool,
+    signaled: bool,
+}
+
+/// Event handles are given their own range so they are easy to tell apart in traces.
+pub fn new_events() -> Handles<HEVENT, Event> {
+    Handles::new(0xE7E0_0001)
+}
+
+/// Signal an event, as SetEvent does.  Returns false for an unknown handle.
+pub fn set_event(machine: &mut Machine, hEvent: HEVENT) -> bool {
+    match machine.state.kernel32.events.get_mut(hEvent) {
+        Some(event) => {
+            event.signaled = true;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Check whether an event is signaled, consuming the signal of an auto-reset event.
+fn try_acquire(machine: &mut Machine, hEvent: HEVENT) -> bool {
+    let Some(event) = machine.state.kernel32.events.get_mut(hEvent) else {
+        return false;
+    };
+    if !event.signaled {
+        return false;
+    }
+    if !event.manual_reset {
+        event.signaled = false;
+    }
+    true
+}
+
+/// Wait until one (or all) of the events is signaled, or the timeout passes.
+async fn wait_for_events(
+    machine: &mut Machine,
+    handles: &[HEVENT],
+    wait_all: bool,
+    dwMilliseconds: u32,
+) -> u32 {
+    if let Some(h) = handles
+        .iter()
+        .find(|&&h| machine.state.kernel32.events.get(h).is_none())
+    {
+        log::warn!("wait on unknown handle {h:x}");
+        return WAIT_FAILED;
+    }
+    let deadline = if dwMilliseconds == INFINITE {
+        None
+    } else {
+        Some(machine.host.time() + dwMilliseconds)
+    };
+
+    loop {
+        let poll = winapi::poll_devices(machine);
+        winapi::run_device_callbacks(machine).await;
+
+        if wait_all {
+            let ev = &machine.state.kernel32.events;
+            if handles.iter().all(|&h| ev.get(h).unwrap().signaled) {
+                for &h in handles {
+                    try_acquire(machine, h);
+                }
+                return WAIT_OBJECT_0;
+            }
+        } else if let Some(i) = handles.iter().position(|&h| try_acquire(machine, h)) {
+            return WAIT_OBJECT_0 + i as u32;
+        }
+
+        if let Some(deadline) = deadline {
+            if machine.host.time() >= deadline {
+                return WAIT_TIMEOUT;
+            }
+        }
+        let until = match (deadline, poll) {
+            (Some(a), Some(b)) => Some(std::cmp::min(a, b)),
+            (a, b) => a.or(b),
+        };
+        if until.is_none() {
+            log::warn!("wait on {handles:x?} would never finish");
+            return WAIT_FAILED;
+        }
+        match machine.emu.block(until) {
+            Some(block) => block.await,
+            None => {
+                machine.host.block(until);
+            }
+        }
+    }
+}
+
+#[win32_derive::dllexport]
+pub async fn WaitForSingleObject(
+    machine: &mut Machine,
+    hHandle: HEVENT,
+    dwMilliseconds: u32,
+) -> u32 {
+    wait_for_events(machine, &[hHandle], false, dwMilliseconds).await
+}
 
 #[win32_derive::dllexport]
-pub fn WaitForSingleObject(
-    _machine: &mut Machine,
-    hHandle: HANDLE<()>,
+pub async fn WaitForMultipleObjects(
+    machine: &mut Machine,
+    nCount: u32,
+    lpHandles: u32,
+    bWaitAll: bool,
     dwMilliseconds: u32,
 ) -> u32 {
-    todo!()
+    let handles = machine
+        .mem()
+        .iter_pod::<HEVENT>(lpHandles, nCount)
+        .collect::<Vec<_>>();
+    wait_for_events(machine, &handles, bWaitAll, dwMilliseconds).await
 }
 
 #[win32_derive::dllexport]
 pub fn CreateEventA(
-    _machine: &mut Machine,
+    machine: &mut Machine,
     lpEventAttributes: u32,
     bManualReset: bool,
     bInitialState: bool,
     lpName: Option<&str>,
-) -> HANDLE<()> {
-    todo!()
+) -> HEVENT {
+    if let Some(name) = lpName {
+        log::warn!("CreateEventA: ignoring name {name:?}");
+    }
+    machine.state.kernel32.events.add(Event {
+        manual_reset: bManualReset,
+        signaled: bInitialState,
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn SetEvent(machine: &mut Machine, hEvent: HEVENT) -> bool {
+    set_event(machine, hEvent)
 }
 
 #[win32_derive::dllexport]
-pub fn SetEvent(_machine: &mut Machine, hEvent: HANDLE<()>) -> bool {
-    todo!()
+pub fn ResetEvent(machine: &mut Machine, hEvent: HEVENT) -> bool {
+    match machine.state.kernel32.events.get_mut(hEvent) {
+        Some(event) => {
+            event.signaled = false;
+            true
+        }
+        None => false,
+    }
 }
@@ -1,6 +1,6 @@
 use super::{peb_mut, teb_mut};
 use crate::{
-    machine::Machine,
+    machine::{Emulator, Machine},
     winapi,
     winapi::types::{Str16, HANDLE},
 };
@@ -19,15 +19,7 @@ pub fn GetCurrentThread(machine: &mut Machine) -> HTHREAD {
 
 #[win32_derive::dllexport]
 pub fn GetCurrentThreadId(machine: &mut Machine) -> u32 {
-    #[cfg(feature = "x86-emu")]
-    {
-        machine.emu.x86.cur_cpu as u32
-    }
-
-    #[cfg(not(feature = "x86-emu"))]
-    {
-        1
-    }
+    machine.emu.current_thread()
 }
 
 #[win32_derive::dllexport]
@@ -72,31 +64,36 @@ pub async fn CreateThread(
     dwCreationFlags: u32,
     lpThreadId: u32,
 ) -> HTHREAD {
-    #[cfg(feature = "x86-emu")]
-    {
-        let retrowin32_thread_main =
-            winapi::kernel32::get_kernel32_builtin(machine, "retrowin32_thread_main");
-
-        let id = 1; // TODO
-        let stack_pointer = machine.create_stack(format!("thread{id} stack"), dwStackSize);
-        let cpu = machine.emu.x86.new_cpu();
-        cpu.regs.set32(x86::Register::ESP, stack_pointer);
-        cpu.regs.set32(x86::Register::EBP, stack_pointer);
-        let mem = machine.emu.memory.mem();
-        x86::ops::push(cpu, mem, lpParameter);
-        x86::ops::push(cpu, mem, lpStartAddress);
-        x86::ops::push(cpu, mem, 0);
-        cpu.regs.eip = retrowin32_thread_main;
-
-        HTHREAD::from_raw(id)
-    }
-
-    #[cfg(not(feature = "x86-emu"))]
-    {
+    if !machine.emu.supports_threads() {
         log::warn!("CreateThread running thread synchronously");
         machine.call_x86(lpStartAddress, vec![lpParameter]).await;
-        HTHREAD::null()
+        return HTHREAD::null();
     }
+
+    let retrowin32_thread_main =
+        winapi::kernel32::get_kernel32_builtin(machine, "retrowin32_thread_main");
+    let stack_desc = format!("thread stack {:x}", lpStartAddress);
+    let stack_pointer = machine.create_stack(stack_desc, dwStackSize);
+    let teb_desc = format!("thread TEB {:x}", lpStartAddress);
+    let teb = machine
+        .state
+        .kernel32
+        .new_teb(&mut machine.emu.memory, stack_pointer, teb_desc);
+    let id = match machine.emu.new_thread(
+        stack_pointer,
+        teb,
+        retrowin32_thread_main,
+        &[lpStartAddress, lpParameter],
+    ) {
+        Ok(id) => id,
+        Err(err) => {
+            log::error!("CreateThread: {err}");
+            return HTHREAD::null();
+        }
+    };
+    let mem = machine.emu.memory.mem();
+    machine.state.kernel32.add_thread(mem, teb, id);
+    HTHREAD::from_raw(id)
 }
 
 #[win32_derive::dllexport]
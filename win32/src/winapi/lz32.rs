@@ -0,0 +1,303 @@
+//! LZExpand: reading files compressed with COMPRESS.EXE, as installers ship them.
+
+#![allow(non_snake_case)]
+
+use super::{kernel32, types::HFILE};
+use crate::machine::Machine;
+use memory::Pod;
+use std::collections::HashMap;
+
+const TRACE_CONTEXT: &'static str = "lz32";
+
+/*
+## LZExpand
+
+Files are read and, if in the SZDD format COMPRESS.EXE writes, expanded whole
+when opened; reads and seeks are then within the expanded data.  Uncompressed
+files read through unchanged, as they do on Windows.
+
+Files opened for writing, as LZCopy's destination is, are written through
+kernel32's in-memory written files (see kernel32::File::create), so the guest
+reads back what it expanded.
+*/
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct State {
+    files: HashMap<i32, LzFile>,
+    next_handle: i32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct LzFile {
+    data: Vec<u8>,
+    pos: usize,
+    /// The path of a file opened for writing.
+    dest: Option<String>,
+}
+
+const LZERROR_BADINHANDLE: i32 = -1;
+const LZERROR_BADOUTHANDLE: i32 = -2;
+const LZERROR_BADVALUE: i32 = -7;
+const LZERROR_UNKNOWNALG: i32 = -8;
+
+/// LZ handles are numbered from here, as on Windows, to stay clear of DOS file handles.
+const LZ_MIN_HANDLE: i32 = 0x400;
+
+const SZDD_MAGIC: &[u8; 8] = b"SZDD\x88\xF0\x27\x33";
+const SZDD_HEADER_LEN: usize = 14;
+
+/// Expand SZDD data, the LZSS variant COMPRESS.EXE writes: a 4K window initially
+/// full of spaces, flag bytes whose bits (low first) mark literals (1) or
+/// back-references (0) of 3 to 18 bytes.
+fn expand_szdd(buf: &[u8]) -> Result<Vec<u8>, i32> {
+    if buf.len() < SZDD_HEADER_LEN || buf[8] != b'A' {
+        return Err(LZERROR_UNKNOWNALG);
+    }
+    let len = u32::from_le_bytes(buf[10..14].try_into().unwrap()) as usize;
+    let mut window = [b' '; 4096];
+    let mut wpos = 4096 - 16;
+    let mut out = Vec::with_capacity(len);
+    let mut input = buf[SZDD_HEADER_LEN..].iter().copied();
+    'data: while let Some(flags) = input.next() {
+        for bit in 0..8 {
+            if flags & (1 << bit) != 0 {
+                let Some(c) = input.next() else {
+                    break 'data;
+                };
+                out.push(c);
+                window[wpos] = c;
+                wpos = (wpos + 1) & 0xFFF;
+            } else {
+                let (Some(lo), Some(hi)) = (input.next(), input.next()) else {
+                    break 'data;
+                };
+                let mut src = lo as usize | ((hi as usize & 0xF0) << 4);
+                for _ in 0..(hi & 0x0F) + 3 {
+                    let c = window[src];
+                    out.push(c);
+                    window[wpos] = c;
+                    wpos = (wpos + 1) & 0xFFF;
+                    src = (src + 1) & 0xFFF;
+                }
+            }
+        }
+    }
+    // The header's length is authoritative over any slop in the last flag group.
+    out.truncate(len);
+    Ok(out)
+}
+
+/// A file's contents, expanded if compressed.
+fn expand(buf: Vec<u8>) -> Result<Vec<u8>, i32> {
+    if buf.starts_with(SZDD_MAGIC) {
+        expand_szdd(&buf)
+    } else {
+        Ok(buf)
+    }
+}
+
+/// The name COMPRESS.EXE gives a compressed file: the last character of the
+/// extension replaced with an underscore.
+fn compressed_name(name: &str) -> String {
+    let base_start = name.rfind(['\\', '/', ':']).map_or(0, |i| i + 1);
+    match name[base_start..].rfind('.') {
+        Some(dot) => {
+            let ext_start = base_start + dot + 1;
+            let ext = &name[ext_start..];
+            if ext.len() < 3 {
+                format!("{name}_")
+            } else {
+                format!("{}_", &name[..name.len() - 1])
+            }
+        }
+        None => format!("{name}._"),
+    }
+}
+
+/// Register an opened file, returning its LZ handle.
+fn add_file(machine: &mut Machine, data: Vec<u8>, dest: Option<String>) -> i32 {
+    let lz32 = &mut machine.state.lz32;
+    let handle = LZ_MIN_HANDLE + lz32.next_handle;
+    lz32.next_handle += 1;
+    lz32.files.insert(handle, LzFile { data, pos: 0, dest });
+    handle
+}
+
+/// Fill in the OFSTRUCT LZOpenFile reports the opened path or error in.
+fn set_ofstruct(ofs: Option<&mut OFSTRUCT>, path: &str, err: u16) {
+    let Some(ofs) = ofs else {
+        return;
+    };
+    ofs.clear_struct();
+    ofs.cBytes = std::mem::size_of::<OFSTRUCT>() as u8;
+    ofs.nErrCode = err;
+    let len = path.len().min(ofs.szPathName.len() - 1);
+    ofs.szPathName[..len].copy_from_slice(&path.as_bytes()[..len]);
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct OFSTRUCT {
+    pub cBytes: u8,
+    pub fFixedDisk: u8,
+    pub nErrCode: u16,
+    pub Reserved1: u16,
+    pub Reserved2: u16,
+    pub szPathName: [u8; 128],
+}
+unsafe impl Pod for OFSTRUCT {}
+
+const OF_READ: u32 = 0x0000;
+const OF_ACCESS_MASK: u32 = 0x0003;
+const OF_CREATE: u32 = 0x1000;
+
+const ERROR_FILE_NOT_FOUND: u16 = 2;
+
+#[win32_derive::dllexport]
+pub fn LZOpenFileA(
+    machine: &mut Machine,
+    lpFileName: Option<&str>,
+    lpReOpenBuf: Option<&mut OFSTRUCT>,
+    wStyle: u32,
+) -> i32 {
+    let Some(name) = lpFileName else {
+        return LZERROR_BADINHANDLE;
+    };
+    if wStyle & OF_ACCESS_MASK != OF_READ || wStyle & OF_CREATE != 0 {
+        // Opened for writing, to be filled by LZCopy.
+        let file = kernel32::File::create(machine, name, wStyle & OF_CREATE != 0);
+        let data = kernel32::written_data(machine, &file.path).clone();
+        set_ofstruct(lpReOpenBuf, name, 0);
+        return add_file(machine, data, Some(file.path));
+    }
+
+    // Try the name given, then the name the file would have compressed.
+    let compressed = compressed_name(name);
+    let path = if kernel32::file_exists(machine, name) {
+        name
+    } else if kernel32::file_exists(machine, &compressed) {
+        compressed.as_str()
+    } else {
+        set_ofstruct(lpReOpenBuf, name, ERROR_FILE_NOT_FOUND);
+        return LZERROR_BADINHANDLE;
+    };
+    let buf = kernel32::read_file(machine, path);
+    set_ofstruct(lpReOpenBuf, path, 0);
+
+    match expand(buf) {
+        Ok(data) => add_file(machine, data, None),
+        Err(err) => err,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn LZInit(machine: &mut Machine, hfSource: HFILE) -> i32 {
+    let Some(file) = machine.state.kernel32.files.get(&hfSource) else {
+        return LZERROR_BADINHANDLE;
+    };
+    let path = file.path.clone();
+    let buf = kernel32::read_file(machine, &path);
+    match expand(buf) {
+        Ok(data) => add_file(machine, data, None),
+        Err(err) => err,
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn LZRead(machine: &mut Machine, hFile: i32, lpBuffer: u32, cbRead: i32) -> i32 {
+    let Some(file) = machine.state.lz32.files.get_mut(&hFile) else {
+        return LZERROR_BADINHANDLE;
+    };
+    if cbRead < 0 {
+        return LZERROR_BADVALUE;
+    }
+    let src = &file.data[file.pos.min(file.data.len())..];
+    let len = src.len().min(cbRead as usize);
+    let mem = machine.emu.memory.mem();
+    mem.sub(lpBuffer, len as u32)
+        .as_mut_slice_todo()
+        .copy_from_slice(&src[..len]);
+    file.pos += len;
+    len as i32
+}
+
+#[win32_derive::dllexport]
+pub fn LZSeek(machine: &mut Machine, hFile: i32, lOffset: i32, iOrigin: i32) -> i32 {
+    let Some(file) = machine.state.lz32.files.get_mut(&hFile) else {
+        return LZERROR_BADINHANDLE;
+    };
+    let base = match iOrigin {
+        0 => 0,
+        1 => file.pos as i64,
+        2 => file.data.len() as i64,
+        _ => return LZERROR_BADVALUE,
+    };
+    let pos = base + lOffset as i64;
+    if pos < 0 || pos > file.data.len() as i64 {
+        return LZERROR_BADVALUE;
+    }
+    file.pos = pos as usize;
+    pos as i32
+}
+
+#[win32_derive::dllexport]
+pub fn LZClose(machine: &mut Machine, hFile: i32) -> u32 {
+    machine.state.lz32.files.remove(&hFile);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn LZCopy(machine: &mut Machine, hfSource: i32, hfDest: i32) -> i32 {
+    let Some(source) = machine.state.lz32.files.get(&hfSource) else {
+        return LZERROR_BADINHANDLE;
+    };
+    let data = source.data.clone();
+    let len = data.len() as i32;
+
+    // The destination is either from LZOpenFile or a plain file handle.
+    if let Some(dest) = machine.state.lz32.files.get_mut(&hfDest) {
+        let Some(path) = dest.dest.clone() else {
+            return LZERROR_BADOUTHANDLE;
+        };
+        dest.data = data.clone();
+        dest.pos = data.len();
+        *kernel32::written_data(machine, &path) = data;
+        return len;
+    }
+    let hfile = HFILE::from_raw(hfDest as u32);
+    let Some(file) = machine.state.kernel32.files.get_mut(&hfile) else {
+        return LZERROR_BADOUTHANDLE;
+    };
+    if !file.write(&mut machine.state.kernel32.written_files, &data) {
+        return LZERROR_BADOUTHANDLE;
+    }
+    len
+}
+
+#[win32_derive::dllexport]
+pub fn GetExpandedNameA(machine: &mut Machine, lpszSource: Option<&str>, lpszBuffer: u32) -> i32 {
+    let Some(source) = lpszSource else {
+        return LZERROR_BADINHANDLE;
+    };
+    // The original name's last character is kept in the header.
+    let buf = kernel32::read_file(machine, source);
+    let mut name = source.to_string();
+    if buf.len() >= SZDD_HEADER_LEN && buf.starts_with(SZDD_MAGIC) && name.ends_with('_') {
+        name.pop();
+        match buf[9] {
+            0 => {
+                if name.ends_with('.') {
+                    name.pop();
+                }
+            }
+            c => name.push(c as char),
+        }
+    }
+    name.push('\0');
+    machine
+        .mem()
+        .sub(lpszBuffer, name.len() as u32)
+        .as_mut_slice_todo()
+        .copy_from_slice(name.as_bytes());
+    1
+}
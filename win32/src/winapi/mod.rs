@@ -1,24 +1,43 @@
-mod advapi32;
+pub mod advapi32;
 mod alloc;
+mod avi;
+pub mod avifil32;
 mod bass;
 mod bitmap;
 mod builtin;
+mod com;
+mod comctl32;
+mod comdlg32;
 pub mod ddraw;
+pub mod dinput;
+mod dinput8;
 pub mod dsound;
 pub mod gdi32;
+mod glide;
+mod glide2x;
+mod glide3x;
 mod handle;
 mod heap;
+mod imm32;
 pub mod kernel32;
+mod lz32;
+pub mod msvcrt;
 mod ntdll;
 mod ole32;
 mod oleaut32;
+pub mod opengl32;
+pub mod quartz;
+mod raster;
 mod retrowin32_test;
+pub mod shell32;
 mod stack_args;
 pub mod types;
 mod ucrtbase;
 pub mod user32;
 mod vcruntime140;
-mod winmm;
+mod version;
+pub mod winmm;
+pub mod ws2_32;
 
 macro_rules! vtable_entry {
     ($shims:expr, $module:ident $fn:ident todo) => {
@@ -58,6 +77,26 @@ macro_rules! vtable {
 }
 pub(crate) use vtable;
 
+/// Snapshots record bitflags by their bits.
+macro_rules! serde_bitflags {
+    ($flags:ident) => {
+        impl serde::Serialize for $flags {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serde::Serialize::serialize(&self.bits(), serializer)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $flags {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                Ok(Self::from_bits_truncate(serde::Deserialize::deserialize(
+                    deserializer,
+                )?))
+            }
+        }
+    };
+}
+pub(crate) use serde_bitflags;
+
 #[derive(Debug)]
 pub enum ImportSymbol<'a> {
     Name(&'a str),
@@ -72,53 +111,196 @@ impl<'a> std::fmt::Display for ImportSymbol<'a> {
     }
 }
 
-pub const DLLS: [builtin::BuiltinDLL; 14] = [
+pub const DLLS: [builtin::BuiltinDLL; 29] = [
     builtin::advapi32::DLL,
+    builtin::avifil32::DLL,
     builtin::bass::DLL,
+    builtin::comctl32::DLL,
+    builtin::comdlg32::DLL,
     builtin::ddraw::DLL,
+    builtin::dinput::DLL,
+    builtin::dinput8::DLL,
     builtin::dsound::DLL,
     builtin::gdi32::DLL,
+    builtin::glide2x::DLL,
+    builtin::glide3x::DLL,
+    builtin::imm32::DLL,
     builtin::kernel32::DLL,
+    builtin::lz32::DLL,
+    builtin::msvcrt::DLL,
     builtin::ntdll::DLL,
     builtin::ole32::DLL,
     builtin::oleaut32::DLL,
+    builtin::opengl32::DLL,
+    builtin::quartz::DLL,
+    builtin::shell32::DLL,
     builtin::ucrtbase::DLL,
     builtin::user32::DLL,
     builtin::vcruntime140::DLL,
+    builtin::version::DLL,
     builtin::winmm::DLL,
+    builtin::ws2_32::DLL,
     builtin::retrowin32_test::DLL,
 ];
 
 /// Maps a DLL "api set" alias to the underlying dll.
 /// https://learn.microsoft.com/en-us/windows/win32/apiindex/api-set-loader-operation
+/// Also maps DLLs that are wholly forwarded to another, like wsock32 to ws2_32.
 pub fn apiset(name: &str) -> Option<&'static str> {
     Some(match name {
         "api-ms-win-crt-runtime-l1-1-0.dll" => "ucrtbase.dll",
+        "api-ms-win-crt-string-l1-1-0.dll" => "ucrtbase.dll",
+        "crtdll.dll" => "msvcrt.dll",
+        "wsock32.dll" => "ws2_32.dll",
         _ => return None,
     })
 }
 
+/// Advance devices that run against the host clock rather than guest code,
+/// like DirectSound playback, which may signal events as it goes.
+/// Called whenever the guest waits or pumps messages.  Returns the host time
+/// by which it wants to be called again, if any.
+pub fn poll_devices(machine: &mut crate::Machine) -> Option<u32> {
+    [
+        dsound::update(machine),
+        quartz::update(machine),
+        winmm::update(machine),
+        ws2_32::update(machine),
+    ]
+    .into_iter()
+    .flatten()
+    .min()
+}
+
+/// Gather the contents of every surface that can reach the screen, for a frame dump.
+pub fn dump_surfaces(machine: &crate::Machine) -> Vec<crate::framedump::SurfaceDump> {
+    [
+        ddraw::dump_surfaces(machine),
+        user32::dump_windows(machine),
+        opengl32::dump_framebuffers(machine),
+        glide::dump_framebuffer(machine),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Run guest callbacks that devices queued while polled, which must wait until
+/// a point where guest code can run.
+pub async fn run_device_callbacks(machine: &mut crate::Machine) {
+    winmm::run_callbacks(machine).await;
+}
+
+/// Stands in for a host window or surface in state loaded from a snapshot, until
+/// restore recreates the real one.
+struct Detached;
+
+impl crate::host::Window for Detached {
+    fn set_title(&mut self, _title: &str) {}
+    fn set_icon(&mut self, _icon: &crate::host::Icon) {}
+    fn set_size(&mut self, _width: u32, _height: u32) {}
+    fn set_fullscreen(&mut self, _fullscreen: bool) {}
+}
+
+impl crate::host::Surface for Detached {
+    fn write_pixels(&mut self, _pixels: &[[u8; 4]]) {}
+    fn show(&mut self) {}
+    fn bit_blt(
+        &mut self,
+        _dx: u32,
+        _dy: u32,
+        _src: &dyn crate::host::Surface,
+        _sx: u32,
+        _sy: u32,
+        _w: u32,
+        _h: u32,
+    ) {
+    }
+}
+
+pub fn detached_window() -> Box<dyn crate::host::Window> {
+    Box::new(Detached)
+}
+
+pub fn detached_surface() -> Box<dyn crate::host::Surface> {
+    Box::new(Detached)
+}
+
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct State {
-    #[serde(skip)] // TODO
+    pub advapi32: advapi32::State,
+    pub avifil32: avifil32::State,
+    pub comctl32: comctl32::State,
+    pub comdlg32: comdlg32::State,
     pub ddraw: ddraw::State,
-    #[serde(skip)] // TODO
+    pub dinput: dinput::State,
     pub dsound: dsound::State,
-    #[serde(skip)] // TODO
     pub gdi32: gdi32::State,
+    pub glide: glide::State,
+    pub imm32: imm32::State,
     pub kernel32: kernel32::State,
-    #[serde(skip)] // TODO
+    pub lz32: lz32::State,
+    pub msvcrt: msvcrt::State,
+    pub ole32: ole32::State,
+    pub opengl32: opengl32::State,
+    pub quartz: quartz::State,
     pub user32: user32::State,
+    pub winmm: winmm::State,
+    pub ws2_32: ws2_32::State,
 }
 
 impl State {
     pub fn new(kernel32: kernel32::State) -> Self {
         State {
+            advapi32: advapi32::State::default(),
+            avifil32: avifil32::State::default(),
+            comctl32: comctl32::State::default(),
+            comdlg32: comdlg32::State::default(),
             ddraw: ddraw::State::default(),
+            dinput: dinput::State::default(),
             dsound: dsound::State::default(),
             gdi32: gdi32::State::default(),
+            glide: glide::State::default(),
+            imm32: imm32::State::default(),
             kernel32,
+            lz32: lz32::State::default(),
+            msvcrt: msvcrt::State::default(),
+            ole32: ole32::State::default(),
+            opengl32: opengl32::State::default(),
+            quartz: quartz::State::default(),
             user32: user32::State::default(),
+            winmm: winmm::State::default(),
+            ws2_32: ws2_32::State::default(),
         }
     }
+
+    /// Take the state recorded in a snapshot, keeping this session's host
+    /// configuration and input, and the host-side resources kernel32 holds.
+    /// The host objects of the recorded state are recreated by restore_host_objects.
+    pub fn restore(&mut self, saved: State) {
+        let live = std::mem::replace(self, saved);
+        self.kernel32.ports = live.kernel32.ports;
+        #[cfg(feature = "x86-64")]
+        {
+            self.kernel32.ldt = live.kernel32.ldt;
+        }
+        self.ddraw.video_memory = live.ddraw.video_memory;
+        self.ddraw.gpu = live.ddraw.gpu;
+        self.user32.background = live.user32.background;
+        self.user32.controllers = live.user32.controllers;
+        self.user32.input = live.user32.input;
+        self.winmm.cd_audio = live.winmm.cd_audio;
+        self.winmm.joystick_map = live.winmm.joystick_map;
+    }
+}
+
+/// After a snapshot is restored, recreate the host windows, surfaces and streams
+/// the recorded state refers to.  Windows go first, as DirectDraw shows on them.
+pub fn restore_host_objects(machine: &mut crate::Machine) {
+    user32::restore(machine);
+    ddraw::restore(machine);
+    opengl32::restore(machine);
+    glide::restore(machine);
+    dsound::restore(machine);
+    dinput::restore(machine);
 }
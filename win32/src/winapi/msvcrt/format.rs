@@ -0,0 +1,319 @@
+//! Formatting for the printf family.
+
+use memory::{Extensions, Mem};
+use std::iter::Peekable;
+
+/// Arguments as laid out on the stack, as a va_list points at them.
+struct Args<'a> {
+    mem: Mem<'a>,
+    addr: u32,
+}
+
+impl<'a> Args<'a> {
+    fn u32(&mut self) -> u32 {
+        let val = self.mem.get_pod::<u32>(self.addr);
+        self.addr += 4;
+        val
+    }
+
+    fn u64(&mut self) -> u64 {
+        let val = self.mem.get_pod::<u64>(self.addr);
+        self.addr += 8;
+        val
+    }
+
+    fn f64(&mut self) -> f64 {
+        f64::from_bits(self.u64())
+    }
+}
+
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alt: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    /// Lay out a converted value with its sign/prefix, padded to the width.
+    fn pad(&self, out: &mut Vec<u8>, prefix: &[u8], body: &[u8], zero_ok: bool) {
+        let len = prefix.len() + body.len();
+        let fill = self.width.saturating_sub(len);
+        if self.left {
+            out.extend_from_slice(prefix);
+            out.extend_from_slice(body);
+            out.resize(out.len() + fill, b' ');
+        } else if self.zero && zero_ok {
+            out.extend_from_slice(prefix);
+            out.resize(out.len() + fill, b'0');
+            out.extend_from_slice(body);
+        } else {
+            out.resize(out.len() + fill, b' ');
+            out.extend_from_slice(prefix);
+            out.extend_from_slice(body);
+        }
+    }
+
+    fn sign(&self, negative: bool) -> &'static [u8] {
+        if negative {
+            b"-"
+        } else if self.plus {
+            b"+"
+        } else if self.space {
+            b" "
+        } else {
+            b""
+        }
+    }
+
+    /// Integers: digits, padded with zeros to the precision.
+    fn int(&self, out: &mut Vec<u8>, negative: bool, prefix: &[u8], digits: String) {
+        let mut body = digits.into_bytes();
+        if let Some(precision) = self.precision {
+            if precision == 0 && body == b"0" {
+                body.clear();
+            } else if body.len() < precision {
+                body.splice(0..0, std::iter::repeat(b'0').take(precision - body.len()));
+            }
+        }
+        let mut full = self.sign(negative).to_vec();
+        full.extend_from_slice(prefix);
+        self.pad(out, &full, &body, self.precision.is_none());
+    }
+
+    fn float(&self, out: &mut Vec<u8>, val: f64, conv: u8) {
+        let sign = self.sign(val.is_sign_negative() && !val.is_nan());
+        if !val.is_finite() {
+            let body: &[u8] = if val.is_nan() { b"1.#QNAN" } else { b"1.#INF" };
+            return self.pad(out, sign, body, false);
+        }
+        let val = val.abs();
+        let precision = self.precision.unwrap_or(6);
+        let mut body = match conv.to_ascii_lowercase() {
+            b'f' => format!("{val:.precision$}"),
+            b'e' => exponent(val, precision),
+            _ => {
+                // %g: %e if the exponent is small or large, else %f, with the
+                // precision counting significant digits.
+                let precision = precision.max(1);
+                let exp = exponent(val, precision - 1);
+                let x: i32 = exp[exp.find('e').unwrap() + 1..].parse().unwrap();
+                let mut body = if x < -4 || x >= precision as i32 {
+                    exp
+                } else {
+                    format!("{val:.*}", (precision as i32 - 1 - x) as usize)
+                };
+                if !self.alt && body.contains('.') {
+                    // Trim trailing zeros from the fraction.
+                    let e = body.find('e').unwrap_or(body.len());
+                    let mantissa = body[..e].trim_end_matches('0').trim_end_matches('.');
+                    body = format!("{mantissa}{}", &body[e..]);
+                }
+                body
+            }
+        };
+        if self.alt && !body.contains('.') {
+            let e = body.find('e').unwrap_or(body.len());
+            body.insert(e, '.');
+        }
+        if conv.is_ascii_uppercase() {
+            body.make_ascii_uppercase();
+        }
+        self.pad(out, sign, body.as_bytes(), true);
+    }
+}
+
+/// Format as %e does, with msvcrt's three digit exponent: 1.500000e+002.
+fn exponent(val: f64, precision: usize) -> String {
+    let s = format!("{val:.precision$e}");
+    let (mantissa, exp) = s.split_once('e').unwrap();
+    let exp: i32 = exp.parse().unwrap();
+    let sign = if exp < 0 { '-' } else { '+' };
+    format!("{mantissa}e{sign}{:03}", exp.abs())
+}
+
+/// A width or precision: digits, or * to take it from the arguments.
+fn number(i: &mut Peekable<impl Iterator<Item = u8>>, args: &mut Args) -> i32 {
+    if i.next_if_eq(&b'*').is_some() {
+        return args.u32() as i32;
+    }
+    let mut n = 0i32;
+    while let Some(c) = i.next_if(u8::is_ascii_digit) {
+        n = n.saturating_mul(10).saturating_add((c - b'0') as i32);
+    }
+    n
+}
+
+/// Format a printf format string with the arguments args points at.
+pub fn printf(mem: Mem, fmt: &[u8], args: u32) -> Vec<u8> {
+    let mut args = Args { mem, addr: args };
+    let mut out = Vec::new();
+    let mut i = fmt.iter().copied().peekable();
+    while let Some(c) = i.next() {
+        if c != b'%' {
+            out.push(c);
+            continue;
+        }
+
+        let mut spec = Spec::default();
+        while let Some(c) = i.next_if(|c| b"-+ #0".contains(c)) {
+            match c {
+                b'-' => spec.left = true,
+                b'+' => spec.plus = true,
+                b' ' => spec.space = true,
+                b'#' => spec.alt = true,
+                _ => spec.zero = true,
+            }
+        }
+        let width = number(&mut i, &mut args);
+        if width < 0 {
+            spec.left = true;
+        }
+        spec.width = width.unsigned_abs() as usize;
+        if i.next_if_eq(&b'.').is_some() {
+            let precision = number(&mut i, &mut args);
+            // A negative precision from * is as if none were given.
+            spec.precision = (precision >= 0).then_some(precision as usize);
+        }
+
+        // Length modifiers: only the ones that change the argument size matter.
+        let mut wide = false;
+        let mut long_long = false;
+        let mut short = false;
+        loop {
+            match i.peek() {
+                Some(b'h') => short = true,
+                Some(b'l') | Some(b'w') => {
+                    wide = true;
+                    i.next();
+                    if i.next_if_eq(&b'l').is_some() {
+                        long_long = true;
+                    }
+                    continue;
+                }
+                Some(b'L') => {}
+                Some(b'I') => {
+                    i.next();
+                    if i.next_if_eq(&b'6').is_some() && i.next_if_eq(&b'4').is_some() {
+                        long_long = true;
+                    } else if i.next_if_eq(&b'3').is_some() {
+                        i.next_if_eq(&b'2');
+                    }
+                    continue;
+                }
+                _ => break,
+            }
+            i.next();
+        }
+
+        let Some(conv) = i.next() else {
+            break;
+        };
+        match conv {
+            b'd' | b'i' => {
+                let val = if long_long {
+                    args.u64() as i64
+                } else if short {
+                    args.u32() as i16 as i64
+                } else {
+                    args.u32() as i32 as i64
+                };
+                spec.int(&mut out, val < 0, b"", val.unsigned_abs().to_string());
+            }
+            b'u' | b'x' | b'X' | b'o' => {
+                let val = if long_long {
+                    args.u64()
+                } else if short {
+                    args.u32() as u16 as u64
+                } else {
+                    args.u32() as u64
+                };
+                let (prefix, digits): (&[u8], String) = match conv {
+                    b'u' => (b"", val.to_string()),
+                    b'x' => (
+                        if spec.alt && val != 0 { b"0x" } else { b"" },
+                        format!("{val:x}"),
+                    ),
+                    b'X' => (
+                        if spec.alt && val != 0 { b"0X" } else { b"" },
+                        format!("{val:X}"),
+                    ),
+                    _ => (
+                        if spec.alt && val != 0 { b"0" } else { b"" },
+                        format!("{val:o}"),
+                    ),
+                };
+                spec.int(&mut out, false, prefix, digits);
+            }
+            b'p' => {
+                let val = args.u32();
+                spec.precision = None;
+                spec.pad(&mut out, b"", format!("{val:08X}").as_bytes(), false);
+            }
+            b'c' | b'C' => {
+                let val = args.u32();
+                let c = if wide || conv == b'C' {
+                    char::from_u32(val & 0xFFFF).map_or(b'?', |c| {
+                        if c.is_ascii() {
+                            c as u8
+                        } else {
+                            b'?'
+                        }
+                    })
+                } else {
+                    val as u8
+                };
+                spec.pad(&mut out, b"", &[c], false);
+            }
+            b's' | b'S' => {
+                let addr = args.u32();
+                let mut str = if addr == 0 {
+                    b"(null)".to_vec()
+                } else if wide || conv == b'S' {
+                    let mut units = Vec::new();
+                    let mut addr = addr;
+                    loop {
+                        let unit = mem.get_pod::<u16>(addr);
+                        if unit == 0 || spec.precision.is_some_and(|p| units.len() >= p) {
+                            break;
+                        }
+                        units.push(unit);
+                        addr += 2;
+                    }
+                    String::from_utf16_lossy(&units).into_bytes()
+                } else {
+                    match spec.precision {
+                        // With a precision, the string needn't be nul-terminated.
+                        Some(precision) => {
+                            let str = mem.sub(addr, precision as u32).as_slice_todo();
+                            str[..str.iter().position(|&c| c == 0).unwrap_or(str.len())].to_vec()
+                        }
+                        None => mem.slicez(addr).to_vec(),
+                    }
+                };
+                if let Some(precision) = spec.precision {
+                    str.truncate(precision);
+                }
+                spec.pad(&mut out, b"", &str, false);
+            }
+            b'f' | b'F' | b'e' | b'E' | b'g' | b'G' => {
+                let val = args.f64();
+                spec.float(&mut out, val, conv);
+            }
+            b'n' => {
+                let addr = args.u32();
+                mem.put::<u32>(addr, out.len() as u32);
+            }
+            b'%' => out.push(b'%'),
+            _ => {
+                log::warn!("printf: unknown conversion {:?}", conv as char);
+                out.push(conv);
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,82 @@
+//! malloc and friends, on the process heap.
+
+use crate::machine::Machine;
+
+const TRACE_CONTEXT: &'static str = "msvcrt/heap";
+
+/// Copy bytes into a new block on the process heap.
+pub fn alloc_bytes(machine: &mut Machine, bytes: &[u8]) -> u32 {
+    let addr = malloc(machine, bytes.len() as u32);
+    machine
+        .mem()
+        .sub(addr, bytes.len() as u32)
+        .as_mut_slice_todo()
+        .copy_from_slice(bytes);
+    addr
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn malloc(machine: &mut Machine, size: u32) -> u32 {
+    let heap = machine
+        .state
+        .kernel32
+        .get_process_heap(&mut machine.emu.memory);
+    heap.alloc(machine.emu.memory.mem(), size)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn calloc(machine: &mut Machine, count: u32, size: u32) -> u32 {
+    let Some(len) = count.checked_mul(size) else {
+        return 0;
+    };
+    let addr = malloc(machine, len);
+    if addr != 0 {
+        machine.mem().sub(addr, len).as_mut_slice_todo().fill(0);
+    }
+    addr
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn free(machine: &mut Machine, ptr: u32) -> u32 {
+    if ptr == 0 {
+        return 0;
+    }
+    let heap = machine
+        .state
+        .kernel32
+        .get_process_heap(&mut machine.emu.memory);
+    heap.free(machine.emu.memory.mem(), ptr);
+    0
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _msize(machine: &mut Machine, ptr: u32) -> u32 {
+    let heap = machine
+        .state
+        .kernel32
+        .get_process_heap(&mut machine.emu.memory);
+    heap.size(machine.emu.memory.mem(), ptr)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn realloc(machine: &mut Machine, ptr: u32, size: u32) -> u32 {
+    if ptr == 0 {
+        return malloc(machine, size);
+    }
+    if size == 0 {
+        free(machine, ptr);
+        return 0;
+    }
+    let old_size = _msize(machine, ptr);
+    let new_addr = malloc(machine, size);
+    if new_addr == 0 {
+        return 0; // the old block is left alone
+    }
+    let len = std::cmp::min(old_size, size) as usize;
+    machine
+        .mem()
+        .as_mut_slice_todo()
+        .copy_within(ptr as usize..ptr as usize + len, new_addr as usize);
+    free(machine, ptr);
+    new_addr
+}
@@ -0,0 +1,288 @@
+//! Startup and shutdown: the arguments main() gets, static initializers, atexit.
+
+use super::{heap::alloc_bytes, stdio};
+use crate::{
+    machine::{Emulator, Machine},
+    winapi::kernel32::{self, ExitProcess},
+};
+use memory::Extensions;
+
+const TRACE_CONTEXT: &'static str = "msvcrt/init";
+
+pub const _CW_DEFAULT: u32 = 0x0009_001F;
+
+/// Addresses of the CRT's global variables in guest memory.
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct Globals {
+    argc: u32,
+    argv: u32,
+    environ: u32,
+    acmdln: u32,
+    fmode: u32,
+    commode: u32,
+    pub errno: u32,
+    /// stdin, stdout and stderr, as __iob_func returns.
+    pub iob: u32,
+}
+
+/// Split a command line into arguments as the CRT does: on spaces outside quotes,
+/// with backslashes escaping quotes.
+fn split_args(cmdline: &[u8]) -> Vec<Vec<u8>> {
+    let mut args = Vec::new();
+    let mut i = cmdline.iter().copied().peekable();
+    loop {
+        while i.next_if(|&c| c == b' ' || c == b'\t').is_some() {}
+        if i.peek().is_none() {
+            break;
+        }
+        let mut arg = Vec::new();
+        let mut quoted = false;
+        while let Some(c) = i.next() {
+            match c {
+                b'\\' => {
+                    let mut slashes = 1;
+                    while i.next_if_eq(&b'\\').is_some() {
+                        slashes += 1;
+                    }
+                    if i.peek() == Some(&b'"') {
+                        arg.resize(arg.len() + slashes / 2, b'\\');
+                        if slashes % 2 == 1 {
+                            arg.push(b'"');
+                            i.next();
+                        }
+                    } else {
+                        arg.resize(arg.len() + slashes, b'\\');
+                    }
+                }
+                b'"' => quoted = !quoted,
+                b' ' | b'\t' if !quoted => break,
+                c => arg.push(c),
+            }
+        }
+        args.push(arg);
+    }
+    args
+}
+
+/// Copy strings into guest memory as a null-terminated array of pointers.
+fn put_str_array(machine: &mut Machine, strs: &[Vec<u8>]) -> u32 {
+    let array = alloc_bytes(machine, &vec![0u8; (strs.len() + 1) * 4]);
+    for (i, str) in strs.iter().enumerate() {
+        let mut str = str.clone();
+        str.push(0);
+        let addr = alloc_bytes(machine, &str);
+        machine.mem().put::<u32>(array + i as u32 * 4, addr);
+    }
+    array
+}
+
+/// The CRT's globals, set up on first use.
+pub fn globals(machine: &mut Machine) -> Globals {
+    if let Some(globals) = machine.state.msvcrt.globals {
+        return globals;
+    }
+
+    let cmdline = kernel32::GetCommandLineA(machine);
+    let args = split_args(machine.mem().slicez(cmdline));
+    let argv = put_str_array(machine, &args);
+
+    // The environment block is a run of nul-terminated strings, ending with an empty one.
+    let env_block = kernel32::GetEnvironmentStrings(machine);
+    let mut env = Vec::new();
+    let mut addr = env_block;
+    loop {
+        let var = machine.mem().slicez(addr).to_vec();
+        if var.is_empty() {
+            break;
+        }
+        addr += var.len() as u32 + 1;
+        env.push(var);
+    }
+    let environ = put_str_array(machine, &env);
+
+    let vars = alloc_bytes(machine, &[0u8; 7 * 4]);
+    let iob = alloc_bytes(machine, &[0u8; 3 * stdio::FILE_SIZE as usize]);
+    let globals = Globals {
+        argc: vars,
+        argv: vars + 4,
+        environ: vars + 8,
+        acmdln: vars + 12,
+        fmode: vars + 16,
+        commode: vars + 20,
+        errno: vars + 24,
+        iob,
+    };
+    let mem = machine.mem();
+    mem.put::<u32>(globals.argc, args.len() as u32);
+    mem.put::<u32>(globals.argv, argv);
+    mem.put::<u32>(globals.environ, environ);
+    mem.put::<u32>(globals.acmdln, cmdline);
+    machine.state.msvcrt.globals = Some(globals);
+    stdio::init_std_streams(machine, iob);
+    globals
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn __getmainargs(
+    machine: &mut Machine,
+    argc: u32,
+    argv: u32,
+    env: u32,
+    doWildCard: u32,
+    startInfo: u32,
+) -> u32 {
+    let globals = globals(machine);
+    let mem = machine.mem();
+    mem.put::<u32>(argc, mem.get_pod::<u32>(globals.argc));
+    mem.put::<u32>(argv, mem.get_pod::<u32>(globals.argv));
+    mem.put::<u32>(env, mem.get_pod::<u32>(globals.environ));
+    0
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn __p___argc(machine: &mut Machine) -> u32 {
+    globals(machine).argc
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn __p___argv(machine: &mut Machine) -> u32 {
+    globals(machine).argv
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn __p__environ(machine: &mut Machine) -> u32 {
+    globals(machine).environ
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn __p__acmdln(machine: &mut Machine) -> u32 {
+    globals(machine).acmdln
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn __p__fmode(machine: &mut Machine) -> u32 {
+    globals(machine).fmode
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn __p__commode(machine: &mut Machine) -> u32 {
+    globals(machine).commode
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _errno(machine: &mut Machine) -> u32 {
+    globals(machine).errno
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn __set_app_type(_machine: &mut Machine, at: u32) -> u32 {
+    0
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn __setusermatherr(_machine: &mut Machine, pf: u32) -> u32 {
+    0
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _XcptFilter(_machine: &mut Machine, xcptnum: u32, pxcptinfoptrs: u32) -> u32 {
+    0 // EXCEPTION_CONTINUE_SEARCH
+}
+
+/// The x87 control word for a _controlfp one, whose bits are laid out differently.
+fn x87_control_word(cw: u32) -> u16 {
+    // _EM_* exception masks, in the order of the x87's mask bits.
+    const EM: [u32; 6] = [0x10, 0x8_0000, 0x08, 0x04, 0x02, 0x01];
+    let masks = EM
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (bit, &em)| acc | (((cw & em != 0) as u16) << bit));
+    let precision = match cw & 0x3_0000 {
+        0x0_0000 => 3, // _PC_64
+        0x1_0000 => 2, // _PC_53
+        _ => 0,        // _PC_24
+    };
+    // _RC_* match the x87's rounding control, just placed lower.
+    let rounding = ((cw >> 8) & 3) as u16;
+    masks | 0x40 | precision << 8 | rounding << 10
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _controlfp(machine: &mut Machine, new: u32, mask: u32) -> u32 {
+    let msvcrt = &mut machine.state.msvcrt;
+    msvcrt.control_word = (msvcrt.control_word & !mask) | (new & mask);
+    let cw = msvcrt.control_word;
+    machine.emu.fpu_set_control(x87_control_word(cw));
+    cw
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _control87(machine: &mut Machine, new: u32, mask: u32) -> u32 {
+    _controlfp(machine, new, mask)
+}
+
+/// Call each function in a table of function pointers, skipping nulls.
+#[win32_derive::dllexport(cdecl)]
+pub async fn _initterm(machine: &mut Machine, start: u32, end: u32) -> u32 {
+    for addr in (start..end).step_by(4) {
+        let func = machine.mem().get_pod::<u32>(addr);
+        if func != 0 {
+            machine.call_x86(func, vec![]).await;
+        }
+    }
+    0
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub async fn _initterm_e(machine: &mut Machine, start: u32, end: u32) -> u32 {
+    // The initializers' error codes are lost, so this always succeeds.
+    _initterm(machine, start, end).await
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn atexit(machine: &mut Machine, func: u32) -> u32 {
+    machine.state.msvcrt.atexit.push(func);
+    0
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _onexit(machine: &mut Machine, func: u32) -> u32 {
+    machine.state.msvcrt.atexit.push(func);
+    func
+}
+
+/// Run the atexit functions, last registered first.
+async fn run_atexit(machine: &mut Machine) {
+    while let Some(func) = machine.state.msvcrt.atexit.pop() {
+        machine.call_x86(func, vec![]).await;
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub async fn exit(machine: &mut Machine, status: u32) -> u32 {
+    run_atexit(machine).await;
+    ExitProcess(machine, status)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub async fn _cexit(machine: &mut Machine) -> u32 {
+    run_atexit(machine).await;
+    0
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _exit(machine: &mut Machine, status: u32) -> u32 {
+    ExitProcess(machine, status)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _amsg_exit(machine: &mut Machine, rterrnum: u32) -> u32 {
+    log::error!("_amsg_exit({rterrnum})");
+    ExitProcess(machine, 255)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn abort(machine: &mut Machine) -> u32 {
+    log::error!("abort()");
+    ExitProcess(machine, 3)
+}
@@ -0,0 +1,292 @@
+//! Math routines, and rand.
+//!
+//! Floating point results are returned on the x87 stack, as the compiler expects.
+//! The _CI functions are the intrinsic forms, which take their arguments there too.
+
+use crate::machine::{Emulator, Machine};
+use memory::Extensions;
+
+const TRACE_CONTEXT: &'static str = "msvcrt/math";
+
+/// Return a floating point value, in ST(0).
+fn ret(machine: &mut Machine, val: f64) -> u32 {
+    if let Err(err) = machine.emu.fpu_push(val) {
+        log::error!("returning {val}: {err}");
+    }
+    0
+}
+
+/// Pop an argument from ST(0), or NaN if the x87 stack can't be reached.
+fn pop(machine: &mut Machine) -> f64 {
+    machine.emu.fpu_pop().unwrap_or_else(|err| {
+        log::error!("x87 argument: {err}");
+        f64::NAN
+    })
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn sin(machine: &mut Machine, x: f64) -> u32 {
+    ret(machine, x.sin())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn cos(machine: &mut Machine, x: f64) -> u32 {
+    ret(machine, x.cos())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn tan(machine: &mut Machine, x: f64) -> u32 {
+    ret(machine, x.tan())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn asin(machine: &mut Machine, x: f64) -> u32 {
+    ret(machine, x.asin())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn acos(machine: &mut Machine, x: f64) -> u32 {
+    ret(machine, x.acos())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn atan(machine: &mut Machine, x: f64) -> u32 {
+    ret(machine, x.atan())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn atan2(machine: &mut Machine, y: f64, x: f64) -> u32 {
+    ret(machine, y.atan2(x))
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn sqrt(machine: &mut Machine, x: f64) -> u32 {
+    ret(machine, x.sqrt())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn pow(machine: &mut Machine, x: f64, y: f64) -> u32 {
+    ret(machine, x.powf(y))
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn exp(machine: &mut Machine, x: f64) -> u32 {
+    ret(machine, x.exp())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn log(machine: &mut Machine, x: f64) -> u32 {
+    ret(machine, x.ln())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn log10(machine: &mut Machine, x: f64) -> u32 {
+    ret(machine, x.log10())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn floor(machine: &mut Machine, x: f64) -> u32 {
+    ret(machine, x.floor())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn ceil(machine: &mut Machine, x: f64) -> u32 {
+    ret(machine, x.ceil())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn fabs(machine: &mut Machine, x: f64) -> u32 {
+    ret(machine, x.abs())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn fmod(machine: &mut Machine, x: f64, y: f64) -> u32 {
+    // Rust's % on floats is C's fmod.
+    ret(machine, x % y)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn ldexp(machine: &mut Machine, x: f64, exp: i32) -> u32 {
+    ret(machine, x * 2f64.powi(exp))
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn modf(machine: &mut Machine, x: f64, intptr: u32) -> u32 {
+    machine.mem().put::<u64>(intptr, x.trunc().to_bits());
+    ret(machine, x.fract())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _isnan(_machine: &mut Machine, x: f64) -> bool {
+    x.is_nan()
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _finite(_machine: &mut Machine, x: f64) -> bool {
+    x.is_finite()
+}
+
+/// The length of the floating point number at the start of str, after whitespace.
+fn float_len(str: &[u8]) -> usize {
+    let mut i = str.iter().take_while(|c| c.is_ascii_whitespace()).count();
+    if matches!(str.get(i), Some(b'-' | b'+')) {
+        i += 1;
+    }
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while str.get(*i).is_some_and(u8::is_ascii_digit) {
+            *i += 1;
+        }
+        *i - start
+    };
+    let mut mantissa = digits(&mut i);
+    if str.get(i) == Some(&b'.') {
+        i += 1;
+        mantissa += digits(&mut i);
+    }
+    if mantissa == 0 {
+        return 0;
+    }
+    if matches!(str.get(i), Some(b'e' | b'E')) {
+        let mut j = i + 1;
+        if matches!(str.get(j), Some(b'-' | b'+')) {
+            j += 1;
+        }
+        if digits(&mut j) > 0 {
+            i = j;
+        }
+    }
+    i
+}
+
+/// Parse the number at the start of str, returning it and its length.
+fn parse_float(str: &[u8]) -> (f64, usize) {
+    let len = float_len(str);
+    let text = std::str::from_utf8(&str[..len]).unwrap().trim_start();
+    (text.parse().unwrap_or(0.0), len)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn atof(machine: &mut Machine, str: u32) -> u32 {
+    let (val, _) = parse_float(machine.mem().slicez(str));
+    ret(machine, val)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn strtod(machine: &mut Machine, nptr: u32, endptr: Option<&mut u32>) -> u32 {
+    let (val, len) = parse_float(machine.mem().slicez(nptr));
+    if let Some(endptr) = endptr {
+        *endptr = nptr + len as u32;
+    }
+    ret(machine, val)
+}
+
+/// Apply an intrinsic to the argument in ST(0), replacing it with the result.
+fn intrinsic(machine: &mut Machine, f: fn(f64) -> f64) -> u32 {
+    let x = pop(machine);
+    ret(machine, f(x))
+}
+
+/// Apply an intrinsic to ST(1) and ST(0), replacing both with the result.
+fn intrinsic2(machine: &mut Machine, f: fn(f64, f64) -> f64) -> u32 {
+    let y = pop(machine);
+    let x = pop(machine);
+    ret(machine, f(x, y))
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _CIsin(machine: &mut Machine) -> u32 {
+    intrinsic(machine, f64::sin)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _CIcos(machine: &mut Machine) -> u32 {
+    intrinsic(machine, f64::cos)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _CItan(machine: &mut Machine) -> u32 {
+    intrinsic(machine, f64::tan)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _CIasin(machine: &mut Machine) -> u32 {
+    intrinsic(machine, f64::asin)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _CIacos(machine: &mut Machine) -> u32 {
+    intrinsic(machine, f64::acos)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _CIatan(machine: &mut Machine) -> u32 {
+    intrinsic(machine, f64::atan)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _CIatan2(machine: &mut Machine) -> u32 {
+    intrinsic2(machine, f64::atan2)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _CIsqrt(machine: &mut Machine) -> u32 {
+    intrinsic(machine, f64::sqrt)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _CIpow(machine: &mut Machine) -> u32 {
+    intrinsic2(machine, f64::powf)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _CIexp(machine: &mut Machine) -> u32 {
+    intrinsic(machine, f64::exp)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _CIlog(machine: &mut Machine) -> u32 {
+    intrinsic(machine, f64::ln)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _CIlog10(machine: &mut Machine) -> u32 {
+    intrinsic(machine, f64::log10)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _CIfmod(machine: &mut Machine) -> u32 {
+    intrinsic2(machine, |x, y| x % y)
+}
+
+/// Convert ST(0) to an integer, truncating, as casts from float do.
+#[win32_derive::dllexport(cdecl)]
+pub fn _ftol(machine: &mut Machine) -> u32 {
+    // TODO: the result is 64-bit, but only the low half in eax is returned;
+    // code casting to int never looks at edx.
+    pop(machine) as i64 as u32
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn abs(_machine: &mut Machine, n: i32) -> i32 {
+    n.wrapping_abs()
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn labs(_machine: &mut Machine, n: i32) -> i32 {
+    n.wrapping_abs()
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn srand(machine: &mut Machine, seed: u32) -> u32 {
+    machine.state.msvcrt.rand_seed = seed;
+    0
+}
+
+/// msvcrt's linear congruential generator, so seeded sequences match Windows.
+#[win32_derive::dllexport(cdecl)]
+pub fn rand(machine: &mut Machine) -> u32 {
+    let seed = &mut machine.state.msvcrt.rand_seed;
+    *seed = seed.wrapping_mul(214013).wrapping_add(2531011);
+    (*seed >> 16) & 0x7FFF
+}
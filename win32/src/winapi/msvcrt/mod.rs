@@ -0,0 +1,79 @@
+//! The C runtime, for programs linked against msvcrt.dll (or the older crtdll.dll)
+//! rather than carrying their own copy.
+
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+
+mod format;
+mod heap;
+mod init;
+mod math;
+mod sort;
+mod stdio;
+mod string;
+
+pub use heap::*;
+pub use init::*;
+pub use math::*;
+pub use sort::*;
+pub use stdio::*;
+pub use string::*;
+
+use crate::machine::Machine;
+use std::collections::HashMap;
+
+/*
+## The CRT
+
+The functions are cdecl, as the CRT's are.  Memory they hand out comes from the
+process heap, so malloc'd blocks are interchangeable with HeapAlloc'd ones as they
+are in the real msvcrt.
+
+FILE structs live in guest memory so their addresses can be passed around, but
+their contents are only placeholders; the stream state is kept here, keyed by
+address.  That means the getc/putc macros, which peek inside the struct, don't
+work, but the functions do.  The stdin/stdout/stderr FILEs are those __iob_func
+returns; the _iob data export isn't available, as builtin DLLs only export
+functions.
+
+Files opened for writing go to kernel32's in-memory written files (see
+kernel32::File::create), so they read back within the session and are kept in
+snapshots, but never reach the host.
+*/
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct State {
+    /// Guest memory for things the CRT hands out pointers to, allocated on first use.
+    globals: Option<init::Globals>,
+    /// Functions registered with atexit, in registration order.
+    atexit: Vec<u32>,
+    rand_seed: u32,
+    /// Floating point control word as set with _controlfp.
+    control_word: u32,
+    /// Open streams, by the address of their FILE, which snapshots record
+    /// separately; see save_streams.
+    #[serde(skip)]
+    files: HashMap<u32, stdio::Stream>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            globals: None,
+            atexit: Vec::new(),
+            rand_seed: 1,
+            control_word: init::_CW_DEFAULT,
+            files: HashMap::new(),
+        }
+    }
+}
+
+const ENOENT: u32 = 2;
+const EBADF: u32 = 9;
+const EINVAL: u32 = 22;
+
+/// Set the errno the guest sees through _errno.
+fn set_errno(machine: &mut Machine, errno: u32) {
+    let addr = init::globals(machine).errno;
+    machine.mem().put::<u32>(addr, errno);
+}
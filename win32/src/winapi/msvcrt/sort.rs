@@ -0,0 +1,78 @@
+//! Sorting and searching arrays, ordered by the guest's comparison functions.
+
+use crate::machine::Machine;
+
+const TRACE_CONTEXT: &'static str = "msvcrt/sort";
+
+/// Call a comparison function, which is cdecl:
+///   int compare(const void* a, const void* b)
+async fn compare(machine: &mut Machine, func: u32, a: u32, b: u32) -> i32 {
+    #[cfg(feature = "x86-emu")]
+    {
+        let mem = machine.emu.memory.mem();
+        machine
+            .emu
+            .x86
+            .cpu_mut()
+            .call_x86_cdecl(mem, func, vec![a, b])
+            .await as i32
+    }
+    #[cfg(not(feature = "x86-emu"))]
+    {
+        machine.call_x86(func, vec![a, b]).await as i32
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub async fn qsort(machine: &mut Machine, base: u32, num: u32, width: u32, compare: u32) -> u32 {
+    if num < 2 || width == 0 {
+        return 0;
+    }
+    // Binary insertion sort of the element indices, which keeps the number of
+    // calls into the guest to O(n log n); elements stay put until the order is known.
+    let elem = |i: u32| base + i * width;
+    let mut order: Vec<u32> = Vec::with_capacity(num as usize);
+    for i in 0..num {
+        let (mut lo, mut hi) = (0, order.len());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if self::compare(machine, compare, elem(i), elem(order[mid])).await < 0 {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        order.insert(lo, i);
+    }
+
+    let len = num * width;
+    let src = machine.mem().sub(base, len).as_slice_todo().to_vec();
+    let dst = machine.mem().sub(base, len).as_mut_slice_todo();
+    for (to, &from) in order.iter().enumerate() {
+        let (to, from) = (to * width as usize, (from * width) as usize);
+        dst[to..][..width as usize].copy_from_slice(&src[from..][..width as usize]);
+    }
+    0
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub async fn bsearch(
+    machine: &mut Machine,
+    key: u32,
+    base: u32,
+    num: u32,
+    width: u32,
+    compare: u32,
+) -> u32 {
+    let (mut lo, mut hi) = (0, num);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let elem = base + mid * width;
+        match self::compare(machine, compare, key, elem).await {
+            0 => return elem,
+            c if c < 0 => hi = mid,
+            _ => lo = mid + 1,
+        }
+    }
+    0
+}
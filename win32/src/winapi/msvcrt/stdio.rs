@@ -0,0 +1,549 @@
+//! FILE streams and the printf family.
+
+use super::{format, heap::malloc, init::globals, set_errno, EBADF, EINVAL, ENOENT};
+use crate::{
+    machine::Machine,
+    winapi::{kernel32, stack_args::VarArgs},
+};
+use memory::Extensions;
+
+const TRACE_CONTEXT: &'static str = "msvcrt/stdio";
+
+/// sizeof(FILE).
+pub const FILE_SIZE: u32 = 32;
+/// Offset of FILE._file, the stream's file descriptor.
+const FILE_FD: u32 = 16;
+
+const EOF: i32 = -1;
+
+enum Kind {
+    Stdin,
+    Stdout,
+    Stderr,
+    File(kernel32::File),
+}
+
+pub struct Stream {
+    kind: Kind,
+    eof: bool,
+    error: bool,
+    /// A byte pushed back with ungetc.
+    unget: Option<u8>,
+    /// Opened in "a" mode, where every write goes to the end.
+    append: bool,
+}
+
+impl Stream {
+    fn new(kind: Kind) -> Self {
+        Stream {
+            kind,
+            eof: false,
+            error: false,
+            unget: None,
+            append: false,
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        let mut n = 0;
+        if let (Some(c), Some(first)) = (self.unget, buf.first_mut()) {
+            *first = c;
+            self.unget = None;
+            n = 1;
+        }
+        while n < buf.len() {
+            let mut len = 0;
+            let ok = match &mut self.kind {
+                Kind::File(file) => file.read(&mut buf[n..], &mut len),
+                // TODO: console input.
+                _ => true,
+            };
+            if !ok {
+                self.error = true;
+                break;
+            }
+            if len == 0 {
+                self.eof = true;
+                break;
+            }
+            n += len as usize;
+        }
+        n
+    }
+}
+
+/// A stream as recorded in a snapshot.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct StreamRecord {
+    kind: KindRecord,
+    eof: bool,
+    error: bool,
+    unget: Option<u8>,
+    append: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+enum KindRecord {
+    Stdin,
+    Stdout,
+    Stderr,
+    /// A file, with its FILE's address as the handle.
+    File(kernel32::FileRecord),
+}
+
+/// Record the open streams, for snapshots.
+pub fn save_streams(machine: &Machine) -> Vec<(u32, StreamRecord)> {
+    machine
+        .state
+        .msvcrt
+        .files
+        .iter()
+        .map(|(&addr, stream)| {
+            let kind = match &stream.kind {
+                Kind::Stdin => KindRecord::Stdin,
+                Kind::Stdout => KindRecord::Stdout,
+                Kind::Stderr => KindRecord::Stderr,
+                Kind::File(file) => KindRecord::File(kernel32::FileRecord::new(addr, file)),
+            };
+            let record = StreamRecord {
+                kind,
+                eof: stream.eof,
+                error: stream.error,
+                unget: stream.unget,
+                append: stream.append,
+            };
+            (addr, record)
+        })
+        .collect()
+}
+
+/// Replace the open streams with those recorded in a snapshot, reopening files as
+/// kernel32 does; a file that changed is left closed.
+pub fn restore_streams(machine: &mut Machine, records: Vec<(u32, StreamRecord)>) {
+    machine.state.msvcrt.files.clear();
+    for (addr, record) in records {
+        let kind = match record.kind {
+            KindRecord::Stdin => Kind::Stdin,
+            KindRecord::Stdout => Kind::Stdout,
+            KindRecord::Stderr => Kind::Stderr,
+            KindRecord::File(file) => match file.reopen(machine) {
+                Some(file) => Kind::File(file),
+                None => continue,
+            },
+        };
+        let stream = Stream {
+            kind,
+            eof: record.eof,
+            error: record.error,
+            unget: record.unget,
+            append: record.append,
+        };
+        machine.state.msvcrt.files.insert(addr, stream);
+    }
+}
+
+/// Set up the FILEs for stdin, stdout and stderr, which are consecutive at iob.
+pub fn init_std_streams(machine: &mut Machine, iob: u32) {
+    let kinds = [Kind::Stdin, Kind::Stdout, Kind::Stderr];
+    for (fd, kind) in kinds.into_iter().enumerate() {
+        let file = iob + fd as u32 * FILE_SIZE;
+        machine.mem().put::<u32>(file + FILE_FD, fd as u32);
+        machine.state.msvcrt.files.insert(file, Stream::new(kind));
+    }
+}
+
+/// Look up a stream by its FILE*, setting errno if it's not one.
+fn stream(machine: &mut Machine, file: u32) -> Option<&mut Stream> {
+    globals(machine); // the std streams exist once the globals do
+    if !machine.state.msvcrt.files.contains_key(&file) {
+        log::warn!("msvcrt: bad FILE* {file:x}");
+        set_errno(machine, EBADF);
+        return None;
+    }
+    machine.state.msvcrt.files.get_mut(&file)
+}
+
+/// Write bytes to a stream, returning how many were written.
+fn write(machine: &mut Machine, file: u32, buf: &[u8]) -> usize {
+    let Some(stream) = stream(machine, file) else {
+        return 0;
+    };
+    if let Kind::Stdout | Kind::Stderr = stream.kind {
+        return machine.host.write(buf);
+    }
+    let state = &mut machine.state;
+    let stream = state.msvcrt.files.get_mut(&file).unwrap();
+    let written = match &mut stream.kind {
+        Kind::File(f) => {
+            if stream.append {
+                let end = f.info();
+                f.seek(end);
+            }
+            f.write(&mut state.kernel32.written_files, buf)
+        }
+        _ => false,
+    };
+    if !written {
+        // Stdin, or a file opened only for reading.
+        stream.error = true;
+        set_errno(machine, EBADF);
+        return 0;
+    }
+    stream.unget = None;
+    buf.len()
+}
+
+/// Read bytes from a stream into guest memory, returning how many were read.
+fn read(machine: &mut Machine, file: u32, addr: u32, len: u32) -> u32 {
+    let Some(stream) = stream(machine, file) else {
+        return 0;
+    };
+    let mut buf = vec![0u8; len as usize];
+    let n = stream.read(&mut buf);
+    machine
+        .mem()
+        .sub(addr, n as u32)
+        .as_mut_slice_todo()
+        .copy_from_slice(&buf[..n]);
+    n as u32
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn __iob_func(machine: &mut Machine) -> u32 {
+    globals(machine).iob
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn fopen(machine: &mut Machine, filename: Option<&str>, mode: Option<&str>) -> u32 {
+    let (Some(filename), Some(mode)) = (filename, mode) else {
+        set_errno(machine, EINVAL);
+        return 0;
+    };
+    // Any mode but plain "r" can write; "+" adds reading, which files always allow.
+    let update = mode.contains('+');
+    let file = match mode.chars().next() {
+        Some('r') => {
+            if !kernel32::file_exists(machine, filename) {
+                set_errno(machine, ENOENT);
+                return 0;
+            }
+            match update {
+                true => kernel32::File::create(machine, filename, false),
+                false => kernel32::File::open(machine, filename),
+            }
+        }
+        Some('w') => kernel32::File::create(machine, filename, true),
+        Some('a') => kernel32::File::create(machine, filename, false),
+        _ => {
+            set_errno(machine, EINVAL);
+            return 0;
+        }
+    };
+    globals(machine);
+    let mut stream = Stream::new(Kind::File(file));
+    stream.append = mode.starts_with('a');
+    let file = malloc(machine, FILE_SIZE);
+    machine
+        .mem()
+        .sub(file, FILE_SIZE)
+        .as_mut_slice_todo()
+        .fill(0);
+    let fd = machine.state.msvcrt.files.len() as u32;
+    machine.mem().put::<u32>(file + FILE_FD, fd);
+    machine.state.msvcrt.files.insert(file, stream);
+    file
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn fclose(machine: &mut Machine, stream: u32) -> i32 {
+    match machine.state.msvcrt.files.get(&stream) {
+        Some(Stream {
+            kind: Kind::File(_),
+            ..
+        }) => {
+            machine.state.msvcrt.files.remove(&stream);
+            super::free(machine, stream);
+            0
+        }
+        Some(_) => 0, // the std streams stay open
+        None => EOF,
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn fread(machine: &mut Machine, buffer: u32, size: u32, count: u32, stream: u32) -> u32 {
+    let Some(len) = size.checked_mul(count).filter(|&len| len > 0) else {
+        return 0;
+    };
+    read(machine, stream, buffer, len) / size
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn fwrite(machine: &mut Machine, buffer: u32, size: u32, count: u32, stream: u32) -> u32 {
+    let Some(len) = size.checked_mul(count).filter(|&len| len > 0) else {
+        return 0;
+    };
+    let buf = machine.mem().sub(buffer, len).as_slice_todo().to_vec();
+    write(machine, stream, &buf) as u32 / size
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn fgetc(machine: &mut Machine, stream: u32) -> i32 {
+    let Some(stream) = self::stream(machine, stream) else {
+        return EOF;
+    };
+    let mut c = [0u8];
+    match stream.read(&mut c) {
+        0 => EOF,
+        _ => c[0] as i32,
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn getc(machine: &mut Machine, stream: u32) -> i32 {
+    fgetc(machine, stream)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn getchar(machine: &mut Machine) -> i32 {
+    let stdin = globals(machine).iob;
+    fgetc(machine, stdin)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn ungetc(machine: &mut Machine, c: i32, stream: u32) -> i32 {
+    let Some(stream) = self::stream(machine, stream) else {
+        return EOF;
+    };
+    if c == EOF || stream.unget.is_some() {
+        return EOF;
+    }
+    stream.unget = Some(c as u8);
+    stream.eof = false;
+    c as u8 as i32
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn fgets(machine: &mut Machine, str: u32, n: i32, stream: u32) -> u32 {
+    let Some(s) = self::stream(machine, stream) else {
+        return 0;
+    };
+    let mut line = Vec::new();
+    let mut c = [0u8];
+    while (line.len() as i32) < n - 1 && s.read(&mut c) == 1 {
+        line.push(c[0]);
+        if c[0] == b'\n' {
+            break;
+        }
+    }
+    if line.is_empty() {
+        return 0;
+    }
+    line.push(0);
+    machine
+        .mem()
+        .sub(str, line.len() as u32)
+        .as_mut_slice_todo()
+        .copy_from_slice(&line);
+    str
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn fputc(machine: &mut Machine, c: i32, stream: u32) -> i32 {
+    match write(machine, stream, &[c as u8]) {
+        1 => c as u8 as i32,
+        _ => EOF,
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn putc(machine: &mut Machine, c: i32, stream: u32) -> i32 {
+    fputc(machine, c, stream)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn putchar(machine: &mut Machine, c: i32) -> i32 {
+    let stdout = globals(machine).iob + FILE_SIZE;
+    fputc(machine, c, stdout)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn fputs(machine: &mut Machine, str: u32, stream: u32) -> i32 {
+    let buf = machine.mem().slicez(str).to_vec();
+    match write(machine, stream, &buf) == buf.len() {
+        true => 0,
+        false => EOF,
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn puts(machine: &mut Machine, str: u32) -> i32 {
+    let mut buf = machine.mem().slicez(str).to_vec();
+    buf.push(b'\n');
+    let stdout = globals(machine).iob + FILE_SIZE;
+    match write(machine, stdout, &buf) == buf.len() {
+        true => 0,
+        false => EOF,
+    }
+}
+
+const SEEK_SET: u32 = 0;
+const SEEK_CUR: u32 = 1;
+const SEEK_END: u32 = 2;
+
+#[win32_derive::dllexport(cdecl)]
+pub fn fseek(machine: &mut Machine, stream: u32, offset: i32, origin: u32) -> i32 {
+    let Some(s) = self::stream(machine, stream) else {
+        return -1;
+    };
+    let Kind::File(file) = &mut s.kind else {
+        return -1;
+    };
+    let base = match origin {
+        SEEK_SET => 0,
+        SEEK_CUR => file.pos as i64 - s.unget.is_some() as i64,
+        SEEK_END => file.info() as i64,
+        _ => {
+            set_errno(machine, EINVAL);
+            return -1;
+        }
+    };
+    let pos = base + offset as i64;
+    if pos < 0 || !file.seek(pos as u32) {
+        set_errno(machine, EINVAL);
+        return -1;
+    }
+    s.unget = None;
+    s.eof = false;
+    0
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn ftell(machine: &mut Machine, stream: u32) -> i32 {
+    let Some(s) = self::stream(machine, stream) else {
+        return -1;
+    };
+    match &s.kind {
+        Kind::File(file) => file.pos as i32 - s.unget.is_some() as i32,
+        _ => 0,
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn rewind(machine: &mut Machine, stream: u32) -> u32 {
+    fseek(machine, stream, 0, SEEK_SET);
+    if let Some(s) = self::stream(machine, stream) {
+        s.error = false;
+    }
+    0
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn feof(machine: &mut Machine, stream: u32) -> bool {
+    self::stream(machine, stream).is_some_and(|s| s.eof)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn ferror(machine: &mut Machine, stream: u32) -> bool {
+    self::stream(machine, stream).is_some_and(|s| s.error)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn clearerr(machine: &mut Machine, stream: u32) -> u32 {
+    if let Some(s) = self::stream(machine, stream) {
+        s.eof = false;
+        s.error = false;
+    }
+    0
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn fflush(_machine: &mut Machine, stream: u32) -> i32 {
+    0 // nothing is buffered
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn vfprintf(machine: &mut Machine, stream: u32, format: u32, argptr: u32) -> i32 {
+    let mem = machine.mem();
+    let buf = format::printf(mem, mem.slicez(format), argptr);
+    match write(machine, stream, &buf) == buf.len() {
+        true => buf.len() as i32,
+        false => -1,
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn fprintf(machine: &mut Machine, stream: u32, format: u32, args: VarArgs) -> i32 {
+    vfprintf(machine, stream, format, args.va_list())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn vprintf(machine: &mut Machine, format: u32, argptr: u32) -> i32 {
+    let stdout = globals(machine).iob + FILE_SIZE;
+    vfprintf(machine, stdout, format, argptr)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn printf(machine: &mut Machine, format: u32, args: VarArgs) -> i32 {
+    vprintf(machine, format, args.va_list())
+}
+
+/// Format into a guest buffer of at most count bytes, returning the length without
+/// the nul, or -1 if it didn't fit.  Without a count, the buffer is assumed big enough.
+fn format_to(
+    machine: &mut Machine,
+    buffer: u32,
+    count: Option<u32>,
+    format: u32,
+    argptr: u32,
+) -> i32 {
+    let mem = machine.mem();
+    let mut buf = format::printf(mem, mem.slicez(format), argptr);
+    let len = buf.len() as i32;
+    buf.push(0);
+    match count {
+        Some(count) if buf.len() as u32 > count => {
+            // _snprintf leaves the buffer unterminated if the nul doesn't fit.
+            mem.sub(buffer, count)
+                .as_mut_slice_todo()
+                .copy_from_slice(&buf[..count as usize]);
+            if len as u32 == count {
+                len
+            } else {
+                -1
+            }
+        }
+        _ => {
+            mem.sub(buffer, buf.len() as u32)
+                .as_mut_slice_todo()
+                .copy_from_slice(&buf);
+            len
+        }
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn vsprintf(machine: &mut Machine, buffer: u32, format: u32, argptr: u32) -> i32 {
+    format_to(machine, buffer, None, format, argptr)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn sprintf(machine: &mut Machine, buffer: u32, format: u32, args: VarArgs) -> i32 {
+    format_to(machine, buffer, None, format, args.va_list())
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _vsnprintf(machine: &mut Machine, buffer: u32, count: u32, format: u32, argptr: u32) -> i32 {
+    format_to(machine, buffer, Some(count), format, argptr)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _snprintf(
+    machine: &mut Machine,
+    buffer: u32,
+    count: u32,
+    format: u32,
+    args: VarArgs,
+) -> i32 {
+    format_to(machine, buffer, Some(count), format, args.va_list())
+}
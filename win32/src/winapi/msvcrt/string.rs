@@ -0,0 +1,403 @@
+//! String and memory functions, and character classes.
+
+use super::{heap::alloc_bytes, set_errno};
+use crate::{machine::Machine, winapi::vcruntime140};
+use memory::Extensions;
+use std::cmp::Ordering;
+
+const TRACE_CONTEXT: &'static str = "msvcrt/string";
+
+const ERANGE: u32 = 34;
+
+/// Write bytes into guest memory at addr.
+fn put_bytes(machine: &mut Machine, addr: u32, bytes: &[u8]) {
+    machine
+        .mem()
+        .sub(addr, bytes.len() as u32)
+        .as_mut_slice_todo()
+        .copy_from_slice(bytes);
+}
+
+/// The nul-terminated string at addr, including the nul.
+fn cstr(machine: &Machine, addr: u32) -> Vec<u8> {
+    let mut str = machine.mem().slicez(addr).to_vec();
+    str.push(0);
+    str
+}
+
+/// Up to n bytes of the string at addr, stopping after a nul.
+fn cstr_n(machine: &Machine, addr: u32, n: u32) -> &[u8] {
+    let mut len = 0;
+    while len < n && machine.mem().get_pod::<u8>(addr + len) != 0 {
+        len += 1;
+    }
+    machine.mem().sub(addr, len).as_slice_todo()
+}
+
+fn ordering(ord: Ordering) -> i32 {
+    match ord {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    }
+}
+
+fn compare(a: &[u8], b: &[u8], ignore_case: bool) -> i32 {
+    if ignore_case {
+        ordering(a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase()))
+    } else {
+        ordering(a.cmp(b))
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub async fn memcpy(machine: &mut Machine, dest: u32, src: u32, count: u32) -> u32 {
+    vcruntime140::memmove(machine, dest, src, count).await
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub async fn memmove(machine: &mut Machine, dest: u32, src: u32, count: u32) -> u32 {
+    vcruntime140::memmove(machine, dest, src, count).await
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub async fn memset(machine: &mut Machine, dest: u32, c: u32, count: u32) -> u32 {
+    vcruntime140::memset(machine, dest, c, count).await
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn memcmp(machine: &mut Machine, buf1: u32, buf2: u32, count: u32) -> u32 {
+    vcruntime140::memcmp(machine, buf1, buf2, count)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn memchr(machine: &mut Machine, buf: u32, c: u32, count: u32) -> u32 {
+    let buf_slice = machine.mem().sub(buf, count).as_slice_todo();
+    match buf_slice.iter().position(|&b| b == c as u8) {
+        Some(i) => buf + i as u32,
+        None => 0,
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn strlen(machine: &mut Machine, str: u32) -> u32 {
+    machine.mem().slicez(str).len() as u32
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn strcpy(machine: &mut Machine, dest: u32, src: u32) -> u32 {
+    let str = cstr(machine, src);
+    put_bytes(machine, dest, &str);
+    dest
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn strncpy(machine: &mut Machine, dest: u32, src: u32, count: u32) -> u32 {
+    // Pads with nuls to count, and doesn't terminate if src is that long.
+    let mut str = cstr_n(machine, src, count).to_vec();
+    str.resize(count as usize, 0);
+    put_bytes(machine, dest, &str);
+    dest
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn strcat(machine: &mut Machine, dest: u32, src: u32) -> u32 {
+    let end = dest + strlen(machine, dest);
+    strcpy(machine, end, src);
+    dest
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn strncat(machine: &mut Machine, dest: u32, src: u32, count: u32) -> u32 {
+    let end = dest + strlen(machine, dest);
+    let mut str = cstr_n(machine, src, count).to_vec();
+    str.push(0);
+    put_bytes(machine, end, &str);
+    dest
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn strcmp(machine: &mut Machine, string1: u32, string2: u32) -> i32 {
+    let mem = machine.mem();
+    compare(mem.slicez(string1), mem.slicez(string2), false)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn strncmp(machine: &mut Machine, string1: u32, string2: u32, count: u32) -> i32 {
+    compare(
+        cstr_n(machine, string1, count),
+        cstr_n(machine, string2, count),
+        false,
+    )
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _stricmp(machine: &mut Machine, string1: u32, string2: u32) -> i32 {
+    let mem = machine.mem();
+    compare(mem.slicez(string1), mem.slicez(string2), true)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _strcmpi(machine: &mut Machine, string1: u32, string2: u32) -> i32 {
+    _stricmp(machine, string1, string2)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _strnicmp(machine: &mut Machine, string1: u32, string2: u32, count: u32) -> i32 {
+    compare(
+        cstr_n(machine, string1, count),
+        cstr_n(machine, string2, count),
+        true,
+    )
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn strchr(machine: &mut Machine, str: u32, c: u32) -> u32 {
+    // The terminating nul counts as part of the string.
+    match cstr(machine, str).iter().position(|&b| b == c as u8) {
+        Some(i) => str + i as u32,
+        None => 0,
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn strrchr(machine: &mut Machine, str: u32, c: u32) -> u32 {
+    match cstr(machine, str).iter().rposition(|&b| b == c as u8) {
+        Some(i) => str + i as u32,
+        None => 0,
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn strstr(machine: &mut Machine, str: u32, strSearch: u32) -> u32 {
+    let mem = machine.mem();
+    let (haystack, needle) = (mem.slicez(str), mem.slicez(strSearch));
+    if needle.is_empty() {
+        return str;
+    }
+    match haystack.windows(needle.len()).position(|w| w == needle) {
+        Some(i) => str + i as u32,
+        None => 0,
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _strdup(machine: &mut Machine, strSource: u32) -> u32 {
+    if strSource == 0 {
+        return 0;
+    }
+    let str = cstr(machine, strSource);
+    alloc_bytes(machine, &str)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _strupr(machine: &mut Machine, str: u32) -> u32 {
+    let mut s = cstr(machine, str);
+    s.make_ascii_uppercase();
+    put_bytes(machine, str, &s);
+    str
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _strlwr(machine: &mut Machine, str: u32) -> u32 {
+    let mut s = cstr(machine, str);
+    s.make_ascii_lowercase();
+    put_bytes(machine, str, &s);
+    str
+}
+
+/// Parse an integer as strtol does, returning its magnitude (saturated), whether
+/// it was negative, and the length consumed; 0 if there was no number.
+fn parse_int(str: &[u8], mut base: u32) -> (u64, bool, usize) {
+    let mut i = str.iter().take_while(|c| c.is_ascii_whitespace()).count();
+    let negative = str.get(i) == Some(&b'-');
+    if matches!(str.get(i), Some(b'-' | b'+')) {
+        i += 1;
+    }
+    let hex_prefix = str.get(i) == Some(&b'0')
+        && matches!(str.get(i + 1), Some(b'x' | b'X'))
+        && str.get(i + 2).is_some_and(u8::is_ascii_hexdigit);
+    if (base == 0 || base == 16) && hex_prefix {
+        base = 16;
+        i += 2;
+    } else if base == 0 {
+        base = if str.get(i) == Some(&b'0') { 8 } else { 10 };
+    }
+    if !(2..=36).contains(&base) {
+        return (0, false, 0);
+    }
+    let start = i;
+    let mut value = 0u64;
+    while let Some(digit) = str.get(i).and_then(|&c| (c as char).to_digit(base)) {
+        value = value
+            .saturating_mul(base as u64)
+            .saturating_add(digit as u64);
+        i += 1;
+    }
+    if i == start {
+        return (0, false, 0);
+    }
+    (value, negative, i)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn strtol(machine: &mut Machine, nptr: u32, endptr: Option<&mut u32>, base: u32) -> i32 {
+    let (value, negative, len) = parse_int(machine.mem().slicez(nptr), base);
+    if let Some(endptr) = endptr {
+        *endptr = nptr + len as u32;
+    }
+    let value = if negative {
+        -(value.min(i64::MAX as u64) as i64)
+    } else {
+        value.min(i64::MAX as u64) as i64
+    };
+    match i32::try_from(value) {
+        Ok(value) => value,
+        Err(_) => {
+            set_errno(machine, ERANGE);
+            if negative {
+                i32::MIN
+            } else {
+                i32::MAX
+            }
+        }
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn strtoul(machine: &mut Machine, nptr: u32, endptr: Option<&mut u32>, base: u32) -> u32 {
+    let (value, negative, len) = parse_int(machine.mem().slicez(nptr), base);
+    if let Some(endptr) = endptr {
+        *endptr = nptr + len as u32;
+    }
+    match u32::try_from(value) {
+        // A negated unsigned value wraps, as in C.
+        Ok(value) if negative => value.wrapping_neg(),
+        Ok(value) => value,
+        Err(_) => {
+            set_errno(machine, ERANGE);
+            u32::MAX
+        }
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn atol(machine: &mut Machine, str: u32) -> i32 {
+    let (value, negative, _) = parse_int(machine.mem().slicez(str), 10);
+    // Overflow is undefined; wrap like the usual implementation.
+    let value = value as u32 as i32;
+    if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn atoi(machine: &mut Machine, str: u32) -> i32 {
+    atol(machine, str)
+}
+
+/// Format an integer in a base for _itoa and friends.
+fn to_base(mut value: u32, radix: u32) -> Vec<u8> {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(std::char::from_digit(value % radix, radix).unwrap() as u8);
+        value /= radix;
+        if value == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    digits
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _itoa(machine: &mut Machine, value: i32, buffer: u32, radix: u32) -> u32 {
+    if !(2..=36).contains(&radix) {
+        return buffer;
+    }
+    // Only base 10 treats the value as signed.
+    let mut str = if radix == 10 && value < 0 {
+        let mut str = vec![b'-'];
+        str.extend(to_base(value.unsigned_abs(), radix));
+        str
+    } else {
+        to_base(value as u32, radix)
+    };
+    str.push(0);
+    put_bytes(machine, buffer, &str);
+    buffer
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn _ltoa(machine: &mut Machine, value: i32, buffer: u32, radix: u32) -> u32 {
+    _itoa(machine, value, buffer, radix)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn toupper(_machine: &mut Machine, c: u32) -> u32 {
+    match u8::try_from(c) {
+        Ok(c) => c.to_ascii_uppercase() as u32,
+        Err(_) => c,
+    }
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn tolower(_machine: &mut Machine, c: u32) -> u32 {
+    match u8::try_from(c) {
+        Ok(c) => c.to_ascii_lowercase() as u32,
+        Err(_) => c,
+    }
+}
+
+/// Test a character against a class, as the is* functions do; EOF (-1) is in none.
+fn is(c: u32, class: fn(&u8) -> bool) -> bool {
+    u8::try_from(c).is_ok_and(|c| class(&c))
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn isalpha(_machine: &mut Machine, c: u32) -> bool {
+    is(c, u8::is_ascii_alphabetic)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn isdigit(_machine: &mut Machine, c: u32) -> bool {
+    is(c, u8::is_ascii_digit)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn isxdigit(_machine: &mut Machine, c: u32) -> bool {
+    is(c, u8::is_ascii_hexdigit)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn isalnum(_machine: &mut Machine, c: u32) -> bool {
+    is(c, u8::is_ascii_alphanumeric)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn isspace(_machine: &mut Machine, c: u32) -> bool {
+    // C's isspace includes vertical tab, which Rust's doesn't.
+    c == 0x0B || is(c, u8::is_ascii_whitespace)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn isupper(_machine: &mut Machine, c: u32) -> bool {
+    is(c, u8::is_ascii_uppercase)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn islower(_machine: &mut Machine, c: u32) -> bool {
+    is(c, u8::is_ascii_lowercase)
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn isprint(_machine: &mut Machine, c: u32) -> bool {
+    is(c, |c| (0x20..0x7F).contains(c))
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub fn ispunct(_machine: &mut Machine, c: u32) -> bool {
+    is(c, u8::is_ascii_punctuation)
+}
@@ -1 +1,235 @@
+//! COM runtime: initialization, task memory, and creating the builtin classes.
 
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+
+use super::com::{
+    self, IID_IUnknown, CLASS_E_NOAGGREGATION, E_NOINTERFACE, E_POINTER, GUID, REGDB_E_CLASSNOTREG,
+    S_FALSE, S_OK,
+};
+use super::{ddraw, dinput, dsound, quartz};
+use crate::machine::Machine;
+
+const TRACE_CONTEXT: &'static str = "ole32";
+
+/*
+## Classes
+
+CoCreateInstance only knows the classes of DLLs implemented here, listed in
+CLASSES, each created through the DLL's own *Create entry point; there is no
+registry of in-process servers to load guest DLLs from.  The objects are what
+those entry points return, so are ready for use without their Initialize method,
+though that is available (and does nothing) for callers that follow the COM
+recipe.
+*/
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct State {
+    /// Outstanding CoInitialize calls, not tracked per thread.
+    init_count: u32,
+}
+
+/// Create an object of a builtin class as the interface riid, storing it at ppv.
+type Create = fn(&mut Machine, riid: u32, ppv: u32) -> u32;
+
+struct Class {
+    name: &'static str,
+    clsid: GUID,
+    create: Create,
+}
+
+const CLASSES: [Class; 6] = [
+    Class {
+        name: "DirectDraw",
+        // {D7B70EE0-4340-11CF-B063-0020AFC2CD35}
+        clsid: [
+            0xe0, 0x0e, 0xb7, 0xd7, 0x40, 0x43, 0xcf, 0x11, 0xb0, 0x63, 0x00, 0x20, 0xaf, 0xc2,
+            0xcd, 0x35,
+        ],
+        create: create_ddraw,
+    },
+    Class {
+        name: "DirectDraw7",
+        // {3C305196-50DB-11D3-9CFE-00C04FD930C5}
+        clsid: [
+            0x96, 0x51, 0x30, 0x3c, 0xdb, 0x50, 0xd3, 0x11, 0x9c, 0xfe, 0x00, 0xc0, 0x4f, 0xd9,
+            0x30, 0xc5,
+        ],
+        create: create_ddraw,
+    },
+    Class {
+        name: "DirectSound",
+        // {47D4D946-62E8-11CF-93BC-444553540000}
+        clsid: [
+            0x46, 0xd9, 0xd4, 0x47, 0xe8, 0x62, 0xcf, 0x11, 0x93, 0xbc, 0x44, 0x45, 0x53, 0x54,
+            0x00, 0x00,
+        ],
+        create: create_dsound,
+    },
+    Class {
+        name: "DirectInput",
+        // {25E609E0-B259-11CF-BFC7-444553540000}
+        clsid: [
+            0xe0, 0x09, 0xe6, 0x25, 0x59, 0xb2, 0xcf, 0x11, 0xbf, 0xc7, 0x44, 0x45, 0x53, 0x54,
+            0x00, 0x00,
+        ],
+        create: create_dinput,
+    },
+    Class {
+        name: "DirectInput8",
+        // {25E609E4-B259-11CF-BFC7-444553540000}
+        clsid: [
+            0xe4, 0x09, 0xe6, 0x25, 0x59, 0xb2, 0xcf, 0x11, 0xbf, 0xc7, 0x44, 0x45, 0x53, 0x54,
+            0x00, 0x00,
+        ],
+        create: create_dinput8,
+    },
+    Class {
+        name: "FilterGraph",
+        // {E436EBB3-524F-11CE-9F53-0020AF0BA770}
+        clsid: [
+            0xb3, 0xeb, 0x36, 0xe4, 0x4f, 0x52, 0xce, 0x11, 0x9f, 0x53, 0x00, 0x20, 0xaf, 0x0b,
+            0xa7, 0x70,
+        ],
+        create: create_filter_graph,
+    },
+];
+
+fn create_ddraw(machine: &mut Machine, riid: u32, ppv: u32) -> u32 {
+    // DirectDrawCreateEx takes no IID to mean IDirectDraw.
+    let iid = if com::read_guid(machine, riid) == IID_IUnknown {
+        0
+    } else {
+        riid
+    };
+    match ddraw::DirectDrawCreateEx(machine, 0, ppv, iid, 0) {
+        0 => S_OK,
+        _ => E_NOINTERFACE,
+    }
+}
+
+fn create_dsound(machine: &mut Machine, _riid: u32, ppv: u32) -> u32 {
+    dsound::DirectSoundCreate(machine, 0, ppv, 0)
+}
+
+fn create_dinput(machine: &mut Machine, _riid: u32, ppv: u32) -> u32 {
+    let mut obj = 0;
+    let hr = dinput::create(machine, false, Some(&mut obj));
+    machine.mem().put::<u32>(ppv, obj);
+    hr
+}
+
+fn create_dinput8(machine: &mut Machine, riid: u32, ppv: u32) -> u32 {
+    let iid = com::read_guid(machine, riid);
+    if iid != IID_IUnknown && iid != dinput::IID_IDirectInput8A {
+        return E_NOINTERFACE;
+    }
+    let mut obj = 0;
+    let hr = dinput::create(machine, true, Some(&mut obj));
+    machine.mem().put::<u32>(ppv, obj);
+    hr
+}
+
+fn create_filter_graph(machine: &mut Machine, riid: u32, ppv: u32) -> u32 {
+    quartz::create_filter_graph(machine, riid, ppv)
+}
+
+#[win32_derive::dllexport]
+pub fn CoInitialize(machine: &mut Machine, pvReserved: u32) -> u32 {
+    CoInitializeEx(machine, pvReserved, 0)
+}
+
+#[win32_derive::dllexport]
+pub fn CoInitializeEx(machine: &mut Machine, pvReserved: u32, dwCoInit: u32) -> u32 {
+    let ole32 = &mut machine.state.ole32;
+    ole32.init_count += 1;
+    if ole32.init_count > 1 {
+        return S_FALSE; // already initialized
+    }
+    S_OK
+}
+
+#[win32_derive::dllexport]
+pub fn CoUninitialize(machine: &mut Machine) -> u32 {
+    let ole32 = &mut machine.state.ole32;
+    ole32.init_count = ole32.init_count.saturating_sub(1);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn CoCreateInstance(
+    machine: &mut Machine,
+    rclsid: u32,
+    pUnkOuter: u32,
+    dwClsContext: u32,
+    riid: u32,
+    ppv: u32,
+) -> u32 {
+    if ppv == 0 || rclsid == 0 || riid == 0 {
+        return E_POINTER;
+    }
+    machine.mem().put::<u32>(ppv, 0);
+    if machine.state.ole32.init_count == 0 {
+        // Windows fails with CO_E_NOTINITIALIZED, but some programs rely on
+        // another DLL having initialized COM for them.
+        log::warn!("CoCreateInstance without CoInitialize");
+    }
+    let clsid = com::read_guid(machine, rclsid);
+    let Some(class) = CLASSES.iter().find(|class| class.clsid == clsid) else {
+        log::warn!("CoCreateInstance: unknown class {clsid:x?}");
+        return REGDB_E_CLASSNOTREG;
+    };
+    if pUnkOuter != 0 {
+        log::warn!("CoCreateInstance({}): aggregation unsupported", class.name);
+        return CLASS_E_NOAGGREGATION;
+    }
+    (class.create)(machine, riid, ppv)
+}
+
+#[win32_derive::dllexport]
+pub fn CoTaskMemAlloc(machine: &mut Machine, cb: u32) -> u32 {
+    let heap = machine
+        .state
+        .kernel32
+        .get_process_heap(&mut machine.emu.memory);
+    heap.alloc(machine.emu.memory.mem(), cb)
+}
+
+#[win32_derive::dllexport]
+pub fn CoTaskMemFree(machine: &mut Machine, pv: u32) -> u32 {
+    if pv == 0 {
+        return 0;
+    }
+    let heap = machine
+        .state
+        .kernel32
+        .get_process_heap(&mut machine.emu.memory);
+    heap.free(machine.emu.memory.mem(), pv);
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn CoTaskMemRealloc(machine: &mut Machine, pv: u32, cb: u32) -> u32 {
+    if pv == 0 {
+        return CoTaskMemAlloc(machine, cb);
+    }
+    if cb == 0 {
+        CoTaskMemFree(machine, pv);
+        return 0;
+    }
+    let heap = machine
+        .state
+        .kernel32
+        .get_process_heap(&mut machine.emu.memory);
+    let mem = machine.emu.memory.mem();
+    let old_size = heap.size(mem, pv);
+    let new_addr = heap.alloc(mem, cb);
+    if new_addr == 0 {
+        return 0; // the old block is left alone
+    }
+    let len = std::cmp::min(old_size, cb) as usize;
+    mem.as_mut_slice_todo()
+        .copy_within(pv as usize..pv as usize + len, new_addr as usize);
+    heap.free(mem, pv);
+    new_addr
+}
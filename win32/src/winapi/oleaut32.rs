@@ -1 +1,430 @@
+//! OLE Automation: BSTR strings and VARIANTs.
 
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+
+use super::com::S_OK;
+use super::types::Str16;
+use crate::machine::Machine;
+use memory::Extensions;
+
+const TRACE_CONTEXT: &'static str = "oleaut32";
+
+/*
+## BSTRs
+
+A BSTR points at UTF-16 text, nul-terminated, preceded by its length in bytes
+as a u32; all of it is one allocation on the process heap.  Programs import these
+functions by ordinal, so each export carries the ordinal Windows gives it.
+*/
+
+const E_INVALIDARG: u32 = 0x8007_0057;
+const E_OUTOFMEMORY: u32 = 0x8007_000E;
+const DISP_E_BADVARTYPE: u32 = 0x8002_0008;
+const DISP_E_OVERFLOW: u32 = 0x8002_000A;
+const DISP_E_TYPEMISMATCH: u32 = 0x8002_0005;
+
+/// Allocate a BSTR of len bytes, copying them from src unless it's null.
+fn alloc_bstr(machine: &mut Machine, src: u32, len: u32) -> u32 {
+    let heap = machine
+        .state
+        .kernel32
+        .get_process_heap(&mut machine.emu.memory);
+    let mem = machine.emu.memory.mem();
+    let addr = heap.alloc(mem, 4 + len + 2);
+    if addr == 0 {
+        return 0;
+    }
+    mem.put::<u32>(addr, len);
+    let bstr = addr + 4;
+    let buf = mem.sub(bstr, len + 2).as_mut_slice_todo();
+    if src != 0 {
+        buf[..len as usize].copy_from_slice(mem.sub(src, len).as_slice_todo());
+    } else {
+        buf[..len as usize].fill(0);
+    }
+    buf[len as usize..].fill(0);
+    bstr
+}
+
+fn free_bstr(machine: &mut Machine, bstr: u32) {
+    if bstr == 0 {
+        return;
+    }
+    let heap = machine
+        .state
+        .kernel32
+        .get_process_heap(&mut machine.emu.memory);
+    heap.free(machine.emu.memory.mem(), bstr - 4);
+}
+
+fn bstr_byte_len(machine: &Machine, bstr: u32) -> u32 {
+    if bstr == 0 {
+        return 0;
+    }
+    machine.mem().get_pod::<u32>(bstr - 4)
+}
+
+fn read_bstr(machine: &Machine, bstr: u32) -> String {
+    let len = bstr_byte_len(machine, bstr);
+    let bytes = machine.mem().sub(bstr, len).as_slice_todo();
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+fn new_bstr(machine: &mut Machine, units: impl Iterator<Item = u16>) -> u32 {
+    let bytes: Vec<u8> = units.flat_map(|u| u.to_le_bytes()).collect();
+    let bstr = alloc_bstr(machine, 0, bytes.len() as u32);
+    if bstr != 0 {
+        let buf = machine.mem().sub(bstr, bytes.len() as u32);
+        buf.as_mut_slice_todo().copy_from_slice(&bytes);
+    }
+    bstr
+}
+
+#[win32_derive::dllexport(2)]
+pub fn SysAllocString(machine: &mut Machine, psz: Option<&Str16>) -> u32 {
+    let Some(psz) = psz else {
+        return 0;
+    };
+    let units = psz.buf().to_vec();
+    new_bstr(machine, units.into_iter())
+}
+
+#[win32_derive::dllexport(4)]
+pub fn SysAllocStringLen(machine: &mut Machine, strIn: u32, ui: u32) -> u32 {
+    alloc_bstr(machine, strIn, ui * 2)
+}
+
+#[win32_derive::dllexport(150)]
+pub fn SysAllocStringByteLen(machine: &mut Machine, psz: u32, len: u32) -> u32 {
+    alloc_bstr(machine, psz, len)
+}
+
+#[win32_derive::dllexport(3)]
+pub fn SysReAllocString(
+    machine: &mut Machine,
+    pbstr: Option<&mut u32>,
+    psz: Option<&Str16>,
+) -> u32 {
+    let Some(pbstr) = pbstr else {
+        return false as u32;
+    };
+    let old = *pbstr;
+    let new = SysAllocString(machine, psz);
+    if new == 0 && psz.is_some() {
+        return false as u32;
+    }
+    free_bstr(machine, old);
+    *pbstr = new;
+    true as u32
+}
+
+#[win32_derive::dllexport(6)]
+pub fn SysFreeString(machine: &mut Machine, bstrString: u32) -> u32 {
+    free_bstr(machine, bstrString);
+    0
+}
+
+#[win32_derive::dllexport(7)]
+pub fn SysStringLen(machine: &mut Machine, pbstr: u32) -> u32 {
+    bstr_byte_len(machine, pbstr) / 2
+}
+
+#[win32_derive::dllexport(149)]
+pub fn SysStringByteLen(machine: &mut Machine, bstr: u32) -> u32 {
+    bstr_byte_len(machine, bstr)
+}
+
+/*
+## VARIANTs
+
+A VARIANT is a type tag and eight bytes of value, here the numeric types, BOOL,
+BSTR, and interface pointers.  Clearing or copying one holding an interface
+calls its Release or AddRef in the guest.
+*/
+
+const VT_EMPTY: u16 = 0;
+const VT_NULL: u16 = 1;
+const VT_I2: u16 = 2;
+const VT_I4: u16 = 3;
+const VT_R4: u16 = 4;
+const VT_R8: u16 = 5;
+const VT_BSTR: u16 = 8;
+const VT_DISPATCH: u16 = 9;
+const VT_BOOL: u16 = 11;
+const VT_UNKNOWN: u16 = 13;
+const VT_I1: u16 = 16;
+const VT_UI1: u16 = 17;
+const VT_UI2: u16 = 18;
+const VT_UI4: u16 = 19;
+const VT_INT: u16 = 22;
+const VT_UINT: u16 = 23;
+const VT_BYREF: u16 = 0x4000;
+
+/// VARIANT_BOOL's true.
+const VARIANT_TRUE: u32 = 0xFFFF;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct VARIANT {
+    vt: u16,
+    wReserved1: u16,
+    wReserved2: u16,
+    wReserved3: u16,
+    data: [u32; 2],
+}
+unsafe impl memory::Pod for VARIANT {}
+
+impl VARIANT {
+    fn empty() -> Self {
+        VARIANT {
+            vt: VT_EMPTY,
+            wReserved1: 0,
+            wReserved2: 0,
+            wReserved3: 0,
+            data: [0, 0],
+        }
+    }
+
+    fn f64(&self) -> f64 {
+        f64::from_bits(self.data[0] as u64 | (self.data[1] as u64) << 32)
+    }
+}
+
+/// A VARIANT's value, read out of guest memory for conversion.
+enum Value {
+    Empty,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+}
+
+impl Value {
+    fn read(machine: &Machine, var: &VARIANT) -> Result<Value, u32> {
+        let word = var.data[0];
+        Ok(match var.vt {
+            VT_EMPTY => Value::Empty,
+            VT_I1 => Value::Int(word as i8 as i64),
+            VT_UI1 => Value::Int(word as u8 as i64),
+            VT_I2 => Value::Int(word as i16 as i64),
+            VT_UI2 => Value::Int(word as u16 as i64),
+            VT_I4 | VT_INT => Value::Int(word as i32 as i64),
+            VT_UI4 | VT_UINT => Value::Int(word as i64),
+            VT_R4 => Value::Float(f32::from_bits(word) as f64),
+            VT_R8 => Value::Float(var.f64()),
+            VT_BOOL => Value::Bool(word as u16 != 0),
+            VT_BSTR => Value::Str(read_bstr(machine, word)),
+            VT_NULL | VT_DISPATCH | VT_UNKNOWN => return Err(DISP_E_TYPEMISMATCH),
+            _ => return Err(DISP_E_BADVARTYPE),
+        })
+    }
+
+    fn to_int(&self) -> Result<i64, u32> {
+        Ok(match self {
+            Value::Empty => 0,
+            Value::Int(i) => *i,
+            // Automation rounds halves to even.
+            Value::Float(f) => {
+                let rounded = f.round_ties_even();
+                if !(i64::MIN as f64..=i64::MAX as f64).contains(&rounded) {
+                    return Err(DISP_E_OVERFLOW);
+                }
+                rounded as i64
+            }
+            Value::Bool(b) => -(*b as i64),
+            Value::Str(s) => Value::parse(s)?.to_int()?,
+        })
+    }
+
+    fn to_float(&self) -> Result<f64, u32> {
+        Ok(match self {
+            Value::Float(f) => *f,
+            Value::Str(s) => Value::parse(s)?.to_float()?,
+            value => value.to_int()? as f64,
+        })
+    }
+
+    fn to_bool(&self) -> Result<bool, u32> {
+        Ok(match self {
+            Value::Bool(b) => *b,
+            Value::Float(f) => *f != 0.0,
+            Value::Str(s) if s.eq_ignore_ascii_case("true") => true,
+            Value::Str(s) if s.eq_ignore_ascii_case("false") => false,
+            value => value.to_int()? != 0,
+        })
+    }
+
+    fn to_string(&self) -> String {
+        match self {
+            Value::Empty => String::new(),
+            Value::Int(i) => i.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::Bool(true) => "True".into(),
+            Value::Bool(false) => "False".into(),
+            Value::Str(s) => s.clone(),
+        }
+    }
+
+    /// Parse a number out of a string, as the numeric conversions do; never a Str.
+    fn parse(s: &str) -> Result<Value, u32> {
+        let s = s.trim();
+        if let Ok(i) = s.parse::<i64>() {
+            return Ok(Value::Int(i));
+        }
+        match s.parse::<f64>() {
+            Ok(f) => Ok(Value::Float(f)),
+            Err(_) => Err(DISP_E_TYPEMISMATCH),
+        }
+    }
+}
+
+/// Convert an integer to fit an integral type, checking its range.
+fn narrow(i: i64, min: i64, max: i64) -> Result<u32, u32> {
+    if i < min || i > max {
+        return Err(DISP_E_OVERFLOW);
+    }
+    Ok(i as u32)
+}
+
+/// Build a VARIANT of type vt from a value.
+fn convert(machine: &mut Machine, value: &Value, vt: u16) -> Result<VARIANT, u32> {
+    let mut var = VARIANT::empty();
+    var.vt = vt;
+    let word = match vt {
+        VT_EMPTY => 0,
+        VT_I1 => narrow(value.to_int()?, i8::MIN as i64, i8::MAX as i64)? & 0xFF,
+        VT_UI1 => narrow(value.to_int()?, 0, u8::MAX as i64)?,
+        VT_I2 => narrow(value.to_int()?, i16::MIN as i64, i16::MAX as i64)? & 0xFFFF,
+        VT_UI2 => narrow(value.to_int()?, 0, u16::MAX as i64)?,
+        VT_I4 | VT_INT => narrow(value.to_int()?, i32::MIN as i64, i32::MAX as i64)?,
+        VT_UI4 | VT_UINT => narrow(value.to_int()?, 0, u32::MAX as i64)?,
+        VT_R4 => (value.to_float()? as f32).to_bits(),
+        VT_R8 => {
+            let bits = value.to_float()?.to_bits();
+            var.data[1] = (bits >> 32) as u32;
+            bits as u32
+        }
+        VT_BOOL => {
+            if value.to_bool()? {
+                VARIANT_TRUE
+            } else {
+                0
+            }
+        }
+        VT_BSTR => {
+            let bstr = new_bstr(machine, value.to_string().encode_utf16());
+            if bstr == 0 {
+                return Err(E_OUTOFMEMORY);
+            }
+            bstr
+        }
+        VT_NULL | VT_DISPATCH | VT_UNKNOWN => return Err(DISP_E_TYPEMISMATCH),
+        _ => return Err(DISP_E_BADVARTYPE),
+    };
+    var.data[0] = word;
+    Ok(var)
+}
+
+/// Call method `index` of the interface at this, for AddRef (1) and Release (2).
+async fn call_method(machine: &mut Machine, this: u32, index: u32) {
+    if this == 0 {
+        return;
+    }
+    let vtable = machine.mem().get_pod::<u32>(this);
+    let func = machine.mem().get_pod::<u32>(vtable + index * 4);
+    machine.call_x86(func, vec![this]).await;
+}
+
+#[win32_derive::dllexport(8)]
+pub fn VariantInit(_machine: &mut Machine, pvarg: Option<&mut VARIANT>) -> u32 {
+    if let Some(var) = pvarg {
+        *var = VARIANT::empty();
+    }
+    0
+}
+
+async fn clear(machine: &mut Machine, var: &VARIANT) {
+    match var.vt {
+        VT_BSTR => free_bstr(machine, var.data[0]),
+        VT_UNKNOWN | VT_DISPATCH => call_method(machine, var.data[0], 2).await,
+        _ => {}
+    }
+}
+
+#[win32_derive::dllexport(9)]
+pub async fn VariantClear(machine: &mut Machine, pvarg: u32) -> u32 {
+    if pvarg == 0 {
+        return E_INVALIDARG;
+    }
+    let var = machine.mem().get_pod::<VARIANT>(pvarg);
+    if var.vt & VT_BYREF == 0 {
+        clear(machine, &var).await;
+    }
+    machine.mem().put::<VARIANT>(pvarg, VARIANT::empty());
+    S_OK
+}
+
+#[win32_derive::dllexport(10)]
+pub async fn VariantCopy(machine: &mut Machine, pvargDest: u32, pvargSrc: u32) -> u32 {
+    if pvargDest == 0 || pvargSrc == 0 {
+        return E_INVALIDARG;
+    }
+    let mut src = machine.mem().get_pod::<VARIANT>(pvargSrc);
+    if pvargDest == pvargSrc {
+        return S_OK;
+    }
+    let dest = machine.mem().get_pod::<VARIANT>(pvargDest);
+    if dest.vt & VT_BYREF == 0 {
+        clear(machine, &dest).await;
+    }
+    match src.vt {
+        VT_BSTR if src.data[0] != 0 => {
+            let len = bstr_byte_len(machine, src.data[0]);
+            src.data[0] = alloc_bstr(machine, src.data[0], len);
+            if src.data[0] == 0 {
+                return E_OUTOFMEMORY;
+            }
+        }
+        VT_UNKNOWN | VT_DISPATCH => call_method(machine, src.data[0], 1).await,
+        _ => {}
+    }
+    machine.mem().put::<VARIANT>(pvargDest, src);
+    S_OK
+}
+
+#[win32_derive::dllexport(12)]
+pub async fn VariantChangeType(
+    machine: &mut Machine,
+    pvargDest: u32,
+    pvarSrc: u32,
+    wFlags: u32,
+    vt: u32,
+) -> u32 {
+    if pvargDest == 0 || pvarSrc == 0 {
+        return E_INVALIDARG;
+    }
+    let vt = vt as u16;
+    let src = machine.mem().get_pod::<VARIANT>(pvarSrc);
+    if src.vt & VT_BYREF != 0 || vt & VT_BYREF != 0 {
+        log::warn!("VariantChangeType: VT_BYREF unsupported");
+        return DISP_E_BADVARTYPE;
+    }
+    if src.vt == vt {
+        return VariantCopy(machine, pvargDest, pvarSrc).await;
+    }
+    let var = match Value::read(machine, &src).and_then(|value| convert(machine, &value, vt)) {
+        Ok(var) => var,
+        Err(hr) => return hr,
+    };
+    // Clear the destination only now, as it may also be the source.
+    let dest = machine.mem().get_pod::<VARIANT>(pvargDest);
+    clear(machine, &dest).await;
+    machine.mem().put::<VARIANT>(pvargDest, var);
+    S_OK
+}
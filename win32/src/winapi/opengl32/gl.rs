@@ -0,0 +1,653 @@
+//! The gl* entry points.
+
+use super::{current, matrix::Matrix, Context, MatrixMode};
+use crate::{
+    machine::Machine,
+    winapi::raster::{DepthFunc, Texture, Vertex},
+};
+use memory::{Extensions, Mem};
+
+const TRACE_CONTEXT: &'static str = "opengl32/gl";
+
+const GL_INVALID_ENUM: u32 = 0x0500;
+const GL_INVALID_VALUE: u32 = 0x0501;
+const GL_INVALID_OPERATION: u32 = 0x0502;
+const GL_STACK_OVERFLOW: u32 = 0x0503;
+const GL_STACK_UNDERFLOW: u32 = 0x0504;
+
+const GL_DEPTH_BUFFER_BIT: u32 = 0x0100;
+const GL_COLOR_BUFFER_BIT: u32 = 0x4000;
+
+const GL_DEPTH_TEST: u32 = 0x0B71;
+const GL_TEXTURE_2D: u32 = 0x0DE1;
+
+/// Maximum depth of the matrix stacks; the spec requires at least 32 for modelview.
+const MAX_STACK_DEPTH: usize = 32;
+
+/// Run f on the current context, ignoring the call if there is none, as GL does.
+fn with_context(machine: &mut Machine, f: impl FnOnce(&mut Context)) -> u32 {
+    if let Some(ctx) = current(machine) {
+        f(ctx);
+    }
+    0
+}
+
+/// Like with_context, for calls that also access guest memory.
+fn with_context_mem(machine: &mut Machine, f: impl FnOnce(&mut Context, Mem)) -> u32 {
+    let mem = machine.emu.memory.mem();
+    let opengl32 = &mut machine.state.opengl32;
+    if let Some(ctx) = opengl32.contexts.get_mut(opengl32.current) {
+        f(ctx, mem);
+    }
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn glGetError(machine: &mut Machine) -> u32 {
+    match current(machine) {
+        Some(ctx) => std::mem::take(&mut ctx.error),
+        None => 0,
+    }
+}
+
+#[derive(Debug, win32_derive::TryFromEnum)]
+pub enum GetStringName {
+    VENDOR = 0x1F00,
+    RENDERER = 0x1F01,
+    VERSION = 0x1F02,
+    EXTENSIONS = 0x1F03,
+}
+
+#[win32_derive::dllexport]
+pub fn glGetString(machine: &mut Machine, name: Result<GetStringName, u32>) -> u32 {
+    let index = match name {
+        Ok(name) => name as usize - GetStringName::VENDOR as usize,
+        Err(_) => {
+            with_context(machine, |ctx| ctx.set_error(GL_INVALID_ENUM));
+            return 0;
+        }
+    };
+    let opengl32 = &mut machine.state.opengl32;
+    if opengl32.strings[index] == 0 {
+        let value = ["retrowin32", "software", "1.1", ""][index];
+        let addr = opengl32
+            .heap
+            .alloc(machine.emu.memory.mem(), value.len() as u32 + 1);
+        let buf = machine
+            .emu
+            .memory
+            .mem()
+            .sub(addr, value.len() as u32 + 1)
+            .as_mut_slice_todo();
+        buf[..value.len()].copy_from_slice(value.as_bytes());
+        buf[value.len()] = 0;
+        opengl32.strings[index] = addr;
+    }
+    opengl32.strings[index]
+}
+
+#[win32_derive::dllexport]
+pub fn glEnable(machine: &mut Machine, cap: u32) -> u32 {
+    with_context(machine, |ctx| match cap {
+        GL_DEPTH_TEST => ctx.depth_test = true,
+        GL_TEXTURE_2D => ctx.texture_2d = true,
+        _ => log::warn!("glEnable({cap:x}): unimplemented"),
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glDisable(machine: &mut Machine, cap: u32) -> u32 {
+    with_context(machine, |ctx| match cap {
+        GL_DEPTH_TEST => ctx.depth_test = false,
+        GL_TEXTURE_2D => ctx.texture_2d = false,
+        _ => log::warn!("glDisable({cap:x}): unimplemented"),
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glDepthFunc(machine: &mut Machine, func: u32) -> u32 {
+    with_context(machine, |ctx| {
+        ctx.depth_func = match func {
+            0x0200 => DepthFunc::Never,
+            0x0201 => DepthFunc::Less,
+            0x0202 => DepthFunc::Equal,
+            0x0203 => DepthFunc::LessEqual,
+            0x0204 => DepthFunc::Greater,
+            0x0205 => DepthFunc::NotEqual,
+            0x0206 => DepthFunc::GreaterEqual,
+            0x0207 => DepthFunc::Always,
+            _ => return ctx.set_error(GL_INVALID_ENUM),
+        }
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glViewport(machine: &mut Machine, x: i32, y: i32, width: i32, height: i32) -> u32 {
+    with_context(machine, |ctx| ctx.viewport = [x, y, width, height])
+}
+
+#[win32_derive::dllexport]
+pub fn glClearColor(machine: &mut Machine, red: f32, green: f32, blue: f32, alpha: f32) -> u32 {
+    with_context(machine, |ctx| ctx.clear_color = [red, green, blue, alpha])
+}
+
+#[win32_derive::dllexport]
+pub fn glClearDepth(machine: &mut Machine, depth: f64) -> u32 {
+    with_context(machine, |ctx| {
+        ctx.clear_depth = depth.clamp(0.0, 1.0) as f32
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glClear(machine: &mut Machine, mask: u32) -> u32 {
+    with_context(machine, |ctx| {
+        let fb = match &mut ctx.fb {
+            Some(fb) => fb,
+            None => return,
+        };
+        // TODO: obey the scissor box and viewport.
+        if mask & GL_COLOR_BUFFER_BIT != 0 {
+            fb.clear_color(ctx.clear_color);
+        }
+        if mask & GL_DEPTH_BUFFER_BIT != 0 {
+            fb.clear_depth(ctx.clear_depth);
+        }
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glFlush(_machine: &mut Machine) -> u32 {
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn glFinish(_machine: &mut Machine) -> u32 {
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn glMatrixMode(machine: &mut Machine, mode: u32) -> u32 {
+    with_context(machine, |ctx| {
+        ctx.matrix_mode = match mode {
+            0x1700 => MatrixMode::ModelView,
+            0x1701 => MatrixMode::Projection,
+            0x1702 => MatrixMode::Texture,
+            _ => return ctx.set_error(GL_INVALID_ENUM),
+        }
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glLoadIdentity(machine: &mut Machine) -> u32 {
+    with_context(machine, |ctx| *ctx.matrix() = Matrix::IDENTITY)
+}
+
+#[win32_derive::dllexport]
+pub fn glLoadMatrixf(machine: &mut Machine, m: Option<&[f32; 16]>) -> u32 {
+    let m = Matrix(*m.unwrap());
+    with_context(machine, |ctx| *ctx.matrix() = m)
+}
+
+#[win32_derive::dllexport]
+pub fn glMultMatrixf(machine: &mut Machine, m: Option<&[f32; 16]>) -> u32 {
+    let m = Matrix(*m.unwrap());
+    with_context(machine, |ctx| mult_matrix(ctx, &m))
+}
+
+fn mult_matrix(ctx: &mut Context, m: &Matrix) {
+    let cur = ctx.matrix();
+    *cur = cur.mul(m);
+}
+
+#[win32_derive::dllexport]
+pub fn glPushMatrix(machine: &mut Machine) -> u32 {
+    with_context(machine, |ctx| {
+        if ctx.matrix_stack().len() == MAX_STACK_DEPTH {
+            return ctx.set_error(GL_STACK_OVERFLOW);
+        }
+        let top = *ctx.matrix();
+        ctx.matrix_stack().push(top);
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glPopMatrix(machine: &mut Machine) -> u32 {
+    with_context(machine, |ctx| {
+        if ctx.matrix_stack().len() == 1 {
+            return ctx.set_error(GL_STACK_UNDERFLOW);
+        }
+        ctx.matrix_stack().pop();
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glTranslatef(machine: &mut Machine, x: f32, y: f32, z: f32) -> u32 {
+    with_context(machine, |ctx| mult_matrix(ctx, &Matrix::translate(x, y, z)))
+}
+
+#[win32_derive::dllexport]
+pub fn glScalef(machine: &mut Machine, x: f32, y: f32, z: f32) -> u32 {
+    with_context(machine, |ctx| mult_matrix(ctx, &Matrix::scale(x, y, z)))
+}
+
+#[win32_derive::dllexport]
+pub fn glRotatef(machine: &mut Machine, angle: f32, x: f32, y: f32, z: f32) -> u32 {
+    with_context(machine, |ctx| {
+        mult_matrix(ctx, &Matrix::rotate(angle, x, y, z))
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glOrtho(
+    machine: &mut Machine,
+    left: f64,
+    right: f64,
+    bottom: f64,
+    top: f64,
+    zNear: f64,
+    zFar: f64,
+) -> u32 {
+    let m = Matrix::ortho(
+        left as f32,
+        right as f32,
+        bottom as f32,
+        top as f32,
+        zNear as f32,
+        zFar as f32,
+    );
+    with_context(machine, |ctx| mult_matrix(ctx, &m))
+}
+
+#[win32_derive::dllexport]
+pub fn glFrustum(
+    machine: &mut Machine,
+    left: f64,
+    right: f64,
+    bottom: f64,
+    top: f64,
+    zNear: f64,
+    zFar: f64,
+) -> u32 {
+    let m = Matrix::frustum(
+        left as f32,
+        right as f32,
+        bottom as f32,
+        top as f32,
+        zNear as f32,
+        zFar as f32,
+    );
+    with_context(machine, |ctx| mult_matrix(ctx, &m))
+}
+
+const GL_TRIANGLES: u32 = 4;
+const GL_TRIANGLE_STRIP: u32 = 5;
+const GL_TRIANGLE_FAN: u32 = 6;
+const GL_QUADS: u32 = 7;
+const GL_QUAD_STRIP: u32 = 8;
+const GL_POLYGON: u32 = 9;
+
+#[win32_derive::dllexport]
+pub fn glBegin(machine: &mut Machine, mode: u32) -> u32 {
+    with_context(machine, |ctx| {
+        if ctx.primitive.is_some() {
+            return ctx.set_error(GL_INVALID_OPERATION);
+        }
+        ctx.primitive = Some((mode, Vec::new()));
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glEnd(machine: &mut Machine) -> u32 {
+    with_context(machine, |ctx| {
+        let (mode, verts) = match ctx.primitive.take() {
+            Some(p) => p,
+            None => return ctx.set_error(GL_INVALID_OPERATION),
+        };
+        let fb = match &mut ctx.fb {
+            Some(fb) => fb,
+            None => return,
+        };
+        fb.depth_func = if ctx.depth_test {
+            Some(ctx.depth_func)
+        } else {
+            None
+        };
+
+        // Indices of triangles making up the primitive.
+        let tris: Vec<[usize; 3]> = match mode {
+            GL_TRIANGLES => (0..verts.len() / 3)
+                .map(|i| [i * 3, i * 3 + 1, i * 3 + 2])
+                .collect(),
+            GL_TRIANGLE_STRIP | GL_QUAD_STRIP => {
+                (2..verts.len()).map(|i| [i - 2, i - 1, i]).collect()
+            }
+            GL_TRIANGLE_FAN | GL_POLYGON => (2..verts.len()).map(|i| [0, i - 1, i]).collect(),
+            GL_QUADS => (0..verts.len() / 4)
+                .flat_map(|i| [[i * 4, i * 4 + 1, i * 4 + 2], [i * 4, i * 4 + 2, i * 4 + 3]])
+                .collect(),
+            _ => {
+                log::warn!("glEnd: unimplemented primitive {mode:x}");
+                return;
+            }
+        };
+        // Draw with the bound texture if texturing is on and it has an image.
+        let texture = ctx
+            .textures
+            .get(&ctx.bound_texture)
+            .filter(|texture| ctx.texture_2d && texture.width > 0 && texture.height > 0);
+        for [a, b, c] in tris {
+            if let (Some(a), Some(b), Some(c)) = (&verts[a], &verts[b], &verts[c]) {
+                fb.triangle_textured([a, b, c], texture);
+            }
+        }
+    })
+}
+
+fn vertex(machine: &mut Machine, v: [f32; 4]) -> u32 {
+    with_context(machine, |ctx| {
+        let vert: Option<Vertex> = ctx.transform(v);
+        match &mut ctx.primitive {
+            Some((_, verts)) => verts.push(vert),
+            None => ctx.set_error(GL_INVALID_OPERATION),
+        }
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glVertex2f(machine: &mut Machine, x: f32, y: f32) -> u32 {
+    vertex(machine, [x, y, 0.0, 1.0])
+}
+
+#[win32_derive::dllexport]
+pub fn glVertex2i(machine: &mut Machine, x: i32, y: i32) -> u32 {
+    vertex(machine, [x as f32, y as f32, 0.0, 1.0])
+}
+
+#[win32_derive::dllexport]
+pub fn glVertex3f(machine: &mut Machine, x: f32, y: f32, z: f32) -> u32 {
+    vertex(machine, [x, y, z, 1.0])
+}
+
+#[win32_derive::dllexport]
+pub fn glVertex3fv(machine: &mut Machine, v: Option<&[f32; 3]>) -> u32 {
+    let [x, y, z] = *v.unwrap();
+    vertex(machine, [x, y, z, 1.0])
+}
+
+#[win32_derive::dllexport]
+pub fn glVertex4f(machine: &mut Machine, x: f32, y: f32, z: f32, w: f32) -> u32 {
+    vertex(machine, [x, y, z, w])
+}
+
+#[win32_derive::dllexport]
+pub fn glColor3f(machine: &mut Machine, red: f32, green: f32, blue: f32) -> u32 {
+    with_context(machine, |ctx| ctx.color = [red, green, blue, 1.0])
+}
+
+#[win32_derive::dllexport]
+pub fn glColor4f(machine: &mut Machine, red: f32, green: f32, blue: f32, alpha: f32) -> u32 {
+    with_context(machine, |ctx| ctx.color = [red, green, blue, alpha])
+}
+
+#[win32_derive::dllexport]
+pub fn glColor3ub(machine: &mut Machine, red: u32, green: u32, blue: u32) -> u32 {
+    let c = |v: u32| (v as u8) as f32 / 255.0;
+    with_context(machine, |ctx| ctx.color = [c(red), c(green), c(blue), 1.0])
+}
+
+#[win32_derive::dllexport]
+pub fn glColor4ub(machine: &mut Machine, red: u32, green: u32, blue: u32, alpha: u32) -> u32 {
+    let c = |v: u32| (v as u8) as f32 / 255.0;
+    with_context(machine, |ctx| {
+        ctx.color = [c(red), c(green), c(blue), c(alpha)]
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glShadeModel(_machine: &mut Machine, mode: u32) -> u32 {
+    // We always interpolate; GL_FLAT would use the last vertex's color.
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn glHint(_machine: &mut Machine, target: u32, mode: u32) -> u32 {
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn glTexCoord2f(machine: &mut Machine, s: f32, t: f32) -> u32 {
+    with_context(machine, |ctx| ctx.tex_coord = [s, t])
+}
+
+#[win32_derive::dllexport]
+pub fn glTexCoord2fv(machine: &mut Machine, v: Option<&[f32; 2]>) -> u32 {
+    let v = *v.unwrap();
+    with_context(machine, |ctx| ctx.tex_coord = v)
+}
+
+#[win32_derive::dllexport]
+pub fn glGenTextures(machine: &mut Machine, n: i32, textures: u32) -> u32 {
+    with_context_mem(machine, |ctx, mem| {
+        if n < 0 {
+            return ctx.set_error(GL_INVALID_VALUE);
+        }
+        for i in 0..n as u32 {
+            while ctx.textures.contains_key(&ctx.next_texture) {
+                ctx.next_texture += 1;
+            }
+            // Names are reserved by creating an empty texture for them.
+            ctx.textures.insert(ctx.next_texture, Texture::default());
+            mem.put::<u32>(textures + i * 4, ctx.next_texture);
+        }
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glDeleteTextures(machine: &mut Machine, n: i32, textures: u32) -> u32 {
+    with_context_mem(machine, |ctx, mem| {
+        if n < 0 {
+            return ctx.set_error(GL_INVALID_VALUE);
+        }
+        for i in 0..n as u32 {
+            let name = mem.get_pod::<u32>(textures + i * 4);
+            // Deleting the default texture or an unused name is ignored.
+            if name == 0 {
+                continue;
+            }
+            ctx.textures.remove(&name);
+            if ctx.bound_texture == name {
+                ctx.bound_texture = 0;
+            }
+        }
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glBindTexture(machine: &mut Machine, target: u32, texture: u32) -> u32 {
+    with_context(machine, |ctx| {
+        if target != GL_TEXTURE_2D {
+            log::warn!("glBindTexture({target:x}): unimplemented target");
+            return ctx.set_error(GL_INVALID_ENUM);
+        }
+        ctx.textures.entry(texture).or_default();
+        ctx.bound_texture = texture;
+    })
+}
+
+const GL_UNPACK_ALIGNMENT: u32 = 0x0CF5;
+
+#[win32_derive::dllexport]
+pub fn glPixelStorei(machine: &mut Machine, pname: u32, param: i32) -> u32 {
+    with_context(machine, |ctx| match (pname, param) {
+        (GL_UNPACK_ALIGNMENT, 1 | 2 | 4 | 8) => ctx.unpack_alignment = param as u32,
+        (GL_UNPACK_ALIGNMENT, _) => ctx.set_error(GL_INVALID_VALUE),
+        _ => log::warn!("glPixelStorei({pname:x}, {param}): unimplemented"),
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glTexParameteri(_machine: &mut Machine, target: u32, pname: u32, param: i32) -> u32 {
+    // We always sample the base level nearest-neighbor, repeating at the edges.
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn glTexParameterf(_machine: &mut Machine, target: u32, pname: u32, param: f32) -> u32 {
+    0 // see glTexParameteri
+}
+
+#[win32_derive::dllexport]
+pub fn glTexEnvi(_machine: &mut Machine, target: u32, pname: u32, param: i32) -> u32 {
+    // We always modulate the color by the texture, GL's default GL_MODULATE.
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn glTexEnvf(_machine: &mut Machine, target: u32, pname: u32, param: f32) -> u32 {
+    0 // see glTexEnvi
+}
+
+const GL_UNSIGNED_BYTE: u32 = 0x1401;
+const GL_ALPHA: u32 = 0x1906;
+const GL_RGB: u32 = 0x1907;
+const GL_RGBA: u32 = 0x1908;
+const GL_LUMINANCE: u32 = 0x1909;
+const GL_LUMINANCE_ALPHA: u32 = 0x190A;
+const GL_BGR_EXT: u32 = 0x80E0;
+const GL_BGRA_EXT: u32 = 0x80E1;
+
+/// Read width x height pixels of GL_UNSIGNED_BYTE components in `format` as RGBA
+/// texels, with rows padded to `alignment` bytes.  None if the format is unknown.
+fn unpack_texels(
+    mem: Mem,
+    addr: u32,
+    (width, height): (u32, u32),
+    format: u32,
+    alignment: u32,
+) -> Option<Vec<[u8; 4]>> {
+    let components = match format {
+        GL_ALPHA | GL_LUMINANCE => 1,
+        GL_LUMINANCE_ALPHA => 2,
+        GL_RGB | GL_BGR_EXT => 3,
+        GL_RGBA | GL_BGRA_EXT => 4,
+        _ => return None,
+    };
+    let stride = (width * components).next_multiple_of(alignment);
+    let mut texels = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        let row = mem
+            .sub(addr + y * stride, width * components)
+            .as_slice_todo();
+        texels.extend(row.chunks_exact(components as usize).map(|p| match format {
+            GL_ALPHA => [255, 255, 255, p[0]],
+            GL_LUMINANCE => [p[0], p[0], p[0], 255],
+            GL_LUMINANCE_ALPHA => [p[0], p[0], p[0], p[1]],
+            GL_RGB => [p[0], p[1], p[2], 255],
+            GL_BGR_EXT => [p[2], p[1], p[0], 255],
+            GL_RGBA => [p[0], p[1], p[2], p[3]],
+            GL_BGRA_EXT => [p[2], p[1], p[0], p[3]],
+            _ => unreachable!(),
+        }));
+    }
+    Some(texels)
+}
+
+#[win32_derive::dllexport]
+pub fn glTexImage2D(
+    machine: &mut Machine,
+    target: u32,
+    level: i32,
+    internalformat: i32,
+    width: i32,
+    height: i32,
+    border: i32,
+    format: u32,
+    type_: u32,
+    pixels: u32,
+) -> u32 {
+    with_context_mem(machine, |ctx, mem| {
+        if target != GL_TEXTURE_2D {
+            return ctx.set_error(GL_INVALID_ENUM);
+        }
+        if level < 0 || width < 0 || height < 0 || border != 0 {
+            return ctx.set_error(GL_INVALID_VALUE);
+        }
+        // We only sample the base level, so other levels have nowhere to go.
+        if level > 0 {
+            return;
+        }
+        if type_ != GL_UNSIGNED_BYTE {
+            log::warn!("glTexImage2D: unimplemented type {type_:x}");
+            return ctx.set_error(GL_INVALID_ENUM);
+        }
+        let size = (width as u32, height as u32);
+        let texels = if pixels == 0 {
+            // Allocate the image, leaving its contents undefined.
+            Some(vec![[0, 0, 0, 255]; (size.0 * size.1) as usize])
+        } else {
+            unpack_texels(mem, pixels, size, format, ctx.unpack_alignment)
+        };
+        let Some(texels) = texels else {
+            log::warn!("glTexImage2D: unimplemented format {format:x}");
+            return ctx.set_error(GL_INVALID_ENUM);
+        };
+        ctx.textures.insert(
+            ctx.bound_texture,
+            Texture {
+                width: size.0,
+                height: size.1,
+                texels,
+            },
+        );
+    })
+}
+
+#[win32_derive::dllexport]
+pub fn glTexSubImage2D(
+    machine: &mut Machine,
+    target: u32,
+    level: i32,
+    xoffset: i32,
+    yoffset: i32,
+    width: i32,
+    height: i32,
+    format: u32,
+    type_: u32,
+    pixels: u32,
+) -> u32 {
+    with_context_mem(machine, |ctx, mem| {
+        if target != GL_TEXTURE_2D {
+            return ctx.set_error(GL_INVALID_ENUM);
+        }
+        if level > 0 {
+            return;
+        }
+        if type_ != GL_UNSIGNED_BYTE {
+            log::warn!("glTexSubImage2D: unimplemented type {type_:x}");
+            return ctx.set_error(GL_INVALID_ENUM);
+        }
+        let alignment = ctx.unpack_alignment;
+        let Some(texture) = ctx.textures.get_mut(&ctx.bound_texture) else {
+            return ctx.set_error(GL_INVALID_OPERATION);
+        };
+        if level < 0
+            || xoffset < 0
+            || yoffset < 0
+            || width < 0
+            || height < 0
+            || (xoffset + width) as u32 > texture.width
+            || (yoffset + height) as u32 > texture.height
+        {
+            return ctx.set_error(GL_INVALID_VALUE);
+        }
+        let (width, height) = (width as u32, height as u32);
+        let Some(texels) = unpack_texels(mem, pixels, (width, height), format, alignment) else {
+            log::warn!("glTexSubImage2D: unimplemented format {format:x}");
+            return ctx.set_error(GL_INVALID_ENUM);
+        };
+        for (y, row) in texels.chunks_exact(width.max(1) as usize).enumerate() {
+            let ofs = ((yoffset as u32 + y as u32) * texture.width + xoffset as u32) as usize;
+            texture.texels[ofs..][..row.len()].copy_from_slice(row);
+        }
+    })
+}
@@ -0,0 +1,103 @@
+//! 4x4 matrices, stored column-major as in OpenGL.
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Matrix(pub [f32; 16]);
+
+impl Matrix {
+    pub const IDENTITY: Matrix = Matrix([
+        1.0, 0.0, 0.0, 0.0, //
+        0.0, 1.0, 0.0, 0.0, //
+        0.0, 0.0, 1.0, 0.0, //
+        0.0, 0.0, 0.0, 1.0, //
+    ]);
+
+    fn at(&self, row: usize, col: usize) -> f32 {
+        self.0[col * 4 + row]
+    }
+
+    pub fn mul(&self, other: &Matrix) -> Matrix {
+        let mut out = [0f32; 16];
+        for col in 0..4 {
+            for row in 0..4 {
+                out[col * 4 + row] = (0..4).map(|k| self.at(row, k) * other.at(k, col)).sum();
+            }
+        }
+        Matrix(out)
+    }
+
+    pub fn transform(&self, v: [f32; 4]) -> [f32; 4] {
+        let mut out = [0f32; 4];
+        for row in 0..4 {
+            out[row] = (0..4).map(|k| self.at(row, k) * v[k]).sum();
+        }
+        out
+    }
+
+    pub fn translate(x: f32, y: f32, z: f32) -> Matrix {
+        let mut m = Self::IDENTITY;
+        m.0[12] = x;
+        m.0[13] = y;
+        m.0[14] = z;
+        m
+    }
+
+    pub fn scale(x: f32, y: f32, z: f32) -> Matrix {
+        let mut m = Self::IDENTITY;
+        m.0[0] = x;
+        m.0[5] = y;
+        m.0[10] = z;
+        m
+    }
+
+    /// Rotation of `angle` degrees around the axis (x, y, z), as in glRotate.
+    pub fn rotate(angle: f32, x: f32, y: f32, z: f32) -> Matrix {
+        let len = (x * x + y * y + z * z).sqrt();
+        if len == 0.0 {
+            return Self::IDENTITY;
+        }
+        let (x, y, z) = (x / len, y / len, z / len);
+        let (s, c) = angle.to_radians().sin_cos();
+        let t = 1.0 - c;
+        Matrix([
+            x * x * t + c,
+            y * x * t + z * s,
+            x * z * t - y * s,
+            0.0,
+            x * y * t - z * s,
+            y * y * t + c,
+            y * z * t + x * s,
+            0.0,
+            x * z * t + y * s,
+            y * z * t - x * s,
+            z * z * t + c,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+        ])
+    }
+
+    pub fn ortho(l: f32, r: f32, b: f32, t: f32, n: f32, f: f32) -> Matrix {
+        let mut m = Self::IDENTITY;
+        m.0[0] = 2.0 / (r - l);
+        m.0[5] = 2.0 / (t - b);
+        m.0[10] = -2.0 / (f - n);
+        m.0[12] = -(r + l) / (r - l);
+        m.0[13] = -(t + b) / (t - b);
+        m.0[14] = -(f + n) / (f - n);
+        m
+    }
+
+    pub fn frustum(l: f32, r: f32, b: f32, t: f32, n: f32, f: f32) -> Matrix {
+        let mut m = [0f32; 16];
+        m[0] = 2.0 * n / (r - l);
+        m[5] = 2.0 * n / (t - b);
+        m[8] = (r + l) / (r - l);
+        m[9] = (t + b) / (t - b);
+        m[10] = -(f + n) / (f - n);
+        m[11] = -1.0;
+        m[14] = -2.0 * f * n / (f - n);
+        Matrix(m)
+    }
+}
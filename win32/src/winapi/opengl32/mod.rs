@@ -0,0 +1,325 @@
+//! OpenGL 1.1, rendered with the software rasterizer in winapi::raster.
+
+#![allow(non_snake_case)]
+
+mod gl;
+mod matrix;
+
+pub use super::gdi32::HDC;
+pub use gl::*;
+
+use super::{
+    gdi32::DCTarget,
+    heap::Heap,
+    raster::{DepthFunc, Framebuffer, Texture, Vertex},
+    types::{HANDLE, HWND},
+};
+use crate::{host, machine::Machine, winapi::handle::Handles, SurfaceOptions};
+use matrix::Matrix;
+use std::collections::HashMap;
+
+const TRACE_CONTEXT: &'static str = "opengl32";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum MatrixMode {
+    ModelView,
+    Projection,
+    Texture,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Context {
+    hdc: HDC,
+    hwnd: HWND,
+    /// Created on first wglMakeCurrent, when we know the window size.
+    fb: Option<Framebuffer>,
+    /// The host surface showing fb, which is recreated when a snapshot is restored.
+    #[serde(skip)]
+    surface: Option<Box<dyn host::Surface>>,
+
+    error: u32,
+    clear_color: [f32; 4],
+    clear_depth: f32,
+    /// x, y, width, height, with y counting from the bottom as in GL.
+    viewport: [i32; 4],
+    depth_test: bool,
+    depth_func: DepthFunc,
+
+    matrix_mode: MatrixMode,
+    /// Matrix stacks, with the current matrix last.
+    modelview: Vec<Matrix>,
+    projection: Vec<Matrix>,
+    texture: Vec<Matrix>,
+
+    color: [f32; 4],
+    tex_coord: [f32; 2],
+    /// Primitive mode and vertices between glBegin/glEnd.
+    /// Vertices behind the eye are None; we don't do proper clipping.
+    primitive: Option<(u32, Vec<Option<Vertex>>)>,
+
+    /// Texture objects by name, including the default texture 0 once it's used.
+    textures: HashMap<u32, Texture>,
+    /// The name glGenTextures tries next.
+    next_texture: u32,
+    bound_texture: u32,
+    texture_2d: bool,
+    /// GL_UNPACK_ALIGNMENT, the alignment of rows of pixels passed to glTexImage2D.
+    unpack_alignment: u32,
+}
+
+impl Context {
+    fn new(hdc: HDC, hwnd: HWND) -> Self {
+        Context {
+            hdc,
+            hwnd,
+            fb: None,
+            surface: None,
+            error: 0,
+            clear_color: [0.0, 0.0, 0.0, 0.0],
+            clear_depth: 1.0,
+            viewport: [0, 0, 0, 0],
+            depth_test: false,
+            depth_func: DepthFunc::Less,
+            matrix_mode: MatrixMode::ModelView,
+            modelview: vec![Matrix::IDENTITY],
+            projection: vec![Matrix::IDENTITY],
+            texture: vec![Matrix::IDENTITY],
+            color: [1.0, 1.0, 1.0, 1.0],
+            tex_coord: [0.0, 0.0],
+            primitive: None,
+            textures: HashMap::new(),
+            next_texture: 1,
+            bound_texture: 0,
+            texture_2d: false,
+            unpack_alignment: 4,
+        }
+    }
+
+    fn matrix_stack(&mut self) -> &mut Vec<Matrix> {
+        match self.matrix_mode {
+            MatrixMode::ModelView => &mut self.modelview,
+            MatrixMode::Projection => &mut self.projection,
+            MatrixMode::Texture => &mut self.texture,
+        }
+    }
+
+    fn matrix(&mut self) -> &mut Matrix {
+        self.matrix_stack().last_mut().unwrap()
+    }
+
+    fn set_error(&mut self, error: u32) {
+        // GL only records the first error until it is queried.
+        if self.error == 0 {
+            self.error = error;
+        }
+    }
+
+    /// Transform an object-space vertex to window coordinates.
+    fn transform(&self, v: [f32; 4]) -> Option<Vertex> {
+        let mvp = self
+            .projection
+            .last()
+            .unwrap()
+            .mul(self.modelview.last().unwrap());
+        let [x, y, z, w] = mvp.transform(v);
+        if w <= 0.0 {
+            return None;
+        }
+        let (x, y, z) = (x / w, y / w, z / w);
+        let fb_height = self.fb.as_ref().map_or(0, |fb| fb.height) as f32;
+        let [s, t, _, q] = self.texture.last().unwrap().transform([
+            self.tex_coord[0],
+            self.tex_coord[1],
+            0.0,
+            1.0,
+        ]);
+        let [vx, vy, vw, vh] = self.viewport.map(|v| v as f32);
+        Some(Vertex {
+            x: vx + (x + 1.0) / 2.0 * vw,
+            // GL window coordinates count from the bottom.
+            y: fb_height - (vy + (y + 1.0) / 2.0 * vh),
+            z: (z + 1.0) / 2.0,
+            color: self.color,
+            uv: [s / q, t / q],
+        })
+    }
+}
+
+pub type HGLRC = HANDLE<Context>;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct State {
+    heap: Heap,
+    contexts: Handles<HGLRC, Context>,
+    current: HGLRC,
+    /// Addresses of the strings returned by glGetString, allocated on first use.
+    strings: [u32; 4],
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            heap: Heap::default(),
+            contexts: Handles::default(),
+            current: HGLRC::null(),
+            strings: [0; 4],
+        }
+    }
+}
+
+impl State {
+    pub fn new_init(machine: &mut Machine) -> Self {
+        let mut opengl32 = State::default();
+        opengl32.heap = machine.state.kernel32.new_private_heap(
+            &mut machine.emu.memory,
+            0x1000,
+            "opengl32.dll heap".into(),
+        );
+        opengl32
+    }
+}
+
+/// After a snapshot is restored, recreate the host surfaces of contexts' framebuffers,
+/// showing what the current one last held.
+pub fn restore(machine: &mut Machine) {
+    let current = machine.state.opengl32.current;
+    for (hglrc, ctx) in machine.state.opengl32.contexts.iter_mut() {
+        let Some(fb) = &ctx.fb else {
+            continue;
+        };
+        let mut surface = machine.host.create_surface(&SurfaceOptions {
+            width: fb.width,
+            height: fb.height,
+            primary: true,
+        });
+        if hglrc.to_raw() == current.to_raw() {
+            let pixels: Vec<_> = fb
+                .color
+                .iter()
+                .map(|&[r, g, b, _]| [r, g, b, 255])
+                .collect();
+            surface.write_pixels(&pixels);
+            surface.show();
+        }
+        ctx.surface = Some(surface);
+    }
+}
+
+/// Get the current context, if any.
+fn current(machine: &mut Machine) -> Option<&mut Context> {
+    let opengl32 = &mut machine.state.opengl32;
+    opengl32.contexts.get_mut(opengl32.current)
+}
+
+#[win32_derive::dllexport]
+pub fn wglCreateContext(machine: &mut Machine, hdc: HDC) -> HGLRC {
+    let hwnd = match machine.state.gdi32.dcs.get(hdc) {
+        Some(dc) => match dc.target {
+            DCTarget::Window(hwnd) => hwnd,
+            _ => todo!("wglCreateContext on {:?}", dc.target),
+        },
+        None => return HGLRC::null(),
+    };
+    if machine.state.opengl32.heap.addr == 0 {
+        machine.state.opengl32 = State::new_init(machine);
+    }
+    machine.state.opengl32.contexts.add(Context::new(hdc, hwnd))
+}
+
+#[win32_derive::dllexport]
+pub fn wglDeleteContext(machine: &mut Machine, hglrc: HGLRC) -> bool {
+    let opengl32 = &mut machine.state.opengl32;
+    if opengl32.current.to_raw() == hglrc.to_raw() {
+        opengl32.current = HGLRC::null();
+    }
+    opengl32.contexts.remove(hglrc).is_some()
+}
+
+#[win32_derive::dllexport]
+pub fn wglMakeCurrent(machine: &mut Machine, hdc: HDC, hglrc: HGLRC) -> bool {
+    if hglrc.is_null() {
+        machine.state.opengl32.current = HGLRC::null();
+        return true;
+    }
+    let ctx = match machine.state.opengl32.contexts.get_mut(hglrc) {
+        Some(ctx) => ctx,
+        None => return false,
+    };
+    if ctx.fb.is_none() {
+        let (width, height) = match machine.state.user32.windows.get(ctx.hwnd) {
+            Some(window) => (window.width, window.height),
+            None => (640, 480),
+        };
+        ctx.fb = Some(Framebuffer::new(width, height));
+        ctx.viewport = [0, 0, width as i32, height as i32];
+        ctx.surface = Some(machine.host.create_surface(&SurfaceOptions {
+            width,
+            height,
+            primary: true,
+        }));
+    }
+    machine.state.opengl32.current = hglrc;
+    true
+}
+
+#[win32_derive::dllexport]
+pub fn wglGetCurrentContext(machine: &mut Machine) -> HGLRC {
+    machine.state.opengl32.current
+}
+
+#[win32_derive::dllexport]
+pub fn wglGetCurrentDC(machine: &mut Machine) -> HDC {
+    match current(machine) {
+        Some(ctx) => ctx.hdc,
+        None => HDC::null(),
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn wglGetProcAddress(_machine: &mut Machine, lpszProc: Option<&str>) -> u32 {
+    log::warn!("wglGetProcAddress({lpszProc:?}): extensions not supported");
+    0
+}
+
+/// Gather contexts' framebuffers for a frame dump.
+pub fn dump_framebuffers(machine: &Machine) -> Vec<crate::framedump::SurfaceDump> {
+    let mut surfaces: Vec<_> = machine
+        .state
+        .opengl32
+        .contexts
+        .entries()
+        .filter_map(|(hglrc, ctx)| {
+            let fb = ctx.fb.as_ref()?;
+            Some(crate::framedump::SurfaceDump {
+                kind: "opengl32",
+                addr: hglrc.to_raw(),
+                width: fb.width,
+                height: fb.height,
+                attached: 0,
+                pixels: Some(fb.color.clone()),
+            })
+        })
+        .collect();
+    surfaces.sort_by_key(|s| s.addr);
+    surfaces
+}
+
+/// Show the current context's framebuffer; called by gdi32 SwapBuffers.
+pub fn swap_buffers(machine: &mut Machine, hdc: HDC) -> bool {
+    let ctx = match current(machine) {
+        Some(ctx) if ctx.hdc.to_raw() == hdc.to_raw() => ctx,
+        _ => return false,
+    };
+    if let (Some(fb), Some(surface)) = (&ctx.fb, &mut ctx.surface) {
+        // The window has no use for alpha.
+        let pixels: Vec<_> = fb
+            .color
+            .iter()
+            .map(|&[r, g, b, _]| [r, g, b, 255])
+            .collect();
+        surface.write_pixels(&pixels);
+        surface.show();
+    }
+    machine.presented();
+    true
+}
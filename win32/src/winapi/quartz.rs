@@ -0,0 +1,1188 @@
+//! DirectShow, enough of a filter graph to play a game's cutscenes.
+
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+
+use super::avi::{Avi, VideoDecoder};
+use super::com::{self, IID_IUnknown, E_NOINTERFACE, E_POINTER, S_FALSE, S_OK};
+use super::heap::Heap;
+use super::kernel32::{self, HEVENT};
+use super::types::{Str16, DWORD, HWND, RECT};
+use super::user32::{self, Window};
+use crate::{host::Host, machine::Emulator, machine::Machine, winapi::vtable};
+use memory::Mem;
+use std::collections::{HashMap, VecDeque};
+
+const TRACE_CONTEXT: &'static str = "quartz";
+
+/*
+## Playback
+
+A filter graph holds at most one movie, loaded by RenderFile.  Rather than building
+a graph of filters, the file is parsed whole as an AVI and its video frames are
+decoded here, for the codecs avi::VideoDecoder knows, and drawn scaled into the
+video window's rectangle of its owner window.  Audio streams aren't played.
+
+Without an owner, the video goes to the first top-level window, standing in for
+the popup window DirectShow would create.  A DirectDraw window only shows the
+video when the game next flips, as with other GDI drawing.
+
+As with dsound, nothing runs on its own: update() is called from the graph's
+methods and, via winapi::poll_devices, whenever the guest waits or pumps messages.
+Movies whose video can't be decoded complete as soon as they are run, so games
+wait for EC_COMPLETE and move on as if the cutscene had played.
+
+Snapshots record each graph with the path and position of its movie, which is
+loaded again on restore and decoded from the start up to that position.
+*/
+
+const E_ABORT: u32 = 0x8000_4004;
+const E_NOTIMPL: u32 = 0x8000_4001;
+const VFW_E_UNKNOWN_FILE_TYPE: u32 = 0x8004_0240;
+const VFW_E_WRONG_STATE: u32 = 0x8004_0227;
+/// HRESULT_FROM_WIN32(ERROR_FILE_NOT_FOUND).
+const HRESULT_FILE_NOT_FOUND: u32 = 0x8007_0002;
+
+const EC_COMPLETE: u32 = 0x01;
+
+const OATRUE: i32 = -1;
+const OAFALSE: i32 = 0;
+
+const IID_IFilterGraph: com::GUID = [
+    0x9f, 0x68, 0xa8, 0x56, 0xd4, 0x0a, 0xce, 0x11, 0xb0, 0x3a, 0x00, 0x20, 0xaf, 0x0b, 0xa7, 0x70,
+];
+const IID_IGraphBuilder: com::GUID = [
+    0xa9, 0x68, 0xa8, 0x56, 0xd4, 0x0a, 0xce, 0x11, 0xb0, 0x3a, 0x00, 0x20, 0xaf, 0x0b, 0xa7, 0x70,
+];
+const IID_IMediaControl: com::GUID = [
+    0xb1, 0x68, 0xa8, 0x56, 0xd4, 0x0a, 0xce, 0x11, 0xb0, 0x3a, 0x00, 0x20, 0xaf, 0x0b, 0xa7, 0x70,
+];
+const IID_IMediaEvent: com::GUID = [
+    0xb6, 0x68, 0xa8, 0x56, 0xd4, 0x0a, 0xce, 0x11, 0xb0, 0x3a, 0x00, 0x20, 0xaf, 0x0b, 0xa7, 0x70,
+];
+const IID_IMediaEventEx: com::GUID = [
+    0xc0, 0x68, 0xa8, 0x56, 0xd4, 0x0a, 0xce, 0x11, 0xb0, 0x3a, 0x00, 0x20, 0xaf, 0x0b, 0xa7, 0x70,
+];
+const IID_IVideoWindow: com::GUID = [
+    0xb4, 0x68, 0xa8, 0x56, 0xd4, 0x0a, 0xce, 0x11, 0xb0, 0x3a, 0x00, 0x20, 0xaf, 0x0b, 0xa7, 0x70,
+];
+
+/// A graph object is one allocation holding a vtable pointer per interface, as a
+/// C++ object with multiple inheritance would; these are the interfaces' offsets
+/// within it.  Every interface shares the graph's reference count.
+const OFS_IGraphBuilder: u32 = 0;
+const OFS_IMediaControl: u32 = 4;
+const OFS_IMediaEventEx: u32 = 8;
+const OFS_IVideoWindow: u32 = 12;
+const GRAPH_SIZE: u32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+enum FilterState {
+    Stopped = 0,
+    Paused = 1,
+    Running = 2,
+}
+
+/// A loaded movie and how far it has played.
+struct Movie {
+    path: String,
+    /// None if the video can't be decoded, or there is none.
+    decoder: Option<VideoDecoder>,
+    frames: Vec<Vec<u8>>,
+    frame_usec: u32,
+    /// Milliseconds played before the current run.
+    played_ms: u32,
+    /// Host time the current run started, while running.
+    run_start: Option<u32>,
+    /// Count of frames decoded so far.
+    decoded: usize,
+    complete: bool,
+}
+
+impl Movie {
+    fn load(path: &str, buf: &[u8]) -> Option<Movie> {
+        let avi = Avi::parse(buf)?;
+        let (decoder, frames, frame_usec) = match avi.video() {
+            Some(video) => (
+                VideoDecoder::new(&video.format),
+                video.chunks.clone(),
+                avi.frame_usec(video),
+            ),
+            None => (None, Vec::new(), 0),
+        };
+        Some(Movie {
+            path: path.to_string(),
+            decoder,
+            frames,
+            frame_usec,
+            played_ms: 0,
+            run_start: None,
+            decoded: 0,
+            complete: false,
+        })
+    }
+
+    fn position_ms(&self, now: u32) -> u32 {
+        self.played_ms + self.run_start.map_or(0, |start| now - start)
+    }
+
+    fn pause(&mut self, now: u32) {
+        self.played_ms = self.position_ms(now);
+        self.run_start = None;
+    }
+}
+
+struct Graph {
+    state: FilterState,
+    movie: Option<Movie>,
+    /// Window the video is drawn into, from IVideoWindow::put_Owner.
+    owner: HWND,
+    /// The video's rectangle within the owner, if placed; otherwise it fills the owner.
+    position: Option<RECT>,
+    visible: bool,
+    /// Manual-reset event, signaled while events are queued.
+    event: HEVENT,
+    /// Queued events as (code, param1, param2).
+    events: VecDeque<(u32, u32, u32)>,
+    /// Window and message to post when an event is queued, from SetNotifyWindow.
+    notify: Option<(HWND, u32, u32)>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct State {
+    heap: Heap,
+    vtable_IGraphBuilder: u32,
+    vtable_IMediaControl: u32,
+    vtable_IMediaEventEx: u32,
+    vtable_IVideoWindow: u32,
+    /// References to graphs, by graph address.
+    refs: com::RefCounts,
+    /// Graphs hold decoders, so snapshots record them separately; see save.
+    #[serde(skip)]
+    graphs: HashMap<u32, Graph>,
+}
+
+impl State {
+    pub fn new_init(machine: &mut Machine) -> Self {
+        let mut quartz = State::default();
+        quartz.heap = machine.state.kernel32.new_private_heap(
+            &mut machine.emu.memory,
+            1 << 20,
+            "quartz.dll heap".into(),
+        );
+        quartz.vtable_IGraphBuilder = IGraphBuilder::vtable(&mut quartz, machine);
+        quartz.vtable_IMediaControl = IMediaControl::vtable(&mut quartz, machine);
+        quartz.vtable_IMediaEventEx = IMediaEventEx::vtable(&mut quartz, machine);
+        quartz.vtable_IVideoWindow = IVideoWindow::vtable(&mut quartz, machine);
+        quartz
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            heap: Heap::default(),
+            vtable_IGraphBuilder: 0,
+            vtable_IMediaControl: 0,
+            vtable_IMediaEventEx: 0,
+            vtable_IVideoWindow: 0,
+            refs: com::RefCounts::default(),
+            graphs: HashMap::new(),
+        }
+    }
+}
+
+/// A graph as recorded in a snapshot.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct GraphRecord {
+    addr: u32,
+    state: FilterState,
+    /// The movie's path and position in ms, and whether it completed.
+    movie: Option<(String, u32, bool)>,
+    owner: HWND,
+    position: Option<RECT>,
+    visible: bool,
+    event: HEVENT,
+    events: Vec<(u32, u32, u32)>,
+    notify: Option<(HWND, u32, u32)>,
+}
+
+/// Record the graphs, for snapshots.
+pub fn save(machine: &Machine) -> Vec<GraphRecord> {
+    let now = machine.host.time();
+    (machine.state.quartz.graphs.iter())
+        .map(|(&addr, g)| GraphRecord {
+            addr,
+            state: g.state,
+            movie: (g.movie.as_ref()).map(|m| (m.path.clone(), m.position_ms(now), m.complete)),
+            owner: g.owner,
+            position: g.position,
+            visible: g.visible,
+            event: g.event,
+            events: g.events.iter().copied().collect(),
+            notify: g.notify,
+        })
+        .collect()
+}
+
+/// Replace the graphs with those recorded in a snapshot, loading their movies
+/// again.  A movie that is gone leaves its graph empty, as before RenderFile.
+pub fn restore(machine: &mut Machine, records: Vec<GraphRecord>) {
+    let now = machine.host.time();
+    let mut graphs = HashMap::new();
+    for record in records {
+        let movie = record.movie.and_then(|(path, position, complete)| {
+            let movie = Movie::load(&path, &kernel32::read_file(machine, &path));
+            if movie.is_none() {
+                log::warn!("quartz: can't reload {path:?}");
+            }
+            movie.map(|movie| Movie {
+                played_ms: position,
+                run_start: (record.state == FilterState::Running).then_some(now),
+                complete,
+                ..movie
+            })
+        });
+        graphs.insert(
+            record.addr,
+            Graph {
+                state: record.state,
+                movie,
+                owner: record.owner,
+                position: record.position,
+                visible: record.visible,
+                event: record.event,
+                events: record.events.into(),
+                notify: record.notify,
+            },
+        );
+    }
+    machine.state.quartz.graphs = graphs;
+}
+
+/// CoCreateInstance(CLSID_FilterGraph).
+pub fn create_filter_graph(machine: &mut Machine, riid: u32, ppv: u32) -> u32 {
+    if machine.state.quartz.heap.addr == 0 {
+        machine.state.quartz = State::new_init(machine);
+    }
+    let event = kernel32::CreateEventA(machine, 0, true, false, None);
+    let quartz = &mut machine.state.quartz;
+    let graph = quartz.heap.alloc(machine.emu.memory.mem(), GRAPH_SIZE);
+    let mem = machine.emu.memory.mem();
+    mem.put::<u32>(graph + OFS_IGraphBuilder, quartz.vtable_IGraphBuilder);
+    mem.put::<u32>(graph + OFS_IMediaControl, quartz.vtable_IMediaControl);
+    mem.put::<u32>(graph + OFS_IMediaEventEx, quartz.vtable_IMediaEventEx);
+    mem.put::<u32>(graph + OFS_IVideoWindow, quartz.vtable_IVideoWindow);
+    quartz.graphs.insert(
+        graph,
+        Graph {
+            state: FilterState::Stopped,
+            movie: None,
+            owner: HWND::null(),
+            position: None,
+            visible: true,
+            event,
+            events: VecDeque::new(),
+            notify: None,
+        },
+    );
+    // Hand out the requested interface, dropping the reference creation made.
+    let hr = query_interface(machine, graph, riid, ppv);
+    release(machine, graph);
+    hr
+}
+
+fn query_interface(machine: &mut Machine, graph: u32, riid: u32, ppv: u32) -> u32 {
+    if ppv == 0 {
+        return E_POINTER;
+    }
+    let iid = com::read_guid(machine, riid);
+    let ofs = if iid == IID_IUnknown || iid == IID_IFilterGraph || iid == IID_IGraphBuilder {
+        OFS_IGraphBuilder
+    } else if iid == IID_IMediaControl {
+        OFS_IMediaControl
+    } else if iid == IID_IMediaEvent || iid == IID_IMediaEventEx {
+        OFS_IMediaEventEx
+    } else if iid == IID_IVideoWindow {
+        OFS_IVideoWindow
+    } else {
+        log::warn!("QueryInterface({graph:x}): unknown IID {iid:x?}");
+        machine.mem().put::<u32>(ppv, 0);
+        return E_NOINTERFACE;
+    };
+    machine.state.quartz.refs.add_ref(graph);
+    machine.mem().put::<u32>(ppv, graph + ofs);
+    S_OK
+}
+
+fn add_ref(machine: &mut Machine, graph: u32) -> u32 {
+    machine.state.quartz.refs.add_ref(graph)
+}
+
+fn release(machine: &mut Machine, graph: u32) -> u32 {
+    let quartz = &mut machine.state.quartz;
+    let refs = quartz.refs.release(graph);
+    if refs == 0 {
+        quartz.graphs.remove(&graph);
+        quartz.heap.free(machine.emu.memory.mem(), graph);
+    }
+    refs
+}
+
+/// Queue an event, signaling the event handle and notifying the notify window.
+fn queue_event(machine: &mut Machine, graph: u32, code: u32, param1: u32, param2: u32) {
+    let Some(g) = machine.state.quartz.graphs.get_mut(&graph) else {
+        return;
+    };
+    g.events.push_back((code, param1, param2));
+    let event = g.event;
+    let notify = g.notify;
+    kernel32::set_event(machine, event);
+    if let Some((hwnd, msg, lparam)) = notify {
+        user32::post_message(machine, hwnd, msg, 0, lparam);
+    }
+}
+
+/// Draw a decoded frame, scaled, into a rectangle of a window.
+/// Returns whether it went to the screen, as Window::flush_pixels().
+fn draw_frame(
+    window: &mut Window,
+    host: &mut dyn Host,
+    mem: Mem,
+    decoder: &VideoDecoder,
+    rect: RECT,
+) -> bool {
+    let bitmap = window.bitmap_mut(host);
+    let (dst_w, dst_h) = (bitmap.width as i32, bitmap.height as i32);
+    let dst = bitmap.pixels.as_slice_mut(mem);
+    let (w, h) = (rect.right - rect.left, rect.bottom - rect.top);
+    if w <= 0 || h <= 0 {
+        return false;
+    }
+    for y in std::cmp::max(rect.top, 0)..std::cmp::min(rect.bottom, dst_h) {
+        let sy = ((y - rect.top) as i64 * decoder.height as i64 / h as i64) as usize;
+        let src = &decoder.pixels[sy * decoder.width as usize..];
+        let row = &mut dst[(y * dst_w) as usize..];
+        for x in std::cmp::max(rect.left, 0)..std::cmp::min(rect.right, dst_w) {
+            let sx = ((x - rect.left) as i64 * decoder.width as i64 / w as i64) as usize;
+            row[x as usize] = src[sx];
+        }
+    }
+    window.flush_pixels(mem, rect)
+}
+
+/// Advance playback of running graphs: decode the frames due and draw the latest,
+/// completing movies that reach their end.
+/// Returns the host time by which it should be called again, if anything is playing.
+pub fn update(machine: &mut Machine) -> Option<u32> {
+    let quartz = &mut machine.state.quartz;
+    if !quartz
+        .graphs
+        .values()
+        .any(|g| g.state == FilterState::Running)
+    {
+        return None;
+    }
+    let now = machine.host.time();
+    let user32 = &mut machine.state.user32;
+    let mem = machine.emu.memory.mem();
+    let mut next = None;
+    let mut completed = Vec::new();
+    let mut presented = false;
+    for (&addr, graph) in quartz.graphs.iter_mut() {
+        if graph.state != FilterState::Running {
+            continue;
+        }
+        let Some(movie) = graph.movie.as_mut() else {
+            continue;
+        };
+        if movie.complete {
+            continue;
+        }
+        let pos_ms = movie.position_ms(now);
+        let due = match movie.frame_usec {
+            0 => 0,
+            usec => (pos_ms as u64 * 1000 / usec as u64) as usize,
+        };
+        if movie.decoder.is_none() || due >= movie.frames.len() {
+            movie.complete = true;
+            completed.push(addr);
+            continue;
+        }
+        let next_frame_ms = ((due as u64 + 1) * movie.frame_usec as u64 / 1000) as u32;
+        let until = now + (next_frame_ms - pos_ms);
+        next = Some(next.map_or(until, |n: u32| std::cmp::min(n, until)));
+        if movie.decoded > due {
+            continue;
+        }
+        let decoder = movie.decoder.as_mut().unwrap();
+        // Frames depend on those before them, so none can be skipped.
+        while movie.decoded <= due {
+            decoder.decode(&movie.frames[movie.decoded]);
+            movie.decoded += 1;
+        }
+        if !graph.visible {
+            continue;
+        }
+        let hwnd = if !graph.owner.is_null() {
+            graph.owner
+        } else {
+            match user32
+                .windows
+                .iter()
+                .filter(|w| w.parent.is_null())
+                .map(|w| w.hwnd)
+                .min_by_key(|hwnd| hwnd.to_raw())
+            {
+                Some(hwnd) => hwnd,
+                None => continue,
+            }
+        };
+        let Some(window) = user32.windows.get_mut(hwnd) else {
+            continue;
+        };
+        let rect = graph.position.unwrap_or(window.client_rect());
+        presented |= draw_frame(window, &mut *machine.host, mem, decoder, rect);
+    }
+    if presented {
+        machine.presented();
+    }
+    for graph in completed {
+        queue_event(machine, graph, EC_COMPLETE, S_OK, 0);
+    }
+    next
+}
+
+#[win32_derive::shims_from_x86]
+mod IGraphBuilder {
+    use super::*;
+
+    fn graph(this: u32) -> u32 {
+        this - OFS_IGraphBuilder
+    }
+
+    #[win32_derive::dllexport]
+    pub fn QueryInterface(machine: &mut Machine, this: u32, riid: u32, ppvObject: u32) -> u32 {
+        query_interface(machine, graph(this), riid, ppvObject)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn AddRef(machine: &mut Machine, this: u32) -> u32 {
+        add_ref(machine, graph(this))
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Release(machine: &mut Machine, this: u32) -> u32 {
+        release(machine, graph(this))
+    }
+
+    #[win32_derive::dllexport]
+    pub fn RenderFile(
+        machine: &mut Machine,
+        this: u32,
+        lpcwstrFile: Option<&Str16>,
+        lpcwstrPlayList: u32,
+    ) -> u32 {
+        let Some(file) = lpcwstrFile else {
+            return E_POINTER;
+        };
+        render_file(machine, graph(this), &file.to_string())
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetLogFile(_machine: &mut Machine, this: u32, hFile: u32) -> u32 {
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Abort(_machine: &mut Machine, this: u32) -> u32 {
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn ShouldOperationContinue(_machine: &mut Machine, this: u32) -> u32 {
+        S_OK
+    }
+
+    vtable![IGraphBuilder shims
+        QueryInterface ok,
+        AddRef ok,
+        Release ok,
+        AddFilter todo,
+        RemoveFilter todo,
+        EnumFilters todo,
+        FindFilterByName todo,
+        ConnectDirect todo,
+        Reconnect todo,
+        Disconnect todo,
+        SetDefaultSyncSource todo,
+        Connect todo,
+        Render todo,
+        RenderFile ok,
+        AddSourceFilter todo,
+        SetLogFile ok,
+        Abort ok,
+        ShouldOperationContinue ok,
+    ];
+}
+
+/// Load a movie into a graph, replacing any it had.
+fn render_file(machine: &mut Machine, graph: u32, path: &str) -> u32 {
+    let buf = kernel32::read_file(machine, path);
+    if buf.is_empty() {
+        return HRESULT_FILE_NOT_FOUND;
+    }
+    let Some(movie) = Movie::load(path, &buf) else {
+        log::warn!("RenderFile({path:?}): not an AVI file");
+        return VFW_E_UNKNOWN_FILE_TYPE;
+    };
+    if movie.decoder.is_none() {
+        log::warn!("RenderFile({path:?}): can't decode video, it will complete when run");
+    }
+    let Some(g) = machine.state.quartz.graphs.get_mut(&graph) else {
+        return E_POINTER;
+    };
+    g.movie = Some(movie);
+    S_OK
+}
+
+/// Change a graph's state, starting or holding the movie's clock.
+fn set_state(machine: &mut Machine, graph: u32, state: FilterState) -> u32 {
+    let now = machine.host.time();
+    let Some(g) = machine.state.quartz.graphs.get_mut(&graph) else {
+        return E_POINTER;
+    };
+    g.state = state;
+    if let Some(movie) = g.movie.as_mut() {
+        match state {
+            FilterState::Running if movie.run_start.is_none() => movie.run_start = Some(now),
+            FilterState::Running => {}
+            FilterState::Paused | FilterState::Stopped => movie.pause(now),
+        }
+    }
+    update(machine);
+    S_OK
+}
+
+#[win32_derive::shims_from_x86]
+mod IMediaControl {
+    use super::*;
+
+    fn graph(this: u32) -> u32 {
+        this - OFS_IMediaControl
+    }
+
+    #[win32_derive::dllexport]
+    pub fn QueryInterface(machine: &mut Machine, this: u32, riid: u32, ppvObject: u32) -> u32 {
+        query_interface(machine, graph(this), riid, ppvObject)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn AddRef(machine: &mut Machine, this: u32) -> u32 {
+        add_ref(machine, graph(this))
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Release(machine: &mut Machine, this: u32) -> u32 {
+        release(machine, graph(this))
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Run(machine: &mut Machine, this: u32) -> u32 {
+        set_state(machine, graph(this), FilterState::Running)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Pause(machine: &mut Machine, this: u32) -> u32 {
+        set_state(machine, graph(this), FilterState::Paused)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Stop(machine: &mut Machine, this: u32) -> u32 {
+        set_state(machine, graph(this), FilterState::Stopped)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn StopWhenReady(machine: &mut Machine, this: u32) -> u32 {
+        set_state(machine, graph(this), FilterState::Stopped)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetState(
+        machine: &mut Machine,
+        this: u32,
+        msTimeout: u32,
+        pfs: Option<&mut u32>,
+    ) -> u32 {
+        let Some(pfs) = pfs else {
+            return E_POINTER;
+        };
+        match machine.state.quartz.graphs.get(&graph(this)) {
+            Some(g) => *pfs = g.state as u32,
+            None => return E_POINTER,
+        }
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn RenderFile(machine: &mut Machine, this: u32, strFilename: Option<&Str16>) -> u32 {
+        let Some(file) = strFilename else {
+            return E_POINTER;
+        };
+        render_file(machine, graph(this), &file.to_string())
+    }
+
+    vtable![IMediaControl shims
+        QueryInterface ok,
+        AddRef ok,
+        Release ok,
+        GetTypeInfoCount todo,
+        GetTypeInfo todo,
+        GetIDsOfNames todo,
+        Invoke todo,
+        Run ok,
+        Pause ok,
+        Stop ok,
+        GetState ok,
+        RenderFile ok,
+        AddSourceFilter todo,
+        get_FilterCollection todo,
+        get_RegFilterCollection todo,
+        StopWhenReady ok,
+    ];
+}
+
+#[win32_derive::shims_from_x86]
+mod IMediaEventEx {
+    use super::*;
+
+    fn graph(this: u32) -> u32 {
+        this - OFS_IMediaEventEx
+    }
+
+    #[win32_derive::dllexport]
+    pub fn QueryInterface(machine: &mut Machine, this: u32, riid: u32, ppvObject: u32) -> u32 {
+        query_interface(machine, graph(this), riid, ppvObject)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn AddRef(machine: &mut Machine, this: u32) -> u32 {
+        add_ref(machine, graph(this))
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Release(machine: &mut Machine, this: u32) -> u32 {
+        release(machine, graph(this))
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetEventHandle(machine: &mut Machine, this: u32, hEvent: Option<&mut u32>) -> u32 {
+        let Some(hEvent) = hEvent else {
+            return E_POINTER;
+        };
+        match machine.state.quartz.graphs.get(&graph(this)) {
+            Some(g) => *hEvent = g.event.to_raw(),
+            None => return E_POINTER,
+        }
+        S_OK
+    }
+
+    /// Take the next event, waiting up to msTimeout for one.
+    #[win32_derive::dllexport]
+    pub async fn GetEvent(
+        machine: &mut Machine,
+        this: u32,
+        lEventCode: u32,
+        lParam1: u32,
+        lParam2: u32,
+        msTimeout: u32,
+    ) -> u32 {
+        update(machine);
+        let Some(event) = machine
+            .state
+            .quartz
+            .graphs
+            .get(&graph(this))
+            .map(|g| g.event)
+        else {
+            return E_POINTER;
+        };
+        if msTimeout != 0 {
+            kernel32::WaitForSingleObject(machine, event, msTimeout).await;
+        }
+        let g = machine.state.quartz.graphs.get_mut(&graph(this)).unwrap();
+        let Some((code, param1, param2)) = g.events.pop_front() else {
+            return E_ABORT;
+        };
+        let drained = g.events.is_empty();
+        if drained {
+            kernel32::ResetEvent(machine, event);
+        }
+        let mem = machine.mem();
+        mem.put::<u32>(lEventCode, code);
+        mem.put::<u32>(lParam1, param1);
+        mem.put::<u32>(lParam2, param2);
+        S_OK
+    }
+
+    /// Wait for the movie to play to its end.
+    #[win32_derive::dllexport]
+    pub async fn WaitForCompletion(
+        machine: &mut Machine,
+        this: u32,
+        msTimeout: u32,
+        pEvCode: u32,
+    ) -> u32 {
+        update(machine);
+        let Some(g) = machine.state.quartz.graphs.get(&graph(this)) else {
+            return E_POINTER;
+        };
+        if g.state != FilterState::Running {
+            return VFW_E_WRONG_STATE;
+        }
+        let event = g.event;
+        let complete = |machine: &Machine| {
+            machine
+                .state
+                .quartz
+                .graphs
+                .get(&graph(this))
+                .map_or(false, |g| g.movie.as_ref().map_or(false, |m| m.complete))
+        };
+        if !complete(machine) {
+            kernel32::WaitForSingleObject(machine, event, msTimeout).await;
+        }
+        if !complete(machine) {
+            return E_ABORT;
+        }
+        if pEvCode != 0 {
+            machine.mem().put::<u32>(pEvCode, EC_COMPLETE);
+        }
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn CancelDefaultHandling(_machine: &mut Machine, this: u32, lEvCode: u32) -> u32 {
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn RestoreDefaultHandling(_machine: &mut Machine, this: u32, lEvCode: u32) -> u32 {
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn FreeEventParams(
+        _machine: &mut Machine,
+        this: u32,
+        lEvCode: u32,
+        lParam1: u32,
+        lParam2: u32,
+    ) -> u32 {
+        // No event we queue has parameters that need freeing.
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetNotifyWindow(
+        machine: &mut Machine,
+        this: u32,
+        hwnd: HWND,
+        lMsg: u32,
+        lInstanceData: u32,
+    ) -> u32 {
+        let Some(g) = machine.state.quartz.graphs.get_mut(&graph(this)) else {
+            return E_POINTER;
+        };
+        g.notify = if hwnd.is_null() {
+            None
+        } else {
+            Some((hwnd, lMsg, lInstanceData))
+        };
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetNotifyFlags(_machine: &mut Machine, this: u32, lNoNotifyFlags: u32) -> u32 {
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetNotifyFlags(
+        _machine: &mut Machine,
+        this: u32,
+        lplNoNotifyFlags: Option<&mut u32>,
+    ) -> u32 {
+        let Some(flags) = lplNoNotifyFlags else {
+            return E_POINTER;
+        };
+        *flags = 0;
+        S_OK
+    }
+
+    vtable![IMediaEventEx shims
+        QueryInterface ok,
+        AddRef ok,
+        Release ok,
+        GetTypeInfoCount todo,
+        GetTypeInfo todo,
+        GetIDsOfNames todo,
+        Invoke todo,
+        GetEventHandle ok,
+        GetEvent ok,
+        WaitForCompletion ok,
+        CancelDefaultHandling ok,
+        RestoreDefaultHandling ok,
+        FreeEventParams ok,
+        SetNotifyWindow ok,
+        SetNotifyFlags ok,
+        GetNotifyFlags ok,
+    ];
+}
+
+#[win32_derive::shims_from_x86]
+mod IVideoWindow {
+    use super::*;
+
+    fn graph(this: u32) -> u32 {
+        this - OFS_IVideoWindow
+    }
+
+    /// The video window's current rectangle within its owner.
+    fn position(machine: &Machine, g: &Graph) -> RECT {
+        if let Some(rect) = g.position {
+            return rect;
+        }
+        match machine.state.user32.windows.get(g.owner) {
+            Some(window) => window.client_rect(),
+            None => RECT::default(),
+        }
+    }
+
+    /// Change one edge of the video window's rectangle.
+    fn update_position(machine: &mut Machine, this: u32, f: impl FnOnce(&mut RECT)) -> u32 {
+        let Some(g) = machine.state.quartz.graphs.get(&graph(this)) else {
+            return E_POINTER;
+        };
+        let mut rect = position(machine, g);
+        f(&mut rect);
+        machine
+            .state
+            .quartz
+            .graphs
+            .get_mut(&graph(this))
+            .unwrap()
+            .position = Some(rect);
+        S_OK
+    }
+
+    /// Read one edge of the video window's rectangle.
+    fn get_position(
+        machine: &mut Machine,
+        this: u32,
+        out: Option<&mut i32>,
+        f: impl FnOnce(&RECT) -> i32,
+    ) -> u32 {
+        let (Some(g), Some(out)) = (machine.state.quartz.graphs.get(&graph(this)), out) else {
+            return E_POINTER;
+        };
+        *out = f(&position(machine, g));
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn QueryInterface(machine: &mut Machine, this: u32, riid: u32, ppvObject: u32) -> u32 {
+        query_interface(machine, graph(this), riid, ppvObject)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn AddRef(machine: &mut Machine, this: u32) -> u32 {
+        add_ref(machine, graph(this))
+    }
+
+    #[win32_derive::dllexport]
+    pub fn Release(machine: &mut Machine, this: u32) -> u32 {
+        release(machine, graph(this))
+    }
+
+    #[win32_derive::dllexport]
+    pub fn put_Owner(machine: &mut Machine, this: u32, Owner: HWND) -> u32 {
+        let Some(g) = machine.state.quartz.graphs.get_mut(&graph(this)) else {
+            return E_POINTER;
+        };
+        g.owner = Owner;
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn get_Owner(machine: &mut Machine, this: u32, Owner: Option<&mut u32>) -> u32 {
+        let (Some(g), Some(Owner)) = (machine.state.quartz.graphs.get(&graph(this)), Owner) else {
+            return E_POINTER;
+        };
+        *Owner = g.owner.to_raw();
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn put_Visible(machine: &mut Machine, this: u32, Visible: i32) -> u32 {
+        let Some(g) = machine.state.quartz.graphs.get_mut(&graph(this)) else {
+            return E_POINTER;
+        };
+        g.visible = Visible != OAFALSE;
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn get_Visible(machine: &mut Machine, this: u32, pVisible: Option<&mut i32>) -> u32 {
+        let (Some(g), Some(pVisible)) = (machine.state.quartz.graphs.get(&graph(this)), pVisible)
+        else {
+            return E_POINTER;
+        };
+        *pVisible = if g.visible { OATRUE } else { OAFALSE };
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetWindowPosition(
+        machine: &mut Machine,
+        this: u32,
+        Left: i32,
+        Top: i32,
+        Width: i32,
+        Height: i32,
+    ) -> u32 {
+        update_position(machine, this, |rect| {
+            *rect = RECT {
+                left: Left,
+                top: Top,
+                right: Left + Width,
+                bottom: Top + Height,
+            }
+        })
+    }
+
+    #[win32_derive::dllexport]
+    pub fn GetWindowPosition(
+        machine: &mut Machine,
+        this: u32,
+        pLeft: Option<&mut i32>,
+        pTop: Option<&mut i32>,
+        pWidth: Option<&mut i32>,
+        pHeight: Option<&mut i32>,
+    ) -> u32 {
+        let (Some(g), Some(pLeft), Some(pTop), Some(pWidth), Some(pHeight)) = (
+            machine.state.quartz.graphs.get(&graph(this)),
+            pLeft,
+            pTop,
+            pWidth,
+            pHeight,
+        ) else {
+            return E_POINTER;
+        };
+        let rect = position(machine, g);
+        *pLeft = rect.left;
+        *pTop = rect.top;
+        *pWidth = rect.right - rect.left;
+        *pHeight = rect.bottom - rect.top;
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn put_Left(machine: &mut Machine, this: u32, Left: i32) -> u32 {
+        update_position(machine, this, |rect| {
+            rect.right += Left - rect.left;
+            rect.left = Left;
+        })
+    }
+
+    #[win32_derive::dllexport]
+    pub fn get_Left(machine: &mut Machine, this: u32, pLeft: Option<&mut i32>) -> u32 {
+        get_position(machine, this, pLeft, |rect| rect.left)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn put_Top(machine: &mut Machine, this: u32, Top: i32) -> u32 {
+        update_position(machine, this, |rect| {
+            rect.bottom += Top - rect.top;
+            rect.top = Top;
+        })
+    }
+
+    #[win32_derive::dllexport]
+    pub fn get_Top(machine: &mut Machine, this: u32, pTop: Option<&mut i32>) -> u32 {
+        get_position(machine, this, pTop, |rect| rect.top)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn put_Width(machine: &mut Machine, this: u32, Width: i32) -> u32 {
+        update_position(machine, this, |rect| rect.right = rect.left + Width)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn get_Width(machine: &mut Machine, this: u32, pWidth: Option<&mut i32>) -> u32 {
+        get_position(machine, this, pWidth, |rect| rect.right - rect.left)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn put_Height(machine: &mut Machine, this: u32, Height: i32) -> u32 {
+        update_position(machine, this, |rect| rect.bottom = rect.top + Height)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn get_Height(machine: &mut Machine, this: u32, pHeight: Option<&mut i32>) -> u32 {
+        get_position(machine, this, pHeight, |rect| rect.bottom - rect.top)
+    }
+
+    #[win32_derive::dllexport]
+    pub fn put_WindowStyle(_machine: &mut Machine, this: u32, WindowStyle: u32) -> u32 {
+        // The video is drawn straight into its owner, so there's no window to style.
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn put_WindowStyleEx(_machine: &mut Machine, this: u32, WindowStyleEx: u32) -> u32 {
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn put_AutoShow(_machine: &mut Machine, this: u32, AutoShow: i32) -> u32 {
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn put_WindowState(_machine: &mut Machine, this: u32, WindowState: u32) -> u32 {
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn put_MessageDrain(_machine: &mut Machine, this: u32, Drain: u32) -> u32 {
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn put_FullScreenMode(_machine: &mut Machine, this: u32, FullScreenMode: i32) -> u32 {
+        // The owner's client area stands in for the screen.
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn get_FullScreenMode(
+        _machine: &mut Machine,
+        this: u32,
+        FullScreenMode: Option<&mut i32>,
+    ) -> u32 {
+        let Some(FullScreenMode) = FullScreenMode else {
+            return E_POINTER;
+        };
+        *FullScreenMode = OAFALSE;
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn SetWindowForeground(_machine: &mut Machine, this: u32, Focus: i32) -> u32 {
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn NotifyOwnerMessage(
+        _machine: &mut Machine,
+        this: u32,
+        hwnd: u32,
+        uMsg: u32,
+        wParam: u32,
+        lParam: u32,
+    ) -> u32 {
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn HideCursor(_machine: &mut Machine, this: u32, HideCursor: i32) -> u32 {
+        S_OK
+    }
+
+    #[win32_derive::dllexport]
+    pub fn IsCursorHidden(
+        _machine: &mut Machine,
+        this: u32,
+        CursorHidden: Option<&mut i32>,
+    ) -> u32 {
+        let Some(CursorHidden) = CursorHidden else {
+            return E_POINTER;
+        };
+        *CursorHidden = OAFALSE;
+        S_FALSE
+    }
+
+    vtable![IVideoWindow shims
+        QueryInterface ok,
+        AddRef ok,
+        Release ok,
+        GetTypeInfoCount todo,
+        GetTypeInfo todo,
+        GetIDsOfNames todo,
+        Invoke todo,
+        put_Caption todo,
+        get_Caption todo,
+        put_WindowStyle ok,
+        get_WindowStyle todo,
+        put_WindowStyleEx ok,
+        get_WindowStyleEx todo,
+        put_AutoShow ok,
+        get_AutoShow todo,
+        put_WindowState ok,
+        get_WindowState todo,
+        put_BackgroundPalette todo,
+        get_BackgroundPalette todo,
+        put_Visible ok,
+        get_Visible ok,
+        put_Left ok,
+        get_Left ok,
+        put_Width ok,
+        get_Width ok,
+        put_Top ok,
+        get_Top ok,
+        put_Height ok,
+        get_Height ok,
+        put_Owner ok,
+        get_Owner ok,
+        put_MessageDrain ok,
+        get_MessageDrain todo,
+        get_BorderColor todo,
+        put_BorderColor todo,
+        get_FullScreenMode ok,
+        put_FullScreenMode ok,
+        SetWindowForeground ok,
+        NotifyOwnerMessage ok,
+        SetWindowPosition ok,
+        GetWindowPosition ok,
+        GetMinIdealImageSize todo,
+        GetMaxIdealImageSize todo,
+        GetRestorePosition todo,
+        HideCursor ok,
+        IsCursorHidden ok,
+    ];
+}
+
+#[win32_derive::dllexport]
+pub fn AMGetErrorTextA(machine: &mut Machine, hr: u32, pbuffer: u32, MaxLen: u32) -> u32 {
+    if pbuffer == 0 || MaxLen == 0 {
+        return 0;
+    }
+    let text = match hr {
+        E_NOTIMPL => "Not implemented.".to_string(),
+        VFW_E_UNKNOWN_FILE_TYPE => "The media type of this file is not recognized.".to_string(),
+        VFW_E_WRONG_STATE => {
+            "The operation could not be performed because the filter is in the wrong state."
+                .to_string()
+        }
+        _ => format!("Error {hr:#010x}."),
+    };
+    let len = std::cmp::min(text.len(), MaxLen as usize - 1);
+    let buf = machine
+        .mem()
+        .sub(pbuffer, len as u32 + 1)
+        .as_mut_slice_todo();
+    buf[..len].copy_from_slice(&text.as_bytes()[..len]);
+    buf[len] = 0;
+    len as u32
+}
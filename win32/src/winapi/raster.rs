@@ -0,0 +1,172 @@
+//! A simple software rasterizer, shared by the 3d APIs (OpenGL, Glide, Direct3D).
+//! This module does not become its own DLL.
+//!
+//! This is deliberately simple: it draws Gouraud-shaded triangles, optionally
+//! modulated by a single texture, with an optional depth test into an RGBA
+//! framebuffer, and leaves all transformation and API state to the callers.
+
+/// A vertex already transformed into window coordinates.
+/// x/y are in pixels with the origin at the top left, z is depth in [0, 1].
+#[derive(Debug, Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub struct Vertex {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    /// RGBA, each in [0, 1].
+    pub color: [f32; 4],
+    /// Texture coordinates, with [0, 1] spanning the texture.
+    pub uv: [f32; 2],
+}
+
+/// An RGBA texture, sampled nearest-neighbor with wrapping coordinates.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct Texture {
+    pub width: u32,
+    pub height: u32,
+    pub texels: Vec<[u8; 4]>,
+}
+
+impl Texture {
+    fn sample(&self, [u, v]: [f32; 2]) -> [f32; 4] {
+        let x = ((u - u.floor()) * self.width as f32) as u32;
+        let y = ((v - v.floor()) * self.height as f32) as u32;
+        let ofs = (y.min(self.height - 1) * self.width + x.min(self.width - 1)) as usize;
+        self.texels[ofs].map(|c| c as f32 / 255.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum DepthFunc {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl DepthFunc {
+    fn test(self, new: f32, old: f32) -> bool {
+        match self {
+            DepthFunc::Never => false,
+            DepthFunc::Less => new < old,
+            DepthFunc::Equal => new == old,
+            DepthFunc::LessEqual => new <= old,
+            DepthFunc::Greater => new > old,
+            DepthFunc::NotEqual => new != old,
+            DepthFunc::GreaterEqual => new >= old,
+            DepthFunc::Always => true,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Framebuffer {
+    pub width: u32,
+    pub height: u32,
+    pub color: Vec<[u8; 4]>,
+    pub depth: Vec<f32>,
+    /// None disables depth testing (and depth writes).
+    pub depth_func: Option<DepthFunc>,
+    /// Whether drawing updates the depth buffer, when depth testing is enabled.
+    pub depth_write: bool,
+}
+
+fn to_pixel(color: [f32; 4]) -> [u8; 4] {
+    color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        let len = (width * height) as usize;
+        Framebuffer {
+            width,
+            height,
+            color: vec![[0, 0, 0, 255]; len],
+            depth: vec![1.0; len],
+            depth_func: None,
+            depth_write: true,
+        }
+    }
+
+    pub fn clear_color(&mut self, color: [f32; 4]) {
+        self.color.fill(to_pixel(color));
+    }
+
+    pub fn clear_depth(&mut self, depth: f32) {
+        self.depth.fill(depth);
+    }
+
+    /// Draw a triangle, with its color modulated by a texture if given;
+    /// either winding is accepted.
+    /// Texture coordinates are interpolated linearly in screen space, without
+    /// perspective correction.
+    pub fn triangle_textured(&mut self, v: [&Vertex; 3], texture: Option<&Texture>) {
+        let area = edge(v[0], v[1], v[2].x, v[2].y);
+        if area == 0.0 || !area.is_finite() {
+            return;
+        }
+
+        let min_x = v.iter().map(|v| v.x).fold(f32::INFINITY, f32::min).max(0.0);
+        let max_x = v
+            .iter()
+            .map(|v| v.x)
+            .fold(f32::NEG_INFINITY, f32::max)
+            .min(self.width as f32 - 1.0);
+        let min_y = v.iter().map(|v| v.y).fold(f32::INFINITY, f32::min).max(0.0);
+        let max_y = v
+            .iter()
+            .map(|v| v.y)
+            .fold(f32::NEG_INFINITY, f32::max)
+            .min(self.height as f32 - 1.0);
+        if min_x > max_x || min_y > max_y {
+            return;
+        }
+
+        for y in min_y as u32..=max_y as u32 {
+            for x in min_x as u32..=max_x as u32 {
+                // Sample at pixel centers.
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let w0 = edge(v[1], v[2], px, py) / area;
+                let w1 = edge(v[2], v[0], px, py) / area;
+                let w2 = edge(v[0], v[1], px, py) / area;
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                    continue;
+                }
+
+                let ofs = (y * self.width + x) as usize;
+                let z = w0 * v[0].z + w1 * v[1].z + w2 * v[2].z;
+                if let Some(func) = self.depth_func {
+                    if !func.test(z, self.depth[ofs]) {
+                        continue;
+                    }
+                    if self.depth_write {
+                        self.depth[ofs] = z;
+                    }
+                }
+                let mut color = [0f32; 4];
+                for i in 0..4 {
+                    color[i] = w0 * v[0].color[i] + w1 * v[1].color[i] + w2 * v[2].color[i];
+                }
+                if let Some(texture) = texture {
+                    let mut uv = [0f32; 2];
+                    for i in 0..2 {
+                        uv[i] = w0 * v[0].uv[i] + w1 * v[1].uv[i] + w2 * v[2].uv[i];
+                    }
+                    let texel = texture.sample(uv);
+                    for i in 0..4 {
+                        color[i] *= texel[i];
+                    }
+                }
+                self.color[ofs] = to_pixel(color);
+            }
+        }
+    }
+}
+
+/// Twice the signed area of the triangle (a, b, (x, y)).
+fn edge(a: &Vertex, b: &Vertex, x: f32, y: f32) -> f32 {
+    (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
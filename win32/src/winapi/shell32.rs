@@ -0,0 +1,181 @@
+//! The shell, as far as opening documents, running programs and finding special
+//! folders.
+
+#![allow(non_snake_case)]
+
+use crate::machine::Machine;
+
+const TRACE_CONTEXT: &'static str = "shell32";
+
+/*
+## Shell
+
+Special folders are at the paths Windows 95 keeps them, which the host can map
+to directories of its own (see kernel32's map_dir), so games that save to
+My Documents save somewhere the user chose.
+
+ShellExecute hands URLs and documents to the host to open, as games do with
+their readmes and web sites.  Programs, like a launcher starting the game proper,
+run in a new instance of the emulator, which the host starts.
+*/
+
+/// A special folder, by CSIDL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Folder {
+    Desktop = 0x0000,
+    Personal = 0x0005,
+    AppData = 0x001a,
+}
+
+impl Folder {
+    const ALL: [Folder; 3] = [Folder::Desktop, Folder::Personal, Folder::AppData];
+
+    fn from_csidl(csidl: u32) -> Option<Folder> {
+        Folder::ALL.into_iter().find(|&f| f as u32 == csidl)
+    }
+
+    /// Where the folder is in the guest's file system.
+    pub fn guest_path(self) -> &'static str {
+        match self {
+            Folder::Desktop => "C:\\WINDOWS\\Desktop",
+            Folder::Personal => "C:\\My Documents",
+            Folder::AppData => "C:\\WINDOWS\\Application Data",
+        }
+    }
+}
+
+/// A special folder redirected to a host directory.
+#[derive(Debug, Clone)]
+pub struct FolderMapping {
+    pub folder: Folder,
+    pub host_dir: String,
+}
+
+impl std::str::FromStr for FolderMapping {
+    type Err = String;
+
+    /// Parses "NAME=DIR", where NAME is desktop, documents or appdata.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, dir) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected folder=dir, got {s:?}"))?;
+        let folder = match name {
+            "desktop" => Folder::Desktop,
+            "documents" => Folder::Personal,
+            "appdata" => Folder::AppData,
+            _ => return Err(format!("unknown folder {name:?}")),
+        };
+        Ok(FolderMapping {
+            folder,
+            host_dir: dir.to_string(),
+        })
+    }
+}
+
+/// Masks the CSIDL_FLAG_* bits out of a CSIDL.
+const CSIDL_FOLDER_MASK: u32 = 0x00ff;
+
+const S_OK: u32 = 0;
+const E_INVALIDARG: u32 = 0x8007_0057;
+
+const MAX_PATH: u32 = 260;
+
+/// Copy a folder's path out to a MAX_PATH buffer.
+fn write_folder_path(machine: &mut Machine, folder: Folder, buf: u32) {
+    let path = folder.guest_path();
+    let out = machine.mem().sub(buf, MAX_PATH).as_mut_slice_todo();
+    out[..path.len()].copy_from_slice(path.as_bytes());
+    out[path.len()] = 0;
+}
+
+#[win32_derive::dllexport]
+pub fn SHGetFolderPathA(
+    machine: &mut Machine,
+    hwnd: u32,
+    csidl: u32,
+    hToken: u32,
+    dwFlags: u32,
+    pszPath: u32,
+) -> u32 {
+    let Some(folder) = Folder::from_csidl(csidl & CSIDL_FOLDER_MASK) else {
+        log::warn!("SHGetFolderPathA: unsupported folder {csidl:x}");
+        return E_INVALIDARG;
+    };
+    if pszPath == 0 {
+        return E_INVALIDARG;
+    }
+    write_folder_path(machine, folder, pszPath);
+    S_OK
+}
+
+#[win32_derive::dllexport]
+pub fn SHGetSpecialFolderPathA(
+    machine: &mut Machine,
+    hwnd: u32,
+    pszPath: u32,
+    csidl: u32,
+    fCreate: bool,
+) -> bool {
+    let Some(folder) = Folder::from_csidl(csidl & CSIDL_FOLDER_MASK) else {
+        log::warn!("SHGetSpecialFolderPathA: unsupported folder {csidl:x}");
+        return false;
+    };
+    if pszPath == 0 {
+        return false;
+    }
+    write_folder_path(machine, folder, pszPath);
+    true
+}
+
+// ShellExecute results: values above 32 mean success.
+const ERROR_FILE_NOT_FOUND: u32 = 2;
+const SE_ERR_NOASSOC: u32 = 31;
+const SHELL_EXECUTE_OK: u32 = 42;
+
+#[win32_derive::dllexport]
+pub fn ShellExecuteA(
+    machine: &mut Machine,
+    hwnd: u32,
+    lpOperation: Option<&str>,
+    lpFile: Option<&str>,
+    lpParameters: Option<&str>,
+    lpDirectory: Option<&str>,
+    nShowCmd: u32,
+) -> u32 {
+    let Some(file) = lpFile else {
+        return ERROR_FILE_NOT_FOUND;
+    };
+    // Null means the default verb, which for what we handle is "open".
+    if !matches!(lpOperation, None | Some("open")) {
+        log::warn!("ShellExecuteA: unsupported operation {lpOperation:?}");
+        return SE_ERR_NOASSOC;
+    }
+    let is_url = file.contains("://") || file.starts_with("mailto:");
+    let target = if is_url {
+        file.to_string()
+    } else {
+        let path = match lpDirectory {
+            Some(dir) if !file.contains(':') && !file.starts_with('\\') => {
+                format!("{}\\{file}", dir.trim_end_matches('\\'))
+            }
+            _ => file.to_string(),
+        };
+        let path = machine.state.kernel32.host_path(&path);
+        if file.to_ascii_lowercase().ends_with(".exe") {
+            let cmdline = match lpParameters {
+                Some(params) if !params.is_empty() => format!("\"{file}\" {params}"),
+                _ => format!("\"{file}\""),
+            };
+            let dir = lpDirectory.map(|dir| machine.state.kernel32.host_path(dir));
+            if !machine.host.launch(&path, &cmdline, dir.as_deref()) {
+                return ERROR_FILE_NOT_FOUND;
+            }
+            return SHELL_EXECUTE_OK;
+        }
+        path
+    };
+    if !machine.host.shell_open(&target) {
+        return SE_ERR_NOASSOC;
+    }
+    SHELL_EXECUTE_OK
+}
@@ -38,6 +38,19 @@ impl<'a> FromArg<'a> for i32 {
     }
 }
 
+impl<'a> FromArg<'a> for f32 {
+    unsafe fn from_arg(_mem: Mem<'a>, arg: u32) -> Self {
+        f32::from_bits(arg)
+    }
+}
+
+/// f64 arguments take up two stack slots.
+impl<'a> FromStack<'a> for f64 {
+    unsafe fn from_stack(mem: Mem<'a>, sp: u32) -> Self {
+        f64::from_bits(mem.get_pod::<u64>(sp))
+    }
+}
+
 impl<'a> FromArg<'a> for bool {
     unsafe fn from_arg(_mem: Mem<'a>, arg: u32) -> Self {
         arg != 0
@@ -132,6 +145,11 @@ impl VarArgs {
         self.0 += 4; // TODO: should expose stack_consumed for use here and switch to FromStack
         value
     }
+
+    /// The address of the next argument, as a va_list would hold.
+    pub fn va_list(&self) -> u32 {
+        self.0
+    }
 }
 impl<'a> FromStack<'a> for VarArgs {
     unsafe fn from_stack(_mem: Mem<'a>, sp: u32) -> Self {
@@ -16,7 +16,7 @@ pub struct HWNDT;
 pub type HWND = HANDLE<HWNDT>;
 
 #[repr(C, packed)]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub struct RECT {
     pub left: i32,
     pub top: i32,
@@ -25,6 +25,25 @@ pub struct RECT {
 }
 unsafe impl memory::Pod for RECT {}
 
+impl RECT {
+    /// The smallest rect containing both rects.
+    pub fn union(&self, other: &RECT) -> RECT {
+        RECT {
+            left: std::cmp::min(self.left, other.left),
+            top: std::cmp::min(self.top, other.top),
+            right: std::cmp::max(self.right, other.right),
+            bottom: std::cmp::max(self.bottom, other.bottom),
+        }
+    }
+
+    pub fn contains(&self, other: &RECT) -> bool {
+        self.left <= other.left
+            && self.top <= other.top
+            && self.right >= other.right
+            && self.bottom >= other.bottom
+    }
+}
+
 #[repr(C, packed)]
 #[derive(Debug)]
 pub struct POINT {
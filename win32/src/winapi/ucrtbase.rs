@@ -2,6 +2,7 @@
 
 use super::kernel32::ExitProcess;
 use crate::Machine;
+use memory::Extensions;
 
 const TRACE_CONTEXT: &'static str = "ucrtbase";
 
@@ -34,3 +35,19 @@ pub fn __p___argc(_machine: &mut Machine) -> u32 {
 pub fn exit(machine: &mut Machine, status: u32) -> u32 {
     ExitProcess(machine, status)
 }
+
+#[win32_derive::dllexport(cdecl)]
+pub async fn strlen(machine: &mut Machine, str: u32) -> u32 {
+    // This stands in for guest code (see pe/fast_paths.rs), so a string running off
+    // the end of memory must fault rather than panic.
+    let mem = machine.mem();
+    let accessible = mem.accessible_len(str, u32::MAX);
+    match mem.sub32(str, accessible).iter().position(|&b| b == 0) {
+        Some(len) => len as u32,
+        None => {
+            let addr = str.wrapping_add(accessible);
+            super::kernel32::raise_access_violation(machine, addr, false).await;
+            0
+        }
+    }
+}
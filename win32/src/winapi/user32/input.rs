@@ -0,0 +1,223 @@
+use crate::host;
+use std::collections::VecDeque;
+
+/*
+## Input
+
+Hosts report keys by USB HID usage, which we translate to the PC set 1 scancodes
+that both window messages and DirectInput (as DIK_ codes) use, and to virtual
+key codes for a US layout.  Mouse motion comes both as a position in the window
+and as raw relative motion, which keeps coming when the host has the mouse
+grabbed (see Host::grab_mouse).
+
+Besides queueing window messages, we keep which keys and buttons are down, the
+total mouse motion, and a log of recent input events, which DirectInput devices
+read their state and buffered data from.
+*/
+
+/// How many recent input events are kept for DirectInput buffers to catch up on.
+/// Mouse motion fills this quickly, so it's sized for readers that fall a few
+/// frames behind.
+const LOG_LEN: usize = 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub enum InputKind {
+    /// A key, by DIK_ code: the set 1 scancode, plus 0x80 for extended keys.
+    Key { dik: u8, down: bool },
+    /// Relative mouse motion, along with the totals after it.
+    Motion { dx: i32, dy: i32, x: i32, y: i32 },
+    /// A mouse button, numbered in DirectInput's order: left, right, middle.
+    Button { button: u8, down: bool },
+}
+
+/// An input event, as DirectInput's buffered data reports it.
+#[derive(Debug, Clone, Copy)]
+pub struct InputEvent {
+    pub kind: InputKind,
+    /// Host time of the event.
+    pub time: u32,
+    /// Increases by one with each event, so readers can tell what they've seen.
+    pub sequence: u32,
+}
+
+pub struct Input {
+    /// Whether each key is down, indexed by DIK_ code.
+    pub keys: [bool; 256],
+    /// Whether each mouse button is down, in InputKind::Button order.
+    pub buttons: [bool; 3],
+    /// Total relative mouse motion, for readers to take differences of.
+    pub motion: (i32, i32),
+    log: VecDeque<InputEvent>,
+    sequence: u32,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Input {
+            keys: [false; 256],
+            buttons: [false; 3],
+            motion: (0, 0),
+            log: VecDeque::new(),
+            sequence: 0,
+        }
+    }
+}
+
+impl Input {
+    fn push(&mut self, kind: InputKind, time: u32) {
+        self.sequence += 1;
+        if self.log.len() == LOG_LEN {
+            self.log.pop_front();
+        }
+        self.log.push_back(InputEvent {
+            kind,
+            time,
+            sequence: self.sequence,
+        });
+    }
+
+    /// Record a host key message, returning its DIK_ code and virtual key, or
+    /// None for keys Windows has no code for.
+    pub fn record_key(&mut self, key: &host::KeyMessage, time: u32) -> Option<(u8, u8)> {
+        let (dik, vk) = translate(key.usage)?;
+        self.keys[dik as usize] = key.down;
+        let down = key.down;
+        self.push(InputKind::Key { dik, down }, time);
+        Some((dik, vk))
+    }
+
+    pub fn record_button(&mut self, button: host::MouseButton, down: bool, time: u32) {
+        let button = match button {
+            host::MouseButton::Left => 0,
+            host::MouseButton::Right => 1,
+            host::MouseButton::Middle => 2,
+        };
+        self.buttons[button as usize] = down;
+        self.push(InputKind::Button { button, down }, time);
+    }
+
+    pub fn record_motion(&mut self, dx: i32, dy: i32, time: u32) {
+        if dx == 0 && dy == 0 {
+            return;
+        }
+        self.motion.0 = self.motion.0.wrapping_add(dx);
+        self.motion.1 = self.motion.1.wrapping_add(dy);
+        let (x, y) = self.motion;
+        self.push(InputKind::Motion { dx, dy, x, y }, time);
+    }
+
+    /// Sequence number of the latest event.
+    pub fn sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    /// Events after the given sequence number.  The bool is false if some of
+    /// those events have already fallen out of the log.
+    pub fn events_since(&self, sequence: u32) -> (impl Iterator<Item = &InputEvent>, bool) {
+        let complete = match self.log.front() {
+            Some(first) => first.sequence <= sequence + 1,
+            None => true,
+        };
+        let events = self.log.iter().filter(move |e| e.sequence > sequence);
+        (events, complete)
+    }
+
+    pub fn alt_down(&self) -> bool {
+        self.keys[0x38] || self.keys[0xB8]
+    }
+
+    /// The MK_ flags of mouse messages: buttons, Shift and Ctrl.
+    pub fn mouse_keys(&self) -> u32 {
+        let mut flags = 0;
+        for (i, flag) in [0x1, 0x2, 0x10].into_iter().enumerate() {
+            if self.buttons[i] {
+                flags |= flag;
+            }
+        }
+        if self.keys[0x2A] || self.keys[0x36] {
+            flags |= 0x4;
+        }
+        if self.keys[0x1D] || self.keys[0x9D] {
+            flags |= 0x8;
+        }
+        flags
+    }
+}
+
+/// Map a USB HID keyboard usage to its DIK_ code and virtual key.
+fn translate(usage: u32) -> Option<(u8, u8)> {
+    const LETTERS: [u8; 26] = [
+        0x1E, 0x30, 0x2E, 0x20, 0x12, 0x21, 0x22, 0x23, 0x17, 0x24, 0x25, 0x26, 0x32, 0x31, 0x18,
+        0x19, 0x10, 0x13, 0x1F, 0x14, 0x16, 0x2F, 0x11, 0x2D, 0x15, 0x2C,
+    ];
+    const KEYPAD_DIGITS: [u8; 10] = [0x52, 0x4F, 0x50, 0x51, 0x4B, 0x4C, 0x4D, 0x47, 0x48, 0x49];
+    Some(match usage {
+        0x04..=0x1D => {
+            let i = (usage - 0x04) as u8;
+            (LETTERS[i as usize], b'A' + i)
+        }
+        // 1 through 9, then 0.
+        0x1E..=0x27 => {
+            let i = (usage - 0x1E) as u8;
+            (0x02 + i, if i == 9 { b'0' } else { b'1' + i })
+        }
+        0x28 => (0x1C, 0x0D), // Enter
+        0x29 => (0x01, 0x1B), // Escape
+        0x2A => (0x0E, 0x08), // Backspace
+        0x2B => (0x0F, 0x09), // Tab
+        0x2C => (0x39, 0x20), // Space
+        0x2D => (0x0C, 0xBD), // -
+        0x2E => (0x0D, 0xBB), // =
+        0x2F => (0x1A, 0xDB), // [
+        0x30 => (0x1B, 0xDD), // ]
+        0x31 => (0x2B, 0xDC), // \
+        0x33 => (0x27, 0xBA), // ;
+        0x34 => (0x28, 0xDE), // '
+        0x35 => (0x29, 0xC0), // `
+        0x36 => (0x33, 0xBC), // ,
+        0x37 => (0x34, 0xBE), // .
+        0x38 => (0x35, 0xBF), // /
+        0x39 => (0x3A, 0x14), // Caps Lock
+        // F1 through F12.
+        0x3A..=0x45 => {
+            let i = (usage - 0x3A) as u8;
+            (if i < 10 { 0x3B + i } else { 0x57 + i - 10 }, 0x70 + i)
+        }
+        0x46 => (0xB7, 0x2C), // Print Screen
+        0x47 => (0x46, 0x91), // Scroll Lock
+        0x48 => (0xC5, 0x13), // Pause
+        0x49 => (0xD2, 0x2D), // Insert
+        0x4A => (0xC7, 0x24), // Home
+        0x4B => (0xC9, 0x21), // Page Up
+        0x4C => (0xD3, 0x2E), // Delete
+        0x4D => (0xCF, 0x23), // End
+        0x4E => (0xD1, 0x22), // Page Down
+        0x4F => (0xCD, 0x27), // Right
+        0x50 => (0xCB, 0x25), // Left
+        0x51 => (0xD0, 0x28), // Down
+        0x52 => (0xC8, 0x26), // Up
+        0x53 => (0x45, 0x90), // Num Lock
+        0x54 => (0xB5, 0x6F), // keypad /
+        0x55 => (0x37, 0x6A), // keypad *
+        0x56 => (0x4A, 0x6D), // keypad -
+        0x57 => (0x4E, 0x6B), // keypad +
+        0x58 => (0x9C, 0x0D), // keypad Enter
+        // Keypad 1 through 9, then 0.
+        0x59..=0x62 => {
+            let i = ((usage - 0x59 + 1) % 10) as u8;
+            (KEYPAD_DIGITS[i as usize], 0x60 + i)
+        }
+        0x63 => (0x53, 0x6E), // keypad .
+        0x64 => (0x56, 0xE2), // the extra key left of Z on non-US keyboards
+        0x65 => (0xDD, 0x5D), // Menu
+        0xE0 => (0x1D, 0x11), // left Ctrl
+        0xE1 => (0x2A, 0x10), // left Shift
+        0xE2 => (0x38, 0x12), // left Alt
+        0xE3 => (0xDB, 0x5B), // left Windows
+        0xE4 => (0x9D, 0x11), // right Ctrl
+        0xE5 => (0x36, 0x10), // right Shift
+        0xE6 => (0xB8, 0x12), // right Alt
+        0xE7 => (0xDC, 0x5C), // right Windows
+        _ => return None,
+    })
+}
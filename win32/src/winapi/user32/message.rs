@@ -1,10 +1,15 @@
-use crate::{host, winapi::types::*, Machine, MouseButton};
+use crate::{
+    host,
+    machine::Emulator,
+    winapi::{imm32, kernel32, types::*},
+    Machine, MouseButton,
+};
 use bitflags::bitflags;
 
 const TRACE_CONTEXT: &'static str = "user32/message";
 
 #[repr(C)]
-#[derive(Clone)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct MSG {
     pub hwnd: HWND,
     pub message: u32,
@@ -34,13 +39,23 @@ impl std::fmt::Debug for MSG {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, win32_derive::TryFromEnum)]
+#[allow(non_camel_case_types)]
 pub enum WM {
     NULL = 0,
     CREATE = 0x0001,
+    SETTEXT = 0x000C,
     PAINT = 0x000F,
     QUIT = 0x0012,
     ACTIVATEAPP = 0x001C,
+    GETICON = 0x007F,
+    SETICON = 0x0080,
+    KEYDOWN = 0x0100,
+    KEYUP = 0x0101,
+    CHAR = 0x0102,
+    SYSKEYDOWN = 0x0104,
+    SYSKEYUP = 0x0105,
     TIMER = 0x0113,
+    MOUSEMOVE = 0x0200,
     LBUTTONDOWN = 0x0201,
     LBUTTONUP = 0x0202,
     LBUTTONDBLCLK = 0x0203,
@@ -50,15 +65,23 @@ pub enum WM {
     MBUTTONDOWN = 0x0207,
     MBUTTONUP = 0x0208,
     MBUTTONDBLCLK = 0x0209,
+    DEVICECHANGE = 0x0219,
+    IME_STARTCOMPOSITION = 0x010D,
+    IME_ENDCOMPOSITION = 0x010E,
+    IME_COMPOSITION = 0x010F,
+    IME_CHAR = 0x0286,
 }
 
-fn msg_from_message(message: host::Message) -> MSG {
+/// WM_DEVICECHANGE wParam: a device was added to or removed from the system.
+const DBT_DEVNODES_CHANGED: u32 = 0x0007;
+
+fn msg_from_message(user32: &mut super::State, now: u32, message: host::Message) -> Option<MSG> {
     let mut msg = MSG {
         hwnd: HWND::from_raw(message.hwnd),
         message: WM::QUIT as u32, // will be overwritten
         wParam: 0,
         lParam: 0,
-        time: 0,
+        time: now,
         pt_x: 0,
         pt_y: 0,
         lPrivate: 0,
@@ -77,12 +100,267 @@ fn msg_from_message(message: host::Message) -> MSG {
                 (MouseButton::Middle, true) => WM::MBUTTONDOWN,
                 (MouseButton::Middle, false) => WM::MBUTTONUP,
             } as u32;
-            msg.wParam = 0; // TODO:  modifiers
+            user32.input.record_button(mouse.button, mouse.down, now);
+            msg.wParam = user32.input.mouse_keys();
+            msg.lParam = (mouse.y << 16) | mouse.x;
+        }
+        host::MessageDetail::MouseMove(mouse) => {
+            user32.input.record_motion(mouse.dx, mouse.dy, now);
+            msg.message = WM::MOUSEMOVE as u32;
+            msg.wParam = user32.input.mouse_keys();
             msg.lParam = (mouse.y << 16) | mouse.x;
         }
+        host::MessageDetail::Key(key) => {
+            // Alt turns keys into system keys, including the release of Alt itself.
+            let alt = user32.input.alt_down();
+            let (dik, vk) = user32.input.record_key(key, now)?;
+            let sys = alt || user32.input.alt_down();
+            msg.message = match (key.down, sys) {
+                (true, false) => WM::KEYDOWN,
+                (false, false) => WM::KEYUP,
+                (true, true) => WM::SYSKEYDOWN,
+                (false, true) => WM::SYSKEYUP,
+            } as u32;
+            msg.wParam = vk as u32;
+            // Repeat count, scancode, extended key flag, Alt, previous state and transition.
+            msg.lParam = 1 | ((dik as u32 & 0x7F) << 16) | ((dik as u32 >> 7) << 24);
+            if sys {
+                msg.lParam |= 1 << 29;
+            }
+            if !key.down {
+                msg.lParam |= 0b11 << 30;
+            }
+        }
+        &host::MessageDetail::Activate(active) => {
+            msg.message = WM::ACTIVATEAPP as u32;
+            msg.wParam = active as u32;
+        }
+        host::MessageDetail::ControllerAdded(_) | host::MessageDetail::ControllerRemoved(_) => {
+            msg.message = WM::DEVICECHANGE as u32;
+            msg.wParam = DBT_DEVNODES_CHANGED;
+        }
+        host::MessageDetail::Text(_) | host::MessageDetail::Composition(_) => {
+            unreachable!("queued by text_messages or composition_messages")
+        }
+    }
+
+    Some(msg)
+}
+
+/// Queue a message from the host for the guest, tracking focus, attached
+/// controllers and input as we go so that our idea of them changes at the
+/// same point the guest sees WM_ACTIVATEAPP, WM_DEVICECHANGE or WM_KEYDOWN.
+pub fn enqueue_host_message(state: &mut crate::winapi::State, now: u32, msg: host::Message) {
+    let user32 = &mut state.user32;
+    match &msg.detail {
+        &host::MessageDetail::Activate(active) => user32.background = !active,
+        host::MessageDetail::ControllerAdded(controller) => {
+            user32.controllers.retain(|c| c.id != controller.id);
+            user32.controllers.push(controller.clone());
+        }
+        &host::MessageDetail::ControllerRemoved(id) => user32.controllers.retain(|c| c.id != id),
+        host::MessageDetail::Text(text) => {
+            let hwnd = focused_child(user32, HWND::from_raw(msg.hwnd));
+            let msgs = match state.imm32.context_mut(hwnd) {
+                Some(context) if context.composing => result_messages(context, hwnd, now, text),
+                _ => text_messages(user32, state.kernel32.code_page, hwnd, now, text),
+            };
+            user32.messages.extend(msgs);
+            return;
+        }
+        host::MessageDetail::Composition(text) => {
+            let hwnd = HWND::from_raw(msg.hwnd);
+            // Without an input context the text arrives only once committed.
+            if let Some(context) = state.imm32.context_mut(hwnd) {
+                let msgs = composition_messages(context, hwnd, now, text);
+                user32.messages.extend(msgs);
+            }
+            return;
+        }
+        _ => {}
+    }
+    let Some(mut msg) = msg_from_message(user32, now, msg) else {
+        return;
+    };
+    retarget_mouse(user32, &mut msg);
+    retarget_keyboard(user32, &mut msg);
+    // Like Windows, keep only the latest of a run of mouse moves.
+    if msg.message == WM::MOUSEMOVE as u32 {
+        if let Some(last) = user32.messages.back_mut() {
+            if last.message == msg.message && last.hwnd == msg.hwnd {
+                *last = msg;
+                return;
+            }
+        }
+    }
+    user32.messages.push_back(msg);
+}
+
+/// Send mouse messages within a built-in child window, or to the window capturing
+/// the mouse, to that window rather than its top-level window, with the position
+/// relative to it.
+fn retarget_mouse(user32: &super::State, msg: &mut MSG) {
+    let is_mouse = msg.message == WM::MOUSEMOVE as u32
+        || (WM::LBUTTONDOWN as u32..=WM::MBUTTONDBLCLK as u32).contains(&msg.message);
+    if !is_mouse {
+        return;
+    }
+    let (x, y) = (msg.lParam as u16 as i32, (msg.lParam >> 16) as u16 as i32);
+    let child = match user32.windows.get(user32.capture) {
+        Some(window) if window.parent == msg.hwnd => window,
+        _ => match user32.windows.iter().find(|w| {
+            let r = w.rect_in_parent();
+            w.parent == msg.hwnd && x >= r.left && x < r.right && y >= r.top && y < r.bottom
+        }) {
+            Some(window) => window,
+            None => return,
+        },
+    };
+    let (x, y) = (x - child.x, y - child.y);
+    msg.hwnd = child.hwnd;
+    msg.lParam = ((y as u16 as u32) << 16) | x as u16 as u32;
+}
+
+/// The built-in child of a top-level window that has the focus, if any, and
+/// otherwise the window itself.
+fn focused_child(user32: &super::State, hwnd: HWND) -> HWND {
+    match user32.windows.get(user32.focus) {
+        Some(window) if window.parent == hwnd => window.hwnd,
+        _ => hwnd,
+    }
+}
+
+/// Send keyboard messages to the built-in child with the focus, like an edit
+/// control, rather than its top-level window.
+fn retarget_keyboard(user32: &super::State, msg: &mut MSG) {
+    if (WM::KEYDOWN as u32..=WM::SYSKEYUP as u32).contains(&msg.message) {
+        msg.hwnd = focused_child(user32, msg.hwnd);
+    }
+}
+
+fn char_msg(hwnd: HWND, message: WM, wParam: u32, now: u32) -> MSG {
+    MSG {
+        hwnd,
+        message: message as u32,
+        wParam,
+        lParam: 1, // repeat count
+        time: now,
+        pt_x: 0,
+        pt_y: 0,
+        lPrivate: 0,
+    }
+}
+
+/// The messages carrying host text to a window: WM_CHAR for each UTF-16 unit if
+/// it's a Unicode window, and otherwise for each character that takes a single
+/// byte in the ANSI code page.  Characters taking more bytes arrive as one
+/// WM_IME_CHAR, as input method results do, which DefWindowProcA then splits into
+/// a WM_CHAR per byte.
+fn text_messages(
+    user32: &super::State,
+    code_page: u32,
+    hwnd: HWND,
+    now: u32,
+    text: &str,
+) -> Vec<MSG> {
+    let unicode = match user32.windows.get(hwnd) {
+        Some(window) => window.wndclass.unicode,
+        None => false,
+    };
+    if unicode {
+        return text
+            .encode_utf16()
+            .map(|unit| char_msg(hwnd, WM::CHAR, unit as u32, now))
+            .collect();
+    }
+    text.chars()
+        .map(|c| match kernel32::encode_ansi(code_page, c).as_slice() {
+            &[byte] => char_msg(hwnd, WM::CHAR, byte as u32, now),
+            bytes => {
+                let packed = bytes.iter().fold(0, |acc, &b| (acc << 8) | b as u32);
+                char_msg(hwnd, WM::IME_CHAR, packed, now)
+            }
+        })
+        .collect()
+}
+
+fn ime_msg(hwnd: HWND, message: WM, lParam: u32, now: u32) -> MSG {
+    MSG {
+        lParam,
+        ..char_msg(hwnd, message, 0, now)
+    }
+}
+
+/// The messages announcing the text being composed in an input context, with an
+/// empty composition canceling it.
+fn composition_messages(
+    context: &mut imm32::Context,
+    hwnd: HWND,
+    now: u32,
+    text: &str,
+) -> Vec<MSG> {
+    if text.is_empty() {
+        if !context.composing {
+            return Vec::new();
+        }
+        context.composing = false;
+        context.composition.clear();
+        return vec![
+            ime_msg(hwnd, WM::IME_COMPOSITION, 0, now),
+            ime_msg(hwnd, WM::IME_ENDCOMPOSITION, 0, now),
+        ];
+    }
+    let mut msgs = Vec::new();
+    if !context.composing {
+        context.composing = true;
+        msgs.push(ime_msg(hwnd, WM::IME_STARTCOMPOSITION, 0, now));
+    }
+    context.composition = text.to_string();
+    let lParam = imm32::GCS_COMPSTR | imm32::GCS_CURSORPOS;
+    msgs.push(ime_msg(hwnd, WM::IME_COMPOSITION, lParam, now));
+    msgs
+}
+
+/// The messages announcing the text committed in an input context, ending the
+/// composition.
+fn result_messages(context: &mut imm32::Context, hwnd: HWND, now: u32, text: &str) -> Vec<MSG> {
+    context.composing = false;
+    context.composition.clear();
+    context.result = text.to_string();
+    vec![
+        ime_msg(hwnd, WM::IME_COMPOSITION, imm32::GCS_RESULTSTR, now),
+        ime_msg(hwnd, WM::IME_ENDCOMPOSITION, 0, now),
+    ]
+}
+
+/// Post the messages for the text committed in a window's input context, as
+/// DefWindowProc does for a WM_IME_COMPOSITION carrying a result.
+pub(super) fn post_ime_result(state: &mut crate::winapi::State, hwnd: HWND, now: u32) {
+    let Some(context) = state.imm32.context_mut(hwnd) else {
+        return;
+    };
+    let result = context.result.clone();
+    let code_page = state.kernel32.code_page;
+    let msgs = text_messages(&state.user32, code_page, hwnd, now, &result);
+    state.user32.messages.extend(msgs);
+}
+
+/// Post the WM_CHARs for the bytes of a WM_IME_CHAR, as DefWindowProcA does.
+pub(super) fn post_ime_char(user32: &mut super::State, hwnd: HWND, wParam: u32, now: u32) {
+    for byte in wParam.to_be_bytes().into_iter().skip_while(|&b| b == 0) {
+        user32
+            .messages
+            .push_back(char_msg(hwnd, WM::CHAR, byte as u32, now));
     }
+}
 
-    msg
+/// Pull in the messages the host has waiting, for readers of input state like
+/// DirectInput that may poll without ever pumping the message queue.
+pub fn read_host_messages(machine: &mut Machine) {
+    while let Some(msg) = machine.host.get_message() {
+        let now = machine.host.time();
+        enqueue_host_message(&mut machine.state, now, msg);
+    }
 }
 
 /// Returns Ok if an event is enqueued.
@@ -109,16 +387,15 @@ fn enqueue_timer_event_if_ready(machine: &mut Machine, hwnd: HWND) -> Result<(),
 /// Returns Ok if an event is enqueued.
 /// Returns Err(wait) if we need to wait for an event.
 fn fill_message_queue(machine: &mut Machine, hwnd: HWND) -> Result<(), Option<u32>> {
+    let device_wait = crate::winapi::poll_devices(machine);
+
     if !machine.state.user32.messages.is_empty() {
         return Ok(());
     }
 
     if let Some(msg) = machine.host.get_message() {
-        machine
-            .state
-            .user32
-            .messages
-            .push_back(msg_from_message(msg));
+        let now = machine.host.time();
+        enqueue_host_message(&mut machine.state, now, msg);
         return Ok(());
     }
 
@@ -126,17 +403,19 @@ fn fill_message_queue(machine: &mut Machine, hwnd: HWND) -> Result<(), Option<u3
         return Ok(());
     }
 
-    enqueue_timer_event_if_ready(machine, hwnd)
-}
-
-#[cfg(feature = "x86-emu")]
-async fn await_message(machine: &mut Machine, _hwnd: HWND, wait: Option<u32>) {
-    machine.emu.x86.cpu_mut().block(wait).await;
+    enqueue_timer_event_if_ready(machine, hwnd).map_err(|wait| match (wait, device_wait) {
+        (Some(a), Some(b)) => Some(std::cmp::min(a, b)),
+        (a, b) => a.or(b),
+    })
 }
 
-#[cfg(not(feature = "x86-emu"))]
 async fn await_message(machine: &mut Machine, _hwnd: HWND, wait: Option<u32>) {
-    machine.host.block(wait);
+    match machine.emu.block(wait) {
+        Some(block) => block.await,
+        None => {
+            machine.host.block(wait);
+        }
+    }
 }
 
 bitflags! {
@@ -195,7 +474,7 @@ fn enqueue_paint_if_needed(machine: &mut Machine, hwnd: HWND) -> bool {
 }
 
 #[win32_derive::dllexport]
-pub fn PeekMessageA(
+pub async fn PeekMessageA(
     machine: &mut Machine,
     lpMsg: Option<&mut MSG>,
     hWnd: HWND,
@@ -208,6 +487,7 @@ pub fn PeekMessageA(
     let lpMsg = lpMsg.unwrap();
 
     let _ = fill_message_queue(machine, hWnd);
+    crate::winapi::run_device_callbacks(machine).await;
 
     let msg: &MSG = match machine.state.user32.messages.front() {
         Some(msg) => msg,
@@ -227,7 +507,7 @@ pub fn PeekMessageA(
 }
 
 #[win32_derive::dllexport]
-pub fn PeekMessageW(
+pub async fn PeekMessageW(
     machine: &mut Machine,
     lpMsg: Option<&mut MSG>,
     hWnd: HWND,
@@ -243,6 +523,7 @@ pub fn PeekMessageW(
         wMsgFilterMax,
         wRemoveMsg,
     )
+    .await
 }
 
 #[win32_derive::dllexport]
@@ -257,7 +538,9 @@ pub async fn GetMessageA(
     assert_eq!(wMsgFilterMax, 0);
 
     loop {
-        match fill_message_queue(machine, hWnd) {
+        let filled = fill_message_queue(machine, hWnd);
+        crate::winapi::run_device_callbacks(machine).await;
+        match filled {
             Ok(_) => break,
             Err(wait_until) => await_message(machine, hWnd, wait_until).await,
         }
@@ -291,21 +574,36 @@ pub fn WaitMessage(_machine: &mut Machine) -> bool {
 }
 
 #[win32_derive::dllexport]
-pub fn TranslateMessage(_machine: &mut Machine, lpMsg: Option<&MSG>) -> bool {
-    // TODO: translate key-related messages into enqueuing a WM_CHAR.
-    false // no message translated
-}
-
-pub async fn dispatch_message(machine: &mut Machine, msg: &MSG) {
-    assert!(!msg.hwnd.is_null());
-    let wndproc = machine
-        .state
-        .user32
-        .windows
-        .get(msg.hwnd)
-        .unwrap()
-        .wndclass
-        .wndproc;
+pub fn TranslateMessage(machine: &mut Machine, lpMsg: Option<&MSG>) -> bool {
+    // Printable characters come from the host as text (see text_messages), so only
+    // the keys that type control characters are left to translate here.
+    let Some(msg) = lpMsg else {
+        return false;
+    };
+    if msg.message != WM::KEYDOWN as u32 {
+        return false;
+    }
+    let c = match msg.wParam {
+        0x08 | 0x09 | 0x1B => msg.wParam, // backspace, tab, escape
+        0x0D => b'\r' as u32,
+        _ => return false,
+    };
+    let char_msg = char_msg(msg.hwnd, WM::CHAR, c, msg.time);
+    machine.state.user32.messages.push_back(char_msg);
+    true
+}
+
+/// Call msg's window's wndproc, returning its result, or 0 if there's no such window.
+pub async fn dispatch_message(machine: &mut Machine, msg: &MSG) -> u32 {
+    let Some(window) = machine.state.user32.windows.get(msg.hwnd) else {
+        log::warn!("message {:#x} to invalid hwnd {:?}", msg.message, msg.hwnd);
+        return 0;
+    };
+    let wndclass = &window.wndclass;
+    if let Some(wndproc) = wndclass.builtin {
+        return wndproc(machine, msg.hwnd, msg.message, msg.wParam, msg.lParam);
+    }
+    let wndproc = wndclass.wndproc;
     // TODO: SetWindowLong can change the wndproc.
     machine
         .call_x86(
@@ -317,7 +615,7 @@ pub async fn dispatch_message(machine: &mut Machine, msg: &MSG) {
                 msg.lParam,
             ],
         )
-        .await;
+        .await
 }
 
 #[win32_derive::dllexport]
@@ -327,8 +625,7 @@ pub async fn DispatchMessageA(machine: &mut Machine, lpMsg: Option<&MSG>) -> u32
         // No associated hwnd.
         return 0;
     }
-    dispatch_message(machine, msg).await;
-    0
+    dispatch_message(machine, msg).await
 }
 
 #[win32_derive::dllexport]
@@ -338,8 +635,53 @@ pub async fn DispatchMessageW(machine: &mut Machine, lpMsg: Option<&MSG>) -> u32
         // No associated hwnd.
         return 0;
     }
-    dispatch_message(machine, msg).await;
-    0
+    dispatch_message(machine, msg).await
+}
+
+#[win32_derive::dllexport]
+pub async fn SendMessageA(
+    machine: &mut Machine,
+    hWnd: HWND,
+    Msg: u32,
+    wParam: u32,
+    lParam: u32,
+) -> u32 {
+    let msg = MSG {
+        hwnd: hWnd,
+        message: Msg,
+        wParam,
+        lParam,
+        time: 0,
+        pt_x: 0,
+        pt_y: 0,
+        lPrivate: 0,
+    };
+    dispatch_message(machine, &msg).await
+}
+
+#[win32_derive::dllexport]
+pub async fn SendMessageW(
+    machine: &mut Machine,
+    hWnd: HWND,
+    Msg: u32,
+    wParam: u32,
+    lParam: u32,
+) -> u32 {
+    SendMessageA(machine, hWnd, Msg, wParam, lParam).await
+}
+
+/// Post a message to the queue, as from another thread or a device.
+pub fn post_message(machine: &mut Machine, hwnd: HWND, message: u32, wParam: u32, lParam: u32) {
+    machine.state.user32.messages.push_back(MSG {
+        hwnd,
+        message,
+        wParam,
+        lParam,
+        time: 0,
+        pt_x: 0,
+        pt_y: 0,
+        lPrivate: 0,
+    });
 }
 
 #[win32_derive::dllexport]
@@ -1,6 +1,7 @@
 #![allow(non_snake_case)]
 
 mod dialog;
+mod input;
 mod message;
 mod paint;
 mod resource;
@@ -16,6 +17,7 @@ use super::{
 };
 use crate::machine::Machine;
 pub use dialog::*;
+pub use input::*;
 use memory::Extensions;
 pub use message::*;
 pub use paint::*;
@@ -28,12 +30,49 @@ const TRACE_CONTEXT: &'static str = "user32";
 
 type HINSTANCE = u32;
 
-#[derive(Default)]
+#[derive(Default, serde::Serialize, serde::Deserialize)]
 pub struct State {
+    #[serde(with = "shared_classes")]
     wndclasses: Vec<Rc<WndClass>>,
     pub windows: Handles<HWND, Window>,
     messages: VecDeque<MSG>,
     timers: Timers,
+    icons: Handles<HICON, crate::host::Icon>,
+    /// Whether the host window has lost focus, as last reported by the host.
+    /// This, controllers and input describe the host as it is now, so snapshots
+    /// don't record them.
+    #[serde(skip)]
+    pub background: bool,
+    /// Game controllers attached to the host, in order of arrival.  Joystick and
+    /// DirectInput enumeration read this afresh each time, so games that re-enumerate
+    /// on WM_DEVICECHANGE pick up hot-plugged controllers.
+    #[serde(skip)]
+    pub controllers: Vec<crate::host::Controller>,
+    #[serde(skip)]
+    pub input: Input,
+    /// The child window dragging the mouse, which gets mouse messages wherever
+    /// the mouse goes; see retarget_mouse.
+    pub capture: HWND,
+    /// The window SetFocus last gave the keyboard focus, which gets keyboard and
+    /// text input if it's a built-in child; see retarget_keyboard.
+    pub focus: HWND,
+}
+
+impl State {
+    pub fn window_infos(&self) -> Vec<crate::host::WindowInfo> {
+        let mut infos: Vec<_> = self
+            .windows
+            .iter()
+            .filter(|window| window.parent.is_null())
+            .map(|window| crate::host::WindowInfo {
+                hwnd: window.hwnd.to_raw(),
+                title: window.title.clone(),
+                icon: self.icons.get(window.icon).cloned(),
+            })
+            .collect();
+        infos.sort_by_key(|info| info.hwnd);
+        infos
+    }
 }
 
 #[derive(Debug, win32_derive::TryFromEnum)]
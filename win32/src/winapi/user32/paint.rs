@@ -92,8 +92,19 @@ pub fn BeginPaint(machine: &mut Machine, hWnd: HWND, lpPaint: Option<&mut PAINTS
 #[win32_derive::dllexport]
 pub fn EndPaint(machine: &mut Machine, hWnd: HWND, lpPaint: Option<&PAINTSTRUCT>) -> bool {
     let window = machine.state.user32.windows.get_mut(hWnd).unwrap();
-    window.flush_pixels(machine.emu.memory.mem());
+    let presented = window.flush_pixels(machine.emu.memory.mem(), window.client_rect());
     window.dirty = None;
+    if presented {
+        machine.presented();
+    }
+    // Child windows draw into this one, so must draw again on top of what it drew.
+    for (_, child) in machine.state.user32.windows.iter_mut() {
+        if child.parent == hWnd {
+            child.dirty = Some(UpdateRegion {
+                erase_background: false,
+            });
+        }
+    }
     true
 }
 
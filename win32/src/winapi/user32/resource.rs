@@ -1,11 +1,12 @@
+use bitflags::bitflags;
 use memory::{Extensions, Mem};
 
 use crate::{
-    pe,
+    host, pe,
     winapi::{
-        bitmap::{BitmapRGBA32, BITMAPINFOHEADER},
+        bitmap::{BitmapRGBA32, PixelData, BITMAPINFOHEADER},
         gdi32::{self, HGDIOBJ},
-        kernel32::ResourceKey,
+        kernel32::{self, ResourceKey},
         types::*,
     },
     Machine,
@@ -15,18 +16,104 @@ const TRACE_CONTEXT: &'static str = "user32/resource";
 
 // TODO: switch to the HANDLE<T> type?
 pub type HCURSOR = u32;
-pub type HICON = u32;
+pub type HICON = HANDLE<host::Icon>;
 pub type HBRUSH = HGDIOBJ;
 pub type HMENU = u32;
 
+/// Decode an RT_ICON resource, which is a DIB whose height counts both
+/// the color (XOR) bitmap and the transparency (AND) mask that follows it.
+fn parse_icon(buf: Mem) -> Option<host::Icon> {
+    let header = buf.view::<BITMAPINFOHEADER>(0);
+    let header_size = std::mem::size_of::<BITMAPINFOHEADER>() as u32;
+    if header.biSize != header_size {
+        // Vista-style icons are stored as PNG.
+        log::warn!("unsupported icon format");
+        return None;
+    }
+    let width = header.width();
+    let height = header.height() / 2;
+    let bit_count = header.biBitCount as u32;
+    let palette_len = match bit_count {
+        1 | 4 | 8 if header.biClrUsed == 0 => 1 << bit_count,
+        1 | 4 | 8 => header.biClrUsed,
+        _ => 0,
+    };
+    let xor_ofs = header_size + palette_len * 4;
+    let xor_stride = header.stride();
+    let xor = buf.sub(xor_ofs, xor_stride * height).as_slice_todo();
+    let and_ofs = xor_ofs + xor_stride * height;
+    let and_stride = ((width + 31) / 32) * 4;
+    let and = buf.sub(and_ofs, and_stride * height).as_slice_todo();
+
+    let bmp = BitmapRGBA32::parse(header, Some((xor, height as usize)));
+    let mut pixels = bmp.pixels_slice(buf).to_vec();
+
+    // 32bpp icons carry their own alpha, unless they were written by a tool
+    // that left it zeroed and relied on the mask.
+    let has_alpha = bit_count == 32 && xor.chunks(4).any(|p| p[3] != 0);
+    for y in 0..height {
+        // Both bitmaps are stored bottom-up.
+        let row = (height - y - 1) as usize;
+        for x in 0..width as usize {
+            let alpha = if has_alpha {
+                xor[row * xor_stride as usize + x * 4 + 3]
+            } else {
+                let bit = (and[row * and_stride as usize + x / 8] >> (7 - (x % 8))) & 1;
+                if bit == 0 {
+                    255
+                } else {
+                    0
+                }
+            };
+            pixels[y as usize * width as usize + x][3] = alpha;
+        }
+    }
+
+    Some(host::Icon {
+        width,
+        height,
+        pixels,
+    })
+}
+
+fn load_icon(machine: &mut Machine, name: ResourceKey<&Str16>) -> Option<HICON> {
+    let group = kernel32::find_resource(
+        &machine.state.kernel32,
+        machine.mem(),
+        ResourceKey::Id(pe::RT::GROUP_ICON as u32),
+        name,
+    )?;
+    // GRPICONDIR is a 6-byte header followed by 14-byte GRPICONDIRENTRYs.
+    // Prefer the 32x32 image with the most colors.
+    let count = group.get_pod::<u16>(4) as u32;
+    let id = (0..count)
+        .map(|i| group.sub(6 + i * 14, 14))
+        .max_by_key(|entry| (entry.get_pod::<u8>(0) == 32, entry.get_pod::<u16>(6)))?
+        .get_pod::<u16>(12);
+
+    let buf = kernel32::find_resource(
+        &machine.state.kernel32,
+        machine.mem(),
+        ResourceKey::Id(pe::RT::ICON as u32),
+        ResourceKey::Id(id as u32),
+    )?;
+    let icon = parse_icon(buf)?;
+    Some(machine.state.user32.icons.add(icon))
+}
+
 #[win32_derive::dllexport]
-pub fn LoadIconA(_machine: &mut Machine, hInstance: u32, lpIconName: u32) -> u32 {
-    0
+pub fn LoadIconA(machine: &mut Machine, hInstance: u32, lpIconName: ResourceKey<&str>) -> HICON {
+    let name = lpIconName.to_string16();
+    LoadIconW(machine, hInstance, name.as_ref())
 }
 
 #[win32_derive::dllexport]
-pub fn LoadIconW(_machine: &mut Machine, hInstance: u32, lpIconName: u32) -> u32 {
-    0
+pub fn LoadIconW(machine: &mut Machine, hInstance: u32, lpIconName: ResourceKey<&Str16>) -> HICON {
+    if hInstance == 0 {
+        // TODO: system icons, e.g. IDI_APPLICATION.
+        return HICON::null();
+    }
+    load_icon(machine, lpIconName).unwrap_or_default()
 }
 
 #[win32_derive::dllexport]
@@ -64,21 +151,94 @@ pub fn SetCursor(_machine: &mut Machine, hCursor: u32) -> u32 {
     0 // previous: null
 }
 
-fn load_bitmap(machine: &mut Machine, name: ResourceKey<&Str16>) -> Option<HGDIOBJ> {
-    let buf = crate::winapi::kernel32::find_resource(
+bitflags! {
+    /// fuLoad flags of LoadImage.
+    pub struct LR: u32 {
+        const MONOCHROME       = 0x0001;
+        const COLOR            = 0x0002;
+        const LOADFROMFILE     = 0x0010;
+        const LOADTRANSPARENT  = 0x0020;
+        const DEFAULTSIZE      = 0x0040;
+        const VGACOLOR         = 0x0080;
+        const LOADMAP3DCOLORS  = 0x1000;
+        const CREATEDIBSECTION = 0x2000;
+        const SHARED           = 0x8000;
+    }
+}
+impl TryFrom<u32> for LR {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        LR::from_bits(value).ok_or(value)
+    }
+}
+
+fn bitmap_from_resource(machine: &Machine, name: ResourceKey<&Str16>) -> Option<BitmapRGBA32> {
+    let buf = kernel32::find_resource(
         &machine.state.kernel32,
         machine.mem(),
         ResourceKey::Id(pe::RT::BITMAP as u32),
         name,
     )?;
-    let bmp = BitmapRGBA32::parse(buf.view::<BITMAPINFOHEADER>(0), None);
-    Some(
-        machine
-            .state
-            .gdi32
-            .objects
-            .add(gdi32::Object::Bitmap(gdi32::BitmapType::RGBA32(bmp))),
-    )
+    Some(BitmapRGBA32::parse(buf.view::<BITMAPINFOHEADER>(0), None))
+}
+
+/// Load a .bmp file, which is a BITMAPFILEHEADER followed by a DIB.
+fn bitmap_from_file(machine: &Machine, path: &str) -> Option<BitmapRGBA32> {
+    let buf = kernel32::read_file(machine, path);
+    const FILE_HEADER_SIZE: usize = 14;
+    if buf.len() < FILE_HEADER_SIZE + std::mem::size_of::<BITMAPINFOHEADER>() || &buf[..2] != b"BM"
+    {
+        log::warn!("{path}: not a bitmap file");
+        return None;
+    }
+    let bits_ofs = Mem::from_slice(&buf).get_pod::<u32>(10) as usize;
+    // Copy the DIB out so its header is aligned.
+    let dib = buf[FILE_HEADER_SIZE..].to_vec();
+    let header = Mem::from_slice(&dib).view::<BITMAPINFOHEADER>(0);
+    if header.biSize as usize != std::mem::size_of::<BITMAPINFOHEADER>() {
+        // e.g. OS/2 BITMAPCOREHEADER, or the V4/V5 headers.
+        log::warn!("{path}: unsupported bitmap header size {}", header.biSize);
+        return None;
+    }
+    let bits = &dib[bits_ofs - FILE_HEADER_SIZE..];
+    Some(BitmapRGBA32::parse(
+        header,
+        Some((bits, header.height() as usize)),
+    ))
+}
+
+/// Make an HBITMAP for a loaded bitmap.  With dib_section, the pixels live in
+/// guest memory as they do for CreateDIBSection, and like there they are
+/// always 32bpp regardless of the source format.
+fn add_bitmap(machine: &mut Machine, bmp: BitmapRGBA32, dib_section: bool) -> HGDIOBJ {
+    let bmp = if dib_section {
+        let pixels = bmp.pixels_slice(machine.mem());
+        let byte_count = (pixels.len() * 4) as u32;
+        let heap = kernel32::GetProcessHeap(machine);
+        let addr = kernel32::HeapAlloc(
+            machine,
+            heap,
+            Ok(kernel32::HeapAllocFlags::default()),
+            byte_count,
+        );
+        let dst = machine.mem().sub(addr, byte_count).as_mut_slice_todo();
+        for (dst, src) in dst.chunks_mut(4).zip(bmp.pixels_slice(machine.mem())) {
+            dst.copy_from_slice(src);
+        }
+        BitmapRGBA32 {
+            width: bmp.width,
+            height: bmp.height,
+            pixels: PixelData::Ptr(addr, byte_count),
+        }
+    } else {
+        bmp
+    };
+    machine
+        .state
+        .gdi32
+        .objects
+        .add(gdi32::Object::Bitmap(gdi32::BitmapType::RGBA32(bmp)))
 }
 
 #[win32_derive::dllexport]
@@ -89,26 +249,40 @@ pub fn LoadImageA(
     typ: u32,
     cx: u32,
     cy: u32,
-    fuLoad: u32,
+    fuLoad: Result<LR, u32>,
 ) -> HGDIOBJ {
-    assert!(hInstance == machine.state.kernel32.image_base);
-
-    if fuLoad != 0 {
-        log::error!("unimplemented fuLoad {:x}", fuLoad);
-        return HGDIOBJ::null();
-    }
-
-    let name = name.to_string16();
+    let flags = fuLoad.unwrap_or_else(|flags| {
+        log::warn!("LoadImageA: unknown flags {flags:x}");
+        LR::from_bits_truncate(flags)
+    });
 
     // TODO: it's unclear whether the width/height is obeyed when loading an image.
 
     const IMAGE_BITMAP: u32 = 0;
-    match typ {
-        IMAGE_BITMAP => load_bitmap(machine, name.as_ref()).unwrap(),
-        _ => {
-            log::error!("unimplemented image type {:x}", typ);
+    if typ != IMAGE_BITMAP {
+        log::error!("unimplemented image type {:x}", typ);
+        return HGDIOBJ::null();
+    }
+
+    let bmp = if flags.contains(LR::LOADFROMFILE) {
+        match name {
+            ResourceKey::Name(path) => bitmap_from_file(machine, path),
+            ResourceKey::Id(id) => {
+                // OEM bitmaps, from the display driver.
+                log::error!("unimplemented LoadImage of OEM bitmap {id}");
+                None
+            }
+        }
+    } else {
+        if hInstance != machine.state.kernel32.image_base {
+            log::error!("unimplemented LoadImage from module {hInstance:x}");
             return HGDIOBJ::null();
         }
+        bitmap_from_resource(machine, name.to_string16().as_ref())
+    };
+    match bmp {
+        Some(bmp) => add_bitmap(machine, bmp, flags.contains(LR::CREATEDIBSECTION)),
+        None => HGDIOBJ::null(),
     }
 }
 
@@ -118,16 +292,23 @@ pub fn LoadBitmapA(
     hInstance: u32,
     lpBitmapName: ResourceKey<&str>,
 ) -> HGDIOBJ {
-    assert!(hInstance == machine.state.kernel32.image_base);
+    if hInstance != machine.state.kernel32.image_base {
+        // hInstance 0 means a system (OBM_*) bitmap.
+        log::error!("unimplemented LoadBitmap from module {hInstance:x}");
+        return HGDIOBJ::null();
+    }
     let name = lpBitmapName.to_string16();
-    load_bitmap(machine, name.as_ref()).unwrap()
+    match bitmap_from_resource(machine, name.as_ref()) {
+        Some(bmp) => add_bitmap(machine, bmp, false),
+        None => HGDIOBJ::null(),
+    }
 }
 
 fn find_string(machine: &Machine, uID: u32) -> Option<Mem> {
     // Strings are stored as blocks of 16 consecutive strings.
     let (resource_id, index) = ((uID >> 4) + 1, uID & 0xF);
 
-    let block = crate::winapi::kernel32::find_resource(
+    let block = kernel32::find_resource(
         &machine.state.kernel32,
         machine.mem(),
         ResourceKey::Id(pe::RT::STRING as u32),
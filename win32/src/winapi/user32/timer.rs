@@ -4,7 +4,7 @@ use super::{MSG, WM};
 
 const TRACE_CONTEXT: &'static str = "user32/timer";
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct Timer {
     id: u32,
     /// Associated window, if any.
@@ -33,7 +33,7 @@ impl Timer {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct Timers(Vec<Timer>);
 
 impl Timers {
@@ -4,7 +4,9 @@ use crate::{
     str16::expect_ascii,
     winapi::{
         bitmap::{self, BitmapRGBA32},
+        comctl32,
         gdi32::HDC,
+        imm32, serde_bitflags,
         stack_args::FromArg,
     },
     Host, SurfaceOptions,
@@ -43,7 +45,10 @@ ShowWindow() {
 
 */
 
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct WindowPixels {
+    /// Recreated when a snapshot is restored; see restore.
+    #[serde(skip, default = "crate::winapi::detached_surface")]
     pub surface: Box<dyn host::Surface>,
     pub bitmap: BitmapRGBA32,
 }
@@ -71,22 +76,50 @@ impl WindowPixels {
     }
 }
 
+/// wParam of WM_SETICON/WM_GETICON; ICON_SMALL is 0.
+pub const ICON_BIG: u32 = 1;
+
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct UpdateRegion {
     /// Whether to erase background in BeginPaint.
     pub erase_background: bool,
     // TODO: rect
 }
 
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct Window {
     pub hwnd: HWND,
     pub hdc: HDC,
+    /// Recreated when a snapshot is restored; see restore.
+    #[serde(skip, default = "crate::winapi::detached_window")]
     pub host: Box<dyn host::Window>,
     pub width: u32,
     pub height: u32,
+    #[serde(with = "shared_class")]
     pub wndclass: Rc<WndClass>,
     pub pixels: Option<WindowPixels>,
     pub dirty: Option<UpdateRegion>,
     pub style: WindowStyle,
+    /// True when the window's contents are presented via a DirectDraw primary surface.
+    /// In that case GDI drawing isn't shown directly, but rather accumulated in
+    /// gdi_region and composited on top of the DirectDraw output when it is next
+    /// presented; see ddraw::present.
+    pub ddraw: bool,
+    /// Bounds of the part of the window drawn via GDI that DirectDraw has not yet
+    /// drawn over.
+    pub gdi_region: Option<RECT>,
+    pub title: String,
+    /// Icons as set by WM_SETICON, initially the window class icon.
+    pub icon: HICON,
+    pub icon_small: HICON,
+    /// For child windows, the parent, with the window's position in its client area
+    /// and its control ID; null for top-level windows.
+    pub parent: HWND,
+    pub x: i32,
+    pub y: i32,
+    pub id: u32,
+    /// The low word of the window style, whose meaning depends on the class.
+    pub class_style: u32,
 }
 
 impl Window {
@@ -104,13 +137,73 @@ impl Window {
         &mut self.ensure_pixels(host).bitmap
     }
 
-    pub fn flush_pixels(&mut self, mem: Mem) {
-        if let Some(pixels) = &mut self.pixels {
-            pixels
-                .surface
-                .write_pixels(&pixels.bitmap.pixels.as_slice(mem));
+    /// Push GDI drawing to the host; `rect` is the area that was drawn.
+    /// Returns whether it went to the screen, as a frame (see PauseHandle::presented).
+    pub fn flush_pixels(&mut self, mem: Mem, rect: RECT) -> bool {
+        let Some(pixels) = &mut self.pixels else {
+            return false;
+        };
+        pixels
+            .surface
+            .write_pixels(&pixels.bitmap.pixels.as_slice(mem));
+        if self.ddraw {
+            self.gdi_region = Some(match &self.gdi_region {
+                Some(region) => region.union(&rect),
+                None => rect,
+            });
+            false
+        } else {
             pixels.surface.show();
+            true
+        }
+    }
+
+    /// The window's rectangle in its parent's client area.
+    pub fn rect_in_parent(&self) -> RECT {
+        RECT {
+            left: self.x,
+            top: self.y,
+            right: self.x + self.width as i32,
+            bottom: self.y + self.height as i32,
+        }
+    }
+
+    pub fn client_rect(&self) -> RECT {
+        RECT {
+            left: 0,
+            top: 0,
+            right: self.width as i32,
+            bottom: self.height as i32,
+        }
+    }
+
+    pub fn set_title(&mut self, title: String) {
+        self.host.set_title(&title);
+        self.title = title;
+    }
+
+    /// Set the big (ICON_BIG) or small icon, returning the previous one.
+    /// The host shows the small icon if there is one.
+    pub fn set_icon(
+        &mut self,
+        icons: &Handles<HICON, host::Icon>,
+        big: bool,
+        icon: HICON,
+    ) -> HICON {
+        let prev = if big {
+            std::mem::replace(&mut self.icon, icon)
+        } else {
+            std::mem::replace(&mut self.icon_small, icon)
+        };
+        let shown = if self.icon_small.is_null() {
+            self.icon
+        } else {
+            self.icon_small
+        };
+        if let Some(icon) = icons.get(shown) {
+            self.host.set_icon(icon);
         }
+        prev
     }
 
     pub fn set_client_size(&mut self, width: u32, height: u32) {
@@ -118,21 +211,198 @@ impl Window {
         self.height = height;
         self.host.set_size(width, height);
         self.pixels = None;
+        self.gdi_region = None;
     }
 }
 
+/// Gather windows' GDI pixels for a frame dump.
+pub fn dump_windows(machine: &Machine) -> Vec<crate::framedump::SurfaceDump> {
+    let mem = machine.emu.memory.mem();
+    let mut surfaces: Vec<_> = machine
+        .state
+        .user32
+        .windows
+        .entries()
+        .filter_map(|(hwnd, window)| {
+            let pixels = window.pixels.as_ref()?;
+            Some(crate::framedump::SurfaceDump {
+                kind: "window",
+                addr: hwnd.to_raw(),
+                width: pixels.bitmap.width,
+                height: pixels.bitmap.height,
+                attached: 0,
+                pixels: Some(pixels.bitmap.pixels_slice(mem).to_vec()),
+            })
+        })
+        .collect();
+    surfaces.sort_by_key(|s| s.addr);
+    surfaces
+}
+
+/// After a snapshot is restored, recreate the host windows and surfaces of the
+/// windows it recorded, and have each window share its class with the registered
+/// one again.
+pub fn restore(machine: &mut Machine) {
+    let mem = machine.emu.memory.mem();
+    let user32 = &mut machine.state.user32;
+    for (hwnd, window) in user32.windows.iter_mut() {
+        if let Some(wndclass) = user32
+            .wndclasses
+            .iter()
+            .find(|c| c.name == window.wndclass.name)
+        {
+            window.wndclass = wndclass.clone();
+        }
+        window.host = if window.parent.is_null() {
+            machine.host.create_window(hwnd.to_raw())
+        } else {
+            Box::new(ChildWindow)
+        };
+        window.host.set_size(window.width, window.height);
+        window.host.set_title(&window.title);
+        let (icon, icon_small) = (window.icon, window.icon_small);
+        window.set_icon(&user32.icons, true, icon);
+        window.set_icon(&user32.icons, false, icon_small);
+        if let Some(pixels) = &mut window.pixels {
+            pixels.surface = machine.host.create_surface(&SurfaceOptions {
+                width: pixels.bitmap.width,
+                height: pixels.bitmap.height,
+                primary: true,
+            });
+            pixels
+                .surface
+                .write_pixels(pixels.bitmap.pixels.as_slice(mem));
+            if !window.ddraw {
+                pixels.surface.show();
+            }
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct WndClass {
     pub name: String,
     pub wndproc: u32,
     pub background: HBRUSH,
+    pub icon: HICON,
+    pub icon_small: HICON,
+    /// Registered with RegisterClassW, so its windows get UTF-16 WM_CHARs.
+    pub unicode: bool,
+    /// For classes implemented here rather than by the guest, like the common
+    /// controls, the window procedure, called in place of wndproc.
+    #[serde(with = "builtin_by_name")]
+    pub builtin: Option<BuiltinWndProc>,
+}
+
+/// Snapshots record a window's class along with the window; see restore for
+/// how windows get to share their class again.
+pub(super) mod shared_class {
+    use super::*;
+
+    pub fn serialize<S: serde::Serializer>(
+        wndclass: &Rc<WndClass>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&**wndclass, serializer)
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Rc<WndClass>, D::Error> {
+        Ok(Rc::new(serde::Deserialize::deserialize(deserializer)?))
+    }
 }
 
+pub(super) mod shared_classes {
+    use super::*;
+
+    pub fn serialize<S: serde::Serializer>(
+        wndclasses: &[Rc<WndClass>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(wndclasses.iter().map(|c| &**c))
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Rc<WndClass>>, D::Error> {
+        let wndclasses: Vec<WndClass> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(wndclasses.into_iter().map(Rc::new).collect())
+    }
+}
+
+/// Snapshots refer to builtin window procedures by the name of their class.
+mod builtin_by_name {
+    use super::*;
+    use crate::winapi::comctl32;
+
+    pub fn serialize<S: serde::Serializer>(
+        builtin: &Option<BuiltinWndProc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let name = builtin.map(|wndproc| {
+            comctl32::CLASSES
+                .iter()
+                .find(|&&(_, f)| f as usize == wndproc as usize)
+                .map(|&(name, _)| name)
+                .expect("unknown builtin window procedure")
+        });
+        serde::Serialize::serialize(&name, serializer)
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<BuiltinWndProc>, D::Error> {
+        let Some(name) = <Option<String> as serde::Deserialize>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        match comctl32::CLASSES.iter().find(|&&(n, _)| n == name) {
+            Some(&(_, wndproc)) => Ok(Some(wndproc)),
+            None => Err(serde::de::Error::custom(format!(
+                "unknown builtin class {name}"
+            ))),
+        }
+    }
+}
+
+/// A window procedure implemented here: (hwnd, msg, wParam, lParam) -> result.
+pub type BuiltinWndProc = fn(&mut Machine, HWND, u32, u32, u32) -> u32;
+
 fn register_class(machine: &mut Machine, wndclass: WndClass) -> u32 {
     let atom = machine.state.user32.wndclasses.len() as u32 + 1;
     machine.state.user32.wndclasses.push(Rc::new(wndclass));
     atom
 }
 
+/// Register a window class implemented by wndproc, unless already registered.
+/// Windows of these classes created as children have no host window of their own,
+/// but draw into their parent's.
+pub fn register_builtin_class(machine: &mut Machine, name: &str, wndproc: BuiltinWndProc) {
+    let user32 = &machine.state.user32;
+    if user32.wndclasses.iter().any(|c| c.name == name) {
+        return;
+    }
+    let wndclass = WndClass {
+        name: name.to_string(),
+        wndproc: 0,
+        background: HBRUSH::null(),
+        icon: HICON::null(),
+        icon_small: HICON::null(),
+        unicode: false,
+        builtin: Some(wndproc),
+    };
+    register_class(machine, wndclass);
+}
+
+/// A host window for windows drawn into their parent, which has nothing to show.
+struct ChildWindow;
+impl host::Window for ChildWindow {
+    fn set_title(&mut self, _title: &str) {}
+    fn set_icon(&mut self, _icon: &host::Icon) {}
+    fn set_size(&mut self, _width: u32, _height: u32) {}
+    fn set_fullscreen(&mut self, _fullscreen: bool) {}
+}
+
 #[repr(C, packed)]
 #[derive(Clone, Debug)]
 pub struct WNDCLASSA {
@@ -164,14 +434,15 @@ pub fn RegisterClassA(machine: &mut Machine, lpWndClass: Option<&WNDCLASSA>) ->
         hbrBackground: wndclass.hbrBackground,
         lpszMenuName: wndclass.lpszMenuName,
         lpszClassName: wndclass.lpszClassName,
-        hIconSm: 0,
+        hIconSm: HICON::null(),
     };
     RegisterClassExA(machine, Some(&ex))
 }
 
 #[win32_derive::dllexport]
 pub fn RegisterClassW(machine: &mut Machine, lpWndClass: Option<&WNDCLASSA>) -> u32 {
-    // TODO: calling the *W variants tags the windows as expecting wide messages(!).
+    // Calling the *W variants tags the windows as expecting wide messages(!).
+    // TODO: only WM_CHAR honors this so far.
     let lpWndClass = lpWndClass.unwrap();
     let name =
         unsafe { Str16::from_nul_term_ptr(machine.mem(), lpWndClass.lpszClassName) }.unwrap();
@@ -180,6 +451,10 @@ pub fn RegisterClassW(machine: &mut Machine, lpWndClass: Option<&WNDCLASSA>) ->
         name: name.to_string(),
         wndproc: lpWndClass.lpfnWndProc,
         background: background.to_brush(machine),
+        icon: lpWndClass.hIcon,
+        icon_small: HICON::null(),
+        unicode: true,
+        builtin: None,
     };
     register_class(machine, wndclass)
 }
@@ -211,6 +486,10 @@ pub fn RegisterClassExA(machine: &mut Machine, lpWndClassEx: Option<&WNDCLASSEXA
         wndproc: lpWndClassEx.lpfnWndProc,
         background: unsafe { BrushOrColor::from_arg(machine.mem(), lpWndClassEx.hbrBackground) }
             .to_brush(machine),
+        icon: lpWndClassEx.hIcon,
+        icon_small: lpWndClassEx.hIconSm,
+        unicode: false,
+        builtin: None,
     };
     register_class(machine, wndclass)
 }
@@ -235,6 +514,8 @@ bitflags! {
         const TABSTOP         = 0x00010000;
     }
 }
+serde_bitflags!(WindowStyle);
+
 impl TryFrom<u32> for WindowStyle {
     type Error = u32;
 
@@ -334,24 +615,46 @@ pub async fn CreateWindowExW(
         CreateWindowClassName::Atom(_) => unimplemented!(),
         CreateWindowClassName::Name(name) => name.to_string(),
     };
-    let wndclass = machine
-        .state
-        .user32
-        .wndclasses
-        .iter()
-        .find(|c| c.name == class_name)
-        .unwrap()
-        .clone();
+    // Class names are case-insensitive, as system classes like "EDIT" get spelled.
+    let find_class = |machine: &Machine| {
+        machine
+            .state
+            .user32
+            .wndclasses
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(&class_name))
+            .cloned()
+    };
+    let wndclass = match find_class(machine) {
+        Some(wndclass) => wndclass,
+        None => {
+            let (name, wndproc) = comctl32::system_class(&class_name)
+                .unwrap_or_else(|| panic!("unknown window class {class_name:?}"));
+            register_builtin_class(machine, name, wndproc);
+            find_class(machine).unwrap()
+        }
+    };
 
-    let _style = dwStyle.unwrap();
     const CW_USEDEFAULT: u32 = 0x8000_0000;
 
     // hInstance is only relevant when multiple DLLs register classes:
     //   https://devblogs.microsoft.com/oldnewthing/20050418-59/?p=35873
 
+    // The low word holds styles particular to the class, like those of controls.
+    let style_bits = match dwStyle {
+        Ok(style) => style.bits(),
+        Err(raw) => raw,
+    };
+    let style = WindowStyle::from_bits_truncate(style_bits);
+    let child =
+        style.contains(WindowStyle::CHILD) && !hWndParent.is_null() && wndclass.builtin.is_some();
+
     let hwnd = machine.state.user32.windows.reserve();
-    let mut host_win = machine.host.create_window(hwnd.to_raw());
-    host_win.set_title(&lpWindowName.unwrap().to_string());
+    let host_win: Box<dyn host::Window> = if child {
+        Box::new(ChildWindow)
+    } else {
+        machine.host.create_window(hwnd.to_raw())
+    };
     let width = if nWidth == CW_USEDEFAULT { 640 } else { nWidth };
     let height = if nHeight == CW_USEDEFAULT {
         480
@@ -359,11 +662,13 @@ pub async fn CreateWindowExW(
         nHeight
     };
 
-    let style = dwStyle.unwrap();
-    let menu = true; // TODO
-    let (width, height) = client_size_from_window_size(style, menu, width, height);
-    host_win.set_size(width, height);
-    let window = Window {
+    let (width, height) = if child {
+        (width, height)
+    } else {
+        let menu = true; // TODO
+        client_size_from_window_size(style, menu, width, height)
+    };
+    let mut window = Window {
         hwnd,
         hdc: machine.state.gdi32.dcs.add(crate::winapi::gdi32::DC::new(
             crate::winapi::gdi32::DCTarget::Window(hwnd),
@@ -377,7 +682,22 @@ pub async fn CreateWindowExW(
             erase_background: true,
         }),
         style,
+        ddraw: false,
+        gdi_region: None,
+        title: String::new(),
+        icon: HICON::null(),
+        icon_small: HICON::null(),
+        parent: if child { hWndParent } else { HWND::null() },
+        x: if child { X as i32 } else { 0 },
+        y: if child { Y as i32 } else { 0 },
+        id: if child { hMenu } else { 0 },
+        class_style: style_bits & 0xFFFF,
     };
+    window.host.set_size(width, height);
+    window.set_title(lpWindowName.unwrap().to_string());
+    let icons = &machine.state.user32.icons;
+    window.set_icon(icons, true, window.wndclass.icon);
+    window.set_icon(icons, false, window.wndclass.icon_small);
     machine.state.user32.windows.set(hwnd, window);
 
     // Synchronously dispatch WM_CREATE.
@@ -506,14 +826,29 @@ pub async fn ShowWindow(machine: &mut Machine, hWnd: HWND, nCmdShow: Result<SW,
 }
 
 #[win32_derive::dllexport]
-pub fn SetFocus(_machine: &mut Machine, hWnd: HWND) -> HWND {
-    let prev_focused = HWND::null();
+pub fn SetFocus(machine: &mut Machine, hWnd: HWND) -> HWND {
+    let user32 = &mut machine.state.user32;
+    let prev_focused = std::mem::replace(&mut user32.focus, hWnd);
+    // Built-in children show focus, as with an edit control's caret.
+    for hwnd in [prev_focused, hWnd] {
+        if let Some(window) = user32.windows.get_mut(hwnd) {
+            if !window.parent.is_null() {
+                window.dirty = Some(UpdateRegion {
+                    erase_background: false,
+                });
+            }
+        }
+    }
     prev_focused
 }
 
 #[win32_derive::dllexport]
 pub fn GetFocus(machine: &mut Machine) -> HWND {
-    machine.state.user32.windows.iter().next().unwrap().hwnd
+    let user32 = &machine.state.user32;
+    if user32.windows.get(user32.focus).is_some() {
+        return user32.focus;
+    }
+    user32.windows.iter().next().unwrap().hwnd
 }
 
 #[win32_derive::dllexport]
@@ -530,6 +865,41 @@ pub fn DefWindowProcA(
                 let window = machine.state.user32.windows.get_mut(hWnd).unwrap();
                 window.dirty = None;
             }
+            WM::SETTEXT => {
+                let title = expect_ascii(machine.mem().slicez(lParam)).to_string();
+                let window = machine.state.user32.windows.get_mut(hWnd).unwrap();
+                window.set_title(title);
+                return true as u32;
+            }
+            WM::IME_COMPOSITION => {
+                if lParam & imm32::GCS_RESULTSTR != 0 {
+                    let now = machine.host.time();
+                    post_ime_result(&mut machine.state, hWnd, now);
+                }
+                return 0;
+            }
+            WM::IME_CHAR => {
+                let now = machine.host.time();
+                post_ime_char(&mut machine.state.user32, hWnd, wParam, now);
+                return 0;
+            }
+            WM::SETICON => {
+                let user32 = &mut machine.state.user32;
+                let window = user32.windows.get_mut(hWnd).unwrap();
+                let big = wParam == ICON_BIG;
+                return window
+                    .set_icon(&user32.icons, big, HICON::from_raw(lParam))
+                    .to_raw();
+            }
+            WM::GETICON => {
+                let window = machine.state.user32.windows.get(hWnd).unwrap();
+                let icon = if wParam == ICON_BIG {
+                    window.icon
+                } else {
+                    window.icon_small
+                };
+                return icon.to_raw();
+            }
             _ => {}
         }
     }
@@ -544,6 +914,14 @@ pub fn DefWindowProcW(
     wParam: u32,
     lParam: u32,
 ) -> u32 {
+    if let Ok(WM::SETTEXT) = msg {
+        let title = unsafe { Str16::from_nul_term_ptr(machine.mem(), lParam) }
+            .unwrap()
+            .to_string();
+        let window = machine.state.user32.windows.get_mut(hWnd).unwrap();
+        window.set_title(title);
+        return true as u32;
+    }
     DefWindowProcA(machine, hWnd, msg, wParam, lParam)
 }
 
@@ -640,6 +1018,15 @@ pub fn MoveWindow(
     bRepaint: bool,
 ) -> bool {
     let window = machine.state.user32.windows.get_mut(hWnd).unwrap();
+    if !window.parent.is_null() {
+        window.x = X as i32;
+        window.y = Y as i32;
+        window.set_client_size(nWidth, nHeight);
+        window.dirty = Some(UpdateRegion {
+            erase_background: true,
+        });
+        return true;
+    }
     let menu = true; // TODO
     let (width, height) = client_size_from_window_size(window.style, menu, nWidth, nHeight);
     window.set_client_size(width, height);
@@ -647,8 +1034,14 @@ pub fn MoveWindow(
 }
 
 #[win32_derive::dllexport]
-pub fn GetClientRect(_machine: &mut Machine, hWnd: HWND, lpRect: Option<&mut RECT>) -> bool {
+pub fn GetClientRect(machine: &mut Machine, hWnd: HWND, lpRect: Option<&mut RECT>) -> bool {
     let rect = lpRect.unwrap();
+    if let Some(window) = machine.state.user32.windows.get(hWnd) {
+        if !window.parent.is_null() {
+            *rect = window.client_rect();
+            return true;
+        }
+    }
     *rect = RECT {
         left: 0,
         top: 0,
@@ -728,7 +1121,21 @@ pub fn ReleaseCapture(_machine: &mut Machine) -> bool {
 pub fn SetWindowTextA(machine: &mut Machine, hWnd: HWND, lpString: Option<&str>) -> bool {
     match machine.state.user32.windows.get_mut(hWnd) {
         Some(window) => {
-            window.host.set_title(lpString.unwrap());
+            window.set_title(lpString.unwrap().to_string());
+            true
+        }
+        None => {
+            log::error!("SetWindowText of non-window?");
+            false
+        }
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn SetWindowTextW(machine: &mut Machine, hWnd: HWND, lpString: Option<&Str16>) -> bool {
+    match machine.state.user32.windows.get_mut(hWnd) {
+        Some(window) => {
+            window.set_title(lpString.unwrap().to_string());
             true
         }
         None => {
@@ -1,29 +1,73 @@
 #![allow(non_snake_case)]
 
-use crate::machine::Machine;
-use memory::Extensions;
+use crate::{
+    machine::{Emulator, Machine},
+    winapi::kernel32,
+};
+use memory::{Extensions, Mem};
 
 const TRACE_CONTEXT: &'static str = "vcruntime140";
 
+/// The first address in addr..addr+len that would fault on access, if any.
+/// These routines run in place of guest code (see pe/fast_paths.rs), so must fault
+/// as it would rather than panic on bad pointers.
+pub fn fault_addr(mem: Mem, addr: u32, len: u32, write: bool) -> Option<u32> {
+    if len == 0 {
+        return None;
+    }
+    let accessible = mem.accessible_len(addr, len);
+    if accessible < len {
+        return Some(addr.wrapping_add(accessible));
+    }
+    if write && !mem.is_writable(addr, len) {
+        return Some(addr);
+    }
+    None
+}
+
 #[win32_derive::dllexport(cdecl)]
-pub fn memcpy(machine: &mut Machine, dst: u32, src: u32, len: u32) -> u32 {
-    // TODO: this probably violates Rust rules around aliasing, if callers expect memmove.
+pub async fn memcpy(machine: &mut Machine, dst: u32, src: u32, len: u32) -> u32 {
+    // MSVC's memcpy is memmove, and callers (and the CRT fast paths) rely on that.
+    memmove(machine, dst, src, len).await
+}
+
+#[win32_derive::dllexport(cdecl)]
+pub async fn memmove(machine: &mut Machine, dst: u32, src: u32, len: u32) -> u32 {
+    let mem = machine.mem();
+    let fault = match fault_addr(mem, src, len, false) {
+        Some(addr) => Some((addr, false)),
+        None => fault_addr(mem, dst, len, true).map(|addr| (addr, true)),
+    };
+    if let Some((addr, write)) = fault {
+        kernel32::raise_access_violation(machine, addr, write).await;
+        return 0;
+    }
+    let (lo, hi) = (dst.min(src), dst.max(src));
+    let (src, dst) = ((src - lo) as usize, (dst - lo) as usize);
     machine
         .mem()
-        .sub(dst, len)
+        .sub(lo, hi - lo + len)
         .as_mut_slice_todo()
-        .copy_from_slice(machine.mem().sub(src, len).as_slice_todo());
-    dst
+        .copy_within(src..src + len as usize, dst);
+    // Unpackers copy code into place with memcpy, which the CRT fast paths run here
+    // rather than as x86 writes that the code cache would notice.
+    machine.emu.invalidate_code(lo + dst as u32, len);
+    lo + dst as u32
 }
 
 #[win32_derive::dllexport(cdecl)]
-pub fn memset(machine: &mut Machine, dst: u32, val: u32, len: u32) -> u32 {
+pub async fn memset(machine: &mut Machine, dst: u32, val: u32, len: u32) -> u32 {
+    if let Some(addr) = fault_addr(machine.mem(), dst, len, true) {
+        kernel32::raise_access_violation(machine, addr, true).await;
+        return 0;
+    }
     machine
         .mem()
         .sub(dst, len)
         .as_mut_slice_todo()
         .fill(val as u8);
-    0
+    machine.emu.invalidate_code(dst, len);
+    dst
 }
 
 #[win32_derive::dllexport(cdecl)]
@@ -0,0 +1,334 @@
+//! File version information, from the VS_VERSIONINFO resource of PE files.
+
+#![allow(non_snake_case)]
+
+use super::kernel32;
+use crate::{machine::Machine, pe};
+use memory::Extensions;
+
+const TRACE_CONTEXT: &'static str = "version";
+
+/*
+## Version resources
+
+GetFileVersionInfo finds a file's version resource in its image if it's loaded,
+else in the file itself.  Builtin DLLs have no image, so are given a version
+resource made up here, claiming the versions Windows 98 shipped with, so that
+checks for a new enough DirectX pass.
+
+The resource is the UTF-16 form of VS_VERSIONINFO.  As on Windows, the size
+GetFileVersionInfoSizeA reports leaves room after it for VerQueryValueA to place
+the ANSI copies of the strings it's asked for.
+*/
+
+const ERROR_FILE_NOT_FOUND: u32 = 2;
+const ERROR_RESOURCE_TYPE_NOT_FOUND: u32 = 1813;
+
+/// A builtin DLL's version, as the four parts of e.g. 4.07.00.0700.
+fn builtin_version(file_name: &str) -> [u16; 4] {
+    match file_name {
+        "ddraw.dll" | "dsound.dll" | "dinput.dll" => [4, 7, 0, 700], // DirectX 7.0a
+        "dinput8.dll" => [4, 8, 1, 881],                             // DirectX 8.1
+        _ => [4, 10, 0, 2222],                                       // Windows 98 SE
+    }
+}
+
+/// Build a node of a VS_VERSIONINFO tree: a header, key, value, and children, each
+/// aligned to 4 bytes.
+fn node(key: &str, value: &[u8], text: bool, children: &[Vec<u8>]) -> Vec<u8> {
+    let mut buf = vec![0u8; 6];
+    buf.extend(key.encode_utf16().chain([0]).flat_map(|u| u.to_le_bytes()));
+    let pad = |buf: &mut Vec<u8>| buf.resize(buf.len().next_multiple_of(4), 0);
+    pad(&mut buf);
+    buf.extend_from_slice(value);
+    for child in children {
+        pad(&mut buf);
+        buf.extend_from_slice(child);
+    }
+    // Text values' lengths are in UTF-16 units.
+    let value_len = if text { value.len() / 2 } else { value.len() };
+    let len = buf.len() as u16;
+    buf[0..2].copy_from_slice(&len.to_le_bytes());
+    buf[2..4].copy_from_slice(&(value_len as u16).to_le_bytes());
+    buf[4..6].copy_from_slice(&(text as u16).to_le_bytes());
+    buf
+}
+
+fn text_node(key: &str, value: &str) -> Vec<u8> {
+    let value: Vec<u8> = value
+        .encode_utf16()
+        .chain([0])
+        .flat_map(|u| u.to_le_bytes())
+        .collect();
+    node(key, &value, true, &[])
+}
+
+/// Make up a version resource for a builtin DLL.
+fn builtin_version_info(file_name: &str) -> Vec<u8> {
+    let [major, minor, build, revision] = builtin_version(file_name);
+    let ms = (major as u32) << 16 | minor as u32;
+    let ls = (build as u32) << 16 | revision as u32;
+    let fixed: Vec<u8> = [
+        0xFEEF04BD, // dwSignature
+        0x00010000, // dwStrucVersion
+        ms,         // dwFileVersionMS
+        ls,         // dwFileVersionLS
+        ms,         // dwProductVersionMS
+        ls,         // dwProductVersionLS
+        0x3F,       // dwFileFlagsMask
+        0,          // dwFileFlags
+        0x00040004, // dwFileOS: VOS_NT_WINDOWS32
+        2,          // dwFileType: VFT_DLL
+        0,          // dwFileSubtype
+        0,          // dwFileDateMS
+        0,          // dwFileDateLS
+    ]
+    .iter()
+    .flat_map(|d: &u32| d.to_le_bytes())
+    .collect();
+    let version = format!("{major}.{minor:02}.{build:02}.{revision:04}");
+    let strings = node(
+        "040904B0",
+        &[],
+        true,
+        &[
+            text_node("FileDescription", file_name),
+            text_node("FileVersion", &version),
+            text_node("InternalName", file_name),
+            text_node("OriginalFilename", file_name),
+            text_node("ProductVersion", &version),
+        ],
+    );
+    let translation = node("Translation", &[0x09, 0x04, 0xB0, 0x04], false, &[]);
+    node(
+        "VS_VERSION_INFO",
+        &fixed,
+        false,
+        &[
+            node("StringFileInfo", &[], true, &[strings]),
+            node("VarFileInfo", &[], true, &[translation]),
+        ],
+    )
+}
+
+/// Find the version resource in a PE image, laid out as loaded.
+fn image_version_info(image: &[u8], lang: u16) -> Option<Vec<u8>> {
+    let file = pe::parse(image).ok()?;
+    let dir = file.get_data_directory(pe::IMAGE_DIRECTORY_ENTRY::RESOURCE)?;
+    let section = dir.as_slice(image)?;
+    let range = pe::find_resource(
+        section,
+        pe::ResourceName::Id(pe::RT::VERSION as u32),
+        pe::ResourceName::Id(1),
+        lang,
+    )?;
+    image
+        .get(range.start as usize..range.end as usize)
+        .map(|r| r.to_vec())
+}
+
+/// Lay out a PE file's headers and sections as they'd be loaded.
+fn layout_image(buf: &[u8]) -> Option<Vec<u8>> {
+    let file = pe::parse(buf).ok()?;
+    let mut image = vec![0u8; file.opt_header.SizeOfImage as usize];
+    let headers = (file.opt_header.SizeOfHeaders as usize).min(buf.len());
+    image.get_mut(..headers)?.copy_from_slice(&buf[..headers]);
+    for section in file.sections.iter() {
+        let src = buf.get(
+            section.PointerToRawData as usize
+                ..(section.PointerToRawData + section.SizeOfRawData) as usize,
+        )?;
+        let len = src
+            .len()
+            .min(image.len().saturating_sub(section.VirtualAddress as usize));
+        image[section.VirtualAddress as usize..][..len].copy_from_slice(&src[..len]);
+    }
+    Some(image)
+}
+
+/// Find a file's version resource, or the error to report.
+fn version_info(machine: &Machine, filename: &str) -> Result<Vec<u8>, u32> {
+    let lang = machine.state.kernel32.ui_language;
+    let base_name = filename
+        .rsplit(['\\', '/'])
+        .next()
+        .unwrap_or(filename)
+        .trim_matches('"')
+        .to_ascii_lowercase();
+
+    // A loaded image, including the exe, whose name may be a whole command line.
+    let loaded = machine.state.kernel32.images.iter().find(|image| {
+        let name = image.name.to_ascii_lowercase();
+        let name = match name.find(".exe") {
+            Some(end) => &name[..end + 4],
+            None => &name,
+        };
+        let name = name.trim_start_matches('"');
+        name.rsplit(['\\', '/']).next().unwrap_or(name) == base_name
+    });
+    if let Some(image) = loaded {
+        let mem = machine.mem().sub(image.base, image.size);
+        return image_version_info(mem.as_slice_todo(), lang).ok_or(ERROR_RESOURCE_TYPE_NOT_FOUND);
+    }
+
+    if let Some(builtin) = super::DLLS.iter().find(|dll| dll.file_name == base_name) {
+        return Ok(builtin_version_info(builtin.file_name));
+    }
+
+    let buf = kernel32::read_file(machine, filename);
+    if buf.is_empty() {
+        return Err(ERROR_FILE_NOT_FOUND);
+    }
+    layout_image(&buf)
+        .and_then(|image| image_version_info(&image, lang))
+        .ok_or(ERROR_RESOURCE_TYPE_NOT_FOUND)
+}
+
+#[win32_derive::dllexport]
+pub fn GetFileVersionInfoSizeA(
+    machine: &mut Machine,
+    lptstrFilename: Option<&str>,
+    lpdwHandle: Option<&mut u32>,
+) -> u32 {
+    if let Some(handle) = lpdwHandle {
+        *handle = 0;
+    }
+    match version_info(machine, lptstrFilename.unwrap_or("")) {
+        // Room for the ANSI strings too; see VerQueryValueA.
+        Ok(info) => info.len() as u32 * 2,
+        Err(err) => {
+            kernel32::SetLastError(machine, err);
+            0
+        }
+    }
+}
+
+#[win32_derive::dllexport]
+pub fn GetFileVersionInfoA(
+    machine: &mut Machine,
+    lptstrFilename: Option<&str>,
+    dwHandle: u32,
+    dwLen: u32,
+    lpData: u32,
+) -> bool {
+    let info = match version_info(machine, lptstrFilename.unwrap_or("")) {
+        Ok(info) => info,
+        Err(err) => {
+            kernel32::SetLastError(machine, err);
+            return false;
+        }
+    };
+    let len = std::cmp::min(info.len() as u32, dwLen);
+    machine
+        .mem()
+        .sub(lpData, len)
+        .as_mut_slice_todo()
+        .copy_from_slice(&info[..len as usize]);
+    true
+}
+
+/// A node of a VS_VERSIONINFO tree, by offsets into the block holding it.
+struct Node {
+    key: String,
+    /// Offset and length in bytes of the value.
+    value: (usize, usize),
+    text: bool,
+    /// Offset and end of the children.
+    children: (usize, usize),
+}
+
+fn read_node(block: &[u8], ofs: usize) -> Option<Node> {
+    let word = |i: usize| -> Option<u16> {
+        let b = block.get(ofs + i..ofs + i + 2)?;
+        Some(u16::from_le_bytes([b[0], b[1]]))
+    };
+    let (len, value_len, text) = (word(0)? as usize, word(2)? as usize, word(4)? == 1);
+    let end = (ofs + len).min(block.len());
+    let mut key = Vec::new();
+    let mut pos = ofs + 6;
+    loop {
+        let unit = u16::from_le_bytes([*block.get(pos)?, *block.get(pos + 1)?]);
+        pos += 2;
+        if unit == 0 {
+            break;
+        }
+        key.push(unit);
+    }
+    let value_ofs = pos.next_multiple_of(4);
+    let value_bytes = if text { value_len * 2 } else { value_len };
+    let value_end = (value_ofs + value_bytes).min(end);
+    Some(Node {
+        key: String::from_utf16_lossy(&key),
+        value: (value_ofs, value_end.saturating_sub(value_ofs)),
+        text,
+        children: (value_end.next_multiple_of(4), end),
+    })
+}
+
+/// Find the node at a path like \StringFileInfo\040904B0\FileVersion.
+fn find_node(block: &[u8], path: &str) -> Option<Node> {
+    let mut node = read_node(block, 0)?;
+    for part in path.split('\\').filter(|part| !part.is_empty()) {
+        let (mut ofs, end) = node.children;
+        node = loop {
+            if ofs >= end {
+                return None;
+            }
+            let child = read_node(block, ofs)?;
+            let len = u16::from_le_bytes([block[ofs], block[ofs + 1]]) as usize;
+            if child.key.eq_ignore_ascii_case(part) {
+                break child;
+            }
+            if len == 0 {
+                return None;
+            }
+            ofs = (ofs + len).next_multiple_of(4);
+        };
+    }
+    Some(node)
+}
+
+#[win32_derive::dllexport]
+pub fn VerQueryValueA(
+    machine: &mut Machine,
+    pBlock: u32,
+    lpSubBlock: Option<&str>,
+    lplpBuffer: Option<&mut u32>,
+    puLen: Option<&mut u32>,
+) -> bool {
+    let mem = machine.mem();
+    let total = mem.get_pod::<u16>(pBlock) as u32;
+    let block = mem.sub(pBlock, total).as_slice_todo();
+    let Some(node) = find_node(block, lpSubBlock.unwrap_or("\\")) else {
+        return false;
+    };
+    let (ofs, len) = node.value;
+    let (addr, len) = if node.text && len > 0 {
+        // Convert the string into the ANSI area after the block, at the same offset.
+        let units = block[ofs..ofs + len]
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .take_while(|&u| u != 0);
+        let ansi: Vec<u8> = char::decode_utf16(units)
+            .map(|c| match c {
+                Ok(c) if c.is_ascii() => c as u8,
+                _ => b'?',
+            })
+            .chain([0])
+            .collect();
+        let addr = pBlock + total + ofs as u32;
+        mem.sub(addr, ansi.len() as u32)
+            .as_mut_slice_todo()
+            .copy_from_slice(&ansi);
+        (addr, ansi.len() as u32)
+    } else {
+        (pBlock + ofs as u32, len as u32)
+    };
+    if let Some(buffer) = lplpBuffer {
+        *buffer = addr;
+    }
+    if let Some(out) = puLen {
+        *out = len;
+    }
+    true
+}
@@ -0,0 +1,341 @@
+//! The joystick API, backed by the game controllers attached to the host.
+
+use crate::{
+    host::{GamepadButton, GamepadState},
+    machine::Machine,
+};
+use memory::Pod;
+
+const TRACE_CONTEXT: &'static str = "winmm/joystick";
+
+/*
+## Joysticks
+
+Joystick ids index the host's controllers in the order they were attached; ids
+past those are unplugged.  A controller's sticks, triggers and buttons are assigned
+to joystick axes and buttons by a JoystickMap, configurable as some games expect
+particular layouts.  The D-pad is reported as the point-of-view hat.
+*/
+
+const MMSYSERR_NOERROR: u32 = 0;
+const MMSYSERR_NODRIVER: u32 = 6;
+const MMSYSERR_INVALPARAM: u32 = 11;
+const JOYERR_PARMS: u32 = 165;
+const JOYERR_UNPLUGGED: u32 = 167;
+
+/// How many joysticks the driver supports, attached or not.
+const NUM_DEVS: u32 = 16;
+
+/// Axis positions run from 0 to AXIS_MAX, centered at half.
+pub(crate) const AXIS_MAX: u32 = 0xFFFF;
+
+const JOYCAPS_HASZ: u32 = 0x1;
+const JOYCAPS_HASR: u32 = 0x2;
+const JOYCAPS_HASU: u32 = 0x4;
+const JOYCAPS_HASV: u32 = 0x8;
+const JOYCAPS_HASPOV: u32 = 0x10;
+const JOYCAPS_POV4DIR: u32 = 0x20;
+
+pub(crate) const JOY_POVCENTERED: u32 = 0xFFFF;
+
+/// Host controller axes, as named in a JoystickMap, in GamepadState::axes order.
+const HOST_AXES: [&str; 6] = ["lx", "ly", "rx", "ry", "lt", "rt"];
+
+/// Host controller buttons, as named in a JoystickMap.
+const HOST_BUTTONS: [(&str, GamepadButton); 15] = [
+    ("a", GamepadButton::A),
+    ("b", GamepadButton::B),
+    ("x", GamepadButton::X),
+    ("y", GamepadButton::Y),
+    ("back", GamepadButton::Back),
+    ("guide", GamepadButton::Guide),
+    ("start", GamepadButton::Start),
+    ("ls", GamepadButton::LeftStick),
+    ("rs", GamepadButton::RightStick),
+    ("lb", GamepadButton::LeftShoulder),
+    ("rb", GamepadButton::RightShoulder),
+    ("up", GamepadButton::DPadUp),
+    ("down", GamepadButton::DPadDown),
+    ("left", GamepadButton::DPadLeft),
+    ("right", GamepadButton::DPadRight),
+];
+
+/// Assignment of host controller inputs to joystick axes and buttons.
+#[derive(Debug, Clone)]
+pub struct JoystickMap {
+    /// Host axis (index into GamepadState::axes) for each of the X, Y, Z, R, U and
+    /// V joystick axes, if any.
+    pub(crate) axes: [Option<usize>; 6],
+    /// Host button for each joystick button, from button 1.
+    pub(crate) buttons: Vec<GamepadButton>,
+}
+
+impl Default for JoystickMap {
+    /// The layout DirectInput gives common controllers: the left stick as X/Y, the
+    /// right stick as R/U, the triggers as Z and V.
+    fn default() -> Self {
+        "x=lx,y=ly,z=rt,r=rx,u=ry,v=lt,buttons=a/b/x/y/lb/rb/back/start/ls/rs"
+            .parse()
+            .unwrap()
+    }
+}
+
+impl std::str::FromStr for JoystickMap {
+    type Err = String;
+
+    /// Parses assignments like "x=lx,y=ly,z=rt,buttons=a/b/x/y", where axes left out
+    /// are absent; see HOST_AXES and HOST_BUTTONS for the names of host inputs.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut map = JoystickMap {
+            axes: [None; 6],
+            buttons: Vec::new(),
+        };
+        for part in s.split(',') {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got {part:?}"))?;
+            if key == "buttons" {
+                map.buttons = value
+                    .split('/')
+                    .map(|name| {
+                        HOST_BUTTONS
+                            .iter()
+                            .find(|&&(n, _)| n == name)
+                            .map(|&(_, button)| button)
+                            .ok_or_else(|| format!("unknown button {name:?}"))
+                    })
+                    .collect::<Result<_, _>>()?;
+                continue;
+            }
+            let axis = ["x", "y", "z", "r", "u", "v"]
+                .iter()
+                .position(|&a| a == key)
+                .ok_or_else(|| format!("unknown joystick axis {key:?}"))?;
+            let host = HOST_AXES
+                .iter()
+                .position(|&a| a == value)
+                .ok_or_else(|| format!("unknown controller axis {value:?}"))?;
+            map.axes[axis] = Some(host);
+        }
+        Ok(map)
+    }
+}
+
+/// A joystick's position, as mapped from its controller.
+pub(crate) struct Position {
+    /// X, Y, Z, R, U and V, from 0 to AXIS_MAX.
+    pub axes: [u32; 6],
+    pub buttons: u32,
+    /// Hundredths of a degree clockwise from forward, or JOY_POVCENTERED.
+    pub pov: u32,
+}
+
+impl JoystickMap {
+    fn position(&self, state: &GamepadState) -> Position {
+        let axes = self.axes.map(|host| match host {
+            // Sticks are centered at zero, triggers rest at zero.
+            Some(i @ 0..=3) => (state.axes[i] as i32 + 0x8000) as u32,
+            Some(i) => std::cmp::max(state.axes[i], 0) as u32 * 2,
+            None => AXIS_MAX / 2,
+        });
+        let down = |button: GamepadButton| state.buttons & (1 << button as u32) != 0;
+        let buttons = self
+            .buttons
+            .iter()
+            .enumerate()
+            .filter(|&(_, &button)| down(button))
+            .fold(0, |bits, (i, _)| bits | 1 << i);
+        let (up, right) = (down(GamepadButton::DPadUp), down(GamepadButton::DPadRight));
+        let (down, left) = (down(GamepadButton::DPadDown), down(GamepadButton::DPadLeft));
+        let pov = match (up, right, down, left) {
+            (true, false, _, true) => 31500,
+            (true, true, _, _) => 4500,
+            (true, _, _, _) => 0,
+            (_, true, true, _) => 13500,
+            (_, true, _, _) => 9000,
+            (_, _, true, true) => 22500,
+            (_, _, true, _) => 18000,
+            (_, _, _, true) => 27000,
+            _ => JOY_POVCENTERED,
+        };
+        Position { axes, buttons, pov }
+    }
+}
+
+/// Read the position of a joystick, or None if it's unplugged.
+pub(crate) fn joystick_position(machine: &Machine, uJoyID: u32) -> Option<Position> {
+    let controller = machine.state.user32.controllers.get(uJoyID as usize)?;
+    let state = machine.host.gamepad(controller.id)?;
+    Some(machine.state.winmm.joystick_map.position(&state))
+}
+
+#[win32_derive::dllexport]
+pub fn joyGetNumDevs(_machine: &mut Machine) -> u32 {
+    NUM_DEVS
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct JOYCAPSA {
+    pub wMid: u16,
+    pub wPid: u16,
+    pub szPname: [u8; 32],
+    pub wXmin: u32,
+    pub wXmax: u32,
+    pub wYmin: u32,
+    pub wYmax: u32,
+    pub wZmin: u32,
+    pub wZmax: u32,
+    pub wNumButtons: u32,
+    pub wPeriodMin: u32,
+    pub wPeriodMax: u32,
+    pub wRmin: u32,
+    pub wRmax: u32,
+    pub wUmin: u32,
+    pub wUmax: u32,
+    pub wVmin: u32,
+    pub wVmax: u32,
+    pub wCaps: u32,
+    pub wMaxAxes: u32,
+    pub wNumAxes: u32,
+    pub wMaxButtons: u32,
+    pub szRegKey: [u8; 32],
+    pub szOEMVxD: [u8; 260],
+}
+unsafe impl Pod for JOYCAPSA {}
+
+#[win32_derive::dllexport]
+pub fn joyGetDevCapsA(
+    machine: &mut Machine,
+    uJoyID: u32,
+    pjc: Option<&mut JOYCAPSA>,
+    cbjc: u32,
+) -> u32 {
+    // An id of -1 asks the driver to rescan, which we always do anyway.
+    if uJoyID == !0 {
+        return MMSYSERR_NOERROR;
+    }
+    if uJoyID >= NUM_DEVS {
+        return MMSYSERR_NODRIVER;
+    }
+    let Some(pjc) = pjc else {
+        return MMSYSERR_INVALPARAM;
+    };
+    if cbjc < std::mem::size_of::<JOYCAPSA>() as u32 {
+        return MMSYSERR_INVALPARAM;
+    }
+    let Some(controller) = machine.state.user32.controllers.get(uJoyID as usize) else {
+        return JOYERR_PARMS;
+    };
+    let map = &machine.state.winmm.joystick_map;
+
+    pjc.clear_struct();
+    let name = controller.name.as_bytes();
+    let len = std::cmp::min(name.len(), pjc.szPname.len() - 1);
+    pjc.szPname[..len].copy_from_slice(&name[..len]);
+    let has = |axis: usize| map.axes[axis].is_some();
+    for (axis, (min, max)) in [
+        (&mut pjc.wXmin, &mut pjc.wXmax),
+        (&mut pjc.wYmin, &mut pjc.wYmax),
+        (&mut pjc.wZmin, &mut pjc.wZmax),
+        (&mut pjc.wRmin, &mut pjc.wRmax),
+        (&mut pjc.wUmin, &mut pjc.wUmax),
+        (&mut pjc.wVmin, &mut pjc.wVmax),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        *min = 0;
+        *max = if has(axis) { AXIS_MAX } else { 0 };
+    }
+    pjc.wNumButtons = map.buttons.len() as u32;
+    pjc.wMaxButtons = 32;
+    pjc.wPeriodMin = 10;
+    pjc.wPeriodMax = 1000;
+    pjc.wNumAxes = map.axes.iter().filter(|a| a.is_some()).count() as u32;
+    pjc.wMaxAxes = 6;
+    pjc.wCaps = JOYCAPS_HASPOV | JOYCAPS_POV4DIR;
+    for (axis, cap) in [
+        (2, JOYCAPS_HASZ),
+        (3, JOYCAPS_HASR),
+        (4, JOYCAPS_HASU),
+        (5, JOYCAPS_HASV),
+    ] {
+        if has(axis) {
+            pjc.wCaps |= cap;
+        }
+    }
+    MMSYSERR_NOERROR
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct JOYINFO {
+    pub wXpos: u32,
+    pub wYpos: u32,
+    pub wZpos: u32,
+    pub wButtons: u32,
+}
+unsafe impl Pod for JOYINFO {}
+
+#[win32_derive::dllexport]
+pub fn joyGetPos(machine: &mut Machine, uJoyID: u32, pji: Option<&mut JOYINFO>) -> u32 {
+    let Some(pji) = pji else {
+        return MMSYSERR_INVALPARAM;
+    };
+    if uJoyID >= NUM_DEVS {
+        return JOYERR_PARMS;
+    }
+    let Some(pos) = joystick_position(machine, uJoyID) else {
+        return JOYERR_UNPLUGGED;
+    };
+    pji.wXpos = pos.axes[0];
+    pji.wYpos = pos.axes[1];
+    pji.wZpos = pos.axes[2];
+    // JOYINFO only has room for the first four buttons.
+    pji.wButtons = pos.buttons & 0xF;
+    MMSYSERR_NOERROR
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct JOYINFOEX {
+    pub dwSize: u32,
+    pub dwFlags: u32,
+    pub dwXpos: u32,
+    pub dwYpos: u32,
+    pub dwZpos: u32,
+    pub dwRpos: u32,
+    pub dwUpos: u32,
+    pub dwVpos: u32,
+    pub dwButtons: u32,
+    pub dwButtonNumber: u32,
+    pub dwPOV: u32,
+    pub dwReserved1: u32,
+    pub dwReserved2: u32,
+}
+unsafe impl Pod for JOYINFOEX {}
+
+#[win32_derive::dllexport]
+pub fn joyGetPosEx(machine: &mut Machine, uJoyID: u32, pji: Option<&mut JOYINFOEX>) -> u32 {
+    let Some(pji) = pji else {
+        return MMSYSERR_INVALPARAM;
+    };
+    if pji.dwSize != std::mem::size_of::<JOYINFOEX>() as u32 || uJoyID >= NUM_DEVS {
+        return JOYERR_PARMS;
+    }
+    let Some(pos) = joystick_position(machine, uJoyID) else {
+        return JOYERR_UNPLUGGED;
+    };
+    // dwFlags selects which fields are wanted, but filling them all is harmless.
+    pji.dwXpos = pos.axes[0];
+    pji.dwYpos = pos.axes[1];
+    pji.dwZpos = pos.axes[2];
+    pji.dwRpos = pos.axes[3];
+    pji.dwUpos = pos.axes[4];
+    pji.dwVpos = pos.axes[5];
+    pji.dwButtons = pos.buttons;
+    pji.dwButtonNumber = pos.buttons.count_ones();
+    pji.dwPOV = pos.pov;
+    MMSYSERR_NOERROR
+}
@@ -0,0 +1,1263 @@
+//! MCI, the Media Control Interface: a command layer over multimedia devices, driven
+//! either by command messages (mciSendCommand) or command strings (mciSendString).
+//! Games mostly use it to play sounds, MIDI music, and CD audio soundtracks.
+
+use super::WAVEFORMATEX;
+use crate::{
+    machine::{Emulator, Machine},
+    winapi::{
+        dsound::{Format, Output, LATENCY_MS, POLL_MS},
+        kernel32,
+        stack_args::ArrayWithSizeMut,
+        types::{HANDLE, HWND},
+        user32,
+    },
+};
+use memory::Extensions;
+use std::io::Write;
+
+const TRACE_CONTEXT: &'static str = "winmm/mci";
+
+/*
+## Devices
+
+Every device is modeled as playing media made of tracks: one track for a waveaudio
+file, and the disc's tracks for cdaudio.  There's no CD drive, so the disc is made
+of audio files the user supplies as trackNN.wav in State::cd_audio; tracks without a
+file are data tracks.  Sequencer (MIDI) devices accept commands, but without a
+synthesizer their media is empty, so they finish playing immediately.
+
+Positions are kept in milliseconds from the start of the media and converted to and
+from the device's time format at the API.  Playback streams to the host like waveOut,
+with the position following what the host has played.
+
+Snapshots record each device's media, mode and position; on restore the media is
+loaded again and a playing device carries on from where it was, so a game's CD
+soundtrack survives a save.
+*/
+
+/// Rate of the stream played devices output, whatever their media's rate.
+const OUTPUT_RATE: u32 = 44100;
+
+/// On a real disc, the first track starts after a two second lead-in.
+const LEAD_IN_MS: u32 = 2000;
+/// CD frames (sectors) per second, for the MSF and TMSF time formats.
+const CD_FRAMES: u32 = 75;
+
+pub const MM_MCINOTIFY: u32 = 0x3B9;
+const MCI_NOTIFY_SUCCESSFUL: u32 = 0x1;
+const MCI_NOTIFY_SUPERSEDED: u32 = 0x2;
+const MCI_NOTIFY_ABORTED: u32 = 0x4;
+
+const MCI_OPEN: u32 = 0x803;
+const MCI_CLOSE: u32 = 0x804;
+const MCI_PLAY: u32 = 0x806;
+const MCI_SEEK: u32 = 0x807;
+const MCI_STOP: u32 = 0x808;
+const MCI_PAUSE: u32 = 0x809;
+const MCI_SET: u32 = 0x80D;
+const MCI_STATUS: u32 = 0x814;
+const MCI_RESUME: u32 = 0x855;
+
+const MCI_NOTIFY: u32 = 0x1;
+const MCI_WAIT: u32 = 0x2;
+const MCI_FROM: u32 = 0x4;
+const MCI_TO: u32 = 0x8;
+const MCI_TRACK: u32 = 0x10;
+
+const MCI_OPEN_ELEMENT: u32 = 0x200;
+const MCI_OPEN_ALIAS: u32 = 0x400;
+const MCI_OPEN_TYPE_ID: u32 = 0x1000;
+const MCI_OPEN_TYPE: u32 = 0x2000;
+
+const MCI_SEEK_TO_START: u32 = 0x100;
+const MCI_SEEK_TO_END: u32 = 0x200;
+
+const MCI_SET_DOOR_OPEN: u32 = 0x100;
+const MCI_SET_DOOR_CLOSED: u32 = 0x200;
+const MCI_SET_TIME_FORMAT: u32 = 0x400;
+
+const MCI_STATUS_ITEM: u32 = 0x100;
+const MCI_STATUS_LENGTH: u32 = 0x1;
+const MCI_STATUS_POSITION: u32 = 0x2;
+const MCI_STATUS_NUMBER_OF_TRACKS: u32 = 0x3;
+const MCI_STATUS_MODE: u32 = 0x4;
+const MCI_STATUS_MEDIA_PRESENT: u32 = 0x5;
+const MCI_STATUS_TIME_FORMAT: u32 = 0x6;
+const MCI_STATUS_READY: u32 = 0x7;
+const MCI_STATUS_CURRENT_TRACK: u32 = 0x8;
+const MCI_CDA_STATUS_TYPE_TRACK: u32 = 0x4001;
+const MCI_CDA_TRACK_AUDIO: u32 = 1088;
+const MCI_CDA_TRACK_OTHER: u32 = 1089;
+
+const MCI_MODE_STOP: u32 = 525;
+const MCI_MODE_PLAY: u32 = 526;
+const MCI_MODE_PAUSE: u32 = 529;
+
+const MCI_FORMAT_MILLISECONDS: u32 = 0;
+const MCI_FORMAT_MSF: u32 = 2;
+const MCI_FORMAT_BYTES: u32 = 8;
+const MCI_FORMAT_SAMPLES: u32 = 9;
+const MCI_FORMAT_TMSF: u32 = 10;
+
+const MCI_DEVTYPE_CD_AUDIO: u32 = 516;
+const MCI_DEVTYPE_WAVEFORM_AUDIO: u32 = 522;
+const MCI_DEVTYPE_SEQUENCER: u32 = 523;
+
+/// Device ID addressing every open device, for closing them all.
+const MCI_ALL_DEVICE_ID: u32 = 0xFFFF;
+
+pub const MCIERR_INVALID_DEVICE_ID: u32 = 257;
+pub const MCIERR_UNRECOGNIZED_KEYWORD: u32 = 259;
+pub const MCIERR_UNRECOGNIZED_COMMAND: u32 = 261;
+pub const MCIERR_INVALID_DEVICE_NAME: u32 = 263;
+pub const MCIERR_BAD_INTEGER: u32 = 270;
+pub const MCIERR_MISSING_PARAMETER: u32 = 273;
+pub const MCIERR_UNSUPPORTED_FUNCTION: u32 = 274;
+pub const MCIERR_FILE_NOT_FOUND: u32 = 275;
+pub const MCIERR_OUTOFRANGE: u32 = 282;
+pub const MCIERR_BAD_TIME_FORMAT: u32 = 293;
+pub const MCIERR_DUPLICATE_ALIAS: u32 = 289;
+pub const MCIERR_MISSING_DEVICE_NAME: u32 = 304;
+
+pub struct MCIDEVICEIDT;
+pub type MCIDEVICEID = HANDLE<MCIDEVICEIDT>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+enum Kind {
+    WaveAudio,
+    Sequencer,
+    CdAudio,
+}
+
+impl Kind {
+    fn from_name(name: &str) -> Option<Kind> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "waveaudio" => Kind::WaveAudio,
+            "sequencer" => Kind::Sequencer,
+            "cdaudio" => Kind::CdAudio,
+            _ => return None,
+        })
+    }
+
+    fn from_type_id(id: u32) -> Option<Kind> {
+        Some(match id {
+            MCI_DEVTYPE_WAVEFORM_AUDIO => Kind::WaveAudio,
+            MCI_DEVTYPE_SEQUENCER => Kind::Sequencer,
+            MCI_DEVTYPE_CD_AUDIO => Kind::CdAudio,
+            _ => return None,
+        })
+    }
+
+    /// The device type that plays a file, judging by its extension.
+    fn from_element(element: &str) -> Option<Kind> {
+        let ext = element.rsplit_once('.')?.1.to_ascii_lowercase();
+        Some(match ext.as_str() {
+            "wav" => Kind::WaveAudio,
+            "mid" | "midi" | "rmi" => Kind::Sequencer,
+            _ => return None,
+        })
+    }
+}
+
+/// PCM audio decoded from a .wav file.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(super) struct Sound {
+    pub(super) format: Format,
+    pub(super) data: Vec<u8>,
+}
+
+impl Sound {
+    pub(super) fn parse_wav(buf: &[u8]) -> Option<Sound> {
+        if buf.len() < 12 || &buf[0..4] != b"RIFF" || &buf[8..12] != b"WAVE" {
+            return None;
+        }
+        let u16_at = |b: &[u8], ofs: usize| u16::from_le_bytes([b[ofs], b[ofs + 1]]);
+        let u32_at = |b: &[u8], ofs: usize| u32::from_le_bytes(b[ofs..ofs + 4].try_into().unwrap());
+        let mut format = None;
+        let mut ofs = 12;
+        while ofs + 8 <= buf.len() {
+            let len = u32_at(buf, ofs + 4) as usize;
+            let body = &buf[ofs + 8..std::cmp::min(ofs + 8 + len, buf.len())];
+            match &buf[ofs..ofs + 4] {
+                b"fmt " if body.len() >= 16 => {
+                    format = Format::from_wave(&WAVEFORMATEX {
+                        wFormatTag: u16_at(body, 0),
+                        nChannels: u16_at(body, 2),
+                        nSamplesPerSec: u32_at(body, 4),
+                        nAvgBytesPerSec: u32_at(body, 8),
+                        nBlockAlign: u16_at(body, 12),
+                        wBitsPerSample: u16_at(body, 14),
+                        cbSize: 0,
+                    });
+                }
+                b"data" => {
+                    return Some(Sound {
+                        format: format?,
+                        data: body.to_vec(),
+                    })
+                }
+                _ => {}
+            }
+            // Chunks are padded to even sizes.
+            ofs += 8 + len + (len & 1);
+        }
+        None
+    }
+
+    pub(super) fn frames(&self) -> u32 {
+        self.data.len() as u32 / self.format.block_align()
+    }
+
+    fn length_ms(&self) -> u32 {
+        (self.frames() as u64 * 1000 / self.format.rate as u64) as u32
+    }
+}
+
+/// A track of the media, with no sound for a CD's data tracks.
+struct Track {
+    start: u32,
+    length: u32,
+    sound: Option<Sound>,
+}
+
+struct Playing {
+    from: u32,
+    to: u32,
+    /// Output frames fed to the host since playing from `from`.
+    fed: u64,
+}
+
+pub struct Device {
+    kind: Kind,
+    /// Name command strings refer to the device by: its alias, element, or type.
+    name: String,
+    /// The file opened as the device's media, if any.
+    element: Option<String>,
+    time_format: u32,
+    tracks: Vec<Track>,
+    /// Position in ms from the start of the media, as of the last update.
+    position: u32,
+    playing: Option<Playing>,
+    paused: bool,
+    output: Option<Output>,
+    /// Window to notify when the current play command ends.
+    notify: Option<HWND>,
+}
+
+impl Device {
+    fn new(kind: Kind, name: String, element: Option<String>, sounds: Vec<Option<Sound>>) -> Self {
+        let mut start = 0;
+        let tracks = sounds
+            .into_iter()
+            .map(|sound| {
+                let length = sound.as_ref().map(|s| s.length_ms()).unwrap_or(0);
+                let track = Track {
+                    start,
+                    length,
+                    sound,
+                };
+                start += length;
+                track
+            })
+            .collect();
+        Device {
+            kind,
+            name,
+            element,
+            time_format: if kind == Kind::CdAudio {
+                MCI_FORMAT_MSF
+            } else {
+                MCI_FORMAT_MILLISECONDS
+            },
+            tracks,
+            position: 0,
+            playing: None,
+            paused: false,
+            output: None,
+            notify: None,
+        }
+    }
+
+    /// The position playback has reached by `now`, without feeding the host.
+    fn position_at(&self, now: u32) -> u32 {
+        let (Some(playing), Some(output)) = (&self.playing, &self.output) else {
+            return self.position;
+        };
+        let queued = output.queued(now) as u64;
+        let played = playing.fed - std::cmp::min(queued, playing.fed);
+        let ms = playing.from + (played * 1000 / OUTPUT_RATE as u64) as u32;
+        std::cmp::min(ms, playing.to)
+    }
+
+    fn length(&self) -> u32 {
+        self.tracks.last().map(|t| t.start + t.length).unwrap_or(0)
+    }
+
+    /// 1-based index of the track containing a position.
+    fn track_at(&self, ms: u32) -> u32 {
+        let i = self
+            .tracks
+            .iter()
+            .rposition(|t| t.start <= ms && t.length > 0)
+            .unwrap_or(0);
+        i as u32 + 1
+    }
+
+    fn track(&self, track: u32) -> Result<&Track, u32> {
+        track
+            .checked_sub(1)
+            .and_then(|i| self.tracks.get(i as usize))
+            .ok_or(MCIERR_OUTOFRANGE)
+    }
+
+    /// The format of the first track, by which sample and byte times are measured.
+    fn format(&self) -> Option<&Format> {
+        self.tracks.first()?.sound.as_ref().map(|s| &s.format)
+    }
+
+    /// Convert a time in the device's time format to ms.
+    fn to_ms(&self, value: u32) -> Result<u32, u32> {
+        let [b0, b1, b2, b3] = value.to_le_bytes().map(|b| b as u32);
+        let ms = match self.time_format {
+            MCI_FORMAT_MILLISECONDS => value,
+            MCI_FORMAT_MSF => {
+                (b0 * 60000 + b1 * 1000 + b2 * 1000 / CD_FRAMES).saturating_sub(LEAD_IN_MS)
+            }
+            MCI_FORMAT_TMSF => {
+                self.track(b0)?.start + b1 * 60000 + b2 * 1000 + b3 * 1000 / CD_FRAMES
+            }
+            MCI_FORMAT_SAMPLES | MCI_FORMAT_BYTES => {
+                let Some(format) = self.format() else {
+                    return Ok(0);
+                };
+                let frames = if self.time_format == MCI_FORMAT_BYTES {
+                    value / format.block_align()
+                } else {
+                    value
+                };
+                (frames as u64 * 1000 / format.rate as u64) as u32
+            }
+            _ => return Err(MCIERR_BAD_TIME_FORMAT),
+        };
+        if ms > self.length() {
+            return Err(MCIERR_OUTOFRANGE);
+        }
+        Ok(ms)
+    }
+
+    /// Convert ms to the device's time format.  Lengths have no track and no lead-in,
+    /// so in TMSF they are given as MSF.
+    fn from_ms(&self, ms: u32, length: bool) -> Time {
+        let msf = |ms: u32| {
+            let frames = ms as u64 * CD_FRAMES as u64 / 1000;
+            let per_minute = 60 * CD_FRAMES as u64;
+            let (m, s, f) = (frames / per_minute, frames / 75 % 60, frames % 75);
+            (m as u8, s as u8, f as u8)
+        };
+        match self.time_format {
+            MCI_FORMAT_MSF | MCI_FORMAT_TMSF if length => {
+                let (m, s, f) = msf(ms);
+                Time::Msf(m, s, f)
+            }
+            MCI_FORMAT_MSF => {
+                let (m, s, f) = msf(ms + LEAD_IN_MS);
+                Time::Msf(m, s, f)
+            }
+            MCI_FORMAT_TMSF => {
+                let track = self.track_at(ms);
+                let (m, s, f) = msf(ms - self.tracks[track as usize - 1].start);
+                Time::Tmsf(track as u8, m, s, f)
+            }
+            MCI_FORMAT_SAMPLES | MCI_FORMAT_BYTES => {
+                let Some(format) = self.format() else {
+                    return Time::Number(0);
+                };
+                let mut n = (ms as u64 * format.rate as u64 / 1000) as u32;
+                if self.time_format == MCI_FORMAT_BYTES {
+                    n *= format.block_align();
+                }
+                Time::Number(n)
+            }
+            _ => Time::Number(ms),
+        }
+    }
+
+    /// Output stereo sample of the media at a position in ms.
+    fn sample(&self, ms: f64) -> [i16; 2] {
+        let Some(track) = self
+            .tracks
+            .iter()
+            .rfind(|t| t.start as f64 <= ms && t.length > 0)
+        else {
+            return [0, 0];
+        };
+        let Some(sound) = &track.sound else {
+            return [0, 0];
+        };
+        let frame = ((ms - track.start as f64) * sound.format.rate as f64 / 1000.0) as u32;
+        if frame >= sound.frames() {
+            return [0, 0];
+        }
+        sound.format.sample(&sound.data, frame).map(|s| s as i16)
+    }
+
+    /// Update the position from the host's playback, and feed the host more output.
+    /// Returns true when playing has reached its end.
+    fn update(&mut self, now: u32) -> bool {
+        let (Some(playing), Some(output)) = (&mut self.playing, &mut self.output) else {
+            return false;
+        };
+        let queued = output.queued(now) as u64;
+        let played = playing.fed - std::cmp::min(queued, playing.fed);
+        let total = (playing.to - playing.from) as u64 * OUTPUT_RATE as u64 / 1000;
+        if played >= total {
+            self.position = playing.to;
+            self.playing = None;
+            return true;
+        }
+        self.position = playing.from + (played * 1000 / OUTPUT_RATE as u64) as u32;
+        if self.paused {
+            return false;
+        }
+
+        let target = (OUTPUT_RATE * LATENCY_MS / 1000) as u64;
+        let count = std::cmp::min(
+            target.saturating_sub(queued),
+            total.saturating_sub(playing.fed),
+        );
+        if count > 0 {
+            let from = playing.from as f64;
+            let fed = playing.fed;
+            let samples = (fed..fed + count)
+                .flat_map(|i| self.sample(from + i as f64 * 1000.0 / OUTPUT_RATE as f64))
+                .collect::<Vec<_>>();
+            let (Some(playing), Some(output)) = (&mut self.playing, &mut self.output) else {
+                unreachable!()
+            };
+            output.write(&samples, now);
+            playing.fed += count;
+        }
+        false
+    }
+}
+
+/// A time as reported in a device's time format.
+enum Time {
+    Number(u32),
+    Msf(u8, u8, u8),
+    Tmsf(u8, u8, u8, u8),
+}
+
+impl Time {
+    fn to_u32(&self) -> u32 {
+        match *self {
+            Time::Number(n) => n,
+            Time::Msf(m, s, f) => u32::from_le_bytes([m, s, f, 0]),
+            Time::Tmsf(t, m, s, f) => u32::from_le_bytes([t, m, s, f]),
+        }
+    }
+}
+
+impl std::fmt::Display for Time {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Time::Number(n) => write!(f, "{n}"),
+            Time::Msf(m, s, fr) => write!(f, "{m:02}:{s:02}:{fr:02}"),
+            Time::Tmsf(t, m, s, fr) => write!(f, "{t:02}:{m:02}:{s:02}:{fr:02}"),
+        }
+    }
+}
+
+/// Result of a status query, returned as a number by mciSendCommand and as text by
+/// mciSendString.
+enum Status {
+    Number(u32),
+    Time(Time),
+    Bool(bool),
+    Mode(u32),
+    TimeFormat(u32),
+    TrackType(u32),
+}
+
+impl Status {
+    fn to_u32(&self) -> u32 {
+        match self {
+            Status::Number(n) | Status::Mode(n) | Status::TimeFormat(n) | Status::TrackType(n) => {
+                *n
+            }
+            Status::Time(t) => t.to_u32(),
+            Status::Bool(b) => *b as u32,
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            Status::Number(n) => n.to_string(),
+            Status::Time(t) => t.to_string(),
+            Status::Bool(b) => b.to_string(),
+            Status::Mode(m) => match *m {
+                MCI_MODE_PLAY => "playing",
+                MCI_MODE_PAUSE => "paused",
+                _ => "stopped",
+            }
+            .into(),
+            Status::TimeFormat(f) => time_format_name(*f).into(),
+            Status::TrackType(t) => if *t == MCI_CDA_TRACK_AUDIO {
+                "audio"
+            } else {
+                "other"
+            }
+            .into(),
+        }
+    }
+}
+
+fn time_format_name(format: u32) -> &'static str {
+    match format {
+        MCI_FORMAT_MSF => "msf",
+        MCI_FORMAT_TMSF => "tmsf",
+        MCI_FORMAT_BYTES => "bytes",
+        MCI_FORMAT_SAMPLES => "samples",
+        _ => "milliseconds",
+    }
+}
+
+fn parse_time_format(name: &str) -> Option<u32> {
+    Some(match name {
+        "ms" | "milliseconds" => MCI_FORMAT_MILLISECONDS,
+        "msf" => MCI_FORMAT_MSF,
+        "tmsf" => MCI_FORMAT_TMSF,
+        "bytes" => MCI_FORMAT_BYTES,
+        "samples" => MCI_FORMAT_SAMPLES,
+        _ => return None,
+    })
+}
+
+/// Load the media a device plays.
+fn load_media(
+    machine: &Machine,
+    kind: Kind,
+    element: Option<&str>,
+) -> Result<Vec<Option<Sound>>, u32> {
+    match kind {
+        Kind::WaveAudio => {
+            let Some(element) = element else {
+                return Ok(Vec::new());
+            };
+            let buf = kernel32::read_file(machine, element);
+            if buf.is_empty() {
+                return Err(MCIERR_FILE_NOT_FOUND);
+            }
+            match Sound::parse_wav(&buf) {
+                Some(sound) => Ok(vec![Some(sound)]),
+                None => {
+                    log::warn!("mci: unsupported wave file {element:?}");
+                    Err(MCIERR_UNSUPPORTED_FUNCTION)
+                }
+            }
+        }
+        Kind::Sequencer => {
+            log::warn!("mci: no MIDI synthesizer, {element:?} will play silently");
+            Ok(Vec::new())
+        }
+        Kind::CdAudio => {
+            let Some(dir) = &machine.state.winmm.cd_audio else {
+                log::warn!("mci: no CD audio tracks configured");
+                return Ok(vec![None]);
+            };
+            let mut tracks: Vec<Option<Sound>> = (1..=99)
+                .map(|n| {
+                    let path = format!("{dir}/track{n:02}.wav");
+                    Sound::parse_wav(&kernel32::read_file(machine, &path))
+                })
+                .collect();
+            while tracks.len() > 1 && tracks.last().unwrap().is_none() {
+                tracks.pop();
+            }
+            Ok(tracks)
+        }
+    }
+}
+
+fn open(
+    machine: &mut Machine,
+    kind: Option<Kind>,
+    element: Option<&str>,
+    alias: Option<&str>,
+) -> Result<MCIDEVICEID, u32> {
+    let Some(kind) = kind.or_else(|| element.and_then(Kind::from_element)) else {
+        return Err(MCIERR_INVALID_DEVICE_NAME);
+    };
+    let name = match (alias, element) {
+        (Some(alias), _) => alias.to_string(),
+        (None, Some(element)) => element.to_string(),
+        (None, None) => format!("{kind:?}").to_ascii_lowercase(),
+    };
+    if alias.is_some() && find(machine, &name).is_some() {
+        return Err(MCIERR_DUPLICATE_ALIAS);
+    }
+    let media = load_media(machine, kind, element)?;
+    let device = Device::new(kind, name, element.map(str::to_string), media);
+    Ok(machine.state.winmm.mci.add(device))
+}
+
+/// An open device as recorded in a snapshot.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct DeviceRecord {
+    id: MCIDEVICEID,
+    kind: Kind,
+    name: String,
+    element: Option<String>,
+    time_format: u32,
+    /// Position in ms from the start of the media.
+    position: u32,
+    /// Where playing was to stop, while playing.
+    playing_to: Option<u32>,
+    paused: bool,
+    notify: Option<HWND>,
+}
+
+/// Record the open devices, for snapshots.
+pub fn save_devices(machine: &Machine) -> Vec<DeviceRecord> {
+    let now = machine.host.time();
+    machine
+        .state
+        .winmm
+        .mci
+        .entries()
+        .map(|(id, dev)| DeviceRecord {
+            id,
+            kind: dev.kind,
+            name: dev.name.clone(),
+            element: dev.element.clone(),
+            time_format: dev.time_format,
+            position: dev.position_at(now),
+            playing_to: dev.playing.as_ref().map(|playing| playing.to),
+            paused: dev.paused,
+            notify: dev.notify,
+        })
+        .collect()
+}
+
+/// Replace the open devices with those recorded in a snapshot, loading their
+/// media again.  Playing devices continue from their recorded position on a fresh
+/// host stream.  A device whose media is gone stays closed.
+pub fn restore_devices(machine: &mut Machine, records: Vec<DeviceRecord>) {
+    machine.state.winmm.mci = Default::default();
+    for record in records {
+        let media = match load_media(machine, record.kind, record.element.as_deref()) {
+            Ok(media) => media,
+            Err(err) => {
+                log::warn!("mci: can't reopen {:?}: error {err}", record.name);
+                continue;
+            }
+        };
+        let mut device = Device::new(record.kind, record.name, record.element, media);
+        device.time_format = record.time_format;
+        device.position = std::cmp::min(record.position, device.length());
+        if let Some(to) = record.playing_to {
+            device.output = Some(Output::new(machine, OUTPUT_RATE));
+            device.playing = Some(Playing {
+                from: device.position,
+                to: std::cmp::min(to, device.length()).max(device.position),
+                fed: 0,
+            });
+            device.paused = record.paused;
+            device.notify = record.notify;
+        }
+        machine.state.winmm.mci.set(record.id, device);
+    }
+}
+
+/// Find an open device by the name command strings use.
+fn find(machine: &mut Machine, name: &str) -> Option<MCIDEVICEID> {
+    machine
+        .state
+        .winmm
+        .mci
+        .iter_mut()
+        .find(|(_, dev)| dev.name.eq_ignore_ascii_case(name))
+        .map(|(id, _)| id)
+}
+
+fn device(machine: &mut Machine, id: MCIDEVICEID) -> Result<&mut Device, u32> {
+    machine
+        .state
+        .winmm
+        .mci
+        .get_mut(id)
+        .ok_or(MCIERR_INVALID_DEVICE_ID)
+}
+
+fn notify(machine: &mut Machine, id: MCIDEVICEID, hwnd: HWND, flag: u32) {
+    user32::post_message(machine, hwnd, MM_MCINOTIFY, flag, id.to_raw());
+}
+
+/// Stop playing, reporting any pending notification with `flag`.
+fn stop(machine: &mut Machine, id: MCIDEVICEID, flag: u32) -> Result<(), u32> {
+    let now = machine.host.time();
+    let dev = device(machine, id)?;
+    dev.update(now);
+    dev.playing = None;
+    dev.paused = false;
+    if let Some(hwnd) = dev.notify.take() {
+        notify(machine, id, hwnd, flag);
+    }
+    Ok(())
+}
+
+fn close(machine: &mut Machine, id: MCIDEVICEID) -> Result<(), u32> {
+    stop(machine, id, MCI_NOTIFY_ABORTED)?;
+    machine.state.winmm.mci.remove(id);
+    Ok(())
+}
+
+/// Start playing from/to positions in the device's time format.
+fn play(
+    machine: &mut Machine,
+    id: MCIDEVICEID,
+    from: Option<u32>,
+    to: Option<u32>,
+    notify_hwnd: Option<HWND>,
+) -> Result<(), u32> {
+    let dev = device(machine, id)?;
+    let from = match from {
+        Some(from) => dev.to_ms(from)?,
+        None => dev.position,
+    };
+    let to = match to {
+        Some(to) => dev.to_ms(to)?,
+        None => dev.length(),
+    };
+    if from > to {
+        return Err(MCIERR_OUTOFRANGE);
+    }
+    stop(machine, id, MCI_NOTIFY_SUPERSEDED)?;
+    if machine.state.winmm.mci.get(id).unwrap().output.is_none() {
+        let output = Output::new(machine, OUTPUT_RATE);
+        device(machine, id)?.output = Some(output);
+    }
+    let dev = device(machine, id)?;
+    dev.position = from;
+    dev.playing = Some(Playing { from, to, fed: 0 });
+    dev.notify = notify_hwnd;
+    update(machine);
+    Ok(())
+}
+
+fn seek(machine: &mut Machine, id: MCIDEVICEID, to: Option<u32>, end: bool) -> Result<(), u32> {
+    stop(machine, id, MCI_NOTIFY_ABORTED)?;
+    let dev = device(machine, id)?;
+    dev.position = match to {
+        Some(to) => dev.to_ms(to)?,
+        None if end => dev.length(),
+        None => 0,
+    };
+    Ok(())
+}
+
+fn pause(machine: &mut Machine, id: MCIDEVICEID, paused: bool) -> Result<(), u32> {
+    let now = machine.host.time();
+    let dev = device(machine, id)?;
+    dev.update(now);
+    if let Some(playing) = &mut dev.playing {
+        if paused {
+            // Resuming continues from the current position.
+            *playing = Playing {
+                from: dev.position,
+                to: playing.to,
+                fed: 0,
+            };
+        }
+        dev.paused = paused;
+    }
+    Ok(())
+}
+
+fn status(
+    machine: &mut Machine,
+    id: MCIDEVICEID,
+    item: u32,
+    track: Option<u32>,
+) -> Result<Status, u32> {
+    let now = machine.host.time();
+    let dev = device(machine, id)?;
+    dev.update(now);
+    Ok(match item {
+        MCI_STATUS_LENGTH => Status::Time(dev.from_ms(
+            match track {
+                Some(track) => dev.track(track)?.length,
+                None => dev.length(),
+            },
+            true,
+        )),
+        MCI_STATUS_POSITION => Status::Time(dev.from_ms(
+            match track {
+                Some(track) => dev.track(track)?.start,
+                None => dev.position,
+            },
+            false,
+        )),
+        MCI_STATUS_NUMBER_OF_TRACKS => Status::Number(dev.tracks.len() as u32),
+        MCI_STATUS_CURRENT_TRACK => Status::Number(dev.track_at(dev.position)),
+        MCI_STATUS_MODE => Status::Mode(match (&dev.playing, dev.paused) {
+            (Some(_), false) => MCI_MODE_PLAY,
+            (Some(_), true) => MCI_MODE_PAUSE,
+            (None, _) => MCI_MODE_STOP,
+        }),
+        MCI_STATUS_MEDIA_PRESENT | MCI_STATUS_READY => Status::Bool(true),
+        MCI_STATUS_TIME_FORMAT => Status::TimeFormat(dev.time_format),
+        MCI_CDA_STATUS_TYPE_TRACK => {
+            let track = dev.track(track.ok_or(MCIERR_MISSING_PARAMETER)?)?;
+            Status::TrackType(if track.sound.is_some() {
+                MCI_CDA_TRACK_AUDIO
+            } else {
+                MCI_CDA_TRACK_OTHER
+            })
+        }
+        _ => {
+            log::warn!("mci: unsupported status item {item:#x}");
+            return Err(MCIERR_UNSUPPORTED_FUNCTION);
+        }
+    })
+}
+
+fn set_time_format(machine: &mut Machine, id: MCIDEVICEID, format: u32) -> Result<(), u32> {
+    let dev = device(machine, id)?;
+    let ok = match format {
+        MCI_FORMAT_MILLISECONDS => true,
+        MCI_FORMAT_MSF | MCI_FORMAT_TMSF => dev.kind == Kind::CdAudio,
+        MCI_FORMAT_BYTES | MCI_FORMAT_SAMPLES => dev.kind == Kind::WaveAudio,
+        _ => false,
+    };
+    if !ok {
+        return Err(MCIERR_BAD_TIME_FORMAT);
+    }
+    dev.time_format = format;
+    Ok(())
+}
+
+/// Advance all playing devices, notifying those that finished.
+/// Returns the host time by which it should be called again, if anything is playing.
+pub(super) fn update(machine: &mut Machine) -> Option<u32> {
+    let now = machine.host.time();
+    let mut wait = None;
+    let mut finished = Vec::new();
+    for (id, dev) in machine.state.winmm.mci.iter_mut() {
+        if dev.update(now) {
+            if let Some(hwnd) = dev.notify.take() {
+                finished.push((id, hwnd));
+            }
+        } else if dev.playing.is_some() {
+            wait = Some(now + POLL_MS);
+        }
+    }
+    for (id, hwnd) in finished {
+        notify(machine, id, hwnd, MCI_NOTIFY_SUCCESSFUL);
+    }
+    wait
+}
+
+/// Implement MCI_WAIT: block until the device stops playing.
+async fn wait_done(machine: &mut Machine, id: MCIDEVICEID) {
+    loop {
+        let until = crate::winapi::poll_devices(machine);
+        match machine.state.winmm.mci.get(id) {
+            Some(dev) if dev.playing.is_some() && !dev.paused => {}
+            _ => return,
+        }
+        match machine.emu.block(until) {
+            Some(block) => block.await,
+            None => {
+                machine.host.block(until);
+            }
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct MCI_OPEN_PARMSA {
+    pub dwCallback: u32,
+    pub wDeviceID: MCIDEVICEID,
+    pub lpstrDeviceType: u32,
+    pub lpstrElementName: u32,
+    pub lpstrAlias: u32,
+}
+unsafe impl memory::Pod for MCI_OPEN_PARMSA {}
+
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct MCI_PLAY_PARMS {
+    pub dwCallback: u32,
+    pub dwFrom: u32,
+    pub dwTo: u32,
+}
+unsafe impl memory::Pod for MCI_PLAY_PARMS {}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct MCI_STATUS_PARMS {
+    pub dwCallback: u32,
+    pub dwReturn: u32,
+    pub dwItem: u32,
+    pub dwTrack: u32,
+}
+unsafe impl memory::Pod for MCI_STATUS_PARMS {}
+
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct MCI_SET_PARMS {
+    pub dwCallback: u32,
+    pub dwTimeFormat: u32,
+    pub dwAudio: u32,
+}
+unsafe impl memory::Pod for MCI_SET_PARMS {}
+
+/// The command message form of a command, as mciSendCommand takes it.
+fn send_command(
+    machine: &mut Machine,
+    id: MCIDEVICEID,
+    msg: u32,
+    flags: u32,
+    parms: u32,
+) -> Result<MCIDEVICEID, u32> {
+    let mem = machine.emu.memory.mem();
+    let string = |addr: u32| match addr {
+        0 => None,
+        addr => Some(String::from_utf8_lossy(mem.slicez(addr)).into_owned()),
+    };
+    match msg {
+        MCI_OPEN => {
+            let p = mem.view_mut::<MCI_OPEN_PARMSA>(parms);
+            let kind = if flags & MCI_OPEN_TYPE_ID != 0 {
+                Some(
+                    Kind::from_type_id(p.lpstrDeviceType & 0xFFFF)
+                        .ok_or(MCIERR_INVALID_DEVICE_NAME)?,
+                )
+            } else if flags & MCI_OPEN_TYPE != 0 {
+                let name = string(p.lpstrDeviceType).ok_or(MCIERR_MISSING_DEVICE_NAME)?;
+                Some(Kind::from_name(&name).ok_or(MCIERR_INVALID_DEVICE_NAME)?)
+            } else {
+                None
+            };
+            let element = (flags & MCI_OPEN_ELEMENT != 0)
+                .then(|| string(p.lpstrElementName))
+                .flatten();
+            let alias = (flags & MCI_OPEN_ALIAS != 0)
+                .then(|| string(p.lpstrAlias))
+                .flatten();
+            let id = open(machine, kind, element.as_deref(), alias.as_deref())?;
+            machine
+                .emu
+                .memory
+                .mem()
+                .view_mut::<MCI_OPEN_PARMSA>(parms)
+                .wDeviceID = id;
+            Ok(id)
+        }
+        MCI_CLOSE if id.to_raw() == MCI_ALL_DEVICE_ID => {
+            let ids = machine
+                .state
+                .winmm
+                .mci
+                .iter_mut()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>();
+            for id in ids {
+                close(machine, id)?;
+            }
+            Ok(id)
+        }
+        MCI_CLOSE => close(machine, id).map(|_| id),
+        MCI_PLAY => {
+            let p = mem.get_pod::<MCI_PLAY_PARMS>(parms);
+            let notify_hwnd = (flags & MCI_NOTIFY != 0).then(|| HWND::from_raw(p.dwCallback));
+            let from = (flags & MCI_FROM != 0).then_some(p.dwFrom);
+            let to = (flags & MCI_TO != 0).then_some(p.dwTo);
+            play(machine, id, from, to, notify_hwnd).map(|_| id)
+        }
+        MCI_SEEK => {
+            let to = match flags & (MCI_SEEK_TO_START | MCI_SEEK_TO_END | MCI_TO) {
+                MCI_TO => Some(mem.get_pod::<u32>(parms + 4)),
+                _ => None,
+            };
+            seek(machine, id, to, flags & MCI_SEEK_TO_END != 0).map(|_| id)
+        }
+        MCI_STOP => stop(machine, id, MCI_NOTIFY_ABORTED).map(|_| id),
+        MCI_PAUSE => pause(machine, id, true).map(|_| id),
+        MCI_RESUME => pause(machine, id, false).map(|_| id),
+        MCI_SET => {
+            let p = mem.get_pod::<MCI_SET_PARMS>(parms);
+            if flags & MCI_SET_TIME_FORMAT != 0 {
+                set_time_format(machine, id, p.dwTimeFormat)?;
+            }
+            if flags & (MCI_SET_DOOR_OPEN | MCI_SET_DOOR_CLOSED) != 0 {
+                device(machine, id)?;
+            }
+            Ok(id)
+        }
+        MCI_STATUS => {
+            if flags & MCI_STATUS_ITEM == 0 {
+                return Err(MCIERR_MISSING_PARAMETER);
+            }
+            let p = mem.view_mut::<MCI_STATUS_PARMS>(parms);
+            let (item, track) = (p.dwItem, (flags & MCI_TRACK != 0).then_some(p.dwTrack));
+            let value = status(machine, id, item, track)?.to_u32();
+            machine
+                .emu
+                .memory
+                .mem()
+                .view_mut::<MCI_STATUS_PARMS>(parms)
+                .dwReturn = value;
+            Ok(id)
+        }
+        _ => {
+            log::warn!("mciSendCommand: unsupported command {msg:#x}");
+            Err(MCIERR_UNSUPPORTED_FUNCTION)
+        }
+    }
+}
+
+#[win32_derive::dllexport]
+pub async fn mciSendCommandA(
+    machine: &mut Machine,
+    IDDevice: MCIDEVICEID,
+    uMsg: u32,
+    fdwCommand: u32,
+    dwParam: u32,
+) -> u32 {
+    match send_command(machine, IDDevice, uMsg, fdwCommand, dwParam) {
+        Ok(id) => {
+            if fdwCommand & MCI_NOTIFY != 0 && uMsg != MCI_PLAY && dwParam != 0 {
+                let hwnd = HWND::from_raw(machine.mem().get_pod::<u32>(dwParam));
+                notify(machine, id, hwnd, MCI_NOTIFY_SUCCESSFUL);
+            }
+            if fdwCommand & MCI_WAIT != 0 && uMsg == MCI_PLAY {
+                wait_done(machine, id).await;
+            }
+            0
+        }
+        Err(err) => err,
+    }
+}
+
+/// Split a command string into words, keeping "quoted strings" together.
+fn split_words(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut chars = command.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            words.push(chars.by_ref().take_while(|&c| c != '"').collect());
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            words.push(word);
+        }
+    }
+    words
+}
+
+/// The command string form of a command, as mciSendString takes it.
+/// Returns the device and the text result, if any.
+fn send_string(
+    machine: &mut Machine,
+    words: &[String],
+    notify_hwnd: Option<HWND>,
+) -> Result<(MCIDEVICEID, String), u32> {
+    let (Some(command), Some(name)) = (words.get(0), words.get(1)) else {
+        return Err(MCIERR_MISSING_DEVICE_NAME);
+    };
+    let command = command.to_ascii_lowercase();
+    let args = words[2..]
+        .iter()
+        .map(|w| w.to_ascii_lowercase())
+        .collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let number = |word: Option<&&str>| -> Result<u32, u32> {
+        let word = word.ok_or(MCIERR_MISSING_PARAMETER)?;
+        // Times like "2:00:00" are TMSF/MSF components, least significant first.
+        let mut value = 0u32;
+        for (i, part) in word.split(':').enumerate() {
+            let part = part.parse::<u32>().map_err(|_| MCIERR_BAD_INTEGER)?;
+            value |= part << (8 * i);
+        }
+        Ok(value)
+    };
+    let arg_after = |key: &str| args.iter().position(|&w| w == key).map(|i| args.get(i + 1));
+
+    if command == "open" {
+        let mut kind = Kind::from_name(name);
+        let element = if kind.is_none() {
+            Some(words[1].as_str())
+        } else {
+            None
+        };
+        let mut alias = None;
+        let mut i = 2;
+        while i < words.len() {
+            match args[i - 2] {
+                "type" => {
+                    let name = words.get(i + 1).ok_or(MCIERR_MISSING_PARAMETER)?;
+                    kind = Some(Kind::from_name(name).ok_or(MCIERR_INVALID_DEVICE_NAME)?);
+                    i += 1;
+                }
+                "alias" => {
+                    alias = Some(words.get(i + 1).ok_or(MCIERR_MISSING_PARAMETER)?.as_str());
+                    i += 1;
+                }
+                "shareable" | "wait" | "notify" => {}
+                word => {
+                    log::warn!("mciSendString: unknown open keyword {word:?}");
+                    return Err(MCIERR_UNRECOGNIZED_KEYWORD);
+                }
+            }
+            i += 1;
+        }
+        let id = open(machine, kind, element, alias)?;
+        return Ok((id, id.to_raw().to_string()));
+    }
+
+    if command == "close" && name.eq_ignore_ascii_case("all") {
+        let ids = machine
+            .state
+            .winmm
+            .mci
+            .iter_mut()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        for id in ids {
+            close(machine, id)?;
+        }
+        return Ok((MCIDEVICEID::from_raw(MCI_ALL_DEVICE_ID), String::new()));
+    }
+
+    let Some(id) = find(machine, name) else {
+        return Err(MCIERR_INVALID_DEVICE_NAME);
+    };
+    let result = match command.as_str() {
+        "close" => close(machine, id).map(|_| String::new()),
+        "play" => {
+            let from = arg_after("from").map(|w| number(w)).transpose()?;
+            let to = arg_after("to").map(|w| number(w)).transpose()?;
+            play(machine, id, from, to, notify_hwnd).map(|_| String::new())
+        }
+        "stop" => stop(machine, id, MCI_NOTIFY_ABORTED).map(|_| String::new()),
+        "pause" => pause(machine, id, true).map(|_| String::new()),
+        "resume" => pause(machine, id, false).map(|_| String::new()),
+        "seek" => {
+            let to = match args.get(1) {
+                Some(&"start") | Some(&"end") => None,
+                _ => Some(number(args.get(1))?),
+            };
+            if args.first() != Some(&"to") {
+                return Err(MCIERR_MISSING_PARAMETER);
+            }
+            seek(machine, id, to, args.get(1) == Some(&"end")).map(|_| String::new())
+        }
+        "set" => {
+            match args.as_slice() {
+                ["time", "format", format, ..] => {
+                    let format = parse_time_format(format).ok_or(MCIERR_BAD_TIME_FORMAT)?;
+                    set_time_format(machine, id, format)?;
+                }
+                ["door", "open" | "closed", ..] | ["audio", ..] | ["video", ..] => {}
+                _ => return Err(MCIERR_UNRECOGNIZED_KEYWORD),
+            }
+            Ok(String::new())
+        }
+        "status" => {
+            let track = arg_after("track").map(|w| number(w)).transpose()?;
+            let item = match args.as_slice() {
+                ["length", ..] => MCI_STATUS_LENGTH,
+                ["position", ..] => MCI_STATUS_POSITION,
+                ["number", "of", "tracks", ..] => MCI_STATUS_NUMBER_OF_TRACKS,
+                ["mode", ..] => MCI_STATUS_MODE,
+                ["media", "present", ..] => MCI_STATUS_MEDIA_PRESENT,
+                ["time", "format", ..] => MCI_STATUS_TIME_FORMAT,
+                ["ready", ..] => MCI_STATUS_READY,
+                ["current", "track", ..] => MCI_STATUS_CURRENT_TRACK,
+                ["type", "track", ..] => MCI_CDA_STATUS_TYPE_TRACK,
+                _ => return Err(MCIERR_UNRECOGNIZED_KEYWORD),
+            };
+            status(machine, id, item, track).map(|s| s.text())
+        }
+        _ => {
+            log::warn!("mciSendString: unsupported command {command:?}");
+            Err(MCIERR_UNRECOGNIZED_COMMAND)
+        }
+    }?;
+    Ok((id, result))
+}
+
+#[win32_derive::dllexport]
+pub async fn mciSendStringA(
+    machine: &mut Machine,
+    lpstrCommand: Option<&str>,
+    lpstrReturnString: ArrayWithSizeMut<'_, u8>,
+    hwndCallback: HWND,
+) -> u32 {
+    let words = split_words(lpstrCommand.unwrap_or_default());
+    let has = |flag: &str| words.iter().skip(2).any(|w| w.eq_ignore_ascii_case(flag));
+    let (notify_flag, wait) = (has("notify"), has("wait"));
+    let play = words
+        .first()
+        .is_some_and(|w| w.eq_ignore_ascii_case("play"));
+    let notify_hwnd = notify_flag.then_some(hwndCallback);
+    let (id, result) = match send_string(machine, &words, notify_hwnd) {
+        Ok(r) => r,
+        Err(err) => return err,
+    };
+    if let Some(buf) = lpstrReturnString {
+        if !buf.is_empty() {
+            let len = std::cmp::min(result.len(), buf.len() - 1);
+            let mut buf = &mut buf[..];
+            buf.write_all(&result.as_bytes()[..len]).unwrap();
+            buf.write_all(b"\0").unwrap();
+        }
+    }
+    if notify_flag && !play {
+        notify(machine, id, hwndCallback, MCI_NOTIFY_SUCCESSFUL);
+    }
+    if wait && play {
+        wait_done(machine, id).await;
+    }
+    0
+}
+
+#[win32_derive::dllexport]
+pub fn mciGetErrorStringA(
+    _machine: &mut Machine,
+    mcierr: u32,
+    pszText: ArrayWithSizeMut<u8>,
+) -> bool {
+    let text = match mcierr {
+        MCIERR_INVALID_DEVICE_ID => "Invalid MCI device ID.",
+        MCIERR_UNRECOGNIZED_KEYWORD => {
+            "The driver cannot recognize the specified command parameter."
+        }
+        MCIERR_UNRECOGNIZED_COMMAND => "The driver cannot recognize the specified command.",
+        MCIERR_INVALID_DEVICE_NAME => {
+            "The specified device is not open or is not recognized by MCI."
+        }
+        MCIERR_FILE_NOT_FOUND => "Cannot find the specified file.",
+        MCIERR_OUTOFRANGE => "The specified parameter is out of range for the specified command.",
+        _ => "An MCI error occurred.",
+    };
+    let Some(buf) = pszText else {
+        return false;
+    };
+    let mut buf = &mut buf[..];
+    let len = std::cmp::min(text.len(), buf.len().saturating_sub(1));
+    buf.write_all(&text.as_bytes()[..len]).is_ok() && buf.write_all(b"\0").is_ok()
+}
@@ -2,8 +2,86 @@
 #![allow(non_upper_case_globals)]
 #![allow(non_camel_case_types)]
 
+mod joystick;
+mod mci;
+mod playsound;
 mod time;
 mod wave;
 
+pub use joystick::*;
+pub use mci::*;
+pub use playsound::*;
 pub use time::*;
 pub use wave::*;
+
+use crate::{machine::Machine, winapi::handle::Handles};
+use std::collections::VecDeque;
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct State {
+    /// Open devices, which snapshots record separately; see save.
+    #[serde(skip)]
+    pub wave_outs: Handles<HWAVEOUT, WaveOut>,
+    /// MCI devices stream from host files, which snapshots record separately; see save.
+    #[serde(skip)]
+    pub mci: Handles<MCIDEVICEID, Device>,
+    /// Directory of trackNN.wav files that make up the disc cdaudio devices play.
+    /// Like joystick_map, this is host configuration and not part of snapshots.
+    #[serde(skip)]
+    pub cd_audio: Option<String>,
+    #[serde(skip)]
+    pub joystick_map: JoystickMap,
+    /// The sound PlaySound is playing, which snapshots record separately; see save.
+    #[serde(skip)]
+    sound: Option<playsound::Playing>,
+    #[serde(skip)]
+    timers: Timers,
+    /// Callback function calls, waiting to be run by run_callbacks.
+    callbacks: VecDeque<(u32, Vec<u32>)>,
+}
+
+/// Devices, sounds and timers as recorded in a snapshot.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Snapshot {
+    wave_outs: Vec<WaveOutRecord>,
+    mci: Vec<DeviceRecord>,
+    sound: Option<PlayingRecord>,
+    timers: Vec<TimerRecord>,
+}
+
+pub fn save(machine: &Machine) -> Snapshot {
+    Snapshot {
+        wave_outs: save_wave_outs(machine),
+        mci: save_devices(machine),
+        sound: save_sound(machine),
+        timers: save_timers(machine),
+    }
+}
+
+/// Restore devices, sounds and timers from a snapshot.
+pub fn restore(machine: &mut Machine, snapshot: Snapshot) {
+    restore_timers(machine, snapshot.timers);
+    restore_wave_outs(machine, snapshot.wave_outs);
+    restore_devices(machine, snapshot.mci);
+    restore_sound(machine, snapshot.sound);
+}
+
+/// Advance devices, returning the host time by which they want to be called again.
+pub fn update(machine: &mut Machine) -> Option<u32> {
+    [
+        wave::update(machine),
+        mci::update(machine),
+        playsound::update(machine),
+        time::update(machine),
+    ]
+    .into_iter()
+    .flatten()
+    .min()
+}
+
+/// Run the callback functions queued by devices.
+pub async fn run_callbacks(machine: &mut Machine) {
+    while let Some((func, args)) = machine.state.winmm.callbacks.pop_front() {
+        machine.call_x86(func, args).await;
+    }
+}
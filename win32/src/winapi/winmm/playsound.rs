@@ -0,0 +1,215 @@
+//! PlaySound, the simplest way to play a WAV: from a file, a resource, or memory.
+
+use super::mci::Sound;
+use crate::{
+    machine::{Emulator, Machine},
+    str16::String16,
+    winapi::{
+        dsound::{Output, LATENCY_MS, POLL_MS},
+        kernel32::{self, ResourceKey},
+        stack_args::FromArg,
+    },
+};
+use bitflags::bitflags;
+use memory::Extensions;
+
+const TRACE_CONTEXT: &'static str = "winmm/playsound";
+
+/*
+## PlaySound
+
+Only one sound plays at a time; starting another stops it.  The sound streams to a
+host stream at its own rate, looping if asked to.  Aliases name system sounds
+configured in the registry, which we have none of, so they play nothing.
+
+Snapshots record the sound itself along with how far it has played, as its
+source may be guest memory that has since been freed.
+*/
+
+bitflags! {
+    pub struct SND: u32 {
+        const ASYNC = 0x0001;
+        const NODEFAULT = 0x0002;
+        const MEMORY = 0x0004;
+        const LOOP = 0x0008;
+        const NOSTOP = 0x0010;
+        const PURGE = 0x0040;
+        const APPLICATION = 0x0080;
+        const NOWAIT = 0x2000;
+        const ALIAS = 0x10000;
+        const ALIAS_ID = 0x110000;
+        const FILENAME = 0x20000;
+        const RESOURCE = 0x40004;
+    }
+}
+impl TryFrom<u32> for SND {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        SND::from_bits(value).ok_or(value)
+    }
+}
+
+pub(super) struct Playing {
+    sound: Sound,
+    looping: bool,
+    output: Output,
+    /// Frames fed to the host.
+    fed: u64,
+}
+
+impl Playing {
+    /// Feed the host more output.  Returns true when the sound has finished.
+    fn update(&mut self, now: u32) -> bool {
+        let frames = self.sound.frames() as u64;
+        let queued = self.output.queued(now) as u64;
+        if frames == 0 || (!self.looping && self.fed >= frames && queued == 0) {
+            return true;
+        }
+        let target = (self.sound.format.rate * LATENCY_MS / 1000) as u64;
+        let mut count = target.saturating_sub(queued);
+        if !self.looping {
+            count = std::cmp::min(count, frames - self.fed);
+        }
+        if count > 0 {
+            let samples = (self.fed..self.fed + count)
+                .flat_map(|i| {
+                    let frame = (i % frames) as u32;
+                    self.sound.format.sample(&self.sound.data, frame)
+                })
+                .map(|s| s as i16)
+                .collect::<Vec<_>>();
+            self.output.write(&samples, now);
+            self.fed += count;
+        }
+        false
+    }
+}
+
+/// The playing sound as recorded in a snapshot.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PlayingRecord {
+    sound: Sound,
+    looping: bool,
+    /// Frames played.
+    played: u64,
+}
+
+/// Record the playing sound, for snapshots.
+pub fn save_sound(machine: &Machine) -> Option<PlayingRecord> {
+    let playing = machine.state.winmm.sound.as_ref()?;
+    let queued = playing.output.queued(machine.host.time()) as u64;
+    Some(PlayingRecord {
+        sound: Sound {
+            format: playing.sound.format,
+            data: playing.sound.data.clone(),
+        },
+        looping: playing.looping,
+        played: playing.fed.saturating_sub(queued),
+    })
+}
+
+/// Resume the sound recorded in a snapshot, on a fresh host stream.
+pub fn restore_sound(machine: &mut Machine, record: Option<PlayingRecord>) {
+    machine.state.winmm.sound = record.map(|record| Playing {
+        output: Output::new(machine, record.sound.format.rate),
+        sound: record.sound,
+        looping: record.looping,
+        fed: record.played,
+    });
+}
+
+/// Advance the playing sound, returning the host time by which to be called again.
+pub(super) fn update(machine: &mut Machine) -> Option<u32> {
+    let now = machine.host.time();
+    let playing = machine.state.winmm.sound.as_mut()?;
+    if playing.update(now) {
+        machine.state.winmm.sound = None;
+        return None;
+    }
+    Some(now + POLL_MS)
+}
+
+/// Load the WAV data a PlaySound call refers to.
+fn load(machine: &Machine, sound: u32, flags: SND) -> Option<Vec<u8>> {
+    let mem = machine.mem();
+    if flags.contains(SND::RESOURCE) {
+        let name = unsafe { ResourceKey::<&str>::from_arg(mem, sound) }.to_string16();
+        let typ = ResourceKey::Name(String16::from("WAVE"));
+        let data =
+            kernel32::find_resource(&machine.state.kernel32, mem, typ.as_ref(), name.as_ref())?;
+        return Some(data.as_slice_todo().to_vec());
+    }
+    if flags.contains(SND::MEMORY) {
+        // The RIFF header gives the size of what follows it.
+        let len = mem.get_pod::<u32>(sound + 4) + 8;
+        return Some(mem.sub(sound, len).as_slice_todo().to_vec());
+    }
+    if flags.contains(SND::ALIAS) {
+        return None;
+    }
+    // With neither SND_FILENAME nor SND_ALIAS, the name is first tried as an alias,
+    // which never succeeds here.
+    let path = String::from_utf8_lossy(mem.slicez(sound)).into_owned();
+    Some(kernel32::read_file(machine, &path))
+}
+
+async fn wait_done(machine: &mut Machine) {
+    loop {
+        let until = crate::winapi::poll_devices(machine);
+        if machine.state.winmm.sound.is_none() {
+            return;
+        }
+        match machine.emu.block(until) {
+            Some(block) => block.await,
+            None => {
+                machine.host.block(until);
+            }
+        }
+    }
+}
+
+#[win32_derive::dllexport]
+pub async fn PlaySoundA(
+    machine: &mut Machine,
+    pszSound: u32,
+    hmod: u32,
+    fdwSound: Result<SND, u32>,
+) -> bool {
+    let flags = fdwSound.unwrap();
+    if pszSound == 0 || flags.contains(SND::PURGE) {
+        machine.state.winmm.sound = None;
+        return true;
+    }
+    if flags.contains(SND::NOSTOP) && machine.state.winmm.sound.is_some() {
+        return false;
+    }
+    machine.state.winmm.sound = None;
+
+    let Some(sound) = load(machine, pszSound, flags).and_then(|wav| Sound::parse_wav(&wav)) else {
+        // Without SND_NODEFAULT, a missing sound plays the default system sound,
+        // which we have none of.
+        return false;
+    };
+    let output = Output::new(machine, sound.format.rate);
+    machine.state.winmm.sound = Some(Playing {
+        sound,
+        // Looping only makes sense asynchronously; the docs require both.
+        looping: flags.contains(SND::LOOP) && flags.contains(SND::ASYNC),
+        output,
+        fed: 0,
+    });
+    if !flags.contains(SND::ASYNC) {
+        wait_done(machine).await;
+    }
+    true
+}
+
+#[win32_derive::dllexport]
+pub async fn sndPlaySoundA(
+    machine: &mut Machine,
+    lpszSound: u32,
+    fuSound: Result<SND, u32>,
+) -> bool {
+    PlaySoundA(machine, lpszSound, 0, fuSound).await
+}
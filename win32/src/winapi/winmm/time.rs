@@ -1,17 +1,184 @@
-use crate::machine::Machine;
+use crate::{
+    machine::Machine,
+    winapi::kernel32::{self, HEVENT},
+};
+use memory::Pod;
 
 const TRACE_CONTEXT: &'static str = "winmm/time";
 
+/*
+## Multimedia timers
+
+Timers are checked whenever devices are polled (see winapi::poll_devices), against
+the host clock, so callbacks run at the next point guest code can be called rather
+than on a thread of their own.  A periodic timer that falls behind fires once for
+each period missed, so code counting ticks keeps time, unless it falls so far
+behind that catching up would be a flood, in which case it skips ahead.
+*/
+
+const TIMERR_NOERROR: u32 = 0;
+const TIMERR_NOCANDO: u32 = 97;
+
+const TIME_PERIODIC: u32 = 0x1;
+const TIME_CALLBACK_EVENT_SET: u32 = 0x10;
+const TIME_CALLBACK_EVENT_PULSE: u32 = 0x20;
+
+/// How far behind a periodic timer may fall before it skips ahead.
+const MAX_LAG_MS: u32 = 100;
+
+/// Longest delay accepted by timeSetEvent, as reported by timeGetDevCaps.
+const PERIOD_MAX: u32 = 1_000_000;
+
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+enum Callback {
+    Function { func: u32, user: u32 },
+    Event(HEVENT),
+}
+
+struct Timer {
+    id: u32,
+    delay: u32,
+    periodic: bool,
+    callback: Callback,
+    /// Host time at which the timer next fires.
+    next: u32,
+}
+
+#[derive(Default)]
+pub struct Timers {
+    timers: Vec<Timer>,
+    next_id: u32,
+}
+
+/// Fire all due timers, returning the host time by which it should be called again.
+pub(super) fn update(machine: &mut Machine) -> Option<u32> {
+    if machine.state.winmm.timers.timers.is_empty() {
+        return None;
+    }
+    let now = machine.host.time();
+    let mut fired = Vec::new();
+    let timers = &mut machine.state.winmm.timers.timers;
+    timers.retain_mut(|timer| {
+        while timer.next <= now {
+            fired.push((timer.id, timer.callback));
+            if !timer.periodic {
+                return false;
+            }
+            timer.next += timer.delay;
+            if now.saturating_sub(timer.next) > MAX_LAG_MS {
+                timer.next = now + timer.delay;
+            }
+        }
+        true
+    });
+    let wait = timers.iter().map(|timer| timer.next).min();
+
+    for (id, callback) in fired {
+        match callback {
+            Callback::Function { func, user } => {
+                let args = vec![id, 0, user, 0, 0];
+                machine.state.winmm.callbacks.push_back((func, args));
+            }
+            // We don't distinguish pulsing from setting; a waiter on an auto-reset
+            // event, the usual pairing, sees the same either way.
+            Callback::Event(event) => {
+                kernel32::set_event(machine, event);
+            }
+        }
+    }
+    wait
+}
+
+/// A timer as recorded in a snapshot.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct TimerRecord {
+    id: u32,
+    delay: u32,
+    periodic: bool,
+    callback: Callback,
+    /// Milliseconds until the timer next fires.
+    due: u32,
+}
+
+/// Record the running timers, for snapshots.
+pub fn save_timers(machine: &Machine) -> Vec<TimerRecord> {
+    let now = machine.host.time();
+    let timers = &machine.state.winmm.timers.timers;
+    timers
+        .iter()
+        .map(|timer| TimerRecord {
+            id: timer.id,
+            delay: timer.delay,
+            periodic: timer.periodic,
+            callback: timer.callback,
+            due: timer.next.saturating_sub(now),
+        })
+        .collect()
+}
+
+/// Replace the running timers with those recorded in a snapshot, keeping their ids.
+pub fn restore_timers(machine: &mut Machine, records: Vec<TimerRecord>) {
+    let now = machine.host.time();
+    let timers = &mut machine.state.winmm.timers;
+    timers.timers = records
+        .into_iter()
+        .map(|record| Timer {
+            id: record.id,
+            delay: record.delay,
+            periodic: record.periodic,
+            callback: record.callback,
+            next: now + record.due,
+        })
+        .collect();
+    timers.next_id = timers
+        .timers
+        .iter()
+        .map(|timer| timer.id)
+        .max()
+        .unwrap_or(0);
+}
+
 #[win32_derive::dllexport]
 pub fn timeSetEvent(
-    _machine: &mut Machine,
+    machine: &mut Machine,
     uDelay: u32,
     uResolution: u32,
     lpTimeProc: u32,
     dwUser: u32,
     fuEvent: u32,
 ) -> u32 {
-    0
+    if uDelay == 0 || uDelay > PERIOD_MAX {
+        return 0;
+    }
+    let callback = if fuEvent & (TIME_CALLBACK_EVENT_SET | TIME_CALLBACK_EVENT_PULSE) != 0 {
+        Callback::Event(HEVENT::from_raw(lpTimeProc))
+    } else {
+        Callback::Function {
+            func: lpTimeProc,
+            user: dwUser,
+        }
+    };
+    let timers = &mut machine.state.winmm.timers;
+    timers.next_id += 1;
+    let id = timers.next_id;
+    timers.timers.push(Timer {
+        id,
+        delay: uDelay,
+        periodic: fuEvent & TIME_PERIODIC != 0,
+        callback,
+        next: machine.host.time() + uDelay,
+    });
+    id
+}
+
+#[win32_derive::dllexport]
+pub fn timeKillEvent(machine: &mut Machine, uTimerID: u32) -> u32 {
+    let timers = &mut machine.state.winmm.timers.timers;
+    let Some(index) = timers.iter().position(|timer| timer.id == uTimerID) else {
+        return TIMERR_NOCANDO;
+    };
+    timers.remove(index);
+    TIMERR_NOERROR
 }
 
 #[win32_derive::dllexport]
@@ -19,10 +186,40 @@ pub fn timeGetTime(machine: &mut Machine) -> u32 {
     machine.host.time()
 }
 
-const TIMERR_NOERROR: u32 = 0;
+#[repr(C)]
+#[derive(Debug)]
+pub struct TIMECAPS {
+    wPeriodMin: u32,
+    wPeriodMax: u32,
+}
+unsafe impl Pod for TIMECAPS {}
+
+#[win32_derive::dllexport]
+pub fn timeGetDevCaps(_machine: &mut Machine, ptc: Option<&mut TIMECAPS>, cbtc: u32) -> u32 {
+    let Some(ptc) = ptc else {
+        return TIMERR_NOCANDO;
+    };
+    if cbtc < std::mem::size_of::<TIMECAPS>() as u32 {
+        return TIMERR_NOCANDO;
+    }
+    ptc.wPeriodMin = 1;
+    ptc.wPeriodMax = PERIOD_MAX;
+    TIMERR_NOERROR
+}
 
 #[win32_derive::dllexport]
 pub fn timeBeginPeriod(_machine: &mut Machine, uPeriod: u32) -> u32 {
-    // ignore
+    // We always keep time to the millisecond.
+    if uPeriod == 0 {
+        return TIMERR_NOCANDO;
+    }
+    TIMERR_NOERROR
+}
+
+#[win32_derive::dllexport]
+pub fn timeEndPeriod(_machine: &mut Machine, uPeriod: u32) -> u32 {
+    if uPeriod == 0 {
+        return TIMERR_NOCANDO;
+    }
     TIMERR_NOERROR
 }
@@ -1,20 +1,50 @@
-use crate::machine::Machine;
+use crate::{
+    machine::Machine,
+    winapi::{
+        dsound::{Format, Output, LATENCY_MS, POLL_MS},
+        kernel32::{self, HEVENT},
+        types::{HANDLE, HWND},
+        user32,
+    },
+};
 use bitflags::bitflags;
-use memory::Pod;
+use memory::{Extensions, Mem, Pod};
+use std::collections::VecDeque;
 
 const TRACE_CONTEXT: &'static str = "winmm/wave";
 
-// This module isn't fully implemented yet.  This toggle lets us tell apps
-// we don't support sound so they don't call into unimplemented code yet.
-const ENABLED: bool = false;
+/*
+## waveOut
+
+Each open device streams to its own host audio stream at the rate of its format.
+Like DirectSound, written headers are fed to the host LATENCY_MS ahead of playback
+(in update(), called via winapi::poll_devices), and a header is done once the host
+has played past its end.  The device's position likewise follows the host.
+
+Completion is reported by the device's callback: a posted message, an event, or a
+function.  Functions can only be called where guest code may run, so their calls are
+queued for winmm::run_callbacks, which runs wherever the guest waits or pumps messages.
+*/
+
+pub const MMSYSERR_NOERROR: u32 = 0;
+pub const MMSYSERR_BADDEVICEID: u32 = 2;
+pub const MMSYSERR_INVALHANDLE: u32 = 5;
+pub const MMSYSERR_INVALPARAM: u32 = 11;
+pub const WAVERR_BADFORMAT: u32 = 32;
+pub const WAVERR_STILLPLAYING: u32 = 33;
+pub const WAVERR_UNPREPARED: u32 = 34;
+
+/// Device ID selecting whichever device suits the format.
+const WAVE_MAPPER: u32 = 0xFFFF_FFFF;
+
+/// Callback messages, also posted as MM_WOM_* window messages.
+const WOM_OPEN: u32 = 0x3BB;
+const WOM_CLOSE: u32 = 0x3BC;
+const WOM_DONE: u32 = 0x3BD;
 
 #[win32_derive::dllexport]
 pub fn waveOutGetNumDevs(_machine: &mut Machine) -> u32 {
-    if ENABLED {
-        1
-    } else {
-        0
-    }
+    1
 }
 
 #[repr(C)]
@@ -41,15 +71,22 @@ pub fn waveOutGetDevCapsA(
 ) -> u32 {
     let woc = pwoc.unwrap();
     assert_eq!(cbwoc, std::mem::size_of::<WAVEOUTCAPS>() as u32);
+    if uDeviceID != 0 && uDeviceID != WAVE_MAPPER {
+        return MMSYSERR_BADDEVICEID;
+    }
     woc.clear_struct();
-    woc.dwFormats = 0x00000400; // WAVE_FORMAT_4M16
-    0
+    let name = b"retrowin32 Wave Out";
+    woc.szPname[..name.len()].copy_from_slice(name);
+    woc.dwFormats = 0x00000FFF; // 8 and 16 bit, mono and stereo, at 11/22/44kHz
+    woc.wChannels = 2;
+    MMSYSERR_NOERROR
 }
 
-pub type HWAVEOUT = u32;
+pub struct HWAVEOUTT;
+pub type HWAVEOUT = HANDLE<HWAVEOUTT>;
 
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct WAVEFORMATEX {
     pub wFormatTag: u16,
     pub nChannels: u16,
@@ -63,7 +100,14 @@ unsafe impl memory::Pod for WAVEFORMATEX {}
 
 bitflags! {
     pub struct WaveOutOpenFlags: u32 {
+        const WAVE_FORMAT_QUERY = 0x0001;
+        const WAVE_ALLOWSYNC = 0x0002;
+        const WAVE_MAPPED = 0x0004;
+        const WAVE_FORMAT_DIRECT = 0x0008;
+        const CALLBACK_WINDOW = 0x00010000;
+        const CALLBACK_THREAD = 0x00020000;
         const CALLBACK_FUNCTION = 0x00030000;
+        const CALLBACK_EVENT = 0x00050000;
     }
 }
 impl TryFrom<u32> for WaveOutOpenFlags {
@@ -74,9 +118,129 @@ impl TryFrom<u32> for WaveOutOpenFlags {
     }
 }
 
+/// How a device reports WOM_* events, from the CALLBACK_* flags to waveOutOpen.
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+enum Callback {
+    None,
+    Window(HWND),
+    Function(u32),
+    Event(HEVENT),
+}
+
+pub struct WaveOut {
+    format: Format,
+    callback: Callback,
+    instance: u32,
+    output: Output,
+    /// Headers written but not yet entirely fed to the host, oldest first.
+    pending: VecDeque<u32>,
+    /// Frames of the first pending header already fed.
+    offset: u32,
+    /// Headers entirely fed, with the count of fed frames at which they end.
+    playing: VecDeque<(u32, u64)>,
+    /// Frames fed to the host since the device was opened.
+    fed: u64,
+    /// Count of frames played at the last reset, where the position counts from.
+    base: u64,
+}
+
+impl WaveOut {
+    /// Frames the host has played since the device was opened.
+    fn played(&self, now: u32) -> u64 {
+        self.fed - std::cmp::min(self.output.queued(now) as u64, self.fed)
+    }
+
+    /// Feed pending headers to the host until LATENCY_MS of output is queued.
+    fn feed(&mut self, mem: Mem, now: u32) {
+        let target = (self.format.rate * LATENCY_MS / 1000) as u64;
+        let mut queued = self.output.queued(now) as u64;
+        while let Some(&hdr) = self.pending.front() {
+            if queued >= target {
+                break;
+            }
+            let header = mem.get_pod::<WAVEHDR>(hdr);
+            let frames = header.dwBufferLength / self.format.block_align();
+            let count = std::cmp::min((frames - self.offset) as u64, target - queued) as u32;
+            if count > 0 {
+                let data = mem
+                    .sub(header.lpData, header.dwBufferLength)
+                    .as_slice_todo();
+                let samples = (self.offset..self.offset + count)
+                    .flat_map(|frame| self.format.sample(data, frame))
+                    .map(|s| s as i16)
+                    .collect::<Vec<_>>();
+                self.output.write(&samples, now);
+                self.fed += count as u64;
+                queued += count as u64;
+                self.offset += count;
+            }
+            if self.offset >= frames {
+                self.pending.pop_front();
+                self.offset = 0;
+                self.playing.push_back((hdr, self.fed));
+            }
+        }
+    }
+}
+
+/// Report a WOM_* event through a device's callback.
+fn notify(machine: &mut Machine, hwo: HWAVEOUT, msg: u32, param: u32) {
+    let Some(wave) = machine.state.winmm.wave_outs.get(hwo) else {
+        return;
+    };
+    match wave.callback {
+        Callback::None => {}
+        Callback::Window(hwnd) => user32::post_message(machine, hwnd, msg, hwo.to_raw(), param),
+        Callback::Event(event) => {
+            kernel32::set_event(machine, event);
+        }
+        Callback::Function(func) => {
+            let args = vec![hwo.to_raw(), msg, wave.instance, param, 0];
+            machine.state.winmm.callbacks.push_back((func, args));
+        }
+    }
+}
+
+/// Mark a header as played and report it.
+fn finish(machine: &mut Machine, hwo: HWAVEOUT, hdr: u32) {
+    let header = machine.emu.memory.mem().view_mut::<WAVEHDR>(hdr);
+    header.dwFlags = (header.dwFlags & !WHDR::INQUEUE) | WHDR::DONE;
+    notify(machine, hwo, WOM_DONE, hdr);
+}
+
+/// Advance playback of all devices, finishing the headers the host has played.
+/// Returns the host time by which it should be called again, if anything is playing.
+pub(super) fn update(machine: &mut Machine) -> Option<u32> {
+    if machine.state.winmm.wave_outs.iter().next().is_none() {
+        return None;
+    }
+    let now = machine.host.time();
+    let mem = machine.emu.memory.mem();
+    let mut done = Vec::new();
+    let mut wait = None;
+    for (hwo, wave) in machine.state.winmm.wave_outs.iter_mut() {
+        let played = wave.played(now);
+        while let Some(&(hdr, end)) = wave.playing.front() {
+            if end > played {
+                break;
+            }
+            wave.playing.pop_front();
+            done.push((hwo, hdr));
+        }
+        wave.feed(mem, now);
+        if !wave.pending.is_empty() || !wave.playing.is_empty() {
+            wait = Some(now + POLL_MS);
+        }
+    }
+    for (hwo, hdr) in done {
+        finish(machine, hwo, hdr);
+    }
+    wait
+}
+
 #[win32_derive::dllexport]
 pub fn waveOutOpen(
-    _machine: &mut Machine,
+    machine: &mut Machine,
     phwo: Option<&mut HWAVEOUT>,
     uDeviceID: u32,
     pwfx: Option<&WAVEFORMATEX>,
@@ -84,24 +248,153 @@ pub fn waveOutOpen(
     dwInstance: u32,
     fdwOpen: Result<WaveOutOpenFlags, u32>,
 ) -> u32 {
-    let flags = fdwOpen.unwrap();
-    if flags.contains(WaveOutOpenFlags::CALLBACK_FUNCTION) {
-        todo!();
+    let Ok(flags) = fdwOpen else {
+        return MMSYSERR_INVALPARAM;
+    };
+    if uDeviceID != 0 && uDeviceID != WAVE_MAPPER {
+        return MMSYSERR_BADDEVICEID;
     }
-    *phwo.unwrap() = 1;
-    0
+    let Some(format) = pwfx.and_then(Format::from_wave) else {
+        return WAVERR_BADFORMAT;
+    };
+    if flags.contains(WaveOutOpenFlags::WAVE_FORMAT_QUERY) {
+        return MMSYSERR_NOERROR;
+    }
+    let mask = WaveOutOpenFlags::CALLBACK_WINDOW
+        | WaveOutOpenFlags::CALLBACK_THREAD
+        | WaveOutOpenFlags::CALLBACK_EVENT;
+    let callback = match flags & mask {
+        f if f.is_empty() => Callback::None,
+        WaveOutOpenFlags::CALLBACK_WINDOW => Callback::Window(HWND::from_raw(dwCallback)),
+        WaveOutOpenFlags::CALLBACK_FUNCTION => Callback::Function(dwCallback),
+        WaveOutOpenFlags::CALLBACK_EVENT => Callback::Event(HEVENT::from_raw(dwCallback)),
+        f => {
+            log::warn!("waveOutOpen: unsupported callback {f:?}");
+            Callback::None
+        }
+    };
+    let Some(phwo) = phwo else {
+        return MMSYSERR_INVALPARAM;
+    };
+
+    let output = Output::new(machine, format.rate);
+    let hwo = machine.state.winmm.wave_outs.add(WaveOut {
+        format,
+        callback,
+        instance: dwInstance,
+        output,
+        pending: VecDeque::new(),
+        offset: 0,
+        playing: VecDeque::new(),
+        fed: 0,
+        base: 0,
+    });
+    *phwo = hwo;
+    notify(machine, hwo, WOM_OPEN, 0);
+    MMSYSERR_NOERROR
 }
 
 #[win32_derive::dllexport]
-pub fn waveOutReset(_machine: &mut Machine, hwo: HWAVEOUT) -> u32 {
-    0
+pub fn waveOutReset(machine: &mut Machine, hwo: HWAVEOUT) -> u32 {
+    let now = machine.host.time();
+    let Some(wave) = machine.state.winmm.wave_outs.get_mut(hwo) else {
+        return MMSYSERR_INVALHANDLE;
+    };
+    // Output already handed to the host plays out, but the position restarts here.
+    wave.base = wave.played(now);
+    wave.offset = 0;
+    let headers = wave
+        .playing
+        .drain(..)
+        .map(|(hdr, _)| hdr)
+        .chain(wave.pending.drain(..))
+        .collect::<Vec<_>>();
+    for hdr in headers {
+        finish(machine, hwo, hdr);
+    }
+    MMSYSERR_NOERROR
 }
 
 #[win32_derive::dllexport]
-pub fn waveOutClose(_machine: &mut Machine, hwo: HWAVEOUT) -> u32 {
-    0
+pub fn waveOutClose(machine: &mut Machine, hwo: HWAVEOUT) -> u32 {
+    let Some(wave) = machine.state.winmm.wave_outs.get(hwo) else {
+        return MMSYSERR_INVALHANDLE;
+    };
+    if !wave.pending.is_empty() || !wave.playing.is_empty() {
+        return WAVERR_STILLPLAYING;
+    }
+    notify(machine, hwo, WOM_CLOSE, 0);
+    machine.state.winmm.wave_outs.remove(hwo);
+    MMSYSERR_NOERROR
+}
+
+/// An open device as recorded in a snapshot.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct WaveOutRecord {
+    handle: HWAVEOUT,
+    format: Format,
+    callback: Callback,
+    instance: u32,
+    /// Position, in frames, as waveOutGetPosition reports it.
+    position: u64,
+    /// Headers written and not yet reported done.
+    headers: Vec<u32>,
+}
+
+/// Record the open devices, for snapshots.
+pub fn save_wave_outs(machine: &Machine) -> Vec<WaveOutRecord> {
+    let now = machine.host.time();
+    let mut records = Vec::new();
+    for (hwo, wave) in machine.state.winmm.wave_outs.entries() {
+        records.push(WaveOutRecord {
+            handle: hwo,
+            format: wave.format,
+            callback: wave.callback,
+            instance: wave.instance,
+            position: wave.played(now) - wave.base,
+            headers: wave
+                .playing
+                .iter()
+                .map(|&(hdr, _)| hdr)
+                .chain(wave.pending.iter().copied())
+                .collect(),
+        });
+    }
+    records
 }
 
+/// Replace the open devices with those recorded in a snapshot.  Each is reopened
+/// on a fresh host stream continuing from its recorded position.  Sound that was
+/// queued at the time is lost: its headers are reported done, as if by
+/// waveOutReset, so the guest refills them.
+pub fn restore_wave_outs(machine: &mut Machine, records: Vec<WaveOutRecord>) {
+    machine.state.winmm.wave_outs = Default::default();
+    for record in records {
+        let output = Output::new(machine, record.format.rate);
+        machine.state.winmm.wave_outs.set(
+            record.handle,
+            WaveOut {
+                format: record.format,
+                callback: record.callback,
+                instance: record.instance,
+                output,
+                pending: VecDeque::new(),
+                offset: 0,
+                playing: VecDeque::new(),
+                fed: record.position,
+                base: 0,
+            },
+        );
+        for hdr in record.headers {
+            finish(machine, record.handle, hdr);
+        }
+    }
+}
+
+const TIME_MS: u32 = 0x1;
+const TIME_SAMPLES: u32 = 0x2;
+const TIME_BYTES: u32 = 0x4;
+
 #[repr(C)]
 pub struct MMTIME {
     wType: u32,
@@ -144,25 +437,51 @@ unsafe impl memory::Pod for MMTIME_smpte {}
 
 #[win32_derive::dllexport]
 pub fn waveOutGetPosition(
-    _machine: &mut Machine,
+    machine: &mut Machine,
     hwo: HWAVEOUT,
     pmmt: Option<&mut MMTIME>,
     cbmmt: u32,
 ) -> u32 {
     assert_eq!(cbmmt, std::mem::size_of::<MMTIME>() as u32);
-    let mmt = pmmt.unwrap();
-    mmt.u.sample = 0;
-    0
+    update(machine);
+    let now = machine.host.time();
+    let Some(wave) = machine.state.winmm.wave_outs.get(hwo) else {
+        return MMSYSERR_INVALHANDLE;
+    };
+    let Some(mmt) = pmmt else {
+        return MMSYSERR_INVALPARAM;
+    };
+    let frames = wave.played(now).saturating_sub(wave.base);
+    match mmt.wType {
+        TIME_MS => mmt.u.ms = (frames * 1000 / wave.format.rate as u64) as u32,
+        TIME_SAMPLES => mmt.u.sample = frames as u32,
+        _ => {
+            // Unsupported formats are answered in bytes.
+            mmt.wType = TIME_BYTES;
+            mmt.u.cb = (frames * wave.format.block_align() as u64) as u32;
+        }
+    }
+    MMSYSERR_NOERROR
+}
+
+bitflags! {
+    pub struct WHDR: u32 {
+        const DONE = 0x00000001;
+        const PREPARED = 0x00000002;
+        const BEGINLOOP = 0x00000004;
+        const ENDLOOP = 0x00000008;
+        const INQUEUE = 0x00000010;
+    }
 }
 
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct WAVEHDR {
     lpData: u32,
     dwBufferLength: u32,
     dwBytesRecorded: u32,
     dwUser: u32,
-    dwFlags: u32,
+    dwFlags: WHDR,
     dwLoops: u32,
     lpNext: u32,
     reserved: u32,
@@ -173,20 +492,53 @@ unsafe impl memory::Pod for WAVEHDR {}
 pub fn waveOutPrepareHeader(
     _machine: &mut Machine,
     hwo: HWAVEOUT,
-    pwh: Option<&WAVEHDR>,
+    pwh: Option<&mut WAVEHDR>,
     cbwh: u32,
 ) -> u32 {
     assert_eq!(cbwh, std::mem::size_of::<WAVEHDR>() as u32);
-    0
+    let Some(header) = pwh else {
+        return MMSYSERR_INVALPARAM;
+    };
+    header.dwFlags.insert(WHDR::PREPARED);
+    MMSYSERR_NOERROR
 }
 
 #[win32_derive::dllexport]
-pub fn waveOutWrite(
+pub fn waveOutUnprepareHeader(
     _machine: &mut Machine,
     hwo: HWAVEOUT,
-    pwh: Option<&WAVEHDR>,
+    pwh: Option<&mut WAVEHDR>,
     cbwh: u32,
 ) -> u32 {
     assert_eq!(cbwh, std::mem::size_of::<WAVEHDR>() as u32);
-    0
+    let Some(header) = pwh else {
+        return MMSYSERR_INVALPARAM;
+    };
+    if header.dwFlags.contains(WHDR::INQUEUE) {
+        return WAVERR_STILLPLAYING;
+    }
+    header.dwFlags.remove(WHDR::PREPARED);
+    MMSYSERR_NOERROR
+}
+
+#[win32_derive::dllexport]
+pub fn waveOutWrite(machine: &mut Machine, hwo: HWAVEOUT, pwh: u32, cbwh: u32) -> u32 {
+    assert_eq!(cbwh, std::mem::size_of::<WAVEHDR>() as u32);
+    if pwh == 0 {
+        return MMSYSERR_INVALPARAM;
+    }
+    let header = machine.emu.memory.mem().view_mut::<WAVEHDR>(pwh);
+    if !header.dwFlags.contains(WHDR::PREPARED) {
+        return WAVERR_UNPREPARED;
+    }
+    let Some(wave) = machine.state.winmm.wave_outs.get_mut(hwo) else {
+        return MMSYSERR_INVALHANDLE;
+    };
+    if header.dwFlags.contains(WHDR::BEGINLOOP) && header.dwLoops > 1 {
+        log::warn!("waveOutWrite: ignoring {} loops", header.dwLoops);
+    }
+    header.dwFlags = (header.dwFlags & !WHDR::DONE) | WHDR::INQUEUE;
+    wave.pending.push_back(pwh);
+    update(machine);
+    MMSYSERR_NOERROR
 }
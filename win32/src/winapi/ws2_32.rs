@@ -0,0 +1,1073 @@
+//! Winsock, over the host's network.  wsock32.dll is forwarded here, sharing ordinals.
+
+#![allow(non_snake_case)]
+
+use super::{
+    handle::Handles,
+    heap::Heap,
+    types::{HANDLE, HWND},
+    user32,
+};
+use crate::{
+    host::{self, SocketKind},
+    machine::{Emulator, Machine},
+};
+use memory::{Extensions, Mem, Pod};
+use std::{
+    collections::VecDeque,
+    io::ErrorKind,
+    net::{Ipv4Addr, Shutdown, SocketAddrV4},
+};
+
+const TRACE_CONTEXT: &'static str = "ws2_32";
+
+/*
+## Winsock
+
+Sockets are host sockets, which never wait; calls that block on Windows poll the
+host socket until done, letting time pass for the rest of the machine between
+tries.  Only IPv4 is supported.
+
+WSAAsyncSelect notifications are posted as sockets are polled along with other
+devices, whenever the guest waits or pumps messages.  Each event, once posted,
+isn't posted again until the call it calls for (recv for FD_READ, etc.) re-arms
+it, as on Windows.
+
+A snapshot can't hold a connection, so it records each socket's addresses as
+hints for reopening it: a listening or datagram socket is bound again to its
+local address, and a stream socket that was connected connects again to its
+peer.  The peer sees a new connection, so whatever protocol state the two sides
+shared is gone; games generally notice and reconnect themselves.
+*/
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SOCKETT;
+pub type SOCKET = HANDLE<SOCKETT>;
+
+const SOCKET_ERROR: i32 = -1;
+
+const WSAEFAULT: u32 = 10014;
+const WSAEINVAL: u32 = 10022;
+const WSAEWOULDBLOCK: u32 = 10035;
+const WSAENOTSOCK: u32 = 10038;
+const WSAEPROTONOSUPPORT: u32 = 10043;
+const WSAEOPNOTSUPP: u32 = 10045;
+const WSAEAFNOSUPPORT: u32 = 10047;
+const WSAEADDRINUSE: u32 = 10048;
+const WSAEADDRNOTAVAIL: u32 = 10049;
+const WSAENETDOWN: u32 = 10050;
+const WSAENETUNREACH: u32 = 10051;
+const WSAECONNABORTED: u32 = 10053;
+const WSAECONNRESET: u32 = 10054;
+const WSAENOTCONN: u32 = 10057;
+const WSAETIMEDOUT: u32 = 10060;
+const WSAECONNREFUSED: u32 = 10061;
+const WSAVERNOTSUPPORTED: u32 = 10092;
+const WSANOTINITIALISED: u32 = 10093;
+const WSAHOST_NOT_FOUND: u32 = 11001;
+
+const AF_INET: u16 = 2;
+const SOCK_STREAM: u32 = 1;
+const SOCK_DGRAM: u32 = 2;
+
+const FIONBIO: u32 = 0x8004667E;
+const FIONREAD: u32 = 0x4004667F;
+
+const SOL_SOCKET: u32 = 0xFFFF;
+const SO_TYPE: u32 = 0x1008;
+const SO_ERROR: u32 = 0x1007;
+
+const FD_READ: u32 = 0x1;
+const FD_WRITE: u32 = 0x2;
+const FD_ACCEPT: u32 = 0x8;
+const FD_CONNECT: u32 = 0x10;
+const FD_CLOSE: u32 = 0x20;
+
+/// How often blocking calls retry, and sockets are polled for WSAAsyncSelect.
+const POLL_MS: u32 = 10;
+
+fn wsa_error(err: &std::io::Error) -> u32 {
+    match err.kind() {
+        ErrorKind::WouldBlock => WSAEWOULDBLOCK,
+        ErrorKind::ConnectionRefused => WSAECONNREFUSED,
+        ErrorKind::ConnectionReset => WSAECONNRESET,
+        ErrorKind::ConnectionAborted => WSAECONNABORTED,
+        ErrorKind::NotConnected => WSAENOTCONN,
+        ErrorKind::AddrInUse => WSAEADDRINUSE,
+        ErrorKind::AddrNotAvailable => WSAEADDRNOTAVAIL,
+        ErrorKind::TimedOut => WSAETIMEDOUT,
+        ErrorKind::InvalidInput => WSAEINVAL,
+        ErrorKind::Unsupported => WSAEOPNOTSUPP,
+        ErrorKind::NotFound => WSAENETUNREACH,
+        _ => WSAENETDOWN,
+    }
+}
+
+pub struct Socket {
+    kind: SocketKind,
+    host: Box<dyn host::Socket>,
+    /// Set by ioctlsocket(FIONBIO).
+    nonblocking: bool,
+    /// Stream data read from the host ahead of recv(), to answer FIONREAD.
+    pending: VecDeque<u8>,
+    /// Set by WSAAsyncSelect.
+    select: Option<AsyncSelect>,
+    /// The address of a nonblocking connect() still under way.
+    connecting: Option<SocketAddrV4>,
+    listening: bool,
+    /// The stream ended, with this error code if it ended badly; seen while
+    /// reading ahead.
+    closed: Option<u32>,
+}
+
+/// Where to post a socket's WSAAsyncSelect notifications.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct AsyncSelect {
+    hwnd: HWND,
+    msg: u32,
+    events: u32,
+    /// Events that will be posted when they next occur.
+    armed: u32,
+}
+
+impl Socket {
+    fn new(kind: SocketKind, host: Box<dyn host::Socket>) -> Self {
+        Socket {
+            kind,
+            host,
+            nonblocking: false,
+            pending: VecDeque::new(),
+            select: None,
+            connecting: None,
+            listening: false,
+            closed: None,
+        }
+    }
+
+    /// Re-enable an async notification, after the call that satisfies it.
+    fn rearm(&mut self, event: u32) {
+        if let Some(select) = &mut self.select {
+            select.armed |= event & select.events;
+        }
+    }
+
+    /// Read ahead whatever the host has received on a stream.
+    fn read_ahead(&mut self) {
+        let mut buf = [0u8; 4096];
+        while self.closed.is_none() {
+            match self.host.recv(&mut buf) {
+                Ok(0) => self.closed = Some(0),
+                Ok(n) => self.pending.extend(&buf[..n]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => self.closed = Some(wsa_error(&err)),
+            }
+        }
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.rearm(FD_READ);
+        if self.pending.is_empty() {
+            if self.closed.is_some() {
+                return Ok(0);
+            }
+            return self.host.recv(buf);
+        }
+        let n = std::cmp::min(buf.len(), self.pending.len());
+        for (dst, src) in buf.iter_mut().zip(self.pending.drain(..n)) {
+            *dst = src;
+        }
+        Ok(n)
+    }
+
+    fn readable(&mut self) -> bool {
+        !self.pending.is_empty() || self.host.readable()
+    }
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct State {
+    heap: Heap,
+    /// Buffers returned by gethostbyname and inet_ntoa, which Winsock owns.
+    hostent: u32,
+    ntoa: u32,
+    /// Sockets hold host connections, which snapshots record separately; see save.
+    #[serde(skip, default = "new_sockets")]
+    sockets: Handles<SOCKET, Socket>,
+    last_error: u32,
+}
+
+impl State {
+    fn new_init(machine: &mut Machine) -> Self {
+        let mut heap = machine.state.kernel32.new_private_heap(
+            &mut machine.emu.memory,
+            0x1000,
+            "ws2_32.dll heap".into(),
+        );
+        let mem = machine.emu.memory.mem();
+        let hostent = heap.alloc(mem, HOSTENT_SIZE);
+        let ntoa = heap.alloc(mem, 16);
+        State {
+            heap,
+            hostent,
+            ntoa,
+            sockets: new_sockets(),
+            last_error: 0,
+        }
+    }
+}
+
+fn new_sockets() -> Handles<SOCKET, Socket> {
+    Handles::new(0x100)
+}
+
+/// An open socket as recorded in a snapshot.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct SocketRecord {
+    handle: SOCKET,
+    kind: SocketKind,
+    nonblocking: bool,
+    select: Option<AsyncSelect>,
+    listening: bool,
+    /// The bound address, to bind again, for listening and datagram sockets.
+    local: Option<SocketAddrV4>,
+    /// The address a stream socket was connected or connecting to, to connect again.
+    peer: Option<SocketAddrV4>,
+    /// Data received and not yet read by the guest.
+    pending: Vec<u8>,
+    closed: Option<u32>,
+}
+
+/// Record the open sockets, for snapshots.
+pub fn save(machine: &Machine) -> Vec<SocketRecord> {
+    machine
+        .state
+        .ws2_32
+        .sockets
+        .entries()
+        .map(|(handle, socket)| {
+            let bound = socket.listening || socket.kind == SocketKind::Datagram;
+            SocketRecord {
+                handle,
+                kind: socket.kind,
+                nonblocking: socket.nonblocking,
+                select: socket.select.clone(),
+                listening: socket.listening,
+                local: bound.then(|| socket.host.local_addr().ok()).flatten(),
+                peer: socket.connecting.or_else(|| socket.host.peer_addr().ok()),
+                pending: socket.pending.iter().copied().collect(),
+                closed: socket.closed,
+            }
+        })
+        .collect()
+}
+
+/// Replace the open sockets with those recorded in a snapshot, reopening each on
+/// a new host socket.  Connections are made again without waiting, as if by a
+/// nonblocking connect(); a socket whose address or peer can't be taken again
+/// reads as reset.
+pub fn restore(machine: &mut Machine, records: Vec<SocketRecord>) {
+    machine.state.ws2_32.sockets = new_sockets();
+    for record in records {
+        let host = match machine.host.socket(record.kind) {
+            Ok(host) => host,
+            Err(err) => {
+                log::warn!("ws2_32: can't reopen socket: {err}");
+                continue;
+            }
+        };
+        let mut socket = Socket::new(record.kind, host);
+        socket.nonblocking = record.nonblocking;
+        socket.select = record.select;
+        socket.listening = record.listening;
+        socket.pending = record.pending.into();
+        socket.closed = record.closed;
+        let mut reopen = || -> std::io::Result<()> {
+            if let Some(local) = record.local {
+                socket.host.bind(local)?;
+            }
+            if record.listening {
+                socket.host.listen()?;
+            }
+            if let (Some(peer), None) = (record.peer, record.closed) {
+                match socket.host.connect(peer) {
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                        socket.connecting = Some(peer)
+                    }
+                    result => result?,
+                }
+            }
+            Ok(())
+        };
+        if let Err(err) = reopen() {
+            log::warn!("ws2_32: can't reopen socket: {err}");
+            socket.closed = Some(WSAECONNRESET);
+        }
+        machine.state.ws2_32.sockets.set(record.handle, socket);
+    }
+}
+
+/// Record an error for WSAGetLastError.
+fn set_error(machine: &mut Machine, err: u32) {
+    machine.state.ws2_32.last_error = err;
+}
+
+/// Let the rest of the machine run for a bit while a blocking call waits.
+async fn wait(machine: &mut Machine) {
+    let until = machine.host.time() + POLL_MS;
+    match machine.emu.block(Some(until)) {
+        Some(block) => block.await,
+        None => {
+            machine.host.block(Some(until));
+        }
+    }
+    crate::winapi::poll_devices(machine);
+}
+
+/// Run a socket operation, retrying while it would block unless the socket is
+/// nonblocking.  Errors are Winsock error codes.
+async fn blocking<T>(
+    machine: &mut Machine,
+    s: SOCKET,
+    mut op: impl FnMut(&mut Socket, Mem) -> std::io::Result<T>,
+) -> Result<T, u32> {
+    loop {
+        let mem = machine.emu.memory.mem();
+        let Some(socket) = machine.state.ws2_32.sockets.get_mut(s) else {
+            return Err(WSAENOTSOCK);
+        };
+        match op(socket, mem) {
+            Ok(t) => return Ok(t),
+            Err(err) if err.kind() == ErrorKind::WouldBlock && !socket.nonblocking => {}
+            Err(err) => return Err(wsa_error(&err)),
+        }
+        wait(machine).await;
+    }
+}
+
+/// Turn a result into a Winsock return value, recording any error.
+fn finish(machine: &mut Machine, result: Result<i32, u32>) -> i32 {
+    match result {
+        Ok(n) => n,
+        Err(err) => {
+            set_error(machine, err);
+            SOCKET_ERROR
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct sockaddr_in {
+    sin_family: u16,
+    /// Network byte order.
+    sin_port: u16,
+    /// Network byte order.
+    sin_addr: [u8; 4],
+    sin_zero: [u8; 8],
+}
+unsafe impl Pod for sockaddr_in {}
+
+fn read_addr(mem: Mem, name: u32, namelen: i32) -> Result<SocketAddrV4, u32> {
+    if name == 0 || namelen < std::mem::size_of::<sockaddr_in>() as i32 {
+        return Err(WSAEFAULT);
+    }
+    let addr = mem.get_pod::<sockaddr_in>(name);
+    if addr.sin_family != AF_INET {
+        return Err(WSAEAFNOSUPPORT);
+    }
+    Ok(SocketAddrV4::new(
+        Ipv4Addr::from(addr.sin_addr),
+        u16::from_be(addr.sin_port),
+    ))
+}
+
+/// Write an address to an optional sockaddr out-parameter and its length.
+fn write_addr(
+    mem: Mem,
+    name: u32,
+    namelen: Option<&mut i32>,
+    addr: SocketAddrV4,
+) -> Result<(), u32> {
+    let (Some(namelen), true) = (namelen, name != 0) else {
+        return Ok(());
+    };
+    let size = std::mem::size_of::<sockaddr_in>() as i32;
+    if *namelen < size {
+        return Err(WSAEFAULT);
+    }
+    mem.put::<sockaddr_in>(
+        name,
+        sockaddr_in {
+            sin_family: AF_INET,
+            sin_port: addr.port().to_be(),
+            sin_addr: addr.ip().octets(),
+            sin_zero: [0; 8],
+        },
+    );
+    *namelen = size;
+    Ok(())
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct WSADATA {
+    wVersion: u16,
+    wHighVersion: u16,
+    szDescription: [u8; 257],
+    szSystemStatus: [u8; 129],
+    iMaxSockets: u16,
+    iMaxUdpDg: u16,
+    lpVendorInfo: u32,
+}
+unsafe impl Pod for WSADATA {}
+
+#[win32_derive::dllexport(115)]
+pub fn WSAStartup(
+    machine: &mut Machine,
+    wVersionRequested: u32,
+    lpWSAData: Option<&mut WSADATA>,
+) -> u32 {
+    let Some(data) = lpWSAData else {
+        return WSAEFAULT;
+    };
+    let (major, minor) = (wVersionRequested & 0xFF, wVersionRequested >> 8 & 0xFF);
+    // We're version 2.2, which can also act as any 1.x.
+    let version = if major >= 2 {
+        0x0202
+    } else {
+        major | minor << 8
+    };
+    data.clear_struct();
+    data.wVersion = version as u16;
+    data.wHighVersion = 0x0202;
+    let description = b"retrowin32 Winsock";
+    data.szDescription[..description.len()].copy_from_slice(description);
+    data.szSystemStatus[..7].copy_from_slice(b"Running");
+    data.iMaxSockets = 0x7FFF;
+    data.iMaxUdpDg = 0xFFFF;
+    if major == 0 {
+        return WSAVERNOTSUPPORTED;
+    }
+    if machine.state.ws2_32.heap.addr == 0 {
+        machine.state.ws2_32 = State::new_init(machine);
+    }
+    0
+}
+
+#[win32_derive::dllexport(116)]
+pub fn WSACleanup(_machine: &mut Machine) -> i32 {
+    0
+}
+
+#[win32_derive::dllexport(111)]
+pub fn WSAGetLastError(machine: &mut Machine) -> u32 {
+    machine.state.ws2_32.last_error
+}
+
+#[win32_derive::dllexport(112)]
+pub fn WSASetLastError(machine: &mut Machine, iError: u32) -> u32 {
+    set_error(machine, iError);
+    0 // unused
+}
+
+#[win32_derive::dllexport(23)]
+pub fn socket(machine: &mut Machine, af: u32, type_: u32, protocol: u32) -> SOCKET {
+    if machine.state.ws2_32.heap.addr == 0 {
+        set_error(machine, WSANOTINITIALISED);
+        return SOCKET::invalid();
+    }
+    if af != AF_INET as u32 {
+        set_error(machine, WSAEAFNOSUPPORT);
+        return SOCKET::invalid();
+    }
+    let kind = match type_ {
+        SOCK_STREAM => SocketKind::Stream,
+        SOCK_DGRAM => SocketKind::Datagram,
+        _ => {
+            log::warn!("socket: unsupported type {type_}");
+            set_error(machine, WSAEPROTONOSUPPORT);
+            return SOCKET::invalid();
+        }
+    };
+    match machine.host.socket(kind) {
+        Ok(host) => machine.state.ws2_32.sockets.add(Socket::new(kind, host)),
+        Err(err) => {
+            set_error(machine, wsa_error(&err));
+            SOCKET::invalid()
+        }
+    }
+}
+
+#[win32_derive::dllexport(3)]
+pub fn closesocket(machine: &mut Machine, s: SOCKET) -> i32 {
+    if machine.state.ws2_32.sockets.remove(s).is_none() {
+        set_error(machine, WSAENOTSOCK);
+        return SOCKET_ERROR;
+    }
+    0
+}
+
+#[win32_derive::dllexport(2)]
+pub fn bind(machine: &mut Machine, s: SOCKET, name: u32, namelen: i32) -> i32 {
+    let mem = machine.emu.memory.mem();
+    let result = read_addr(mem, name, namelen).and_then(|addr| {
+        let socket = machine.state.ws2_32.sockets.get_mut(s).ok_or(WSAENOTSOCK)?;
+        socket.host.bind(addr).map_err(|err| wsa_error(&err))?;
+        Ok(0)
+    });
+    finish(machine, result)
+}
+
+#[win32_derive::dllexport(13)]
+pub fn listen(machine: &mut Machine, s: SOCKET, backlog: i32) -> i32 {
+    let result = match machine.state.ws2_32.sockets.get_mut(s) {
+        None => Err(WSAENOTSOCK),
+        Some(socket) => {
+            socket.listening = true;
+            socket
+                .host
+                .listen()
+                .map(|_| 0)
+                .map_err(|err| wsa_error(&err))
+        }
+    };
+    finish(machine, result)
+}
+
+#[win32_derive::dllexport(1)]
+pub async fn accept(machine: &mut Machine, s: SOCKET, addr: u32, addrlen: u32) -> SOCKET {
+    let result = blocking(machine, s, |socket, _| {
+        socket.rearm(FD_ACCEPT);
+        socket.host.accept()
+    })
+    .await;
+    let (host, peer) = match result {
+        Ok(accepted) => accepted,
+        Err(err) => {
+            set_error(machine, err);
+            return SOCKET::invalid();
+        }
+    };
+    let mem = machine.emu.memory.mem();
+    let addrlen = (addrlen != 0).then(|| mem.view_mut::<i32>(addrlen));
+    if let Err(err) = write_addr(mem, addr, addrlen, peer) {
+        set_error(machine, err);
+        return SOCKET::invalid();
+    }
+    machine
+        .state
+        .ws2_32
+        .sockets
+        .add(Socket::new(SocketKind::Stream, host))
+}
+
+#[win32_derive::dllexport(4)]
+pub async fn connect(machine: &mut Machine, s: SOCKET, name: u32, namelen: i32) -> i32 {
+    let addr = match read_addr(machine.emu.memory.mem(), name, namelen) {
+        Ok(addr) => addr,
+        Err(err) => return finish(machine, Err(err)),
+    };
+    // The host reports a connection still under way as WouldBlock, which a
+    // nonblocking socket passes on as Windows does; asking again checks on it.
+    let result = blocking(machine, s, |socket, _| socket.host.connect(addr)).await;
+    if let (Err(WSAEWOULDBLOCK), Some(socket)) = (result, machine.state.ws2_32.sockets.get_mut(s)) {
+        socket.connecting = Some(addr);
+    }
+    finish(machine, result.map(|_| 0))
+}
+
+#[win32_derive::dllexport(19)]
+pub async fn send(machine: &mut Machine, s: SOCKET, buf: u32, len: i32, flags: u32) -> i32 {
+    let result = blocking(machine, s, |socket, mem| {
+        let result = socket.host.send(mem.sub(buf, len as u32).as_slice_todo());
+        if matches!(&result, Err(err) if err.kind() == ErrorKind::WouldBlock) {
+            socket.rearm(FD_WRITE);
+        }
+        result
+    })
+    .await;
+    finish(machine, result.map(|n| n as i32))
+}
+
+#[win32_derive::dllexport(20)]
+pub async fn sendto(
+    machine: &mut Machine,
+    s: SOCKET,
+    buf: u32,
+    len: i32,
+    flags: u32,
+    to: u32,
+    tolen: i32,
+) -> i32 {
+    let result = match (to, read_addr(machine.emu.memory.mem(), to, tolen)) {
+        // Without an address, sendto is send.
+        (0, _) => return send(machine, s, buf, len, flags).await,
+        (_, Err(err)) => Err(err),
+        (_, Ok(addr)) => {
+            blocking(machine, s, |socket, mem| {
+                let buf = mem.sub(buf, len as u32).as_slice_todo();
+                let result = socket.host.send_to(buf, addr);
+                if matches!(&result, Err(err) if err.kind() == ErrorKind::WouldBlock) {
+                    socket.rearm(FD_WRITE);
+                }
+                result
+            })
+            .await
+        }
+    };
+    finish(machine, result.map(|n| n as i32))
+}
+
+#[win32_derive::dllexport(16)]
+pub async fn recv(machine: &mut Machine, s: SOCKET, buf: u32, len: i32, flags: u32) -> i32 {
+    let result = blocking(machine, s, |socket, mem| {
+        socket.recv(mem.sub(buf, len as u32).as_mut_slice_todo())
+    })
+    .await;
+    finish(machine, result.map(|n| n as i32))
+}
+
+#[win32_derive::dllexport(17)]
+pub async fn recvfrom(
+    machine: &mut Machine,
+    s: SOCKET,
+    buf: u32,
+    len: i32,
+    flags: u32,
+    from: u32,
+    fromlen: u32,
+) -> i32 {
+    let result = blocking(machine, s, |socket, mem| {
+        let buf = mem.sub(buf, len as u32).as_mut_slice_todo();
+        match socket.kind {
+            SocketKind::Datagram => {
+                socket.rearm(FD_READ);
+                socket.host.recv_from(buf)
+            }
+            // Streams have no per-recv source address; report the peer's.
+            SocketKind::Stream => Ok((socket.recv(buf)?, socket.host.peer_addr()?)),
+        }
+    })
+    .await;
+    let result = result.and_then(|(n, addr)| {
+        let mem = machine.emu.memory.mem();
+        let fromlen = (fromlen != 0).then(|| mem.view_mut::<i32>(fromlen));
+        write_addr(mem, from, fromlen, addr)?;
+        Ok(n as i32)
+    });
+    finish(machine, result)
+}
+
+#[win32_derive::dllexport(22)]
+pub fn shutdown(machine: &mut Machine, s: SOCKET, how: u32) -> i32 {
+    let how = match how {
+        0 => Shutdown::Read,
+        1 => Shutdown::Write,
+        _ => Shutdown::Both,
+    };
+    let result = match machine.state.ws2_32.sockets.get_mut(s) {
+        None => Err(WSAENOTSOCK),
+        Some(socket) => socket
+            .host
+            .shutdown(how)
+            .map(|_| 0)
+            .map_err(|err| wsa_error(&err)),
+    };
+    finish(machine, result)
+}
+
+#[win32_derive::dllexport(12)]
+pub fn ioctlsocket(machine: &mut Machine, s: SOCKET, cmd: u32, argp: Option<&mut u32>) -> i32 {
+    let Some(socket) = machine.state.ws2_32.sockets.get_mut(s) else {
+        return finish(machine, Err(WSAENOTSOCK));
+    };
+    let Some(arg) = argp else {
+        return finish(machine, Err(WSAEFAULT));
+    };
+    match cmd {
+        // Sockets stay nonblocking while they have async notifications.
+        FIONBIO if *arg == 0 && socket.select.is_some() => {
+            return finish(machine, Err(WSAEINVAL));
+        }
+        FIONBIO => socket.nonblocking = *arg != 0,
+        FIONREAD => {
+            if socket.kind == SocketKind::Stream {
+                socket.read_ahead();
+            }
+            // TODO: the size of the next datagram, which we can't see without reading it.
+            *arg = socket.pending.len() as u32;
+        }
+        _ => {
+            log::warn!("ioctlsocket: unsupported command {cmd:x}");
+            return finish(machine, Err(WSAEINVAL));
+        }
+    }
+    0
+}
+
+#[win32_derive::dllexport(21)]
+pub fn setsockopt(
+    machine: &mut Machine,
+    s: SOCKET,
+    level: u32,
+    optname: u32,
+    optval: u32,
+    optlen: i32,
+) -> i32 {
+    if machine.state.ws2_32.sockets.get(s).is_none() {
+        return finish(machine, Err(WSAENOTSOCK));
+    }
+    // Options like SO_REUSEADDR, SO_BROADCAST and TCP_NODELAY tune the host
+    // socket's behavior in ways we can do without.
+    log::info!("setsockopt({level:x}, {optname:x}): ignored");
+    0
+}
+
+#[win32_derive::dllexport(7)]
+pub fn getsockopt(
+    machine: &mut Machine,
+    s: SOCKET,
+    level: u32,
+    optname: u32,
+    optval: u32,
+    optlen: Option<&mut i32>,
+) -> i32 {
+    let Some(socket) = machine.state.ws2_32.sockets.get(s) else {
+        return finish(machine, Err(WSAENOTSOCK));
+    };
+    let value = match (level, optname) {
+        (SOL_SOCKET, SO_TYPE) => match socket.kind {
+            SocketKind::Stream => SOCK_STREAM,
+            SocketKind::Datagram => SOCK_DGRAM,
+        },
+        (SOL_SOCKET, SO_ERROR) => 0,
+        _ => {
+            log::warn!("getsockopt({level:x}, {optname:x}): unsupported");
+            return finish(machine, Err(WSAEINVAL));
+        }
+    };
+    match optlen {
+        Some(len) if *len >= 4 && optval != 0 => {
+            machine.mem().put::<u32>(optval, value);
+            *len = 4;
+            0
+        }
+        _ => finish(machine, Err(WSAEFAULT)),
+    }
+}
+
+#[win32_derive::dllexport(6)]
+pub fn getsockname(machine: &mut Machine, s: SOCKET, name: u32, namelen: Option<&mut i32>) -> i32 {
+    let mem = machine.emu.memory.mem();
+    let result = machine
+        .state
+        .ws2_32
+        .sockets
+        .get(s)
+        .ok_or(WSAENOTSOCK)
+        .and_then(|socket| socket.host.local_addr().map_err(|err| wsa_error(&err)))
+        .and_then(|addr| write_addr(mem, name, namelen, addr));
+    finish(machine, result.map(|_| 0))
+}
+
+#[win32_derive::dllexport(5)]
+pub fn getpeername(machine: &mut Machine, s: SOCKET, name: u32, namelen: Option<&mut i32>) -> i32 {
+    let mem = machine.emu.memory.mem();
+    let result = machine
+        .state
+        .ws2_32
+        .sockets
+        .get(s)
+        .ok_or(WSAENOTSOCK)
+        .and_then(|socket| socket.host.peer_addr().map_err(|err| wsa_error(&err)))
+        .and_then(|addr| write_addr(mem, name, namelen, addr));
+    finish(machine, result.map(|_| 0))
+}
+
+/// Maximum number of sockets in an fd_set, FD_SETSIZE.
+const FD_SETSIZE: u32 = 64;
+
+/// Read the sockets in an fd_set: a count, then that many SOCKETs.
+fn read_fd_set(mem: Mem, set: u32) -> Vec<SOCKET> {
+    if set == 0 {
+        return Vec::new();
+    }
+    let count = std::cmp::min(mem.get_pod::<u32>(set), FD_SETSIZE);
+    (0..count)
+        .map(|i| SOCKET::from_raw(mem.get_pod::<u32>(set + 4 + i * 4)))
+        .collect()
+}
+
+fn write_fd_set(mem: Mem, set: u32, sockets: &[SOCKET]) {
+    if set == 0 {
+        return;
+    }
+    mem.put::<u32>(set, sockets.len() as u32);
+    for (i, s) in sockets.iter().enumerate() {
+        mem.put::<u32>(set + 4 + i as u32 * 4, s.to_raw());
+    }
+}
+
+#[win32_derive::dllexport(18)]
+pub async fn select(
+    machine: &mut Machine,
+    nfds: i32,
+    readfds: u32,
+    writefds: u32,
+    exceptfds: u32,
+    timeout: u32,
+) -> i32 {
+    let mem = machine.emu.memory.mem();
+    let (read, write) = (read_fd_set(mem, readfds), read_fd_set(mem, writefds));
+    // A null timeout waits forever; otherwise it's a timeval of seconds and microseconds.
+    let until = (timeout != 0).then(|| {
+        let ms = mem.get_pod::<u32>(timeout) * 1000 + mem.get_pod::<u32>(timeout + 4) / 1000;
+        machine.host.time() + ms
+    });
+    loop {
+        let sockets = &mut machine.state.ws2_32.sockets;
+        if read
+            .iter()
+            .chain(write.iter())
+            .any(|&s| sockets.get(s).is_none())
+        {
+            return finish(machine, Err(WSAENOTSOCK));
+        }
+        let ready_read = read
+            .iter()
+            .copied()
+            .filter(|&s| sockets.get_mut(s).unwrap().readable())
+            .collect::<Vec<_>>();
+        let ready_write = write
+            .iter()
+            .copied()
+            .filter(|&s| sockets.get_mut(s).unwrap().host.writable())
+            .collect::<Vec<_>>();
+        let count = ready_read.len() + ready_write.len();
+        if count > 0 || until.map_or(false, |until| machine.host.time() >= until) {
+            let mem = machine.emu.memory.mem();
+            write_fd_set(mem, readfds, &ready_read);
+            write_fd_set(mem, writefds, &ready_write);
+            // Errors surface through the calls that hit them instead.
+            write_fd_set(mem, exceptfds, &[]);
+            return count as i32;
+        }
+        wait(machine).await;
+    }
+}
+
+#[win32_derive::dllexport(151)]
+pub fn __WSAFDIsSet(machine: &mut Machine, s: SOCKET, set: u32) -> bool {
+    read_fd_set(machine.mem(), set).contains(&s)
+}
+
+#[win32_derive::dllexport(8)]
+pub fn htonl(_machine: &mut Machine, hostlong: u32) -> u32 {
+    hostlong.to_be()
+}
+
+#[win32_derive::dllexport(9)]
+pub fn htons(_machine: &mut Machine, hostshort: u32) -> u32 {
+    (hostshort as u16).to_be() as u32
+}
+
+#[win32_derive::dllexport(14)]
+pub fn ntohl(_machine: &mut Machine, netlong: u32) -> u32 {
+    u32::from_be(netlong)
+}
+
+#[win32_derive::dllexport(15)]
+pub fn ntohs(_machine: &mut Machine, netshort: u32) -> u32 {
+    u16::from_be(netshort as u16) as u32
+}
+
+#[win32_derive::dllexport(10)]
+pub fn inet_addr(_machine: &mut Machine, cp: Option<&str>) -> u32 {
+    const INADDR_NONE: u32 = !0;
+    match cp.and_then(|cp| cp.trim().parse::<Ipv4Addr>().ok()) {
+        // The address in network byte order, as it sits in memory.
+        Some(addr) => u32::from_le_bytes(addr.octets()),
+        None => INADDR_NONE,
+    }
+}
+
+#[win32_derive::dllexport(11)]
+pub fn inet_ntoa(machine: &mut Machine, in_: u32) -> u32 {
+    let text = Ipv4Addr::from(in_.to_le_bytes()).to_string();
+    let buf = machine.state.ws2_32.ntoa;
+    if buf == 0 {
+        return 0;
+    }
+    let out = machine.mem().sub(buf, 16).as_mut_slice_todo();
+    out[..text.len()].copy_from_slice(text.as_bytes());
+    out[text.len()] = 0;
+    buf
+}
+
+/// Room for a hostent, its name, and a handful of addresses.
+const HOSTENT_SIZE: u32 = 0x200;
+const MAX_HOST_ADDRS: usize = 8;
+
+#[win32_derive::dllexport(52)]
+pub fn gethostbyname(machine: &mut Machine, name: Option<&str>) -> u32 {
+    let Some(name) = name else {
+        set_error(machine, WSAEFAULT);
+        return 0;
+    };
+    let hostent = machine.state.ws2_32.hostent;
+    if hostent == 0 {
+        set_error(machine, WSANOTINITIALISED);
+        return 0;
+    }
+    let addrs = match name.parse::<Ipv4Addr>() {
+        Ok(addr) => vec![addr],
+        Err(_) => machine.host.resolve(name).unwrap_or_default(),
+    };
+    if addrs.is_empty() {
+        set_error(machine, WSAHOST_NOT_FOUND);
+        return 0;
+    }
+    let name = &name.as_bytes()[..std::cmp::min(name.len(), 0xFF)];
+    let addrs = &addrs[..std::cmp::min(addrs.len(), MAX_HOST_ADDRS)];
+
+    // Laid out as the hostent, then the null-terminated alias and address
+    // pointer lists, the addresses, and the name.
+    let aliases = hostent + 16;
+    let addr_list = aliases + 4;
+    let addr_data = addr_list + (MAX_HOST_ADDRS as u32 + 1) * 4;
+    let name_data = addr_data + MAX_HOST_ADDRS as u32 * 4;
+    let mem = machine.mem();
+    mem.put::<u32>(hostent, name_data);
+    mem.put::<u32>(hostent + 4, aliases);
+    mem.put::<u16>(hostent + 8, AF_INET);
+    mem.put::<u16>(hostent + 10, 4);
+    mem.put::<u32>(hostent + 12, addr_list);
+    mem.put::<u32>(aliases, 0);
+    for (i, addr) in addrs.iter().enumerate() {
+        let data = addr_data + i as u32 * 4;
+        mem.put::<[u8; 4]>(data, addr.octets());
+        mem.put::<u32>(addr_list + i as u32 * 4, data);
+    }
+    mem.put::<u32>(addr_list + addrs.len() as u32 * 4, 0);
+    let out = mem
+        .sub(name_data, name.len() as u32 + 1)
+        .as_mut_slice_todo();
+    out[..name.len()].copy_from_slice(name);
+    out[name.len()] = 0;
+    hostent
+}
+
+#[win32_derive::dllexport(57)]
+pub fn gethostname(machine: &mut Machine, name: u32, namelen: i32) -> i32 {
+    let host = machine.state.kernel32.computer_name.to_ascii_lowercase();
+    let host = host.as_bytes();
+    if name == 0 || namelen <= host.len() as i32 {
+        return finish(machine, Err(WSAEFAULT));
+    }
+    let out = machine
+        .mem()
+        .sub(name, host.len() as u32 + 1)
+        .as_mut_slice_todo();
+    out[..host.len()].copy_from_slice(host);
+    out[host.len()] = 0;
+    0
+}
+
+#[win32_derive::dllexport(101)]
+pub fn WSAAsyncSelect(machine: &mut Machine, s: SOCKET, hWnd: HWND, wMsg: u32, lEvent: u32) -> i32 {
+    let Some(socket) = machine.state.ws2_32.sockets.get_mut(s) else {
+        return finish(machine, Err(WSAENOTSOCK));
+    };
+    socket.nonblocking = true;
+    // Zero events cancels notifications, though the socket stays nonblocking.
+    socket.select = (lEvent != 0).then_some(AsyncSelect {
+        hwnd: hWnd,
+        msg: wMsg,
+        events: lEvent,
+        armed: lEvent,
+    });
+    0
+}
+
+/// The events that have occurred on a socket, with the error code each carries.
+fn socket_events(socket: &mut Socket) -> Vec<(u32, u32)> {
+    let mut events = Vec::new();
+    if let Some(addr) = socket.connecting {
+        if !socket.host.writable() {
+            return events;
+        }
+        socket.connecting = None;
+        let err = match socket.host.connect(addr) {
+            Ok(()) => 0,
+            Err(err) => wsa_error(&err),
+        };
+        events.push((FD_CONNECT, err));
+        if err != 0 {
+            return events;
+        }
+    }
+    match socket.kind {
+        SocketKind::Stream if socket.listening => {
+            if socket.host.readable() {
+                events.push((FD_ACCEPT, 0));
+            }
+            return events;
+        }
+        // Not connected yet.
+        SocketKind::Stream if socket.host.peer_addr().is_err() && socket.closed.is_none() => {
+            return events;
+        }
+        SocketKind::Stream => {
+            socket.read_ahead();
+            if !socket.pending.is_empty() {
+                events.push((FD_READ, 0));
+            } else if let Some(err) = socket.closed {
+                events.push((FD_CLOSE, err));
+            }
+        }
+        SocketKind::Datagram => {
+            if socket.host.readable() {
+                events.push((FD_READ, 0));
+            }
+        }
+    }
+    if socket.closed.is_none() && socket.host.writable() {
+        events.push((FD_WRITE, 0));
+    }
+    events
+}
+
+/// Post WSAAsyncSelect notifications for the events that have occurred.
+/// Returns the host time by which it should be called again, if any socket is
+/// waiting on events.
+pub fn update(machine: &mut Machine) -> Option<u32> {
+    let mut posts = Vec::new();
+    for (s, socket) in machine.state.ws2_32.sockets.iter_mut() {
+        if socket
+            .select
+            .as_ref()
+            .map_or(true, |select| select.armed == 0)
+        {
+            continue;
+        }
+        for (event, err) in socket_events(socket) {
+            let select = socket.select.as_mut().unwrap();
+            if select.events & select.armed & event == 0 {
+                continue;
+            }
+            select.armed &= !event;
+            // WSAMAKESELECTREPLY.
+            posts.push((select.hwnd, select.msg, s.to_raw(), err << 16 | event));
+        }
+    }
+    for (hwnd, msg, wParam, lParam) in posts {
+        user32::post_message(machine, hwnd, msg, wParam, lParam);
+    }
+    let waiting = machine.state.ws2_32.sockets.iter().any(|socket| {
+        socket
+            .select
+            .as_ref()
+            .is_some_and(|select| select.armed != 0)
+    });
+    waiting.then(|| machine.host.time() + POLL_MS)
+}
@@ -0,0 +1,81 @@
+//! Code coverage: which guest code was executed, for loading into reverse engineering
+//! tools.  Coverage is exported in the drcov format produced by DynamoRIO, which the
+//! lighthouse plugins for IDA and Binary Ninja (and its ports to Ghidra) understand.
+//!
+//! Coverage is recorded per basic block, but only for the instructions of the block
+//! that actually ran, so it is exact at instruction granularity.
+
+use std::collections::HashMap;
+
+/// A loaded image that coverage can be attributed to.
+pub struct Module {
+    pub path: String,
+    pub base: u32,
+    pub size: u32,
+}
+
+#[derive(Default)]
+pub struct Coverage {
+    /// Block start address => count of bytes of the block that executed.
+    blocks: HashMap<u32, u32>,
+}
+
+impl Coverage {
+    /// Record that the len bytes of code starting at addr executed.
+    #[inline]
+    pub fn record(&mut self, addr: u32, len: u32) {
+        let entry = self.blocks.entry(addr).or_default();
+        if len > *entry {
+            *entry = len;
+        }
+    }
+
+    /// Executed (address, length) spans, sorted by address.
+    pub fn blocks(&self) -> Vec<(u32, u32)> {
+        let mut blocks: Vec<_> = self.blocks.iter().map(|(&a, &l)| (a, l)).collect();
+        blocks.sort();
+        blocks
+    }
+
+    /// Render in drcov format.  Blocks outside all modules (like shims, or code
+    /// generated at runtime) can't be represented and are left out.
+    pub fn drcov(&self, modules: &[Module]) -> Vec<u8> {
+        let mut table = Vec::new();
+        for (addr, len) in self.blocks() {
+            let Some(id) = modules
+                .iter()
+                .position(|m| addr >= m.base && addr - m.base < m.size)
+            else {
+                continue;
+            };
+            table.push((addr - modules[id].base, len, id));
+        }
+
+        let mut out = String::new();
+        out.push_str("DRCOV VERSION: 2\n");
+        out.push_str("DRCOV FLAVOR: retrowin32\n");
+        out.push_str(&format!(
+            "Module Table: version 2, count {}\n",
+            modules.len()
+        ));
+        out.push_str("Columns: id, base, end, entry, checksum, timestamp, path\n");
+        for (id, m) in modules.iter().enumerate() {
+            out.push_str(&format!(
+                "{id:3}, 0x{:08x}, 0x{:08x}, 0x0000000000000000, 0x00000000, 0x00000000, {}\n",
+                m.base,
+                m.base as u64 + m.size as u64,
+                m.path
+            ));
+        }
+        out.push_str(&format!("BB Table: {} bbs\n", table.len()));
+
+        let mut out = out.into_bytes();
+        for (offset, len, id) in table {
+            // Each entry is struct { u32 start; u16 size; u16 mod_id; }.
+            out.extend_from_slice(&offset.to_le_bytes());
+            out.extend_from_slice(&(std::cmp::min(len, 0xFFFF) as u16).to_le_bytes());
+            out.extend_from_slice(&(id as u16).to_le_bytes());
+        }
+        out
+    }
+}
@@ -0,0 +1,69 @@
+//! Approximate cycle counting, and holding execution to a clock rate with it.
+//!
+//! Old games often time themselves by how fast the processor runs, in busy-wait
+//! loops or by tying physics to frame rate, and run far too fast on a modern host.
+//! Costs here are rough Pentium figures: enough to make a throttled program run
+//! at about the right speed, not to time anything exactly.
+
+use iced_x86::{Instruction, Mnemonic, OpKind};
+
+/// Estimated cycles an instruction takes, or for rep-prefixed string instructions,
+/// its setup; each iteration costs another cycle.
+pub fn cost(instr: &Instruction) -> u32 {
+    let base = match instr.mnemonic() {
+        Mnemonic::Mul | Mnemonic::Imul => 10,
+        Mnemonic::Div | Mnemonic::Idiv => 40,
+        Mnemonic::Call | Mnemonic::Ret | Mnemonic::Leave => 2,
+        Mnemonic::Enter => 11,
+        Mnemonic::Loop | Mnemonic::Loope | Mnemonic::Loopne => 5,
+        Mnemonic::Cpuid => 14,
+        Mnemonic::Rdtsc => 20,
+        Mnemonic::Fdiv | Mnemonic::Fdivp | Mnemonic::Fdivr | Mnemonic::Fdivrp => 39,
+        Mnemonic::Fidiv | Mnemonic::Fidivr => 42,
+        Mnemonic::Fsqrt => 70,
+        Mnemonic::Fsin | Mnemonic::Fcos | Mnemonic::Fsincos | Mnemonic::Fptan => 100,
+        Mnemonic::Fpatan | Mnemonic::Fyl2x | Mnemonic::F2xm1 | Mnemonic::Fscale => 50,
+        _ if instr.is_string_instruction() => 3,
+        _ if instr.op_count() > 0 && instr.op0_register().is_st() => 3,
+        _ => 1,
+    };
+    let memory = (0..instr.op_count()).any(|i| instr.op_kind(i) == OpKind::Memory)
+        && instr.mnemonic() != Mnemonic::Lea;
+    base + memory as u32
+}
+
+/// Holds execution to a clock rate, by blocking whenever more cycles have run than
+/// the host time since the limit started allows.
+pub struct SpeedLimit {
+    /// Emulated clock frequency.
+    pub mhz: u32,
+    /// Host time and cycle count that the budget is measured from.
+    start: Option<(u32, u64)>,
+}
+
+/// How far execution may fall behind the clock, in ms, before the shortfall is
+/// written off rather than made up in a burst; e.g. after waiting for input.
+const MAX_LAG_MS: u64 = 50;
+
+impl SpeedLimit {
+    pub fn new(mhz: u32) -> Self {
+        SpeedLimit { mhz, start: None }
+    }
+
+    /// Given the cycles run so far and the host time in ms, returns the host time
+    /// to wait until if execution is ahead of the clock.
+    pub fn wait(&mut self, cycles: u64, now: u32) -> Option<u32> {
+        let per_ms = self.mhz as u64 * 1000;
+        let (start_time, start_cycles) = *self.start.get_or_insert((now, cycles));
+        let allowed = now.wrapping_sub(start_time) as u64 * per_ms;
+        let ran = cycles - start_cycles;
+        if ran + MAX_LAG_MS * per_ms < allowed {
+            self.start = Some((now, cycles));
+            return None;
+        }
+        if ran <= allowed {
+            return None;
+        }
+        Some(start_time.wrapping_add(ran.div_ceil(per_ms) as u32))
+    }
+}
@@ -0,0 +1,1009 @@
+//! 80-bit extended precision floats, as the x87 computes with.
+//!
+//! Arithmetic is done in software on the significand as an integer, so that
+//! results are rounded once, to the precision and in the direction the FPU
+//! control word asks for, and raise the same exceptions the hardware does.
+//! Transcendental functions are the exception: they're computed with f64, which
+//! is short of the x87's precision but within what code can tell apart.
+
+use bitflags::bitflags;
+use std::cmp::Ordering;
+
+bitflags! {
+    /// Floating point exceptions, as they appear in the x87 status and control words.
+    #[derive(serde::Serialize, serde::Deserialize)]
+    pub struct Exceptions: u16 {
+        const INVALID = 1 << 0;
+        const DENORMAL = 1 << 1;
+        const ZERO_DIVIDE = 1 << 2;
+        const OVERFLOW = 1 << 3;
+        const UNDERFLOW = 1 << 4;
+        const PRECISION = 1 << 5;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    Nearest,
+    Down,
+    Up,
+    Zero,
+}
+
+/// The rounding an operation runs under and the exceptions it raised.
+pub struct Env {
+    pub rounding: Rounding,
+    /// Significand bits results of basic arithmetic are rounded to: 24, 53 or 64.
+    pub precision: u32,
+    pub raised: Exceptions,
+}
+
+/// The exponent range of a format's normal numbers, unbiased.
+struct Range {
+    min: i32,
+    max: i32,
+}
+
+const EXTENDED: Range = Range {
+    min: -16382,
+    max: 16383,
+};
+const DOUBLE: Range = Range {
+    min: -1022,
+    max: 1023,
+};
+const SINGLE: Range = Range {
+    min: -126,
+    max: 127,
+};
+
+const BIAS: i32 = 16383;
+const INT_BIT: u64 = 1 << 63;
+const QUIET_BIT: u64 = 1 << 62;
+
+#[derive(Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub struct F80 {
+    /// The significand, with the integer bit explicit as bit 63.
+    pub mant: u64,
+    /// Sign (bit 15) and biased exponent.
+    pub se: u16,
+}
+
+impl std::fmt::Debug for F80 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.to_f64_lossy())
+    }
+}
+
+/// An F80 taken apart.  Finite values are normalized, including denormals,
+/// with the value mant * 2^(exp - 63).
+#[derive(Clone, Copy)]
+enum Kind {
+    Zero,
+    Finite {
+        exp: i32,
+        mant: u64,
+    },
+    Inf,
+    NaN {
+        quiet: bool,
+    },
+    /// Encodings the 387 and later reject: unnormals, pseudo-infinities
+    /// and pseudo-NaNs, all of which raise invalid.
+    Unsupported,
+}
+
+/// Shift right, keeping whether any 1 bits were shifted out in the low bit.
+fn shift_right_sticky(sig: u128, n: u32) -> u128 {
+    if n == 0 {
+        sig
+    } else if n >= 128 {
+        (sig != 0) as u128
+    } else {
+        (sig >> n) | ((sig & ((1 << n) - 1) != 0) as u128)
+    }
+}
+
+impl F80 {
+    /// The "real indefinite" QNaN that invalid operations produce.
+    pub const INDEFINITE: F80 = F80 {
+        mant: INT_BIT | QUIET_BIT,
+        se: 0xFFFF,
+    };
+    pub const ZERO: F80 = F80 { mant: 0, se: 0 };
+    pub const ONE: F80 = F80 {
+        mant: INT_BIT,
+        se: BIAS as u16,
+    };
+
+    fn sign(&self) -> bool {
+        self.se >> 15 != 0
+    }
+
+    fn zero(sign: bool) -> F80 {
+        F80 {
+            mant: 0,
+            se: (sign as u16) << 15,
+        }
+    }
+
+    fn infinity(sign: bool) -> F80 {
+        F80 {
+            mant: INT_BIT,
+            se: (sign as u16) << 15 | 0x7FFF,
+        }
+    }
+
+    fn kind(&self) -> Kind {
+        let biased = (self.se & 0x7FFF) as i32;
+        let mant = self.mant;
+        match biased {
+            0x7FFF if mant & INT_BIT == 0 => Kind::Unsupported,
+            0x7FFF if mant == INT_BIT => Kind::Inf,
+            0x7FFF => Kind::NaN {
+                quiet: mant & QUIET_BIT != 0,
+            },
+            0 if mant == 0 => Kind::Zero,
+            0 => {
+                // Denormal: the exponent is that of the smallest normal.
+                let lz = mant.leading_zeros();
+                Kind::Finite {
+                    exp: EXTENDED.min - lz as i32,
+                    mant: mant << lz,
+                }
+            }
+            _ if mant & INT_BIT == 0 => Kind::Unsupported,
+            _ => Kind::Finite {
+                exp: biased - BIAS,
+                mant,
+            },
+        }
+    }
+
+    pub fn is_nan(&self) -> bool {
+        matches!(self.kind(), Kind::NaN { .. } | Kind::Unsupported)
+    }
+
+    fn is_denormal(&self) -> bool {
+        self.se & 0x7FFF == 0 && self.mant != 0
+    }
+
+    /// Pack a value mant * 2^(exp - 63) that's known to be representable.
+    fn pack(sign: bool, mut exp: i32, mut mant: u64) -> F80 {
+        if mant == 0 {
+            return F80::zero(sign);
+        }
+        let lz = mant.leading_zeros();
+        mant <<= lz;
+        exp -= lz as i32;
+        let mut biased = exp + BIAS;
+        if biased <= 0 {
+            mant >>= 1 - biased;
+            biased = 0;
+        }
+        F80 {
+            mant,
+            se: (sign as u16) << 15 | biased as u16,
+        }
+    }
+
+    pub fn from_i64(x: i64) -> F80 {
+        F80::pack(x < 0, 63, x.unsigned_abs())
+    }
+
+    /// Convert exactly, keeping signaling NaNs signaling.
+    pub fn from_f64(x: f64) -> F80 {
+        let bits = x.to_bits();
+        let sign = bits >> 63 != 0;
+        let exp = ((bits >> 52) & 0x7FF) as i32;
+        let frac = bits & ((1 << 52) - 1);
+        match exp {
+            0x7FF => F80 {
+                mant: INT_BIT | frac << 11,
+                se: (sign as u16) << 15 | 0x7FFF,
+            },
+            0 => F80::pack(sign, DOUBLE.min, frac << 11),
+            _ => F80::pack(sign, exp - 1023, INT_BIT | frac << 11),
+        }
+    }
+
+    /// Convert exactly, keeping signaling NaNs signaling.
+    pub fn from_f32(x: f32) -> F80 {
+        let bits = x.to_bits();
+        let sign = bits >> 31 != 0;
+        let exp = ((bits >> 23) & 0xFF) as i32;
+        let frac = (bits & ((1 << 23) - 1)) as u64;
+        match exp {
+            0xFF => F80 {
+                mant: INT_BIT | frac << 40,
+                se: (sign as u16) << 15 | 0x7FFF,
+            },
+            0 => F80::pack(sign, SINGLE.min, frac << 40),
+            _ => F80::pack(sign, exp - 127, INT_BIT | frac << 40),
+        }
+    }
+
+    /// Load an f64 as FLD does, which raises denormal for denormal values and
+    /// invalid for signaling NaNs, which it quiets.
+    pub fn load_f64(x: f64, env: &mut Env) -> F80 {
+        if x.is_subnormal() {
+            env.raised |= Exceptions::DENORMAL;
+        }
+        F80::from_f64(x).quieted(env)
+    }
+
+    /// Load an f32 as FLD does; see load_f64.
+    pub fn load_f32(x: f32, env: &mut Env) -> F80 {
+        if x.is_subnormal() {
+            env.raised |= Exceptions::DENORMAL;
+        }
+        F80::from_f32(x).quieted(env)
+    }
+
+    /// Load an 80-bit value as FLD does, which doesn't raise anything.
+    pub fn from_bytes(bytes: [u8; 10]) -> F80 {
+        F80 {
+            mant: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            se: u16::from_le_bytes([bytes[8], bytes[9]]),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; 10] {
+        let mut bytes = [0; 10];
+        bytes[..8].copy_from_slice(&self.mant.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.se.to_le_bytes());
+        bytes
+    }
+
+    fn quieted(self, env: &mut Env) -> F80 {
+        match self.kind() {
+            Kind::NaN { quiet: false } => {
+                env.raised |= Exceptions::INVALID;
+                F80 {
+                    mant: self.mant | QUIET_BIT,
+                    se: self.se,
+                }
+            }
+            _ => self,
+        }
+    }
+
+    /// The result of an operation on a NaN: invalid if either is signaling, and
+    /// otherwise the NaN with the larger significand, quieted.
+    fn propagate_nan(self, other: F80, env: &mut Env) -> F80 {
+        let (ka, kb) = (self.kind(), other.kind());
+        let signaling = |k: Kind| matches!(k, Kind::NaN { quiet: false } | Kind::Unsupported);
+        if signaling(ka) || signaling(kb) {
+            env.raised |= Exceptions::INVALID;
+        }
+        if matches!(ka, Kind::Unsupported) || matches!(kb, Kind::Unsupported) {
+            return F80::INDEFINITE;
+        }
+        let nan = match (ka, kb) {
+            (Kind::NaN { .. }, Kind::NaN { .. }) => {
+                if (other.mant | QUIET_BIT) > (self.mant | QUIET_BIT) {
+                    other
+                } else {
+                    self
+                }
+            }
+            (Kind::NaN { .. }, _) => self,
+            _ => other,
+        };
+        F80 {
+            mant: nan.mant | QUIET_BIT,
+            se: nan.se,
+        }
+    }
+
+    fn invalid(env: &mut Env) -> F80 {
+        env.raised |= Exceptions::INVALID;
+        F80::INDEFINITE
+    }
+
+    fn check_denormal(&self, env: &mut Env) {
+        if self.is_denormal() {
+            env.raised |= Exceptions::DENORMAL;
+        }
+    }
+
+    /// Round a value sig * 2^(exp - 127) to a precision and exponent range.
+    /// Any bits lost before this must be or'd into sig's low bit.
+    fn round_pack(
+        env: &mut Env,
+        sign: bool,
+        mut exp: i32,
+        mut sig: u128,
+        precision: u32,
+        range: &Range,
+    ) -> F80 {
+        if sig == 0 {
+            return F80::zero(sign);
+        }
+        let lz = sig.leading_zeros();
+        sig <<= lz;
+        exp -= lz as i32;
+        let tiny = exp < range.min;
+        if tiny {
+            sig = shift_right_sticky(sig, (range.min - exp) as u32);
+            exp = range.min;
+        }
+
+        let dropped = 128 - precision;
+        let rest = sig & ((1 << dropped) - 1);
+        let half = 1 << (dropped - 1);
+        let mut kept = sig >> dropped;
+        let up = match env.rounding {
+            Rounding::Nearest => rest > half || (rest == half && kept & 1 != 0),
+            Rounding::Down => sign && rest != 0,
+            Rounding::Up => !sign && rest != 0,
+            Rounding::Zero => false,
+        };
+        if up {
+            kept += 1;
+            if kept >> precision != 0 {
+                kept >>= 1;
+                exp += 1;
+            }
+        }
+        if rest != 0 {
+            env.raised |= Exceptions::PRECISION;
+            if tiny {
+                env.raised |= Exceptions::UNDERFLOW;
+            }
+        }
+
+        if exp > range.max {
+            env.raised |= Exceptions::OVERFLOW | Exceptions::PRECISION;
+            let to_infinity = match env.rounding {
+                Rounding::Nearest => true,
+                Rounding::Down => sign,
+                Rounding::Up => !sign,
+                Rounding::Zero => false,
+            };
+            if to_infinity {
+                return F80::infinity(sign);
+            }
+            let largest = !0u64 << (64 - precision);
+            return F80::pack(sign, range.max, largest);
+        }
+        // The top of the kept bits weighs 2^exp.
+        F80::pack(sign, exp, (kept as u64) << (64 - precision))
+    }
+
+    /// Round to the precision control's precision, as basic arithmetic results are.
+    fn round(env: &mut Env, sign: bool, exp: i32, sig: u128) -> F80 {
+        let precision = env.precision;
+        F80::round_pack(env, sign, exp, sig, precision, &EXTENDED)
+    }
+
+    pub fn neg(self) -> F80 {
+        F80 {
+            mant: self.mant,
+            se: self.se ^ 0x8000,
+        }
+    }
+
+    pub fn abs(self) -> F80 {
+        F80 {
+            mant: self.mant,
+            se: self.se & 0x7FFF,
+        }
+    }
+
+    fn add_signed(self, other: F80, negate: bool, env: &mut Env) -> F80 {
+        self.check_denormal(env);
+        other.check_denormal(env);
+        let sa = self.sign();
+        let sb = other.sign() ^ negate;
+        match (self.kind(), other.kind()) {
+            (Kind::NaN { .. } | Kind::Unsupported, _)
+            | (_, Kind::NaN { .. } | Kind::Unsupported) => self.propagate_nan(other, env),
+            (Kind::Inf, Kind::Inf) if sa != sb => F80::invalid(env),
+            (Kind::Inf, _) => F80::infinity(sa),
+            (_, Kind::Inf) => F80::infinity(sb),
+            (Kind::Zero, Kind::Zero) => F80::zero(if sa == sb {
+                sa
+            } else {
+                env.rounding == Rounding::Down
+            }),
+            (Kind::Zero, Kind::Finite { exp, mant }) => {
+                F80::round(env, sb, exp, (mant as u128) << 64)
+            }
+            (Kind::Finite { exp, mant }, Kind::Zero) => {
+                F80::round(env, sa, exp, (mant as u128) << 64)
+            }
+            (Kind::Finite { exp: ea, mant: ma }, Kind::Finite { exp: eb, mant: mb }) => {
+                // Line up the smaller exponent's significand, keeping 62 guard bits.
+                let ((sa, ea, ma), (sb, eb, mb)) = if ea >= eb {
+                    ((sa, ea, ma), (sb, eb, mb))
+                } else {
+                    ((sb, eb, mb), (sa, ea, ma))
+                };
+                let siga = (ma as u128) << 62;
+                let sigb = shift_right_sticky((mb as u128) << 62, (ea - eb) as u32);
+                let (sign, sig) = if sa == sb {
+                    (sa, siga + sigb)
+                } else if siga >= sigb {
+                    (sa, siga - sigb)
+                } else {
+                    (sb, sigb - siga)
+                };
+                if sig == 0 {
+                    return F80::zero(env.rounding == Rounding::Down);
+                }
+                F80::round(env, sign, ea + 2, sig)
+            }
+        }
+    }
+
+    pub fn add(self, other: F80, env: &mut Env) -> F80 {
+        self.add_signed(other, false, env)
+    }
+
+    pub fn sub(self, other: F80, env: &mut Env) -> F80 {
+        self.add_signed(other, true, env)
+    }
+
+    pub fn mul(self, other: F80, env: &mut Env) -> F80 {
+        self.check_denormal(env);
+        other.check_denormal(env);
+        let sign = self.sign() ^ other.sign();
+        match (self.kind(), other.kind()) {
+            (Kind::NaN { .. } | Kind::Unsupported, _)
+            | (_, Kind::NaN { .. } | Kind::Unsupported) => self.propagate_nan(other, env),
+            (Kind::Inf, Kind::Zero) | (Kind::Zero, Kind::Inf) => F80::invalid(env),
+            (Kind::Inf, _) | (_, Kind::Inf) => F80::infinity(sign),
+            (Kind::Zero, _) | (_, Kind::Zero) => F80::zero(sign),
+            (Kind::Finite { exp: ea, mant: ma }, Kind::Finite { exp: eb, mant: mb }) => {
+                F80::round(env, sign, ea + eb + 1, ma as u128 * mb as u128)
+            }
+        }
+    }
+
+    pub fn div(self, other: F80, env: &mut Env) -> F80 {
+        self.check_denormal(env);
+        other.check_denormal(env);
+        let sign = self.sign() ^ other.sign();
+        match (self.kind(), other.kind()) {
+            (Kind::NaN { .. } | Kind::Unsupported, _)
+            | (_, Kind::NaN { .. } | Kind::Unsupported) => self.propagate_nan(other, env),
+            (Kind::Inf, Kind::Inf) | (Kind::Zero, Kind::Zero) => F80::invalid(env),
+            (Kind::Inf, _) => F80::infinity(sign),
+            (_, Kind::Inf) => F80::zero(sign),
+            (Kind::Zero, _) => F80::zero(sign),
+            (_, Kind::Zero) => {
+                env.raised |= Exceptions::ZERO_DIVIDE;
+                F80::infinity(sign)
+            }
+            (Kind::Finite { exp: ea, mant: ma }, Kind::Finite { exp: eb, mant: mb }) => {
+                // The first division gives only 64 bits of quotient when ma < mb,
+                // so divide the remainder for 62 more, enough to round with.
+                let num = (ma as u128) << 64;
+                let (q, r) = (num / mb as u128, num % mb as u128);
+                let (q2, r2) = ((r << 62) / mb as u128, (r << 62) % mb as u128);
+                let sig = (q << 62) | q2 | (r2 != 0) as u128;
+                F80::round(env, sign, ea - eb + 1, sig)
+            }
+        }
+    }
+
+    pub fn sqrt(self, env: &mut Env) -> F80 {
+        self.check_denormal(env);
+        match self.kind() {
+            Kind::NaN { .. } | Kind::Unsupported => self.propagate_nan(self, env),
+            Kind::Zero => self,
+            _ if self.sign() => F80::invalid(env),
+            Kind::Inf => self,
+            Kind::Finite { exp, mant } => {
+                // Take the root of mant * 2^k for an even exponent, a bit pair at
+                // a time, for 66 bits of root: two more than needed for rounding.
+                let e = exp - 63;
+                let (radicand, k) = if e % 2 == 0 {
+                    ((mant as u128) << 64, 68)
+                } else {
+                    ((mant as u128) << 63, 67)
+                };
+                let (mut root, mut rem) = (0u128, 0u128);
+                for i in 0..66 {
+                    let pair = if i < 64 {
+                        (radicand >> (126 - 2 * i)) & 3
+                    } else {
+                        0
+                    };
+                    rem = (rem << 2) | pair;
+                    let trial = (root << 2) | 1;
+                    if rem >= trial {
+                        rem -= trial;
+                        root = (root << 1) | 1;
+                    } else {
+                        root <<= 1;
+                    }
+                }
+                let sig = (root << 1) | (rem != 0) as u128;
+                F80::round(env, false, (e - k) / 2 + 126, sig)
+            }
+        }
+    }
+
+    /// Partial remainder, as FPREM (truncating quotient) or FPREM1 (nearest).
+    /// Returns the remainder, the low three bits of the quotient, and whether the
+    /// reduction is complete; if not, it reduced the exponent by up to 63.
+    pub fn rem(self, other: F80, nearest: bool, env: &mut Env) -> (F80, u64, bool) {
+        self.check_denormal(env);
+        other.check_denormal(env);
+        let sign = self.sign();
+        match (self.kind(), other.kind()) {
+            (Kind::NaN { .. } | Kind::Unsupported, _)
+            | (_, Kind::NaN { .. } | Kind::Unsupported) => {
+                (self.propagate_nan(other, env), 0, true)
+            }
+            (Kind::Inf, _) | (_, Kind::Zero) => (F80::invalid(env), 0, true),
+            (Kind::Zero, _) | (_, Kind::Inf) => (self, 0, true),
+            (Kind::Finite { exp: ex, mant: mx }, Kind::Finite { exp: ey, mant: my }) => {
+                let d = ex - ey;
+                if d < -1 || (d == -1 && !nearest) {
+                    return (self, 0, true);
+                }
+                // num / den is |x| / |y|, scaled so remainders weigh 2^base.
+                let (num, den, base, complete) = match d {
+                    -1 => (mx as u128, (my as u128) << 1, ey - 64, true),
+                    0..=63 => ((mx as u128) << d, my as u128, ey - 63, true),
+                    _ => ((mx as u128) << 63, my as u128, ex - 126, false),
+                };
+                let mut q = num / den;
+                let mut r = num % den;
+                let mut sign = sign;
+                if nearest && complete && (2 * r > den || (2 * r == den && q & 1 != 0)) {
+                    r = den - r;
+                    q += 1;
+                    sign = !sign;
+                }
+                let result = F80::round_pack(env, sign, base + 127, r, 64, &EXTENDED);
+                let q = if complete { (q & 7) as u64 } else { 0 };
+                (result, q, complete)
+            }
+        }
+    }
+
+    /// The magnitude rounded to an integer under the rounding mode, and whether
+    /// that was inexact, for finite values less than 2^63.
+    fn round_magnitude(sign: bool, exp: i32, mant: u64, rounding: Rounding) -> (u128, bool) {
+        let shift = 63 - exp;
+        let (int, cmp_half, inexact) = if shift > 64 {
+            (0u128, Ordering::Less, true)
+        } else {
+            let shift = shift as u32;
+            let full = mant as u128;
+            let rest = full & ((1 << shift) - 1);
+            let half = 1u128 << (shift - 1);
+            (full >> shift, rest.cmp(&half), rest != 0)
+        };
+        let up = match rounding {
+            Rounding::Nearest => {
+                cmp_half == Ordering::Greater || (cmp_half == Ordering::Equal && int & 1 != 0)
+            }
+            Rounding::Down => sign && inexact,
+            Rounding::Up => !sign && inexact,
+            Rounding::Zero => false,
+        };
+        (int + up as u128, inexact)
+    }
+
+    /// Round to an integral value, as FRNDINT.
+    pub fn round_int(self, env: &mut Env) -> F80 {
+        self.check_denormal(env);
+        match self.kind() {
+            Kind::NaN { .. } | Kind::Unsupported => self.propagate_nan(self, env),
+            Kind::Zero | Kind::Inf => self,
+            Kind::Finite { exp, .. } if exp >= 63 => self,
+            Kind::Finite { exp, mant } => {
+                let sign = self.sign();
+                let (int, inexact) = F80::round_magnitude(sign, exp, mant, env.rounding);
+                if inexact {
+                    env.raised |= Exceptions::PRECISION;
+                }
+                F80::round_pack(env, sign, 127, int, 64, &EXTENDED)
+            }
+        }
+    }
+
+    /// Convert to an integer of the given width, as FIST does, or None (invalid)
+    /// if it's out of range and the integer indefinite should be stored.
+    pub fn to_int(self, bits: u32, env: &mut Env) -> Option<i64> {
+        let sign = self.sign();
+        let (exp, mant) = match self.kind() {
+            Kind::Zero => return Some(0),
+            Kind::Finite { exp, mant } => (exp, mant),
+            _ => {
+                env.raised |= Exceptions::INVALID;
+                return None;
+            }
+        };
+        let (int, inexact) = if exp >= 63 {
+            if exp > 63 {
+                env.raised |= Exceptions::INVALID;
+                return None;
+            }
+            (mant as u128, false)
+        } else {
+            F80::round_magnitude(sign, exp, mant, env.rounding)
+        };
+        let limit = 1u128 << (bits - 1);
+        if int > limit || (int == limit && !sign) {
+            env.raised |= Exceptions::INVALID;
+            return None;
+        }
+        if inexact {
+            env.raised |= Exceptions::PRECISION;
+        }
+        let int = int as i128;
+        Some(if sign { -int } else { int } as i64)
+    }
+
+    /// Multiply by 2 to the power of another value's integer part, as FSCALE.
+    pub fn scale(self, other: F80, env: &mut Env) -> F80 {
+        self.check_denormal(env);
+        other.check_denormal(env);
+        match (self.kind(), other.kind()) {
+            (Kind::NaN { .. } | Kind::Unsupported, _)
+            | (_, Kind::NaN { .. } | Kind::Unsupported) => self.propagate_nan(other, env),
+            (Kind::Zero, Kind::Inf) if !other.sign() => F80::invalid(env),
+            (Kind::Inf, Kind::Inf) if other.sign() => F80::invalid(env),
+            (Kind::Zero | Kind::Inf, _) => self,
+            (Kind::Finite { .. }, Kind::Inf) if other.sign() => F80::zero(self.sign()),
+            (Kind::Finite { .. }, Kind::Inf) => F80::infinity(self.sign()),
+            (Kind::Finite { .. }, Kind::Zero) => self,
+            (
+                Kind::Finite { exp, mant },
+                Kind::Finite {
+                    exp: n_exp,
+                    mant: n_mant,
+                },
+            ) => {
+                // Scales past the exponent range all over- or underflow alike.
+                let n = if n_exp >= 16 {
+                    1 << 16
+                } else if n_exp < 0 {
+                    0
+                } else {
+                    (n_mant >> (63 - n_exp)) as i32
+                };
+                let n = if other.sign() { -n } else { n };
+                F80::round_pack(
+                    env,
+                    self.sign(),
+                    exp + n,
+                    (mant as u128) << 64,
+                    64,
+                    &EXTENDED,
+                )
+            }
+        }
+    }
+
+    /// Compare, raising invalid for any NaN unless quiet, in which case only for
+    /// signaling NaNs (FUCOM vs FCOM).  None if unordered.
+    pub fn compare(self, other: F80, quiet: bool, env: &mut Env) -> Option<Ordering> {
+        self.check_denormal(env);
+        other.check_denormal(env);
+        let (ka, kb) = (self.kind(), other.kind());
+        let nan = |k: Kind| matches!(k, Kind::NaN { .. } | Kind::Unsupported);
+        if nan(ka) || nan(kb) {
+            let signaling = |k: Kind| matches!(k, Kind::NaN { quiet: false } | Kind::Unsupported);
+            if !quiet || signaling(ka) || signaling(kb) {
+                env.raised |= Exceptions::INVALID;
+            }
+            return None;
+        }
+        // Order magnitudes by class, then exponent and significand.
+        let magnitude = |k: Kind| match k {
+            Kind::Zero => (0, 0, 0),
+            Kind::Finite { exp, mant } => (1, exp, mant),
+            _ => (2, 0, 0),
+        };
+        let (ma, mb) = (magnitude(ka), magnitude(kb));
+        if ma.0 == 0 && mb.0 == 0 {
+            return Some(Ordering::Equal);
+        }
+        Some(match (self.sign(), other.sign()) {
+            (false, false) => ma.cmp(&mb),
+            (true, true) => mb.cmp(&ma),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        })
+    }
+
+    /// Round to a float format, encoding it with the given fraction width.
+    fn to_format(self, env: &mut Env, precision: u32, range: &Range, exp_bits: u32) -> u64 {
+        let frac_bits = precision - 1;
+        let sign = (self.sign() as u64) << (frac_bits + exp_bits);
+        let max_exp = (1u64 << exp_bits) - 1;
+        let bias = range.max;
+        match self.kind() {
+            Kind::Zero => sign,
+            Kind::Inf => sign | max_exp << frac_bits,
+            Kind::NaN { .. } | Kind::Unsupported => {
+                let nan = self.propagate_nan(self, env);
+                let payload = (nan.mant & !INT_BIT) >> (64 - precision);
+                ((nan.sign() as u64) << (frac_bits + exp_bits)) | max_exp << frac_bits | payload
+            }
+            Kind::Finite { exp, mant } => {
+                let rounded = F80::round_pack(
+                    env,
+                    self.sign(),
+                    exp,
+                    (mant as u128) << 64,
+                    precision,
+                    range,
+                );
+                match rounded.kind() {
+                    Kind::Zero => sign,
+                    Kind::Inf => sign | max_exp << frac_bits,
+                    Kind::Finite { exp, mant } if exp >= range.min => {
+                        let frac = (mant & !INT_BIT) >> (64 - precision);
+                        sign | ((exp + bias) as u64) << frac_bits | frac
+                    }
+                    Kind::Finite { exp, mant } => {
+                        sign | mant >> (64 - precision + (range.min - exp) as u32)
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+
+    /// Round to an f64, as storing it does.
+    pub fn to_f64(self, env: &mut Env) -> f64 {
+        f64::from_bits(self.to_format(env, 53, &DOUBLE, 11))
+    }
+
+    /// Round to an f32, as storing it does.
+    pub fn to_f32(self, env: &mut Env) -> f32 {
+        f32::from_bits(self.to_format(env, 24, &SINGLE, 8) as u32)
+    }
+
+    /// The nearest f64, for display and for computing what isn't done in software.
+    pub fn to_f64_lossy(self) -> f64 {
+        let mut env = Env {
+            rounding: Rounding::Nearest,
+            precision: 64,
+            raised: Exceptions::empty(),
+        };
+        self.to_f64(&mut env)
+    }
+
+    /// A constant of the x87 ROM, given to 128 bits, rounded to 64 as FLDPI etc. do.
+    pub fn constant(sig: u128, exp: i32, rounding: Rounding) -> F80 {
+        let mut env = Env {
+            rounding,
+            precision: 64,
+            raised: Exceptions::empty(),
+        };
+        F80::round_pack(&mut env, false, exp, sig, 64, &EXTENDED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_for(rounding: Rounding, precision: u32) -> Env {
+        Env {
+            rounding,
+            precision,
+            raised: Exceptions::empty(),
+        }
+    }
+
+    fn bits(x: F80) -> (u16, u64) {
+        (x.se, x.mant)
+    }
+
+    fn f(x: f64) -> F80 {
+        F80::from_f64(x)
+    }
+
+    fn third(rounding: Rounding, precision: u32, sign: bool) -> (F80, Exceptions) {
+        let mut env = env_for(rounding, precision);
+        let one = if sign { F80::ONE.neg() } else { F80::ONE };
+        let x = one.div(f(3.0), &mut env);
+        (x, env.raised)
+    }
+
+    #[test]
+    fn rounding_modes() {
+        // 1/3 is 0.AAAA... in binary; the bits past 64 are more than half.
+        let (x, raised) = third(Rounding::Nearest, 64, false);
+        assert_eq!(bits(x), (0x3FFD, 0xAAAA_AAAA_AAAA_AAAB));
+        assert_eq!(raised, Exceptions::PRECISION);
+        let (x, _) = third(Rounding::Zero, 64, false);
+        assert_eq!(bits(x), (0x3FFD, 0xAAAA_AAAA_AAAA_AAAA));
+        let (x, _) = third(Rounding::Down, 64, false);
+        assert_eq!(bits(x), (0x3FFD, 0xAAAA_AAAA_AAAA_AAAA));
+        let (x, _) = third(Rounding::Up, 64, false);
+        assert_eq!(bits(x), (0x3FFD, 0xAAAA_AAAA_AAAA_AAAB));
+
+        // Directed rounding of negatives goes the other way in magnitude.
+        let (x, _) = third(Rounding::Down, 64, true);
+        assert_eq!(bits(x), (0xBFFD, 0xAAAA_AAAA_AAAA_AAAB));
+        let (x, _) = third(Rounding::Up, 64, true);
+        assert_eq!(bits(x), (0xBFFD, 0xAAAA_AAAA_AAAA_AAAA));
+    }
+
+    #[test]
+    fn round_to_even() {
+        let mut env = env_for(Rounding::Nearest, 64);
+        // 1 + 2^-64 is exactly halfway between 1 and the next value up; 1 is even.
+        let half_ulp = F80::pack(false, -64, INT_BIT);
+        let x = F80::ONE.add(half_ulp, &mut env);
+        assert_eq!(bits(x), bits(F80::ONE));
+        // (1 + 2^-63) + 2^-64 is halfway too, and rounds up to the even neighbor.
+        let odd = F80 {
+            mant: INT_BIT | 1,
+            se: BIAS as u16,
+        };
+        let x = odd.add(half_ulp, &mut env);
+        assert_eq!(bits(x), (BIAS as u16, INT_BIT | 2));
+    }
+
+    #[test]
+    fn precision_control() {
+        let (x, _) = third(Rounding::Nearest, 53, false);
+        assert_eq!(bits(x), (0x3FFD, 0xAAAA_AAAA_AAAA_A800));
+        assert_eq!(x.to_f64_lossy(), 1.0 / 3.0);
+        let (x, _) = third(Rounding::Nearest, 24, false);
+        assert_eq!(bits(x), (0x3FFD, 0xAAAA_AB00_0000_0000));
+        assert_eq!(x.to_f32(&mut env_for(Rounding::Nearest, 64)), 1.0f32 / 3.0);
+    }
+
+    #[test]
+    fn sqrt() {
+        let mut env = env_for(Rounding::Nearest, 64);
+        let x = f(2.0).sqrt(&mut env);
+        assert_eq!(bits(x), (0x3FFF, 0xB504_F333_F9DE_6484));
+        assert_eq!(env.raised, Exceptions::PRECISION);
+
+        let mut env = env_for(Rounding::Nearest, 64);
+        let x = f(2.25).sqrt(&mut env);
+        assert_eq!(x.to_f64_lossy(), 1.5);
+        assert_eq!(env.raised, Exceptions::empty());
+
+        let x = f(-1.0).sqrt(&mut env);
+        assert_eq!(bits(x), bits(F80::INDEFINITE));
+        assert_eq!(env.raised, Exceptions::INVALID);
+    }
+
+    #[test]
+    fn denormals() {
+        let min = f64::from_bits(1); // 2^-1074
+        let mut env = env_for(Rounding::Nearest, 64);
+        let x = F80::load_f64(min, &mut env);
+        assert_eq!(env.raised, Exceptions::DENORMAL);
+        // It's normal as an F80, and goes back exactly.
+        env.raised = Exceptions::empty();
+        assert_eq!(x.to_f64(&mut env), min);
+        assert_eq!(env.raised, Exceptions::empty());
+
+        // Half of it is a tie between 0 and min, going to the even 0.
+        let half = x.mul(f(0.5), &mut env);
+        assert_eq!(half.to_f64(&mut env), 0.0);
+        assert_eq!(env.raised, Exceptions::UNDERFLOW | Exceptions::PRECISION);
+        // Three quarters rounds up to min.
+        let three_quarters = x.mul(f(0.75), &mut env);
+        assert_eq!(three_quarters.to_f64(&mut env), min);
+
+        // F80 denormals raise denormal when operated on.
+        let mut env = env_for(Rounding::Nearest, 64);
+        let tiny = F80 { mant: 1, se: 0 };
+        tiny.add(F80::ONE, &mut env);
+        assert_eq!(env.raised, Exceptions::DENORMAL | Exceptions::PRECISION);
+    }
+
+    #[test]
+    fn nan_propagation() {
+        let qnan = F80 {
+            mant: INT_BIT | QUIET_BIT | 5,
+            se: 0x7FFF,
+        };
+        let snan = F80 {
+            mant: INT_BIT | 7,
+            se: 0x7FFF,
+        };
+
+        let mut env = env_for(Rounding::Nearest, 64);
+        let x = qnan.add(F80::ONE, &mut env);
+        assert_eq!(bits(x), bits(qnan));
+        assert_eq!(env.raised, Exceptions::empty());
+
+        // Signaling NaNs raise invalid and come out quieted.
+        let x = F80::ONE.mul(snan, &mut env);
+        assert_eq!(bits(x), (0x7FFF, INT_BIT | QUIET_BIT | 7));
+        assert_eq!(env.raised, Exceptions::INVALID);
+
+        // Of two NaNs, the larger significand wins.
+        let mut env = env_for(Rounding::Nearest, 64);
+        let x = qnan.sub(snan, &mut env);
+        assert_eq!(bits(x), (0x7FFF, INT_BIT | QUIET_BIT | 7));
+
+        // Comparisons are unordered; only FCOM (not FUCOM) raises invalid for QNaNs.
+        let mut env = env_for(Rounding::Nearest, 64);
+        assert_eq!(qnan.compare(F80::ONE, true, &mut env), None);
+        assert_eq!(env.raised, Exceptions::empty());
+        assert_eq!(qnan.compare(F80::ONE, false, &mut env), None);
+        assert_eq!(env.raised, Exceptions::INVALID);
+    }
+
+    #[test]
+    fn exceptions() {
+        let mut env = env_for(Rounding::Nearest, 64);
+        let x = F80::ONE.div(F80::ZERO, &mut env);
+        assert_eq!(x.to_f64_lossy(), f64::INFINITY);
+        assert_eq!(env.raised, Exceptions::ZERO_DIVIDE);
+
+        let mut env = env_for(Rounding::Nearest, 64);
+        let x = F80::ZERO.div(F80::ZERO, &mut env);
+        assert_eq!(bits(x), bits(F80::INDEFINITE));
+        assert_eq!(env.raised, Exceptions::INVALID);
+
+        let mut env = env_for(Rounding::Nearest, 64);
+        let inf = f(f64::INFINITY);
+        assert_eq!(bits(inf.sub(inf, &mut env)), bits(F80::INDEFINITE));
+        assert_eq!(env.raised, Exceptions::INVALID);
+
+        // Overflow goes to infinity, or under truncation to the largest finite value.
+        let max = F80 {
+            mant: !0,
+            se: 0x7FFE,
+        };
+        let mut env = env_for(Rounding::Nearest, 64);
+        let x = max.mul(f(2.0), &mut env);
+        assert_eq!(bits(x), bits(F80::infinity(false)));
+        assert_eq!(env.raised, Exceptions::OVERFLOW | Exceptions::PRECISION);
+        let mut env = env_for(Rounding::Zero, 64);
+        let x = max.mul(f(2.0), &mut env);
+        assert_eq!(bits(x), bits(max));
+
+        // Storing to f64 overflows at the narrower range.
+        let mut env = env_for(Rounding::Nearest, 64);
+        assert_eq!(max.to_f64(&mut env), f64::INFINITY);
+        assert_eq!(env.raised, Exceptions::OVERFLOW | Exceptions::PRECISION);
+    }
+
+    #[test]
+    fn to_int() {
+        let int = |x: f64, rounding: Rounding| {
+            let mut env = env_for(rounding, 64);
+            (f(x).to_int(32, &mut env), env.raised)
+        };
+        assert_eq!(
+            int(2.5, Rounding::Nearest),
+            (Some(2), Exceptions::PRECISION)
+        );
+        assert_eq!(int(3.5, Rounding::Nearest).0, Some(4));
+        assert_eq!(int(-2.5, Rounding::Down).0, Some(-3));
+        assert_eq!(int(-2.5, Rounding::Up).0, Some(-2));
+        assert_eq!(int(2.9, Rounding::Zero).0, Some(2));
+        assert_eq!(int(7.0, Rounding::Nearest), (Some(7), Exceptions::empty()));
+        assert_eq!(int(-2147483648.0, Rounding::Nearest).0, Some(-2147483648));
+        assert_eq!(
+            int(2147483648.0, Rounding::Nearest),
+            (None, Exceptions::INVALID)
+        );
+    }
+
+    #[test]
+    fn rem() {
+        let mut env = env_for(Rounding::Nearest, 64);
+        // FPREM truncates the quotient: 5.5 = 2 * 2 + 1.5.
+        let (r, q, complete) = f(5.5).rem(f(2.0), false, &mut env);
+        assert_eq!((r.to_f64_lossy(), q, complete), (1.5, 2, true));
+        // FPREM1 rounds it: 5.5 = 3 * 2 - 0.5.
+        let (r, q, complete) = f(5.5).rem(f(2.0), true, &mut env);
+        assert_eq!((r.to_f64_lossy(), q, complete), (-0.5, 3, true));
+        // Exponents too far apart take more than one step.
+        let (_, _, complete) = f(1e30).rem(f(3.0), false, &mut env);
+        assert!(!complete);
+        assert_eq!(env.raised, Exceptions::empty());
+    }
+}
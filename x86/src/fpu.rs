@@ -1,56 +1,119 @@
 //! FPU registers.
 
+use crate::f80::{Env, Exceptions, Rounding, F80};
 use bitflags::bitflags;
 
 bitflags! {
     #[derive(serde::Serialize, serde::Deserialize)]
     pub struct Status: u16 {
+        const B = 1 << 15;
         const C3 = 1 << 14;
         const C2 = 1 << 10;
         const C1 = 1 << 9;
         const C0 = 1 << 8;
+        /// Exception summary: some exception is raised that isn't masked.
+        const ES = 1 << 7;
+        /// Stack fault: the invalid exception was a stack over/underflow.
+        const SF = 1 << 6;
+        const PE = 1 << 5;
+        const UE = 1 << 4;
+        const OE = 1 << 3;
+        const ZE = 1 << 2;
+        const DE = 1 << 1;
+        const IE = 1 << 0;
     }
 }
 
+/// The control word after FINIT: all exceptions masked, 64-bit precision,
+/// round to nearest.
+pub const CONTROL_DEFAULT: u16 = 0x037F;
+
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct FPU {
     /// FPU ST0 through ST7 registers.
-    pub st: [f64; 8],
+    pub st: [F80; 8],
     /// Index of top of FPU stack; 8 when stack empty.
     pub st_top: usize,
-    /// FPU status word (TODO fold st_top in here?)
+    /// FPU status word, less the stack top, which status_word() adds.
     pub status: Status,
+    /// FPU control word: exception masks, precision and rounding control.
+    pub control: u16,
 }
 
 impl Default for FPU {
     fn default() -> Self {
         Self {
-            st: [0.; 8],
+            st: [F80::ZERO; 8],
             st_top: 8,
             status: Status::empty(),
+            control: CONTROL_DEFAULT,
         }
     }
 }
 
 impl FPU {
-    fn exception(_msg: &str) {
-        // TODO: modify state bits etc.
-        // At least ignoring these may allow programs to make some progress.
-        // See note in https://github.com/joncampbell123/dosbox-x/issues/94 ,
-        // "I've seen DOSBox SVN bail out on perfectly good demoscene programs because
-        // of [not allowing underflow]."
-        // Don't log because anatyda underflows thousands of times, eek.
-        // log::warn!("{}", msg);
+    /// The status word as FNSTSW stores it, with the stack top in bits 11-13.
+    pub fn status_word(&self) -> u16 {
+        self.status.bits() | ((self.st_top as u16 & 7) << 11)
+    }
+
+    pub fn rounding(&self) -> Rounding {
+        match (self.control >> 10) & 3 {
+            0 => Rounding::Nearest,
+            1 => Rounding::Down,
+            2 => Rounding::Up,
+            _ => Rounding::Zero,
+        }
+    }
+
+    fn precision(&self) -> u32 {
+        match (self.control >> 8) & 3 {
+            0 => 24,
+            2 => 53,
+            _ => 64,
+        }
+    }
+
+    /// Record exceptions in the status word.
+    /// TODO: unmasked exceptions should fault; for now they get the masked response.
+    pub fn raise(&mut self, exceptions: Exceptions) {
+        self.status |= Status::from_bits_truncate(exceptions.bits());
+        if exceptions.bits() & !self.control & 0x3F != 0 {
+            self.status |= Status::ES | Status::B;
+        }
+    }
+
+    /// Run an operation under the control word's rounding and precision,
+    /// recording the exceptions it raises.
+    pub fn calc<T>(&mut self, op: impl FnOnce(&mut Env) -> T) -> T {
+        let mut env = Env {
+            rounding: self.rounding(),
+            precision: self.precision(),
+            raised: Exceptions::empty(),
+        };
+        let result = op(&mut env);
+        self.raise(env.raised);
+        result
+    }
+
+    fn stack_fault(&mut self, overflow: bool) {
+        self.raise(Exceptions::INVALID);
+        self.status |= Status::SF;
+        self.status.set(Status::C1, overflow);
     }
 
     /// Get st(0), the current top of the FPU stack.
-    pub fn st0(&mut self) -> &mut f64 {
-        &mut self.st[self.st_top]
+    pub fn st0(&mut self) -> F80 {
+        self.get(iced_x86::Register::ST0)
     }
 
-    pub fn push(&mut self, val: f64) {
+    pub fn set_st0(&mut self, val: F80) {
+        self.set(iced_x86::Register::ST0, val);
+    }
+
+    pub fn push(&mut self, val: F80) {
         if self.st_top == 0 {
-            Self::exception("fpu stack overflow");
+            self.stack_fault(true);
             return;
         }
         self.st_top -= 1;
@@ -59,14 +122,33 @@ impl FPU {
 
     pub fn pop(&mut self) {
         if self.st_top == 8 {
-            Self::exception("fpu stack underflow");
+            self.stack_fault(false);
             return;
         }
         self.st_top += 1;
     }
 
-    /// Index in self.st for a given ST0, ST1 etc reg.
-    fn st_offset(&self, reg: iced_x86::Register) -> usize {
+    pub fn push_f64(&mut self, val: f64) {
+        self.push(F80::from_f64(val));
+    }
+
+    /// Pop st(0) as the nearest f64; an empty stack underflows, giving a NaN.
+    pub fn pop_f64(&mut self) -> f64 {
+        let val = self.st0().to_f64_lossy();
+        self.pop();
+        val
+    }
+
+    /// The stack from st(0) down, for display.
+    pub fn stack_f64(&self) -> Vec<f64> {
+        self.st[self.st_top..]
+            .iter()
+            .map(|f| f.to_f64_lossy())
+            .collect()
+    }
+
+    /// Index in self.st for a given ST0, ST1 etc reg, or None if it's empty.
+    fn st_offset(&self, reg: iced_x86::Register) -> Option<usize> {
         let ofs = match reg {
             iced_x86::Register::ST0 => 0,
             iced_x86::Register::ST1 => 1,
@@ -80,19 +162,38 @@ impl FPU {
         };
         let new = self.st_top + ofs;
         if new >= 8 {
-            Self::exception("fpu stack underflow");
-            return 7;
+            return None;
         }
-        new
+        Some(new)
+    }
+
+    /// Whether a register is empty, as FXAM reports.
+    pub fn is_empty(&self, reg: iced_x86::Register) -> bool {
+        self.st_offset(reg).is_none()
     }
 
     pub fn swap(&mut self, r1: iced_x86::Register, r2: iced_x86::Register) {
-        let o1 = self.st_offset(r1);
-        let o2 = self.st_offset(r2);
-        self.st.swap(o1, o2);
+        match (self.st_offset(r1), self.st_offset(r2)) {
+            (Some(o1), Some(o2)) => self.st.swap(o1, o2),
+            _ => self.stack_fault(false),
+        }
     }
 
-    pub fn get(&mut self, reg: iced_x86::Register) -> &mut f64 {
-        &mut self.st[self.st_offset(reg)]
+    /// Read a register; an empty one underflows, giving the indefinite NaN.
+    pub fn get(&mut self, reg: iced_x86::Register) -> F80 {
+        match self.st_offset(reg) {
+            Some(ofs) => self.st[ofs],
+            None => {
+                self.stack_fault(false);
+                F80::INDEFINITE
+            }
+        }
+    }
+
+    pub fn set(&mut self, reg: iced_x86::Register, val: F80) {
+        match self.st_offset(reg) {
+            Some(ofs) => self.st[ofs] = val,
+            None => self.stack_fault(false),
+        }
     }
 }
@@ -7,19 +7,107 @@
 //! any affected basic block into smaller pieces to maintain the invariant of
 //! always executing through a basic block's end.
 //!
+//! Blocks are kept by start address for as long as the code they were decoded
+//! from is unchanged; whatever changes code must invalidate() it.  The x86 code
+//! itself can also rewrite code (e.g. a packer unpacking over itself), so each op
+//! notes whether it writes memory, and X86 invalidates what the write covered.
+//!
 //! Some good notes on how to make this kind of thing perform well:
 //! http://www.emulators.com/docs/nx25_nostradamus.htm
 
 use memory::{Extensions, Mem};
-use std::collections::HashMap;
-
-const CACHE_LINES: usize = 2 << 10;
+use std::collections::{HashMap, HashSet};
 
 pub struct Op {
     pub instr: iced_x86::Instruction,
     /// The function that implements instr.  Cached here to avoid looking it up;
     /// this was worth about 10% performance in a quick test.
     pub op: crate::ops::Op,
+    pub writes: Writes,
+    /// Estimated cost, see cycles::cost.
+    pub cycles: u32,
+}
+
+/// How an instruction writes memory, so that writes to code can be noticed.
+/// Stack writes from push/call are ignored, on the theory that code doesn't live there.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Writes {
+    Nothing,
+    /// Writes its memory operand, at CPU::last_addr.
+    Operand,
+    /// A string instruction writing from EDI on, like stos or movs.
+    String,
+}
+
+impl Writes {
+    fn classify(
+        factory: &mut iced_x86::InstructionInfoFactory,
+        instr: &iced_x86::Instruction,
+    ) -> Self {
+        let info = factory.info(instr);
+        let mut writes = Writes::Nothing;
+        for i in 0..instr.op_count() {
+            let written = matches!(
+                info.op_access(i),
+                iced_x86::OpAccess::Write
+                    | iced_x86::OpAccess::CondWrite
+                    | iced_x86::OpAccess::ReadWrite
+                    | iced_x86::OpAccess::ReadCondWrite
+            );
+            if !written {
+                continue;
+            }
+            match instr.op_kind(i) {
+                iced_x86::OpKind::Memory => writes = Writes::Operand,
+                iced_x86::OpKind::MemoryESDI
+                | iced_x86::OpKind::MemoryESEDI
+                | iced_x86::OpKind::MemoryESRDI => writes = Writes::String,
+                _ => {}
+            }
+        }
+        writes
+    }
+}
+
+impl Op {
+    /// The memory range the op just wrote, if any; `edi` is EDI from before it ran.
+    pub fn written(&self, cpu: &crate::CPU, edi: u32) -> Option<(u32, u32)> {
+        let size = self.instr.memory_size().size() as u32;
+        match self.writes {
+            Writes::Nothing => None,
+            Writes::Operand => Some((cpu.last_addr, size.max(1))),
+            Writes::String if cpu.regs.code16 => {
+                // DI is an offset into ES; see ops::string16.
+                let es = cpu.regs.seg_base(iced_x86::Register::ES);
+                let di = cpu.regs.get16(iced_x86::Register::DI);
+                string_range(es + (edi & 0xFFFF), es + di as u32, size)
+            }
+            Writes::String => string_range(edi, cpu.regs.get32(iced_x86::Register::EDI), size),
+        }
+    }
+}
+
+/// The memory a string instruction went over, given its ESI or EDI from before
+/// and after it ran and its element size.
+pub(crate) fn string_range(before: u32, now: u32, size: u32) -> Option<(u32, u32)> {
+    if now == before {
+        // A rep with ECX=0.
+        None
+    } else if now > before {
+        Some((before, now - before))
+    } else {
+        // Direction flag set: the accesses run down from before.
+        Some((now.wrapping_add(size), before - now))
+    }
+}
+
+/// The accessible code starting at ip, for decoding.
+fn code_at(mem: Mem, ip: u32) -> Mem {
+    // Blocks are much shorter than this; it just bounds the check.
+    match mem.accessible_len(ip, 0x1_0000) {
+        0 => Mem::from_slice(&[]),
+        len => mem.sub(ip, len),
+    }
 }
 
 #[derive(Default)]
@@ -27,20 +115,32 @@ pub struct BasicBlock {
     /// Number of x86 instruction bytes covered by this block.
     pub len: u32,
     pub ops: Vec<Op>,
+    /// The base of the 16-bit code segment this was decoded as part of, if it's
+    /// 16-bit code.
+    pub cs16: Option<u32>,
+    #[cfg(feature = "jit")]
+    pub jit: crate::jit::BlockState,
 }
 
 impl BasicBlock {
-    fn decode(buf: Mem, ip: u32, single_step: bool) -> Option<Self> {
+    fn decode(
+        buf: Mem,
+        ip: u32,
+        cs16: Option<u32>,
+        single_step: bool,
+        accurate_flags: bool,
+    ) -> Self {
         let mut ops = Vec::new();
         let mut decoder = iced_x86::Decoder::with_ip(
-            32,
+            if cs16.is_some() { 16 } else { 32 },
             buf.as_slice_todo(),
             ip as u64,
             iced_x86::DecoderOptions::NONE,
         );
+        let mut factory = iced_x86::InstructionInfoFactory::new();
         let mut len = 0;
         while decoder.can_decode() {
-            let instr = decoder.decode();
+            let mut instr = decoder.decode();
             if instr.code() == iced_x86::Code::INVALID {
                 // We can hit invalid instruction when decoding confusing control flows.
                 // For example, this UPX code
@@ -56,51 +156,140 @@ impl BasicBlock {
                     // logic a chance to generate smaller basic blocks that will be able to toggle between
                     // the two interpretations.
                     break;
-                } else {
-                    // Otherwise the caller must repair this.
-                    return None;
+                }
+                // Otherwise we're really executing it, which faults like UD2, or if
+                // we ran out of code to decode, like reading inaccessible memory.
+                let op = match decoder.last_error() {
+                    iced_x86::DecoderError::NoMoreBytes => crate::ops::unmapped_code,
+                    _ => crate::ops::ud2,
+                };
+                ops.push(Op {
+                    op,
+                    instr,
+                    writes: Writes::Nothing,
+                    cycles: 1,
+                });
+                len += instr.len() as u32;
+                break;
+            }
+            if let Some(base) = cs16 {
+                // We decode at the linear address, where the decoder wraps branch
+                // targets to 16 bits as if that were IP.  Make them offsets within
+                // the code segment again, and then linear for the ops to jump to.
+                if instr.op0_kind() == iced_x86::OpKind::NearBranch16 {
+                    let ip = instr.near_branch16().wrapping_sub(base as u16);
+                    instr.set_near_branch32(base.wrapping_add(ip as u32));
                 }
             }
-            let op =
-                crate::ops::decode(&instr).unwrap_or_else(|| todo!("{instr} ({:?})", instr.code()));
-            ops.push(Op { op, instr });
+            let op = if accurate_flags && crate::ops::accurate::affects(&instr) {
+                crate::ops::accurate::op
+            } else if cs16.is_some() && instr.is_string_instruction() {
+                crate::ops::string16
+            } else {
+                crate::ops::decode(&instr).unwrap_or(crate::ops::unimplemented)
+            };
+            let writes = Writes::classify(&mut factory, &instr);
+            ops.push(Op {
+                op,
+                instr,
+                writes,
+                cycles: crate::cycles::cost(&instr),
+            });
             len += instr.len() as u32;
-            if instr.flow_control() != iced_x86::FlowControl::Next || single_step {
+            // RDTSC ends a block so that X86 knows, before running the block, which
+            // instruction count it reads the counter at.
+            if instr.flow_control() != iced_x86::FlowControl::Next
+                || instr.code() == iced_x86::Code::Rdtsc
+                || single_step
+            {
                 break;
             }
         }
-        Some(BasicBlock { ops, len })
+        BasicBlock {
+            ops,
+            len,
+            cs16,
+            #[cfg(feature = "jit")]
+            jit: Default::default(),
+        }
+    }
+
+    /// Whether the block ends with an RDTSC.
+    pub fn reads_tsc(&self) -> bool {
+        matches!(self.ops.last(), Some(op) if op.instr.code() == iced_x86::Code::Rdtsc)
     }
 }
 
+/// Block start addresses are already well spread, so a multiply is enough hashing.
 #[derive(Default)]
-struct CacheLine {
-    ip: u32,
-    block: BasicBlock,
+struct IpHasher(u64);
+
+impl std::hash::Hasher for IpHasher {
+    fn write(&mut self, _bytes: &[u8]) {
+        unreachable!("only u32 keys")
+    }
+
+    fn write_u32(&mut self, ip: u32) {
+        self.0 = (ip as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+type IpMap<V> = HashMap<u32, V, std::hash::BuildHasherDefault<IpHasher>>;
+type IpSet = HashSet<u32, std::hash::BuildHasherDefault<IpHasher>>;
+
+/// Blocks are tracked by the pages their code is in, to discard them when code changes.
+const PAGE_SHIFT: u32 = 12;
+
+/// The most blocks kept at once.  Past this the whole cache is dropped and refilled
+/// with what's still running, which keeps code that's generated over and over (or
+/// long runs through many different paths) from growing the cache without bound.
+const MAX_BLOCKS: usize = 0x1_0000;
+
+/// The pages, as page numbers, that a block's code covers.
+fn block_pages(ip: u32, len: u32) -> std::ops::RangeInclusive<u32> {
+    let last = ip.saturating_add(len.max(1) - 1);
+    (ip >> PAGE_SHIFT)..=(last >> PAGE_SHIFT)
+}
+
+/// Record a block's start in the pages its code covers.
+fn track_pages(pages: &mut IpMap<IpSet>, ip: u32, len: u32) {
+    for page in block_pages(ip, len) {
+        pages.entry(page).or_default().insert(ip);
+    }
+}
+
+/// Undo track_pages, for a block being dropped.
+fn untrack_pages(pages: &mut IpMap<IpSet>, ip: u32, len: u32) {
+    for page in block_pages(ip, len) {
+        if let Some(starts) = pages.get_mut(&page) {
+            starts.remove(&ip);
+            if starts.is_empty() {
+                pages.remove(&page);
+            }
+        }
+    }
 }
 
 /// Cache of decoded instructions as basic blocks.
+#[derive(Default)]
 pub struct InstrCache {
-    lines: Box<[CacheLine; CACHE_LINES]>,
+    /// Decoded blocks, by start address.
+    blocks: IpMap<BasicBlock>,
+    /// For each page of code, the start addresses of blocks covering some of it.
+    pages: IpMap<IpSet>,
     hit: usize,
     miss: usize,
 
     /// Places where we've patched out the instruction with an int3.
     /// The map values are the bytes from before the breakpoint.
     breakpoints: HashMap<u32, u8>,
-}
 
-impl Default for InstrCache {
-    fn default() -> Self {
-        let mut lines = Vec::with_capacity(CACHE_LINES);
-        lines.resize_with(CACHE_LINES, || Default::default());
-        InstrCache {
-            lines: lines.try_into().unwrap_or_else(|_| panic!()),
-            hit: 0,
-            miss: 0,
-            breakpoints: HashMap::new(),
-        }
-    }
+    /// Whether to decode to ops that compute all flags; see ops::accurate.
+    accurate_flags: bool,
 }
 
 impl InstrCache {
@@ -108,42 +297,96 @@ impl InstrCache {
         let total = self.hit + self.miss;
         let percent = if total > 0 { self.hit * 100 / total } else { 0 };
         format!(
-            "{} hit, {} miss, {}% hit rate",
-            self.hit, self.miss, percent
+            "{} hit, {} miss, {}% hit rate, {} blocks",
+            self.hit,
+            self.miss,
+            percent,
+            self.blocks.len()
         )
     }
 
-    /// Remove any cache line that covers ip.
+    /// Choose the flag accuracy of decoded ops, discarding any decoded under the other.
+    pub fn set_accurate_flags(&mut self, accurate: bool) {
+        if accurate != self.accurate_flags {
+            self.blocks.clear();
+            self.pages.clear();
+            self.accurate_flags = accurate;
+        }
+    }
+
+    /// Whether ip holds an int3 patched in by add_breakpoint.
+    pub fn is_breakpoint(&self, ip: u32) -> bool {
+        self.breakpoints.contains_key(&ip)
+    }
+
+    /// Drop the block starting at start, if any.
+    fn remove_block(&mut self, start: u32) {
+        if let Some(block) = self.blocks.remove(&start) {
+            untrack_pages(&mut self.pages, start, block.len);
+        }
+    }
+
+    /// Remove any block that covers ip.
     fn clear_cache(&mut self, ip: u32) {
-        for line in self.lines.iter_mut() {
-            if line.ip <= ip && line.ip + line.block.len > ip {
-                line.ip = 0;
-                return;
+        let Some(starts) = self.pages.get(&(ip >> PAGE_SHIFT)) else {
+            return;
+        };
+        let covering = starts
+            .iter()
+            .copied()
+            .filter(|&start| {
+                matches!(self.blocks.get(&start), Some(block) if start <= ip && ip < start + block.len)
+            })
+            .collect::<Vec<_>>();
+        for start in covering {
+            self.remove_block(start);
+        }
+    }
+
+    /// Discard blocks decoded from memory in the range, because the code there changed.
+    pub fn invalidate(&mut self, addr: u32, len: u32) {
+        if len == 0 {
+            return;
+        }
+        let last = addr.saturating_add(len - 1);
+        for page in (addr >> PAGE_SHIFT)..=(last >> PAGE_SHIFT) {
+            let Some(starts) = self.pages.get(&page) else {
+                continue;
+            };
+            // Blocks sharing the page but not the written bytes stay, so that
+            // data stored next to code doesn't keep discarding it.
+            let stale = starts
+                .iter()
+                .copied()
+                .filter(|&start| match self.blocks.get(&start) {
+                    Some(block) => start <= last && addr < start.wrapping_add(block.len.max(1)),
+                    None => true,
+                })
+                .collect::<Vec<_>>();
+            for start in stale {
+                self.remove_block(start);
             }
         }
     }
 
-    /// Decode the instructions starting at ip and save in self.lines.
-    fn decode_block(&mut self, mem: Mem, ip: u32, single_step: bool) -> &BasicBlock {
-        let block = match BasicBlock::decode(mem.slice(ip..), ip, single_step) {
-            Some(block) => block,
-            None => unreachable!(),
-        };
-        // log::info!("added block {:x}..{:x}", ip, ip + block.len);
-        // if block.len == 1 {
-        //     log::info!(
-        //         "{}",
-        //         block
-        //             .instrs
-        //             .iter()
-        //             .map(|i| i.to_string())
-        //             .collect::<Vec<_>>()
-        //             .join("; ")
-        //     );
-        // }
-        let index = ip as usize % self.lines.len();
-        self.lines[index] = CacheLine { ip, block };
-        &self.lines[index].block
+    /// Decode the instructions starting at ip and save in self.blocks, replacing
+    /// any block already there.  cs16 is the base of the code segment for 16-bit code.
+    fn decode_block(
+        &mut self,
+        mem: Mem,
+        ip: u32,
+        cs16: Option<u32>,
+        single_step: bool,
+    ) -> &mut BasicBlock {
+        self.remove_block(ip);
+        if self.blocks.len() >= MAX_BLOCKS {
+            self.blocks.clear();
+            self.pages.clear();
+        }
+        let block =
+            BasicBlock::decode(code_at(mem, ip), ip, cs16, single_step, self.accurate_flags);
+        track_pages(&mut self.pages, ip, block.len);
+        self.blocks.entry(ip).or_insert(block)
     }
 
     /// Patch in an int3 over the instruction at that addr, backing up the current one.
@@ -160,21 +403,20 @@ impl InstrCache {
         mem.put::<u8>(addr, prev);
     }
 
-    /// Gets basic block starting at a given ip.
-    pub fn get_block<'a>(&'a mut self, mem: Mem, ip: u32) -> &'a BasicBlock {
-        let index = ip as usize % self.lines.len();
-        if self.lines[index].ip == ip {
+    /// Gets basic block starting at a given ip, as 16-bit code in the code segment at
+    /// cs16 if that's given.  A block cached as the other kind of code is decoded afresh.
+    pub fn get_block<'a>(&'a mut self, mem: Mem, ip: u32, cs16: Option<u32>) -> &'a mut BasicBlock {
+        if matches!(self.blocks.get(&ip), Some(block) if block.cs16 == cs16) {
             self.hit += 1;
-            return &self.lines[index].block;
-        } else {
-            self.miss += 1;
-            self.decode_block(mem, ip, false)
+            return self.blocks.get_mut(&ip).unwrap();
         }
+        self.miss += 1;
+        self.decode_block(mem, ip, cs16, false)
     }
 
     /// Change cache such that there's a single basic block at ip.
     /// This means the next get_block() will get a block with a single instruction.
-    pub fn make_single_step(&mut self, mem: Mem, ip: u32) {
-        self.decode_block(mem, ip, true);
+    pub fn make_single_step(&mut self, mem: Mem, ip: u32, cs16: Option<u32>) {
+        self.decode_block(mem, ip, cs16, true);
     }
 }
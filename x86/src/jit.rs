@@ -0,0 +1,372 @@
+//! An optional tier that compiles hot basic blocks to host x86-64 code.
+//!
+//! Only integer instructions between registers are translated.  A block's compiled
+//! code covers its longest translatable prefix, and the interpreter runs the rest,
+//! which includes every instruction that touches memory (and so might fault) or
+//! changes control flow.  Guest registers stay in the Registers array, and each
+//! instruction becomes the same host instruction operating on its slot there, so
+//! the host computes the results and flags the guest would.
+//!
+//! Compiled code doesn't compute AF and PF, so it isn't used under
+//! FlagAccuracy::Accurate.
+//!
+//! Compiled code hangs off the icache's blocks, so it is dropped along with them
+//! when the guest code they came from changes.
+
+#[cfg(not(all(target_arch = "x86_64", unix)))]
+compile_error!("the jit feature needs an x86-64 unix host");
+
+use crate::{registers::Flags, CPU};
+use iced_x86::{Instruction, Mnemonic, OpKind, Register};
+
+/// Executions of a block before it is worth compiling.
+const THRESHOLD: u32 = 50;
+
+/// Compiled prefixes shorter than this cost more to call than to interpret.
+const MIN_OPS: usize = 2;
+
+/// Size of the executable memory compiled code is placed in.
+const ARENA_SIZE: usize = 16 << 20;
+
+/// The flags compiled code computes; the rest are left to the interpreter.
+const FLAGS_MASK: u32 = Flags::CF.bits() | Flags::ZF.bits() | Flags::SF.bits() | Flags::OF.bits();
+
+/// Compiled code takes the guest registers and flags, and returns the new flags.
+type NativeFn = extern "sysv64" fn(regs: *mut u32, flags: u32) -> u32;
+
+/// A block's JIT state, kept in its icache entry.
+pub enum BlockState {
+    /// Not compiled yet, with the count of executions so far.
+    Counting(u32),
+    /// The first `ops` ops of the block are compiled.
+    Compiled { func: NativeFn, ops: usize },
+    /// The block starts with too little we can translate.
+    Untranslatable,
+}
+
+impl Default for BlockState {
+    fn default() -> Self {
+        BlockState::Counting(0)
+    }
+}
+
+/// Executable memory that compiled code is appended to.  Code of discarded blocks
+/// isn't reclaimed; once the arena fills, no more blocks are compiled.
+struct CodeArena {
+    base: *mut u8,
+    used: usize,
+}
+
+impl CodeArena {
+    fn new() -> Self {
+        let base = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                ARENA_SIZE,
+                libc::PROT_READ | libc::PROT_WRITE | libc::PROT_EXEC,
+                libc::MAP_PRIVATE | libc::MAP_ANON,
+                -1,
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            panic!("jit: mmap failed: {}", std::io::Error::last_os_error());
+        }
+        CodeArena {
+            base: base as *mut u8,
+            used: 0,
+        }
+    }
+
+    fn alloc(&mut self, code: &[u8]) -> Option<NativeFn> {
+        if self.used + code.len() > ARENA_SIZE {
+            return None;
+        }
+        unsafe {
+            let dst = self.base.add(self.used);
+            std::ptr::copy_nonoverlapping(code.as_ptr(), dst, code.len());
+            self.used = (self.used + code.len() + 15) & !15;
+            Some(std::mem::transmute::<*mut u8, NativeFn>(dst))
+        }
+    }
+}
+
+impl Drop for CodeArena {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.base as *mut _, ARENA_SIZE);
+        }
+    }
+}
+
+pub struct Jit {
+    arena: CodeArena,
+    full: bool,
+    /// Blocks compiled so far.
+    compiled: usize,
+    /// Instructions run as compiled code.
+    pub instrs: usize,
+}
+
+impl Default for Jit {
+    fn default() -> Self {
+        Jit {
+            arena: CodeArena::new(),
+            full: false,
+            compiled: 0,
+            instrs: 0,
+        }
+    }
+}
+
+impl Jit {
+    pub fn stats(&self) -> String {
+        format!(
+            "{} blocks compiled ({} KB), {} instrs run compiled",
+            self.compiled,
+            self.arena.used >> 10,
+            self.instrs
+        )
+    }
+
+    /// Run the compiled prefix of a block, if it has one, returning how many of its
+    /// ops ran.  Also counts executions towards compiling the block.
+    pub fn run(
+        &mut self,
+        cpu: &mut CPU,
+        state: &mut BlockState,
+        ops: &[crate::icache::Op],
+    ) -> usize {
+        match *state {
+            BlockState::Compiled { func, ops } => {
+                let flags = func(cpu.regs.r32_ptr(), cpu.flags.bits());
+                cpu.flags =
+                    Flags::from_bits_truncate(cpu.flags.bits() & !FLAGS_MASK | flags & FLAGS_MASK);
+                self.instrs += ops;
+                ops
+            }
+            BlockState::Counting(count) if count + 1 < THRESHOLD => {
+                *state = BlockState::Counting(count + 1);
+                0
+            }
+            BlockState::Counting(_) => {
+                *state = self.compile(ops);
+                0
+            }
+            BlockState::Untranslatable => 0,
+        }
+    }
+
+    fn compile(&mut self, ops: &[crate::icache::Op]) -> BlockState {
+        if self.full {
+            return BlockState::Untranslatable;
+        }
+        let mut asm = Assembler::default();
+        asm.prologue();
+        let count = ops.iter().take_while(|op| asm.translate(&op.instr)).count();
+        if count < MIN_OPS {
+            return BlockState::Untranslatable;
+        }
+        asm.epilogue();
+        match self.arena.alloc(&asm.code) {
+            Some(func) => {
+                self.compiled += 1;
+                BlockState::Compiled { func, ops: count }
+            }
+            None => {
+                log::warn!("jit: code arena full, no longer compiling");
+                self.full = true;
+                BlockState::Untranslatable
+            }
+        }
+    }
+}
+
+/// Offset of a guest register's slot in the Registers array, if it's a 32-bit GPR.
+fn slot(reg: Register) -> Option<u8> {
+    reg.is_gpr32()
+        .then(|| (reg as u8 - Register::EAX as u8) * 4)
+}
+
+/// The /digit that selects an ALU operation in the 0x81 (r/m32, imm32) opcode group;
+/// the (r/m32, r32) form of the operation is opcode digit*8+1.
+fn alu_digit(mnemonic: Mnemonic) -> Option<u8> {
+    Some(match mnemonic {
+        Mnemonic::Add => 0,
+        Mnemonic::Or => 1,
+        Mnemonic::Adc => 2,
+        Mnemonic::Sbb => 3,
+        Mnemonic::And => 4,
+        Mnemonic::Sub => 5,
+        Mnemonic::Xor => 6,
+        Mnemonic::Cmp => 7,
+        _ => return None,
+    })
+}
+
+/// Emits host code, in which rdi points at the guest registers and r8d/r9d are scratch.
+#[derive(Default)]
+struct Assembler {
+    code: Vec<u8>,
+}
+
+impl Assembler {
+    fn emit(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    fn emit_u32(&mut self, val: u32) {
+        self.code.extend_from_slice(&val.to_le_bytes());
+    }
+
+    /// Load the guest flags we model, passed in esi, into the host flags.
+    fn prologue(&mut self) {
+        self.emit(&[0x81, 0xE6]); // and esi, FLAGS_MASK
+        self.emit_u32(FLAGS_MASK);
+        self.emit(&[0x56, 0x9D]); // push rsi; popfq
+    }
+
+    /// Return the host flags.
+    fn epilogue(&mut self) {
+        self.emit(&[0x9C, 0x58, 0xC3]); // pushfq; pop rax; ret
+    }
+
+    /// `op r8d, [rdi+slot]` (or the reverse), for an opcode with an r32 reg operand;
+    /// r9d if `r9`.
+    fn reg_slot(&mut self, opcode: u8, r9: bool, slot: u8) {
+        let modrm = 0x47 | if r9 { 1 << 3 } else { 0 };
+        self.emit(&[0x44, opcode, modrm, slot]);
+    }
+
+    /// `op [rdi+slot]` for an opcode group taking a /digit.
+    fn group_slot(&mut self, opcode: u8, digit: u8, slot: u8) {
+        self.emit(&[opcode, 0x47 | digit << 3, slot]);
+    }
+
+    /// Append the translation of an instruction, returning false if it can't be.
+    fn translate(&mut self, instr: &Instruction) -> bool {
+        if instr.op_count() == 0 || instr.op0_kind() != OpKind::Register {
+            return false;
+        }
+        let Some(dst) = slot(instr.op0_register()) else {
+            return false;
+        };
+        let mnemonic = instr.mnemonic();
+        if instr.op_count() == 1 {
+            let (opcode, digit) = match mnemonic {
+                Mnemonic::Inc => (0xFF, 0),
+                Mnemonic::Dec => (0xFF, 1),
+                Mnemonic::Not => (0xF7, 2),
+                Mnemonic::Neg => (0xF7, 3),
+                _ => return false,
+            };
+            self.group_slot(opcode, digit, dst);
+            return true;
+        }
+        if instr.op_count() != 2 {
+            return false;
+        }
+        let imm = match instr.op1_kind() {
+            OpKind::Immediate32 => Some(instr.immediate32()),
+            OpKind::Immediate8to32 => Some(instr.immediate8to32() as u32),
+            _ => None,
+        };
+        match (mnemonic, instr.op1_kind()) {
+            (Mnemonic::Mov, OpKind::Register) => {
+                let Some(src) = slot(instr.op1_register()) else {
+                    return false;
+                };
+                self.reg_slot(0x8B, false, src); // mov r8d, [src]
+                self.reg_slot(0x89, false, dst); // mov [dst], r8d
+            }
+            (Mnemonic::Mov, _) if imm.is_some() => {
+                self.group_slot(0xC7, 0, dst); // mov [dst], imm32
+                self.emit_u32(imm.unwrap());
+            }
+            (Mnemonic::Test, OpKind::Register) => {
+                let Some(src) = slot(instr.op1_register()) else {
+                    return false;
+                };
+                self.reg_slot(0x8B, false, src);
+                self.reg_slot(0x85, false, dst); // test [dst], r8d
+            }
+            (Mnemonic::Test, _) if imm.is_some() => {
+                self.group_slot(0xF7, 0, dst); // test [dst], imm32
+                self.emit_u32(imm.unwrap());
+            }
+            (_, OpKind::Register) if alu_digit(mnemonic).is_some() => {
+                let Some(src) = slot(instr.op1_register()) else {
+                    return false;
+                };
+                self.reg_slot(0x8B, false, src);
+                self.reg_slot(alu_digit(mnemonic).unwrap() * 8 + 1, false, dst);
+                // op [dst], r8d
+            }
+            (_, _) if alu_digit(mnemonic).is_some() && imm.is_some() => {
+                self.group_slot(0x81, alu_digit(mnemonic).unwrap(), dst); // op [dst], imm32
+                self.emit_u32(imm.unwrap());
+            }
+            (
+                Mnemonic::Rol | Mnemonic::Ror | Mnemonic::Shl | Mnemonic::Shr | Mnemonic::Sar,
+                OpKind::Immediate8,
+            ) => {
+                let digit = match mnemonic {
+                    Mnemonic::Rol => 0,
+                    Mnemonic::Ror => 1,
+                    Mnemonic::Shl => 4,
+                    Mnemonic::Shr => 5,
+                    _ => 7,
+                };
+                self.group_slot(0xC1, digit, dst); // op [dst], imm8
+                self.emit(&[instr.immediate8()]);
+            }
+            (Mnemonic::Lea, OpKind::Memory) => return self.lea(instr, dst),
+            _ => return false,
+        }
+        true
+    }
+
+    /// lea, computed with a host lea so as not to disturb the flags.
+    fn lea(&mut self, instr: &Instruction, dst: u8) -> bool {
+        let base = match instr.memory_base() {
+            Register::None => None,
+            reg => match slot(reg) {
+                Some(slot) => Some(slot),
+                None => return false,
+            },
+        };
+        let index = match instr.memory_index() {
+            Register::None => None,
+            reg => match slot(reg) {
+                Some(slot) => Some(slot),
+                None => return false,
+            },
+        };
+        let scale = match instr.memory_index_scale() {
+            1 => 0,
+            2 => 1,
+            4 => 2,
+            _ => 3,
+        };
+        let disp = instr.memory_displacement32();
+        if let Some(base) = base {
+            self.reg_slot(0x8B, false, base); // mov r8d, [base]
+        }
+        if let Some(index) = index {
+            self.reg_slot(0x8B, true, index); // mov r9d, [index]
+        }
+        match (base, index) {
+            (Some(_), Some(_)) => self.emit(&[0x47, 0x8D, 0x84, scale << 6 | 0x08]), // lea r8d, [r8+r9*s+disp32]
+            (None, Some(_)) => self.emit(&[0x46, 0x8D, 0x04, scale << 6 | 0x08 | 0x05]), // lea r8d, [r9*s+disp32]
+            (Some(_), None) => self.emit(&[0x45, 0x8D, 0x80]), // lea r8d, [r8+disp32]
+            (None, None) => {
+                self.group_slot(0xC7, 0, dst); // mov [dst], disp32
+                self.emit_u32(disp);
+                return true;
+            }
+        }
+        self.emit_u32(disp);
+        self.reg_slot(0x89, false, dst); // mov [dst], r8d
+        true
+    }
+}
@@ -1,9 +1,22 @@
+pub mod coverage;
+pub mod cycles;
 pub mod debug;
+mod f80;
 mod fpu;
 mod icache;
+#[cfg(feature = "jit")]
+pub mod jit;
+mod model;
 pub mod ops;
+pub mod opstats;
+mod ports;
 mod registers;
+pub mod watch;
 mod x86;
 
-pub use crate::x86::{CPUState, CPU, X86};
+pub use crate::x86::{CPUState, Exception, CPU, MAGIC_ADDR, X86};
+pub use f80::F80;
 pub use iced_x86::Register;
+pub use model::{CpuModel, Features, FlagAccuracy, TscSource};
+pub use ports::{PortHook, Ports};
+pub use registers::Flags;
@@ -0,0 +1,187 @@
+//! The processor we claim to be.  Programs check CPUID before using instruction
+//! set extensions, so the features reported are limited to ones implemented here.
+//! Games also calibrate timing loops against RDTSC, so its counter must advance in
+//! step with the clock they measure it against, at a plausible frequency.
+
+use bitflags::bitflags;
+
+bitflags! {
+    /// CPUID leaf 1 feature bits, as reported in EDX.
+    pub struct Features: u32 {
+        const FPU = 1 << 0;
+        const TSC = 1 << 4;
+        const CX8 = 1 << 8;
+        const MMX = 1 << 23;
+    }
+}
+
+/// What RDTSC counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TscSource {
+    /// Host time, at the model's clock frequency.
+    RealTime,
+    /// Instructions executed, one cycle each, for runs that repeat exactly.
+    Instructions,
+}
+
+/// How closely flags are computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagAccuracy {
+    /// Enough for the conditions programs branch on, skipping AF and PF.
+    Simple,
+    /// Everything a real processor sets, including its "undefined" results, for
+    /// programs (like some copy protection) that inspect flags directly.  Slower.
+    Accurate,
+}
+
+/// Description of the reported processor.
+#[derive(Debug, Clone)]
+pub struct CpuModel {
+    /// Vendor string, 12 characters like "GenuineIntel".
+    pub vendor: String,
+    /// Name returned by the extended CPUID leaves, if the processor has them.
+    pub brand: Option<String>,
+    pub family: u32,
+    pub model: u32,
+    pub stepping: u32,
+    pub features: Features,
+    /// Clock frequency, which RDTSC counts at in real time.
+    pub mhz: u32,
+    pub tsc: TscSource,
+    pub flags: FlagAccuracy,
+}
+
+impl CpuModel {
+    pub fn preset(name: &str) -> Option<CpuModel> {
+        Some(match name {
+            "pentium" => CpuModel {
+                vendor: "GenuineIntel".into(),
+                brand: None,
+                family: 5,
+                model: 2,
+                stepping: 12,
+                features: Features::FPU | Features::TSC | Features::CX8,
+                mhz: 133,
+                tsc: TscSource::RealTime,
+                flags: FlagAccuracy::Simple,
+            },
+            "pentium-mmx" => CpuModel {
+                vendor: "GenuineIntel".into(),
+                brand: None,
+                family: 5,
+                model: 4,
+                stepping: 3,
+                features: Features::FPU | Features::TSC | Features::CX8 | Features::MMX,
+                mhz: 233,
+                tsc: TscSource::RealTime,
+                flags: FlagAccuracy::Simple,
+            },
+            "k6-2" => CpuModel {
+                vendor: "AuthenticAMD".into(),
+                brand: Some("AMD-K6(tm) 3D processor".into()),
+                family: 5,
+                model: 8,
+                stepping: 12,
+                features: Features::FPU | Features::TSC | Features::CX8 | Features::MMX,
+                mhz: 350,
+                tsc: TscSource::RealTime,
+                flags: FlagAccuracy::Simple,
+            },
+            _ => return None,
+        })
+    }
+
+    /// The EAX of CPUID leaf 1.
+    pub fn signature(&self) -> u32 {
+        (self.family & 0xF) << 8 | (self.model & 0xF) << 4 | (self.stepping & 0xF)
+    }
+}
+
+impl Default for CpuModel {
+    fn default() -> Self {
+        CpuModel::preset("pentium-mmx").unwrap()
+    }
+}
+
+impl std::str::FromStr for CpuModel {
+    type Err = String;
+
+    /// Parses a preset name ("pentium", "pentium-mmx", "k6-2"), optionally followed
+    /// by overrides, like "pentium-mmx,mhz=300,tsc=instructions,flags=accurate,features=fpu/tsc/cx8".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',');
+        let preset = parts.next().unwrap_or_default();
+        let mut cpu = CpuModel::preset(preset).ok_or_else(|| format!("unknown cpu {preset:?}"))?;
+        let number = |v: &str| v.parse::<u32>().map_err(|_| format!("bad number {v:?}"));
+        for part in parts {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got {part:?}"))?;
+            match key {
+                "vendor" => {
+                    if value.len() != 12 {
+                        return Err(format!("vendor {value:?} must be 12 characters"));
+                    }
+                    cpu.vendor = value.into();
+                }
+                "brand" => cpu.brand = Some(value.into()),
+                "family" => cpu.family = number(value)?,
+                "model" => cpu.model = number(value)?,
+                "stepping" => cpu.stepping = number(value)?,
+                "mhz" => cpu.mhz = number(value)?,
+                "tsc" => {
+                    cpu.tsc = match value {
+                        "realtime" => TscSource::RealTime,
+                        "instructions" => TscSource::Instructions,
+                        _ => return Err(format!("bad tsc source {value:?}")),
+                    }
+                }
+                "flags" => {
+                    cpu.flags = match value {
+                        "simple" => FlagAccuracy::Simple,
+                        "accurate" => FlagAccuracy::Accurate,
+                        _ => return Err(format!("bad flags accuracy {value:?}")),
+                    }
+                }
+                "features" => {
+                    // Only features we implement can be named, so none can be claimed
+                    // that programs would then fail on.
+                    cpu.features = value
+                        .split('/')
+                        .filter(|f| !f.is_empty())
+                        .map(|f| match f {
+                            "fpu" => Ok(Features::FPU),
+                            "tsc" => Ok(Features::TSC),
+                            "cx8" => Ok(Features::CX8),
+                            "mmx" => Ok(Features::MMX),
+                            _ => Err(format!("unsupported cpu feature {f:?}")),
+                        })
+                        .collect::<Result<_, _>>()?
+                }
+                _ => return Err(format!("unknown cpu setting {key:?}")),
+            }
+        }
+        Ok(cpu)
+    }
+}
+
+/// The counter RDTSC reads, shared by all threads as a single-core machine's is.
+#[derive(Default)]
+pub struct TimeStampCounter {
+    last: u64,
+}
+
+impl TimeStampCounter {
+    /// Read the counter, given the instructions executed so far and a way to get
+    /// the host time in milliseconds.
+    pub fn read(&mut self, model: &CpuModel, instr_count: usize, time: &dyn Fn() -> u32) -> u64 {
+        let count = match model.tsc {
+            TscSource::RealTime => time() as u64 * model.mhz as u64 * 1000,
+            TscSource::Instructions => instr_count as u64,
+        };
+        // Host time only has millisecond resolution, but code measuring a short
+        // loop divides by the difference between reads, so they never repeat.
+        self.last = count.max(self.last + 1);
+        self.last
+    }
+}
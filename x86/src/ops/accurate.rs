@@ -0,0 +1,213 @@
+//! Flag fixups for CPU models with FlagAccuracy::Accurate.
+//!
+//! The ops normally skip computing AF and PF, and leave flags the manuals call
+//! "undefined" however was convenient.  In accurate mode, the instructions where
+//! that shows are decoded to `op` here instead, which runs the usual op and then
+//! corrects its flags to what a P6-era Intel processor produces, going by the
+//! operands from before it ran.
+
+use super::helpers::{is_unmapped, segment_base, x86_offset};
+use crate::{registers::Flags, x86::CPU};
+use iced_x86::{Instruction, Mnemonic, OpKind, Register};
+use memory::{Extensions, Mem};
+
+/// Whether the instruction's flags need fixing up in accurate mode.
+pub fn affects(instr: &Instruction) -> bool {
+    matches!(
+        instr.mnemonic(),
+        Mnemonic::Add
+            | Mnemonic::Xadd
+            | Mnemonic::Adc
+            | Mnemonic::Sub
+            | Mnemonic::Sbb
+            | Mnemonic::Cmp
+            | Mnemonic::Inc
+            | Mnemonic::Dec
+            | Mnemonic::Neg
+            | Mnemonic::And
+            | Mnemonic::Or
+            | Mnemonic::Xor
+            | Mnemonic::Test
+            | Mnemonic::Shl
+            | Mnemonic::Sal
+            | Mnemonic::Shr
+            | Mnemonic::Sar
+            | Mnemonic::Shld
+            | Mnemonic::Shrd
+            | Mnemonic::Mul
+            | Mnemonic::Imul
+    )
+}
+
+/// Read an operand's value, zero extended, without faulting.
+fn operand(cpu: &CPU, mem: Mem, instr: &Instruction, i: u32) -> Option<u32> {
+    Some(match instr.op_kind(i) {
+        OpKind::Register => {
+            let reg = instr.op_register(i);
+            match reg.size() {
+                4 => cpu.regs.get32(reg),
+                2 => cpu.regs.get16(reg) as u32,
+                1 => cpu.regs.get8(reg) as u32,
+                _ => return None,
+            }
+        }
+        OpKind::Memory => {
+            let addr = segment_base(cpu, instr).wrapping_add(x86_offset(cpu, instr));
+            let size = instr.memory_size().size() as u32;
+            if is_unmapped(mem, addr, size) {
+                return None;
+            }
+            match size {
+                4 => mem.get_pod::<u32>(addr),
+                2 => mem.get_pod::<u16>(addr) as u32,
+                1 => mem.get_pod::<u8>(addr) as u32,
+                _ => return None,
+            }
+        }
+        OpKind::Immediate8
+        | OpKind::Immediate16
+        | OpKind::Immediate32
+        | OpKind::Immediate8to16
+        | OpKind::Immediate8to32 => instr.immediate(i) as u32,
+        _ => return None,
+    })
+}
+
+/// Width of the instruction's first operand, in bits.
+fn width(instr: &Instruction) -> u32 {
+    match instr.op0_kind() {
+        OpKind::Register => instr.op0_register().size() as u32 * 8,
+        _ => instr.memory_size().size() as u32 * 8,
+    }
+}
+
+fn parity(x: u32) -> bool {
+    (x as u8).count_ones().is_multiple_of(2)
+}
+
+/// Set SF, ZF and PF from a result, and clear AF, as logical operations do.
+fn logic(flags: &mut Flags, result: u32, bits: u32) {
+    flags.set(Flags::SF, (result >> (bits - 1)) & 1 != 0);
+    flags.set(Flags::ZF, result == 0);
+    flags.set(Flags::PF, parity(result));
+    flags.remove(Flags::AF);
+}
+
+/// The one-operand mul/imul results, which land in AL/AX/EAX and the register above.
+fn product(cpu: &CPU, bits: u32) -> (u32, u32) {
+    match bits {
+        8 => (
+            cpu.regs.get8(Register::AL) as u32,
+            cpu.regs.get8(Register::AH) as u32,
+        ),
+        16 => (
+            cpu.regs.get16(Register::AX) as u32,
+            cpu.regs.get16(Register::DX) as u32,
+        ),
+        _ => (cpu.regs.get32(Register::EAX), cpu.regs.get32(Register::EDX)),
+    }
+}
+
+fn sign_extend(x: u32, bits: u32) -> i64 {
+    ((x as i64) << (64 - bits)) >> (64 - bits)
+}
+
+pub fn op(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let op = super::decode(instr).unwrap_or(super::unimplemented);
+    let bits = width(instr);
+    let mask = if bits == 32 { !0 } else { (1 << bits) - 1 };
+    let ops = (0..instr.op_count().min(3))
+        .map(|i| operand(cpu, mem, instr, i))
+        .collect::<Option<Vec<_>>>();
+    op(cpu, mem, instr);
+    let Some(ops) = ops else {
+        return;
+    };
+    if !cpu.state.is_running() {
+        return;
+    }
+    let x = ops[0];
+    let y = ops.get(1).copied().unwrap_or(0);
+    let after = operand(cpu, mem, instr, 0).unwrap_or(0);
+    let (low, high) = product(cpu, bits);
+    let flags = &mut cpu.flags;
+    match instr.mnemonic() {
+        Mnemonic::Add
+        | Mnemonic::Xadd
+        | Mnemonic::Adc
+        | Mnemonic::Sub
+        | Mnemonic::Sbb
+        | Mnemonic::Cmp => {
+            let result = match instr.mnemonic() {
+                // cmp doesn't store its result, but it's the same as sub's.
+                Mnemonic::Cmp => x.wrapping_sub(y) & mask,
+                _ => after,
+            };
+            flags.set(Flags::AF, (x ^ y ^ result) & 0x10 != 0);
+            flags.set(Flags::PF, parity(result));
+        }
+        Mnemonic::Inc | Mnemonic::Dec => {
+            let result = after;
+            flags.set(Flags::AF, (x ^ 1 ^ result) & 0x10 != 0);
+            flags.set(Flags::PF, parity(result));
+        }
+        Mnemonic::Neg => {
+            let result = after;
+            flags.set(Flags::AF, (x ^ result) & 0x10 != 0);
+            flags.set(Flags::PF, parity(result));
+        }
+        Mnemonic::And | Mnemonic::Or | Mnemonic::Xor => logic(flags, after, bits),
+        Mnemonic::Test => logic(flags, x & y, bits),
+        Mnemonic::Shl | Mnemonic::Sal | Mnemonic::Shr | Mnemonic::Sar => {
+            if y & 0x1F == 0 {
+                return;
+            }
+            let result = after;
+            let cf = flags.contains(Flags::CF);
+            logic(flags, result, bits);
+            // OF is only defined for 1-bit shifts, but is computed the same way for all.
+            let msb = (result >> (bits - 1)) & 1 != 0;
+            let next = (result >> (bits - 2)) & 1 != 0;
+            match instr.mnemonic() {
+                Mnemonic::Shr => flags.set(Flags::OF, msb != next),
+                Mnemonic::Sar => flags.remove(Flags::OF),
+                _ => flags.set(Flags::OF, msb != cf),
+            }
+        }
+        Mnemonic::Shld | Mnemonic::Shrd => {
+            let count = ops.get(2).copied().unwrap_or(0);
+            if count & 0x1F == 0 {
+                return;
+            }
+            let result = after;
+            let cf = flags.contains(Flags::CF);
+            logic(flags, result, bits);
+            let msb = (result >> (bits - 1)) & 1 != 0;
+            let next = (result >> (bits - 2)) & 1 != 0;
+            match instr.mnemonic() {
+                Mnemonic::Shld => flags.set(Flags::OF, msb != cf),
+                _ => flags.set(Flags::OF, msb != next),
+            }
+        }
+        Mnemonic::Mul => logic(flags, low, bits),
+        Mnemonic::Imul => {
+            let (low, overflow) = match instr.op_count() {
+                1 => {
+                    let high_expected = (sign_extend(low, bits) >> bits) as u32 & mask;
+                    (low, high != high_expected)
+                }
+                count => {
+                    // The two-operand form multiplies into op0; the three-operand
+                    // form multiplies op1 by the immediate.
+                    let (a, b) = if count == 2 { (x, y) } else { (y, ops[2]) };
+                    let full = sign_extend(a, bits) * sign_extend(b & mask, bits);
+                    (after, full != sign_extend(after, bits))
+                }
+            };
+            logic(flags, low, bits);
+            flags.set(Flags::CF, overflow);
+            flags.set(Flags::OF, overflow);
+        }
+        _ => {}
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{registers::Flags, x86::CPU, CPUState};
+use crate::{registers::Flags, x86::CPU, Exception};
 use iced_x86::{Instruction, Register};
 use memory::{Extensions, Mem};
 
@@ -7,8 +7,29 @@ use super::helpers::*;
 pub fn nop(_cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {}
 
 pub fn enterd_imm16_imm8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    push(cpu, mem, cpu.regs.get32(Register::EBP));
-    cpu.regs.set32(Register::EBP, cpu.regs.get32(Register::ESP));
+    // A nesting level copies the enclosing frames' pointers (the "display")
+    // from the old frame, followed by the new frame's own pointer.
+    let level = instr.immediate8_2nd() % 32;
+    // Fault before pushing anything, so a retry doesn't push twice.
+    let pushes = 4 * (1 + level as u32);
+    let esp = cpu.regs.get32(Register::ESP);
+    if !check_access(cpu, mem, esp.wrapping_sub(pushes), pushes, true) {
+        return;
+    }
+    let mut ebp = cpu.regs.get32(Register::EBP);
+    push(cpu, mem, ebp);
+    let frame = cpu.regs.get32(Register::ESP);
+    if level > 0 {
+        for _ in 1..level {
+            ebp = ebp.wrapping_sub(4);
+            if !check_access(cpu, mem, ebp, 4, false) {
+                return;
+            }
+            push(cpu, mem, mem.get_pod::<u32>(ebp));
+        }
+        push(cpu, mem, frame);
+    }
+    cpu.regs.set32(Register::EBP, frame);
     *cpu.regs.get32_mut(Register::ESP) -= instr.immediate16() as u32;
 }
 
@@ -18,6 +39,38 @@ pub fn leaved(cpu: &mut CPU, mem: Mem, _instr: &Instruction) {
     cpu.regs.set32(Register::EBP, ebp);
 }
 
+pub fn enterw_imm16_imm8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    // As enterd_imm16_imm8, with the frame pointers being offsets into the stack segment.
+    let level = instr.immediate8_2nd() % 32;
+    let pushes = 2 * (1 + level as u32);
+    if !check_access(cpu, mem, stack_addr(cpu).wrapping_sub(pushes), pushes, true) {
+        return;
+    }
+    let ss = cpu.regs.seg_base(Register::SS);
+    let mut bp = cpu.regs.get16(Register::BP);
+    push16(cpu, mem, bp);
+    let frame = cpu.regs.get16(Register::SP);
+    if level > 0 {
+        for _ in 1..level {
+            bp = bp.wrapping_sub(2);
+            let addr = ss.wrapping_add(bp as u32);
+            if !check_access(cpu, mem, addr, 2, false) {
+                return;
+            }
+            push16(cpu, mem, mem.get_pod::<u16>(addr));
+        }
+        push16(cpu, mem, frame);
+    }
+    cpu.regs.set16(Register::BP, frame);
+    release_stack(cpu, (instr.immediate16() as u32).wrapping_neg());
+}
+
+pub fn leavew(cpu: &mut CPU, mem: Mem, _instr: &Instruction) {
+    cpu.regs.set16(Register::SP, cpu.regs.get16(Register::BP));
+    let bp = pop16(cpu, mem);
+    cpu.regs.set16(Register::BP, bp);
+}
+
 pub fn pushd_r16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     // Pushing segment registers is subtle:
     // "If the source operand is a segment register (16 bits) and [...]
@@ -30,6 +83,11 @@ pub fn pushd_r16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     push(cpu, mem, x as u32);
 }
 
+pub fn pushw_sreg(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let x = cpu.regs.get16(instr.op0_register());
+    push16(cpu, mem, x);
+}
+
 pub fn pushd_imm8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     push(cpu, mem, instr.immediate8to32() as u32);
 }
@@ -54,8 +112,26 @@ pub fn push_rm16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 
 pub fn popd_r16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     // See discussion in pushd_r16.
-    let value = pop(cpu, mem);
-    cpu.regs.set16(instr.op0_register(), value as u16);
+    // The selector is checked before it's popped, so a fault leaves the stack be.
+    let value = mem.get_pod::<u32>(stack_addr(cpu));
+    if load_segment(cpu, mem, instr.op0_register(), value as u16) {
+        release_stack(cpu, 4);
+    }
+}
+
+pub fn popw_sreg(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let value = mem.get_pod::<u16>(stack_addr(cpu));
+    if load_segment(cpu, mem, instr.op0_register(), value) {
+        release_stack(cpu, 2);
+    }
+}
+
+pub fn pushw_imm16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    push16(cpu, mem, instr.immediate16());
+}
+
+pub fn pushw_imm8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    push16(cpu, mem, instr.immediate8to16() as u16);
 }
 
 pub fn pop_rm32(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
@@ -121,7 +197,7 @@ pub fn mov_rm8_imm8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 }
 
 pub fn mov_moffs8_al(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let addr = x86_addr(cpu, instr);
+    let addr = x86_addr(cpu, mem, instr);
     mem.put::<u8>(addr, cpu.regs.get8(Register::AL));
 }
 
@@ -131,7 +207,7 @@ pub fn mov_r32m16_sreg(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     match instr.op0_kind() {
         iced_x86::OpKind::Register => cpu.regs.set32(instr.op0_register(), y as u32),
         iced_x86::OpKind::Memory => {
-            let addr = x86_addr(cpu, instr);
+            let addr = x86_addr(cpu, mem, instr);
             mem.put::<u16>(addr, y)
         }
         _ => unimplemented!(),
@@ -140,13 +216,38 @@ pub fn mov_r32m16_sreg(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 
 pub fn mov_sreg_r32m16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     // This weirdly is either a 16-bit or 32-write, so we must match to determine.
-    // TODO: this is supposed to do segment selector validation stuff.
     let y = match instr.op1_kind() {
         iced_x86::OpKind::Register => cpu.regs.get32(instr.op1_register()) as u16,
-        iced_x86::OpKind::Memory => mem.get_pod::<u16>(x86_addr(cpu, instr)),
+        iced_x86::OpKind::Memory => mem.get_pod::<u16>(x86_addr(cpu, mem, instr)),
         _ => unimplemented!(),
     };
-    cpu.regs.set16(instr.op0_register(), y);
+    load_segment(cpu, mem, instr.op0_register(), y);
+}
+
+pub fn mov_sreg_rm16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let y = op1_rm16(cpu, mem, instr);
+    load_segment(cpu, mem, instr.op0_register(), y);
+}
+
+pub fn mov_rm16_sreg(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let y = cpu.regs.get16(instr.op1_register());
+    let x = rm16(cpu, mem, instr);
+    x.set(y);
+}
+
+/// lds/les: load a far pointer into a segment register and a general one.
+pub fn lxs_r16_m1616(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let addr = x86_addr(cpu, mem, instr);
+    let offset = mem.get_pod::<u16>(addr);
+    let selector = mem.get_pod::<u16>(addr.wrapping_add(2));
+    let sreg = match instr.code() {
+        iced_x86::Code::Lds_r16_m1616 => Register::DS,
+        iced_x86::Code::Les_r16_m1616 => Register::ES,
+        _ => unreachable!(),
+    };
+    if load_segment(cpu, mem, sreg, selector) {
+        cpu.regs.set16(instr.op0_register(), offset);
+    }
 }
 
 pub fn movsx_r32_rm16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
@@ -228,27 +329,47 @@ pub fn xchg_rm8_r8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     cpu.regs.set8(r1, tmp);
 }
 
+/// LOCK cmpxchg and friends need nothing extra for atomicity: an instruction always
+/// runs to completion before any other thread does, see X86::schedule.
 pub fn cmpxchg_rm32_r32(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     let y = cpu.regs.get32(instr.op1_register());
-    match instr.op0_kind() {
-        iced_x86::OpKind::Register => todo!(),
-        iced_x86::OpKind::Memory => {
-            let addr = x86_addr(cpu, instr);
-            let x = mem.get_pod::<u32>(addr);
-            if cpu.regs.get32(Register::EAX) == x {
-                cpu.flags.insert(Flags::ZF);
-                mem.put::<u32>(addr, y);
-            } else {
-                cpu.flags.remove(Flags::ZF);
-                cpu.regs.set32(Register::EAX, y);
-            }
-        }
-        _ => unreachable!(),
-    };
+    let x = rm32(cpu, mem, instr);
+    let old = x.get();
+    // Flags are set as by cmp eax,old.
+    super::math::sub(cpu.regs.get32(Register::EAX), old, &mut cpu.flags);
+    if cpu.flags.contains(Flags::ZF) {
+        x.set(y);
+    } else {
+        cpu.regs.set32(Register::EAX, old);
+    }
+}
+
+pub fn cmpxchg_rm16_r16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let y = cpu.regs.get16(instr.op1_register());
+    let x = rm16(cpu, mem, instr);
+    let old = x.get();
+    super::math::sub(cpu.regs.get16(Register::AX), old, &mut cpu.flags);
+    if cpu.flags.contains(Flags::ZF) {
+        x.set(y);
+    } else {
+        cpu.regs.set16(Register::AX, old);
+    }
+}
+
+pub fn cmpxchg_rm8_r8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let y = cpu.regs.get8(instr.op1_register());
+    let x = rm8(cpu, mem, instr);
+    let old = x.get();
+    super::math::sub(cpu.regs.get8(Register::AL), old, &mut cpu.flags);
+    if cpu.flags.contains(Flags::ZF) {
+        x.set(y);
+    } else {
+        cpu.regs.set8(Register::AL, old);
+    }
 }
 
 pub fn cmpxchg8b_m64(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let addr = x86_addr(cpu, instr);
+    let addr = x86_addr(cpu, mem, instr);
     let m64 = mem.get_pod::<u64>(addr);
     let test = get_edx_eax(cpu);
     if test == m64 {
@@ -264,7 +385,13 @@ pub fn cmpxchg8b_m64(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 
 pub fn lea_r32_m(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
     // lea eax,[esp+10h]
-    cpu.regs.set32(instr.op0_register(), x86_addr(cpu, instr));
+    // The segment isn't part of the result, even with an override.
+    cpu.regs.set32(instr.op0_register(), x86_offset(cpu, instr));
+}
+
+pub fn lea_r16_m(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    cpu.regs
+        .set16(instr.op0_register(), x86_offset(cpu, instr) as u16);
 }
 
 pub fn seta_rm8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
@@ -317,6 +444,10 @@ pub fn setge_rm8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 
 pub fn pushad(cpu: &mut CPU, mem: Mem, _instr: &Instruction) {
     let esp = cpu.regs.get32(Register::ESP); // get before any pushes
+                                             // Fault before pushing anything, so a retry doesn't push twice.
+    if !check_access(cpu, mem, esp.wrapping_sub(32), 32, true) {
+        return;
+    }
 
     push(cpu, mem, cpu.regs.get32(Register::EAX));
     push(cpu, mem, cpu.regs.get32(Register::ECX));
@@ -346,6 +477,32 @@ pub fn popad(cpu: &mut CPU, mem: Mem, _instr: &Instruction) {
     cpu.regs.set32(Register::EAX, eax);
 }
 
+pub fn pushaw(cpu: &mut CPU, mem: Mem, _instr: &Instruction) {
+    let sp = cpu.regs.get16(Register::SP);
+    if !check_access(cpu, mem, stack_addr(cpu).wrapping_sub(16), 16, true) {
+        return;
+    }
+    for reg in [Register::AX, Register::CX, Register::DX, Register::BX] {
+        push16(cpu, mem, cpu.regs.get16(reg));
+    }
+    push16(cpu, mem, sp);
+    for reg in [Register::BP, Register::SI, Register::DI] {
+        push16(cpu, mem, cpu.regs.get16(reg));
+    }
+}
+
+pub fn popaw(cpu: &mut CPU, mem: Mem, _instr: &Instruction) {
+    for reg in [Register::DI, Register::SI, Register::BP] {
+        let value = pop16(cpu, mem);
+        cpu.regs.set16(reg, value);
+    }
+    pop16(cpu, mem); // ignore sp
+    for reg in [Register::BX, Register::DX, Register::CX, Register::AX] {
+        let value = pop16(cpu, mem);
+        cpu.regs.set16(reg, value);
+    }
+}
+
 pub fn pushfd(cpu: &mut CPU, mem: Mem, _instr: &Instruction) {
     push(cpu, mem, cpu.flags.bits());
 }
@@ -356,19 +513,19 @@ pub fn pushfw(cpu: &mut CPU, mem: Mem, _instr: &Instruction) {
 }
 
 pub fn popfd(cpu: &mut CPU, mem: Mem, _instr: &Instruction) {
-    let value = pop(cpu, mem);
-    cpu.flags = Flags::from_bits(value).unwrap_or_else(|| panic!("invalid flags {:#x}", value));
+    // Bits we don't model, like IF and the always-set bit 1, are dropped.
+    cpu.flags = Flags::from_bits_truncate(pop(cpu, mem));
 }
 
 pub fn popfw(cpu: &mut CPU, mem: Mem, _instr: &Instruction) {
-    let prev = Flags::from_bits(cpu.flags.bits() & 0xFFFF_0000).unwrap();
-    let new = Flags::from_bits(pop16(cpu, mem) as u32).unwrap();
+    let prev = Flags::from_bits_truncate(cpu.flags.bits() & 0xFFFF_0000);
+    let new = Flags::from_bits_truncate(pop16(cpu, mem) as u32);
     cpu.flags = prev.union(new);
 }
 
 pub fn sahf(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
     let ah = cpu.regs.get8(Register::AH);
-    cpu.flags = Flags::from_bits((cpu.flags.bits() & 0xFFFF_FF00) | ah as u32).unwrap();
+    cpu.flags = Flags::from_bits_truncate((cpu.flags.bits() & 0xFFFF_FF00) | ah as u32);
 }
 
 pub fn salc(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
@@ -407,6 +564,20 @@ pub fn cwde(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
     cpu.regs.set32(Register::EAX, value as u32);
 }
 
+pub fn cbw(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    let value = cpu.regs.get8(Register::AL) as i8 as i16;
+    cpu.regs.set16(Register::AX, value as u16);
+}
+
+pub fn cwd(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    let dx = if cpu.regs.get16(Register::AX) >> 15 == 0 {
+        0
+    } else {
+        0xFFFF
+    };
+    cpu.regs.set16(Register::DX, dx);
+}
+
 pub fn cdq(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
     let edx = if cpu.regs.get32(Register::EAX) >> 31 == 0 {
         0
@@ -417,9 +588,63 @@ pub fn cdq(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
 }
 
 pub fn int3(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    log::warn!("debugger interrupt");
-    cpu.state = CPUState::Blocked(None);
-    cpu.regs.eip -= 1;
+    cpu.raise(Exception::Breakpoint);
+}
+
+pub fn int_imm8(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    cpu.raise(Exception::Interrupt(instr.immediate8()));
+}
+
+pub fn ud2(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    cpu.raise(Exception::InvalidOpcode);
+}
+
+/// Stands in for code that couldn't be decoded because it runs into memory that
+/// isn't accessible, faulting as fetching it does.
+pub fn unmapped_code(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let ip = instr.ip32();
+    // Either the instruction starts in an inaccessible page or runs into the next one.
+    let addr = match is_unmapped(mem, ip, 1) {
+        true => ip,
+        false => (ip & !0xFFF).wrapping_add(0x1000),
+    };
+    cpu.raise(Exception::AccessViolation { addr, write: false });
+}
+
+/// Stands in for valid instructions we haven't implemented, which is our bug
+/// rather than the program's, so it stops emulation instead of faulting.
+pub fn unimplemented(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    cpu.err(format!("unimplemented: {instr} ({:?})", instr.code()));
+}
+
+/// The port of an IN or OUT, from an immediate or DX.
+fn port(cpu: &CPU, instr: &Instruction, i: u32) -> u16 {
+    match instr.op_kind(i) {
+        iced_x86::OpKind::Register => cpu.regs.get16(Register::DX),
+        _ => instr.immediate8() as u16,
+    }
+}
+
+pub fn in_port(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    let reg = instr.op0_register();
+    let size = reg.size() as u32;
+    let value = cpu.ports.read(port(cpu, instr, 1), size);
+    match size {
+        1 => cpu.regs.set8(reg, value as u8),
+        2 => cpu.regs.set16(reg, value as u16),
+        _ => cpu.regs.set32(reg, value),
+    }
+}
+
+pub fn out_port(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    let reg = instr.op1_register();
+    let value = match reg.size() {
+        1 => cpu.regs.get8(reg) as u32,
+        2 => cpu.regs.get16(reg) as u32,
+        _ => cpu.regs.get32(reg),
+    };
+    cpu.ports
+        .write(port(cpu, instr, 0), reg.size() as u32, value);
 }
 
 pub fn bswap_r32(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
@@ -434,11 +659,42 @@ pub fn bswap_r32(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
     );
 }
 
-pub fn xlat_m8(cpu: &mut CPU, mem: Mem, _instr: &Instruction) {
-    let addr = cpu.regs.get32(Register::EBX) + (cpu.regs.get8(Register::AL) as u32);
+pub fn xlat_m8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let addr = segment_base(cpu, instr)
+        .wrapping_add(cpu.regs.get32(Register::EBX))
+        .wrapping_add(cpu.regs.get8(Register::AL) as u32);
+    if !check_access(cpu, mem, addr, 1, false) {
+        return;
+    }
     cpu.regs.set8(Register::AL, mem.get_pod::<u8>(addr));
 }
 
+pub fn bound_r32_m3232(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let index = cpu.regs.get32(instr.op0_register()) as i32;
+    let addr = x86_addr(cpu, mem, instr);
+    if !check_access(cpu, mem, addr, 8, false) {
+        return;
+    }
+    let lower = mem.get_pod::<u32>(addr) as i32;
+    let upper = mem.get_pod::<u32>(addr + 4) as i32;
+    if index < lower || index > upper {
+        cpu.raise(Exception::BoundRange);
+    }
+}
+
+pub fn bound_r16_m1616(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let index = cpu.regs.get16(instr.op0_register()) as i16;
+    let addr = x86_addr(cpu, mem, instr);
+    if !check_access(cpu, mem, addr, 4, false) {
+        return;
+    }
+    let lower = mem.get_pod::<u16>(addr) as i16;
+    let upper = mem.get_pod::<u16>(addr + 2) as i16;
+    if index < lower || index > upper {
+        cpu.raise(Exception::BoundRange);
+    }
+}
+
 pub fn bts_rm32_r32(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     let y = op1_rm32(cpu, mem, instr);
     let x = rm32(cpu, mem, instr);
@@ -455,3 +711,159 @@ pub fn tzcnt_r32_rm32(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     cpu.flags.set(Flags::ZF, count == 0);
     x.set(count);
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ops::testing::{run, run16, DATA16, STACK, STACK16},
+        registers::Flags,
+        CPUState, Exception,
+    };
+    use iced_x86::Register::*;
+    use memory::Extensions;
+
+    const DATA: u32 = 0x2000;
+
+    #[test]
+    fn popfd_masks_unmodeled_bits() {
+        // push 0x302 (TF, IF, and the reserved always-set bit 1); popfd
+        let (cpu, _) = run(b"\x68\x02\x03\x00\x00\x9d", |_, _| {});
+        assert_eq!(cpu.state, CPUState::Running);
+        assert_eq!(cpu.flags, Flags::TF);
+    }
+
+    #[test]
+    fn cmpxchg() {
+        // cmpxchg [DATA], ecx
+        let code = b"\x0f\xb1\x0d\x00\x20\x00\x00";
+        let (cpu, mem) = run(code, |cpu, mem| {
+            cpu.regs.set32(EAX, 1);
+            cpu.regs.set32(ECX, 5);
+            mem.put::<u32>(DATA, 1);
+        });
+        assert!(cpu.flags.contains(Flags::ZF));
+        assert_eq!(mem.get_pod::<u32>(DATA), 5);
+        assert_eq!(cpu.regs.get32(EAX), 1);
+
+        let (cpu, mem) = run(code, |cpu, mem| {
+            cpu.regs.set32(EAX, 2);
+            cpu.regs.set32(ECX, 5);
+            mem.put::<u32>(DATA, 1);
+        });
+        assert!(!cpu.flags.contains(Flags::ZF));
+        assert_eq!(mem.get_pod::<u32>(DATA), 1);
+        assert_eq!(cpu.regs.get32(EAX), 1);
+    }
+
+    #[test]
+    fn cmpxchg8b() {
+        // cmpxchg8b [DATA]
+        let code = b"\x0f\xc7\x0d\x00\x20\x00\x00";
+        let (cpu, mem) = run(code, |cpu, mem| {
+            cpu.regs.set32(EDX, 1);
+            cpu.regs.set32(EAX, 2);
+            cpu.regs.set32(ECX, 3);
+            cpu.regs.set32(EBX, 4);
+            mem.put::<u64>(DATA, 0x1_0000_0002);
+        });
+        assert!(cpu.flags.contains(Flags::ZF));
+        assert_eq!(mem.get_pod::<u64>(DATA), 0x3_0000_0004);
+
+        let (cpu, mem) = run(code, |cpu, mem| {
+            cpu.regs.set32(EDX, 1);
+            cpu.regs.set32(EAX, 1);
+            mem.put::<u64>(DATA, 0x7_0000_0008);
+        });
+        assert!(!cpu.flags.contains(Flags::ZF));
+        assert_eq!((cpu.regs.get32(EDX), cpu.regs.get32(EAX)), (7, 8));
+        assert_eq!(mem.get_pod::<u64>(DATA), 0x7_0000_0008);
+    }
+
+    #[test]
+    fn bound() {
+        // bound eax, [DATA]
+        let code = b"\x62\x05\x00\x20\x00\x00";
+        let bounds = |index: i32| {
+            run(code, |cpu, mem| {
+                cpu.regs.set32(EAX, index as u32);
+                mem.put::<i32>(DATA, -2);
+                mem.put::<i32>(DATA + 4, 4);
+            })
+            .0
+            .state
+        };
+        assert_eq!(bounds(-2), CPUState::Running);
+        assert_eq!(bounds(4), CPUState::Running);
+        assert_eq!(bounds(-3), CPUState::Exception(Exception::BoundRange));
+        assert_eq!(bounds(5), CPUState::Exception(Exception::BoundRange));
+    }
+
+    #[test]
+    fn enter() {
+        // enter 0x10, 0
+        let (cpu, mem) = run(b"\xc8\x10\x00\x00", |cpu, _| {
+            cpu.regs.set32(EBP, 0x7f00);
+        });
+        assert_eq!(cpu.regs.get32(EBP), STACK - 4);
+        assert_eq!(cpu.regs.get32(ESP), STACK - 4 - 0x10);
+        assert_eq!(mem.get_pod::<u32>(STACK - 4), 0x7f00);
+    }
+
+    #[test]
+    fn enter_nested() {
+        // enter 0x10, 2: copies the enclosing frame's pointer from below the old ebp.
+        let (cpu, mem) = run(b"\xc8\x10\x00\x02", |cpu, mem| {
+            cpu.regs.set32(EBP, 0x7f00);
+            mem.put::<u32>(0x7efc, 0xaaaa);
+        });
+        let frame = STACK - 4;
+        assert_eq!(cpu.regs.get32(EBP), frame);
+        assert_eq!(cpu.regs.get32(ESP), STACK - 12 - 0x10);
+        assert_eq!(mem.get_pod::<u32>(STACK - 4), 0x7f00);
+        assert_eq!(mem.get_pod::<u32>(STACK - 8), 0xaaaa);
+        assert_eq!(mem.get_pod::<u32>(STACK - 12), frame);
+    }
+
+    #[test]
+    fn addressing16() {
+        // mov ax,[bx+si+2]; mov dx,[bp+2]; lea cx,[bx+si-1]
+        let code = b"\x8b\x40\x02\x8b\x56\x02\x8d\x48\xff";
+        let (cpu, _) = run16(code, |cpu, mem| {
+            // Only the low words address.
+            cpu.regs.set32(EBX, 0xFFFF_0010);
+            cpu.regs.set32(ESI, 0x20);
+            cpu.regs.set32(EBP, 0x100);
+            mem.put::<u16>(DATA16.1 + 0x32, 0x1234);
+            mem.put::<u16>(STACK16.1 + 0x102, 0x5678);
+        });
+        assert_eq!(cpu.state, CPUState::Running);
+        assert_eq!(cpu.regs.get16(AX), 0x1234);
+        assert_eq!(cpu.regs.get16(DX), 0x5678);
+        assert_eq!(cpu.regs.get16(CX), 0x2f);
+    }
+
+    #[test]
+    fn lds() {
+        // lds si,[0]; mov ax,[si]
+        let (cpu, _) = run16(b"\xc5\x36\x00\x00\x8b\x04", |_, mem| {
+            mem.put::<u16>(DATA16.1, 0x44);
+            mem.put::<u16>(DATA16.1 + 2, STACK16.0);
+            mem.put::<u16>(STACK16.1 + 0x44, 0xbeef);
+        });
+        assert_eq!(cpu.state, CPUState::Running);
+        assert_eq!(cpu.regs.get16(DS), STACK16.0);
+        assert_eq!(cpu.regs.get16(SI), 0x44);
+        assert_eq!(cpu.regs.get16(AX), 0xbeef);
+    }
+
+    #[test]
+    fn bad_selector() {
+        // mov ax,2fh; mov ds,ax, selecting an LDT entry that isn't present.
+        let (cpu, _) = run16(b"\xb8\x2f\x00\x8e\xd8", |_, _| {});
+        assert_eq!(
+            cpu.state,
+            CPUState::Exception(Exception::GeneralProtection { selector: 0x2f })
+        );
+        assert_eq!(cpu.regs.get16(DS), DATA16.0);
+    }
+}
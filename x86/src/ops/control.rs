@@ -1,6 +1,6 @@
 use crate::{registers::Flags, x86::CPU, Register};
 use iced_x86::Instruction;
-use memory::Mem;
+use memory::{Extensions, Mem};
 
 use super::helpers::*;
 
@@ -125,3 +125,140 @@ pub fn loop_(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
         x86_jmp(cpu, instr.near_branch32());
     }
 }
+
+pub fn jcxz(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    if cpu.regs.get16(Register::CX) == 0 {
+        x86_jmp(cpu, instr.near_branch32());
+    }
+}
+
+pub fn loopw(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    let cx = cpu.regs.get16_mut(Register::CX);
+    *cx = cx.wrapping_sub(1);
+    if *cx != 0 {
+        x86_jmp(cpu, instr.near_branch32());
+    }
+}
+
+// Near transfers in 16-bit code push and pop IP, the offset within CS, though EIP
+// holds the linear address.  Decoding already made branch targets linear.
+
+/// Jump to an offset within the code segment.
+fn jmp16(cpu: &mut CPU, ip: u16) {
+    let base = cpu.regs.seg_base(Register::CS);
+    x86_jmp(cpu, base.wrapping_add(ip as u32))
+}
+
+pub fn callw(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    push16(cpu, mem, ip16(cpu));
+    x86_jmp(cpu, instr.near_branch32())
+}
+
+pub fn callw_rm16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    // call word ptr [addr]
+    let target = rm16(cpu, mem, instr).get();
+    push16(cpu, mem, ip16(cpu));
+    jmp16(cpu, target)
+}
+
+pub fn jmpw_rm16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let target = rm16(cpu, mem, instr).get();
+    jmp16(cpu, target)
+}
+
+pub fn retnw(cpu: &mut CPU, mem: Mem, _instr: &Instruction) {
+    let ip = pop16(cpu, mem);
+    jmp16(cpu, ip)
+}
+
+pub fn retnw_imm16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let ip = pop16(cpu, mem);
+    jmp16(cpu, ip);
+    release_stack(cpu, instr.immediate16() as u32);
+}
+
+// Far transfers load CS from a selector, which can switch between 16-bit code and
+// the flat space.
+
+/// Read a far pointer, offset then selector, from an instruction's memory operand.
+fn far_ptr16(cpu: &mut CPU, mem: Mem, instr: &Instruction) -> (u16, u16) {
+    let addr = x86_addr(cpu, mem, instr);
+    (
+        mem.get_pod::<u16>(addr.wrapping_add(2)),
+        mem.get_pod::<u16>(addr),
+    )
+}
+
+fn far_call16(cpu: &mut CPU, mem: Mem, selector: u16, offset: u16) {
+    push16(cpu, mem, cpu.regs.get16(Register::CS));
+    push16(cpu, mem, ip16(cpu));
+    far_jmp(cpu, mem, selector, offset)
+}
+
+pub fn callw_ptr1616(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    // call 1234h:5678h
+    far_call16(cpu, mem, instr.far_branch_selector(), instr.far_branch16())
+}
+
+pub fn callw_m1616(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    // call dword ptr [addr]
+    let (selector, offset) = far_ptr16(cpu, mem, instr);
+    far_call16(cpu, mem, selector, offset)
+}
+
+pub fn jmpw_ptr1616(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    far_jmp(cpu, mem, instr.far_branch_selector(), instr.far_branch16())
+}
+
+pub fn jmpw_m1616(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let (selector, offset) = far_ptr16(cpu, mem, instr);
+    far_jmp(cpu, mem, selector, offset)
+}
+
+pub fn retfw(cpu: &mut CPU, mem: Mem, _instr: &Instruction) {
+    let ip = pop16(cpu, mem);
+    let cs = pop16(cpu, mem);
+    far_jmp(cpu, mem, cs, ip)
+}
+
+pub fn retfw_imm16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    retfw(cpu, mem, instr);
+    release_stack(cpu, instr.immediate16() as u32);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ops::testing::{run16, CODE16, FAR16},
+        CPUState,
+    };
+    use iced_x86::Register::*;
+
+    #[test]
+    fn near_call16() {
+        // call 7; inc ax; jmp 0bh; nop; mov al,10h; ret; nop
+        let code = b"\xe8\x04\x00\x40\xeb\x05\x90\xb0\x10\xc3\x90";
+        let (cpu, _) = run16(code, |_, _| {});
+        assert_eq!(cpu.state, CPUState::Running);
+        assert_eq!(cpu.regs.get16(AX), 0x11);
+        assert_eq!(cpu.regs.get16(SP), 0x1000);
+    }
+
+    #[test]
+    fn far_call16() {
+        // push 5; call 0017h:0000h
+        let code = b"\x6a\x05\x9a\x00\x00\x17\x00";
+        let (cpu, _) = run16(code, |_, mem| {
+            // push bp; mov bp,sp; mov ax,[bp+6]; pop bp; retf 2
+            let far = b"\x55\x89\xe5\x8b\x46\x06\x5d\xca\x02\x00";
+            mem.sub(FAR16.1, far.len() as u32)
+                .as_mut_slice_todo()
+                .copy_from_slice(far);
+        });
+        assert_eq!(cpu.state, CPUState::Running);
+        assert_eq!(cpu.regs.get16(AX), 5);
+        assert_eq!(cpu.regs.get16(SP), 0x1000);
+        assert_eq!(cpu.regs.get16(CS), CODE16.0);
+        assert_eq!(cpu.regs.eip, CODE16.1 + code.len() as u32);
+    }
+}
@@ -1,23 +1,53 @@
 use crate::CPU;
-use bitflags::bitflags;
 use iced_x86::{Instruction, Register};
 use memory::Mem;
 
-bitflags! {
-    pub struct EDXFeatures: u32 {
-        const MMX = 1 << 23;
+/// Set EBX, EDX, ECX to 12 bytes of a string, the order CPUID returns the vendor in.
+fn set_string(cpu: &mut CPU, regs: &[Register], text: &[u8]) {
+    for (i, &reg) in regs.iter().enumerate() {
+        let mut bytes = [0u8; 4];
+        for (j, b) in bytes.iter_mut().enumerate() {
+            *b = text.get(i * 4 + j).copied().unwrap_or(0);
+        }
+        cpu.regs.set32(reg, u32::from_le_bytes(bytes));
     }
 }
 
 pub fn cpuid(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    match cpu.regs.get32(Register::EAX) {
+    let model = cpu.model.clone();
+    let leaf = cpu.regs.get32(Register::EAX);
+    let (eax, ebx, ecx, edx) = (Register::EAX, Register::EBX, Register::ECX, Register::EDX);
+    for reg in [eax, ebx, ecx, edx] {
+        cpu.regs.set32(reg, 0);
+    }
+    match leaf {
+        0 => {
+            // Highest standard leaf, and vendor.
+            cpu.regs.set32(eax, 1);
+            set_string(cpu, &[ebx, edx, ecx], model.vendor.as_bytes());
+        }
         1 => {
-            // CPUID_GETFEATURES
-            // Just enough to convince heaven7 that we support MMX.
-            cpu.regs.set32(Register::EAX, 0);
-            cpu.regs.set32(Register::ECX, 0);
-            cpu.regs.set32(Register::EDX, EDXFeatures::MMX.bits());
+            cpu.regs.set32(eax, model.signature());
+            cpu.regs.set32(edx, model.features.bits());
+        }
+        0x8000_0000 => {
+            // Highest extended leaf; processors without a brand string have none.
+            if model.brand.is_some() {
+                cpu.regs.set32(eax, 0x8000_0004);
+            }
         }
-        mode => todo!("cpuid {mode}"),
+        0x8000_0002..=0x8000_0004 => {
+            if let Some(brand) = &model.brand {
+                let ofs = (leaf - 0x8000_0002) as usize * 16;
+                let text = brand.as_bytes().get(ofs..).unwrap_or_default();
+                set_string(cpu, &[eax, ebx, ecx, edx], text);
+            }
+        }
+        _ => log::warn!("cpuid {leaf:#x}: unknown leaf"),
     }
 }
+
+pub fn rdtsc(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    cpu.regs.set32(Register::EAX, cpu.tsc as u32);
+    cpu.regs.set32(Register::EDX, (cpu.tsc >> 32) as u32);
+}
@@ -1,87 +1,189 @@
 use super::helpers::*;
-use crate::{fpu, registers::Flags, x86::CPU};
+use crate::{
+    f80::{Env, F80},
+    fpu,
+    registers::Flags,
+    x86::CPU,
+};
 use iced_x86::{Instruction, Register};
 use memory::{Extensions, Mem};
+use std::cmp::Ordering;
 
 pub fn finit(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
     cpu.fpu.st_top = 8;
     cpu.fpu.status = fpu::Status::empty();
+    cpu.fpu.control = fpu::CONTROL_DEFAULT;
 }
 
-/// Compare two values and set floating-point comparison flags.
-fn fcom<T: std::cmp::PartialOrd>(cpu: &mut CPU, x: T, y: T) {
-    if x > y {
-        cpu.fpu.status.set(fpu::Status::C3, false);
-        cpu.fpu.status.set(fpu::Status::C2, false);
-        cpu.fpu.status.set(fpu::Status::C0, false);
-    } else if x < y {
-        cpu.fpu.status.set(fpu::Status::C3, false);
-        cpu.fpu.status.set(fpu::Status::C2, false);
-        cpu.fpu.status.set(fpu::Status::C0, true);
-    } else if x == y {
-        cpu.fpu.status.set(fpu::Status::C3, true);
-        cpu.fpu.status.set(fpu::Status::C2, false);
-        cpu.fpu.status.set(fpu::Status::C0, false);
-    } else {
-        cpu.fpu.status.set(fpu::Status::C3, true);
-        cpu.fpu.status.set(fpu::Status::C2, true);
-        cpu.fpu.status.set(fpu::Status::C0, true);
+pub fn fnclex(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    cpu.fpu.status &= fpu::Status::C0 | fpu::Status::C1 | fpu::Status::C2 | fpu::Status::C3;
+}
+
+fn m64fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) -> F80 {
+    let x = mem.get_pod::<f64>(x86_addr(cpu, mem, instr));
+    cpu.fpu.calc(|env| F80::load_f64(x, env))
+}
+
+fn m32fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) -> F80 {
+    let x = mem.get_pod::<f32>(x86_addr(cpu, mem, instr));
+    cpu.fpu.calc(|env| F80::load_f32(x, env))
+}
+
+fn m32int(cpu: &mut CPU, mem: Mem, instr: &Instruction) -> F80 {
+    F80::from_i64(mem.get_pod::<i32>(x86_addr(cpu, mem, instr)) as i64)
+}
+
+fn m16int(cpu: &mut CPU, mem: Mem, instr: &Instruction) -> F80 {
+    F80::from_i64(mem.get_pod::<i16>(x86_addr(cpu, mem, instr)) as i64)
+}
+
+type BinOp = fn(F80, F80, &mut Env) -> F80;
+
+/// st(0) = op(st(0), y).
+fn st0_op(cpu: &mut CPU, y: F80, op: BinOp) {
+    let x = cpu.fpu.st0();
+    let val = cpu.fpu.calc(|env| op(x, y, env));
+    cpu.fpu.set_st0(val);
+}
+
+/// op0 = op(op0, op1), for the forms between registers.
+fn sti_op(cpu: &mut CPU, instr: &Instruction, op: BinOp) {
+    let y = cpu.fpu.get(instr.op1_register());
+    let x = cpu.fpu.get(instr.op0_register());
+    let val = cpu.fpu.calc(|env| op(x, y, env));
+    cpu.fpu.set(instr.op0_register(), val);
+}
+
+/// st(0) = op(st(0)), for functions computed with f64.
+fn st0_f64(cpu: &mut CPU, op: impl FnOnce(f64) -> f64) {
+    let x = cpu.fpu.st0();
+    if x.is_nan() {
+        let val = cpu.fpu.calc(|env| x.add(F80::ZERO, env));
+        cpu.fpu.set_st0(val);
+        return;
+    }
+    let val = F80::from_f64(op(x.to_f64_lossy()));
+    cpu.fpu.raise(crate::f80::Exceptions::PRECISION);
+    cpu.fpu.set_st0(val);
+}
+
+/// Compare and set floating-point comparison flags, for FCOM and FUCOM.
+fn fcom(cpu: &mut CPU, x: F80, y: F80, quiet: bool) {
+    let ord = cpu.fpu.calc(|env| x.compare(y, quiet, env));
+    let (c3, c2, c0) = match ord {
+        Some(Ordering::Greater) => (false, false, false),
+        Some(Ordering::Less) => (false, false, true),
+        Some(Ordering::Equal) => (true, false, false),
+        None => (true, true, true),
+    };
+    cpu.fpu.status.set(fpu::Status::C3, c3);
+    cpu.fpu.status.set(fpu::Status::C2, c2);
+    cpu.fpu.status.set(fpu::Status::C1, false);
+    cpu.fpu.status.set(fpu::Status::C0, c0);
+}
+
+/// Compare and set eflags, for FCOMI and FUCOMI.
+fn fcomi(cpu: &mut CPU, instr: &Instruction, quiet: bool) {
+    let x = cpu.fpu.st0();
+    let y = cpu.fpu.get(instr.op1_register());
+    let ord = cpu.fpu.calc(|env| x.compare(y, quiet, env));
+    // Unordered also sets PF, which isn't modeled.
+    let (zf, cf) = match ord {
+        Some(Ordering::Greater) => (false, false),
+        Some(Ordering::Less) => (false, true),
+        Some(Ordering::Equal) => (true, false),
+        None => (true, true),
     };
+    cpu.flags.set(Flags::ZF, zf);
+    cpu.flags.set(Flags::CF, cf);
+    cpu.fpu.status.set(fpu::Status::C1, false);
+}
+
+/// Push a constant from the FPU's ROM, given to 128 bits.
+fn fld_constant(cpu: &mut CPU, sig: u128, exp: i32) {
+    let val = F80::constant(sig, exp, cpu.fpu.rounding());
+    cpu.fpu.push(val);
 }
 
 pub fn fld1(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    cpu.fpu.push(1.0);
+    cpu.fpu.push(F80::ONE);
 }
 
 pub fn fldz(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    cpu.fpu.push(0.0);
+    cpu.fpu.push(F80::ZERO);
 }
 
 pub fn fldpi(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    cpu.fpu.push(std::f64::consts::PI);
+    fld_constant(cpu, 0xC90FDAA22168C234_C4C6628B80DC1CD1, 1);
 }
 
 pub fn fldl2e(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    cpu.fpu.push(std::f64::consts::LOG2_E);
+    fld_constant(cpu, 0xB8AA3B295C17F0BB_BE87FED0691D3E89, 0);
+}
+
+pub fn fldl2t(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    fld_constant(cpu, 0xD49A784BCD1B8AFE_492BF6FF4DAFDB4D, 1);
+}
+
+pub fn fldlg2(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    fld_constant(cpu, 0x9A209A84FBCFF798_8F8959AC0B7C9178, -2);
+}
+
+pub fn fldln2(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    fld_constant(cpu, 0xB17217F7D1CF79AB_C9E3B39803F2F6AF, -1);
 }
 
 pub fn fld_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
-    let x = *cpu.fpu.get(instr.op0_register());
+    let x = cpu.fpu.get(instr.op0_register());
     cpu.fpu.push(x);
 }
 
+pub fn fld_m80fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let bytes = mem.get_pod::<[u8; 10]>(x86_addr(cpu, mem, instr));
+    cpu.fpu.push(F80::from_bytes(bytes));
+}
+
 pub fn fld_m64fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    cpu.fpu.push(mem.get_pod::<f64>(x86_addr(cpu, instr)));
+    let x = m64fp(cpu, mem, instr);
+    cpu.fpu.push(x);
 }
 
 pub fn fld_m32fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    cpu.fpu
-        .push(mem.get_pod::<f32>(x86_addr(cpu, instr)) as f64);
+    let x = m32fp(cpu, mem, instr);
+    cpu.fpu.push(x);
 }
 
 pub fn fild_m64int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    cpu.fpu
-        .push(mem.get_pod::<i64>(x86_addr(cpu, instr)) as f64);
+    let x = mem.get_pod::<i64>(x86_addr(cpu, mem, instr));
+    cpu.fpu.push(F80::from_i64(x));
 }
 
 pub fn fild_m32int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    cpu.fpu
-        .push(mem.get_pod::<i32>(x86_addr(cpu, instr)) as f64);
+    let x = m32int(cpu, mem, instr);
+    cpu.fpu.push(x);
 }
 
 pub fn fild_m16int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    cpu.fpu
-        .push(mem.get_pod::<i16>(x86_addr(cpu, instr)) as f64);
+    let x = m16int(cpu, mem, instr);
+    cpu.fpu.push(x);
 }
 
 pub fn fst_m64fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let f = *cpu.fpu.st0();
-    mem.put::<f64>(x86_addr(cpu, instr), f);
+    let x = cpu.fpu.st0();
+    let f = cpu.fpu.calc(|env| x.to_f64(env));
+    mem.put::<f64>(x86_addr(cpu, mem, instr), f);
 }
 
 pub fn fst_m32fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let f = *cpu.fpu.st0();
-    mem.put::<f32>(x86_addr(cpu, instr), f as f32);
+    let x = cpu.fpu.st0();
+    let f = cpu.fpu.calc(|env| x.to_f32(env));
+    mem.put::<f32>(x86_addr(cpu, mem, instr), f);
+}
+
+pub fn fstp_m80fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let x = cpu.fpu.st0();
+    mem.put::<[u8; 10]>(x86_addr(cpu, mem, instr), x.to_bytes());
+    cpu.fpu.pop();
 }
 
 pub fn fstp_m64fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
@@ -94,21 +196,35 @@ pub fn fstp_m32fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     cpu.fpu.pop();
 }
 
-pub fn fstp_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
-    let f = *cpu.fpu.st0();
-    *cpu.fpu.get(instr.op0_register()) = f;
+pub fn fst_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    let f = cpu.fpu.st0();
+    cpu.fpu.set(instr.op0_register(), f);
+}
+
+pub fn fstp_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    fst_sti(cpu, mem, instr);
     cpu.fpu.pop();
 }
 
+/// Convert st(0) to an integer of a width for FIST, giving the integer
+/// indefinite (just the sign bit) if it's out of range.
+fn st0_int(cpu: &mut CPU, bits: u32) -> i64 {
+    let x = cpu.fpu.st0();
+    match cpu.fpu.calc(|env| x.to_int(bits, env)) {
+        Some(i) => i,
+        None => -1 << (bits - 1),
+    }
+}
+
 pub fn fistp_m64int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let f = *cpu.fpu.st0();
-    mem.put::<i64>(x86_addr(cpu, instr), f as i64);
+    let i = st0_int(cpu, 64);
+    mem.put::<i64>(x86_addr(cpu, mem, instr), i);
     cpu.fpu.pop();
 }
 
 pub fn fist_m32int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let f = *cpu.fpu.st0();
-    mem.put::<i32>(x86_addr(cpu, instr), f as i32);
+    let i = st0_int(cpu, 32);
+    mem.put::<i32>(x86_addr(cpu, mem, instr), i as i32);
 }
 
 pub fn fistp_m32int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
@@ -116,62 +232,98 @@ pub fn fistp_m32int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     cpu.fpu.pop();
 }
 
+pub fn fist_m16int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let i = st0_int(cpu, 16);
+    mem.put::<i16>(x86_addr(cpu, mem, instr), i as i16);
+}
+
 pub fn fistp_m16int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let f = *cpu.fpu.st0();
-    mem.put::<i16>(x86_addr(cpu, instr), f as i16);
+    fist_m16int(cpu, mem, instr);
     cpu.fpu.pop();
 }
 
 pub fn fchs(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    *cpu.fpu.st0() = -*cpu.fpu.st0();
+    let x = cpu.fpu.st0();
+    cpu.fpu.set_st0(x.neg());
+    cpu.fpu.status.set(fpu::Status::C1, false);
 }
 
 pub fn fabs(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    *cpu.fpu.st0() = num_traits::abs(*cpu.fpu.st0());
+    let x = cpu.fpu.st0();
+    cpu.fpu.set_st0(x.abs());
+    cpu.fpu.status.set(fpu::Status::C1, false);
+}
+
+/// The largest operand FSIN etc. accept; beyond it they set C2 and do nothing.
+const TRIG_LIMIT: f64 = 9223372036854775808.0; // 2^63
+
+/// Check the operand of FSIN etc. for being in range, setting C2 if not.
+fn trig_in_range(cpu: &mut CPU) -> bool {
+    let x = cpu.fpu.st0().to_f64_lossy();
+    let in_range = !(x.abs() >= TRIG_LIMIT);
+    cpu.fpu.status.set(fpu::Status::C2, !in_range);
+    in_range
 }
 
 pub fn fcos(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    let reg = cpu.fpu.st0();
-    *reg = reg.cos();
+    if trig_in_range(cpu) {
+        st0_f64(cpu, f64::cos);
+    }
 }
 
 pub fn fsin(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    let reg = cpu.fpu.st0();
-    *reg = reg.sin();
+    if trig_in_range(cpu) {
+        st0_f64(cpu, f64::sin);
+    }
 }
 
 pub fn fsincos(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    let val = *cpu.fpu.st0();
-    *cpu.fpu.st0() = val.sin();
-    cpu.fpu.push(val.cos());
+    if !trig_in_range(cpu) {
+        return;
+    }
+    let val = cpu.fpu.st0().to_f64_lossy();
+    st0_f64(cpu, f64::sin);
+    cpu.fpu.push(F80::from_f64(val.cos()));
+}
+
+pub fn fptan(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    if !trig_in_range(cpu) {
+        return;
+    }
+    st0_f64(cpu, f64::tan);
+    cpu.fpu.push(F80::ONE);
 }
 
 pub fn fpatan(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    let x = *cpu.fpu.st0();
+    let x = cpu.fpu.st0().to_f64_lossy();
+    cpu.fpu.pop();
+    st0_f64(cpu, |y| y.atan2(x));
+}
+
+pub fn fyl2x(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    let x = cpu.fpu.st0().to_f64_lossy();
     cpu.fpu.pop();
-    let reg = cpu.fpu.st0();
-    *reg = reg.atan2(x);
+    st0_f64(cpu, |y| y * x.log2());
 }
 
 pub fn fsqrt(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    let reg = cpu.fpu.st0();
-    *reg = reg.sqrt();
+    let x = cpu.fpu.st0();
+    let val = cpu.fpu.calc(|env| x.sqrt(env));
+    cpu.fpu.set_st0(val);
 }
 
 pub fn fadd_sti_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
-    let y = *cpu.fpu.get(instr.op1_register());
-    let x = cpu.fpu.get(instr.op0_register());
-    *x += y;
+    sti_op(cpu, instr, F80::add);
 }
 
 pub fn fadd_m64fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<f64>(x86_addr(cpu, instr));
-    *cpu.fpu.st0() += y;
+    let y = m64fp(cpu, mem, instr);
+    st0_op(cpu, y, F80::add);
 }
 
 pub fn fadd_m32fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<f32>(x86_addr(cpu, instr)) as f64;
-    *cpu.fpu.st0() += y;
+    let y = m32fp(cpu, mem, instr);
+    st0_op(cpu, y, F80::add);
 }
 
 pub fn faddp_sti_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
@@ -180,31 +332,27 @@ pub fn faddp_sti_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 }
 
 pub fn fiadd_m32int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<i32>(x86_addr(cpu, instr)) as f64;
-    *cpu.fpu.st0() += y;
+    let y = m32int(cpu, mem, instr);
+    st0_op(cpu, y, F80::add);
 }
 
 pub fn fiadd_m16int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<i16>(x86_addr(cpu, instr)) as f64;
-    *cpu.fpu.st0() += y;
+    let y = m16int(cpu, mem, instr);
+    st0_op(cpu, y, F80::add);
 }
 
 pub fn fsub_m64fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<f64>(x86_addr(cpu, instr));
-    let x = cpu.fpu.st0();
-    *x = *x - y;
+    let y = m64fp(cpu, mem, instr);
+    st0_op(cpu, y, F80::sub);
 }
 
 pub fn fsub_m32fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<f32>(x86_addr(cpu, instr)) as f64;
-    let x = cpu.fpu.st0();
-    *x = *x - y;
+    let y = m32fp(cpu, mem, instr);
+    st0_op(cpu, y, F80::sub);
 }
 
 pub fn fsub_sti_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
-    let y = *cpu.fpu.get(instr.op1_register());
-    let x = cpu.fpu.get(instr.op0_register());
-    *x -= y;
+    sti_op(cpu, instr, F80::sub);
 }
 
 pub fn fsubp_sti_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
@@ -213,87 +361,79 @@ pub fn fsubp_sti_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 }
 
 pub fn fisub_m32int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<i32>(x86_addr(cpu, instr)) as f64;
-    let x = cpu.fpu.st0();
-    *x = *x - y;
+    let y = m32int(cpu, mem, instr);
+    st0_op(cpu, y, F80::sub);
 }
 
 pub fn fsubr_m64fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<f64>(x86_addr(cpu, instr));
-    let x = cpu.fpu.st0();
-    *x = y - *x;
+    let y = m64fp(cpu, mem, instr);
+    st0_op(cpu, y, |x, y, env| y.sub(x, env));
 }
 
 pub fn fsubr_m32fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<f32>(x86_addr(cpu, instr)) as f64;
-    let x = cpu.fpu.st0();
-    *x = y - *x;
+    let y = m32fp(cpu, mem, instr);
+    st0_op(cpu, y, |x, y, env| y.sub(x, env));
 }
 
-pub fn fsubr_st0_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
-    let y = *cpu.fpu.get(instr.op1_register());
-    let x = cpu.fpu.st0();
-    *x = y - *x;
+pub fn fsubr_sti_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    sti_op(cpu, instr, |x, y, env| y.sub(x, env));
+}
+
+pub fn fsubrp_sti_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    fsubr_sti_sti(cpu, mem, instr);
+    cpu.fpu.pop();
 }
 
 pub fn fmul_m64fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<f64>(x86_addr(cpu, instr));
-    *cpu.fpu.st0() *= y;
+    let y = m64fp(cpu, mem, instr);
+    st0_op(cpu, y, F80::mul);
 }
 
 pub fn fmul_m32fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<f32>(x86_addr(cpu, instr)) as f64;
-    *cpu.fpu.st0() *= y;
+    let y = m32fp(cpu, mem, instr);
+    st0_op(cpu, y, F80::mul);
 }
 
 pub fn fimul_m32int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<i32>(x86_addr(cpu, instr)) as f64;
-    *cpu.fpu.st0() *= y;
+    let y = m32int(cpu, mem, instr);
+    st0_op(cpu, y, F80::mul);
 }
 
 pub fn fimul_m16int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<i16>(x86_addr(cpu, instr)) as f64;
-    *cpu.fpu.st0() *= y;
+    let y = m16int(cpu, mem, instr);
+    st0_op(cpu, y, F80::mul);
 }
 
 pub fn fmul_sti_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
-    let y = *cpu.fpu.get(instr.op1_register());
-    let x = cpu.fpu.get(instr.op0_register());
-    *x *= y;
+    sti_op(cpu, instr, F80::mul);
 }
 
-pub fn fmulp_sti_st0(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
-    let y = *cpu.fpu.st0();
-    let x = cpu.fpu.get(instr.op0_register());
-    *x *= y;
+pub fn fmulp_sti_st0(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    fmul_sti_sti(cpu, mem, instr);
     cpu.fpu.pop();
 }
 
 pub fn f2xm1(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    let x = cpu.fpu.st0();
-    *x = 2.0_f64.powf(*x) - 1.0;
+    st0_f64(cpu, |x| (x * std::f64::consts::LN_2).exp_m1());
 }
 
 pub fn fscale(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    let y = *cpu.fpu.get(iced_x86::Register::ST1);
-    let x = cpu.fpu.st0();
-    *x = *x * 2f64.powf(y.floor());
+    let y = cpu.fpu.get(Register::ST1);
+    st0_op(cpu, y, F80::scale);
 }
 
 pub fn fdiv_m64fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<f64>(x86_addr(cpu, instr));
-    *cpu.fpu.st0() /= y;
+    let y = m64fp(cpu, mem, instr);
+    st0_op(cpu, y, F80::div);
 }
 
 pub fn fdiv_m32fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<f32>(x86_addr(cpu, instr)) as f64;
-    *cpu.fpu.st0() /= y;
+    let y = m32fp(cpu, mem, instr);
+    st0_op(cpu, y, F80::div);
 }
 
 pub fn fdiv_sti_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
-    let y = *cpu.fpu.get(instr.op1_register());
-    let x = cpu.fpu.get(instr.op0_register());
-    *x = *x / y;
+    sti_op(cpu, instr, F80::div);
 }
 
 pub fn fdivp_sti_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
@@ -302,31 +442,27 @@ pub fn fdivp_sti_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 }
 
 pub fn fidiv_m32int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<i32>(x86_addr(cpu, instr)) as f64;
-    *cpu.fpu.st0() /= y;
+    let y = m32int(cpu, mem, instr);
+    st0_op(cpu, y, F80::div);
 }
 
 pub fn fidiv_m16int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<i16>(x86_addr(cpu, instr)) as f64;
-    *cpu.fpu.st0() /= y;
+    let y = m16int(cpu, mem, instr);
+    st0_op(cpu, y, F80::div);
 }
 
 pub fn fdivr_m64fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<f64>(x86_addr(cpu, instr));
-    let x = cpu.fpu.st0();
-    *x = y / *x;
+    let y = m64fp(cpu, mem, instr);
+    st0_op(cpu, y, |x, y, env| y.div(x, env));
 }
 
 pub fn fdivr_m32fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<f32>(x86_addr(cpu, instr)) as f64;
-    let x = cpu.fpu.st0();
-    *x = y / *x;
+    let y = m32fp(cpu, mem, instr);
+    st0_op(cpu, y, |x, y, env| y.div(x, env));
 }
 
 pub fn fdivr_sti_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
-    let y = *cpu.fpu.get(instr.op1_register());
-    let x = cpu.fpu.get(instr.op0_register());
-    *x = y / *x;
+    sti_op(cpu, instr, |x, y, env| y.div(x, env));
 }
 
 pub fn fdivrp_sti_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
@@ -335,25 +471,40 @@ pub fn fdivrp_sti_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 }
 
 pub fn fidivr_m32int(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let y = mem.get_pod::<i32>(x86_addr(cpu, instr)) as f64;
+    let y = m32int(cpu, mem, instr);
+    st0_op(cpu, y, |x, y, env| y.div(x, env));
+}
+
+/// FPREM and FPREM1: C2 is set if the reduction is incomplete, and otherwise
+/// C0, C3 and C1 are the low bits of the quotient.
+fn fprem_common(cpu: &mut CPU, nearest: bool) {
+    let y = cpu.fpu.get(Register::ST1);
     let x = cpu.fpu.st0();
-    *x = y / *x;
+    let (val, q, complete) = cpu.fpu.calc(|env| x.rem(y, nearest, env));
+    cpu.fpu.set_st0(val);
+    cpu.fpu.status.set(fpu::Status::C2, !complete);
+    cpu.fpu.status.set(fpu::Status::C0, q & 4 != 0);
+    cpu.fpu.status.set(fpu::Status::C3, q & 2 != 0);
+    cpu.fpu.status.set(fpu::Status::C1, q & 1 != 0);
 }
 
 pub fn fprem(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    let y = *cpu.fpu.get(iced_x86::Register::ST1);
-    let x = cpu.fpu.st0();
-    *x = *x % y;
+    fprem_common(cpu, false);
+}
+
+pub fn fprem1(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    fprem_common(cpu, true);
 }
 
 pub fn fxch_st0_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
     cpu.fpu.swap(instr.op0_register(), instr.op1_register());
+    cpu.fpu.status.set(fpu::Status::C1, false);
 }
 
 pub fn fcom_m32fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let x = *cpu.fpu.st0();
-    let y = mem.get_pod::<f32>(x86_addr(cpu, instr)) as f64;
-    fcom(cpu, x, y);
+    let x = cpu.fpu.st0();
+    let y = F80::from_f32(mem.get_pod::<f32>(x86_addr(cpu, mem, instr)));
+    fcom(cpu, x, y, false);
 }
 
 pub fn fcomp_m32fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
@@ -361,44 +512,96 @@ pub fn fcomp_m32fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     cpu.fpu.pop();
 }
 
+pub fn fcom_m64fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let x = cpu.fpu.st0();
+    let y = F80::from_f64(mem.get_pod::<f64>(x86_addr(cpu, mem, instr)));
+    fcom(cpu, x, y, false);
+}
+
 pub fn fcomp_m64fp(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    let x = *cpu.fpu.st0();
-    let y = mem.get_pod::<f64>(x86_addr(cpu, instr));
-    fcom(cpu, x, y);
+    fcom_m64fp(cpu, mem, instr);
     cpu.fpu.pop();
 }
 
-pub fn fcomp_st0_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
-    let x = *cpu.fpu.st0();
-    let y = *cpu.fpu.get(instr.op1_register());
-    fcom(cpu, x, y);
+pub fn fcom_st0_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    let x = cpu.fpu.st0();
+    let y = cpu.fpu.get(instr.op1_register());
+    fcom(cpu, x, y, false);
+}
+
+pub fn fcomp_st0_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    fcom_st0_sti(cpu, mem, instr);
+    cpu.fpu.pop();
+}
+
+pub fn fcompp(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    let x = cpu.fpu.st0();
+    let y = cpu.fpu.get(Register::ST1);
+    fcom(cpu, x, y, false);
+    cpu.fpu.pop();
     cpu.fpu.pop();
 }
 
+pub fn fucom_st0_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    let x = cpu.fpu.st0();
+    let y = cpu.fpu.get(instr.op1_register());
+    fcom(cpu, x, y, true);
+}
+
 pub fn fucomp_st0_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    fcomp_st0_sti(cpu, mem, instr);
-    // TODO: raise the invalid-arithmetic-operand exception when appropriate.
+    fucom_st0_sti(cpu, mem, instr);
+    cpu.fpu.pop();
 }
 
-pub fn fcomi_st0_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
-    let x = *cpu.fpu.st0();
-    let y = *cpu.fpu.get(instr.op1_register());
-    //cpu.flags.set(Flags::PF, false);
-    if x > y {
-        cpu.flags.set(Flags::ZF, false);
-        cpu.flags.set(Flags::CF, false);
-    } else if x < y {
-        cpu.flags.set(Flags::ZF, false);
-        cpu.flags.set(Flags::CF, true);
+pub fn fucompp(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    let x = cpu.fpu.st0();
+    let y = cpu.fpu.get(Register::ST1);
+    fcom(cpu, x, y, true);
+    cpu.fpu.pop();
+    cpu.fpu.pop();
+}
+
+pub fn ftst(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    let x = cpu.fpu.st0();
+    fcom(cpu, x, F80::ZERO, false);
+}
+
+/// Classify st(0) in C3, C2 and C0, with its sign in C1.
+pub fn fxam(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    use fpu::Status;
+    let x = cpu.fpu.st[cpu.fpu.st_top.min(7)];
+    let class = if cpu.fpu.is_empty(Register::ST0) {
+        Status::C3 | Status::C0
     } else {
-        cpu.flags.set(Flags::ZF, true);
-        cpu.flags.set(Flags::CF, false);
-    }
+        let int_bit = x.mant & (1 << 63) != 0;
+        match x.se & 0x7FFF {
+            0x7FFF if x.mant == 1 << 63 => Status::C2 | Status::C0, // infinity
+            0x7FFF if int_bit => Status::C0,                        // NaN
+            0 if x.mant == 0 => Status::C3,                         // zero
+            0 => Status::C3 | Status::C2,                           // denormal
+            0x7FFF => Status::empty(),                              // unsupported
+            _ if int_bit => Status::C2,                             // normal
+            _ => Status::empty(),                                   // unsupported
+        }
+    };
+    cpu.fpu
+        .status
+        .remove(Status::C3 | Status::C2 | Status::C1 | Status::C0);
+    cpu.fpu.status |= class;
+    cpu.fpu.status.set(Status::C1, x.se & 0x8000 != 0);
 }
 
-pub fn fucomi_st0_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+pub fn fcomi_st0_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    fcomi(cpu, instr, false);
+}
+
+pub fn fcomip_st0_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     fcomi_st0_sti(cpu, mem, instr);
-    // TODO: raise the invalid-arithmetic-operand exception when appropriate.
+    cpu.fpu.pop();
+}
+
+pub fn fucomi_st0_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    fcomi(cpu, instr, true);
 }
 
 pub fn fucomip_st0_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
@@ -408,29 +611,32 @@ pub fn fucomip_st0_sti(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 
 pub fn frndint(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
     let x = cpu.fpu.st0();
-    *x = x.round();
+    let val = cpu.fpu.calc(|env| x.round_int(env));
+    cpu.fpu.set_st0(val);
 }
 
 pub fn fnstsw_ax(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
-    // TODO: does this need stack top in it?
-    cpu.regs.set32(Register::EAX, cpu.fpu.status.bits() as u32);
+    cpu.regs.set16(Register::AX, cpu.fpu.status_word());
+}
+
+pub fn fnstsw_m2byte(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let addr = x86_addr(cpu, mem, instr);
+    mem.put::<u16>(addr, cpu.fpu.status_word());
 }
 
 pub fn fnstcw_m2byte(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    // TODO: control word
-    let cw = 0x37u16; // default value
-    let addr = x86_addr(cpu, instr);
-    mem.put::<u16>(addr, cw);
+    let addr = x86_addr(cpu, mem, instr);
+    mem.put::<u16>(addr, cpu.fpu.control);
 }
 
 pub fn fldcw_m2byte(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
-    // TODO: control word
-    mem.get_pod::<u16>(x86_addr(cpu, instr));
+    // Bit 6 is reserved and always reads as set.
+    cpu.fpu.control = mem.get_pod::<u16>(x86_addr(cpu, mem, instr)) | 0x40;
 }
 
 pub fn fcmovnbe_st0_sti(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
     if !cpu.flags.contains(Flags::CF) && !cpu.flags.contains(Flags::ZF) {
-        let y = *cpu.fpu.get(instr.op1_register());
-        *cpu.fpu.st0() = y;
+        let y = cpu.fpu.get(instr.op1_register());
+        cpu.fpu.set_st0(y);
     }
 }
@@ -1,6 +1,9 @@
 //! Functions for common behaviors across all operations.
 
-use crate::{x86::CPU, Register};
+use crate::{
+    x86::{Exception, CPU},
+    Register,
+};
 use memory::{Extensions, Mem};
 
 // TODO: maybe there are no 64-bit memory reads needed (?)
@@ -18,7 +21,7 @@ pub fn rm64_x(
             cpu.regs.set64(reg, value);
         }
         iced_x86::OpKind::Memory => {
-            let addr = x86_addr(cpu, instr);
+            let addr = x86_addr(cpu, mem, instr);
             let x = mem.get_pod::<u64>(addr);
             let value = op(cpu, x);
             mem.put::<u64>(addr, value);
@@ -55,7 +58,7 @@ pub fn rm32<'a>(cpu: &'a mut CPU, mem: Mem, instr: &iced_x86::Instruction) -> Ar
             Arg(cpu.regs.get32_mut(reg))
         }
         iced_x86::OpKind::Memory => {
-            let addr = x86_addr(cpu, instr);
+            let addr = x86_addr(cpu, mem, instr);
             Arg(mem.ptr_mut::<u32>(addr))
         }
         _ => unimplemented!(),
@@ -69,7 +72,7 @@ pub fn rm16<'a>(cpu: &'a mut CPU, mem: Mem, instr: &iced_x86::Instruction) -> Ar
             Arg(cpu.regs.get16_mut(reg))
         }
         iced_x86::OpKind::Memory => {
-            let addr = x86_addr(cpu, instr);
+            let addr = x86_addr(cpu, mem, instr);
             Arg(mem.ptr_mut::<u16>(addr))
         }
         _ => unimplemented!(),
@@ -83,11 +86,7 @@ pub fn rm8<'a>(cpu: &'a mut CPU, mem: Mem, instr: &iced_x86::Instruction) -> Arg
             Arg(cpu.regs.get8_mut(reg))
         }
         iced_x86::OpKind::Memory => {
-            let mut addr = x86_addr(cpu, instr);
-            if mem.is_oob::<u8>(addr) {
-                cpu.err(format!("oob at {addr:x}"));
-                addr = 0;
-            }
+            let addr = x86_addr(cpu, mem, instr);
             Arg(mem.ptr_mut::<u8>(addr))
         }
         _ => unimplemented!(),
@@ -97,7 +96,7 @@ pub fn rm8<'a>(cpu: &'a mut CPU, mem: Mem, instr: &iced_x86::Instruction) -> Arg
 pub fn op1_rm32(cpu: &mut CPU, mem: Mem, instr: &iced_x86::Instruction) -> u32 {
     match instr.op1_kind() {
         iced_x86::OpKind::Register => cpu.regs.get32(instr.op1_register()),
-        iced_x86::OpKind::Memory => mem.get_pod::<u32>(x86_addr(cpu, instr)),
+        iced_x86::OpKind::Memory => mem.get_pod::<u32>(x86_addr(cpu, mem, instr)),
         _ => unreachable!(),
     }
 }
@@ -105,7 +104,7 @@ pub fn op1_rm32(cpu: &mut CPU, mem: Mem, instr: &iced_x86::Instruction) -> u32 {
 pub fn op1_rm16(cpu: &mut CPU, mem: Mem, instr: &iced_x86::Instruction) -> u16 {
     match instr.op1_kind() {
         iced_x86::OpKind::Register => cpu.regs.get16(instr.op1_register()),
-        iced_x86::OpKind::Memory => mem.get_pod::<u16>(x86_addr(cpu, instr)),
+        iced_x86::OpKind::Memory => mem.get_pod::<u16>(x86_addr(cpu, mem, instr)),
         _ => unreachable!(),
     }
 }
@@ -113,63 +112,223 @@ pub fn op1_rm16(cpu: &mut CPU, mem: Mem, instr: &iced_x86::Instruction) -> u16 {
 pub fn op1_rm8(cpu: &mut CPU, mem: Mem, instr: &iced_x86::Instruction) -> u8 {
     match instr.op1_kind() {
         iced_x86::OpKind::Register => cpu.regs.get8(instr.op1_register()),
-        iced_x86::OpKind::Memory => mem.get_pod::<u8>(x86_addr(cpu, instr)),
+        iced_x86::OpKind::Memory => mem.get_pod::<u8>(x86_addr(cpu, mem, instr)),
         _ => unreachable!(),
     }
 }
 
-/// Push a u32 on the x86 stack.
+/// The stack pointer after moving it by delta bytes, and the address it then points
+/// at.  A 16-bit stack moves SP within its segment and leaves ESP's high word alone.
+fn stack_move(cpu: &CPU, delta: i32) -> (u32, u32) {
+    let esp = cpu.regs.get32(Register::ESP);
+    if cpu.regs.stack16 {
+        let sp = (esp as u16).wrapping_add(delta as u16);
+        let base = cpu.regs.seg_base(Register::SS);
+        (
+            (esp & 0xFFFF_0000) | sp as u32,
+            base.wrapping_add(sp as u32),
+        )
+    } else {
+        let esp = esp.wrapping_add(delta as u32);
+        (esp, esp)
+    }
+}
+
+/// The address of the top of the x86 stack, which for a 16-bit stack is SS:SP.
+pub fn stack_addr(cpu: &CPU) -> u32 {
+    stack_move(cpu, 0).1
+}
+
+/// Drop bytes off the x86 stack, as a ret with an immediate does after popping.
+pub fn release_stack(cpu: &mut CPU, bytes: u32) {
+    let (esp, _) = stack_move(cpu, bytes as i32);
+    cpu.regs.set32(Register::ESP, esp);
+}
+
+/// Push a u32 on the x86 stack.  If the stack memory isn't accessible, as when
+/// the stack reaches its guard page, this raises an access violation instead and
+/// leaves ESP as it was, so the instruction can be retried.
 pub fn push(cpu: &mut CPU, mem: Mem, value: u32) {
-    let esp = cpu.regs.get32_mut(Register::ESP);
-    *esp -= 4;
-    mem.put::<u32>(*esp, value);
+    let (esp, addr) = stack_move(cpu, -4);
+    if !check_access(cpu, mem, addr, 4, true) {
+        return;
+    }
+    cpu.regs.set32(Register::ESP, esp);
+    mem.put::<u32>(addr, value);
 }
 
-/// Push a u16 on the x86 stack.
+/// Push a u16 on the x86 stack, faulting like push().
 pub fn push16(cpu: &mut CPU, mem: Mem, value: u16) {
-    let esp = cpu.regs.get32_mut(Register::ESP);
-    *esp -= 2;
-    mem.put::<u16>(*esp, value);
+    let (esp, addr) = stack_move(cpu, -2);
+    if !check_access(cpu, mem, addr, 2, true) {
+        return;
+    }
+    cpu.regs.set32(Register::ESP, esp);
+    mem.put::<u16>(addr, value);
+}
+
+/// Push a u32 on the x86 stack on the emulator's behalf, like the arguments of a
+/// call into x86 code, where there's no instruction to fault.  The stack only
+/// needs to be backed by the host, not committed.
+pub fn push_unchecked(cpu: &mut CPU, mem: Mem, value: u32) {
+    let (esp, addr) = stack_move(cpu, -4);
+    cpu.regs.set32(Register::ESP, esp);
+    mem.put::<u32>(addr, value);
 }
 
 /// Pop a u32 from the x86 stack.
 pub fn pop(cpu: &mut CPU, mem: Mem) -> u32 {
-    let esp = cpu.regs.get32_mut(Register::ESP);
-    let value = mem.get_pod::<u32>(*esp);
-    *esp += 4;
+    let value = mem.get_pod::<u32>(stack_addr(cpu));
+    release_stack(cpu, 4);
     value
 }
 
 /// Pop a u16 from the x86 stack.
 pub fn pop16(cpu: &mut CPU, mem: Mem) -> u16 {
-    let esp = cpu.regs.get32_mut(Register::ESP);
-    let value = mem.get_pod::<u16>(*esp);
-    *esp += 2;
+    let value = mem.get_pod::<u16>(stack_addr(cpu));
+    release_stack(cpu, 2);
     value
 }
 
+/// The base address of the segment an instruction's memory operand is in.
+/// 16-bit code uses the bases cached from the segments' descriptors.  In 32-bit code
+/// only FS, which points at the thread's TEB, has a nonzero base; this is also
+/// the segment an override prefix gives string instructions' ESI operand.
+pub fn segment_base(cpu: &CPU, instr: &iced_x86::Instruction) -> u32 {
+    if cpu.regs.code16 {
+        return cpu.regs.seg_base(instr.memory_segment());
+    }
+    match instr.segment_prefix() {
+        iced_x86::Register::FS => cpu.regs.fs_addr,
+        _ => 0,
+    }
+}
+
+/// Look up the base of the segment an LDT selector selects, and whether the
+/// segment is 16-bit, from its descriptor.
+fn ldt_descriptor(cpu: &CPU, mem: Mem, selector: u16) -> Option<(u32, bool)> {
+    let (ldt, limit) = cpu.ldt;
+    let ofs = (selector & !7) as u32;
+    if ofs + 7 > limit {
+        return None;
+    }
+    let desc = mem.get_pod::<u64>(ldt + ofs);
+    let present = desc & (1 << 47) != 0;
+    if !present {
+        return None;
+    }
+    let base = ((desc >> 16) & 0xFF_FFFF) as u32 | (((desc >> 56) as u32) << 24);
+    let big = desc & (1 << 54) != 0;
+    Some((base, !big))
+}
+
+/// Load a segment register with a selector, caching the base of the segment it
+/// selects as protected mode does.  LDT selectors give the segments of 16-bit code;
+/// others, including null, select the flat 32-bit space as Windows' GDT ones do.
+/// An LDT selector past the table's end or for a segment that isn't present raises
+/// a general protection fault and leaves the register as it was.
+pub fn load_segment(cpu: &mut CPU, mem: Mem, reg: Register, selector: u16) -> bool {
+    let (base, is16) = if selector & 4 == 0 {
+        (0, false)
+    } else {
+        match ldt_descriptor(cpu, mem, selector) {
+            Some(desc) => desc,
+            None => {
+                cpu.raise(Exception::GeneralProtection { selector });
+                return false;
+            }
+        }
+    };
+    cpu.regs.set16(reg, selector);
+    cpu.regs.set_seg_base(reg, base);
+    match reg {
+        Register::CS => cpu.regs.code16 = is16,
+        Register::SS => cpu.regs.stack16 = is16,
+        _ => {}
+    }
+    true
+}
+
+/// IP, the offset of EIP within the code segment.
+pub fn ip16(cpu: &CPU) -> u16 {
+    cpu.regs.eip.wrapping_sub(cpu.regs.seg_base(Register::CS)) as u16
+}
+
+/// Jump to selector:offset, loading CS.
+pub fn far_jmp(cpu: &mut CPU, mem: Mem, selector: u16, offset: u16) {
+    if !load_segment(cpu, mem, Register::CS, selector) {
+        return;
+    }
+    let base = cpu.regs.seg_base(Register::CS);
+    x86_jmp(cpu, base.wrapping_add(offset as u32))
+}
+
 /// Compute the address found in instructions that reference memory, e.g.
 ///   mov [eax+03h],...
-pub fn x86_addr(cpu: &CPU, instr: &iced_x86::Instruction) -> u32 {
-    let mut addr = instr.memory_displacement32();
-
+/// An address in the null page or past the end of memory raises an access violation,
+/// and the instruction finishes against the (unused) null page instead.
+pub fn x86_addr(cpu: &mut CPU, mem: Mem, instr: &iced_x86::Instruction) -> u32 {
     // A full address is
     //    segment:[base + index*scale + displacement]
-    // but most references don't use most of these, so we conditionally add them
-    // in the following blocks.
-    //
     // We use wrapping_add().  In general these operations aren't written to
     // wrap, but in some cases the components are negative which is implemented
     // in two's complement by a wrapping add.
+    let addr = segment_base(cpu, instr).wrapping_add(x86_offset(cpu, instr));
+    let size = (instr.memory_size().size() as u32).max(1);
+    if is_unmapped(mem, addr, size) || (cpu.writing && !mem.is_writable(addr, size)) {
+        access_violation(cpu, instr, addr);
+        return 0;
+    }
+    cpu.last_addr = addr;
+    addr
+}
 
-    // TODO: see comments on regs.fs_addr.
-    match instr.segment_prefix() {
-        iced_x86::Register::FS => {
-            let seg = cpu.regs.fs_addr;
-            addr = addr.wrapping_add(seg);
-        }
-        _ => {}
+/// Addresses below this are never mapped, to catch null pointer accesses.
+const NULL_POINTER_REGION_SIZE: u32 = 0x1000;
+
+pub fn is_unmapped(mem: Mem, addr: u32, size: u32) -> bool {
+    addr < NULL_POINTER_REGION_SIZE
+        || addr.checked_add(size).map_or(true, |end| end > mem.len())
+        || !mem.is_committed(addr, size)
+}
+
+/// Check an access an instruction makes other than through its memory operand,
+/// like a string instruction's ESI and EDI, raising an access violation if it faults.
+pub fn check_access(cpu: &mut CPU, mem: Mem, addr: u32, size: u32, write: bool) -> bool {
+    if is_unmapped(mem, addr, size) || (write && !mem.is_writable(addr, size)) {
+        cpu.raise(Exception::AccessViolation { addr, write });
+        return false;
     }
+    true
+}
+
+#[cold]
+fn access_violation(cpu: &mut CPU, instr: &iced_x86::Instruction, addr: u32) {
+    // Only now that it matters, work out whether the faulting access was a write.
+    let mut factory = iced_x86::InstructionInfoFactory::new();
+    let write = factory.info(instr).used_memory().iter().any(|m| {
+        !matches!(
+            m.access(),
+            iced_x86::OpAccess::Read
+                | iced_x86::OpAccess::CondRead
+                | iced_x86::OpAccess::NoMemAccess
+        )
+    });
+    // TODO: the instruction still completes against the substitute address, so any
+    // other effects it has (like a push's ESP change) happen before the fault.
+    cpu.raise(Exception::AccessViolation { addr, write });
+}
+
+/// The offset part of a memory operand's address, within its segment, as LEA computes.
+pub fn x86_offset(cpu: &CPU, instr: &iced_x86::Instruction) -> u32 {
+    if cpu.regs.code16 && is_addr16(instr) {
+        return x86_offset16(cpu, instr) as u32;
+    }
+
+    let mut addr = instr.memory_displacement32();
+
+    // Most references don't use most of the components, so we conditionally
+    // add them in the following blocks.
 
     if instr.memory_base() != iced_x86::Register::None {
         let base = cpu.regs.get32(instr.memory_base());
@@ -187,6 +346,26 @@ pub fn x86_addr(cpu: &CPU, instr: &iced_x86::Instruction) -> u32 {
     addr
 }
 
+/// Whether a memory operand uses 16-bit addressing, as in 16-bit code without an
+/// address size prefix.
+fn is_addr16(instr: &iced_x86::Instruction) -> bool {
+    instr.memory_base().is_gpr16()
+        || instr.memory_index().is_gpr16()
+        || instr.memory_displ_size() == 2
+}
+
+/// x86_offset for 16-bit addressing, where the offset wraps within the segment.
+fn x86_offset16(cpu: &CPU, instr: &iced_x86::Instruction) -> u16 {
+    let mut addr = instr.memory_displacement32() as u16;
+    if instr.memory_base() != iced_x86::Register::None {
+        addr = addr.wrapping_add(cpu.regs.get16(instr.memory_base()));
+    }
+    if instr.memory_index() != iced_x86::Register::None {
+        addr = addr.wrapping_add(cpu.regs.get16(instr.memory_index()));
+    }
+    addr
+}
+
 pub fn x86_jmp(cpu: &mut CPU, addr: u32) {
     if addr < 0x1000 {
         cpu.err(format!("jmp to null page addr={addr:x}"));
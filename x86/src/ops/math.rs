@@ -1,5 +1,5 @@
 use super::helpers::*;
-use crate::{registers::Flags, x86::CPU};
+use crate::{registers::Flags, x86::CPU, Exception};
 use iced_x86::{Instruction, Register};
 use memory::Mem;
 use num_traits::ops::overflowing::OverflowingSub;
@@ -575,6 +575,37 @@ pub fn add_r8_rm8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     x.set(add(x.get(), y, &mut cpu.flags));
 }
 
+pub fn xadd_rm32_r32(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let r1 = instr.op1_register();
+    let y = cpu.regs.get32(r1);
+    let x = rm32(cpu, mem, instr);
+    let tmp = x.get();
+    let sum = add(tmp, y, &mut cpu.flags);
+    // Store the register first, so that xadd eax,eax leaves the sum.
+    cpu.regs.set32(r1, tmp);
+    x.set(sum);
+}
+
+pub fn xadd_rm16_r16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let r1 = instr.op1_register();
+    let y = cpu.regs.get16(r1);
+    let x = rm16(cpu, mem, instr);
+    let tmp = x.get();
+    let sum = add(tmp, y, &mut cpu.flags);
+    cpu.regs.set16(r1, tmp);
+    x.set(sum);
+}
+
+pub fn xadd_rm8_r8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let r1 = instr.op1_register();
+    let y = cpu.regs.get8(r1);
+    let x = rm8(cpu, mem, instr);
+    let tmp = x.get();
+    let sum = add(tmp, y, &mut cpu.flags);
+    cpu.regs.set8(r1, tmp);
+    x.set(sum);
+}
+
 pub fn adc_rm32_rm32(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     let y = op1_rm32(cpu, mem, instr);
     let carry = cpu.flags.contains(Flags::CF);
@@ -812,16 +843,26 @@ pub fn imul_r32_rm32_imm8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 pub fn idiv_rm32(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     let x = get_edx_eax(cpu) as i64;
     let y = rm32(cpu, mem, instr).get() as i32 as i64;
-    cpu.regs.set32(Register::EAX, (x / y) as i32 as u32);
+    if y == 0 {
+        return cpu.raise(Exception::DivideByZero);
+    }
+    let quotient = match x.checked_div(y) {
+        Some(q) if q == q as i32 as i64 => q,
+        _ => return cpu.raise(Exception::DivideOverflow),
+    };
+    cpu.regs.set32(Register::EAX, quotient as i32 as u32);
     cpu.regs.set32(Register::EDX, (x % y) as i32 as u32);
 }
 
 pub fn idiv_rm16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     let x = get_dx_ax(cpu) as i32;
     let y = rm16(cpu, mem, instr).get() as i16 as i32;
+    if y == 0 {
+        return cpu.raise(Exception::DivideByZero);
+    }
     let quotient = x / y;
     if quotient > 0x7FFF || quotient < -0x8000 {
-        panic!("divide error");
+        return cpu.raise(Exception::DivideOverflow);
     }
     cpu.regs.set16(Register::AX, quotient as i16 as u16);
     cpu.regs.set16(Register::DX, (x % y) as u16);
@@ -830,18 +871,30 @@ pub fn idiv_rm16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 pub fn idiv_rm8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     let x = cpu.regs.get16(Register::AX) as i16;
     let y = rm8(cpu, mem, instr).get() as i8 as i16;
-    let quotient = x / y;
+    if y == 0 {
+        return cpu.raise(Exception::DivideByZero);
+    }
+    // -0x8000 / -1 overflows even i16, so compute in i32.
+    let quotient = x as i32 / y as i32;
     if quotient > 0x7F || quotient < -0x80 {
-        panic!("divide error");
+        return cpu.raise(Exception::DivideOverflow);
     }
     let rem = x % y;
-    cpu.regs
-        .set16(Register::AX, ((rem << 8) as u16) | (quotient as i8 as u16));
+    cpu.regs.set16(
+        Register::AX,
+        ((rem << 8) as u16) | (quotient as i8 as u8 as u16),
+    );
 }
 
 pub fn div_rm32(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     let x = get_edx_eax(cpu);
     let y = rm32(cpu, mem, instr).get() as u64;
+    if y == 0 {
+        return cpu.raise(Exception::DivideByZero);
+    }
+    if x / y > 0xFFFF_FFFF {
+        return cpu.raise(Exception::DivideOverflow);
+    }
     cpu.regs.set32(Register::EAX, (x / y) as u32);
     cpu.regs.set32(Register::EDX, (x % y) as u32);
     // No flags.
@@ -850,38 +903,58 @@ pub fn div_rm32(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 pub fn div_rm16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     let x = get_dx_ax(cpu);
     let y = rm16(cpu, mem, instr).get() as u32;
-    cpu.regs.set32(Register::EAX, ((x / y) as u16) as u32);
-    cpu.regs.set32(Register::EDX, ((x % y) as u16) as u32);
+    if y == 0 {
+        return cpu.raise(Exception::DivideByZero);
+    }
+    if x / y > 0xFFFF {
+        return cpu.raise(Exception::DivideOverflow);
+    }
+    cpu.regs.set16(Register::AX, (x / y) as u16);
+    cpu.regs.set16(Register::DX, (x % y) as u16);
     // No flags.
 }
 
 pub fn div_rm8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     let x = cpu.regs.get16(Register::AX);
     let y = rm8(cpu, mem, instr).get() as u16;
-    cpu.regs
-        .set32(Register::EAX, (((x % y) as u32) << 16) | ((x / y) as u32));
+    if y == 0 {
+        return cpu.raise(Exception::DivideByZero);
+    }
+    if x / y > 0xFF {
+        return cpu.raise(Exception::DivideOverflow);
+    }
+    cpu.regs.set16(Register::AX, ((x % y) << 8) | (x / y));
     // No flags.
 }
 
+fn dec<I: Int + OverflowingSub + num_traits::WrappingAdd>(x: I, flags: &mut Flags) -> I {
+    // Like sub(1), but CF is preserved.
+    let cf = flags.contains(Flags::CF);
+    let result = sub(x, I::one(), flags);
+    flags.set(Flags::CF, cf);
+    result
+}
+
 pub fn dec_rm32(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     let x = rm32(cpu, mem, instr);
-    x.set(sub(x.get(), 1, &mut cpu.flags));
+    x.set(dec(x.get(), &mut cpu.flags));
 }
 
 pub fn dec_rm16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     let x = rm16(cpu, mem, instr);
-    x.set(sub(x.get(), 1, &mut cpu.flags));
+    x.set(dec(x.get(), &mut cpu.flags));
 }
 
 pub fn dec_rm8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     let x = rm8(cpu, mem, instr);
-    x.set(sub(x.get(), 1, &mut cpu.flags));
+    x.set(dec(x.get(), &mut cpu.flags));
 }
 
 fn inc<I: Int + num_traits::WrappingAdd>(x: I, flags: &mut Flags) -> I {
     // Note this is not add(1) because CF should be preserved.
     let result = x.wrapping_add(&I::one());
-    flags.set(Flags::OF, result.is_zero());
+    // Overflow only when incrementing up to the most negative value.
+    flags.set(Flags::OF, result == I::one() << (I::bits() - 1));
     flags.set(Flags::SF, (result >> (I::bits() - 1)).is_one());
     flags.set(Flags::ZF, result.is_zero());
     result
@@ -905,6 +978,7 @@ pub fn inc_rm8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 fn neg<I: Int + OverflowingSub>(x: I, flags: &mut Flags) -> I {
     let (res, of) = I::zero().overflowing_sub(&x);
     flags.set(Flags::ZF, res.is_zero());
+    flags.set(Flags::SF, (res >> (I::bits() - 1)).is_one());
     flags.set(Flags::CF, !res.is_zero());
     flags.set(Flags::OF, of);
     res
@@ -939,3 +1013,188 @@ pub fn not_rm8(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     let x = rm8(cpu, mem, instr);
     x.set(!x.get())
 }
+
+/// Set SF, ZF and PF from AL, as the BCD adjustments do.
+fn bcd_flags(al: u8, flags: &mut Flags) {
+    flags.set(Flags::SF, al & 0x80 != 0);
+    flags.set(Flags::ZF, al == 0);
+    flags.set(Flags::PF, al.count_ones().is_multiple_of(2));
+}
+
+pub fn aaa(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    let mut ax = cpu.regs.get16(Register::AX);
+    let adjust = ax & 0xF > 9 || cpu.flags.contains(Flags::AF);
+    if adjust {
+        ax = ax.wrapping_add(0x106);
+    }
+    cpu.flags.set(Flags::AF, adjust);
+    cpu.flags.set(Flags::CF, adjust);
+    cpu.regs.set16(Register::AX, ax & 0xFF0F);
+}
+
+pub fn aas(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    let mut ax = cpu.regs.get16(Register::AX);
+    let adjust = ax & 0xF > 9 || cpu.flags.contains(Flags::AF);
+    if adjust {
+        ax = ax.wrapping_sub(6).wrapping_sub(0x100);
+    }
+    cpu.flags.set(Flags::AF, adjust);
+    cpu.flags.set(Flags::CF, adjust);
+    cpu.regs.set16(Register::AX, ax & 0xFF0F);
+}
+
+pub fn aam_imm8(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    let base = instr.immediate8();
+    if base == 0 {
+        return cpu.raise(Exception::DivideByZero);
+    }
+    let al = cpu.regs.get8(Register::AL);
+    cpu.regs.set8(Register::AH, al / base);
+    cpu.regs.set8(Register::AL, al % base);
+    bcd_flags(al % base, &mut cpu.flags);
+}
+
+pub fn aad_imm8(cpu: &mut CPU, _mem: Mem, instr: &Instruction) {
+    let base = instr.immediate8();
+    let al = cpu
+        .regs
+        .get8(Register::AL)
+        .wrapping_add(cpu.regs.get8(Register::AH).wrapping_mul(base));
+    cpu.regs.set16(Register::AX, al as u16);
+    bcd_flags(al, &mut cpu.flags);
+}
+
+pub fn daa(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    let old_al = cpu.regs.get8(Register::AL);
+    let old_cf = cpu.flags.contains(Flags::CF);
+    let mut al = old_al;
+    let mut cf = false;
+    if al & 0xF > 9 || cpu.flags.contains(Flags::AF) {
+        let (sum, carry) = al.overflowing_add(6);
+        al = sum;
+        cf = old_cf || carry;
+        cpu.flags.insert(Flags::AF);
+    } else {
+        cpu.flags.remove(Flags::AF);
+    }
+    if old_al > 0x99 || old_cf {
+        al = al.wrapping_add(0x60);
+        cf = true;
+    }
+    cpu.flags.set(Flags::CF, cf);
+    cpu.regs.set8(Register::AL, al);
+    bcd_flags(al, &mut cpu.flags);
+}
+
+pub fn das(cpu: &mut CPU, _mem: Mem, _instr: &Instruction) {
+    let old_al = cpu.regs.get8(Register::AL);
+    let old_cf = cpu.flags.contains(Flags::CF);
+    let mut al = old_al;
+    let mut cf = false;
+    if al & 0xF > 9 || cpu.flags.contains(Flags::AF) {
+        let (diff, borrow) = al.overflowing_sub(6);
+        al = diff;
+        cf = old_cf || borrow;
+        cpu.flags.insert(Flags::AF);
+    } else {
+        cpu.flags.remove(Flags::AF);
+    }
+    if old_al > 0x99 || old_cf {
+        al = al.wrapping_sub(0x60);
+        cf = true;
+    }
+    cpu.flags.set(Flags::CF, cf);
+    cpu.regs.set8(Register::AL, al);
+    bcd_flags(al, &mut cpu.flags);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ops::testing::run, registers::Flags, CPUState, Exception};
+    use iced_x86::Register::*;
+    use memory::Extensions;
+
+    const DATA: u32 = 0x2000;
+
+    #[test]
+    fn xadd() {
+        // xadd [DATA], eax
+        let (cpu, mem) = run(b"\x0f\xc1\x05\x00\x20\x00\x00", |cpu, mem| {
+            cpu.regs.set32(EAX, 3);
+            mem.put::<u32>(DATA, 4);
+        });
+        assert_eq!(mem.get_pod::<u32>(DATA), 7);
+        assert_eq!(cpu.regs.get32(EAX), 4);
+
+        // xadd eax, eax
+        let (cpu, _) = run(b"\x0f\xc1\xc0", |cpu, _| cpu.regs.set32(EAX, 3));
+        assert_eq!(cpu.regs.get32(EAX), 6);
+
+        // xadd al, bl, carrying out
+        let (cpu, _) = run(b"\x0f\xc0\xd8", |cpu, _| {
+            cpu.regs.set8(AL, 0xff);
+            cpu.regs.set8(BL, 2);
+        });
+        assert_eq!((cpu.regs.get8(AL), cpu.regs.get8(BL)), (1, 0xff));
+        assert!(cpu.flags.contains(Flags::CF));
+    }
+
+    // The BCD examples are from the Intel SDM's descriptions of each instruction.
+
+    #[test]
+    fn daa() {
+        // add al, bl; daa
+        let (cpu, _) = run(b"\x00\xd8\x27", |cpu, _| {
+            cpu.regs.set8(AL, 0x79);
+            cpu.regs.set8(BL, 0x35);
+        });
+        assert_eq!(cpu.regs.get8(AL), 0x14);
+        assert!(cpu.flags.contains(Flags::CF | Flags::AF));
+    }
+
+    #[test]
+    fn das() {
+        // sub al, bl; das
+        let (cpu, _) = run(b"\x28\xd8\x2f", |cpu, _| {
+            cpu.regs.set8(AL, 0x35);
+            cpu.regs.set8(BL, 0x47);
+        });
+        assert_eq!(cpu.regs.get8(AL), 0x88);
+        assert!(cpu.flags.contains(Flags::CF | Flags::AF));
+    }
+
+    #[test]
+    fn aaa() {
+        // add al, bl; aaa
+        let (cpu, _) = run(b"\x00\xd8\x37", |cpu, _| {
+            cpu.regs.set16(AX, 0x0006);
+            cpu.regs.set8(BL, 0x07);
+        });
+        assert_eq!(cpu.regs.get16(AX), 0x0103);
+        assert!(cpu.flags.contains(Flags::CF | Flags::AF));
+    }
+
+    #[test]
+    fn aas() {
+        // sub al, bl; aas
+        let (cpu, _) = run(b"\x28\xd8\x3f", |cpu, _| {
+            cpu.regs.set16(AX, 0x0203);
+            cpu.regs.set8(BL, 0x05);
+        });
+        assert_eq!(cpu.regs.get16(AX), 0x0108);
+        assert!(cpu.flags.contains(Flags::CF | Flags::AF));
+    }
+
+    #[test]
+    fn aam_aad() {
+        // aam
+        let (cpu, _) = run(b"\xd4\x0a", |cpu, _| cpu.regs.set8(AL, 63));
+        assert_eq!(cpu.regs.get16(AX), 0x0603);
+        // aad
+        let (cpu, _) = run(b"\xd5\x0a", |cpu, _| cpu.regs.set16(AX, 0x0603));
+        assert_eq!(cpu.regs.get16(AX), 63);
+        // aam 0
+        let (cpu, _) = run(b"\xd4\x00", |_, _| {});
+        assert_eq!(cpu.state, CPUState::Exception(Exception::DivideByZero));
+    }
+}
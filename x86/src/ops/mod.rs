@@ -1,3 +1,4 @@
+pub mod accurate;
 mod basic;
 mod control;
 mod cpuid;
@@ -12,9 +13,105 @@ pub use basic::*;
 pub use control::*;
 pub use cpuid::*;
 pub use fpu::*;
-pub use helpers::{pop, push, x86_jmp};
+pub use helpers::{
+    far_jmp, load_segment, pop, pop16, push, push_unchecked, release_stack, stack_addr, x86_jmp,
+};
 pub use math::*;
 pub use mmx::*;
 pub use string::*;
 pub use table::{decode, init_op_tab, Op};
 pub use test::*;
+
+/// Running snippets of machine code, for the ops' tests.
+#[cfg(test)]
+pub(crate) mod testing {
+    use crate::{x86::CPU, CPUState, Register};
+    use memory::Mem;
+
+    /// Where code runs from; below it is free for data.
+    pub const CODE: u32 = 0x1000;
+    pub const STACK: u32 = 0x8000;
+
+    /// Run code at CODE, with the stack at STACK, until it runs off its end or stops.
+    /// setup prepares the CPU and the 64kb of memory it runs in.
+    pub fn run(code: &[u8], setup: impl FnOnce(&mut CPU, Mem)) -> (CPU, Vec<u8>) {
+        let mut buf = vec![0u8; 0x1_0000];
+        buf[CODE as usize..][..code.len()].copy_from_slice(code);
+        let mut cpu = CPU::new();
+        cpu.regs.set32(iced_x86::Register::ESP, STACK);
+        let mem = Mem::from_slice(&buf);
+        setup(&mut cpu, mem);
+        let decoder =
+            iced_x86::Decoder::with_ip(32, code, CODE as u64, iced_x86::DecoderOptions::NONE);
+        for instr in decoder {
+            cpu.regs.eip = instr.next_ip32();
+            let op = super::decode(&instr).expect("unimplemented op");
+            op(&mut cpu, mem, &instr);
+            if cpu.state != CPUState::Running {
+                break;
+            }
+        }
+        (cpu, buf)
+    }
+
+    /// Where run16 puts its local descriptor table.
+    const LDT: u32 = 0x0800;
+    /// Selectors for the segments run16 sets up, and their bases.  All are 4kb.
+    pub const CODE16: (u16, u32) = (0x0F, CODE);
+    pub const FAR16: (u16, u32) = (0x17, 0x3000);
+    pub const DATA16: (u16, u32) = (0x1F, 0x4000);
+    pub const STACK16: (u16, u32) = (0x27, 0x7000);
+
+    /// An LDT descriptor for a 16-bit segment.
+    fn descriptor(base: u32, limit: u32, code: bool) -> u64 {
+        let access: u64 = if code { 0xFA } else { 0xF2 }; // present, ring 3, code/data
+        (limit as u64 & 0xFFFF)
+            | ((base as u64 & 0xFF_FFFF) << 16)
+            | (access << 40)
+            | (((limit >> 16) as u64 & 0xF) << 48)
+            | ((base as u64 >> 24) << 56)
+    }
+
+    /// Run 16-bit code at CODE16 until it runs off its end or stops, with DS and ES
+    /// at DATA16 and SP at the top of STACK16.  Unlike run, this executes blocks as
+    /// X86 does, so the code can branch; FAR16 is for code to make far calls to.
+    pub fn run16(code: &[u8], setup: impl FnOnce(&mut CPU, Mem)) -> (CPU, Vec<u8>) {
+        let mut buf = vec![0u8; 0x1_0000];
+        // Stop with a breakpoint after the code.
+        let end = CODE + code.len() as u32;
+        buf[CODE as usize..][..code.len()].copy_from_slice(code);
+        buf[end as usize] = 0xcc;
+        let mut x86 = crate::X86::new();
+        let mem = Mem::from_slice(&buf);
+        for ((selector, base), code) in [
+            (CODE16, true),
+            (FAR16, true),
+            (DATA16, false),
+            (STACK16, false),
+        ] {
+            let desc = descriptor(base, 0xFFF, code);
+            mem.put::<u64>(LDT + (selector & !7) as u32, desc);
+        }
+        let cpu = x86.cpu_mut();
+        cpu.ldt = (LDT, 0x3F);
+        for (reg, selector) in [
+            (Register::CS, CODE16.0),
+            (Register::SS, STACK16.0),
+            (Register::DS, DATA16.0),
+            (Register::ES, DATA16.0),
+        ] {
+            assert!(super::load_segment(cpu, mem, reg, selector));
+        }
+        cpu.regs.eip = CODE;
+        cpu.regs.set32(Register::ESP, 0x1000);
+        setup(cpu, mem);
+        while x86.cpu().state == CPUState::Running && x86.cpu().regs.eip != end {
+            x86.execute_block(mem, &|| 0);
+        }
+        if x86.cpu().state == CPUState::Exception(crate::Exception::Breakpoint) {
+            x86.cpu_mut().state = CPUState::Running;
+        }
+        let cpu = std::mem::replace(x86.cpu_mut(), CPU::new());
+        (cpu, buf)
+    }
+}
@@ -1,10 +1,16 @@
 //! Ops that tend to loop with 'rep' prefix, e.g. movs, stos.
 
-use super::math::sub;
+use super::{
+    helpers::{check_access, is_unmapped, segment_base},
+    math::sub,
+};
 use crate::{registers::Flags, x86::CPU};
 use iced_x86::{Instruction, Register};
 use memory::{Extensions, Mem};
 
+// The ESI operand of cmps, movs and lods is in DS unless a prefix overrides it,
+// which is how fs:-relative copies out of the TEB work; the EDI operand is always in ES.
+
 /// Width of an operation, e.g. movsb/w/d.
 #[derive(Clone, Copy)]
 enum Size {
@@ -20,25 +26,56 @@ enum Rep {
 }
 
 impl Rep {
+    /// Note that REP and REPE are the same prefix byte, so this never returns REP;
+    /// instructions that only have plain REP forms ignore the distinction.
     fn from_instr(instr: &Instruction) -> Option<Rep> {
-        Some(if instr.has_rep_prefix() {
-            Rep::REP
+        Some(if instr.has_repne_prefix() {
+            Rep::REPNE
         } else if instr.has_repe_prefix() {
             Rep::REPE
-        } else if instr.has_repne_prefix() {
-            Rep::REPNE
         } else {
             return None;
         })
     }
 }
 
+/// Reps shorter than this aren't worth the bulk paths' checks.
+const BULK_MIN: u32 = 16;
+
+/// The length in bytes of a forward rep's remaining iterations, if there are enough
+/// to be worth doing as one bulk operation and every address they touch is mapped.
+/// Otherwise the iterations run one at a time, so that a fault lands on the right one.
+fn bulk_len(cpu: &CPU, mem: Mem, size: Size, addrs: &[u32]) -> Option<u32> {
+    let count = cpu.regs.get32(Register::ECX);
+    if count < BULK_MIN || cpu.flags.contains(Flags::DF) {
+        return None;
+    }
+    let len = count.checked_mul(size as u32)?;
+    if addrs.iter().any(|&addr| is_unmapped(mem, addr, len)) {
+        return None;
+    }
+    Some(len)
+}
+
+/// Update the registers as if `len` bytes of a forward rep had run one at a time.
+fn bulk_advance(cpu: &mut CPU, size: Size, len: u32, esi: bool) {
+    *cpu.regs.get32_mut(Register::ECX) -= len / size as u32;
+    *cpu.regs.get32_mut(Register::EDI) += len;
+    if esi {
+        *cpu.regs.get32_mut(Register::ESI) += len;
+    }
+}
+
 /// Looping logic of various 'rep' prefixes, generalized for different instructions.
 /// Note: some instructions do not have varying Reps and it is important to treat them
 /// as plain REP; e.g. "REPNE MOVS" just means "REP MOVS".
 fn rep(cpu: &mut CPU, mem: Mem, rep: Rep, size: Size, func: impl Fn(&mut CPU, Mem, Size)) {
     while cpu.regs.get32(Register::ECX) > 0 {
         func(cpu, mem, size);
+        if !cpu.state.is_running() {
+            // A fault leaves the registers at the faulting iteration, to resume from there.
+            break;
+        }
         *cpu.regs.get32_mut(Register::ECX) -= 1;
         match rep {
             Rep::REPE if !cpu.flags.contains(Flags::ZF) => break,
@@ -48,21 +85,28 @@ fn rep(cpu: &mut CPU, mem: Mem, rep: Rep, size: Size, func: impl Fn(&mut CPU, Me
     }
 }
 
-fn cmps_single(cpu: &mut CPU, mem: Mem, size: Size) {
+fn cmps_single(cpu: &mut CPU, mem: Mem, size: Size, src: u32) {
+    let si = src.wrapping_add(cpu.regs.get32(Register::ESI));
+    let di = cpu.regs.get32(Register::EDI);
+    if !check_access(cpu, mem, si, size as u32, false)
+        || !check_access(cpu, mem, di, size as u32, false)
+    {
+        return;
+    }
     match size {
         Size::Dword => {
-            let x = mem.get_pod::<u32>(cpu.regs.get32(Register::ESI));
-            let y = mem.get_pod::<u32>(cpu.regs.get32(Register::EDI));
+            let x = mem.get_pod::<u32>(si);
+            let y = mem.get_pod::<u32>(di);
             sub(x, y, &mut cpu.flags);
         }
         Size::Word => {
-            let x = mem.get_pod::<u16>(cpu.regs.get32(Register::ESI));
-            let y = mem.get_pod::<u16>(cpu.regs.get32(Register::EDI));
+            let x = mem.get_pod::<u16>(si);
+            let y = mem.get_pod::<u16>(di);
             sub(x, y, &mut cpu.flags);
         }
         Size::Byte => {
-            let x = mem.get_pod::<u8>(cpu.regs.get32(Register::ESI));
-            let y = mem.get_pod::<u8>(cpu.regs.get32(Register::EDI));
+            let x = mem.get_pod::<u8>(si);
+            let y = mem.get_pod::<u8>(di);
             sub(x, y, &mut cpu.flags);
         }
     }
@@ -75,11 +119,36 @@ fn cmps_single(cpu: &mut CPU, mem: Mem, size: Size) {
     };
 }
 
+/// Skip the leading run of equal elements of a repe cmps, like memcmp.
+fn cmps_bulk(cpu: &mut CPU, mem: Mem, size: Size, src: u32) {
+    let si = src.wrapping_add(cpu.regs.get32(Register::ESI));
+    let di = cpu.regs.get32(Register::EDI);
+    let Some(len) = bulk_len(cpu, mem, size, &[si, di]) else {
+        return;
+    };
+    let x = mem.sub32(si, len);
+    let y = mem.sub32(di, len);
+    let same = x
+        .iter()
+        .zip(y)
+        .position(|(a, b)| a != b)
+        .unwrap_or(len as usize) as u32;
+    // The last comparison made sets the flags, so leave at least one for rep to do.
+    let skip = (same - same % size as u32).min(len - size as u32);
+    bulk_advance(cpu, size, skip, true);
+}
+
 fn cmps(cpu: &mut CPU, mem: Mem, instr: &Instruction, size: Size) {
+    let src = segment_base(cpu, instr);
     if let Some(r) = Rep::from_instr(instr) {
-        rep(cpu, mem, r, size, cmps_single);
+        if matches!(r, Rep::REPE) {
+            cmps_bulk(cpu, mem, size, src);
+        }
+        rep(cpu, mem, r, size, |cpu, mem, size| {
+            cmps_single(cpu, mem, size, src)
+        });
     } else {
-        cmps_single(cpu, mem, size);
+        cmps_single(cpu, mem, size, src);
     }
 }
 
@@ -95,19 +164,26 @@ pub fn cmpsb(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     cmps(cpu, mem, instr, Size::Byte);
 }
 
-fn movs_single(cpu: &mut CPU, mem: Mem, size: Size) {
+fn movs_single(cpu: &mut CPU, mem: Mem, size: Size, src: u32) {
+    let si = src.wrapping_add(cpu.regs.get32(Register::ESI));
+    let di = cpu.regs.get32(Register::EDI);
+    if !check_access(cpu, mem, si, size as u32, false)
+        || !check_access(cpu, mem, di, size as u32, true)
+    {
+        return;
+    }
     match size {
         Size::Dword => {
-            let src = mem.get_pod::<u32>(cpu.regs.get32(Register::ESI));
-            mem.put::<u32>(cpu.regs.get32(Register::EDI), src);
+            let src = mem.get_pod::<u32>(si);
+            mem.put::<u32>(di, src);
         }
         Size::Word => {
-            let src = mem.get_pod::<u16>(cpu.regs.get32(Register::ESI));
-            mem.put::<u16>(cpu.regs.get32(Register::EDI), src);
+            let src = mem.get_pod::<u16>(si);
+            mem.put::<u16>(di, src);
         }
         Size::Byte => {
-            let src = mem.get_pod::<u8>(cpu.regs.get32(Register::ESI));
-            mem.put::<u8>(cpu.regs.get32(Register::EDI), src);
+            let src = mem.get_pod::<u8>(si);
+            mem.put::<u8>(di, src);
         }
     }
     if cpu.flags.contains(Flags::DF) {
@@ -119,11 +195,36 @@ fn movs_single(cpu: &mut CPU, mem: Mem, size: Size) {
     };
 }
 
+/// Do a whole rep movs as a memmove.
+fn movs_bulk(cpu: &mut CPU, mem: Mem, size: Size, src: u32) {
+    let si = src.wrapping_add(cpu.regs.get32(Register::ESI));
+    let di = cpu.regs.get32(Register::EDI);
+    let Some(len) = bulk_len(cpu, mem, size, &[si, di]) else {
+        return;
+    };
+    if !mem.is_writable(di, len) {
+        // Leave it to the single steps to fault on the first read-only page.
+        return;
+    }
+    if si < di && di < si + len {
+        // Copying forward over the source repeats it, which some decompressors rely on
+        // and a memmove wouldn't do.
+        return;
+    }
+    mem.as_mut_slice_todo()
+        .copy_within(si as usize..(si + len) as usize, di as usize);
+    bulk_advance(cpu, size, len, true);
+}
+
 fn movs(cpu: &mut CPU, mem: Mem, instr: &Instruction, size: Size) {
+    let src = segment_base(cpu, instr);
     if let Some(_) = Rep::from_instr(instr) {
-        rep(cpu, mem, Rep::REP, size, movs_single);
+        movs_bulk(cpu, mem, size, src);
+        rep(cpu, mem, Rep::REP, size, |cpu, mem, size| {
+            movs_single(cpu, mem, size, src)
+        });
     } else {
-        movs_single(cpu, mem, size);
+        movs_single(cpu, mem, size, src);
     }
 }
 
@@ -140,17 +241,21 @@ pub fn movsb(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 }
 
 fn scas_single(cpu: &mut CPU, mem: Mem, size: Size) {
+    let di = cpu.regs.get32(Register::EDI);
+    if !check_access(cpu, mem, di, size as u32, false) {
+        return;
+    }
     match size {
         Size::Dword => {
-            let src = mem.get_pod::<u32>(cpu.regs.get32(Register::EDI));
+            let src = mem.get_pod::<u32>(di);
             sub(cpu.regs.get32(Register::EAX), src, &mut cpu.flags);
         }
         Size::Word => {
-            let src = mem.get_pod::<u16>(cpu.regs.get32(Register::EDI));
+            let src = mem.get_pod::<u16>(di);
             sub(cpu.regs.get32(Register::EAX) as u16, src, &mut cpu.flags);
         }
         Size::Byte => {
-            let src = mem.get_pod::<u8>(cpu.regs.get32(Register::EDI));
+            let src = mem.get_pod::<u8>(di);
             sub(cpu.regs.get32(Register::EAX) as u8, src, &mut cpu.flags);
         }
     }
@@ -182,16 +287,14 @@ pub fn scasb(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 }
 
 fn stos_single(cpu: &mut CPU, mem: Mem, size: Size) {
+    let di = cpu.regs.get32(Register::EDI);
+    if !check_access(cpu, mem, di, size as u32, true) {
+        return;
+    }
     match size {
-        Size::Byte => mem.put::<u8>(
-            cpu.regs.get32(Register::EDI),
-            cpu.regs.get32(Register::EAX) as u8,
-        ),
-        Size::Word => mem.put::<u16>(
-            cpu.regs.get32(Register::EDI),
-            cpu.regs.get32(Register::EAX) as u16,
-        ),
-        Size::Dword => mem.put::<u32>(cpu.regs.get32(Register::EDI), cpu.regs.get32(Register::EAX)),
+        Size::Byte => mem.put::<u8>(di, cpu.regs.get32(Register::EAX) as u8),
+        Size::Word => mem.put::<u16>(di, cpu.regs.get32(Register::EAX) as u16),
+        Size::Dword => mem.put::<u32>(di, cpu.regs.get32(Register::EAX)),
     }
     if cpu.flags.contains(Flags::DF) {
         *cpu.regs.get32_mut(Register::EDI) -= size as u32;
@@ -200,8 +303,31 @@ fn stos_single(cpu: &mut CPU, mem: Mem, size: Size) {
     };
 }
 
+/// Do a whole rep stos as a memset.
+fn stos_bulk(cpu: &mut CPU, mem: Mem, size: Size) {
+    let di = cpu.regs.get32(Register::EDI);
+    let Some(len) = bulk_len(cpu, mem, size, &[di]) else {
+        return;
+    };
+    if !mem.is_writable(di, len) {
+        return;
+    }
+    let val = cpu.regs.get32(Register::EAX).to_le_bytes();
+    let buf = &mut mem.as_mut_slice_todo()[di as usize..][..len as usize];
+    match size {
+        Size::Byte => buf.fill(val[0]),
+        _ => {
+            for elem in buf.chunks_exact_mut(size as usize) {
+                elem.copy_from_slice(&val[..size as usize]);
+            }
+        }
+    }
+    bulk_advance(cpu, size, len, false);
+}
+
 fn stos(cpu: &mut CPU, mem: Mem, instr: &Instruction, size: Size) {
     if let Some(_) = Rep::from_instr(instr) {
+        stos_bulk(cpu, mem, size);
         rep(cpu, mem, Rep::REP, size, stos_single);
     } else {
         stos_single(cpu, mem, size);
@@ -220,21 +346,22 @@ pub fn stosb(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     stos(cpu, mem, instr, Size::Byte)
 }
 
-fn lods_single(cpu: &mut CPU, mem: Mem, size: Size) {
+fn lods_single(cpu: &mut CPU, mem: Mem, size: Size, src: u32) {
+    let si = src.wrapping_add(cpu.regs.get32(Register::ESI));
+    if !check_access(cpu, mem, si, size as u32, false) {
+        return;
+    }
     match size {
         Size::Byte => {
-            let value = mem.get_pod::<u8>(cpu.regs.get32(Register::ESI));
+            let value = mem.get_pod::<u8>(si);
             cpu.regs.set8(iced_x86::Register::AL, value)
         }
         Size::Word => {
-            let value = mem.get_pod::<u16>(cpu.regs.get32(Register::ESI));
+            let value = mem.get_pod::<u16>(si);
             cpu.regs.set16(iced_x86::Register::AX, value)
         }
         Size::Dword => {
-            cpu.regs.set32(
-                Register::EAX,
-                mem.get_pod::<u32>(cpu.regs.get32(Register::ESI)),
-            );
+            cpu.regs.set32(Register::EAX, mem.get_pod::<u32>(si));
         }
     }
     if cpu.flags.contains(Flags::DF) {
@@ -245,10 +372,13 @@ fn lods_single(cpu: &mut CPU, mem: Mem, size: Size) {
 }
 
 fn lods(cpu: &mut CPU, mem: Mem, instr: &Instruction, size: Size) {
+    let src = segment_base(cpu, instr);
     if let Some(_) = Rep::from_instr(instr) {
-        rep(cpu, mem, Rep::REP, size, lods_single);
+        rep(cpu, mem, Rep::REP, size, |cpu, mem, size| {
+            lods_single(cpu, mem, size, src)
+        });
     } else {
-        lods_single(cpu, mem, size);
+        lods_single(cpu, mem, size, src);
     }
 }
 
@@ -263,3 +393,68 @@ pub fn lodsw(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
 pub fn lodsb(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
     lods(cpu, mem, instr, Size::Byte)
 }
+
+/// Whether a string instruction addresses with SI and DI, as 16-bit code does unless
+/// an address size prefix makes it use ESI and EDI.
+fn is_addr16(instr: &Instruction) -> bool {
+    (0..instr.op_count()).any(|i| {
+        matches!(
+            instr.op_kind(i),
+            iced_x86::OpKind::MemorySegSI
+                | iced_x86::OpKind::MemorySegDI
+                | iced_x86::OpKind::MemoryESDI
+        )
+    })
+}
+
+/// Run a string instruction in 16-bit code, where SI and DI are offsets into their
+/// segments and CX is the count.  The 32-bit op runs on the linear equivalents:
+/// it adds the source segment's base to ESI itself, so EDI becomes ES's base plus DI.
+pub fn string16(cpu: &mut CPU, mem: Mem, instr: &Instruction) {
+    let op = super::decode(instr).unwrap_or(super::unimplemented);
+    if !is_addr16(instr) {
+        return op(cpu, mem, instr);
+    }
+    let es = cpu.regs.seg_base(Register::ES);
+    let esi = cpu.regs.get32(Register::ESI);
+    let edi = cpu.regs.get32(Register::EDI);
+    let ecx = cpu.regs.get32(Register::ECX);
+    cpu.regs.set32(Register::ESI, esi & 0xFFFF);
+    cpu.regs.set32(Register::EDI, es.wrapping_add(edi & 0xFFFF));
+    cpu.regs.set32(Register::ECX, ecx & 0xFFFF);
+    op(cpu, mem, instr);
+    let si = cpu.regs.get32(Register::ESI) as u16;
+    let di = cpu.regs.get32(Register::EDI).wrapping_sub(es) as u16;
+    let cx = cpu.regs.get32(Register::ECX) as u16;
+    cpu.regs
+        .set32(Register::ESI, (esi & 0xFFFF_0000) | si as u32);
+    cpu.regs
+        .set32(Register::EDI, (edi & 0xFFFF_0000) | di as u32);
+    cpu.regs
+        .set32(Register::ECX, (ecx & 0xFFFF_0000) | cx as u32);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ops::testing::{run16, DATA16, STACK16},
+        CPUState,
+    };
+    use iced_x86::Register::*;
+    use memory::Extensions;
+
+    #[test]
+    fn movsb16() {
+        // mov ax,27h; mov es,ax; mov di,100h; mov cx,4; rep movsb
+        let code = b"\xb8\x27\x00\x8e\xc0\xbf\x00\x01\xb9\x04\x00\xf3\xa4";
+        let (cpu, mem) = run16(code, |cpu, mem| {
+            cpu.regs.set32(ESI, 0xFFFF_0000);
+            mem.put::<u32>(DATA16.1, 0x4433_2211);
+        });
+        assert_eq!(cpu.state, CPUState::Running);
+        assert_eq!(mem.get_pod::<u32>(STACK16.1 + 0x100), 0x4433_2211);
+        assert_eq!(cpu.regs.get32(ESI), 0xFFFF_0004);
+        assert_eq!(cpu.regs.get16(DI), 0x104);
+        assert_eq!(cpu.regs.get16(CX), 0);
+    }
+}
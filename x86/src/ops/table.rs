@@ -49,6 +49,49 @@ pub unsafe fn init_op_tab() {
 
     OP_TAB[iced_x86::Code::Loop_rel8_32_ECX as usize] = Some(ops::loop_);
 
+    // 16-bit code.
+    OP_TAB[iced_x86::Code::Call_rel16 as usize] = Some(ops::callw);
+    OP_TAB[iced_x86::Code::Call_rm16 as usize] = Some(ops::callw_rm16);
+    OP_TAB[iced_x86::Code::Call_ptr1616 as usize] = Some(ops::callw_ptr1616);
+    OP_TAB[iced_x86::Code::Call_m1616 as usize] = Some(ops::callw_m1616);
+    OP_TAB[iced_x86::Code::Retnw as usize] = Some(ops::retnw);
+    OP_TAB[iced_x86::Code::Retnw_imm16 as usize] = Some(ops::retnw_imm16);
+    OP_TAB[iced_x86::Code::Retfw as usize] = Some(ops::retfw);
+    OP_TAB[iced_x86::Code::Retfw_imm16 as usize] = Some(ops::retfw_imm16);
+    OP_TAB[iced_x86::Code::Jmp_rm16 as usize] = Some(ops::jmpw_rm16);
+    OP_TAB[iced_x86::Code::Jmp_ptr1616 as usize] = Some(ops::jmpw_ptr1616);
+    OP_TAB[iced_x86::Code::Jmp_m1616 as usize] = Some(ops::jmpw_m1616);
+    OP_TAB[iced_x86::Code::Jmp_rel16 as usize] = Some(ops::jmp);
+    OP_TAB[iced_x86::Code::Jmp_rel8_16 as usize] = Some(ops::jmp);
+    OP_TAB[iced_x86::Code::Ja_rel16 as usize] = Some(ops::ja);
+    OP_TAB[iced_x86::Code::Ja_rel8_16 as usize] = Some(ops::ja);
+    OP_TAB[iced_x86::Code::Jae_rel16 as usize] = Some(ops::jae);
+    OP_TAB[iced_x86::Code::Jae_rel8_16 as usize] = Some(ops::jae);
+    OP_TAB[iced_x86::Code::Jb_rel16 as usize] = Some(ops::jb);
+    OP_TAB[iced_x86::Code::Jb_rel8_16 as usize] = Some(ops::jb);
+    OP_TAB[iced_x86::Code::Jbe_rel16 as usize] = Some(ops::jbe);
+    OP_TAB[iced_x86::Code::Jbe_rel8_16 as usize] = Some(ops::jbe);
+    OP_TAB[iced_x86::Code::Je_rel16 as usize] = Some(ops::je);
+    OP_TAB[iced_x86::Code::Je_rel8_16 as usize] = Some(ops::je);
+    OP_TAB[iced_x86::Code::Jne_rel16 as usize] = Some(ops::jne);
+    OP_TAB[iced_x86::Code::Jne_rel8_16 as usize] = Some(ops::jne);
+    OP_TAB[iced_x86::Code::Jns_rel16 as usize] = Some(ops::jns);
+    OP_TAB[iced_x86::Code::Jns_rel8_16 as usize] = Some(ops::jns);
+    OP_TAB[iced_x86::Code::Jg_rel16 as usize] = Some(ops::jg);
+    OP_TAB[iced_x86::Code::Jg_rel8_16 as usize] = Some(ops::jg);
+    OP_TAB[iced_x86::Code::Jge_rel16 as usize] = Some(ops::jge);
+    OP_TAB[iced_x86::Code::Jge_rel8_16 as usize] = Some(ops::jge);
+    OP_TAB[iced_x86::Code::Jle_rel16 as usize] = Some(ops::jle);
+    OP_TAB[iced_x86::Code::Jle_rel8_16 as usize] = Some(ops::jle);
+    OP_TAB[iced_x86::Code::Jl_rel16 as usize] = Some(ops::jl);
+    OP_TAB[iced_x86::Code::Jl_rel8_16 as usize] = Some(ops::jl);
+    OP_TAB[iced_x86::Code::Js_rel16 as usize] = Some(ops::js);
+    OP_TAB[iced_x86::Code::Js_rel8_16 as usize] = Some(ops::js);
+    OP_TAB[iced_x86::Code::Jcxz_rel8_16 as usize] = Some(ops::jcxz);
+    OP_TAB[iced_x86::Code::Loop_rel8_16_CX as usize] = Some(ops::loopw);
+    OP_TAB[iced_x86::Code::Enterw_imm16_imm8 as usize] = Some(ops::enterw_imm16_imm8);
+    OP_TAB[iced_x86::Code::Leavew as usize] = Some(ops::leavew);
+
     OP_TAB[iced_x86::Code::Pushd_DS as usize] = Some(ops::pushd_r16);
     OP_TAB[iced_x86::Code::Pushd_ES as usize] = Some(ops::pushd_r16);
     OP_TAB[iced_x86::Code::Pushd_FS as usize] = Some(ops::pushd_r16);
@@ -64,6 +107,15 @@ pub unsafe fn init_op_tab() {
     OP_TAB[iced_x86::Code::Popd_ES as usize] = Some(ops::popd_r16);
     OP_TAB[iced_x86::Code::Popd_FS as usize] = Some(ops::popd_r16);
     OP_TAB[iced_x86::Code::Popd_GS as usize] = Some(ops::popd_r16);
+    OP_TAB[iced_x86::Code::Pushw_ES as usize] = Some(ops::pushw_sreg);
+    OP_TAB[iced_x86::Code::Pushw_CS as usize] = Some(ops::pushw_sreg);
+    OP_TAB[iced_x86::Code::Pushw_SS as usize] = Some(ops::pushw_sreg);
+    OP_TAB[iced_x86::Code::Pushw_DS as usize] = Some(ops::pushw_sreg);
+    OP_TAB[iced_x86::Code::Popw_ES as usize] = Some(ops::popw_sreg);
+    OP_TAB[iced_x86::Code::Popw_SS as usize] = Some(ops::popw_sreg);
+    OP_TAB[iced_x86::Code::Popw_DS as usize] = Some(ops::popw_sreg);
+    OP_TAB[iced_x86::Code::Push_imm16 as usize] = Some(ops::pushw_imm16);
+    OP_TAB[iced_x86::Code::Pushw_imm8 as usize] = Some(ops::pushw_imm8);
     OP_TAB[iced_x86::Code::Pop_r32 as usize] = Some(ops::pop_rm32);
     OP_TAB[iced_x86::Code::Pop_rm32 as usize] = Some(ops::pop_rm32);
     OP_TAB[iced_x86::Code::Pop_r16 as usize] = Some(ops::pop_rm16);
@@ -89,6 +141,10 @@ pub unsafe fn init_op_tab() {
     OP_TAB[iced_x86::Code::Mov_moffs8_AL as usize] = Some(ops::mov_moffs8_al);
     OP_TAB[iced_x86::Code::Mov_r32m16_Sreg as usize] = Some(ops::mov_r32m16_sreg);
     OP_TAB[iced_x86::Code::Mov_Sreg_r32m16 as usize] = Some(ops::mov_sreg_r32m16);
+    OP_TAB[iced_x86::Code::Mov_rm16_Sreg as usize] = Some(ops::mov_rm16_sreg);
+    OP_TAB[iced_x86::Code::Mov_Sreg_rm16 as usize] = Some(ops::mov_sreg_rm16);
+    OP_TAB[iced_x86::Code::Lds_r16_m1616 as usize] = Some(ops::lxs_r16_m1616);
+    OP_TAB[iced_x86::Code::Les_r16_m1616 as usize] = Some(ops::lxs_r16_m1616);
 
     OP_TAB[iced_x86::Code::Movsx_r32_rm16 as usize] = Some(ops::movsx_r32_rm16);
     OP_TAB[iced_x86::Code::Movsx_r32_rm8 as usize] = Some(ops::movsx_r32_rm8);
@@ -103,10 +159,13 @@ pub unsafe fn init_op_tab() {
 
     OP_TAB[iced_x86::Code::Xchg_rm32_r32 as usize] = Some(ops::xchg_rm32_r32);
     OP_TAB[iced_x86::Code::Xchg_r32_EAX as usize] = Some(ops::xchg_rm32_r32);
+    OP_TAB[iced_x86::Code::Xchg_rm16_r16 as usize] = Some(ops::xchg_rm16_r16);
     OP_TAB[iced_x86::Code::Xchg_r16_AX as usize] = Some(ops::xchg_rm16_r16);
     OP_TAB[iced_x86::Code::Xchg_rm8_r8 as usize] = Some(ops::xchg_rm8_r8);
 
     OP_TAB[iced_x86::Code::Cmpxchg_rm32_r32 as usize] = Some(ops::cmpxchg_rm32_r32);
+    OP_TAB[iced_x86::Code::Cmpxchg_rm16_r16 as usize] = Some(ops::cmpxchg_rm16_r16);
+    OP_TAB[iced_x86::Code::Cmpxchg_rm8_r8 as usize] = Some(ops::cmpxchg_rm8_r8);
     OP_TAB[iced_x86::Code::Cmpxchg8b_m64 as usize] = Some(ops::cmpxchg8b_m64);
 
     OP_TAB[iced_x86::Code::Cmpsd_m32_m32 as usize] = Some(ops::cmpsd);
@@ -213,6 +272,11 @@ pub unsafe fn init_op_tab() {
     OP_TAB[iced_x86::Code::Add_rm8_imm8 as usize] = Some(ops::add_rm8_imm8);
     OP_TAB[iced_x86::Code::Add_AL_imm8 as usize] = Some(ops::add_rm8_imm8);
     OP_TAB[iced_x86::Code::Add_r8_rm8 as usize] = Some(ops::add_r8_rm8);
+
+    OP_TAB[iced_x86::Code::Xadd_rm32_r32 as usize] = Some(ops::xadd_rm32_r32);
+    OP_TAB[iced_x86::Code::Xadd_rm16_r16 as usize] = Some(ops::xadd_rm16_r16);
+    OP_TAB[iced_x86::Code::Xadd_rm8_r8 as usize] = Some(ops::xadd_rm8_r8);
+
     OP_TAB[iced_x86::Code::Adc_rm32_r32 as usize] = Some(ops::adc_rm32_rm32);
     OP_TAB[iced_x86::Code::Adc_r32_rm32 as usize] = Some(ops::adc_rm32_rm32);
     OP_TAB[iced_x86::Code::Adc_rm32_imm8 as usize] = Some(ops::adc_rm32_imm8);
@@ -271,6 +335,7 @@ pub unsafe fn init_op_tab() {
     OP_TAB[iced_x86::Code::Not_rm8 as usize] = Some(ops::not_rm8);
 
     OP_TAB[iced_x86::Code::Lea_r32_m as usize] = Some(ops::lea_r32_m);
+    OP_TAB[iced_x86::Code::Lea_r16_m as usize] = Some(ops::lea_r16_m);
 
     OP_TAB[iced_x86::Code::Cmp_rm32_r32 as usize] = Some(ops::cmp_rm32_r32);
     OP_TAB[iced_x86::Code::Cmp_r32_rm32 as usize] = Some(ops::cmp_r32_rm32);
@@ -322,8 +387,12 @@ pub unsafe fn init_op_tab() {
     OP_TAB[iced_x86::Code::Fldz as usize] = Some(ops::fldz);
     OP_TAB[iced_x86::Code::Fldpi as usize] = Some(ops::fldpi);
     OP_TAB[iced_x86::Code::Fldl2e as usize] = Some(ops::fldl2e);
+    OP_TAB[iced_x86::Code::Fldl2t as usize] = Some(ops::fldl2t);
+    OP_TAB[iced_x86::Code::Fldlg2 as usize] = Some(ops::fldlg2);
+    OP_TAB[iced_x86::Code::Fldln2 as usize] = Some(ops::fldln2);
 
     OP_TAB[iced_x86::Code::Fld_sti as usize] = Some(ops::fld_sti);
+    OP_TAB[iced_x86::Code::Fld_m80fp as usize] = Some(ops::fld_m80fp);
     OP_TAB[iced_x86::Code::Fld_m64fp as usize] = Some(ops::fld_m64fp);
     OP_TAB[iced_x86::Code::Fld_m32fp as usize] = Some(ops::fld_m32fp);
     OP_TAB[iced_x86::Code::Fild_m64int as usize] = Some(ops::fild_m64int);
@@ -331,22 +400,26 @@ pub unsafe fn init_op_tab() {
     OP_TAB[iced_x86::Code::Fild_m16int as usize] = Some(ops::fild_m16int);
     OP_TAB[iced_x86::Code::Fst_m64fp as usize] = Some(ops::fst_m64fp);
     OP_TAB[iced_x86::Code::Fst_m32fp as usize] = Some(ops::fst_m32fp);
+    OP_TAB[iced_x86::Code::Fstp_m80fp as usize] = Some(ops::fstp_m80fp);
     OP_TAB[iced_x86::Code::Fstp_m64fp as usize] = Some(ops::fstp_m64fp);
     OP_TAB[iced_x86::Code::Fstp_m32fp as usize] = Some(ops::fstp_m32fp);
+    OP_TAB[iced_x86::Code::Fst_sti as usize] = Some(ops::fst_sti);
     OP_TAB[iced_x86::Code::Fstp_sti as usize] = Some(ops::fstp_sti);
     OP_TAB[iced_x86::Code::Fistp_m64int as usize] = Some(ops::fistp_m64int);
     OP_TAB[iced_x86::Code::Fistp_m32int as usize] = Some(ops::fistp_m32int);
     OP_TAB[iced_x86::Code::Fistp_m16int as usize] = Some(ops::fistp_m16int);
     // OP_TAB[iced_x86::Code::Fisp_m64int as usize] = Some(ops::fist_m64int);
     OP_TAB[iced_x86::Code::Fist_m32int as usize] = Some(ops::fist_m32int);
-    // OP_TAB[iced_x86::Code::Fist_m16int as usize] = Some(ops::fist_m16int);
+    OP_TAB[iced_x86::Code::Fist_m16int as usize] = Some(ops::fist_m16int);
 
     OP_TAB[iced_x86::Code::Fchs as usize] = Some(ops::fchs);
     OP_TAB[iced_x86::Code::Fabs as usize] = Some(ops::fabs);
     OP_TAB[iced_x86::Code::Fcos as usize] = Some(ops::fcos);
     OP_TAB[iced_x86::Code::Fsin as usize] = Some(ops::fsin);
     OP_TAB[iced_x86::Code::Fsincos as usize] = Some(ops::fsincos);
+    OP_TAB[iced_x86::Code::Fptan as usize] = Some(ops::fptan);
     OP_TAB[iced_x86::Code::Fpatan as usize] = Some(ops::fpatan);
+    OP_TAB[iced_x86::Code::Fyl2x as usize] = Some(ops::fyl2x);
     OP_TAB[iced_x86::Code::Fsqrt as usize] = Some(ops::fsqrt);
 
     OP_TAB[iced_x86::Code::Fadd_st0_sti as usize] = Some(ops::fadd_sti_sti);
@@ -360,12 +433,15 @@ pub unsafe fn init_op_tab() {
     OP_TAB[iced_x86::Code::Fsub_m64fp as usize] = Some(ops::fsub_m64fp);
     OP_TAB[iced_x86::Code::Fsub_m32fp as usize] = Some(ops::fsub_m32fp);
     OP_TAB[iced_x86::Code::Fsub_st0_sti as usize] = Some(ops::fsub_sti_sti);
+    OP_TAB[iced_x86::Code::Fsub_sti_st0 as usize] = Some(ops::fsub_sti_sti);
     OP_TAB[iced_x86::Code::Fsubp_sti_st0 as usize] = Some(ops::fsubp_sti_sti);
     OP_TAB[iced_x86::Code::Fisub_m32int as usize] = Some(ops::fisub_m32int);
 
     OP_TAB[iced_x86::Code::Fsubr_m64fp as usize] = Some(ops::fsubr_m64fp);
     OP_TAB[iced_x86::Code::Fsubr_m32fp as usize] = Some(ops::fsubr_m32fp);
-    OP_TAB[iced_x86::Code::Fsubr_st0_sti as usize] = Some(ops::fsubr_st0_sti);
+    OP_TAB[iced_x86::Code::Fsubr_st0_sti as usize] = Some(ops::fsubr_sti_sti);
+    OP_TAB[iced_x86::Code::Fsubr_sti_st0 as usize] = Some(ops::fsubr_sti_sti);
+    OP_TAB[iced_x86::Code::Fsubrp_sti_st0 as usize] = Some(ops::fsubrp_sti_sti);
 
     OP_TAB[iced_x86::Code::Fmul_m64fp as usize] = Some(ops::fmul_m64fp);
     OP_TAB[iced_x86::Code::Fmul_m32fp as usize] = Some(ops::fmul_m32fp);
@@ -393,23 +469,35 @@ pub unsafe fn init_op_tab() {
     OP_TAB[iced_x86::Code::Fidivr_m32int as usize] = Some(ops::fidivr_m32int);
 
     OP_TAB[iced_x86::Code::Fprem as usize] = Some(ops::fprem);
+    OP_TAB[iced_x86::Code::Fprem1 as usize] = Some(ops::fprem1);
 
     OP_TAB[iced_x86::Code::Fxch_st0_sti as usize] = Some(ops::fxch_st0_sti);
     OP_TAB[iced_x86::Code::Fcom_m32fp as usize] = Some(ops::fcom_m32fp);
     OP_TAB[iced_x86::Code::Fcomp_m32fp as usize] = Some(ops::fcomp_m32fp);
+    OP_TAB[iced_x86::Code::Fcom_m64fp as usize] = Some(ops::fcom_m64fp);
     OP_TAB[iced_x86::Code::Fcomp_m64fp as usize] = Some(ops::fcomp_m64fp);
+    OP_TAB[iced_x86::Code::Fcom_st0_sti as usize] = Some(ops::fcom_st0_sti);
     OP_TAB[iced_x86::Code::Fcomp_st0_sti as usize] = Some(ops::fcomp_st0_sti);
+    OP_TAB[iced_x86::Code::Fcompp as usize] = Some(ops::fcompp);
+    OP_TAB[iced_x86::Code::Fucom_st0_sti as usize] = Some(ops::fucom_st0_sti);
     OP_TAB[iced_x86::Code::Fucomp_st0_sti as usize] = Some(ops::fucomp_st0_sti);
+    OP_TAB[iced_x86::Code::Fucompp as usize] = Some(ops::fucompp);
+    OP_TAB[iced_x86::Code::Ftst as usize] = Some(ops::ftst);
+    OP_TAB[iced_x86::Code::Fxam as usize] = Some(ops::fxam);
     OP_TAB[iced_x86::Code::Fcomi_st0_sti as usize] = Some(ops::fcomi_st0_sti);
+    OP_TAB[iced_x86::Code::Fcomip_st0_sti as usize] = Some(ops::fcomip_st0_sti);
     OP_TAB[iced_x86::Code::Fucomi_st0_sti as usize] = Some(ops::fucomi_st0_sti);
     OP_TAB[iced_x86::Code::Fucomip_st0_sti as usize] = Some(ops::fucomip_st0_sti);
 
     OP_TAB[iced_x86::Code::Frndint as usize] = Some(ops::frndint);
     OP_TAB[iced_x86::Code::Fnstsw_AX as usize] = Some(ops::fnstsw_ax);
+    OP_TAB[iced_x86::Code::Fstsw_AX as usize] = Some(ops::fnstsw_ax);
+    OP_TAB[iced_x86::Code::Fnstsw_m2byte as usize] = Some(ops::fnstsw_m2byte);
+    OP_TAB[iced_x86::Code::Fstsw_m2byte as usize] = Some(ops::fnstsw_m2byte);
     OP_TAB[iced_x86::Code::Fnstcw_m2byte as usize] = Some(ops::fnstcw_m2byte);
     OP_TAB[iced_x86::Code::Fldcw_m2byte as usize] = Some(ops::fldcw_m2byte);
-    OP_TAB[iced_x86::Code::Fclex as usize] = Some(ops::nop);
-    OP_TAB[iced_x86::Code::Fnclex as usize] = Some(ops::nop);
+    OP_TAB[iced_x86::Code::Fclex as usize] = Some(ops::fnclex);
+    OP_TAB[iced_x86::Code::Fnclex as usize] = Some(ops::fnclex);
 
     OP_TAB[iced_x86::Code::Fcmovnbe_st0_sti as usize] = Some(ops::fcmovnbe_st0_sti);
 
@@ -417,6 +505,8 @@ pub unsafe fn init_op_tab() {
 
     OP_TAB[iced_x86::Code::Pushad as usize] = Some(ops::pushad);
     OP_TAB[iced_x86::Code::Popad as usize] = Some(ops::popad);
+    OP_TAB[iced_x86::Code::Pushaw as usize] = Some(ops::pushaw);
+    OP_TAB[iced_x86::Code::Popaw as usize] = Some(ops::popaw);
     OP_TAB[iced_x86::Code::Pushfd as usize] = Some(ops::pushfd);
     OP_TAB[iced_x86::Code::Pushfw as usize] = Some(ops::pushfw);
     OP_TAB[iced_x86::Code::Popfd as usize] = Some(ops::popfd);
@@ -424,12 +514,20 @@ pub unsafe fn init_op_tab() {
     OP_TAB[iced_x86::Code::Sahf as usize] = Some(ops::sahf);
 
     OP_TAB[iced_x86::Code::Salc as usize] = Some(ops::salc);
+    OP_TAB[iced_x86::Code::Aaa as usize] = Some(ops::aaa);
+    OP_TAB[iced_x86::Code::Aas as usize] = Some(ops::aas);
+    OP_TAB[iced_x86::Code::Aam_imm8 as usize] = Some(ops::aam_imm8);
+    OP_TAB[iced_x86::Code::Aad_imm8 as usize] = Some(ops::aad_imm8);
+    OP_TAB[iced_x86::Code::Daa as usize] = Some(ops::daa);
+    OP_TAB[iced_x86::Code::Das as usize] = Some(ops::das);
     OP_TAB[iced_x86::Code::Std as usize] = Some(ops::std);
     OP_TAB[iced_x86::Code::Cld as usize] = Some(ops::cld);
     OP_TAB[iced_x86::Code::Stc as usize] = Some(ops::stc);
     OP_TAB[iced_x86::Code::Clc as usize] = Some(ops::clc);
     OP_TAB[iced_x86::Code::Cmc as usize] = Some(ops::cmc);
     OP_TAB[iced_x86::Code::Cwde as usize] = Some(ops::cwde);
+    OP_TAB[iced_x86::Code::Cbw as usize] = Some(ops::cbw);
+    OP_TAB[iced_x86::Code::Cwd as usize] = Some(ops::cwd);
     OP_TAB[iced_x86::Code::Cdq as usize] = Some(ops::cdq);
 
     OP_TAB[iced_x86::Code::Pxor_mm_mmm64 as usize] = Some(ops::pxor_mm_mmm64);
@@ -459,13 +557,31 @@ pub unsafe fn init_op_tab() {
     OP_TAB[iced_x86::Code::Nop_rm32 as usize] = Some(ops::nop);
 
     OP_TAB[iced_x86::Code::Int3 as usize] = Some(ops::int3);
+    OP_TAB[iced_x86::Code::Int_imm8 as usize] = Some(ops::int_imm8);
+    OP_TAB[iced_x86::Code::Ud2 as usize] = Some(ops::ud2);
 
     OP_TAB[iced_x86::Code::Bswap_r32 as usize] = Some(ops::bswap_r32);
     OP_TAB[iced_x86::Code::Xlat_m8 as usize] = Some(ops::xlat_m8);
+    OP_TAB[iced_x86::Code::Bound_r32_m3232 as usize] = Some(ops::bound_r32_m3232);
+    OP_TAB[iced_x86::Code::Bound_r16_m1616 as usize] = Some(ops::bound_r16_m1616);
     OP_TAB[iced_x86::Code::Bts_rm32_r32 as usize] = Some(ops::bts_rm32_r32);
     OP_TAB[iced_x86::Code::Tzcnt_r32_rm32 as usize] = Some(ops::tzcnt_r32_rm32);
 
+    OP_TAB[iced_x86::Code::In_AL_imm8 as usize] = Some(ops::in_port);
+    OP_TAB[iced_x86::Code::In_AX_imm8 as usize] = Some(ops::in_port);
+    OP_TAB[iced_x86::Code::In_EAX_imm8 as usize] = Some(ops::in_port);
+    OP_TAB[iced_x86::Code::In_AL_DX as usize] = Some(ops::in_port);
+    OP_TAB[iced_x86::Code::In_AX_DX as usize] = Some(ops::in_port);
+    OP_TAB[iced_x86::Code::In_EAX_DX as usize] = Some(ops::in_port);
+    OP_TAB[iced_x86::Code::Out_imm8_AL as usize] = Some(ops::out_port);
+    OP_TAB[iced_x86::Code::Out_imm8_AX as usize] = Some(ops::out_port);
+    OP_TAB[iced_x86::Code::Out_imm8_EAX as usize] = Some(ops::out_port);
+    OP_TAB[iced_x86::Code::Out_DX_AL as usize] = Some(ops::out_port);
+    OP_TAB[iced_x86::Code::Out_DX_AX as usize] = Some(ops::out_port);
+    OP_TAB[iced_x86::Code::Out_DX_EAX as usize] = Some(ops::out_port);
+
     OP_TAB[iced_x86::Code::Cpuid as usize] = Some(ops::cpuid);
+    OP_TAB[iced_x86::Code::Rdtsc as usize] = Some(ops::rdtsc);
 
     // Code to print the necessary size of the table:
     // let last = OP_TAB.iter().rposition(|op| op.is_some());
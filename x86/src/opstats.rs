@@ -0,0 +1,192 @@
+//! Instrumentation of the interpreter: counts of executed instructions by opcode
+//! form and addressing mode, and the share of execution time spent in each.
+//! This is meant to guide which ops are worth optimizing (or compiling) first.
+//!
+//! Timing calls into the host clock around every instruction, so it slows
+//! execution considerably; only the relative shares are meaningful.
+//! On wasm there is no cheap clock, so only counts are collected.
+
+use std::collections::HashMap;
+
+/// How an instruction's operands are addressed, beyond what the opcode form says.
+/// Forms like Add_rm32_r32 cover both register and memory operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AddrMode {
+    Reg,
+    Imm,
+    Mem,
+}
+
+impl AddrMode {
+    pub fn of(instr: &iced_x86::Instruction) -> Self {
+        let mut mode = AddrMode::Reg;
+        for i in 0..instr.op_count() {
+            match instr.op_kind(i) {
+                iced_x86::OpKind::Memory => return AddrMode::Mem,
+                iced_x86::OpKind::Register => {}
+                _ => mode = AddrMode::Imm,
+            }
+        }
+        mode
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            AddrMode::Reg => "reg",
+            AddrMode::Imm => "imm",
+            AddrMode::Mem => "mem",
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct Counter {
+    pub count: u64,
+    pub nanos: u64,
+}
+
+/// Output format of OpStats::report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "text" | "txt" => ReportFormat::Text,
+            "csv" => ReportFormat::Csv,
+            "json" => ReportFormat::Json,
+            _ => return Err(format!("unknown report format {s:?}")),
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct OpStats {
+    ops: HashMap<(iced_x86::Code, AddrMode), Counter>,
+}
+
+/// Host clock for timing instructions, if there is a usable one.
+#[cfg(not(target_family = "wasm"))]
+pub struct Timer(std::time::Instant);
+#[cfg(target_family = "wasm")]
+pub struct Timer;
+
+impl Timer {
+    #[inline]
+    pub fn start() -> Self {
+        #[cfg(not(target_family = "wasm"))]
+        return Timer(std::time::Instant::now());
+        #[cfg(target_family = "wasm")]
+        return Timer;
+    }
+
+    #[inline]
+    fn nanos(&self) -> u64 {
+        #[cfg(not(target_family = "wasm"))]
+        return self.0.elapsed().as_nanos() as u64;
+        #[cfg(target_family = "wasm")]
+        return 0;
+    }
+}
+
+impl OpStats {
+    /// Record one execution of instr, which started at timer.
+    #[inline]
+    pub fn record(&mut self, instr: &iced_x86::Instruction, timer: Timer) {
+        let nanos = timer.nanos();
+        let counter = self
+            .ops
+            .entry((instr.code(), AddrMode::of(instr)))
+            .or_default();
+        counter.count += 1;
+        counter.nanos += nanos;
+    }
+
+    /// Entries sorted by descending time, or by count if there is no timing.
+    pub fn entries(&self) -> Vec<(iced_x86::Code, AddrMode, Counter)> {
+        let mut entries: Vec<_> = self
+            .ops
+            .iter()
+            .map(|(&(code, mode), &counter)| (code, mode, counter))
+            .collect();
+        entries.sort_by(|a, b| {
+            (b.2.nanos, b.2.count)
+                .cmp(&(a.2.nanos, a.2.count))
+                .then((a.0 as u32, a.1).cmp(&(b.0 as u32, b.1)))
+        });
+        entries
+    }
+
+    pub fn report(&self, format: ReportFormat) -> String {
+        let entries = self.entries();
+        let total_count: u64 = entries.iter().map(|e| e.2.count).sum();
+        let total_nanos: u64 = entries.iter().map(|e| e.2.nanos).sum();
+        let share = |c: &Counter| {
+            if total_nanos > 0 {
+                c.nanos as f64 * 100.0 / total_nanos as f64
+            } else if total_count > 0 {
+                c.count as f64 * 100.0 / total_count as f64
+            } else {
+                0.0
+            }
+        };
+
+        let mut out = String::new();
+        match format {
+            ReportFormat::Text => {
+                out.push_str(&format!(
+                    "{:<32} {:<4} {:>12} {:>12} {:>7}\n",
+                    "op", "mode", "count", "ns", "share"
+                ));
+                for (code, mode, c) in &entries {
+                    out.push_str(&format!(
+                        "{:<32} {:<4} {:>12} {:>12} {:>6.2}%\n",
+                        format!("{code:?}"),
+                        mode.name(),
+                        c.count,
+                        c.nanos,
+                        share(c)
+                    ));
+                }
+                out.push_str(&format!(
+                    "{} instrs, {} distinct ops\n",
+                    total_count,
+                    entries.len()
+                ));
+            }
+            ReportFormat::Csv => {
+                out.push_str("op,mode,count,ns,share\n");
+                for (code, mode, c) in &entries {
+                    out.push_str(&format!(
+                        "{code:?},{},{},{},{:.4}\n",
+                        mode.name(),
+                        c.count,
+                        c.nanos,
+                        share(c)
+                    ));
+                }
+            }
+            ReportFormat::Json => {
+                out.push_str("[\n");
+                for (i, (code, mode, c)) in entries.iter().enumerate() {
+                    let sep = if i + 1 < entries.len() { "," } else { "" };
+                    out.push_str(&format!(
+                        "  {{\"op\": \"{code:?}\", \"mode\": \"{}\", \"count\": {}, \"ns\": {}, \"share\": {:.4}}}{sep}\n",
+                        mode.name(),
+                        c.count,
+                        c.nanos,
+                        share(c)
+                    ));
+                }
+                out.push_str("]\n");
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,136 @@
+//! The x86 I/O port space, read and written by IN and OUT.
+//!
+//! Windows programs shouldn't touch hardware directly, but Win9x let them get away
+//! with it, and some timing loops and copy protection checks rely on that.
+//! Rather than faulting, accesses go to hooks registered per port; ports without
+//! one read as all ones, as an empty bus does, and ignore writes.
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    ops::RangeInclusive,
+    rc::Rc,
+};
+
+/// An emulated device behind one or more ports.
+/// `size` is the access width in bytes: 1, 2 or 4.
+pub trait PortHook {
+    fn read(&mut self, port: u16, size: u32) -> u32;
+    fn write(&mut self, port: u16, size: u32, value: u32);
+}
+
+pub struct Ports {
+    hooks: RefCell<HashMap<u16, Rc<RefCell<dyn PortHook>>>>,
+    /// Unhooked ports already warned about, to log each only once.
+    logged: RefCell<HashSet<u16>>,
+}
+
+impl Default for Ports {
+    fn default() -> Self {
+        let ports = Ports {
+            hooks: Default::default(),
+            logged: Default::default(),
+        };
+        ports.register(0x40..=0x43, Pit::default());
+        ports.register(0x201..=0x201, Joystick);
+        ports
+    }
+}
+
+impl Ports {
+    /// Route accesses to the given ports to a hook, replacing any previous one.
+    pub fn register(&self, ports: RangeInclusive<u16>, hook: impl PortHook + 'static) {
+        let hook: Rc<RefCell<dyn PortHook>> = Rc::new(RefCell::new(hook));
+        let mut hooks = self.hooks.borrow_mut();
+        for port in ports {
+            hooks.insert(port, hook.clone());
+        }
+    }
+
+    fn hook(&self, port: u16) -> Option<Rc<RefCell<dyn PortHook>>> {
+        let hook = self.hooks.borrow().get(&port).cloned();
+        if hook.is_none() && self.logged.borrow_mut().insert(port) {
+            log::warn!("unhandled I/O port {port:#x}");
+        }
+        hook
+    }
+
+    pub fn read(&self, port: u16, size: u32) -> u32 {
+        match self.hook(port) {
+            Some(hook) => hook.borrow_mut().read(port, size),
+            None => u32::MAX >> (32 - size * 8),
+        }
+    }
+
+    pub fn write(&self, port: u16, size: u32, value: u32) {
+        if let Some(hook) = self.hook(port) {
+            hook.borrow_mut().write(port, size, value);
+        }
+    }
+}
+
+/// The 8253/8254 programmable interval timer, ports 0x40-0x42 for its channels and
+/// 0x43 for its control word.  Programs read channel 0 for finer timing than the
+/// system clock gives.  There's no clock behind it: each count read moves the
+/// counter down a bit, which is enough for loops waiting for it to change.
+/// Counts are read low byte then high byte, the access mode everything uses.
+#[derive(Default)]
+struct Pit {
+    count: [u16; 3],
+    latch: [Option<u16>; 3],
+    high: [bool; 3],
+}
+
+/// How far a counter moves between reads, about 50us at the PIT's 1.19MHz.
+const PIT_STEP: u16 = 60;
+
+impl PortHook for Pit {
+    fn read(&mut self, port: u16, _size: u32) -> u32 {
+        let ch = (port - 0x40) as usize;
+        if ch == 3 {
+            // The control word is write-only.
+            return 0xFF;
+        }
+        let value = self.latch[ch].unwrap_or(self.count[ch]);
+        let byte = if self.high[ch] {
+            self.latch[ch] = None;
+            self.count[ch] = self.count[ch].wrapping_sub(PIT_STEP);
+            value >> 8
+        } else {
+            value & 0xFF
+        };
+        self.high[ch] = !self.high[ch];
+        byte as u32
+    }
+
+    fn write(&mut self, port: u16, _size: u32, value: u32) {
+        if port != 0x43 {
+            // Loading a new count is ignored; counters always count the full range.
+            return;
+        }
+        let ch = ((value >> 6) & 0b11) as usize;
+        if ch == 3 {
+            // Read-back command, 8254 only.
+            return;
+        }
+        if value & 0x30 == 0 {
+            // Counter latch command.
+            self.latch[ch] = Some(self.count[ch]);
+        }
+        self.high[ch] = false;
+    }
+}
+
+/// The game port: no buttons pressed, and axis timers that have already run out,
+/// so loops polling for them finish immediately.
+struct Joystick;
+
+impl PortHook for Joystick {
+    fn read(&mut self, _port: u16, _size: u32) -> u32 {
+        0xF0
+    }
+
+    fn write(&mut self, _port: u16, _size: u32, _value: u32) {
+        // Writing starts the axis timers, which are always done.
+    }
+}
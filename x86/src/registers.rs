@@ -6,14 +6,22 @@ bitflags! {
     pub struct Flags: u32 {
         /// carry
         const CF = 1 << 0;
+        /// parity, only computed in accurate flags mode
+        const PF = 1 << 2;
+        /// adjust, only computed in accurate flags mode
+        const AF = 1 << 4;
         /// zero
         const ZF = 1 << 6;
         /// sign
         const SF = 1 << 7;
+        /// trap, to single-step
+        const TF = 1 << 8;
         /// direction
         const DF = 1 << 10;
         /// overflow
         const OF = 1 << 11;
+        /// resume, to not break again on an instruction breakpoint being resumed from
+        const RF = 1 << 16;
         /// cpuid
         const ID = 1 << 21;
     }
@@ -38,12 +46,23 @@ pub struct Registers {
     ///   es cs ss ds fs gs
     segment: [u16; 6],
 
-    // TODO: segment registers are actually 16-bit indexes into the GDT/LDT,
-    // but for our purposes all we ever care about is making FS-relative accesses point
-    // at the Windows TEB.
-    /// Address that FS-relative accesses point to.
+    /// Bases of the segments the segment registers select, in the same order, as
+    /// cached from their LDT descriptors when loaded; see ops::load_segment.
+    /// Only 16-bit code uses these.  32-bit code runs flat, where all we ever care
+    /// about is making FS-relative accesses point at the Windows TEB.
+    seg_base: [u32; 6],
+
+    /// Address that FS-relative accesses in 32-bit code point to.
     pub fs_addr: u32,
 
+    /// Whether CS selects a 16-bit code segment, whose code decodes with 16-bit
+    /// operands and addresses.  EIP is then CS's base plus IP.
+    pub code16: bool,
+
+    /// Whether SS selects a 16-bit stack segment, where pushes and pops move SP
+    /// within the segment and leave the high word of ESP alone.
+    pub stack16: bool,
+
     /// MMX registers.
     // TODO: officially these should alias the FPU registers(!).
     mm: [u64; 8],
@@ -106,6 +125,12 @@ fn r8h_to_32(r8: Register) -> Register {
 }
 
 impl Registers {
+    /// The 32-bit registers as an array, for compiled code.
+    #[cfg(feature = "jit")]
+    pub(crate) fn r32_ptr(&mut self) -> *mut u32 {
+        self.r32.as_mut_ptr()
+    }
+
     pub fn get32_mut(&mut self, reg: Register) -> &mut u32 {
         let idx = reg as usize - Register::EAX as usize;
         // See check in assert_enums_as_expected() -- the registers we can fetch are always < 8.
@@ -150,6 +175,15 @@ impl Registers {
         }
     }
 
+    /// The cached base of the segment a segment register selects.
+    pub fn seg_base(&self, reg: Register) -> u32 {
+        self.seg_base[reg as usize - ES as usize]
+    }
+
+    pub fn set_seg_base(&mut self, reg: Register, base: u32) {
+        self.seg_base[reg as usize - ES as usize] = base;
+    }
+
     pub fn get8(&self, reg: Register) -> u8 {
         match reg {
             AL | CL | DL | BL => self.get32(r8l_to_32(reg)) as u8,
@@ -176,7 +210,7 @@ impl Registers {
 
     pub fn set16(&mut self, reg: Register, value: u16) {
         match reg {
-            AX | CX | DX | BX | SI | DI | BP => {
+            AX | CX | DX | BX | SP | BP | SI | DI => {
                 let r32 = r16_to_32(reg);
                 self.set32(r32, (self.get32(r32) & 0xFFFF_0000) | value as u32);
             }
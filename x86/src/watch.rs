@@ -0,0 +1,179 @@
+//! Watchpoints: noticing when the program reads or writes a range of memory, for
+//! finding what code touches some variable, like one holding a corrupted value.
+//!
+//! Like the hardware's data breakpoints, a watchpoint triggers after the accessing
+//! instruction completes.  Accesses are those of an instruction's memory operand
+//! and string instructions' ESI/EDI; the implicit stack accesses of push, pop and
+//! call aren't watched.  While any watchpoint is set every instruction is checked,
+//! so execution is slower and the JIT is bypassed.
+
+use crate::{icache::Op, CPU};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub addr: u32,
+    pub len: u32,
+    pub access: Access,
+}
+
+impl std::str::FromStr for Watchpoint {
+    type Err = String;
+
+    /// Parses "ADDR[+LEN][:r|w|rw]", with the address and length in hex; the
+    /// default is a 4 byte write watch, like "4a1f00+4:w".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (range, access) = s.split_once(':').unwrap_or((s, "w"));
+        let (addr, len) = range.split_once('+').unwrap_or((range, "4"));
+        let hex = |v: &str| {
+            u32::from_str_radix(v.trim_start_matches("0x"), 16)
+                .map_err(|_| format!("bad hex number {v:?}"))
+        };
+        let access = match access {
+            "r" => Access::Read,
+            "w" => Access::Write,
+            "rw" => Access::ReadWrite,
+            _ => return Err(format!("bad access {access:?}, expected r, w or rw")),
+        };
+        let len = hex(len)?;
+        if len == 0 {
+            return Err("watchpoint length must be nonzero".into());
+        }
+        Ok(Watchpoint {
+            addr: hex(addr)?,
+            len,
+            access,
+        })
+    }
+}
+
+/// An access that triggered a watchpoint.
+#[derive(Clone, Copy, Debug)]
+pub struct Hit {
+    pub watch: Watchpoint,
+    /// Address of the accessing instruction.
+    pub ip: u32,
+    /// The memory accessed, which overlaps the watched range.
+    pub addr: u32,
+    pub len: u32,
+    pub write: bool,
+}
+
+/// Called on a hit, instead of pausing; returns whether to pause anyway.
+pub type OnHit = Box<dyn FnMut(&CPU, &Hit) -> bool>;
+
+pub struct Watchpoints {
+    watches: Vec<(Watchpoint, Option<OnHit>)>,
+    factory: iced_x86::InstructionInfoFactory,
+    /// The hit that most recently paused execution.
+    pub last_hit: Option<Hit>,
+}
+
+impl Default for Watchpoints {
+    fn default() -> Self {
+        Watchpoints {
+            watches: Vec::new(),
+            factory: iced_x86::InstructionInfoFactory::new(),
+            last_hit: None,
+        }
+    }
+}
+
+impl Watchpoints {
+    /// Watch a range of memory.  On a hit, on_hit is called if given, and otherwise
+    /// execution pauses as at a breakpoint, with the CPU blocked after the access.
+    pub fn add(&mut self, watch: Watchpoint, on_hit: Option<OnHit>) {
+        self.watches.push((watch, on_hit));
+    }
+
+    /// Remove the watchpoints starting at addr, returning whether there were any.
+    pub fn remove(&mut self, addr: u32) -> bool {
+        let len = self.watches.len();
+        self.watches.retain(|(watch, _)| watch.addr != addr);
+        self.watches.len() != len
+    }
+
+    pub fn list(&self) -> impl Iterator<Item = &Watchpoint> {
+        self.watches.iter().map(|(watch, _)| watch)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watches.is_empty()
+    }
+
+    /// Check the accesses of an op that just ran, given ESI and EDI from before it,
+    /// returning whether to pause.
+    pub(crate) fn check(&mut self, cpu: &CPU, op: &Op, esi: u32, edi: u32) -> bool {
+        let mut accesses = Vec::new();
+        if let Some((addr, len)) = op.written(cpu, edi) {
+            accesses.push((addr, len, true));
+        }
+        let size = op.instr.memory_size().size() as u32;
+        let info = self.factory.info(&op.instr);
+        for i in 0..op.instr.op_count() {
+            let read = matches!(
+                info.op_access(i),
+                iced_x86::OpAccess::Read
+                    | iced_x86::OpAccess::CondRead
+                    | iced_x86::OpAccess::ReadWrite
+                    | iced_x86::OpAccess::ReadCondWrite
+            );
+            if !read {
+                continue;
+            }
+            let range = match op.instr.op_kind(i) {
+                iced_x86::OpKind::Memory => Some((cpu.last_addr, size.max(1))),
+                iced_x86::OpKind::MemorySegESI | iced_x86::OpKind::MemorySegRSI => {
+                    let now = cpu.regs.get32(iced_x86::Register::ESI);
+                    crate::icache::string_range(esi, now, size)
+                }
+                iced_x86::OpKind::MemoryESEDI | iced_x86::OpKind::MemoryESRDI => {
+                    let now = cpu.regs.get32(iced_x86::Register::EDI);
+                    crate::icache::string_range(edi, now, size)
+                }
+                _ => None,
+            };
+            if let Some((addr, len)) = range {
+                accesses.push((addr, len, false));
+            }
+        }
+
+        let mut pause = false;
+        for (watch, on_hit) in self.watches.iter_mut() {
+            for &(addr, len, write) in &accesses {
+                let wanted = match watch.access {
+                    Access::Read => !write,
+                    Access::Write => write,
+                    Access::ReadWrite => true,
+                };
+                let overlaps = (addr as u64) < watch.addr as u64 + watch.len as u64
+                    && (watch.addr as u64) < addr as u64 + len as u64;
+                if !wanted || !overlaps {
+                    continue;
+                }
+                let hit = Hit {
+                    watch: *watch,
+                    ip: op.instr.ip32(),
+                    addr,
+                    len,
+                    write,
+                };
+                let stop = match on_hit {
+                    Some(on_hit) => on_hit(cpu, &hit),
+                    None => true,
+                };
+                if stop {
+                    self.last_hit = Some(hit);
+                    pause = true;
+                }
+            }
+        }
+        pause
+    }
+}
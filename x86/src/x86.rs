@@ -1,13 +1,20 @@
 //! The central x86 machine object.
 
 use crate::{
+    coverage::Coverage,
+    cycles::SpeedLimit,
     fpu::FPU,
-    icache::InstrCache,
+    icache::{InstrCache, Writes},
+    model::{CpuModel, FlagAccuracy, TimeStampCounter},
     ops,
+    opstats::{OpStats, Timer},
+    ports::Ports,
     registers::{Flags, Registers},
+    watch::Watchpoints,
     Register,
 };
 use memory::Mem;
+use std::rc::Rc;
 
 #[derive(Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum CPUState {
@@ -15,9 +22,41 @@ pub enum CPUState {
     Running,
     Blocked(Option<u32>),
     Error(String),
+    /// The instruction at eip faulted; the OS decides what happens next.
+    Exception(Exception),
     Exit(u32),
 }
 
+/// A fault raised by the processor while executing an instruction, which the OS
+/// delivers to the program as it would on hardware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Exception {
+    /// #DE from dividing by zero.
+    DivideByZero,
+    /// #DE from a quotient too large for its register.
+    DivideOverflow,
+    /// #UD, from an undefined encoding or UD2.
+    InvalidOpcode,
+    /// #PF, from accessing memory that isn't mapped.
+    AccessViolation { addr: u32, write: bool },
+    /// #BP, from INT3.
+    Breakpoint,
+    /// #BR, from BOUND with an index outside its bounds.
+    BoundRange,
+    /// #DB, from the trap flag or a debug register breakpoint.  Unlike the others this
+    /// can be a trap, reported after its instruction completes; DR6 says what caused it.
+    SingleStep,
+    /// #GP, from loading a segment register with a selector that doesn't select a
+    /// usable segment.
+    GeneralProtection { selector: u16 },
+    /// INT n, a software interrupt.  A trap: it's reported after the instruction, as
+    /// the system call it stands for returns to the next one.
+    Interrupt(u8),
+}
+
+/// DR6 bit for a single-step trap; bits 0-3 are for hits on DR0-DR3.
+const DR6_BS: u32 = 1 << 14;
+
 impl CPUState {
     pub fn is_running(&self) -> bool {
         matches!(*self, CPUState::Running)
@@ -25,7 +64,7 @@ impl CPUState {
 }
 
 /// When eip==MAGIC_ADDR, the CPU executes futures (async tasks) rather than x86 code.
-const MAGIC_ADDR: u32 = 0xFFFF_FFF0;
+pub const MAGIC_ADDR: u32 = 0xFFFF_FFF0;
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct CPU {
@@ -38,10 +77,43 @@ pub struct CPU {
 
     pub state: CPUState,
 
+    /// Debug registers DR0-DR7, of which DR4 and DR5 are unused.
+    pub dr: [u32; 8],
+
+    /// Address and limit of the local descriptor table, which selectors for 16-bit
+    /// segments index.  There's no GDT; selectors into it all select the flat space.
+    pub ldt: (u32, u32),
+
+    /// The processor CPUID describes, shared with the other CPUs.
+    #[serde(skip)]
+    pub model: Rc<CpuModel>,
+
+    /// The I/O port space, shared with the other CPUs.
+    #[serde(skip)]
+    pub ports: Rc<Ports>,
+
+    /// The value RDTSC reads, set by X86 before running a block ending in one.
+    #[serde(skip)]
+    pub tsc: u64,
+
+    /// The address of the last memory operand computed, which is where an
+    /// instruction that writes its memory operand wrote.
+    #[serde(skip)]
+    pub(crate) last_addr: u32,
+
+    /// Whether the running instruction writes its memory operand, for x86_addr to
+    /// check the operand is writable.
+    #[serde(skip)]
+    pub(crate) writing: bool,
+
     /// If eip==MAGIC_ADDR, then the next step is to poll a future rather than
     /// executing a basic block.
+    /// Each is paired with the ESP at the x86 call that started it.
     #[serde(skip)]
-    futures: Vec<std::pin::Pin<Box<dyn std::future::Future<Output = ()>>>>,
+    futures: Vec<(
+        u32,
+        std::pin::Pin<Box<dyn std::future::Future<Output = ()>>>,
+    )>,
 }
 
 impl CPU {
@@ -54,6 +126,13 @@ impl CPU {
             flags: Flags::empty(),
             fpu: FPU::default(),
             state: Default::default(),
+            dr: [0; 8],
+            ldt: (0, 0),
+            model: Default::default(),
+            ports: Default::default(),
+            tsc: 0,
+            last_addr: 0,
+            writing: false,
             futures: Default::default(),
         }
     }
@@ -62,6 +141,70 @@ impl CPU {
         self.state = CPUState::Error(msg);
     }
 
+    /// Fault the current instruction.  Only the first fault an instruction raises counts.
+    pub fn raise(&mut self, exception: Exception) {
+        if self.state.is_running() {
+            self.state = CPUState::Exception(exception);
+        }
+    }
+
+    /// The base of the code segment, if it's 16-bit code.
+    pub fn cs16(&self) -> Option<u32> {
+        self.regs.code16.then(|| self.regs.seg_base(Register::CS))
+    }
+
+    /// Whether instructions need checking against TF or the debug registers.
+    fn debugging(&self) -> bool {
+        self.flags.contains(Flags::TF) || self.dr[7] & 0xFF != 0
+    }
+
+    /// The enabled DR0-DR3 breakpoints, as (index, address, length, DR7 R/W bits).
+    fn hw_breakpoints(&self) -> impl Iterator<Item = (usize, u32, u32, u32)> + '_ {
+        let dr7 = self.dr[7];
+        (0..4)
+            .filter(move |i| (dr7 >> (i * 2)) & 0b11 != 0)
+            .map(move |i| {
+                let rw = (dr7 >> (16 + i * 4)) & 0b11;
+                let len = match (dr7 >> (18 + i * 4)) & 0b11 {
+                    0b00 => 1,
+                    0b01 => 2,
+                    0b10 => 8,
+                    _ => 4,
+                };
+                (i, self.dr[i] & !(len - 1), len, rw)
+            })
+    }
+
+    /// Raise #DB if an instruction breakpoint is set at ip, before it executes.
+    fn check_instruction_breakpoint(&mut self, ip: u32) -> bool {
+        if self.flags.contains(Flags::RF) {
+            return false;
+        }
+        let Some((i, ..)) = self
+            .hw_breakpoints()
+            .find(|&(_, addr, _, rw)| rw == 0b00 && addr == ip)
+        else {
+            return false;
+        };
+        self.dr[6] |= 1 << i;
+        // Resuming from the handler would hit the breakpoint again, so
+        // (as debuggers do) arrange for the retry to skip it.
+        self.flags.insert(Flags::RF);
+        self.raise(Exception::SingleStep);
+        true
+    }
+
+    /// Raise #DB if a data breakpoint covers memory an instruction just wrote.
+    fn check_write_breakpoint(&mut self, start: u32, size: u32) {
+        let hit = self.hw_breakpoints().find(|&(_, addr, len, rw)| {
+            matches!(rw, 0b01 | 0b11) && addr < start.wrapping_add(size) && start < addr + len
+        });
+        if let Some((i, ..)) = hit {
+            self.dr[6] |= 1 << i;
+            self.raise(Exception::SingleStep);
+        }
+    }
+
     // /// Check whether reading a T from mem[addr] would cause OOB, and crash() if so.
     // fn check_oob<T>(&mut self, addr: u32) -> bool {
     //     if addr < NULL_POINTER_REGION_SIZE {
@@ -81,11 +224,32 @@ impl CPU {
     pub fn call_x86(&mut self, mem: Mem, func: u32, args: Vec<u32>) -> X86Future {
         // Save original esp, as that's the marker that we use to know when the call is done.
         let esp = self.regs.get32(Register::ESP);
+        self.push_call(mem, func, &args);
+        X86Future {
+            cpu: self,
+            esp,
+            pop: 0,
+        }
+    }
+
+    /// Like call_x86, but for a cdecl function, which leaves its args for the caller to pop.
+    pub fn call_x86_cdecl(&mut self, mem: Mem, func: u32, args: Vec<u32>) -> X86Future {
+        let pop = args.len() as u32 * 4;
+        let esp = self.regs.get32(Register::ESP).wrapping_sub(pop);
+        self.push_call(mem, func, &args);
+        X86Future {
+            cpu: self,
+            esp,
+            pop,
+        }
+    }
+
+    fn push_call(&mut self, mem: Mem, func: u32, args: &[u32]) {
         // Push the args in reverse order.
         for &arg in args.iter().rev() {
-            ops::push(self, mem, arg);
+            ops::push_unchecked(self, mem, arg);
         }
-        ops::push(self, mem, MAGIC_ADDR); // return address
+        ops::push_unchecked(self, mem, MAGIC_ADDR); // return address
         self.regs.eip = func;
 
         // Clear registers to make traces clean.
@@ -93,20 +257,25 @@ impl CPU {
         self.regs.set32(Register::EAX, 0);
         self.regs.set32(Register::ECX, 0);
         self.regs.set32(Register::EDX, 0);
-
-        X86Future { cpu: self, esp }
     }
 
     /// Set up the CPU such that we are making an x86->async call, enqueuing a Future
     /// that is polled the next time the CPU executes.
     pub fn call_async(&mut self, future: std::pin::Pin<Box<dyn std::future::Future<Output = ()>>>) {
         self.regs.eip = MAGIC_ADDR;
-        self.futures.push(future);
+        self.futures.push((self.regs.get32(Register::ESP), future));
     }
 
     #[allow(deref_nullptr)]
     fn async_executor(&mut self) {
-        let mut future = self.futures.pop().unwrap();
+        // A future whose x86 stack frame has since been unwound, like a call into an
+        // exception handler that jumped to an __except block rather than returning,
+        // will never complete; the stack having moved up past it shows it's abandoned.
+        let esp = self.regs.get32(Register::ESP);
+        while self.futures.len() > 1 && self.futures.last().unwrap().0 < esp {
+            self.futures.pop();
+        }
+        let (frame, mut future) = self.futures.pop().unwrap();
         // TODO: we don't use the waker at all.  Rust doesn't like us passing a random null pointer
         // here but it seems like nothing accesses it(?).
         //let c = unsafe { std::task::Context::from_waker(&Waker::from_raw(std::task::RawWaker::)) };
@@ -115,7 +284,7 @@ impl CPU {
         match poll {
             std::task::Poll::Ready(()) => {}
             std::task::Poll::Pending => {
-                self.futures.push(future);
+                self.futures.push((frame, future));
             }
         }
     }
@@ -131,9 +300,12 @@ pub struct X86Future {
     // https://github.com/rust-lang/futures-rs/issues/316
     cpu: *mut CPU,
     esp: u32,
+    /// Bytes of args to pop once the call returns, for cdecl calls.
+    pop: u32,
 }
 impl std::future::Future for X86Future {
-    type Output = ();
+    /// The function's return value, from EAX.
+    type Output = u32;
 
     fn poll(
         self: std::pin::Pin<&mut Self>,
@@ -142,7 +314,8 @@ impl std::future::Future for X86Future {
         let cpu = self.cpu;
         let cpu = unsafe { &mut *cpu };
         if cpu.regs.get32(Register::ESP) == self.esp {
-            std::task::Poll::Ready(())
+            *cpu.regs.get32_mut(Register::ESP) += self.pop;
+            std::task::Poll::Ready(cpu.regs.get32(Register::EAX))
         } else {
             std::task::Poll::Pending
         }
@@ -183,8 +356,39 @@ pub struct X86 {
     /// Total number of instructions executed.
     pub instr_count: usize,
 
+    /// Estimated cycles executed, see cycles::cost.
+    #[serde(skip)]
+    pub cycles: u64,
+
+    /// If set, execution is held to a clock rate by the embedder, using `cycles`.
+    #[serde(skip)]
+    pub speed_limit: Option<SpeedLimit>,
+
     #[serde(skip)]
     pub icache: InstrCache,
+
+    /// If set, per-opcode statistics are collected as instructions execute.
+    #[serde(skip)]
+    pub opstats: Option<OpStats>,
+
+    /// If set, executed code is recorded as instructions execute.
+    #[serde(skip)]
+    pub coverage: Option<Coverage>,
+
+    #[serde(skip)]
+    pub watchpoints: Watchpoints,
+
+    #[serde(skip)]
+    pub tsc: TimeStampCounter,
+
+    /// Memory written by the current block, checked against the icache when it ends.
+    #[serde(skip)]
+    writes: Vec<(u32, u32)>,
+
+    /// If set, hot blocks are compiled to host code.
+    #[cfg(feature = "jit")]
+    #[serde(skip)]
+    pub jit: Option<crate::jit::Jit>,
 }
 
 impl X86 {
@@ -193,7 +397,26 @@ impl X86 {
             cpus: vec![Box::new(CPU::new())],
             cur_cpu: 0,
             instr_count: 0,
+            cycles: 0,
+            speed_limit: None,
             icache: InstrCache::default(),
+            opstats: None,
+            coverage: None,
+            watchpoints: Watchpoints::default(),
+            tsc: TimeStampCounter::default(),
+            writes: Vec::new(),
+            #[cfg(feature = "jit")]
+            jit: None,
+        }
+    }
+
+    /// Change the processor reported to all CPUs, current and future.
+    pub fn set_model(&mut self, model: CpuModel) {
+        self.icache
+            .set_accurate_flags(model.flags == FlagAccuracy::Accurate);
+        let model = Rc::new(model);
+        for cpu in self.cpus.iter_mut() {
+            cpu.model = model.clone();
         }
     }
 
@@ -206,7 +429,10 @@ impl X86 {
     }
 
     pub fn new_cpu(&mut self) -> &mut CPU {
-        self.cpus.push(Box::new(CPU::new()));
+        let mut cpu = CPU::new();
+        cpu.model = self.cpus[0].model.clone();
+        cpu.ports = self.cpus[0].ports.clone();
+        self.cpus.push(Box::new(cpu));
         self.cpus.last_mut().unwrap()
     }
 
@@ -218,15 +444,25 @@ impl X86 {
         self.icache.clear_breakpoint(mem, addr)
     }
 
+    /// Discard decoded code in the range, after the memory there was changed.
+    pub fn invalidate_code(&mut self, addr: u32, len: u32) {
+        self.icache.invalidate(addr, len)
+    }
+
     pub fn single_step_next_block(&mut self, mem: Mem) {
         let ip = self.cpu().regs.eip;
         if ip == MAGIC_ADDR {
             return;
         }
-        self.icache.make_single_step(mem, ip);
+        let cs16 = self.cpu().cs16();
+        self.icache.make_single_step(mem, ip, cs16);
     }
 
     /// Schedule the next runnable thread to run.
+    /// Threads only switch between blocks, so every instruction is atomic with respect
+    /// to other threads; that is what makes LOCK-prefixed instructions and xchg with
+    /// memory work without any special handling.  Preempting mid-block would need to
+    /// preserve that by never switching within an instruction.
     pub fn schedule(&mut self) -> &CPUState {
         // log::info!(
         //     "cpustate {:?}",
@@ -252,7 +488,8 @@ impl X86 {
     }
 
     /// Execute one basic block starting at current ip.
-    pub fn execute_block(&mut self, mem: Mem) {
+    /// `time` gets the host time in milliseconds, for blocks reading the time stamp counter.
+    pub fn execute_block(&mut self, mem: Mem, time: &dyn Fn() -> u32) {
         let cpu = &mut *self.cpus[self.cur_cpu];
         debug_assert!(cpu.state.is_running());
         if cpu.regs.eip == MAGIC_ADDR {
@@ -260,21 +497,125 @@ impl X86 {
             return;
         }
         let mut prev_ip = cpu.regs.eip;
-        let block = self.icache.get_block(mem, prev_ip);
-        for op in block.ops.iter() {
+        let block_ip = prev_ip;
+        let mut end_ip = prev_ip;
+        let block = self.icache.get_block(mem, prev_ip, cpu.cs16());
+        let block_end = block_ip.wrapping_add(block.len);
+        if block.reads_tsc() {
+            let instr_count = self.instr_count + block.ops.len();
+            cpu.tsc = self.tsc.read(&cpu.model, instr_count, time);
+        }
+        let watching = !self.watchpoints.is_empty();
+        #[allow(unused_mut)]
+        let mut ops = &block.ops[..];
+        #[cfg(feature = "jit")]
+        if let Some(jit) = self.jit.as_mut().filter(|_| {
+            cpu.model.flags == FlagAccuracy::Simple
+                && !cpu.debugging()
+                && !watching
+                && block.cs16.is_none()
+        }) {
+            let ran = jit.run(cpu, &mut block.jit, &block.ops);
+            if ran > 0 {
+                // Compiled ops aren't counted in opstats.
+                self.instr_count += ran;
+                self.cycles += block.ops[..ran]
+                    .iter()
+                    .map(|op| op.cycles as u64)
+                    .sum::<u64>();
+                prev_ip = block.ops[ran - 1].instr.ip32();
+                cpu.regs.eip = block.ops[ran - 1].instr.next_ip32();
+                end_ip = cpu.regs.eip;
+                ops = &block.ops[ran..];
+            }
+        }
+        for op in ops.iter() {
+            // TF traps after the instruction that starts with it set, so that the
+            // popf setting it runs without one.
+            let debugging = cpu.debugging();
+            let trap = debugging && cpu.flags.contains(Flags::TF);
+            if debugging && cpu.check_instruction_breakpoint(cpu.regs.eip) {
+                break;
+            }
             prev_ip = cpu.regs.eip;
             cpu.regs.eip = op.instr.next_ip() as u32;
+            end_ip = cpu.regs.eip;
             self.instr_count += 1;
-            (op.op)(cpu, mem, &op.instr);
+            self.cycles += op.cycles as u64;
+            cpu.writing = op.writes == Writes::Operand;
+            let esi = cpu.regs.get32(iced_x86::Register::ESI);
+            let edi = cpu.regs.get32(iced_x86::Register::EDI);
+            let ecx = cpu.regs.get32(iced_x86::Register::ECX);
+            if let Some(stats) = &mut self.opstats {
+                let timer = Timer::start();
+                (op.op)(cpu, mem, &op.instr);
+                stats.record(&op.instr, timer);
+            } else {
+                (op.op)(cpu, mem, &op.instr);
+            }
             if !cpu.state.is_running() {
                 break;
             }
+            if op.instr.has_rep_prefix() || op.instr.has_repne_prefix() {
+                // One more cycle per iteration.
+                self.cycles += ecx.wrapping_sub(cpu.regs.get32(iced_x86::Register::ECX)) as u64;
+            }
+            if debugging {
+                cpu.flags.remove(Flags::RF);
+            }
+            if let Some((addr, len)) = op.written(cpu, edi) {
+                self.writes.push((addr, len));
+                if debugging {
+                    cpu.check_write_breakpoint(addr, len);
+                }
+                if addr < block_end && block_ip < addr.wrapping_add(len) {
+                    // The block overwrote some of itself, so what remains of it is stale.
+                    break;
+                }
+            }
+            if watching && self.watchpoints.check(cpu, op, esi, edi) {
+                // Pause after the access, as at a breakpoint.
+                log::warn!("watchpoint hit at {:x}", op.instr.ip32());
+                cpu.state = CPUState::Blocked(None);
+            }
+            if trap {
+                cpu.dr[6] |= DR6_BS;
+                cpu.raise(Exception::SingleStep);
+            }
+            if !cpu.state.is_running() {
+                break;
+            }
+        }
+        for (addr, len) in self.writes.drain(..) {
+            self.icache.invalidate(addr, len);
+        }
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record(block_ip, end_ip.wrapping_sub(block_ip));
         }
         match cpu.state {
             CPUState::Error(_) => {
                 // Point the debugger at the failed instruction.
                 cpu.regs.eip = prev_ip;
             }
+            CPUState::Exception(Exception::Breakpoint) if self.icache.is_breakpoint(prev_ip) => {
+                // One of our own breakpoints rather than the program's int3.
+                log::warn!("debugger interrupt");
+                cpu.state = CPUState::Blocked(None);
+                cpu.regs.eip = prev_ip;
+            }
+            CPUState::Exception(Exception::SingleStep) => {
+                // Traps report the next instruction, and instruction breakpoints
+                // stopped before advancing; either way eip is right.  The handler
+                // runs without single-stepping.
+                cpu.flags.remove(Flags::TF);
+            }
+            CPUState::Exception(Exception::Interrupt(_)) => {
+                // Like single-stepping, a trap that reports the next instruction.
+            }
+            CPUState::Exception(_) => {
+                // Faults report the faulting instruction, so a handler can retry it.
+                cpu.regs.eip = prev_ip;
+            }
             _ => {}
         }
     }